@@ -0,0 +1,22 @@
+-- Instance-level staff roles, distinct from per-server roles.
+-- NULL = regular user. 'operator' is kept in sync with is_instance_admin.
+ALTER TABLE users ADD COLUMN instance_role TEXT DEFAULT NULL
+    CHECK (instance_role IS NULL OR instance_role IN ('operator', 'instance_moderator', 'support'));
+
+UPDATE users SET instance_role = 'operator' WHERE is_instance_admin = TRUE;
+
+-- Audit trail of every staff action taken through the admin API.
+-- target_id has no FK so entries survive deletion of the target.
+CREATE TABLE instance_audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    actor_role TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_type TEXT,
+    target_id UUID,
+    details JSONB,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_instance_audit_log_created ON instance_audit_log(created_at DESC);
+CREATE INDEX idx_instance_audit_log_actor ON instance_audit_log(actor_id, created_at DESC);
@@ -0,0 +1,4 @@
+-- Support access entries are shown to the affected user, so the audit log
+-- needs to be searchable by target.
+CREATE INDEX idx_instance_audit_log_target ON instance_audit_log(target_id, created_at DESC)
+    WHERE target_id IS NOT NULL;
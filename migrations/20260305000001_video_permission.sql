@@ -0,0 +1,11 @@
+-- Split camera out of STREAM (1 << 17) into its own VIDEO bit (1 << 27).
+-- STREAM used to grant both camera and screen sharing, so anything that had
+-- STREAM keeps camera access; denies of STREAM keep denying camera too.
+UPDATE roles SET permissions = permissions | 134217728
+    WHERE permissions & 131072 <> 0;
+
+UPDATE channel_permission_overwrites SET allow_bits = allow_bits | 134217728
+    WHERE allow_bits & 131072 <> 0;
+
+UPDATE channel_permission_overwrites SET deny_bits = deny_bits | 134217728
+    WHERE deny_bits & 131072 <> 0;
@@ -0,0 +1,20 @@
+-- Per-server upload tier: governs per-file size and content-type limits.
+ALTER TABLE servers ADD COLUMN upload_tier TEXT NOT NULL DEFAULT 'standard'
+    CHECK (upload_tier IN ('standard', 'boosted'));
+
+-- Resumable (tus-style) upload sessions. The session id becomes the
+-- attachment id on finalization. Chunks are stored as separate part blobs
+-- (part_ids, in upload order) and assembled when the upload is finalized.
+CREATE TABLE upload_sessions (
+    id UUID PRIMARY KEY,
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type TEXT NOT NULL,
+    upload_length BIGINT NOT NULL,
+    upload_offset BIGINT NOT NULL DEFAULT 0,
+    part_ids UUID[] NOT NULL DEFAULT '{}',
+    file_hash TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_upload_sessions_expires ON upload_sessions(expires_at);
@@ -0,0 +1,5 @@
+-- Attachment garbage collection. Message deletion paths leave attachment rows
+-- behind; the GC worker stamps rows whose message is gone and deletes the row
+-- and its blob once the grace period has passed.
+ALTER TABLE attachments ADD COLUMN orphaned_at TIMESTAMPTZ;
+CREATE INDEX idx_attachments_orphaned_at ON attachments(orphaned_at) WHERE orphaned_at IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Instance branding: a single row, edited by operators and served publicly to
+-- clients and the transactional email templates.
+CREATE TABLE instance_branding (
+    id               BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    name             TEXT NOT NULL DEFAULT 'Haven',
+    has_logo         BOOLEAN NOT NULL DEFAULT FALSE,
+    accent_color     TEXT NOT NULL DEFAULT '#C2410C',
+    background_color TEXT NOT NULL DEFAULT '#F5F0E8',
+    terms_url        TEXT,
+    privacy_url      TEXT,
+    updated_by       UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO instance_branding DEFAULT VALUES;
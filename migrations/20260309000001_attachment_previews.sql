@@ -0,0 +1,6 @@
+-- Server-generated image previews for attachments in unencrypted channels
+-- (THUMBNAILS_ENABLED). All four columns are set together, or all left NULL.
+ALTER TABLE attachments ADD COLUMN thumbnail_key TEXT;
+ALTER TABLE attachments ADD COLUMN blurhash TEXT;
+ALTER TABLE attachments ADD COLUMN width INTEGER;
+ALTER TABLE attachments ADD COLUMN height INTEGER;
@@ -0,0 +1,12 @@
+-- Content-addressed profile media (avatars, banners). One row per user and
+-- slot; size_bytes counts toward the per-user profile media quota. Avatars
+-- uploaded before this migration have no row and keep their legacy key.
+CREATE TABLE user_profile_media (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    slot TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, slot)
+);
@@ -0,0 +1,5 @@
+-- 'friends_strict': only friends may open a DM (others are refused rather
+-- than landing in DM requests as with 'friends_only').
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_dm_privacy_check;
+ALTER TABLE users ADD CONSTRAINT users_dm_privacy_check
+    CHECK (dm_privacy IN ('everyone', 'friends_only', 'server_members', 'friends_strict'));
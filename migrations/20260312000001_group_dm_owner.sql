@@ -0,0 +1,11 @@
+-- Group DM owner: may remove members and transfer ownership.
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS owner_id UUID REFERENCES users(id) ON DELETE SET NULL;
+
+-- Existing groups: the longest-standing member becomes owner.
+UPDATE channels c SET owner_id = (
+    SELECT cm.user_id FROM channel_members cm
+    WHERE cm.channel_id = c.id
+    ORDER BY cm.joined_at ASC
+    LIMIT 1
+)
+WHERE c.channel_type = 'group' AND c.owner_id IS NULL;
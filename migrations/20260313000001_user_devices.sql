@@ -0,0 +1,24 @@
+-- Per-device identity keys. Each client install registers its own keypair so
+-- senders can encrypt to every device of a recipient.
+CREATE TABLE user_devices (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    identity_key BYTEA NOT NULL,
+    signed_prekey BYTEA NOT NULL,
+    signed_prekey_sig BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, identity_key)
+);
+CREATE INDEX idx_user_devices_user ON user_devices(user_id);
+
+-- A user's verdict on a device (their own or someone else's), pinned to the
+-- identity key it was made against. No row = unverified.
+CREATE TABLE device_verifications (
+    verifier_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_id UUID NOT NULL REFERENCES user_devices(id) ON DELETE CASCADE,
+    status TEXT NOT NULL CHECK (status IN ('verified', 'blocked')),
+    identity_key BYTEA NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (verifier_id, device_id)
+);
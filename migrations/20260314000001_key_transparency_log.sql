@@ -0,0 +1,22 @@
+-- Append-only log of published identity keys, hashed into an RFC 6962 Merkle
+-- tree (see src/key_transparency.rs). seq is the leaf index, so it is assigned
+-- contiguously by the application rather than from a sequence. No foreign key:
+-- entries must outlive account deletion or the tree would change underneath
+-- clients that already hold a tree head.
+CREATE TABLE key_transparency_log (
+    seq BIGINT PRIMARY KEY,
+    user_id UUID NOT NULL,
+    identity_key BYTEA NOT NULL,
+    leaf_hash BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_key_transparency_log_user ON key_transparency_log(user_id, seq DESC);
+
+-- Seed the log with every current identity key.
+INSERT INTO key_transparency_log (seq, user_id, identity_key, leaf_hash, created_at)
+SELECT ROW_NUMBER() OVER (ORDER BY created_at, id) - 1,
+       id,
+       identity_key,
+       sha256('\x00'::bytea || uuid_send(id) || identity_key),
+       created_at
+FROM users;
@@ -0,0 +1,29 @@
+-- Versioned session key backup (secret storage). A user creates a backup
+-- version with the public half of their recovery key in auth_data, then
+-- uploads each Megolm-style session key encrypted to it. The server only ever
+-- sees ciphertext. Version numbers are never reused, so deleted versions are
+-- tombstoned rather than removed.
+
+CREATE TABLE key_backup_versions (
+    user_id     UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    version     INT NOT NULL,
+    algorithm   TEXT NOT NULL,
+    auth_data   BYTEA NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at  TIMESTAMPTZ,
+    PRIMARY KEY (user_id, version)
+);
+
+CREATE TABLE key_backup_sessions (
+    user_id             UUID NOT NULL,
+    version             INT NOT NULL,
+    channel_id          UUID NOT NULL,
+    session_id          TEXT NOT NULL,
+    first_message_index INT NOT NULL,
+    forwarded_count     INT NOT NULL DEFAULT 0,
+    is_verified         BOOLEAN NOT NULL DEFAULT FALSE,
+    session_data        BYTEA NOT NULL,
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, version, channel_id, session_id),
+    FOREIGN KEY (user_id, version) REFERENCES key_backup_versions(user_id, version) ON DELETE CASCADE
+);
@@ -0,0 +1,27 @@
+-- DM-only federation between Haven instances.
+
+-- Remote users are represented locally by a shadow row in users (username
+-- "name@server", unusable password) so DMs, members and messages work
+-- unchanged. This table maps the shadow back to the remote identity.
+CREATE TABLE federated_users (
+    user_id         UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    server_name     TEXT NOT NULL,
+    remote_user_id  UUID NOT NULL,
+    fetched_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (server_name, remote_user_id)
+);
+
+-- Cached Ed25519 public keys of remote servers, used to verify their requests.
+CREATE TABLE federation_servers (
+    server_name  TEXT PRIMARY KEY,
+    public_key   BYTEA NOT NULL,
+    fetched_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Inbound transaction ids, so a retried delivery is applied once.
+CREATE TABLE federation_transactions (
+    origin       TEXT NOT NULL,
+    txn_id       TEXT NOT NULL,
+    received_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (origin, txn_id)
+);
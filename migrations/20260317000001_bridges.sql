@@ -0,0 +1,42 @@
+-- Bridges (application-service style integrations such as a Matrix or IRC
+-- bridge). An instance operator registers a bridge and receives a token once;
+-- the bridge uses it to manage puppet users, post as them, and poll events
+-- from the channels it has been linked to.
+
+CREATE TABLE bridges (
+    id           UUID PRIMARY KEY,
+    name         TEXT NOT NULL,
+    token_hash   TEXT NOT NULL UNIQUE,
+    user_prefix  TEXT NOT NULL UNIQUE,
+    created_by   UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Puppet accounts owned by a bridge. The user rows outlive the bridge so
+-- bridged history keeps its authors.
+CREATE TABLE bridge_puppets (
+    user_id    UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    bridge_id  UUID NOT NULL REFERENCES bridges(id) ON DELETE CASCADE,
+    remote_id  TEXT NOT NULL,
+    UNIQUE (bridge_id, remote_id)
+);
+
+-- Channels a server manager has opened to a bridge.
+CREATE TABLE bridge_channels (
+    bridge_id   UUID NOT NULL REFERENCES bridges(id) ON DELETE CASCADE,
+    channel_id  UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    linked_by   UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (bridge_id, channel_id)
+);
+CREATE INDEX idx_bridge_channels_channel ON bridge_channels(channel_id);
+
+-- Per-bridge event queue; a bridge acknowledges by polling with since=<seq>.
+CREATE TABLE bridge_events (
+    seq         BIGSERIAL PRIMARY KEY,
+    bridge_id   UUID NOT NULL REFERENCES bridges(id) ON DELETE CASCADE,
+    channel_id  UUID NOT NULL,
+    message_id  UUID NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_bridge_events_bridge ON bridge_events(bridge_id, seq);
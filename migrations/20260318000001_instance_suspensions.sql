@@ -0,0 +1,3 @@
+-- Temporary instance bans ("suspensions"). A ban with expires_at stops
+-- applying once it passes; NULL keeps the existing permanent behaviour.
+ALTER TABLE instance_bans ADD COLUMN expires_at TIMESTAMPTZ;
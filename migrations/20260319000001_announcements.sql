@@ -0,0 +1,27 @@
+-- Instance-wide announcements (maintenance windows, new versions) pushed by
+-- operators. Scheduled announcements stay unpublished until publish_at;
+-- published_at records when the background worker broadcast them.
+CREATE TABLE announcements (
+    id           UUID PRIMARY KEY,
+    title        TEXT NOT NULL,
+    body         TEXT NOT NULL,
+    kind         TEXT NOT NULL DEFAULT 'info' CHECK (kind IN ('info', 'maintenance', 'release')),
+    send_dm      BOOLEAN NOT NULL DEFAULT FALSE,
+    publish_at   TIMESTAMPTZ NOT NULL,
+    expires_at   TIMESTAMPTZ,
+    published_at TIMESTAMPTZ,
+    created_by   UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_announcements_unpublished ON announcements(publish_at) WHERE published_at IS NULL;
+
+-- Which users have dismissed which announcement (synced across devices).
+CREATE TABLE announcement_dismissals (
+    announcement_id UUID NOT NULL REFERENCES announcements(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    dismissed_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (announcement_id, user_id)
+);
+
+CREATE INDEX idx_announcement_dismissals_user ON announcement_dismissals(user_id);
@@ -0,0 +1,16 @@
+-- Per-server quota overrides set by operators. A NULL limit falls back to
+-- the instance default (SERVER_* settings); 0 means unlimited.
+CREATE TABLE server_quotas (
+    server_id UUID PRIMARY KEY REFERENCES servers(id) ON DELETE CASCADE,
+    max_storage_bytes BIGINT CHECK (max_storage_bytes >= 0),
+    max_messages_per_minute INTEGER CHECK (max_messages_per_minute >= 0),
+    max_members INTEGER CHECK (max_members >= 0),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Attachment bytes are charged to the server they were posted in.
+-- Attachments stored before this migration have no recorded size.
+ALTER TABLE attachments ADD COLUMN size_bytes BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE attachments ADD COLUMN server_id UUID REFERENCES servers(id) ON DELETE SET NULL;
+CREATE INDEX idx_attachments_server ON attachments(server_id) WHERE server_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Trigram indexes for server-side member search
+-- (GET /servers/:id/members/search matches substrings of these names).
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_users_username_trgm ON users USING gin (username gin_trgm_ops);
+CREATE INDEX idx_users_display_name_trgm ON users USING gin (display_name gin_trgm_ops);
+CREATE INDEX idx_server_members_nickname_trgm ON server_members USING gin (nickname gin_trgm_ops);
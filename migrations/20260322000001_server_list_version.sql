@@ -0,0 +1,3 @@
+-- Per-server version behind the ETags of the channel, role and member lists.
+-- Bumped by every query that changes what those lists return.
+ALTER TABLE servers ADD COLUMN list_version BIGINT NOT NULL DEFAULT 0;
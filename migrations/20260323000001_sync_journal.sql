@@ -0,0 +1,16 @@
+-- Change journal for WS delta sync. Each change to a server, one of its
+-- channels or roles, or its membership gets the next instance-wide version;
+-- reconnecting clients ask for everything after the version they last saw.
+-- No foreign keys: entries must outlive the rows they describe.
+CREATE TABLE sync_changes (
+    version BIGSERIAL PRIMARY KEY,
+    server_id UUID NOT NULL,
+    entity_type TEXT NOT NULL CHECK (entity_type IN ('server', 'channel', 'role', 'member')),
+    entity_id UUID NOT NULL,
+    deleted BOOLEAN NOT NULL DEFAULT FALSE,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sync_changes_server_version ON sync_changes (server_id, version);
+CREATE INDEX idx_sync_changes_member ON sync_changes (entity_id, version) WHERE entity_type = 'member';
+CREATE INDEX idx_sync_changes_changed_at ON sync_changes (changed_at);
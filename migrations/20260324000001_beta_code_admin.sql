@@ -0,0 +1,15 @@
+-- Operator management of beta codes (registration invites with no creator).
+-- Revoked codes are kept rather than deleted so their email hash still
+-- blocks a second request; the label groups codes generated for an event.
+ALTER TABLE registration_invites ADD COLUMN revoked_at TIMESTAMPTZ;
+ALTER TABLE registration_invites ADD COLUMN label TEXT;
+
+-- Runtime override of BETA_CODE_LIMIT: a single row, NULL uses the config.
+CREATE TABLE beta_settings (
+    id         BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    code_limit INTEGER CHECK (code_limit >= 0),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO beta_settings DEFAULT VALUES;
@@ -0,0 +1,10 @@
+-- Beta waitlist for requests made while the cap is reached, in queue order.
+-- The address is needed to send the code on promotion, so it is kept
+-- encrypted with the server storage key; the row is deleted when the code
+-- is issued, leaving only the email hash on the invite.
+CREATE TABLE beta_waitlist (
+    id               BIGSERIAL PRIMARY KEY,
+    email_hash       TEXT NOT NULL UNIQUE,
+    email_ciphertext BYTEA NOT NULL,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+-- Language of the waitlist email, reused for the code email on promotion.
+ALTER TABLE beta_waitlist ADD COLUMN locale TEXT;
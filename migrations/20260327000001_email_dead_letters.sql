@@ -0,0 +1,15 @@
+-- Emails that failed every delivery attempt, kept for an operator to retry
+-- or discard. Recipient, subject and body are encrypted with the server
+-- storage key; only the email kind and the error are readable.
+CREATE TABLE email_dead_letters (
+    id              UUID PRIMARY KEY,
+    kind            TEXT NOT NULL,
+    provider        TEXT NOT NULL,
+    payload         BYTEA NOT NULL,
+    error           TEXT NOT NULL,
+    attempts        INTEGER NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_dead_letters_created ON email_dead_letters (created_at DESC);
@@ -0,0 +1,15 @@
+-- Outbound mail queue. Each email waits here, encrypted with the server
+-- storage key, until it is delivered (row deleted) or runs out of attempts
+-- (row moved to email_dead_letters). A worker claiming a row pushes
+-- next_attempt_at ahead as a lease, so other instances skip it.
+CREATE TABLE email_outbox (
+    id              UUID PRIMARY KEY,
+    kind            TEXT NOT NULL,
+    payload         BYTEA NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_outbox_next_attempt ON email_outbox (next_attempt_at);
@@ -0,0 +1,19 @@
+-- Abuse scores of registration and beta code requests (see src/abuse.rs).
+-- The requester's network (/24 or /48) is kept only as a keyed hash, for
+-- velocity counting; the raw IP is erased after ABUSE_IP_RETENTION_HOURS.
+CREATE TABLE abuse_events (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    network_hash BYTEA,
+    ip TEXT,
+    score SMALLINT NOT NULL,
+    reasons TEXT[] NOT NULL DEFAULT '{}',
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_abuse_events_network ON abuse_events(network_hash, created_at);
+CREATE INDEX idx_abuse_events_created_at ON abuse_events(created_at);
+CREATE INDEX idx_abuse_events_ip ON abuse_events(created_at) WHERE ip IS NOT NULL;
+
+-- Score at registration, matched against servers' 'abuse' content filters
+ALTER TABLE users ADD COLUMN abuse_score SMALLINT;
@@ -0,0 +1,6 @@
+-- Accounts registered under REGISTRATION_MODE=approval, waiting for an
+-- operator. Approval deletes the row; rejection deletes the account.
+CREATE TABLE pending_registrations (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
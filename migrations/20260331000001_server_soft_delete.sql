@@ -0,0 +1,6 @@
+-- Servers deleted by their owner stay restorable for a grace period before
+-- the deleted-servers job purges them with their content and blobs.
+ALTER TABLE servers ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE servers ADD COLUMN deleted_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_servers_deleted_at ON servers (deleted_at) WHERE deleted_at IS NOT NULL;
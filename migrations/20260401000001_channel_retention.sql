@@ -0,0 +1,13 @@
+-- Per-channel message retention. No row means keep forever; otherwise
+-- exactly one of max_age_days / max_messages is set. The channel-retention
+-- job deletes what falls outside the policy, after shipping it to backup
+-- storage when `archive` is set.
+CREATE TABLE channel_retention_policies (
+    channel_id UUID PRIMARY KEY REFERENCES channels(id) ON DELETE CASCADE,
+    max_age_days INT CHECK (max_age_days > 0),
+    max_messages INT CHECK (max_messages > 0),
+    archive BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((max_age_days IS NULL) <> (max_messages IS NULL))
+);
@@ -0,0 +1,19 @@
+-- Legal holds placed by instance staff on a server or a user. While a hold
+-- is active (released_at IS NULL) nothing the subject covers may be deleted:
+-- retention sweeps and message expiry skip it, and message, channel, server
+-- and account deletion are refused. Released holds are kept as the trail.
+CREATE TABLE legal_holds (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    subject_type TEXT NOT NULL CHECK (subject_type IN ('server', 'user')),
+    subject_id UUID NOT NULL,
+    reason TEXT,
+    placed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    placed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    released_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    released_at TIMESTAMPTZ,
+    release_reason TEXT
+);
+
+CREATE UNIQUE INDEX idx_legal_holds_active ON legal_holds (subject_type, subject_id)
+    WHERE released_at IS NULL;
+CREATE INDEX idx_legal_holds_placed ON legal_holds (placed_at DESC);
@@ -0,0 +1,7 @@
+-- Provenance for forwarded messages (message_type = 'forwarded'). Points at
+-- the original message and its channel; the original author is deliberately
+-- not copied so sealed-sender channels do not leak it to the new audience.
+ALTER TABLE messages
+    ADD COLUMN forwarded_from_id UUID,
+    ADD COLUMN forwarded_from_channel_id UUID,
+    ADD COLUMN forwarded_from_timestamp TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- Reply lookups: per-message reply counts and GET /messages/:id/replies.
+CREATE INDEX idx_messages_reply_to ON messages (channel_id, reply_to_id, timestamp)
+    WHERE reply_to_id IS NOT NULL;
@@ -0,0 +1,31 @@
+-- Scheduled server events. Title, description and any external location are
+-- client-encrypted in encrypted_meta; channel_id is set when the event takes
+-- place in one of the server's channels. reminder_sent_at marks events whose
+-- RSVP reminders went out.
+CREATE TABLE server_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    creator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    encrypted_meta BYTEA NOT NULL,
+    channel_id UUID REFERENCES channels(id) ON DELETE SET NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ,
+    reminder_sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE INDEX idx_server_events_server ON server_events (server_id, starts_at);
+CREATE INDEX idx_server_events_reminders ON server_events (starts_at)
+    WHERE reminder_sent_at IS NULL;
+
+CREATE TABLE server_event_rsvps (
+    event_id UUID NOT NULL REFERENCES server_events(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status TEXT NOT NULL CHECK (status IN ('interested', 'going')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (event_id, user_id)
+);
+
+CREATE INDEX idx_server_event_rsvps_user ON server_event_rsvps (user_id);
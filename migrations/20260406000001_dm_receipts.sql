@@ -0,0 +1,17 @@
+-- DM delivery and read receipts. delivery_states is the newest message
+-- timestamp that reached one of the user's devices in a DM channel; read
+-- receipts come from read_states. receipt_privacy controls what a user
+-- shares (and, reciprocally, sees): 'all', 'delivered' (no read receipts)
+-- or 'none'.
+ALTER TABLE users
+    ADD COLUMN receipt_privacy TEXT NOT NULL DEFAULT 'all'
+        CHECK (receipt_privacy IN ('all', 'delivered', 'none'));
+
+CREATE TABLE delivery_states (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    last_delivered_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX idx_delivery_states_channel ON delivery_states (channel_id);
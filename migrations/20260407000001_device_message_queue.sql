@@ -0,0 +1,14 @@
+-- Per-device offline queues for DM and group messages. A message is queued
+-- for each recipient device that had no WebSocket connection when it was
+-- sent; the device drains its queue on reconnect and acknowledges by polling
+-- with since=<seq>. Unacknowledged entries are trimmed after
+-- DEVICE_QUEUE_TTL_DAYS.
+CREATE TABLE device_message_queue (
+    seq         BIGSERIAL PRIMARY KEY,
+    device_id   UUID NOT NULL REFERENCES user_devices(id) ON DELETE CASCADE,
+    channel_id  UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    message_id  UUID NOT NULL,
+    queued_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_device_message_queue_device ON device_message_queue(device_id, seq);
+CREATE INDEX idx_device_message_queue_queued_at ON device_message_queue(queued_at);
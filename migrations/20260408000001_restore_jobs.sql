@@ -0,0 +1,17 @@
+-- Progress of a server restore: the structure restore plus the message import
+-- batches that follow it. Progress is pushed to the requester over WebSocket
+-- as it changes; the row lets a reloaded client re-attach to a running job.
+CREATE TABLE restore_jobs (
+    id          UUID PRIMARY KEY,
+    server_id   UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    user_id     UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phase       TEXT NOT NULL DEFAULT 'structure',
+    done        BIGINT NOT NULL DEFAULT 0,
+    total       BIGINT NOT NULL DEFAULT 0,
+    status      TEXT NOT NULL DEFAULT 'running'
+                CHECK (status IN ('running', 'completed', 'failed')),
+    error       TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_restore_jobs_server_user ON restore_jobs(server_id, user_id, created_at DESC);
@@ -0,0 +1,19 @@
+-- Snapshot of a server's structure taken before a restore wipes it, so the
+-- restore can be rolled back for 24 hours. Categories, roles, member roles
+-- and channel memberships are copied into `structure`; the old channels are
+-- not deleted but detached (server_id NULL, no members) and keep their
+-- messages, pins and overwrites until the snapshot is rolled back or expires.
+CREATE TABLE restore_snapshots (
+    id          UUID PRIMARY KEY,
+    server_id   UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    created_by  UUID REFERENCES users(id) ON DELETE SET NULL,
+    structure   JSONB NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at  TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_restore_snapshots_server ON restore_snapshots(server_id, created_at DESC);
+CREATE INDEX idx_restore_snapshots_expires ON restore_snapshots(expires_at);
+
+-- Detached channels belong to the snapshot and go away with it
+ALTER TABLE channels ADD COLUMN detached_snapshot_id UUID REFERENCES restore_snapshots(id) ON DELETE CASCADE;
+CREATE INDEX idx_channels_detached_snapshot ON channels(detached_snapshot_id) WHERE detached_snapshot_id IS NOT NULL;
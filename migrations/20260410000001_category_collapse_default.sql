@@ -0,0 +1,3 @@
+-- Whether a category starts collapsed for members who haven't toggled it
+-- themselves. Restored from .haven backups along with the category.
+ALTER TABLE channel_categories ADD COLUMN collapsed_by_default BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+-- Mention metadata declared by the sending client: bodies are end-to-end
+-- encrypted, so the server can't find mentions itself. One row per message
+-- that mentions anyone; read-state mention counts and per-recipient
+-- notification flags are computed from it. No FK to messages (partitioned),
+-- rows of deleted messages drop out through the join on messages.
+CREATE TABLE message_mentions (
+    message_id  UUID PRIMARY KEY,
+    channel_id  UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_ids    UUID[] NOT NULL DEFAULT '{}',
+    role_ids    UUID[] NOT NULL DEFAULT '{}',
+    everyone    BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_message_mentions_channel ON message_mentions(channel_id, created_at);
+
+-- Role mentions in these channels are still shown, but notify no one
+ALTER TABLE channels ADD COLUMN suppress_role_mentions BOOLEAN NOT NULL DEFAULT FALSE;
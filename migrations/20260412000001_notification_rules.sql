@@ -0,0 +1,18 @@
+-- Per-user notification routing. A rule covers everything ('global'), one
+-- server or one channel, and sets what notifies there: 'all' messages,
+-- 'mentions' only, or 'none'. The most specific matching rule wins. Bodies
+-- and channel names are encrypted, so rules can't match on keywords.
+CREATE TABLE notification_rules (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id     UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    scope       TEXT NOT NULL CHECK (scope IN ('global', 'server', 'channel')),
+    target_id   UUID,
+    level       TEXT NOT NULL CHECK (level IN ('all', 'mentions', 'none')),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((scope = 'global') = (target_id IS NULL))
+);
+-- One rule per user and target
+CREATE UNIQUE INDEX idx_notification_rules_user_target
+    ON notification_rules(user_id, scope, COALESCE(target_id, '00000000-0000-0000-0000-000000000000'));
+CREATE INDEX idx_notification_rules_target ON notification_rules(target_id) WHERE target_id IS NOT NULL;
@@ -0,0 +1,20 @@
+-- Per-user Do Not Disturb schedule. Times are minutes after local midnight
+-- at the user's UTC offset, which clients refresh when it changes (e.g. for
+-- daylight saving); `timezone` is the IANA name, kept for clients only.
+-- `days` is a bitmask of the weekdays a quiet period starts on (bit 0 is
+-- Monday). `active` is whether the schedule was in effect at the last check.
+CREATE TABLE quiet_hours (
+    user_id            UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    enabled            BOOLEAN NOT NULL DEFAULT TRUE,
+    start_minute       INT NOT NULL CHECK (start_minute BETWEEN 0 AND 1439),
+    end_minute         INT NOT NULL CHECK (end_minute BETWEEN 0 AND 1439),
+    days               SMALLINT NOT NULL DEFAULT 127 CHECK (days BETWEEN 1 AND 127),
+    utc_offset_minutes INT NOT NULL CHECK (utc_offset_minutes BETWEEN -840 AND 840),
+    timezone           TEXT,
+    -- Users whose messages notify even during quiet hours
+    priority_user_ids  UUID[] NOT NULL DEFAULT '{}',
+    active             BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (start_minute <> end_minute)
+);
+CREATE INDEX idx_quiet_hours_enabled ON quiet_hours(user_id) WHERE enabled OR active;
@@ -0,0 +1,25 @@
+-- Per-server onboarding. The welcome description, rules text and prompt
+-- labels are in `encrypted_meta`, like other server metadata; the server
+-- only sees which channels new members join, whether the rules must be
+-- acknowledged before posting, and which roles each prompt option grants.
+CREATE TABLE server_onboarding (
+    server_id              UUID PRIMARY KEY REFERENCES servers(id) ON DELETE CASCADE,
+    enabled                BOOLEAN NOT NULL DEFAULT FALSE,
+    encrypted_meta         BYTEA NOT NULL,
+    -- Channels new members join; empty means every channel
+    default_channel_ids    UUID[] NOT NULL DEFAULT '{}',
+    require_acknowledgment BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Bumped to ask everyone to acknowledge the rules again
+    rules_version          INT NOT NULL DEFAULT 1,
+    -- [{id, single_select, required, options: [{id, role_ids}]}]
+    prompts                JSONB NOT NULL DEFAULT '[]',
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE onboarding_acknowledgments (
+    server_id       UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rules_version   INT NOT NULL,
+    acknowledged_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (server_id, user_id)
+);
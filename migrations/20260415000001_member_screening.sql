@@ -0,0 +1,31 @@
+-- Member screening. Applicants answer the server's questions before they
+-- are let in; until a moderator approves them they are not server members
+-- and see no channels. Applicants don't hold the server key yet, so the
+-- questions and answers are not end-to-end encrypted.
+CREATE TABLE server_screening (
+    server_id  UUID PRIMARY KEY REFERENCES servers(id) ON DELETE CASCADE,
+    enabled    BOOLEAN NOT NULL DEFAULT FALSE,
+    -- [{id, prompt, required}]
+    questions  JSONB NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE server_applications (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id   UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    user_id     UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- The invite applied through; its use is counted on approval
+    invite_id   UUID REFERENCES invites(id) ON DELETE SET NULL,
+    -- [{question_id, answer}]
+    answers     JSONB NOT NULL DEFAULT '[]',
+    status      TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'denied')),
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    deny_reason TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- One open application per user and server
+CREATE UNIQUE INDEX idx_server_applications_pending
+    ON server_applications(server_id, user_id) WHERE status = 'pending';
+CREATE INDEX idx_server_applications_queue ON server_applications(server_id, status, created_at);
+CREATE INDEX idx_server_applications_user ON server_applications(user_id, created_at);
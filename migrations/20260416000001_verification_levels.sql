@@ -0,0 +1,23 @@
+-- Verification levels for posting. Each level includes the requirements of
+-- the ones below it: none < email < account_age < membership < phone.
+CREATE TABLE server_verification (
+    server_id              UUID PRIMARY KEY REFERENCES servers(id) ON DELETE CASCADE,
+    level                  TEXT NOT NULL DEFAULT 'none'
+                           CHECK (level IN ('none', 'email', 'account_age', 'membership', 'phone')),
+    min_account_age_days   INT NOT NULL DEFAULT 5 CHECK (min_account_age_days BETWEEN 1 AND 365),
+    min_membership_minutes INT NOT NULL DEFAULT 10 CHECK (min_membership_minutes BETWEEN 1 AND 10080),
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Set once the user proves they receive mail at the address behind email_hash
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- Outstanding email verification codes, one per user. Only a hash of the
+-- code is kept; the address itself is never stored.
+CREATE TABLE email_verification_codes (
+    user_id    UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    code_hash  TEXT NOT NULL,
+    attempts   INT NOT NULL DEFAULT 0,
+    sent_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Attachment size and count limits set on a role or a channel. A channel's
+-- limit takes precedence over the limits of the member's roles; the server's
+-- upload tier stays the ceiling either way.
+CREATE TABLE upload_limits (
+    id             UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id      UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    role_id        UUID REFERENCES roles(id) ON DELETE CASCADE,
+    channel_id     UUID REFERENCES channels(id) ON DELETE CASCADE,
+    -- NULL leaves that limit to the role, or the tier
+    max_size_bytes BIGINT CHECK (max_size_bytes >= 0),
+    -- Uploads a member may have in progress in a channel at once; 0 forbids uploads
+    max_count      INT CHECK (max_count >= 0),
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (num_nonnulls(role_id, channel_id) = 1)
+);
+CREATE UNIQUE INDEX idx_upload_limits_role ON upload_limits(role_id) WHERE role_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_upload_limits_channel ON upload_limits(channel_id) WHERE channel_id IS NOT NULL;
+CREATE INDEX idx_upload_limits_server ON upload_limits(server_id);
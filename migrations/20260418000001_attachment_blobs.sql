@@ -0,0 +1,18 @@
+-- Content-addressed attachment storage. Blobs are keyed by the BLAKE3 hash
+-- of the uploaded bytes and shared by every attachment with that content;
+-- ref_count counts those attachments (and simple uploads not yet sent).
+-- A blob is deleted once it has been unreferenced for the GC grace period.
+CREATE TABLE attachment_blobs (
+    content_hash    TEXT PRIMARY KEY,
+    storage_key     TEXT NOT NULL,
+    size_bytes      BIGINT NOT NULL,
+    ref_count       INT NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    unreferenced_at TIMESTAMPTZ
+);
+CREATE INDEX idx_attachment_blobs_unreferenced
+    ON attachment_blobs(unreferenced_at) WHERE ref_count = 0;
+
+-- NULL for attachments stored before this migration, each in its own blob
+ALTER TABLE attachments ADD COLUMN content_hash TEXT REFERENCES attachment_blobs(content_hash);
+CREATE INDEX idx_attachments_content_hash ON attachments(content_hash) WHERE content_hash IS NOT NULL;
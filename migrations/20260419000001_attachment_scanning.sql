@@ -0,0 +1,9 @@
+-- Malware scan verdicts. Only attachments in unencrypted channels are
+-- scanned (the server can't read ciphertext); the rest keep a NULL status.
+-- Pending and infected attachments can't be downloaded.
+ALTER TABLE attachments ADD COLUMN scan_status TEXT
+    CHECK (scan_status IN ('pending', 'clean', 'infected', 'failed'));
+-- Signature name reported by the scanner for an infected attachment
+ALTER TABLE attachments ADD COLUMN scan_signature TEXT;
+ALTER TABLE attachments ADD COLUMN scanned_at TIMESTAMPTZ;
+CREATE INDEX idx_attachments_scan_pending ON attachments(created_at) WHERE scan_status = 'pending';
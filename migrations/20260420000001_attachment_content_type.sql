@@ -0,0 +1,4 @@
+-- Declared MIME type of attachments in unencrypted channels, so downloads
+-- can be served with it. Ciphertext is always served as octet-stream and
+-- keeps a NULL type.
+ALTER TABLE attachments ADD COLUMN content_type TEXT;
@@ -0,0 +1,22 @@
+-- Voice notes (message_type = 'voice_note'). The audio is an ordinary
+-- attachment; the duration and waveform the sending client computed are
+-- kept alongside so every client draws the same player before downloading
+-- it. No FK to messages (partitioned), as with message_mentions.
+CREATE TABLE message_voice_notes (
+    message_id  UUID PRIMARY KEY,
+    channel_id  UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    duration_ms INTEGER NOT NULL CHECK (duration_ms > 0),
+    waveform    BYTEA NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- New SEND_VOICE_NOTES bit (1 << 28). Anything that could attach files can
+-- send voice notes; denies of ATTACH_FILES keep denying them too.
+UPDATE roles SET permissions = permissions | 268435456
+    WHERE permissions & 8192 <> 0;
+
+UPDATE channel_permission_overwrites SET allow_bits = allow_bits | 268435456
+    WHERE allow_bits & 8192 <> 0;
+
+UPDATE channel_permission_overwrites SET deny_bits = deny_bits | 268435456
+    WHERE deny_bits & 8192 <> 0;
@@ -0,0 +1,17 @@
+-- Location shares (message_type = 'location'). The coordinates are in the
+-- encrypted body. A live share also opens a session for a bounded time,
+-- during which the sender pushes encrypted position updates over WS; the
+-- latest one is kept so clients that open the channel later can place the
+-- pin. Sessions end when the sender stops them or when they expire. No FK
+-- to messages (partitioned), as with message_mentions.
+CREATE TABLE live_location_sessions (
+    message_id      UUID PRIMARY KEY,
+    channel_id      UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at      TIMESTAMPTZ NOT NULL,
+    ended_at        TIMESTAMPTZ,
+    last_position   BYTEA,
+    last_update_at  TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_live_location_sessions_open ON live_location_sessions(expires_at) WHERE ended_at IS NULL;
@@ -0,0 +1,25 @@
+-- System message bodies carry parameters, not prose. Disappearing-message
+-- notices stored an English duration label; replace it with the timer in
+-- seconds so clients can phrase it themselves.
+UPDATE messages
+SET encrypted_body = convert_to(
+        ((convert_from(encrypted_body, 'UTF8')::jsonb - 'duration') || jsonb_build_object(
+            'ttl_secs',
+            CASE convert_from(encrypted_body, 'UTF8')::jsonb ->> 'duration'
+                WHEN '30 seconds' THEN 30
+                WHEN '5 minutes' THEN 300
+                WHEN '1 hour' THEN 3600
+                WHEN '8 hours' THEN 28800
+                WHEN '1 day' THEN 86400
+                WHEN '1 week' THEN 604800
+            END
+        ))::text,
+        'UTF8'
+    )
+WHERE message_type = 'system'
+  AND convert_from(encrypted_body, 'UTF8')::jsonb ->> 'event' = 'disappearing_messages_set';
+
+UPDATE messages
+SET encrypted_body = convert_to((convert_from(encrypted_body, 'UTF8')::jsonb - 'duration')::text, 'UTF8')
+WHERE message_type = 'system'
+  AND convert_from(encrypted_body, 'UTF8')::jsonb ->> 'event' = 'disappearing_messages_off';
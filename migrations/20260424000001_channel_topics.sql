@@ -0,0 +1,5 @@
+-- Plaintext topic and description of unencrypted channels. Encrypted
+-- channels keep theirs inside encrypted_meta, so these stay NULL for them.
+ALTER TABLE channels
+    ADD COLUMN topic TEXT,
+    ADD COLUMN description TEXT;
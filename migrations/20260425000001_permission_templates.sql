@@ -0,0 +1,24 @@
+-- Named allow/deny permission bitfields a server keeps for new roles (the
+-- allow bits become the role's permissions) and new channels (both become
+-- the @everyone overwrite).
+CREATE TABLE permission_templates (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id   UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    name        TEXT NOT NULL,
+    allow_bits  BIGINT NOT NULL DEFAULT 0,
+    deny_bits   BIGINT NOT NULL DEFAULT 0,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (server_id, name)
+);
+
+-- Built-in presets for existing servers (new servers get them on creation):
+-- Moderator allows KICK_MEMBERS, MANAGE_MESSAGES, MUTE_MEMBERS, VIEW_AUDIT_LOG,
+-- MANAGE_THREADS, MODERATE_MEMBERS and MANAGE_NICKNAMES; Read-only denies
+-- SEND_MESSAGES, ADD_REACTIONS, ATTACH_FILES and SEND_VOICE_NOTES.
+INSERT INTO permission_templates (server_id, name, allow_bits, deny_bits)
+SELECT s.id, t.name, t.allow_bits, t.deny_bits
+FROM servers s
+CROSS JOIN (VALUES
+    ('Moderator', 121700432::BIGINT, 0::BIGINT),
+    ('Read-only', 0::BIGINT, 268445952::BIGINT)
+) AS t(name, allow_bits, deny_bits);
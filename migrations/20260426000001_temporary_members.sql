@@ -0,0 +1,5 @@
+-- Temporary invites: members who join through one are removed when they go
+-- offline while holding no role.
+ALTER TABLE invites ADD COLUMN temporary BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE server_members ADD COLUMN temporary BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX idx_server_members_temporary ON server_members(user_id) WHERE temporary;
@@ -0,0 +1,32 @@
+-- Server insights. Joins and leaves are logged without user ids and only
+-- until the rollup has counted them; the rollups hold daily counts alone.
+CREATE TABLE server_membership_events (
+    server_id   UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    joined      BOOLEAN NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_server_membership_events_created ON server_membership_events(created_at);
+
+-- Days are UTC
+CREATE TABLE server_insights_daily (
+    server_id       UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    day             DATE NOT NULL,
+    messages        INT NOT NULL DEFAULT 0,
+    -- Distinct senders that day
+    active_members  INT NOT NULL DEFAULT 0,
+    joins           INT NOT NULL DEFAULT 0,
+    leaves          INT NOT NULL DEFAULT 0,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (server_id, day)
+);
+CREATE INDEX idx_server_insights_daily_day ON server_insights_daily(day);
+
+CREATE TABLE channel_insights_daily (
+    channel_id      UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    server_id       UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    day             DATE NOT NULL,
+    messages        INT NOT NULL DEFAULT 0,
+    active_members  INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (channel_id, day)
+);
+CREATE INDEX idx_channel_insights_daily_server ON channel_insights_daily(server_id, day);
@@ -0,0 +1,5 @@
+-- When a user last opened a session, to the hour: connecting stamps it at
+-- most once an hour. Feeds the daily and monthly active user counts of the
+-- admin usage stats.
+ALTER TABLE users ADD COLUMN last_active_at TIMESTAMPTZ;
+CREATE INDEX idx_users_last_active_at ON users(last_active_at) WHERE last_active_at IS NOT NULL;
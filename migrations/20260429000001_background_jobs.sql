@@ -0,0 +1,29 @@
+-- Persistent background job queue. Workers claim due jobs with a lease
+-- (locked_until); a job whose worker died is claimed again once its lease
+-- runs out. Failed attempts are retried with backoff until max_attempts.
+-- At most one not-yet-attempted job exists per dedupe_key, so repeated
+-- requests for the same work (e.g. draining the email outbox) collapse.
+CREATE TABLE background_jobs (
+    id           UUID PRIMARY KEY,
+    kind         TEXT NOT NULL,
+    payload      JSONB NOT NULL DEFAULT '{}',
+    status       TEXT NOT NULL DEFAULT 'queued'
+                 CHECK (status IN ('queued', 'running', 'completed', 'failed', 'cancelled')),
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5 CHECK (max_attempts > 0),
+    run_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    last_error   TEXT,
+    dedupe_key   TEXT,
+    created_by   UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at  TIMESTAMPTZ
+);
+
+CREATE INDEX idx_background_jobs_due ON background_jobs(run_at) WHERE status = 'queued';
+CREATE INDEX idx_background_jobs_lease ON background_jobs(locked_until) WHERE status = 'running';
+CREATE INDEX idx_background_jobs_finished ON background_jobs(finished_at) WHERE finished_at IS NOT NULL;
+CREATE INDEX idx_background_jobs_created ON background_jobs(created_at DESC);
+CREATE UNIQUE INDEX idx_background_jobs_dedupe ON background_jobs(dedupe_key)
+    WHERE dedupe_key IS NOT NULL AND status = 'queued' AND attempts = 0;
@@ -0,0 +1,21 @@
+-- Transactional outbox for WebSocket events. A change and the events that
+-- announce it are written in one transaction; a dispatcher publishes pending
+-- events after commit and marks them sent, so a crash between commit and
+-- broadcast delays the events rather than losing them. Delivery is
+-- at-least-once: an event published just before a crash is sent again.
+-- A dispatcher claiming a row sets locked_until as a lease, so other
+-- instances skip it.
+CREATE TABLE ws_outbox (
+    id           BIGSERIAL PRIMARY KEY,
+    target       TEXT NOT NULL CHECK (target IN ('channel', 'user', 'server', 'instance')),
+    target_id    UUID,
+    event        JSONB NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at      TIMESTAMPTZ,
+    CHECK ((target = 'instance') = (target_id IS NULL))
+);
+
+CREATE INDEX idx_ws_outbox_pending ON ws_outbox(id) WHERE sent_at IS NULL;
+CREATE INDEX idx_ws_outbox_sent ON ws_outbox(sent_at) WHERE sent_at IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Finalizing claims the session (uploading -> finalizing) before composing
+-- its parts, so two concurrent finalizes can't both assemble the same upload
+-- and a cancel can't delete parts mid-compose. A failed finalize hands the
+-- session back to 'uploading' so the client can retry.
+ALTER TABLE upload_sessions ADD COLUMN status TEXT NOT NULL DEFAULT 'uploading'
+    CHECK (status IN ('uploading', 'finalizing'));
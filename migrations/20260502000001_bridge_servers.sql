@@ -0,0 +1,4 @@
+-- A bridge can be registered to a single server. Server managers may only
+-- link bridges registered to their server; instance-wide bridges (NULL) can
+-- be linked only by the operator who registered them.
+ALTER TABLE bridges ADD COLUMN server_id UUID REFERENCES servers(id) ON DELETE CASCADE;
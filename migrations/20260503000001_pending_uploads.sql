@@ -0,0 +1,13 @@
+-- A simple upload takes a reference on its shared blob before any message
+-- links it. The reference is recorded here rather than in process memory, so
+-- any instance can link the upload and a restart doesn't leak it. The
+-- attachment GC releases references whose upload expired without being sent.
+CREATE TABLE pending_uploads (
+    attachment_id UUID PRIMARY KEY,
+    user_id       UUID NOT NULL,
+    content_hash  TEXT NOT NULL REFERENCES attachment_blobs(content_hash),
+    size_bytes    BIGINT NOT NULL,
+    file_hash     TEXT,
+    expires_at    TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_pending_uploads_expires ON pending_uploads(expires_at);
@@ -0,0 +1,8 @@
+-- One-time prekey ids come from a per-user counter bumped in the same
+-- transaction as the insert, so concurrent uploads never collide and ids
+-- are not reused once unused keys are cleared.
+ALTER TABLE users ADD COLUMN next_prekey_id INTEGER NOT NULL DEFAULT 0;
+
+UPDATE users SET next_prekey_id = p.max_key_id + 1
+FROM (SELECT user_id, MAX(key_id) AS max_key_id FROM prekeys GROUP BY user_id) p
+WHERE p.user_id = users.id;
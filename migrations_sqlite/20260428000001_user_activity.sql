@@ -0,0 +1,1154 @@
+-- SQLite baseline: the schema of migrations/ through
+-- 20260428000001_user_activity.sql, for the `sqlite` build. It takes the name
+-- of the last Postgres migration it covers; earlier Postgres migrations have
+-- no SQLite counterpart. Later schema changes get a migration here as well as
+-- in migrations/, under the same name.
+--
+-- Types follow what sqlx stores on SQLite: UUIDs and byte strings as BLOB,
+-- timestamps as RFC 3339 TEXT, JSONB and array columns as JSON TEXT. Messages
+-- are one unpartitioned table keyed by id. The trigram indexes of fuzzy
+-- member search have no equivalent and are left out.
+
+CREATE TABLE abuse_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    network_hash BLOB,
+    ip TEXT,
+    score INTEGER NOT NULL,
+    reasons TEXT NOT NULL DEFAULT '[]',
+    user_id BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE announcement_dismissals (
+    announcement_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    dismissed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (announcement_id, user_id),
+    FOREIGN KEY (announcement_id) REFERENCES announcements(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE announcements (
+    id BLOB NOT NULL PRIMARY KEY,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'info',
+    send_dm BOOLEAN NOT NULL DEFAULT FALSE,
+    publish_at TEXT NOT NULL,
+    expires_at TEXT,
+    published_at TEXT,
+    created_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (kind IN ('info', 'maintenance', 'release'))
+);
+
+CREATE TABLE attachment_blobs (
+    content_hash TEXT NOT NULL PRIMARY KEY,
+    storage_key TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    unreferenced_at TEXT,
+    CHECK (ref_count >= 0)
+);
+
+CREATE TABLE attachments (
+    id BLOB NOT NULL PRIMARY KEY,
+    message_id BLOB NOT NULL,
+    storage_key TEXT NOT NULL,
+    encrypted_meta BLOB,
+    size_bucket INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    file_hash TEXT,
+    orphaned_at TEXT,
+    thumbnail_key TEXT,
+    blurhash TEXT,
+    width INTEGER,
+    height INTEGER,
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    server_id BLOB,
+    content_hash TEXT,
+    scan_status TEXT,
+    scan_signature TEXT,
+    scanned_at TEXT,
+    content_type TEXT,
+    FOREIGN KEY (content_hash) REFERENCES attachment_blobs(content_hash),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE SET NULL,
+    CHECK (scan_status IN ('pending', 'clean', 'infected', 'failed'))
+);
+
+CREATE TABLE audit_log (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    actor_id BLOB NOT NULL,
+    action TEXT NOT NULL,
+    target_type TEXT,
+    target_id BLOB,
+    changes TEXT,
+    reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (actor_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE bans (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    reason TEXT,
+    banned_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (server_id, user_id),
+    FOREIGN KEY (banned_by) REFERENCES users(id),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE beta_settings (
+    id BOOLEAN NOT NULL DEFAULT TRUE PRIMARY KEY,
+    code_limit INTEGER,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (code_limit >= 0),
+    CHECK (id)
+);
+
+CREATE TABLE beta_waitlist (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email_hash TEXT NOT NULL UNIQUE,
+    email_ciphertext BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    locale TEXT
+);
+
+CREATE TABLE blocked_hashes (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    hash TEXT NOT NULL UNIQUE,
+    description TEXT,
+    added_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (added_by) REFERENCES users(id)
+);
+
+CREATE TABLE blocked_users (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    blocker_id BLOB NOT NULL,
+    blocked_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (blocker_id, blocked_id),
+    FOREIGN KEY (blocked_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (blocker_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE bridge_channels (
+    bridge_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    linked_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (bridge_id, channel_id),
+    FOREIGN KEY (bridge_id) REFERENCES bridges(id) ON DELETE CASCADE,
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (linked_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE bridge_events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    bridge_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (bridge_id) REFERENCES bridges(id) ON DELETE CASCADE
+);
+
+CREATE TABLE bridge_puppets (
+    user_id BLOB NOT NULL PRIMARY KEY,
+    bridge_id BLOB NOT NULL,
+    remote_id TEXT NOT NULL,
+    UNIQUE (bridge_id, remote_id),
+    FOREIGN KEY (bridge_id) REFERENCES bridges(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE bridges (
+    id BLOB NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    user_prefix TEXT NOT NULL UNIQUE,
+    created_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE channel_categories (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    collapsed_by_default BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_insights_daily (
+    channel_id BLOB NOT NULL,
+    server_id BLOB NOT NULL,
+    day TEXT NOT NULL,
+    messages INTEGER NOT NULL DEFAULT 0,
+    active_members INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (channel_id, day),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_members (
+    id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (channel_id, user_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_permission_overwrites (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    target_type TEXT NOT NULL,
+    target_id BLOB NOT NULL,
+    allow_bits INTEGER NOT NULL DEFAULT 0,
+    deny_bits INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (channel_id, target_type, target_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CHECK (target_type IN ('role', 'member'))
+);
+
+CREATE TABLE channel_retention_policies (
+    channel_id BLOB NOT NULL PRIMARY KEY,
+    max_age_days INTEGER,
+    max_messages INTEGER,
+    archive BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK ((max_age_days IS NULL) <> (max_messages IS NULL)),
+    CHECK (max_age_days > 0),
+    CHECK (max_messages > 0)
+);
+
+CREATE TABLE channels (
+    id BLOB NOT NULL PRIMARY KEY,
+    server_id BLOB,
+    encrypted_meta BLOB,
+    channel_type TEXT NOT NULL DEFAULT 'text',
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    category_id BLOB,
+    dm_status TEXT DEFAULT 'active',
+    is_private BOOLEAN NOT NULL DEFAULT FALSE,
+    encrypted BOOLEAN NOT NULL DEFAULT TRUE,
+    export_allowed BOOLEAN NOT NULL DEFAULT TRUE,
+    message_ttl INTEGER,
+    owner_id BLOB,
+    detached_snapshot_id BLOB,
+    suppress_role_mentions BOOLEAN NOT NULL DEFAULT FALSE,
+    topic TEXT,
+    description TEXT,
+    FOREIGN KEY (category_id) REFERENCES channel_categories(id) ON DELETE SET NULL,
+    FOREIGN KEY (detached_snapshot_id) REFERENCES restore_snapshots(id) ON DELETE CASCADE,
+    FOREIGN KEY (owner_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    CHECK (dm_status IN ('active', 'pending', 'declined')),
+    CHECK ((message_ttl IS NULL) OR (message_ttl > 0))
+);
+
+CREATE TABLE content_filters (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    pattern TEXT NOT NULL,
+    filter_type TEXT NOT NULL DEFAULT 'keyword',
+    action TEXT NOT NULL DEFAULT 'hide',
+    created_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (created_by) REFERENCES users(id),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE custom_emojis (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    uploaded_by BLOB,
+    animated BOOLEAN NOT NULL DEFAULT FALSE,
+    storage_key TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (server_id, name),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (uploaded_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE delivery_states (
+    user_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    last_delivered_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, channel_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE device_message_queue (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    device_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    queued_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (device_id) REFERENCES user_devices(id) ON DELETE CASCADE
+);
+
+CREATE TABLE device_verifications (
+    verifier_id BLOB NOT NULL,
+    device_id BLOB NOT NULL,
+    status TEXT NOT NULL,
+    identity_key BLOB NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (verifier_id, device_id),
+    FOREIGN KEY (device_id) REFERENCES user_devices(id) ON DELETE CASCADE,
+    FOREIGN KEY (verifier_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (status IN ('verified', 'blocked'))
+);
+
+CREATE TABLE email_dead_letters (
+    id BLOB NOT NULL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE email_outbox (
+    id BLOB NOT NULL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE email_verification_codes (
+    user_id BLOB NOT NULL PRIMARY KEY,
+    code_hash TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    sent_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE federated_users (
+    user_id BLOB NOT NULL PRIMARY KEY,
+    server_name TEXT NOT NULL,
+    remote_user_id BLOB NOT NULL,
+    fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (server_name, remote_user_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE federation_servers (
+    server_name TEXT NOT NULL PRIMARY KEY,
+    public_key BLOB NOT NULL,
+    fetched_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE federation_transactions (
+    origin TEXT NOT NULL,
+    txn_id TEXT NOT NULL,
+    received_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (origin, txn_id)
+);
+
+CREATE TABLE friendships (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    requester_id BLOB NOT NULL,
+    addressee_id BLOB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (requester_id, addressee_id),
+    FOREIGN KEY (addressee_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (requester_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (requester_id <> addressee_id),
+    CHECK (status IN ('pending', 'accepted'))
+);
+
+CREATE TABLE instance_audit_log (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    actor_id BLOB,
+    actor_role TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_type TEXT,
+    target_id BLOB,
+    details TEXT,
+    reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (actor_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE instance_bans (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    user_id BLOB NOT NULL UNIQUE,
+    reason TEXT,
+    banned_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT,
+    FOREIGN KEY (banned_by) REFERENCES users(id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE instance_branding (
+    id BOOLEAN NOT NULL DEFAULT TRUE PRIMARY KEY,
+    name TEXT NOT NULL DEFAULT 'Haven',
+    has_logo BOOLEAN NOT NULL DEFAULT FALSE,
+    accent_color TEXT NOT NULL DEFAULT '#C2410C',
+    background_color TEXT NOT NULL DEFAULT '#F5F0E8',
+    terms_url TEXT,
+    privacy_url TEXT,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (id)
+);
+
+CREATE TABLE invites (
+    id BLOB NOT NULL PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    created_by BLOB NOT NULL,
+    code TEXT NOT NULL UNIQUE,
+    max_uses INTEGER,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    temporary BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (created_by) REFERENCES users(id),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE key_backup_sessions (
+    user_id BLOB NOT NULL,
+    version INTEGER NOT NULL,
+    channel_id BLOB NOT NULL,
+    session_id TEXT NOT NULL,
+    first_message_index INTEGER NOT NULL,
+    forwarded_count INTEGER NOT NULL DEFAULT 0,
+    is_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    session_data BLOB NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (user_id, version, channel_id, session_id),
+    FOREIGN KEY (user_id, version) REFERENCES key_backup_versions(user_id, version) ON DELETE CASCADE
+);
+
+CREATE TABLE key_backup_versions (
+    user_id BLOB NOT NULL,
+    version INTEGER NOT NULL,
+    algorithm TEXT NOT NULL,
+    auth_data BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    deleted_at TEXT,
+    PRIMARY KEY (user_id, version),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE key_backups (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    user_id BLOB NOT NULL UNIQUE,
+    encrypted_data BLOB NOT NULL,
+    nonce BLOB NOT NULL,
+    salt BLOB NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE key_transparency_log (
+    seq INTEGER NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    identity_key BLOB NOT NULL,
+    leaf_hash BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE legal_holds (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    subject_type TEXT NOT NULL,
+    subject_id BLOB NOT NULL,
+    reason TEXT,
+    placed_by BLOB,
+    placed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    released_by BLOB,
+    released_at TEXT,
+    release_reason TEXT,
+    FOREIGN KEY (placed_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (released_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (subject_type IN ('server', 'user'))
+);
+
+CREATE TABLE live_location_sessions (
+    message_id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    expires_at TEXT NOT NULL,
+    ended_at TEXT,
+    last_position BLOB,
+    last_update_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE member_roles (
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    role_id BLOB NOT NULL,
+    PRIMARY KEY (server_id, user_id, role_id),
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE message_mentions (
+    message_id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    user_ids TEXT NOT NULL DEFAULT '[]',
+    role_ids TEXT NOT NULL DEFAULT '[]',
+    everyone BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE
+);
+
+CREATE TABLE message_voice_notes (
+    message_id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    waveform BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CHECK (duration_ms > 0)
+);
+
+CREATE TABLE messages (
+    id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    sender_token BLOB NOT NULL,
+    encrypted_body BLOB NOT NULL,
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT,
+    has_attachments BOOLEAN NOT NULL DEFAULT FALSE,
+    sender_id BLOB,
+    edited_at TEXT,
+    reply_to_id BLOB,
+    message_type TEXT NOT NULL DEFAULT 'user',
+    forwarded_from_id BLOB,
+    forwarded_from_channel_id BLOB,
+    forwarded_from_timestamp TEXT,
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (sender_id) REFERENCES users(id)
+);
+
+CREATE TABLE notification_rules (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    scope TEXT NOT NULL,
+    target_id BLOB,
+    level TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK ((scope = 'global') = (target_id IS NULL)),
+    CHECK (level IN ('all', 'mentions', 'none')),
+    CHECK (scope IN ('global', 'server', 'channel'))
+);
+
+CREATE TABLE onboarding_acknowledgments (
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    rules_version INTEGER NOT NULL,
+    acknowledged_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (server_id, user_id),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE pending_registrations (
+    user_id BLOB NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE permission_templates (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    allow_bits INTEGER NOT NULL DEFAULT 0,
+    deny_bits INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (server_id, name),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE pinned_messages (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    pinned_by BLOB NOT NULL,
+    pinned_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (channel_id, message_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (pinned_by) REFERENCES users(id)
+);
+
+CREATE TABLE prekeys (
+    id BLOB NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    key_id INTEGER NOT NULL,
+    public_key BLOB NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE profile_key_distributions (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    from_user_id BLOB NOT NULL,
+    to_user_id BLOB NOT NULL,
+    encrypted_profile_key BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (from_user_id, to_user_id),
+    FOREIGN KEY (from_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (to_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE quiet_hours (
+    user_id BLOB NOT NULL PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    start_minute INTEGER NOT NULL,
+    end_minute INTEGER NOT NULL,
+    days INTEGER NOT NULL DEFAULT 127,
+    utc_offset_minutes INTEGER NOT NULL,
+    timezone TEXT,
+    priority_user_ids TEXT NOT NULL DEFAULT '[]',
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (start_minute <> end_minute),
+    CHECK ((days >= 1) AND (days <= 127)),
+    CHECK ((end_minute >= 0) AND (end_minute <= 1439)),
+    CHECK ((start_minute >= 0) AND (start_minute <= 1439)),
+    CHECK ((utc_offset_minutes >= -840) AND (utc_offset_minutes <= 840))
+);
+
+CREATE TABLE reactions (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    message_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    emoji TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    sender_token TEXT,
+    UNIQUE (message_id, user_id, emoji),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE read_states (
+    user_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    last_read_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (user_id, channel_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE refresh_tokens (
+    id BLOB NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    family_id BLOB,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    device_name TEXT,
+    ip_address TEXT,
+    last_activity TEXT,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE registration_invites (
+    id BLOB NOT NULL PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    created_by BLOB,
+    used_by BLOB,
+    used_at TEXT,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    email_hash TEXT,
+    revoked_at TEXT,
+    label TEXT,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (used_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE reports (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    reporter_id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    reviewed_by BLOB,
+    reviewed_at TEXT,
+    admin_notes TEXT,
+    escalated_to TEXT,
+    escalated_at TEXT,
+    escalated_by BLOB,
+    FOREIGN KEY (escalated_by) REFERENCES users(id),
+    FOREIGN KEY (reporter_id) REFERENCES users(id),
+    FOREIGN KEY (reviewed_by) REFERENCES users(id)
+);
+
+CREATE TABLE restore_jobs (
+    id BLOB NOT NULL PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    phase TEXT NOT NULL DEFAULT 'structure',
+    done INTEGER NOT NULL DEFAULT 0,
+    total INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'running',
+    error TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (status IN ('running', 'completed', 'failed'))
+);
+
+CREATE TABLE restore_snapshots (
+    id BLOB NOT NULL PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    created_by BLOB,
+    structure TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE roles (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT,
+    permissions INTEGER NOT NULL DEFAULT 0,
+    position INTEGER NOT NULL DEFAULT 0,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE sender_key_distributions (
+    id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    from_user_id BLOB NOT NULL,
+    to_user_id BLOB NOT NULL,
+    distribution_id BLOB NOT NULL,
+    encrypted_skdm BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (channel_id, from_user_id, to_user_id, distribution_id),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (from_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (to_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_applications (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    invite_id BLOB,
+    answers TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewed_by BLOB,
+    reviewed_at TEXT,
+    deny_reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (invite_id) REFERENCES invites(id) ON DELETE SET NULL,
+    FOREIGN KEY (reviewed_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (status IN ('pending', 'approved', 'denied'))
+);
+
+CREATE TABLE server_event_rsvps (
+    event_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (event_id, user_id),
+    FOREIGN KEY (event_id) REFERENCES server_events(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (status IN ('interested', 'going'))
+);
+
+CREATE TABLE server_events (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    creator_id BLOB,
+    encrypted_meta BLOB NOT NULL,
+    channel_id BLOB,
+    starts_at TEXT NOT NULL,
+    ends_at TEXT,
+    reminder_sent_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE SET NULL,
+    FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    CHECK ((ends_at IS NULL) OR (ends_at > starts_at))
+);
+
+CREATE TABLE server_insights_daily (
+    server_id BLOB NOT NULL,
+    day TEXT NOT NULL,
+    messages INTEGER NOT NULL DEFAULT 0,
+    active_members INTEGER NOT NULL DEFAULT 0,
+    joins INTEGER NOT NULL DEFAULT 0,
+    leaves INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (server_id, day),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_members (
+    id BLOB NOT NULL PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    encrypted_role BLOB,
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    nickname TEXT,
+    timed_out_until TEXT,
+    temporary BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (server_id, user_id),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_membership_events (
+    server_id BLOB NOT NULL,
+    joined BOOLEAN NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_onboarding (
+    server_id BLOB NOT NULL PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    encrypted_meta BLOB NOT NULL,
+    default_channel_ids TEXT NOT NULL DEFAULT '[]',
+    require_acknowledgment BOOLEAN NOT NULL DEFAULT FALSE,
+    rules_version INTEGER NOT NULL DEFAULT 1,
+    prompts TEXT NOT NULL DEFAULT '[]',
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_quotas (
+    server_id BLOB NOT NULL PRIMARY KEY,
+    max_storage_bytes INTEGER,
+    max_messages_per_minute INTEGER,
+    max_members INTEGER,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (max_members >= 0),
+    CHECK (max_messages_per_minute >= 0),
+    CHECK (max_storage_bytes >= 0)
+);
+
+CREATE TABLE server_screening (
+    server_id BLOB NOT NULL PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    questions TEXT NOT NULL DEFAULT '[]',
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE
+);
+
+CREATE TABLE server_verification (
+    server_id BLOB NOT NULL PRIMARY KEY,
+    level TEXT NOT NULL DEFAULT 'none',
+    min_account_age_days INTEGER NOT NULL DEFAULT 5,
+    min_membership_minutes INTEGER NOT NULL DEFAULT 10,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    CHECK (level IN ('none', 'email', 'account_age', 'membership', 'phone')),
+    CHECK ((min_account_age_days >= 1) AND (min_account_age_days <= 365)),
+    CHECK ((min_membership_minutes >= 1) AND (min_membership_minutes <= 10080))
+);
+
+CREATE TABLE servers (
+    id BLOB NOT NULL PRIMARY KEY,
+    encrypted_meta BLOB,
+    owner_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    system_channel_id BLOB,
+    icon_url TEXT,
+    is_system BOOLEAN NOT NULL DEFAULT FALSE,
+    upload_tier TEXT NOT NULL DEFAULT 'standard',
+    list_version INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT,
+    deleted_by BLOB,
+    FOREIGN KEY (deleted_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (owner_id) REFERENCES users(id),
+    FOREIGN KEY (system_channel_id) REFERENCES channels(id) ON DELETE SET NULL,
+    CHECK (upload_tier IN ('standard', 'boosted'))
+);
+
+CREATE TABLE sync_changes (
+    version INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id BLOB NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id BLOB NOT NULL,
+    deleted BOOLEAN NOT NULL DEFAULT FALSE,
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CHECK (entity_type IN ('server', 'channel', 'role', 'member'))
+);
+
+CREATE TABLE upload_limits (
+    id BLOB NOT NULL DEFAULT (randomblob(16)) PRIMARY KEY,
+    server_id BLOB NOT NULL,
+    role_id BLOB,
+    channel_id BLOB,
+    max_size_bytes INTEGER,
+    max_count INTEGER,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    FOREIGN KEY (server_id) REFERENCES servers(id) ON DELETE CASCADE,
+    CHECK (((role_id IS NOT NULL) + (channel_id IS NOT NULL)) = 1),
+    CHECK (max_count >= 0),
+    CHECK (max_size_bytes >= 0)
+);
+
+CREATE TABLE upload_sessions (
+    id BLOB NOT NULL PRIMARY KEY,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    content_type TEXT NOT NULL,
+    upload_length INTEGER NOT NULL,
+    upload_offset INTEGER NOT NULL DEFAULT 0,
+    part_ids TEXT NOT NULL DEFAULT '[]',
+    file_hash TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE user_devices (
+    id BLOB NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    identity_key BLOB NOT NULL,
+    signed_prekey BLOB NOT NULL,
+    signed_prekey_sig BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (user_id, identity_key),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE user_profile_media (
+    user_id BLOB NOT NULL,
+    slot TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (user_id, slot),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE users (
+    id BLOB NOT NULL PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    display_name TEXT,
+    email_hash TEXT,
+    password_hash TEXT NOT NULL,
+    identity_key BLOB NOT NULL,
+    signed_prekey BLOB NOT NULL,
+    signed_prekey_sig BLOB NOT NULL,
+    totp_secret TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    about_me TEXT,
+    custom_status TEXT,
+    custom_status_emoji TEXT,
+    avatar_url TEXT,
+    dm_privacy TEXT NOT NULL DEFAULT 'friends_only',
+    pending_totp_secret TEXT,
+    encrypted_profile BLOB,
+    banner_url TEXT,
+    is_instance_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    is_system BOOLEAN NOT NULL DEFAULT FALSE,
+    instance_role TEXT,
+    abuse_score INTEGER,
+    receipt_privacy TEXT NOT NULL DEFAULT 'all',
+    email_verified_at TEXT,
+    last_active_at TEXT,
+    CHECK (dm_privacy IN ('everyone', 'friends_only', 'server_members', 'friends_strict')),
+    CHECK ((instance_role IS NULL) OR (instance_role IN ('operator', 'instance_moderator', 'support'))),
+    CHECK (receipt_privacy IN ('all', 'delivered', 'none'))
+);
+
+CREATE INDEX idx_abuse_events_created_at ON abuse_events (created_at);
+CREATE INDEX idx_abuse_events_ip ON abuse_events (created_at) WHERE ip IS NOT NULL;
+CREATE INDEX idx_abuse_events_network ON abuse_events (network_hash, created_at);
+CREATE INDEX idx_announcement_dismissals_user ON announcement_dismissals (user_id);
+CREATE INDEX idx_announcements_unpublished ON announcements (publish_at) WHERE published_at IS NULL;
+CREATE INDEX idx_attachment_blobs_unreferenced ON attachment_blobs (unreferenced_at) WHERE ref_count = 0;
+CREATE INDEX idx_attachments_content_hash ON attachments (content_hash) WHERE content_hash IS NOT NULL;
+CREATE INDEX idx_attachments_file_hash ON attachments (file_hash);
+CREATE INDEX idx_attachments_message ON attachments (message_id);
+CREATE INDEX idx_attachments_orphaned_at ON attachments (orphaned_at) WHERE orphaned_at IS NOT NULL;
+CREATE INDEX idx_attachments_scan_pending ON attachments (created_at) WHERE scan_status = 'pending';
+CREATE INDEX idx_attachments_server ON attachments (server_id) WHERE server_id IS NOT NULL;
+CREATE INDEX idx_audit_log_server ON audit_log (server_id, created_at DESC);
+CREATE INDEX idx_bans_server ON bans (server_id);
+CREATE INDEX idx_bans_user ON bans (user_id);
+CREATE INDEX idx_blocked_hashes_hash ON blocked_hashes (hash);
+CREATE INDEX idx_blocked_users_blocked ON blocked_users (blocked_id);
+CREATE INDEX idx_blocked_users_blocker ON blocked_users (blocker_id);
+CREATE INDEX idx_bridge_channels_channel ON bridge_channels (channel_id);
+CREATE INDEX idx_bridge_events_bridge ON bridge_events (bridge_id, seq);
+CREATE INDEX idx_channel_categories_server ON channel_categories (server_id);
+CREATE INDEX idx_channel_insights_daily_server ON channel_insights_daily (server_id, day);
+CREATE INDEX idx_channel_members_channel ON channel_members (channel_id);
+CREATE INDEX idx_channel_members_user ON channel_members (user_id);
+CREATE INDEX idx_channel_members_user_channel ON channel_members (user_id, channel_id);
+CREATE INDEX idx_channel_overwrites_channel ON channel_permission_overwrites (channel_id);
+CREATE INDEX idx_channels_category ON channels (category_id);
+CREATE INDEX idx_channels_detached_snapshot ON channels (detached_snapshot_id) WHERE detached_snapshot_id IS NOT NULL;
+CREATE INDEX idx_channels_server ON channels (server_id);
+CREATE INDEX idx_channels_type ON channels (channel_type);
+CREATE INDEX idx_content_filters_server_id ON content_filters (server_id);
+CREATE INDEX idx_custom_emojis_server_id ON custom_emojis (server_id);
+CREATE INDEX idx_delivery_states_channel ON delivery_states (channel_id);
+CREATE INDEX idx_device_message_queue_device ON device_message_queue (device_id, seq);
+CREATE INDEX idx_device_message_queue_queued_at ON device_message_queue (queued_at);
+CREATE INDEX idx_email_dead_letters_created ON email_dead_letters (created_at DESC);
+CREATE INDEX idx_email_outbox_next_attempt ON email_outbox (next_attempt_at);
+CREATE INDEX idx_friendships_addressee ON friendships (addressee_id);
+CREATE INDEX idx_friendships_addressee_status ON friendships (addressee_id, status);
+CREATE INDEX idx_friendships_pair ON friendships (requester_id, addressee_id);
+CREATE INDEX idx_friendships_pair_reverse ON friendships (addressee_id, requester_id);
+CREATE INDEX idx_friendships_requester ON friendships (requester_id);
+CREATE INDEX idx_friendships_status ON friendships (status);
+CREATE INDEX idx_instance_audit_log_actor ON instance_audit_log (actor_id, created_at DESC);
+CREATE INDEX idx_instance_audit_log_created ON instance_audit_log (created_at DESC);
+CREATE INDEX idx_instance_audit_log_target ON instance_audit_log (target_id, created_at DESC) WHERE target_id IS NOT NULL;
+CREATE INDEX idx_instance_bans_user_id ON instance_bans (user_id);
+CREATE INDEX idx_invites_code ON invites (code);
+CREATE INDEX idx_invites_server ON invites (server_id);
+CREATE INDEX idx_key_backups_user ON key_backups (user_id);
+CREATE INDEX idx_key_transparency_log_user ON key_transparency_log (user_id, seq DESC);
+CREATE UNIQUE INDEX idx_legal_holds_active ON legal_holds (subject_type, subject_id) WHERE released_at IS NULL;
+CREATE INDEX idx_legal_holds_placed ON legal_holds (placed_at DESC);
+CREATE INDEX idx_live_location_sessions_open ON live_location_sessions (expires_at) WHERE ended_at IS NULL;
+CREATE INDEX idx_member_roles_user ON member_roles (user_id);
+CREATE INDEX idx_message_mentions_channel ON message_mentions (channel_id, created_at);
+CREATE INDEX idx_messages_channel_time ON messages (channel_id, timestamp DESC);
+CREATE INDEX idx_messages_expires ON messages (expires_at) WHERE expires_at IS NOT NULL;
+CREATE INDEX idx_messages_reply_to ON messages (channel_id, reply_to_id, timestamp) WHERE reply_to_id IS NOT NULL;
+CREATE INDEX idx_messages_sender ON messages (sender_id) WHERE sender_id IS NOT NULL;
+CREATE INDEX idx_notification_rules_target ON notification_rules (target_id) WHERE target_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_notification_rules_user_target ON notification_rules (user_id, scope, COALESCE(target_id, X'00000000000000000000000000000000'));
+CREATE INDEX idx_pinned_messages_channel ON pinned_messages (channel_id);
+CREATE INDEX idx_prekeys_user_unused ON prekeys (user_id, used) WHERE used = false;
+CREATE INDEX idx_pkd_to_user ON profile_key_distributions (to_user_id);
+CREATE INDEX idx_quiet_hours_enabled ON quiet_hours (user_id) WHERE enabled OR active;
+CREATE INDEX idx_reactions_message_id ON reactions (message_id);
+CREATE INDEX idx_reactions_user_id ON reactions (user_id);
+CREATE INDEX idx_read_states_channel ON read_states (channel_id);
+CREATE INDEX idx_refresh_tokens_expires ON refresh_tokens (expires_at) WHERE expires_at IS NOT NULL;
+CREATE INDEX idx_refresh_tokens_family ON refresh_tokens (family_id) WHERE family_id IS NOT NULL;
+CREATE INDEX idx_refresh_tokens_hash ON refresh_tokens (token_hash);
+CREATE INDEX idx_refresh_tokens_user ON refresh_tokens (user_id);
+CREATE INDEX idx_reg_invites_code ON registration_invites (code);
+CREATE INDEX idx_reg_invites_created_by ON registration_invites (created_by);
+CREATE INDEX idx_registration_invites_email_hash ON registration_invites (email_hash) WHERE email_hash IS NOT NULL;
+CREATE INDEX idx_reports_created_at ON reports (created_at DESC);
+CREATE INDEX idx_reports_message ON reports (message_id);
+CREATE INDEX idx_reports_reporter ON reports (reporter_id);
+CREATE INDEX idx_reports_status ON reports (status);
+CREATE INDEX idx_restore_jobs_server_user ON restore_jobs (server_id, user_id, created_at DESC);
+CREATE INDEX idx_restore_snapshots_expires ON restore_snapshots (expires_at);
+CREATE INDEX idx_restore_snapshots_server ON restore_snapshots (server_id, created_at DESC);
+CREATE INDEX idx_roles_server ON roles (server_id);
+CREATE INDEX idx_skdm_from_user_channel ON sender_key_distributions (from_user_id, channel_id);
+CREATE INDEX idx_skdm_to_user_channel ON sender_key_distributions (to_user_id, channel_id);
+CREATE UNIQUE INDEX idx_server_applications_pending ON server_applications (server_id, user_id) WHERE status = 'pending';
+CREATE INDEX idx_server_applications_queue ON server_applications (server_id, status, created_at);
+CREATE INDEX idx_server_applications_user ON server_applications (user_id, created_at);
+CREATE INDEX idx_server_event_rsvps_user ON server_event_rsvps (user_id);
+CREATE INDEX idx_server_events_reminders ON server_events (starts_at) WHERE reminder_sent_at IS NULL;
+CREATE INDEX idx_server_events_server ON server_events (server_id, starts_at);
+CREATE INDEX idx_server_insights_daily_day ON server_insights_daily (day);
+CREATE INDEX idx_server_members_server ON server_members (server_id);
+CREATE INDEX idx_server_members_temporary ON server_members (user_id) WHERE temporary;
+CREATE INDEX idx_server_members_user ON server_members (user_id);
+CREATE INDEX idx_server_membership_events_created ON server_membership_events (created_at);
+CREATE INDEX idx_servers_deleted_at ON servers (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_servers_owner ON servers (owner_id);
+CREATE INDEX idx_sync_changes_changed_at ON sync_changes (changed_at);
+CREATE INDEX idx_sync_changes_member ON sync_changes (entity_id, version) WHERE entity_type = 'member';
+CREATE INDEX idx_sync_changes_server_version ON sync_changes (server_id, version);
+CREATE UNIQUE INDEX idx_upload_limits_channel ON upload_limits (channel_id) WHERE channel_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_upload_limits_role ON upload_limits (role_id) WHERE role_id IS NOT NULL;
+CREATE INDEX idx_upload_limits_server ON upload_limits (server_id);
+CREATE INDEX idx_upload_sessions_expires ON upload_sessions (expires_at);
+CREATE INDEX idx_user_devices_user ON user_devices (user_id);
+CREATE INDEX idx_users_last_active_at ON users (last_active_at) WHERE last_active_at IS NOT NULL;
+CREATE INDEX idx_users_username ON users (username);
+
+-- Seed rows (the Haven system user and default server, singleton settings)
+PRAGMA defer_foreign_keys = ON;
+
+INSERT INTO users (id, username, display_name, password_hash, identity_key, signed_prekey, signed_prekey_sig, custom_status, is_system)
+VALUES (X'00000000000040008000000000000001', 'haven', 'Haven', '!SYSTEM_USER_NO_LOGIN!', X'0000000000000000000000000000000000000000000000000000000000000000', X'0000000000000000000000000000000000000000000000000000000000000000', X'00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000', 'Official Haven Message', TRUE);
+INSERT INTO servers (id, encrypted_meta, owner_id, system_channel_id, is_system)
+VALUES (X'00000000000040008000000000000002', X'7b226e616d65223a22486176656e227d', X'00000000000040008000000000000001', X'00000000000040008000000000000003', TRUE);
+INSERT INTO channels (id, server_id, encrypted_meta, encrypted)
+VALUES (X'00000000000040008000000000000003', X'00000000000040008000000000000002', X'7b226e616d65223a2277656c636f6d65227d', FALSE);
+INSERT INTO channels (id, server_id, encrypted_meta, position, encrypted)
+VALUES (X'00000000000040008000000000000004', X'00000000000040008000000000000002', X'7b226e616d65223a2267656e6572616c227d', 1, FALSE);
+INSERT INTO channel_members (id, channel_id, user_id)
+VALUES (randomblob(16), X'00000000000040008000000000000003', X'00000000000040008000000000000001');
+INSERT INTO channel_members (id, channel_id, user_id)
+VALUES (randomblob(16), X'00000000000040008000000000000004', X'00000000000040008000000000000001');
+INSERT INTO server_members (id, server_id, user_id, encrypted_role)
+VALUES (randomblob(16), X'00000000000040008000000000000002', X'00000000000040008000000000000001', X'6f776e6572');
+INSERT INTO messages (id, channel_id, sender_token, encrypted_body, sender_id)
+VALUES (X'00000000000040008000000000000005', X'00000000000040008000000000000003', X'00', X'57656c636f6d6520746f20486176656e21205468697320697320796f757220686f6d6520666f7220707269766174652c20656e6372797074656420636f6d6d756e69636174696f6e2e204578706c6f726520746865206368616e6e656c732c2061646420667269656e64732c20616e64206d616b6520796f757273656c6620617420686f6d652e', X'00000000000040008000000000000001');
+INSERT INTO key_transparency_log (seq, user_id, identity_key, leaf_hash)
+VALUES (0, X'00000000000040008000000000000001', X'0000000000000000000000000000000000000000000000000000000000000000', X'75b37a1a7c52a063b05c30176a0cda1c73c4aa28bd04a36f284ab4bfa1164ead');
+INSERT INTO permission_templates (server_id, name, allow_bits)
+VALUES (X'00000000000040008000000000000002', 'Moderator', 121700432);
+INSERT INTO permission_templates (server_id, name, deny_bits)
+VALUES (X'00000000000040008000000000000002', 'Read-only', 268445952);
+INSERT INTO beta_settings DEFAULT VALUES;
+INSERT INTO instance_branding DEFAULT VALUES;
@@ -0,0 +1,27 @@
+-- Persistent background job queue; see the Postgres migration of the same name.
+CREATE TABLE background_jobs (
+    id BLOB NOT NULL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    run_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    locked_until TEXT,
+    last_error TEXT,
+    dedupe_key TEXT,
+    created_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    finished_at TEXT,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    CHECK (status IN ('queued', 'running', 'completed', 'failed', 'cancelled')),
+    CHECK (max_attempts > 0)
+);
+
+CREATE INDEX idx_background_jobs_due ON background_jobs (run_at) WHERE status = 'queued';
+CREATE INDEX idx_background_jobs_lease ON background_jobs (locked_until) WHERE status = 'running';
+CREATE INDEX idx_background_jobs_finished ON background_jobs (finished_at) WHERE finished_at IS NOT NULL;
+CREATE INDEX idx_background_jobs_created ON background_jobs (created_at DESC);
+CREATE UNIQUE INDEX idx_background_jobs_dedupe ON background_jobs (dedupe_key)
+    WHERE dedupe_key IS NOT NULL AND status = 'queued' AND attempts = 0;
@@ -0,0 +1,16 @@
+-- Transactional outbox for WebSocket events; see the Postgres migration of the same name.
+CREATE TABLE ws_outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    target TEXT NOT NULL,
+    target_id BLOB,
+    event TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    sent_at TEXT,
+    CHECK (target IN ('channel', 'user', 'server', 'instance')),
+    CHECK ((target = 'instance') = (target_id IS NULL))
+);
+
+CREATE INDEX idx_ws_outbox_pending ON ws_outbox (id) WHERE sent_at IS NULL;
+CREATE INDEX idx_ws_outbox_sent ON ws_outbox (sent_at) WHERE sent_at IS NOT NULL;
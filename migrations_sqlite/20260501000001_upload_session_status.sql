@@ -0,0 +1,3 @@
+-- Upload session claim state; see the Postgres migration of the same name.
+ALTER TABLE upload_sessions ADD COLUMN status TEXT NOT NULL DEFAULT 'uploading'
+    CHECK (status IN ('uploading', 'finalizing'));
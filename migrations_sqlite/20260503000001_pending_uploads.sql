@@ -0,0 +1,12 @@
+-- Blob references held by unsent simple uploads; see the Postgres migration
+-- of the same name.
+CREATE TABLE pending_uploads (
+    attachment_id BLOB NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    content_hash TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    file_hash TEXT,
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (content_hash) REFERENCES attachment_blobs(content_hash)
+);
+CREATE INDEX idx_pending_uploads_expires ON pending_uploads(expires_at);
@@ -0,0 +1,9 @@
+-- One-time prekey ids come from a per-user counter bumped in the same
+-- transaction as the insert, so concurrent uploads never collide and ids
+-- are not reused once unused keys are cleared.
+ALTER TABLE users ADD COLUMN next_prekey_id INTEGER NOT NULL DEFAULT 0;
+
+UPDATE users SET next_prekey_id = COALESCE(
+    (SELECT MAX(key_id) + 1 FROM prekeys WHERE prekeys.user_id = users.id),
+    0
+);
@@ -0,0 +1,376 @@
+//! Abuse scoring for registrations and beta code requests.
+//!
+//! Each request gets a score from 0 (nothing suspicious) to 100, the sum of
+//! the signals it shows:
+//! - `listed`: the IP is on one of the operator's `ABUSE_IP_LISTS` (Tor
+//!   exits, VPN or hosting ranges, known abusers), refreshed every 6 hours
+//! - `velocity`: other scored requests from the same /24 (IPv4) or /48
+//!   (IPv6) in the last hour
+//! - `proxy_headers`: the request announces a proxy the server isn't behind
+//! - `no_user_agent`, `no_accept_language`: a bare HTTP client, not a browser
+//!
+//! Scores are recorded in `abuse_events`. The network is stored only as a
+//! hash keyed with the storage key, and the raw IP only for
+//! `ABUSE_IP_RETENTION_HOURS` (0 = not at all). Loopback and private
+//! addresses aren't grouped into networks: behind a proxy without
+//! `TRUST_PROXY`, every request would look like the same one.
+//!
+//! Requests scoring `ABUSE_BLOCK_SCORE` or more are refused. A
+//! registration's score also stays on the account, where a server's
+//! `abuse` content filters match it (the member list's `automod_action`).
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::AppState;
+
+const LISTED: i16 = 50;
+const VELOCITY_PER_REQUEST: i16 = 15;
+const VELOCITY_MAX: i16 = 45;
+const PROXY_HEADERS: i16 = 15;
+const NO_USER_AGENT: i16 = 10;
+const NO_ACCEPT_LANGUAGE: i16 = 5;
+
+/// How far back requests from the same network count toward `velocity`.
+pub const VELOCITY_WINDOW_SECS: i64 = 3600;
+/// Scored requests (without their IPs) are kept this long.
+pub const EVENT_RETENTION_DAYS: u32 = 7;
+
+/// A range from an IP list: an address or CIDR block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IpRange {
+    bits: u128,
+    prefix: u8,
+    v6: bool,
+}
+
+/// IPv4 (including IPv4-mapped IPv6) as 32 bits, IPv6 as 128.
+fn to_bits(ip: IpAddr) -> (u128, bool) {
+    match ip {
+        IpAddr::V4(v4) => (u32::from(v4) as u128, false),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => (u32::from(v4) as u128, false),
+            None => (u128::from(v6), true),
+        },
+    }
+}
+
+impl IpRange {
+    fn parse(s: &str) -> Option<Self> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (s, None),
+        };
+        let (bits, v6) = to_bits(addr.parse().ok()?);
+        let len = if v6 { 128 } else { 32 };
+        let prefix = prefix.unwrap_or(len);
+        (prefix <= len).then_some(Self { bits, prefix, v6 })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let (bits, v6) = to_bits(ip);
+        let len = if v6 { 128 } else { 32 };
+        v6 == self.v6 && (self.prefix == 0 || (bits ^ self.bits) >> (len - self.prefix) == 0)
+    }
+}
+
+/// Parse an IP list: one address or CIDR block per line, optionally followed
+/// by whitespace and anything else. `#` and `;` start comments.
+fn parse_list(text: &str) -> Vec<IpRange> {
+    text.lines()
+        .filter_map(|line| {
+            line.split(['#', ';'])
+                .next()?
+                .split_whitespace()
+                .next()
+        })
+        .filter_map(IpRange::parse)
+        .collect()
+}
+
+/// The operator's IP lists (`ABUSE_IP_LISTS`), as last loaded.
+#[derive(Clone, Default)]
+pub struct IpLists(Arc<RwLock<Vec<IpRange>>>);
+
+impl IpLists {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .any(|range| range.contains(ip))
+    }
+}
+
+fn http() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("HTTP client builds")
+    })
+}
+
+async fn fetch_list(source: &str) -> Result<String, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = http().get(source).send().await.map_err(|e| e.to_string())?;
+        let response = response.error_for_status().map_err(|e| e.to_string())?;
+        response.text().await.map_err(|e| e.to_string())
+    } else {
+        tokio::fs::read_to_string(source)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Reload every `ABUSE_IP_LISTS` source and return how many ranges they
+/// hold. If one can't be loaded, the lists loaded before are kept.
+pub async fn refresh_lists(state: &AppState) -> AppResult<u64> {
+    let sources = state.live_config.get().abuse_ip_lists.clone();
+    let mut ranges = Vec::new();
+    for source in sources.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let text = fetch_list(source).await.map_err(|e| {
+            AppError::BadRequest(format!("Could not load abuse IP list {}: {}", source, e))
+        })?;
+        ranges.extend(parse_list(&text));
+    }
+    let count = ranges.len() as u64;
+    *state.abuse_lists.0.write().unwrap() = ranges;
+    Ok(count)
+}
+
+/// The client's address: the first `X-Forwarded-For` hop (or `X-Real-IP`)
+/// behind a trusted proxy, the socket peer otherwise.
+pub fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trust_proxy: bool,
+) -> Option<IpAddr> {
+    let forwarded = || {
+        let first_hop = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.split(',').next());
+        let real_ip = headers.get("x-real-ip").and_then(|v| v.to_str().ok());
+        first_hop.or(real_ip)?.trim().parse().ok()
+    };
+    trust_proxy
+        .then(forwarded)
+        .flatten()
+        .or(peer.map(|p| p.ip()))
+}
+
+fn is_local_v4(ip: Ipv4Addr) -> bool {
+    ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()
+}
+
+fn is_local_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback() || ip.is_unspecified() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+}
+
+/// The /24 or /48 an address belongs to, or None for local addresses.
+fn network(ip: IpAddr) -> Option<String> {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+        v4 => v4,
+    };
+    match ip {
+        IpAddr::V4(v4) if !is_local_v4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            Some(format!("{}.{}.{}.0/24", a, b, c))
+        }
+        IpAddr::V6(v6) if !is_local_v6(v6) => {
+            let s = v6.segments();
+            Some(format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2]))
+        }
+        _ => None,
+    }
+}
+
+fn network_hash(network: &str, key: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(b"abuse-network:");
+    mac.update(network.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signals read from the request headers alone.
+fn header_signals(headers: &HeaderMap, trust_proxy: bool) -> Vec<(&'static str, i16)> {
+    let mut signals = Vec::new();
+    // Behind a trusted proxy the forwarding headers are its own
+    let proxied = headers.contains_key(header::VIA)
+        || (!trust_proxy
+            && ["x-forwarded-for", "x-real-ip", "forwarded"]
+                .iter()
+                .any(|h| headers.contains_key(*h)));
+    if proxied {
+        signals.push(("proxy_headers", PROXY_HEADERS));
+    }
+    if !headers.contains_key(header::USER_AGENT) {
+        signals.push(("no_user_agent", NO_USER_AGENT));
+    }
+    if !headers.contains_key(header::ACCEPT_LANGUAGE) {
+        signals.push(("no_accept_language", NO_ACCEPT_LANGUAGE));
+    }
+    signals
+}
+
+/// A scored request.
+#[derive(Debug)]
+pub struct Assessment {
+    pub event_id: i64,
+    pub score: i16,
+}
+
+/// Score a request of `kind` ("register" or "beta"), record it, and refuse
+/// it if it reaches `ABUSE_BLOCK_SCORE`.
+pub async fn assess(
+    state: &AppState,
+    kind: &str,
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+) -> AppResult<Assessment> {
+    let config = state.live_config.get();
+    let ip = client_ip(headers, peer, state.config.trust_proxy);
+    let mut signals = header_signals(headers, state.config.trust_proxy);
+
+    if ip.is_some_and(|ip| state.abuse_lists.contains(ip)) {
+        signals.push(("listed", LISTED));
+    }
+    let hash = ip
+        .and_then(network)
+        .map(|n| network_hash(&n, &state.storage_key));
+    if let Some(hash) = &hash {
+        // Bursts land within seconds, so count on the primary
+        let recent =
+            queries::count_recent_abuse_events(state.db.primary(), hash, VELOCITY_WINDOW_SECS)
+                .await?;
+        if recent > 0 {
+            let weight = (recent * VELOCITY_PER_REQUEST as i64).min(VELOCITY_MAX as i64) as i16;
+            signals.push(("velocity", weight));
+        }
+    }
+
+    let score = signals
+        .iter()
+        .map(|(_, weight)| weight)
+        .sum::<i16>()
+        .min(100);
+    let reasons: Vec<String> = signals.iter().map(|(name, _)| name.to_string()).collect();
+    let raw_ip = ip
+        .filter(|_| config.abuse_ip_retention_hours > 0)
+        .map(|ip| ip.to_string());
+    let event_id = queries::insert_abuse_event(
+        state.db.write(),
+        kind,
+        hash.as_deref(),
+        raw_ip.as_deref(),
+        score,
+        &reasons,
+    )
+    .await?;
+
+    if config.abuse_block_score > 0 && score >= config.abuse_block_score as i16 {
+        tracing::info!(
+            "Refused a {} request with abuse score {} ({})",
+            kind,
+            score,
+            reasons.join(", ")
+        );
+        return Err(AppError::Forbidden(
+            "Requests from this network are temporarily refused".into(),
+        )
+        .with_code("ABUSE_SCORE_TOO_HIGH"));
+    }
+    Ok(Assessment { event_id, score })
+}
+
+/// Erase raw IPs past `ABUSE_IP_RETENTION_HOURS` and drop old events.
+pub async fn purge_events(state: &AppState) -> AppResult<u64> {
+    let hours = state.live_config.get().abuse_ip_retention_hours;
+    queries::purge_abuse_events(state.db.primary(), hours, EVENT_RETENTION_DAYS).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn lists_parse_addresses_and_blocks_with_comments() {
+        let ranges = parse_list(
+            "# Tor exits\n198.51.100.0/24 ; SBL1\n203.0.113.7\n2001:db8::/32 extra\nnot-an-ip\n10.0.0.0/33\n",
+        );
+        assert_eq!(ranges.len(), 3);
+        let lists = IpLists::default();
+        *lists.0.write().unwrap() = ranges;
+
+        assert!(lists.contains(ip("198.51.100.250")));
+        assert!(lists.contains(ip("::ffff:198.51.100.1")));
+        assert!(!lists.contains(ip("198.51.101.1")));
+        assert!(lists.contains(ip("203.0.113.7")));
+        assert!(!lists.contains(ip("203.0.113.8")));
+        assert!(lists.contains(ip("2001:db8:1::1")));
+        assert!(!lists.contains(ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn networks_group_by_24_and_48_and_skip_local_addresses() {
+        assert_eq!(
+            network(ip("203.0.113.77")).as_deref(),
+            Some("203.0.113.0/24")
+        );
+        assert_eq!(
+            network(ip("::ffff:203.0.113.77")).as_deref(),
+            Some("203.0.113.0/24")
+        );
+        assert_eq!(
+            network(ip("2001:db8:aa:bb::1")).as_deref(),
+            Some("2001:db8:aa::/48")
+        );
+        for local in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.0.9",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert_eq!(network(ip(local)), None, "{}", local);
+        }
+    }
+
+    #[test]
+    fn forwarded_addresses_are_only_read_behind_a_trusted_proxy() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9, 10.0.0.1".parse().unwrap());
+        let peer = Some(SocketAddr::from(([10, 0, 0, 1], 443)));
+        assert_eq!(client_ip(&headers, peer, true), Some(ip("203.0.113.9")));
+        assert_eq!(client_ip(&headers, peer, false), Some(ip("10.0.0.1")));
+        assert_eq!(client_ip(&HeaderMap::new(), None, true), None);
+
+        let signals = |trust_proxy| {
+            header_signals(&headers, trust_proxy)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            signals(false),
+            ["proxy_headers", "no_user_agent", "no_accept_language"]
+        );
+        assert_eq!(signals(true), ["no_user_agent", "no_accept_language"]);
+    }
+}
@@ -4,21 +4,68 @@ use axum::{
 };
 use uuid::Uuid;
 
-use crate::db::queries;
+use crate::attachment_gc;
+use crate::config::{AppConfig, RELOADABLE_FIELDS};
+use crate::db::{pool_monitor, queries, slow_queries};
 use crate::errors::{AppError, AppResult};
-use crate::middleware::AdminUser;
+use crate::jobs::{self, NewJob};
+use crate::maintenance;
+use crate::middleware::StaffUser;
 use crate::models::{
-    AdminSearchQuery, AdminStats, AdminUserResponse, CreateBlockedHashRequest,
-    CreateInstanceBanRequest, PaginationQuery, ReportCounts, ReportFilterQuery, SetAdminRequest,
-    UpdateReportRequest, WsServerMessage,
+    AbuseEvent, AbuseEventQuery, AdminJobsQuery, AdminSearchQuery, AdminServerResponse, AdminStats, AdminUserResponse, AttachmentGcReport,
+    AttachmentGcRunResponse, BackgroundJob, BetaInviteStats, ConfigReloadResponse, CreateBlockedHashRequest,
+    CreateInstanceBanRequest, DbBreakerStatus, DbPoolReport, DbReplicaReport, DisconnectUserRequest, DisconnectUserResponse,
+    EmailDeadLetter, EmailOutboxEntry,
+    LegalHold, LegalHoldQuery, LegalHoldSubject, PlaceLegalHoldRequest, ReleaseLegalHoldRequest,
+    InstanceAuditLogQuery, InstanceAuditLogResponse, InstanceUsageStats, LatencyBudgetReport, LatencyBudgetViolation,
+    MaintenanceJobResponse, PaginationQuery, PartitionStatusResponse, PendingRegistration, RateLimitUsage, ReportCounts,
+    ReportFilterQuery, ScheduleJobRequest, ServerQuotaResponse, ServerQuotaUsage, SetAdminRequest,
+    SetServerQuotasRequest, SetStaffRoleRequest, SetUploadTierRequest,
+    ShadowReadReport, ShadowReadStats, StaffMemberResponse,
+    SupportAccessQuery, SupportAccountInfo, SupportDevice, SupportRateLimits, SupportUserView,
+    UpdateReportRequest, WsHeartbeatReport, WsServerMessage,
 };
+use crate::permissions::{self, InstanceRole};
+use crate::quota;
+use crate::uploads::UploadTier;
+use crate::usage_stats;
 use crate::AppState;
 
+/// Record a staff action in the instance audit log.
+pub async fn record_staff_action(
+    state: &AppState,
+    staff: &StaffUser,
+    action: &str,
+    target_type: Option<&str>,
+    target_id: Option<Uuid>,
+    details: Option<&serde_json::Value>,
+    reason: Option<&str>,
+) {
+    let _ = queries::insert_instance_audit_log(
+        state.db.write(),
+        staff.user_id,
+        staff.role.as_str(),
+        action,
+        target_type,
+        target_id,
+        details,
+        reason,
+    )
+    .await;
+}
+
 /// GET /api/v1/admin/stats
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/stats",
+    tag = "admin",
+    responses((status = 200, body = AdminStats))
+)]
 pub async fn get_stats(
-    AdminUser(_user_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
 ) -> AppResult<Json<AdminStats>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
     let (users, servers, channels, messages) = tokio::try_join!(
         queries::count_all_users(state.db.read()),
         queries::count_all_servers(state.db.read()),
@@ -37,12 +84,234 @@ pub async fn get_stats(
     }))
 }
 
+/// GET /api/v1/admin/usage
+/// Users, daily and monthly active users, message volumes and storage used:
+/// the report the telemetry job sends when `TELEMETRY_ENDPOINT` is set.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/usage",
+    tag = "admin",
+    responses((status = 200, body = InstanceUsageStats))
+)]
+pub async fn get_usage(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<InstanceUsageStats>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    Ok(Json(usage_stats::collect(&state).await?))
+}
+
+/// GET /api/v1/admin/latency-budgets
+/// Configured handler budgets and how often each route has exceeded its budget
+/// since startup.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/latency-budgets",
+    tag = "admin",
+    responses((status = 200, body = LatencyBudgetReport))
+)]
+pub async fn get_latency_budgets(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<LatencyBudgetReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let budgets = &state.latency_budgets;
+    Ok(Json(LatencyBudgetReport {
+        interactive_timeout_ms: budgets.interactive().as_millis() as u64,
+        job_timeout_ms: budgets.job().as_millis() as u64,
+        violations: budgets
+            .violations()
+            .into_iter()
+            .map(|(route, count)| LatencyBudgetViolation { route, count })
+            .collect(),
+    }))
+}
+
+/// GET /api/v1/admin/shadow-reads
+/// Shadow-read sample rate and per-query run/mismatch counts since startup.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/shadow-reads",
+    tag = "admin",
+    responses((status = 200, body = ShadowReadReport))
+)]
+pub async fn get_shadow_reads(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<ShadowReadReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let shadow = &state.shadow_reads;
+    Ok(Json(ShadowReadReport {
+        sample_rate: shadow.sample_rate(),
+        queries: shadow
+            .stats()
+            .into_iter()
+            .map(|(query, s)| ShadowReadStats {
+                query: query.to_string(),
+                runs: s.runs,
+                mismatches: s.mismatches,
+                errors: s.errors,
+            })
+            .collect(),
+    }))
+}
+
+/// GET /api/v1/admin/ws-heartbeats
+/// Heartbeat settings, open connections and heartbeat/missed/reaped counts
+/// since startup.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/ws-heartbeats",
+    tag = "admin",
+    responses((status = 200, body = WsHeartbeatReport))
+)]
+pub async fn get_ws_heartbeats(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<WsHeartbeatReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let stats = &state.ws_heartbeats;
+    Ok(Json(WsHeartbeatReport {
+        heartbeat_interval_ms: state.config.ws_heartbeat_interval_secs * 1000,
+        heartbeat_timeout_ms: state.config.ws_heartbeat_timeout_secs * 1000,
+        connections: state.connections.iter().map(|c| c.len() as u64).sum(),
+        heartbeats: stats.heartbeats(),
+        missed: stats.missed(),
+        reaped: stats.reaped(),
+    }))
+}
+
+/// GET /api/v1/admin/db-replicas
+/// Read replica health and replication lag from the most recent probe.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/db-replicas",
+    tag = "admin",
+    responses((status = 200, body = DbReplicaReport))
+)]
+pub async fn get_db_replicas(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<DbReplicaReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    Ok(Json(DbReplicaReport {
+        strategy: state.config.db_replica_strategy.clone(),
+        max_lag_secs: state.config.db_replica_max_lag_secs,
+        replicas: state.db.replica_status(),
+    }))
+}
+
+/// GET /api/v1/admin/db-pool
+/// Connection pool gauges, acquire latency, the load-shedding breaker and the
+/// slowest statement fingerprints since startup.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/db-pool",
+    tag = "admin",
+    responses((status = 200, body = DbPoolReport))
+)]
+pub async fn get_db_pool(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<DbPoolReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let monitor = &state.db_pool;
+    let primary = pool_monitor::gauges(state.db.primary());
+    Ok(Json(DbPoolReport {
+        primary,
+        replicas: state.db.replicas().map(pool_monitor::gauges).collect(),
+        acquire: monitor.acquire_latency(),
+        in_flight: monitor.in_flight(),
+        waiting: monitor.waiting(&primary),
+        breaker: DbBreakerStatus {
+            open: monitor.is_open(),
+            saturation_ms: monitor.saturation().as_millis() as u64,
+            trips: monitor.trips(),
+            shed: monitor.shed(),
+        },
+        slow_query_ms: state.config.db_slow_query_ms,
+        slow_queries: slow_queries::top(20),
+    }))
+}
+
+/// GET /api/v1/admin/attachment-gc
+/// Orphaned attachment counts. In dry-run mode this is what GC would delete.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/attachment-gc",
+    tag = "admin",
+    responses((status = 200, body = AttachmentGcReport))
+)]
+pub async fn get_attachment_gc(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<AttachmentGcReport>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let grace_hours = state.config.attachment_gc_grace_hours;
+    let (pending, collectable) =
+        queries::count_orphaned_attachments(state.db.primary(), grace_hours).await?;
+    let (blobs_pending, blobs_collectable) =
+        queries::count_unreferenced_attachment_blobs(state.db.read(), grace_hours).await?;
+    Ok(Json(AttachmentGcReport {
+        dry_run: state.config.attachment_gc_dry_run,
+        grace_hours,
+        pending,
+        collectable,
+        blobs_pending,
+        blobs_collectable,
+    }))
+}
+
+/// POST /api/v1/admin/attachment-gc
+/// Run an attachment GC pass now instead of waiting for the hourly worker. Operator only.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/attachment-gc",
+    tag = "admin",
+    responses((status = 200, body = AttachmentGcRunResponse))
+)]
+pub async fn run_attachment_gc(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<AttachmentGcRunResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_SERVERS)?;
+    let pass = attachment_gc::run(&state).await?;
+
+    record_staff_action(
+        &state, &staff, "attachment_gc_run", None, None,
+        Some(&serde_json::json!({
+            "marked": pass.marked,
+            "deleted": pass.deleted,
+            "pending_released": pass.pending_released,
+            "blobs_deleted": pass.blobs_deleted,
+        })),
+        None,
+    ).await;
+
+    Ok(Json(AttachmentGcRunResponse {
+        dry_run: state.config.attachment_gc_dry_run,
+        marked: pass.marked,
+        collectable: pass.collectable,
+        deleted: pass.deleted,
+        pending_released: pass.pending_released,
+        blobs_deleted: pass.blobs_deleted,
+    }))
+}
+
 /// GET /api/v1/admin/users
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users",
+    tag = "admin",
+    params(AdminSearchQuery),
+    responses((status = 200, body = Vec<AdminUserResponse>))
+)]
 pub async fn list_users(
-    AdminUser(_user_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Query(params): Query<AdminSearchQuery>,
 ) -> AppResult<Json<Vec<AdminUserResponse>>> {
+    staff.require(permissions::INSTANCE_VIEW_USERS)?;
     let limit = params.limit.unwrap_or(50).clamp(1, 100);
     let offset = params.offset.unwrap_or(0).max(0);
 
@@ -58,12 +327,22 @@ pub async fn list_users(
 }
 
 /// PUT /api/v1/admin/users/:user_id/admin
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{user_id}/admin",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = SetAdminRequest,
+    responses((status = 200))
+)]
 pub async fn set_admin(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(user_id): Path<Uuid>,
     Json(req): Json<SetAdminRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_STAFF)?;
+    let admin_id = staff.user_id;
     // Prevent self-demotion
     if user_id == admin_id && !req.is_admin {
         return Err(crate::errors::AppError::BadRequest(
@@ -78,6 +357,12 @@ pub async fn set_admin(
 
     queries::set_instance_admin(state.db.write(), user_id, req.is_admin).await?;
 
+    record_staff_action(
+        &state, &staff, "staff_set_admin",
+        Some("user"), Some(user_id),
+        Some(&serde_json::json!({ "is_admin": req.is_admin })), None,
+    ).await;
+
     Ok(Json(serde_json::json!({
         "user_id": user_id,
         "is_instance_admin": req.is_admin,
@@ -85,11 +370,20 @@ pub async fn set_admin(
 }
 
 /// DELETE /api/v1/admin/users/:user_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/users/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn delete_user(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(user_id): Path<Uuid>,
 ) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_DELETE_USERS)?;
+    let admin_id = staff.user_id;
     // Prevent self-deletion via admin panel
     if user_id == admin_id {
         return Err(crate::errors::AppError::BadRequest(
@@ -102,6 +396,10 @@ pub async fn delete_user(
         .await?
         .ok_or(crate::errors::AppError::NotFound("User not found".into()))?;
 
+    if queries::is_user_erasure_held(state.db.read(), user_id).await? {
+        return Err(legal_hold_error());
+    }
+
     // 1. Delete servers owned by this user (CASCADE handles members, channels, etc.)
     let owned_servers = queries::get_servers_owned_by(state.db.read(), user_id).await?;
     for server in &owned_servers {
@@ -119,11 +417,6 @@ pub async fn delete_user(
     }
 
     // 2. Clean up message children (no FK cascade on partitioned tables in PG < 17)
-    sqlx::query("DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE sender_id = $1)")
-        .bind(user_id)
-        .execute(state.db.write())
-        .await
-        .ok();
     sqlx::query("DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE sender_id = $1)")
         .bind(user_id)
         .execute(state.db.write())
@@ -222,19 +515,11 @@ pub async fn delete_user(
 
     // 8. Delete user (FK CASCADE handles server_members, channel_members,
     //    friendships, blocks, prekeys, key_backups, sender_key_distributions, etc.)
+    let profile_media = queries::list_profile_media(state.db.read(), user_id).await?;
     queries::delete_user_account(state.db.write(), user_id).await?;
 
     // 9. Clean up stored files (avatar, banner)
-    let avatar_key = crate::storage::obfuscated_key(
-        &state.storage_key,
-        &format!("avatar:{}", user_id),
-    );
-    let banner_key = crate::storage::obfuscated_key(
-        &state.storage_key,
-        &format!("banner:{}", user_id),
-    );
-    let _ = state.storage.delete_blob(&avatar_key).await;
-    let _ = state.storage.delete_blob(&banner_key).await;
+    crate::api::users::delete_profile_media_blobs(&state, user_id, &profile_media).await;
 
     // 10. Invalidate caches
     crate::cache::invalidate(
@@ -244,20 +529,528 @@ pub async fn delete_user(
     )
     .await;
 
+    record_staff_action(
+        &state, &staff, "user_delete",
+        Some("user"), Some(user_id),
+        Some(&serde_json::json!({ "owned_servers_deleted": owned_servers.len() })), None,
+    ).await;
+
     Ok(Json(serde_json::json!({
         "deleted": true,
         "user_id": user_id,
     })))
 }
 
+// ─── Operator Tools ──────────────────────────────────
+
+/// POST /api/v1/admin/users/:user_id/disconnect
+/// Force-close a user's WebSocket sessions, optionally revoking their refresh
+/// tokens so clients must log in again.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/disconnect",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = DisconnectUserRequest,
+    responses((status = 200, body = DisconnectUserResponse))
+)]
+pub async fn disconnect_user(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<DisconnectUserRequest>,
+) -> AppResult<Json<DisconnectUserResponse>> {
+    staff.require(permissions::INSTANCE_BAN_USERS)?;
+    queries::find_user_basic_by_id(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("User not found".into()))?;
+
+    if req.revoke_sessions {
+        queries::revoke_all_user_refresh_tokens(state.db.write(), user_id).await?;
+        revoke_redis_refresh_tokens(&state, user_id).await;
+    }
+    let connections_closed =
+        close_ws_connections(&state, user_id, "Your session was closed by an administrator");
+    if connections_closed > 0 {
+        crate::ws::broadcast_presence(user_id, "offline", &state).await;
+        crate::api::voice::cleanup_voice_state(&state, user_id).await;
+    }
+
+    record_staff_action(
+        &state, &staff, "user_disconnect",
+        Some("user"), Some(user_id),
+        Some(&serde_json::json!({
+            "connections_closed": connections_closed,
+            "revoke_sessions": req.revoke_sessions,
+        })),
+        req.reason.as_deref(),
+    ).await;
+
+    Ok(Json(DisconnectUserResponse {
+        user_id,
+        connections_closed,
+        sessions_revoked: req.revoke_sessions,
+    }))
+}
+
+/// GET /api/v1/admin/servers
+/// Servers with member, channel, message and attachment counts, largest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/servers",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<AdminServerResponse>))
+)]
+pub async fn list_servers(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(pagination): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<AdminServerResponse>>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let (limit, offset) = pagination.resolve();
+    Ok(Json(queries::list_servers_admin(state.db.read(), limit, offset).await?))
+}
+
+/// GET /api/v1/admin/beta-stats
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/beta-stats",
+    tag = "admin",
+    responses((status = 200, body = BetaInviteStats))
+)]
+pub async fn get_beta_stats(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<BetaInviteStats>> {
+    staff.require(permissions::INSTANCE_VIEW_INVITES)?;
+    Ok(Json(beta_stats(&state).await?))
+}
+
+pub(crate) async fn beta_stats(state: &AppState) -> AppResult<BetaInviteStats> {
+    let (issued, redeemed, expired, revoked, issued_last_7_days, redeemed_last_7_days) =
+        queries::get_beta_code_counts(state.db.read()).await?;
+    let (limit, limit_overridden) = crate::api::beta::code_limit(state).await?;
+    let waitlisted = queries::count_beta_waitlist(state.db.read()).await?;
+    Ok(BetaInviteStats {
+        limit,
+        limit_overridden,
+        issued,
+        redeemed,
+        expired,
+        revoked,
+        waitlisted,
+        outstanding: issued - redeemed - expired - revoked,
+        issued_last_7_days,
+        redeemed_last_7_days,
+    })
+}
+
+/// POST /api/v1/admin/maintenance/:job
+/// Run a background maintenance job now instead of waiting for its schedule.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/maintenance/{job}",
+    tag = "admin",
+    params(("job" = String, Path, description = "Job name")),
+    responses((status = 200, body = MaintenanceJobResponse))
+)]
+pub async fn run_maintenance_job(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(job): Path<String>,
+) -> AppResult<Json<MaintenanceJobResponse>> {
+    staff.require(permissions::INSTANCE_RUN_MAINTENANCE)?;
+    let job = maintenance::Job::parse(&job).ok_or_else(|| {
+        let known: Vec<&str> = maintenance::Job::ALL.iter().map(|j| j.as_str()).collect();
+        AppError::NotFound(format!("Unknown maintenance job; expected one of: {}", known.join(", ")))
+    })?;
+
+    let affected = maintenance::run(&state, job).await?;
+
+    record_staff_action(
+        &state, &staff, "maintenance_run",
+        None, None,
+        Some(&serde_json::json!({ "job": job.as_str(), "affected": affected })), None,
+    ).await;
+
+    Ok(Json(MaintenanceJobResponse { job: job.as_str(), affected }))
+}
+
+const JOB_STATUSES: [&str; 5] = ["queued", "running", "completed", "failed", "cancelled"];
+
+/// GET /api/v1/admin/jobs
+/// Background jobs, newest first, optionally filtered by status and kind.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs",
+    tag = "admin",
+    params(AdminJobsQuery),
+    responses((status = 200, body = Vec<BackgroundJob>))
+)]
+pub async fn list_jobs(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(query): Query<AdminJobsQuery>,
+) -> AppResult<Json<Vec<BackgroundJob>>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    if let Some(status) = query.status.as_deref() {
+        if !JOB_STATUSES.contains(&status) {
+            return Err(AppError::Validation(format!(
+                "status must be one of: {}",
+                JOB_STATUSES.join(", ")
+            )));
+        }
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let listed = queries::list_jobs(
+        state.db.read(),
+        query.status.as_deref(),
+        query.kind.as_deref(),
+        limit,
+    )
+    .await?;
+    Ok(Json(listed))
+}
+
+/// GET /api/v1/admin/jobs/:job_id
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/{job_id}",
+    tag = "admin",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = BackgroundJob))
+)]
+pub async fn get_job(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+) -> AppResult<Json<BackgroundJob>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    let job = queries::get_job(state.db.read(), job_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Job not found".into()))?;
+    Ok(Json(job))
+}
+
+/// POST /api/v1/admin/jobs
+/// Schedule a maintenance job to run in the background, now or at `run_at`.
+/// If that job is already waiting to run, it is returned (moved up to
+/// `run_at` if sooner) rather than queued twice.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs",
+    tag = "admin",
+    request_body = ScheduleJobRequest,
+    responses((status = 200, body = BackgroundJob))
+)]
+pub async fn schedule_job(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<ScheduleJobRequest>,
+) -> AppResult<Json<BackgroundJob>> {
+    staff.require(permissions::INSTANCE_RUN_MAINTENANCE)?;
+    let task = maintenance::Job::parse(&req.job).ok_or_else(|| {
+        let known: Vec<&str> = maintenance::Job::ALL.iter().map(|j| j.as_str()).collect();
+        AppError::Validation(format!("Unknown maintenance job; expected one of: {}", known.join(", ")))
+    })?;
+    let mut new_job = NewJob::maintenance(task).created_by(staff.user_id);
+    if let Some(run_at) = req.run_at {
+        new_job = new_job.at(run_at);
+    }
+    let job = jobs::enqueue(&state, new_job).await?;
+
+    record_staff_action(
+        &state, &staff, "job_schedule",
+        Some("job"), Some(job.id),
+        Some(&serde_json::json!({ "job": task.as_str(), "run_at": job.run_at })), None,
+    ).await;
+
+    Ok(Json(job))
+}
+
+/// POST /api/v1/admin/jobs/:job_id/cancel
+/// Cancel a queued or running job. A running attempt finishes, but its
+/// outcome is discarded and it isn't retried.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/{job_id}/cancel",
+    tag = "admin",
+    params(("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = BackgroundJob), (status = 409, description = "Job already finished"))
+)]
+pub async fn cancel_job(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+) -> AppResult<Json<BackgroundJob>> {
+    staff.require(permissions::INSTANCE_RUN_MAINTENANCE)?;
+    let Some(job) = queries::cancel_job(state.db.write(), job_id).await? else {
+        return match queries::get_job(state.db.primary(), job_id).await? {
+            Some(job) => Err(AppError::Conflict(format!("Job already {}", job.status))),
+            None => Err(AppError::NotFound("Job not found".into())),
+        };
+    };
+
+    record_staff_action(
+        &state, &staff, "job_cancel",
+        Some("job"), Some(job.id),
+        Some(&serde_json::json!({ "kind": job.kind, "payload": job.payload })), None,
+    ).await;
+
+    Ok(Json(job))
+}
+
+/// POST /api/v1/admin/config/reload
+/// Re-read the config, as SIGHUP does, and apply the settings that can change
+/// without a restart.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/config/reload",
+    tag = "admin",
+    responses((status = 200, body = ConfigReloadResponse))
+)]
+pub async fn reload_config(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<ConfigReloadResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_CONFIG)?;
+    let fresh = AppConfig::reload_from_source().map_err(AppError::BadRequest)?;
+    let changed = state.reload_config(&fresh);
+
+    record_staff_action(
+        &state, &staff, "config_reload",
+        None, None,
+        Some(&serde_json::json!({ "changed": changed })), None,
+    ).await;
+
+    Ok(Json(ConfigReloadResponse {
+        changed,
+        reloadable: RELOADABLE_FIELDS.to_vec(),
+    }))
+}
+
+/// GET /api/v1/admin/partitions
+/// Message table partitions: size, row estimate, when each is dropped, and
+/// any month in the create-ahead window still missing a partition.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/partitions",
+    tag = "admin",
+    responses((status = 200, body = PartitionStatusResponse))
+)]
+pub async fn get_partitions(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<PartitionStatusResponse>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    Ok(Json(maintenance::partition_status(&state).await?))
+}
+
+// ─── Pending Registrations ───────────────────────────
+
+/// GET /api/v1/admin/registrations/pending
+/// Accounts waiting for approval (`REGISTRATION_MODE=approval`), oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/registrations/pending",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<PendingRegistration>))
+)]
+pub async fn list_pending_registrations(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<PendingRegistration>>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    let (limit, offset) = params.resolve();
+    Ok(Json(queries::list_pending_registrations(state.db.read(), limit, offset).await?))
+}
+
+/// POST /api/v1/admin/registrations/:user_id/approve
+/// Let a pending account log in, and set up its personal server.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/registrations/{user_id}/approve",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
+pub async fn approve_registration(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    if !queries::approve_pending_registration(state.db.write(), user_id).await? {
+        return Err(AppError::NotFound("No pending registration for this user".into()));
+    }
+    if let Some(user) = queries::find_user_by_id(state.db.primary(), user_id).await? {
+        crate::api::auth_routes::provision_new_user(&state, &user).await;
+    }
+    record_staff_action(
+        &state, &staff, "registration_approve",
+        Some("user"), Some(user_id), None, None,
+    ).await;
+    Ok(Json(serde_json::json!({ "approved": true, "user_id": user_id })))
+}
+
+/// POST /api/v1/admin/registrations/:user_id/reject
+/// Delete a pending account. Its username becomes free again.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/registrations/{user_id}/reject",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
+pub async fn reject_registration(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    if !queries::reject_pending_registration(state.db.write(), user_id).await? {
+        return Err(AppError::NotFound("No pending registration for this user".into()));
+    }
+    record_staff_action(
+        &state, &staff, "registration_reject",
+        Some("user"), Some(user_id), None, None,
+    ).await;
+    Ok(Json(serde_json::json!({ "rejected": true, "user_id": user_id })))
+}
+
+// ─── Abuse Scoring ───────────────────────────────────
+
+/// GET /api/v1/admin/abuse/events
+/// Scored registrations and beta code requests, newest first. Raw IPs are
+/// only present within `ABUSE_IP_RETENTION_HOURS`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/abuse/events",
+    tag = "admin",
+    params(AbuseEventQuery),
+    responses((status = 200, body = Vec<AbuseEvent>))
+)]
+pub async fn list_abuse_events(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<AbuseEventQuery>,
+) -> AppResult<Json<Vec<AbuseEvent>>> {
+    staff.require(permissions::INSTANCE_VIEW_USERS)?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = params.offset.unwrap_or(0).max(0);
+    Ok(Json(queries::list_abuse_events(state.db.read(), params.user_id, limit, offset).await?))
+}
+
+// ─── Email Queue ─────────────────────────────────────
+
+/// GET /api/v1/admin/email/queue
+/// Emails waiting to be sent, next due first, with their failed attempts.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email/queue",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<EmailOutboxEntry>))
+)]
+pub async fn list_email_queue(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<EmailOutboxEntry>>> {
+    staff.require(permissions::INSTANCE_MANAGE_CONFIG)?;
+    let (limit, offset) = params.resolve();
+    Ok(Json(queries::list_email_outbox(state.db.read(), limit, offset).await?))
+}
+
+/// GET /api/v1/admin/email/dead-letters
+/// Emails that failed every delivery attempt, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email/dead-letters",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<EmailDeadLetter>))
+)]
+pub async fn list_email_dead_letters(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<EmailDeadLetter>>> {
+    staff.require(permissions::INSTANCE_MANAGE_CONFIG)?;
+    let (limit, offset) = params.resolve();
+    Ok(Json(queries::list_email_dead_letters(state.db.read(), limit, offset).await?))
+}
+
+/// POST /api/v1/admin/email/dead-letters/:letter_id/retry
+/// Send a dead-lettered email again through the current provider, e.g.
+/// after fixing its settings. Removed once it goes through.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/dead-letters/{letter_id}/retry",
+    tag = "admin",
+    params(("letter_id" = Uuid, Path, description = "Letter ID")),
+    responses((status = 200), (status = 400, body = crate::errors::ErrorResponse))
+)]
+pub async fn retry_email_dead_letter(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(letter_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_CONFIG)?;
+    let result = crate::email::mailer::retry_dead_letter(&state, letter_id).await;
+    record_staff_action(
+        &state, &staff, "email_dead_letter_retry",
+        Some("email_dead_letter"), Some(letter_id),
+        Some(&serde_json::json!({ "sent": result.is_ok() })), None,
+    ).await;
+    result?;
+    Ok(Json(serde_json::json!({ "sent": true })))
+}
+
+/// DELETE /api/v1/admin/email/dead-letters/:letter_id
+/// Discard a dead-lettered email, and the address it holds.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/email/dead-letters/{letter_id}",
+    tag = "admin",
+    params(("letter_id" = Uuid, Path, description = "Letter ID")),
+    responses((status = 200))
+)]
+pub async fn delete_email_dead_letter(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(letter_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_CONFIG)?;
+    if !queries::delete_email_dead_letter(state.db.write(), letter_id).await? {
+        return Err(AppError::NotFound("Dead letter not found".into()));
+    }
+    record_staff_action(
+        &state, &staff, "email_dead_letter_delete",
+        Some("email_dead_letter"), Some(letter_id), None, None,
+    ).await;
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
 // ─── Report Triage ───────────────────────────────────
 
 /// GET /api/v1/admin/reports
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/reports",
+    tag = "admin",
+    params(ReportFilterQuery),
+    responses((status = 200, body = Vec<crate::models::AdminReportResponse>))
+)]
 pub async fn list_reports(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Query(params): Query<ReportFilterQuery>,
 ) -> AppResult<Json<Vec<crate::models::AdminReportResponse>>> {
+    staff.require(permissions::INSTANCE_MANAGE_REPORTS)?;
     let limit = params.limit.unwrap_or(50).clamp(1, 100);
     let offset = params.offset.unwrap_or(0).max(0);
     let reports =
@@ -267,20 +1060,35 @@ pub async fn list_reports(
 }
 
 /// GET /api/v1/admin/reports/counts
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/reports/counts",
+    tag = "admin",
+    responses((status = 200, body = ReportCounts))
+)]
 pub async fn report_counts(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
 ) -> AppResult<Json<ReportCounts>> {
+    staff.require(permissions::INSTANCE_MANAGE_REPORTS)?;
     let counts = queries::count_reports_by_status(state.db.read()).await?;
     Ok(Json(counts))
 }
 
 /// GET /api/v1/admin/reports/:report_id
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/reports/{report_id}",
+    tag = "admin",
+    params(("report_id" = Uuid, Path, description = "Report ID")),
+    responses((status = 200, body = crate::models::AdminReportResponse))
+)]
 pub async fn get_report(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(report_id): Path<Uuid>,
 ) -> AppResult<Json<crate::models::AdminReportResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_REPORTS)?;
     let report = queries::get_report_admin(state.db.read(), report_id)
         .await?
         .ok_or(AppError::NotFound("Report not found".into()))?;
@@ -288,12 +1096,22 @@ pub async fn get_report(
 }
 
 /// PUT /api/v1/admin/reports/:report_id
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/reports/{report_id}",
+    tag = "admin",
+    params(("report_id" = Uuid, Path, description = "Report ID")),
+    request_body = UpdateReportRequest,
+    responses((status = 200, body = crate::models::AdminReportResponse))
+)]
 pub async fn update_report(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(report_id): Path<Uuid>,
     Json(req): Json<UpdateReportRequest>,
 ) -> AppResult<Json<crate::models::AdminReportResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_REPORTS)?;
+    let admin_id = staff.user_id;
     // Validate status
     let valid_statuses = ["pending", "reviewed", "dismissed", "escalated_ncmec"];
     if !valid_statuses.contains(&req.status.as_str()) {
@@ -327,6 +1145,13 @@ pub async fn update_report(
         .await?;
     }
 
+    record_staff_action(
+        &state, &staff, "report_update",
+        Some("report"), Some(report_id),
+        Some(&serde_json::json!({ "from": existing.status, "to": req.status })),
+        req.admin_notes.as_deref(),
+    ).await;
+
     // Fetch updated report
     let report = queries::get_report_admin(state.db.read(), report_id)
         .await?
@@ -337,23 +1162,41 @@ pub async fn update_report(
 // ─── Instance Bans ───────────────────────────────────
 
 /// GET /api/v1/admin/bans
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/bans",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<crate::models::InstanceBanResponse>))
+)]
 pub async fn list_instance_bans(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Query(pagination): Query<PaginationQuery>,
 ) -> AppResult<Json<Vec<crate::models::InstanceBanResponse>>> {
+    staff.require(permissions::INSTANCE_BAN_USERS)?;
     let (limit, offset) = pagination.resolve();
     let bans = queries::list_instance_bans(state.db.read(), limit, offset).await?;
     Ok(Json(bans))
 }
 
 /// POST /api/v1/admin/bans/:user_id
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/bans/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = CreateInstanceBanRequest,
+    responses((status = 200, body = crate::models::InstanceBanResponse))
+)]
 pub async fn instance_ban_user(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(user_id): Path<Uuid>,
     Json(req): Json<CreateInstanceBanRequest>,
 ) -> AppResult<Json<crate::models::InstanceBanResponse>> {
+    staff.require(permissions::INSTANCE_BAN_USERS)?;
+    let admin_id = staff.user_id;
     // Prevent self-ban
     if user_id == admin_id {
         return Err(AppError::BadRequest("Cannot ban yourself".into()));
@@ -364,34 +1207,85 @@ pub async fn instance_ban_user(
         .await?
         .ok_or(AppError::NotFound("User not found".into()))?;
 
-    // Prevent banning other admins
+    // Prevent banning other admins, and staff at or above the caller's own rank
     if target.is_instance_admin {
         return Err(AppError::BadRequest(
             "Cannot ban an instance admin. Remove their admin status first.".into(),
         ));
     }
+    if let Some(target_role) = InstanceRole::resolve(false, target.instance_role.as_deref()) {
+        if target_role >= staff.role {
+            return Err(AppError::Forbidden(
+                "Cannot ban staff at or above your own instance role".into(),
+            ));
+        }
+    }
+
+    if req.expires_at.is_some_and(|t| t <= chrono::Utc::now()) {
+        return Err(AppError::Validation("expires_at must be in the future".into()));
+    }
 
     let ban = queries::create_instance_ban(
         state.db.write(),
         user_id,
         req.reason.as_deref(),
         admin_id,
+        req.expires_at,
     )
     .await?;
 
     // Force-disconnect user's WS connections
-    if let Some((_, senders)) = state.connections.remove(&user_id) {
-        for sender in senders {
-            let _ = sender.send(crate::models::WsServerMessage::Error {
-                message: "Your account has been banned from this platform".into(),
-            });
-        }
-    }
+    let notice = if ban.expires_at.is_some() {
+        "Your account has been suspended from this platform"
+    } else {
+        "Your account has been banned from this platform"
+    };
+    close_ws_connections(&state, user_id, notice);
 
     // Update ban cache immediately for instant consistency
     state.ban_cache.set(user_id, true);
 
-    // Invalidate refresh tokens in Redis (SCAN cursor loop, non-blocking)
+    record_staff_action(
+        &state, &staff, if ban.expires_at.is_some() { "instance_suspend" } else { "instance_ban" },
+        Some("user"), Some(user_id),
+        Some(&serde_json::json!({ "username": &target.username, "expires_at": ban.expires_at })),
+        req.reason.as_deref(),
+    ).await;
+
+    revoke_redis_refresh_tokens(&state, user_id).await;
+
+    let admin_user = queries::find_user_basic_by_id(state.db.read(), admin_id)
+        .await?
+        .ok_or(AppError::NotFound("Admin user not found".into()))?;
+
+    Ok(Json(crate::models::InstanceBanResponse {
+        id: ban.id,
+        user_id: ban.user_id,
+        username: target.username,
+        reason: ban.reason,
+        banned_by: ban.banned_by,
+        banned_by_username: admin_user.username,
+        created_at: ban.created_at.to_rfc3339(),
+        expires_at: ban.expires_at.map(|t| t.to_rfc3339()),
+    }))
+}
+
+/// Drop every live WS connection of a user on this node, telling each client why.
+/// Returns how many connections were closed.
+fn close_ws_connections(state: &AppState, user_id: Uuid, message: &str) -> usize {
+    match state.connections.remove(&user_id) {
+        Some((_, senders)) => {
+            for sender in &senders {
+                let _ = sender.send(WsServerMessage::Error { message: message.into() });
+            }
+            senders.len()
+        }
+        None => 0,
+    }
+}
+
+/// Invalidate a user's refresh tokens in Redis (SCAN cursor loop, non-blocking).
+async fn revoke_redis_refresh_tokens(state: &AppState, user_id: Uuid) {
     if let Some(ref redis) = state.redis {
         let pattern = format!("refresh_token:{}:*", user_id);
         let mut conn = redis.clone();
@@ -420,52 +1314,65 @@ pub async fn instance_ban_user(
             }
         }
     }
-
-    let admin_user = queries::find_user_basic_by_id(state.db.read(), admin_id)
-        .await?
-        .ok_or(AppError::NotFound("Admin user not found".into()))?;
-
-    Ok(Json(crate::models::InstanceBanResponse {
-        id: ban.id,
-        user_id: ban.user_id,
-        username: target.username,
-        reason: ban.reason,
-        banned_by: ban.banned_by,
-        banned_by_username: admin_user.username,
-        created_at: ban.created_at.to_rfc3339(),
-    }))
 }
 
 /// DELETE /api/v1/admin/bans/:user_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/bans/{user_id}",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn instance_revoke_ban(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(user_id): Path<Uuid>,
 ) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_BAN_USERS)?;
     queries::remove_instance_ban(state.db.write(), user_id).await?;
     state.ban_cache.invalidate(&user_id);
+
+    record_staff_action(&state, &staff, "instance_unban", Some("user"), Some(user_id), None, None).await;
     Ok(Json(serde_json::json!({ "unbanned": true })))
 }
 
 // ─── Blocked Hashes ─────────────────────────────────
 
 /// GET /api/v1/admin/blocked-hashes
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/blocked-hashes",
+    tag = "admin",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<crate::models::BlockedHashResponse>))
+)]
 pub async fn list_blocked_hashes(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Query(pagination): Query<PaginationQuery>,
 ) -> AppResult<Json<Vec<crate::models::BlockedHashResponse>>> {
+    staff.require(permissions::INSTANCE_MANAGE_BLOCKED_HASHES)?;
     let (limit, offset) = pagination.resolve();
     let hashes = queries::list_blocked_hashes(state.db.read(), limit, offset).await?;
     Ok(Json(hashes))
 }
 
 /// POST /api/v1/admin/blocked-hashes
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/blocked-hashes",
+    tag = "admin",
+    request_body = CreateBlockedHashRequest,
+    responses((status = 200, body = crate::models::BlockedHashResponse))
+)]
 pub async fn create_blocked_hash(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Json(req): Json<CreateBlockedHashRequest>,
 ) -> AppResult<Json<crate::models::BlockedHashResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_BLOCKED_HASHES)?;
+    let admin_id = staff.user_id;
     // Validate hash format: 64 hex characters (SHA-256)
     let hash = req.hash.to_lowercase();
     if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
@@ -482,6 +1389,12 @@ pub async fn create_blocked_hash(
     )
     .await?;
 
+    record_staff_action(
+        &state, &staff, "blocked_hash_create",
+        Some("blocked_hash"), Some(bh.id),
+        Some(&serde_json::json!({ "hash": &bh.hash })), None,
+    ).await;
+
     let admin_user = queries::find_user_basic_by_id(state.db.read(), admin_id)
         .await?
         .ok_or(AppError::NotFound("Admin user not found".into()))?;
@@ -496,11 +1409,435 @@ pub async fn create_blocked_hash(
 }
 
 /// DELETE /api/v1/admin/blocked-hashes/:hash_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/blocked-hashes/{hash_id}",
+    tag = "admin",
+    params(("hash_id" = Uuid, Path, description = "Hash ID")),
+    responses((status = 200))
+)]
 pub async fn delete_blocked_hash(
-    AdminUser(_admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(hash_id): Path<Uuid>,
 ) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_BLOCKED_HASHES)?;
     queries::delete_blocked_hash(state.db.write(), hash_id).await?;
+    record_staff_action(&state, &staff, "blocked_hash_delete", Some("blocked_hash"), Some(hash_id), None, None).await;
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
+
+// ─── Instance Staff ──────────────────────────────────
+
+/// GET /api/v1/admin/staff
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/staff",
+    tag = "admin",
+    responses((status = 200, body = Vec<StaffMemberResponse>))
+)]
+pub async fn list_staff(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<Vec<StaffMemberResponse>>> {
+    staff.require(permissions::INSTANCE_VIEW_USERS)?;
+    let members = queries::list_instance_staff(state.db.read()).await?;
+    Ok(Json(members))
+}
+
+/// PUT /api/v1/admin/users/:user_id/staff-role
+/// Assign or revoke an instance staff role. Operator only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{user_id}/staff-role",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    request_body = SetStaffRoleRequest,
+    responses((status = 200))
+)]
+pub async fn set_staff_role(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<SetStaffRoleRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_STAFF)?;
+
+    let role = match req.role.as_deref() {
+        Some(r) => Some(InstanceRole::parse(r).ok_or_else(|| {
+            AppError::Validation(
+                "Invalid role. Must be one of: operator, instance_moderator, support".into(),
+            )
+        })?),
+        None => None,
+    };
+
+    // Prevent self-demotion (an instance could otherwise be left without an operator)
+    if user_id == staff.user_id {
+        return Err(AppError::BadRequest("Cannot change your own staff role".into()));
+    }
+
+    let target = queries::find_user_basic_by_id(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("User not found".into()))?;
+    if target.is_system {
+        return Err(AppError::BadRequest("Cannot assign a staff role to the system user".into()));
+    }
+    let previous = InstanceRole::resolve(target.is_instance_admin, target.instance_role.as_deref());
+
+    queries::set_instance_role(state.db.write(), user_id, role.map(|r| r.as_str())).await?;
+
+    record_staff_action(
+        &state, &staff, "staff_role_update",
+        Some("user"), Some(user_id),
+        Some(&serde_json::json!({
+            "from": previous.map(|r| r.as_str()),
+            "to": role.map(|r| r.as_str()),
+        })),
+        req.reason.as_deref(),
+    ).await;
+
+    Ok(Json(serde_json::json!({
+        "user_id": user_id,
+        "instance_role": role.map(|r| r.as_str()),
+    })))
+}
+
+/// PUT /api/v1/admin/servers/:server_id/upload-tier
+/// Change a server's upload tier (per-file size and content-type limits). Operator only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/servers/{server_id}/upload-tier",
+    tag = "admin",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = SetUploadTierRequest,
+    responses((status = 200))
+)]
+pub async fn set_server_upload_tier(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<SetUploadTierRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_SERVERS)?;
+
+    let tier = UploadTier::parse(&req.upload_tier).ok_or_else(|| {
+        AppError::Validation("Invalid upload tier. Must be one of: standard, boosted".into())
+    })?;
+    let previous = queries::get_server_upload_tier(state.db.read(), server_id)
+        .await?
+        .ok_or(AppError::NotFound("Server not found".into()))?;
+
+    queries::set_server_upload_tier(state.db.write(), server_id, tier.as_str()).await?;
+
+    record_staff_action(
+        &state, &staff, "server_upload_tier_update",
+        Some("server"), Some(server_id),
+        Some(&serde_json::json!({ "from": previous, "to": tier.as_str() })),
+        req.reason.as_deref(),
+    ).await;
+
+    Ok(Json(serde_json::json!({
+        "server_id": server_id,
+        "upload_tier": tier.as_str(),
+    })))
+}
+
+async fn server_quota_response(state: &AppState, server_id: Uuid) -> AppResult<ServerQuotaResponse> {
+    let overrides = queries::get_server_quota_overrides(state.db.read(), server_id).await?;
+    Ok(ServerQuotaResponse {
+        server_id,
+        limits: quota::effective_limits(state, &overrides),
+        overrides,
+        usage: ServerQuotaUsage {
+            storage_bytes: queries::get_server_storage_usage(state.db.read(), server_id).await?,
+            members: queries::count_server_members(state.db.read(), server_id).await?,
+            messages_this_minute: quota::messages_this_minute(state, server_id).await,
+        },
+    })
+}
+
+/// GET /api/v1/admin/servers/:server_id/quotas
+/// Effective limits, operator overrides and current usage for a server.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/servers/{server_id}/quotas",
+    tag = "admin",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = ServerQuotaResponse))
+)]
+pub async fn get_server_quotas(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<ServerQuotaResponse>> {
+    staff.require(permissions::INSTANCE_VIEW_STATS)?;
+    queries::find_server_by_id(state.db.read(), server_id)
+        .await?
+        .ok_or(AppError::NotFound("Server not found".into()))?;
+    Ok(Json(server_quota_response(&state, server_id).await?))
+}
+
+/// PUT /api/v1/admin/servers/:server_id/quotas
+/// Replace a server's quota overrides. Null limits use the instance default,
+/// 0 lifts the limit. Operator only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/servers/{server_id}/quotas",
+    tag = "admin",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = SetServerQuotasRequest,
+    responses((status = 200, body = ServerQuotaResponse))
+)]
+pub async fn set_server_quotas(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<SetServerQuotasRequest>,
+) -> AppResult<Json<ServerQuotaResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_SERVERS)?;
+
+    let overrides = req.overrides;
+    if overrides.max_storage_bytes.is_some_and(|v| v < 0)
+        || overrides.max_messages_per_minute.is_some_and(|v| v < 0)
+        || overrides.max_members.is_some_and(|v| v < 0)
+    {
+        return Err(AppError::Validation("Quota limits cannot be negative".into()));
+    }
+    queries::find_server_by_id(state.db.read(), server_id)
+        .await?
+        .ok_or(AppError::NotFound("Server not found".into()))?;
+    let previous = queries::get_server_quota_overrides(state.db.read(), server_id).await?;
+
+    queries::set_server_quota_overrides(state.db.write(), server_id, &overrides, staff.user_id).await?;
+    quota::invalidate(&state, server_id).await;
+
+    record_staff_action(
+        &state, &staff, "server_quota_update",
+        Some("server"), Some(server_id),
+        Some(&serde_json::json!({ "from": previous, "to": overrides })),
+        req.reason.as_deref(),
+    ).await;
+
+    Ok(Json(server_quota_response(&state, server_id).await?))
+}
+
+/// GET /api/v1/admin/audit-log
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit-log",
+    tag = "admin",
+    params(InstanceAuditLogQuery),
+    responses((status = 200, body = Vec<InstanceAuditLogResponse>))
+)]
+pub async fn get_instance_audit_log(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<InstanceAuditLogQuery>,
+) -> AppResult<Json<Vec<InstanceAuditLogResponse>>> {
+    staff.require(permissions::INSTANCE_VIEW_AUDIT_LOG)?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+    let entries =
+        queries::list_instance_audit_log(state.db.read(), params.actor_id, limit, params.before)
+            .await?;
+    Ok(Json(entries))
+}
+
+// ─── Support Access ──────────────────────────────────
+
+/// GET /api/v1/admin/users/:user_id/support?reason=...
+/// Read-only account metadata for support staff. Never returns message
+/// content, profile content, or IP addresses. Every access is recorded in the
+/// instance audit log and shown to the user before any data is returned.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{user_id}/support",
+    tag = "admin",
+    params(("user_id" = Uuid, Path, description = "User ID"), SupportAccessQuery),
+    responses((status = 200, body = SupportUserView))
+)]
+pub async fn get_support_view(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(params): Query<SupportAccessQuery>,
+) -> AppResult<Json<SupportUserView>> {
+    staff.require(permissions::INSTANCE_SUPPORT_ACCESS)?;
+
+    let reason = params.reason.as_deref().map(str::trim).unwrap_or("");
+    if reason.len() < 10 {
+        return Err(AppError::Validation("Reason must be at least 10 characters".into()));
+    }
+    if reason.len() > 500 {
+        return Err(AppError::Validation("Reason must be at most 500 characters".into()));
+    }
+
+    let user = queries::find_user_by_id(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+    if user.is_system {
+        return Err(AppError::BadRequest("Cannot open support access on the system user".into()));
+    }
+
+    // Unlike other staff actions, the audit write must succeed: no log, no access.
+    queries::insert_instance_audit_log(
+        state.db.write(),
+        staff.user_id,
+        staff.role.as_str(),
+        "support_access",
+        Some("user"),
+        Some(user_id),
+        None,
+        Some(reason),
+    )
+    .await?;
+
+    let (memberships, sessions, unused_prekeys, instance_banned, instance_role) = tokio::try_join!(
+        queries::list_support_memberships(state.db.read(), user_id),
+        queries::list_user_sessions(state.db.read(), user_id),
+        queries::count_unused_prekeys(state.db.read(), user_id),
+        queries::is_instance_banned(state.db.read(), user_id),
+        queries::find_user_basic_by_id(state.db.read(), user_id),
+    )?;
+
+    let devices = sessions
+        .into_iter()
+        .map(|t| SupportDevice {
+            family_id: t.family_id,
+            device_name: t.device_name,
+            last_activity: t.last_activity,
+            created_at: t.created_at,
+        })
+        .collect();
+
+    let usage = |(used, limit, resets_in_secs)| RateLimitUsage { used, limit, resets_in_secs };
+
+    Ok(Json(SupportUserView {
+        account: SupportAccountInfo {
+            id: user.id,
+            username: user.username,
+            created_at: user.created_at,
+            instance_role: instance_role
+                .and_then(|u| InstanceRole::resolve(u.is_instance_admin, u.instance_role.as_deref()))
+                .map(|r| r.as_str().to_string()),
+            totp_enabled: user.totp_secret.is_some(),
+            instance_banned,
+        },
+        memberships,
+        devices,
+        unused_prekeys,
+        rate_limits: SupportRateLimits {
+            api: usage(state.api_rate_limiter.usage(user_id)),
+            websocket: usage(state.ws_rate_limiter.usage(user_id)),
+        },
+    }))
+}
+
+// ─── Legal Holds ─────────────────────────────────────
+
+/// The error for any deletion refused because of an active legal hold.
+pub(crate) fn legal_hold_error() -> AppError {
+    AppError::Forbidden("This content is under a legal hold and cannot be deleted".into())
+        .with_code("LEGAL_HOLD")
+}
+
+/// GET /api/v1/admin/legal-holds
+/// Legal holds, newest first. Active only unless `include_released`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/legal-holds",
+    tag = "admin",
+    params(LegalHoldQuery),
+    responses((status = 200, body = Vec<LegalHold>))
+)]
+pub async fn list_legal_holds(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<LegalHoldQuery>,
+) -> AppResult<Json<Vec<LegalHold>>> {
+    staff.require(permissions::INSTANCE_MANAGE_LEGAL_HOLDS)?;
+    let (limit, offset) = PaginationQuery { limit: params.limit, offset: params.offset }.resolve();
+    let holds =
+        queries::list_legal_holds(state.db.read(), params.include_released, limit, offset).await?;
+    Ok(Json(holds))
+}
+
+/// POST /api/v1/admin/legal-holds
+/// Place a hold on a server or user. Until it is released, retention sweeps
+/// and message expiry skip the subject's messages, and deleting its messages,
+/// channels, server or account is refused with `LEGAL_HOLD`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/legal-holds",
+    tag = "admin",
+    request_body = PlaceLegalHoldRequest,
+    responses((status = 200, body = LegalHold), (status = 409))
+)]
+pub async fn place_legal_hold(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<PlaceLegalHoldRequest>,
+) -> AppResult<Json<LegalHold>> {
+    staff.require(permissions::INSTANCE_MANAGE_LEGAL_HOLDS)?;
+    let pool = state.db.read();
+    let exists = match req.subject_type {
+        LegalHoldSubject::Server => {
+            queries::find_server_by_id(pool, req.subject_id).await?.is_some()
+                || queries::find_deleted_server(pool, req.subject_id).await?.is_some()
+        }
+        LegalHoldSubject::User => {
+            queries::find_user_basic_by_id(pool, req.subject_id).await?.is_some()
+        }
+    };
+    if !exists {
+        return Err(AppError::NotFound(format!("No {} with that id", req.subject_type.as_str())));
+    }
+
+    let reason = req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let hold = queries::place_legal_hold(
+        state.db.write(),
+        req.subject_type,
+        req.subject_id,
+        reason,
+        staff.user_id,
+    )
+    .await?
+    .ok_or_else(|| AppError::Conflict("This subject is already under a legal hold".into()))?;
+
+    record_staff_action(
+        &state, &staff, "legal_hold_place",
+        Some(req.subject_type.as_str()), Some(req.subject_id),
+        Some(&serde_json::json!({ "hold_id": hold.id })), reason,
+    ).await;
+    Ok(Json(hold))
+}
+
+/// POST /api/v1/admin/legal-holds/:hold_id/release
+/// Release an active hold. The hold stays listed with who released it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/legal-holds/{hold_id}/release",
+    tag = "admin",
+    params(("hold_id" = Uuid, Path, description = "Hold ID")),
+    request_body = ReleaseLegalHoldRequest,
+    responses((status = 200, body = LegalHold))
+)]
+pub async fn release_legal_hold(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(hold_id): Path<Uuid>,
+    Json(req): Json<ReleaseLegalHoldRequest>,
+) -> AppResult<Json<LegalHold>> {
+    staff.require(permissions::INSTANCE_MANAGE_LEGAL_HOLDS)?;
+    let reason = req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let hold = queries::release_legal_hold(state.db.write(), hold_id, staff.user_id, reason)
+        .await?
+        .ok_or(AppError::NotFound("No active legal hold with that id".into()))?;
+
+    record_staff_action(
+        &state, &staff, "legal_hold_release",
+        Some(hold.subject_type.as_str()), Some(hold.subject_id),
+        Some(&serde_json::json!({ "hold_id": hold.id })), reason,
+    ).await;
+    Ok(Json(hold))
+}
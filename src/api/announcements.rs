@@ -0,0 +1,272 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::api::admin::record_staff_action;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::jobs::{self, NewJob};
+use crate::middleware::{AuthUser, StaffUser};
+use crate::models::*;
+use crate::permissions;
+use crate::pubsub;
+use crate::ws_outbox::{self, Target};
+use crate::AppState;
+
+const MAX_TITLE_LEN: usize = 120;
+const MAX_BODY_LEN: usize = 4000;
+const KINDS: [&str; 3] = ["info", "maintenance", "release"];
+/// Users fetched per page while sending announcement DMs.
+const DM_BATCH_SIZE: i64 = 200;
+
+/// Broadcast every announcement whose publish time has passed and return
+/// them. Called by the background worker and right after an unscheduled
+/// announcement is created. The broadcasts go through the WS outbox, so an
+/// announcement marked published is never left unannounced.
+pub async fn publish_due(state: &AppState) -> AppResult<Vec<Announcement>> {
+    let mut tx = state.db.write().begin().await?;
+    let due = queries::claim_due_announcements(&mut tx).await?;
+    for announcement in &due {
+        let event = WsServerMessage::Announcement(announcement.clone().into());
+        ws_outbox::stage(&mut tx, Target::Instance, &event).await?;
+    }
+    tx.commit().await?;
+    if due.is_empty() {
+        return Ok(due);
+    }
+    state.ws_outbox.wake();
+
+    for announcement in &due {
+        if announcement.send_dm {
+            if let Err(e) = jobs::enqueue(state, NewJob::announcement_dms(announcement.id)).await {
+                tracing::error!("Failed to queue DMs for announcement {}: {}", announcement.id, e);
+            }
+        }
+    }
+    Ok(due)
+}
+
+/// Background job: DM the announcement from the Haven system user to every
+/// recipient, reusing the welcome DM channel created at registration (or
+/// creating it). Progress is checkpointed per batch, so a retry resends at
+/// most the batch that failed. Returns how many DMs this attempt sent.
+pub async fn send_dms(state: &AppState, job: &BackgroundJob) -> AppResult<u64> {
+    let announcement_id = job.payload["announcement_id"]
+        .as_str()
+        .and_then(|id| id.parse::<Uuid>().ok())
+        .ok_or_else(|| AppError::Validation("Job payload has no announcement_id".into()))?;
+    let mut after = job.payload["after"].as_str().and_then(|id| id.parse::<Uuid>().ok());
+    // Deleted since it was published: nothing left to send
+    let Some(announcement) = queries::get_announcement(state.db.primary(), announcement_id).await? else {
+        return Ok(0);
+    };
+    let Some(system_user) = queries::find_system_user(state.db.read()).await? else {
+        return Ok(0);
+    };
+    // Wire format for unencrypted messages: 0x00 + JSON payload
+    let payload = serde_json::json!({
+        "text": format!("{}\n\n{}", announcement.title, announcement.body),
+        "sender_id": system_user.id,
+    });
+    let mut body = vec![0u8];
+    body.extend_from_slice(payload.to_string().as_bytes());
+
+    let mut sent = 0;
+    loop {
+        let recipients = queries::list_announcement_recipients(state.db.read(), after, DM_BATCH_SIZE).await?;
+        let Some(&(last_id, _, _)) = recipients.last() else {
+            break;
+        };
+
+        for (user_id, username, display_name) in recipients {
+            let channel_id = match queries::find_dm_channel(state.db.read(), system_user.id, user_id).await? {
+                Some(channel) => channel.id,
+                None => {
+                    let display = display_name.as_deref().unwrap_or(&username);
+                    let dm_meta = format!(
+                        r#"{{"type":"dm","participants":["{}","{}"],"names":{{"{}":"Haven","{}":"{}"}}}}"#,
+                        system_user.id, user_id, system_user.id, user_id, display,
+                    );
+                    let channel = queries::create_channel(
+                        state.db.write(), None, dm_meta.as_bytes(), "dm", 0, None, false, false,
+                    )
+                    .await?;
+                    queries::add_channel_member(state.db.write(), channel.id, system_user.id).await?;
+                    queries::add_channel_member(state.db.write(), channel.id, user_id).await?;
+                    channel.id
+                }
+            };
+            let message = queries::insert_message(
+                state.db.write(), channel_id, &[0u8], &body, None, false, system_user.id, None,
+            )
+            .await?;
+            crate::ws::deliver_new_message(state, message).await?;
+            sent += 1;
+        }
+        after = Some(last_id);
+        let checkpoint = serde_json::json!({ "announcement_id": announcement.id, "after": after });
+        queries::checkpoint_job(state.db.write(), job.id, &checkpoint).await?;
+    }
+    tracing::info!("Announcement {} sent as DM to {} users", announcement.id, sent);
+    Ok(sent)
+}
+
+// ─── Operator management ─────────────────────────────
+
+/// POST /api/v1/admin/announcements
+/// Create an announcement; it is broadcast now, or at `publish_at` if given.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/announcements",
+    tag = "announcements",
+    request_body = CreateAnnouncementRequest,
+    responses((status = 200, body = Announcement))
+)]
+pub async fn create_announcement(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<CreateAnnouncementRequest>,
+) -> AppResult<Json<Announcement>> {
+    staff.require(permissions::INSTANCE_MANAGE_ANNOUNCEMENTS)?;
+
+    let title = req.title.trim();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_LEN {
+        return Err(AppError::Validation(format!("Title must be 1-{} characters", MAX_TITLE_LEN)));
+    }
+    let body = req.body.trim();
+    if body.is_empty() || body.chars().count() > MAX_BODY_LEN {
+        return Err(AppError::Validation(format!("Body must be 1-{} characters", MAX_BODY_LEN)));
+    }
+    let kind = req.kind.as_deref().unwrap_or("info");
+    if !KINDS.contains(&kind) {
+        return Err(AppError::Validation(format!("kind must be one of: {}", KINDS.join(", "))));
+    }
+    let now = Utc::now();
+    let publish_at = req.publish_at.unwrap_or(now).max(now);
+    if req.expires_at.is_some_and(|t| t <= publish_at) {
+        return Err(AppError::Validation("expires_at must be after the publish time".into()));
+    }
+
+    let announcement = queries::create_announcement(
+        state.db.write(),
+        title,
+        body,
+        kind,
+        req.send_dm,
+        publish_at,
+        req.expires_at,
+        staff.user_id,
+    )
+    .await?;
+
+    record_staff_action(
+        &state, &staff, "announcement_create", Some("announcement"), Some(announcement.id),
+        Some(&serde_json::json!({
+            "title": announcement.title,
+            "send_dm": announcement.send_dm,
+            "publish_at": announcement.publish_at,
+        })),
+        None,
+    )
+    .await;
+
+    if req.publish_at.is_none_or(|t| t <= now) {
+        if let Some(published) = publish_due(&state).await?.into_iter().find(|a| a.id == announcement.id) {
+            return Ok(Json(published));
+        }
+    }
+    Ok(Json(announcement))
+}
+
+/// GET /api/v1/admin/announcements
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/announcements",
+    tag = "announcements",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<AdminAnnouncementResponse>))
+)]
+pub async fn list_announcements(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(pagination): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<AdminAnnouncementResponse>>> {
+    staff.require(permissions::INSTANCE_MANAGE_ANNOUNCEMENTS)?;
+    let (limit, offset) = pagination.resolve();
+    Ok(Json(queries::list_announcements_admin(state.db.read(), limit, offset).await?))
+}
+
+/// DELETE /api/v1/admin/announcements/:announcement_id
+/// Cancel a scheduled announcement, or stop showing a published one.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/announcements/{announcement_id}",
+    tag = "announcements",
+    params(("announcement_id" = Uuid, Path, description = "Announcement ID")),
+    responses((status = 200))
+)]
+pub async fn delete_announcement(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(announcement_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_ANNOUNCEMENTS)?;
+    if !queries::delete_announcement(state.db.write(), announcement_id).await? {
+        return Err(AppError::NotFound("Announcement not found".into()));
+    }
+    record_staff_action(
+        &state, &staff, "announcement_delete", Some("announcement"), Some(announcement_id), None, None,
+    )
+    .await;
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+// ─── User endpoints ──────────────────────────────────
+
+/// GET /api/v1/announcements
+/// Active announcements the caller has not dismissed, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/announcements",
+    tag = "announcements",
+    responses((status = 200, body = Vec<AnnouncementResponse>))
+)]
+pub async fn get_announcements(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<Vec<AnnouncementResponse>>> {
+    let announcements = queries::get_active_announcements(state.db.read(), user_id).await?;
+    Ok(Json(announcements.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/v1/announcements/:announcement_id/dismiss
+#[utoipa::path(
+    post,
+    path = "/api/v1/announcements/{announcement_id}/dismiss",
+    tag = "announcements",
+    params(("announcement_id" = Uuid, Path, description = "Announcement ID")),
+    responses((status = 200))
+)]
+pub async fn dismiss_announcement(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(announcement_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !queries::dismiss_announcement(state.db.write(), announcement_id, user_id).await? {
+        return Err(AppError::NotFound("Announcement not found".into()));
+    }
+
+    // Hide it on the user's other devices too
+    let event = WsServerMessage::AnnouncementDismissed { announcement_id };
+    if let Some(conns) = state.connections.get(&user_id) {
+        for tx in conns.iter() {
+            let _ = tx.send(event.clone());
+        }
+    }
+    pubsub::publish_user_event(&state, user_id, &event).await;
+
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
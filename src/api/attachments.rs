@@ -1,8 +1,8 @@
 use axum::{
     body::Bytes,
-    extract::{Path as AxumPath, State},
-    http::{header, HeaderMap, StatusCode},
-    response::{IntoResponse, Redirect},
+    extract::{Path as AxumPath, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
 use uuid::Uuid;
@@ -11,14 +11,30 @@ use crate::db::queries;
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
-use crate::storage;
+use crate::permissions;
+use crate::quota;
+use crate::ranges::{self, ByteRange};
+use crate::scanning;
+use crate::signed_urls;
+use crate::thumbnails;
+use crate::uploads::{self, UploadTier};
 use crate::AppState;
 
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+
 /// POST /api/v1/attachments/upload
 /// Receives encrypted blob bytes, stores them.
 /// When CDN is enabled, stores raw (no server-side encryption — client-side E2EE is sufficient).
 /// When CDN is disabled, applies server-side AES-256-GCM encryption at rest.
 /// Optionally checks X-File-Hash header against the blocked hashes table.
+#[utoipa::path(
+    post,
+    path = "/api/v1/attachments/upload",
+    tag = "attachments",
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200, body = UploadResponse))
+)]
 pub async fn upload(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -49,71 +65,67 @@ pub async fn upload(
         .map(|s| s.to_lowercase());
 
     if let Some(ref hash) = file_hash {
-        // Validate format: 64 hex characters (SHA-256)
-        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(AppError::Validation("Invalid file hash format (expected 64 hex characters)".into()));
-        }
-
-        if queries::is_hash_blocked(state.db.read(), hash).await? {
-            return Err(AppError::Forbidden("Upload rejected".into()));
-        }
+        check_file_hash(&state, hash).await?;
     }
 
     let attachment_id = Uuid::new_v4();
-    let storage_key = storage::obfuscated_key(&state.storage_key, &attachment_id.to_string());
-
-    if state.config.cdn_enabled {
-        // CDN mode: store raw bytes (client-side E2EE is sufficient)
-        state
-            .storage
-            .store_blob_raw(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store attachment: {}", e)))?;
-    } else {
-        // Standard mode: encrypt at rest with server-side AES
-        state
-            .storage
-            .store_blob(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store attachment: {}", e)))?;
-    }
+    // The reference taken here is the attachment's once it is linked
+    let (content_hash, storage_key) = store_shared_blob(&state, &body).await?;
 
     tracing::debug!("Stored attachment {} ({} bytes, cdn={})", attachment_id, body.len(), state.config.cdn_enabled);
 
-    // Store file hash for later linking to the attachment record
-    if let Some(hash) = file_hash {
-        state.memory.pending_file_hashes.insert(attachment_id, hash);
+    // Held until a message links it (charged to the server's storage quota
+    // then); the attachment GC releases it if it is never sent.
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::from_std(uploads::PENDING_UPLOAD_TTL).expect("pending TTL fits in chrono");
+    if let Err(e) = queries::create_pending_upload(
+        state.db.write(),
+        attachment_id,
+        user_id,
+        &content_hash,
+        body.len() as i64,
+        file_hash.as_deref(),
+        expires_at,
+    )
+    .await
+    {
+        let _ = queries::release_attachment_blob(state.db.write(), &content_hash).await;
+        return Err(e);
     }
 
     Ok(Json(UploadResponse {
         attachment_id,
         storage_key,
+        preview: None,
     }))
 }
 
 /// GET /api/v1/attachments/:attachment_id
 /// When CDN is enabled, returns a presigned S3 URL redirect (or raw bytes for local storage).
 /// When CDN is disabled, decrypts server-side encryption and returns raw bytes.
+/// Supports `Range` and `If-Range` (see `ranges`); `?download=true` and
+/// `?filename=` shape the `Content-Disposition`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/{attachment_id}",
+    tag = "attachments",
+    params(("attachment_id" = Uuid, Path, description = "Attachment ID"), AttachmentDownloadQuery),
+    responses(
+        (status = 200),
+        (status = 206, description = "The requested byte range"),
+        (status = 416, description = "The range starts past the end of the attachment")
+    )
+)]
 pub async fn download(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     AxumPath(attachment_id): AxumPath<Uuid>,
-) -> AppResult<impl IntoResponse> {
-    // Look up the attachment
-    let att = queries::find_attachment_by_id(state.db.read(), attachment_id)
-        .await?
-        .ok_or(AppError::NotFound("Attachment not found".into()))?;
-
-    // Verify the user has access to the message's channel
-    let message = queries::find_message_by_id(state.db.read(), att.message_id)
-        .await?
-        .ok_or(AppError::NotFound("Message not found".into()))?;
-
-    if !queries::can_access_channel(state.db.read(), message.channel_id, user_id).await? {
-        return Err(AppError::Forbidden("Not a member of this channel".into()));
-    }
+    Query(query): Query<AttachmentDownloadQuery>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let att = find_downloadable(&state, attachment_id, user_id).await?;
 
-    if state.config.cdn_enabled {
+    let data = if state.config.cdn_enabled {
         // CDN mode: try to return a presigned URL redirect
         if let Some(url) = state
             .storage
@@ -128,31 +140,718 @@ pub async fn download(
         }
 
         // Fallback for local storage: serve raw bytes directly
-        let data = state
-            .storage
-            .load_blob_raw(&att.storage_key)
-            .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load attachment: {}", e)))?;
-
-        Ok((
-            StatusCode::OK,
-            [(header::CONTENT_TYPE, "application/octet-stream")],
-            data,
-        )
-            .into_response())
+        state.storage.load_blob_raw(&att.storage_key).await
     } else {
         // Standard mode: decrypt server-side encryption and return bytes
-        let data = state
-            .storage
-            .load_blob(&att.storage_key)
+        state.storage.load_blob(&att.storage_key).await
+    }
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load attachment: {}", e)))?;
+
+    let content_type = att.content_type.as_deref().unwrap_or("application/octet-stream");
+    let disposition = ranges::content_disposition(content_type, query.download, query.filename.as_deref());
+    let mut response = ranged_response(
+        data,
+        &headers,
+        content_type,
+        &disposition,
+        &ranges::attachment_etag(att.id),
+        Some(att.created_at),
+    )?;
+    response
+        .headers_mut()
+        .insert(header::LAST_MODIFIED, header_value(&ranges::http_date(att.created_at))?);
+    Ok(response)
+}
+
+/// GET /api/v1/attachments/:attachment_id/signed-url
+/// Issue a short-lived signed URL for the attachment that an edge worker can
+/// verify without the database (see `signed_urls`). Takes the same
+/// `?download=` and `?filename=` as a direct download.
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/{attachment_id}/signed-url",
+    tag = "attachments",
+    params(("attachment_id" = Uuid, Path, description = "Attachment ID"), AttachmentDownloadQuery),
+    responses((status = 200, body = SignedAttachmentUrl))
+)]
+pub async fn create_signed_url(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(attachment_id): AxumPath<Uuid>,
+    Query(query): Query<AttachmentDownloadQuery>,
+) -> AppResult<Json<SignedAttachmentUrl>> {
+    let att = find_downloadable(&state, attachment_id, user_id).await?;
+    let content_type = att.content_type.as_deref().unwrap_or("application/octet-stream");
+    let disposition = ranges::content_disposition(content_type, query.download, query.filename.as_deref());
+    let signed = signed_urls::issue(&state.live_config.get(), &att.storage_key, content_type, &disposition)?;
+    Ok(Json(signed))
+}
+
+/// GET /api/v1/attachments/signed/*storage_key
+/// Serve a blob through a signed URL. Unauthenticated: the signature is the
+/// credential, and nothing is looked up in the database.
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/signed/{storage_key}",
+    tag = "attachments",
+    params(("storage_key" = String, Path, description = "Storage key"), SignedAttachmentQuery),
+    responses(
+        (status = 200),
+        (status = 206, description = "The requested byte range"),
+        (status = 403, description = "Invalid or expired signature")
+    )
+)]
+pub async fn download_signed(
+    State(state): State<AppState>,
+    AxumPath(storage_key): AxumPath<String>,
+    Query(query): Query<SignedAttachmentQuery>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    signed_urls::verify(&state.live_config.get(), &storage_key, &query)?;
+
+    let data = if state.config.cdn_enabled {
+        state.storage.load_blob_raw(&storage_key).await
+    } else {
+        state.storage.load_blob(&storage_key).await
+    }
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load attachment: {}", e)))?;
+
+    let mut response = ranged_response(data, &headers, &query.ct, &query.cd, &ranges::blob_etag(&storage_key), None)?;
+    // The URL is the credential, so any cache may keep it until it expires
+    let max_age = (query.exp - chrono::Utc::now().timestamp()).max(0);
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, header_value(&format!("public, max-age={}", max_age))?);
+    Ok(response)
+}
+
+/// The attachment, if `user_id` can read its channel and it passed scanning.
+async fn find_downloadable(state: &AppState, attachment_id: Uuid, user_id: Uuid) -> AppResult<Attachment> {
+    let att = queries::find_attachment_by_id(state.db.read(), attachment_id)
+        .await?
+        .ok_or(AppError::NotFound("Attachment not found".into()))?;
+
+    // Verify the user has access to the message's channel
+    let message = queries::find_message_by_id(state.db.read(), att.message_id)
+        .await?
+        .ok_or(AppError::NotFound("Message not found".into()))?;
+
+    if !queries::can_access_channel(state.db.read(), message.channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+    scanning::check_downloadable(&state.config, &att)?;
+    Ok(att)
+}
+
+/// A 200, 206 or 416 response for `data`, honouring `Range` and `If-Range`.
+fn ranged_response(
+    data: Vec<u8>,
+    headers: &HeaderMap,
+    content_type: &str,
+    disposition: &str,
+    etag: &str,
+    last_modified: Option<chrono::DateTime<chrono::Utc>>,
+) -> AppResult<Response> {
+    let len = data.len() as u64;
+    let range = ranges::requested_range(headers, len, etag, last_modified);
+
+    let mut response = match range {
+        ByteRange::Full => (StatusCode::OK, data).into_response(),
+        ByteRange::Partial { start, end } => {
+            (StatusCode::PARTIAL_CONTENT, data[start as usize..=end as usize].to_vec()).into_response()
+        }
+        ByteRange::Unsatisfiable => StatusCode::RANGE_NOT_SATISFIABLE.into_response(),
+    };
+    let response_headers = response.headers_mut();
+    if let Some(content_range) = range.content_range(len) {
+        response_headers.insert(header::CONTENT_RANGE, header_value(&content_range)?);
+    }
+    if range != ByteRange::Unsatisfiable {
+        response_headers.insert(header::CONTENT_TYPE, header_value(content_type)?);
+        response_headers.insert(header::CONTENT_DISPOSITION, header_value(disposition)?);
+    }
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(header::ETAG, header_value(etag)?);
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    Ok(response)
+}
+
+fn header_value(value: &str) -> AppResult<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid header value: {}", e)))
+}
+
+/// GET /api/v1/attachments/:attachment_id/thumbnail
+/// Serve the server-generated JPEG thumbnail of an image attachment.
+#[utoipa::path(
+    get,
+    path = "/api/v1/attachments/{attachment_id}/thumbnail",
+    tag = "attachments",
+    params(("attachment_id" = Uuid, Path, description = "Attachment ID")),
+    responses((status = 200))
+)]
+pub async fn download_thumbnail(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(attachment_id): AxumPath<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let att = find_downloadable(&state, attachment_id, user_id).await?;
+
+    let thumbnail_key = att
+        .thumbnail_key
+        .ok_or(AppError::NotFound("Attachment has no thumbnail".into()))?;
+    let data = state
+        .storage
+        .load_blob(&thumbnail_key)
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load thumbnail: {}", e)))?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, thumbnails::thumbnail_content_type()),
+            (header::CACHE_CONTROL, "private, max-age=86400"),
+        ],
+        data,
+    ))
+}
+
+/// POST /api/v1/channels/:channel_id/attachments
+/// Open a resumable upload session. Size and content-type limits come from
+/// the channel's server upload tier (DMs use the standard tier), narrowed by
+/// the role and channel limits in `api::upload_limits`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/attachments",
+    tag = "attachments",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = CreateUploadSessionRequest,
+    responses((status = 201))
+)]
+pub async fn create_upload_session(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(channel_id): AxumPath<Uuid>,
+    Json(req): Json<CreateUploadSessionRequest>,
+) -> AppResult<impl IntoResponse> {
+    if !state.api_rate_limiter.check(user_id) {
+        return Err(AppError::BadRequest("Rate limit exceeded — try again later".into()));
+    }
+
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+
+    let mut limits = None;
+    let tier = match channel.server_id {
+        Some(server_id) => {
+            let perms =
+                queries::get_member_channel_permissions(state.db.read(), server_id, channel_id, user_id)
+                    .await?;
+            state.shadow_reads.compare("member_channel_permissions", &perms, || {
+                let pool = state.db.read().clone();
+                async move {
+                    queries::get_member_channel_permissions_joined(&pool, server_id, channel_id, user_id).await
+                }
+            });
+            if !permissions::has_permission(perms, permissions::ATTACH_FILES) {
+                return Err(AppError::Forbidden("Missing ATTACH_FILES permission".into()));
+            }
+            let is_owner = queries::find_server_by_id(state.db.read(), server_id)
+                .await?
+                .is_some_and(|s| s.owner_id == user_id);
+            limits = Some(
+                crate::api::upload_limits::member_upload_limits(&state, server_id, Some(channel_id), user_id, is_owner)
+                    .await?,
+            );
+            queries::get_server_upload_tier(state.db.read(), server_id)
+                .await?
+                .and_then(|t| UploadTier::parse(&t))
+                .unwrap_or(UploadTier::Standard)
+        }
+        None => UploadTier::Standard,
+    };
+
+    if req.upload_length == 0 {
+        return Err(AppError::Validation("upload_length must be greater than zero".into()));
+    }
+    let max_size = match &limits {
+        Some(limits) => limits.max_size_bytes,
+        None => tier.max_size(state.config.max_upload_size_bytes),
+    };
+    if req.upload_length > max_size {
+        return Err(AppError::BadRequest(format!("File too large (max {} bytes)", max_size)));
+    }
+    if let Some(max_count) = limits.as_ref().and_then(|l| l.max_count) {
+        if max_count == 0 {
+            return Err(AppError::Forbidden("Uploads are not allowed here".into()).with_code("UPLOAD_LIMIT"));
+        }
+        let active = queries::count_active_upload_sessions(state.db.read(), channel_id, user_id).await?;
+        if active >= max_count as i64 {
+            return Err(AppError::BadRequest(format!(
+                "Too many uploads in progress (max {})",
+                max_count
+            ))
+            .with_code("UPLOAD_LIMIT"));
+        }
+    }
+    let content_type = req.content_type.trim().to_ascii_lowercase();
+    if content_type.is_empty() || content_type.len() > 255 {
+        return Err(AppError::Validation("content_type must be 1-255 characters".into()));
+    }
+    if !tier.allows_content_type(&content_type) {
+        return Err(AppError::BadRequest(format!(
+            "Content type {} is not allowed on the {} upload tier",
+            content_type,
+            tier.as_str()
+        )));
+    }
+
+    if let Some(server_id) = channel.server_id {
+        quota::check_storage(&state, server_id, req.upload_length).await?;
+    }
+
+    let file_hash = req.file_hash.map(|h| h.to_lowercase());
+    if let Some(ref hash) = file_hash {
+        check_file_hash(&state, hash).await?;
+    }
+
+    let expires_at = chrono::Utc::now()
+        + chrono::Duration::from_std(uploads::SESSION_TTL).expect("session TTL fits in chrono");
+    let session = queries::create_upload_session(
+        state.db.write(),
+        channel_id,
+        user_id,
+        &content_type,
+        req.upload_length as i64,
+        file_hash.as_deref(),
+        expires_at,
+    )
+    .await?;
+
+    let location = format!("/api/v1/attachments/uploads/{}", session.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(UploadSessionResponse::from(session)),
+    ))
+}
+
+/// HEAD /api/v1/attachments/uploads/:upload_id
+/// Report how many bytes have been received so an interrupted upload can resume.
+#[utoipa::path(
+    head,
+    path = "/api/v1/attachments/uploads/{upload_id}",
+    tag = "attachments",
+    params(("upload_id" = Uuid, Path, description = "Upload ID")),
+    responses((status = 200))
+)]
+pub async fn get_upload_offset(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(upload_id): AxumPath<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let session = find_session(&state, upload_id, user_id).await?;
+    Ok((
+        StatusCode::OK,
+        [
+            (UPLOAD_OFFSET, session.upload_offset.to_string()),
+            (UPLOAD_LENGTH, session.upload_length.to_string()),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+    ))
+}
+
+/// PUT /api/v1/attachments/uploads/:upload_id
+/// Upload the next chunk. The `Upload-Offset` header must match the number of
+/// bytes already received; a mismatch returns 409 and the client should HEAD
+/// the session to resume from the server's offset.
+#[utoipa::path(
+    put,
+    path = "/api/v1/attachments/uploads/{upload_id}",
+    tag = "attachments",
+    params(("upload_id" = Uuid, Path, description = "Upload ID")),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 204))
+)]
+pub async fn upload_chunk(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(upload_id): AxumPath<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AppResult<impl IntoResponse> {
+    let offset: i64 = headers
+        .get(&UPLOAD_OFFSET)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .filter(|o| *o >= 0)
+        .ok_or(AppError::Validation("Missing or invalid Upload-Offset header".into()))?;
+
+    let session = find_session(&state, upload_id, user_id).await?;
+    if offset != session.upload_offset {
+        return Err(AppError::Conflict(format!(
+            "Upload-Offset mismatch (server has {} bytes)",
+            session.upload_offset
+        )));
+    }
+    if body.is_empty() {
+        return Err(AppError::Validation("Empty chunk".into()));
+    }
+    if body.len() as u64 > uploads::MAX_CHUNK_SIZE {
+        return Err(AppError::BadRequest(format!(
+            "Chunk too large (max {} bytes)",
+            uploads::MAX_CHUNK_SIZE
+        )));
+    }
+    if offset + body.len() as i64 > session.upload_length {
+        return Err(AppError::BadRequest("Chunk exceeds the declared upload length".into()));
+    }
+
+    // Parts are intermediate and always encrypted at rest, whatever the CDN mode.
+    let part_id = Uuid::new_v4();
+    let part_key = uploads::part_key(&state.storage_key, part_id);
+    state
+        .storage
+        .store_blob(&part_key, &body)
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store upload chunk: {}", e)))?;
+
+    let updated = queries::append_upload_part(
+        state.db.write(),
+        upload_id,
+        offset,
+        part_id,
+        body.len() as i64,
+    )
+    .await?;
+    let Some(updated) = updated else {
+        // A concurrent chunk advanced the offset first; drop ours.
+        let _ = state.storage.delete_blob(&part_key).await;
+        return Err(AppError::Conflict("Upload-Offset mismatch".into()));
+    };
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(UPLOAD_OFFSET, updated.upload_offset.to_string())],
+    ))
+}
+
+/// DELETE /api/v1/attachments/uploads/:upload_id
+/// Abandon an upload session and discard any received chunks.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/attachments/uploads/{upload_id}",
+    tag = "attachments",
+    params(("upload_id" = Uuid, Path, description = "Upload ID")),
+    responses((status = 204))
+)]
+pub async fn cancel_upload(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(upload_id): AxumPath<Uuid>,
+) -> AppResult<StatusCode> {
+    find_session(&state, upload_id, user_id).await?;
+    let part_ids = queries::cancel_upload_session(state.db.write(), upload_id)
+        .await?
+        .ok_or(AppError::Conflict("Upload is being finalized".into()))?;
+    delete_parts(&state, &part_ids).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/attachments/uploads/:upload_id/finalize
+/// Assemble a completed upload into an attachment and link it to a message the
+/// uploader sent in the session's channel. The upload id becomes the attachment id.
+/// Images in unencrypted channels also get a thumbnail and blurhash when
+/// `thumbnails_enabled` is set.
+#[utoipa::path(
+    post,
+    path = "/api/v1/attachments/uploads/{upload_id}/finalize",
+    tag = "attachments",
+    params(("upload_id" = Uuid, Path, description = "Upload ID")),
+    request_body = FinalizeUploadRequest,
+    responses((status = 200, body = UploadResponse))
+)]
+pub async fn finalize_upload(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    AxumPath(upload_id): AxumPath<Uuid>,
+    Json(req): Json<FinalizeUploadRequest>,
+) -> AppResult<Json<UploadResponse>> {
+    let session = find_session(&state, upload_id, user_id).await?;
+    if session.upload_offset != session.upload_length {
+        return Err(AppError::BadRequest(format!(
+            "Upload incomplete ({} of {} bytes received)",
+            session.upload_offset, session.upload_length
+        )));
+    }
+
+    let message = queries::find_message_by_id(state.db.read(), req.message_id)
+        .await?
+        .ok_or(AppError::NotFound("Message not found".into()))?;
+    if message.channel_id != session.channel_id {
+        return Err(AppError::BadRequest("Message is not in the upload's channel".into()));
+    }
+    if message.sender_id != Some(user_id) {
+        return Err(AppError::Forbidden("Can only attach files to your own messages".into()));
+    }
+
+    let channel = queries::find_channel_by_id(state.db.read(), session.channel_id).await?;
+    let server_id = channel.as_ref().and_then(|channel| channel.server_id);
+    let encrypted = channel.as_ref().is_none_or(|channel| channel.encrypted);
+    let scan = scanning::should_scan(&state.config, encrypted);
+    let wants_preview = !encrypted
+        && state.live_config.get().thumbnails_enabled
+        && thumbnails::is_previewable(&session.content_type)
+        && session.upload_length as u64 <= thumbnails::MAX_SOURCE_BYTES;
+
+    // Only one finalize may compose a session; a failed one hands it back.
+    let session = queries::claim_upload_session(state.db.write(), upload_id, user_id)
+        .await?
+        .ok_or(AppError::Conflict("Upload is already being finalized".into()))?;
+    let (content_hash, storage_key, preview_source) =
+        match compose_upload(&state, &session, wants_preview).await {
+            Ok(composed) => composed,
+            Err(e) => {
+                let _ = queries::release_upload_session(state.db.write(), upload_id).await;
+                return Err(e);
+            }
+        };
+
+    if let Err(e) = queries::link_attachment(
+        state.db.write(),
+        upload_id,
+        message.id,
+        &storage_key,
+        session.file_hash.as_deref(),
+        server_id,
+        session.upload_length,
+        Some(&content_hash),
+        scan.then_some(ScanStatus::Pending),
+        (!encrypted).then_some(session.content_type.as_str()),
+    )
+    .await
+    {
+        let _ = queries::release_attachment_blob(state.db.write(), &content_hash).await;
+        let _ = queries::release_upload_session(state.db.write(), upload_id).await;
+        return Err(e);
+    }
+    if scan {
+        scanning::spawn_scan(&state, upload_id, storage_key.clone());
+    }
+    queries::mark_message_has_attachments(state.db.write(), message.id).await?;
+
+    if let Some(part_ids) = queries::delete_upload_session(state.db.write(), upload_id).await? {
+        delete_parts(&state, &part_ids).await;
+    }
+
+    tracing::debug!("Finalized upload {} ({} bytes) onto message {}", upload_id, session.upload_length, message.id);
+
+    let preview = match preview_source {
+        // A failed preview never fails the upload; clients fall back to the full image.
+        Some(data) => generate_preview(&state, upload_id, &message, data)
             .await
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load attachment: {}", e)))?;
-
-        Ok((
-            StatusCode::OK,
-            [(header::CONTENT_TYPE, "application/octet-stream")],
-            data,
-        )
-            .into_response())
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to generate preview for attachment {}: {}", upload_id, e);
+                None
+            }),
+        None => None,
+    };
+
+    Ok(Json(UploadResponse {
+        attachment_id: upload_id,
+        storage_key,
+        preview,
+    }))
+}
+
+/// Build, store and announce the preview for a finalized image attachment.
+/// Encrypted channels are skipped (the data is ciphertext), as is anything
+/// that fails to decode.
+async fn generate_preview(
+    state: &AppState,
+    attachment_id: Uuid,
+    message: &Message,
+    data: Vec<u8>,
+) -> AppResult<Option<AttachmentPreview>> {
+    let channel = queries::find_channel_by_id(state.db.read(), message.channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    if channel.encrypted {
+        return Ok(None);
+    }
+
+    let generated = tokio::task::spawn_blocking(move || thumbnails::generate(&data))
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Thumbnail task failed: {}", e)))?;
+    let Some(generated) = generated else {
+        tracing::debug!("Attachment {} is not a decodable image; no preview", attachment_id);
+        return Ok(None);
+    };
+
+    // Thumbnails are server-generated plaintext, so always encrypted at rest.
+    let thumbnail_key = thumbnails::thumbnail_key(&state.storage_key, attachment_id);
+    state
+        .storage
+        .store_blob(&thumbnail_key, &generated.thumbnail)
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store thumbnail: {}", e)))?;
+
+    let (width, height) = (generated.width as i32, generated.height as i32);
+    queries::set_attachment_preview(
+        state.db.write(),
+        attachment_id,
+        &thumbnail_key,
+        &generated.blurhash,
+        width,
+        height,
+    )
+    .await?;
+
+    let preview = AttachmentPreview::new(attachment_id, generated.blurhash, width, height);
+    let event = WsServerMessage::AttachmentPreviewReady {
+        channel_id: message.channel_id,
+        message_id: message.id,
+        preview: preview.clone(),
+    };
+    if let Some(broadcaster) = state.channel_broadcasts.get(&message.channel_id) {
+        let _ = broadcaster.send(event.clone());
+    }
+    crate::pubsub::publish_channel_event(state, message.channel_id, &event).await;
+
+    Ok(Some(preview))
+}
+
+/// Validate a hex SHA-256 and reject it if it's on the blocked list.
+async fn check_file_hash(state: &AppState, hash: &str) -> AppResult<()> {
+    // Validate format: 64 hex characters (SHA-256)
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::Validation("Invalid file hash format (expected 64 hex characters)".into()));
+    }
+
+    if queries::is_hash_blocked(state.db.read(), hash).await? {
+        return Err(AppError::Forbidden("Upload rejected".into()));
+    }
+    Ok(())
+}
+
+/// Store a finished attachment blob.
+/// When CDN is enabled, stores raw (no server-side encryption — client-side E2EE is sufficient).
+/// When CDN is disabled, applies server-side AES-256-GCM encryption at rest.
+async fn store_attachment_blob(state: &AppState, storage_key: &str, data: &[u8]) -> AppResult<()> {
+    let result = if state.config.cdn_enabled {
+        state.storage.store_blob_raw(storage_key, data).await
+    } else {
+        state.storage.store_blob(storage_key, data).await
+    };
+    result.map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to store attachment: {}", e)))
+}
+
+/// Store attachment bytes under their content hash, taking a reference on
+/// the shared blob. Identical bytes already stored are not written again.
+/// Returns the hash and the blob's storage key.
+async fn store_shared_blob(state: &AppState, data: &[u8]) -> AppResult<(String, String)> {
+    let content_hash = uploads::content_hash(data);
+    let storage_key = uploads::blob_key(&state.storage_key, &content_hash);
+    let is_new =
+        queries::acquire_attachment_blob(state.db.write(), &content_hash, &storage_key, data.len() as i64).await?;
+    if is_new {
+        if let Err(e) = store_attachment_blob(state, &storage_key, data).await {
+            let _ = queries::release_attachment_blob(state.db.write(), &content_hash).await;
+            return Err(e);
+        }
+    }
+    Ok((content_hash, storage_key))
+}
+
+/// Assemble a claimed session's parts into a shared blob, a part at a time:
+/// one pass hashes them, and a second writes them only if the blob is new.
+/// Returns the content hash, the blob's storage key and, with
+/// `keep_for_preview`, the assembled bytes (bounded by
+/// `thumbnails::MAX_SOURCE_BYTES`).
+async fn compose_upload(
+    state: &AppState,
+    session: &UploadSession,
+    keep_for_preview: bool,
+) -> AppResult<(String, String, Option<Vec<u8>>)> {
+    let mut hasher = blake3::Hasher::new();
+    let mut length = 0u64;
+    let mut preview_source = keep_for_preview.then(|| Vec::with_capacity(session.upload_length as usize));
+    for part_id in &session.part_ids {
+        let part = load_part(state, *part_id).await?;
+        hasher.update(&part);
+        length += part.len() as u64;
+        if let Some(source) = preview_source.as_mut() {
+            source.extend_from_slice(&part);
+        }
+    }
+    if length as i64 != session.upload_length {
+        return Err(AppError::Internal(anyhow::anyhow!(
+            "Upload {} assembled to {} bytes, expected {}",
+            session.id,
+            length,
+            session.upload_length
+        )));
+    }
+
+    let content_hash = hasher.finalize().to_hex().to_string();
+    let storage_key = uploads::blob_key(&state.storage_key, &content_hash);
+    let is_new =
+        queries::acquire_attachment_blob(state.db.write(), &content_hash, &storage_key, length as i64).await?;
+    if is_new {
+        if let Err(e) = write_parts(state, &storage_key, &session.part_ids).await {
+            let _ = queries::release_attachment_blob(state.db.write(), &content_hash).await;
+            return Err(e);
+        }
+    }
+    Ok((content_hash, storage_key, preview_source))
+}
+
+/// Stream upload parts into one blob at `storage_key`.
+async fn write_parts(state: &AppState, storage_key: &str, part_ids: &[Uuid]) -> AppResult<()> {
+    let storage_err = |e: std::io::Error| AppError::Internal(anyhow::anyhow!("Failed to store attachment: {}", e));
+    let mut writer = state
+        .storage
+        .blob_writer(storage_key, state.config.cdn_enabled)
+        .await
+        .map_err(storage_err)?;
+    for part_id in part_ids {
+        let written = match load_part(state, *part_id).await {
+            Ok(part) => writer.write(part).await.map_err(storage_err),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            writer.abort().await;
+            return Err(e);
+        }
+    }
+    writer.finish().await.map_err(storage_err)
+}
+
+async fn load_part(state: &AppState, part_id: Uuid) -> AppResult<Vec<u8>> {
+    state
+        .storage
+        .load_blob(&uploads::part_key(&state.storage_key, part_id))
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to load upload chunk: {}", e)))
+}
+
+/// Reads go to the primary: sessions are written on every chunk and a lagging
+/// replica would report a stale offset.
+async fn find_session(state: &AppState, upload_id: Uuid, user_id: Uuid) -> AppResult<UploadSession> {
+    queries::find_upload_session(state.db.write(), upload_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound("Upload session not found".into()))
+}
+
+/// Best-effort removal of upload part blobs.
+pub async fn delete_parts(state: &AppState, part_ids: &[Uuid]) {
+    for part_id in part_ids {
+        let key = uploads::part_key(&state.storage_key, *part_id);
+        if let Err(e) = state.storage.delete_blob(&key).await {
+            tracing::warn!("Failed to delete upload part {}: {}", part_id, e);
+        }
     }
 }
@@ -1,15 +1,17 @@
-use axum::{extract::State, http::{HeaderMap, StatusCode}, Json};
+use std::net::SocketAddr;
+
+use axum::{extract::{ConnectInfo, State}, http::{HeaderMap, StatusCode}, Json};
 use chrono::{Duration, Utc};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::auth;
-use crate::db::queries;
+use crate::config::RegistrationMode;
+use crate::db::{queries, Staleness};
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
-use crate::storage;
 use crate::AppState;
 
 /// Parse a User-Agent header into a short device name like "Chrome on macOS".
@@ -81,7 +83,8 @@ async fn verify_turnstile(secret: &str, token: &str) -> AppResult<()> {
     if body.get("success").and_then(|v| v.as_bool()) == Some(true) {
         Ok(())
     } else {
-        Err(AppError::Validation("CAPTCHA verification failed — please try again".into()))
+        Err(AppError::Validation("CAPTCHA verification failed — please try again".into())
+            .with_code("CAPTCHA_FAILED"))
     }
 }
 
@@ -91,6 +94,13 @@ const POW_DIFFICULTY: u32 = 20;
 const POW_CHALLENGE_TTL: u64 = 300;
 
 /// GET /api/v1/auth/challenge — generate a PoW challenge for registration
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/challenge",
+    tag = "auth_routes",
+    responses((status = 200, body = PowChallengeResponse)),
+    security(())
+)]
 pub async fn pow_challenge(
     State(state): State<AppState>,
 ) -> AppResult<Json<PowChallengeResponse>> {
@@ -149,14 +159,35 @@ fn verify_pow(challenge: &str, nonce: &str, difficulty: u32) -> bool {
 }
 
 /// POST /api/v1/auth/register
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/register",
+    tag = "auth_routes",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, body = RegisterResponse),
+        (status = 202, body = RegisterResponse, description = "Account created, awaiting operator approval"),
+    ),
+    security(())
+)]
 pub async fn register(
     State(state): State<AppState>,
     headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<RegisterRequest>,
-) -> AppResult<Json<AuthResponse>> {
+) -> AppResult<RegisterResponse> {
     // Validate request
-    req.validate()
-        .map_err(|e| AppError::Validation(e.to_string()))?;
+    req.validate()?;
+
+    // REGISTRATION_MODE applies from the second account on, so a new
+    // instance can always get its operator
+    let mode = state.live_config.get().effective_registration_mode();
+    let is_first = queries::is_first_user_precheck(state.db.read()).await.unwrap_or(false);
+    if mode == RegistrationMode::Closed && !is_first {
+        return Err(AppError::Forbidden("Registration is closed on this instance".into())
+            .with_code("REGISTRATION_CLOSED"));
+    }
+    let pending = mode == RegistrationMode::Approval && !is_first;
 
     // Verify Proof-of-Work challenge exists and consume it (single-use)
     let challenge_valid = if let Some(mut redis) = state.redis.clone() {
@@ -189,33 +220,46 @@ pub async fn register(
     }
 
     if !verify_pow(&req.pow_challenge, &req.pow_nonce, POW_DIFFICULTY) {
-        return Err(AppError::Validation("Invalid Proof-of-Work solution".into()));
+        return Err(AppError::Validation("Invalid Proof-of-Work solution".into())
+            .with_code("INVALID_POW"));
     }
 
     // Verify Cloudflare Turnstile CAPTCHA (if enabled)
     if state.config.turnstile_enabled() {
         let token = req.turnstile_token.as_deref()
-            .ok_or(AppError::Validation("CAPTCHA token required".into()))?;
+            .ok_or(AppError::Validation("CAPTCHA token required".into()).with_code("CAPTCHA_REQUIRED"))?;
         verify_turnstile(&state.config.turnstile_secret_key, token).await?;
     }
 
+    // Score the request (IP lists, signups per network, proxy headers);
+    // refused at ABUSE_BLOCK_SCORE
+    let abuse = crate::abuse::assess(&state, "register", &headers, peer.map(|ConnectInfo(addr)| addr)).await?;
+
     // Validate registration invite code (if invite-only mode is enabled)
-    let is_first = queries::is_first_user_precheck(state.db.read()).await.unwrap_or(false);
-    let invite_to_consume = if state.config.registration_invite_only && !is_first {
+    let invite_to_consume = if mode == RegistrationMode::Invite && !is_first {
         let code = req.invite_code.as_deref()
-            .ok_or(AppError::Validation("Registration invite code required".into()))?;
+            .ok_or(AppError::Validation("Registration invite code required".into())
+                .with_code("INVITE_REQUIRED"))?;
 
         let invite = queries::find_registration_invite_by_code(state.db.read(), code)
             .await?
-            .ok_or(AppError::Validation("Invalid registration invite code".into()))?;
+            .ok_or(AppError::Validation("Invalid registration invite code".into())
+                .with_code("INVITE_INVALID"))?;
 
         if invite.used_by.is_some() {
-            return Err(AppError::Validation("This invite code has already been used".into()));
+            return Err(AppError::Validation("This invite code has already been used".into())
+                .with_code("INVITE_USED"));
+        }
+
+        if invite.revoked_at.is_some() {
+            return Err(AppError::Validation("This invite code has been revoked".into())
+                .with_code("INVITE_REVOKED"));
         }
 
         if let Some(expires_at) = invite.expires_at {
             if Utc::now() > expires_at {
-                return Err(AppError::Validation("This invite code has expired".into()));
+                return Err(AppError::Validation("This invite code has expired".into())
+                    .with_code("INVITE_EXPIRED"));
             }
         }
 
@@ -247,7 +291,9 @@ pub async fn register(
         &base64::engine::general_purpose::STANDARD,
         &req.signed_prekey_signature,
     )
-    .map_err(|_| AppError::Validation("Invalid signed_prekey_signature encoding".into()))?;
+    .map_err(|_| {
+        AppError::Validation("Invalid signed_prekey_signature encoding".into()).with_code("INVALID_SIGNATURE")
+    })?;
 
     // Create user
     let user = queries::create_user(
@@ -261,13 +307,20 @@ pub async fn register(
         &signed_prekey_sig,
     )
     .await?;
+    // Before anything else, so the account can't log in ahead of approval
+    if pending {
+        queries::create_pending_registration(state.db.write(), user.id).await?;
+    }
+
+    queries::append_key_log_entry(state.db.write(), user.id, &identity_key).await?;
+    queries::attach_abuse_event(state.db.write(), abuse.event_id, user.id).await?;
 
     // Auto-grant instance admin to the first registered user
-    if queries::is_first_user(state.db.read()).await.unwrap_or(false) {
+    if queries::is_first_user(state.db.read_with(Staleness::Fresh)).await.unwrap_or(false) {
         let _ = queries::set_instance_admin(state.db.write(), user.id, true).await;
         tracing::info!("First user {} auto-granted instance admin", user.username);
         // First user gets invite codes even without using one
-        if state.config.registration_invite_only {
+        if mode == RegistrationMode::Invite {
             let _ = queries::create_registration_invites(
                 state.db.write(),
                 Some(user.id),
@@ -288,7 +341,7 @@ pub async fn register(
 
     // Store one-time prekeys
     if !req.one_time_prekeys.is_empty() {
-        let prekeys: Result<Vec<(i32, Vec<u8>)>, _> = req
+        let prekeys: Result<Vec<Vec<u8>>, _> = req
             .one_time_prekeys
             .iter()
             .enumerate()
@@ -297,7 +350,6 @@ pub async fn register(
                     &base64::engine::general_purpose::STANDARD,
                     key_b64,
                 )
-                .map(|bytes| (i as i32, bytes))
                 .map_err(|_| AppError::Validation(format!("Invalid prekey encoding at index {}", i)))
             })
             .collect();
@@ -305,6 +357,40 @@ pub async fn register(
         queries::insert_prekeys(state.db.write(), user.id, &prekeys?).await?;
     }
 
+    // Approval mode: the account waits for an operator, with no tokens and
+    // no personal server until it is approved
+    if pending {
+        tracing::info!("User {} registered, awaiting approval", user.username);
+        return Ok(RegisterResponse::PendingApproval { pending_approval: true, user_id: user.id });
+    }
+
+    provision_new_user(&state, &user).await;
+
+    // Generate tokens with a new token family
+    let family_id = Uuid::new_v4();
+    let access_token = auth::generate_access_token(user.id, &state.config)?;
+    let refresh_token = auth::generate_refresh_token();
+    let refresh_hash = auth::hash_refresh_token(&refresh_token);
+
+    let device = headers.get("user-agent").and_then(|v| v.to_str().ok()).map(parse_device_name);
+    let ip = extract_ip_from_headers(&headers, state.config.trust_proxy);
+    let expiry = Utc::now() + Duration::days(state.config.refresh_token_expiry_days);
+    queries::store_refresh_token_with_metadata(
+        state.db.write(), user.id, &refresh_hash, expiry, Some(family_id),
+        device.as_deref(), ip.as_deref(),
+    ).await?;
+
+    Ok(RegisterResponse::Success(Box::new(AuthResponse {
+        access_token,
+        refresh_token,
+        user: user.into(),
+    })))
+}
+
+/// Set up a new account: its personal Haven server with #welcome and
+/// #general, and a DM from the Haven system user. Each step is best-effort:
+/// the account is usable without it.
+pub(crate) async fn provision_new_user(state: &AppState, user: &User) {
     // Create a personal Haven server and befriend the Haven system user
     if let Ok(Some(system_user)) = queries::find_system_user(state.db.read()).await {
         // Auto-friend the system user
@@ -382,47 +468,43 @@ pub async fn register(
             ).await;
         }
     }
-
-    // Generate tokens with a new token family
-    let family_id = Uuid::new_v4();
-    let access_token = auth::generate_access_token(user.id, &state.config)?;
-    let refresh_token = auth::generate_refresh_token();
-    let refresh_hash = auth::hash_refresh_token(&refresh_token);
-
-    let device = headers.get("user-agent").and_then(|v| v.to_str().ok()).map(parse_device_name);
-    let ip = extract_ip_from_headers(&headers, state.config.trust_proxy);
-    let expiry = Utc::now() + Duration::days(state.config.refresh_token_expiry_days);
-    queries::store_refresh_token_with_metadata(
-        state.db.write(), user.id, &refresh_hash, expiry, Some(family_id),
-        device.as_deref(), ip.as_deref(),
-    ).await?;
-
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token,
-        user: user.into(),
-    }))
 }
 
 /// POST /api/v1/auth/login
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login",
+    tag = "auth_routes",
+    request_body = LoginRequest,
+    responses((status = 200, body = LoginResponse)),
+    security(())
+)]
 pub async fn login(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(req): Json<LoginRequest>,
 ) -> AppResult<LoginResponse> {
+    let invalid_credentials =
+        || AppError::AuthError("Invalid username or password".into()).with_code("INVALID_CREDENTIALS");
+
     // Find user
     let user = queries::find_user_by_username(state.db.read(), &req.username)
         .await?
-        .ok_or(AppError::AuthError("Invalid username or password".into()))?;
+        .ok_or_else(invalid_credentials)?;
 
-    // System users cannot log in
-    if user.is_system {
-        return Err(AppError::AuthError("Invalid username or password".into()));
+    // System users and federated shadow accounts cannot log in
+    if user.is_system || crate::federation::is_remote_username(&user.username) {
+        return Err(invalid_credentials());
     }
 
     // Verify password
     if !auth::verify_password(&req.password, &user.password_hash)? {
-        return Err(AppError::AuthError("Invalid username or password".into()));
+        return Err(invalid_credentials());
+    }
+
+    if queries::is_registration_pending(state.db.read(), user.id).await? {
+        return Err(AppError::Forbidden("This account is awaiting approval by an operator".into())
+            .with_code("ACCOUNT_PENDING_APPROVAL"));
     }
 
     // Verify TOTP if enabled
@@ -434,7 +516,9 @@ pub async fn login(
             }
             Some(code) => {
                 if !auth::verify_totp(secret, code)? {
-                    return Err(AppError::AuthError("Invalid TOTP code".into()));
+                    return Err(
+                        AppError::AuthError("Invalid TOTP code".into()).with_code("INVALID_TOTP")
+                    );
                 }
             }
         }
@@ -465,6 +549,14 @@ pub async fn login(
 /// Implements token family rotation with theft detection:
 /// - If the token is valid and not revoked: rotate normally, mark old as revoked
 /// - If the token was already revoked (replayed): THEFT DETECTED — revoke entire family
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    tag = "auth_routes",
+    request_body = RefreshRequest,
+    responses((status = 200, body = AuthResponse)),
+    security(())
+)]
 pub async fn refresh_token(
     State(state): State<AppState>,
     Json(req): Json<RefreshRequest>,
@@ -525,6 +617,12 @@ pub async fn refresh_token(
 }
 
 /// POST /api/v1/auth/logout
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    tag = "auth_routes",
+    responses((status = 200))
+)]
 pub async fn logout(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -540,6 +638,12 @@ pub async fn logout(
 }
 
 /// GET /api/v1/auth/sessions — list active sessions for current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/sessions",
+    tag = "auth_routes",
+    responses((status = 200, body = Vec<SessionResponse>))
+)]
 pub async fn list_sessions(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -566,6 +670,13 @@ pub async fn list_sessions(
 }
 
 /// DELETE /api/v1/auth/sessions/:family_id — revoke a specific session
+#[utoipa::path(
+    delete,
+    path = "/api/v1/auth/sessions/{family_id}",
+    tag = "auth_routes",
+    params(("family_id" = Uuid, Path, description = "Family ID")),
+    responses((status = 200))
+)]
 pub async fn revoke_session(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -590,6 +701,12 @@ fn mask_ip(ip: &str) -> String {
 }
 
 /// POST /api/v1/auth/totp/setup
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/totp/setup",
+    tag = "auth_routes",
+    responses((status = 200, body = TotpSetupResponse))
+)]
 pub async fn totp_setup(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -617,6 +734,13 @@ pub async fn totp_setup(
 /// POST /api/v1/auth/totp/verify
 /// Verifies the user can produce a valid TOTP code, then promotes the
 /// pending secret to active.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/totp/verify",
+    tag = "auth_routes",
+    request_body = TotpVerifyRequest,
+    responses((status = 200))
+)]
 pub async fn totp_verify(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -633,7 +757,7 @@ pub async fn totp_verify(
         .ok_or(AppError::BadRequest("TOTP not set up".into()))?;
 
     if !auth::verify_totp(&secret, &req.code)? {
-        return Err(AppError::AuthError("Invalid TOTP code".into()));
+        return Err(AppError::AuthError("Invalid TOTP code".into()).with_code("INVALID_TOTP"));
     }
 
     // Promote pending secret to active (idempotent if already active)
@@ -643,6 +767,13 @@ pub async fn totp_verify(
 }
 
 /// PUT /api/v1/auth/password
+#[utoipa::path(
+    put,
+    path = "/api/v1/auth/password",
+    tag = "auth_routes",
+    request_body = ChangePasswordRequest,
+    responses((status = 200))
+)]
 pub async fn change_password(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -673,6 +804,12 @@ pub async fn change_password(
 }
 
 /// DELETE /api/v1/auth/totp
+#[utoipa::path(
+    delete,
+    path = "/api/v1/auth/totp",
+    tag = "auth_routes",
+    responses((status = 200))
+)]
 pub async fn totp_disable(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -681,7 +818,99 @@ pub async fn totp_disable(
     Ok(Json(serde_json::json!({ "message": "TOTP disabled" })))
 }
 
+/// Minutes an email verification code stays valid.
+const EMAIL_CODE_EXPIRY_MINUTES: i64 = 15;
+/// Seconds before another code may be sent.
+const EMAIL_CODE_COOLDOWN_SECS: i64 = 60;
+const EMAIL_CODE_MAX_ATTEMPTS: i32 = 5;
+
+/// POST /api/v1/auth/email/verify
+/// Send a verification code to the account's email address. Only a hash of
+/// the address is stored, so the client supplies it again; it must match.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/email/verify",
+    tag = "auth_routes",
+    request_body = SendEmailVerificationRequest,
+    responses((status = 204))
+)]
+pub async fn send_email_verification(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<SendEmailVerificationRequest>,
+) -> AppResult<StatusCode> {
+    let user = queries::find_user_by_id(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+    let email_hash = auth::hash_email(&req.email, &state.config.jwt_secret);
+    if user.email_hash.as_deref() != Some(email_hash.as_str()) {
+        return Err(AppError::Validation("That is not the email address of this account".into())
+            .with_code("EMAIL_MISMATCH"));
+    }
+    if queries::get_email_verified_at(state.db.read(), user_id).await?.is_some() {
+        return Err(AppError::Conflict("Email is already verified".into()));
+    }
+
+    let code = auth::generate_email_code();
+    let expires_at = Utc::now() + Duration::minutes(EMAIL_CODE_EXPIRY_MINUTES);
+    let code_hash = auth::hash_email_code(&code, &state.config.jwt_secret);
+    if !queries::store_email_verification_code(
+        state.db.write(),
+        user_id,
+        &code_hash,
+        expires_at,
+        EMAIL_CODE_COOLDOWN_SECS,
+    )
+    .await?
+    {
+        return Err(AppError::Validation("A code was sent recently; wait a minute before asking again".into())
+            .with_code("EMAIL_CODE_COOLDOWN"));
+    }
+
+    let branding = queries::get_instance_branding(state.db.read()).await?;
+    let mut vars = tera::Context::new();
+    vars.insert("code", &code);
+    vars.insert("expires_minutes", &EMAIL_CODE_EXPIRY_MINUTES);
+    let message = state
+        .email_templates
+        .render("email_verification", None, &branding, branding.has_logo, vars)?;
+    crate::email::mailer::enqueue(&state, "email_verification", req.email.trim().to_string(), message, branding.has_logo)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/auth/email/confirm
+/// Confirm the emailed code. A code allows five attempts.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/email/confirm",
+    tag = "auth_routes",
+    request_body = ConfirmEmailVerificationRequest,
+    responses((status = 200, body = EmailVerificationResponse))
+)]
+pub async fn confirm_email_verification(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<ConfirmEmailVerificationRequest>,
+) -> AppResult<Json<EmailVerificationResponse>> {
+    let code_hash = queries::take_email_verification_attempt(state.db.write(), user_id, EMAIL_CODE_MAX_ATTEMPTS)
+        .await?
+        .ok_or(AppError::BadRequest("No valid verification code; request a new one".into()))?;
+    if auth::hash_email_code(&req.code, &state.config.jwt_secret) != code_hash {
+        return Err(AppError::Validation("Invalid verification code".into()).with_code("INVALID_EMAIL_CODE"));
+    }
+    let verified_at = queries::mark_email_verified(state.db.write(), user_id).await?;
+    Ok(Json(EmailVerificationResponse { email_verified_at: Some(verified_at) }))
+}
+
 /// POST /api/v1/auth/delete-account — permanently delete the user's account
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/delete-account",
+    tag = "auth_routes",
+    request_body = DeleteAccountRequest,
+    responses((status = 204))
+)]
 pub async fn delete_account(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -696,6 +925,10 @@ pub async fn delete_account(
         return Err(AppError::AuthError("Incorrect password".into()));
     }
 
+    if queries::is_user_erasure_held(state.db.read(), user_id).await? {
+        return Err(crate::api::admin::legal_hold_error());
+    }
+
     // 1. Delete servers owned by this user (cascade removes channels, members, etc.)
     let owned_servers = queries::get_servers_owned_by(state.db.read(), user_id).await?;
     for server in &owned_servers {
@@ -714,11 +947,6 @@ pub async fn delete_account(
 
     // 2. Clean up message children that reference the partitioned messages table
     //    (no FK cascade on partitioned tables in PG < 17)
-    sqlx::query("DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE sender_id = $1)")
-        .bind(user_id)
-        .execute(state.db.write())
-        .await
-        .ok();
     sqlx::query("DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE sender_id = $1)")
         .bind(user_id)
         .execute(state.db.write())
@@ -765,6 +993,7 @@ pub async fn delete_account(
 
     // 7. Delete user (FK CASCADE handles server_members, channel_members,
     //    friendships, blocks, prekeys, key_backups, sender_key_distributions, etc.)
+    let profile_media = queries::list_profile_media(state.db.read(), user_id).await?;
     sqlx::query("DELETE FROM users WHERE id = $1")
         .bind(user_id)
         .execute(state.db.write())
@@ -772,10 +1001,7 @@ pub async fn delete_account(
         .map_err(AppError::Database)?;
 
     // 8. Clean up stored files (avatar, banner)
-    let avatar_key = storage::obfuscated_key(&state.storage_key, &format!("avatar:{}", user_id));
-    let banner_key = storage::obfuscated_key(&state.storage_key, &format!("banner:{}", user_id));
-    let _ = state.storage.delete_blob(&avatar_key).await;
-    let _ = state.storage.delete_blob(&banner_key).await;
+    crate::api::users::delete_profile_media_blobs(&state, user_id, &profile_media).await;
 
     // 9. Invalidate caches
     crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:user:{}", user_id)).await;
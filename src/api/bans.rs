@@ -13,6 +13,14 @@ use crate::AppState;
 
 /// POST /api/v1/servers/:server_id/bans/:target_user_id
 /// Ban a member from the server. Also kicks them if they are a member.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/bans/{user_id}",
+    tag = "bans",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = CreateBanRequest,
+    responses((status = 200, body = BanResponse))
+)]
 pub async fn ban_member(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -48,6 +56,8 @@ pub async fn ban_member(
 
     // Also kick them from the server if they are a member
     let _ = queries::remove_server_member(state.db.write(), server_id, target_user_id).await;
+    crate::api::servers::remove_member_profile(&state, server_id, target_user_id).await;
+    crate::api::sender_keys::require_rotation_for_server(&state, server_id, target_user_id).await;
 
     // Look up username for response
     let target = queries::find_user_basic_by_id(state.db.read(), target_user_id)
@@ -74,6 +84,13 @@ pub async fn ban_member(
 
 /// DELETE /api/v1/servers/:server_id/bans/:target_user_id
 /// Revoke a ban.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/bans/{user_id}",
+    tag = "bans",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn revoke_ban(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -100,6 +117,13 @@ pub async fn revoke_ban(
 
 /// GET /api/v1/servers/:server_id/bans
 /// List all bans for a server.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/bans",
+    tag = "bans",
+    params(("server_id" = Uuid, Path, description = "Server ID"), PaginationQuery),
+    responses((status = 200, body = Vec<BanResponse>))
+)]
 pub async fn list_bans(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
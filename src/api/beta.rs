@@ -1,18 +1,32 @@
-use axum::{extract::State, Json};
-use lettre::{
-    message::header::ContentType,
-    transport::smtp::authentication::Credentials,
-    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap},
+    Json,
 };
 use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
 use crate::db::queries;
+use crate::email;
+use crate::email::mailer;
 use crate::errors::{AppError, AppResult};
-use crate::models::{BetaCodeRequest, BetaCodeResponse};
+use crate::middleware::StaffUser;
+use crate::models::{
+    AdminBetaCode, AdminBetaCodeQuery, BetaCodeRequest, BetaCodeResponse, BetaInviteStats,
+    GenerateBetaCodesRequest, SetBetaCodeLimitRequest,
+};
+use crate::permissions;
 use crate::AppState;
 
+/// Most codes one bulk generation may create.
+const MAX_GENERATED_CODES: u32 = 500;
+
+const BETA_CODE_STATUSES: &[&str] = &["active", "redeemed", "expired", "revoked"];
+
 /// Hash an email address with SHA-256 for duplicate detection.
-/// Only the hash is stored — the email itself is never persisted.
+/// Only the hash is kept once a code is sent.
 fn hash_email(email: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(email.as_bytes());
@@ -24,28 +38,70 @@ fn hash_email(email: &str) -> String {
 /// Public endpoint (no auth required). Rate-limited to 3 req/min per IP.
 ///
 /// Privacy guarantee: the email address exists ONLY in the request body
-/// and the SMTP send buffer. Only a SHA-256 hash is stored for dedup.
+/// and the mail provider's send buffer. Only a SHA-256 hash is stored for
+/// dedup. The exceptions are kept encrypted with the server storage key:
+/// the outbox, until the email is sent; the waitlist, past the cap, until
+/// its code is sent; and an email that failed to send, until an operator
+/// retries or discards it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/beta/request-code",
+    tag = "beta",
+    request_body = BetaCodeRequest,
+    responses((status = 200, body = BetaCodeResponse)),
+    security(())
+)]
 pub async fn request_beta_code(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<BetaCodeRequest>,
 ) -> AppResult<Json<BetaCodeResponse>> {
-    // 1. Validate SMTP is configured
-    if !state.config.smtp_enabled() {
+    // Email settings and the cap can change on config reload
+    let config = state.live_config.get();
+
+    // 1. Validate email delivery is configured
+    if !config.email_enabled() {
         return Err(AppError::BadRequest(
             "Beta signups are not currently available".into(),
         ));
     }
 
-    // 2. Basic email validation (not exhaustive — just reject garbage)
+    // 2. Basic email validation (not exhaustive — just reject garbage), and
+    //    refuse disposable or undeliverable domains and requests scored as
+    //    abusive (see crate::abuse) so the cap isn't drained
     let email = req.email.trim().to_lowercase();
     if email.is_empty() || !email.contains('@') || email.len() > 254 {
         return Err(AppError::Validation("Invalid email address".into()));
     }
+    let Some(domain) = email::domains::domain_of(&email) else {
+        return Err(AppError::Validation("Invalid email address".into()));
+    };
+    if email::domains::is_blocked(domain, &config) {
+        return Err(
+            AppError::Validation("Please use a permanent email address".into())
+                .with_code("EMAIL_DOMAIN_BLOCKED"),
+        );
+    }
+    if config.beta_require_mx && !email::domains::accepts_mail(domain).await {
+        return Err(
+            AppError::Validation("This email domain can't receive mail".into())
+                .with_code("EMAIL_DOMAIN_UNDELIVERABLE"),
+        );
+    }
+    crate::abuse::assess(&state, "beta", &headers, peer.map(|ConnectInfo(addr)| addr)).await?;
+    let locale = req.locale.as_deref().and_then(email::normalize_locale).or_else(|| {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(email::preferred_locale)
+    });
 
-    // 3. Check if this email already received a beta code
+    // 3. Check if this email already received a beta code or is waiting for one
     let email_hash = hash_email(&email);
     let already_issued = queries::beta_code_exists_for_email(state.db.read(), &email_hash).await?;
-    if already_issued {
+    let waiting = queries::beta_waitlist_position(state.db.read(), &email_hash).await?;
+    if already_issued || waiting.is_some() {
         // Same generic response — don't reveal whether we recognized the email
         return Ok(Json(BetaCodeResponse {
             success: true,
@@ -53,9 +109,16 @@ pub async fn request_beta_code(
         }));
     }
 
-    // 4. Check global cap
+    // 4. Check global cap; past it, join the waitlist and say so by email only
     let issued = queries::count_beta_codes(state.db.read()).await?;
-    if issued >= state.config.beta_code_limit as i64 {
+    let (limit, _) = code_limit(&state).await?;
+    if issued >= limit as i64 {
+        let sealed = crate::storage::encrypt_blob(email.as_bytes(), &state.storage_key)
+            .map_err(|e| AppError::Internal(e.into()))?;
+        let position =
+            queries::join_beta_waitlist(state.db.write(), &email_hash, &sealed, locale.as_deref())
+                .await?;
+        queue_beta_email(&state, email, locale, BetaEmail::Waitlisted { position }).await?;
         return Ok(Json(BetaCodeResponse {
             success: true,
             message: "If slots are available, you'll receive a code shortly.".into(),
@@ -70,40 +133,14 @@ pub async fn request_beta_code(
     )
     .await?;
 
-    // 6. Send the email (fire-and-forget: spawn so we don't block the response)
-    let smtp_host = state.config.smtp_host.clone();
-    let smtp_port = state.config.smtp_port;
-    let smtp_username = state.config.smtp_username.clone();
-    let smtp_password = state.config.smtp_password.clone();
-    let smtp_from = state.config.smtp_from.clone();
-    let code = invite.code.clone();
-    let expiry_days = state.config.beta_code_expiry_days;
-
-    tokio::spawn(async move {
-        match send_beta_email(
-            &smtp_host,
-            smtp_port,
-            &smtp_username,
-            &smtp_password,
-            &smtp_from,
-            &email,
-            &code,
-            expiry_days,
-        )
-        .await
-        {
-            Ok(()) => {
-                tracing::info!("Beta code email sent successfully via {}", smtp_host);
-            }
-            Err(e) => {
-                tracing::error!("Failed to send beta code email via {}: {:?}", smtp_host, e);
-                // Note: the invite code was already created in the DB.
-                // We intentionally do NOT delete it on send failure — the code
-                // is still valid and the user could retry or contact support.
-            }
-        }
-        // After this block, `email` is dropped and gone forever.
-    });
+    // 6. Send the email (fire-and-forget: spawned so we don't block the response)
+    queue_beta_email(
+        &state,
+        email,
+        locale,
+        BetaEmail::Code { code: invite.code, expiry_days: state.config.beta_code_expiry_days },
+    )
+    .await?;
 
     // 7. Always return success (don't leak whether email was valid/duplicate)
     Ok(Json(BetaCodeResponse {
@@ -112,57 +149,283 @@ pub async fn request_beta_code(
     }))
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn send_beta_email(
-    smtp_host: &str,
-    smtp_port: u16,
-    smtp_username: &str,
-    smtp_password: &str,
-    smtp_from: &str,
-    to_email: &str,
-    code: &str,
-    expiry_days: i64,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let from_trimmed = smtp_from.trim().trim_matches('"');
-    let to_trimmed = to_email.trim();
-
-    let from_mailbox = from_trimmed.parse().map_err(|e| {
-        format!("Failed to parse From address '{}': {}", from_trimmed, e)
-    })?;
-    let to_mailbox = to_trimmed.parse().map_err(|e| {
-        format!("Failed to parse To address: {}", e)
-    })?;
-
-    let email = Message::builder()
-        .from(from_mailbox)
-        .to(to_mailbox)
-        .subject("Your Haven Beta Code")
-        .header(ContentType::TEXT_HTML)
-        .body(format!(
-            r#"<!DOCTYPE html>
-<html>
-<body style="font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #F5F0E8; padding: 40px 20px;">
-  <div style="max-width: 480px; margin: 0 auto; background: #fff; border-radius: 12px; padding: 40px; box-shadow: 0 2px 8px rgba(0,0,0,0.06);">
-    <h1 style="color: #1A1310; font-size: 24px; margin: 0 0 8px;">Welcome to Haven</h1>
-    <p style="color: #6F6358; margin: 0 0 24px;">Your beta access code is below. Use it when registering at Haven.</p>
-    <div style="background: #F5F0E8; border: 1px solid #D1C8BA; border-radius: 8px; padding: 16px; text-align: center; margin: 0 0 24px;">
-      <code style="font-size: 28px; font-weight: 700; color: #C2410C; letter-spacing: 2px;">{code}</code>
-    </div>
-    <p style="color: #8A7E73; font-size: 14px; margin: 0;">This code expires in {expiry_days} days and can only be used once.</p>
-    <hr style="border: none; border-top: 1px solid #D1C8BA; margin: 24px 0;" />
-    <p style="color: #8A7E73; font-size: 12px; margin: 0;">Haven &mdash; Privacy-first communication.<br/>This email was sent because someone requested a beta code. Your email is not stored.</p>
-  </div>
-</body>
-</html>"#,
-        ))?;
-
-    let creds = Credentials::new(smtp_username.to_owned(), smtp_password.to_owned());
-
-    let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)?
-        .port(smtp_port)
-        .credentials(creds)
-        .build();
-
-    mailer.send(email).await?;
-    Ok(())
+/// Issue codes to waitlisted emails while there is room under the cap, and
+/// email each its code. Runs on a schedule (codes expiring or being revoked
+/// free slots) and right after the cap is raised. Returns how many were
+/// promoted. Nothing is promoted while email is unconfigured.
+pub async fn promote_waitlist(state: &AppState) -> AppResult<u64> {
+    if !state.live_config.get().email_enabled() {
+        return Ok(0);
+    }
+    let (limit, _) = code_limit(state).await?;
+    let issued = queries::count_beta_codes(state.db.primary()).await?;
+    let open = limit as i64 - issued;
+    if open <= 0 {
+        return Ok(0);
+    }
+
+    let promoted =
+        queries::promote_beta_waitlist(state.db.primary(), open, state.config.beta_code_expiry_days)
+            .await?;
+    let count = promoted.len() as u64;
+    for (sealed, code, locale) in promoted {
+        let email = match crate::storage::decrypt_blob(&sealed, &state.storage_key)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+        {
+            Some(email) => email,
+            None => {
+                tracing::error!("Could not decrypt a waitlisted beta email; its code was not sent");
+                continue;
+            }
+        };
+        let kind = BetaEmail::Code { code, expiry_days: state.config.beta_code_expiry_days };
+        queue_beta_email(state, email, locale, kind).await?;
+    }
+    Ok(count)
+}
+
+/// Render a beta email and queue it for delivery. The address is kept
+/// encrypted in the outbox until the email is sent, then deleted. A code
+/// already issued stays valid either way.
+async fn queue_beta_email(
+    state: &AppState,
+    email: String,
+    locale: Option<String>,
+    kind: BetaEmail,
+) -> AppResult<()> {
+    let branding = queries::get_instance_branding(state.db.read()).await?;
+    let (template, vars) = kind.template();
+    let message = state
+        .email_templates
+        .render(template, locale.as_deref(), &branding, branding.has_logo, vars)?;
+    mailer::enqueue(state, template, email, message, branding.has_logo).await
+}
+
+/// The cap on beta codes and whether it is the operator override rather
+/// than `BETA_CODE_LIMIT`.
+pub(crate) async fn code_limit(state: &AppState) -> AppResult<(u32, bool)> {
+    Ok(match queries::get_beta_code_limit_override(state.db.read()).await? {
+        Some(limit) => (limit.max(0) as u32, true),
+        None => (state.live_config.get().beta_code_limit, false),
+    })
+}
+
+// ─── Operator Management ───────────────────────────────
+
+/// GET /api/v1/admin/beta/codes
+/// List beta codes, newest first. Emailed codes show only the email's hash.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/beta/codes",
+    tag = "beta",
+    params(AdminBetaCodeQuery),
+    responses((status = 200, body = Vec<AdminBetaCode>))
+)]
+pub async fn list_codes(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Query(params): Query<AdminBetaCodeQuery>,
+) -> AppResult<Json<Vec<AdminBetaCode>>> {
+    staff.require(permissions::INSTANCE_VIEW_INVITES)?;
+    if let Some(status) = params.status.as_deref() {
+        if !BETA_CODE_STATUSES.contains(&status) {
+            return Err(AppError::Validation(format!(
+                "status must be one of: {}",
+                BETA_CODE_STATUSES.join(", ")
+            )));
+        }
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let codes =
+        queries::list_beta_codes(state.db.read(), params.status.as_deref(), limit, offset).await?;
+    Ok(Json(codes))
+}
+
+/// POST /api/v1/admin/beta/codes
+/// Generate a batch of beta codes for an event. Generated codes are handed
+/// out by the operator, so they are not held to the cap.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/beta/codes",
+    tag = "beta",
+    request_body = GenerateBetaCodesRequest,
+    responses((status = 200, body = Vec<AdminBetaCode>))
+)]
+pub async fn generate_codes(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<GenerateBetaCodesRequest>,
+) -> AppResult<Json<Vec<AdminBetaCode>>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    if req.count == 0 || req.count > MAX_GENERATED_CODES {
+        return Err(AppError::Validation(format!(
+            "count must be between 1 and {}",
+            MAX_GENERATED_CODES
+        )));
+    }
+    let expiry_days = req.expiry_days.unwrap_or(state.config.beta_code_expiry_days);
+    if !(1..=365).contains(&expiry_days) {
+        return Err(AppError::Validation("expiry_days must be between 1 and 365".into()));
+    }
+    let label = req.label.as_deref().map(str::trim).filter(|l| !l.is_empty());
+    if label.is_some_and(|l| l.len() > 100) {
+        return Err(AppError::Validation("label must be at most 100 characters".into()));
+    }
+
+    let codes =
+        queries::create_labeled_beta_codes(state.db.write(), req.count, expiry_days, label).await?;
+    crate::api::admin::record_staff_action(
+        &state, &staff, "beta_codes_generate",
+        None, None,
+        Some(&serde_json::json!({ "count": codes.len(), "label": label, "expiry_days": expiry_days })),
+        None,
+    ).await;
+    Ok(Json(codes))
+}
+
+/// POST /api/v1/admin/beta/codes/:invite_id/revoke
+/// Revoke an unredeemed beta code. The code stays listed, and an emailed
+/// code still counts against its email, which can't request another.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/beta/codes/{invite_id}/revoke",
+    tag = "beta",
+    params(("invite_id" = Uuid, Path, description = "Invite ID")),
+    responses((status = 200))
+)]
+pub async fn revoke_code(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(invite_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    if !queries::revoke_beta_code(state.db.write(), invite_id).await? {
+        return Err(AppError::NotFound(
+            "Beta code not found, already redeemed or already revoked".into(),
+        ));
+    }
+    // The revoked code's slot goes to the waitlist
+    promote_waitlist(&state).await?;
+    crate::api::admin::record_staff_action(
+        &state, &staff, "beta_code_revoke",
+        Some("registration_invite"), Some(invite_id), None, None,
+    ).await;
+    Ok(Json(serde_json::json!({ "revoked": true })))
+}
+
+/// PUT /api/v1/admin/beta/limit
+/// Change the cap on emailed beta codes without a config change. The
+/// override is stored in the database, so it applies to every instance and
+/// survives restarts; `null` goes back to `BETA_CODE_LIMIT`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/beta/limit",
+    tag = "beta",
+    request_body = SetBetaCodeLimitRequest,
+    responses((status = 200, body = BetaInviteStats))
+)]
+pub async fn set_code_limit(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<SetBetaCodeLimitRequest>,
+) -> AppResult<Json<BetaInviteStats>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
+    let limit = req
+        .limit
+        .map(i32::try_from)
+        .transpose()
+        .map_err(|_| AppError::Validation("limit is too large".into()))?;
+    queries::set_beta_code_limit_override(state.db.write(), limit, staff.user_id).await?;
+    promote_waitlist(&state).await?;
+    crate::api::admin::record_staff_action(
+        &state, &staff, "beta_limit_set",
+        None, None,
+        Some(&serde_json::json!({ "limit": limit })), None,
+    ).await;
+    Ok(Json(crate::api::admin::beta_stats(&state).await?))
+}
+
+/// What a beta email is for.
+enum BetaEmail {
+    /// A code, issued on request or on promotion from the waitlist.
+    Code { code: String, expiry_days: i64 },
+    /// Confirmation of a place on the waitlist.
+    Waitlisted { position: i64 },
+}
+
+impl BetaEmail {
+    /// Template name and variables for this email.
+    fn template(&self) -> (&'static str, tera::Context) {
+        let mut vars = tera::Context::new();
+        match self {
+            BetaEmail::Code { code, expiry_days } => {
+                vars.insert("code", code);
+                vars.insert("expiry_days", expiry_days);
+                ("beta_code", vars)
+            }
+            BetaEmail::Waitlisted { position } => {
+                vars.insert("position", position);
+                ("beta_waitlist", vars)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::{EmailTemplates, RenderedEmail};
+    use crate::models::InstanceBranding;
+
+    fn branding() -> InstanceBranding {
+        InstanceBranding {
+            name: "Acme <Chat>".into(),
+            has_logo: false,
+            accent_color: "#123456".into(),
+            background_color: "#ABCDEF".into(),
+            terms_url: Some("https://acme.example/terms".into()),
+            privacy_url: None,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn code_email() -> BetaEmail {
+        BetaEmail::Code { code: "CODE123".into(), expiry_days: 7 }
+    }
+
+    fn render(kind: &BetaEmail, inline_logo: bool) -> RenderedEmail {
+        let (template, vars) = kind.template();
+        EmailTemplates::builtin().render(template, None, &branding(), inline_logo, vars).unwrap()
+    }
+
+    #[test]
+    fn beta_email_uses_instance_branding() {
+        let RenderedEmail { subject, html } = render(&code_email(), false);
+        assert_eq!(subject, "Your Acme <Chat> Beta Code");
+        assert!(html.contains("Welcome to Acme &lt;Chat&gt;"));
+        assert!(html.contains("CODE123"));
+        assert!(html.contains("color: #123456"));
+        assert!(html.contains("background: #ABCDEF"));
+        assert!(html.contains(r#"href="https://acme.example/terms""#));
+        assert!(!html.contains("Privacy</a>"));
+        assert!(!html.contains("Haven"));
+        assert!(!html.contains("cid:"));
+    }
+
+    #[test]
+    fn beta_email_references_inline_logo() {
+        let html = render(&code_email(), true).html;
+        assert!(html.contains("cid:instance-logo"));
+    }
+
+    #[test]
+    fn waitlist_email_shows_position_and_storage_note() {
+        let RenderedEmail { subject, html } = render(&BetaEmail::Waitlisted { position: 12 }, false);
+        assert_eq!(subject, "You're on the Acme <Chat> Beta Waitlist");
+        assert!(html.contains(">12</strong>"));
+        assert!(html.contains("kept encrypted until your code is sent"));
+        assert!(!html.contains("<code"));
+    }
 }
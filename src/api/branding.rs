@@ -0,0 +1,314 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+
+use crate::api::admin::record_staff_action;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::StaffUser;
+use crate::models::{InstanceBranding, InstanceBrandingResponse, UpdateBrandingRequest};
+use crate::permissions;
+use crate::storage;
+use crate::AppState;
+
+const MAX_LOGO_SIZE: usize = 2 * 1024 * 1024; // 2MB
+const MAX_NAME_LEN: usize = 64;
+const MAX_URL_LEN: usize = 2048;
+
+fn logo_storage_key(state: &AppState) -> String {
+    storage::obfuscated_key(&state.storage_key, "instance-logo")
+}
+
+fn to_response(branding: InstanceBranding) -> InstanceBrandingResponse {
+    InstanceBrandingResponse {
+        logo_url: branding.has_logo.then(|| "/api/v1/instance/branding/logo".to_string()),
+        name: branding.name,
+        accent_color: branding.accent_color,
+        background_color: branding.background_color,
+        terms_url: branding.terms_url,
+        privacy_url: branding.privacy_url,
+        updated_at: branding.updated_at,
+    }
+}
+
+/// GET /api/v1/instance/branding — public, so login and registration pages can brand themselves
+#[utoipa::path(
+    get,
+    path = "/api/v1/instance/branding",
+    tag = "branding",
+    responses((status = 200, body = InstanceBrandingResponse)),
+    security(())
+)]
+pub async fn get_branding(State(state): State<AppState>) -> AppResult<Json<InstanceBrandingResponse>> {
+    let branding = queries::get_instance_branding(state.db.read()).await?;
+    Ok(Json(to_response(branding)))
+}
+
+/// GET /api/v1/instance/branding/logo — serve the instance logo (no auth for <img> src)
+#[utoipa::path(
+    get,
+    path = "/api/v1/instance/branding/logo",
+    tag = "branding",
+    responses((status = 200)),
+    security(())
+)]
+pub async fn get_logo(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+    let branding = queries::get_instance_branding(state.db.read()).await?;
+    if !branding.has_logo {
+        return Err(AppError::NotFound("No logo set".into()));
+    }
+
+    let storage_key = logo_storage_key(&state);
+    if state.config.cdn_enabled {
+        if let Some(url) = state.storage.presign_url(
+            &storage_key,
+            state.config.cdn_presign_expiry_secs,
+            &state.config.cdn_base_url,
+        ).await {
+            return Ok(Redirect::temporary(&url).into_response());
+        }
+    }
+
+    let (data, content_type) = load_logo(&state)
+        .await
+        .ok_or(AppError::NotFound("Logo file not found".into()))?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "public, max-age=3600".to_string()),
+        ],
+        data,
+    ).into_response())
+}
+
+/// PUT /api/v1/admin/branding
+/// Update the instance name, colors and legal URLs. Operator only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/branding",
+    tag = "branding",
+    request_body = UpdateBrandingRequest,
+    responses((status = 200, body = InstanceBrandingResponse))
+)]
+pub async fn update_branding(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<UpdateBrandingRequest>,
+) -> AppResult<Json<InstanceBrandingResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRANDING)?;
+
+    let mut branding = queries::get_instance_branding(state.db.read()).await?;
+    if let Some(name) = req.name {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(AppError::Validation(format!(
+                "Instance name must be 1-{} characters",
+                MAX_NAME_LEN
+            )));
+        }
+        branding.name = name.to_string();
+    }
+    if let Some(color) = req.accent_color {
+        branding.accent_color = validate_color("accent_color", &color)?;
+    }
+    if let Some(color) = req.background_color {
+        branding.background_color = validate_color("background_color", &color)?;
+    }
+    if let Some(url) = req.terms_url {
+        branding.terms_url = validate_url("terms_url", &url)?;
+    }
+    if let Some(url) = req.privacy_url {
+        branding.privacy_url = validate_url("privacy_url", &url)?;
+    }
+
+    let branding = queries::update_instance_branding(state.db.write(), &branding, staff.user_id).await?;
+
+    record_staff_action(
+        &state, &staff, "branding_update", None, None,
+        Some(&serde_json::json!({
+            "name": branding.name,
+            "accent_color": branding.accent_color,
+            "background_color": branding.background_color,
+            "terms_url": branding.terms_url,
+            "privacy_url": branding.privacy_url,
+        })),
+        req.reason.as_deref(),
+    ).await;
+
+    Ok(Json(to_response(branding)))
+}
+
+/// PUT /api/v1/admin/branding/logo — replace the instance logo (binary body). Operator only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/branding/logo",
+    tag = "branding",
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200, body = InstanceBrandingResponse))
+)]
+pub async fn upload_logo(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> AppResult<Json<InstanceBrandingResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRANDING)?;
+
+    if body.is_empty() {
+        return Err(AppError::Validation("No image data provided".into()));
+    }
+    if body.len() > MAX_LOGO_SIZE {
+        return Err(AppError::Validation(format!(
+            "Logo too large (max {}MB)",
+            MAX_LOGO_SIZE / 1024 / 1024
+        )));
+    }
+    if detect_image_type(&body).is_none() {
+        return Err(AppError::Validation("Logo must be a PNG, JPEG, GIF or WebP image".into()));
+    }
+
+    let storage_key = logo_storage_key(&state);
+    if state.config.cdn_enabled {
+        state.storage.store_blob_raw(&storage_key, &body).await
+            .map_err(|e| AppError::BadRequest(format!("Failed to store logo: {}", e)))?;
+    } else {
+        state.storage.store_blob(&storage_key, &body).await
+            .map_err(|e| AppError::BadRequest(format!("Failed to store logo: {}", e)))?;
+    }
+
+    let mut branding = queries::get_instance_branding(state.db.read()).await?;
+    branding.has_logo = true;
+    let branding = queries::update_instance_branding(state.db.write(), &branding, staff.user_id).await?;
+
+    record_staff_action(&state, &staff, "branding_logo_update", None, None, None, None).await;
+
+    Ok(Json(to_response(branding)))
+}
+
+/// DELETE /api/v1/admin/branding/logo — remove the instance logo. Operator only.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/branding/logo",
+    tag = "branding",
+    responses((status = 200, body = InstanceBrandingResponse))
+)]
+pub async fn delete_logo(
+    staff: StaffUser,
+    State(state): State<AppState>,
+) -> AppResult<Json<InstanceBrandingResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRANDING)?;
+
+    let mut branding = queries::get_instance_branding(state.db.read()).await?;
+    if !branding.has_logo {
+        return Ok(Json(to_response(branding)));
+    }
+    branding.has_logo = false;
+    let branding = queries::update_instance_branding(state.db.write(), &branding, staff.user_id).await?;
+    if let Err(e) = state.storage.delete_blob(&logo_storage_key(&state)).await {
+        tracing::warn!("Failed to delete instance logo blob: {}", e);
+    }
+
+    record_staff_action(&state, &staff, "branding_logo_delete", None, None, None, None).await;
+
+    Ok(Json(to_response(branding)))
+}
+
+/// Load the stored logo and its content type, if one is set and readable.
+pub async fn load_logo(state: &AppState) -> Option<(Vec<u8>, String)> {
+    let storage_key = logo_storage_key(state);
+    let data = if state.config.cdn_enabled {
+        state.storage.load_blob_raw(&storage_key).await.ok()?
+    } else {
+        state.storage.load_blob(&storage_key).await.ok()?
+    };
+    let content_type = detect_image_type(&data)?.to_string();
+    Some((data, content_type))
+}
+
+/// Escape operator-provided text for interpolation into email HTML.
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn validate_color(field: &str, color: &str) -> AppResult<String> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err(AppError::Validation(format!("{} must be a hex color like #C2410C", field)));
+    }
+    Ok(color.to_ascii_uppercase())
+}
+
+/// Empty clears the URL.
+fn validate_url(field: &str, url: &str) -> AppResult<Option<String>> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Ok(None);
+    }
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(AppError::Validation(format!("{} must start with http:// or https://", field)));
+    }
+    if url.len() > MAX_URL_LEN || url.chars().any(|c| c.is_whitespace() || c == '"' || c == '<' || c == '>') {
+        return Err(AppError::Validation(format!("{} is not a valid URL", field)));
+    }
+    Ok(Some(url.to_string()))
+}
+
+fn detect_image_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.starts_with(b"RIFF") && data.len() > 12 && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_must_be_six_digit_hex() {
+        assert_eq!(validate_color("c", "#c2410c").unwrap(), "#C2410C");
+        assert!(validate_color("c", "C2410C").is_err());
+        assert!(validate_color("c", "#fff").is_err());
+        assert!(validate_color("c", "#GGGGGG").is_err());
+    }
+
+    #[test]
+    fn urls_must_be_http_and_empty_clears() {
+        assert_eq!(validate_url("u", "").unwrap(), None);
+        assert_eq!(
+            validate_url("u", " https://example.com/terms ").unwrap().as_deref(),
+            Some("https://example.com/terms")
+        );
+        assert!(validate_url("u", "javascript:alert(1)").is_err());
+        assert!(validate_url("u", "https://example.com/\"><script>").is_err());
+    }
+
+    #[test]
+    fn escape_html_escapes_markup() {
+        assert_eq!(escape_html("<b>A & 'B'</b>"), "&lt;b&gt;A &amp; &#39;B&#39;&lt;/b&gt;");
+    }
+}
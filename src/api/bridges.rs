@@ -0,0 +1,366 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::api::admin::record_staff_action;
+use crate::auth::{generate_bridge_token, hash_refresh_token};
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::{AuthUser, BridgeAuth, StaffUser};
+use crate::models::*;
+use crate::permissions;
+use crate::quota;
+use crate::ws::deliver_new_message;
+use crate::AppState;
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_PREFIX_LEN: usize = 16;
+const MAX_REMOTE_ID_LEN: usize = 255;
+const MAX_DISPLAY_NAME_LEN: usize = 64;
+const MAX_BODY_LEN: usize = 8192;
+const DEFAULT_EVENT_LIMIT: i64 = 100;
+const MAX_EVENT_LIMIT: i64 = 500;
+
+/// Queue a new message for any bridges linked to its channel. Failures are
+/// logged, never surfaced to the sender.
+pub async fn enqueue_message(state: &AppState, message: &Message) {
+    if let Err(e) =
+        queries::enqueue_bridge_events(state.db.write(), message.channel_id, message.id, message.sender_id).await
+    {
+        tracing::warn!("Failed to queue message {} for bridges: {}", message.id, e);
+    }
+}
+
+// ─── Operator management ─────────────────────────────
+
+/// POST /api/v1/admin/bridges
+/// Register a bridge. The token is returned once and only its hash is kept.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/bridges",
+    tag = "bridges",
+    request_body = CreateBridgeRequest,
+    responses((status = 200, body = CreateBridgeResponse))
+)]
+pub async fn create_bridge(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Json(req): Json<CreateBridgeRequest>,
+) -> AppResult<Json<CreateBridgeResponse>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRIDGES)?;
+
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(AppError::Validation(format!("Bridge name must be 1-{} characters", MAX_NAME_LEN)));
+    }
+    if req.user_prefix.len() < 2
+        || req.user_prefix.len() > MAX_PREFIX_LEN
+        || validate_username(&req.user_prefix).is_err()
+    {
+        return Err(AppError::Validation(format!(
+            "User prefix must be 2-{} letters, digits, '_' or '-'",
+            MAX_PREFIX_LEN
+        )));
+    }
+
+    if let Some(server_id) = req.server_id {
+        queries::find_server_by_id(state.db.read(), server_id)
+            .await?
+            .ok_or(AppError::NotFound("Server not found".into()))?;
+    }
+
+    let token = generate_bridge_token();
+    let bridge = queries::create_bridge(
+        state.db.write(),
+        name,
+        &hash_refresh_token(&token),
+        &req.user_prefix,
+        staff.user_id,
+        req.server_id,
+    )
+    .await?;
+
+    record_staff_action(
+        &state, &staff, "bridge_create", Some("bridge"), Some(bridge.id),
+        Some(&serde_json::json!({
+            "name": bridge.name,
+            "user_prefix": bridge.user_prefix,
+            "server_id": bridge.server_id,
+        })),
+        None,
+    )
+    .await;
+
+    Ok(Json(CreateBridgeResponse { bridge, token }))
+}
+
+/// GET /api/v1/admin/bridges
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/bridges",
+    tag = "bridges",
+    responses((status = 200, body = Vec<Bridge>))
+)]
+pub async fn list_bridges(staff: StaffUser, State(state): State<AppState>) -> AppResult<Json<Vec<Bridge>>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRIDGES)?;
+    Ok(Json(queries::list_bridges(state.db.read()).await?))
+}
+
+/// DELETE /api/v1/admin/bridges/:bridge_id
+/// Revoke a bridge's token and all its channel links. Puppet accounts remain.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/bridges/{bridge_id}",
+    tag = "bridges",
+    params(("bridge_id" = Uuid, Path, description = "Bridge ID")),
+    responses((status = 200))
+)]
+pub async fn delete_bridge(
+    staff: StaffUser,
+    State(state): State<AppState>,
+    Path(bridge_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_BRIDGES)?;
+
+    if !queries::delete_bridge(state.db.write(), bridge_id).await? {
+        return Err(AppError::NotFound("Bridge not found".into()));
+    }
+    record_staff_action(&state, &staff, "bridge_delete", Some("bridge"), Some(bridge_id), None, None).await;
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+// ─── Channel links ───────────────────────────────────
+
+/// Bridges are linked per server channel by someone who can manage it.
+/// Returns the channel's server.
+async fn require_link_permission(state: &AppState, channel_id: Uuid, user_id: Uuid) -> AppResult<Uuid> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    let server_id = channel
+        .server_id
+        .ok_or(AppError::Validation("Only server channels can be bridged".into()))?;
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_CHANNELS).await?;
+    Ok(server_id)
+}
+
+/// GET /api/v1/channels/:channel_id/bridges
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/bridges",
+    tag = "bridges",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<Bridge>))
+)]
+pub async fn list_channel_bridges(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<Vec<Bridge>>> {
+    require_link_permission(&state, channel_id, user_id).await?;
+    Ok(Json(queries::get_channel_bridges(state.db.read(), channel_id).await?))
+}
+
+/// PUT /api/v1/channels/:channel_id/bridges/:bridge_id
+/// Let a bridge read and post in this channel.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/bridges/{bridge_id}",
+    tag = "bridges",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("bridge_id" = Uuid, Path, description = "Bridge ID")),
+    responses((status = 200))
+)]
+pub async fn link_channel(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, bridge_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let server_id = require_link_permission(&state, channel_id, user_id).await?;
+    let bridge = queries::find_bridge_by_id(state.db.read(), bridge_id)
+        .await?
+        .ok_or(AppError::NotFound("Bridge not found".into()))?;
+    // A server's managers may only link bridges registered to that server;
+    // instance-wide bridges are linked by the operator who registered them.
+    if bridge.server_id != Some(server_id) && bridge.created_by != Some(user_id) {
+        return Err(AppError::Forbidden("Bridge is not registered to this server".into()));
+    }
+
+    queries::link_bridge_channel(state.db.write(), bridge_id, channel_id, user_id).await?;
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+/// DELETE /api/v1/channels/:channel_id/bridges/:bridge_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}/bridges/{bridge_id}",
+    tag = "bridges",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("bridge_id" = Uuid, Path, description = "Bridge ID")),
+    responses((status = 200))
+)]
+pub async fn unlink_channel(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, bridge_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<serde_json::Value>> {
+    require_link_permission(&state, channel_id, user_id).await?;
+    if !queries::unlink_bridge_channel(state.db.write(), bridge_id, channel_id).await? {
+        return Err(AppError::NotFound("Bridge is not linked to this channel".into()));
+    }
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+// ─── Bridge API (bridge token) ───────────────────────
+
+/// GET /api/v1/bridge/whoami
+#[utoipa::path(
+    get,
+    path = "/api/v1/bridge/whoami",
+    tag = "bridges",
+    responses((status = 200, body = Bridge)),
+    security(("bridge_token" = []))
+)]
+pub async fn whoami(BridgeAuth(bridge): BridgeAuth) -> Json<Bridge> {
+    Json(bridge)
+}
+
+/// PUT /api/v1/bridge/puppets
+/// Create or update the puppet standing in for a user on the bridged network.
+/// Its username is the bridge's prefix plus `localpart`.
+#[utoipa::path(
+    put,
+    path = "/api/v1/bridge/puppets",
+    tag = "bridges",
+    request_body = CreatePuppetRequest,
+    responses((status = 200, body = UserPublic)),
+    security(("bridge_token" = []))
+)]
+pub async fn upsert_puppet(
+    State(state): State<AppState>,
+    BridgeAuth(bridge): BridgeAuth,
+    Json(req): Json<CreatePuppetRequest>,
+) -> AppResult<Json<UserPublic>> {
+    if req.remote_id.is_empty() || req.remote_id.len() > MAX_REMOTE_ID_LEN {
+        return Err(AppError::Validation(format!("remote_id must be 1-{} bytes", MAX_REMOTE_ID_LEN)));
+    }
+    let username = format!("{}{}", bridge.user_prefix, req.localpart);
+    if username.len() < 3 || username.len() > 32 || validate_username(&username).is_err() {
+        return Err(AppError::Validation(
+            "Puppet username must be 3-32 letters, digits, '_' or '-' including the bridge prefix".into(),
+        ));
+    }
+    let display_name = req.display_name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if display_name.is_some_and(|n| n.chars().count() > MAX_DISPLAY_NAME_LEN) {
+        return Err(AppError::Validation(format!(
+            "Display name must be at most {} characters",
+            MAX_DISPLAY_NAME_LEN
+        )));
+    }
+
+    let user = queries::upsert_bridge_puppet(state.db.write(), bridge.id, &req.remote_id, &username, display_name)
+        .await?;
+    Ok(Json(UserPublic::from(user)))
+}
+
+/// POST /api/v1/bridge/messages
+/// Post into a linked channel as one of this bridge's puppets.
+#[utoipa::path(
+    post,
+    path = "/api/v1/bridge/messages",
+    tag = "bridges",
+    request_body = BridgeSendMessageRequest,
+    responses((status = 200, body = MessageResponse)),
+    security(("bridge_token" = []))
+)]
+pub async fn send_message(
+    State(state): State<AppState>,
+    BridgeAuth(bridge): BridgeAuth,
+    Json(req): Json<BridgeSendMessageRequest>,
+) -> AppResult<Json<MessageResponse>> {
+    let puppet = queries::find_bridge_puppet(state.db.read(), bridge.id, req.sender_id)
+        .await?
+        .ok_or(AppError::Forbidden("Not a puppet of this bridge".into()))?;
+
+    if !queries::is_bridge_linked(state.db.read(), bridge.id, req.channel_id).await? {
+        return Err(AppError::Forbidden("Bridge is not linked to this channel".into()));
+    }
+    if let Some(server_id) = queries::find_channel_by_id(state.db.read(), req.channel_id)
+        .await?
+        .and_then(|channel| channel.server_id)
+    {
+        quota::record_message(&state, server_id).await?;
+    }
+    if let Some(reply_to_id) = req.reply_to_id {
+        let target = queries::find_message_by_id(state.db.read(), reply_to_id).await?;
+        if target.map(|m| m.channel_id) != Some(req.channel_id) {
+            return Err(AppError::Validation("reply_to_id must reference a message in this channel".into()));
+        }
+    }
+
+    let sender_token = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &req.sender_token)
+        .map_err(|_| AppError::Validation("Invalid sender_token encoding".into()))?;
+    let encrypted_body = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &req.encrypted_body)
+        .map_err(|_| AppError::Validation("Invalid encrypted_body encoding".into()))?;
+    if encrypted_body.len() > MAX_BODY_LEN {
+        return Err(AppError::Validation("Message too large".into()));
+    }
+
+    // Backdating keeps imported history in order; future stamps would pin a
+    // message to the bottom of the channel.
+    let now = Utc::now();
+    let timestamp = match req.timestamp {
+        Some(ts) if ts > now + Duration::minutes(5) => {
+            return Err(AppError::Validation("timestamp cannot be in the future".into()));
+        }
+        Some(ts) => ts,
+        None => now,
+    };
+
+    let message = queries::insert_bridged_message(
+        state.db.write(),
+        req.channel_id,
+        puppet.id,
+        &sender_token,
+        &encrypted_body,
+        timestamp,
+        req.reply_to_id,
+    )
+    .await?;
+
+    let response = deliver_new_message(&state, message).await?;
+    Ok(Json(response))
+}
+
+/// GET /api/v1/bridge/events?since=&limit=
+/// New messages in linked channels, oldest first, excluding this bridge's own
+/// puppets. Passing `since` acknowledges everything up to it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/bridge/events",
+    tag = "bridges",
+    params(BridgeEventsQuery),
+    responses((status = 200, body = BridgeEventsResponse)),
+    security(("bridge_token" = []))
+)]
+pub async fn get_events(
+    State(state): State<AppState>,
+    BridgeAuth(bridge): BridgeAuth,
+    Query(query): Query<BridgeEventsQuery>,
+) -> AppResult<Json<BridgeEventsResponse>> {
+    let since = query.since.unwrap_or(0).max(0);
+    let limit = query.limit.unwrap_or(DEFAULT_EVENT_LIMIT).clamp(1, MAX_EVENT_LIMIT);
+
+    let (batch, next_since) = queries::take_bridge_events(state.db.write(), bridge.id, since, limit).await?;
+    let events = batch
+        .into_iter()
+        .map(|(seq, message)| BridgeEvent {
+            seq,
+            channel_id: message.channel_id,
+            message: message.into(),
+        })
+        .collect();
+
+    Ok(Json(BridgeEventsResponse { events, next_since }))
+}
@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::{CallStartResponse, TurnCredentials};
+use crate::voice::turn;
+use crate::AppState;
+
+/// POST /api/v1/channels/:channel_id/calls
+///
+/// Ring the other participant of a DM. The peer receives `CallRinging` over
+/// WebSocket; accept/reject/end continue over WebSocket as before.
+/// Returns TURN credentials for the caller when a relay is configured.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/calls",
+    tag = "calls",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = CallStartResponse))
+)]
+pub async fn start_call(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<CallStartResponse>> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+
+    if channel.channel_type != "dm" {
+        return Err(AppError::BadRequest("Only direct messages support one-to-one calls".into()));
+    }
+
+    crate::ws::start_call(&state, user_id, &channel).await?;
+
+    Ok(Json(CallStartResponse {
+        channel_id,
+        turn: turn::mint_credentials(&state.config, user_id),
+    }))
+}
+
+/// GET /api/v1/voice/turn-credentials
+///
+/// Mint short-lived TURN credentials (e.g. for the callee after accepting).
+#[utoipa::path(
+    get,
+    path = "/api/v1/voice/turn-credentials",
+    tag = "calls",
+    responses((status = 200, body = TurnCredentials))
+)]
+pub async fn get_turn_credentials(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<TurnCredentials>> {
+    turn::mint_credentials(&state.config, user_id)
+        .map(Json)
+        .ok_or(AppError::NotFound("TURN relay is not configured on this instance".into()))
+}
@@ -13,6 +13,13 @@ use crate::ws::broadcast_to_server;
 use crate::AppState;
 
 /// GET /api/v1/servers/:server_id/categories
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/categories",
+    tag = "categories",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<CategoryResponse>))
+)]
 pub async fn list_categories(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -28,6 +35,14 @@ pub async fn list_categories(
 }
 
 /// POST /api/v1/servers/:server_id/categories
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/categories",
+    tag = "categories",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateCategoryRequest,
+    responses((status = 200, body = CategoryResponse))
+)]
 pub async fn create_category(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -51,6 +66,14 @@ pub async fn create_category(
 }
 
 /// PUT /api/v1/servers/:server_id/categories/reorder
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/categories/reorder",
+    tag = "categories",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = ReorderCategoriesRequest,
+    responses((status = 200))
+)]
 pub async fn reorder_categories(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -74,6 +97,14 @@ pub async fn reorder_categories(
 }
 
 /// PUT /api/v1/servers/:server_id/categories/:category_id
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/categories/{category_id}",
+    tag = "categories",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("category_id" = Uuid, Path, description = "Category ID")),
+    request_body = UpdateCategoryRequest,
+    responses((status = 200, body = CategoryResponse))
+)]
 pub async fn update_category(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -100,6 +131,7 @@ pub async fn update_category(
         category_id,
         req.name.as_deref(),
         req.position,
+        req.collapsed_by_default,
     )
     .await?;
 
@@ -109,6 +141,13 @@ pub async fn update_category(
 }
 
 /// DELETE /api/v1/servers/:server_id/categories/:category_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/categories/{category_id}",
+    tag = "categories",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("category_id" = Uuid, Path, description = "Category ID")),
+    responses((status = 200))
+)]
 pub async fn delete_category(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -137,6 +176,14 @@ pub async fn delete_category(
 }
 
 /// PUT /api/v1/channels/:channel_id/category
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/category",
+    tag = "categories",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetChannelCategoryRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
 pub async fn set_channel_category(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -189,5 +236,8 @@ pub async fn set_channel_category(
         encrypted: updated.encrypted,
         export_allowed: updated.export_allowed,
         message_ttl: updated.message_ttl,
+        owner_id: updated.owner_id,
+        topic: updated.topic,
+        description: updated.description,
     }))
 }
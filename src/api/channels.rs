@@ -6,16 +6,25 @@ use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::db::queries;
+use crate::db::{queries, Pool, Staleness};
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
 use crate::permissions;
 use crate::pubsub;
 use crate::ws::broadcast_to_server;
+use crate::ws_outbox::{self, Target};
 use crate::AppState;
 
 /// POST /api/v1/servers/:server_id/channels
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/channels",
+    tag = "channels",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateChannelRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
 pub async fn create_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -44,6 +53,10 @@ pub async fn create_channel(
     let position = req.position.unwrap_or(0);
     let is_private = req.is_private.unwrap_or(false);
     let encrypted = req.encrypted.unwrap_or(true);
+    let template = match req.template_id {
+        Some(template_id) => Some(crate::api::permission_templates::resolve(&state, server_id, template_id).await?),
+        None => None,
+    };
 
     let channel = queries::create_channel(
         state.db.write(),
@@ -60,11 +73,27 @@ pub async fn create_channel(
     // Add creator to the channel
     queries::add_channel_member(state.db.write(), channel.id, user_id).await?;
 
+    // A template becomes the channel's @everyone overwrite
+    if let Some(template) = &template {
+        let everyone = queries::find_default_role(state.db.read(), server_id)
+            .await?
+            .ok_or(AppError::NotFound("Default role not found".into()))?;
+        queries::set_channel_overwrite(
+            state.db.write(),
+            channel.id,
+            "role",
+            everyone.id,
+            template.allow_bits,
+            template.deny_bits,
+        )
+        .await?;
+    }
+
     // Audit log
     let _ = queries::insert_audit_log(
         state.db.write(), server_id, user_id, "channel_create",
         Some("channel"), Some(channel.id),
-        Some(&serde_json::json!({ "channel_type": &channel.channel_type })), None,
+        Some(&serde_json::json!({ "channel_type": &channel.channel_type, "template_id": req.template_id })), None,
     ).await;
 
     // Notify other server members so their channel list updates
@@ -87,10 +116,20 @@ pub async fn create_channel(
         encrypted: channel.encrypted,
         export_allowed: channel.export_allowed,
         message_ttl: channel.message_ttl,
+        owner_id: channel.owner_id,
+        topic: channel.topic,
+        description: channel.description,
     }))
 }
 
 /// POST /api/v1/channels/:channel_id/join
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/join",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200))
+)]
 pub async fn join_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -115,7 +154,16 @@ pub async fn join_channel(
 
 /// POST /api/v1/dm
 /// Create a DM channel between two users, or return existing one.
-/// Enforces DM privacy: if the target has friends_only, creates a pending DM.
+/// Enforces DM privacy: if the target has friends_only, creates a pending DM;
+/// with friends_strict, only friends may open one. Users the target has
+/// blocked cannot open or reopen a DM at all.
+#[utoipa::path(
+    post,
+    path = "/api/v1/dm",
+    tag = "channels",
+    request_body = CreateDmRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
 pub async fn create_dm(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -126,6 +174,10 @@ pub async fn create_dm(
         .await?
         .ok_or(AppError::UserNotFound)?;
 
+    if queries::is_blocked(state.db.read(), req.target_user_id, user_id).await? {
+        return Err(AppError::Forbidden("You cannot message this user".into()));
+    }
+
     // Check for existing DM channel between these two users
     if let Some(existing) = queries::find_dm_channel(state.db.read(), user_id, req.target_user_id).await? {
         return Ok(Json(ChannelResponse {
@@ -145,12 +197,23 @@ pub async fn create_dm(
             encrypted: true,
             export_allowed: existing.export_allowed,
             message_ttl: existing.message_ttl,
+            owner_id: existing.owner_id,
+            topic: existing.topic,
+            description: existing.description,
         }));
     }
 
     // Determine DM status based on target's privacy setting
     let dm_status = match target.dm_privacy.as_str() {
         "everyone" => "active",
+        "friends_strict" => {
+            if !queries::are_friends(state.db.read(), user_id, req.target_user_id).await? {
+                return Err(AppError::Forbidden(
+                    "This user only accepts direct messages from friends".into(),
+                ));
+            }
+            "active"
+        }
         "friends_only" => {
             if queries::are_friends(state.db.read(), user_id, req.target_user_id).await? {
                 "active"
@@ -192,6 +255,13 @@ pub async fn create_dm(
     queries::add_channel_member(state.db.write(), channel.id, user_id).await?;
     queries::add_channel_member(state.db.write(), channel.id, req.target_user_id).await?;
 
+    // A remote target learns about the DM from its own server
+    if crate::federation::is_remote_username(&target.username) {
+        if let Some(me) = queries::find_user_by_id(state.db.read(), user_id).await? {
+            crate::federation::announce_dm(&state, channel.id, &me, target.id).await;
+        }
+    }
+
     // If pending, notify the target user via WS
     if dm_status == "pending" {
         send_to_user(&state, req.target_user_id, WsServerMessage::DmRequestReceived {
@@ -217,42 +287,39 @@ pub async fn create_dm(
         encrypted: true,
         export_allowed: channel.export_allowed,
         message_ttl: channel.message_ttl,
+        owner_id: channel.owner_id,
+        topic: channel.topic,
+        description: channel.description,
     }))
 }
 
 /// GET /api/v1/dm
 /// List all DM channels for the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/api/v1/dm",
+    tag = "channels",
+    responses((status = 200, body = Vec<ChannelResponse>))
+)]
 pub async fn list_dm_channels(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
 ) -> AppResult<Json<Vec<ChannelResponse>>> {
     let channels = queries::get_user_dm_channels(state.db.read(), user_id).await?;
-    let responses: Vec<ChannelResponse> = channels
-        .into_iter()
-        .map(|ch| ChannelResponse {
-            id: ch.id,
-            server_id: ch.server_id,
-            encrypted_meta: base64::Engine::encode(
-                &base64::engine::general_purpose::STANDARD,
-                &ch.encrypted_meta,
-            ),
-            channel_type: ch.channel_type,
-            position: ch.position,
-            created_at: ch.created_at,
-            category_id: ch.category_id,
-            dm_status: ch.dm_status,
-            last_message_id: None,
-            is_private: ch.is_private,
-            encrypted: ch.encrypted,
-            export_allowed: ch.export_allowed,
-            message_ttl: ch.message_ttl,
-        })
-        .collect();
-    Ok(Json(responses))
+    Ok(Json(channels.into_iter().map(ChannelResponse::from).collect()))
 }
 
 /// PUT /api/v1/channels/:channel_id
-/// Rename a channel (update its encrypted_meta). Server owner only.
+/// Rename a channel (update its encrypted_meta). Server channels need
+/// MANAGE_CHANNELS; in a group DM any member may rename or change the icon.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = UpdateChannelRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
 pub async fn update_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -263,6 +330,10 @@ pub async fn update_channel(
         .await?
         .ok_or(AppError::NotFound("Channel not found".into()))?;
 
+    if channel.channel_type == "group" {
+        return update_group_dm_meta(&state, user_id, channel, &req).await.map(Json);
+    }
+
     // Must be a server channel
     let server_id = channel.server_id
         .ok_or(AppError::Forbidden("Cannot rename DM channels".into()))?;
@@ -286,6 +357,10 @@ pub async fn update_channel(
     }
 
     let updated = queries::update_channel_meta(state.db.write(), channel_id, &encrypted_meta, req.encrypted).await?;
+    if updated.encrypted_meta != channel.encrypted_meta {
+        let username = display_name_of(&state, user_id).await;
+        post_system_message(&state, channel_id, SystemMessage::new(SystemEvent::ChannelRenamed, user_id, username)).await;
+    }
 
     // If encryption setting changed, insert a system message
     if let Some(new_encrypted) = req.encrypted {
@@ -295,19 +370,11 @@ pub async fn update_channel(
                 .as_ref()
                 .map(|u| u.display_name.as_deref().unwrap_or(&u.username).to_string())
                 .unwrap_or_else(|| "Someone".to_string());
-            let event = if new_encrypted { "encryption_enabled" } else { "encryption_disabled" };
-            let body = serde_json::json!({
-                "event": event,
-                "username": username,
-                "user_id": user_id.to_string(),
-            });
-            if let Ok(sys_msg) = queries::insert_system_message(
-                state.db.write(), channel_id, &body.to_string(),
-            ).await {
+            let event = if new_encrypted { SystemEvent::EncryptionEnabled } else { SystemEvent::EncryptionDisabled };
+            let body = SystemMessage::new(event, user_id, username);
+            if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
                 let response: MessageResponse = sys_msg.into();
-                if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-                    let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-                }
+                pubsub::broadcast_channel_event(&state, channel_id, &WsServerMessage::NewMessage(response)).await;
             }
         }
     }
@@ -337,12 +404,181 @@ pub async fn update_channel(
         encrypted: updated.encrypted,
         export_allowed: updated.export_allowed,
         message_ttl: updated.message_ttl,
+        owner_id: updated.owner_id,
+        topic: updated.topic,
+        description: updated.description,
     }))
 }
 
+const MAX_TOPIC_CHARS: usize = 1024;
+const MAX_DESCRIPTION_CHARS: usize = 4096;
+
+/// PATCH /api/v1/channels/:channel_id
+/// Change a server channel's topic and description. Needs MANAGE_CHANNELS.
+/// Unencrypted channels take them in plaintext and audit the old and new
+/// values; encrypted channels carry them in `encrypted_meta`. A topic change
+/// posts a `topic_changed` system message.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/channels/{channel_id}",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = PatchChannelRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
+pub async fn patch_channel(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<PatchChannelRequest>,
+) -> AppResult<Json<ChannelResponse>> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    let server_id = channel
+        .server_id
+        .ok_or(AppError::Forbidden("DM channels have no topic".into()))?;
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_CHANNELS).await?;
+
+    let (updated, event, changes) = if channel.encrypted {
+        if req.topic.is_some() || req.description.is_some() {
+            return Err(AppError::Validation(
+                "Encrypted channels keep their topic in encrypted_meta".into(),
+            )
+            .with_code("TOPIC_MUST_BE_ENCRYPTED"));
+        }
+        let Some(meta) = &req.encrypted_meta else {
+            return Err(AppError::Validation("encrypted_meta is required".into()));
+        };
+        let encrypted_meta = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, meta)
+            .map_err(|_| AppError::Validation("Invalid encrypted_meta encoding".into()))?;
+        if encrypted_meta.len() > 8192 {
+            return Err(AppError::Validation("encrypted_meta exceeds maximum size (8KB)".into()));
+        }
+        if encrypted_meta == channel.encrypted_meta {
+            return Ok(Json(channel.into()));
+        }
+        let updated = queries::update_channel_meta(state.db.write(), channel_id, &encrypted_meta, None).await?;
+        (updated, Some(SystemEvent::TopicChanged { topic: None }), None)
+    } else {
+        if req.encrypted_meta.is_some() {
+            return Err(AppError::Validation("Use PUT /channels/:id to change encrypted_meta".into()));
+        }
+        let topic = match req.topic {
+            Some(topic) => clean_text(topic, "topic", MAX_TOPIC_CHARS)?,
+            None => channel.topic.clone(),
+        };
+        let description = match req.description {
+            Some(description) => clean_text(description, "description", MAX_DESCRIPTION_CHARS)?,
+            None => channel.description.clone(),
+        };
+        let mut changes = serde_json::Map::new();
+        if topic != channel.topic {
+            changes.insert("topic".into(), serde_json::json!({ "from": channel.topic, "to": topic }));
+        }
+        if description != channel.description {
+            changes.insert(
+                "description".into(),
+                serde_json::json!({ "from": channel.description, "to": description }),
+            );
+        }
+        if changes.is_empty() {
+            return Ok(Json(channel.into()));
+        }
+        let event = changes.contains_key("topic").then(|| SystemEvent::TopicChanged { topic: topic.clone() });
+        let updated =
+            queries::update_channel_topic(state.db.write(), channel_id, topic.as_deref(), description.as_deref())
+                .await?;
+        (updated, event, Some(serde_json::Value::Object(changes)))
+    };
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "channel_update",
+        Some("channel"), Some(channel_id), changes.as_ref(), None,
+    ).await;
+    if let Some(event) = event {
+        let username = display_name_of(&state, user_id).await;
+        post_system_message(&state, channel_id, SystemMessage::new(event, user_id, username)).await;
+    }
+    broadcast_to_server(&state, server_id, WsServerMessage::ServerUpdated { server_id }).await;
+
+    Ok(Json(updated.into()))
+}
+
+/// Trim a plaintext channel field; empty clears it.
+fn clean_text(value: Option<String>, field: &str, max_chars: usize) -> AppResult<Option<String>> {
+    let Some(value) = value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    if value.chars().count() > max_chars {
+        return Err(AppError::Validation(format!("{} must be at most {} characters", field, max_chars)));
+    }
+    Ok(Some(value))
+}
+
+/// Group DM name/icon live in encrypted_meta; any member may change them.
+async fn update_group_dm_meta(
+    state: &AppState,
+    user_id: Uuid,
+    channel: Channel,
+    req: &UpdateChannelRequest,
+) -> AppResult<ChannelResponse> {
+    if !queries::is_channel_member(state.db.read(), channel.id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+
+    let encrypted_meta = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        &req.encrypted_meta,
+    )
+    .map_err(|_| AppError::Validation("Invalid encrypted_meta encoding".into()))?;
+
+    if encrypted_meta.len() > 8192 {
+        return Err(AppError::Validation("encrypted_meta exceeds maximum size (8KB)".into()));
+    }
+
+    // Group DMs are always end-to-end encrypted; the toggle is ignored
+    let updated = queries::update_channel_meta(state.db.write(), channel.id, &encrypted_meta, None).await?;
+    if updated.encrypted_meta != channel.encrypted_meta {
+        let username = display_name_of(state, user_id).await;
+        post_system_message(state, channel.id, SystemMessage::new(SystemEvent::ChannelRenamed, user_id, username)).await;
+    }
+    notify_group_dm(state, channel.id, None).await;
+
+    Ok(ChannelResponse {
+        id: updated.id,
+        server_id: None,
+        encrypted_meta: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &updated.encrypted_meta,
+        ),
+        channel_type: updated.channel_type,
+        position: updated.position,
+        created_at: updated.created_at,
+        category_id: None,
+        dm_status: updated.dm_status,
+        last_message_id: None,
+        is_private: false,
+        encrypted: updated.encrypted,
+        export_allowed: updated.export_allowed,
+        message_ttl: updated.message_ttl,
+        owner_id: updated.owner_id,
+        topic: updated.topic,
+        description: updated.description,
+    })
+}
+
 /// PUT /api/v1/channels/:channel_id/message-ttl
 /// Set or clear the disappearing message timer for any channel type.
 /// Server channels: requires MANAGE_CHANNELS. DMs/groups: any member can toggle.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/message-ttl",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetMessageTtlRequest,
+    responses((status = 200))
+)]
 pub async fn set_message_ttl(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -394,31 +630,18 @@ pub async fn set_message_ttl(
         .map(|u| u.display_name.as_deref().unwrap_or(&u.username).to_string())
         .unwrap_or_else(|| "Someone".to_string());
 
-    let (event, duration_label) = match req.message_ttl {
-        Some(30) => ("disappearing_messages_set", "30 seconds"),
-        Some(300) => ("disappearing_messages_set", "5 minutes"),
-        Some(3600) => ("disappearing_messages_set", "1 hour"),
-        Some(28800) => ("disappearing_messages_set", "8 hours"),
-        Some(86400) => ("disappearing_messages_set", "1 day"),
-        Some(604800) => ("disappearing_messages_set", "1 week"),
-        _ => ("disappearing_messages_off", ""),
+    let event = match req.message_ttl {
+        Some(ttl_secs) => SystemEvent::DisappearingMessagesSet { ttl_secs },
+        None => SystemEvent::DisappearingMessagesOff,
     };
-
-    let body = serde_json::json!({
-        "event": event,
-        "username": username,
-        "user_id": user_id.to_string(),
-        "duration": duration_label,
-    });
-    if let Ok(sys_msg) = queries::insert_system_message(
-        state.db.write(), channel_id, &body.to_string(),
-    ).await {
+    let body = SystemMessage::new(event, user_id, username);
+    if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
         let response: MessageResponse = sys_msg.into();
         let sys_ws_msg = WsServerMessage::NewMessage(response);
         if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
             let _ = broadcaster.send(sys_ws_msg.clone());
         }
-        pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &sys_ws_msg).await;
+        pubsub::publish_channel_event(&state, channel_id, &sys_ws_msg).await;
     }
 
     // Broadcast settings change to channel subscribers
@@ -430,7 +653,7 @@ pub async fn set_message_ttl(
     if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
         let _ = broadcaster.send(settings_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &settings_msg).await;
+    pubsub::publish_channel_event(&state, channel_id, &settings_msg).await;
 
     // For DM/group, also deliver directly to all member connections
     if channel.channel_type == "dm" || channel.channel_type == "group" {
@@ -459,8 +682,205 @@ pub async fn set_message_ttl(
     Ok(Json(serde_json::json!({ "message_ttl": req.message_ttl })))
 }
 
+/// GET /api/v1/channels/:channel_id/mention-settings
+/// Whether role mentions in the channel notify anyone.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/mention-settings",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = ChannelMentionSettingsResponse))
+)]
+pub async fn get_mention_settings(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<ChannelMentionSettingsResponse>> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+    let suppress_role_mentions = queries::get_suppress_role_mentions(state.db.read(), channel_id).await?;
+    Ok(Json(ChannelMentionSettingsResponse { channel_id, suppress_role_mentions }))
+}
+
+/// PUT /api/v1/channels/:channel_id/mention-settings
+/// Suppress (or re-enable) role mentions in a server channel: they are still
+/// delivered, but notify no one and don't count towards mention counts.
+/// Requires MANAGE_CHANNELS.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/mention-settings",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetChannelMentionSettingsRequest,
+    responses((status = 200, body = ChannelMentionSettingsResponse))
+)]
+pub async fn set_mention_settings(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<SetChannelMentionSettingsRequest>,
+) -> AppResult<Json<ChannelMentionSettingsResponse>> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    let server_id = channel.server_id.ok_or_else(|| {
+        AppError::BadRequest("Mention settings apply to server channels only".into())
+    })?;
+    queries::require_server_permission(
+        state.db.read(),
+        server_id,
+        user_id,
+        permissions::MANAGE_CHANNELS,
+    )
+    .await?;
+
+    queries::set_suppress_role_mentions(state.db.write(), channel_id, req.suppress_role_mentions).await?;
+
+    let _ = queries::insert_audit_log(
+        state.db.write(),
+        server_id,
+        user_id,
+        "channel_mention_settings_update",
+        Some("channel"),
+        Some(channel_id),
+        Some(&serde_json::json!({ "suppress_role_mentions": req.suppress_role_mentions })),
+        None,
+    )
+    .await;
+
+    Ok(Json(ChannelMentionSettingsResponse {
+        channel_id,
+        suppress_role_mentions: req.suppress_role_mentions,
+    }))
+}
+
+/// Longest retention period and message count a policy may set.
+const MAX_RETENTION_DAYS: i32 = 3650;
+const MAX_RETENTION_MESSAGES: i32 = 1_000_000;
+
+/// GET /api/v1/channels/:channel_id/retention
+/// The channel's message retention policy (`forever` when none is set).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/retention",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = ChannelRetentionResponse))
+)]
+pub async fn get_retention(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<ChannelRetentionResponse>> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+    let policy = queries::get_channel_retention(state.db.read(), channel_id).await?;
+    Ok(Json(ChannelRetentionResponse::new(channel_id, policy)))
+}
+
+/// PUT /api/v1/channels/:channel_id/retention
+/// Set how long a server channel keeps messages. Requires MANAGE_CHANNELS.
+/// The channel-retention job deletes messages outside the policy, archiving
+/// them to backup storage first when `archive` is set.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/retention",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetChannelRetentionRequest,
+    responses((status = 200, body = ChannelRetentionResponse))
+)]
+pub async fn set_retention(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<SetChannelRetentionRequest>,
+) -> AppResult<Json<ChannelRetentionResponse>> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    let server_id = channel.server_id.ok_or_else(|| {
+        AppError::BadRequest("Retention policies apply to server channels only".into())
+    })?;
+    queries::require_server_permission(
+        state.db.read(),
+        server_id,
+        user_id,
+        permissions::MANAGE_CHANNELS,
+    )
+    .await?;
+
+    let (max_age_days, max_messages) = match (req.mode, req.value) {
+        (RetentionMode::Forever, _) => (None, None),
+        (RetentionMode::Days, Some(days)) if (1..=MAX_RETENTION_DAYS).contains(&days) => {
+            (Some(days), None)
+        }
+        (RetentionMode::Messages, Some(count)) if (1..=MAX_RETENTION_MESSAGES).contains(&count) => {
+            (None, Some(count))
+        }
+        (RetentionMode::Days, _) => {
+            return Err(AppError::Validation(format!(
+                "value must be between 1 and {} days",
+                MAX_RETENTION_DAYS
+            )))
+        }
+        (RetentionMode::Messages, _) => {
+            return Err(AppError::Validation(format!(
+                "value must be between 1 and {} messages",
+                MAX_RETENTION_MESSAGES
+            )))
+        }
+    };
+    let archive = req.archive && req.mode != RetentionMode::Forever;
+    if archive && state.backup_storage.is_none() {
+        return Err(AppError::BadRequest(
+            "This instance has no backup storage for archives".into(),
+        )
+        .with_code("BACKUP_STORAGE_UNAVAILABLE"));
+    }
+
+    let policy = queries::set_channel_retention(
+        state.db.write(),
+        channel_id,
+        max_age_days,
+        max_messages,
+        archive,
+        user_id,
+    )
+    .await?;
+    let response = ChannelRetentionResponse::new(channel_id, policy);
+
+    let _ = queries::insert_audit_log(
+        state.db.write(),
+        server_id,
+        user_id,
+        "channel_retention_update",
+        Some("channel"),
+        Some(channel_id),
+        Some(&serde_json::json!({
+            "mode": response.mode,
+            "value": response.value,
+            "archive": response.archive,
+        })),
+        None,
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
 /// PUT /api/v1/servers/:server_id/channels/reorder
 /// Reorder channels within a server (position + category assignment).
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/channels/reorder",
+    tag = "channels",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = ReorderChannelsRequest,
+    responses((status = 200))
+)]
 pub async fn reorder_channels(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -487,8 +907,95 @@ pub async fn reorder_channels(
     Ok(Json(serde_json::json!({ "message": "Channels reordered" })))
 }
 
+/// PATCH /api/v1/servers/:server_id/channels/positions
+/// Replace the whole channel and category layout at once, so concurrent
+/// drags can't interleave. The list must name every channel and category;
+/// if one was created or deleted meanwhile nothing changes and the client
+/// gets `CHANNEL_LAYOUT_STALE` to reload and retry.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/channels/positions",
+    tag = "channels",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = UpdateChannelPositionsRequest,
+    responses(
+        (status = 200, body = ChannelPositionsResponse),
+        (status = 409, description = "The layout changed since it was loaded")
+    )
+)]
+pub async fn update_channel_positions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<UpdateChannelPositionsRequest>,
+) -> AppResult<Json<ChannelPositionsResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_CHANNELS).await?;
+
+    let mut seen = std::collections::HashSet::new();
+    let duplicate = req.categories.iter().chain(req.channels.iter().map(|c| &c.id)).any(|id| !seen.insert(*id));
+    if duplicate {
+        return Err(AppError::Validation("A channel or category is listed twice".into()));
+    }
+    if let Some(placement) = req
+        .channels
+        .iter()
+        .find(|c| c.category_id.is_some_and(|id| !req.categories.contains(&id)))
+    {
+        return Err(AppError::Validation(format!(
+            "Channel {} is placed in a category that isn't listed",
+            placement.id
+        )));
+    }
+
+    // Number categories in order, and channels in order within each category
+    let categories = req
+        .categories
+        .iter()
+        .enumerate()
+        .map(|(i, id)| CategoryPosition { id: *id, position: i as i32 })
+        .collect();
+    let mut next_in_category: HashMap<Option<Uuid>, i32> = HashMap::new();
+    let channels = req
+        .channels
+        .iter()
+        .map(|c| {
+            let next = next_in_category.entry(c.category_id).or_default();
+            let position = *next;
+            *next += 1;
+            ChannelPosition { id: c.id, position, category_id: c.category_id }
+        })
+        .collect();
+    let positions = ChannelPositionsResponse { categories, channels };
+
+    let mut tx = state.db.write().begin().await?;
+    if !queries::apply_channel_positions(&mut tx, server_id, &positions).await? {
+        return Err(AppError::Conflict("Channels or categories changed since the layout was loaded".into())
+            .with_code("CHANNEL_LAYOUT_STALE"));
+    }
+    let event = WsServerMessage::ChannelPositionsUpdated {
+        server_id,
+        categories: positions.categories.clone(),
+        channels: positions.channels.clone(),
+    };
+    ws_outbox::stage(&mut tx, Target::Server(server_id), &event).await?;
+    tx.commit().await?;
+
+    let channel_ids: Vec<Uuid> = positions.channels.iter().map(|c| c.id).collect();
+    queries::record_sync_changes(state.db.write(), server_id, queries::SyncEntity::Channel, &channel_ids, false).await?;
+    state.ws_outbox.wake();
+
+    Ok(Json(positions))
+}
+
 /// DELETE /api/v1/channels/:channel_id
 /// Delete a channel. Server owner only.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200))
+)]
 pub async fn delete_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -509,6 +1016,10 @@ pub async fn delete_channel(
     )
     .await?;
 
+    if queries::is_under_legal_hold(state.db.read(), LegalHoldSubject::Server, server_id).await? {
+        return Err(crate::api::admin::legal_hold_error());
+    }
+
     queries::delete_channel(state.db.write(), channel_id).await?;
 
     // Audit log
@@ -524,6 +1035,13 @@ pub async fn delete_channel(
 
 /// GET /api/v1/channels/:channel_id/members
 /// List members of a channel with user info (for DM/group member sidebar).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/members",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<ChannelMemberInfo>))
+)]
 pub async fn list_channel_members(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -539,31 +1057,41 @@ pub async fn list_channel_members(
 }
 
 /// POST /api/v1/dm/group
-/// Create a group DM channel with multiple friends.
+/// Create a group DM channel with multiple friends. The creator becomes owner.
+#[utoipa::path(
+    post,
+    path = "/api/v1/dm/group",
+    tag = "channels",
+    request_body = CreateGroupDmRequest,
+    responses((status = 200, body = ChannelResponse))
+)]
 pub async fn create_group_dm(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Json(req): Json<CreateGroupDmRequest>,
 ) -> AppResult<Json<ChannelResponse>> {
-    // Validate member count: need at least 2 others (3+ total), max 10 total
-    if req.member_ids.len() < 2 {
+    let mut member_ids: Vec<Uuid> = Vec::new();
+    for &member_id in &req.member_ids {
+        if member_id != user_id && !member_ids.contains(&member_id) {
+            member_ids.push(member_id);
+        }
+    }
+
+    // Need at least 2 others (3+ total), capped by MAX_GROUP_DM_MEMBERS including the owner
+    let max_members = state.config.max_group_dm_members;
+    if member_ids.len() < 2 {
         return Err(AppError::Validation("Group DM requires at least 2 other members".into()));
     }
-    if req.member_ids.len() > 9 {
-        return Err(AppError::Validation("Group DM can have at most 10 members total".into()));
+    if member_ids.len() + 1 > max_members {
+        return Err(AppError::Validation(format!(
+            "Group DM can have at most {} members total",
+            max_members
+        )));
     }
 
     // Verify all members are friends of the creator
-    for &member_id in &req.member_ids {
-        if member_id == user_id {
-            continue; // Skip self if accidentally included
-        }
-        if !queries::are_friends(state.db.read(), user_id, member_id).await? {
-            return Err(AppError::Validation(format!(
-                "User {} is not your friend",
-                member_id
-            )));
-        }
+    for &member_id in &member_ids {
+        check_can_add_to_group(&state, user_id, member_id).await?;
     }
 
     let encrypted_meta = base64::Engine::decode(
@@ -577,18 +1105,18 @@ pub async fn create_group_dm(
     }
 
     let channel = queries::create_channel(state.db.write(), None, &encrypted_meta, "group", 0, None, false, true).await?;
+    queries::set_channel_owner(state.db.write(), channel.id, Some(user_id)).await?;
 
     // Add creator
     queries::add_channel_member(state.db.write(), channel.id, user_id).await?;
 
     // Add all other members
-    for &member_id in &req.member_ids {
-        if member_id == user_id {
-            continue;
-        }
+    for &member_id in &member_ids {
         queries::add_channel_member(state.db.write(), channel.id, member_id).await?;
     }
 
+    notify_group_dm(&state, channel.id, None).await;
+
     Ok(Json(ChannelResponse {
         id: channel.id,
         server_id: None,
@@ -606,11 +1134,22 @@ pub async fn create_group_dm(
         encrypted: true,
         export_allowed: channel.export_allowed,
         message_ttl: channel.message_ttl,
+        owner_id: Some(user_id),
+        topic: channel.topic,
+        description: channel.description,
     }))
 }
 
 /// DELETE /api/v1/channels/:channel_id/leave
-/// Leave a group DM channel.
+/// Leave a group DM channel. If the owner leaves, ownership passes to the
+/// longest-standing remaining member.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}/leave",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200))
+)]
 pub async fn leave_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -628,42 +1167,42 @@ pub async fn leave_channel(
         return Err(AppError::Forbidden("Not a member of this channel".into()));
     }
 
-    // Look up username before removing
-    let user = queries::find_user_by_id(state.db.read(), user_id).await?;
-    let username = user
-        .as_ref()
-        .map(|u| u.display_name.as_deref().unwrap_or(&u.username).to_string())
-        .unwrap_or_else(|| "Someone".to_string());
+    let username = display_name_of(&state, user_id).await;
 
     // Remove the user from the channel
     queries::remove_channel_member(state.db.write(), channel_id, user_id).await?;
 
     // Check if channel is now empty
-    let remaining = queries::get_channel_member_ids(state.db.read(), channel_id).await?;
+    let remaining = queries::get_channel_member_ids(state.db.read_with(Staleness::Fresh), channel_id).await?;
     if remaining.is_empty() {
         queries::delete_channel(state.db.write(), channel_id).await?;
     } else {
-        // Insert system message about the user leaving
-        let body = serde_json::json!({
-            "event": "member_left",
-            "username": username,
-            "user_id": user_id.to_string(),
-        });
-        if let Ok(sys_msg) = queries::insert_system_message(
-            state.db.write(), channel_id, &body.to_string(),
-        ).await {
-            let response: MessageResponse = sys_msg.into();
-            if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-                let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-            }
+        if channel.owner_id == Some(user_id) {
+            let new_owner = queries::get_oldest_channel_member(state.db.read_with(Staleness::Fresh), channel_id).await?;
+            queries::set_channel_owner(state.db.write(), channel_id, new_owner).await?;
         }
+
+        crate::api::sender_keys::require_rotation(&state, channel_id, user_id).await;
+
+        // Insert system message about the user leaving
+        post_system_message(&state, channel_id, SystemMessage::new(SystemEvent::MemberLeft, user_id, username))
+            .await;
+        notify_group_dm(&state, channel_id, Some(user_id)).await;
     }
 
     Ok(Json(serde_json::json!({ "message": "Left channel" })))
 }
 
 /// POST /api/v1/channels/:channel_id/members
-/// Add a member to an existing group DM.
+/// Add a friend to an existing group DM. Any member may add.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/members",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = AddGroupMemberRequest,
+    responses((status = 200))
+)]
 pub async fn add_group_member(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -686,38 +1225,178 @@ pub async fn add_group_member(
         return Err(AppError::Validation("User is already a member".into()));
     }
 
-    // Cap at 10 members
     let members = queries::get_channel_member_ids(state.db.read(), channel_id).await?;
-    if members.len() >= 10 {
-        return Err(AppError::Validation("Group DM cannot have more than 10 members".into()));
+    let max_members = state.config.max_group_dm_members;
+    if members.len() >= max_members {
+        return Err(AppError::Validation(format!(
+            "Group DM cannot have more than {} members",
+            max_members
+        )));
     }
 
+    check_can_add_to_group(&state, user_id, body.user_id).await?;
+
     queries::add_channel_member(state.db.write(), channel_id, body.user_id).await?;
 
+    let event = SystemEvent::MemberAdded { added_by: user_id };
+    let username = display_name_of(&state, body.user_id).await;
+    post_system_message(&state, channel_id, SystemMessage::new(event, body.user_id, username)).await;
+    notify_group_dm(&state, channel_id, None).await;
+
     Ok(Json(serde_json::json!({ "added": true })))
 }
 
-#[derive(Debug, serde::Deserialize)]
+/// DELETE /api/v1/channels/:channel_id/members/:user_id
+/// Remove a member from a group DM. Owner only.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}/members/{user_id}",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
+pub async fn remove_group_member(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, target_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let channel = require_group_owner(&state, channel_id, user_id).await?;
+
+    if target_id == user_id {
+        return Err(AppError::Validation("Use leave to remove yourself".into()));
+    }
+    if !queries::is_channel_member(state.db.read(), channel.id, target_id).await? {
+        return Err(AppError::NotFound("User is not a member of this group".into()));
+    }
+
+    queries::remove_channel_member(state.db.write(), channel_id, target_id).await?;
+    crate::api::sender_keys::require_rotation(&state, channel_id, target_id).await;
+
+    let event = SystemEvent::MemberRemoved { removed_by: user_id };
+    let username = display_name_of(&state, target_id).await;
+    post_system_message(&state, channel_id, SystemMessage::new(event, target_id, username)).await;
+    notify_group_dm(&state, channel_id, Some(target_id)).await;
+
+    Ok(Json(serde_json::json!({ "removed": true })))
+}
+
+/// PUT /api/v1/channels/:channel_id/owner
+/// Hand group DM ownership to another member. Owner only.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/owner",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = TransferGroupOwnerRequest,
+    responses((status = 200))
+)]
+pub async fn transfer_group_owner(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<TransferGroupOwnerRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    require_group_owner(&state, channel_id, user_id).await?;
+
+    if !queries::is_channel_member(state.db.read(), channel_id, req.user_id).await? {
+        return Err(AppError::Validation("New owner must be a member of this group".into()));
+    }
+
+    queries::set_channel_owner(state.db.write(), channel_id, Some(req.user_id)).await?;
+
+    let username = display_name_of(&state, req.user_id).await;
+    post_system_message(&state, channel_id, SystemMessage::new(SystemEvent::OwnerChanged, req.user_id, username))
+        .await;
+    notify_group_dm(&state, channel_id, None).await;
+
+    Ok(Json(serde_json::json!({ "owner_id": req.user_id })))
+}
+
+/// Only friends who haven't blocked the adder can be put into a group DM.
+async fn check_can_add_to_group(state: &AppState, user_id: Uuid, member_id: Uuid) -> AppResult<()> {
+    if !queries::are_friends(state.db.read(), user_id, member_id).await? {
+        return Err(AppError::Validation(format!("User {} is not your friend", member_id)));
+    }
+    if queries::is_blocked(state.db.read(), member_id, user_id).await? {
+        return Err(AppError::Forbidden("You cannot add this user".into()));
+    }
+    Ok(())
+}
+
+async fn require_group_owner(state: &AppState, channel_id: Uuid, user_id: Uuid) -> AppResult<Channel> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    if channel.channel_type != "group" {
+        return Err(AppError::Validation("Not a group DM channel".into()));
+    }
+    if channel.owner_id != Some(user_id) {
+        return Err(AppError::Forbidden("Only the group owner can do this".into()));
+    }
+    Ok(channel)
+}
+
+async fn display_name_of(state: &AppState, user_id: Uuid) -> String {
+    queries::find_user_by_id(state.db.read(), user_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|u| u.display_name.unwrap_or(u.username))
+        .unwrap_or_else(|| "Someone".to_string())
+}
+
+/// Insert a system message and broadcast it to the channel.
+async fn post_system_message(state: &AppState, channel_id: Uuid, body: SystemMessage) {
+    if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
+        let response: MessageResponse = sys_msg.into();
+        pubsub::broadcast_channel_event(state, channel_id, &WsServerMessage::NewMessage(response)).await;
+    }
+}
+
+/// Push the current group state to every member, plus `removed` if someone
+/// just left or was removed so their client can drop the channel.
+async fn notify_group_dm(state: &AppState, channel_id: Uuid, removed: Option<Uuid>) {
+    let Ok(Some(channel)) = queries::find_channel_by_id(state.db.read(), channel_id).await else {
+        return;
+    };
+    let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await else {
+        return;
+    };
+    let msg = WsServerMessage::GroupDmUpdated {
+        channel_id,
+        owner_id: channel.owner_id,
+        member_ids: member_ids.clone(),
+        encrypted_meta: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &channel.encrypted_meta,
+        ),
+    };
+    for member_id in member_ids.into_iter().chain(removed) {
+        send_to_user(state, member_id, msg.clone()).await;
+    }
+}
+
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct AddGroupMemberRequest {
     pub user_id: Uuid,
 }
 
 /// Helper request type for DM creation.
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct CreateDmRequest {
     pub target_user_id: Uuid,
     pub encrypted_meta: String, // base64
 }
 
 /// Request type for setting disappearing message timer.
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct SetMessageTtlRequest {
     /// Timer in seconds. null = disable disappearing messages.
     pub message_ttl: Option<i32>,
 }
 
 /// Request type for channel updates (rename, encryption toggle, disappearing messages).
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct UpdateChannelRequest {
     pub encrypted_meta: String, // base64
     pub encrypted: Option<bool>,
@@ -732,11 +1411,18 @@ async fn send_to_user(state: &AppState, user_id: Uuid, msg: WsServerMessage) {
             let _ = tx.send(msg.clone());
         }
     }
-    crate::pubsub::publish_user_event(state.redis.clone().as_mut(), user_id, &msg).await;
+    crate::pubsub::publish_user_event(state, user_id, &msg).await;
 }
 
 /// PUT /api/v1/channels/:channel_id/read-state
 /// Mark a channel as read (sets last_read_at to now).
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/read-state",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = ReadState))
+)]
 pub async fn mark_channel_read(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -760,28 +1446,46 @@ pub async fn mark_channel_read(
             let _ = conn.send(sync_msg.clone());
         }
     }
-    crate::pubsub::publish_user_event(state.redis.clone().as_mut(), user_id, &sync_msg).await;
+    crate::pubsub::publish_user_event(&state, user_id, &sync_msg).await;
+
+    if let Err(e) =
+        crate::api::receipts::notify_read(&state, user_id, channel_id, read_state.last_read_at).await
+    {
+        tracing::warn!("Failed to send read receipt for {}: {}", channel_id, e);
+    }
 
     Ok(Json(read_state))
 }
 
 /// GET /api/v1/channels/read-states
 /// Get unread info for all channels the user belongs to.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/read-states",
+    tag = "channels",
+    responses((status = 200, body = Vec<ChannelUnreadInfo>))
+)]
 pub async fn get_read_states(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
 ) -> AppResult<Json<Vec<ChannelUnreadInfo>>> {
+    Ok(Json(unread_infos(state.db.read(), user_id).await?))
+}
+
+/// Last message and unread count for each of the user's channels that has messages.
+pub(crate) async fn unread_infos(pool: &Pool, user_id: Uuid) -> AppResult<Vec<ChannelUnreadInfo>> {
     // Get all channel IDs the user has access to
-    let all_channel_ids = queries::get_user_channel_ids(state.db.read(), user_id).await?;
+    let all_channel_ids = queries::get_user_channel_ids(pool, user_id).await?;
 
     if all_channel_ids.is_empty() {
-        return Ok(Json(vec![]));
+        return Ok(vec![]);
     }
 
-    // Fetch last message IDs and unread counts in parallel
-    let (last_msgs, unread_counts) = tokio::try_join!(
-        queries::get_channel_last_message_ids(state.db.read(), &all_channel_ids),
-        queries::get_user_unread_counts(state.db.read(), user_id, &all_channel_ids),
+    // Fetch last message IDs, unread and mention counts in parallel
+    let (last_msgs, unread_counts, mention_counts) = tokio::try_join!(
+        queries::get_channel_last_message_ids(pool, &all_channel_ids),
+        queries::get_user_unread_counts(pool, user_id, &all_channel_ids),
+        queries::get_user_mention_counts(pool, user_id, &all_channel_ids),
     )?;
 
     // Build lookup maps
@@ -790,6 +1494,7 @@ pub async fn get_read_states(
         .map(|(ch_id, msg_id, ts)| (ch_id, (msg_id, ts)))
         .collect();
     let unread_map: HashMap<Uuid, i64> = unread_counts.into_iter().collect();
+    let mention_map: HashMap<Uuid, i64> = mention_counts.into_iter().collect();
 
     // Only return channels that have unreads or recent messages
     let infos: Vec<ChannelUnreadInfo> = all_channel_ids
@@ -801,16 +1506,18 @@ pub async fn get_read_states(
                 last_message_id: Some(*last_id),
                 last_message_at: Some(*last_at),
                 unread_count: *unread_map.get(ch_id).unwrap_or(&0),
+                mention_count: *mention_map.get(ch_id).unwrap_or(&0),
             })
         })
         .collect();
 
-    Ok(Json(infos))
+    Ok(infos)
 }
 
 // ─── Channel Export ───────────────────────────────────
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ChannelExportQuery {
     pub after: Option<DateTime<Utc>>,
     pub before: Option<DateTime<Utc>>,
@@ -818,7 +1525,7 @@ pub struct ChannelExportQuery {
     pub include_attachments: bool,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct ChannelExportResponse {
     pub channel_id: Uuid,
     pub channel_type: String,
@@ -832,6 +1539,13 @@ pub struct ChannelExportResponse {
 
 /// GET /api/v1/channels/:channel_id/export
 /// Bulk export all messages from a channel (excludes disappearing messages).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/export",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ChannelExportQuery),
+    responses((status = 200, body = ChannelExportResponse))
+)]
 pub async fn export_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -888,13 +1602,21 @@ pub async fn export_channel(
 
 // ─── DM Export Consent ──────────────────────────────
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::ToSchema)]
 pub struct ExportConsentRequest {
     pub export_allowed: bool,
 }
 
 /// PUT /api/v1/channels/:channel_id/export-consent
 /// Toggle export_allowed for a DM channel. Either participant can toggle.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/export-consent",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = ExportConsentRequest,
+    responses((status = 200))
+)]
 pub async fn set_export_consent(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -933,22 +1655,14 @@ pub async fn set_export_consent(
         .unwrap_or_else(|| "Someone".to_string());
 
     let event = if req.export_allowed {
-        "export_consent_enabled"
+        SystemEvent::ExportConsentEnabled
     } else {
-        "export_consent_disabled"
+        SystemEvent::ExportConsentDisabled
     };
-    let body = serde_json::json!({
-        "event": event,
-        "username": username,
-        "user_id": user_id.to_string(),
-    });
-    if let Ok(sys_msg) =
-        queries::insert_system_message(state.db.write(), channel_id, &body.to_string()).await
-    {
+    let body = SystemMessage::new(event, user_id, username);
+    if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
         let response: MessageResponse = sys_msg.into();
-        if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-            let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-        }
+        pubsub::broadcast_channel_event(&state, channel_id, &WsServerMessage::NewMessage(response)).await;
     }
 
     Ok(Json(serde_json::json!({ "export_allowed": req.export_allowed })))
@@ -957,6 +1671,13 @@ pub async fn set_export_consent(
 /// PUT /api/v1/channels/:channel_id/hide
 /// Hide a DM/group channel from the user's sidebar. Channel still exists
 /// and can be un-hidden when a new message arrives.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/hide",
+    tag = "channels",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200))
+)]
 pub async fn hide_channel(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
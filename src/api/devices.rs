@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+const MAX_DEVICES_PER_USER: i64 = 10;
+const DEFAULT_QUEUE_LIMIT: i64 = 100;
+const MAX_QUEUE_LIMIT: i64 = 500;
+
+/// POST /api/v1/keys/devices
+/// Register a device with its own identity keypair.
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys/devices",
+    tag = "devices",
+    request_body = RegisterDeviceRequest,
+    responses((status = 200, body = DeviceResponse))
+)]
+pub async fn register_device(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<RegisterDeviceRequest>,
+) -> AppResult<Json<DeviceResponse>> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 64 {
+        return Err(AppError::Validation("Device name must be 1-64 characters".into()));
+    }
+
+    let decode = |field: &str, value: &str| {
+        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)
+            .ok()
+            .filter(|bytes| !bytes.is_empty())
+            .ok_or_else(|| AppError::Validation(format!("Invalid {} encoding", field)))
+    };
+    let identity_key = decode("identity_key", &req.identity_key)?;
+    let signed_prekey = decode("signed_prekey", &req.signed_prekey)?;
+    let signed_prekey_sig = decode("signed_prekey_signature", &req.signed_prekey_signature)?;
+
+    if queries::count_user_devices(state.db.read(), user_id).await? >= MAX_DEVICES_PER_USER {
+        return Err(AppError::Validation(format!(
+            "At most {} devices per account; remove one first",
+            MAX_DEVICES_PER_USER
+        )));
+    }
+
+    let device = queries::insert_user_device(
+        state.db.write(),
+        user_id,
+        name,
+        &identity_key,
+        &signed_prekey,
+        &signed_prekey_sig,
+    )
+    .await?
+    .ok_or(AppError::Conflict("A device with this identity key is already registered".into()))?;
+
+    notify_device_list(&state, user_id).await;
+
+    Ok(Json(DeviceResponse::new(device, None)))
+}
+
+/// GET /api/v1/keys/devices
+/// The authenticated user's own devices, with their cross-device verification.
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/devices",
+    tag = "devices",
+    responses((status = 200, body = Vec<DeviceResponse>))
+)]
+pub async fn list_own_devices(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<Vec<DeviceResponse>>> {
+    let devices = queries::get_user_devices_for_viewer(state.db.read(), user_id, user_id).await?;
+    Ok(Json(devices.into_iter().map(|(d, v)| DeviceResponse::new(d, v)).collect()))
+}
+
+/// GET /api/v1/users/:user_id/devices
+/// Another user's device list, for encrypting to each of their devices.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/devices",
+    tag = "devices",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = Vec<DeviceResponse>))
+)]
+pub async fn list_user_devices(
+    State(state): State<AppState>,
+    AuthUser(requester_id): AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<Json<Vec<DeviceResponse>>> {
+    let devices = queries::get_user_devices_for_viewer(state.db.read(), user_id, requester_id).await?;
+    Ok(Json(devices.into_iter().map(|(d, v)| DeviceResponse::new(d, v)).collect()))
+}
+
+/// DELETE /api/v1/keys/devices/:device_id
+/// Remove one of the authenticated user's devices.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/devices/{device_id}",
+    tag = "devices",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    responses((status = 200))
+)]
+pub async fn remove_device(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(device_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !queries::delete_user_device(state.db.write(), user_id, device_id).await? {
+        return Err(AppError::NotFound("Device not found".into()));
+    }
+
+    notify_device_list(&state, user_id).await;
+
+    Ok(Json(serde_json::json!({ "removed": true })))
+}
+
+/// PUT /api/v1/keys/devices/:device_id/verification
+/// Record the authenticated user's verdict on a device — one of their own
+/// (cross-device verification) or a contact's.
+#[utoipa::path(
+    put,
+    path = "/api/v1/keys/devices/{device_id}/verification",
+    tag = "devices",
+    params(("device_id" = Uuid, Path, description = "Device ID")),
+    request_body = SetDeviceVerificationRequest,
+    responses((status = 200))
+)]
+pub async fn set_device_verification(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(device_id): Path<Uuid>,
+    Json(req): Json<SetDeviceVerificationRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let device = queries::find_user_device(state.db.read(), device_id)
+        .await?
+        .ok_or(AppError::NotFound("Device not found".into()))?;
+
+    match req.status.as_str() {
+        "verified" | "blocked" => {
+            queries::set_device_verification(state.db.write(), user_id, &device, &req.status).await?
+        }
+        "unverified" => queries::clear_device_verification(state.db.write(), user_id, device_id).await?,
+        _ => {
+            return Err(AppError::Validation(
+                "status must be verified, blocked or unverified".into(),
+            ))
+        }
+    }
+
+    Ok(Json(serde_json::json!({ "device_id": device_id, "status": req.status })))
+}
+
+/// GET /api/v1/users/me/devices/:device_id/queue?since=&limit=
+/// DM and group messages sent while this device was offline, oldest first.
+/// Passing `since` acknowledges everything up to it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/devices/{device_id}/queue",
+    tag = "devices",
+    params(("device_id" = Uuid, Path, description = "Device ID"), DeviceQueueQuery),
+    responses((status = 200, body = DeviceQueueResponse))
+)]
+pub async fn drain_device_queue(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(device_id): Path<Uuid>,
+    Query(query): Query<DeviceQueueQuery>,
+) -> AppResult<Json<DeviceQueueResponse>> {
+    let device = queries::find_user_device(state.db.read(), device_id).await?;
+    if device.is_none_or(|d| d.user_id != user_id) {
+        return Err(AppError::NotFound("Device not found".into()));
+    }
+
+    let since = query.since.unwrap_or(0).max(0);
+    let limit = query.limit.unwrap_or(DEFAULT_QUEUE_LIMIT).clamp(1, MAX_QUEUE_LIMIT);
+
+    let (batch, next_since) = queries::take_device_messages(state.db.write(), device_id, since, limit).await?;
+    let messages = batch
+        .into_iter()
+        .map(|(seq, message)| QueuedMessage {
+            seq,
+            message: MessageResponse::from(message).for_viewer(user_id),
+        })
+        .collect();
+
+    Ok(Json(DeviceQueueResponse { messages, next_since }))
+}
+
+/// Queue a new DM or group message for every member device with no open
+/// connection on this instance. A device connected to another instance may
+/// get a redundant copy; clients deduplicate by message ID. Failures are
+/// logged, never surfaced to the sender.
+pub async fn enqueue_for_offline_devices(state: &AppState, channel_id: Uuid, message_id: Uuid) {
+    let device_ids = match queries::get_channel_member_device_ids(state.db.read(), channel_id).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Failed to list devices for message {}: {}", message_id, e);
+            return;
+        }
+    };
+    let offline: Vec<Uuid> = device_ids
+        .into_iter()
+        .filter(|id| !state.memory.connected_devices.contains_key(id))
+        .collect();
+    if offline.is_empty() {
+        return;
+    }
+    if let Err(e) = queries::enqueue_device_messages(state.db.write(), &offline, channel_id, message_id).await {
+        tracing::warn!("Failed to queue message {} for offline devices: {}", message_id, e);
+    }
+}
+
+/// Tell the owner's other sessions and everyone who encrypts to them that
+/// their device list changed.
+async fn notify_device_list(state: &AppState, user_id: Uuid) {
+    let msg = WsServerMessage::DeviceListUpdated { user_id };
+    let mut recipients = queries::get_device_list_watchers(state.db.read(), user_id)
+        .await
+        .unwrap_or_default();
+    recipients.push(user_id);
+    for recipient in recipients {
+        if let Some(conns) = state.connections.get(&recipient) {
+            for tx in conns.iter() {
+                let _ = tx.send(msg.clone());
+            }
+        }
+        crate::pubsub::publish_user_event(state, recipient, &msg).await;
+    }
+}
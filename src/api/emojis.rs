@@ -13,6 +13,7 @@ use crate::middleware::AuthUser;
 use crate::models::*;
 use crate::permissions;
 use crate::storage;
+use crate::ws::broadcast_to_server;
 use crate::AppState;
 
 const MAX_EMOJI_SIZE: usize = 256 * 1024; // 256KB
@@ -20,6 +21,13 @@ const MAX_STATIC_EMOJIS: i64 = 25;
 const MAX_ANIMATED_EMOJIS: i64 = 10;
 
 /// GET /api/v1/servers/:server_id/emojis — list all custom emojis (requires membership)
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/emojis",
+    tag = "emojis",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<CustomEmojiResponse>))
+)]
 pub async fn list_emojis(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -35,6 +43,14 @@ pub async fn list_emojis(
 }
 
 /// POST /api/v1/servers/:server_id/emojis?name=xxx — upload a custom emoji (binary body)
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/emojis",
+    tag = "emojis",
+    params(("server_id" = Uuid, Path, description = "Server ID"), CreateEmojiQuery),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200, body = CustomEmojiResponse))
+)]
 pub async fn upload_emoji(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -155,6 +171,14 @@ pub async fn upload_emoji(
 }
 
 /// PATCH /api/v1/servers/:server_id/emojis/:emoji_id — rename an emoji
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/emojis/{emoji_id}",
+    tag = "emojis",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("emoji_id" = Uuid, Path, description = "Emoji ID")),
+    request_body = RenameEmojiRequest,
+    responses((status = 200, body = CustomEmojiResponse))
+)]
 pub async fn update_emoji(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -192,6 +216,13 @@ pub async fn update_emoji(
 }
 
 /// DELETE /api/v1/servers/:server_id/emojis/:emoji_id — delete an emoji
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/emojis/{emoji_id}",
+    tag = "emojis",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("emoji_id" = Uuid, Path, description = "Emoji ID")),
+    responses((status = 204))
+)]
 pub async fn delete_emoji(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -228,6 +259,14 @@ pub async fn delete_emoji(
 }
 
 /// GET /api/v1/servers/:server_id/emojis/:emoji_id/image — serve emoji image (no auth)
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/emojis/{emoji_id}/image",
+    tag = "emojis",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("emoji_id" = Uuid, Path, description = "Emoji ID")),
+    responses((status = 200)),
+    security(())
+)]
 pub async fn get_emoji_image(
     State(state): State<AppState>,
     Path((server_id, emoji_id)): Path<(Uuid, Uuid)>,
@@ -302,15 +341,3 @@ fn detect_image_type(data: &[u8]) -> String {
         "application/octet-stream".into()
     }
 }
-
-/// Broadcast a WS message to all members of a server by iterating their connections.
-async fn broadcast_to_server(state: &AppState, server_id: Uuid, msg: WsServerMessage) {
-    // Get all server channels and broadcast via channel subscriptions
-    if let Ok(channels) = queries::get_server_channels(state.db.read(), server_id).await {
-        for ch in channels {
-            if let Some(broadcaster) = state.channel_broadcasts.get(&ch.id) {
-                let _ = broadcaster.send(msg.clone());
-            }
-        }
-    }
-}
@@ -0,0 +1,270 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::ws::broadcast_to_server;
+use crate::AppState;
+
+/// GET /api/v1/servers/:server_id/events
+/// Upcoming and ongoing events in start order, with RSVP counts.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/events",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ServerEventQuery),
+    responses((status = 200, body = Vec<ServerEventResponse>))
+)]
+pub async fn list_events(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Query(params): Query<ServerEventQuery>,
+) -> AppResult<Json<Vec<ServerEventResponse>>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+
+    let events =
+        queries::list_server_events(state.db.read(), server_id, user_id, params.include_past).await?;
+    Ok(Json(events.into_iter().map(ServerEventResponse::from).collect()))
+}
+
+/// POST /api/v1/servers/:server_id/events
+/// Schedule an event. Requires MANAGE_EVENTS.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/events",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateServerEventRequest,
+    responses((status = 200, body = ServerEventResponse))
+)]
+pub async fn create_event(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<CreateServerEventRequest>,
+) -> AppResult<Json<ServerEventResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_EVENTS)
+        .await?;
+
+    let encrypted_meta = decode_meta(&req.encrypted_meta)?;
+    if req.starts_at <= Utc::now() {
+        return Err(AppError::Validation("starts_at must be in the future".into()));
+    }
+    validate_schedule(req.starts_at, req.ends_at)?;
+    if let Some(channel_id) = req.channel_id {
+        require_server_channel(&state, server_id, channel_id).await?;
+    }
+
+    let event_id = queries::create_server_event(
+        state.db.write(),
+        server_id,
+        user_id,
+        &encrypted_meta,
+        req.channel_id,
+        req.starts_at,
+        req.ends_at,
+    )
+    .await?;
+
+    broadcast_event(&state, server_id, event_id).await;
+    Ok(Json(find_event(&state, server_id, event_id, user_id).await?.into()))
+}
+
+/// PATCH /api/v1/servers/:server_id/events/:event_id
+/// Edit or reschedule an event. Requires MANAGE_EVENTS unless the caller
+/// created it.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/events/{event_id}",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("event_id" = Uuid, Path, description = "Event ID")),
+    request_body = UpdateServerEventRequest,
+    responses((status = 200, body = ServerEventResponse))
+)]
+pub async fn update_event(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, event_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateServerEventRequest>,
+) -> AppResult<Json<ServerEventResponse>> {
+    let event = find_event(&state, server_id, event_id, user_id).await?;
+    require_event_manager(&state, &event, user_id).await?;
+
+    let encrypted_meta = req.encrypted_meta.as_deref().map(decode_meta).transpose()?;
+    validate_schedule(
+        req.starts_at.unwrap_or(event.starts_at),
+        req.ends_at.unwrap_or(event.ends_at),
+    )?;
+    if let Some(Some(channel_id)) = req.channel_id {
+        require_server_channel(&state, server_id, channel_id).await?;
+    }
+
+    queries::update_server_event(
+        state.db.write(),
+        event_id,
+        encrypted_meta.as_deref(),
+        req.channel_id,
+        req.starts_at,
+        req.ends_at,
+    )
+    .await?;
+
+    broadcast_event(&state, server_id, event_id).await;
+    Ok(Json(find_event(&state, server_id, event_id, user_id).await?.into()))
+}
+
+/// DELETE /api/v1/servers/:server_id/events/:event_id
+/// Cancel an event. Requires MANAGE_EVENTS unless the caller created it.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/events/{event_id}",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("event_id" = Uuid, Path, description = "Event ID")),
+    responses((status = 204))
+)]
+pub async fn delete_event(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, event_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    let event = find_event(&state, server_id, event_id, user_id).await?;
+    require_event_manager(&state, &event, user_id).await?;
+
+    if queries::delete_server_event(state.db.write(), event_id).await? {
+        broadcast_to_server(&state, server_id, WsServerMessage::EventDeleted { server_id, event_id }).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/v1/servers/:server_id/events/:event_id/rsvp
+/// Mark the caller as interested in or going to an event.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/events/{event_id}/rsvp",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("event_id" = Uuid, Path, description = "Event ID")),
+    request_body = EventRsvpRequest,
+    responses((status = 200, body = ServerEventResponse))
+)]
+pub async fn set_rsvp(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, event_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<EventRsvpRequest>,
+) -> AppResult<Json<ServerEventResponse>> {
+    find_event(&state, server_id, event_id, user_id).await?;
+
+    queries::set_event_rsvp(state.db.write(), event_id, user_id, req.status).await?;
+
+    broadcast_event(&state, server_id, event_id).await;
+    Ok(Json(find_event(&state, server_id, event_id, user_id).await?.into()))
+}
+
+/// DELETE /api/v1/servers/:server_id/events/:event_id/rsvp
+/// Withdraw the caller's RSVP.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/events/{event_id}/rsvp",
+    tag = "events",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("event_id" = Uuid, Path, description = "Event ID")),
+    responses((status = 200, body = ServerEventResponse))
+)]
+pub async fn remove_rsvp(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, event_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<ServerEventResponse>> {
+    find_event(&state, server_id, event_id, user_id).await?;
+
+    if queries::remove_event_rsvp(state.db.write(), event_id, user_id).await? {
+        broadcast_event(&state, server_id, event_id).await;
+    }
+    Ok(Json(find_event(&state, server_id, event_id, user_id).await?.into()))
+}
+
+/// Remind everyone who RSVPed to an event starting within
+/// `EVENT_REMINDER_MINUTES`. Runs as the `event-reminders` maintenance job;
+/// returns how many events were reminded.
+pub(crate) async fn send_reminders(state: &AppState) -> AppResult<u64> {
+    let minutes = state.config.event_reminder_minutes;
+    if minutes == 0 {
+        return Err(AppError::BadRequest("Event reminders are disabled".into()));
+    }
+
+    let due = queries::claim_due_event_reminders(state.db.primary(), minutes).await?;
+    for &(event_id, server_id, starts_at) in &due {
+        let msg = WsServerMessage::EventReminder { server_id, event_id, starts_at };
+        for user_id in queries::get_event_rsvp_user_ids(state.db.read(), event_id).await? {
+            crate::pubsub::broadcast_user_event(state, user_id, &msg).await;
+        }
+    }
+    Ok(due.len() as u64)
+}
+
+/// Look up an event in `server_id` for a member of that server.
+async fn find_event(
+    state: &AppState,
+    server_id: Uuid,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<ServerEvent> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    queries::find_server_event(state.db.read(), event_id, user_id)
+        .await?
+        .filter(|e| e.server_id == server_id)
+        .ok_or(AppError::NotFound("Event not found".into()))
+}
+
+/// Event creators may manage their own events; anyone else needs MANAGE_EVENTS.
+async fn require_event_manager(state: &AppState, event: &ServerEvent, user_id: Uuid) -> AppResult<()> {
+    if event.creator_id == Some(user_id) {
+        return Ok(());
+    }
+    queries::require_server_permission(state.db.read(), event.server_id, user_id, permissions::MANAGE_EVENTS)
+        .await
+}
+
+async fn require_server_channel(state: &AppState, server_id: Uuid, channel_id: Uuid) -> AppResult<()> {
+    match queries::find_channel_by_id(state.db.read(), channel_id).await? {
+        Some(channel) if channel.server_id == Some(server_id) => Ok(()),
+        _ => Err(AppError::Validation("channel_id is not a channel of this server".into())),
+    }
+}
+
+fn decode_meta(encoded: &str) -> AppResult<Vec<u8>> {
+    let meta = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+        .map_err(|_| AppError::Validation("Invalid encrypted_meta encoding".into()))?;
+    if meta.len() > 8192 {
+        return Err(AppError::Validation("encrypted_meta exceeds maximum size (8KB)".into()));
+    }
+    Ok(meta)
+}
+
+fn validate_schedule(starts_at: DateTime<Utc>, ends_at: Option<DateTime<Utc>>) -> AppResult<()> {
+    if ends_at.is_some_and(|end| end <= starts_at) {
+        return Err(AppError::Validation("ends_at must be after starts_at".into()));
+    }
+    Ok(())
+}
+
+/// Tell the server's members about an event's current state. The broadcast
+/// copy carries counts but no per-viewer RSVP.
+async fn broadcast_event(state: &AppState, server_id: Uuid, event_id: Uuid) {
+    if let Ok(Some(event)) = queries::find_server_event(state.db.read(), event_id, Uuid::nil()).await {
+        let event = ServerEventResponse::from(event);
+        broadcast_to_server(state, server_id, WsServerMessage::EventUpdated { server_id, event }).await;
+    }
+}
@@ -1,23 +1,34 @@
 use std::collections::HashMap;
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::queries;
-use crate::errors::{AppError, AppResult};
+use crate::db::{queries, Connection};
+use crate::errors::{AppError, AppResult, FieldError};
+use crate::export_format::{self, ExportChunk};
 use crate::middleware::AuthUser;
+use crate::restore_sections::{self, RestoreContext};
 use crate::models::{
-    ChannelCategory, ImportMessagesResponse, RestoreServerRequest, RestoreServerResponse, Role,
+    ChannelCategory, ImportFailure, ImportMessagesQuery, ImportMessagesResponse, RestoreArchive, RestoreCounts, RestoreJob,
+    RestoreRollbackResponse, RestoreServerQuery, RestoreServerRequest, RestoreServerResponse, Role,
 };
-use crate::ws::broadcast_to_server;
+use crate::restore_jobs::{self, RestoreTracker};
+use crate::ws::{broadcast_to_server, broadcast_to_server_members};
 use crate::AppState;
 use crate::models::WsServerMessage;
 
+/// How long a restore can be rolled back.
+pub const ROLLBACK_WINDOW_HOURS: i32 = 24;
+/// How far past the server's clock an imported timestamp may be.
+const IMPORT_MAX_CLOCK_SKEW_SECS: i64 = 300;
+/// Same cap as live messages.
+const MAX_IMPORT_BODY_LEN: usize = 8192;
+
 #[derive(Debug, Deserialize)]
 pub struct ExportManifestExporter {
     pub user_id: Uuid,
@@ -30,30 +41,51 @@ pub struct ExportManifest {
     pub rest: serde_json::Value,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
 pub struct VerifyExportRequest {
     pub manifest: serde_json::Value,
     pub signature: String, // base64-encoded Ed25519 signature
+    /// v2 only: chunk data to check against the manifest's chunk hashes
+    #[serde(default)]
+    pub chunks: Option<Vec<ExportChunk>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct VerifyExportSigner {
     pub user_id: Uuid,
     pub username: String,
     pub display_name: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct VerifyExportResponse {
     pub valid: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signer: Option<VerifyExportSigner>,
     pub identity_key_matches: bool,
+    /// The signing key is the signer's latest entry in the key transparency
+    /// log; clients can fetch an inclusion proof for it to rule out a swap.
+    pub identity_key_logged: bool,
+    pub format_version: u32,
+    /// Whether the supplied chunks match the (v2) manifest; absent when no
+    /// chunks were sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunks_valid: Option<bool>,
 }
 
 /// POST /api/v1/exports/verify
-/// Verifies an Ed25519 signature over a manifest's canonical JSON.
+/// Verifies an Ed25519 signature over a manifest's canonical JSON. A v2
+/// manifest commits to its chunk hashes, so sending the chunks as well checks
+/// the archive contents against the signature.
 /// Does not require authentication — anyone with a manifest can verify.
+#[utoipa::path(
+    post,
+    path = "/api/v1/exports/verify",
+    tag = "exports",
+    request_body = VerifyExportRequest,
+    responses((status = 200, body = VerifyExportResponse)),
+    security(())
+)]
 pub async fn verify_export(
     State(state): State<AppState>,
     Json(req): Json<VerifyExportRequest>,
@@ -69,17 +101,24 @@ pub async fn verify_export(
             AppError::Validation("manifest.exported_by.user_id is required".into())
         })?;
 
+    let format_version = export_format::format_version(&req.manifest)?;
+    let chunk_entries = match format_version {
+        export_format::FORMAT_V2 => Some(export_format::manifest_chunks(&req.manifest)?),
+        _ => None,
+    };
+
     // Decode signature
     let sig_bytes = base64::Engine::decode(
         &base64::engine::general_purpose::STANDARD,
         &req.signature,
     )
-    .map_err(|_| AppError::Validation("Invalid base64 signature".into()))?;
+    .map_err(|_| AppError::Validation("Invalid base64 signature".into()).with_code("INVALID_SIGNATURE"))?;
 
     if sig_bytes.len() != 64 {
         return Err(AppError::Validation(
             "Signature must be 64 bytes (Ed25519)".into(),
-        ));
+        )
+        .with_code("INVALID_SIGNATURE"));
     }
 
     // Look up user
@@ -105,7 +144,7 @@ pub async fn verify_export(
 
     let sig = ed25519_dalek::Signature::from_bytes(
         sig_bytes.as_slice().try_into().map_err(|_| {
-            AppError::Validation("Invalid signature length".into())
+            AppError::Validation("Invalid signature length".into()).with_code("INVALID_SIGNATURE")
         })?,
     );
 
@@ -123,14 +162,29 @@ pub async fn verify_export(
         false
     };
 
+    let identity_key_logged = queries::find_latest_key_log_entry(state.db.read(), user.id)
+        .await?
+        .is_some_and(|entry| entry.identity_key == *identity_key);
+
+    let chunks_valid = match (&chunk_entries, &req.chunks) {
+        (Some(entries), Some(chunks)) => Some(export_format::decode_sections(entries, chunks).is_ok()),
+        (None, Some(_)) => {
+            return Err(AppError::Validation("Only v2 manifests have chunks".into()).with_code("INVALID_MANIFEST"))
+        }
+        (_, None) => None,
+    };
+
     Ok(Json(VerifyExportResponse {
         valid,
         signer: Some(signer),
         identity_key_matches: valid,
+        identity_key_logged,
+        format_version,
+        chunks_valid,
     }))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
 pub struct LogExportRequest {
     pub scope: String, // "server", "channel", or "dm"
     pub server_id: Option<Uuid>,
@@ -141,6 +195,13 @@ pub struct LogExportRequest {
 /// POST /api/v1/exports/log
 /// Records an export event in the server's audit log.
 /// Called by the client after a successful client-side export.
+#[utoipa::path(
+    post,
+    path = "/api/v1/exports/log",
+    tag = "exports",
+    request_body = LogExportRequest,
+    responses((status = 200))
+)]
 pub async fn log_export(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -172,14 +233,54 @@ pub async fn log_export(
     Ok(Json(serde_json::json!({ "logged": true })))
 }
 
+/// A restore body is either the v1 document itself or a v2 archive, whose
+/// chunks are verified against its manifest and reassembled into the same
+/// document.
+fn parse_restore_body(body: serde_json::Value) -> AppResult<RestoreServerRequest> {
+    let document = match body.get("format_version") {
+        Some(_) => {
+            let archive: RestoreArchive = serde_json::from_value(body)
+                .map_err(|e| AppError::Validation(format!("Invalid restore archive: {}", e)))?;
+            if archive.format_version != export_format::FORMAT_V2
+                || export_format::format_version(&archive.manifest)? != export_format::FORMAT_V2
+            {
+                return Err(AppError::Validation(format!(
+                    "Unsupported export format version {}",
+                    archive.format_version
+                ))
+                .with_code("UNSUPPORTED_FORMAT"));
+            }
+            let entries = export_format::manifest_chunks(&archive.manifest)?;
+            let sections = export_format::decode_sections(&entries, &archive.chunks)?;
+            export_format::assemble_document(sections)?
+        }
+        None => body,
+    };
+    serde_json::from_value(document)
+        .map_err(|e| AppError::Validation(format!("Invalid restore document: {}", e)))
+}
+
 /// POST /api/v1/servers/:server_id/restore
 /// Restores server structure (categories, channels, roles, permission overwrites)
-/// from a parsed .haven backup. Requires MANAGE_SERVER permission or owner.
+/// and any registered configuration sections from a parsed .haven backup, sent
+/// either as the v1 document or as a v2 archive (`RestoreArchive`).
+/// Progress is tracked as a restore job (`job_id` in the response); pass
+/// `total_messages` to keep the job open for that many imported messages.
+/// Requires MANAGE_SERVER permission or owner.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/restore",
+    tag = "exports",
+    params(("server_id" = Uuid, Path, description = "Server ID"), RestoreServerQuery),
+    request_body = RestoreServerRequest,
+    responses((status = 200, body = RestoreServerResponse))
+)]
 pub async fn restore_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
-    Json(req): Json<RestoreServerRequest>,
+    Query(query): Query<RestoreServerQuery>,
+    Json(body): Json<serde_json::Value>,
 ) -> AppResult<Json<RestoreServerResponse>> {
     // Verify membership
     if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
@@ -195,85 +296,154 @@ pub async fn restore_server(
         ));
     }
 
-    // Validate limits
-    if req.categories.len() > 50 {
-        return Err(AppError::Validation(
-            "Too many categories (max 50)".into(),
-        ));
-    }
-    if req.channels.len() > 500 {
-        return Err(AppError::Validation(
-            "Too many channels (max 500)".into(),
-        ));
+    let total_messages = query.total_messages.unwrap_or(0);
+    if total_messages < 0 {
+        return Err(AppError::Validation("total_messages must not be negative".into()));
     }
-    if req.roles.len() > 250 {
-        return Err(AppError::Validation("Too many roles (max 250)".into()));
+
+    let req = parse_restore_body(body)?;
+    validate_restore_limits(&req)?;
+
+    let tracker = RestoreTracker::start(&state, server_id, user_id).await?;
+    let mut response = match restore_structure_tx(&state, server_id, user_id, &req).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracker.fail(&e.to_string()).await;
+            return Err(e);
+        }
+    };
+    response.job_id = Some(tracker.job_id());
+
+    // Finishing the structure completes the job unless messages follow
+    if total_messages > 0 {
+        tracker.set_phase(restore_jobs::PHASE_MESSAGES, total_messages).await?;
+    } else {
+        tracker.advance(1).await?;
     }
 
-    // Begin transaction
-    let pool = state.db.write();
-    let mut tx = pool.begin().await?;
+    // Channels and roles were replaced wholesale: a server-level change makes
+    // delta sync clients refetch all of them (and changes the list ETags)
+    queries::record_sync_change(state.db.write(), server_id, queries::SyncEntity::Server, server_id, false).await?;
 
-    // ── Wipe existing server structure before restore ──
-    // Clean up orphaned records (FK constraints to messages were dropped
-    // during partition migration, so these won't cascade from channel deletion)
-    sqlx::query(
-        r#"DELETE FROM attachments WHERE message_id IN (
-             SELECT m.id FROM messages m
-             JOIN channels c ON c.id = m.channel_id
-             WHERE c.server_id = $1
-           )"#,
+    // Audit log (best effort, outside transaction)
+    let _ = queries::insert_audit_log(
+        state.db.write(),
+        server_id,
+        user_id,
+        "server_restore",
+        Some("server"),
+        Some(server_id),
+        Some(&serde_json::json!({
+            "source_server_name": req.server.name,
+            "categories_created": response.categories_created,
+            "channels_created": response.channels_created,
+            "roles_created": response.roles_created,
+            "sections_restored": response.sections_restored,
+        })),
+        None,
     )
-    .bind(server_id)
-    .execute(&mut *tx)
-    .await?;
+    .await;
 
-    sqlx::query(
-        r#"DELETE FROM reactions WHERE message_id IN (
-             SELECT m.id FROM messages m
-             JOIN channels c ON c.id = m.channel_id
-             WHERE c.server_id = $1
-           )"#,
+    // Notify connected members, with the channel map so they can remap
+    // open channels, drafts and the like without a full reload
+    broadcast_to_server_members(
+        &state,
+        server_id,
+        WsServerMessage::ServerRestored {
+            server_id,
+            channel_id_map: response.channel_id_map.clone(),
+            counts: RestoreCounts::from(&response),
+        },
     )
-    .bind(server_id)
-    .execute(&mut *tx)
-    .await?;
+    .await;
 
-    sqlx::query(
-        r#"DELETE FROM reports WHERE message_id IN (
-             SELECT m.id FROM messages m
-             JOIN channels c ON c.id = m.channel_id
-             WHERE c.server_id = $1
-           )"#,
-    )
-    .bind(server_id)
-    .execute(&mut *tx)
-    .await?;
+    Ok(Json(response))
+}
 
-    // Null out system_channel_id before deleting channels
-    sqlx::query("UPDATE servers SET system_channel_id = NULL WHERE id = $1")
-        .bind(server_id)
-        .execute(&mut *tx)
-        .await?;
+async fn restore_structure_tx(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+    req: &RestoreServerRequest,
+) -> AppResult<RestoreServerResponse> {
+    let mut tx = state.db.write().begin().await?;
+    let snapshot_id = snapshot_structure(state, &mut tx, server_id, user_id).await?;
+    let (mut response, _) = restore_structure(&mut tx, server_id, user_id, req).await?;
+    tx.commit().await?;
+    response.snapshot_id = Some(snapshot_id);
+    Ok(response)
+}
 
-    // Delete all channels (cascades to messages, channel_members,
-    // channel_permission_overwrites, sender_key_distributions, pinned_messages, read_states)
-    sqlx::query("DELETE FROM channels WHERE server_id = $1")
-        .bind(server_id)
-        .execute(&mut *tx)
-        .await?;
+/// Snapshot the server's structure and configuration sections and detach its
+/// channels, so the restore that follows in the same transaction can be
+/// rolled back for [`ROLLBACK_WINDOW_HOURS`].
+pub(crate) async fn snapshot_structure(
+    state: &AppState,
+    conn: &mut Connection,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Uuid> {
+    let sections = restore_sections::export_all(state.db.read(), server_id).await?;
+    let sections = serde_json::to_value(sections).expect("exported sections serialize");
+    let counts = queries::count_server_structure(&mut *conn, server_id).await?;
+    let snapshot_id =
+        queries::create_restore_snapshot(&mut *conn, server_id, user_id, &sections, ROLLBACK_WINDOW_HOURS).await?;
+
+    queries::insert_audit_log_in(
+        &mut *conn,
+        server_id,
+        user_id,
+        "server_restore_detach",
+        Some("server"),
+        Some(server_id),
+        Some(&serde_json::json!({
+            "snapshot_id": snapshot_id,
+            "channels_detached": counts.channels,
+            "channel_members_removed": counts.channel_members,
+            "overwrites_detached": counts.overwrites,
+        })),
+        None,
+    )
+    .await?;
+    Ok(snapshot_id)
+}
 
-    // Delete all categories
-    sqlx::query("DELETE FROM channel_categories WHERE server_id = $1")
-        .bind(server_id)
-        .execute(&mut *tx)
-        .await?;
+/// Server-wide limits on a restore document.
+pub(crate) fn validate_restore_limits(req: &RestoreServerRequest) -> AppResult<()> {
+    if req.categories.len() > 50 {
+        return Err(AppError::Validation(
+            "Too many categories (max 50)".into(),
+        )
+        .with_code("RESTORE_LIMIT_EXCEEDED"));
+    }
+    if req.channels.len() > 500 {
+        return Err(AppError::Validation(
+            "Too many channels (max 500)".into(),
+        )
+        .with_code("RESTORE_LIMIT_EXCEEDED"));
+    }
+    if req.roles.len() > 250 {
+        return Err(
+            AppError::Validation("Too many roles (max 250)".into()).with_code("RESTORE_LIMIT_EXCEEDED")
+        );
+    }
+    Ok(())
+}
 
-    // Delete non-default roles (cascades to member_roles)
-    sqlx::query("DELETE FROM roles WHERE server_id = $1 AND is_default = FALSE")
-        .bind(server_id)
-        .execute(&mut *tx)
-        .await?;
+/// Replace a server's structure (categories, channels, roles, overwrites and
+/// configuration sections) with a restore document, inside the caller's
+/// transaction. Returns the restore summary and the backup role id → new role
+/// id map.
+pub(crate) async fn restore_structure(
+    conn: &mut Connection,
+    server_id: Uuid,
+    user_id: Uuid,
+    req: &RestoreServerRequest,
+) -> AppResult<(RestoreServerResponse, HashMap<String, Uuid>)> {
+    // ── Wipe existing server structure before restore ──
+    // After a snapshot the channels are already detached and this only
+    // removes categories and roles.
+    wipe_structure(&mut *conn, server_id, user_id, "server_restore_wipe").await?;
 
     // ID Mapping: old backup ID → new DB UUID
     let mut category_map: HashMap<String, Uuid> = HashMap::new();
@@ -286,16 +456,24 @@ pub async fn restore_server(
     let mut roles_updated = 0usize;
     let mut overwrites_applied = 0usize;
 
+    // Restored rows all share one created_at, which can't break position ties
+    // the way it did on the original server: renumber positions densely in
+    // backup order instead.
+    let category_positions = dense_positions(req.categories.iter().map(|c| ((), c.position)));
+    let channel_positions =
+        dense_positions(req.channels.iter().map(|c| (c.category_id.as_deref(), c.position)));
+
     // Step 1: Create categories
-    for cat in &req.categories {
+    for (cat, position) in req.categories.iter().zip(category_positions) {
         let new_cat = sqlx::query_as::<_, ChannelCategory>(
-            r#"INSERT INTO channel_categories (server_id, name, position)
-               VALUES ($1, $2, $3) RETURNING *"#,
+            r#"INSERT INTO channel_categories (server_id, name, position, collapsed_by_default)
+               VALUES ($1, $2, $3, $4) RETURNING *"#,
         )
         .bind(server_id)
         .bind(&cat.name)
-        .bind(cat.position)
-        .fetch_one(&mut *tx)
+        .bind(position)
+        .bind(cat.collapsed_by_default)
+        .fetch_one(&mut *conn)
         .await?;
 
         category_map.insert(cat.id.clone(), new_cat.id);
@@ -303,7 +481,7 @@ pub async fn restore_server(
     }
 
     // Step 2: Create channels
-    for ch in &req.channels {
+    for (ch, position) in req.channels.iter().zip(channel_positions) {
         // Skip DM/group channels
         if ch.channel_type == "dm" || ch.channel_type == "group_dm" {
             continue;
@@ -326,10 +504,10 @@ pub async fn restore_server(
         .bind(Some(server_id))
         .bind(ch.name.as_bytes())
         .bind(&ch.channel_type)
-        .bind(ch.position)
+        .bind(position)
         .bind(new_category_id)
         .bind(ch.is_private)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
 
         // Add restoring user as channel member
@@ -341,20 +519,38 @@ pub async fn restore_server(
         .bind(Uuid::new_v4())
         .bind(new_channel_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
 
         channel_map.insert(ch.id.clone(), new_channel_id);
         channels_created += 1;
     }
 
+    // Bridge links, retention policies and the like of channels this restore
+    // replaced move over to their restored copies
+    let remap: Vec<(Uuid, Uuid)> = channel_map
+        .iter()
+        .filter_map(|(old, new)| Some((old.parse().ok()?, *new)))
+        .collect();
+    let channel_rows_remapped = queries::remap_channel_rows(&mut *conn, server_id, &remap).await? as usize;
+
+    // Point system messages at the backup's system channel, if it came back
+    let system_channel_id = req.server.system_channel_id.as_ref().and_then(|id| channel_map.get(id));
+    if let Some(system_channel_id) = system_channel_id {
+        sqlx::query("UPDATE servers SET system_channel_id = $2 WHERE id = $1")
+            .bind(server_id)
+            .bind(system_channel_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
     // Step 3: Roles
     // Find existing @everyone role to update its permissions
     let existing_everyone = sqlx::query_as::<_, Role>(
         "SELECT * FROM roles WHERE server_id = $1 AND is_default = TRUE LIMIT 1",
     )
     .bind(server_id)
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut *conn)
     .await?;
 
     for role in &req.roles {
@@ -364,7 +560,7 @@ pub async fn restore_server(
                 sqlx::query("UPDATE roles SET permissions = $1 WHERE id = $2")
                     .bind(role.permissions)
                     .bind(everyone.id)
-                    .execute(&mut *tx)
+                    .execute(&mut *conn)
                     .await?;
                 role_map.insert(role.id.clone(), everyone.id);
                 roles_updated += 1;
@@ -380,7 +576,7 @@ pub async fn restore_server(
             .bind(role.color.as_deref())
             .bind(role.permissions)
             .bind(role.position)
-            .fetch_one(&mut *tx)
+            .fetch_one(&mut *conn)
             .await?;
 
             role_map.insert(role.id.clone(), new_role.id);
@@ -416,34 +612,233 @@ pub async fn restore_server(
         .bind(new_target_id)
         .bind(ow.allow)
         .bind(ow.deny)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
 
         overwrites_applied += 1;
     }
 
-    // Commit transaction
+    // Step 5: Pluggable configuration sections, in registry order
+    let ctx = RestoreContext {
+        server_id,
+        user_id,
+        channel_map: &channel_map,
+        role_map: &role_map,
+    };
+    let mut sections = req.sections.clone();
+    let mut sections_restored: HashMap<String, usize> = HashMap::new();
+    for section in restore_sections::registry() {
+        if let Some(data) = sections.remove(section.key()) {
+            let count = section.restore(&mut *conn, &ctx, data).await?;
+            sections_restored.insert(section.key().to_string(), count);
+        }
+    }
+    let mut sections_skipped: Vec<String> = sections.into_keys().collect();
+    sections_skipped.sort();
+
+    // Build channel_id_map as String→String for JSON serialization
+    let channel_id_map: HashMap<String, String> = channel_map
+        .iter()
+        .map(|(old, new)| (old.clone(), new.to_string()))
+        .collect();
+
+    let response = RestoreServerResponse {
+        categories_created,
+        channels_created,
+        roles_created,
+        roles_updated,
+        overwrites_applied,
+        channel_rows_remapped,
+        channel_id_map,
+        sections_restored,
+        sections_skipped,
+        job_id: None,
+        snapshot_id: None,
+    };
+    Ok((response, role_map))
+}
+
+/// Positions 0, 1, 2… within each group, ordered by original position and
+/// then by order of appearance, returned in input order.
+fn dense_positions<K: Eq + std::hash::Hash>(items: impl Iterator<Item = (K, i32)>) -> Vec<i32> {
+    let items: Vec<(K, i32)> = items.collect();
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    // Stable, so ties keep backup order
+    order.sort_by_key(|&i| items[i].1);
+
+    let mut next: HashMap<&K, i32> = HashMap::new();
+    let mut positions = vec![0; items.len()];
+    for i in order {
+        let position = next.entry(&items[i].0).or_insert(0);
+        positions[i] = *position;
+        *position += 1;
+    }
+    positions
+}
+
+/// Delete a server's channels (with their messages), categories and
+/// non-default roles, recording what went as an `action` audit entry in the
+/// same transaction.
+async fn wipe_structure(conn: &mut Connection, server_id: Uuid, user_id: Uuid, action: &str) -> AppResult<()> {
+    let counts = queries::count_server_structure(&mut *conn, server_id).await?;
+
+    // Clean up orphaned records (FK constraints to messages were dropped
+    // during partition migration, so these won't cascade from channel deletion)
+
+    let reactions_deleted = sqlx::query(
+        r#"DELETE FROM reactions WHERE message_id IN (
+             SELECT m.id FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+             WHERE c.server_id = $1
+           )"#,
+    )
+    .bind(server_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    let reports_deleted = sqlx::query(
+        r#"DELETE FROM reports WHERE message_id IN (
+             SELECT m.id FROM messages m
+             JOIN channels c ON c.id = m.channel_id
+             WHERE c.server_id = $1
+           )"#,
+    )
+    .bind(server_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    // Null out system_channel_id before deleting channels
+    sqlx::query("UPDATE servers SET system_channel_id = NULL WHERE id = $1")
+        .bind(server_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Delete all channels (cascades to messages, channel_members,
+    // channel_permission_overwrites, sender_key_distributions, pinned_messages, read_states)
+    sqlx::query("DELETE FROM channels WHERE server_id = $1")
+        .bind(server_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Delete all categories
+    sqlx::query("DELETE FROM channel_categories WHERE server_id = $1")
+        .bind(server_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Delete non-default roles (cascades to member_roles)
+    sqlx::query("DELETE FROM roles WHERE server_id = $1 AND is_default = FALSE")
+        .bind(server_id)
+        .execute(&mut *conn)
+        .await?;
+
+    queries::insert_audit_log_in(
+        &mut *conn,
+        server_id,
+        user_id,
+        action,
+        Some("server"),
+        Some(server_id),
+        Some(&serde_json::json!({
+            "channels_deleted": counts.channels,
+            "channel_members_removed": counts.channel_members,
+            "overwrites_deleted": counts.overwrites,
+            "categories_deleted": counts.categories,
+            "roles_deleted": counts.roles,
+            "member_roles_removed": counts.member_roles,
+            "reactions_deleted": reactions_deleted,
+            "reports_deleted": reports_deleted,
+        })),
+        None,
+    )
+    .await?;
+    Ok(())
+}
+
+/// POST /api/v1/servers/:server_id/restore/rollback
+/// Undoes the latest restore within [`ROLLBACK_WINDOW_HOURS`]: the restored
+/// structure and any messages imported into it are deleted, and the snapshot
+/// taken before the restore is put back, original channels and messages
+/// included. Requires MANAGE_SERVER permission or owner.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/restore/rollback",
+    tag = "exports",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = RestoreRollbackResponse))
+)]
+pub async fn rollback_restore(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<RestoreRollbackResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let (is_owner, perms) =
+        queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner && !crate::permissions::has_permission(perms, crate::permissions::MANAGE_SERVER) {
+        return Err(AppError::Forbidden(
+            "Missing MANAGE_SERVER permission".into(),
+        ));
+    }
+
+    let snapshot = queries::get_latest_restore_snapshot(state.db.write(), server_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("No restore to roll back".into()).with_code("NO_RESTORE_SNAPSHOT")
+        })?;
+
+    let mut tx = state.db.write().begin().await?;
+    wipe_structure(&mut tx, server_id, user_id, "server_restore_rollback_wipe").await?;
+    let mut response = queries::apply_restore_snapshot(&mut tx, &snapshot).await?;
+
+    // Sections go back through their own restore, with every id mapping to itself
+    let ids = |key: &str| -> HashMap<String, Uuid> {
+        snapshot.structure[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|row| row["id"].as_str()?.parse().ok())
+            .map(|id: Uuid| (id.to_string(), id))
+            .collect()
+    };
+    let channel_map = ids("channels");
+    let role_map = ids("roles");
+    let ctx = RestoreContext {
+        server_id,
+        user_id,
+        channel_map: &channel_map,
+        role_map: &role_map,
+    };
+    for section in restore_sections::registry() {
+        if let Some(data) = snapshot.structure["sections"].get(section.key()) {
+            let count = section.restore(&mut tx, &ctx, data.clone()).await?;
+            response.sections_restored.insert(section.key().to_string(), count);
+        }
+    }
     tx.commit().await?;
 
-    // Audit log (best effort, outside transaction)
+    queries::record_sync_change(state.db.write(), server_id, queries::SyncEntity::Server, server_id, false).await?;
+
     let _ = queries::insert_audit_log(
         state.db.write(),
         server_id,
         user_id,
-        "server_restore",
+        "server_restore_rollback",
         Some("server"),
         Some(server_id),
         Some(&serde_json::json!({
-            "source_server_name": req.server.name,
-            "categories_created": categories_created,
-            "channels_created": channels_created,
-            "roles_created": roles_created,
+            "snapshot_id": snapshot.id,
+            "channels_restored": response.channels_restored,
+            "roles_restored": response.roles_restored,
         })),
         None,
     )
     .await;
 
-    // Notify connected members
     broadcast_to_server(
         &state,
         server_id,
@@ -451,30 +846,27 @@ pub async fn restore_server(
     )
     .await;
 
-    // Build channel_id_map as String→String for JSON serialization
-    let channel_id_map: HashMap<String, String> = channel_map
-        .into_iter()
-        .map(|(old, new)| (old, new.to_string()))
-        .collect();
-
-    Ok(Json(RestoreServerResponse {
-        categories_created,
-        channels_created,
-        roles_created,
-        roles_updated,
-        overwrites_applied,
-        channel_id_map,
-    }))
+    Ok(Json(response))
 }
 
 /// POST /api/v1/channels/:channel_id/import-messages
 /// Imports a batch of messages into a channel (used during server restore).
-/// Messages are stored with their original timestamps.
+/// Messages are stored with their original timestamps. With `job_id`, the
+/// batch counts towards that restore job's progress.
 /// Requires MANAGE_SERVER permission on the channel's server.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/import-messages",
+    tag = "exports",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ImportMessagesQuery),
+    request_body = crate::models::ImportMessagesRequest,
+    responses((status = 200, body = ImportMessagesResponse))
+)]
 pub async fn import_messages(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(channel_id): Path<Uuid>,
+    Query(query): Query<ImportMessagesQuery>,
     Json(req): Json<crate::models::ImportMessagesRequest>,
 ) -> AppResult<Json<ImportMessagesResponse>> {
     // Validate batch size
@@ -502,42 +894,230 @@ pub async fn import_messages(
         ));
     }
 
-    let pool = state.db.write();
-    let mut tx = pool.begin().await?;
-    let mut imported = 0usize;
+    // A rejected batch changes nothing, so it leaves the restore job running
+    let (rows, errors) = check_import_batch(&req.messages, Utc::now());
+    if !errors.is_empty() && !req.continue_on_error {
+        let fields = errors.into_iter().map(ImportProblem::into_field_error).collect();
+        return Err(AppError::InvalidFields(fields).with_code("INVALID_IMPORT_MESSAGES"));
+    }
+    let failed = import_failures(errors);
+
+    let tracker = match query.job_id {
+        Some(job_id) => {
+            // Only the requester's own job for this server can be advanced
+            let job = queries::get_restore_job(state.db.write(), job_id)
+                .await?
+                .filter(|job| job.user_id == user_id && job.server_id == server_id)
+                .ok_or(AppError::NotFound("Restore job not found".into()))?;
+            Some(RestoreTracker::attach(&state, job.id))
+        }
+        None => None,
+    };
 
-    for msg in &req.messages {
-        // Decode base64 fields
-        let sender_token = base64::Engine::decode(
-            &base64::engine::general_purpose::STANDARD,
-            &msg.sender_token,
-        )
-        .map_err(|_| AppError::Validation("Invalid base64 sender_token".into()))?;
+    let imported = match insert_imported_messages(&state, channel_id, &rows).await {
+        Ok(imported) => imported,
+        Err(e) => {
+            if let Some(tracker) = &tracker {
+                tracker.fail(&e.to_string()).await;
+            }
+            return Err(e);
+        }
+    };
 
-        let encrypted_body = base64::Engine::decode(
-            &base64::engine::general_purpose::STANDARD,
-            &msg.encrypted_body,
-        )
-        .map_err(|_| AppError::Validation("Invalid base64 encrypted_body".into()))?;
+    // Skipped messages count as processed, so the job can still complete
+    if let Some(tracker) = &tracker {
+        tracker.advance((imported + failed.len()) as i64).await?;
+    }
+
+    Ok(Json(ImportMessagesResponse { imported, failed }))
+}
+
+/// GET /api/v1/servers/:server_id/restore-jobs
+/// The caller's restore jobs on this server, newest first, so a reloaded
+/// client can find a running restore and re-attach to its progress events.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/restore-jobs",
+    tag = "exports",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<RestoreJob>))
+)]
+pub async fn list_restore_jobs(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<Vec<RestoreJob>>> {
+    let jobs = queries::list_restore_jobs(state.db.read(), server_id, user_id, 20).await?;
+    Ok(Json(jobs))
+}
+
+/// GET /api/v1/servers/:server_id/restore-jobs/:job_id
+/// Current state of one of the caller's restore jobs.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/restore-jobs/{job_id}",
+    tag = "exports",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("job_id" = Uuid, Path, description = "Job ID")),
+    responses((status = 200, body = RestoreJob))
+)]
+pub async fn get_restore_job(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, job_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<RestoreJob>> {
+    let job = queries::get_restore_job(state.db.read(), job_id)
+        .await?
+        .filter(|job| job.user_id == user_id && job.server_id == server_id)
+        .ok_or(AppError::NotFound("Restore job not found".into()))?;
+    Ok(Json(job))
+}
+
+/// An imported message that passed validation.
+struct ImportRow<'a> {
+    sender_token: Vec<u8>,
+    encrypted_body: Vec<u8>,
+    timestamp: DateTime<Utc>,
+    sender_id: Option<Uuid>,
+    reply_to_id: Option<Uuid>,
+    message_type: &'a str,
+    has_attachments: bool,
+}
+
+/// Check every message of an import batch. Timestamps must not run ahead of
+/// `now` by more than [`IMPORT_MAX_CLOCK_SKEW_SECS`] and must not go backwards
+/// within the batch, and bodies are capped like live messages. Returns the
+/// valid messages (with their index in the batch) and every problem found.
+fn check_import_batch(
+    messages: &[crate::models::ImportMessage],
+    now: DateTime<Utc>,
+) -> (Vec<(usize, ImportRow<'_>)>, Vec<ImportProblem>) {
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let latest = now + chrono::Duration::seconds(IMPORT_MAX_CLOCK_SKEW_SECS);
+    let mut errors = Vec::new();
+    let mut rows = Vec::with_capacity(messages.len());
+    let mut previous: Option<DateTime<Utc>> = None;
+
+    for (index, msg) in messages.iter().enumerate() {
+        let errors_before = errors.len();
+        let mut error = |field: &'static str, code: &'static str, reason: String| {
+            errors.push(ImportProblem { index, field, code, reason });
+        };
+
+        let sender_token = base64::Engine::decode(b64, &msg.sender_token)
+            .map_err(|_| error("sender_token", "base64", "invalid base64 sender_token".into()))
+            .ok();
+
+        let encrypted_body = match base64::Engine::decode(b64, &msg.encrypted_body) {
+            Ok(body) if body.len() > MAX_IMPORT_BODY_LEN => {
+                error(
+                    "encrypted_body",
+                    "length",
+                    format!("encrypted_body exceeds {} bytes", MAX_IMPORT_BODY_LEN),
+                );
+                None
+            }
+            Ok(body) => Some(body),
+            Err(_) => {
+                error("encrypted_body", "base64", "invalid base64 encrypted_body".into());
+                None
+            }
+        };
 
-        // Parse timestamp
-        let timestamp = DateTime::parse_from_rfc3339(&msg.timestamp)
+        if msg.message_type.is_empty() || msg.message_type.len() > 20 {
+            error("message_type", "length", "message_type must be 1-20 characters".into());
+        }
+
+        let timestamp = match DateTime::parse_from_rfc3339(&msg.timestamp)
             .or_else(|_| DateTime::parse_from_str(&msg.timestamp, "%Y-%m-%dT%H:%M:%S%.fZ"))
-            .map(|dt| dt.with_timezone(&chrono::Utc))
-            .map_err(|_| AppError::Validation(format!("Invalid timestamp: {}", msg.timestamp)))?;
+            .map(|dt| dt.with_timezone(&Utc))
+        {
+            Ok(ts) if ts > latest => {
+                error("timestamp", "future", format!("timestamp {} is in the future", msg.timestamp));
+                None
+            }
+            Ok(ts) if previous.is_some_and(|prev| ts < prev) => {
+                error(
+                    "timestamp",
+                    "order",
+                    "timestamp is earlier than the previous message's".into(),
+                );
+                None
+            }
+            Ok(ts) => Some(ts),
+            Err(_) => {
+                error("timestamp", "format", format!("invalid timestamp {}", msg.timestamp));
+                None
+            }
+        };
 
-        // Parse optional sender_id
-        let sender_id = msg
-            .sender_id
-            .as_ref()
-            .and_then(|s| s.parse::<Uuid>().ok());
+        if errors.len() > errors_before {
+            continue;
+        }
+        if let (Some(sender_token), Some(encrypted_body), Some(timestamp)) =
+            (sender_token, encrypted_body, timestamp)
+        {
+            previous = Some(timestamp);
+            rows.push((
+                index,
+                ImportRow {
+                    sender_token,
+                    encrypted_body,
+                    timestamp,
+                    // Ids that don't parse are dropped rather than rejected
+                    sender_id: msg.sender_id.as_ref().and_then(|s| s.parse().ok()),
+                    reply_to_id: msg.reply_to_id.as_ref().and_then(|s| s.parse().ok()),
+                    message_type: &msg.message_type,
+                    has_attachments: msg.has_attachments,
+                },
+            ));
+        }
+    }
+    (rows, errors)
+}
 
-        // Parse optional reply_to_id
-        let reply_to_id = msg
-            .reply_to_id
-            .as_ref()
-            .and_then(|s| s.parse::<Uuid>().ok());
+/// A problem with one field of one imported message.
+struct ImportProblem {
+    index: usize,
+    field: &'static str,
+    code: &'static str,
+    reason: String,
+}
 
+impl ImportProblem {
+    /// As a `details` entry naming the message's index (`messages[3].timestamp`).
+    fn into_field_error(self) -> FieldError {
+        FieldError {
+            field: format!("messages[{}].{}", self.index, self.field),
+            code: self.code.into(),
+            message: format!("Message {}: {}", self.index, self.reason),
+        }
+    }
+}
+
+/// One `failed` entry per rejected message, its problems joined.
+fn import_failures(problems: Vec<ImportProblem>) -> Vec<ImportFailure> {
+    let mut failed: Vec<ImportFailure> = Vec::new();
+    for problem in problems {
+        match failed.last_mut() {
+            Some(last) if last.index == problem.index => {
+                last.reason.push_str("; ");
+                last.reason.push_str(&problem.reason);
+            }
+            _ => failed.push(ImportFailure { index: problem.index, reason: problem.reason }),
+        }
+    }
+    failed
+}
+
+async fn insert_imported_messages(
+    state: &AppState,
+    channel_id: Uuid,
+    rows: &[(usize, ImportRow<'_>)],
+) -> AppResult<usize> {
+    let pool = state.db.write();
+    let mut tx = pool.begin().await?;
+
+    for (_, row) in rows {
         sqlx::query(
             r#"INSERT INTO messages (id, channel_id, sender_token, encrypted_body,
                                      timestamp, has_attachments, sender_id, reply_to_id, message_type)
@@ -545,20 +1125,17 @@ pub async fn import_messages(
         )
         .bind(Uuid::new_v4())
         .bind(channel_id)
-        .bind(&sender_token)
-        .bind(&encrypted_body)
-        .bind(timestamp)
-        .bind(msg.has_attachments)
-        .bind(sender_id)
-        .bind(reply_to_id)
-        .bind(&msg.message_type)
+        .bind(&row.sender_token)
+        .bind(&row.encrypted_body)
+        .bind(row.timestamp)
+        .bind(row.has_attachments)
+        .bind(row.sender_id)
+        .bind(row.reply_to_id)
+        .bind(row.message_type)
         .execute(&mut *tx)
         .await?;
-
-        imported += 1;
     }
 
     tx.commit().await?;
-
-    Ok(Json(ImportMessagesResponse { imported }))
+    Ok(rows.len())
 }
@@ -0,0 +1,289 @@
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Path, State},
+    http::{HeaderMap, Method},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::federation::{self, client, signing};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::pubsub;
+use crate::ws::deliver_new_message;
+use crate::AppState;
+
+/// Authenticate a server-to-server request and return the origin server name.
+async fn verify_origin(
+    state: &AppState,
+    headers: &HeaderMap,
+    method: &Method,
+    uri: &OriginalUri,
+    body: &[u8],
+) -> AppResult<String> {
+    if !state.config.federation_enabled() {
+        return Err(AppError::NotFound("Federation is disabled".into()));
+    }
+    let auth = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(signing::parse_auth_header)
+        .ok_or(AppError::AuthError("Missing federation signature".into()).with_code("MISSING_SIGNATURE"))?;
+
+    if !federation::valid_origin(&auth.origin) {
+        return Err(AppError::AuthError("Invalid federation origin".into()).with_code("INVALID_ORIGIN"));
+    }
+    if !auth.destination.eq_ignore_ascii_case(&state.config.federation_server_name) {
+        return Err(AppError::AuthError("Request was signed for another server".into()));
+    }
+    if !federation::server_allowed(&state.config, &auth.origin) {
+        return Err(AppError::Forbidden("Federation with this server is not allowed".into()));
+    }
+
+    if !auth.is_fresh(chrono::Utc::now().timestamp()) {
+        return Err(AppError::AuthError("Federation signature has expired".into()).with_code("STALE_SIGNATURE"));
+    }
+
+    let key = client::server_key(state, &auth.origin).await?;
+    if !signing::verify_request(&key, &auth, method.as_str(), uri.0.path(), body) {
+        return Err(AppError::AuthError("Invalid federation signature".into()).with_code("INVALID_SIGNATURE"));
+    }
+    Ok(auth.origin)
+}
+
+/// GET /api/v1/federation/key
+/// This server's public signing key. Unauthenticated — it is how other
+/// servers learn to verify us.
+#[utoipa::path(
+    get,
+    path = "/api/v1/federation/key",
+    tag = "federation",
+    responses((status = 200, body = FederationKeyResponse)),
+    security(())
+)]
+pub async fn get_server_key(State(state): State<AppState>) -> AppResult<Json<FederationKeyResponse>> {
+    if !state.config.federation_enabled() {
+        return Err(AppError::NotFound("Federation is disabled".into()));
+    }
+    let key = signing::signing_key(&state.config)?;
+    Ok(Json(FederationKeyResponse {
+        server_name: state.config.federation_server_name.clone(),
+        public_key: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            key.verifying_key().to_bytes(),
+        ),
+    }))
+}
+
+/// GET /api/v1/federation/users/:username
+/// A local user's public identity, for a signed request from another server.
+#[utoipa::path(
+    get,
+    path = "/api/v1/federation/users/{username}",
+    tag = "federation",
+    params(("username" = String, Path, description = "Username")),
+    responses((status = 200, body = FederatedProfile)),
+    security(())
+)]
+pub async fn get_user_profile(
+    State(state): State<AppState>,
+    method: Method,
+    uri: OriginalUri,
+    headers: HeaderMap,
+    Path(username): Path<String>,
+) -> AppResult<Json<FederatedProfile>> {
+    verify_origin(&state, &headers, &method, &uri, &[]).await?;
+
+    let user = queries::find_user_by_username(state.db.read(), &username)
+        .await?
+        .filter(|u| !u.is_system && !federation::is_remote_username(&u.username))
+        .ok_or(AppError::UserNotFound)?;
+    Ok(Json(FederatedProfile::from(&user)))
+}
+
+/// POST /api/v1/federation/resolve
+/// Look up `user@server` and return the local account standing in for them,
+/// which can then be passed to `POST /dm` like any other user id.
+#[utoipa::path(
+    post,
+    path = "/api/v1/federation/resolve",
+    tag = "federation",
+    request_body = ResolveFederatedUserRequest,
+    responses((status = 200, body = UserPublic))
+)]
+pub async fn resolve_user(
+    State(state): State<AppState>,
+    AuthUser(_user_id): AuthUser,
+    Json(req): Json<ResolveFederatedUserRequest>,
+) -> AppResult<Json<UserPublic>> {
+    let user = federation::resolve_remote_user(&state, &req.address).await?;
+    Ok(Json(user.into()))
+}
+
+/// PUT /api/v1/federation/transactions/:txn_id
+/// Apply a batch of events from another server. Retries with the same id
+/// are acknowledged without being applied twice.
+#[utoipa::path(
+    put,
+    path = "/api/v1/federation/transactions/{txn_id}",
+    tag = "federation",
+    params(("txn_id" = String, Path, description = "Client transaction ID")),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200)),
+    security(())
+)]
+pub async fn receive_transaction(
+    State(state): State<AppState>,
+    method: Method,
+    uri: OriginalUri,
+    headers: HeaderMap,
+    Path(txn_id): Path<String>,
+    body: Bytes,
+) -> AppResult<Json<serde_json::Value>> {
+    let origin = verify_origin(&state, &headers, &method, &uri, &body).await?;
+
+    let txn: FederationTransaction = serde_json::from_slice(&body)
+        .map_err(|_| AppError::Validation("Invalid transaction body".into()))?;
+    if txn.origin != origin {
+        return Err(AppError::Forbidden("Transaction origin does not match signer".into()));
+    }
+    if txn.events.len() > 100 {
+        return Err(AppError::Validation("At most 100 events per transaction".into()));
+    }
+    if txn_id.is_empty() || txn_id.len() > 128 {
+        return Err(AppError::Validation("Invalid transaction id".into()));
+    }
+    if !queries::record_federation_transaction(state.db.write(), &origin, &txn_id).await? {
+        return Ok(Json(serde_json::json!({ "ok": true })));
+    }
+
+    // Events are applied independently; one bad event does not sink the batch.
+    for event in txn.events {
+        if let Err(e) = apply_event(&state, &origin, event).await {
+            tracing::warn!("Dropped federation event from {}: {}", origin, e);
+        }
+    }
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+async fn apply_event(state: &AppState, origin: &str, event: FederationEvent) -> AppResult<()> {
+    match event {
+        FederationEvent::DmCreate { channel_id, sender, target_user_id } => {
+            apply_dm_create(state, origin, channel_id, &sender, target_user_id).await
+        }
+        FederationEvent::Message {
+            channel_id,
+            message_id,
+            sender_id,
+            sender_token,
+            encrypted_body,
+            timestamp,
+        } => {
+            let decode = |field: &str, value: &str| {
+                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)
+                    .map_err(|_| AppError::Validation(format!("Invalid {} encoding", field)))
+            };
+            let sender_token = decode("sender_token", &sender_token)?;
+            let encrypted_body = decode("encrypted_body", &encrypted_body)?;
+            if encrypted_body.len() > 8192 {
+                return Err(AppError::Validation("Message too large".into()));
+            }
+            apply_message(state, origin, channel_id, message_id, sender_id, &sender_token, &encrypted_body, timestamp)
+                .await
+        }
+    }
+}
+
+async fn apply_dm_create(
+    state: &AppState,
+    origin: &str,
+    channel_id: Uuid,
+    sender: &FederatedProfile,
+    target_user_id: Uuid,
+) -> AppResult<()> {
+    let target = queries::find_user_by_id(state.db.read(), target_user_id)
+        .await?
+        .filter(|u| !u.is_system && !federation::is_remote_username(&u.username))
+        .ok_or(AppError::UserNotFound)?;
+    let shadow = federation::store_remote_profile(state, origin, sender).await?;
+
+    if queries::is_blocked(state.db.read(), target.id, shadow.id).await? {
+        return Err(AppError::Forbidden("Sender is blocked".into()));
+    }
+
+    // The id is chosen by the origin; refuse to graft onto an unrelated channel.
+    if queries::find_channel_by_id(state.db.read(), channel_id).await?.is_some() {
+        let ours = queries::is_channel_member(state.db.read(), channel_id, target.id).await?
+            && queries::is_channel_member(state.db.read(), channel_id, shadow.id).await?;
+        return if ours {
+            Ok(())
+        } else {
+            Err(AppError::Conflict("Channel id already in use".into()))
+        };
+    }
+
+    // Remote users are never friends or server co-members, so anything
+    // stricter than "everyone" turns into a DM request.
+    let dm_status = match target.dm_privacy.as_str() {
+        "everyone" => "active",
+        "friends_strict" => return Err(AppError::Forbidden("Target only accepts DMs from friends".into())),
+        _ => "pending",
+    };
+
+    queries::create_federated_dm_channel(state.db.write(), channel_id, target.id, shadow.id).await?;
+    if dm_status != "active" {
+        queries::set_dm_status(state.db.write(), channel_id, dm_status).await?;
+        let msg = WsServerMessage::DmRequestReceived {
+            channel_id,
+            from_user_id: shadow.id,
+        };
+        if let Some(conns) = state.connections.get(&target.id) {
+            for tx in conns.iter() {
+                let _ = tx.send(msg.clone());
+            }
+        }
+        pubsub::publish_user_event(state, target.id, &msg).await;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn apply_message(
+    state: &AppState,
+    origin: &str,
+    channel_id: Uuid,
+    message_id: Uuid,
+    remote_sender_id: Uuid,
+    sender_token: &[u8],
+    encrypted_body: &[u8],
+    timestamp: chrono::DateTime<chrono::Utc>,
+) -> AppResult<()> {
+    let shadow = queries::find_federated_user_by_remote(state.db.read(), origin, remote_sender_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+    if !queries::is_channel_member(state.db.read(), channel_id, shadow.user_id).await? {
+        return Err(AppError::Forbidden("Sender is not in this channel".into()));
+    }
+    if queries::is_blocked_in_dm(state.db.read(), channel_id, shadow.user_id).await? {
+        return Err(AppError::Forbidden("Sender is blocked".into()));
+    }
+
+    let Some(message) = queries::insert_federated_message(
+        state.db.write(),
+        message_id,
+        channel_id,
+        shadow.user_id,
+        sender_token,
+        encrypted_body,
+        timestamp,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    deliver_new_message(state, message).await?;
+    Ok(())
+}
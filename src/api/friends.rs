@@ -12,6 +12,13 @@ use crate::AppState;
 
 /// GET /api/v1/friends
 /// List all friends and pending requests for the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/api/v1/friends",
+    tag = "friends",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<FriendResponse>))
+)]
 pub async fn list_friends(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -24,6 +31,13 @@ pub async fn list_friends(
 
 /// POST /api/v1/friends/request
 /// Send a friend request by username.
+#[utoipa::path(
+    post,
+    path = "/api/v1/friends/request",
+    tag = "friends",
+    request_body = FriendRequestBody,
+    responses((status = 200, body = FriendResponse))
+)]
 pub async fn send_friend_request(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -38,9 +52,20 @@ pub async fn send_friend_request(
         return Err(AppError::Validation("Cannot send a friend request to yourself".into()));
     }
 
-    // Check if blocked
+    // Requests from blocked users are dropped without a trace; the response
+    // looks like a normal pending request so the block isn't revealed.
     if queries::is_blocked(state.db.read(), target.id, user_id).await? {
-        return Err(AppError::Forbidden("Cannot send friend request to this user".into()));
+        return Ok(Json(FriendResponse {
+            id: Uuid::new_v4(),
+            user_id: target.id,
+            username: target.username,
+            display_name: target.display_name,
+            avatar_url: target.avatar_url,
+            status: "pending".to_string(),
+            is_incoming: false,
+            created_at: chrono::Utc::now(),
+            is_system: if target.is_system { Some(true) } else { None },
+        }));
     }
 
     // Check for existing friendship
@@ -64,6 +89,8 @@ pub async fn send_friend_request(
                     .unwrap_or_default(),
                 friendship_id: accepted.id,
             }).await;
+            notify_relationship(&state, user_id, target.id).await;
+            notify_relationship(&state, target.id, user_id).await;
 
             return Ok(Json(FriendResponse {
                 id: accepted.id,
@@ -93,6 +120,8 @@ pub async fn send_friend_request(
         from_username: requester_user.username.clone(),
         friendship_id: friendship.id,
     }).await;
+    notify_relationship(&state, user_id, target.id).await;
+    notify_relationship(&state, target.id, user_id).await;
 
     Ok(Json(FriendResponse {
         id: friendship.id,
@@ -108,6 +137,13 @@ pub async fn send_friend_request(
 }
 
 /// POST /api/v1/friends/:friendship_id/accept
+#[utoipa::path(
+    post,
+    path = "/api/v1/friends/{friendship_id}/accept",
+    tag = "friends",
+    params(("friendship_id" = Uuid, Path, description = "Friendship ID")),
+    responses((status = 200, body = FriendResponse))
+)]
 pub async fn accept_friend_request(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -141,6 +177,8 @@ pub async fn accept_friend_request(
         username: accepter.username,
         friendship_id: accepted.id,
     }).await;
+    notify_relationship(&state, user_id, accepted.requester_id).await;
+    notify_relationship(&state, accepted.requester_id, user_id).await;
 
     Ok(Json(FriendResponse {
         id: accepted.id,
@@ -156,6 +194,13 @@ pub async fn accept_friend_request(
 }
 
 /// POST /api/v1/friends/:friendship_id/decline
+#[utoipa::path(
+    post,
+    path = "/api/v1/friends/{friendship_id}/decline",
+    tag = "friends",
+    params(("friendship_id" = Uuid, Path, description = "Friendship ID")),
+    responses((status = 200))
+)]
 pub async fn decline_friend_request(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -174,11 +219,20 @@ pub async fn decline_friend_request(
     }
 
     queries::delete_friendship(state.db.write(), friendship_id).await?;
+    notify_relationship(&state, user_id, friendship.requester_id).await;
+    notify_relationship(&state, friendship.requester_id, user_id).await;
     Ok(Json(serde_json::json!({ "message": "Friend request declined" })))
 }
 
 /// DELETE /api/v1/friends/:friendship_id
 /// Cancel a pending request (requester) or remove an existing friend.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/friends/{friendship_id}",
+    tag = "friends",
+    params(("friendship_id" = Uuid, Path, description = "Friendship ID")),
+    responses((status = 200))
+)]
 pub async fn remove_friend(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -205,12 +259,39 @@ pub async fn remove_friend(
     send_to_user(&state, other_user_id, WsServerMessage::FriendRemoved {
         user_id,
     }).await;
+    notify_relationship(&state, user_id, other_user_id).await;
+    notify_relationship(&state, other_user_id, user_id).await;
 
     Ok(Json(serde_json::json!({ "message": "Friend removed" })))
 }
 
+/// GET /api/v1/users/me/relationships
+/// Friends, incoming and outgoing requests, and blocked users in one list.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/relationships",
+    tag = "friends",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<RelationshipResponse>))
+)]
+pub async fn list_relationships(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(pagination): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<RelationshipResponse>>> {
+    let (limit, offset) = pagination.resolve();
+    let relationships = queries::get_relationships(state.db.read(), user_id, limit, offset).await?;
+    Ok(Json(relationships))
+}
+
 /// GET /api/v1/dm/requests
 /// List pending DM channels for the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/api/v1/dm/requests",
+    tag = "friends",
+    responses((status = 200, body = Vec<ChannelResponse>))
+)]
 pub async fn list_dm_requests(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -235,6 +316,9 @@ pub async fn list_dm_requests(
             encrypted: true,
             export_allowed: ch.export_allowed,
             message_ttl: ch.message_ttl,
+            owner_id: ch.owner_id,
+            topic: ch.topic,
+            description: ch.description,
         })
         .collect();
     Ok(Json(responses))
@@ -242,6 +326,14 @@ pub async fn list_dm_requests(
 
 /// POST /api/v1/dm/:channel_id/request
 /// Accept or decline a DM request.
+#[utoipa::path(
+    post,
+    path = "/api/v1/dm/{channel_id}/request",
+    tag = "friends",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = DmRequestAction,
+    responses((status = 200))
+)]
 pub async fn handle_dm_request(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -278,16 +370,23 @@ pub async fn handle_dm_request(
 }
 
 /// PUT /api/v1/users/dm-privacy
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/dm-privacy",
+    tag = "friends",
+    request_body = UpdateDmPrivacyRequest,
+    responses((status = 200))
+)]
 pub async fn update_dm_privacy(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Json(req): Json<UpdateDmPrivacyRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
     match req.dm_privacy.as_str() {
-        "everyone" | "friends_only" | "server_members" => {}
+        "everyone" | "friends_only" | "server_members" | "friends_strict" => {}
         _ => {
             return Err(AppError::Validation(
-                "dm_privacy must be 'everyone', 'friends_only', or 'server_members'".into(),
+                "dm_privacy must be 'everyone', 'friends_only', 'server_members', or 'friends_strict'".into(),
             ));
         }
     }
@@ -303,5 +402,20 @@ async fn send_to_user(state: &AppState, user_id: Uuid, msg: WsServerMessage) {
             let _ = tx.send(msg.clone());
         }
     }
-    crate::pubsub::publish_user_event(state.redis.clone().as_mut(), user_id, &msg).await;
+    crate::pubsub::publish_user_event(state, user_id, &msg).await;
+}
+
+/// Send `user_id` a `RelationshipUpdate` with their current relationship to
+/// `other_id` (or None if there is none left).
+pub async fn notify_relationship(state: &AppState, user_id: Uuid, other_id: Uuid) {
+    match queries::find_relationship(state.db.read(), user_id, other_id).await {
+        Ok(relationship) => {
+            send_to_user(state, user_id, WsServerMessage::RelationshipUpdate {
+                user_id: other_id,
+                relationship,
+            })
+            .await;
+        }
+        Err(e) => tracing::warn!("Failed to load relationship for {}: {}", user_id, e),
+    }
 }
@@ -7,6 +7,13 @@ use crate::models::{GifResult, GifSearchQuery, GifSearchResponse};
 use crate::AppState;
 
 /// Search GIFs via the Giphy API (proxied to avoid exposing API key to clients).
+#[utoipa::path(
+    get,
+    path = "/api/v1/gifs/search",
+    tag = "gifs",
+    params(GifSearchQuery),
+    responses((status = 200, body = GifSearchResponse))
+)]
 pub async fn search_gifs(
     _user: AuthUser,
     State(state): State<AppState>,
@@ -32,6 +39,12 @@ pub async fn search_gifs(
 }
 
 /// Get trending GIFs via the Giphy API.
+#[utoipa::path(
+    get,
+    path = "/api/v1/gifs/trending",
+    tag = "gifs",
+    responses((status = 200, body = GifSearchResponse))
+)]
 pub async fn trending_gifs(
     _user: AuthUser,
     State(state): State<AppState>,
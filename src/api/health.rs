@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, Json};
+
+use crate::db::Pool;
+use crate::models::{ComponentHealth, HealthResponse};
+use crate::AppState;
+
+/// Per-component probe budget; a hung dependency reads as down, not as a hung probe.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Run one probe with a timeout. Failure details go to the log, not the
+/// (unauthenticated) response.
+async fn probe<F, E>(name: &str, check: F) -> ComponentHealth
+where
+    F: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let started = Instant::now();
+    let error = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => {
+            tracing::warn!("Health check '{}' failed: {}", name, e);
+            Some("unreachable")
+        }
+        Err(_) => {
+            tracing::warn!("Health check '{}' timed out", name);
+            Some("timeout")
+        }
+    };
+    ComponentHealth {
+        status: if error.is_none() { "ok" } else { "down" },
+        latency_ms: Some(started.elapsed().as_millis() as u64),
+        error: error.map(str::to_string),
+    }
+}
+
+fn disabled() -> ComponentHealth {
+    ComponentHealth { status: "disabled", latency_ms: None, error: None }
+}
+
+async fn check_pool(name: &str, pool: &Pool) -> ComponentHealth {
+    probe(name, async {
+        sqlx::query("SELECT 1").execute(pool).await.map(|_| ())
+    })
+    .await
+}
+
+/// Reads fail over to the primary, so replicas only count as down when none
+/// of them is reachable.
+async fn replica_component(state: &AppState) -> ComponentHealth {
+    let mut health = disabled();
+    for pool in state.db.replicas() {
+        health = check_pool("database_replica", pool).await;
+        if health.status == "ok" {
+            break;
+        }
+    }
+    health
+}
+
+async fn database_components(state: &AppState) -> BTreeMap<&'static str, ComponentHealth> {
+    let mut components = BTreeMap::new();
+    components.insert("database_primary", check_pool("database_primary", state.db.primary()).await);
+    components.insert("database_replica", replica_component(state).await);
+    components
+}
+
+/// The email provider is only validated, not dialled: a probe every few
+/// seconds should not open mail sessions or spend API quota. Catches the
+/// misconfigurations that otherwise surface as a failed beta-code email.
+/// Reported as `smtp` whichever provider is configured.
+fn check_email(state: &AppState) -> ComponentHealth {
+    let error = match crate::email::mailer::from_config(&state.live_config.get()) {
+        Ok(None) => return disabled(),
+        Ok(Some(_)) => None,
+        Err(e) => Some(e),
+    };
+    ComponentHealth {
+        status: if error.is_none() { "ok" } else { "down" },
+        latency_ms: None,
+        error,
+    }
+}
+
+fn respond(components: BTreeMap<&'static str, ComponentHealth>) -> (StatusCode, Json<HealthResponse>) {
+    let healthy = components.values().all(|c| c.status != "down");
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(HealthResponse {
+            status: if healthy { "ok" } else { "down" },
+            components,
+        }),
+    )
+}
+
+/// GET /healthz — liveness: the process is up and can reach its database.
+pub async fn healthz(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    respond(database_components(&state).await)
+}
+
+/// GET /readyz — readiness: every configured dependency (database pools,
+/// Redis, object storage, email provider settings) is usable, and the
+/// instance isn't draining for shutdown, so the load balancer stops routing
+/// to it.
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let mut components = database_components(&state).await;
+
+    let redis = match state.redis.clone() {
+        Some(mut conn) => {
+            probe("redis", async move {
+                redis::cmd("PING").query_async::<_, String>(&mut conn).await.map(|_| ())
+            })
+            .await
+        }
+        None => disabled(),
+    };
+    components.insert("redis", redis);
+    components.insert("storage", probe("storage", state.storage.check_health()).await);
+    components.insert("smtp", check_email(&state));
+    if state.shutdown.is_draining() {
+        components.insert(
+            "shutdown",
+            ComponentHealth { status: "down", latency_ms: None, error: Some("draining".into()) },
+        );
+    }
+
+    respond(components)
+}
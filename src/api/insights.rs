@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::insights::{self, MAX_DAYS};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::AppState;
+
+const DEFAULT_DAYS: u32 = 30;
+
+/// GET /api/v1/servers/:server_id/insights
+/// Daily activity and channel ranking from the insights rollup (see
+/// `insights`). Requires MANAGE_SERVER.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/insights",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ServerInsightsQuery),
+    responses((status = 200, body = ServerInsightsResponse))
+)]
+pub async fn get_server_insights(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Query(query): Query<ServerInsightsQuery>,
+) -> AppResult<Json<ServerInsightsResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_SERVER).await?;
+
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(AppError::Validation(format!("days must be 1-{}", MAX_DAYS)));
+    }
+    let today = Utc::now().date_naive();
+    let from = insights::window_start(today, days);
+
+    let pool = state.db.read();
+    let rows = queries::get_server_insights_days(pool, server_id, from, today).await?;
+    Ok(Json(ServerInsightsResponse {
+        days: insights::fill_days(from, today, rows),
+        channels: queries::get_channel_insights(pool, server_id, from, today).await?,
+        member_count: queries::count_server_members(pool, server_id).await?,
+        updated_at: queries::get_insights_updated_at(pool, server_id).await?,
+    }))
+}
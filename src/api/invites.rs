@@ -1,20 +1,34 @@
 use axum::{
     extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Response,
     Json,
 };
 use chrono::Utc;
 use rand::Rng;
 use uuid::Uuid;
 
-use crate::db::queries;
+use crate::db::{queries, Staleness};
 use crate::errors::{AppError, AppResult};
+use crate::etag;
 use crate::middleware::AuthUser;
 use crate::models::*;
 use crate::permissions;
+use crate::quota;
 use crate::AppState;
 
+const MEMBER_SEARCH_MAX_LEN: usize = 32;
+
 /// POST /api/v1/servers/:server_id/invites
 /// Create an invite code for a server (owner/admin only).
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/invites",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateInviteRequest,
+    responses((status = 200, body = InviteResponse))
+)]
 pub async fn create_invite(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -48,6 +62,7 @@ pub async fn create_invite(
         &code,
         req.max_uses,
         expires_at,
+        req.temporary,
     )
     .await?;
 
@@ -56,6 +71,13 @@ pub async fn create_invite(
 
 /// GET /api/v1/servers/:server_id/invites
 /// List all invites for a server (owner/admin only).
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/invites",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID"), PaginationQuery),
+    responses((status = 200, body = Vec<InviteResponse>))
+)]
 pub async fn list_invites(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -79,6 +101,13 @@ pub async fn list_invites(
 
 /// DELETE /api/v1/servers/:server_id/invites/:invite_id
 /// Revoke an invite (owner/admin only).
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/invites/{invite_id}",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("invite_id" = Uuid, Path, description = "Invite ID")),
+    responses((status = 200))
+)]
 pub async fn delete_invite(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -99,13 +128,58 @@ pub async fn delete_invite(
 
 /// POST /api/v1/invites/:code/join
 /// Join a server by invite code.
+#[utoipa::path(
+    post,
+    path = "/api/v1/invites/{code}/join",
+    tag = "invites",
+    params(("code" = String, Path, description = "Invite code")),
+    responses((status = 200, body = ServerResponse))
+)]
 pub async fn join_by_invite(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(code): Path<String>,
 ) -> AppResult<Json<ServerResponse>> {
+    let invite = find_usable_invite(&state, &code, user_id).await?;
+
+    if queries::is_screening_enabled(state.db.read(), invite.server_id).await? {
+        return Err(AppError::Forbidden("This server screens new members; apply to join".into())
+            .with_code("SCREENING_REQUIRED"));
+    }
+
+    quota::check_members(&state, invite.server_id).await?;
+
+    let server = admit_member(&state, invite.server_id, user_id).await?;
+    if invite.temporary {
+        queries::mark_member_temporary(state.db.write(), server.id, user_id).await?;
+    }
+
+    // Increment invite use count
+    queries::increment_invite_uses(state.db.write(), invite.id).await?;
+
+    let (_, perms) = queries::get_member_permissions(state.db.read_with(Staleness::Fresh), server.id, user_id).await?;
+
+    let system = if server.is_system { Some(true) } else { None };
+    Ok(Json(ServerResponse {
+        id: server.id,
+        encrypted_meta: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &server.encrypted_meta,
+        ),
+        owner_id: server.owner_id,
+        created_at: server.created_at,
+        my_permissions: Some(perms.to_string()),
+        system_channel_id: server.system_channel_id,
+        icon_url: server.icon_url.clone(),
+        is_system: system,
+    }))
+}
+
+/// Look up an invite the user can still join (or apply) with: not expired,
+/// not used up, and the user neither banned from nor already in its server.
+pub(crate) async fn find_usable_invite(state: &AppState, code: &str, user_id: Uuid) -> AppResult<Invite> {
     // Find the invite
-    let invite = queries::find_invite_by_code(state.db.read(), &code)
+    let invite = queries::find_invite_by_code(state.db.read(), code)
         .await?
         .ok_or(AppError::NotFound("Invalid invite code".into()))?;
 
@@ -133,19 +207,38 @@ pub async fn join_by_invite(
         return Err(AppError::Validation("Already a member of this server".into()));
     }
 
+    Ok(invite)
+}
+
+/// Make the user a member of the server, add them to its channels and
+/// announce the join in the system channel. Returns the server.
+pub(crate) async fn admit_member(state: &AppState, server_id: Uuid, user_id: Uuid) -> AppResult<Server> {
     // Add user to the server
     let member_role = b"member";
-    queries::add_server_member(state.db.write(), invite.server_id, user_id, member_role).await?;
-
-    // Add user to all server channels (single bulk INSERT)
-    queries::add_channel_members_bulk(state.db.write(), invite.server_id, user_id).await?;
-    let channels = queries::get_server_channels(state.db.read(), invite.server_id).await?;
+    queries::add_server_member(state.db.write(), server_id, user_id, member_role).await?;
 
-    // Increment invite use count
-    queries::increment_invite_uses(state.db.write(), invite.id).await?;
+    // Add user to the onboarding's default channels, or else to all server
+    // channels (single bulk INSERT). A rejoining member acknowledges the
+    // rules again.
+    let onboarding = queries::get_server_onboarding(state.db.read(), server_id)
+        .await?
+        .filter(|o| o.enabled);
+    match onboarding {
+        Some(o) if !o.default_channel_ids.is_empty() => {
+            queries::add_channel_members_for_channels(
+                state.db.write(), server_id, user_id, &o.default_channel_ids,
+            )
+            .await?;
+        }
+        _ => {
+            queries::add_channel_members_bulk(state.db.write(), server_id, user_id).await?;
+        }
+    }
+    queries::clear_onboarding_acknowledgment(state.db.write(), server_id, user_id).await?;
+    let channels = queries::get_server_channels(state.db.read(), server_id).await?;
 
-    // Return the server info (need it for system_channel_id)
-    let server = queries::find_server_by_id(state.db.read(), invite.server_id)
+    // Need the server for system_channel_id
+    let server = queries::find_server_by_id(state.db.read(), server_id)
         .await?
         .ok_or(AppError::Internal(anyhow::anyhow!("Server not found after join")))?;
 
@@ -156,58 +249,95 @@ pub async fn join_by_invite(
     if let Some(target_channel) = sys_channel {
         let user = queries::find_user_basic_by_id(state.db.read(), user_id).await?.unwrap();
         let username = user.display_name.as_deref().unwrap_or(&user.username);
-        let body = serde_json::json!({
-            "event": "member_joined",
-            "username": username,
-            "user_id": user_id.to_string(),
-        });
-        if let Ok(sys_msg) = queries::insert_system_message(
-            state.db.write(), target_channel.id, &body.to_string(),
-        ).await {
+        let body = SystemMessage::new(SystemEvent::MemberJoined, user_id, username);
+        if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), target_channel.id, &body).await {
             let response: MessageResponse = sys_msg.into();
-            if let Some(broadcaster) = state.channel_broadcasts.get(&target_channel.id) {
-                let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-            }
+            crate::pubsub::broadcast_channel_event(state, target_channel.id, &WsServerMessage::NewMessage(response)).await;
         }
     }
 
-    let (_, perms) = queries::get_member_permissions(state.db.read(), server.id, user_id).await?;
-
-    let system = if server.is_system { Some(true) } else { None };
-    Ok(Json(ServerResponse {
-        id: server.id,
-        encrypted_meta: base64::Engine::encode(
-            &base64::engine::general_purpose::STANDARD,
-            &server.encrypted_meta,
-        ),
-        owner_id: server.owner_id,
-        created_at: server.created_at,
-        my_permissions: Some(perms.to_string()),
-        system_channel_id: server.system_channel_id,
-        icon_url: server.icon_url.clone(),
-        is_system: system,
-    }))
+    Ok(server)
 }
 
 /// GET /api/v1/servers/:server_id/members
-/// List members of a server.
+/// List members of a server. Supports `If-None-Match` (see `etag`).
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/members",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID"), PaginationQuery),
+    responses(
+        (status = 200, body = Vec<ServerMemberResponse>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 pub async fn list_members(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
     Query(pagination): Query<PaginationQuery>,
-) -> AppResult<Json<Vec<ServerMemberResponse>>> {
+    headers: HeaderMap,
+) -> AppResult<Response> {
     if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
         return Err(AppError::Forbidden("Not a member of this server".into()));
     }
 
     let (limit, offset) = pagination.resolve();
-    let members = queries::get_server_members(state.db.read(), server_id, limit, offset).await?;
+    let pool = state.db.read();
+    let version = queries::get_server_list_version(pool, server_id).await?.unwrap_or(0);
+    let etag = etag::list_etag(server_id, version, user_id, &format!("members:{}:{}", limit, offset));
+    if etag::if_none_match(&headers, &etag) {
+        return Ok(etag::not_modified(etag));
+    }
+
+    let members = queries::get_server_members(pool, server_id, limit, offset).await?;
+    Ok(etag::with_etag(etag, members))
+}
+
+/// GET /api/v1/servers/:server_id/members/search?q=
+/// Find members by username, display name or nickname, optionally filtered
+/// by role or timeout state, without loading the whole roster.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/members/search",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID"), MemberSearchQuery),
+    responses((status = 200, body = Vec<ServerMemberResponse>))
+)]
+pub async fn search_members(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Query(query): Query<MemberSearchQuery>,
+) -> AppResult<Json<Vec<ServerMemberResponse>>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > MEMBER_SEARCH_MAX_LEN {
+        return Err(AppError::Validation(format!(
+            "Search query must be 1-{} characters",
+            MEMBER_SEARCH_MAX_LEN
+        )));
+    }
+    let (limit, offset) = PaginationQuery { limit: query.limit, offset: query.offset }.resolve();
+    let members = queries::search_server_members(
+        state.db.read(), server_id, q, query.role_id, query.timed_out, limit, offset,
+    )
+    .await?;
     Ok(Json(members))
 }
 
 /// DELETE /api/v1/servers/:server_id/members/:target_user_id
 /// Kick a member from the server (owner only).
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/members/{user_id}",
+    tag = "invites",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn kick_member(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -233,22 +363,16 @@ pub async fn kick_member(
         .unwrap_or("Unknown");
 
     queries::remove_server_member(state.db.write(), server_id, target_user_id).await?;
+    crate::api::servers::remove_member_profile(&state, server_id, target_user_id).await;
+    crate::api::sender_keys::require_rotation_for_server(&state, server_id, target_user_id).await;
 
     // Insert system message in the first server channel
     let channels = queries::get_server_channels(state.db.read(), server_id).await?;
     if let Some(first_channel) = channels.first() {
-        let body = serde_json::json!({
-            "event": "member_kicked",
-            "username": target_name,
-            "user_id": target_user_id.to_string(),
-        });
-        if let Ok(sys_msg) = queries::insert_system_message(
-            state.db.write(), first_channel.id, &body.to_string(),
-        ).await {
+        let body = SystemMessage::new(SystemEvent::MemberKicked, target_user_id, target_name);
+        if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), first_channel.id, &body).await {
             let response: MessageResponse = sys_msg.into();
-            if let Some(broadcaster) = state.channel_broadcasts.get(&first_channel.id) {
-                let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-            }
+            crate::pubsub::broadcast_channel_event(&state, first_channel.id, &WsServerMessage::NewMessage(response)).await;
         }
     }
 
@@ -262,6 +386,48 @@ pub async fn kick_member(
     Ok(Json(serde_json::json!({ "kicked": true })))
 }
 
+/// Remove the user from every server they joined with a temporary invite
+/// and still hold no role in. Called when their last connection closes.
+pub(crate) async fn remove_lapsed_temporary_members(state: &AppState, user_id: Uuid) {
+    let server_ids = match queries::get_lapsed_temporary_memberships(state.db.write(), user_id).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Failed to look up temporary memberships of {}: {}", user_id, e);
+            return;
+        }
+    };
+    if server_ids.is_empty() {
+        return;
+    }
+    let username = match queries::find_user_basic_by_id(state.db.read(), user_id).await {
+        Ok(Some(user)) => user.display_name.unwrap_or(user.username),
+        _ => "Unknown".to_string(),
+    };
+
+    for server_id in server_ids {
+        if let Err(e) = queries::remove_server_member(state.db.write(), server_id, user_id).await {
+            tracing::warn!("Failed to remove temporary member {} from {}: {}", user_id, server_id, e);
+            continue;
+        }
+        crate::api::servers::remove_member_profile(state, server_id, user_id).await;
+        crate::api::sender_keys::require_rotation_for_server(state, server_id, user_id).await;
+
+        let system_channel_id = queries::find_server_by_id(state.db.read(), server_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|server| server.system_channel_id);
+        if let Some(channel_id) = system_channel_id {
+            let body = SystemMessage::new(SystemEvent::MemberLeft, user_id, username.clone());
+            if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
+                let response: MessageResponse = sys_msg.into();
+                crate::pubsub::broadcast_channel_event(state, channel_id, &WsServerMessage::NewMessage(response)).await;
+            }
+        }
+        tracing::info!("Removed temporary member {} from server {}", user_id, server_id);
+    }
+}
+
 fn generate_invite_code() -> String {
     const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
     let mut rng = rand::thread_rng();
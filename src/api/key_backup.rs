@@ -1,4 +1,7 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
 
 use crate::db::queries;
 use crate::errors::{AppError, AppResult};
@@ -7,8 +10,18 @@ use crate::models::*;
 use crate::AppState;
 
 const MAX_BACKUP_SIZE: usize = 512 * 1024; // 512 KB
+const MAX_AUTH_DATA_SIZE: usize = 8 * 1024; // 8 KB
+const MAX_SESSION_DATA_SIZE: usize = 16 * 1024; // 16 KB
+const MAX_SESSIONS_PER_UPLOAD: usize = 1000;
 
 /// PUT /api/v1/keys/backup
+#[utoipa::path(
+    put,
+    path = "/api/v1/keys/backup",
+    tag = "key_backup",
+    request_body = UploadKeyBackupRequest,
+    responses((status = 200))
+)]
 pub async fn upload_key_backup(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -61,6 +74,12 @@ pub async fn upload_key_backup(
 }
 
 /// GET /api/v1/keys/backup
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/backup",
+    tag = "key_backup",
+    responses((status = 200, body = KeyBackupResponse))
+)]
 pub async fn get_key_backup(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -88,6 +107,12 @@ pub async fn get_key_backup(
 }
 
 /// GET /api/v1/keys/backup/status
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/backup/status",
+    tag = "key_backup",
+    responses((status = 200, body = KeyBackupStatusResponse))
+)]
 pub async fn get_key_backup_status(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -102,6 +127,12 @@ pub async fn get_key_backup_status(
 }
 
 /// DELETE /api/v1/keys/backup
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/backup",
+    tag = "key_backup",
+    responses((status = 200))
+)]
 pub async fn delete_key_backup(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -109,3 +140,224 @@ pub async fn delete_key_backup(
     queries::delete_key_backup(state.db.write(), user_id).await?;
     Ok(Json(serde_json::json!({ "ok": true })))
 }
+
+// ─── Versioned session key backup ─────────────────────
+
+async fn version_response(state: &AppState, version: KeyBackupVersion) -> AppResult<KeyBackupVersionResponse> {
+    let count = queries::count_key_backup_sessions(state.db.read(), version.user_id, version.version).await?;
+    Ok(KeyBackupVersionResponse {
+        version: version.version,
+        algorithm: version.algorithm,
+        auth_data: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &version.auth_data,
+        ),
+        count,
+        created_at: version.created_at,
+    })
+}
+
+/// POST /api/v1/keys/backup/versions
+/// Start a new backup version. It becomes the current version; session keys
+/// in older versions stay readable until those versions are deleted.
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys/backup/versions",
+    tag = "key_backup",
+    request_body = CreateKeyBackupVersionRequest,
+    responses((status = 200, body = KeyBackupVersionResponse))
+)]
+pub async fn create_backup_version(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<CreateKeyBackupVersionRequest>,
+) -> AppResult<Json<KeyBackupVersionResponse>> {
+    let algorithm = req.algorithm.trim();
+    if algorithm.is_empty() || algorithm.len() > 128 {
+        return Err(AppError::Validation("algorithm must be 1-128 characters".into()));
+    }
+
+    let auth_data = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        &req.auth_data,
+    )
+    .map_err(|_| AppError::Validation("Invalid auth_data encoding".into()))?;
+    if auth_data.is_empty() || auth_data.len() > MAX_AUTH_DATA_SIZE {
+        return Err(AppError::Validation(format!(
+            "auth_data must be 1-{} bytes",
+            MAX_AUTH_DATA_SIZE
+        )));
+    }
+
+    let version = queries::create_key_backup_version(state.db.write(), user_id, algorithm, &auth_data).await?;
+    Ok(Json(version_response(&state, version).await?))
+}
+
+/// GET /api/v1/keys/backup/versions
+/// The current backup version — what a freshly logged-in device restores from.
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/backup/versions",
+    tag = "key_backup",
+    responses((status = 200, body = KeyBackupVersionResponse))
+)]
+pub async fn get_current_backup_version(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<KeyBackupVersionResponse>> {
+    let version = queries::get_current_key_backup_version(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("No backup version found".into()))?;
+    Ok(Json(version_response(&state, version).await?))
+}
+
+/// GET /api/v1/keys/backup/versions/:version
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/backup/versions/{version}",
+    tag = "key_backup",
+    params(("version" = i32, Path, description = "Backup version")),
+    responses((status = 200, body = KeyBackupVersionResponse))
+)]
+pub async fn get_backup_version(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(version): Path<i32>,
+) -> AppResult<Json<KeyBackupVersionResponse>> {
+    let version = queries::find_key_backup_version(state.db.read(), user_id, version)
+        .await?
+        .ok_or(AppError::NotFound("Backup version not found".into()))?;
+    Ok(Json(version_response(&state, version).await?))
+}
+
+/// DELETE /api/v1/keys/backup/versions/:version
+/// Delete a version and every session key in it. The number is not reused.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/backup/versions/{version}",
+    tag = "key_backup",
+    params(("version" = i32, Path, description = "Backup version")),
+    responses((status = 200))
+)]
+pub async fn delete_backup_version(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(version): Path<i32>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !queries::delete_key_backup_version(state.db.write(), user_id, version).await? {
+        return Err(AppError::NotFound("Backup version not found".into()));
+    }
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+/// PUT /api/v1/keys/backup/versions/:version/sessions
+/// Upload encrypted session keys. Only the current version accepts writes, so
+/// a device still holding an old recovery key can't keep feeding a retired
+/// backup. Existing keys are kept when the upload is not an improvement.
+#[utoipa::path(
+    put,
+    path = "/api/v1/keys/backup/versions/{version}/sessions",
+    tag = "key_backup",
+    params(("version" = i32, Path, description = "Backup version")),
+    request_body = UploadBackupSessionsRequest,
+    responses((status = 200, body = KeyBackupVersionResponse))
+)]
+pub async fn upload_backup_sessions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(version): Path<i32>,
+    Json(req): Json<UploadBackupSessionsRequest>,
+) -> AppResult<Json<KeyBackupVersionResponse>> {
+    let current = queries::get_current_key_backup_version(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("No backup version found".into()))?;
+    if current.version != version {
+        return Err(AppError::Conflict(format!(
+            "Backup version {} is not current (current is {})",
+            version, current.version
+        )));
+    }
+
+    if req.sessions.len() > MAX_SESSIONS_PER_UPLOAD {
+        return Err(AppError::Validation(format!(
+            "At most {} sessions per upload",
+            MAX_SESSIONS_PER_UPLOAD
+        )));
+    }
+
+    let mut decoded = Vec::with_capacity(req.sessions.len());
+    for (i, key) in req.sessions.iter().enumerate() {
+        if key.session_id.is_empty() || key.session_id.len() > 255 {
+            return Err(AppError::Validation(format!(
+                "session_id at index {} must be 1-255 characters",
+                i
+            )));
+        }
+        if key.first_message_index < 0 || key.forwarded_count < 0 {
+            return Err(AppError::Validation(format!(
+                "Negative message index or forward count at index {}",
+                i
+            )));
+        }
+        let session_data = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            &key.session_data,
+        )
+        .map_err(|_| AppError::Validation(format!("Invalid session_data encoding at index {}", i)))?;
+        if session_data.is_empty() || session_data.len() > MAX_SESSION_DATA_SIZE {
+            return Err(AppError::Validation(format!(
+                "session_data at index {} must be 1-{} bytes",
+                i, MAX_SESSION_DATA_SIZE
+            )));
+        }
+        decoded.push((key, session_data));
+    }
+
+    queries::upsert_key_backup_sessions(state.db.write(), user_id, version, &decoded).await?;
+    Ok(Json(version_response(&state, current).await?))
+}
+
+/// GET /api/v1/keys/backup/versions/:version/sessions?channel_id=
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/backup/versions/{version}/sessions",
+    tag = "key_backup",
+    params(("version" = i32, Path, description = "Backup version"), BackupSessionsQuery),
+    responses((status = 200, body = Vec<BackupSessionResponse>))
+)]
+pub async fn get_backup_sessions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(version): Path<i32>,
+    Query(query): Query<BackupSessionsQuery>,
+) -> AppResult<Json<Vec<BackupSessionResponse>>> {
+    queries::find_key_backup_version(state.db.read(), user_id, version)
+        .await?
+        .ok_or(AppError::NotFound("Backup version not found".into()))?;
+
+    let sessions = queries::get_key_backup_sessions(state.db.read(), user_id, version, query.channel_id).await?;
+    Ok(Json(sessions.into_iter().map(BackupSessionResponse::from).collect()))
+}
+
+/// DELETE /api/v1/keys/backup/versions/:version/sessions?channel_id=
+/// Remove session keys from a version (all of them, or one channel's).
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/backup/versions/{version}/sessions",
+    tag = "key_backup",
+    params(("version" = i32, Path, description = "Backup version"), BackupSessionsQuery),
+    responses((status = 200))
+)]
+pub async fn delete_backup_sessions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(version): Path<i32>,
+    Query(query): Query<BackupSessionsQuery>,
+) -> AppResult<Json<serde_json::Value>> {
+    queries::find_key_backup_version(state.db.read(), user_id, version)
+        .await?
+        .ok_or(AppError::NotFound("Backup version not found".into()))?;
+
+    let deleted = queries::delete_key_backup_sessions(state.db.write(), user_id, version, query.channel_id).await?;
+    Ok(Json(serde_json::json!({ "deleted": deleted })))
+}
@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::key_transparency::{self, Hash};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+fn b64(bytes: &[u8]) -> String {
+    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)
+}
+
+async fn load_leaves(state: &AppState) -> AppResult<Vec<Hash>> {
+    queries::get_key_log_leaves(state.db.read())
+        .await?
+        .into_iter()
+        .map(|leaf| {
+            Hash::try_from(leaf.as_slice())
+                .map_err(|_| AppError::Internal(anyhow::anyhow!("Corrupt key transparency leaf")))
+        })
+        .collect()
+}
+
+/// GET /api/v1/key-transparency/head
+/// Current tree size and root hash. Clients store this and later ask for a
+/// consistency proof from it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/key-transparency/head",
+    tag = "key_transparency",
+    responses((status = 200, body = KeyTransparencyHead))
+)]
+pub async fn get_head(
+    State(state): State<AppState>,
+    AuthUser(_user_id): AuthUser,
+) -> AppResult<Json<KeyTransparencyHead>> {
+    let leaves = load_leaves(&state).await?;
+    Ok(Json(KeyTransparencyHead {
+        tree_size: leaves.len() as i64,
+        root_hash: b64(&key_transparency::root_hash(&leaves)),
+    }))
+}
+
+/// GET /api/v1/key-transparency/proof/:user_id
+/// Inclusion proof for the user's most recently logged identity key.
+#[utoipa::path(
+    get,
+    path = "/api/v1/key-transparency/proof/{user_id}",
+    tag = "key_transparency",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = KeyTransparencyProofResponse))
+)]
+pub async fn get_proof(
+    State(state): State<AppState>,
+    AuthUser(_user_id): AuthUser,
+    Path(target_id): Path<Uuid>,
+) -> AppResult<Json<KeyTransparencyProofResponse>> {
+    let entry = queries::find_latest_key_log_entry(state.db.read(), target_id)
+        .await?
+        .ok_or(AppError::NotFound("No logged identity key for this user".into()))?;
+
+    let leaves = load_leaves(&state).await?;
+    let index = entry.seq as usize;
+    if index >= leaves.len() {
+        return Err(AppError::Internal(anyhow::anyhow!("Key transparency log is not contiguous")));
+    }
+
+    Ok(Json(KeyTransparencyProofResponse {
+        user_id: entry.user_id,
+        identity_key: b64(&entry.identity_key),
+        leaf_index: entry.seq,
+        logged_at: entry.created_at,
+        tree_size: leaves.len() as i64,
+        root_hash: b64(&key_transparency::root_hash(&leaves)),
+        audit_path: key_transparency::inclusion_proof(&leaves, index)
+            .iter()
+            .map(|h| b64(h.as_slice()))
+            .collect(),
+    }))
+}
+
+/// GET /api/v1/key-transparency/consistency?from=N
+/// Proof that the tree of size N is a prefix of the current tree.
+#[utoipa::path(
+    get,
+    path = "/api/v1/key-transparency/consistency",
+    tag = "key_transparency",
+    params(KeyTransparencyConsistencyQuery),
+    responses((status = 200, body = KeyTransparencyConsistencyResponse))
+)]
+pub async fn get_consistency(
+    State(state): State<AppState>,
+    AuthUser(_user_id): AuthUser,
+    Query(query): Query<KeyTransparencyConsistencyQuery>,
+) -> AppResult<Json<KeyTransparencyConsistencyResponse>> {
+    let leaves = load_leaves(&state).await?;
+    if query.from < 1 || query.from as usize > leaves.len() {
+        return Err(AppError::Validation(format!(
+            "from must be between 1 and the current tree size ({})",
+            leaves.len()
+        )));
+    }
+
+    Ok(Json(KeyTransparencyConsistencyResponse {
+        from_size: query.from,
+        tree_size: leaves.len() as i64,
+        root_hash: b64(&key_transparency::root_hash(&leaves)),
+        proof: key_transparency::consistency_proof(&leaves, query.from as usize)
+            .iter()
+            .map(|h| b64(h.as_slice()))
+            .collect(),
+    }))
+}
@@ -4,7 +4,7 @@ use axum::{
 };
 use uuid::Uuid;
 
-use crate::db::queries;
+use crate::db::{queries, Staleness};
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
@@ -13,11 +13,41 @@ use crate::AppState;
 /// GET /api/v1/users/:user_id/keys
 /// Fetch a user's key bundle for establishing an E2EE session (X3DH).
 /// Consumes one one-time prekey atomically.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/keys",
+    tag = "keys",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = KeyBundle))
+)]
 pub async fn get_key_bundle(
     State(state): State<AppState>,
     AuthUser(_requester_id): AuthUser,
     Path(user_id): Path<Uuid>,
 ) -> AppResult<Json<KeyBundle>> {
+    Ok(Json(take_key_bundle(&state, user_id).await?))
+}
+
+/// GET /api/v1/users/:user_id/prekey-bundle
+/// Same bundle as `/keys`, named after the X3DH step it serves.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/prekey-bundle",
+    tag = "keys",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = KeyBundle))
+)]
+pub async fn get_prekey_bundle(
+    State(state): State<AppState>,
+    AuthUser(_requester_id): AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<Json<KeyBundle>> {
+    Ok(Json(take_key_bundle(&state, user_id).await?))
+}
+
+/// Build a bundle, consuming one one-time prekey, and tell the owner to
+/// replenish once they fall below the low watermark.
+async fn take_key_bundle(state: &AppState, user_id: Uuid) -> AppResult<KeyBundle> {
     // Fetch the target user
     let user = queries::find_user_by_id(state.db.read(), user_id)
         .await?
@@ -39,6 +69,7 @@ pub async fn get_key_bundle(
             &base64::engine::general_purpose::STANDARD,
             &user.signed_prekey_sig,
         ),
+        one_time_prekey_id: one_time_prekey.as_ref().map(|pk| pk.key_id),
         one_time_prekey: one_time_prekey.map(|pk| {
             base64::Engine::encode(
                 &base64::engine::general_purpose::STANDARD,
@@ -47,22 +78,31 @@ pub async fn get_key_bundle(
         }),
     };
 
-    // Log a warning if prekeys are running low
+    // Prompt the owner's connected devices to replenish
     if let Ok(remaining) = queries::count_unused_prekeys(state.db.read(), user_id).await {
-        if remaining < 10 {
-            tracing::warn!(
-                "User {} has only {} prekeys remaining. Client should replenish.",
-                user_id,
-                remaining
-            );
+        if remaining < state.config.prekey_low_watermark {
+            let msg = WsServerMessage::PreKeysLow { remaining };
+            if let Some(conns) = state.connections.get(&user_id) {
+                for tx in conns.iter() {
+                    let _ = tx.send(msg.clone());
+                }
+            }
+            crate::pubsub::publish_user_event(state, user_id, &msg).await;
         }
     }
 
-    Ok(Json(bundle))
+    Ok(bundle)
 }
 
 /// POST /api/v1/keys/prekeys
 /// Upload new one-time prekeys (clients should call this when running low).
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys/prekeys",
+    tag = "keys",
+    request_body = UploadPreKeysRequest,
+    responses((status = 200))
+)]
 pub async fn upload_prekeys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -76,11 +116,7 @@ pub async fn upload_prekeys(
         return Err(AppError::Validation("Maximum 100 prekeys per upload".into()));
     }
 
-    // Get current max key_id for this user to continue the sequence
-    let current_count = queries::count_unused_prekeys(state.db.read(), user_id).await?;
-    let start_id = current_count as i32;
-
-    let prekeys: Result<Vec<(i32, Vec<u8>)>, _> = req
+    let prekeys: Result<Vec<Vec<u8>>, _> = req
         .prekeys
         .iter()
         .enumerate()
@@ -89,24 +125,32 @@ pub async fn upload_prekeys(
                 &base64::engine::general_purpose::STANDARD,
                 key_b64,
             )
-            .map(|bytes| (start_id + i as i32, bytes))
             .map_err(|_| AppError::Validation(format!("Invalid prekey encoding at index {}", i)))
         })
         .collect();
 
-    queries::insert_prekeys(state.db.write(), user_id, &prekeys?).await?;
+    // Ids continue the user's key_id sequence
+    let key_ids = queries::insert_prekeys(state.db.write(), user_id, &prekeys?).await?;
 
-    let total = queries::count_unused_prekeys(state.db.read(), user_id).await?;
+    let total = queries::count_unused_prekeys(state.db.read_with(Staleness::Fresh), user_id).await?;
 
     Ok(Json(serde_json::json!({
         "message": "Prekeys uploaded",
         "total_available": total,
+        "key_ids": key_ids,
     })))
 }
 
 /// PUT /api/v1/keys/identity
 /// Update the authenticated user's identity key and signed prekey.
 /// Called after login when the client generates new ephemeral keys.
+#[utoipa::path(
+    put,
+    path = "/api/v1/keys/identity",
+    tag = "keys",
+    request_body = UpdateKeysRequest,
+    responses((status = 200))
+)]
 pub async fn update_identity_keys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -128,27 +172,78 @@ pub async fn update_identity_keys(
         &base64::engine::general_purpose::STANDARD,
         &req.signed_prekey_signature,
     )
-    .map_err(|_| AppError::Validation("Invalid signed_prekey_signature encoding".into()))?;
+    .map_err(|_| {
+        AppError::Validation("Invalid signed_prekey_signature encoding".into()).with_code("INVALID_SIGNATURE")
+    })?;
 
     // Check if the identity key actually changed — if so, old SKDMs are undecryptable
     let user = queries::find_user_by_id(state.db.read(), user_id)
         .await?
         .ok_or(AppError::NotFound("User not found".into()))?;
 
-    if user.identity_key != identity_key {
+    let key_changed = user.identity_key != identity_key;
+    if key_changed {
         // Identity key changed: clear all pending SKDMs encrypted with the old key
         queries::clear_sender_key_distributions_for_user(state.db.write(), user_id).await?;
     }
 
     queries::update_user_keys(state.db.write(), user_id, &identity_key, &signed_prekey, &signed_prekey_sig).await?;
 
+    if key_changed {
+        queries::append_key_log_entry(state.db.write(), user_id, &identity_key).await?;
+    }
+
     Ok(Json(serde_json::json!({ "message": "Keys updated" })))
 }
 
+/// PUT /api/v1/keys/signed-prekey
+/// Rotate the signed prekey without touching the identity key, so existing
+/// sender key distributions stay valid.
+#[utoipa::path(
+    put,
+    path = "/api/v1/keys/signed-prekey",
+    tag = "keys",
+    request_body = UpdateSignedPreKeyRequest,
+    responses((status = 200))
+)]
+pub async fn update_signed_prekey(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<UpdateSignedPreKeyRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let signed_prekey = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        &req.signed_prekey,
+    )
+    .map_err(|_| AppError::Validation("Invalid signed_prekey encoding".into()))?;
+
+    let signed_prekey_sig = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        &req.signed_prekey_signature,
+    )
+    .map_err(|_| {
+        AppError::Validation("Invalid signed_prekey_signature encoding".into()).with_code("INVALID_SIGNATURE")
+    })?;
+
+    if signed_prekey.is_empty() || signed_prekey_sig.is_empty() {
+        return Err(AppError::Validation("Signed prekey and signature are required".into()));
+    }
+
+    queries::update_signed_prekey(state.db.write(), user_id, &signed_prekey, &signed_prekey_sig).await?;
+
+    Ok(Json(serde_json::json!({ "message": "Signed prekey updated" })))
+}
+
 /// DELETE /api/v1/keys/prekeys
 /// Delete all unused one-time prekeys for the authenticated user.
 /// Called on login before uploading fresh prekeys so the server only holds
 /// OTPs whose private keys exist in the client's current MemoryStore.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/keys/prekeys",
+    tag = "keys",
+    responses((status = 200))
+)]
 pub async fn delete_prekeys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -162,6 +257,12 @@ pub async fn delete_prekeys(
 
 /// GET /api/v1/keys/prekeys/count
 /// Check how many unused prekeys the authenticated user has remaining.
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/prekeys/count",
+    tag = "keys",
+    responses((status = 200))
+)]
 pub async fn prekey_count(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -170,6 +271,6 @@ pub async fn prekey_count(
 
     Ok(Json(serde_json::json!({
         "count": count,
-        "needs_replenishment": count < 20,
+        "needs_replenishment": count < state.config.prekey_low_watermark,
     })))
 }
@@ -88,6 +88,13 @@ fn build_ssrf_safe_client() -> Result<reqwest::Client, AppError> {
 /// data is included in the E2EE payload. The server does NOT log URLs.
 ///
 /// Requires authentication to prevent abuse by unauthenticated scrapers.
+#[utoipa::path(
+    get,
+    path = "/api/v1/link-preview",
+    tag = "link_preview",
+    params(LinkPreviewQuery),
+    responses((status = 200, body = LinkPreviewResponse))
+)]
 pub async fn fetch_link_preview(
     _user: AuthUser,
     Query(query): Query<LinkPreviewQuery>,
@@ -0,0 +1,164 @@
+//! Location shares and live location sessions.
+//!
+//! A `location` message carries its coordinates in the encrypted body like
+//! any other message. A live share also opens a session of
+//! `live_duration_secs` (at most `LIVE_LOCATION_MAX_DURATION_SECS`), during
+//! which the sender pushes encrypted positions with the WS
+//! `UpdateLiveLocation` command; subscribers get `LiveLocationUpdated` and
+//! the latest position is kept for history reads. A session ends when the
+//! sender stops it or, server-side, when the `live-locations` job finds it
+//! expired; both announce `LiveLocationEnded`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::pubsub;
+use crate::AppState;
+
+/// Shortest live session that may be requested.
+const MIN_LIVE_DURATION_SECS: u32 = 60;
+/// Updates closer together than this are refused.
+const MIN_UPDATE_INTERVAL_SECS: i64 = 2;
+/// Largest encrypted position update.
+const MAX_POSITION_BYTES: usize = 1024;
+
+/// Check a location share declared for a new message.
+pub(crate) fn check_location(state: &AppState, share: &LocationShare, voice_note: bool) -> AppResult<()> {
+    if voice_note {
+        return Err(AppError::Validation("A message can't be both a voice note and a location".into())
+            .with_code("INVALID_LOCATION"));
+    }
+    if let Some(secs) = share.live_duration_secs {
+        let max_secs = state.config.live_location_max_duration_secs;
+        if !(MIN_LIVE_DURATION_SECS..=max_secs).contains(&secs) {
+            return Err(AppError::Validation(format!(
+                "Live locations can be shared for {}-{} seconds",
+                MIN_LIVE_DURATION_SECS, max_secs
+            ))
+            .with_code("INVALID_LOCATION"));
+        }
+    }
+    Ok(())
+}
+
+/// Mark a new message a `location` share, opening its live session if asked.
+pub(crate) async fn record_location(
+    state: &AppState,
+    response: &mut MessageResponse,
+    user_id: Uuid,
+    share: LocationShare,
+) -> AppResult<()> {
+    let live_until = share
+        .live_duration_secs
+        .map(|secs| Utc::now() + chrono::Duration::seconds(i64::from(secs)));
+    let session =
+        queries::insert_location_share(state.db.write(), response.id, response.channel_id, user_id, live_until)
+            .await?;
+    response.message_type = Some("location".into());
+    response.live_location = session.map(LiveLocationState::from);
+    Ok(())
+}
+
+/// Store and fan out a position update to the sender's open session.
+pub(crate) async fn update_position(
+    state: &AppState,
+    user_id: Uuid,
+    message_id: Uuid,
+    encrypted_position: &str,
+) -> AppResult<()> {
+    let position = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encrypted_position)
+        .map_err(|_| AppError::Validation("Invalid encrypted_position encoding".into()))?;
+    if position.is_empty() || position.len() > MAX_POSITION_BYTES {
+        return Err(AppError::Validation(format!(
+            "encrypted_position must be 1-{} bytes",
+            MAX_POSITION_BYTES
+        )));
+    }
+    let Some(session) =
+        queries::update_live_location(state.db.write(), message_id, user_id, &position, MIN_UPDATE_INTERVAL_SECS)
+            .await?
+    else {
+        return Err(refusal(state, user_id, message_id).await);
+    };
+    let event = WsServerMessage::LiveLocationUpdated {
+        channel_id: session.channel_id,
+        message_id,
+        encrypted_position: encrypted_position.to_string(),
+        updated_at: session.last_update_at.unwrap_or_else(Utc::now),
+    };
+    publish(state, session.channel_id, event).await;
+    Ok(())
+}
+
+/// End the sender's open session before it expires.
+pub(crate) async fn stop(state: &AppState, user_id: Uuid, message_id: Uuid) -> AppResult<()> {
+    let Some(session) = queries::stop_live_location(state.db.write(), message_id, user_id).await? else {
+        return Err(refusal(state, user_id, message_id).await);
+    };
+    let event = WsServerMessage::LiveLocationEnded {
+        channel_id: session.channel_id,
+        message_id,
+        expired: false,
+    };
+    publish(state, session.channel_id, event).await;
+    Ok(())
+}
+
+/// Why an update or stop of `message_id` by `user_id` changed nothing.
+async fn refusal(state: &AppState, user_id: Uuid, message_id: Uuid) -> AppError {
+    match queries::find_live_location_session(state.db.write(), message_id).await {
+        Ok(Some(session)) if session.user_id != user_id => {
+            AppError::Forbidden("Only the sender can update a live location".into())
+        }
+        Ok(Some(session)) if session.ended_at.is_some() || session.expires_at <= Utc::now() => {
+            AppError::Conflict("Live location has ended".into()).with_code("LIVE_LOCATION_ENDED")
+        }
+        Ok(Some(_)) => AppError::TooManyRequests("Live location updated too often".into()),
+        Ok(None) => AppError::NotFound("Live location not found".into()),
+        Err(e) => e,
+    }
+}
+
+/// End sessions past their expiry and tell their channels (the
+/// `live-locations` job). Returns how many ended.
+pub async fn expire_sessions(state: &AppState) -> AppResult<u64> {
+    let ended = queries::end_expired_live_locations(state.db.primary()).await?;
+    for &(channel_id, message_id) in &ended {
+        let event = WsServerMessage::LiveLocationEnded { channel_id, message_id, expired: true };
+        publish(state, channel_id, event).await;
+    }
+    Ok(ended.len() as u64)
+}
+
+async fn publish(state: &AppState, channel_id: Uuid, event: WsServerMessage) {
+    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+        let _ = broadcaster.send(event.clone());
+    }
+    pubsub::publish_channel_event(state, channel_id, &event).await;
+}
+
+/// DELETE /api/v1/messages/:message_id/live-location
+/// Stop sharing a live location (REST fallback for `StopLiveLocation`).
+#[utoipa::path(
+    delete,
+    path = "/api/v1/messages/{message_id}/live-location",
+    tag = "messages",
+    params(("message_id" = Uuid, Path, description = "Message ID")),
+    responses((status = 204), (status = 409, description = "The session already ended"))
+)]
+pub async fn stop_live_location(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(message_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    stop(&state, user_id, message_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
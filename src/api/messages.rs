@@ -1,5 +1,6 @@
 use axum::{
     extract::{Path, Query, State},
+    http::StatusCode,
     Json,
 };
 use chrono::{DateTime, Utc};
@@ -10,9 +11,11 @@ use crate::db::queries;
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
+use crate::quota;
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct MessageQuery {
     pub before: Option<DateTime<Utc>>,
     pub after: Option<DateTime<Utc>>,
@@ -21,6 +24,13 @@ pub struct MessageQuery {
 
 /// GET /api/v1/channels/:channel_id/messages
 /// Paginated message history (encrypted blobs).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/messages",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), MessageQuery),
+    responses((status = 200, body = Vec<MessageResponse>))
+)]
 pub async fn get_messages(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -36,14 +46,25 @@ pub async fn get_messages(
 
     let messages =
         queries::get_channel_messages(state.db.read(), channel_id, params.before, params.after, limit).await?;
+    state.shadow_reads.compare("channel_messages", &messages, || {
+        let pool = state.db.read().clone();
+        async move {
+            queries::get_channel_messages_by_cursor(&pool, channel_id, params.before, params.after, limit).await
+        }
+    });
 
-    let responses: Vec<MessageResponse> = messages.into_iter().map(|m| m.into()).collect();
-
-    Ok(Json(responses))
+    Ok(Json(to_message_responses(&state, user_id, channel_id, messages).await?))
 }
 
 /// GET /api/v1/channels/:channel_id/reactions
 /// Returns grouped reactions for the most recent messages in a channel.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/reactions",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<ReactionGroup>))
+)]
 pub async fn get_channel_reactions(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -84,6 +105,13 @@ pub async fn get_channel_reactions(
 }
 
 /// GET /api/v1/messages/:message_id/reactions — get who reacted to a message
+#[utoipa::path(
+    get,
+    path = "/api/v1/messages/{message_id}/reactions",
+    tag = "messages",
+    params(("message_id" = Uuid, Path, description = "Message ID")),
+    responses((status = 200, body = Vec<ReactionGroup>))
+)]
 pub async fn get_message_reactions(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -120,8 +148,178 @@ pub async fn get_message_reactions(
     Ok(Json(result))
 }
 
+/// Look up a message in `channel_id` that `user_id` can see.
+async fn find_channel_message(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> AppResult<Message> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+    queries::find_message_by_id(state.db.read(), message_id)
+        .await?
+        .filter(|m| m.channel_id == channel_id)
+        .ok_or(AppError::NotFound("Message not found".into()))
+}
+
+/// PUT /api/v1/channels/:channel_id/messages/:message_id/reactions/:emoji/@me
+/// React to a message. Reacting again with the same emoji is a no-op.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/messages/{message_id}/reactions/{emoji}/@me",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("message_id" = Uuid, Path, description = "Message ID"), ("emoji" = String, Path, description = "Emoji shortcode or unicode")),
+    responses((status = 204))
+)]
+pub async fn add_own_reaction(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, message_id, emoji)): Path<(Uuid, Uuid, String)>,
+) -> AppResult<StatusCode> {
+    if emoji.is_empty() || emoji.chars().count() > 64 {
+        return Err(AppError::Validation("Emoji must be 1-64 characters".into()));
+    }
+    let message = find_channel_message(&state, user_id, channel_id, message_id).await?;
+
+    if let Some(channel) = queries::find_channel_by_id(state.db.read(), channel_id).await? {
+        if let Some(server_id) = channel.server_id {
+            let perms =
+                queries::get_member_channel_permissions(state.db.read(), server_id, channel_id, user_id).await?;
+            if !crate::permissions::has_permission(perms, crate::permissions::ADD_REACTIONS) {
+                return Err(AppError::Forbidden("Missing ADD_REACTIONS permission".into()));
+            }
+            if queries::is_member_timed_out(state.db.read(), server_id, user_id)
+                .await
+                .unwrap_or(false)
+            {
+                return Err(AppError::Forbidden("You are timed out in this server".into()));
+            }
+        }
+    }
+
+    // Random sender token for sealed sender, as on the WebSocket path
+    let sender_token = Uuid::new_v4().to_string();
+    queries::add_reaction(state.db.write(), message.id, user_id, &emoji, Some(&sender_token)).await?;
+
+    let react_msg = WsServerMessage::ReactionAdded {
+        message_id,
+        channel_id,
+        user_id,
+        sender_token,
+        emoji,
+    };
+    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+        let _ = broadcaster.send(react_msg.clone());
+    }
+    crate::pubsub::publish_channel_event(&state, channel_id, &react_msg).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/v1/channels/:channel_id/messages/:message_id/reactions/:emoji/@me
+/// Remove the caller's reaction. Removing a reaction that isn't there is a no-op.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}/messages/{message_id}/reactions/{emoji}/@me",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("message_id" = Uuid, Path, description = "Message ID"), ("emoji" = String, Path, description = "Emoji shortcode or unicode")),
+    responses((status = 204))
+)]
+pub async fn remove_own_reaction(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, message_id, emoji)): Path<(Uuid, Uuid, String)>,
+) -> AppResult<StatusCode> {
+    let message = find_channel_message(&state, user_id, channel_id, message_id).await?;
+
+    if queries::remove_reaction(state.db.write(), message.id, user_id, &emoji).await? {
+        let unreact_msg = WsServerMessage::ReactionRemoved {
+            message_id,
+            channel_id,
+            user_id,
+            sender_token: Uuid::new_v4().to_string(),
+            emoji,
+        };
+        if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+            let _ = broadcaster.send(unreact_msg.clone());
+        }
+        crate::pubsub::publish_channel_event(&state, channel_id, &unreact_msg).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/channels/:channel_id/messages/:message_id/reactions/:emoji
+/// Paginated list of users who reacted with `emoji`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/messages/{message_id}/reactions/{emoji}",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("message_id" = Uuid, Path, description = "Message ID"), ("emoji" = String, Path, description = "Emoji shortcode or unicode"), ReactorQuery),
+    responses((status = 200, body = Vec<Reactor>))
+)]
+pub async fn get_reactors(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, message_id, emoji)): Path<(Uuid, Uuid, String)>,
+    Query(params): Query<ReactorQuery>,
+) -> AppResult<Json<Vec<Reactor>>> {
+    let message = find_channel_message(&state, user_id, channel_id, message_id).await?;
+
+    let limit = params.limit.unwrap_or(25).clamp(1, 100);
+    let reactors = queries::get_reactors(state.db.read(), message.id, &emoji, params.after, limit).await?;
+    Ok(Json(reactors))
+}
+
+/// GET /api/v1/messages/:message_id/replies
+/// Paginated replies to a message, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/messages/{message_id}/replies",
+    tag = "messages",
+    params(("message_id" = Uuid, Path, description = "Message ID"), MessageQuery),
+    responses((status = 200, body = Vec<MessageResponse>))
+)]
+pub async fn get_message_replies(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(message_id): Path<Uuid>,
+    Query(params): Query<MessageQuery>,
+) -> AppResult<Json<Vec<MessageResponse>>> {
+    let message = queries::find_message_by_id(state.db.read(), message_id)
+        .await?
+        .ok_or(AppError::NotFound("Message not found".into()))?;
+
+    if !queries::can_access_channel(state.db.read(), message.channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+
+    let limit = params.limit.unwrap_or(50).min(100);
+    let replies = queries::get_message_replies(
+        state.db.read(),
+        message.channel_id,
+        message_id,
+        params.before,
+        params.after,
+        limit,
+    )
+    .await?;
+
+    Ok(Json(to_message_responses(&state, user_id, message.channel_id, replies).await?))
+}
+
 /// POST /api/v1/channels/:channel_id/messages
 /// REST fallback for sending messages (primary path is WebSocket).
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/messages",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SendMessageRequest,
+    responses((status = 200, body = MessageResponse))
+)]
 pub async fn send_message(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -142,6 +340,27 @@ pub async fn send_message(
             {
                 return Err(AppError::Forbidden("You are timed out in this server".into()));
             }
+            require_onboarding_done(&state, server_id, user_id).await?;
+            crate::api::verification::require_verified(&state, server_id, user_id).await?;
+            quota::record_message(&state, server_id).await?;
+        }
+    }
+
+    if queries::is_blocked_in_dm(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("You cannot message this user".into()));
+    }
+
+    if let Some(location) = &req.location {
+        crate::api::locations::check_location(&state, location, req.voice_note.is_some())?;
+    }
+    let mut mentions = req.mentions.unwrap_or_default();
+    if !mentions.is_empty() || req.voice_note.is_some() {
+        let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+            .await?
+            .ok_or(AppError::NotFound("Channel not found".into()))?;
+        check_mentions(&state, user_id, &channel, &mut mentions).await?;
+        if let Some(voice_note) = &req.voice_note {
+            check_voice_note(&state, user_id, &channel, voice_note, req.has_attachments).await?;
         }
     }
 
@@ -181,27 +400,315 @@ pub async fn send_message(
     )
     .await?;
 
-    let response: MessageResponse = message.into();
+    let kind = match (req.voice_note, req.location) {
+        (Some(voice_note), _) => MessageKind::VoiceNote(voice_note),
+        (None, Some(location)) => MessageKind::Location(location),
+        (None, None) => MessageKind::Plain,
+    };
+    Ok(Json(deliver_new_message(&state, user_id, message, mentions, kind).await?))
+}
+
+/// POST /api/v1/channels/:channel_id/messages/:message_id/forward
+/// Re-post a message into another channel the caller can write to. The client
+/// supplies a copy re-encrypted for the target channel; between unencrypted
+/// channels the original body may be reused instead.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/messages/{message_id}/forward",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("message_id" = Uuid, Path, description = "Message ID")),
+    request_body = ForwardMessageRequest,
+    responses((status = 200, body = MessageResponse))
+)]
+pub async fn forward_message(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ForwardMessageRequest>,
+) -> AppResult<Json<MessageResponse>> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+
+    let original = queries::find_message_by_id(state.db.read(), message_id)
+        .await?
+        .filter(|m| m.channel_id == channel_id)
+        .filter(|m| m.expires_at.is_none_or(|ea| ea > Utc::now()))
+        .ok_or(AppError::NotFound("Message not found".into()))?;
+    if original.message_type == "system" {
+        return Err(AppError::Validation("System messages cannot be forwarded".into()));
+    }
+
+    let source = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    let target = queries::find_channel_by_id(state.db.read(), req.target_channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Target channel not found".into()))?;
+
+    if !queries::can_access_channel(state.db.read(), target.id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of the target channel".into()));
+    }
+    if let Some(server_id) = target.server_id {
+        let perms =
+            queries::get_member_channel_permissions(state.db.read(), server_id, target.id, user_id).await?;
+        if !crate::permissions::has_permission(perms, crate::permissions::SEND_MESSAGES) {
+            return Err(AppError::Forbidden("Missing SEND_MESSAGES permission in the target channel".into()));
+        }
+        if queries::is_member_timed_out(state.db.read(), server_id, user_id)
+            .await
+            .unwrap_or(false)
+        {
+            return Err(AppError::Forbidden("You are timed out in this server".into()));
+        }
+        require_onboarding_done(&state, server_id, user_id).await?;
+        crate::api::verification::require_verified(&state, server_id, user_id).await?;
+        quota::record_message(&state, server_id).await?;
+    }
+    if queries::is_blocked_in_dm(state.db.read(), target.id, user_id).await? {
+        return Err(AppError::Forbidden("You cannot message this user".into()));
+    }
+
+    let sender_token = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        &req.sender_token,
+    )
+    .map_err(|_| AppError::Validation("Invalid sender_token encoding".into()))?;
+
+    let encrypted_body = match &req.encrypted_body {
+        Some(body) => base64::Engine::decode(&base64::engine::general_purpose::STANDARD, body)
+            .map_err(|_| AppError::Validation("Invalid encrypted_body encoding".into()))?,
+        None if !source.encrypted && !target.encrypted => original.encrypted_body.clone(),
+        None => {
+            return Err(AppError::Validation(
+                "A copy re-encrypted for the target channel is required".into(),
+            )
+            .with_code("REENCRYPTION_REQUIRED"))
+        }
+    };
+
+    let expires_at = req.expires_at.or_else(|| {
+        target
+            .message_ttl
+            .map(|ttl| Utc::now() + chrono::Duration::seconds(ttl as i64))
+    });
+
+    let message = queries::insert_forwarded_message(
+        state.db.write(),
+        target.id,
+        &sender_token,
+        &encrypted_body,
+        expires_at,
+        req.has_attachments,
+        user_id,
+        &original,
+    )
+    .await?;
+
+    Ok(Json(deliver_new_message(&state, user_id, message, MessageMentions::default(), MessageKind::Plain).await?))
+}
+
+/// Relay a freshly stored message to federation peers and bridges, then fan it
+/// out to connected channel members and other instances.
+async fn deliver_new_message(
+    state: &AppState,
+    user_id: Uuid,
+    message: Message,
+    mentions: MessageMentions,
+    kind: MessageKind,
+) -> AppResult<MessageResponse> {
+    let channel_id = message.channel_id;
+    crate::federation::relay_message(state, &message).await;
+    crate::api::bridges::enqueue_message(state, &message).await;
+    crate::api::devices::enqueue_for_offline_devices(state, message.channel_id, message.id).await;
+
+    let mut response: MessageResponse = message.into();
+    response.blocked_by = queries::get_blocker_ids(state.db.read(), user_id).await?;
+    record_mentions(state, &mut response, mentions).await?;
+    record_kind(state, &mut response, user_id, kind).await?;
+    crate::notification_rules::apply(state, &mut response, Some(user_id)).await?;
 
     // Fan out via WebSocket to channel members
     if let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await {
         for member_id in member_ids {
             if let Some(conns) = state.connections.get(&member_id) {
+                let personal = WsServerMessage::NewMessage(response.clone().for_viewer(member_id));
                 for sender in conns.iter() {
-                    let _ = sender.send(WsServerMessage::NewMessage(response.clone()));
+                    let _ = sender.send(personal.clone());
+                }
+                if member_id != user_id {
+                    crate::api::receipts::spawn_record_delivery(state, member_id, &personal);
                 }
             }
         }
     }
     // Also publish to Redis for cross-instance delivery
     let channel_msg = WsServerMessage::NewMessage(response.clone());
-    crate::pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &channel_msg).await;
+    crate::pubsub::publish_channel_event(state, channel_id, &channel_msg).await;
+
+    Ok(response)
+}
+
+/// Most users and roles a single message may mention.
+const MAX_MENTIONED_USERS: usize = 100;
+const MAX_MENTIONED_ROLES: usize = 20;
+
+/// Most bars a voice note waveform may have.
+const MAX_WAVEFORM_BARS: usize = 256;
+
+/// Refuse messages from members who haven't acknowledged the server's rules
+/// yet (see `api::onboarding`).
+pub(crate) async fn require_onboarding_done(state: &AppState, server_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    if queries::is_onboarding_pending(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Acknowledge the server rules before posting".into())
+            .with_code("ONBOARDING_REQUIRED"));
+    }
+    Ok(())
+}
+
+/// Check the mentions a client declared for a new message in `channel`:
+/// roles only in server channels and only that server's, `@everyone` only
+/// with MENTION_EVERYONE. Duplicate ids are dropped.
+pub(crate) async fn check_mentions(
+    state: &AppState,
+    user_id: Uuid,
+    channel: &Channel,
+    mentions: &mut MessageMentions,
+) -> AppResult<()> {
+    mentions.user_ids.sort_unstable();
+    mentions.user_ids.dedup();
+    mentions.role_ids.sort_unstable();
+    mentions.role_ids.dedup();
+    if mentions.user_ids.len() > MAX_MENTIONED_USERS || mentions.role_ids.len() > MAX_MENTIONED_ROLES {
+        return Err(AppError::Validation(format!(
+            "Too many mentions (max {} users and {} roles)",
+            MAX_MENTIONED_USERS, MAX_MENTIONED_ROLES
+        ))
+        .with_code("TOO_MANY_MENTIONS"));
+    }
 
-    Ok(Json(response))
+    let Some(server_id) = channel.server_id else {
+        if !mentions.role_ids.is_empty() {
+            return Err(AppError::Validation("Roles can only be mentioned in server channels".into())
+                .with_code("INVALID_MENTION"));
+        }
+        return Ok(());
+    };
+    if !mentions.role_ids.is_empty()
+        && queries::count_server_roles(state.db.read(), server_id, &mentions.role_ids).await?
+            != mentions.role_ids.len() as i64
+    {
+        return Err(AppError::Validation("Mentioned roles must belong to this server".into())
+            .with_code("INVALID_MENTION"));
+    }
+    if mentions.everyone {
+        let perms =
+            queries::get_member_channel_permissions(state.db.read(), server_id, channel.id, user_id).await?;
+        if !crate::permissions::has_permission(perms, crate::permissions::MENTION_EVERYONE) {
+            return Err(AppError::Forbidden("Missing MENTION_EVERYONE permission".into()));
+        }
+    }
+    Ok(())
+}
+
+/// Check a voice note declared for a new message in `channel`: its audio
+/// must be attached, the duration within `VOICE_NOTE_MAX_DURATION_SECS`, the
+/// waveform 1–256 bars, and server channels need SEND_VOICE_NOTES.
+pub(crate) async fn check_voice_note(
+    state: &AppState,
+    user_id: Uuid,
+    channel: &Channel,
+    voice_note: &VoiceNote,
+    has_attachments: bool,
+) -> AppResult<()> {
+    if !has_attachments {
+        return Err(AppError::Validation("A voice note needs its audio attached".into())
+            .with_code("INVALID_VOICE_NOTE"));
+    }
+    if voice_note.duration_ms == 0 {
+        return Err(AppError::Validation("Voice note duration must be positive".into())
+            .with_code("INVALID_VOICE_NOTE"));
+    }
+    if voice_note.waveform.is_empty() || voice_note.waveform.len() > MAX_WAVEFORM_BARS {
+        return Err(AppError::Validation(format!("Voice note waveform must have 1-{} bars", MAX_WAVEFORM_BARS))
+            .with_code("INVALID_VOICE_NOTE"));
+    }
+    let max_secs = state.config.voice_note_max_duration_secs;
+    if u64::from(voice_note.duration_ms) > u64::from(max_secs) * 1000 {
+        return Err(AppError::Validation(format!("Voice notes can be at most {} seconds long", max_secs))
+            .with_code("VOICE_NOTE_TOO_LONG"));
+    }
+    if let Some(server_id) = channel.server_id {
+        let perms =
+            queries::get_member_channel_permissions(state.db.read(), server_id, channel.id, user_id).await?;
+        if !crate::permissions::has_permission(perms, crate::permissions::SEND_VOICE_NOTES) {
+            return Err(AppError::Forbidden("Missing SEND_VOICE_NOTES permission".into()));
+        }
+    }
+    Ok(())
+}
+
+/// What a new message is beyond its encrypted body.
+pub(crate) enum MessageKind {
+    Plain,
+    VoiceNote(VoiceNote),
+    Location(LocationShare),
+}
+
+/// Store what makes a new message a voice note or location share.
+pub(crate) async fn record_kind(
+    state: &AppState,
+    response: &mut MessageResponse,
+    user_id: Uuid,
+    kind: MessageKind,
+) -> AppResult<()> {
+    match kind {
+        MessageKind::Plain => Ok(()),
+        MessageKind::VoiceNote(voice_note) => record_voice_note(state, response, voice_note).await,
+        MessageKind::Location(share) => crate::api::locations::record_location(state, response, user_id, share).await,
+    }
+}
+
+/// Store a new message's voice note and mark its response a `voice_note`.
+async fn record_voice_note(
+    state: &AppState,
+    response: &mut MessageResponse,
+    voice_note: VoiceNote,
+) -> AppResult<()> {
+    queries::insert_message_voice_note(state.db.write(), response.id, response.channel_id, &voice_note).await?;
+    response.message_type = Some("voice_note".into());
+    response.voice_note = Some(voice_note);
+    Ok(())
+}
+
+/// Store a new message's mentions and resolve who they notify onto its
+/// response, for `for_viewer` to set `mentions_me` during fan-out.
+pub(crate) async fn record_mentions(
+    state: &AppState,
+    response: &mut MessageResponse,
+    mentions: MessageMentions,
+) -> AppResult<()> {
+    if mentions.is_empty() {
+        return Ok(());
+    }
+    queries::insert_message_mentions(state.db.write(), response.id, response.channel_id, &mentions).await?;
+    let mut mentioned =
+        queries::get_role_mention_recipients(state.db.read(), response.channel_id, &mentions.role_ids).await?;
+    mentioned.extend(&mentions.user_ids);
+    response.mentioned = mentioned;
+    response.mentions = Some(mentions);
+    Ok(())
 }
 
 /// GET /api/v1/channels/:channel_id/pins
 /// Returns all pinned messages in a channel.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/pins",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<MessageResponse>))
+)]
 pub async fn get_pins(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -212,12 +719,18 @@ pub async fn get_pins(
     }
 
     let messages = queries::get_pinned_messages(state.db.read(), channel_id).await?;
-    let responses: Vec<MessageResponse> = messages.into_iter().map(|m| m.into()).collect();
-    Ok(Json(responses))
+    Ok(Json(to_message_responses(&state, user_id, channel_id, messages).await?))
 }
 
 /// GET /api/v1/channels/:channel_id/pin-ids
 /// Returns just the IDs of pinned messages (lightweight).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/pin-ids",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<Uuid>))
+)]
 pub async fn get_pin_ids(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -233,6 +746,14 @@ pub async fn get_pin_ids(
 
 /// POST /api/v1/channels/:channel_id/messages/bulk-delete
 /// Bulk delete messages. Requires MANAGE_MESSAGES permission.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/messages/bulk-delete",
+    tag = "messages",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = BulkDeleteRequest,
+    responses((status = 200))
+)]
 pub async fn bulk_delete_messages(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -262,6 +783,10 @@ pub async fn bulk_delete_messages(
     )
     .await?;
 
+    if queries::any_message_under_legal_hold(state.db.read(), &req.message_ids).await? {
+        return Err(crate::api::admin::legal_hold_error());
+    }
+
     // Execute bulk delete
     let deleted_ids =
         queries::bulk_delete_messages(state.db.write(), channel_id, &req.message_ids).await?;
@@ -274,7 +799,7 @@ pub async fn bulk_delete_messages(
     if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
         let _ = broadcaster.send(del_msg.clone());
     }
-    crate::pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &del_msg)
+    crate::pubsub::publish_channel_event(&state, channel_id, &del_msg)
         .await;
 
     // Audit log
@@ -292,3 +817,107 @@ pub async fn bulk_delete_messages(
 
     Ok(Json(serde_json::json!({ "deleted": deleted_ids.len() })))
 }
+
+/// Convert `channel_id`'s messages to responses for `viewer_id`, nesting
+/// previews of the messages they reply to, attaching their mentions, counting
+/// their replies and reactions, filling in server-generated attachment previews (only ever present in unencrypted
+/// channels) and flagging messages from authors the viewer has blocked.
+async fn to_message_responses(
+    state: &AppState,
+    viewer_id: Uuid,
+    channel_id: Uuid,
+    messages: Vec<Message>,
+) -> AppResult<Vec<MessageResponse>> {
+    let blocked = queries::get_blocked_user_ids(state.db.read(), viewer_id).await?;
+    let ids: Vec<Uuid> = messages.iter().filter(|m| m.has_attachments).map(|m| m.id).collect();
+    let all_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+    let reply_to_ids: Vec<Uuid> = messages.iter().filter_map(|m| m.reply_to_id).collect();
+    let mut responses: Vec<MessageResponse> = messages
+        .into_iter()
+        .map(|m| {
+            let from_blocked = m.sender_id.is_some_and(|s| blocked.contains(&s));
+            let mut response: MessageResponse = m.into();
+            response.from_blocked = from_blocked;
+            response
+        })
+        .collect();
+
+    if !reply_to_ids.is_empty() {
+        let targets: std::collections::HashMap<Uuid, ReplyPreview> =
+            queries::get_reply_targets(state.db.read(), channel_id, &reply_to_ids)
+                .await?
+                .into_iter()
+                .map(|m| (m.id, ReplyPreview::from(&MessageResponse::from(m))))
+                .collect();
+        for response in &mut responses {
+            response.reply_to = response.reply_to_id.and_then(|id| targets.get(&id).cloned());
+        }
+    }
+    if !all_ids.is_empty() {
+        let mut mentions = queries::get_message_mentions(state.db.read(), &all_ids).await?;
+        for response in &mut responses {
+            response.mentions = mentions.remove(&response.id);
+        }
+    }
+    let voice_note_ids: Vec<Uuid> = responses
+        .iter()
+        .filter(|r| r.message_type.as_deref() == Some("voice_note"))
+        .map(|r| r.id)
+        .collect();
+    if !voice_note_ids.is_empty() {
+        let mut voice_notes = queries::get_message_voice_notes(state.db.read(), &voice_note_ids).await?;
+        for response in &mut responses {
+            response.voice_note = voice_notes.remove(&response.id);
+        }
+    }
+    let location_ids: Vec<Uuid> = responses
+        .iter()
+        .filter(|r| r.message_type.as_deref() == Some("location"))
+        .map(|r| r.id)
+        .collect();
+    if !location_ids.is_empty() {
+        let mut sessions = queries::get_live_location_sessions(state.db.read(), &location_ids).await?;
+        for response in &mut responses {
+            response.live_location = sessions.remove(&response.id).map(LiveLocationState::from);
+        }
+    }
+    if !all_ids.is_empty() {
+        let counts: std::collections::HashMap<Uuid, i64> =
+            queries::get_reply_counts(state.db.read(), channel_id, &all_ids)
+                .await?
+                .into_iter()
+                .collect();
+        for response in &mut responses {
+            response.reply_count = counts.get(&response.id).copied().unwrap_or(0);
+        }
+    }
+    if !all_ids.is_empty() {
+        let mut reactions: std::collections::HashMap<Uuid, Vec<ReactionCount>> =
+            std::collections::HashMap::new();
+        for (message_id, emoji, count, me) in
+            queries::get_reaction_counts(state.db.read(), &all_ids, viewer_id).await?
+        {
+            reactions.entry(message_id).or_default().push(ReactionCount { emoji, count, me });
+        }
+        for response in &mut responses {
+            if let Some(r) = reactions.remove(&response.id) {
+                response.reactions = r;
+            }
+        }
+    }
+    if ids.is_empty() {
+        return Ok(responses);
+    }
+
+    let mut previews: std::collections::HashMap<Uuid, Vec<AttachmentPreview>> =
+        std::collections::HashMap::new();
+    for (message_id, preview) in queries::get_attachment_previews(state.db.read(), &ids).await? {
+        previews.entry(message_id).or_default().push(preview);
+    }
+    for response in &mut responses {
+        if let Some(p) = previews.remove(&response.id) {
+            response.attachment_previews = p;
+        }
+    }
+    Ok(responses)
+}
@@ -0,0 +1,280 @@
+//! Moving a community between self-hosted instances.
+//!
+//! `POST /servers/:id/migrate` packs a server's structure, configuration
+//! sections and members' role assignments into a [`MigrationBundle`]. On the
+//! destination instance, the owner of a (typically new, empty) server posts
+//! the bundle to `POST /servers/:id/migrate/import`: the structure replaces
+//! the server's as in a restore, and every bundled member whose identity key
+//! belongs to a local account joins with their roles. User ids differ between
+//! instances, so identity keys are the only stable link; members who haven't
+//! brought their key over are reported back by username.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::ws::broadcast_to_server_members;
+use crate::AppState;
+
+pub const BUNDLE_VERSION: u32 = 1;
+const MAX_BUNDLE_MEMBERS: usize = 10_000;
+
+/// Moving a server is owner-only at both ends.
+async fn require_owner(state: &AppState, server_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let (is_owner, _) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner {
+        return Err(AppError::Forbidden("Only the server owner can migrate a server".into()));
+    }
+    Ok(())
+}
+
+fn meta_text(meta: &[u8]) -> String {
+    String::from_utf8_lossy(meta).into_owned()
+}
+
+/// POST /api/v1/servers/:server_id/migrate
+/// Export the server as a migration bundle for another instance.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/migrate",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = MigrationBundle))
+)]
+pub async fn export_migration(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<MigrationBundle>> {
+    require_owner(&state, server_id, user_id).await?;
+    let pool = state.db.read();
+
+    let server = queries::find_server_by_id(pool, server_id)
+        .await?
+        .ok_or(AppError::NotFound("Server not found".into()))?;
+
+    let categories = queries::get_server_categories(pool, server_id)
+        .await?
+        .into_iter()
+        .map(|c| RestoreCategory {
+            id: c.id.to_string(),
+            name: c.name,
+            position: c.position,
+            collapsed_by_default: c.collapsed_by_default,
+        })
+        .collect();
+
+    let channels: Vec<Channel> = queries::get_server_channels(pool, server_id)
+        .await?
+        .into_iter()
+        .filter(|c| c.channel_type != "dm" && c.channel_type != "group")
+        .collect();
+    let mut permission_overwrites = Vec::new();
+    for channel in &channels {
+        for ow in queries::get_channel_overwrites(pool, channel.id).await? {
+            if ow.target_type == "role" {
+                permission_overwrites.push(RestoreOverwrite {
+                    channel_id: channel.id.to_string(),
+                    target_type: ow.target_type,
+                    target_id: ow.target_id.to_string(),
+                    allow: ow.allow_bits,
+                    deny: ow.deny_bits,
+                });
+            }
+        }
+    }
+    let channels = channels
+        .into_iter()
+        .map(|c| RestoreChannel {
+            id: c.id.to_string(),
+            name: meta_text(&c.encrypted_meta),
+            channel_type: c.channel_type,
+            category_id: c.category_id.map(|id| id.to_string()),
+            position: c.position,
+            encrypted: c.encrypted,
+            is_private: c.is_private,
+        })
+        .collect();
+
+    let roles = queries::get_server_roles(pool, server_id)
+        .await?
+        .into_iter()
+        .map(|r| RestoreRole {
+            id: r.id.to_string(),
+            name: r.name,
+            color: r.color,
+            permissions: r.permissions,
+            position: r.position,
+            is_default: r.is_default,
+        })
+        .collect();
+
+    let sections = crate::restore_sections::export_all(pool, server_id).await?;
+
+    let members = queries::get_migration_members(pool, server_id)
+        .await?
+        .into_iter()
+        .map(|(username, identity_key, role_ids)| MigrationMember {
+            identity_key: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, identity_key),
+            username,
+            role_ids: role_ids.iter().map(Uuid::to_string).collect(),
+        })
+        .collect();
+
+    let _ = queries::insert_audit_log(
+        state.db.write(),
+        server_id,
+        user_id,
+        "server_migration_export",
+        Some("server"),
+        Some(server_id),
+        None,
+        None,
+    )
+    .await;
+
+    Ok(Json(MigrationBundle {
+        version: BUNDLE_VERSION,
+        source_server_id: server_id,
+        exported_at: chrono::Utc::now(),
+        structure: RestoreServerRequest {
+            server: RestoreServerMeta {
+                id: server_id.to_string(),
+                name: meta_text(&server.encrypted_meta),
+                description: None,
+                system_channel_id: server.system_channel_id.map(|id| id.to_string()),
+            },
+            categories,
+            channels,
+            roles,
+            permission_overwrites,
+            sections,
+        },
+        members,
+    }))
+}
+
+/// POST /api/v1/servers/:server_id/migrate/import
+/// Replace this server's structure with a migration bundle and bring over
+/// every member whose identity key matches a local account, with their roles.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/migrate/import",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = MigrationBundle,
+    responses((status = 200, body = MigrationImportResponse))
+)]
+pub async fn import_migration(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(bundle): Json<MigrationBundle>,
+) -> AppResult<Json<MigrationImportResponse>> {
+    require_owner(&state, server_id, user_id).await?;
+
+    if bundle.version != BUNDLE_VERSION {
+        return Err(AppError::Validation(format!("Unsupported migration bundle version {}", bundle.version))
+            .with_code("UNSUPPORTED_FORMAT"));
+    }
+    if bundle.members.len() > MAX_BUNDLE_MEMBERS {
+        return Err(AppError::Validation(format!("Too many members (max {})", MAX_BUNDLE_MEMBERS))
+            .with_code("RESTORE_LIMIT_EXCEEDED"));
+    }
+    crate::api::exports::validate_restore_limits(&bundle.structure)?;
+
+    let mut keys = Vec::with_capacity(bundle.members.len());
+    for member in &bundle.members {
+        let key = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &member.identity_key)
+            .map_err(|_| AppError::Validation(format!("Invalid identity key for {}", member.username)))?;
+        keys.push(key);
+    }
+    let local_users: HashMap<Vec<u8>, Uuid> = queries::find_users_by_identity_keys(state.db.read(), &keys)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut tx = state.db.write().begin().await?;
+    let snapshot_id = crate::api::exports::snapshot_structure(&state, &mut tx, server_id, user_id).await?;
+    let (mut restore, role_map) =
+        crate::api::exports::restore_structure(&mut tx, server_id, user_id, &bundle.structure).await?;
+    tx.commit().await?;
+    restore.snapshot_id = Some(snapshot_id);
+
+    // Bring members over after the structure exists, so they join its channels
+    let pool = state.db.write();
+    let mut members_added = 0;
+    let mut roles_assigned = 0;
+    let mut unmatched_members = Vec::new();
+    for (member, key) in bundle.members.iter().zip(&keys) {
+        let Some(&local_id) = local_users.get(key) else {
+            unmatched_members.push(member.username.clone());
+            continue;
+        };
+        if queries::is_banned(pool, server_id, local_id).await? {
+            continue;
+        }
+        if local_id != user_id && !queries::is_server_member(pool, server_id, local_id).await? {
+            if crate::quota::check_members(&state, server_id).await.is_err() {
+                unmatched_members.push(member.username.clone());
+                continue;
+            }
+            queries::add_server_member(pool, server_id, local_id, b"member").await?;
+            members_added += 1;
+        }
+        queries::add_channel_members_bulk(pool, server_id, local_id).await?;
+        for role_id in member.role_ids.iter().filter_map(|id| role_map.get(id)) {
+            queries::assign_role(pool, server_id, local_id, *role_id).await?;
+            roles_assigned += 1;
+        }
+    }
+
+    queries::record_sync_change(pool, server_id, queries::SyncEntity::Server, server_id, false).await?;
+
+    let _ = queries::insert_audit_log(
+        pool,
+        server_id,
+        user_id,
+        "server_migration_import",
+        Some("server"),
+        Some(server_id),
+        Some(&serde_json::json!({
+            "source_server_id": bundle.source_server_id,
+            "channels_created": restore.channels_created,
+            "roles_created": restore.roles_created,
+            "members_added": members_added,
+            "members_unmatched": unmatched_members.len(),
+        })),
+        None,
+    )
+    .await;
+
+    broadcast_to_server_members(
+        &state,
+        server_id,
+        WsServerMessage::ServerRestored {
+            server_id,
+            channel_id_map: restore.channel_id_map.clone(),
+            counts: RestoreCounts::from(&restore),
+        },
+    )
+    .await;
+
+    Ok(Json(MigrationImportResponse {
+        restore,
+        members_added,
+        roles_assigned,
+        unmatched_members,
+    }))
+}
@@ -1,20 +1,40 @@
 pub mod admin;
+pub mod announcements;
 pub mod auth_routes;
 pub mod bans;
+pub mod calls;
 pub mod beta;
+pub mod branding;
+pub mod bridges;
 pub mod categories;
 pub mod channels;
+pub mod devices;
 pub mod emojis;
+pub mod events;
 pub mod exports;
+pub mod federation;
 pub mod friends;
+pub mod health;
 pub mod invites;
 pub mod key_backup;
+pub mod key_transparency;
 pub mod keys;
+pub mod locations;
 pub mod messages;
+pub mod migration;
+pub mod notification_rules;
+pub mod onboarding;
+pub mod permission_templates;
 pub mod presence;
+pub mod quiet_hours;
+pub mod receipts;
 pub mod roles;
+pub mod screening;
 pub mod sender_keys;
 pub mod servers;
+pub mod sync;
+pub mod upload_limits;
+pub mod verification;
 pub mod attachments;
 pub mod link_preview;
 pub mod reports;
@@ -22,3 +42,4 @@ pub mod users;
 pub mod registration_invites;
 pub mod voice;
 pub mod gifs;
+pub mod insights;
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+/// Most notification rules one user may have.
+const MAX_NOTIFICATION_RULES: i64 = 500;
+
+/// GET /api/v1/users/me/notification-rules
+/// The caller's notification rules.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/notification-rules",
+    tag = "users",
+    responses((status = 200, body = Vec<NotificationRule>))
+)]
+pub async fn list_rules(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<Vec<NotificationRule>>> {
+    Ok(Json(queries::list_notification_rules(state.db.read(), user_id).await?))
+}
+
+/// POST /api/v1/users/me/notification-rules
+/// Add a rule for everywhere, a server the caller is in or a channel they
+/// can read. One rule per target; change an existing one with PATCH.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/me/notification-rules",
+    tag = "users",
+    request_body = CreateNotificationRuleRequest,
+    responses((status = 200, body = NotificationRule))
+)]
+pub async fn create_rule(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<CreateNotificationRuleRequest>,
+) -> AppResult<Json<NotificationRule>> {
+    match (req.scope, req.target_id) {
+        (NotificationScope::Global, None) => {}
+        (NotificationScope::Global, Some(_)) => {
+            return Err(AppError::Validation("Global rules take no target_id".into()));
+        }
+        (_, None) => {
+            return Err(AppError::Validation("target_id is required for server and channel rules".into()));
+        }
+        (NotificationScope::Server, Some(server_id)) => {
+            if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+                return Err(AppError::NotFound("Server not found".into()));
+            }
+        }
+        (NotificationScope::Channel, Some(channel_id)) => {
+            if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+                return Err(AppError::NotFound("Channel not found".into()));
+            }
+        }
+    }
+
+    if queries::find_notification_rule_for_target(state.db.read(), user_id, req.scope, req.target_id)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict("A rule for this target already exists".into())
+            .with_code("NOTIFICATION_RULE_EXISTS"));
+    }
+    if queries::count_notification_rules(state.db.read(), user_id).await? >= MAX_NOTIFICATION_RULES {
+        return Err(AppError::Validation(format!(
+            "Too many notification rules (max {})",
+            MAX_NOTIFICATION_RULES
+        )));
+    }
+
+    let rule =
+        queries::create_notification_rule(state.db.write(), user_id, req.scope, req.target_id, req.level).await?;
+    Ok(Json(rule))
+}
+
+/// PATCH /api/v1/users/me/notification-rules/:rule_id
+/// Change what a rule lets through.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/me/notification-rules/{rule_id}",
+    tag = "users",
+    params(("rule_id" = Uuid, Path, description = "Rule ID")),
+    request_body = UpdateNotificationRuleRequest,
+    responses((status = 200, body = NotificationRule))
+)]
+pub async fn update_rule(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(rule_id): Path<Uuid>,
+    Json(req): Json<UpdateNotificationRuleRequest>,
+) -> AppResult<Json<NotificationRule>> {
+    let rule = queries::update_notification_rule(state.db.write(), user_id, rule_id, req.level)
+        .await?
+        .ok_or(AppError::NotFound("Notification rule not found".into()))?;
+    Ok(Json(rule))
+}
+
+/// DELETE /api/v1/users/me/notification-rules/:rule_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/me/notification-rules/{rule_id}",
+    tag = "users",
+    params(("rule_id" = Uuid, Path, description = "Rule ID")),
+    responses((status = 204))
+)]
+pub async fn delete_rule(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(rule_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    if !queries::delete_notification_rule(state.db.write(), user_id, rule_id).await? {
+        return Err(AppError::NotFound("Notification rule not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::AppState;
+
+const MAX_DEFAULT_CHANNELS: usize = 50;
+const MAX_PROMPTS: usize = 10;
+const MAX_PROMPT_OPTIONS: usize = 20;
+const MAX_OPTION_ROLES: usize = 10;
+
+/// GET /api/v1/servers/:server_id/onboarding
+/// The server's onboarding and whether the caller has acknowledged its rules.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/onboarding",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = OnboardingResponse))
+)]
+pub async fn get_onboarding(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<OnboardingResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let onboarding = queries::get_server_onboarding(state.db.read(), server_id)
+        .await?
+        .ok_or(AppError::NotFound("Onboarding is not configured".into()))?;
+    let acknowledged =
+        queries::has_acknowledged_onboarding(state.db.read(), server_id, user_id, onboarding.rules_version).await?;
+    Ok(Json(onboarding_response(onboarding, acknowledged)))
+}
+
+/// PUT /api/v1/servers/:server_id/onboarding
+/// Configure onboarding. Requires MANAGE_SERVER, and prompts may only grant
+/// roles the caller could assign themselves.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/onboarding",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = SetOnboardingRequest,
+    responses((status = 200, body = OnboardingResponse))
+)]
+pub async fn set_onboarding(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(mut req): Json<SetOnboardingRequest>,
+) -> AppResult<Json<OnboardingResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner && !permissions::has_permission(perms, permissions::MANAGE_SERVER) {
+        return Err(AppError::Forbidden("Missing MANAGE_SERVER permission".into()));
+    }
+
+    let encrypted_meta = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &req.encrypted_meta)
+        .map_err(|_| AppError::Validation("Invalid encrypted_meta encoding".into()))?;
+    if encrypted_meta.len() > 16384 {
+        return Err(AppError::Validation("encrypted_meta exceeds maximum size (16KB)".into()));
+    }
+
+    req.default_channel_ids.sort();
+    req.default_channel_ids.dedup();
+    if req.default_channel_ids.len() > MAX_DEFAULT_CHANNELS {
+        return Err(AppError::Validation(format!(
+            "Too many default channels (max {})",
+            MAX_DEFAULT_CHANNELS
+        )));
+    }
+    for &channel_id in &req.default_channel_ids {
+        match queries::find_channel_by_id(state.db.read(), channel_id).await? {
+            Some(channel) if channel.server_id == Some(server_id) => {}
+            _ => return Err(AppError::Validation("default_channel_ids must be channels of this server".into())),
+        }
+    }
+
+    validate_prompts(&req.prompts)?;
+    let role_ids: HashSet<Uuid> = req
+        .prompts
+        .iter()
+        .flat_map(|p| &p.options)
+        .flat_map(|o| o.role_ids.iter().copied())
+        .collect();
+    if !role_ids.is_empty() {
+        require_grantable_roles(&state, server_id, user_id, is_owner, perms, &role_ids).await?;
+    }
+
+    let prompts = serde_json::to_value(&req.prompts)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode prompts: {}", e)))?;
+    let onboarding = queries::upsert_server_onboarding(
+        state.db.write(),
+        server_id,
+        req.enabled,
+        &encrypted_meta,
+        &req.default_channel_ids,
+        req.require_acknowledgment,
+        req.reset_acknowledgments,
+        &prompts,
+    )
+    .await?;
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "onboarding_update",
+        Some("server"), Some(server_id),
+        Some(&serde_json::json!({
+            "enabled": onboarding.enabled,
+            "default_channel_ids": onboarding.default_channel_ids,
+            "require_acknowledgment": onboarding.require_acknowledgment,
+            "rules_version": onboarding.rules_version,
+            "prompt_count": req.prompts.len(),
+        })),
+        None,
+    ).await;
+
+    let acknowledged =
+        queries::has_acknowledged_onboarding(state.db.read(), server_id, user_id, onboarding.rules_version).await?;
+    Ok(Json(onboarding_response(onboarding, acknowledged)))
+}
+
+/// POST /api/v1/servers/:server_id/onboarding/complete
+/// Acknowledge the rules and pick prompt options, which grants their roles.
+/// Can be called again later to pick more options; roles are never removed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/onboarding/complete",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CompleteOnboardingRequest,
+    responses((status = 200, body = OnboardingResponse))
+)]
+pub async fn complete_onboarding(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<CompleteOnboardingRequest>,
+) -> AppResult<Json<OnboardingResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let onboarding = queries::get_server_onboarding(state.db.read(), server_id)
+        .await?
+        .filter(|o| o.enabled)
+        .ok_or(AppError::NotFound("Onboarding is not enabled".into()))?;
+
+    let acknowledged = req.acknowledge_rules
+        || queries::has_acknowledged_onboarding(state.db.read(), server_id, user_id, onboarding.rules_version)
+            .await?;
+    if onboarding.require_acknowledgment && !acknowledged {
+        return Err(AppError::Validation("The server rules must be acknowledged".into()));
+    }
+
+    let prompts = onboarding.prompts();
+    let mut role_ids = HashSet::new();
+    for selection in &req.selections {
+        let prompt = prompts
+            .iter()
+            .find(|p| p.id == selection.prompt_id)
+            .ok_or(AppError::Validation("Unknown onboarding prompt".into()))?;
+        if prompt.single_select && selection.option_ids.len() > 1 {
+            return Err(AppError::Validation("Only one option may be picked for this prompt".into()));
+        }
+        for option_id in &selection.option_ids {
+            let option = prompt
+                .options
+                .iter()
+                .find(|o| o.id == *option_id)
+                .ok_or(AppError::Validation("Unknown onboarding prompt option".into()))?;
+            role_ids.extend(option.role_ids.iter().copied());
+        }
+    }
+    let answered = |prompt: &OnboardingPrompt| {
+        req.selections.iter().any(|s| s.prompt_id == prompt.id && !s.option_ids.is_empty())
+    };
+    if prompts.iter().any(|p| p.required && !answered(p)) {
+        return Err(AppError::Validation("Every required prompt must be answered".into()));
+    }
+
+    // Roles deleted since the prompt was configured are skipped
+    let mut granted = Vec::new();
+    for role_id in role_ids {
+        match queries::find_role_by_id(state.db.read(), role_id).await? {
+            Some(role) if role.server_id == server_id && !role.is_default => {
+                queries::assign_role(state.db.write(), server_id, user_id, role_id).await?;
+                granted.push(role_id);
+            }
+            _ => {}
+        }
+    }
+    if !granted.is_empty() {
+        crate::cache::invalidate(
+            state.redis.clone().as_mut(),
+            &state.memory,
+            &format!("haven:perms:{}:{}", server_id, user_id),
+        ).await;
+    }
+    if req.acknowledge_rules {
+        queries::acknowledge_onboarding_rules(state.db.write(), server_id, user_id, onboarding.rules_version)
+            .await?;
+    }
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "onboarding_complete",
+        Some("member"), Some(user_id),
+        Some(&serde_json::json!({
+            "rules_version": req.acknowledge_rules.then_some(onboarding.rules_version),
+            "role_ids": granted,
+        })),
+        None,
+    ).await;
+
+    Ok(Json(onboarding_response(onboarding, acknowledged)))
+}
+
+/// Prompt and option ids must be unique, and every prompt needs an option.
+fn validate_prompts(prompts: &[OnboardingPrompt]) -> AppResult<()> {
+    if prompts.len() > MAX_PROMPTS {
+        return Err(AppError::Validation(format!("Too many prompts (max {})", MAX_PROMPTS)));
+    }
+    let mut ids = HashSet::new();
+    for prompt in prompts {
+        if prompt.options.is_empty() || prompt.options.len() > MAX_PROMPT_OPTIONS {
+            return Err(AppError::Validation(format!(
+                "A prompt needs between 1 and {} options",
+                MAX_PROMPT_OPTIONS
+            )));
+        }
+        if !ids.insert(prompt.id) {
+            return Err(AppError::Validation("Duplicate prompt or option id".into()));
+        }
+        for option in &prompt.options {
+            if option.role_ids.len() > MAX_OPTION_ROLES {
+                return Err(AppError::Validation(format!(
+                    "An option may grant at most {} roles",
+                    MAX_OPTION_ROLES
+                )));
+            }
+            if !ids.insert(option.id) {
+                return Err(AppError::Validation("Duplicate prompt or option id".into()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Roles granted by prompts must belong to the server, can't be the default
+/// role, and must be below the caller's highest role unless they own the
+/// server, same as assigning them directly.
+async fn require_grantable_roles(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+    is_owner: bool,
+    perms: i64,
+    role_ids: &HashSet<Uuid>,
+) -> AppResult<()> {
+    if !is_owner && !permissions::has_permission(perms, permissions::MANAGE_ROLES) {
+        return Err(AppError::Forbidden("Missing MANAGE_ROLES permission".into()));
+    }
+    let my_highest = if is_owner {
+        i32::MAX
+    } else {
+        let my_roles = queries::get_member_roles(state.db.read(), server_id, user_id).await?;
+        my_roles.iter().map(|r| r.position).max().unwrap_or(0)
+    };
+    for &role_id in role_ids {
+        let role = queries::find_role_by_id(state.db.read(), role_id)
+            .await?
+            .filter(|r| r.server_id == server_id && !r.is_default)
+            .ok_or(AppError::Validation("Prompts can only grant this server's non-default roles".into()))?;
+        if role.position >= my_highest {
+            return Err(AppError::Forbidden("Cannot grant a role at or above your position".into()));
+        }
+    }
+    Ok(())
+}
+
+fn onboarding_response(onboarding: ServerOnboarding, acknowledged: bool) -> OnboardingResponse {
+    let prompts = onboarding.prompts();
+    OnboardingResponse {
+        server_id: onboarding.server_id,
+        enabled: onboarding.enabled,
+        encrypted_meta: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &onboarding.encrypted_meta),
+        default_channel_ids: onboarding.default_channel_ids,
+        require_acknowledgment: onboarding.require_acknowledgment,
+        rules_version: onboarding.rules_version,
+        prompts,
+        acknowledged,
+        updated_at: onboarding.updated_at,
+    }
+}
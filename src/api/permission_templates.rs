@@ -0,0 +1,215 @@
+//! Permission templates: named allow/deny bitfields ("Moderator",
+//! "Read-only", ...) a server keeps for new roles and channels. Creating a
+//! role with `template_id` gives it the template's allow bits; creating a
+//! channel with one sets both as the channel's @everyone overwrite. The
+//! template is copied, not linked: editing it later changes nothing already
+//! created from it.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::AppState;
+
+/// Most templates a server may keep.
+const MAX_TEMPLATES: i64 = 25;
+/// Longest template name.
+const MAX_NAME_CHARS: usize = 64;
+
+/// GET /api/v1/servers/:server_id/permission-templates
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/permission-templates",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<PermissionTemplateResponse>))
+)]
+pub async fn list_permission_templates(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<Vec<PermissionTemplateResponse>>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let templates = queries::list_permission_templates(state.db.read(), server_id).await?;
+    Ok(Json(templates.into_iter().map(PermissionTemplateResponse::from).collect()))
+}
+
+/// POST /api/v1/servers/:server_id/permission-templates
+/// Requires MANAGE_ROLES.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/permission-templates",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreatePermissionTemplateRequest,
+    responses((status = 200, body = PermissionTemplateResponse), (status = 409, description = "Name already used"))
+)]
+pub async fn create_permission_template(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<CreatePermissionTemplateRequest>,
+) -> AppResult<Json<PermissionTemplateResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_ROLES).await?;
+
+    let name = validate_name(&req.name)?;
+    let allow_bits = parse_bits("allow_bits", req.allow_bits.as_deref())?.unwrap_or(0);
+    let deny_bits = parse_bits("deny_bits", req.deny_bits.as_deref())?.unwrap_or(0);
+    check_overlap(allow_bits, deny_bits)?;
+    if queries::count_permission_templates(state.db.read(), server_id).await? >= MAX_TEMPLATES {
+        return Err(AppError::Validation(format!(
+            "A server can have at most {} permission templates",
+            MAX_TEMPLATES
+        )));
+    }
+
+    let template =
+        queries::create_permission_template(state.db.write(), server_id, name, allow_bits, deny_bits).await?;
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "permission_template_create",
+        Some("permission_template"), Some(template.id),
+        Some(&serde_json::json!({
+            "name": &template.name,
+            "allow_bits": template.allow_bits.to_string(),
+            "deny_bits": template.deny_bits.to_string(),
+        })), None,
+    ).await;
+    Ok(Json(PermissionTemplateResponse::from(template)))
+}
+
+/// PATCH /api/v1/servers/:server_id/permission-templates/:template_id
+/// Requires MANAGE_ROLES. Roles and channels created from the template
+/// earlier keep their permissions.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/permission-templates/{template_id}",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("template_id" = Uuid, Path, description = "Template ID")),
+    request_body = UpdatePermissionTemplateRequest,
+    responses((status = 200, body = PermissionTemplateResponse), (status = 409, description = "Name already used"))
+)]
+pub async fn update_permission_template(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, template_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdatePermissionTemplateRequest>,
+) -> AppResult<Json<PermissionTemplateResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_ROLES).await?;
+    let current = queries::find_permission_template(state.db.read(), server_id, template_id)
+        .await?
+        .ok_or(AppError::NotFound("Permission template not found".into()))?;
+
+    let name = req.name.as_deref().map(validate_name).transpose()?;
+    let allow_bits = parse_bits("allow_bits", req.allow_bits.as_deref())?;
+    let deny_bits = parse_bits("deny_bits", req.deny_bits.as_deref())?;
+    check_overlap(
+        allow_bits.unwrap_or(current.allow_bits),
+        deny_bits.unwrap_or(current.deny_bits),
+    )?;
+
+    let template = queries::update_permission_template(
+        state.db.write(),
+        server_id,
+        template_id,
+        name,
+        allow_bits,
+        deny_bits,
+    )
+    .await?
+    .ok_or(AppError::NotFound("Permission template not found".into()))?;
+
+    let mut changes = serde_json::Map::new();
+    if template.name != current.name {
+        changes.insert("name".into(), serde_json::json!({ "from": current.name, "to": template.name }));
+    }
+    for (key, from, to) in [
+        ("allow_bits", current.allow_bits, template.allow_bits),
+        ("deny_bits", current.deny_bits, template.deny_bits),
+    ] {
+        if from != to {
+            changes.insert(key.into(), serde_json::json!({ "from": from.to_string(), "to": to.to_string() }));
+        }
+    }
+    if !changes.is_empty() {
+        let _ = queries::insert_audit_log(
+            state.db.write(), server_id, user_id, "permission_template_update",
+            Some("permission_template"), Some(template_id),
+            Some(&serde_json::Value::Object(changes)), None,
+        ).await;
+    }
+    Ok(Json(PermissionTemplateResponse::from(template)))
+}
+
+/// DELETE /api/v1/servers/:server_id/permission-templates/:template_id
+/// Requires MANAGE_ROLES.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/permission-templates/{template_id}",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("template_id" = Uuid, Path, description = "Template ID")),
+    responses((status = 204))
+)]
+pub async fn delete_permission_template(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, template_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_ROLES).await?;
+    let template = queries::find_permission_template(state.db.read(), server_id, template_id)
+        .await?
+        .ok_or(AppError::NotFound("Permission template not found".into()))?;
+    if !queries::delete_permission_template(state.db.write(), server_id, template_id).await? {
+        return Err(AppError::NotFound("Permission template not found".into()));
+    }
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "permission_template_delete",
+        Some("permission_template"), Some(template_id),
+        Some(&serde_json::json!({ "name": template.name })), None,
+    ).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The template `template_id` of the server, for creating a role or channel.
+pub(crate) async fn resolve(state: &AppState, server_id: Uuid, template_id: Uuid) -> AppResult<PermissionTemplate> {
+    queries::find_permission_template(state.db.read(), server_id, template_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Permission template not found".into()).with_code("UNKNOWN_TEMPLATE"))
+}
+
+fn validate_name(name: &str) -> AppResult<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Template name must be 1-{} characters",
+            MAX_NAME_CHARS
+        )));
+    }
+    Ok(name)
+}
+
+/// Bits are sent as decimal strings, like role permissions.
+fn parse_bits(field: &str, value: Option<&str>) -> AppResult<Option<i64>> {
+    value
+        .map(|v| match v.trim().parse::<i64>() {
+            Ok(bits) if bits >= 0 => Ok(bits),
+            _ => Err(AppError::Validation(format!("{} must be a non-negative integer string", field))),
+        })
+        .transpose()
+}
+
+fn check_overlap(allow_bits: i64, deny_bits: i64) -> AppResult<()> {
+    if allow_bits & deny_bits != 0 {
+        return Err(AppError::Validation("A permission can't be both allowed and denied".into()));
+    }
+    Ok(())
+}
@@ -1,12 +1,22 @@
 use axum::{extract::{Query, State}, Json};
 use uuid::Uuid;
 
+use crate::db::queries;
 use crate::errors::AppError;
 use crate::models::{PresenceEntry, PresenceQuery};
 use crate::AppState;
 
 /// Bulk presence check: returns online/offline status for a list of user IDs.
+/// Users in their quiet hours show as "dnd" while online or idle.
 /// GET /api/v1/presence?user_ids=uuid1,uuid2,...
+#[utoipa::path(
+    get,
+    path = "/api/v1/presence",
+    tag = "presence",
+    params(PresenceQuery),
+    responses((status = 200, body = Vec<PresenceEntry>)),
+    security(())
+)]
 pub async fn get_presence(
     State(state): State<AppState>,
     Query(query): Query<PresenceQuery>,
@@ -21,7 +31,8 @@ pub async fn get_presence(
         return Ok(Json(vec![]));
     }
 
-    let entries = if let Some(mut redis) = state.redis.clone() {
+    let quiet = queries::get_quiet_user_ids(state.db.read(), &user_ids).await?;
+    let mut entries: Vec<PresenceEntry> = if let Some(mut redis) = state.redis.clone() {
         // Bulk-fetch presence from Redis hash
         let mut cmd = redis::cmd("HMGET");
         cmd.arg("haven:presence");
@@ -65,6 +76,11 @@ pub async fn get_presence(
             })
             .collect()
     };
+    for entry in &mut entries {
+        if quiet.contains(&entry.user_id) && (entry.status == "online" || entry.status == "idle") {
+            entry.status = "dnd".into();
+        }
+    }
 
     Ok(Json(entries))
 }
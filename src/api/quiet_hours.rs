@@ -0,0 +1,120 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::Utc;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+/// Most priority senders one schedule may list.
+const MAX_PRIORITY_SENDERS: usize = 50;
+
+/// GET /api/v1/users/me/quiet-hours
+/// The caller's Do Not Disturb schedule.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/quiet-hours",
+    tag = "users",
+    responses((status = 200, body = QuietHours))
+)]
+pub async fn get_quiet_hours(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<QuietHours>> {
+    let schedule = queries::get_quiet_hours(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("No quiet hours set".into()))?;
+    Ok(Json(schedule))
+}
+
+/// PUT /api/v1/users/me/quiet-hours
+/// Set the caller's schedule, replacing any previous one. Takes effect right
+/// away, including the caller's presence.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/me/quiet-hours",
+    tag = "users",
+    request_body = SetQuietHoursRequest,
+    responses((status = 200, body = QuietHours))
+)]
+pub async fn set_quiet_hours(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(mut req): Json<SetQuietHoursRequest>,
+) -> AppResult<Json<QuietHours>> {
+    let days = req.days.unwrap_or(0b111_1111);
+    if !(0..1440).contains(&req.start_minute) || !(0..1440).contains(&req.end_minute) {
+        return Err(AppError::Validation("start_minute and end_minute must be between 0 and 1439".into()));
+    }
+    if req.start_minute == req.end_minute {
+        return Err(AppError::Validation("Quiet hours can't start and end at the same time".into()));
+    }
+    if !(1..=0b111_1111).contains(&days) {
+        return Err(AppError::Validation("days must name at least one weekday".into()));
+    }
+    if !(-840..=840).contains(&req.utc_offset_minutes) {
+        return Err(AppError::Validation("utc_offset_minutes must be between -840 and 840".into()));
+    }
+    if req.timezone.as_ref().is_some_and(|tz| tz.len() > 64) {
+        return Err(AppError::Validation("timezone must be at most 64 characters".into()));
+    }
+    req.priority_user_ids.sort();
+    req.priority_user_ids.dedup();
+    if req.priority_user_ids.len() > MAX_PRIORITY_SENDERS {
+        return Err(AppError::Validation(format!(
+            "Too many priority senders (max {})",
+            MAX_PRIORITY_SENDERS
+        )));
+    }
+
+    let enabled = req.enabled.unwrap_or(true);
+    let active = enabled
+        && crate::quiet_hours::in_window(
+            req.start_minute,
+            req.end_minute,
+            days,
+            req.utc_offset_minutes,
+            Utc::now(),
+        );
+    let was_active = queries::get_quiet_hours(state.db.read(), user_id)
+        .await?
+        .is_some_and(|s| s.active);
+    let schedule = queries::upsert_quiet_hours(
+        state.db.write(),
+        user_id,
+        enabled,
+        req.start_minute,
+        req.end_minute,
+        days,
+        req.utc_offset_minutes,
+        req.timezone.as_deref(),
+        &req.priority_user_ids,
+        active,
+    )
+    .await?;
+    if active != was_active {
+        crate::quiet_hours::announce(&state, user_id, active).await;
+    }
+    Ok(Json(schedule))
+}
+
+/// DELETE /api/v1/users/me/quiet-hours
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/me/quiet-hours",
+    tag = "users",
+    responses((status = 204))
+)]
+pub async fn delete_quiet_hours(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<StatusCode> {
+    let schedule = queries::delete_quiet_hours(state.db.write(), user_id)
+        .await?
+        .ok_or(AppError::NotFound("No quiet hours set".into()))?;
+    if schedule.active {
+        crate::quiet_hours::announce(&state, user_id, false).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
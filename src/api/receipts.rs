@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+/// Whether a `receipt_privacy` setting shares (and sees) delivery receipts.
+fn shares_delivery(privacy: &str) -> bool {
+    privacy != "none"
+}
+
+/// Whether a `receipt_privacy` setting shares (and sees) read receipts.
+fn shares_read(privacy: &str) -> bool {
+    privacy == "all"
+}
+
+/// GET /api/v1/users/receipt-privacy
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/receipt-privacy",
+    tag = "receipts",
+    responses((status = 200))
+)]
+pub async fn get_receipt_privacy(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let receipt_privacy = queries::get_receipt_privacy(state.db.read(), user_id).await?;
+    Ok(Json(serde_json::json!({ "receipt_privacy": receipt_privacy })))
+}
+
+/// PUT /api/v1/users/receipt-privacy
+/// Choose which DM receipts to share. Receipts are reciprocal: a user who
+/// stops sharing a kind also stops seeing it from others.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/receipt-privacy",
+    tag = "receipts",
+    request_body = UpdateReceiptPrivacyRequest,
+    responses((status = 200))
+)]
+pub async fn update_receipt_privacy(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(req): Json<UpdateReceiptPrivacyRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    match req.receipt_privacy.as_str() {
+        "all" | "delivered" | "none" => {}
+        _ => {
+            return Err(AppError::Validation(
+                "receipt_privacy must be 'all', 'delivered', or 'none'".into(),
+            ));
+        }
+    }
+
+    queries::update_receipt_privacy(state.db.write(), user_id, &req.receipt_privacy).await?;
+    Ok(Json(serde_json::json!({ "receipt_privacy": req.receipt_privacy })))
+}
+
+/// GET /api/v1/channels/:channel_id/receipts
+/// How far the other member of a DM has received and read.
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/receipts",
+    tag = "receipts",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<DmReceipt>))
+)]
+pub async fn get_channel_receipts(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<Vec<DmReceipt>>> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id)
+        .await?
+        .ok_or(AppError::NotFound("Channel not found".into()))?;
+    if channel.channel_type != "dm" {
+        return Err(AppError::Validation("Receipts are only available in DMs".into()));
+    }
+
+    let viewer_privacy = queries::get_receipt_privacy(state.db.read(), user_id).await?;
+    let receipts = queries::get_channel_receipts(state.db.read(), channel_id, user_id)
+        .await?
+        .into_iter()
+        .map(|r| DmReceipt {
+            user_id: r.user_id,
+            delivered_at: r
+                .last_delivered_at
+                .filter(|_| shares_delivery(&r.receipt_privacy) && shares_delivery(&viewer_privacy)),
+            read_at: r
+                .last_read_at
+                .filter(|_| shares_read(&r.receipt_privacy) && shares_read(&viewer_privacy)),
+        })
+        .collect();
+    Ok(Json(receipts))
+}
+
+/// Record in the background that a `NewMessage` reached one of
+/// `recipient_id`'s connections. Only DM messages from someone else count.
+pub(crate) fn spawn_record_delivery(state: &AppState, recipient_id: Uuid, msg: &WsServerMessage) {
+    let WsServerMessage::NewMessage(m) = msg else { return };
+    let (state, channel_id, message_id) = (state.clone(), m.channel_id, m.id);
+    tokio::spawn(async move {
+        if let Err(e) = record_delivery(&state, recipient_id, channel_id, message_id).await {
+            tracing::warn!("Failed to record delivery of {}: {}", message_id, e);
+        }
+    });
+}
+
+async fn record_delivery(
+    state: &AppState,
+    recipient_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> AppResult<()> {
+    let Some(channel) = queries::find_channel_by_id(state.db.read(), channel_id).await? else {
+        return Ok(());
+    };
+    if channel.channel_type != "dm" {
+        return Ok(());
+    }
+    let Some(message) = queries::find_message_by_id(state.db.read(), message_id).await? else {
+        return Ok(());
+    };
+    let Some(sender_id) = message.sender_id.filter(|&s| s != recipient_id) else {
+        return Ok(());
+    };
+    if !shares_delivery(&queries::get_receipt_privacy(state.db.read(), recipient_id).await?) {
+        return Ok(());
+    }
+    if !queries::advance_delivery_state(state.db.write(), recipient_id, channel_id, message.timestamp).await? {
+        return Ok(());
+    }
+
+    if shares_delivery(&queries::get_receipt_privacy(state.db.read(), sender_id).await?) {
+        let msg = WsServerMessage::ReceiptUpdated {
+            channel_id,
+            user_id: recipient_id,
+            kind: "delivered".into(),
+            up_to: message.timestamp,
+        };
+        crate::pubsub::broadcast_user_event(state, sender_id, &msg).await;
+    }
+    Ok(())
+}
+
+/// Tell the other DM member that `reader_id` read up to `read_at`, if both
+/// share read receipts. Called when the read state is acked.
+pub(crate) async fn notify_read(
+    state: &AppState,
+    reader_id: Uuid,
+    channel_id: Uuid,
+    read_at: DateTime<Utc>,
+) -> AppResult<()> {
+    let Some(channel) = queries::find_channel_by_id(state.db.read(), channel_id).await? else {
+        return Ok(());
+    };
+    if channel.channel_type != "dm"
+        || !shares_read(&queries::get_receipt_privacy(state.db.read(), reader_id).await?)
+    {
+        return Ok(());
+    }
+
+    let msg = WsServerMessage::ReceiptUpdated {
+        channel_id,
+        user_id: reader_id,
+        kind: "read".into(),
+        up_to: read_at,
+    };
+    for member_id in queries::get_channel_member_ids(state.db.read(), channel_id).await? {
+        if member_id != reader_id
+            && shares_read(&queries::get_receipt_privacy(state.db.read(), member_id).await?)
+        {
+            crate::pubsub::broadcast_user_event(state, member_id, &msg).await;
+        }
+    }
+    Ok(())
+}
@@ -4,24 +4,42 @@ use axum::{
 };
 use uuid::Uuid;
 
+use crate::config::RegistrationMode;
 use crate::db::queries;
 use crate::errors::{AppError, AppResult};
-use crate::middleware::{AdminUser, AuthUser};
+use crate::middleware::{AuthUser, StaffUser};
+use crate::permissions;
 use crate::models::*;
 use crate::AppState;
 
 /// GET /api/v1/auth/invite-required
-/// Public: check whether registration requires an invite code.
+/// Public: check whether registration requires an invite code, and the
+/// registration mode (`open`, `invite`, `approval` or `closed`).
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/invite-required",
+    tag = "registration_invites",
+    responses((status = 200)),
+    security(())
+)]
 pub async fn invite_required(
     State(state): State<AppState>,
 ) -> AppResult<Json<serde_json::Value>> {
+    let mode = state.live_config.get().effective_registration_mode();
     Ok(Json(serde_json::json!({
-        "invite_required": state.config.registration_invite_only,
+        "invite_required": mode == RegistrationMode::Invite,
+        "registration_mode": mode.as_str(),
     })))
 }
 
 /// GET /api/v1/registration-invites
 /// List the authenticated user's registration invites.
+#[utoipa::path(
+    get,
+    path = "/api/v1/registration-invites",
+    tag = "registration_invites",
+    responses((status = 200, body = Vec<RegistrationInviteResponse>))
+)]
 pub async fn list_my_invites(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -32,11 +50,19 @@ pub async fn list_my_invites(
 
 /// GET /api/v1/admin/registration-invites
 /// Admin: list all registration invites (paginated).
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/registration-invites",
+    tag = "registration_invites",
+    params(AdminSearchQuery),
+    responses((status = 200, body = Vec<RegistrationInviteResponse>))
+)]
 pub async fn admin_list_invites(
-    AdminUser(_): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Query(params): Query<AdminSearchQuery>,
 ) -> AppResult<Json<Vec<RegistrationInviteResponse>>> {
+    staff.require(permissions::INSTANCE_VIEW_INVITES)?;
     let limit = params.limit.unwrap_or(50).min(200);
     let offset = params.offset.unwrap_or(0);
     let invites = queries::list_all_registration_invites(state.db.read(), limit, offset).await?;
@@ -45,29 +71,54 @@ pub async fn admin_list_invites(
 
 /// POST /api/v1/admin/registration-invites
 /// Admin: create registration invites (not tied to any specific user).
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/registration-invites",
+    tag = "registration_invites",
+    request_body = AdminCreateInvitesRequest,
+    responses((status = 200, body = Vec<RegistrationInviteResponse>))
+)]
 pub async fn admin_create_invites(
-    AdminUser(admin_id): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Json(req): Json<AdminCreateInvitesRequest>,
 ) -> AppResult<Json<Vec<RegistrationInviteResponse>>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
     let count = req.count.unwrap_or(1).min(50);
     let invites =
-        queries::create_registration_invites(state.db.write(), Some(admin_id), count).await?;
+        queries::create_registration_invites(state.db.write(), Some(staff.user_id), count).await?;
+    crate::api::admin::record_staff_action(
+        &state, &staff, "registration_invites_create",
+        None, None,
+        Some(&serde_json::json!({ "count": invites.len() })), None,
+    ).await;
     Ok(Json(invites.into_iter().map(Into::into).collect()))
 }
 
 /// DELETE /api/v1/admin/registration-invites/:invite_id
 /// Admin: revoke an unused registration invite.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/registration-invites/{invite_id}",
+    tag = "registration_invites",
+    params(("invite_id" = Uuid, Path, description = "Invite ID")),
+    responses((status = 200))
+)]
 pub async fn admin_delete_invite(
-    AdminUser(_): AdminUser,
+    staff: StaffUser,
     State(state): State<AppState>,
     Path(invite_id): Path<Uuid>,
 ) -> AppResult<Json<serde_json::Value>> {
+    staff.require(permissions::INSTANCE_MANAGE_INVITES)?;
     let deleted = queries::delete_registration_invite(state.db.write(), invite_id).await?;
     if !deleted {
         return Err(AppError::NotFound(
             "Invite not found or already used".into(),
         ));
     }
+    crate::api::admin::record_staff_action(
+        &state, &staff, "registration_invite_delete",
+        Some("registration_invite"), Some(invite_id), None, None,
+    ).await;
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
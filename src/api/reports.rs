@@ -7,6 +7,13 @@ use crate::models::*;
 use crate::AppState;
 
 /// POST /api/v1/reports
+#[utoipa::path(
+    post,
+    path = "/api/v1/reports",
+    tag = "reports",
+    request_body = CreateReportRequest,
+    responses((status = 200, body = ReportResponse))
+)]
 pub async fn create_report(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -1,32 +1,62 @@
 use axum::{
     extract::{Path, State},
+    http::HeaderMap,
+    response::Response,
     Json,
 };
 use uuid::Uuid;
 
 use crate::db::queries;
 use crate::errors::{AppError, AppResult};
+use crate::etag;
 use crate::middleware::AuthUser;
 use crate::models::*;
 use crate::permissions;
+use crate::ws_outbox::{self, Target};
 use crate::AppState;
 
 /// GET /api/v1/servers/:server_id/roles
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/roles",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses(
+        (status = 200, body = Vec<RoleResponse>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 pub async fn list_roles(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
-) -> AppResult<Json<Vec<RoleResponse>>> {
+    headers: HeaderMap,
+) -> AppResult<Response> {
     if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
         return Err(AppError::Forbidden("Not a member of this server".into()));
     }
 
-    let roles = queries::get_server_roles(state.db.read(), server_id).await?;
+    let pool = state.db.read();
+    let version = queries::get_server_list_version(pool, server_id).await?.unwrap_or(0);
+    let etag = etag::list_etag(server_id, version, user_id, "roles");
+    if etag::if_none_match(&headers, &etag) {
+        return Ok(etag::not_modified(etag));
+    }
+
+    let roles = queries::get_server_roles(pool, server_id).await?;
     let responses: Vec<RoleResponse> = roles.into_iter().map(RoleResponse::from).collect();
-    Ok(Json(responses))
+    Ok(etag::with_etag(etag, responses))
 }
 
 /// POST /api/v1/servers/:server_id/roles
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/roles",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateRoleRequest,
+    responses((status = 200, body = RoleResponse))
+)]
 pub async fn create_role(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -41,11 +71,15 @@ pub async fn create_role(
     )
     .await?;
 
-    let perms: i64 = req
-        .permissions
-        .as_deref()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(0);
+    let perms: i64 = match req.template_id {
+        Some(_) if req.permissions.is_some() => {
+            return Err(AppError::Validation("Send either permissions or template_id, not both".into()));
+        }
+        Some(template_id) => {
+            crate::api::permission_templates::resolve(&state, server_id, template_id).await?.allow_bits
+        }
+        None => req.permissions.as_deref().and_then(|s| s.parse().ok()).unwrap_or(0),
+    };
 
     let position = req.position.unwrap_or(0);
 
@@ -64,13 +98,21 @@ pub async fn create_role(
     let _ = queries::insert_audit_log(
         state.db.write(), server_id, user_id, "role_create",
         Some("role"), Some(role.id),
-        Some(&serde_json::json!({ "name": &role.name })), None,
+        Some(&serde_json::json!({ "name": &role.name, "template_id": req.template_id })), None,
     ).await;
 
     Ok(Json(RoleResponse::from(role)))
 }
 
 /// PUT /api/v1/servers/:server_id/roles/:role_id
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/roles/{role_id}",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("role_id" = Uuid, Path, description = "Role ID")),
+    request_body = UpdateRoleRequest,
+    responses((status = 200, body = RoleResponse))
+)]
 pub async fn update_role(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -134,6 +176,13 @@ pub async fn update_role(
 }
 
 /// DELETE /api/v1/servers/:server_id/roles/:role_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/roles/{role_id}",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("role_id" = Uuid, Path, description = "Role ID")),
+    responses((status = 200))
+)]
 pub async fn delete_role(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -187,7 +236,101 @@ pub async fn delete_role(
     Ok(Json(serde_json::json!({ "message": "Role deleted" })))
 }
 
+/// PATCH /api/v1/servers/:server_id/roles/positions
+/// Reorder the role hierarchy in one step. The list names every role but
+/// the default one, lowest first. Members without ownership can only
+/// rearrange roles below their highest: the roles at or above it must stay
+/// on top in their current order.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/roles/positions",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = UpdateRolePositionsRequest,
+    responses(
+        (status = 200, body = Vec<RolePosition>),
+        (status = 409, description = "Roles were created or deleted since the list was loaded")
+    )
+)]
+pub async fn update_role_positions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<UpdateRolePositionsRequest>,
+) -> AppResult<Json<Vec<RolePosition>>> {
+    let (is_owner, _) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner {
+        queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MANAGE_ROLES).await?;
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    if !req.roles.iter().all(|id| seen.insert(*id)) {
+        return Err(AppError::Validation("A role is listed twice".into()));
+    }
+
+    let current: Vec<Role> = queries::get_server_roles(state.db.read(), server_id)
+        .await?
+        .into_iter()
+        .filter(|r| !r.is_default)
+        .collect();
+    if !is_owner {
+        let my_highest = highest_role_position(&state, server_id, user_id).await?;
+        let protected: Vec<Uuid> = current.iter().filter(|r| r.position >= my_highest).map(|r| r.id).collect();
+        if !req.roles.ends_with(&protected) {
+            return Err(AppError::Forbidden("Cannot move roles at or above your position".into()));
+        }
+    }
+
+    let positions: Vec<RolePosition> = req
+        .roles
+        .iter()
+        .enumerate()
+        .map(|(i, id)| RolePosition { id: *id, position: i as i32 + 1 })
+        .collect();
+    let mut tx = state.db.write().begin().await?;
+    if !queries::apply_role_positions(&mut tx, server_id, &positions).await? {
+        return Err(AppError::Conflict("Roles changed since the list was loaded".into()).with_code("ROLE_LAYOUT_STALE"));
+    }
+    let event = WsServerMessage::RolePositionsUpdated { server_id, roles: positions.clone() };
+    ws_outbox::stage(&mut tx, Target::Server(server_id), &event).await?;
+    tx.commit().await?;
+
+    let role_ids: Vec<Uuid> = positions.iter().map(|r| r.id).collect();
+    queries::record_sync_changes(state.db.write(), server_id, queries::SyncEntity::Role, &role_ids, false).await?;
+
+    crate::cache::invalidate_pattern(
+        state.redis.clone().as_mut(),
+        &state.memory,
+        &format!("haven:perms:{}:*", server_id),
+    ).await;
+
+    let before: Vec<Uuid> = current.iter().map(|r| r.id).collect();
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "role_reorder",
+        None, None,
+        Some(&serde_json::json!({ "from": before, "to": &req.roles })), None,
+    ).await;
+
+    state.ws_outbox.wake();
+
+    Ok(Json(positions))
+}
+
+async fn highest_role_position(state: &AppState, server_id: Uuid, user_id: Uuid) -> AppResult<i32> {
+    let my_roles = queries::get_member_roles(state.db.read(), server_id, user_id).await?;
+    Ok(my_roles.iter().map(|r| r.position).max().unwrap_or(0))
+}
+
 /// PUT /api/v1/servers/:server_id/members/:target_user_id/roles
+/// Add one role (`role_id`), or replace the member's roles (`role_ids`).
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/members/{user_id}/roles",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = AssignRoleRequest,
+    responses((status = 200))
+)]
 pub async fn assign_role(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -205,7 +348,14 @@ pub async fn assign_role(
         .await?;
     }
 
-    let role = queries::find_role_by_id(state.db.read(), req.role_id)
+    if let Some(role_ids) = req.role_ids {
+        return replace_member_roles(&state, user_id, is_owner, server_id, target_user_id, role_ids).await.map(Json);
+    }
+    let role_id = req
+        .role_id
+        .ok_or(AppError::Validation("role_id or role_ids is required".into()))?;
+
+    let role = queries::find_role_by_id(state.db.read(), role_id)
         .await?
         .ok_or(AppError::NotFound("Role not found".into()))?;
     if role.server_id != server_id {
@@ -220,7 +370,7 @@ pub async fn assign_role(
         }
     }
 
-    queries::assign_role(state.db.write(), server_id, target_user_id, req.role_id).await?;
+    queries::assign_role(state.db.write(), server_id, target_user_id, role_id).await?;
 
     // Invalidate permission cache for target user
     crate::cache::invalidate(
@@ -233,13 +383,92 @@ pub async fn assign_role(
     let _ = queries::insert_audit_log(
         state.db.write(), server_id, user_id, "member_role_add",
         Some("member"), Some(target_user_id),
-        Some(&serde_json::json!({ "role_id": req.role_id, "role_name": &role.name })), None,
+        Some(&serde_json::json!({ "role_id": role_id, "role_name": &role.name })), None,
     ).await;
 
     Ok(Json(serde_json::json!({ "message": "Role assigned" })))
 }
 
+/// Give a member exactly `role_ids`, as one change with one audit entry and
+/// one `MemberRolesUpdated` event. Without ownership, only roles below the
+/// caller's highest may be added or removed.
+async fn replace_member_roles(
+    state: &AppState,
+    user_id: Uuid,
+    is_owner: bool,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    mut role_ids: Vec<Uuid>,
+) -> AppResult<serde_json::Value> {
+    if !queries::is_server_member(state.db.read(), server_id, target_user_id).await? {
+        return Err(AppError::NotFound("Member not found".into()));
+    }
+    role_ids.sort_unstable();
+    role_ids.dedup();
+
+    let roles = queries::get_server_roles(state.db.read(), server_id).await?;
+    let role = |id: &Uuid| roles.iter().find(|r| r.id == *id);
+    for id in &role_ids {
+        match role(id) {
+            None => return Err(AppError::NotFound("Role not found".into())),
+            Some(r) if r.is_default => {
+                return Err(AppError::Validation("The default role can't be assigned".into()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let current = queries::get_member_role_ids(state.db.read(), server_id, target_user_id).await?;
+    let added: Vec<Uuid> = role_ids.iter().filter(|id| !current.contains(id)).copied().collect();
+    let removed: Vec<Uuid> = current.iter().filter(|id| !role_ids.contains(id)).copied().collect();
+    if added.is_empty() && removed.is_empty() {
+        return Ok(serde_json::json!({ "role_ids": role_ids }));
+    }
+    if !is_owner {
+        let my_highest = highest_role_position(state, server_id, user_id).await?;
+        if added.iter().chain(&removed).filter_map(role).any(|r| r.position >= my_highest) {
+            return Err(AppError::Forbidden("Cannot change roles at or above your position".into()));
+        }
+    }
+
+    queries::set_member_roles(state.db.write(), server_id, target_user_id, &role_ids).await?;
+
+    crate::cache::invalidate(
+        state.redis.clone().as_mut(),
+        &state.memory,
+        &format!("haven:perms:{}:{}", server_id, target_user_id),
+    ).await;
+
+    let names = |ids: &[Uuid]| -> Vec<serde_json::Value> {
+        ids.iter()
+            .filter_map(role)
+            .map(|r| serde_json::json!({ "role_id": r.id, "role_name": &r.name }))
+            .collect()
+    };
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "member_roles_update",
+        Some("member"), Some(target_user_id),
+        Some(&serde_json::json!({ "added": names(&added), "removed": names(&removed) })), None,
+    ).await;
+
+    let event = WsServerMessage::MemberRolesUpdated {
+        server_id,
+        user_id: target_user_id,
+        role_ids: role_ids.clone(),
+    };
+    crate::ws::broadcast_to_server(state, server_id, event).await;
+
+    Ok(serde_json::json!({ "role_ids": role_ids }))
+}
+
 /// DELETE /api/v1/servers/:server_id/members/:target_user_id/roles/:role_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/members/{user_id}/roles/{role_id}",
+    tag = "roles",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID"), ("role_id" = Uuid, Path, description = "Role ID")),
+    responses((status = 200))
+)]
 pub async fn unassign_role(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -291,6 +520,13 @@ pub async fn unassign_role(
 }
 
 /// GET /api/v1/channels/:channel_id/overwrites
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/overwrites",
+    tag = "roles",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<OverwriteResponse>))
+)]
 pub async fn list_overwrites(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -312,6 +548,14 @@ pub async fn list_overwrites(
 }
 
 /// PUT /api/v1/channels/:channel_id/overwrites
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/overwrites",
+    tag = "roles",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetOverwriteRequest,
+    responses((status = 200, body = OverwriteResponse))
+)]
 pub async fn set_overwrite(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -361,6 +605,13 @@ pub async fn set_overwrite(
 }
 
 /// DELETE /api/v1/channels/:channel_id/overwrites/:target_type/:target_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/channels/{channel_id}/overwrites/{target_type}/{target_id}",
+    tag = "roles",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("target_type" = String, Path, description = "Overwrite target type (role or member)"), ("target_id" = Uuid, Path, description = "Role or user ID")),
+    responses((status = 200))
+)]
 pub async fn delete_overwrite(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
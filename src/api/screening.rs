@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::quota;
+use crate::AppState;
+
+const MAX_QUESTIONS: usize = 10;
+const MAX_PROMPT_LEN: usize = 300;
+const MAX_ANSWER_LEN: usize = 1000;
+const MAX_DENY_REASON_LEN: usize = 500;
+
+/// GET /api/v1/servers/:server_id/screening
+/// The server's screening questions.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/screening",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = ScreeningResponse))
+)]
+pub async fn get_screening(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<ScreeningResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    Ok(Json(screening_for(&state, server_id).await?))
+}
+
+/// PUT /api/v1/servers/:server_id/screening
+/// Turn screening on or off and set its questions. Requires MANAGE_SERVER.
+/// Pending applications stay in the queue when screening is turned off.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/screening",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = SetScreeningRequest,
+    responses((status = 200, body = ScreeningResponse))
+)]
+pub async fn set_screening(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<SetScreeningRequest>,
+) -> AppResult<Json<ScreeningResponse>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner && !permissions::has_permission(perms, permissions::MANAGE_SERVER) {
+        return Err(AppError::Forbidden("Missing MANAGE_SERVER permission".into()));
+    }
+
+    if req.questions.len() > MAX_QUESTIONS {
+        return Err(AppError::Validation(format!("Too many questions (max {})", MAX_QUESTIONS)));
+    }
+    let mut ids = HashSet::new();
+    for question in &req.questions {
+        if question.prompt.trim().is_empty() || question.prompt.len() > MAX_PROMPT_LEN {
+            return Err(AppError::Validation(format!(
+                "Question prompts must be 1-{} characters",
+                MAX_PROMPT_LEN
+            )));
+        }
+        if !ids.insert(question.id) {
+            return Err(AppError::Validation("Duplicate question id".into()));
+        }
+    }
+
+    let questions = serde_json::to_value(&req.questions)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode questions: {}", e)))?;
+    let screening = queries::upsert_server_screening(state.db.write(), server_id, req.enabled, &questions).await?;
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "screening_update",
+        Some("server"), Some(server_id),
+        Some(&serde_json::json!({ "enabled": req.enabled, "question_count": req.questions.len() })),
+        None,
+    ).await;
+
+    Ok(Json(screening.into()))
+}
+
+/// GET /api/v1/invites/:code/screening
+/// The questions to answer before joining through this invite.
+#[utoipa::path(
+    get,
+    path = "/api/v1/invites/{code}/screening",
+    tag = "invites",
+    params(("code" = String, Path, description = "Invite code")),
+    responses((status = 200, body = ScreeningResponse))
+)]
+pub async fn get_invite_screening(
+    State(state): State<AppState>,
+    AuthUser(_user_id): AuthUser,
+    Path(code): Path<String>,
+) -> AppResult<Json<ScreeningResponse>> {
+    let invite = queries::find_invite_by_code(state.db.read(), &code)
+        .await?
+        .ok_or(AppError::NotFound("Invalid invite code".into()))?;
+    Ok(Json(screening_for(&state, invite.server_id).await?))
+}
+
+/// POST /api/v1/invites/:code/apply
+/// Apply to join a screened server. The applicant isn't a member, and sees
+/// none of its channels, until a moderator approves the application.
+#[utoipa::path(
+    post,
+    path = "/api/v1/invites/{code}/apply",
+    tag = "invites",
+    params(("code" = String, Path, description = "Invite code")),
+    request_body = ApplyToServerRequest,
+    responses((status = 200, body = ServerApplicationResponse))
+)]
+pub async fn apply(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(code): Path<String>,
+    Json(req): Json<ApplyToServerRequest>,
+) -> AppResult<Json<ServerApplicationResponse>> {
+    let invite = crate::api::invites::find_usable_invite(&state, &code, user_id).await?;
+    let screening = queries::get_server_screening(state.db.read(), invite.server_id)
+        .await?
+        .filter(|s| s.enabled)
+        .ok_or(AppError::Validation("This server doesn't screen members; join it directly".into()))?;
+
+    let questions = screening.questions();
+    let mut answered = HashSet::new();
+    for answer in &req.answers {
+        if !questions.iter().any(|q| q.id == answer.question_id) {
+            return Err(AppError::Validation("Unknown screening question".into()));
+        }
+        if answer.answer.len() > MAX_ANSWER_LEN {
+            return Err(AppError::Validation(format!(
+                "Answers must be at most {} characters",
+                MAX_ANSWER_LEN
+            )));
+        }
+        if !answered.insert(answer.question_id) {
+            return Err(AppError::Validation("Each question can only be answered once".into()));
+        }
+    }
+    let answered_required = |q: &ScreeningQuestion| {
+        req.answers.iter().any(|a| a.question_id == q.id && !a.answer.trim().is_empty())
+    };
+    if questions.iter().any(|q| q.required && !answered_required(q)) {
+        return Err(AppError::Validation("Every required question must be answered".into()));
+    }
+
+    if queries::has_pending_application(state.db.read(), invite.server_id, user_id).await? {
+        return Err(AppError::Conflict("You already have a pending application to this server".into())
+            .with_code("APPLICATION_PENDING"));
+    }
+
+    let answers = serde_json::to_value(&req.answers)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode answers: {}", e)))?;
+    let application_id =
+        queries::create_server_application(state.db.write(), invite.server_id, user_id, invite.id, &answers)
+            .await?;
+    Ok(Json(find_application(&state, invite.server_id, application_id).await?.into()))
+}
+
+/// GET /api/v1/servers/:server_id/applications
+/// The moderation queue, oldest first. Requires MODERATE_MEMBERS.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/applications",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ApplicationQuery),
+    responses((status = 200, body = Vec<ServerApplicationResponse>))
+)]
+pub async fn list_applications(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Query(query): Query<ApplicationQuery>,
+) -> AppResult<Json<Vec<ServerApplicationResponse>>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MODERATE_MEMBERS)
+        .await?;
+
+    let status = query.status.unwrap_or(ApplicationStatus::Pending);
+    let applications = queries::list_server_applications(state.db.read(), server_id, status).await?;
+    Ok(Json(applications.into_iter().map(Into::into).collect()))
+}
+
+/// POST /api/v1/servers/:server_id/applications/:application_id/approve
+/// Let the applicant in. Requires MODERATE_MEMBERS.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/applications/{application_id}/approve",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("application_id" = Uuid, Path, description = "Application ID")),
+    responses((status = 200, body = ServerApplicationResponse))
+)]
+pub async fn approve_application(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, application_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<ServerApplicationResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MODERATE_MEMBERS)
+        .await?;
+    let application = find_application(&state, server_id, application_id).await?;
+    if application.status != "pending" {
+        return Err(already_decided());
+    }
+    if queries::is_banned(state.db.read(), server_id, application.user_id).await? {
+        return Err(AppError::Validation("The applicant is banned from this server".into()));
+    }
+    let already_member = queries::is_server_member(state.db.read(), server_id, application.user_id).await?;
+    if !already_member {
+        quota::check_members(&state, server_id).await?;
+    }
+
+    if !queries::decide_server_application(
+        state.db.write(),
+        application_id,
+        ApplicationStatus::Approved,
+        user_id,
+        None,
+    )
+    .await?
+    {
+        return Err(already_decided());
+    }
+    if !already_member {
+        crate::api::invites::admit_member(&state, server_id, application.user_id).await?;
+        if let Some(invite_id) = application.invite_id {
+            let invite = queries::find_invite_by_id(state.db.read(), invite_id).await?;
+            if invite.is_some_and(|invite| invite.temporary) {
+                queries::mark_member_temporary(state.db.write(), server_id, application.user_id).await?;
+            }
+            queries::increment_invite_uses(state.db.write(), invite_id).await?;
+        }
+    }
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "application_approve",
+        Some("member"), Some(application.user_id),
+        Some(&serde_json::json!({ "application_id": application_id, "username": application.username })),
+        None,
+    ).await;
+    notify_applicant(&state, &application, ApplicationStatus::Approved).await;
+
+    Ok(Json(find_application(&state, server_id, application_id).await?.into()))
+}
+
+/// POST /api/v1/servers/:server_id/applications/:application_id/deny
+/// Turn the applicant away, with an optional reason they can see.
+/// Requires MODERATE_MEMBERS. They may apply again later.
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/applications/{application_id}/deny",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("application_id" = Uuid, Path, description = "Application ID")),
+    request_body = DenyApplicationRequest,
+    responses((status = 200, body = ServerApplicationResponse))
+)]
+pub async fn deny_application(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, application_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<DenyApplicationRequest>,
+) -> AppResult<Json<ServerApplicationResponse>> {
+    queries::require_server_permission(state.db.read(), server_id, user_id, permissions::MODERATE_MEMBERS)
+        .await?;
+    if req.reason.as_ref().is_some_and(|r| r.len() > MAX_DENY_REASON_LEN) {
+        return Err(AppError::Validation(format!(
+            "reason must be at most {} characters",
+            MAX_DENY_REASON_LEN
+        )));
+    }
+    let application = find_application(&state, server_id, application_id).await?;
+
+    if !queries::decide_server_application(
+        state.db.write(),
+        application_id,
+        ApplicationStatus::Denied,
+        user_id,
+        req.reason.as_deref(),
+    )
+    .await?
+    {
+        return Err(already_decided());
+    }
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "application_deny",
+        Some("member"), Some(application.user_id),
+        Some(&serde_json::json!({ "application_id": application_id, "username": application.username })),
+        req.reason.as_deref(),
+    ).await;
+    notify_applicant(&state, &application, ApplicationStatus::Denied).await;
+
+    Ok(Json(find_application(&state, server_id, application_id).await?.into()))
+}
+
+/// GET /api/v1/users/me/applications
+/// The caller's applications to screened servers, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/applications",
+    tag = "users",
+    responses((status = 200, body = Vec<ServerApplicationResponse>))
+)]
+pub async fn list_my_applications(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<Vec<ServerApplicationResponse>>> {
+    let applications = queries::list_user_applications(state.db.read(), user_id).await?;
+    Ok(Json(applications.into_iter().map(Into::into).collect()))
+}
+
+async fn screening_for(state: &AppState, server_id: Uuid) -> AppResult<ScreeningResponse> {
+    Ok(match queries::get_server_screening(state.db.read(), server_id).await? {
+        Some(screening) => screening.into(),
+        None => ScreeningResponse {
+            server_id,
+            enabled: false,
+            questions: Vec::new(),
+        },
+    })
+}
+
+async fn find_application(state: &AppState, server_id: Uuid, application_id: Uuid) -> AppResult<ServerApplication> {
+    queries::find_server_application(state.db.read(), application_id)
+        .await?
+        .filter(|a| a.server_id == server_id)
+        .ok_or(AppError::NotFound("Application not found".into()))
+}
+
+fn already_decided() -> AppError {
+    AppError::Conflict("This application has already been decided".into()).with_code("APPLICATION_DECIDED")
+}
+
+/// Tell the applicant, on all their connections, how their application went.
+async fn notify_applicant(state: &AppState, application: &ServerApplication, status: ApplicationStatus) {
+    let msg = WsServerMessage::ApplicationDecided {
+        server_id: application.server_id,
+        application_id: application.id,
+        status: status.as_str().to_string(),
+    };
+    if let Some(conns) = state.connections.get(&application.user_id) {
+        for tx in conns.iter() {
+            let _ = tx.send(msg.clone());
+        }
+    }
+    crate::pubsub::publish_user_event(state, application.user_id, &msg).await;
+}
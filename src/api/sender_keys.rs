@@ -12,6 +12,14 @@ use crate::AppState;
 
 /// POST /api/v1/channels/:channel_id/sender-keys
 /// Distribute encrypted sender keys to channel members.
+#[utoipa::path(
+    post,
+    path = "/api/v1/channels/{channel_id}/sender-keys",
+    tag = "sender_keys",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = DistributeSenderKeyRequest,
+    responses((status = 200))
+)]
 pub async fn distribute_sender_keys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -55,15 +63,109 @@ pub async fn distribute_sender_keys(
     // Notify affected recipients via their WebSocket connections + Redis pub/sub
     let sk_msg = WsServerMessage::SenderKeysUpdated { channel_id };
     for (to_user_id, _, _) in &distributions {
-        if let Some(conns) = state.connections.get(to_user_id) {
-            for sender in conns.iter() {
-                let _ = sender.send(sk_msg.clone());
+        send_to_user(&state, *to_user_id, &sk_msg).await;
+    }
+
+    Ok(Json(serde_json::json!({ "distributed": count })))
+}
+
+/// PUT /api/v1/channels/:channel_id/sender-keys
+/// Submit a rotated sender key. Supersedes every distribution the caller
+/// previously made in this channel; recipients must all be current members.
+#[utoipa::path(
+    put,
+    path = "/api/v1/channels/{channel_id}/sender-keys",
+    tag = "sender_keys",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = DistributeSenderKeyRequest,
+    responses((status = 200))
+)]
+pub async fn rotate_sender_keys(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<DistributeSenderKeyRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
+    }
+
+    if req.distributions.is_empty() {
+        return Err(AppError::Validation("No distributions provided".into()));
+    }
+
+    let mut distributions = Vec::with_capacity(req.distributions.len());
+    for d in &req.distributions {
+        if !queries::can_access_channel(state.db.read(), channel_id, d.to_user_id).await? {
+            return Err(AppError::Validation(format!(
+                "User {} is not a member of this channel",
+                d.to_user_id
+            )));
+        }
+        let bytes = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            &d.encrypted_skdm,
+        )
+        .map_err(|_| AppError::Validation("Invalid encrypted_skdm encoding".into()))?;
+        distributions.push((d.to_user_id, d.distribution_id, bytes));
+    }
+
+    let superseded = queries::replace_sender_key_distributions(
+        state.db.write(),
+        channel_id,
+        user_id,
+        &distributions,
+    )
+    .await?;
+
+    let sk_msg = WsServerMessage::SenderKeysUpdated { channel_id };
+    for (to_user_id, _, _) in &distributions {
+        send_to_user(&state, *to_user_id, &sk_msg).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "distributed": distributions.len(),
+        "superseded": superseded,
+    })))
+}
+
+/// Burn the sender keys a departed member held in a channel: drop every SKDM
+/// to or from them and tell the remaining members to rotate.
+pub async fn require_rotation(state: &AppState, channel_id: Uuid, removed_user_id: Uuid) {
+    if let Err(e) =
+        queries::clear_channel_sender_keys_for_member(state.db.write(), channel_id, removed_user_id).await
+    {
+        tracing::warn!("Failed to clear sender keys of {} in {}: {}", removed_user_id, channel_id, e);
+    }
+    let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await else {
+        return;
+    };
+    let msg = WsServerMessage::SenderKeyRotationRequired { channel_id, removed_user_id };
+    for member_id in member_ids {
+        send_to_user(state, member_id, &msg).await;
+    }
+}
+
+/// After someone leaves or is removed from a server, require rotation in every
+/// channel of that server where they held sender keys.
+pub async fn require_rotation_for_server(state: &AppState, server_id: Uuid, removed_user_id: Uuid) {
+    match queries::get_server_sender_key_channels_for_user(state.db.read(), server_id, removed_user_id).await {
+        Ok(channel_ids) => {
+            for channel_id in channel_ids {
+                require_rotation(state, channel_id, removed_user_id).await;
             }
         }
-        crate::pubsub::publish_user_event(state.redis.clone().as_mut(), *to_user_id, &sk_msg).await;
+        Err(e) => tracing::warn!("Failed to find sender key channels of {}: {}", removed_user_id, e),
     }
+}
 
-    Ok(Json(serde_json::json!({ "distributed": count })))
+async fn send_to_user(state: &AppState, user_id: Uuid, msg: &WsServerMessage) {
+    if let Some(conns) = state.connections.get(&user_id) {
+        for sender in conns.iter() {
+            let _ = sender.send(msg.clone());
+        }
+    }
+    crate::pubsub::publish_user_event(state, user_id, msg).await;
 }
 
 /// GET /api/v1/channels/:channel_id/sender-keys
@@ -71,6 +173,13 @@ pub async fn distribute_sender_keys(
 /// SKDMs are retained so clients can re-fetch after page reloads or on new devices.
 /// The INSERT uses ON CONFLICT ... DO UPDATE, so rows are bounded to one per
 /// (channel, sender, recipient, distributionId).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/sender-keys",
+    tag = "sender_keys",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<SenderKeyDistributionResponse>))
+)]
 pub async fn get_sender_keys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -104,6 +213,13 @@ pub async fn get_sender_keys(
 /// GET /api/v1/channels/:channel_id/members/keys
 /// Fetch identity keys for all members of a channel (for encrypting SKDMs).
 /// Excludes the requesting user (they don't need to encrypt to themselves).
+#[utoipa::path(
+    get,
+    path = "/api/v1/channels/{channel_id}/members/keys",
+    tag = "sender_keys",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<ChannelMemberKeyInfo>))
+)]
 pub async fn get_channel_member_keys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
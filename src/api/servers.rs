@@ -1,24 +1,33 @@
 use axum::{
     body::Bytes,
     extract::{Path, Query, State},
-    http::{header, StatusCode},
-    response::{IntoResponse, Redirect},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::db::queries;
+use crate::db::{queries, Pool};
 use crate::errors::{AppError, AppResult};
+use crate::etag;
 use crate::middleware::AuthUser;
 use crate::models::*;
 use crate::permissions;
+use crate::profile_media;
 use crate::storage;
 use crate::AppState;
 
 const MAX_ICON_SIZE: usize = 2 * 1024 * 1024; // 2MB
 
 /// POST /api/v1/servers
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers",
+    tag = "servers",
+    request_body = CreateServerRequest,
+    responses((status = 200, body = ServerResponse))
+)]
 pub async fn create_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -51,6 +60,7 @@ pub async fn create_server(
         true,
     )
     .await?;
+    queries::create_default_permission_templates(state.db.write(), server.id).await?;
 
     // Create a default "welcome" channel
     let default_channel_meta = b"welcome"; // Would be encrypted in practice
@@ -89,6 +99,13 @@ pub async fn create_server(
 }
 
 /// GET /api/v1/servers/:server_id
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = ServerResponse))
+)]
 pub async fn get_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -104,25 +121,17 @@ pub async fn get_server(
         .ok_or(AppError::NotFound("Server not found".into()))?;
 
     let (_, perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
-
-    let system = if server.is_system { Some(true) } else { None };
-    Ok(Json(ServerResponse {
-        id: server.id,
-        encrypted_meta: base64::Engine::encode(
-            &base64::engine::general_purpose::STANDARD,
-            &server.encrypted_meta,
-        ),
-        owner_id: server.owner_id,
-        created_at: server.created_at,
-        my_permissions: Some(perms.to_string()),
-        system_channel_id: server.system_channel_id,
-        icon_url: server.icon_url.clone(),
-        is_system: system,
-    }))
+    Ok(Json(server_response(&server, perms)))
 }
 
 /// GET /api/v1/servers
 /// List servers the authenticated user is a member of.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers",
+    tag = "servers",
+    responses((status = 200, body = Vec<ServerResponse>))
+)]
 pub async fn list_servers(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -132,48 +141,64 @@ pub async fn list_servers(
     let mut responses = Vec::with_capacity(servers.len());
     for s in servers {
         let (_, perms) = queries::get_member_permissions(state.db.read(), s.id, user_id).await?;
-        let system = if s.is_system { Some(true) } else { None };
-        responses.push(ServerResponse {
-            id: s.id,
-            encrypted_meta: base64::Engine::encode(
-                &base64::engine::general_purpose::STANDARD,
-                &s.encrypted_meta,
-            ),
-            owner_id: s.owner_id,
-            created_at: s.created_at,
-            my_permissions: Some(perms.to_string()),
-            system_channel_id: s.system_channel_id,
-            icon_url: s.icon_url.clone(),
-            is_system: system,
-        });
+        responses.push(server_response(&s, perms));
     }
 
     Ok(Json(responses))
 }
 
 /// GET /api/v1/servers/:server_id/channels
+/// Supports `If-None-Match` (see `etag`).
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/channels",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses(
+        (status = 200, body = Vec<ChannelResponse>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 pub async fn list_server_channels(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
-) -> AppResult<Json<Vec<ChannelResponse>>> {
+    headers: HeaderMap,
+) -> AppResult<Response> {
     if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
         return Err(AppError::Forbidden("Not a member of this server".into()));
     }
 
-    let channels = queries::get_server_channels(state.db.read(), server_id).await?;
+    let pool = state.db.read();
+    let version = queries::get_server_list_version(pool, server_id).await?.unwrap_or(0);
+    let etag = etag::list_etag(server_id, version, user_id, "channels");
+    if etag::if_none_match(&headers, &etag) {
+        return Ok(etag::not_modified(etag));
+    }
+
+    let responses = visible_server_channels(pool, server_id, user_id).await?;
+    Ok(etag::with_etag(etag, responses))
+}
+
+/// A server's channels, minus private channels `user_id` cannot view.
+pub(crate) async fn visible_server_channels(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Vec<ChannelResponse>> {
+    let channels = queries::get_server_channels(pool, server_id).await?;
 
     // For private channel filtering, compute member's base permissions and role IDs
-    let (_is_owner, base_perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
-    let member_role_ids = queries::get_member_role_ids(state.db.read(), server_id, user_id).await?;
-    let everyone_role = queries::find_default_role(state.db.read(), server_id).await?;
+    let (_is_owner, base_perms) = queries::get_member_permissions(pool, server_id, user_id).await?;
+    let member_role_ids = queries::get_member_role_ids(pool, server_id, user_id).await?;
+    let everyone_role = queries::find_default_role(pool, server_id).await?;
     let everyone_role_id = everyone_role.map(|r| r.id).unwrap_or(Uuid::nil());
 
     let mut responses = Vec::with_capacity(channels.len());
     for c in channels {
         // Filter out private channels the user can't see
         if c.is_private {
-            let overwrites = queries::get_channel_overwrites(state.db.read(), c.id).await?;
+            let overwrites = queries::get_channel_overwrites(pool, c.id).await?;
             let ow_tuples: Vec<_> = overwrites.iter().map(|o| {
                 let target = if o.target_type == "role" {
                     permissions::OverwriteTarget::Role(o.target_id)
@@ -190,50 +215,83 @@ pub async fn list_server_channels(
             }
         }
 
-        responses.push(ChannelResponse {
-            id: c.id,
-            server_id: c.server_id,
-            encrypted_meta: base64::Engine::encode(
-                &base64::engine::general_purpose::STANDARD,
-                &c.encrypted_meta,
-            ),
-            channel_type: c.channel_type,
-            position: c.position,
-            created_at: c.created_at,
-            category_id: c.category_id,
-            dm_status: c.dm_status,
-            last_message_id: None,
-            encrypted: c.encrypted,
-            is_private: c.is_private,
-            export_allowed: c.export_allowed,
-            message_ttl: c.message_ttl,
-        });
+        responses.push(ChannelResponse::from(c));
     }
 
-    Ok(Json(responses))
+    Ok(responses)
+}
+
+/// API view of a server for a member holding `perms`.
+pub(crate) fn server_response(server: &Server, perms: i64) -> ServerResponse {
+    ServerResponse {
+        id: server.id,
+        encrypted_meta: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &server.encrypted_meta,
+        ),
+        owner_id: server.owner_id,
+        created_at: server.created_at,
+        my_permissions: Some(perms.to_string()),
+        system_channel_id: server.system_channel_id,
+        icon_url: server.icon_url.clone(),
+        is_system: if server.is_system { Some(true) } else { None },
+    }
 }
 
 /// GET /api/v1/servers/:server_id/members/@me/permissions
-/// Get the current user's effective permissions for a server.
+/// Get the current user's effective permissions for a server, and the upload
+/// limits that apply to them. With `?channel_id=`, also the permissions and
+/// upload limits in that channel.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/members/@me/permissions",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), MyPermissionsQuery),
+    responses((status = 200))
+)]
 pub async fn get_my_permissions(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
+    Query(query): Query<MyPermissionsQuery>,
 ) -> AppResult<Json<serde_json::Value>> {
     if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
         return Err(AppError::Forbidden("Not a member of this server".into()));
     }
 
     let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
-
-    Ok(Json(serde_json::json!({
+    let mut body = serde_json::json!({
         "permissions": perms.to_string(),
         "is_owner": is_owner,
-    })))
+    });
+
+    if let Some(channel_id) = query.channel_id {
+        let channel = queries::find_channel_by_id(state.db.read(), channel_id).await?;
+        if channel.and_then(|c| c.server_id) != Some(server_id) {
+            return Err(AppError::NotFound("Channel not found".into()));
+        }
+        let channel_perms =
+            queries::get_member_channel_permissions(state.db.read(), server_id, channel_id, user_id).await?;
+        body["channel_permissions"] = serde_json::json!(channel_perms.to_string());
+    }
+    let upload_limits =
+        crate::api::upload_limits::member_upload_limits(&state, server_id, query.channel_id, user_id, is_owner)
+            .await?;
+    body["upload_limits"] = serde_json::json!(upload_limits);
+
+    Ok(Json(body))
 }
 
 /// PATCH /api/v1/servers/:server_id
 /// Update server settings (system channel, etc.).
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = UpdateServerRequest,
+    responses((status = 200))
+)]
 pub async fn update_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -296,6 +354,14 @@ pub async fn update_server(
 
 /// PUT /api/v1/servers/:server_id/nickname
 /// Set or clear per-server nickname for the authenticated user.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/nickname",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = UpdateNicknameRequest,
+    responses((status = 200))
+)]
 pub async fn set_nickname(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -306,42 +372,48 @@ pub async fn set_nickname(
         return Err(AppError::Forbidden("Not a member of this server".into()));
     }
 
-    if let Some(ref nick) = req.nickname {
-        if nick.len() > 32 || nick.trim().is_empty() {
-            return Err(AppError::Validation("Nickname must be 1-32 characters".into()));
-        }
+    if req.nickname.as_deref().is_some_and(|nick| !is_valid_nickname(nick)) {
+        return Err(AppError::Validation("Nickname must be 1-32 characters".into()));
     }
 
     queries::update_member_nickname(state.db.write(), server_id, user_id, req.nickname.as_deref()).await?;
+    broadcast_member_updated(&state, server_id, user_id).await?;
 
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
 /// PUT /api/v1/servers/:server_id/members/:user_id/nickname
-/// Set or clear a member's nickname (requires MANAGE_SERVER permission).
+/// Set or clear a member's nickname (requires MANAGE_NICKNAMES permission).
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/members/{user_id}/nickname",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = UpdateNicknameRequest,
+    responses((status = 200))
+)]
 pub async fn set_member_nickname(
     State(state): State<AppState>,
     AuthUser(caller_id): AuthUser,
     Path((server_id, target_user_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<UpdateNicknameRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
-    // Require MANAGE_SERVER permission
+    // Require MANAGE_NICKNAMES permission
     let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, caller_id).await?;
-    if !is_owner && !crate::permissions::has_permission(perms, crate::permissions::MANAGE_SERVER) {
-        return Err(AppError::Forbidden("Missing MANAGE_SERVER permission".into()));
+    if !is_owner && !crate::permissions::has_permission(perms, crate::permissions::MANAGE_NICKNAMES) {
+        return Err(AppError::Forbidden("Missing MANAGE_NICKNAMES permission".into()));
     }
 
     if !queries::is_server_member(state.db.read(), server_id, target_user_id).await? {
         return Err(AppError::NotFound("Member not found".into()));
     }
 
-    if let Some(ref nick) = req.nickname {
-        if nick.len() > 32 || nick.trim().is_empty() {
-            return Err(AppError::Validation("Nickname must be 1-32 characters".into()));
-        }
+    if req.nickname.as_deref().is_some_and(|nick| !is_valid_nickname(nick)) {
+        return Err(AppError::Validation("Nickname must be 1-32 characters".into()));
     }
 
     queries::update_member_nickname(state.db.write(), server_id, target_user_id, req.nickname.as_deref()).await?;
+    broadcast_member_updated(&state, server_id, target_user_id).await?;
 
     // Audit log
     let _ = queries::insert_audit_log(
@@ -353,7 +425,160 @@ pub async fn set_member_nickname(
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
+/// PATCH /api/v1/servers/:server_id/members/:user_id
+/// Update a member's per-server profile (nickname and/or server avatar).
+/// Members may edit their own; editing others requires MANAGE_NICKNAMES.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/servers/{server_id}/members/{user_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = UpdateMemberRequest,
+    responses((status = 200))
+)]
+pub async fn update_member(
+    State(state): State<AppState>,
+    AuthUser(caller_id): AuthUser,
+    Path((server_id, target_user_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateMemberRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !queries::is_server_member(state.db.read(), server_id, caller_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    if target_user_id != caller_id {
+        let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, caller_id).await?;
+        if !is_owner && !crate::permissions::has_permission(perms, crate::permissions::MANAGE_NICKNAMES) {
+            return Err(AppError::Forbidden("Missing MANAGE_NICKNAMES permission".into()));
+        }
+        if !queries::is_server_member(state.db.read(), server_id, target_user_id).await? {
+            return Err(AppError::NotFound("Member not found".into()));
+        }
+    }
+
+    if let Some(Some(ref nick)) = req.nickname {
+        if !is_valid_nickname(nick) {
+            return Err(AppError::Validation("Nickname must be 1-32 characters".into()));
+        }
+    }
+    let avatar = match req.avatar {
+        Some(Some(ref encoded)) => Some(Some(
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+                .map_err(|_| AppError::Validation("Invalid base64 in avatar".into()))?,
+        )),
+        Some(None) => Some(None),
+        None => None,
+    };
+
+    // Avatar first: it can still fail validation or the quota check
+    let slot = profile_media::MediaSlot::ServerAvatar(server_id);
+    match avatar {
+        Some(Some(data)) => {
+            crate::api::users::store_profile_media(&state, target_user_id, slot, &data).await?;
+        }
+        Some(None) => {
+            crate::api::users::remove_profile_media(&state, target_user_id, slot).await?;
+        }
+        None => {}
+    }
+    if let Some(ref nickname) = req.nickname {
+        queries::update_member_nickname(state.db.write(), server_id, target_user_id, nickname.as_deref()).await?;
+    }
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, caller_id, "member_update",
+        Some("member"), Some(target_user_id),
+        Some(&serde_json::json!({
+            "nickname": req.nickname,
+            "avatar_updated": req.avatar.is_some(),
+        })), None,
+    ).await;
+
+    let (nickname, server_avatar_url) = broadcast_member_updated(&state, server_id, target_user_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "user_id": target_user_id,
+        "nickname": nickname,
+        "server_avatar_url": server_avatar_url,
+    })))
+}
+
+/// GET /api/v1/servers/:server_id/members/:user_id/avatar/:hash — one server
+/// avatar version (no auth required for <img> src).
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/members/{user_id}/avatar/{hash}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID"), ("hash" = String, Path, description = "Content hash")),
+    responses((status = 200)),
+    security(())
+)]
+pub async fn get_member_avatar(
+    State(state): State<AppState>,
+    Path((server_id, user_id, hash)): Path<(Uuid, Uuid, String)>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let slot = profile_media::MediaSlot::ServerAvatar(server_id);
+    crate::api::users::serve_profile_media(&state, user_id, slot, Some(&hash), &headers).await
+}
+
+/// Tell the server about a member's current per-server profile, and return
+/// it as (nickname, server avatar URL).
+async fn broadcast_member_updated(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<(Option<String>, Option<String>)> {
+    let (nickname, server_avatar_url) = queries::get_member_profile(state.db.read(), server_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound("Member not found".into()))?;
+    crate::ws::broadcast_to_server(state, server_id, WsServerMessage::MemberUpdated {
+        server_id,
+        user_id,
+        nickname: nickname.clone(),
+        server_avatar_url: server_avatar_url.clone(),
+    })
+    .await;
+    Ok((nickname, server_avatar_url))
+}
+
+/// Nicknames are 1–32 bytes and not blank.
+pub fn is_valid_nickname(nickname: &str) -> bool {
+    nickname.len() <= 32 && !nickname.trim().is_empty()
+}
+
+/// Drop the per-server profile a departing member leaves behind (their
+/// nickname goes with the membership row; the server avatar is stored apart).
+pub async fn remove_member_profile(state: &AppState, server_id: Uuid, user_id: Uuid) {
+    let slot = profile_media::MediaSlot::ServerAvatar(server_id);
+    if let Err(e) = crate::api::users::remove_profile_media(state, user_id, slot).await {
+        tracing::warn!("Failed to remove server avatar of {} in {}: {}", user_id, server_id, e);
+    }
+}
+
+/// Delete every member's server avatar for a deleted server.
+async fn delete_server_avatars(state: &AppState, server_id: Uuid) {
+    let slot = profile_media::MediaSlot::ServerAvatar(server_id);
+    let media = match queries::delete_profile_media_by_slot(state.db.write(), &slot.key()).await {
+        Ok(media) => media,
+        Err(e) => {
+            tracing::warn!("Failed to remove server avatars of {}: {}", server_id, e);
+            return;
+        }
+    };
+    for m in media {
+        let key = profile_media::storage_key(&state.storage_key, slot, m.user_id, &m.content_hash);
+        let _ = state.storage.delete_blob(&key).await;
+    }
+}
+
 /// DELETE /api/v1/servers/:server_id/members/@me — leave a server
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/members/@me",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200))
+)]
 pub async fn leave_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -377,6 +602,7 @@ pub async fn leave_server(
         }
         // Owner is the only member — delete the entire server
         queries::delete_server(state.db.write(), server_id).await?;
+        delete_server_avatars(&state, server_id).await;
         crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:server:{}", server_id)).await;
         return Ok(Json(serde_json::json!({ "ok": true })));
     }
@@ -387,37 +613,56 @@ pub async fn leave_server(
         .ok_or(AppError::UserNotFound)?;
 
     queries::remove_server_member(state.db.write(), server_id, user_id).await?;
+    remove_member_profile(&state, server_id, user_id).await;
+    crate::api::sender_keys::require_rotation_for_server(&state, server_id, user_id).await;
 
     // Post system message in system channel
     if let Some(system_channel_id) = server.system_channel_id {
-        let body = serde_json::json!({
-            "event": "member_left",
-            "username": user.username,
-            "user_id": user_id.to_string(),
-        });
-        if let Ok(sys_msg) = queries::insert_system_message(
-            state.db.write(),
-            system_channel_id,
-            &body.to_string(),
-        )
-        .await
+        let body = SystemMessage::new(SystemEvent::MemberLeft, user_id, user.username);
+        if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), system_channel_id, &body).await
         {
             let response: MessageResponse = sys_msg.into();
-            if let Some(broadcaster) = state.channel_broadcasts.get(&system_channel_id) {
-                let _ = broadcaster.send(WsServerMessage::NewMessage(response));
-            }
+            crate::pubsub::broadcast_channel_event(&state, system_channel_id, &WsServerMessage::NewMessage(response)).await;
         }
     }
 
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
-/// DELETE /api/v1/servers/:server_id — delete a server (owner only)
+/// How long a deleted server can be restored before it is purged.
+pub const DELETION_GRACE_DAYS: i32 = 7;
+
+fn deleted_server_response(server: DeletedServer) -> DeletedServerResponse {
+    DeletedServerResponse {
+        id: server.id,
+        encrypted_meta: base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            &server.encrypted_meta,
+        ),
+        icon_url: server.icon_url,
+        deleted_at: server.deleted_at,
+        purge_at: server.deleted_at + chrono::Duration::days(DELETION_GRACE_DAYS as i64),
+    }
+}
+
+/// DELETE /api/v1/servers/:server_id — delete a server (owner only).
+/// The owner re-enters their password (and TOTP code, if enabled). The
+/// server disappears for every member at once but can be restored for
+/// `DELETION_GRACE_DAYS`; clients should offer an export first.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = DeleteServerRequest,
+    responses((status = 200, body = DeletedServerResponse))
+)]
 pub async fn delete_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(server_id): Path<Uuid>,
-) -> AppResult<Json<serde_json::Value>> {
+    Json(req): Json<DeleteServerRequest>,
+) -> AppResult<Json<DeletedServerResponse>> {
     let server = queries::find_server_by_id(state.db.read(), server_id)
         .await?
         .ok_or(AppError::NotFound("Server not found".into()))?;
@@ -432,18 +677,123 @@ pub async fn delete_server(
         ));
     }
 
-    // Cascade delete handles all child records
-    queries::delete_server(state.db.write(), server_id).await?;
+    if queries::is_under_legal_hold(state.db.read(), LegalHoldSubject::Server, server_id).await? {
+        return Err(crate::api::admin::legal_hold_error());
+    }
+
+    let user = queries::find_user_by_id(state.db.read(), user_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+    if !crate::auth::verify_password(&req.password, &user.password_hash)? {
+        return Err(AppError::AuthError("Incorrect password".into()));
+    }
+    if let Some(ref secret) = user.totp_secret {
+        let code = req.totp_code.as_deref().ok_or_else(|| {
+            AppError::AuthError("TOTP code required".into()).with_code("TOTP_REQUIRED")
+        })?;
+        if !crate::auth::verify_totp(secret, code)? {
+            return Err(AppError::AuthError("Invalid TOTP code".into()).with_code("INVALID_TOTP"));
+        }
+    }
+
+    let deleted_at = queries::soft_delete_server(state.db.write(), server_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound("Server not found".into()))?;
 
     // Invalidate cache
     crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:server:{}", server_id)).await;
+    crate::cache::invalidate_pattern(state.redis.clone().as_mut(), &state.memory, &format!("haven:perms:{}:*", server_id)).await;
+
+    Ok(Json(deleted_server_response(DeletedServer {
+        id: server.id,
+        encrypted_meta: server.encrypted_meta,
+        icon_url: server.icon_url,
+        deleted_at,
+    })))
+}
+
+/// GET /api/v1/servers/deleted — the caller's servers awaiting purge
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/deleted",
+    tag = "servers",
+    responses((status = 200, body = Vec<DeletedServerResponse>))
+)]
+pub async fn list_deleted_servers(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<Vec<DeletedServerResponse>>> {
+    let servers = queries::get_deleted_servers_owned_by(state.db.read(), user_id).await?;
+    Ok(Json(servers.into_iter().map(deleted_server_response).collect()))
+}
+
+/// POST /api/v1/servers/:server_id/undelete — restore a deleted server
+/// during its grace period (owner only)
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/undelete",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200))
+)]
+pub async fn undelete_server(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    let (owner_id, _) = queries::find_deleted_server(state.db.read(), server_id)
+        .await?
+        .ok_or(AppError::NotFound("No deleted server with that id".into()))?;
+    if owner_id != user_id {
+        return Err(AppError::Forbidden(
+            "Only the server owner can restore the server".into(),
+        ));
+    }
+
+    if !queries::restore_deleted_server(state.db.write(), server_id).await? {
+        return Err(AppError::NotFound("No deleted server with that id".into()));
+    }
+    crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:server:{}", server_id)).await;
 
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
+/// Remove a soft-deleted server for good: attachment and emoji blobs, server
+/// avatars, messages, then the server row and everything cascading from it.
+pub(crate) async fn purge_server(state: &AppState, server_id: Uuid) -> AppResult<()> {
+    let pool = state.db.primary();
+    for (id, storage_key, thumbnail_key) in queries::get_server_attachment_keys(pool, server_id).await? {
+        if let Some(key) = thumbnail_key {
+            let _ = state.storage.delete_blob(&key).await;
+        }
+        if let Some(storage_key) = storage_key {
+            if let Err(e) = state.storage.delete_blob(&storage_key).await {
+                tracing::warn!("Failed to delete blob of attachment {}: {}", id, e);
+            }
+        }
+    }
+    for emoji in queries::list_server_emojis(pool, server_id).await? {
+        let _ = state.storage.delete_blob(&emoji.storage_key).await;
+    }
+    delete_server_avatars(state, server_id).await;
+
+    queries::purge_server_messages(pool, server_id).await?;
+    // Cascade delete handles all child records
+    queries::delete_server(pool, server_id).await?;
+    Ok(())
+}
+
 // ─── Server Icon ────────────────────────────────────────
 
 /// POST /api/v1/servers/:server_id/icon — upload server icon (raw bytes)
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/icon",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200))
+)]
 pub async fn upload_icon(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -485,6 +835,14 @@ pub async fn upload_icon(
 }
 
 /// GET /api/v1/servers/:server_id/icon — serve server icon image (no auth for <img> src)
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/icon",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200)),
+    security(())
+)]
 pub async fn get_icon(
     State(state): State<AppState>,
     Path(server_id): Path<Uuid>,
@@ -537,6 +895,13 @@ pub async fn get_icon(
 }
 
 /// DELETE /api/v1/servers/:server_id/icon — remove server icon
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/icon",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200))
+)]
 pub async fn delete_icon(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -562,6 +927,14 @@ pub async fn delete_icon(
 // ─── Member Timeout ──────────────────────────────────
 
 /// PUT /api/v1/servers/:server_id/members/:user_id/timeout
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/members/{user_id}/timeout",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = TimeoutMemberRequest,
+    responses((status = 200))
+)]
 pub async fn timeout_member(
     State(state): State<AppState>,
     AuthUser(caller_id): AuthUser,
@@ -631,6 +1004,7 @@ pub async fn timeout_member(
                     let _ = sender.send(ws_msg.clone());
                 }
             }
+            crate::pubsub::publish_user_event(&state, member_id, &ws_msg).await;
         }
     }
 
@@ -653,6 +1027,13 @@ pub async fn timeout_member(
 // ─── Audit Log ───────────────────────────────────────
 
 /// GET /api/v1/servers/:server_id/audit-log
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/audit-log",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), AuditLogQuery),
+    responses((status = 200, body = Vec<AuditLogResponse>))
+)]
 pub async fn get_audit_log(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -688,7 +1069,8 @@ fn detect_icon_type(data: &[u8]) -> String {
 
 // ─── Server Export ───────────────────────────────────
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ServerExportQuery {
     pub after: Option<DateTime<Utc>>,
     pub before: Option<DateTime<Utc>>,
@@ -696,7 +1078,7 @@ pub struct ServerExportQuery {
     pub include_attachments: bool,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct ServerExportChannelData {
     pub channel_id: Uuid,
     pub channel_type: String,
@@ -708,12 +1090,13 @@ pub struct ServerExportChannelData {
     pub messages: Vec<MessageResponse>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
 pub struct ServerExportResponse {
     pub server_id: Uuid,
     pub encrypted_meta: String,
     pub owner_id: Uuid,
     pub icon_url: Option<String>,
+    pub system_channel_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub exported_at: DateTime<Utc>,
     pub exported_by: Uuid,
@@ -722,13 +1105,21 @@ pub struct ServerExportResponse {
     pub roles: Vec<RoleResponse>,
     pub permission_overwrites: Vec<OverwriteResponse>,
     pub members: Vec<ServerMemberResponse>,
-    pub emojis: Vec<CustomEmojiResponse>,
+    /// Pluggable configuration sections, keyed by section name (see `restore_sections`)
+    pub sections: std::collections::HashMap<String, serde_json::Value>,
     pub include_attachments: bool,
 }
 
 /// GET /api/v1/servers/:server_id/export
 /// Bulk export server metadata, structure, and messages for accessible channels.
 #[allow(clippy::too_many_lines)]
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/export",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ServerExportQuery),
+    responses((status = 200, body = ServerExportResponse))
+)]
 pub async fn export_server(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -842,13 +1233,8 @@ pub async fn export_server(
     // Get members (all, no pagination for export)
     let members = queries::get_server_members(state.db.read(), server_id, 10000, 0).await?;
 
-    // Get emojis
-    let emojis: Vec<CustomEmojiResponse> =
-        queries::list_server_emojis(state.db.read(), server_id)
-            .await?
-            .iter()
-            .map(|e| e.to_response())
-            .collect();
+    // Pluggable configuration sections (emoji metadata is exported here)
+    let sections = crate::restore_sections::export_all(state.db.read(), server_id).await?;
 
     // Audit log
     let _ = queries::insert_audit_log(
@@ -873,6 +1259,7 @@ pub async fn export_server(
         ),
         owner_id: server.owner_id,
         icon_url: server.icon_url,
+        system_channel_id: server.system_channel_id,
         created_at: server.created_at,
         exported_at: Utc::now(),
         exported_by: user_id,
@@ -881,7 +1268,7 @@ pub async fn export_server(
         roles,
         permission_overwrites: all_overwrites,
         members,
-        emojis,
+        sections,
         include_attachments: params.include_attachments,
     }))
 }
@@ -889,6 +1276,13 @@ pub async fn export_server(
 // ─── Content Filters ─────────────────────────────────
 
 /// GET /api/v1/servers/:server_id/content-filters
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/content-filters",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<ContentFilterResponse>))
+)]
 pub async fn list_content_filters(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -916,6 +1310,14 @@ pub async fn list_content_filters(
 }
 
 /// POST /api/v1/servers/:server_id/content-filters
+#[utoipa::path(
+    post,
+    path = "/api/v1/servers/{server_id}/content-filters",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateContentFilterRequest,
+    responses((status = 200, body = ContentFilterResponse))
+)]
 pub async fn create_content_filter(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -941,9 +1343,9 @@ pub async fn create_content_filter(
     let action = req.action.as_deref().unwrap_or("hide");
 
     // Validate filter_type
-    if filter_type != "keyword" && filter_type != "regex" {
+    if filter_type != "keyword" && filter_type != "regex" && filter_type != "abuse" {
         return Err(AppError::Validation(
-            "filter_type must be 'keyword' or 'regex'".into(),
+            "filter_type must be 'keyword', 'regex' or 'abuse'".into(),
         ));
     }
 
@@ -959,6 +1361,13 @@ pub async fn create_content_filter(
         return Err(AppError::Validation("Invalid regex pattern".into()));
     }
 
+    // Abuse filters match members by registration score (see crate::abuse)
+    if filter_type == "abuse" && !matches!(req.pattern.parse::<i16>(), Ok(1..=100)) {
+        return Err(AppError::Validation(
+            "An abuse filter's pattern must be a score from 1 to 100".into(),
+        ));
+    }
+
     // Max 50 filters per server
     let count = queries::count_content_filters(state.db.read(), server_id).await?;
     if count >= 50 {
@@ -976,6 +1385,10 @@ pub async fn create_content_filter(
         user_id,
     )
     .await?;
+    if filter_type == "abuse" {
+        // Member lists carry the resulting automod actions
+        queries::bump_server_list_version(state.db.write(), server_id).await?;
+    }
 
     Ok(Json(ContentFilterResponse {
         id: filter.id,
@@ -988,6 +1401,13 @@ pub async fn create_content_filter(
 }
 
 /// DELETE /api/v1/servers/:server_id/content-filters/:filter_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/content-filters/{filter_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("filter_id" = Uuid, Path, description = "Filter ID")),
+    responses((status = 200))
+)]
 pub async fn delete_content_filter(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -1002,5 +1422,7 @@ pub async fn delete_content_filter(
     .await?;
 
     queries::delete_content_filter(state.db.write(), filter_id, server_id).await?;
+    // It may have been an abuse filter, which member lists reflect
+    queries::bump_server_list_version(state.db.write(), server_id).await?;
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
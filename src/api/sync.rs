@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::{queries, Pool};
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::AppState;
+
+/// Relationships returned by a sync; far above what any real account has.
+const SYNC_RELATIONSHIP_LIMIT: i64 = 1000;
+
+/// GET /api/v1/sync
+/// Everything a client needs at startup in one response: servers with their
+/// channels and roles, DMs, read states and relationships. Servers listed in
+/// `known` at their current version are returned without channels and roles.
+#[utoipa::path(
+    get,
+    path = "/api/v1/sync",
+    tag = "sync",
+    params(SyncQuery),
+    responses((status = 200, body = SyncResponse))
+)]
+pub async fn get_sync(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<SyncQuery>,
+) -> AppResult<Json<SyncResponse>> {
+    let known = parse_known(query.known.as_deref())?;
+    // One pool throughout, so versions and lists come from the same snapshot source
+    let pool = state.db.read();
+    // Read the journal version first: a concurrent change then shows up again
+    // in the next delta rather than being skipped
+    let (version, _) = queries::sync_journal_bounds(pool).await?;
+
+    let mut servers = Vec::new();
+    for server in queries::get_user_servers(pool, user_id).await? {
+        servers.push(sync_server(pool, &server, user_id, known.get(&server.id).copied()).await?);
+    }
+
+    let dm_channels = queries::get_user_dm_channels(pool, user_id).await?;
+    let (read_states, relationships) = tokio::try_join!(
+        super::channels::unread_infos(pool, user_id),
+        queries::get_relationships(pool, user_id, SYNC_RELATIONSHIP_LIMIT, 0),
+    )?;
+
+    Ok(Json(SyncResponse {
+        version,
+        servers,
+        dm_channels: dm_channels.into_iter().map(ChannelResponse::from).collect(),
+        read_states,
+        relationships,
+    }))
+}
+
+/// Changes to the user's servers, channels and roles after `since` (WS `Sync`).
+/// Channel changes are sent per channel; anything that can change the user's
+/// permissions (server, role or own membership changes) resends the server whole.
+pub async fn sync_delta(pool: &Pool, user_id: Uuid, since: i64) -> AppResult<SyncDelta> {
+    let (version, oldest) = queries::sync_journal_bounds(pool).await?;
+    let pruned = since < version && oldest.is_none_or(|oldest| since + 1 < oldest);
+    if since > version || pruned {
+        return Ok(SyncDelta { version, full_sync_required: true, ..Default::default() });
+    }
+
+    let servers: HashMap<Uuid, Server> = queries::get_user_servers(pool, user_id)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect();
+    let server_ids: Vec<Uuid> = servers.keys().copied().collect();
+    let mut by_server: HashMap<Uuid, Vec<SyncChange>> = HashMap::new();
+    for change in queries::get_sync_changes(pool, user_id, &server_ids, since).await? {
+        by_server.entry(change.server_id).or_default().push(change);
+    }
+
+    let mut delta = SyncDelta { version, ..Default::default() };
+    for (server_id, changes) in by_server {
+        let Some(server) = servers.get(&server_id) else {
+            delta.removed_servers.push(server_id);
+            continue;
+        };
+        if changes.iter().any(|c| c.entity_type != "channel") {
+            delta.servers.push(sync_server(pool, server, user_id, None).await?);
+            continue;
+        }
+        let mut visible: HashMap<Uuid, ChannelResponse> =
+            super::servers::visible_server_channels(pool, server_id, user_id)
+                .await?
+                .into_iter()
+                .map(|c| (c.id, c))
+                .collect();
+        for change in changes {
+            match visible.remove(&change.entity_id) {
+                Some(channel) => delta.channels.push(channel),
+                None => delta.deleted_channels.push(change.entity_id),
+            }
+        }
+    }
+    Ok(delta)
+}
+
+/// A server as seen by `user_id`, without channels and roles when the client
+/// already holds them at the current list version.
+async fn sync_server(pool: &Pool, server: &Server, user_id: Uuid, known_version: Option<i64>) -> AppResult<SyncServer> {
+    // Version before lists: a racing change costs a refetch, never a stale list
+    let version = queries::get_server_list_version(pool, server.id).await?.unwrap_or(0);
+    let (_, perms) = queries::get_member_permissions(pool, server.id, user_id).await?;
+    let (channels, roles) = if known_version == Some(version) {
+        (None, None)
+    } else {
+        let channels = super::servers::visible_server_channels(pool, server.id, user_id).await?;
+        let roles = queries::get_server_roles(pool, server.id).await?;
+        (Some(channels), Some(roles.into_iter().map(RoleResponse::from).collect()))
+    };
+    Ok(SyncServer {
+        server: super::servers::server_response(server, perms),
+        version,
+        channels,
+        roles,
+    })
+}
+
+fn parse_known(known: Option<&str>) -> AppResult<HashMap<Uuid, i64>> {
+    let invalid = || AppError::Validation("known must be comma-separated server_id:version pairs".into());
+    known
+        .into_iter()
+        .flat_map(|k| k.split(','))
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| -> AppResult<(Uuid, i64)> {
+            let (id, version) = pair.trim().split_once(':').ok_or_else(invalid)?;
+            Ok((id.parse().map_err(|_| invalid())?, version.parse().map_err(|_| invalid())?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_known_pairs() {
+        let id = Uuid::new_v4();
+        let known = parse_known(Some(&format!("{}:7, ", id))).unwrap();
+        assert_eq!(known.get(&id), Some(&7));
+        assert!(parse_known(None).unwrap().is_empty());
+        assert!(parse_known(Some("not-a-uuid:1")).is_err());
+        assert!(parse_known(Some(&id.to_string())).is_err());
+    }
+}
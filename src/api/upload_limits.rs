@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::uploads::{self, UploadTier};
+use crate::AppState;
+
+/// Most uploads a limit may allow in progress at once.
+const MAX_UPLOAD_COUNT: i32 = 100;
+
+/// GET /api/v1/servers/:server_id/upload-limits
+/// The upload limits set on the server's roles and channels.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/upload-limits",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = Vec<UploadLimit>))
+)]
+pub async fn list_upload_limits(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<Vec<UploadLimit>>> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    Ok(Json(queries::list_upload_limits(state.db.read(), server_id).await?))
+}
+
+/// PUT /api/v1/servers/:server_id/upload-limits/roles/:role_id
+/// Set the upload limits of a role. Requires MANAGE_ROLES.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/upload-limits/roles/{role_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("role_id" = Uuid, Path, description = "Role ID")),
+    request_body = SetUploadLimitRequest,
+    responses((status = 200, body = UploadLimit))
+)]
+pub async fn set_role_upload_limit(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, role_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<SetUploadLimitRequest>,
+) -> AppResult<Json<UploadLimit>> {
+    require_manager(&state, server_id, user_id, permissions::MANAGE_ROLES).await?;
+    let role = queries::find_role_by_id(state.db.read(), role_id).await?;
+    if role.map(|r| r.server_id) != Some(server_id) {
+        return Err(AppError::NotFound("Role not found".into()));
+    }
+    validate(&req)?;
+
+    let limit = queries::set_role_upload_limit(
+        state.db.write(),
+        server_id,
+        role_id,
+        req.max_size_bytes,
+        req.max_count,
+    )
+    .await?;
+    audit(&state, server_id, user_id, "role", role_id, Some(&req)).await;
+    Ok(Json(limit))
+}
+
+/// DELETE /api/v1/servers/:server_id/upload-limits/roles/:role_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/upload-limits/roles/{role_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("role_id" = Uuid, Path, description = "Role ID")),
+    responses((status = 204))
+)]
+pub async fn delete_role_upload_limit(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, role_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    require_manager(&state, server_id, user_id, permissions::MANAGE_ROLES).await?;
+    if !queries::delete_role_upload_limit(state.db.write(), server_id, role_id).await? {
+        return Err(AppError::NotFound("No upload limit set on this role".into()));
+    }
+    audit(&state, server_id, user_id, "role", role_id, None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/v1/servers/:server_id/upload-limits/channels/:channel_id
+/// Set the upload limits of a channel. Requires MANAGE_CHANNELS.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/upload-limits/channels/{channel_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("channel_id" = Uuid, Path, description = "Channel ID")),
+    request_body = SetUploadLimitRequest,
+    responses((status = 200, body = UploadLimit))
+)]
+pub async fn set_channel_upload_limit(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<SetUploadLimitRequest>,
+) -> AppResult<Json<UploadLimit>> {
+    require_manager(&state, server_id, user_id, permissions::MANAGE_CHANNELS).await?;
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id).await?;
+    if channel.and_then(|c| c.server_id) != Some(server_id) {
+        return Err(AppError::NotFound("Channel not found".into()));
+    }
+    validate(&req)?;
+
+    let limit = queries::set_channel_upload_limit(
+        state.db.write(),
+        server_id,
+        channel_id,
+        req.max_size_bytes,
+        req.max_count,
+    )
+    .await?;
+    audit(&state, server_id, user_id, "channel", channel_id, Some(&req)).await;
+    Ok(Json(limit))
+}
+
+/// DELETE /api/v1/servers/:server_id/upload-limits/channels/:channel_id
+#[utoipa::path(
+    delete,
+    path = "/api/v1/servers/{server_id}/upload-limits/channels/{channel_id}",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID"), ("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 204))
+)]
+pub async fn delete_channel_upload_limit(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    require_manager(&state, server_id, user_id, permissions::MANAGE_CHANNELS).await?;
+    if !queries::delete_channel_upload_limit(state.db.write(), server_id, channel_id).await? {
+        return Err(AppError::NotFound("No upload limit set on this channel".into()));
+    }
+    audit(&state, server_id, user_id, "channel", channel_id, None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The upload limits for a member of the server, in `channel_id` if given.
+/// The owner is held only to the upload tier.
+pub(crate) async fn member_upload_limits(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Option<Uuid>,
+    user_id: Uuid,
+    is_owner: bool,
+) -> AppResult<EffectiveUploadLimits> {
+    let tier = queries::get_server_upload_tier(state.db.read(), server_id)
+        .await?
+        .and_then(|t| UploadTier::parse(&t))
+        .unwrap_or(UploadTier::Standard);
+    let tier_max = tier.max_size(state.config.max_upload_size_bytes);
+    if is_owner {
+        return Ok(uploads::effective_limits(tier_max, &[], None));
+    }
+    let role_limits = queries::get_member_role_upload_limits(state.db.read(), server_id, user_id).await?;
+    let channel_limit = match channel_id {
+        Some(channel_id) => queries::get_channel_upload_limit(state.db.read(), channel_id).await?,
+        None => None,
+    };
+    Ok(uploads::effective_limits(tier_max, &role_limits, channel_limit.as_ref()))
+}
+
+async fn require_manager(state: &AppState, server_id: Uuid, user_id: Uuid, required: i64) -> AppResult<()> {
+    if !queries::is_server_member(state.db.read(), server_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this server".into()));
+    }
+    queries::require_server_permission(state.db.read(), server_id, user_id, required).await
+}
+
+fn validate(req: &SetUploadLimitRequest) -> AppResult<()> {
+    if req.max_size_bytes.is_some_and(|size| size < 0) {
+        return Err(AppError::Validation("max_size_bytes can't be negative".into()));
+    }
+    if req.max_count.is_some_and(|count| !(0..=MAX_UPLOAD_COUNT).contains(&count)) {
+        return Err(AppError::Validation(format!("max_count must be 0-{}", MAX_UPLOAD_COUNT)));
+    }
+    if req.max_size_bytes.is_none() && req.max_count.is_none() {
+        return Err(AppError::Validation("Set max_size_bytes, max_count or both".into()));
+    }
+    Ok(())
+}
+
+async fn audit(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+    target_type: &str,
+    target_id: Uuid,
+    req: Option<&SetUploadLimitRequest>,
+) {
+    let details = match req {
+        Some(req) => serde_json::json!({ "max_size_bytes": req.max_size_bytes, "max_count": req.max_count }),
+        None => serde_json::json!({ "removed": true }),
+    };
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "upload_limit_update",
+        Some(target_type), Some(target_id), Some(&details), None,
+    ).await;
+}
@@ -1,8 +1,8 @@
 use axum::{
     body::Bytes,
     extract::{Path, Query, State},
-    http::{header, StatusCode},
-    response::{IntoResponse, Redirect},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
 use serde::Deserialize;
@@ -12,23 +12,29 @@ use crate::db::queries;
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::*;
-use crate::storage;
+use crate::profile_media::{self, MediaSlot};
 use crate::AppState;
 
-const MAX_AVATAR_SIZE: usize = 2 * 1024 * 1024; // 2MB
-const MAX_BANNER_SIZE: usize = 8 * 1024 * 1024; // 8MB
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserSearchQuery {
     pub username: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ProfileQuery {
     pub server_id: Option<Uuid>,
 }
 
 /// GET /api/v1/users/search?username=Mork
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/search",
+    tag = "users",
+    params(UserSearchQuery),
+    responses((status = 200, body = UserPublic))
+)]
 pub async fn get_user_by_username(
     State(state): State<AppState>,
     AuthUser(_user_id): AuthUser,
@@ -40,6 +46,13 @@ pub async fn get_user_by_username(
 }
 
 /// GET /api/v1/users/:user_id/profile
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/profile",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID"), ProfileQuery),
+    responses((status = 200, body = UserProfileResponse))
+)]
 pub async fn get_profile(
     State(state): State<AppState>,
     AuthUser(requester_id): AuthUser,
@@ -120,6 +133,13 @@ pub async fn get_profile(
 }
 
 /// PUT /api/v1/users/profile
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/profile",
+    tag = "users",
+    request_body = UpdateProfileRequest,
+    responses((status = 200, body = UserPublic))
+)]
 pub async fn update_profile(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -152,171 +172,325 @@ pub async fn update_profile(
     Ok(Json(UserPublic::from(user)))
 }
 
-/// POST /api/v1/users/avatar — upload avatar image (raw bytes)
+/// PUT /api/v1/users/me/avatar — upload avatar image (raw bytes)
+/// Also mounted at the older POST /api/v1/users/avatar.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/me/avatar",
+    tag = "users",
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200, body = UserPublic))
+)]
 pub async fn upload_avatar(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     body: Bytes,
 ) -> AppResult<Json<UserPublic>> {
-    if body.is_empty() {
-        return Err(AppError::Validation("No image data provided".into()));
-    }
-    if body.len() > MAX_AVATAR_SIZE {
-        return Err(AppError::Validation(format!(
-            "Avatar too large (max {}MB)",
-            MAX_AVATAR_SIZE / 1024 / 1024
-        )));
-    }
-
-    // Store using user_id-based storage key
-    let storage_key = storage::obfuscated_key(&state.storage_key, &format!("avatar:{}", user_id));
-    if state.config.cdn_enabled {
-        state
-            .storage
-            .store_blob_raw(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::BadRequest(format!("Failed to store avatar: {}", e)))?;
-    } else {
-        state
-            .storage
-            .store_blob(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::BadRequest(format!("Failed to store avatar: {}", e)))?;
-    }
-
-    // Update avatar_url in DB to the download endpoint
-    let avatar_url = format!("/api/v1/users/{}/avatar", user_id);
-    let user = queries::update_user_avatar(state.db.write(), user_id, &avatar_url).await?;
-
-    // Invalidate user cache
-    crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:user:{}", user_id)).await;
+    upload_profile_media(&state, user_id, MediaSlot::Avatar, body).await
+}
 
-    Ok(Json(UserPublic::from(user)))
+/// DELETE /api/v1/users/me/avatar
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/me/avatar",
+    tag = "users",
+    responses((status = 200, body = UserPublic))
+)]
+pub async fn delete_avatar(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<UserPublic>> {
+    delete_profile_media(&state, user_id, MediaSlot::Avatar).await
 }
 
-/// GET /api/v1/users/:user_id/avatar — download avatar image (no auth required for <img> src)
+/// GET /api/v1/users/:user_id/avatar — current avatar (no auth required for <img> src).
+/// Revalidated on every use; prefer the content-addressed `avatar_url`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/avatar",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200)),
+    security(())
+)]
 pub async fn get_avatar(
     State(state): State<AppState>,
     Path(user_id): Path<Uuid>,
-) -> Result<impl IntoResponse, AppError> {
-    // Verify user exists and has an avatar
-    let user = queries::find_user_by_id(state.db.read(), user_id)
-        .await?
-        .ok_or(AppError::UserNotFound)?;
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    serve_profile_media(&state, user_id, MediaSlot::Avatar, None, &headers).await
+}
 
-    if user.avatar_url.is_none() {
-        return Err(AppError::NotFound("No avatar set".into()));
-    }
+/// GET /api/v1/users/:user_id/avatar/:hash — one avatar version, cacheable forever
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/avatar/{hash}",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("hash" = String, Path, description = "Content hash")),
+    responses((status = 200)),
+    security(())
+)]
+pub async fn get_avatar_version(
+    State(state): State<AppState>,
+    Path((user_id, hash)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    serve_profile_media(&state, user_id, MediaSlot::Avatar, Some(&hash), &headers).await
+}
+
+/// PUT /api/v1/users/me/banner — upload banner image (raw bytes)
+/// Also mounted at the older POST /api/v1/users/banner.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/me/banner",
+    tag = "users",
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses((status = 200, body = UserPublic))
+)]
+pub async fn upload_banner(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    body: Bytes,
+) -> AppResult<Json<UserPublic>> {
+    upload_profile_media(&state, user_id, MediaSlot::Banner, body).await
+}
 
-    let storage_key = storage::obfuscated_key(&state.storage_key, &format!("avatar:{}", user_id));
+/// DELETE /api/v1/users/me/banner
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/me/banner",
+    tag = "users",
+    responses((status = 200, body = UserPublic))
+)]
+pub async fn delete_banner(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<Json<UserPublic>> {
+    delete_profile_media(&state, user_id, MediaSlot::Banner).await
+}
 
-    if state.config.cdn_enabled {
-        // CDN mode: try to return a presigned URL redirect
-        if let Some(url) = state
-            .storage
-            .presign_url(
-                &storage_key,
-                state.config.cdn_presign_expiry_secs,
-                &state.config.cdn_base_url,
-            )
-            .await
-        {
-            return Ok(Redirect::temporary(&url).into_response());
-        }
+/// GET /api/v1/users/:user_id/banner — current banner (no auth required for <img> src)
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/banner",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200)),
+    security(())
+)]
+pub async fn get_banner(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    serve_profile_media(&state, user_id, MediaSlot::Banner, None, &headers).await
+}
 
-        // Fallback for local storage: serve raw bytes directly
-        let data = state
-            .storage
-            .load_blob_raw(&storage_key)
-            .await
-            .map_err(|_| AppError::NotFound("Avatar file not found".into()))?;
-
-        let content_type = detect_image_type(&data);
-        Ok((
-            StatusCode::OK,
-            [
-                (header::CONTENT_TYPE, content_type),
-                (header::CACHE_CONTROL, "public, no-cache".to_string()),
-            ],
-            data,
-        )
-            .into_response())
-    } else {
-        // Standard mode: decrypt server-side and return
-        let data = state
-            .storage
-            .load_blob(&storage_key)
-            .await
-            .map_err(|_| AppError::NotFound("Avatar file not found".into()))?;
-
-        let content_type = detect_image_type(&data);
-        Ok((
-            StatusCode::OK,
-            [
-                (header::CONTENT_TYPE, content_type),
-                (header::CACHE_CONTROL, "public, no-cache".to_string()),
-            ],
-            data,
-        )
-            .into_response())
-    }
+/// GET /api/v1/users/:user_id/banner/:hash — one banner version, cacheable forever
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/banner/{hash}",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID"), ("hash" = String, Path, description = "Content hash")),
+    responses((status = 200)),
+    security(())
+)]
+pub async fn get_banner_version(
+    State(state): State<AppState>,
+    Path((user_id, hash)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    serve_profile_media(&state, user_id, MediaSlot::Banner, Some(&hash), &headers).await
 }
 
-/// POST /api/v1/users/banner — upload banner image (raw bytes)
-pub async fn upload_banner(
+/// GET /api/v1/users/me/media-usage — profile media bytes stored vs. quota
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/me/media-usage",
+    tag = "users",
+    responses((status = 200, body = ProfileMediaUsageResponse))
+)]
+pub async fn get_media_usage(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
+) -> AppResult<Json<ProfileMediaUsageResponse>> {
+    let used_bytes = queries::profile_media_usage(state.db.read(), user_id, None).await?;
+    Ok(Json(ProfileMediaUsageResponse {
+        used_bytes,
+        quota_bytes: state.config.profile_media_quota_bytes,
+    }))
+}
+
+/// Validate, resize and store a profile image, then point the user's
+/// profile at its content-addressed URL.
+async fn upload_profile_media(
+    state: &AppState,
+    user_id: Uuid,
+    slot: MediaSlot,
     body: Bytes,
 ) -> AppResult<Json<UserPublic>> {
+    let url = store_profile_media(state, user_id, slot, &body).await?;
+    let user = set_profile_url(state, user_id, slot, Some(&url)).await?;
+    Ok(Json(UserPublic::from(user)))
+}
+
+/// Validate, resize and store a profile image in `slot`, replacing the
+/// previous version. Returns the new content-addressed URL.
+pub async fn store_profile_media(state: &AppState, user_id: Uuid, slot: MediaSlot, body: &[u8]) -> AppResult<String> {
     if body.is_empty() {
         return Err(AppError::Validation("No image data provided".into()));
     }
-    if body.len() > MAX_BANNER_SIZE {
+    if body.len() > slot.max_upload_size() {
         return Err(AppError::Validation(format!(
-            "Banner too large (max {}MB)",
-            MAX_BANNER_SIZE / 1024 / 1024
+            "{} too large (max {}MB)",
+            capitalized(slot),
+            slot.max_upload_size() / 1024 / 1024
         )));
     }
 
-    let storage_key = storage::obfuscated_key(&state.storage_key, &format!("banner:{}", user_id));
-    if state.config.cdn_enabled {
-        state
-            .storage
-            .store_blob_raw(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::BadRequest(format!("Failed to store banner: {}", e)))?;
-    } else {
-        state
-            .storage
-            .store_blob(&storage_key, &body)
-            .await
-            .map_err(|e| AppError::BadRequest(format!("Failed to store banner: {}", e)))?;
+    let body = body.to_vec();
+    let image = tokio::task::spawn_blocking(move || profile_media::process(slot, &body))
+        .await
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Image processing failed: {}", e)))?
+        .ok_or(AppError::Validation("Not a supported image (PNG, JPEG, GIF or WebP)".into()))?;
+
+    let slot_key = slot.key();
+    let used = queries::profile_media_usage(state.db.read(), user_id, Some(&slot_key)).await?;
+    let quota = state.config.profile_media_quota_bytes;
+    if used as u64 + image.data.len() as u64 > quota {
+        return Err(AppError::BadRequest(format!(
+            "Profile media quota exceeded ({} of {} bytes used)",
+            used, quota
+        )));
     }
 
-    let banner_url = format!("/api/v1/users/{}/banner", user_id);
-    let user = queries::update_user_banner(state.db.write(), user_id, &banner_url).await?;
+    let storage_key = profile_media::storage_key(&state.storage_key, slot, user_id, &image.hash);
+    let stored = if state.config.cdn_enabled {
+        state.storage.store_blob_raw(&storage_key, &image.data).await
+    } else {
+        state.storage.store_blob(&storage_key, &image.data).await
+    };
+    stored.map_err(|e| AppError::BadRequest(format!("Failed to store {}: {}", slot.as_str(), e)))?;
 
-    crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:user:{}", user_id)).await;
+    let previous = queries::find_profile_media(state.db.write(), user_id, &slot_key).await?;
+    queries::upsert_profile_media(
+        state.db.write(),
+        user_id,
+        &slot_key,
+        &image.hash,
+        image.content_type,
+        image.data.len() as i64,
+    )
+    .await?;
 
+    // Drop the version this upload replaced (the key is stable, so an
+    // identical re-upload must keep its blob).
+    let stale_key = match previous {
+        Some(p) if p.content_hash != image.hash => {
+            Some(profile_media::storage_key(&state.storage_key, slot, user_id, &p.content_hash))
+        }
+        // Server avatars were always content-addressed: no legacy key
+        None if !matches!(slot, MediaSlot::ServerAvatar(_)) => {
+            Some(profile_media::legacy_storage_key(&state.storage_key, slot, user_id))
+        }
+        _ => None,
+    };
+    if let Some(key) = stale_key {
+        let _ = state.storage.delete_blob(&key).await;
+    }
+
+    Ok(profile_media::url(slot, user_id, &image.hash))
+}
+
+async fn delete_profile_media(state: &AppState, user_id: Uuid, slot: MediaSlot) -> AppResult<Json<UserPublic>> {
+    remove_profile_media(state, user_id, slot).await?;
+    let user = set_profile_url(state, user_id, slot, None).await?;
     Ok(Json(UserPublic::from(user)))
 }
 
-/// GET /api/v1/users/:user_id/banner — download banner image (no auth required for <img> src)
-pub async fn get_banner(
-    State(state): State<AppState>,
-    Path(user_id): Path<Uuid>,
-) -> Result<impl IntoResponse, AppError> {
-    let user = queries::find_user_by_id(state.db.read(), user_id)
-        .await?
-        .ok_or(AppError::UserNotFound)?;
+/// Delete the stored image in `slot`, if any.
+pub async fn remove_profile_media(state: &AppState, user_id: Uuid, slot: MediaSlot) -> AppResult<()> {
+    let key = match queries::delete_profile_media(state.db.write(), user_id, &slot.key()).await? {
+        Some(media) => profile_media::storage_key(&state.storage_key, slot, user_id, &media.content_hash),
+        None if matches!(slot, MediaSlot::ServerAvatar(_)) => return Ok(()),
+        None => profile_media::legacy_storage_key(&state.storage_key, slot, user_id),
+    };
+    let _ = state.storage.delete_blob(&key).await;
+    Ok(())
+}
+
+async fn set_profile_url(state: &AppState, user_id: Uuid, slot: MediaSlot, url: Option<&str>) -> AppResult<User> {
+    let user = match slot {
+        MediaSlot::Avatar => queries::update_user_avatar(state.db.write(), user_id, url).await?,
+        MediaSlot::Banner => queries::update_user_banner(state.db.write(), user_id, url).await?,
+        MediaSlot::ServerAvatar(_) => unreachable!("server avatars are not stored on the user"),
+    };
+    crate::cache::invalidate(state.redis.clone().as_mut(), &state.memory, &format!("haven:user:{}", user_id)).await;
+    Ok(user)
+}
 
-    if user.banner_url.is_none() {
-        return Err(AppError::NotFound("No banner set".into()));
+/// Serve a user's profile image. With a hash, only that exact version is
+/// served and marked immutable; without one, the current version is served
+/// with an ETag for revalidation.
+pub async fn serve_profile_media(
+    state: &AppState,
+    user_id: Uuid,
+    slot: MediaSlot,
+    hash: Option<&str>,
+    headers: &HeaderMap,
+) -> AppResult<Response> {
+    let not_found = || AppError::NotFound(format!("No {} set", slot.as_str()));
+    if hash.is_some_and(|h| !profile_media::is_valid_hash(h)) {
+        return Err(not_found());
     }
 
-    let storage_key = storage::obfuscated_key(&state.storage_key, &format!("banner:{}", user_id));
+    let media = queries::find_profile_media(state.db.read(), user_id, &slot.key()).await?;
+    let (storage_key, etag, content_type) = match (&media, hash) {
+        (Some(m), Some(h)) if m.content_hash != h => return Err(not_found()),
+        (Some(m), _) => (
+            profile_media::storage_key(&state.storage_key, slot, user_id, &m.content_hash),
+            Some(format!("\"{}\"", m.content_hash)),
+            Some(m.content_type.clone()),
+        ),
+        (None, Some(_)) => return Err(not_found()),
+        (None, None) => {
+            // Uploaded before content addressing: fall back to the legacy key
+            let user = queries::find_user_by_id(state.db.read(), user_id)
+                .await?
+                .ok_or(AppError::UserNotFound)?;
+            let current = match slot {
+                MediaSlot::Avatar => user.avatar_url,
+                MediaSlot::Banner => user.banner_url,
+                MediaSlot::ServerAvatar(_) => None,
+            };
+            if current.is_none() {
+                return Err(not_found());
+            }
+            (profile_media::legacy_storage_key(&state.storage_key, slot, user_id), None, None)
+        }
+    };
+
+    let cache_control = if hash.is_some() {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, no-cache"
+    };
+    if let Some(ref etag) = etag {
+        let matches = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.split(',').any(|t| t.trim() == etag));
+        if matches {
+            return Ok((
+                StatusCode::NOT_MODIFIED,
+                [(header::ETAG, etag.clone()), (header::CACHE_CONTROL, cache_control.to_string())],
+            )
+                .into_response());
+        }
+    }
 
-    if state.config.cdn_enabled {
+    let loaded = if state.config.cdn_enabled {
+        // CDN mode: try to return a presigned URL redirect
         if let Some(url) = state
             .storage
             .presign_url(
@@ -328,40 +502,50 @@ pub async fn get_banner(
         {
             return Ok(Redirect::temporary(&url).into_response());
         }
-
-        let data = state
-            .storage
-            .load_blob_raw(&storage_key)
-            .await
-            .map_err(|_| AppError::NotFound("Banner file not found".into()))?;
-
-        let content_type = detect_image_type(&data);
-        Ok((
-            StatusCode::OK,
-            [
-                (header::CONTENT_TYPE, content_type),
-                (header::CACHE_CONTROL, "public, no-cache".to_string()),
-            ],
-            data,
-        )
-            .into_response())
+        // Fallback for local storage: serve raw bytes directly
+        state.storage.load_blob_raw(&storage_key).await
     } else {
-        let data = state
+        state.storage.load_blob(&storage_key).await
+    };
+    let data = loaded.map_err(|_| AppError::NotFound(format!("{} file not found", capitalized(slot))))?;
+
+    let content_type = content_type.unwrap_or_else(|| detect_image_type(&data));
+    let mut response = (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, cache_control.to_string()),
+        ],
+        data,
+    )
+        .into_response();
+    if let Some(etag) = etag.and_then(|e| e.parse().ok()) {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    Ok(response)
+}
+
+/// Delete every stored profile image of a user. Takes the rows up front
+/// because deleting the user cascades them away.
+pub async fn delete_profile_media_blobs(state: &AppState, user_id: Uuid, media: &[ProfileMedia]) {
+    for slot in [MediaSlot::Avatar, MediaSlot::Banner] {
+        let _ = state
             .storage
-            .load_blob(&storage_key)
-            .await
-            .map_err(|_| AppError::NotFound("Banner file not found".into()))?;
-
-        let content_type = detect_image_type(&data);
-        Ok((
-            StatusCode::OK,
-            [
-                (header::CONTENT_TYPE, content_type),
-                (header::CACHE_CONTROL, "public, no-cache".to_string()),
-            ],
-            data,
-        )
-            .into_response())
+            .delete_blob(&profile_media::legacy_storage_key(&state.storage_key, slot, user_id))
+            .await;
+    }
+    for m in media {
+        let Some(slot) = MediaSlot::parse(&m.slot) else { continue };
+        let key = profile_media::storage_key(&state.storage_key, slot, user_id, &m.content_hash);
+        let _ = state.storage.delete_blob(&key).await;
+    }
+}
+
+fn capitalized(slot: MediaSlot) -> &'static str {
+    match slot {
+        MediaSlot::Avatar => "Avatar",
+        MediaSlot::Banner => "Banner",
+        MediaSlot::ServerAvatar(_) => "Server avatar",
     }
 }
 
@@ -380,6 +564,13 @@ fn detect_image_type(data: &[u8]) -> String {
 }
 
 /// POST /api/v1/users/:user_id/block
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{user_id}/block",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn block_user(
     State(state): State<AppState>,
     AuthUser(blocker_id): AuthUser,
@@ -395,20 +586,43 @@ pub async fn block_user(
         .ok_or(AppError::UserNotFound)?;
 
     queries::block_user(state.db.write(), blocker_id, blocked_id).await?;
+
+    // Blocking ends any friendship or pending request between the two
+    if let Some(friendship) = queries::find_friendship(state.db.read(), blocker_id, blocked_id).await? {
+        queries::delete_friendship(state.db.write(), friendship.id).await?;
+        crate::api::friends::notify_relationship(&state, blocked_id, blocker_id).await;
+    }
+    crate::api::friends::notify_relationship(&state, blocker_id, blocked_id).await;
     Ok(Json(()))
 }
 
 /// DELETE /api/v1/users/:user_id/block
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{user_id}/block",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200))
+)]
 pub async fn unblock_user(
     State(state): State<AppState>,
     AuthUser(blocker_id): AuthUser,
     Path(blocked_id): Path<Uuid>,
 ) -> AppResult<Json<()>> {
-    queries::unblock_user(state.db.write(), blocker_id, blocked_id).await?;
+    if queries::unblock_user(state.db.write(), blocker_id, blocked_id).await? {
+        crate::api::friends::notify_relationship(&state, blocker_id, blocked_id).await;
+    }
     Ok(Json(()))
 }
 
 /// GET /api/v1/users/blocked
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/blocked",
+    tag = "users",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<BlockedUserResponse>))
+)]
 pub async fn get_blocked_users(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -419,7 +633,33 @@ pub async fn get_blocked_users(
     Ok(Json(blocked))
 }
 
+/// GET /api/v1/users/support-access — when instance staff viewed this account, and why
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/support-access",
+    tag = "users",
+    params(PaginationQuery),
+    responses((status = 200, body = Vec<SupportAccessEntry>))
+)]
+pub async fn get_support_access_log(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(pagination): Query<PaginationQuery>,
+) -> AppResult<Json<Vec<SupportAccessEntry>>> {
+    let (limit, offset) = pagination.resolve();
+    let entries =
+        queries::list_support_access_for_user(state.db.read(), user_id, limit, offset).await?;
+    Ok(Json(entries))
+}
+
 /// PUT /api/v1/users/profile-keys — distribute profile keys to contacts
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/profile-keys",
+    tag = "users",
+    request_body = DistributeProfileKeysRequest,
+    responses((status = 200))
+)]
 pub async fn distribute_profile_keys(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -440,6 +680,13 @@ pub async fn distribute_profile_keys(
 }
 
 /// GET /api/v1/users/:user_id/profile-key — get the profile key for a specific user
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{user_id}/profile-key",
+    tag = "users",
+    params(("user_id" = Uuid, Path, description = "User ID")),
+    responses((status = 200, body = ProfileKeyResponse))
+)]
 pub async fn get_profile_key(
     State(state): State<AppState>,
     AuthUser(requester_id): AuthUser,
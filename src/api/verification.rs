@@ -0,0 +1,251 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::middleware::AuthUser;
+use crate::models::*;
+use crate::permissions;
+use crate::AppState;
+
+const DEFAULT_ACCOUNT_AGE_DAYS: i32 = 5;
+const DEFAULT_MEMBERSHIP_MINUTES: i32 = 10;
+const MAX_ACCOUNT_AGE_DAYS: i32 = 365;
+const MAX_MEMBERSHIP_MINUTES: i32 = 10080;
+
+/// GET /api/v1/servers/:server_id/verification
+/// The server's verification level and whether the caller meets it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/servers/{server_id}/verification",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    responses((status = 200, body = VerificationResponse))
+)]
+pub async fn get_verification(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<VerificationResponse>> {
+    let facts = queries::get_member_verification_facts(state.db.read(), server_id, user_id)
+        .await?
+        .ok_or(AppError::Forbidden("Not a member of this server".into()))?;
+    let verification = queries::get_server_verification(state.db.read(), server_id).await?;
+    Ok(Json(verification_response(server_id, verification.as_ref(), &facts)))
+}
+
+/// PUT /api/v1/servers/:server_id/verification
+/// Set what members need before they may post. Requires MANAGE_SERVER.
+#[utoipa::path(
+    put,
+    path = "/api/v1/servers/{server_id}/verification",
+    tag = "servers",
+    params(("server_id" = Uuid, Path, description = "Server ID")),
+    request_body = SetVerificationRequest,
+    responses((status = 200, body = VerificationResponse))
+)]
+pub async fn set_verification(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<SetVerificationRequest>,
+) -> AppResult<Json<VerificationResponse>> {
+    let facts = queries::get_member_verification_facts(state.db.read(), server_id, user_id)
+        .await?
+        .ok_or(AppError::Forbidden("Not a member of this server".into()))?;
+    let (is_owner, perms) = queries::get_member_permissions(state.db.read(), server_id, user_id).await?;
+    if !is_owner && !permissions::has_permission(perms, permissions::MANAGE_SERVER) {
+        return Err(AppError::Forbidden("Missing MANAGE_SERVER permission".into()));
+    }
+
+    if req.level == VerificationLevel::Phone {
+        return Err(AppError::Validation("Phone verification is not configured on this server".into())
+            .with_code("PHONE_VERIFICATION_UNAVAILABLE"));
+    }
+    let current = queries::get_server_verification(state.db.read(), server_id).await?;
+    let min_account_age_days = req
+        .min_account_age_days
+        .or(current.as_ref().map(|v| v.min_account_age_days))
+        .unwrap_or(DEFAULT_ACCOUNT_AGE_DAYS);
+    if !(1..=MAX_ACCOUNT_AGE_DAYS).contains(&min_account_age_days) {
+        return Err(AppError::Validation(format!(
+            "min_account_age_days must be 1-{}",
+            MAX_ACCOUNT_AGE_DAYS
+        )));
+    }
+    let min_membership_minutes = req
+        .min_membership_minutes
+        .or(current.as_ref().map(|v| v.min_membership_minutes))
+        .unwrap_or(DEFAULT_MEMBERSHIP_MINUTES);
+    if !(1..=MAX_MEMBERSHIP_MINUTES).contains(&min_membership_minutes) {
+        return Err(AppError::Validation(format!(
+            "min_membership_minutes must be 1-{}",
+            MAX_MEMBERSHIP_MINUTES
+        )));
+    }
+
+    let verification = queries::upsert_server_verification(
+        state.db.write(),
+        server_id,
+        req.level,
+        min_account_age_days,
+        min_membership_minutes,
+    )
+    .await?;
+
+    let _ = queries::insert_audit_log(
+        state.db.write(), server_id, user_id, "verification_update",
+        Some("server"), Some(server_id),
+        Some(&serde_json::json!({
+            "level": req.level.as_str(),
+            "min_account_age_days": min_account_age_days,
+            "min_membership_minutes": min_membership_minutes,
+        })),
+        None,
+    ).await;
+
+    Ok(Json(verification_response(server_id, Some(&verification), &facts)))
+}
+
+/// Why the member may not post yet under the server's verification level,
+/// or None if they may. Not being a member is left to the access checks.
+pub(crate) async fn posting_blocked_reason(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<&'static str>> {
+    let Some(verification) = queries::get_server_verification(state.db.read(), server_id).await? else {
+        return Ok(None);
+    };
+    if verification.level() == VerificationLevel::None {
+        return Ok(None);
+    }
+    let Some(facts) = queries::get_member_verification_facts(state.db.read(), server_id, user_id).await? else {
+        return Ok(None);
+    };
+    Ok(unmet_requirement(&verification, &facts, Utc::now()))
+}
+
+/// Refuse messages from members below the server's verification level.
+pub(crate) async fn require_verified(state: &AppState, server_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    match posting_blocked_reason(state, server_id, user_id).await? {
+        Some(reason) => Err(AppError::Forbidden(reason.into()).with_code("VERIFICATION_REQUIRED")),
+        None => Ok(()),
+    }
+}
+
+/// The first requirement of the level the member doesn't meet. The owner
+/// and members holding a role are exempt.
+fn unmet_requirement(
+    verification: &ServerVerification,
+    facts: &MemberVerificationFacts,
+    now: DateTime<Utc>,
+) -> Option<&'static str> {
+    let level = verification.level();
+    if facts.is_owner || facts.has_roles {
+        return None;
+    }
+    if level >= VerificationLevel::Email && facts.email_verified_at.is_none() {
+        return Some("Verify your email address before posting in this server");
+    }
+    if level >= VerificationLevel::AccountAge
+        && now - facts.account_created_at < Duration::days(verification.min_account_age_days as i64)
+    {
+        return Some("Your account is too new to post in this server");
+    }
+    if level >= VerificationLevel::Membership
+        && now - facts.joined_at < Duration::minutes(verification.min_membership_minutes as i64)
+    {
+        return Some("You joined this server too recently to post");
+    }
+    if level >= VerificationLevel::Phone {
+        return Some("Verify your phone number before posting in this server");
+    }
+    None
+}
+
+fn verification_response(
+    server_id: Uuid,
+    verification: Option<&ServerVerification>,
+    facts: &MemberVerificationFacts,
+) -> VerificationResponse {
+    match verification {
+        Some(v) => VerificationResponse {
+            server_id,
+            level: v.level(),
+            min_account_age_days: v.min_account_age_days,
+            min_membership_minutes: v.min_membership_minutes,
+            satisfied: unmet_requirement(v, facts, Utc::now()).is_none(),
+        },
+        None => VerificationResponse {
+            server_id,
+            level: VerificationLevel::None,
+            min_account_age_days: DEFAULT_ACCOUNT_AGE_DAYS,
+            min_membership_minutes: DEFAULT_MEMBERSHIP_MINUTES,
+            satisfied: true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verification(level: VerificationLevel) -> ServerVerification {
+        ServerVerification {
+            server_id: Uuid::nil(),
+            level: level.as_str().into(),
+            min_account_age_days: 5,
+            min_membership_minutes: 10,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn newcomer(now: DateTime<Utc>) -> MemberVerificationFacts {
+        MemberVerificationFacts {
+            is_owner: false,
+            has_roles: false,
+            email_verified_at: None,
+            account_created_at: now - Duration::days(1),
+            joined_at: now - Duration::minutes(1),
+        }
+    }
+
+    #[test]
+    fn levels_include_the_requirements_below_them() {
+        let now = Utc::now();
+        let mut facts = newcomer(now);
+        assert!(unmet_requirement(&verification(VerificationLevel::None), &facts, now).is_none());
+        assert!(unmet_requirement(&verification(VerificationLevel::Email), &facts, now).is_some());
+
+        facts.email_verified_at = Some(now);
+        assert!(unmet_requirement(&verification(VerificationLevel::Email), &facts, now).is_none());
+        assert_eq!(
+            unmet_requirement(&verification(VerificationLevel::Membership), &facts, now),
+            Some("Your account is too new to post in this server")
+        );
+
+        facts.account_created_at = now - Duration::days(6);
+        assert_eq!(
+            unmet_requirement(&verification(VerificationLevel::Membership), &facts, now),
+            Some("You joined this server too recently to post")
+        );
+        facts.joined_at = now - Duration::minutes(11);
+        assert!(unmet_requirement(&verification(VerificationLevel::Membership), &facts, now).is_none());
+    }
+
+    #[test]
+    fn owner_and_role_holders_are_exempt() {
+        let now = Utc::now();
+        let mut facts = newcomer(now);
+        facts.has_roles = true;
+        assert!(unmet_requirement(&verification(VerificationLevel::Membership), &facts, now).is_none());
+        facts.has_roles = false;
+        facts.is_owner = true;
+        assert!(unmet_requirement(&verification(VerificationLevel::Membership), &facts, now).is_none());
+    }
+}
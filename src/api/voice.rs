@@ -2,21 +2,27 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use livekit_api::access_token::{AccessToken, VideoGrants};
 use std::collections::HashSet;
-use std::time::Duration;
 use uuid::Uuid;
 
 use crate::db::queries;
 use crate::errors::{AppError, AppResult};
 use crate::middleware::AuthUser;
 use crate::models::{VoiceDeafenRequest, VoiceMuteRequest, VoiceParticipantResponse, VoiceTokenResponse, WsServerMessage};
-use crate::{pubsub, AppState};
+use crate::voice::sfu;
+use crate::{permissions, pubsub, AppState};
 
 /// POST /api/v1/voice/:channel_id/join
 ///
 /// Join a voice channel. Returns a LiveKit token for the client to connect with.
 /// Automatically leaves any previously joined voice channel.
+#[utoipa::path(
+    post,
+    path = "/api/v1/voice/{channel_id}/join",
+    tag = "voice",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = VoiceTokenResponse))
+)]
 pub async fn join_voice(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -43,6 +49,12 @@ pub async fn join_voice(
         ));
     }
 
+    // Derive SFU grants from the caller's effective channel permissions
+    let perms = sfu::channel_voice_permissions(&state, &channel, user_id).await?;
+    if !permissions::has_permission(perms, permissions::VIEW_CHANNELS) {
+        return Err(AppError::Forbidden("Missing VIEW_CHANNELS permission".into()));
+    }
+
     // Remove user from any current voice channel
     let mut old_channels = Vec::new();
     for entry in state.memory.voice_participants.iter() {
@@ -51,26 +63,32 @@ pub async fn join_voice(
         }
     }
     for old_ch in &old_channels {
-        if let Some(mut participants) = state.memory.voice_participants.get_mut(old_ch) {
-            participants.remove(&user_id);
+        if *old_ch == channel_id {
+            continue;
         }
-        if *old_ch != channel_id {
-            // Clean up mute/deafen for old channel
-            if let Some(mut muted) = state.memory.voice_muted.get_mut(old_ch) {
-                muted.remove(&user_id);
-            }
-            if let Some(mut deafened) = state.memory.voice_deafened.get_mut(old_ch) {
-                deafened.remove(&user_id);
-            }
-            broadcast_voice_state(&state, *old_ch, user_id, &user_id.to_string(), false).await;
+        sfu::remove_participant(&state, *old_ch, user_id);
+        // Clean up mute/deafen for old channel
+        if let Some(mut muted) = state.memory.voice_muted.get_mut(old_ch) {
+            muted.remove(&user_id);
         }
+        if let Some(mut deafened) = state.memory.voice_deafened.get_mut(old_ch) {
+            deafened.remove(&user_id);
+        }
+        broadcast_voice_state(&state, *old_ch, user_id, &user_id.to_string(), false).await;
     }
 
-    // Add user to the new voice channel
-    state.memory.voice_participants
-        .entry(channel_id)
-        .or_insert_with(HashSet::new)
-        .insert(user_id);
+    // Add user to the new voice channel, provisioning the SFU room if it was idle
+    let became_active = {
+        let mut participants = state.memory.voice_participants
+            .entry(channel_id)
+            .or_insert_with(HashSet::new);
+        let was_empty = participants.is_empty();
+        participants.insert(user_id);
+        was_empty
+    };
+    if became_active {
+        sfu::provision_room(&state, channel_id).await;
+    }
 
     // Look up display name for LiveKit participant metadata
     let participant_name = match queries::find_user_basic_by_id(state.db.read(), user_id).await {
@@ -78,23 +96,18 @@ pub async fn join_voice(
         _ => user_id.to_string(),
     };
 
+    let server_muted = state.memory.voice_muted
+        .get(&channel_id)
+        .map(|set| set.contains(&user_id))
+        .unwrap_or(false);
+    let server_deafened = state.memory.voice_deafened
+        .get(&channel_id)
+        .map(|set| set.contains(&user_id))
+        .unwrap_or(false);
+
     // Generate LiveKit token
-    let token = AccessToken::with_api_key(
-        &state.config.livekit_api_key,
-        &state.config.livekit_api_secret,
-    )
-    .with_identity(&user_id.to_string())
-    .with_name(&participant_name)
-    .with_ttl(Duration::from_secs(6 * 3600)) // 6 hours
-    .with_grants(VideoGrants {
-        room_join: true,
-        room: channel_id.to_string(),
-        can_publish: true,
-        can_subscribe: true,
-        ..Default::default()
-    })
-    .to_jwt()
-    .map_err(|e| AppError::BadRequest(format!("Failed to generate voice token: {}", e)))?;
+    let grants = sfu::grants_for(channel_id, perms, server_muted, server_deafened);
+    let token = sfu::mint_join_token(&state.config, user_id, &participant_name, grants)?;
 
     // Broadcast join to channel subscribers
     broadcast_voice_state(&state, channel_id, user_id, &user_id.to_string(), true).await;
@@ -103,21 +116,27 @@ pub async fn join_voice(
         token,
         url: state.config.livekit_url_for_client().to_string(),
         channel_id,
+        can_video: permissions::has_permission(perms, permissions::VIDEO),
+        can_stream: permissions::has_permission(perms, permissions::STREAM),
     }))
 }
 
 /// POST /api/v1/voice/:channel_id/leave
 ///
 /// Leave a voice channel.
+#[utoipa::path(
+    post,
+    path = "/api/v1/voice/{channel_id}/leave",
+    tag = "voice",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200))
+)]
 pub async fn leave_voice(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(channel_id): Path<Uuid>,
 ) -> AppResult<Json<serde_json::Value>> {
-    let removed = state.memory.voice_participants
-        .get_mut(&channel_id)
-        .map(|mut set| set.remove(&user_id))
-        .unwrap_or(false);
+    let removed = sfu::remove_participant(&state, channel_id, user_id);
 
     if removed {
         // Clean up server mute/deafen state
@@ -136,6 +155,13 @@ pub async fn leave_voice(
 /// GET /api/v1/voice/:channel_id/participants
 ///
 /// List users currently in a voice channel.
+#[utoipa::path(
+    get,
+    path = "/api/v1/voice/{channel_id}/participants",
+    tag = "voice",
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses((status = 200, body = Vec<VoiceParticipantResponse>))
+)]
 pub async fn get_participants(
     State(state): State<AppState>,
     AuthUser(_user_id): AuthUser,
@@ -176,6 +202,14 @@ pub async fn get_participants(
 /// PUT /api/v1/voice/:channel_id/members/:user_id/mute
 ///
 /// Server-mute a user in a voice channel. Requires MUTE_MEMBERS permission.
+#[utoipa::path(
+    put,
+    path = "/api/v1/voice/{channel_id}/members/{user_id}/mute",
+    tag = "voice",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = VoiceMuteRequest,
+    responses((status = 200))
+)]
 pub async fn server_mute(
     State(state): State<AppState>,
     AuthUser(caller_id): AuthUser,
@@ -228,6 +262,14 @@ pub async fn server_mute(
 /// PUT /api/v1/voice/:channel_id/members/:user_id/deafen
 ///
 /// Server-deafen a user in a voice channel. Requires MUTE_MEMBERS permission.
+#[utoipa::path(
+    put,
+    path = "/api/v1/voice/{channel_id}/members/{user_id}/deafen",
+    tag = "voice",
+    params(("channel_id" = Uuid, Path, description = "Channel ID"), ("user_id" = Uuid, Path, description = "User ID")),
+    request_body = VoiceDeafenRequest,
+    responses((status = 200))
+)]
 pub async fn server_deafen(
     State(state): State<AppState>,
     AuthUser(caller_id): AuthUser,
@@ -276,11 +318,16 @@ pub async fn server_deafen(
 /// Remove a user from all voice channels and broadcast their departure.
 /// Called during WebSocket disconnect cleanup.
 pub async fn cleanup_voice_state(state: &AppState, user_id: Uuid) {
-    let mut left_channels = Vec::new();
+    let joined_channels: Vec<Uuid> = state.memory.voice_participants
+        .iter()
+        .filter(|entry| entry.value().contains(&user_id))
+        .map(|entry| *entry.key())
+        .collect();
 
-    for mut entry in state.memory.voice_participants.iter_mut() {
-        if entry.value_mut().remove(&user_id) {
-            left_channels.push(*entry.key());
+    let mut left_channels = Vec::new();
+    for ch_id in joined_channels {
+        if sfu::remove_participant(state, ch_id, user_id) {
+            left_channels.push(ch_id);
         }
     }
 
@@ -317,9 +364,7 @@ async fn broadcast_voice_state(
         joined,
     };
 
-    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-        let _ = broadcaster.send(msg.clone());
-    }
+    pubsub::broadcast_channel_event(state, channel_id, &msg).await;
 
     // Also broadcast to all channels in the same server so sidebar can update
     if let Ok(Some(channel)) = queries::find_channel_by_id(state.db.read(), channel_id).await {
@@ -327,9 +372,7 @@ async fn broadcast_voice_state(
             if let Ok(channels) = queries::get_server_channels(state.db.read(), server_id).await {
                 for ch in channels {
                     if ch.id != channel_id {
-                        if let Some(broadcaster) = state.channel_broadcasts.get(&ch.id) {
-                            let _ = broadcaster.send(msg.clone());
-                        }
+                        pubsub::broadcast_channel_event(state, ch.id, &msg).await;
                     }
                 }
             }
@@ -343,7 +386,7 @@ async fn broadcast_voice_state(
                             let _ = tx.send(msg.clone());
                         }
                     }
-                    pubsub::publish_user_event(state.redis.clone().as_mut(), mid, &msg).await;
+                    pubsub::publish_user_event(state, mid, &msg).await;
                 }
             }
         }
@@ -365,9 +408,7 @@ async fn broadcast_voice_mute_state(
         server_deafened,
     };
 
-    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-        let _ = broadcaster.send(msg.clone());
-    }
+    pubsub::broadcast_channel_event(state, channel_id, &msg).await;
 
     // Broadcast to all channels in the same server
     if let Ok(Some(channel)) = queries::find_channel_by_id(state.db.read(), channel_id).await {
@@ -375,9 +416,7 @@ async fn broadcast_voice_mute_state(
             if let Ok(channels) = queries::get_server_channels(state.db.read(), server_id).await {
                 for ch in channels {
                     if ch.id != channel_id {
-                        if let Some(broadcaster) = state.channel_broadcasts.get(&ch.id) {
-                            let _ = broadcaster.send(msg.clone());
-                        }
+                        pubsub::broadcast_channel_event(state, ch.id, &msg).await;
                     }
                 }
             }
@@ -0,0 +1,218 @@
+//! REST API versions.
+//!
+//! Every version is served by the same router and handlers, nested under its
+//! own prefix. A handler whose response shape changed takes [`ApiVersion`]
+//! and returns [`VersionedJson`], so older clients keep the shape they were
+//! built against. Older versions can be marked deprecated with
+//! `API_DEPRECATED_VERSIONS`, which adds `Deprecation`/`Sunset` headers.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.as_str().eq_ignore_ascii_case(s))
+    }
+
+    /// Path prefix the version is served under, e.g. `/api/v1`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/api/v1",
+            ApiVersion::V2 => "/api/v2",
+        }
+    }
+}
+
+/// A route template without its version prefix, so per-route rate policies
+/// and latency budgets apply to the route in every version.
+pub fn unversioned(route: &str) -> &str {
+    ApiVersion::ALL
+        .iter()
+        .find_map(|v| route.strip_prefix(v.prefix()))
+        .unwrap_or(route)
+}
+
+/// The version the request came in on. Each version's router inserts it as
+/// an extension; anything outside a versioned router counts as v1.
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ApiVersion {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ApiVersion>().copied().unwrap_or(ApiVersion::V1))
+    }
+}
+
+// ─── Per-Version Serializers ───────────────────────────
+
+/// Implemented by response models whose JSON shape changed after v1. The
+/// model's own `Serialize` is the latest shape; `V1` is what v1 clients get.
+pub trait V1Compat: Serialize {
+    type V1: Serialize;
+
+    fn into_v1(self) -> Self::V1;
+}
+
+impl<T: V1Compat> V1Compat for Vec<T> {
+    type V1 = Vec<T::V1>;
+
+    fn into_v1(self) -> Self::V1 {
+        self.into_iter().map(V1Compat::into_v1).collect()
+    }
+}
+
+/// JSON body in the shape of the caller's API version.
+pub struct VersionedJson<T>(pub ApiVersion, pub T);
+
+impl<T: V1Compat> IntoResponse for VersionedJson<T> {
+    fn into_response(self) -> Response {
+        match self.0 {
+            ApiVersion::V1 => Json(self.1.into_v1()).into_response(),
+            ApiVersion::V2 => Json(self.1).into_response(),
+        }
+    }
+}
+
+// ─── Deprecation ───────────────────────────────────────
+
+/// A deprecated version and the date it stops being served, if announced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    pub version: ApiVersion,
+    pub sunset: Option<NaiveDate>,
+}
+
+/// Parse `API_DEPRECATED_VERSIONS`: comma-separated `v1` or `v1=YYYY-MM-DD`.
+/// Invalid entries, and the latest version, are logged and skipped.
+pub fn parse_deprecations(spec: &str) -> Vec<Deprecation> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (version, sunset) = match entry.split_once('=') {
+                Some((version, date)) => (version, Some(date.trim())),
+                None => (entry, None),
+            };
+            let parsed = ApiVersion::parse(version.trim())
+                .filter(|v| *v != ApiVersion::LATEST)
+                .and_then(|version| match sunset {
+                    Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .ok()
+                        .map(|d| Deprecation { version, sunset: Some(d) }),
+                    None => Some(Deprecation { version, sunset: None }),
+                });
+            if parsed.is_none() {
+                tracing::warn!("Ignoring invalid API_DEPRECATED_VERSIONS entry: {}", entry);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Mark every response of a deprecated version, pointing at the latest one.
+pub async fn deprecation_middleware(
+    State(deprecation): State<Deprecation>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Some(sunset) = deprecation.sunset {
+        let date = sunset.format("%a, %d %b %Y 00:00:00 GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert("sunset", value);
+        }
+    }
+    if let Ok(link) = HeaderValue::from_str(&format!(
+        "<{}>; rel=\"successor-version\"",
+        ApiVersion::LATEST.prefix()
+    )) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_any_version_prefix() {
+        assert_eq!(unversioned("/api/v1/servers/:server_id"), "/servers/:server_id");
+        assert_eq!(unversioned("/api/v2/servers/:server_id"), "/servers/:server_id");
+        assert_eq!(unversioned("/health"), "/health");
+    }
+
+    #[test]
+    fn parses_deprecations_and_skips_invalid_entries() {
+        assert_eq!(
+            parse_deprecations("v1=2027-06-30, V1, v2, v3, v1=soon,"),
+            vec![
+                Deprecation {
+                    version: ApiVersion::V1,
+                    sunset: NaiveDate::from_ymd_opt(2027, 6, 30),
+                },
+                Deprecation { version: ApiVersion::V1, sunset: None },
+            ]
+        );
+        assert!(parse_deprecations("").is_empty());
+    }
+
+    #[derive(Serialize)]
+    struct Renamed {
+        display_name: String,
+    }
+
+    #[derive(Serialize)]
+    struct RenamedV1 {
+        name: String,
+    }
+
+    impl V1Compat for Renamed {
+        type V1 = RenamedV1;
+
+        fn into_v1(self) -> RenamedV1 {
+            RenamedV1 { name: self.display_name }
+        }
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn versioned_json_uses_the_callers_shape() {
+        let list = || vec![Renamed { display_name: "a".into() }];
+        let v1 = body_json(VersionedJson(ApiVersion::V1, list()).into_response()).await;
+        let v2 = body_json(VersionedJson(ApiVersion::V2, list()).into_response()).await;
+        assert_eq!(v1, serde_json::json!([{ "name": "a" }]));
+        assert_eq!(v2, serde_json::json!([{ "display_name": "a" }]));
+    }
+}
@@ -0,0 +1,120 @@
+//! Garbage collection for attachments whose message no longer exists.
+//!
+//! Message deletion paths (single and bulk deletes, expiry, channel deletion,
+//! account deletion, server restore) leave attachment rows in place. Each GC
+//! pass stamps rows whose message is gone with `orphaned_at`, then deletes the
+//! blob and the row once the configured grace period has passed. In dry-run
+//! mode a pass only stamps and counts.
+//!
+//! Attachments in a shared, content-addressed blob (see `uploads`) only
+//! release their reference when their row goes, and simple uploads that
+//! expired without being sent release theirs. A pass then deletes blobs
+//! that have had no references for the grace period. Each blob is deleted
+//! under its row lock, so an upload of the same content waits and stores
+//! the bytes again rather than reusing a blob on its way out.
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::AppState;
+
+/// Attachments collected per query round-trip.
+const BATCH_SIZE: i64 = 500;
+
+/// Outcome of one GC pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GcPass {
+    /// Attachments newly found without a message.
+    pub marked: u64,
+    /// Orphaned attachments past the grace period (deleted unless dry run).
+    pub collectable: u64,
+    /// Attachments actually deleted.
+    pub deleted: u64,
+    /// Expired, never-sent simple uploads whose blob reference was released.
+    pub pending_released: u64,
+    /// Shared blobs deleted after their last reference went.
+    pub blobs_deleted: u64,
+}
+
+/// Run one GC pass.
+pub async fn run(state: &AppState) -> AppResult<GcPass> {
+    let pool = state.db.primary();
+    let grace_hours = state.config.attachment_gc_grace_hours;
+    let mut pass = GcPass {
+        marked: queries::mark_orphaned_attachments(pool).await?,
+        ..Default::default()
+    };
+
+    if state.config.attachment_gc_dry_run {
+        let (_, collectable) = queries::count_orphaned_attachments(pool, grace_hours).await?;
+        pass.collectable = collectable as u64;
+        return Ok(pass);
+    }
+
+    loop {
+        let batch = queries::list_collectable_attachments(pool, grace_hours, BATCH_SIZE).await?;
+        if batch.is_empty() {
+            break;
+        }
+        pass.collectable += batch.len() as u64;
+
+        // Keep the row when its blob can't be removed so the next pass retries.
+        let mut ids = Vec::with_capacity(batch.len());
+        for (id, storage_key, thumbnail_key) in batch {
+            // Thumbnail removal is best-effort and not retried.
+            if let Some(key) = thumbnail_key {
+                if let Err(e) = state.storage.delete_blob(&key).await {
+                    tracing::warn!("Attachment GC failed to delete thumbnail for {}: {}", id, e);
+                }
+            }
+            // Shared blobs are released with the row
+            let Some(storage_key) = storage_key else {
+                ids.push(id);
+                continue;
+            };
+            match state.storage.delete_blob(&storage_key).await {
+                Ok(()) => ids.push(id),
+                Err(e) => tracing::warn!("Attachment GC failed to delete blob for {}: {}", id, e),
+            }
+        }
+        if ids.is_empty() {
+            break;
+        }
+        pass.deleted += queries::delete_orphaned_attachments(pool, &ids).await?;
+        if (ids.len() as i64) < BATCH_SIZE {
+            break;
+        }
+    }
+
+    pass.pending_released = queries::release_expired_pending_uploads(pool).await?;
+    pass.blobs_deleted = collect_blobs(state, grace_hours).await?;
+    Ok(pass)
+}
+
+/// Delete shared blobs unreferenced for the grace period. Returns how many.
+async fn collect_blobs(state: &AppState, grace_hours: u32) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let mut deleted = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        let batch = queries::lock_collectable_attachment_blobs_in(&mut tx, grace_hours, BATCH_SIZE).await?;
+        if batch.is_empty() {
+            break;
+        }
+        let full = batch.len() as i64 == BATCH_SIZE;
+
+        // Keep the row when its blob can't be removed so the next pass retries.
+        let mut hashes = Vec::with_capacity(batch.len());
+        for (content_hash, storage_key) in batch {
+            match state.storage.delete_blob(&storage_key).await {
+                Ok(()) => hashes.push(content_hash),
+                Err(e) => tracing::warn!("Attachment GC failed to delete shared blob {}: {}", content_hash, e),
+            }
+        }
+        deleted += queries::delete_attachment_blobs_in(&mut tx, &hashes).await?;
+        tx.commit().await?;
+        if hashes.is_empty() || !full {
+            break;
+        }
+    }
+    Ok(deleted)
+}
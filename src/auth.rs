@@ -34,8 +34,12 @@ pub fn hash_password(password: &str) -> AppResult<String> {
     Ok(hash.to_string())
 }
 
-/// Verify a password against a stored Argon2id hash.
+/// Verify a password against a stored Argon2id hash. Placeholder hashes
+/// (`!...!`, used for system, federated and bridge puppet accounts) never match.
 pub fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
+    if hash.starts_with('!') {
+        return Ok(false);
+    }
     let parsed_hash = PasswordHash::new(hash)
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid password hash: {}", e)))?;
     Ok(Argon2::default()
@@ -110,6 +114,14 @@ pub fn generate_refresh_token() -> String {
     base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, &bytes)
 }
 
+/// Prefix on bridge tokens so they are recognisable in logs and config files.
+pub const BRIDGE_TOKEN_PREFIX: &str = "hvb_";
+
+/// Generate a bridge token. Stored hashed like refresh tokens.
+pub fn generate_bridge_token() -> String {
+    format!("{}{}", BRIDGE_TOKEN_PREFIX, generate_refresh_token())
+}
+
 /// Hash a refresh token for storage (we never store the raw token).
 pub fn hash_refresh_token(token: &str) -> String {
     let mut hasher = Sha256::new();
@@ -117,6 +129,18 @@ pub fn hash_refresh_token(token: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+// ─── Email Verification ────────────────────────────────
+
+/// Generate a six-digit email verification code.
+pub fn generate_email_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+/// Keyed hash of an email verification code for storage.
+pub fn hash_email_code(code: &str, secret: &str) -> String {
+    hash_email(code, secret)
+}
+
 // ─── TOTP (2FA) ────────────────────────────────────────
 
 /// Generate a new TOTP secret and return it with the provisioning URI.
@@ -198,6 +222,11 @@ mod tests {
         assert!(!verify_password("wronghorse", &hash).unwrap());
     }
 
+    #[test]
+    fn verify_password_rejects_placeholder_hash() {
+        assert!(!verify_password("anything", "!BRIDGE_PUPPET_NO_LOGIN!").unwrap());
+    }
+
     // ─── Email Hashing ──────────────────────────────────
 
     #[test]
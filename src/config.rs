@@ -1,8 +1,10 @@
 use std::env;
 use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 
 // ─── TOML Config File ─────────────────────────────────
 
@@ -21,9 +23,21 @@ pub struct ConfigFile {
     pub database_replica_url: String,
     #[serde(default = "default_db_max_connections")]
     pub db_max_connections: u32,
+    #[serde(default = "default_db_replica_strategy")]
+    pub db_replica_strategy: String,
+    #[serde(default = "default_db_replica_max_lag_secs")]
+    pub db_replica_max_lag_secs: u64,
+    #[serde(default = "default_db_auto_migrate")]
+    pub db_auto_migrate: bool,
+    #[serde(default = "default_db_slow_query_ms")]
+    pub db_slow_query_ms: u64,
+    #[serde(default = "default_db_pool_saturation_ms")]
+    pub db_pool_saturation_ms: u64,
 
     #[serde(default)]
     pub redis_url: String,
+    #[serde(default = "default_pubsub_backend")]
+    pub pubsub_backend: String,
 
     #[serde(default)]
     pub jwt_secret: String,
@@ -49,15 +63,27 @@ pub struct ConfigFile {
     pub s3_secret_key: String,
     #[serde(default = "default_s3_region")]
     pub s3_region: String,
+    #[serde(default = "default_backup_storage_dir")]
+    pub backup_storage_dir: String,
+    #[serde(default = "default_backup_s3_bucket")]
+    pub backup_s3_bucket: String,
 
     #[serde(default = "default_cors_origins")]
     pub cors_origins: String,
+    #[serde(default = "default_api_deprecated_versions")]
+    pub api_deprecated_versions: String,
 
     #[serde(default)]
     pub trust_proxy: bool,
 
     #[serde(default = "default_max_requests_per_minute")]
     pub max_requests_per_minute: u32,
+    #[serde(default = "default_rate_limit_per_user")]
+    pub rate_limit_per_user: u32,
+    #[serde(default = "default_rate_limit_routes")]
+    pub rate_limit_routes: String,
+    #[serde(default = "default_rate_limit_redis")]
+    pub rate_limit_redis: bool,
     #[serde(default = "default_max_ws_connections_per_user")]
     pub max_ws_connections_per_user: u32,
 
@@ -66,6 +92,8 @@ pub struct ConfigFile {
 
     #[serde(default = "default_ws_heartbeat_timeout_secs")]
     pub ws_heartbeat_timeout_secs: u64,
+    #[serde(default = "default_ws_heartbeat_interval_secs")]
+    pub ws_heartbeat_interval_secs: u64,
 
     #[serde(default = "default_ws_session_buffer_size")]
     pub ws_session_buffer_size: usize,
@@ -73,8 +101,42 @@ pub struct ConfigFile {
     #[serde(default = "default_ws_session_ttl_secs")]
     pub ws_session_ttl_secs: u64,
 
+    #[serde(default = "default_ws_coalesce_window_ms")]
+    pub ws_coalesce_window_ms: u64,
+
     #[serde(default = "default_max_upload_size_bytes")]
     pub max_upload_size_bytes: u64,
+    #[serde(default = "default_profile_media_quota_bytes")]
+    pub profile_media_quota_bytes: u64,
+    #[serde(default = "default_voice_note_max_duration_secs")]
+    pub voice_note_max_duration_secs: u32,
+    #[serde(default = "default_live_location_max_duration_secs")]
+    pub live_location_max_duration_secs: u32,
+    #[serde(default = "default_server_storage_quota_bytes")]
+    pub server_storage_quota_bytes: u64,
+    #[serde(default = "default_server_message_rate_per_minute")]
+    pub server_message_rate_per_minute: u32,
+    #[serde(default = "default_server_max_members")]
+    pub server_max_members: u32,
+    #[serde(default = "default_max_group_dm_members")]
+    pub max_group_dm_members: usize,
+    #[serde(default = "default_prekey_low_watermark")]
+    pub prekey_low_watermark: i64,
+    #[serde(default = "default_event_reminder_minutes")]
+    pub event_reminder_minutes: u32,
+
+    #[serde(default = "default_interactive_timeout_secs")]
+    pub interactive_timeout_secs: u64,
+    #[serde(default = "default_job_timeout_secs")]
+    pub job_timeout_secs: u64,
+    #[serde(default = "default_shutdown_drain_secs")]
+    pub shutdown_drain_secs: u64,
+
+    #[serde(default)]
+    pub shadow_read_sample_rate: f64,
+
+    #[serde(default)]
+    pub thumbnails_enabled: bool,
 
     #[serde(default)]
     pub cdn_enabled: bool,
@@ -96,20 +158,80 @@ pub struct ConfigFile {
     #[serde(default = "default_livekit_port")]
     pub livekit_port: u16,
 
+    // TURN relay for 1:1 DM calls — disabled when turn_urls is empty
+    #[serde(default)]
+    pub turn_urls: String,
+    #[serde(default)]
+    pub turn_secret: String,
+    #[serde(default = "default_turn_credential_ttl_secs")]
+    pub turn_credential_ttl_secs: u64,
+
+    // Federation (DM-only) — disabled when federation_server_name is empty
+    #[serde(default)]
+    pub federation_server_name: String,
+    #[serde(default)]
+    pub federation_signing_key: String,
+    #[serde(default)]
+    pub federation_allowlist: String,
+    #[serde(default)]
+    pub federation_denylist: String,
+    #[serde(default = "default_federation_user_cache_ttl_secs")]
+    pub federation_user_cache_ttl_secs: u64,
+    #[serde(default = "default_federation_key_cache_ttl_secs")]
+    pub federation_key_cache_ttl_secs: u64,
+    #[serde(default = "default_irc_port")]
+    pub irc_port: u16,
+
     #[serde(default)]
     pub tls: TlsConfig,
 
     // Data Retention (days, 0 = keep forever)
     #[serde(default = "default_audit_log_retention_days")]
     pub audit_log_retention_days: u32,
+    #[serde(default = "default_message_partition_months_ahead")]
+    pub message_partition_months_ahead: u32,
+    #[serde(default = "default_message_partition_retention_months")]
+    pub message_partition_retention_months: u32,
     #[serde(default = "default_resolved_report_retention_days")]
     pub resolved_report_retention_days: u32,
+    #[serde(default = "default_sync_journal_retention_days")]
+    pub sync_journal_retention_days: u32,
+    #[serde(default = "default_device_queue_ttl_days")]
+    pub device_queue_ttl_days: u32,
     #[serde(default = "default_expired_invite_cleanup")]
     pub expired_invite_cleanup: bool,
+    #[serde(default = "default_attachment_gc_grace_hours")]
+    pub attachment_gc_grace_hours: u32,
+    #[serde(default)]
+    pub attachment_gc_dry_run: bool,
+    #[serde(default)]
+    pub attachment_scanner: String,
+    #[serde(default)]
+    pub attachment_scanner_url: String,
+    #[serde(default = "default_attachment_scan_timeout_secs")]
+    pub attachment_scan_timeout_secs: u64,
+    #[serde(default)]
+    pub attachment_scan_fail_open: bool,
+    #[serde(default)]
+    pub attachment_url_signing_keys: String,
+    #[serde(default = "default_attachment_url_ttl_secs")]
+    pub attachment_url_ttl_secs: u64,
+    #[serde(default)]
+    pub attachment_url_base: String,
+
+    // Usage telemetry (opt-in)
+    #[serde(default)]
+    pub telemetry_endpoint: String,
+    #[serde(default)]
+    pub telemetry_token: String,
+    #[serde(default)]
+    pub telemetry_instance_id: String,
 
     // Registration gating
     #[serde(default)]
     pub registration_invite_only: bool,
+    #[serde(default = "default_registration_mode")]
+    pub registration_mode: String,
     #[serde(default = "default_registration_invites_per_user")]
     pub registration_invites_per_user: u32,
 
@@ -123,7 +245,15 @@ pub struct ConfigFile {
     #[serde(default)]
     pub turnstile_secret_key: String,
 
-    // SMTP (beta code email delivery) — disabled when smtp_host is empty
+    // Abuse scoring of registrations and beta code requests
+    #[serde(default)]
+    pub abuse_ip_lists: String,
+    #[serde(default)]
+    pub abuse_block_score: u8,
+    #[serde(default = "default_abuse_ip_retention_hours")]
+    pub abuse_ip_retention_hours: u32,
+
+    // Email delivery (EMAIL_PROVIDER); SMTP is disabled when smtp_host is empty
     #[serde(default)]
     pub smtp_host: String,
     #[serde(default = "default_smtp_port")]
@@ -134,12 +264,28 @@ pub struct ConfigFile {
     pub smtp_password: String,
     #[serde(default)]
     pub smtp_from: String,
+    #[serde(default = "default_email_provider")]
+    pub email_provider: String,
+    #[serde(default)]
+    pub email_api_key: String,
+    #[serde(default)]
+    pub email_domain: String,
+    #[serde(default)]
+    pub email_api_url: String,
+    #[serde(default)]
+    pub email_template_dir: String,
 
     // Beta code system
     #[serde(default = "default_beta_code_limit")]
     pub beta_code_limit: u32,
     #[serde(default = "default_beta_code_expiry_days")]
     pub beta_code_expiry_days: i64,
+    #[serde(default = "default_beta_block_disposable_emails")]
+    pub beta_block_disposable_emails: bool,
+    #[serde(default)]
+    pub beta_blocked_email_domains: String,
+    #[serde(default)]
+    pub beta_require_mx: bool,
 }
 
 // ─── TLS Config ───────────────────────────────────────
@@ -173,34 +319,75 @@ impl Default for TlsConfig {
 fn default_host() -> String { "0.0.0.0".into() }
 fn default_port() -> u16 { 8080 }
 fn default_db_max_connections() -> u32 { 50 }
+fn default_db_replica_strategy() -> String { "round-robin".into() }
+fn default_db_replica_max_lag_secs() -> u64 { 10 }
+fn default_db_auto_migrate() -> bool { true }
+fn default_db_slow_query_ms() -> u64 { 500 }
+fn default_db_pool_saturation_ms() -> u64 { 1000 }
 fn default_jwt_expiry_hours() -> i64 { 24 }
 fn default_refresh_token_expiry_days() -> i64 { 30 }
+fn default_pubsub_backend() -> String { "redis".into() }
 fn default_storage_backend() -> String { "local".into() }
 fn default_storage_dir() -> String { "./data/attachments".into() }
 fn default_s3_region() -> String { "us-east-1".into() }
+fn default_backup_storage_dir() -> String { String::new() }
+fn default_backup_s3_bucket() -> String { String::new() }
 fn default_cors_origins() -> String { "http://localhost:8080".into() }
+fn default_api_deprecated_versions() -> String { String::new() }
 fn default_max_requests_per_minute() -> u32 { 1200 }
+fn default_rate_limit_per_user() -> u32 { 600 }
+fn default_rate_limit_routes() -> String { String::new() }
+fn default_rate_limit_redis() -> bool { false }
 fn default_max_ws_connections_per_user() -> u32 { 5 }
 fn default_broadcast_channel_capacity() -> usize { 4096 }
 fn default_ws_heartbeat_timeout_secs() -> u64 { 90 }
+fn default_ws_heartbeat_interval_secs() -> u64 { 30 }
 fn default_ws_session_buffer_size() -> usize { 500 }
 fn default_ws_session_ttl_secs() -> u64 { 300 }
+fn default_ws_coalesce_window_ms() -> u64 { 250 }
 fn default_max_upload_size_bytes() -> u64 { 524_288_000 }
+fn default_profile_media_quota_bytes() -> u64 { 16 * 1024 * 1024 }
+fn default_voice_note_max_duration_secs() -> u32 { 300 }
+fn default_live_location_max_duration_secs() -> u32 { 28_800 }
+fn default_server_storage_quota_bytes() -> u64 { 10 * 1024 * 1024 * 1024 }
+fn default_server_message_rate_per_minute() -> u32 { 1200 }
+fn default_server_max_members() -> u32 { 0 }
+fn default_max_group_dm_members() -> usize { 10 }
+fn default_prekey_low_watermark() -> i64 { 20 }
+fn default_event_reminder_minutes() -> u32 { 15 }
+fn default_interactive_timeout_secs() -> u64 { 15 }
+fn default_job_timeout_secs() -> u64 { 600 }
+fn default_shutdown_drain_secs() -> u64 { 30 }
 fn default_cdn_presign_expiry_secs() -> u64 { 3600 }
 fn default_livekit_bundled() -> bool { true }
 fn default_livekit_port() -> u16 { 7880 }
+fn default_turn_credential_ttl_secs() -> u64 { 3600 }
+fn default_federation_user_cache_ttl_secs() -> u64 { 3600 }
+fn default_federation_key_cache_ttl_secs() -> u64 { 86400 }
+fn default_irc_port() -> u16 { 0 }
 fn default_tls_enabled() -> bool { true }
 fn default_tls_port() -> u16 { 8443 }
 fn default_tls_cert_path() -> String { "./data/certs/cert.pem".into() }
 fn default_tls_key_path() -> String { "./data/certs/key.pem".into() }
 fn default_tls_auto_generate() -> bool { true }
 fn default_audit_log_retention_days() -> u32 { 90 }
+fn default_message_partition_months_ahead() -> u32 { 3 }
+fn default_message_partition_retention_months() -> u32 { 0 }
 fn default_resolved_report_retention_days() -> u32 { 180 }
+fn default_sync_journal_retention_days() -> u32 { 7 }
+fn default_device_queue_ttl_days() -> u32 { 30 }
 fn default_expired_invite_cleanup() -> bool { true }
+fn default_attachment_gc_grace_hours() -> u32 { 24 }
+fn default_attachment_scan_timeout_secs() -> u64 { 30 }
+fn default_attachment_url_ttl_secs() -> u64 { 300 }
+fn default_registration_mode() -> String { "open".into() }
 fn default_registration_invites_per_user() -> u32 { 3 }
 fn default_smtp_port() -> u16 { 587 }
+fn default_email_provider() -> String { "smtp".into() }
 fn default_beta_code_limit() -> u32 { 50 }
 fn default_beta_code_expiry_days() -> i64 { 7 }
+fn default_beta_block_disposable_emails() -> bool { true }
+fn default_abuse_ip_retention_hours() -> u32 { 24 }
 
 // ─── Application Config ───────────────────────────────
 
@@ -212,11 +399,17 @@ pub struct AppConfig {
 
     // Database
     pub database_url: String,
-    pub database_replica_url: String, // empty = no replica, all queries go to primary
+    pub database_replica_url: String, // comma-separated; empty = no replica, all queries go to primary
     pub db_max_connections: u32,
+    pub db_replica_strategy: String, // "round-robin" or "least-lag"
+    pub db_replica_max_lag_secs: u64, // replicas further behind than this are skipped; 0 = no cutoff
+    pub db_auto_migrate: bool, // apply pending migrations at startup; otherwise refuse to start while any are pending
+    pub db_slow_query_ms: u64, // statements at least this slow are logged with a fingerprint; 0 = off
+    pub db_pool_saturation_ms: u64, // connection wait at which API requests are shed with 503s; 0 = never shed
 
     // Redis
     pub redis_url: String,
+    pub pubsub_backend: String, // WS fan-out across instances: "redis" or "local" (single instance)
 
     // JWT
     pub jwt_secret: String,
@@ -235,24 +428,57 @@ pub struct AppConfig {
     pub s3_secret_key: String,
     pub s3_region: String,
 
+    // Backup storage for retention archives — a directory, or a bucket reached
+    // with the S3_* credentials above. Neither set = no archives.
+    pub backup_storage_dir: String,
+    pub backup_s3_bucket: String,
+
     // CORS — comma-separated list of allowed origins (e.g. "http://localhost:5173,https://app.haven.example")
     pub cors_origins: String,
+    pub api_deprecated_versions: String, // deprecated API versions, "v1" or "v1=YYYY-MM-DD" (sunset) comma-separated
 
     // Proxy
     pub trust_proxy: bool,
 
     // Rate Limiting
     pub max_requests_per_minute: u32,
+    pub rate_limit_per_user: u32, // token-bucket requests per minute per authenticated user, 0 = off
+    pub rate_limit_routes: String, // per-route policies, "METHOD /route=N/SECS" comma-separated
+    pub rate_limit_redis: bool, // share rate limit buckets across instances via Redis
     pub max_ws_connections_per_user: u32,
 
     // WebSocket
     pub broadcast_channel_capacity: usize,
     pub ws_heartbeat_timeout_secs: u64,
+    pub ws_heartbeat_interval_secs: u64, // how often clients must send Heartbeat; a connection silent past the timeout is reaped
     pub ws_session_buffer_size: usize,
     pub ws_session_ttl_secs: u64,
+    pub ws_coalesce_window_ms: u64, // collapse identical consecutive state events; 0 disables
 
     // File Upload
     pub max_upload_size_bytes: u64,
+    pub profile_media_quota_bytes: u64, // avatars + banners, per user
+    pub voice_note_max_duration_secs: u32,
+    pub live_location_max_duration_secs: u32, // longest live location session (8 hours by default)
+    pub server_storage_quota_bytes: u64, // attachment bytes per server, 0 = unlimited
+    pub server_message_rate_per_minute: u32, // messages per minute per server, 0 = unlimited
+    pub server_max_members: u32, // per server, 0 = unlimited
+    pub max_group_dm_members: usize, // including the owner
+    pub prekey_low_watermark: i64, // PreKeysLow is pushed below this many one-time prekeys
+    pub event_reminder_minutes: u32, // scheduled events remind RSVPed members this long before they start, 0 = off
+
+    // Latency budgets — handlers exceeding these are aborted with 504
+    pub interactive_timeout_secs: u64, // most API routes
+    pub job_timeout_secs: u64,         // uploads and other job submission routes
+
+    // Graceful shutdown — on SIGTERM, WS handoff and in-flight work must finish within this
+    pub shutdown_drain_secs: u64,
+
+    // Shadow reads — fraction of hot reads also run through their rewritten query
+    pub shadow_read_sample_rate: f64,  // 0.0 disables, 1.0 shadows every read
+
+    // Image previews — thumbnails + blurhash for unencrypted channels, off by default
+    pub thumbnails_enabled: bool,
 
     // CDN — optional, disabled by default
     pub cdn_enabled: bool,
@@ -267,6 +493,22 @@ pub struct AppConfig {
     pub livekit_bundled: bool,
     pub livekit_port: u16,
 
+    // TURN (DM calls) — comma-separated URLs, shared secret for the coturn REST API scheme
+    pub turn_urls: String,
+    pub turn_secret: String,
+    pub turn_credential_ttl_secs: u64,
+
+    // Federation — signed server-to-server DM relay
+    pub federation_server_name: String,
+    pub federation_signing_key: String,
+    pub federation_allowlist: String,
+    pub federation_denylist: String,
+    pub federation_user_cache_ttl_secs: u64,
+    pub federation_key_cache_ttl_secs: u64,
+
+    // IRC gateway for unencrypted channels — needs the `irc` build feature; 0 disables
+    pub irc_port: u16,
+
     // TLS — auto-generated self-signed certs by default
     pub tls_enabled: bool,
     pub tls_port: u16,
@@ -276,11 +518,30 @@ pub struct AppConfig {
 
     // Data Retention (days, 0 = keep forever)
     pub audit_log_retention_days: u32,
+    pub message_partition_months_ahead: u32, // monthly message partitions created ahead of the current month
+    pub message_partition_retention_months: u32, // partitions entirely older than this are dropped; 0 = keep forever
     pub resolved_report_retention_days: u32,
+    pub sync_journal_retention_days: u32, // WS delta sync journal entries older than this are pruned; 0 = keep forever
+    pub device_queue_ttl_days: u32, // unacknowledged messages queued for offline devices are trimmed after this many days; 0 = keep until acknowledged
     pub expired_invite_cleanup: bool,
+    pub attachment_gc_grace_hours: u32, // orphaned attachments are kept this long before deletion
+    pub attachment_gc_dry_run: bool,    // count orphaned attachments without deleting them
+    pub attachment_scanner: String, // empty (no scanning), clamd or icap
+    pub attachment_scanner_url: String, // tcp://host:port or unix:///path for clamd, icap://host:port/service for icap
+    pub attachment_scan_timeout_secs: u64,
+    pub attachment_scan_fail_open: bool, // let attachments whose scan failed be downloaded
+    pub attachment_url_signing_keys: String, // kid:secret,... — the first signs, all verify; empty disables signed URLs
+    pub attachment_url_ttl_secs: u64,
+    pub attachment_url_base: String, // origin of the edge serving signed URLs; empty = this server
+
+    // Usage telemetry (opt-in)
+    pub telemetry_endpoint: String, // URL the daily usage stats are POSTed to; empty = never sent
+    pub telemetry_token: String,    // bearer token for the endpoint
+    pub telemetry_instance_id: String, // label identifying this instance in the report
 
     // Registration gating
     pub registration_invite_only: bool,
+    pub registration_mode: String, // open, invite, approval or closed
     pub registration_invites_per_user: u32,
 
     // External APIs
@@ -290,22 +551,79 @@ pub struct AppConfig {
     pub turnstile_site_key: String,
     pub turnstile_secret_key: String,
 
-    // SMTP (beta code email delivery) — disabled when smtp_host is empty
+    // Abuse scoring of registrations and beta code requests (see crate::abuse)
+    pub abuse_ip_lists: String, // IP/CIDR list URLs or file paths (comma-separated)
+    pub abuse_block_score: u8, // refuse requests scoring at least this (0 = never)
+    pub abuse_ip_retention_hours: u32, // raw IPs of scored requests are erased after this
+
+    // Email delivery (EMAIL_PROVIDER); SMTP is disabled when smtp_host is empty
     pub smtp_host: String,
     pub smtp_port: u16,
     pub smtp_username: String,
     pub smtp_password: String,
     pub smtp_from: String,
+    pub email_provider: String, // smtp, sendgrid, mailgun or noop
+    pub email_api_key: String, // SendGrid / Mailgun API key
+    pub email_domain: String, // Mailgun sending domain
+    pub email_api_url: String, // provider API base URL override (e.g. Mailgun EU)
+    pub email_template_dir: String, // template overrides and translations (empty = built-in only)
 
     // Beta code system
     pub beta_code_limit: u32,
     pub beta_code_expiry_days: i64,
+    pub beta_block_disposable_emails: bool, // refuse the bundled disposable email domains
+    pub beta_blocked_email_domains: String, // extra refused domains (comma-separated)
+    pub beta_require_mx: bool, // refuse domains with no MX (or A/AAAA) record
+}
+
+/// Who may create an account (`REGISTRATION_MODE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationMode {
+    /// Anyone can register.
+    Open,
+    /// A registration invite (or beta code) is required.
+    Invite,
+    /// Accounts wait for an operator to approve them before they can log in.
+    Approval,
+    /// Nobody can register.
+    Closed,
+}
+
+impl RegistrationMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(Self::Open),
+            "invite" => Some(Self::Invite),
+            "approval" => Some(Self::Approval),
+            "closed" => Some(Self::Closed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Invite => "invite",
+            Self::Approval => "approval",
+            Self::Closed => "closed",
+        }
+    }
 }
 
 impl AppConfig {
-    /// Returns true if SMTP is configured for beta code delivery.
-    pub fn smtp_enabled(&self) -> bool {
-        !self.smtp_host.is_empty() && !self.smtp_username.is_empty() && !self.smtp_from.is_empty()
+    /// The registration mode in force. `REGISTRATION_INVITE_ONLY=true`, the
+    /// older switch, still turns an open instance invite-only.
+    pub fn effective_registration_mode(&self) -> RegistrationMode {
+        match RegistrationMode::parse(&self.registration_mode).unwrap_or(RegistrationMode::Open) {
+            RegistrationMode::Open if self.registration_invite_only => RegistrationMode::Invite,
+            mode => mode,
+        }
+    }
+
+    /// Returns true if an email provider is configured (beta codes and
+    /// other transactional mail). See [`crate::email::mailer::from_config`].
+    pub fn email_enabled(&self) -> bool {
+        matches!(crate::email::mailer::from_config(self), Ok(Some(_)))
     }
 
     /// Returns true if Cloudflare Turnstile CAPTCHA is configured.
@@ -313,7 +631,8 @@ impl AppConfig {
         !self.turnstile_site_key.is_empty() && !self.turnstile_secret_key.is_empty()
     }
 
-    /// Validate the config, rejecting known-weak JWT secrets.
+    /// Validate the config, rejecting known-weak JWT secrets, unknown backends
+    /// and incomplete email provider settings.
     /// Panics if the secret is too short or contains placeholder text.
     pub fn validate(&self) {
         if self.jwt_secret.len() < 32 {
@@ -325,6 +644,30 @@ impl AppConfig {
         if self.jwt_secret.to_lowercase().contains("change-me") {
             panic!("JWT_SECRET contains 'change-me' — replace it with a strong random secret.");
         }
+        if !matches!(self.pubsub_backend.as_str(), "redis" | "local") {
+            panic!("PUBSUB_BACKEND must be 'redis' or 'local', got '{}'.", self.pubsub_backend);
+        }
+        if crate::db::ReplicaStrategy::parse(&self.db_replica_strategy).is_none() {
+            panic!("DB_REPLICA_STRATEGY must be 'round-robin' or 'least-lag', got '{}'.", self.db_replica_strategy);
+        }
+        if let Err(e) = crate::email::mailer::from_config(self) {
+            panic!("{}", e);
+        }
+        if self.abuse_block_score > 100 {
+            panic!("ABUSE_BLOCK_SCORE must be from 0 to 100, got {}.", self.abuse_block_score);
+        }
+        if let Err(e) = crate::scanning::from_config(self) {
+            panic!("{}", e);
+        }
+        if let Err(e) = crate::signed_urls::SigningKeys::parse(&self.attachment_url_signing_keys) {
+            panic!("ATTACHMENT_URL_SIGNING_KEYS: {}", e);
+        }
+        if RegistrationMode::parse(&self.registration_mode).is_none() {
+            panic!(
+                "REGISTRATION_MODE must be 'open', 'invite', 'approval' or 'closed', got '{}'.",
+                self.registration_mode
+            );
+        }
     }
 
     /// Returns true if LiveKit voice is configured.
@@ -344,6 +687,16 @@ impl AppConfig {
         }
     }
 
+    /// Returns true if a TURN relay is configured for DM calls.
+    pub fn turn_enabled(&self) -> bool {
+        !self.turn_urls.is_empty() && !self.turn_secret.is_empty()
+    }
+
+    /// Returns true if this instance federates with other Haven servers.
+    pub fn federation_enabled(&self) -> bool {
+        !self.federation_server_name.is_empty() && !self.federation_signing_key.is_empty()
+    }
+
     /// Config with test-appropriate defaults (no env vars needed).
     #[cfg(test)]
     pub fn test_default() -> Self {
@@ -353,7 +706,13 @@ impl AppConfig {
             database_url: String::new(), // not used — pool comes from #[sqlx::test]
             database_replica_url: String::new(),
             db_max_connections: 5,
+            db_replica_strategy: "round-robin".into(),
+            db_replica_max_lag_secs: 10,
+            db_auto_migrate: true,
+            db_slow_query_ms: 500,
+            db_pool_saturation_ms: 1000,
             redis_url: "redis://127.0.0.1:6379".into(),
+            pubsub_backend: "redis".into(),
             jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
             jwt_expiry_hours: 24,
             refresh_token_expiry_days: 30,
@@ -365,15 +724,37 @@ impl AppConfig {
             s3_access_key: String::new(),
             s3_secret_key: String::new(),
             s3_region: String::new(),
+            backup_storage_dir: String::new(),
+            backup_s3_bucket: String::new(),
             cors_origins: "*".into(),
+            api_deprecated_versions: String::new(),
             trust_proxy: false,
             max_requests_per_minute: 1000,
+            rate_limit_per_user: 600,
+            rate_limit_routes: String::new(),
+            rate_limit_redis: false,
             max_ws_connections_per_user: 10,
             broadcast_channel_capacity: 4096,
             ws_heartbeat_timeout_secs: 90,
+            ws_heartbeat_interval_secs: 30,
             ws_session_buffer_size: 500,
             ws_session_ttl_secs: 300,
+            ws_coalesce_window_ms: 250,
             max_upload_size_bytes: 10_000_000,
+            profile_media_quota_bytes: 16 * 1024 * 1024,
+            voice_note_max_duration_secs: 300,
+            live_location_max_duration_secs: 28_800,
+            server_storage_quota_bytes: 10 * 1024 * 1024 * 1024,
+            server_message_rate_per_minute: 1200,
+            server_max_members: 0,
+            max_group_dm_members: 10,
+            prekey_low_watermark: 20,
+            event_reminder_minutes: 15,
+            interactive_timeout_secs: 15,
+            job_timeout_secs: 600,
+            shutdown_drain_secs: 30,
+            shadow_read_sample_rate: 0.0,
+            thumbnails_enabled: false,
             cdn_enabled: false,
             cdn_base_url: String::new(),
             cdn_presign_expiry_secs: 3600,
@@ -383,6 +764,16 @@ impl AppConfig {
             livekit_api_secret: String::new(),
             livekit_bundled: false,
             livekit_port: 7880,
+            turn_urls: String::new(),
+            turn_secret: String::new(),
+            turn_credential_ttl_secs: 3600,
+            federation_server_name: String::new(),
+            federation_signing_key: String::new(),
+            federation_allowlist: String::new(),
+            federation_denylist: String::new(),
+            federation_user_cache_ttl_secs: 3600,
+            federation_key_cache_ttl_secs: 86400,
+            irc_port: 0,
             tls_enabled: false,
             tls_port: 8443,
             tls_cert_path: "./data/certs/cert.pem".into(),
@@ -390,10 +781,28 @@ impl AppConfig {
             tls_auto_generate: false,
 
             audit_log_retention_days: 90,
+            message_partition_months_ahead: 3,
+            message_partition_retention_months: 0,
             resolved_report_retention_days: 180,
+            sync_journal_retention_days: 7,
+            device_queue_ttl_days: 30,
             expired_invite_cleanup: true,
+            attachment_gc_grace_hours: 24,
+            attachment_gc_dry_run: false,
+            attachment_scanner: String::new(),
+            attachment_scanner_url: String::new(),
+            attachment_scan_timeout_secs: 30,
+            attachment_scan_fail_open: false,
+            attachment_url_signing_keys: String::new(),
+            attachment_url_ttl_secs: 300,
+            attachment_url_base: String::new(),
+
+            telemetry_endpoint: String::new(),
+            telemetry_token: String::new(),
+            telemetry_instance_id: String::new(),
 
             registration_invite_only: false,
+            registration_mode: "open".into(),
             registration_invites_per_user: 3,
 
             giphy_api_key: String::new(),
@@ -401,14 +810,26 @@ impl AppConfig {
             turnstile_site_key: String::new(),
             turnstile_secret_key: String::new(),
 
+            abuse_ip_lists: String::new(),
+            abuse_block_score: 0,
+            abuse_ip_retention_hours: 24,
+
             smtp_host: String::new(),
             smtp_port: 587,
             smtp_username: String::new(),
             smtp_password: String::new(),
             smtp_from: String::new(),
+            email_provider: "smtp".into(),
+            email_api_key: String::new(),
+            email_domain: String::new(),
+            email_api_url: String::new(),
+            email_template_dir: String::new(),
 
             beta_code_limit: 50,
             beta_code_expiry_days: 7,
+            beta_block_disposable_emails: true,
+            beta_blocked_email_domains: String::new(),
+            beta_require_mx: false,
         }
     }
 
@@ -428,9 +849,27 @@ impl AppConfig {
                 .unwrap_or_else(|_| "50".into())
                 .parse()
                 .unwrap_or(50),
+            db_replica_strategy: env::var("DB_REPLICA_STRATEGY").unwrap_or_else(|_| "round-robin".into()),
+            db_replica_max_lag_secs: env::var("DB_REPLICA_MAX_LAG_SECS")
+                .unwrap_or_else(|_| "10".into())
+                .parse()
+                .unwrap_or(10),
+            db_auto_migrate: env::var("DB_AUTO_MIGRATE")
+                .unwrap_or_else(|_| "true".into())
+                .parse()
+                .unwrap_or(true),
+            db_slow_query_ms: env::var("DB_SLOW_QUERY_MS")
+                .unwrap_or_else(|_| "500".into())
+                .parse()
+                .unwrap_or(500),
+            db_pool_saturation_ms: env::var("DB_POOL_SATURATION_MS")
+                .unwrap_or_else(|_| "1000".into())
+                .parse()
+                .unwrap_or(1000),
 
             redis_url: env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://127.0.0.1:6379".into()),
+            pubsub_backend: env::var("PUBSUB_BACKEND").unwrap_or_else(|_| "redis".into()),
 
             jwt_secret: env::var("JWT_SECRET")
                 .expect("JWT_SECRET must be set"),
@@ -454,9 +893,12 @@ impl AppConfig {
             s3_access_key: env::var("S3_ACCESS_KEY").unwrap_or_default(),
             s3_secret_key: env::var("S3_SECRET_KEY").unwrap_or_default(),
             s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".into()),
+            backup_storage_dir: env::var("BACKUP_STORAGE_DIR").unwrap_or_default(),
+            backup_s3_bucket: env::var("BACKUP_S3_BUCKET").unwrap_or_default(),
 
             cors_origins: env::var("CORS_ORIGINS")
                 .unwrap_or_else(|_| "http://localhost:5173".into()),
+            api_deprecated_versions: env::var("API_DEPRECATED_VERSIONS").unwrap_or_default(),
 
             trust_proxy: env::var("TRUST_PROXY")
                 .unwrap_or_else(|_| "true".into())
@@ -467,6 +909,15 @@ impl AppConfig {
                 .unwrap_or_else(|_| "300".into())
                 .parse()
                 .unwrap_or(300),
+            rate_limit_per_user: env::var("RATE_LIMIT_PER_USER")
+                .unwrap_or_else(|_| "600".into())
+                .parse()
+                .unwrap_or(600),
+            rate_limit_routes: env::var("RATE_LIMIT_ROUTES").unwrap_or_default(),
+            rate_limit_redis: env::var("RATE_LIMIT_REDIS")
+                .unwrap_or_else(|_| "false".into())
+                .parse()
+                .unwrap_or(false),
             max_ws_connections_per_user: env::var("MAX_WS_CONNECTIONS_PER_USER")
                 .unwrap_or_else(|_| "5".into())
                 .parse()
@@ -478,13 +929,74 @@ impl AppConfig {
                 .unwrap_or(4096),
 
             ws_heartbeat_timeout_secs: default_ws_heartbeat_timeout_secs(),
+            ws_heartbeat_interval_secs: default_ws_heartbeat_interval_secs(),
             ws_session_buffer_size: default_ws_session_buffer_size(),
             ws_session_ttl_secs: default_ws_session_ttl_secs(),
+            ws_coalesce_window_ms: default_ws_coalesce_window_ms(),
 
             max_upload_size_bytes: env::var("MAX_UPLOAD_SIZE_BYTES")
                 .unwrap_or_else(|_| "524288000".into()) // 500MB
                 .parse()
                 .unwrap_or(524_288_000),
+            profile_media_quota_bytes: env::var("PROFILE_MEDIA_QUOTA_BYTES")
+                .unwrap_or_else(|_| "16777216".into()) // 16MB
+                .parse()
+                .unwrap_or(16 * 1024 * 1024),
+            voice_note_max_duration_secs: env::var("VOICE_NOTE_MAX_DURATION_SECS")
+                .unwrap_or_else(|_| "300".into())
+                .parse()
+                .unwrap_or(300),
+            live_location_max_duration_secs: env::var("LIVE_LOCATION_MAX_DURATION_SECS")
+                .unwrap_or_else(|_| "28800".into())
+                .parse()
+                .unwrap_or(28_800),
+            server_storage_quota_bytes: env::var("SERVER_STORAGE_QUOTA_BYTES")
+                .unwrap_or_else(|_| "10737418240".into())
+                .parse()
+                .unwrap_or(10 * 1024 * 1024 * 1024),
+            server_message_rate_per_minute: env::var("SERVER_MESSAGE_RATE_PER_MINUTE")
+                .unwrap_or_else(|_| "1200".into())
+                .parse()
+                .unwrap_or(1200),
+            server_max_members: env::var("SERVER_MAX_MEMBERS")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .unwrap_or(0),
+            max_group_dm_members: env::var("MAX_GROUP_DM_MEMBERS")
+                .unwrap_or_else(|_| "10".into())
+                .parse()
+                .unwrap_or(10),
+            prekey_low_watermark: env::var("PREKEY_LOW_WATERMARK")
+                .unwrap_or_else(|_| "20".into())
+                .parse()
+                .unwrap_or(20),
+            event_reminder_minutes: env::var("EVENT_REMINDER_MINUTES")
+                .unwrap_or_else(|_| "15".into())
+                .parse()
+                .unwrap_or(15),
+
+            interactive_timeout_secs: env::var("INTERACTIVE_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "15".into())
+                .parse()
+                .unwrap_or(15),
+            job_timeout_secs: env::var("JOB_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "600".into())
+                .parse()
+                .unwrap_or(600),
+            shutdown_drain_secs: env::var("SHUTDOWN_DRAIN_SECS")
+                .unwrap_or_else(|_| "30".into())
+                .parse()
+                .unwrap_or(30),
+
+            shadow_read_sample_rate: env::var("SHADOW_READ_SAMPLE_RATE")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .unwrap_or(0.0),
+
+            thumbnails_enabled: env::var("THUMBNAILS_ENABLED")
+                .unwrap_or_else(|_| "false".into())
+                .parse()
+                .unwrap_or(false),
 
             cdn_enabled: env::var("CDN_ENABLED")
                 .unwrap_or_else(|_| "false".into())
@@ -509,6 +1021,30 @@ impl AppConfig {
                 .parse()
                 .unwrap_or(7880),
 
+            turn_urls: env::var("TURN_URLS").unwrap_or_default(),
+            turn_secret: env::var("TURN_SECRET").unwrap_or_default(),
+            turn_credential_ttl_secs: env::var("TURN_CREDENTIAL_TTL_SECS")
+                .unwrap_or_else(|_| "3600".into())
+                .parse()
+                .unwrap_or(3600),
+
+            federation_server_name: env::var("FEDERATION_SERVER_NAME").unwrap_or_default(),
+            federation_signing_key: env::var("FEDERATION_SIGNING_KEY").unwrap_or_default(),
+            federation_allowlist: env::var("FEDERATION_ALLOWLIST").unwrap_or_default(),
+            federation_denylist: env::var("FEDERATION_DENYLIST").unwrap_or_default(),
+            federation_user_cache_ttl_secs: env::var("FEDERATION_USER_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "3600".into())
+                .parse()
+                .unwrap_or(3600),
+            federation_key_cache_ttl_secs: env::var("FEDERATION_KEY_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "86400".into())
+                .parse()
+                .unwrap_or(86400),
+            irc_port: env::var("IRC_PORT")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .unwrap_or(0),
+
             tls_enabled: env::var("TLS_ENABLED")
                 .unwrap_or_else(|_| "true".into())
                 .parse()
@@ -530,19 +1066,64 @@ impl AppConfig {
                 .unwrap_or_else(|_| "90".into())
                 .parse()
                 .unwrap_or(90),
+            message_partition_months_ahead: env::var("MESSAGE_PARTITION_MONTHS_AHEAD")
+                .unwrap_or_else(|_| "3".into())
+                .parse()
+                .unwrap_or(3),
+            message_partition_retention_months: env::var("MESSAGE_PARTITION_RETENTION_MONTHS")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .unwrap_or(0),
             resolved_report_retention_days: env::var("RESOLVED_REPORT_RETENTION_DAYS")
                 .unwrap_or_else(|_| "180".into())
                 .parse()
                 .unwrap_or(180),
+            sync_journal_retention_days: env::var("SYNC_JOURNAL_RETENTION_DAYS")
+                .unwrap_or_else(|_| "7".into())
+                .parse()
+                .unwrap_or(7),
+            device_queue_ttl_days: env::var("DEVICE_QUEUE_TTL_DAYS")
+                .unwrap_or_else(|_| "30".into())
+                .parse()
+                .unwrap_or(30),
             expired_invite_cleanup: env::var("EXPIRED_INVITE_CLEANUP")
                 .unwrap_or_else(|_| "true".into())
                 .parse()
                 .unwrap_or(true),
+            attachment_gc_grace_hours: env::var("ATTACHMENT_GC_GRACE_HOURS")
+                .unwrap_or_else(|_| "24".into())
+                .parse()
+                .unwrap_or(24),
+            attachment_gc_dry_run: env::var("ATTACHMENT_GC_DRY_RUN")
+                .unwrap_or_else(|_| "false".into())
+                .parse()
+                .unwrap_or(false),
+            attachment_scanner: env::var("ATTACHMENT_SCANNER").unwrap_or_default(),
+            attachment_scanner_url: env::var("ATTACHMENT_SCANNER_URL").unwrap_or_default(),
+            attachment_scan_timeout_secs: env::var("ATTACHMENT_SCAN_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".into())
+                .parse()
+                .unwrap_or(30),
+            attachment_scan_fail_open: env::var("ATTACHMENT_SCAN_FAIL_OPEN")
+                .unwrap_or_else(|_| "false".into())
+                .parse()
+                .unwrap_or(false),
+            attachment_url_signing_keys: env::var("ATTACHMENT_URL_SIGNING_KEYS").unwrap_or_default(),
+            attachment_url_ttl_secs: env::var("ATTACHMENT_URL_TTL_SECS")
+                .unwrap_or_else(|_| "300".into())
+                .parse()
+                .unwrap_or(300),
+            attachment_url_base: env::var("ATTACHMENT_URL_BASE").unwrap_or_default(),
+
+            telemetry_endpoint: env::var("TELEMETRY_ENDPOINT").unwrap_or_default(),
+            telemetry_token: env::var("TELEMETRY_TOKEN").unwrap_or_default(),
+            telemetry_instance_id: env::var("TELEMETRY_INSTANCE_ID").unwrap_or_default(),
 
             registration_invite_only: env::var("REGISTRATION_INVITE_ONLY")
                 .unwrap_or_else(|_| "false".into())
                 .parse()
                 .unwrap_or(false),
+            registration_mode: env::var("REGISTRATION_MODE").unwrap_or_else(|_| "open".into()),
             registration_invites_per_user: env::var("REGISTRATION_INVITES_PER_USER")
                 .unwrap_or_else(|_| "3".into())
                 .parse()
@@ -553,6 +1134,16 @@ impl AppConfig {
             turnstile_site_key: env::var("TURNSTILE_SITE_KEY").unwrap_or_default(),
             turnstile_secret_key: env::var("TURNSTILE_SECRET_KEY").unwrap_or_default(),
 
+            abuse_ip_lists: env::var("ABUSE_IP_LISTS").unwrap_or_default(),
+            abuse_block_score: env::var("ABUSE_BLOCK_SCORE")
+                .unwrap_or_else(|_| "0".into())
+                .parse()
+                .unwrap_or(0),
+            abuse_ip_retention_hours: env::var("ABUSE_IP_RETENTION_HOURS")
+                .unwrap_or_else(|_| "24".into())
+                .parse()
+                .unwrap_or(24),
+
             smtp_host: env::var("SMTP_HOST").unwrap_or_default(),
             smtp_port: env::var("SMTP_PORT")
                 .unwrap_or_else(|_| "587".into())
@@ -561,6 +1152,11 @@ impl AppConfig {
             smtp_username: env::var("SMTP_USERNAME").unwrap_or_default(),
             smtp_password: env::var("SMTP_PASSWORD").unwrap_or_default(),
             smtp_from: env::var("SMTP_FROM").unwrap_or_default(),
+            email_provider: env::var("EMAIL_PROVIDER").unwrap_or_else(|_| "smtp".into()),
+            email_api_key: env::var("EMAIL_API_KEY").unwrap_or_default(),
+            email_domain: env::var("EMAIL_DOMAIN").unwrap_or_default(),
+            email_api_url: env::var("EMAIL_API_URL").unwrap_or_default(),
+            email_template_dir: env::var("EMAIL_TEMPLATE_DIR").unwrap_or_default(),
 
             beta_code_limit: env::var("BETA_CODE_LIMIT")
                 .unwrap_or_else(|_| "50".into())
@@ -570,6 +1166,15 @@ impl AppConfig {
                 .unwrap_or_else(|_| "7".into())
                 .parse()
                 .unwrap_or(7),
+            beta_block_disposable_emails: env::var("BETA_BLOCK_DISPOSABLE_EMAILS")
+                .unwrap_or_else(|_| "true".into())
+                .parse()
+                .unwrap_or(true),
+            beta_blocked_email_domains: env::var("BETA_BLOCKED_EMAIL_DOMAINS").unwrap_or_default(),
+            beta_require_mx: env::var("BETA_REQUIRE_MX")
+                .unwrap_or_else(|_| "false".into())
+                .parse()
+                .unwrap_or(false),
         };
         config.validate();
         config
@@ -590,18 +1195,30 @@ impl AppConfig {
 
     /// Parse a TOML config file into AppConfig.
     fn from_toml_file(path: &str) -> Self {
+        let config = Self::read_toml_file(path).unwrap_or_else(|e| panic!("{}", e));
+        config.validate();
+        config
+    }
+
+    fn read_toml_file(path: &str) -> Result<Self, String> {
         let content = std::fs::read_to_string(path)
-            .unwrap_or_else(|e| panic!("Failed to read config file {}: {}", path, e));
+            .map_err(|e| format!("Failed to read config file {}: {}", path, e))?;
         let file: ConfigFile = toml::from_str(&content)
-            .unwrap_or_else(|e| panic!("Failed to parse config file {}: {}", path, e));
+            .map_err(|e| format!("Failed to parse config file {}: {}", path, e))?;
 
-        let config = Self {
+        Ok(Self {
             host: file.host,
             port: file.port,
             database_url: file.database_url,
             database_replica_url: file.database_replica_url,
             db_max_connections: file.db_max_connections,
+            db_replica_strategy: file.db_replica_strategy,
+            db_replica_max_lag_secs: file.db_replica_max_lag_secs,
+            db_auto_migrate: file.db_auto_migrate,
+            db_slow_query_ms: file.db_slow_query_ms,
+            db_pool_saturation_ms: file.db_pool_saturation_ms,
             redis_url: file.redis_url,
+            pubsub_backend: file.pubsub_backend,
             jwt_secret: file.jwt_secret,
             jwt_expiry_hours: file.jwt_expiry_hours,
             refresh_token_expiry_days: file.refresh_token_expiry_days,
@@ -613,15 +1230,37 @@ impl AppConfig {
             s3_access_key: file.s3_access_key,
             s3_secret_key: file.s3_secret_key,
             s3_region: file.s3_region,
+            backup_storage_dir: file.backup_storage_dir,
+            backup_s3_bucket: file.backup_s3_bucket,
             cors_origins: file.cors_origins,
+            api_deprecated_versions: file.api_deprecated_versions,
             trust_proxy: file.trust_proxy,
             max_requests_per_minute: file.max_requests_per_minute,
+            rate_limit_per_user: file.rate_limit_per_user,
+            rate_limit_routes: file.rate_limit_routes,
+            rate_limit_redis: file.rate_limit_redis,
             max_ws_connections_per_user: file.max_ws_connections_per_user,
             broadcast_channel_capacity: file.broadcast_channel_capacity,
             ws_heartbeat_timeout_secs: file.ws_heartbeat_timeout_secs,
+            ws_heartbeat_interval_secs: file.ws_heartbeat_interval_secs,
             ws_session_buffer_size: file.ws_session_buffer_size,
             ws_session_ttl_secs: file.ws_session_ttl_secs,
+            ws_coalesce_window_ms: file.ws_coalesce_window_ms,
             max_upload_size_bytes: file.max_upload_size_bytes,
+            profile_media_quota_bytes: file.profile_media_quota_bytes,
+            voice_note_max_duration_secs: file.voice_note_max_duration_secs,
+            live_location_max_duration_secs: file.live_location_max_duration_secs,
+            server_storage_quota_bytes: file.server_storage_quota_bytes,
+            server_message_rate_per_minute: file.server_message_rate_per_minute,
+            server_max_members: file.server_max_members,
+            max_group_dm_members: file.max_group_dm_members,
+            prekey_low_watermark: file.prekey_low_watermark,
+            event_reminder_minutes: file.event_reminder_minutes,
+            interactive_timeout_secs: file.interactive_timeout_secs,
+            job_timeout_secs: file.job_timeout_secs,
+            shutdown_drain_secs: file.shutdown_drain_secs,
+            shadow_read_sample_rate: file.shadow_read_sample_rate,
+            thumbnails_enabled: file.thumbnails_enabled,
             cdn_enabled: file.cdn_enabled,
             cdn_base_url: file.cdn_base_url,
             cdn_presign_expiry_secs: file.cdn_presign_expiry_secs,
@@ -631,6 +1270,16 @@ impl AppConfig {
             livekit_api_secret: file.livekit_api_secret,
             livekit_bundled: file.livekit_bundled,
             livekit_port: file.livekit_port,
+            turn_urls: file.turn_urls,
+            turn_secret: file.turn_secret,
+            turn_credential_ttl_secs: file.turn_credential_ttl_secs,
+            federation_server_name: file.federation_server_name,
+            federation_signing_key: file.federation_signing_key,
+            federation_allowlist: file.federation_allowlist,
+            federation_denylist: file.federation_denylist,
+            federation_user_cache_ttl_secs: file.federation_user_cache_ttl_secs,
+            federation_key_cache_ttl_secs: file.federation_key_cache_ttl_secs,
+            irc_port: file.irc_port,
             tls_enabled: file.tls.enabled,
             tls_port: file.tls.port,
             tls_cert_path: file.tls.cert_path,
@@ -638,10 +1287,28 @@ impl AppConfig {
             tls_auto_generate: file.tls.auto_generate,
 
             audit_log_retention_days: file.audit_log_retention_days,
+            message_partition_months_ahead: file.message_partition_months_ahead,
+            message_partition_retention_months: file.message_partition_retention_months,
             resolved_report_retention_days: file.resolved_report_retention_days,
+            sync_journal_retention_days: file.sync_journal_retention_days,
+            device_queue_ttl_days: file.device_queue_ttl_days,
             expired_invite_cleanup: file.expired_invite_cleanup,
+            attachment_gc_grace_hours: file.attachment_gc_grace_hours,
+            attachment_gc_dry_run: file.attachment_gc_dry_run,
+            attachment_scanner: file.attachment_scanner,
+            attachment_scanner_url: file.attachment_scanner_url,
+            attachment_scan_timeout_secs: file.attachment_scan_timeout_secs,
+            attachment_scan_fail_open: file.attachment_scan_fail_open,
+            attachment_url_signing_keys: file.attachment_url_signing_keys,
+            attachment_url_ttl_secs: file.attachment_url_ttl_secs,
+            attachment_url_base: file.attachment_url_base,
+
+            telemetry_endpoint: file.telemetry_endpoint,
+            telemetry_token: file.telemetry_token,
+            telemetry_instance_id: file.telemetry_instance_id,
 
             registration_invite_only: file.registration_invite_only,
+            registration_mode: file.registration_mode,
             registration_invites_per_user: file.registration_invites_per_user,
 
             giphy_api_key: file.giphy_api_key,
@@ -649,17 +1316,27 @@ impl AppConfig {
             turnstile_site_key: file.turnstile_site_key,
             turnstile_secret_key: file.turnstile_secret_key,
 
+            abuse_ip_lists: file.abuse_ip_lists,
+            abuse_block_score: file.abuse_block_score,
+            abuse_ip_retention_hours: file.abuse_ip_retention_hours,
+
             smtp_host: file.smtp_host,
             smtp_port: file.smtp_port,
             smtp_username: file.smtp_username,
             smtp_password: file.smtp_password,
             smtp_from: file.smtp_from,
+            email_provider: file.email_provider,
+            email_api_key: file.email_api_key,
+            email_domain: file.email_domain,
+            email_api_url: file.email_api_url,
+            email_template_dir: file.email_template_dir,
 
             beta_code_limit: file.beta_code_limit,
             beta_code_expiry_days: file.beta_code_expiry_days,
-        };
-        config.validate();
-        config
+            beta_block_disposable_emails: file.beta_block_disposable_emails,
+            beta_blocked_email_domains: file.beta_blocked_email_domains,
+            beta_require_mx: file.beta_require_mx,
+        })
     }
 
     /// Generate a config file with secure random secrets and sane defaults.
@@ -684,7 +1361,13 @@ impl AppConfig {
             database_url: String::new(),
             database_replica_url: String::new(),
             db_max_connections: default_db_max_connections(),
+            db_replica_strategy: default_db_replica_strategy(),
+            db_replica_max_lag_secs: default_db_replica_max_lag_secs(),
+            db_auto_migrate: default_db_auto_migrate(),
+            db_slow_query_ms: default_db_slow_query_ms(),
+            db_pool_saturation_ms: default_db_pool_saturation_ms(),
             redis_url: String::new(),
+            pubsub_backend: default_pubsub_backend(),
             jwt_secret,
             jwt_expiry_hours: default_jwt_expiry_hours(),
             refresh_token_expiry_days: default_refresh_token_expiry_days(),
@@ -696,15 +1379,37 @@ impl AppConfig {
             s3_access_key: String::new(),
             s3_secret_key: String::new(),
             s3_region: default_s3_region(),
+            backup_storage_dir: default_backup_storage_dir(),
+            backup_s3_bucket: default_backup_s3_bucket(),
             cors_origins: default_cors_origins(),
+            api_deprecated_versions: default_api_deprecated_versions(),
             trust_proxy: false,
             max_requests_per_minute: default_max_requests_per_minute(),
+            rate_limit_per_user: default_rate_limit_per_user(),
+            rate_limit_routes: default_rate_limit_routes(),
+            rate_limit_redis: default_rate_limit_redis(),
             max_ws_connections_per_user: default_max_ws_connections_per_user(),
             broadcast_channel_capacity: default_broadcast_channel_capacity(),
             ws_heartbeat_timeout_secs: default_ws_heartbeat_timeout_secs(),
+            ws_heartbeat_interval_secs: default_ws_heartbeat_interval_secs(),
             ws_session_buffer_size: default_ws_session_buffer_size(),
             ws_session_ttl_secs: default_ws_session_ttl_secs(),
+            ws_coalesce_window_ms: default_ws_coalesce_window_ms(),
             max_upload_size_bytes: default_max_upload_size_bytes(),
+            profile_media_quota_bytes: default_profile_media_quota_bytes(),
+            voice_note_max_duration_secs: default_voice_note_max_duration_secs(),
+            live_location_max_duration_secs: default_live_location_max_duration_secs(),
+            server_storage_quota_bytes: default_server_storage_quota_bytes(),
+            server_message_rate_per_minute: default_server_message_rate_per_minute(),
+            server_max_members: default_server_max_members(),
+            max_group_dm_members: default_max_group_dm_members(),
+            prekey_low_watermark: default_prekey_low_watermark(),
+            event_reminder_minutes: default_event_reminder_minutes(),
+            interactive_timeout_secs: default_interactive_timeout_secs(),
+            job_timeout_secs: default_job_timeout_secs(),
+            shutdown_drain_secs: default_shutdown_drain_secs(),
+            shadow_read_sample_rate: 0.0,
+            thumbnails_enabled: false,
             cdn_enabled: false,
             cdn_base_url: String::new(),
             cdn_presign_expiry_secs: default_cdn_presign_expiry_secs(),
@@ -714,13 +1419,41 @@ impl AppConfig {
             livekit_api_secret: String::new(),
             livekit_bundled: default_livekit_bundled(),
             livekit_port: default_livekit_port(),
+            turn_urls: String::new(),
+            turn_secret: String::new(),
+            turn_credential_ttl_secs: default_turn_credential_ttl_secs(),
+            federation_server_name: String::new(),
+            federation_signing_key: String::new(),
+            federation_allowlist: String::new(),
+            federation_denylist: String::new(),
+            federation_user_cache_ttl_secs: default_federation_user_cache_ttl_secs(),
+            federation_key_cache_ttl_secs: default_federation_key_cache_ttl_secs(),
+            irc_port: default_irc_port(),
             tls: TlsConfig::default(),
 
             audit_log_retention_days: default_audit_log_retention_days(),
+            message_partition_months_ahead: default_message_partition_months_ahead(),
+            message_partition_retention_months: default_message_partition_retention_months(),
             resolved_report_retention_days: default_resolved_report_retention_days(),
+            sync_journal_retention_days: default_sync_journal_retention_days(),
+            device_queue_ttl_days: default_device_queue_ttl_days(),
             expired_invite_cleanup: default_expired_invite_cleanup(),
+            attachment_gc_grace_hours: default_attachment_gc_grace_hours(),
+            attachment_gc_dry_run: false,
+            attachment_scanner: String::new(),
+            attachment_scanner_url: String::new(),
+            attachment_scan_timeout_secs: default_attachment_scan_timeout_secs(),
+            attachment_scan_fail_open: false,
+            attachment_url_signing_keys: String::new(),
+            attachment_url_ttl_secs: default_attachment_url_ttl_secs(),
+            attachment_url_base: String::new(),
+
+            telemetry_endpoint: String::new(),
+            telemetry_token: String::new(),
+            telemetry_instance_id: String::new(),
 
             registration_invite_only: false,
+            registration_mode: default_registration_mode(),
             registration_invites_per_user: default_registration_invites_per_user(),
 
             giphy_api_key: String::new(),
@@ -728,14 +1461,26 @@ impl AppConfig {
             turnstile_site_key: String::new(),
             turnstile_secret_key: String::new(),
 
+            abuse_ip_lists: String::new(),
+            abuse_block_score: 0,
+            abuse_ip_retention_hours: default_abuse_ip_retention_hours(),
+
             smtp_host: String::new(),
             smtp_port: default_smtp_port(),
             smtp_username: String::new(),
             smtp_password: String::new(),
             smtp_from: String::new(),
+            email_provider: default_email_provider(),
+            email_api_key: String::new(),
+            email_domain: String::new(),
+            email_api_url: String::new(),
+            email_template_dir: String::new(),
 
             beta_code_limit: default_beta_code_limit(),
             beta_code_expiry_days: default_beta_code_expiry_days(),
+            beta_block_disposable_emails: default_beta_block_disposable_emails(),
+            beta_blocked_email_domains: String::new(),
+            beta_require_mx: false,
         };
 
         // Write the TOML file
@@ -754,7 +1499,13 @@ impl AppConfig {
             database_url: file.database_url,
             database_replica_url: file.database_replica_url,
             db_max_connections: file.db_max_connections,
+            db_replica_strategy: file.db_replica_strategy,
+            db_replica_max_lag_secs: file.db_replica_max_lag_secs,
+            db_auto_migrate: file.db_auto_migrate,
+            db_slow_query_ms: file.db_slow_query_ms,
+            db_pool_saturation_ms: file.db_pool_saturation_ms,
             redis_url: file.redis_url,
+            pubsub_backend: file.pubsub_backend,
             jwt_secret: file.jwt_secret,
             jwt_expiry_hours: file.jwt_expiry_hours,
             refresh_token_expiry_days: file.refresh_token_expiry_days,
@@ -766,15 +1517,37 @@ impl AppConfig {
             s3_access_key: file.s3_access_key,
             s3_secret_key: file.s3_secret_key,
             s3_region: file.s3_region,
+            backup_storage_dir: file.backup_storage_dir,
+            backup_s3_bucket: file.backup_s3_bucket,
             cors_origins: file.cors_origins,
+            api_deprecated_versions: file.api_deprecated_versions,
             trust_proxy: file.trust_proxy,
             max_requests_per_minute: file.max_requests_per_minute,
+            rate_limit_per_user: file.rate_limit_per_user,
+            rate_limit_routes: file.rate_limit_routes,
+            rate_limit_redis: file.rate_limit_redis,
             max_ws_connections_per_user: file.max_ws_connections_per_user,
             broadcast_channel_capacity: file.broadcast_channel_capacity,
             ws_heartbeat_timeout_secs: file.ws_heartbeat_timeout_secs,
+            ws_heartbeat_interval_secs: file.ws_heartbeat_interval_secs,
             ws_session_buffer_size: file.ws_session_buffer_size,
             ws_session_ttl_secs: file.ws_session_ttl_secs,
+            ws_coalesce_window_ms: file.ws_coalesce_window_ms,
             max_upload_size_bytes: file.max_upload_size_bytes,
+            profile_media_quota_bytes: file.profile_media_quota_bytes,
+            voice_note_max_duration_secs: file.voice_note_max_duration_secs,
+            live_location_max_duration_secs: file.live_location_max_duration_secs,
+            server_storage_quota_bytes: file.server_storage_quota_bytes,
+            server_message_rate_per_minute: file.server_message_rate_per_minute,
+            server_max_members: file.server_max_members,
+            max_group_dm_members: file.max_group_dm_members,
+            prekey_low_watermark: file.prekey_low_watermark,
+            event_reminder_minutes: file.event_reminder_minutes,
+            interactive_timeout_secs: file.interactive_timeout_secs,
+            job_timeout_secs: file.job_timeout_secs,
+            shutdown_drain_secs: file.shutdown_drain_secs,
+            shadow_read_sample_rate: file.shadow_read_sample_rate,
+            thumbnails_enabled: file.thumbnails_enabled,
             cdn_enabled: file.cdn_enabled,
             cdn_base_url: file.cdn_base_url,
             cdn_presign_expiry_secs: file.cdn_presign_expiry_secs,
@@ -784,6 +1557,16 @@ impl AppConfig {
             livekit_api_secret: file.livekit_api_secret,
             livekit_bundled: file.livekit_bundled,
             livekit_port: file.livekit_port,
+            turn_urls: file.turn_urls,
+            turn_secret: file.turn_secret,
+            turn_credential_ttl_secs: file.turn_credential_ttl_secs,
+            federation_server_name: file.federation_server_name,
+            federation_signing_key: file.federation_signing_key,
+            federation_allowlist: file.federation_allowlist,
+            federation_denylist: file.federation_denylist,
+            federation_user_cache_ttl_secs: file.federation_user_cache_ttl_secs,
+            federation_key_cache_ttl_secs: file.federation_key_cache_ttl_secs,
+            irc_port: file.irc_port,
             tls_enabled: file.tls.enabled,
             tls_port: file.tls.port,
             tls_cert_path: file.tls.cert_path,
@@ -791,10 +1574,28 @@ impl AppConfig {
             tls_auto_generate: file.tls.auto_generate,
 
             audit_log_retention_days: file.audit_log_retention_days,
+            message_partition_months_ahead: file.message_partition_months_ahead,
+            message_partition_retention_months: file.message_partition_retention_months,
             resolved_report_retention_days: file.resolved_report_retention_days,
+            sync_journal_retention_days: file.sync_journal_retention_days,
+            device_queue_ttl_days: file.device_queue_ttl_days,
             expired_invite_cleanup: file.expired_invite_cleanup,
+            attachment_gc_grace_hours: file.attachment_gc_grace_hours,
+            attachment_gc_dry_run: file.attachment_gc_dry_run,
+            attachment_scanner: file.attachment_scanner,
+            attachment_scanner_url: file.attachment_scanner_url,
+            attachment_scan_timeout_secs: file.attachment_scan_timeout_secs,
+            attachment_scan_fail_open: file.attachment_scan_fail_open,
+            attachment_url_signing_keys: file.attachment_url_signing_keys,
+            attachment_url_ttl_secs: file.attachment_url_ttl_secs,
+            attachment_url_base: file.attachment_url_base,
+
+            telemetry_endpoint: file.telemetry_endpoint,
+            telemetry_token: file.telemetry_token,
+            telemetry_instance_id: file.telemetry_instance_id,
 
             registration_invite_only: file.registration_invite_only,
+            registration_mode: file.registration_mode,
             registration_invites_per_user: file.registration_invites_per_user,
 
             giphy_api_key: file.giphy_api_key,
@@ -802,15 +1603,158 @@ impl AppConfig {
             turnstile_site_key: file.turnstile_site_key,
             turnstile_secret_key: file.turnstile_secret_key,
 
+            abuse_ip_lists: file.abuse_ip_lists,
+            abuse_block_score: file.abuse_block_score,
+            abuse_ip_retention_hours: file.abuse_ip_retention_hours,
+
             smtp_host: file.smtp_host,
             smtp_port: file.smtp_port,
             smtp_username: file.smtp_username,
             smtp_password: file.smtp_password,
             smtp_from: file.smtp_from,
+            email_provider: file.email_provider,
+            email_api_key: file.email_api_key,
+            email_domain: file.email_domain,
+            email_api_url: file.email_api_url,
+            email_template_dir: file.email_template_dir,
 
             beta_code_limit: file.beta_code_limit,
             beta_code_expiry_days: file.beta_code_expiry_days,
+            beta_block_disposable_emails: file.beta_block_disposable_emails,
+            beta_blocked_email_domains: file.beta_blocked_email_domains,
+            beta_require_mx: file.beta_require_mx,
+        }
+    }
+}
+
+// ─── Hot Reload ───────────────────────────────────────
+
+/// Settings that SIGHUP or `POST /admin/config/reload` pick up without a
+/// restart. Everything else is read once at startup.
+pub const RELOADABLE_FIELDS: &[&str] = &[
+    "max_requests_per_minute",
+    "rate_limit_per_user",
+    "rate_limit_routes",
+    "smtp_host",
+    "smtp_port",
+    "smtp_username",
+    "smtp_password",
+    "smtp_from",
+    "email_provider",
+    "email_api_key",
+    "email_domain",
+    "email_api_url",
+    "beta_code_limit",
+    "beta_block_disposable_emails",
+    "beta_blocked_email_domains",
+    "beta_require_mx",
+    "abuse_ip_lists",
+    "abuse_block_score",
+    "abuse_ip_retention_hours",
+    "registration_invite_only",
+    "registration_mode",
+    "thumbnails_enabled",
+    "attachment_url_signing_keys",
+    "attachment_url_ttl_secs",
+    "attachment_url_base",
+    "telemetry_endpoint",
+    "telemetry_token",
+    "telemetry_instance_id",
+];
+
+impl AppConfig {
+    /// Load the config again from where startup loaded it: `.env` and the
+    /// environment, or the TOML file (`HAVEN_CONFIG`) in SQLite mode.
+    pub fn reload_from_source() -> Result<Self, String> {
+        #[cfg(feature = "postgres")]
+        {
+            dotenvy::dotenv_override().ok();
+            // A required variable gone missing must not take the running server down
+            std::panic::catch_unwind(Self::from_env)
+                .map_err(|_| "Invalid configuration in the environment; see the log".to_string())
+        }
+        #[cfg(not(feature = "postgres"))]
+        {
+            let path = env::var("HAVEN_CONFIG").unwrap_or_else(|_| "./data/haven.toml".into());
+            Self::read_toml_file(&path)
+        }
+    }
+
+    /// Copy the [`RELOADABLE_FIELDS`] from `fresh`, returning those that changed.
+    pub fn apply_reloadable(&mut self, fresh: &AppConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        macro_rules! reload {
+            ($($field:ident),* $(,)?) => {
+                $(
+                    if self.$field != fresh.$field {
+                        self.$field = fresh.$field.clone();
+                        changed.push(stringify!($field));
+                    }
+                )*
+            };
         }
+        reload!(
+            max_requests_per_minute,
+            rate_limit_per_user,
+            rate_limit_routes,
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            smtp_from,
+            email_provider,
+            email_api_key,
+            email_domain,
+            email_api_url,
+            beta_code_limit,
+            beta_block_disposable_emails,
+            beta_blocked_email_domains,
+            beta_require_mx,
+            abuse_ip_lists,
+            abuse_block_score,
+            abuse_ip_retention_hours,
+            registration_invite_only,
+            registration_mode,
+            thumbnails_enabled,
+            attachment_url_signing_keys,
+            attachment_url_ttl_secs,
+            attachment_url_base,
+            telemetry_endpoint,
+            telemetry_token,
+            telemetry_instance_id,
+        );
+        changed
+    }
+}
+
+/// The running config. Starts as the startup config; a reload swaps in a
+/// copy with the [`RELOADABLE_FIELDS`] updated. Code reading a reloadable
+/// setting goes through [`LiveConfig::get`] instead of `AppState::config`.
+#[derive(Clone)]
+pub struct LiveConfig(Arc<watch::Sender<Arc<AppConfig>>>);
+
+impl LiveConfig {
+    pub fn new(config: AppConfig) -> Self {
+        Self(Arc::new(watch::channel(Arc::new(config)).0))
+    }
+
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.0.borrow().clone()
+    }
+
+    /// Apply the reloadable settings of `fresh`; returns the fields that changed.
+    pub fn apply(&self, fresh: &AppConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        self.0.send_if_modified(|current| {
+            let mut next = (**current).clone();
+            changed = next.apply_reloadable(fresh);
+            if changed.is_empty() {
+                return false;
+            }
+            *current = Arc::new(next);
+            true
+        });
+        changed
     }
 }
 
@@ -822,7 +1766,13 @@ impl fmt::Debug for AppConfig {
             .field("database_url", &self.database_url)
             .field("database_replica_url", &self.database_replica_url)
             .field("db_max_connections", &self.db_max_connections)
+            .field("db_replica_strategy", &self.db_replica_strategy)
+            .field("db_replica_max_lag_secs", &self.db_replica_max_lag_secs)
+            .field("db_auto_migrate", &self.db_auto_migrate)
+            .field("db_slow_query_ms", &self.db_slow_query_ms)
+            .field("db_pool_saturation_ms", &self.db_pool_saturation_ms)
             .field("redis_url", &self.redis_url)
+            .field("pubsub_backend", &self.pubsub_backend)
             .field("jwt_secret", &"[REDACTED]")
             .field("jwt_expiry_hours", &self.jwt_expiry_hours)
             .field("refresh_token_expiry_days", &self.refresh_token_expiry_days)
@@ -834,15 +1784,37 @@ impl fmt::Debug for AppConfig {
             .field("s3_access_key", &self.s3_access_key)
             .field("s3_secret_key", &"[REDACTED]")
             .field("s3_region", &self.s3_region)
+            .field("backup_storage_dir", &self.backup_storage_dir)
+            .field("backup_s3_bucket", &self.backup_s3_bucket)
             .field("cors_origins", &self.cors_origins)
+            .field("api_deprecated_versions", &self.api_deprecated_versions)
             .field("trust_proxy", &self.trust_proxy)
             .field("max_requests_per_minute", &self.max_requests_per_minute)
+            .field("rate_limit_per_user", &self.rate_limit_per_user)
+            .field("rate_limit_routes", &self.rate_limit_routes)
+            .field("rate_limit_redis", &self.rate_limit_redis)
             .field("max_ws_connections_per_user", &self.max_ws_connections_per_user)
             .field("broadcast_channel_capacity", &self.broadcast_channel_capacity)
             .field("ws_heartbeat_timeout_secs", &self.ws_heartbeat_timeout_secs)
+            .field("ws_heartbeat_interval_secs", &self.ws_heartbeat_interval_secs)
             .field("ws_session_buffer_size", &self.ws_session_buffer_size)
             .field("ws_session_ttl_secs", &self.ws_session_ttl_secs)
+            .field("ws_coalesce_window_ms", &self.ws_coalesce_window_ms)
             .field("max_upload_size_bytes", &self.max_upload_size_bytes)
+            .field("profile_media_quota_bytes", &self.profile_media_quota_bytes)
+            .field("voice_note_max_duration_secs", &self.voice_note_max_duration_secs)
+            .field("live_location_max_duration_secs", &self.live_location_max_duration_secs)
+            .field("server_storage_quota_bytes", &self.server_storage_quota_bytes)
+            .field("server_message_rate_per_minute", &self.server_message_rate_per_minute)
+            .field("server_max_members", &self.server_max_members)
+            .field("max_group_dm_members", &self.max_group_dm_members)
+            .field("prekey_low_watermark", &self.prekey_low_watermark)
+            .field("event_reminder_minutes", &self.event_reminder_minutes)
+            .field("interactive_timeout_secs", &self.interactive_timeout_secs)
+            .field("job_timeout_secs", &self.job_timeout_secs)
+            .field("shutdown_drain_secs", &self.shutdown_drain_secs)
+            .field("shadow_read_sample_rate", &self.shadow_read_sample_rate)
+            .field("thumbnails_enabled", &self.thumbnails_enabled)
             .field("cdn_enabled", &self.cdn_enabled)
             .field("cdn_base_url", &self.cdn_base_url)
             .field("cdn_presign_expiry_secs", &self.cdn_presign_expiry_secs)
@@ -852,26 +1824,64 @@ impl fmt::Debug for AppConfig {
             .field("livekit_api_secret", &"[REDACTED]")
             .field("livekit_bundled", &self.livekit_bundled)
             .field("livekit_port", &self.livekit_port)
+            .field("turn_urls", &self.turn_urls)
+            .field("turn_secret", &"[REDACTED]")
+            .field("turn_credential_ttl_secs", &self.turn_credential_ttl_secs)
+            .field("federation_server_name", &self.federation_server_name)
+            .field("federation_signing_key", &"[REDACTED]")
+            .field("federation_allowlist", &self.federation_allowlist)
+            .field("federation_denylist", &self.federation_denylist)
+            .field("federation_user_cache_ttl_secs", &self.federation_user_cache_ttl_secs)
+            .field("federation_key_cache_ttl_secs", &self.federation_key_cache_ttl_secs)
+            .field("irc_port", &self.irc_port)
             .field("tls_enabled", &self.tls_enabled)
             .field("tls_port", &self.tls_port)
             .field("tls_cert_path", &self.tls_cert_path)
             .field("tls_key_path", &self.tls_key_path)
             .field("tls_auto_generate", &self.tls_auto_generate)
             .field("audit_log_retention_days", &self.audit_log_retention_days)
+            .field("message_partition_months_ahead", &self.message_partition_months_ahead)
+            .field("message_partition_retention_months", &self.message_partition_retention_months)
             .field("resolved_report_retention_days", &self.resolved_report_retention_days)
+            .field("sync_journal_retention_days", &self.sync_journal_retention_days)
+            .field("device_queue_ttl_days", &self.device_queue_ttl_days)
             .field("expired_invite_cleanup", &self.expired_invite_cleanup)
+            .field("attachment_gc_grace_hours", &self.attachment_gc_grace_hours)
+            .field("attachment_gc_dry_run", &self.attachment_gc_dry_run)
+            .field("attachment_scanner", &self.attachment_scanner)
+            .field("attachment_scanner_url", &self.attachment_scanner_url)
+            .field("attachment_scan_timeout_secs", &self.attachment_scan_timeout_secs)
+            .field("attachment_scan_fail_open", &self.attachment_scan_fail_open)
+            .field("attachment_url_signing_keys", &"[REDACTED]")
+            .field("attachment_url_ttl_secs", &self.attachment_url_ttl_secs)
+            .field("attachment_url_base", &self.attachment_url_base)
+            .field("telemetry_endpoint", &self.telemetry_endpoint)
+            .field("telemetry_token", &"[REDACTED]")
+            .field("telemetry_instance_id", &self.telemetry_instance_id)
             .field("registration_invite_only", &self.registration_invite_only)
+            .field("registration_mode", &self.registration_mode)
             .field("registration_invites_per_user", &self.registration_invites_per_user)
             .field("giphy_api_key", &"[REDACTED]")
             .field("turnstile_site_key", &self.turnstile_site_key)
             .field("turnstile_secret_key", &"[REDACTED]")
+            .field("abuse_ip_lists", &self.abuse_ip_lists)
+            .field("abuse_block_score", &self.abuse_block_score)
+            .field("abuse_ip_retention_hours", &self.abuse_ip_retention_hours)
             .field("smtp_host", &self.smtp_host)
             .field("smtp_port", &self.smtp_port)
             .field("smtp_username", &self.smtp_username)
             .field("smtp_password", &"[REDACTED]")
             .field("smtp_from", &self.smtp_from)
+            .field("email_provider", &self.email_provider)
+            .field("email_api_key", &"[REDACTED]")
+            .field("email_domain", &self.email_domain)
+            .field("email_api_url", &self.email_api_url)
+            .field("email_template_dir", &self.email_template_dir)
             .field("beta_code_limit", &self.beta_code_limit)
             .field("beta_code_expiry_days", &self.beta_code_expiry_days)
+            .field("beta_block_disposable_emails", &self.beta_block_disposable_emails)
+            .field("beta_blocked_email_domains", &self.beta_blocked_email_domains)
+            .field("beta_require_mx", &self.beta_require_mx)
             .finish()
     }
 }
@@ -957,4 +1967,31 @@ mod tests {
         assert_eq!(config.host, config2.host);
         assert_eq!(config.port, config2.port);
     }
+
+    #[test]
+    fn reload_only_applies_reloadable_fields() {
+        let live = LiveConfig::new(AppConfig::test_default());
+        let mut fresh = AppConfig::test_default();
+        fresh.beta_code_limit = 7;
+        fresh.registration_invite_only = true;
+        fresh.port = 9999;
+        assert_eq!(live.apply(&fresh), vec!["beta_code_limit", "registration_invite_only"]);
+        let current = live.get();
+        assert_eq!(current.beta_code_limit, 7);
+        assert!(current.registration_invite_only);
+        assert_eq!(current.port, 0);
+        assert!(live.apply(&fresh).is_empty());
+    }
+
+    #[test]
+    fn invite_only_flag_still_gates_open_registration() {
+        let mut config = AppConfig::test_default();
+        assert_eq!(config.effective_registration_mode(), RegistrationMode::Open);
+        config.registration_invite_only = true;
+        assert_eq!(config.effective_registration_mode(), RegistrationMode::Invite);
+        config.registration_mode = "approval".into();
+        assert_eq!(config.effective_registration_mode(), RegistrationMode::Approval);
+        config.registration_mode = "closed".into();
+        assert_eq!(config.effective_registration_mode(), RegistrationMode::Closed);
+    }
 }
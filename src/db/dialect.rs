@@ -0,0 +1,63 @@
+//! SQL fragments that differ between the Postgres and SQLite backends.
+//!
+//! Queries are written once and splice these in where the dialects part
+//! ways: the current time, relative timestamps and lists of ids (SQLite has
+//! no array binds, so `= ANY($1)` becomes an `IN` list bound value by
+//! value) and row locks for claiming work. SQLite stores timestamps as RFC 3339 text, the format sqlx
+//! encodes `DateTime<Utc>` with, so comparisons against these stay ordered.
+
+/// The current time.
+#[cfg(feature = "postgres")]
+pub const NOW: &str = "NOW()";
+#[cfg(feature = "sqlite")]
+pub const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+/// The time `interval` ago, e.g. `ago("1 hour")` or `ago("30 days")`.
+#[cfg(feature = "postgres")]
+pub fn ago(interval: &str) -> String {
+    format!("NOW() - INTERVAL '{}'", interval)
+}
+
+#[cfg(feature = "sqlite")]
+pub fn ago(interval: &str) -> String {
+    format!("strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-{}')", interval)
+}
+
+/// Row lock for claiming queued work without blocking other claimers.
+/// SQLite has a single writer, so a claim never races another and needs none.
+#[cfg(feature = "postgres")]
+pub const SKIP_LOCKED: &str = "FOR UPDATE SKIP LOCKED";
+#[cfg(feature = "sqlite")]
+pub const SKIP_LOCKED: &str = "";
+
+/// `$first, $first+1, ...` for `count` values bound one at a time.
+pub fn placeholders(first: usize, count: usize) -> String {
+    (first..first + count)
+        .map(|i| format!("${}", i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_number_from_first() {
+        assert_eq!(placeholders(1, 3), "$1, $2, $3");
+        assert_eq!(placeholders(2, 1), "$2");
+        assert_eq!(placeholders(1, 0), "");
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn ago_is_an_interval_before_now() {
+        assert_eq!(ago("1 hour"), "NOW() - INTERVAL '1 hour'");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn ago_is_a_date_modifier() {
+        assert_eq!(ago("30 days"), "strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-30 days')");
+    }
+}
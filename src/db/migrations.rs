@@ -0,0 +1,220 @@
+//! Embedded schema migrations and the pre-flight check run before serving.
+//!
+//! The migrations are compiled into the binary. At startup [`prepare`]
+//! compares them with those recorded in the database's `_sqlx_migrations`
+//! table before any traffic is served: a migration that failed partway, one
+//! edited since it was applied, or one this build doesn't know (a newer
+//! release ran against the database) stops the server with an explanation.
+//! Pending migrations are applied when `DB_AUTO_MIGRATE` is on (the default);
+//! with it off the server refuses to start and lists them, so they can be
+//! run as a separate deploy step instead of failing mid-request.
+
+use std::fmt;
+
+use sqlx::migrate::{Migrate, Migrator};
+
+use crate::db::Pool;
+
+#[cfg(feature = "postgres")]
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[cfg(feature = "sqlite")]
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+/// How the database's schema compares with the migrations of this build.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SchemaStatus {
+    /// Embedded migrations not yet applied, oldest first.
+    pub pending: Vec<(i64, String)>,
+    /// Applied migrations whose file has changed since.
+    pub modified: Vec<(i64, String)>,
+    /// Applied migrations this build doesn't include.
+    pub unknown: Vec<i64>,
+    /// A migration that started but didn't finish.
+    pub dirty: Option<i64>,
+}
+
+/// Why the server can't start on this database.
+#[derive(Debug)]
+pub enum SchemaError {
+    Database(String),
+    Incompatible(SchemaStatus),
+    Pending(Vec<(i64, String)>),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Database(e) => write!(f, "Could not check database migrations: {}", e),
+            SchemaError::Incompatible(status) => {
+                writeln!(f, "The database schema doesn't match this build of Haven:")?;
+                if let Some(version) = status.dirty {
+                    writeln!(
+                        f,
+                        "  migration {} failed partway; repair the schema and delete its row from _sqlx_migrations",
+                        version
+                    )?;
+                }
+                for (version, description) in &status.modified {
+                    writeln!(
+                        f,
+                        "  migration {} ({}) was changed after it was applied",
+                        version, description
+                    )?;
+                }
+                if !status.unknown.is_empty() {
+                    let versions: Vec<String> = status.unknown.iter().map(i64::to_string).collect();
+                    writeln!(
+                        f,
+                        "  applied migrations {} are newer than this build; run a release that includes them",
+                        versions.join(", ")
+                    )?;
+                }
+                Ok(())
+            }
+            SchemaError::Pending(pending) => {
+                writeln!(
+                    f,
+                    "{} pending database migration{} and DB_AUTO_MIGRATE is off:",
+                    pending.len(),
+                    if pending.len() == 1 { "" } else { "s" }
+                )?;
+                for (version, description) in pending {
+                    writeln!(f, "  {} {}", version, description)?;
+                }
+                write!(
+                    f,
+                    "Apply them with `sqlx migrate run` or start once with DB_AUTO_MIGRATE=true."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Check the schema of `pool` against this build, applying pending
+/// migrations if `auto_migrate`. Returns how many were applied.
+pub async fn prepare(pool: &Pool, auto_migrate: bool) -> Result<usize, SchemaError> {
+    let status = inspect(pool).await?;
+    if status.dirty.is_some() || !status.modified.is_empty() || !status.unknown.is_empty() {
+        return Err(SchemaError::Incompatible(status));
+    }
+    if status.pending.is_empty() {
+        return Ok(0);
+    }
+    if !auto_migrate {
+        return Err(SchemaError::Pending(status.pending));
+    }
+    for (version, description) in &status.pending {
+        tracing::info!("Applying migration {} {}", version, description);
+    }
+    MIGRATOR
+        .run(pool)
+        .await
+        .map_err(|e| SchemaError::Database(e.to_string()))?;
+    Ok(status.pending.len())
+}
+
+/// Compare the migrations recorded in the database with this build's.
+pub async fn inspect(pool: &Pool) -> Result<SchemaStatus, SchemaError> {
+    let database = |e: sqlx::migrate::MigrateError| SchemaError::Database(e.to_string());
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| SchemaError::Database(e.to_string()))?;
+    conn.ensure_migrations_table().await.map_err(database)?;
+    let dirty = conn.dirty_version().await.map_err(database)?;
+    let applied: Vec<(i64, Vec<u8>)> = conn
+        .list_applied_migrations()
+        .await
+        .map_err(database)?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+    let embedded: Vec<(i64, &str, &[u8])> = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| (m.version, m.description.as_ref(), m.checksum.as_ref()))
+        .collect();
+    Ok(compare(&embedded, &applied, dirty))
+}
+
+fn compare(
+    embedded: &[(i64, &str, &[u8])],
+    applied: &[(i64, Vec<u8>)],
+    dirty: Option<i64>,
+) -> SchemaStatus {
+    let mut status = SchemaStatus {
+        dirty,
+        ..Default::default()
+    };
+    for &(version, description, checksum) in embedded {
+        match applied.iter().find(|(v, _)| *v == version) {
+            None => status.pending.push((version, description.to_string())),
+            Some((_, applied_checksum)) if applied_checksum.as_slice() != checksum => {
+                status.modified.push((version, description.to_string()))
+            }
+            Some(_) => {}
+        }
+    }
+    status.unknown = applied
+        .iter()
+        .map(|(v, _)| *v)
+        .filter(|v| !embedded.iter().any(|(e, _, _)| e == v))
+        .collect();
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMBEDDED: &[(i64, &str, &[u8])] = &[
+        (1, "users", b"a"),
+        (2, "servers", b"b"),
+        (3, "channels", b"c"),
+    ];
+
+    #[test]
+    fn compare_finds_pending_modified_and_unknown() {
+        let up_to_date = compare(
+            EMBEDDED,
+            &[(1, b"a".to_vec()), (2, b"b".to_vec()), (3, b"c".to_vec())],
+            None,
+        );
+        assert_eq!(up_to_date, SchemaStatus::default());
+
+        let behind = compare(EMBEDDED, &[(1, b"a".to_vec())], None);
+        assert_eq!(
+            behind.pending,
+            [(2, "servers".to_string()), (3, "channels".to_string())]
+        );
+        assert!(behind.modified.is_empty() && behind.unknown.is_empty());
+
+        let drifted = compare(
+            EMBEDDED,
+            &[(1, b"a".to_vec()), (2, b"x".to_vec()), (4, b"d".to_vec())],
+            Some(4),
+        );
+        assert_eq!(drifted.pending, [(3, "channels".to_string())]);
+        assert_eq!(drifted.modified, [(2, "servers".to_string())]);
+        assert_eq!(drifted.unknown, [4]);
+        assert_eq!(drifted.dirty, Some(4));
+    }
+
+    #[test]
+    fn pending_error_lists_each_migration() {
+        let message =
+            SchemaError::Pending(vec![(20260428000001, "user activity".into())]).to_string();
+        assert!(message.starts_with("1 pending database migration and DB_AUTO_MIGRATE is off:"));
+        assert!(message.contains("  20260428000001 user activity\n"));
+    }
+
+    #[test]
+    fn embedded_migrations_are_ordered_and_unique() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert!(!versions.is_empty());
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
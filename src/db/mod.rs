@@ -1,4 +1,15 @@
+pub mod dialect;
+pub mod migrations;
+pub mod pool_monitor;
 pub mod queries;
+pub mod shadow;
+pub mod slow_queries;
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
 
 use crate::config::AppConfig;
 
@@ -19,31 +30,111 @@ pub type Pool = sqlx::PgPool;
 #[cfg(feature = "sqlite")]
 pub type Pool = sqlx::SqlitePool;
 
-/// Wraps primary and optional replica database pools.
-/// Read queries go to the replica (if available), writes always go to primary.
+/// A single connection (e.g. `&mut *tx` inside a transaction).
+#[cfg(feature = "postgres")]
+pub type Connection = sqlx::PgConnection;
+
+#[cfg(feature = "sqlite")]
+pub type Connection = sqlx::SqliteConnection;
+
+/// Replica selection policy (`DB_REPLICA_STRATEGY`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaStrategy {
+    /// Rotate through healthy replicas.
+    RoundRobin,
+    /// Always pick the healthy replica with the smallest replication lag.
+    LeastLag,
+}
+
+impl ReplicaStrategy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "round-robin" => Some(Self::RoundRobin),
+            "least-lag" => Some(Self::LeastLag),
+            _ => None,
+        }
+    }
+}
+
+/// How stale a read is allowed to be. Most reads take whatever replica the
+/// strategy picks; reads that must observe a write made moments earlier in
+/// the same request ask for `Fresh`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    /// Any healthy replica within `DB_REPLICA_MAX_LAG_SECS`.
+    Any,
+    /// A healthy replica no further behind than this, else the primary.
+    Within(Duration),
+    /// The primary only (read-your-writes).
+    Fresh,
+}
+
+/// One read replica with the health recorded by the last probe.
+struct Replica {
+    pool: Pool,
+    healthy: AtomicBool,
+    /// Replication lag in milliseconds at the last probe.
+    lag_ms: AtomicU64,
+}
+
+impl Replica {
+    fn usable(&self, max_lag: Option<Duration>) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+            && match max_lag {
+                Some(max) => self.lag_ms.load(Ordering::Relaxed) <= max.as_millis() as u64,
+                None => true,
+            }
+    }
+}
+
+/// Health of one replica, as reported by `DbPools::replica_status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaStatus {
+    pub index: usize,
+    pub healthy: bool,
+    pub lag_ms: u64,
+}
+
+/// How often replicas are probed for reachability and lag.
+const REPLICA_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const REPLICA_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Seconds this server is behind its primary; 0 when fully caught up (an idle
+/// primary writes nothing, so the last replay timestamp alone would overstate it).
+#[cfg(feature = "postgres")]
+const REPLICA_LAG_SQL: &str = "SELECT COALESCE(CASE \
+        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+        ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()) \
+    END, 0)::float8";
+
+/// Wraps the primary and any number of read replica pools.
+/// Reads go to a healthy replica picked by the configured strategy, falling
+/// back to the primary when none qualifies; writes always go to the primary.
 #[derive(Clone)]
 pub struct DbPools {
     primary: Pool,
-    replica: Option<Pool>,
+    replicas: Arc<Vec<Replica>>,
+    strategy: ReplicaStrategy,
+    /// Replicas lagging further than this are skipped; `None` = no cutoff.
+    max_lag: Option<Duration>,
+    next: Arc<AtomicUsize>,
 }
 
 impl DbPools {
-    /// Initialize pools from config. Runs migrations on the primary.
+    /// Initialize pools from config. Checks the schema of the primary and,
+    /// with `db_auto_migrate`, applies pending migrations.
     pub async fn init(config: &AppConfig) -> Self {
         #[cfg(feature = "postgres")]
         let primary = {
+            let options = connect_options(&config.database_url, config);
             let pool = sqlx::postgres::PgPoolOptions::new()
                 .max_connections(config.db_max_connections)
-                .connect(&config.database_url)
+                .connect_with(options)
                 .await
                 .expect("Failed to connect to PostgreSQL (primary)");
 
-            sqlx::migrate!("./migrations")
-                .run(&pool)
-                .await
-                .expect("Failed to run database migrations");
-
-            tracing::info!("PostgreSQL connected and migrations applied");
+            prepare_schema(&pool, config).await;
+            tracing::info!("PostgreSQL connected and schema up to date");
             pool
         };
 
@@ -56,9 +147,10 @@ impl DbPools {
                 }
             }
 
+            let options = connect_options(&config.database_url, config);
             let pool = sqlx::sqlite::SqlitePoolOptions::new()
                 .max_connections(config.db_max_connections)
-                .connect(&config.database_url)
+                .connect_with(options)
                 .await
                 .expect("Failed to connect to SQLite");
 
@@ -72,38 +164,79 @@ impl DbPools {
                 .await
                 .ok();
 
-            sqlx::migrate!("./migrations_sqlite")
-                .run(&pool)
-                .await
-                .expect("Failed to run database migrations");
-
-            tracing::info!("SQLite connected and migrations applied");
+            prepare_schema(&pool, config).await;
+            tracing::info!("SQLite connected and schema up to date");
             pool
         };
 
-        // Replica support (PostgreSQL only)
+        // Replica support (PostgreSQL only). Replicas connect lazily so one
+        // that is down at startup is failed over instead of blocking boot.
         #[cfg(feature = "postgres")]
-        let replica = if config.database_replica_url.is_empty() {
-            None
-        } else {
-            let pool = sqlx::postgres::PgPoolOptions::new()
-                .max_connections(config.db_max_connections)
-                .connect(&config.database_replica_url)
-                .await
-                .expect("Failed to connect to PostgreSQL (replica)");
-            tracing::info!("Read replica connected");
-            Some(pool)
-        };
+        let replicas: Vec<Replica> = config
+            .database_replica_url
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| Replica {
+                pool: sqlx::postgres::PgPoolOptions::new()
+                    .max_connections(config.db_max_connections)
+                    .acquire_timeout(REPLICA_CHECK_TIMEOUT)
+                    .connect_lazy_with(connect_options(url, config)),
+                healthy: AtomicBool::new(false),
+                lag_ms: AtomicU64::new(0),
+            })
+            .collect();
 
         #[cfg(feature = "sqlite")]
-        let replica: Option<Pool> = None; // SQLite doesn't support replicas
+        let replicas: Vec<Replica> = Vec::new(); // SQLite doesn't support replicas
 
-        Self { primary, replica }
+        let pools = Self {
+            primary,
+            replicas: Arc::new(replicas),
+            strategy: ReplicaStrategy::parse(&config.db_replica_strategy).unwrap_or(ReplicaStrategy::RoundRobin),
+            max_lag: (config.db_replica_max_lag_secs > 0)
+                .then(|| Duration::from_secs(config.db_replica_max_lag_secs)),
+            next: Arc::new(AtomicUsize::new(0)),
+        };
+        if !pools.replicas.is_empty() {
+            pools.check_replicas().await;
+            tracing::info!(
+                "{} read replica(s) configured ({:?}), {} healthy",
+                pools.replicas.len(),
+                pools.strategy,
+                pools.replica_status().iter().filter(|r| r.healthy).count(),
+            );
+        }
+        pools
     }
 
-    /// Read queries — returns replica if available, else primary.
+    /// Read queries — a replica picked by the configured strategy, else primary.
     pub fn read(&self) -> &Pool {
-        self.replica.as_ref().unwrap_or(&self.primary)
+        self.read_with(Staleness::Any)
+    }
+
+    /// Read queries with an explicit staleness bound.
+    pub fn read_with(&self, staleness: Staleness) -> &Pool {
+        let max_lag = match staleness {
+            Staleness::Fresh => return &self.primary,
+            Staleness::Within(bound) => Some(self.max_lag.map_or(bound, |max| max.min(bound))),
+            Staleness::Any => self.max_lag,
+        };
+        let replicas = &self.replicas;
+        let picked = match self.strategy {
+            ReplicaStrategy::RoundRobin if !replicas.is_empty() => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..replicas.len())
+                    .map(|i| &replicas[(start + i) % replicas.len()])
+                    .find(|r| r.usable(max_lag))
+            }
+            ReplicaStrategy::RoundRobin => None,
+            ReplicaStrategy::LeastLag => replicas
+                .iter()
+                .filter(|r| r.usable(max_lag))
+                .min_by_key(|r| r.lag_ms.load(Ordering::Relaxed)),
+        };
+        picked.map_or(&self.primary, |r| &r.pool)
     }
 
     /// Write queries — always primary.
@@ -116,40 +249,200 @@ impl DbPools {
         &self.primary
     }
 
-    /// Single-pool constructor for tests (no replica).
-    #[cfg(feature = "postgres")]
-    pub fn from_single(pool: Pool) -> Self {
-        Self {
-            primary: pool,
-            replica: None,
+    /// Every configured read replica, healthy or not.
+    pub fn replicas(&self) -> impl Iterator<Item = &Pool> {
+        self.replicas.iter().map(|r| &r.pool)
+    }
+
+    /// Close every pool, waiting for checked-out connections (in-flight
+    /// transactions) to be returned.
+    pub async fn close(&self) {
+        self.primary.close().await;
+        for replica in self.replicas.iter() {
+            replica.pool.close().await;
+        }
+    }
+
+    pub fn replica_status(&self) -> Vec<ReplicaStatus> {
+        self.replicas
+            .iter()
+            .enumerate()
+            .map(|(index, r)| ReplicaStatus {
+                index,
+                healthy: r.healthy.load(Ordering::Relaxed),
+                lag_ms: r.lag_ms.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Probe every replica once, updating its health and lag. A replica that
+    /// errors or times out stops receiving reads until a later probe succeeds.
+    pub async fn check_replicas(&self) {
+        for (index, replica) in self.replicas.iter().enumerate() {
+            let result = tokio::time::timeout(REPLICA_CHECK_TIMEOUT, replica_lag(&replica.pool)).await;
+            let was_healthy = replica.healthy.load(Ordering::Relaxed);
+            match result {
+                Ok(Ok(lag)) => {
+                    replica.lag_ms.store(lag.as_millis() as u64, Ordering::Relaxed);
+                    replica.healthy.store(true, Ordering::Relaxed);
+                    if !was_healthy {
+                        tracing::info!("Read replica {} is healthy (lag {}ms)", index, lag.as_millis());
+                    }
+                }
+                Ok(Err(e)) => {
+                    replica.healthy.store(false, Ordering::Relaxed);
+                    if was_healthy {
+                        tracing::warn!("Read replica {} failed its health check, reads fail over: {}", index, e);
+                    }
+                }
+                Err(_) => {
+                    replica.healthy.store(false, Ordering::Relaxed);
+                    if was_healthy {
+                        tracing::warn!("Read replica {} health check timed out, reads fail over", index);
+                    }
+                }
+            }
         }
     }
 
     /// Single-pool constructor for tests (no replica).
-    #[cfg(feature = "sqlite")]
     pub fn from_single(pool: Pool) -> Self {
+        Self::with_replicas(pool, Vec::new(), ReplicaStrategy::RoundRobin)
+    }
+
+    /// Pools with pre-built replicas, all initially healthy and caught up.
+    fn with_replicas(primary: Pool, replicas: Vec<Pool>, strategy: ReplicaStrategy) -> Self {
         Self {
-            primary: pool,
-            replica: None,
+            primary,
+            replicas: Arc::new(
+                replicas
+                    .into_iter()
+                    .map(|pool| Replica {
+                        pool,
+                        healthy: AtomicBool::new(true),
+                        lag_ms: AtomicU64::new(0),
+                    })
+                    .collect(),
+            ),
+            strategy,
+            max_lag: None,
+            next: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
 
+#[cfg(feature = "postgres")]
+async fn replica_lag(pool: &Pool) -> Result<Duration, sqlx::Error> {
+    let secs: f64 = sqlx::query_scalar(REPLICA_LAG_SQL).fetch_one(pool).await?;
+    Ok(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+#[cfg(feature = "sqlite")]
+async fn replica_lag(_pool: &Pool) -> Result<Duration, sqlx::Error> {
+    Ok(Duration::ZERO)
+}
+
+/// Re-probe replicas in the background so reads fail over to the primary
+/// (and come back) without a restart.
+pub fn spawn_replica_monitor(pools: DbPools) {
+    if pools.replicas.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REPLICA_CHECK_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            pools.check_replicas().await;
+        }
+    });
+}
+
+/// Connect options for `url` with the slow statement threshold applied.
+#[cfg(feature = "postgres")]
+fn connect_options(url: &str, config: &AppConfig) -> sqlx::postgres::PgConnectOptions {
+    let options = url.parse().expect("Invalid PostgreSQL connection URL");
+    slow_queries::configure(options, config.db_slow_query_ms)
+}
+
+#[cfg(feature = "sqlite")]
+fn connect_options(url: &str, config: &AppConfig) -> sqlx::sqlite::SqliteConnectOptions {
+    let options = url.parse().expect("Invalid SQLite connection URL");
+    slow_queries::configure(options, config.db_slow_query_ms)
+}
+
+/// Refuse to start on a schema this build can't serve, rather than failing
+/// mid-request. See [`migrations::prepare`].
+async fn prepare_schema(pool: &Pool, config: &AppConfig) {
+    match migrations::prepare(pool, config.db_auto_migrate).await {
+        Ok(0) => {}
+        Ok(applied) => tracing::info!("Applied {} database migration(s)", applied),
+        Err(e) => panic!("{}", e),
+    }
+}
+
 /// Initialize a single database connection pool and run migrations.
 /// Kept for backward compatibility; prefer `DbPools::init` for production.
 #[cfg(feature = "postgres")]
 pub async fn init_pool(config: &AppConfig) -> Pool {
     let pool = sqlx::postgres::PgPoolOptions::new()
         .max_connections(config.db_max_connections)
-        .connect(&config.database_url)
+        .connect_with(connect_options(&config.database_url, config))
         .await
         .expect("Failed to connect to PostgreSQL");
 
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("Failed to run database migrations");
-
-    tracing::info!("Database connected and migrations applied");
+    prepare_schema(&pool, config).await;
+    tracing::info!("Database connected and schema up to date");
     pool
 }
+
+#[cfg(all(test, feature = "postgres"))]
+mod tests {
+    use super::*;
+
+    fn lazy_pool() -> Pool {
+        sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/haven")
+            .unwrap()
+    }
+
+    fn set_replica(pools: &DbPools, index: usize, healthy: bool, lag_ms: u64) {
+        pools.replicas[index].healthy.store(healthy, Ordering::Relaxed);
+        pools.replicas[index].lag_ms.store(lag_ms, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn round_robin_skips_unhealthy_and_fails_over_to_primary() {
+        let pools = DbPools::with_replicas(lazy_pool(), vec![lazy_pool(), lazy_pool()], ReplicaStrategy::RoundRobin);
+        let (r0, r1) = (&pools.replicas[0].pool, &pools.replicas[1].pool);
+        assert!(std::ptr::eq(pools.read(), r0));
+        assert!(std::ptr::eq(pools.read(), r1));
+        assert!(std::ptr::eq(pools.read(), r0));
+
+        set_replica(&pools, 0, false, 0);
+        assert!(std::ptr::eq(pools.read(), r1));
+        assert!(std::ptr::eq(pools.read(), r1));
+
+        set_replica(&pools, 1, false, 0);
+        assert!(std::ptr::eq(pools.read(), pools.primary()));
+    }
+
+    #[tokio::test]
+    async fn least_lag_and_staleness_bounds() {
+        let pools = DbPools::with_replicas(lazy_pool(), vec![lazy_pool(), lazy_pool()], ReplicaStrategy::LeastLag);
+        set_replica(&pools, 0, true, 4_000);
+        set_replica(&pools, 1, true, 500);
+        assert!(std::ptr::eq(pools.read(), &pools.replicas[1].pool));
+
+        assert!(std::ptr::eq(pools.read_with(Staleness::Within(Duration::from_secs(1))), &pools.replicas[1].pool));
+        assert!(std::ptr::eq(pools.read_with(Staleness::Within(Duration::from_millis(100))), pools.primary()));
+        assert!(std::ptr::eq(pools.read_with(Staleness::Fresh), pools.primary()));
+    }
+
+    #[test]
+    fn strategy_names() {
+        assert_eq!(ReplicaStrategy::parse("round-robin"), Some(ReplicaStrategy::RoundRobin));
+        assert_eq!(ReplicaStrategy::parse("least-lag"), Some(ReplicaStrategy::LeastLag));
+        assert_eq!(ReplicaStrategy::parse("random"), None);
+    }
+}
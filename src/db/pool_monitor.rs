@@ -0,0 +1,282 @@
+//! Connection pool metrics and the load-shedding breaker.
+//!
+//! sqlx doesn't report how long callers wait for a connection, so a sampler
+//! acquires one from the primary every second and times it: when the pool is
+//! saturated the probe queues behind requests like any other caller. Once that
+//! wait reaches `DB_POOL_SATURATION_MS` the breaker opens and API requests get
+//! a 503 with `Retry-After` instead of hanging on acquire. It closes when a
+//! probe acquires under the threshold again, no sooner than the cooldown.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{DbPools, Pool};
+use crate::models::{DbAcquireLatency, DbPoolGauges};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Acquire waits kept for the latency percentiles (a minute of samples).
+const ACQUIRE_SAMPLES: usize = 60;
+/// Probe timeout when the breaker is disabled; the wait is still reported.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Minimum time the breaker stays open, also sent as `Retry-After`.
+pub const BREAKER_COOLDOWN: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct PoolMonitor {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Acquire wait that opens the breaker; zero disables it.
+    saturation: Duration,
+    open: AtomicBool,
+    opened_at: Mutex<Option<Instant>>,
+    trips: AtomicU64,
+    shed: AtomicU64,
+    in_flight: AtomicU64,
+    acquire_ms: Mutex<VecDeque<u64>>,
+}
+
+/// Counts a request as in flight until dropped.
+pub struct InFlight(Arc<Inner>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl PoolMonitor {
+    /// `saturation` of zero never opens the breaker.
+    pub fn new(saturation: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                saturation,
+                open: AtomicBool::new(false),
+                opened_at: Mutex::new(None),
+                trips: AtomicU64::new(0),
+                shed: AtomicU64::new(0),
+                in_flight: AtomicU64::new(0),
+                acquire_ms: Mutex::new(VecDeque::with_capacity(ACQUIRE_SAMPLES)),
+            }),
+        }
+    }
+
+    pub fn saturation(&self) -> Duration {
+        self.inner.saturation
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.inner.open.load(Ordering::Relaxed)
+    }
+
+    pub fn trips(&self) -> u64 {
+        self.inner.trips.load(Ordering::Relaxed)
+    }
+
+    pub fn shed(&self) -> u64 {
+        self.inner.shed.load(Ordering::Relaxed)
+    }
+
+    pub fn record_shed(&self) {
+        self.inner.shed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn in_flight(&self) -> u64 {
+        self.inner.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn track(&self) -> InFlight {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.inner.clone())
+    }
+
+    /// Requests in flight beyond the connections in use, while none is idle.
+    /// sqlx doesn't expose its wait queue, so this is an estimate.
+    pub fn waiting(&self, primary: &DbPoolGauges) -> u64 {
+        if primary.idle > 0 {
+            return 0;
+        }
+        self.in_flight().saturating_sub(primary.in_use as u64)
+    }
+
+    fn probe_timeout(&self) -> Duration {
+        if self.inner.saturation.is_zero() {
+            PROBE_TIMEOUT
+        } else {
+            self.inner.saturation
+        }
+    }
+
+    /// Record one probe's acquire wait and open or close the breaker.
+    pub fn observe(&self, wait: Duration, now: Instant) {
+        {
+            let mut samples = self.inner.acquire_ms.lock().unwrap();
+            if samples.len() == ACQUIRE_SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back(wait.as_millis() as u64);
+        }
+        if self.inner.saturation.is_zero() {
+            return;
+        }
+        let saturated = wait >= self.inner.saturation;
+        let mut opened_at = self.inner.opened_at.lock().unwrap();
+        match *opened_at {
+            None if saturated => {
+                *opened_at = Some(now);
+                self.inner.open.store(true, Ordering::Relaxed);
+                self.inner.trips.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    "Database pool saturated (acquire waited {:?}); shedding API requests",
+                    wait
+                );
+            }
+            Some(since) if !saturated && now.duration_since(since) >= BREAKER_COOLDOWN => {
+                *opened_at = None;
+                self.inner.open.store(false, Ordering::Relaxed);
+                tracing::info!(
+                    "Database pool recovered after {:?}; no longer shedding requests",
+                    now.duration_since(since)
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Acquire waits over the last minute of samples.
+    pub fn acquire_latency(&self) -> DbAcquireLatency {
+        let samples = self.inner.acquire_ms.lock().unwrap();
+        let Some(&last_ms) = samples.back() else {
+            return DbAcquireLatency::default();
+        };
+        let mut sorted: Vec<u64> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+        DbAcquireLatency {
+            last_ms,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+pub fn gauges(pool: &Pool) -> DbPoolGauges {
+    let size = pool.size();
+    let idle = (pool.num_idle() as u32).min(size);
+    DbPoolGauges {
+        size,
+        idle,
+        in_use: size - idle,
+        max_connections: pool.options().get_max_connections(),
+    }
+}
+
+/// Time one connection acquire. A timeout or acquire error (the database is
+/// unreachable) counts as the full timeout.
+async fn probe_acquire(pool: &Pool, timeout: Duration) -> Duration {
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, pool.acquire()).await {
+        Ok(Ok(_conn)) => started.elapsed(),
+        _ => timeout.max(started.elapsed()),
+    }
+}
+
+/// Sample the primary pool every second for the rest of the process.
+pub fn spawn_pool_monitor(pools: DbPools, monitor: PoolMonitor) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let wait = probe_acquire(pools.primary(), monitor.probe_timeout()).await;
+            monitor.observe(wait, Instant::now());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn breaker_opens_on_slow_acquire_and_closes_after_cooldown() {
+        let monitor = PoolMonitor::new(100 * MS);
+        let start = Instant::now();
+
+        monitor.observe(5 * MS, start);
+        assert!(!monitor.is_open());
+
+        monitor.observe(100 * MS, start);
+        assert!(monitor.is_open());
+        assert_eq!(monitor.trips(), 1);
+
+        // Healthy again, but still within the cooldown
+        monitor.observe(5 * MS, start + BREAKER_COOLDOWN / 2);
+        assert!(monitor.is_open());
+
+        // Still saturated after the cooldown: stays open without a second trip
+        monitor.observe(200 * MS, start + BREAKER_COOLDOWN);
+        assert!(monitor.is_open());
+        assert_eq!(monitor.trips(), 1);
+
+        monitor.observe(5 * MS, start + BREAKER_COOLDOWN);
+        assert!(!monitor.is_open());
+    }
+
+    #[test]
+    fn zero_saturation_never_opens() {
+        let monitor = PoolMonitor::new(Duration::ZERO);
+        monitor.observe(Duration::from_secs(30), Instant::now());
+        assert!(!monitor.is_open());
+        assert_eq!(monitor.acquire_latency().last_ms, 30_000);
+    }
+
+    #[test]
+    fn acquire_latency_covers_the_last_minute() {
+        let monitor = PoolMonitor::new(Duration::ZERO);
+        assert_eq!(monitor.acquire_latency(), DbAcquireLatency::default());
+
+        let now = Instant::now();
+        for ms in 1..=100 {
+            monitor.observe(ms * MS, now);
+        }
+        // Only the last 60 samples (41..=100) are kept
+        assert_eq!(
+            monitor.acquire_latency(),
+            DbAcquireLatency {
+                last_ms: 100,
+                p50_ms: 70,
+                p95_ms: 97,
+                max_ms: 100
+            }
+        );
+    }
+
+    #[test]
+    fn waiting_counts_requests_beyond_busy_connections() {
+        let monitor = PoolMonitor::new(Duration::ZERO);
+        let requests: Vec<InFlight> = (0..8).map(|_| monitor.track()).collect();
+        let full = DbPoolGauges {
+            size: 5,
+            idle: 0,
+            in_use: 5,
+            max_connections: 5,
+        };
+        let spare = DbPoolGauges {
+            size: 5,
+            idle: 1,
+            in_use: 4,
+            max_connections: 5,
+        };
+        assert_eq!(monitor.waiting(&full), 3);
+        assert_eq!(monitor.waiting(&spare), 0);
+        drop(requests);
+        assert_eq!(monitor.in_flight(), 0);
+    }
+}
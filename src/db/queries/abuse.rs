@@ -0,0 +1,109 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Abuse scoring ────────────────────────────────────
+
+/// Requests from a network since `since_secs` ago.
+pub async fn count_recent_abuse_events(
+    pool: &Pool,
+    network_hash: &[u8],
+    since_secs: i64,
+) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        r#"SELECT COUNT(*) FROM abuse_events
+           WHERE network_hash = $1 AND created_at > NOW() - make_interval(secs => $2)"#,
+    )
+    .bind(network_hash)
+    .bind(since_secs as f64)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+pub async fn insert_abuse_event(
+    pool: &Pool,
+    kind: &str,
+    network_hash: Option<&[u8]>,
+    ip: Option<&str>,
+    score: i16,
+    reasons: &[String],
+) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        r#"INSERT INTO abuse_events (kind, network_hash, ip, score, reasons)
+           VALUES ($1, $2, $3, $4, $5)
+           RETURNING id"#,
+    )
+    .bind(kind)
+    .bind(network_hash)
+    .bind(ip)
+    .bind(score)
+    .bind(reasons)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Tie a registration's event to the account it created, and keep its
+/// score on the account.
+pub async fn attach_abuse_event(pool: &Pool, event_id: i64, user_id: Uuid) -> AppResult<()> {
+    sqlx::query(
+        r#"WITH event AS (
+               UPDATE abuse_events SET user_id = $2 WHERE id = $1 RETURNING score
+           )
+           UPDATE users SET abuse_score = (SELECT score FROM event) WHERE id = $2"#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_abuse_events(
+    pool: &Pool,
+    user_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<AbuseEvent>> {
+    let rows = sqlx::query_as::<_, AbuseEvent>(
+        r#"SELECT id, kind, encode(network_hash, 'hex') AS network, ip, score, reasons, user_id, created_at
+           FROM abuse_events
+           WHERE $1::uuid IS NULL OR user_id = $1
+           ORDER BY created_at DESC, id DESC
+           LIMIT $2 OFFSET $3"#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Erase raw IPs older than `ip_retention_hours` and drop events older than
+/// `event_retention_days`. Returns how many rows were touched.
+pub async fn purge_abuse_events(
+    pool: &Pool,
+    ip_retention_hours: u32,
+    event_retention_days: u32,
+) -> AppResult<u64> {
+    let erased = sqlx::query(
+        r#"UPDATE abuse_events SET ip = NULL
+           WHERE ip IS NOT NULL AND created_at < NOW() - make_interval(hours => $1)"#,
+    )
+    .bind(ip_retention_hours as i32)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    let deleted = sqlx::query(
+        "DELETE FROM abuse_events WHERE created_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(event_retention_days as i32)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(erased + deleted)
+}
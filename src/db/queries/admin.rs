@@ -1,6 +1,6 @@
 use uuid::Uuid;
 
-use crate::db::Pool;
+use crate::db::{dialect, Pool};
 use crate::errors::{AppError, AppResult};
 use crate::models::*;
 
@@ -14,12 +14,12 @@ pub async fn create_report(
     reason: &str,
 ) -> AppResult<Report> {
     // Rate limit: max 5 reports per user per hour
-    #[cfg(feature = "postgres")]
-    let count_sql = "SELECT COUNT(*) FROM reports WHERE reporter_id = $1 AND created_at > CURRENT_TIMESTAMP - INTERVAL '1 hour'";
-    #[cfg(feature = "sqlite")]
-    let count_sql = "SELECT COUNT(*) FROM reports WHERE reporter_id = $1 AND created_at > datetime('now', '-1 hour')";
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM reports WHERE reporter_id = $1 AND created_at > {}",
+        dialect::ago("1 hour")
+    );
 
-    let count: (i64,) = sqlx::query_as(count_sql)
+    let count: (i64,) = sqlx::query_as(&count_sql)
         .bind(reporter_id)
         .fetch_one(pool)
         .await?;
@@ -73,6 +73,58 @@ pub async fn count_all_messages(pool: &Pool) -> AppResult<i64> {
     Ok(row.0)
 }
 
+/// Stamp the user active, at most once an hour.
+pub async fn touch_user_activity(pool: &Pool, user_id: Uuid) -> AppResult<()> {
+    let sql = format!(
+        "UPDATE users SET last_active_at = {} \
+         WHERE id = $1 AND (last_active_at IS NULL OR last_active_at < {})",
+        dialect::NOW,
+        dialect::ago("1 hour")
+    );
+    sqlx::query(&sql).bind(user_id).execute(pool).await?;
+    Ok(())
+}
+
+/// Users active in the last day and in the last 30 days.
+pub async fn count_active_users(pool: &Pool) -> AppResult<(i64, i64)> {
+    let sql = format!(
+        "SELECT COUNT(*) FILTER (WHERE last_active_at > {}), COUNT(*) \
+         FROM users WHERE last_active_at > {} AND NOT is_system",
+        dialect::ago("1 day"),
+        dialect::ago("30 days")
+    );
+    let row: (i64, i64) = sqlx::query_as(&sql).fetch_one(pool).await?;
+    Ok(row)
+}
+
+/// Messages sent in the last day and in the last 30 days, leaving out the
+/// system user's welcome and notice messages.
+pub async fn count_recent_messages(pool: &Pool) -> AppResult<(i64, i64)> {
+    let sql = format!(
+        "SELECT COUNT(*) FILTER (WHERE m.timestamp > {}), COUNT(*) FROM messages m \
+         WHERE m.timestamp > {} \
+           AND NOT EXISTS (SELECT 1 FROM users u WHERE u.id = m.sender_id AND u.is_system)",
+        dialect::ago("1 day"),
+        dialect::ago("30 days")
+    );
+    let row: (i64, i64) = sqlx::query_as(&sql).fetch_one(pool).await?;
+    Ok(row)
+}
+
+/// Bytes held in storage: attachments (deduplicated blobs once) and profile media.
+pub async fn total_storage_bytes(pool: &Pool) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        r#"
+        SELECT CAST((SELECT COALESCE(SUM(size_bytes), 0) FROM attachments WHERE content_hash IS NULL)
+                  + (SELECT COALESCE(SUM(size_bytes), 0) FROM attachment_blobs)
+                  + (SELECT COALESCE(SUM(size_bytes), 0) FROM user_profile_media) AS BIGINT)
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
 pub async fn search_users_admin(
     pool: &Pool,
     search: Option<&str>,
@@ -82,8 +134,12 @@ pub async fn search_users_admin(
     let rows = sqlx::query_as::<_, AdminUserResponse>(
         r#"
         SELECT u.id, u.username, u.display_name, u.avatar_url,
-               u.created_at, u.is_instance_admin,
-               COALESCE(sc.cnt, 0) AS server_count
+               u.created_at, u.is_instance_admin, u.instance_role,
+               COALESCE(sc.cnt, 0) AS server_count,
+               EXISTS(
+                   SELECT 1 FROM instance_bans ib
+                   WHERE ib.user_id = u.id AND (ib.expires_at IS NULL OR ib.expires_at > NOW())
+               ) AS is_banned
         FROM users u
         LEFT JOIN (
             SELECT user_id, COUNT(*) AS cnt FROM server_members GROUP BY user_id
@@ -102,15 +158,209 @@ pub async fn search_users_admin(
     Ok(rows)
 }
 
+/// Servers with their sizes, largest membership first. Counts are computed
+/// per returned row, so only the requested page is scanned.
+pub async fn list_servers_admin(pool: &Pool, limit: i64, offset: i64) -> AppResult<Vec<AdminServerResponse>> {
+    let rows = sqlx::query_as::<_, AdminServerResponse>(
+        r#"
+        SELECT s.id, s.owner_id, o.username AS owner_username, s.is_system,
+               s.upload_tier, s.created_at, s.member_count,
+               (SELECT COUNT(*) FROM channels c WHERE c.server_id = s.id) AS channel_count,
+               (SELECT COUNT(*) FROM messages m
+                INNER JOIN channels c ON c.id = m.channel_id
+                WHERE c.server_id = s.id) AS message_count,
+               (SELECT COUNT(*) FROM attachments a
+                INNER JOIN messages m ON m.id = a.message_id
+                INNER JOIN channels c ON c.id = m.channel_id
+                WHERE c.server_id = s.id) AS attachment_count
+        FROM (
+            SELECT sv.*, (SELECT COUNT(*) FROM server_members sm WHERE sm.server_id = sv.id) AS member_count
+            FROM servers sv
+            ORDER BY member_count DESC, sv.created_at
+            LIMIT $1 OFFSET $2
+        ) s
+        INNER JOIN users o ON o.id = s.owner_id
+        ORDER BY s.member_count DESC, s.created_at
+        "#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 pub async fn set_instance_admin(pool: &Pool, user_id: Uuid, is_admin: bool) -> AppResult<()> {
-    sqlx::query("UPDATE users SET is_instance_admin = $1 WHERE id = $2")
-        .bind(is_admin)
-        .bind(user_id)
-        .execute(pool)
-        .await?;
+    let role = if is_admin { Some("operator") } else { None };
+    set_instance_role(pool, user_id, role).await
+}
+
+/// Set (or clear) a user's instance staff role. Keeps `is_instance_admin`
+/// in sync so that operator and the legacy admin flag never disagree.
+pub async fn set_instance_role(pool: &Pool, user_id: Uuid, role: Option<&str>) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE users SET instance_role = $1, is_instance_admin = ($1 IS NOT DISTINCT FROM 'operator') WHERE id = $2",
+    )
+    .bind(role)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
+pub async fn list_instance_staff(pool: &Pool) -> AppResult<Vec<StaffMemberResponse>> {
+    let rows = sqlx::query_as::<_, StaffMemberResponse>(
+        r#"
+        SELECT id, username, display_name,
+               CASE WHEN is_instance_admin THEN 'operator' ELSE instance_role END AS instance_role
+        FROM users
+        WHERE is_instance_admin = TRUE OR instance_role IS NOT NULL
+        ORDER BY username
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// ─── Instance Audit Log ──────────────────────────────
+
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_instance_audit_log(
+    pool: &Pool,
+    actor_id: Uuid,
+    actor_role: &str,
+    action: &str,
+    target_type: Option<&str>,
+    target_id: Option<Uuid>,
+    details: Option<&serde_json::Value>,
+    reason: Option<&str>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO instance_audit_log (actor_id, actor_role, action, target_type, target_id, details, reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(actor_id)
+    .bind(actor_role)
+    .bind(action)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(details)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_instance_audit_log(
+    pool: &Pool,
+    actor_id: Option<Uuid>,
+    limit: i64,
+    before: Option<chrono::DateTime<chrono::Utc>>,
+) -> AppResult<Vec<InstanceAuditLogResponse>> {
+    let rows = sqlx::query_as::<_, InstanceAuditLogResponse>(
+        r#"
+        SELECT al.id, al.actor_id, u.username AS actor_username, al.actor_role, al.action,
+               al.target_type, al.target_id, al.details, al.reason, al.created_at
+        FROM instance_audit_log al
+        LEFT JOIN users u ON u.id = al.actor_id
+        WHERE ($1::UUID IS NULL OR al.actor_id = $1)
+          AND ($2::TIMESTAMPTZ IS NULL OR al.created_at < $2)
+        ORDER BY al.created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(actor_id)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// ─── Instance Branding ───────────────────────────────
+
+pub async fn get_instance_branding(pool: &Pool) -> AppResult<InstanceBranding> {
+    let branding = sqlx::query_as::<_, InstanceBranding>(
+        r#"
+        SELECT name, has_logo, accent_color, background_color, terms_url, privacy_url, updated_at
+        FROM instance_branding
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(branding)
+}
+
+pub async fn update_instance_branding(
+    pool: &Pool,
+    branding: &InstanceBranding,
+    updated_by: Uuid,
+) -> AppResult<InstanceBranding> {
+    let branding = sqlx::query_as::<_, InstanceBranding>(
+        r#"
+        UPDATE instance_branding
+        SET name = $1, has_logo = $2, accent_color = $3, background_color = $4,
+            terms_url = $5, privacy_url = $6, updated_by = $7, updated_at = CURRENT_TIMESTAMP
+        RETURNING name, has_logo, accent_color, background_color, terms_url, privacy_url, updated_at
+        "#,
+    )
+    .bind(&branding.name)
+    .bind(branding.has_logo)
+    .bind(&branding.accent_color)
+    .bind(&branding.background_color)
+    .bind(&branding.terms_url)
+    .bind(&branding.privacy_url)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(branding)
+}
+
+// ─── Support Access ──────────────────────────────────
+
+pub async fn list_support_memberships(pool: &Pool, user_id: Uuid) -> AppResult<Vec<SupportMembership>> {
+    let rows = sqlx::query_as::<_, SupportMembership>(
+        r#"
+        SELECT sm.server_id, (s.owner_id = sm.user_id) AS is_owner, sm.joined_at
+        FROM server_members sm
+        INNER JOIN servers s ON s.id = sm.server_id
+        WHERE sm.user_id = $1
+        ORDER BY sm.joined_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Support access entries recorded against a user, newest first.
+pub async fn list_support_access_for_user(
+    pool: &Pool,
+    user_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<SupportAccessEntry>> {
+    let rows = sqlx::query_as::<_, SupportAccessEntry>(
+        r#"
+        SELECT id, actor_role AS staff_role, reason, created_at
+        FROM instance_audit_log
+        WHERE target_id = $1 AND target_type = 'user' AND action = 'support_access'
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 pub async fn delete_user_account(pool: &Pool, user_id: Uuid) -> AppResult<()> {
     sqlx::query("DELETE FROM users WHERE id = $1")
         .bind(user_id)
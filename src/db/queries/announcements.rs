@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::{Connection, Pool};
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Announcements ───────────────────────────────────
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_announcement(
+    pool: &Pool,
+    title: &str,
+    body: &str,
+    kind: &str,
+    send_dm: bool,
+    publish_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    created_by: Uuid,
+) -> AppResult<Announcement> {
+    let announcement = sqlx::query_as::<_, Announcement>(
+        r#"
+        INSERT INTO announcements (id, title, body, kind, send_dm, publish_at, expires_at, created_by, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(title)
+    .bind(body)
+    .bind(kind)
+    .bind(send_dm)
+    .bind(publish_at)
+    .bind(expires_at)
+    .bind(created_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(announcement)
+}
+
+/// All announcements, newest first, with how many users dismissed each.
+pub async fn list_announcements_admin(
+    pool: &Pool,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<AdminAnnouncementResponse>> {
+    let rows = sqlx::query_as::<_, AdminAnnouncementResponse>(
+        r#"
+        SELECT a.*, COALESCE(d.cnt, 0) AS dismissal_count
+        FROM announcements a
+        LEFT JOIN (
+            SELECT announcement_id, COUNT(*) AS cnt FROM announcement_dismissals GROUP BY announcement_id
+        ) d ON d.announcement_id = a.id
+        ORDER BY a.publish_at DESC
+        LIMIT $1 OFFSET $2
+        "#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_announcement(pool: &Pool, announcement_id: Uuid) -> AppResult<Option<Announcement>> {
+    let announcement = sqlx::query_as::<_, Announcement>("SELECT * FROM announcements WHERE id = $1")
+        .bind(announcement_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(announcement)
+}
+
+pub async fn delete_announcement(pool: &Pool, announcement_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM announcements WHERE id = $1")
+        .bind(announcement_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark every announcement whose time has come as published and return them.
+/// The UPDATE claims them, so with several instances each is broadcast once.
+/// Runs on the caller's transaction, which stages the broadcasts.
+pub async fn claim_due_announcements(conn: &mut Connection) -> AppResult<Vec<Announcement>> {
+    let announcements = sqlx::query_as::<_, Announcement>(
+        r#"
+        UPDATE announcements SET published_at = CURRENT_TIMESTAMP
+        WHERE published_at IS NULL AND publish_at <= CURRENT_TIMESTAMP
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        RETURNING *
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(announcements)
+}
+
+/// Published, unexpired announcements the user has not dismissed.
+pub async fn get_active_announcements(pool: &Pool, user_id: Uuid) -> AppResult<Vec<Announcement>> {
+    let announcements = sqlx::query_as::<_, Announcement>(
+        r#"
+        SELECT a.* FROM announcements a
+        WHERE a.published_at IS NOT NULL
+          AND (a.expires_at IS NULL OR a.expires_at > CURRENT_TIMESTAMP)
+          AND NOT EXISTS (
+              SELECT 1 FROM announcement_dismissals d
+              WHERE d.announcement_id = a.id AND d.user_id = $1
+          )
+        ORDER BY a.published_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(announcements)
+}
+
+/// Record a dismissal. Returns false if the announcement is not published.
+pub async fn dismiss_announcement(pool: &Pool, announcement_id: Uuid, user_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO announcement_dismissals (announcement_id, user_id, dismissed_at)
+        SELECT id, $2, CURRENT_TIMESTAMP FROM announcements
+        WHERE id = $1 AND published_at IS NOT NULL
+        ON CONFLICT (announcement_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(announcement_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() > 0 {
+        return Ok(true);
+    }
+    // Already dismissed counts as success.
+    let row: Option<(i32,)> = sqlx::query_as(
+        "SELECT 1 FROM announcement_dismissals WHERE announcement_id = $1 AND user_id = $2",
+    )
+    .bind(announcement_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// A page of users who receive announcement DMs: everyone except the system
+/// user, bridge puppets and users under an active instance ban. Keyset
+/// paginated by id. Returns (id, username, display_name).
+pub async fn list_announcement_recipients(
+    pool: &Pool,
+    after: Option<Uuid>,
+    limit: i64,
+) -> AppResult<Vec<(Uuid, String, Option<String>)>> {
+    let rows = sqlx::query_as(
+        r#"
+        SELECT u.id, u.username, u.display_name FROM users u
+        WHERE u.is_system = FALSE
+          AND ($1::UUID IS NULL OR u.id > $1)
+          AND NOT EXISTS (SELECT 1 FROM bridge_puppets bp WHERE bp.user_id = u.id)
+          AND NOT EXISTS (
+              SELECT 1 FROM instance_bans ib
+              WHERE ib.user_id = u.id AND (ib.expires_at IS NULL OR ib.expires_at > NOW())
+          )
+        ORDER BY u.id
+        LIMIT $2
+        "#,
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
@@ -1,23 +1,34 @@
 use uuid::Uuid;
 
-use crate::db::Pool;
+use crate::db::{dialect, Connection, Pool};
 use crate::errors::AppResult;
 use crate::models::*;
 
 // ─── Attachments ───────────────────────────────────────
 
 /// Link an attachment (uploaded via presigned URL) to a message.
+/// `server_id` and `size_bytes` charge the blob to a server's storage quota.
+/// `content_hash` names the shared blob, whose reference the upload already
+/// took. `scan_status` is `Pending` when a malware scan is queued for it.
+/// `content_type` is served with downloads (unencrypted channels only).
+#[allow(clippy::too_many_arguments)]
 pub async fn link_attachment(
     pool: &Pool,
     attachment_id: Uuid,
     message_id: Uuid,
     storage_key: &str,
     file_hash: Option<&str>,
+    server_id: Option<Uuid>,
+    size_bytes: i64,
+    content_hash: Option<&str>,
+    scan_status: Option<ScanStatus>,
+    content_type: Option<&str>,
 ) -> AppResult<Attachment> {
     let att = sqlx::query_as::<_, Attachment>(
         r#"
-        INSERT INTO attachments (id, message_id, storage_key, encrypted_meta, size_bucket, created_at, file_hash)
-        VALUES ($1, $2, $3, $4, 0, CURRENT_TIMESTAMP, $5)
+        INSERT INTO attachments (id, message_id, storage_key, encrypted_meta, size_bucket, created_at,
+                                 file_hash, server_id, size_bytes, content_hash, scan_status, content_type)
+        VALUES ($1, $2, $3, $4, 0, CURRENT_TIMESTAMP, $5, $6, $7, $8, $9, $10)
         RETURNING *
         "#,
     )
@@ -26,11 +37,128 @@ pub async fn link_attachment(
     .bind(storage_key)
     .bind(&[] as &[u8])
     .bind(file_hash)
+    .bind(server_id)
+    .bind(size_bytes)
+    .bind(content_hash)
+    .bind(scan_status.map(|status| status.as_str()))
+    .bind(content_type)
     .fetch_one(pool)
     .await?;
     Ok(att)
 }
 
+// ─── Attachment Blobs ────────────────────────────────
+
+/// Take a reference to the blob with this content hash, registering it if
+/// it is new. Returns true when it is new and the caller must store the
+/// bytes. A blob being collected holds its row lock, so this waits and then
+/// registers it afresh.
+pub async fn acquire_attachment_blob(
+    pool: &Pool,
+    content_hash: &str,
+    storage_key: &str,
+    size_bytes: i64,
+) -> AppResult<bool> {
+    let (created,): (bool,) = sqlx::query_as(
+        r#"
+        INSERT INTO attachment_blobs (content_hash, storage_key, size_bytes, ref_count)
+        VALUES ($1, $2, $3, 1)
+        ON CONFLICT (content_hash) DO UPDATE
+        SET ref_count = attachment_blobs.ref_count + 1, unreferenced_at = NULL
+        RETURNING (xmax = 0)
+        "#,
+    )
+    .bind(content_hash)
+    .bind(storage_key)
+    .bind(size_bytes)
+    .fetch_one(pool)
+    .await?;
+    Ok(created)
+}
+
+/// Drop a reference taken by [`acquire_attachment_blob`] that was never
+/// linked to an attachment (e.g. storing the bytes failed).
+pub async fn release_attachment_blob(pool: &Pool, content_hash: &str) -> AppResult<()> {
+    let mut conn = pool.acquire().await?;
+    release_attachment_blob_in(&mut conn, content_hash).await
+}
+
+/// [`release_attachment_blob`] on the caller's connection.
+pub async fn release_attachment_blob_in(conn: &mut Connection, content_hash: &str) -> AppResult<()> {
+    sqlx::query(&format!(
+        r#"
+        UPDATE attachment_blobs
+        SET ref_count = ref_count - 1,
+            unreferenced_at = CASE WHEN ref_count = 1 THEN {} END
+        WHERE content_hash = $1 AND ref_count > 0
+        "#,
+        dialect::NOW
+    ))
+    .bind(content_hash)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Lock blobs unreferenced for longer than the grace period, for deletion
+/// inside the caller's transaction: (content_hash, storage_key).
+pub async fn lock_collectable_attachment_blobs_in(
+    conn: &mut Connection,
+    grace_hours: u32,
+    limit: i64,
+) -> AppResult<Vec<(String, String)>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT content_hash, storage_key FROM attachment_blobs
+        WHERE ref_count = 0 AND unreferenced_at < CURRENT_TIMESTAMP - make_interval(hours => $1)
+        ORDER BY unreferenced_at
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(grace_hours as i32)
+    .bind(limit)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn delete_attachment_blobs_in(conn: &mut Connection, content_hashes: &[String]) -> AppResult<u64> {
+    let result = sqlx::query("DELETE FROM attachment_blobs WHERE content_hash = ANY($1) AND ref_count = 0")
+        .bind(content_hashes)
+        .execute(&mut *conn)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Unreferenced blob count: (still in grace period, past grace period).
+pub async fn count_unreferenced_attachment_blobs(pool: &Pool, grace_hours: u32) -> AppResult<(i64, i64)> {
+    let row: (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE unreferenced_at >= CURRENT_TIMESTAMP - make_interval(hours => $1)),
+            COUNT(*) FILTER (WHERE unreferenced_at < CURRENT_TIMESTAMP - make_interval(hours => $1))
+        FROM attachment_blobs
+        WHERE ref_count = 0
+        "#,
+    )
+    .bind(grace_hours as i32)
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Releases the blob references of the attachments deleted by a `gone` CTE
+/// that returns their `content_hash`.
+pub(crate) const RELEASE_DELETED_BLOBS: &str = r#"
+    UPDATE attachment_blobs b
+    SET ref_count = b.ref_count - g.refs,
+        unreferenced_at = CASE WHEN b.ref_count - g.refs = 0 THEN CURRENT_TIMESTAMP END
+    FROM (SELECT content_hash, COUNT(*)::INT AS refs FROM gone
+          WHERE content_hash IS NOT NULL GROUP BY content_hash) g
+    WHERE b.content_hash = g.content_hash
+"#;
+
 pub async fn find_attachment_by_id(pool: &Pool, id: Uuid) -> AppResult<Option<Attachment>> {
     let att = sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE id = $1")
         .bind(id)
@@ -65,6 +193,405 @@ pub async fn insert_attachment(
     Ok(att)
 }
 
+/// Record a generated preview on an attachment.
+pub async fn set_attachment_preview(
+    pool: &Pool,
+    attachment_id: Uuid,
+    thumbnail_key: &str,
+    blurhash: &str,
+    width: i32,
+    height: i32,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE attachments SET thumbnail_key = $2, blurhash = $3, width = $4, height = $5 WHERE id = $1",
+    )
+    .bind(attachment_id)
+    .bind(thumbnail_key)
+    .bind(blurhash)
+    .bind(width)
+    .bind(height)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Previews of the given messages' attachments: (message_id, preview), oldest first.
+pub async fn get_attachment_previews(
+    pool: &Pool,
+    message_ids: &[Uuid],
+) -> AppResult<Vec<(Uuid, AttachmentPreview)>> {
+    let rows = sqlx::query_as::<_, (Uuid, Uuid, String, i32, i32)>(
+        r#"
+        SELECT id, message_id, blurhash, width, height FROM attachments
+        WHERE message_id = ANY($1) AND thumbnail_key IS NOT NULL
+        ORDER BY created_at
+        "#,
+    )
+    .bind(message_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, message_id, blurhash, width, height)| {
+            (message_id, AttachmentPreview::new(id, blurhash, width, height))
+        })
+        .collect())
+}
+
+// ─── Attachment Scanning ─────────────────────────────
+
+/// Record a scan verdict. Returns false if the attachment is gone.
+pub async fn set_attachment_scan_verdict(
+    pool: &Pool,
+    attachment_id: Uuid,
+    status: ScanStatus,
+    signature: Option<&str>,
+) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE attachments SET scan_status = $2, scan_signature = $3, scanned_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(attachment_id)
+    .bind(status.as_str())
+    .bind(signature)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Attachments whose scan was queued more than `min_age_mins` ago and never
+/// finished (e.g. the instance restarted mid-scan): (id, storage_key).
+pub async fn list_stale_pending_scans(
+    pool: &Pool,
+    min_age_mins: i32,
+    limit: i64,
+) -> AppResult<Vec<(Uuid, String)>> {
+    let rows = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        SELECT id, storage_key FROM attachments
+        WHERE scan_status = 'pending' AND created_at < CURRENT_TIMESTAMP - make_interval(mins => $1)
+        ORDER BY created_at
+        LIMIT $2
+        "#,
+    )
+    .bind(min_age_mins)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// ─── Attachment GC ───────────────────────────────────
+
+/// Stamp attachments whose message no longer exists. Returns the number newly stamped.
+pub async fn mark_orphaned_attachments(pool: &Pool) -> AppResult<u64> {
+    let result = sqlx::query(&format!(
+        r#"
+        UPDATE attachments SET orphaned_at = {}
+        WHERE orphaned_at IS NULL
+          AND NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = attachments.message_id)
+        "#,
+        dialect::NOW
+    ))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Orphaned attachments past the grace period: (id, storage_key, thumbnail_key).
+/// The storage key is absent for attachments in a shared blob, which is
+/// released with the row instead.
+pub async fn list_collectable_attachments(
+    pool: &Pool,
+    grace_hours: u32,
+    limit: i64,
+) -> AppResult<Vec<(Uuid, Option<String>, Option<String>)>> {
+    let rows = sqlx::query_as::<_, (Uuid, Option<String>, Option<String>)>(&format!(
+        r#"
+        SELECT id, CASE WHEN content_hash IS NULL THEN storage_key END, thumbnail_key FROM attachments
+        WHERE orphaned_at < {}
+        ORDER BY orphaned_at
+        LIMIT $1
+        "#,
+        dialect::ago(&format!("{} hours", grace_hours))
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Orphaned attachment counts: (still in grace period, past grace period).
+pub async fn count_orphaned_attachments(pool: &Pool, grace_hours: u32) -> AppResult<(i64, i64)> {
+    let cutoff = dialect::ago(&format!("{} hours", grace_hours));
+    let row: (i64, i64) = sqlx::query_as(&format!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE orphaned_at >= {cutoff}),
+            COUNT(*) FILTER (WHERE orphaned_at < {cutoff})
+        FROM attachments
+        WHERE orphaned_at IS NOT NULL
+        "#
+    ))
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Delete attachment rows and release their shared blobs. Only rows still
+/// orphaned are removed.
+pub async fn delete_orphaned_attachments(pool: &Pool, ids: &[Uuid]) -> AppResult<u64> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    #[cfg(feature = "postgres")]
+    let (deleted,): (i64,) = sqlx::query_as(&format!(
+        r#"
+        WITH gone AS (
+            DELETE FROM attachments WHERE id = ANY($1) AND orphaned_at IS NOT NULL RETURNING content_hash
+        ), released AS ({})
+        SELECT COUNT(*) FROM gone
+        "#,
+        RELEASE_DELETED_BLOBS
+    ))
+    .bind(ids)
+    .fetch_one(pool)
+    .await?;
+
+    // SQLite has no data-modifying CTEs: delete, then release each blob reference.
+    #[cfg(feature = "sqlite")]
+    let deleted = {
+        let mut tx = pool.begin().await?;
+        let sql = format!(
+            "DELETE FROM attachments WHERE id IN ({}) AND orphaned_at IS NOT NULL RETURNING content_hash",
+            dialect::placeholders(1, ids.len())
+        );
+        let mut query = sqlx::query_as::<_, (Option<String>,)>(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        let gone = query.fetch_all(&mut *tx).await?;
+        for content_hash in gone.iter().filter_map(|(hash,)| hash.as_deref()) {
+            release_attachment_blob_in(&mut tx, content_hash).await?;
+        }
+        tx.commit().await?;
+        gone.len() as i64
+    };
+
+    Ok(deleted as u64)
+}
+
+// ─── Pending Uploads ─────────────────────────────────
+
+/// Record the blob reference a simple upload took, until a message links it.
+pub async fn create_pending_upload(
+    pool: &Pool,
+    attachment_id: Uuid,
+    user_id: Uuid,
+    content_hash: &str,
+    size_bytes: i64,
+    file_hash: Option<&str>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO pending_uploads (attachment_id, user_id, content_hash, size_bytes, file_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(attachment_id)
+    .bind(user_id)
+    .bind(content_hash)
+    .bind(size_bytes)
+    .bind(file_hash)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Find a live pending upload owned by `user_id`.
+pub async fn find_pending_upload(pool: &Pool, attachment_id: Uuid, user_id: Uuid) -> AppResult<Option<PendingUpload>> {
+    let pending = sqlx::query_as::<_, PendingUpload>(&format!(
+        "SELECT * FROM pending_uploads WHERE attachment_id = $1 AND user_id = $2 AND expires_at > {}",
+        dialect::NOW
+    ))
+    .bind(attachment_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(pending)
+}
+
+/// Remove a live pending upload owned by `user_id` so it can be linked. Its
+/// blob reference passes to the caller, who must link or release it.
+pub async fn take_pending_upload(pool: &Pool, attachment_id: Uuid, user_id: Uuid) -> AppResult<Option<PendingUpload>> {
+    let pending = sqlx::query_as::<_, PendingUpload>(&format!(
+        "DELETE FROM pending_uploads WHERE attachment_id = $1 AND user_id = $2 AND expires_at > {} RETURNING *",
+        dialect::NOW
+    ))
+    .bind(attachment_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(pending)
+}
+
+/// Delete pending uploads that expired unsent and release their blob
+/// references. Returns how many were released.
+pub async fn release_expired_pending_uploads(pool: &Pool) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+    let expired: Vec<(String,)> = sqlx::query_as(&format!(
+        "DELETE FROM pending_uploads WHERE expires_at <= {} RETURNING content_hash",
+        dialect::NOW
+    ))
+    .fetch_all(&mut *tx)
+    .await?;
+    for (content_hash,) in &expired {
+        release_attachment_blob_in(&mut tx, content_hash).await?;
+    }
+    tx.commit().await?;
+    Ok(expired.len() as u64)
+}
+
+// ─── Upload Sessions ─────────────────────────────────
+
+pub async fn create_upload_session(
+    pool: &Pool,
+    channel_id: Uuid,
+    user_id: Uuid,
+    content_type: &str,
+    upload_length: i64,
+    file_hash: Option<&str>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+) -> AppResult<UploadSession> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        r#"
+        INSERT INTO upload_sessions (id, channel_id, user_id, content_type, upload_length, file_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(channel_id)
+    .bind(user_id)
+    .bind(content_type)
+    .bind(upload_length)
+    .bind(file_hash)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Find a live (unexpired) upload session owned by `user_id`.
+pub async fn find_upload_session(
+    pool: &Pool,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        "SELECT * FROM upload_sessions WHERE id = $1 AND user_id = $2 AND expires_at > NOW()",
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Append a stored chunk to a session, but only if the session is still at
+/// `expected_offset`. Returns the updated session, or None if another chunk
+/// won the race (or the session vanished).
+pub async fn append_upload_part(
+    pool: &Pool,
+    session_id: Uuid,
+    expected_offset: i64,
+    part_id: Uuid,
+    part_len: i64,
+) -> AppResult<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        r#"
+        UPDATE upload_sessions
+        SET upload_offset = upload_offset + $4, part_ids = array_append(part_ids, $3)
+        WHERE id = $1 AND upload_offset = $2 AND upload_offset + $4 <= upload_length
+        RETURNING *
+        "#,
+    )
+    .bind(session_id)
+    .bind(expected_offset)
+    .bind(part_id)
+    .bind(part_len)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Claim a complete session for finalization. Returns None if it is gone,
+/// expired, or another finalize already claimed it.
+pub async fn claim_upload_session(
+    pool: &Pool,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>(&format!(
+        r#"
+        UPDATE upload_sessions SET status = 'finalizing'
+        WHERE id = $1 AND user_id = $2 AND status = 'uploading' AND expires_at > {}
+        RETURNING *
+        "#,
+        dialect::NOW
+    ))
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Hand a claimed session back after a failed finalize, so it can be retried.
+pub async fn release_upload_session(pool: &Pool, session_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE upload_sessions SET status = 'uploading' WHERE id = $1 AND status = 'finalizing'")
+        .bind(session_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Delete a session that is not being finalized, returning its part ids.
+/// None if it is gone or a finalize holds it.
+pub async fn cancel_upload_session(pool: &Pool, session_id: Uuid) -> AppResult<Option<Vec<Uuid>>> {
+    let row: Option<(Vec<Uuid>,)> = sqlx::query_as(
+        "DELETE FROM upload_sessions WHERE id = $1 AND status = 'uploading' RETURNING part_ids",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Delete an upload session, returning its part ids so the blobs can be removed.
+pub async fn delete_upload_session(pool: &Pool, session_id: Uuid) -> AppResult<Option<Vec<Uuid>>> {
+    let row: Option<(Vec<Uuid>,)> =
+        sqlx::query_as("DELETE FROM upload_sessions WHERE id = $1 RETURNING part_ids")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Delete expired upload sessions, returning the part ids of every removed session.
+pub async fn purge_expired_upload_sessions(pool: &Pool) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Vec<Uuid>,)> =
+        sqlx::query_as("DELETE FROM upload_sessions WHERE expires_at < NOW() RETURNING part_ids")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().flat_map(|r| r.0).collect())
+}
+
 // ─── Blocked Hashes ──────────────────────────────────
 
 pub async fn is_hash_blocked(pool: &Pool, hash: &str) -> AppResult<bool> {
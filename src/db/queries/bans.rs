@@ -70,18 +70,29 @@ pub async fn is_banned(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult
 
 // ─── Instance Bans ───────────────────────────────────
 
+/// Ban (or, with `expires_at`, suspend) a user. Banning an already-banned
+/// user replaces the existing ban, so a suspension can be extended, shortened
+/// or made permanent.
 pub async fn create_instance_ban(
     pool: &Pool,
     user_id: Uuid,
     reason: Option<&str>,
     banned_by: Uuid,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
 ) -> AppResult<crate::models::InstanceBan> {
     let ban = sqlx::query_as::<_, crate::models::InstanceBan>(
-        "INSERT INTO instance_bans (user_id, reason, banned_by) VALUES ($1, $2, $3) RETURNING *",
+        r#"
+        INSERT INTO instance_bans (user_id, reason, banned_by, expires_at) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET reason = EXCLUDED.reason, banned_by = EXCLUDED.banned_by,
+            expires_at = EXCLUDED.expires_at, created_at = NOW()
+        RETURNING *
+        "#,
     )
     .bind(user_id)
     .bind(reason)
     .bind(banned_by)
+    .bind(expires_at)
     .fetch_one(pool)
     .await?;
     Ok(ban)
@@ -100,13 +111,15 @@ pub async fn list_instance_bans(
     limit: i64,
     offset: i64,
 ) -> AppResult<Vec<crate::models::InstanceBanResponse>> {
-    let rows = sqlx::query_as::<_, (Uuid, Uuid, Option<String>, Uuid, chrono::DateTime<chrono::Utc>, String, String)>(
+    #[allow(clippy::type_complexity)]
+    let rows = sqlx::query_as::<_, (Uuid, Uuid, Option<String>, Uuid, chrono::DateTime<chrono::Utc>, Option<chrono::DateTime<chrono::Utc>>, String, String)>(
         r#"
-        SELECT ib.id, ib.user_id, ib.reason, ib.banned_by, ib.created_at,
+        SELECT ib.id, ib.user_id, ib.reason, ib.banned_by, ib.created_at, ib.expires_at,
                u.username, admin.username
         FROM instance_bans ib
         JOIN users u ON u.id = ib.user_id
         JOIN users admin ON admin.id = ib.banned_by
+        WHERE ib.expires_at IS NULL OR ib.expires_at > NOW()
         ORDER BY ib.created_at DESC
         LIMIT $1 OFFSET $2
         "#,
@@ -116,7 +129,7 @@ pub async fn list_instance_bans(
     .fetch_all(pool)
     .await?;
 
-    Ok(rows.into_iter().map(|(id, user_id, reason, banned_by, created_at, username, banned_by_username)| {
+    Ok(rows.into_iter().map(|(id, user_id, reason, banned_by, created_at, expires_at, username, banned_by_username)| {
         crate::models::InstanceBanResponse {
             id,
             user_id,
@@ -125,16 +138,26 @@ pub async fn list_instance_bans(
             banned_by,
             banned_by_username,
             created_at: created_at.to_rfc3339(),
+            expires_at: expires_at.map(|t| t.to_rfc3339()),
         }
     }).collect())
 }
 
 pub async fn is_instance_banned(pool: &Pool, user_id: Uuid) -> AppResult<bool> {
     let row: (bool,) = sqlx::query_as(
-        "SELECT EXISTS(SELECT 1 FROM instance_bans WHERE user_id = $1)",
+        "SELECT EXISTS(SELECT 1 FROM instance_bans WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > NOW()))",
     )
     .bind(user_id)
     .fetch_one(pool)
     .await?;
     Ok(row.0)
 }
+
+/// Delete suspensions that have run out. They already stopped applying when
+/// they expired; this only keeps the table small.
+pub async fn purge_expired_instance_bans(pool: &Pool) -> AppResult<u64> {
+    let result = sqlx::query("DELETE FROM instance_bans WHERE expires_at IS NOT NULL AND expires_at <= NOW()")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
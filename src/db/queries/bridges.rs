@@ -0,0 +1,347 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::{AppError, AppResult};
+use crate::models::*;
+
+// ─── Bridges ─────────────────────────────────────────
+
+/// Password hash that never verifies — puppets are driven by their bridge.
+const PUPPET_PASSWORD_HASH: &str = "!BRIDGE_PUPPET_NO_LOGIN!";
+
+pub async fn create_bridge(
+    pool: &Pool,
+    name: &str,
+    token_hash: &str,
+    user_prefix: &str,
+    created_by: Uuid,
+    server_id: Option<Uuid>,
+) -> AppResult<Bridge> {
+    let result = sqlx::query_as::<_, Bridge>(
+        r#"
+        INSERT INTO bridges (id, name, token_hash, user_prefix, created_by, created_at, server_id)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(name)
+    .bind(token_hash)
+    .bind(user_prefix)
+    .bind(created_by)
+    .bind(server_id)
+    .fetch_one(pool)
+    .await;
+
+    match result {
+        Ok(bridge) => Ok(bridge),
+        Err(sqlx::Error::Database(ref db_err)) if db_err.is_unique_violation() => Err(
+            AppError::Conflict("Another bridge already uses this user prefix".into()),
+        ),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_bridges(pool: &Pool) -> AppResult<Vec<Bridge>> {
+    let bridges = sqlx::query_as::<_, Bridge>("SELECT * FROM bridges ORDER BY created_at")
+        .fetch_all(pool)
+        .await?;
+    Ok(bridges)
+}
+
+pub async fn find_bridge_by_id(pool: &Pool, bridge_id: Uuid) -> AppResult<Option<Bridge>> {
+    let bridge = sqlx::query_as::<_, Bridge>("SELECT * FROM bridges WHERE id = $1")
+        .bind(bridge_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(bridge)
+}
+
+pub async fn find_bridge_by_token_hash(pool: &Pool, token_hash: &str) -> AppResult<Option<Bridge>> {
+    let bridge = sqlx::query_as::<_, Bridge>("SELECT * FROM bridges WHERE token_hash = $1")
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await?;
+    Ok(bridge)
+}
+
+/// Delete a bridge. Its links, queue and puppet mappings go with it; the
+/// puppet accounts stay so bridged history keeps its authors.
+pub async fn delete_bridge(pool: &Pool, bridge_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM bridges WHERE id = $1")
+        .bind(bridge_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn link_bridge_channel(pool: &Pool, bridge_id: Uuid, channel_id: Uuid, linked_by: Uuid) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO bridge_channels (bridge_id, channel_id, linked_by, created_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+        ON CONFLICT (bridge_id, channel_id) DO NOTHING
+        "#,
+    )
+    .bind(bridge_id)
+    .bind(channel_id)
+    .bind(linked_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Unlink a bridge from a channel and drop its undelivered events for it.
+pub async fn unlink_bridge_channel(pool: &Pool, bridge_id: Uuid, channel_id: Uuid) -> AppResult<bool> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("DELETE FROM bridge_channels WHERE bridge_id = $1 AND channel_id = $2")
+        .bind(bridge_id)
+        .bind(channel_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM bridge_events WHERE bridge_id = $1 AND channel_id = $2")
+        .bind(bridge_id)
+        .bind(channel_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn is_bridge_linked(pool: &Pool, bridge_id: Uuid, channel_id: Uuid) -> AppResult<bool> {
+    let row: Option<(i32,)> = sqlx::query_as(
+        "SELECT 1 FROM bridge_channels WHERE bridge_id = $1 AND channel_id = $2",
+    )
+    .bind(bridge_id)
+    .bind(channel_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// Bridges linked to a channel, for the channel settings page.
+pub async fn get_channel_bridges(pool: &Pool, channel_id: Uuid) -> AppResult<Vec<Bridge>> {
+    let bridges = sqlx::query_as::<_, Bridge>(
+        r#"
+        SELECT b.* FROM bridges b
+        INNER JOIN bridge_channels bc ON bc.bridge_id = b.id
+        WHERE bc.channel_id = $1
+        ORDER BY bc.created_at
+        "#,
+    )
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(bridges)
+}
+
+pub async fn find_bridge_puppet(pool: &Pool, bridge_id: Uuid, user_id: Uuid) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        INNER JOIN bridge_puppets bp ON bp.user_id = u.id
+        WHERE bp.bridge_id = $1 AND bp.user_id = $2
+        "#,
+    )
+    .bind(bridge_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+/// Create the puppet for `remote_id`, or refresh its display name if the
+/// bridge already has one. Puppets get placeholder keys like the system user.
+pub async fn upsert_bridge_puppet(
+    pool: &Pool,
+    bridge_id: Uuid,
+    remote_id: &str,
+    username: &str,
+    display_name: Option<&str>,
+) -> AppResult<User> {
+    let mut tx = pool.begin().await?;
+
+    let existing: Option<(Uuid, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT bp.user_id, u.display_name FROM bridge_puppets bp
+        INNER JOIN users u ON u.id = bp.user_id
+        WHERE bp.bridge_id = $1 AND bp.remote_id = $2
+        "#,
+    )
+    .bind(bridge_id)
+    .bind(remote_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let renamed = existing
+        .as_ref()
+        .is_some_and(|(_, current)| current.as_deref() != display_name);
+
+    let user = match existing {
+        Some((user_id, _)) => {
+            sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users SET display_name = $1, updated_at = CURRENT_TIMESTAMP
+                WHERE id = $2
+                RETURNING *
+                "#,
+            )
+            .bind(display_name)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        None => {
+            let result = sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (id, username, display_name, password_hash,
+                                  identity_key, signed_prekey, signed_prekey_sig,
+                                  created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                RETURNING *
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(username)
+            .bind(display_name)
+            .bind(PUPPET_PASSWORD_HASH)
+            .bind(vec![0u8; 32])
+            .bind(vec![0u8; 32])
+            .bind(vec![0u8; 64])
+            .fetch_one(&mut *tx)
+            .await;
+            let user = match result {
+                Ok(user) => user,
+                Err(sqlx::Error::Database(ref db_err)) if db_err.is_unique_violation() => {
+                    return Err(AppError::Conflict("Username already taken".into()));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            sqlx::query("INSERT INTO bridge_puppets (user_id, bridge_id, remote_id) VALUES ($1, $2, $3)")
+                .bind(user.id)
+                .bind(bridge_id)
+                .bind(remote_id)
+                .execute(&mut *tx)
+                .await?;
+            user
+        }
+    };
+
+    tx.commit().await?;
+    if renamed {
+        crate::db::queries::bump_user_server_list_versions(pool, user.id).await?;
+    }
+    Ok(user)
+}
+
+/// Store a message posted by a puppet, keeping the bridged network's timestamp.
+pub async fn insert_bridged_message(
+    pool: &Pool,
+    channel_id: Uuid,
+    sender_id: Uuid,
+    sender_token: &[u8],
+    encrypted_body: &[u8],
+    timestamp: DateTime<Utc>,
+    reply_to_id: Option<Uuid>,
+) -> AppResult<Message> {
+    let msg = sqlx::query_as::<_, Message>(
+        r#"
+        INSERT INTO messages (id, channel_id, sender_token, encrypted_body,
+                             timestamp, has_attachments, sender_id, reply_to_id)
+        VALUES ($1, $2, $3, $4, $5, FALSE, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(channel_id)
+    .bind(sender_token)
+    .bind(encrypted_body)
+    .bind(timestamp)
+    .bind(sender_id)
+    .bind(reply_to_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(msg)
+}
+
+/// Queue a new message for every bridge linked to its channel, except the
+/// bridge whose puppet sent it (bridges never see their own echoes).
+pub async fn enqueue_bridge_events(
+    pool: &Pool,
+    channel_id: Uuid,
+    message_id: Uuid,
+    sender_id: Option<Uuid>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO bridge_events (bridge_id, channel_id, message_id, created_at)
+        SELECT bc.bridge_id, bc.channel_id, $2, CURRENT_TIMESTAMP
+        FROM bridge_channels bc
+        WHERE bc.channel_id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM bridge_puppets bp
+              WHERE bp.bridge_id = bc.bridge_id AND bp.user_id = $3
+          )
+        "#,
+    )
+    .bind(channel_id)
+    .bind(message_id)
+    .bind(sender_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Drop events the bridge has acknowledged (everything up to `since`) and
+/// return the next batch with their messages, plus the cursor to pass back.
+/// Events whose message has since been deleted are skipped.
+pub async fn take_bridge_events(
+    pool: &Pool,
+    bridge_id: Uuid,
+    since: i64,
+    limit: i64,
+) -> AppResult<(Vec<(i64, Message)>, i64)> {
+    sqlx::query("DELETE FROM bridge_events WHERE bridge_id = $1 AND seq <= $2")
+        .bind(bridge_id)
+        .bind(since)
+        .execute(pool)
+        .await?;
+
+    let events: Vec<(i64, Uuid)> = sqlx::query_as(
+        r#"
+        SELECT seq, message_id FROM bridge_events
+        WHERE bridge_id = $1 AND seq > $2
+        ORDER BY seq
+        LIMIT $3
+        "#,
+    )
+    .bind(bridge_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    let Some(&(next_since, _)) = events.last() else {
+        return Ok((Vec::new(), since));
+    };
+
+    let messages = sqlx::query_as::<_, Message>(
+        r#"
+        SELECT m.* FROM bridge_events e
+        INNER JOIN messages m ON m.id = e.message_id AND m.channel_id = e.channel_id
+        WHERE e.bridge_id = $1 AND e.seq > $2 AND e.seq <= $3
+        ORDER BY e.seq
+        "#,
+    )
+    .bind(bridge_id)
+    .bind(since)
+    .bind(next_since)
+    .fetch_all(pool)
+    .await?;
+    let seqs: std::collections::HashMap<Uuid, i64> = events.into_iter().map(|(seq, id)| (id, seq)).collect();
+
+    let batch = messages
+        .into_iter()
+        .filter_map(|m| seqs.get(&m.id).map(|&seq| (seq, m)))
+        .collect();
+    Ok((batch, next_since))
+}
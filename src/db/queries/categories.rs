@@ -1,6 +1,7 @@
 use uuid::Uuid;
 
-use crate::db::Pool;
+use crate::db::queries::SyncEntity;
+use crate::db::{Connection, Pool};
 use crate::errors::AppResult;
 use crate::models::*;
 
@@ -52,12 +53,14 @@ pub async fn update_category(
     category_id: Uuid,
     name: Option<&str>,
     position: Option<i32>,
+    collapsed_by_default: Option<bool>,
 ) -> AppResult<ChannelCategory> {
     let cat = sqlx::query_as::<_, ChannelCategory>(
         r#"
         UPDATE channel_categories
         SET name = COALESCE($2, name),
-            position = COALESCE($3, position)
+            position = COALESCE($3, position),
+            collapsed_by_default = COALESCE($4, collapsed_by_default)
         WHERE id = $1
         RETURNING *
         "#,
@@ -65,16 +68,27 @@ pub async fn update_category(
     .bind(category_id)
     .bind(name)
     .bind(position)
+    .bind(collapsed_by_default)
     .fetch_one(pool)
     .await?;
     Ok(cat)
 }
 
 pub async fn delete_category(pool: &Pool, category_id: Uuid) -> AppResult<()> {
-    sqlx::query("DELETE FROM channel_categories WHERE id = $1")
+    // Channels in the category fall back to uncategorized
+    let channel_ids: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM channels WHERE category_id = $1")
         .bind(category_id)
-        .execute(pool)
+        .fetch_all(pool)
         .await?;
+    let deleted: Option<(Uuid,)> =
+        sqlx::query_as("DELETE FROM channel_categories WHERE id = $1 RETURNING server_id")
+            .bind(category_id)
+            .fetch_optional(pool)
+            .await?;
+    if let Some((server_id,)) = deleted {
+        let channel_ids: Vec<Uuid> = channel_ids.into_iter().map(|r| r.0).collect();
+        crate::db::queries::record_sync_changes(pool, server_id, SyncEntity::Channel, &channel_ids, false).await?;
+    }
     Ok(())
 }
 
@@ -116,9 +130,66 @@ pub async fn reorder_channels(
         .await?;
     }
     tx.commit().await?;
+    let channel_ids: Vec<Uuid> = order.iter().map(|(id, _, _)| *id).collect();
+    crate::db::queries::record_sync_changes(pool, server_id, SyncEntity::Channel, &channel_ids, false).await?;
     Ok(())
 }
 
+/// Apply a complete layout on the caller's transaction. Returns false,
+/// changing nothing, unless it names exactly the server's current channels
+/// and categories (one was created or deleted since the client loaded it).
+/// The caller records the sync changes once the transaction commits.
+pub async fn apply_channel_positions(
+    conn: &mut Connection,
+    server_id: Uuid,
+    positions: &ChannelPositionsResponse,
+) -> AppResult<bool> {
+    let mut category_ids: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM channel_categories WHERE server_id = $1 FOR UPDATE")
+            .bind(server_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    let mut channel_ids: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM channels WHERE server_id = $1 FOR UPDATE")
+        .bind(server_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    let mut given_categories: Vec<Uuid> = positions.categories.iter().map(|c| c.id).collect();
+    let mut given_channels: Vec<Uuid> = positions.channels.iter().map(|c| c.id).collect();
+    for ids in [&mut category_ids, &mut channel_ids, &mut given_categories, &mut given_channels] {
+        ids.sort_unstable();
+    }
+    if category_ids != given_categories || channel_ids != given_channels {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE channel_categories c SET position = v.position
+        FROM UNNEST($2::uuid[], $3::int[]) AS v(id, position)
+        WHERE c.id = v.id AND c.server_id = $1
+        "#,
+    )
+    .bind(server_id)
+    .bind(positions.categories.iter().map(|c| c.id).collect::<Vec<Uuid>>())
+    .bind(positions.categories.iter().map(|c| c.position).collect::<Vec<i32>>())
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        UPDATE channels c SET position = v.position, category_id = v.category_id
+        FROM UNNEST($2::uuid[], $3::int[], $4::uuid[]) AS v(id, position, category_id)
+        WHERE c.id = v.id AND c.server_id = $1
+        "#,
+    )
+    .bind(server_id)
+    .bind(positions.channels.iter().map(|c| c.id).collect::<Vec<Uuid>>())
+    .bind(positions.channels.iter().map(|c| c.position).collect::<Vec<i32>>())
+    .bind(positions.channels.iter().map(|c| c.category_id).collect::<Vec<Option<Uuid>>>())
+    .execute(&mut *conn)
+    .await?;
+    Ok(true)
+}
+
 pub async fn set_channel_category(
     pool: &Pool,
     channel_id: Uuid,
@@ -133,5 +204,6 @@ pub async fn set_channel_category(
     .bind(category_id)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
     Ok(channel)
 }
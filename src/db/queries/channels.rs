@@ -1,7 +1,8 @@
 use uuid::Uuid;
 
+use crate::db::queries::SyncEntity;
 use crate::db::Pool;
-use crate::errors::AppResult;
+use crate::errors::{AppError, AppResult};
 use crate::models::*;
 
 // ─── Channels ──────────────────────────────────────────
@@ -53,6 +54,9 @@ pub async fn create_channel(
     .bind(encrypted)
     .fetch_one(pool)
     .await?;
+    if let Some(server_id) = server_id {
+        crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Channel, channel.id, false).await?;
+    }
     Ok(channel)
 }
 
@@ -95,14 +99,41 @@ pub async fn update_channel_meta(
     encrypted_meta: &[u8],
     encrypted: Option<bool>,
 ) -> AppResult<Channel> {
+    // Turning encryption on drops the plaintext topic and description
     let ch = sqlx::query_as::<_, Channel>(
-        "UPDATE channels SET encrypted_meta = $1, encrypted = COALESCE($3, encrypted) WHERE id = $2 RETURNING *",
+        r#"
+        UPDATE channels SET encrypted_meta = $1, encrypted = COALESCE($3, encrypted),
+            topic = CASE WHEN COALESCE($3, encrypted) THEN NULL ELSE topic END,
+            description = CASE WHEN COALESCE($3, encrypted) THEN NULL ELSE description END
+        WHERE id = $2 RETURNING *
+        "#,
     )
     .bind(encrypted_meta)
     .bind(channel_id)
     .bind(encrypted)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
+    Ok(ch)
+}
+
+/// Set the plaintext topic and description of an unencrypted channel.
+pub async fn update_channel_topic(
+    pool: &Pool,
+    channel_id: Uuid,
+    topic: Option<&str>,
+    description: Option<&str>,
+) -> AppResult<Channel> {
+    let ch = sqlx::query_as::<_, Channel>(
+        "UPDATE channels SET topic = $2, description = $3 WHERE id = $1 AND NOT encrypted RETURNING *",
+    )
+    .bind(channel_id)
+    .bind(topic)
+    .bind(description)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict("Channel is encrypted".into()))?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
     Ok(ch)
 }
 
@@ -118,10 +149,15 @@ pub async fn update_channel_ttl(
     .bind(channel_id)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
     Ok(ch)
 }
 
 pub async fn delete_channel(pool: &Pool, channel_id: Uuid) -> AppResult<()> {
+    let server_id: Option<(Option<Uuid>,)> = sqlx::query_as("SELECT server_id FROM channels WHERE id = $1")
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await?;
     // Delete members first, then message children, then messages, then the channel
     sqlx::query("DELETE FROM channel_members WHERE channel_id = $1")
         .bind(channel_id)
@@ -141,18 +177,16 @@ pub async fn delete_channel(pool: &Pool, channel_id: Uuid) -> AppResult<()> {
         .bind(channel_id)
         .execute(pool)
         .await?;
+    if let Some((Some(server_id),)) = server_id {
+        crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Channel, channel_id, true).await?;
+    }
     Ok(())
 }
 
 /// Clean up child rows for all messages in a channel.
 /// Used when deleting a channel (bulk message delete).
+/// Attachment rows are left for the attachment GC, which also deletes their blobs.
 async fn cleanup_channel_message_children(pool: &Pool, channel_id: Uuid) -> AppResult<()> {
-    sqlx::query(
-        "DELETE FROM attachments WHERE message_id IN (SELECT id FROM messages WHERE channel_id = $1)"
-    )
-    .bind(channel_id)
-    .execute(pool)
-    .await?;
     sqlx::query(
         "DELETE FROM reactions WHERE message_id IN (SELECT id FROM messages WHERE channel_id = $1)"
     )
@@ -240,7 +274,8 @@ pub async fn can_access_channel(pool: &Pool, channel_id: Uuid, user_id: Uuid) ->
             UNION ALL
             SELECT 1 FROM channels c
             JOIN server_members sm ON sm.server_id = c.server_id
-            WHERE c.id = $1 AND sm.user_id = $2 AND c.server_id IS NOT NULL
+            JOIN servers s ON s.id = c.server_id
+            WHERE c.id = $1 AND sm.user_id = $2 AND s.deleted_at IS NULL
         )
         "#,
     )
@@ -260,6 +295,27 @@ pub async fn get_channel_member_ids(pool: &Pool, channel_id: Uuid) -> AppResult<
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
+/// Set (or hand over) the owner of a group DM.
+pub async fn set_channel_owner(pool: &Pool, channel_id: Uuid, owner_id: Option<Uuid>) -> AppResult<()> {
+    sqlx::query("UPDATE channels SET owner_id = $2 WHERE id = $1")
+        .bind(channel_id)
+        .bind(owner_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Longest-standing member of a channel, used to pick a new group DM owner.
+pub async fn get_oldest_channel_member(pool: &Pool, channel_id: Uuid) -> AppResult<Option<Uuid>> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT user_id FROM channel_members WHERE channel_id = $1 ORDER BY joined_at ASC LIMIT 1",
+    )
+    .bind(channel_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
 /// Remove a user from a channel.
 pub async fn remove_channel_member(
     pool: &Pool,
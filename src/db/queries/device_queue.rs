@@ -0,0 +1,110 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Device Message Queue ─────────────────────────────
+
+/// Registered devices of everyone in a DM or group channel. Empty for server
+/// channels, which are not queued per device.
+pub async fn get_channel_member_device_ids(pool: &Pool, channel_id: Uuid) -> AppResult<Vec<Uuid>> {
+    let ids: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT d.id FROM user_devices d
+        INNER JOIN channel_members cm ON cm.user_id = d.user_id
+        INNER JOIN channels c ON c.id = cm.channel_id
+        WHERE cm.channel_id = $1 AND c.channel_type IN ('dm', 'group')
+        "#,
+    )
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+/// Queue a message for each of `device_ids`.
+pub async fn enqueue_device_messages(
+    pool: &Pool,
+    device_ids: &[Uuid],
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO device_message_queue (device_id, channel_id, message_id, queued_at)
+        SELECT d, $2, $3, CURRENT_TIMESTAMP FROM UNNEST($1::uuid[]) AS d
+        "#,
+    )
+    .bind(device_ids)
+    .bind(channel_id)
+    .bind(message_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Drop entries the device has acknowledged (everything up to `since`) and
+/// return the next batch with their messages, plus the cursor to pass back.
+/// Entries whose message has since been deleted are skipped.
+pub async fn take_device_messages(
+    pool: &Pool,
+    device_id: Uuid,
+    since: i64,
+    limit: i64,
+) -> AppResult<(Vec<(i64, Message)>, i64)> {
+    sqlx::query("DELETE FROM device_message_queue WHERE device_id = $1 AND seq <= $2")
+        .bind(device_id)
+        .bind(since)
+        .execute(pool)
+        .await?;
+
+    let entries: Vec<(i64, Uuid)> = sqlx::query_as(
+        r#"
+        SELECT seq, message_id FROM device_message_queue
+        WHERE device_id = $1 AND seq > $2
+        ORDER BY seq
+        LIMIT $3
+        "#,
+    )
+    .bind(device_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    let Some(&(next_since, _)) = entries.last() else {
+        return Ok((Vec::new(), since));
+    };
+
+    let messages = sqlx::query_as::<_, Message>(
+        r#"
+        SELECT m.* FROM device_message_queue q
+        INNER JOIN messages m ON m.id = q.message_id AND m.channel_id = q.channel_id
+        WHERE q.device_id = $1 AND q.seq > $2 AND q.seq <= $3
+        ORDER BY q.seq
+        "#,
+    )
+    .bind(device_id)
+    .bind(since)
+    .bind(next_since)
+    .fetch_all(pool)
+    .await?;
+    let seqs: std::collections::HashMap<Uuid, i64> = entries.into_iter().map(|(seq, id)| (id, seq)).collect();
+
+    let batch = messages
+        .into_iter()
+        .filter_map(|m| seqs.get(&m.id).map(|&seq| (seq, m)))
+        .collect();
+    Ok((batch, next_since))
+}
+
+/// Trim queue entries older than `ttl_days`, acknowledged or not.
+pub async fn purge_expired_device_messages(pool: &Pool, ttl_days: u32) -> AppResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM device_message_queue WHERE queued_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(ttl_days as i32)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,156 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Email outbox ─────────────────────────────────────
+
+pub async fn enqueue_email(pool: &Pool, id: Uuid, kind: &str, payload: &[u8]) -> AppResult<()> {
+    sqlx::query("INSERT INTO email_outbox (id, kind, payload) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(kind)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Claim up to `limit` queued emails that are due, oldest first, pushing
+/// each one's next attempt `lease_secs` ahead so no other worker picks it up
+/// meanwhile. Returns `(id, kind, payload, attempts)`.
+pub async fn claim_email_outbox(
+    pool: &Pool,
+    limit: i64,
+    lease_secs: i64,
+) -> AppResult<Vec<(Uuid, String, Vec<u8>, i32)>> {
+    let rows = sqlx::query_as(
+        r#"UPDATE email_outbox SET next_attempt_at = NOW() + make_interval(secs => $2)
+           WHERE id IN (
+               SELECT id FROM email_outbox
+               WHERE next_attempt_at <= NOW()
+               ORDER BY next_attempt_at
+               LIMIT $1
+               FOR UPDATE SKIP LOCKED
+           )
+           RETURNING id, kind, payload, attempts"#,
+    )
+    .bind(limit)
+    .bind(lease_secs as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Remove a delivered email.
+pub async fn delete_email_outbox(pool: &Pool, id: Uuid) -> AppResult<()> {
+    sqlx::query("DELETE FROM email_outbox WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Record a failed attempt and schedule the next one `delay_secs` from now.
+pub async fn reschedule_email_outbox(pool: &Pool, id: Uuid, error: &str, delay_secs: i64) -> AppResult<()> {
+    sqlx::query(
+        r#"UPDATE email_outbox
+           SET attempts = attempts + 1, last_error = $2,
+               next_attempt_at = NOW() + make_interval(secs => $3)
+           WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(delay_secs as f64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Move a queued email that gave up to the dead letters, payload and all.
+pub async fn dead_letter_email_outbox(
+    pool: &Pool,
+    id: Uuid,
+    provider: &str,
+    error: &str,
+    attempts: i32,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"WITH moved AS (
+               DELETE FROM email_outbox WHERE id = $1 RETURNING id, kind, payload
+           )
+           INSERT INTO email_dead_letters (id, kind, provider, payload, error, attempts)
+           SELECT id, kind, $2, payload, $3, $4 FROM moved"#,
+    )
+    .bind(id)
+    .bind(provider)
+    .bind(error)
+    .bind(attempts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Queued emails, next due first. The encrypted payload is left out.
+pub async fn list_email_outbox(pool: &Pool, limit: i64, offset: i64) -> AppResult<Vec<EmailOutboxEntry>> {
+    let rows = sqlx::query_as::<_, EmailOutboxEntry>(
+        r#"SELECT id, kind, attempts, last_error, next_attempt_at, created_at
+           FROM email_outbox
+           ORDER BY next_attempt_at
+           LIMIT $1 OFFSET $2"#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// ─── Email dead letters ───────────────────────────────
+
+/// Dead letters, newest first. The encrypted payload is left out.
+pub async fn list_email_dead_letters(pool: &Pool, limit: i64, offset: i64) -> AppResult<Vec<EmailDeadLetter>> {
+    let rows = sqlx::query_as::<_, EmailDeadLetter>(
+        r#"SELECT id, kind, provider, error, attempts, created_at, last_attempt_at
+           FROM email_dead_letters
+           ORDER BY created_at DESC
+           LIMIT $1 OFFSET $2"#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_email_dead_letter_payload(pool: &Pool, id: Uuid) -> AppResult<Option<Vec<u8>>> {
+    let row: Option<(Vec<u8>,)> = sqlx::query_as("SELECT payload FROM email_dead_letters WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Record another failed attempt at a dead letter.
+pub async fn update_email_dead_letter_error(pool: &Pool, id: Uuid, provider: &str, error: &str) -> AppResult<()> {
+    sqlx::query(
+        r#"UPDATE email_dead_letters
+           SET provider = $2, error = $3, attempts = attempts + 1, last_attempt_at = NOW()
+           WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(provider)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns whether the dead letter existed.
+pub async fn delete_email_dead_letter(pool: &Pool, id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM email_dead_letters WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Server Events ────────────────────────────────────
+
+/// Event columns plus RSVP counts and the RSVP of the viewer bound as `$1`.
+const EVENT_SELECT: &str = r#"
+    SELECT e.*,
+           (SELECT COUNT(*) FROM server_event_rsvps r
+            WHERE r.event_id = e.id AND r.status = 'interested') AS interested_count,
+           (SELECT COUNT(*) FROM server_event_rsvps r
+            WHERE r.event_id = e.id AND r.status = 'going') AS going_count,
+           (SELECT r.status FROM server_event_rsvps r
+            WHERE r.event_id = e.id AND r.user_id = $1) AS viewer_rsvp
+    FROM server_events e
+"#;
+
+pub async fn create_server_event(
+    pool: &Pool,
+    server_id: Uuid,
+    creator_id: Uuid,
+    encrypted_meta: &[u8],
+    channel_id: Option<Uuid>,
+    starts_at: DateTime<Utc>,
+    ends_at: Option<DateTime<Utc>>,
+) -> AppResult<Uuid> {
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO server_events (server_id, creator_id, encrypted_meta, channel_id, starts_at, ends_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+    )
+    .bind(server_id)
+    .bind(creator_id)
+    .bind(encrypted_meta)
+    .bind(channel_id)
+    .bind(starts_at)
+    .bind(ends_at)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Look up an event as `viewer_id` sees it (their RSVP filled in).
+pub async fn find_server_event(
+    pool: &Pool,
+    event_id: Uuid,
+    viewer_id: Uuid,
+) -> AppResult<Option<ServerEvent>> {
+    let event = sqlx::query_as::<_, ServerEvent>(&format!("{} WHERE e.id = $2", EVENT_SELECT))
+        .bind(viewer_id)
+        .bind(event_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(event)
+}
+
+/// A server's events in start order. Unless `include_past` is set, only
+/// events that have not ended yet (or started, when they have no end).
+pub async fn list_server_events(
+    pool: &Pool,
+    server_id: Uuid,
+    viewer_id: Uuid,
+    include_past: bool,
+) -> AppResult<Vec<ServerEvent>> {
+    let events = sqlx::query_as::<_, ServerEvent>(&format!(
+        r#"{}
+        WHERE e.server_id = $2
+          AND ($3 OR COALESCE(e.ends_at, e.starts_at) >= NOW())
+        ORDER BY e.starts_at
+        LIMIT 200
+        "#,
+        EVENT_SELECT
+    ))
+    .bind(viewer_id)
+    .bind(server_id)
+    .bind(include_past)
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+/// Update an event. `None` leaves a field alone; `Some(None)` clears a
+/// nullable one. Moving the start time re-arms the reminder.
+pub async fn update_server_event(
+    pool: &Pool,
+    event_id: Uuid,
+    encrypted_meta: Option<&[u8]>,
+    channel_id: Option<Option<Uuid>>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<Option<DateTime<Utc>>>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE server_events
+        SET encrypted_meta = COALESCE($2, encrypted_meta),
+            channel_id = CASE WHEN $3::bool THEN $4 ELSE channel_id END,
+            reminder_sent_at = CASE WHEN $5::timestamptz <> starts_at
+                                    THEN NULL ELSE reminder_sent_at END,
+            starts_at = COALESCE($5, starts_at),
+            ends_at = CASE WHEN $6::bool THEN $7 ELSE ends_at END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(event_id)
+    .bind(encrypted_meta)
+    .bind(channel_id.is_some())
+    .bind(channel_id.flatten())
+    .bind(starts_at)
+    .bind(ends_at.is_some())
+    .bind(ends_at.flatten())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_server_event(pool: &Pool, event_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM server_events WHERE id = $1")
+        .bind(event_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set (or change) a user's RSVP.
+pub async fn set_event_rsvp(
+    pool: &Pool,
+    event_id: Uuid,
+    user_id: Uuid,
+    status: RsvpStatus,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO server_event_rsvps (event_id, user_id, status)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (event_id, user_id) DO UPDATE SET status = EXCLUDED.status
+        "#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .bind(status.as_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Withdraw a user's RSVP. Returns true if there was one.
+pub async fn remove_event_rsvp(pool: &Pool, event_id: Uuid, user_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM server_event_rsvps WHERE event_id = $1 AND user_id = $2")
+        .bind(event_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Users who are interested in or going to an event.
+pub async fn get_event_rsvp_user_ids(pool: &Pool, event_id: Uuid) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as("SELECT user_id FROM server_event_rsvps WHERE event_id = $1")
+        .bind(event_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Mark events starting within `minutes` as reminded and return them as
+/// `(event_id, server_id, starts_at)`. Claiming and returning in one statement
+/// keeps concurrent workers from reminding twice.
+pub async fn claim_due_event_reminders(
+    pool: &Pool,
+    minutes: u32,
+) -> AppResult<Vec<(Uuid, Uuid, DateTime<Utc>)>> {
+    let rows = sqlx::query_as::<_, (Uuid, Uuid, DateTime<Utc>)>(
+        r#"
+        UPDATE server_events e
+        SET reminder_sent_at = NOW()
+        FROM servers s
+        WHERE s.id = e.server_id AND s.deleted_at IS NULL
+          AND e.reminder_sent_at IS NULL
+          AND e.starts_at > NOW()
+          AND e.starts_at <= NOW() + make_interval(mins => $1)
+        RETURNING e.id, e.server_id, e.starts_at
+        "#,
+    )
+    .bind(minutes as i32)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
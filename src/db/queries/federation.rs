@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Federation ──────────────────────────────────────
+
+/// Password hash that never verifies — shadow accounts cannot log in.
+const FEDERATED_PASSWORD_HASH: &str = "!FEDERATED_USER_NO_LOGIN!";
+
+pub async fn find_federated_user(pool: &Pool, user_id: Uuid) -> AppResult<Option<FederatedUser>> {
+    let user = sqlx::query_as::<_, FederatedUser>("SELECT * FROM federated_users WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user)
+}
+
+pub async fn find_federated_user_by_remote(
+    pool: &Pool,
+    server_name: &str,
+    remote_user_id: Uuid,
+) -> AppResult<Option<FederatedUser>> {
+    let user = sqlx::query_as::<_, FederatedUser>(
+        "SELECT * FROM federated_users WHERE server_name = $1 AND remote_user_id = $2",
+    )
+    .bind(server_name)
+    .bind(remote_user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(user)
+}
+
+/// Create or refresh the local shadow account for a remote user and return it.
+/// Shadows accept any DM here; the remote user's own server enforces their
+/// real privacy setting.
+pub async fn upsert_federated_user(
+    pool: &Pool,
+    server_name: &str,
+    profile: &FederatedProfile,
+    identity_key: &[u8],
+    signed_prekey: &[u8],
+    signed_prekey_sig: &[u8],
+) -> AppResult<User> {
+    let username = format!("{}@{}", profile.username, server_name);
+    let mut tx = pool.begin().await?;
+
+    let existing: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT user_id FROM federated_users WHERE server_name = $1 AND remote_user_id = $2",
+    )
+    .bind(server_name)
+    .bind(profile.user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user = match existing {
+        Some((user_id,)) => {
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users SET username = $1, display_name = $2, identity_key = $3,
+                    signed_prekey = $4, signed_prekey_sig = $5, updated_at = CURRENT_TIMESTAMP
+                WHERE id = $6
+                RETURNING *
+                "#,
+            )
+            .bind(&username)
+            .bind(&profile.display_name)
+            .bind(identity_key)
+            .bind(signed_prekey)
+            .bind(signed_prekey_sig)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE federated_users SET fetched_at = CURRENT_TIMESTAMP WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            user
+        }
+        None => {
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (id, username, display_name, password_hash,
+                                  identity_key, signed_prekey, signed_prekey_sig,
+                                  dm_privacy, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, 'everyone', CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                RETURNING *
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(&username)
+            .bind(&profile.display_name)
+            .bind(FEDERATED_PASSWORD_HASH)
+            .bind(identity_key)
+            .bind(signed_prekey)
+            .bind(signed_prekey_sig)
+            .fetch_one(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO federated_users (user_id, server_name, remote_user_id, fetched_at)
+                VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+                "#,
+            )
+            .bind(user.id)
+            .bind(server_name)
+            .bind(profile.user_id)
+            .execute(&mut *tx)
+            .await?;
+            user
+        }
+    };
+
+    tx.commit().await?;
+    Ok(user)
+}
+
+/// Remote members of a channel, for outbound relay.
+pub async fn get_channel_federated_members(pool: &Pool, channel_id: Uuid) -> AppResult<Vec<FederatedUser>> {
+    let members = sqlx::query_as::<_, FederatedUser>(
+        r#"
+        SELECT fu.* FROM federated_users fu
+        INNER JOIN channel_members cm ON cm.user_id = fu.user_id
+        WHERE cm.channel_id = $1
+        "#,
+    )
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(members)
+}
+
+pub async fn get_federation_server_key(
+    pool: &Pool,
+    server_name: &str,
+) -> AppResult<Option<(Vec<u8>, DateTime<Utc>)>> {
+    let row = sqlx::query_as::<_, (Vec<u8>, DateTime<Utc>)>(
+        "SELECT public_key, fetched_at FROM federation_servers WHERE server_name = $1",
+    )
+    .bind(server_name)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn upsert_federation_server_key(pool: &Pool, server_name: &str, public_key: &[u8]) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO federation_servers (server_name, public_key, fetched_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP)
+        ON CONFLICT (server_name) DO UPDATE SET
+            public_key = EXCLUDED.public_key,
+            fetched_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(server_name)
+    .bind(public_key)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record an inbound transaction. Returns false if it was already applied.
+pub async fn record_federation_transaction(pool: &Pool, origin: &str, txn_id: &str) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO federation_transactions (origin, txn_id, received_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP)
+        ON CONFLICT (origin, txn_id) DO NOTHING
+        "#,
+    )
+    .bind(origin)
+    .bind(txn_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Create the local side of a federated DM under the id the origin chose, so
+/// both servers refer to the conversation by the same channel id.
+pub async fn create_federated_dm_channel(
+    pool: &Pool,
+    channel_id: Uuid,
+    local_user_id: Uuid,
+    remote_shadow_id: Uuid,
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO channels (id, server_id, encrypted_meta, channel_type, position, is_private, encrypted, created_at)
+        VALUES ($1, NULL, $2, 'dm', 0, FALSE, TRUE, CURRENT_TIMESTAMP)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(channel_id)
+    .bind(Vec::<u8>::new())
+    .execute(&mut *tx)
+    .await?;
+    for user_id in [local_user_id, remote_shadow_id] {
+        sqlx::query(
+            r#"
+            INSERT INTO channel_members (id, channel_id, user_id, joined_at)
+            VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+            ON CONFLICT (channel_id, user_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(channel_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Store a message relayed from another server under its original id.
+/// Returns None if it was already stored.
+pub async fn insert_federated_message(
+    pool: &Pool,
+    message_id: Uuid,
+    channel_id: Uuid,
+    sender_id: Uuid,
+    sender_token: &[u8],
+    encrypted_body: &[u8],
+    timestamp: DateTime<Utc>,
+) -> AppResult<Option<Message>> {
+    // `messages` is partitioned with PRIMARY KEY (id, timestamp), so the
+    // conflict target alone would let a redelivery with another timestamp in;
+    // the NOT EXISTS dedupes on the id.
+    let msg = sqlx::query_as::<_, Message>(
+        r#"
+        INSERT INTO messages (id, channel_id, sender_token, encrypted_body,
+                             timestamp, has_attachments, sender_id)
+        SELECT $1, $2, $3, $4, $5, FALSE, $6
+        WHERE NOT EXISTS (SELECT 1 FROM messages WHERE id = $1)
+        ON CONFLICT (id, timestamp) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(sender_token)
+    .bind(encrypted_body)
+    .bind(timestamp)
+    .bind(sender_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(msg)
+}
@@ -177,6 +177,71 @@ pub async fn get_pending_dm_channels(pool: &Pool, user_id: Uuid) -> AppResult<Ve
     Ok(channels)
 }
 
+// ─── Relationships ─────────────────────────────────────
+
+/// Friends, pending requests and blocks of `user_id`, optionally narrowed to
+/// one other user. A block hides any friendship with the same user.
+async fn query_relationships(
+    pool: &Pool,
+    user_id: Uuid,
+    other_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<RelationshipResponse>> {
+    let rows = sqlx::query_as::<_, RelationshipResponse>(
+        r#"
+        SELECT * FROM (
+            SELECT u.id AS user_id, u.username, u.display_name, u.avatar_url,
+                   CASE WHEN f.status = 'accepted' THEN 'friend'
+                        WHEN f.requester_id = $1 THEN 'outgoing'
+                        ELSE 'incoming' END AS kind,
+                   f.id AS friendship_id, f.updated_at AS since
+            FROM friendships f
+            INNER JOIN users u
+                ON u.id = CASE WHEN f.requester_id = $1 THEN f.addressee_id ELSE f.requester_id END
+            WHERE (f.requester_id = $1 OR f.addressee_id = $1)
+              AND NOT EXISTS (
+                SELECT 1 FROM blocked_users b WHERE b.blocker_id = $1 AND b.blocked_id = u.id
+              )
+            UNION ALL
+            SELECT u.id AS user_id, u.username, u.display_name, u.avatar_url,
+                   'blocked' AS kind, NULL::uuid AS friendship_id, b.created_at AS since
+            FROM blocked_users b
+            INNER JOIN users u ON u.id = b.blocked_id
+            WHERE b.blocker_id = $1
+        ) AS relationships
+        WHERE $2::uuid IS NULL OR user_id = $2
+        ORDER BY since DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(user_id)
+    .bind(other_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_relationships(
+    pool: &Pool,
+    user_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<RelationshipResponse>> {
+    query_relationships(pool, user_id, None, limit, offset).await
+}
+
+/// `user_id`'s relationship with `other_id`, if any.
+pub async fn find_relationship(
+    pool: &Pool,
+    user_id: Uuid,
+    other_id: Uuid,
+) -> AppResult<Option<RelationshipResponse>> {
+    Ok(query_relationships(pool, user_id, Some(other_id), 1, 0).await?.pop())
+}
+
 // ─── Mutual Friends / Servers ───────────────────────────
 
 pub async fn get_mutual_friends(
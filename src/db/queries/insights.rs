@@ -0,0 +1,155 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Server Insights ─────────────────────────────────
+
+/// The most recent day any server has a rollup for.
+pub async fn latest_insights_day(pool: &Pool) -> AppResult<Option<NaiveDate>> {
+    let row: (Option<NaiveDate>,) = sqlx::query_as("SELECT MAX(day) FROM server_insights_daily")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Recompute the daily rollups of every server and channel for the UTC days
+/// `from..=to`, then drop membership events older than `from`, which no
+/// later run counts again. Returns the number of server-days written.
+pub async fn rollup_insights(pool: &Pool, from: NaiveDate, to: NaiveDate) -> AppResult<u64> {
+    let start = from.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc();
+    let end = (to + chrono::Duration::days(1)).and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO channel_insights_daily (channel_id, server_id, day, messages, active_members)
+        SELECT m.channel_id, c.server_id, (m.timestamp AT TIME ZONE 'UTC')::date,
+               COUNT(*), COUNT(DISTINCT m.sender_id)
+        FROM messages m
+        JOIN channels c ON c.id = m.channel_id
+        WHERE c.server_id IS NOT NULL AND m.message_type <> 'system'
+          AND m.timestamp >= $1 AND m.timestamp < $2
+        GROUP BY 1, 2, 3
+        ON CONFLICT (channel_id, day) DO UPDATE
+        SET messages = EXCLUDED.messages, active_members = EXCLUDED.active_members
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .execute(&mut *tx)
+    .await?;
+
+    let written = sqlx::query(
+        r#"
+        INSERT INTO server_insights_daily (server_id, day, messages, active_members, joins, leaves, updated_at)
+        SELECT server_id, day, SUM(messages)::INT, SUM(active_members)::INT,
+               SUM(joins)::INT, SUM(leaves)::INT, NOW()
+        FROM (
+            SELECT c.server_id, (m.timestamp AT TIME ZONE 'UTC')::date AS day,
+                   COUNT(*) AS messages, COUNT(DISTINCT m.sender_id) AS active_members,
+                   0 AS joins, 0 AS leaves
+            FROM messages m
+            JOIN channels c ON c.id = m.channel_id
+            WHERE c.server_id IS NOT NULL AND m.message_type <> 'system'
+              AND m.timestamp >= $1 AND m.timestamp < $2
+            GROUP BY 1, 2
+            UNION ALL
+            SELECT server_id, (created_at AT TIME ZONE 'UTC')::date, 0, 0,
+                   COUNT(*) FILTER (WHERE joined), COUNT(*) FILTER (WHERE NOT joined)
+            FROM server_membership_events
+            WHERE created_at >= $1 AND created_at < $2
+            GROUP BY 1, 2
+        ) activity
+        GROUP BY server_id, day
+        ON CONFLICT (server_id, day) DO UPDATE
+        SET messages = EXCLUDED.messages, active_members = EXCLUDED.active_members,
+            joins = EXCLUDED.joins, leaves = EXCLUDED.leaves, updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    sqlx::query("DELETE FROM server_membership_events WHERE created_at < $1")
+        .bind(start)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(written)
+}
+
+/// Drop rollups from before `before`.
+pub async fn purge_old_insights(pool: &Pool, before: NaiveDate) -> AppResult<u64> {
+    let channels = sqlx::query("DELETE FROM channel_insights_daily WHERE day < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    let servers = sqlx::query("DELETE FROM server_insights_daily WHERE day < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    Ok(channels.rows_affected() + servers.rows_affected())
+}
+
+/// A server's rollups for the days `from..=to` that had any activity.
+pub async fn get_server_insights_days(
+    pool: &Pool,
+    server_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<Vec<ServerInsightsDay>> {
+    let days = sqlx::query_as::<_, ServerInsightsDay>(
+        r#"
+        SELECT day, messages, active_members, joins, leaves
+        FROM server_insights_daily
+        WHERE server_id = $1 AND day BETWEEN $2 AND $3
+        ORDER BY day
+        "#,
+    )
+    .bind(server_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(days)
+}
+
+/// The server's channels ranked by messages over the days `from..=to`.
+pub async fn get_channel_insights(
+    pool: &Pool,
+    server_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<Vec<ChannelInsights>> {
+    let channels = sqlx::query_as::<_, ChannelInsights>(
+        r#"
+        SELECT channel_id, SUM(messages)::BIGINT AS messages, COUNT(*) AS active_days
+        FROM channel_insights_daily
+        WHERE server_id = $1 AND day BETWEEN $2 AND $3
+        GROUP BY channel_id
+        ORDER BY messages DESC, channel_id
+        "#,
+    )
+    .bind(server_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(channels)
+}
+
+/// When the rollup last wrote a row for the server.
+pub async fn get_insights_updated_at(pool: &Pool, server_id: Uuid) -> AppResult<Option<DateTime<Utc>>> {
+    let row: (Option<DateTime<Utc>>,) =
+        sqlx::query_as("SELECT MAX(updated_at) FROM server_insights_daily WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(row.0)
+}
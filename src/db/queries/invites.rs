@@ -14,11 +14,12 @@ pub async fn create_invite(
     code: &str,
     max_uses: Option<i32>,
     expires_at: Option<DateTime<Utc>>,
+    temporary: bool,
 ) -> AppResult<Invite> {
     let invite = sqlx::query_as::<_, Invite>(
         r#"
-        INSERT INTO invites (id, server_id, created_by, code, max_uses, use_count, expires_at, created_at)
-        VALUES ($1, $2, $3, $4, $5, 0, $6, CURRENT_TIMESTAMP)
+        INSERT INTO invites (id, server_id, created_by, code, max_uses, use_count, expires_at, created_at, temporary)
+        VALUES ($1, $2, $3, $4, $5, 0, $6, CURRENT_TIMESTAMP, $7)
         RETURNING *
         "#,
     )
@@ -28,19 +29,35 @@ pub async fn create_invite(
     .bind(code)
     .bind(max_uses)
     .bind(expires_at)
+    .bind(temporary)
     .fetch_one(pool)
     .await?;
     Ok(invite)
 }
 
-pub async fn find_invite_by_code(pool: &Pool, code: &str) -> AppResult<Option<Invite>> {
-    let invite = sqlx::query_as::<_, Invite>("SELECT * FROM invites WHERE code = $1")
-        .bind(code)
+pub async fn find_invite_by_id(pool: &Pool, invite_id: Uuid) -> AppResult<Option<Invite>> {
+    let invite = sqlx::query_as::<_, Invite>("SELECT * FROM invites WHERE id = $1")
+        .bind(invite_id)
         .fetch_optional(pool)
         .await?;
     Ok(invite)
 }
 
+pub async fn find_invite_by_code(pool: &Pool, code: &str) -> AppResult<Option<Invite>> {
+    // Invites to a server in its deletion grace period stop working
+    let invite = sqlx::query_as::<_, Invite>(
+        r#"
+        SELECT i.* FROM invites i
+        JOIN servers s ON s.id = i.server_id
+        WHERE i.code = $1 AND s.deleted_at IS NULL
+        "#,
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await?;
+    Ok(invite)
+}
+
 pub async fn get_server_invites(pool: &Pool, server_id: Uuid, limit: i64, offset: i64) -> AppResult<Vec<Invite>> {
     let invites = sqlx::query_as::<_, Invite>(
         "SELECT * FROM invites WHERE server_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
@@ -69,6 +86,37 @@ pub async fn delete_invite(pool: &Pool, invite_id: Uuid) -> AppResult<()> {
     Ok(())
 }
 
+/// Mark a member who joined with a temporary invite.
+pub async fn mark_member_temporary(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE server_members SET temporary = TRUE WHERE server_id = $1 AND user_id = $2")
+        .bind(server_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    crate::db::queries::record_sync_change(pool, server_id, crate::db::queries::SyncEntity::Member, user_id, false)
+        .await
+}
+
+/// Servers where the user is a temporary member holding no role, and so
+/// should be removed once they go offline. Owners are never listed.
+pub async fn get_lapsed_temporary_memberships(pool: &Pool, user_id: Uuid) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT sm.server_id FROM server_members sm
+        JOIN servers s ON s.id = sm.server_id
+        WHERE sm.user_id = $1 AND sm.temporary AND s.owner_id <> sm.user_id
+          AND NOT EXISTS (
+            SELECT 1 FROM member_roles mr
+            WHERE mr.server_id = sm.server_id AND mr.user_id = sm.user_id
+          )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
 // ─── Registration Invites (instance-level) ────────────
 
 pub async fn find_registration_invite_by_code(
@@ -90,7 +138,7 @@ pub async fn consume_registration_invite(
     user_id: Uuid,
 ) -> AppResult<()> {
     sqlx::query(
-        "UPDATE registration_invites SET used_by = $1, used_at = NOW() WHERE id = $2 AND used_by IS NULL",
+        "UPDATE registration_invites SET used_by = $1, used_at = NOW() WHERE id = $2 AND used_by IS NULL AND revoked_at IS NULL",
     )
     .bind(user_id)
     .bind(invite_id)
@@ -163,16 +211,41 @@ pub async fn list_all_registration_invites(
     Ok(invites)
 }
 
-/// Count beta codes (registration invites with created_by = NULL).
+/// Count the emailed beta codes that hold a slot under the cap: redeemed
+/// or still usable. Expired and revoked codes free their slot.
 pub async fn count_beta_codes(pool: &Pool) -> AppResult<i64> {
     let row: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM registration_invites WHERE created_by IS NULL",
+        r#"SELECT COUNT(*) FROM registration_invites
+           WHERE created_by IS NULL AND email_hash IS NOT NULL
+             AND (used_at IS NOT NULL
+                  OR (revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())))"#,
     )
     .fetch_one(pool)
     .await?;
     Ok(row.0)
 }
 
+/// Issued/redeemed/expired/revoked counts for beta codes, for the operator dashboard.
+/// Returns (issued, redeemed, expired, revoked, issued_last_7_days, redeemed_last_7_days).
+pub async fn get_beta_code_counts(pool: &Pool) -> AppResult<(i64, i64, i64, i64, i64, i64)> {
+    let row: (i64, i64, i64, i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE used_at IS NOT NULL),
+               COUNT(*) FILTER (WHERE used_at IS NULL AND revoked_at IS NULL
+                                  AND expires_at IS NOT NULL AND expires_at <= NOW()),
+               COUNT(*) FILTER (WHERE used_at IS NULL AND revoked_at IS NOT NULL),
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '7 days'),
+               COUNT(*) FILTER (WHERE used_at > NOW() - INTERVAL '7 days')
+        FROM registration_invites
+        WHERE created_by IS NULL
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
 /// Check if a beta code already exists for the given email hash.
 pub async fn beta_code_exists_for_email(pool: &Pool, email_hash: &str) -> AppResult<bool> {
     let row: (bool,) = sqlx::query_as(
@@ -201,6 +274,187 @@ pub async fn create_beta_invite(pool: &Pool, expiry_days: i64, email_hash: &str)
     Ok(invite)
 }
 
+/// Status of a beta code as listed to operators; revocation wins over expiry.
+const BETA_CODE_STATUS: &str = r#"
+    CASE WHEN used_at IS NOT NULL THEN 'redeemed'
+         WHEN revoked_at IS NOT NULL THEN 'revoked'
+         WHEN expires_at IS NOT NULL AND expires_at <= NOW() THEN 'expired'
+         ELSE 'active' END"#;
+
+/// Beta codes, newest first, optionally only those in one status.
+pub async fn list_beta_codes(
+    pool: &Pool,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<AdminBetaCode>> {
+    let codes = sqlx::query_as::<_, AdminBetaCode>(&format!(
+        r#"SELECT * FROM (
+               SELECT id, code, email_hash, label, {BETA_CODE_STATUS} AS status,
+                      expires_at, created_at, used_at, revoked_at
+               FROM registration_invites
+               WHERE created_by IS NULL
+           ) codes
+           WHERE $1::TEXT IS NULL OR status = $1
+           ORDER BY created_at DESC
+           LIMIT $2 OFFSET $3"#
+    ))
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(codes)
+}
+
+/// Generate beta codes for an event. Generated codes carry no email hash.
+pub async fn create_labeled_beta_codes(
+    pool: &Pool,
+    count: u32,
+    expiry_days: i64,
+    label: Option<&str>,
+) -> AppResult<Vec<AdminBetaCode>> {
+    let mut tx = pool.begin().await?;
+    let mut codes = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let code = sqlx::query_as::<_, AdminBetaCode>(
+            r#"INSERT INTO registration_invites (id, code, created_by, expires_at, created_at, label)
+               VALUES ($1, $2, NULL, NOW() + make_interval(days => $3), NOW(), $4)
+               RETURNING id, code, email_hash, label, 'active' AS status,
+                         expires_at, created_at, used_at, revoked_at"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(crate::crypto::generate_invite_code())
+        .bind(expiry_days as i32)
+        .bind(label)
+        .fetch_one(&mut *tx)
+        .await?;
+        codes.push(code);
+    }
+    tx.commit().await?;
+    Ok(codes)
+}
+
+/// Revoke an unredeemed beta code. Returns false if there is no such code
+/// or it was already redeemed or revoked.
+pub async fn revoke_beta_code(pool: &Pool, invite_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"UPDATE registration_invites SET revoked_at = NOW()
+           WHERE id = $1 AND created_by IS NULL AND used_by IS NULL AND revoked_at IS NULL"#,
+    )
+    .bind(invite_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ─── Beta Waitlist ────────────────────────────────────
+
+/// 1-based queue position of a waiting email hash.
+pub async fn beta_waitlist_position(pool: &Pool, email_hash: &str) -> AppResult<Option<i64>> {
+    let row: Option<(i64,)> = sqlx::query_as(
+        r#"SELECT (SELECT COUNT(*) FROM beta_waitlist w WHERE w.id <= me.id)
+           FROM beta_waitlist me WHERE me.email_hash = $1"#,
+    )
+    .bind(email_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Add an email to the end of the waitlist (no-op if it is already waiting)
+/// and return its position.
+pub async fn join_beta_waitlist(
+    pool: &Pool,
+    email_hash: &str,
+    email_ciphertext: &[u8],
+    locale: Option<&str>,
+) -> AppResult<i64> {
+    sqlx::query(
+        r#"INSERT INTO beta_waitlist (email_hash, email_ciphertext, locale)
+           VALUES ($1, $2, $3)
+           ON CONFLICT (email_hash) DO NOTHING"#,
+    )
+    .bind(email_hash)
+    .bind(email_ciphertext)
+    .bind(locale)
+    .execute(pool)
+    .await?;
+    Ok(beta_waitlist_position(pool, email_hash).await?.unwrap_or(1))
+}
+
+pub async fn count_beta_waitlist(pool: &Pool) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM beta_waitlist")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Issue beta codes to the first `count` waiting emails, removing them from
+/// the waitlist. Returns each encrypted address with its new code and the
+/// email's locale. Entries being promoted by another instance are skipped.
+pub async fn promote_beta_waitlist(
+    pool: &Pool,
+    count: i64,
+    expiry_days: i64,
+) -> AppResult<Vec<(Vec<u8>, String, Option<String>)>> {
+    let mut tx = pool.begin().await?;
+    let waiting: Vec<(String, Vec<u8>, Option<String>)> = sqlx::query_as(
+        r#"DELETE FROM beta_waitlist WHERE id IN (
+               SELECT id FROM beta_waitlist ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED
+           )
+           RETURNING email_hash, email_ciphertext, locale"#,
+    )
+    .bind(count)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut promoted = Vec::with_capacity(waiting.len());
+    for (email_hash, email_ciphertext, locale) in waiting {
+        let code = crate::crypto::generate_invite_code();
+        sqlx::query(
+            r#"INSERT INTO registration_invites (id, code, created_by, expires_at, created_at, email_hash)
+               VALUES ($1, $2, NULL, NOW() + make_interval(days => $3), NOW(), $4)"#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&code)
+        .bind(expiry_days as i32)
+        .bind(&email_hash)
+        .execute(&mut *tx)
+        .await?;
+        promoted.push((email_ciphertext, code, locale));
+    }
+    tx.commit().await?;
+    Ok(promoted)
+}
+
+/// Operator override of `BETA_CODE_LIMIT`, if one is set.
+pub async fn get_beta_code_limit_override(pool: &Pool) -> AppResult<Option<i32>> {
+    let row: Option<(Option<i32>,)> =
+        sqlx::query_as("SELECT code_limit FROM beta_settings")
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|r| r.0))
+}
+
+pub async fn set_beta_code_limit_override(
+    pool: &Pool,
+    limit: Option<i32>,
+    updated_by: Uuid,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"INSERT INTO beta_settings (id, code_limit, updated_by, updated_at)
+           VALUES (TRUE, $1, $2, NOW())
+           ON CONFLICT (id) DO UPDATE
+           SET code_limit = EXCLUDED.code_limit, updated_by = EXCLUDED.updated_by, updated_at = NOW()"#,
+    )
+    .bind(limit)
+    .bind(updated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 pub async fn delete_registration_invite(pool: &Pool, invite_id: Uuid) -> AppResult<bool> {
     let result = sqlx::query(
         "DELETE FROM registration_invites WHERE id = $1 AND used_by IS NULL",
@@ -210,3 +464,62 @@ pub async fn delete_registration_invite(pool: &Pool, invite_id: Uuid) -> AppResu
     .await?;
     Ok(result.rows_affected() > 0)
 }
+
+// ─── Pending Registrations ───────────────────────────
+
+pub async fn create_pending_registration(pool: &Pool, user_id: Uuid) -> AppResult<()> {
+    sqlx::query("INSERT INTO pending_registrations (user_id) VALUES ($1)")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn is_registration_pending(pool: &Pool, user_id: Uuid) -> AppResult<bool> {
+    let row: Option<(Uuid,)> =
+        sqlx::query_as("SELECT user_id FROM pending_registrations WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.is_some())
+}
+
+/// Accounts awaiting approval, oldest first.
+pub async fn list_pending_registrations(
+    pool: &Pool,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<PendingRegistration>> {
+    let rows = sqlx::query_as::<_, PendingRegistration>(
+        r#"SELECT u.id AS user_id, u.username, u.display_name, u.abuse_score, p.created_at
+           FROM pending_registrations p
+           JOIN users u ON u.id = p.user_id
+           ORDER BY p.created_at, u.id
+           LIMIT $1 OFFSET $2"#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Let a pending account in. Returns false if it wasn't pending.
+pub async fn approve_pending_registration(pool: &Pool, user_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM pending_registrations WHERE user_id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a pending account. Returns false if it wasn't pending.
+pub async fn reject_pending_registration(pool: &Pool, user_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        "DELETE FROM users WHERE id = $1 AND id IN (SELECT user_id FROM pending_registrations)",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
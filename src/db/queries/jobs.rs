@@ -0,0 +1,217 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::{dialect, Pool};
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Background Jobs ──────────────────────────────────
+
+/// Queue a job. If an unattempted job with the same `dedupe_key` is already
+/// queued, that job is returned instead, moved up to `run_at` if sooner.
+#[allow(clippy::too_many_arguments)]
+pub async fn enqueue_job(
+    pool: &Pool,
+    id: Uuid,
+    kind: &str,
+    payload: &serde_json::Value,
+    run_at: DateTime<Utc>,
+    max_attempts: i32,
+    dedupe_key: Option<&str>,
+    created_by: Option<Uuid>,
+) -> AppResult<BackgroundJob> {
+    let job = sqlx::query_as::<_, BackgroundJob>(&format!(
+        r#"
+        INSERT INTO background_jobs (id, kind, payload, run_at, max_attempts, dedupe_key, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (dedupe_key) WHERE dedupe_key IS NOT NULL AND status = 'queued' AND attempts = 0
+        DO UPDATE SET
+            run_at = CASE WHEN EXCLUDED.run_at < background_jobs.run_at
+                          THEN EXCLUDED.run_at ELSE background_jobs.run_at END,
+            updated_at = {now}
+        RETURNING *
+        "#,
+        now = dialect::NOW
+    ))
+    .bind(id)
+    .bind(kind)
+    .bind(payload)
+    .bind(run_at)
+    .bind(max_attempts)
+    .bind(dedupe_key)
+    .bind(created_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(job)
+}
+
+/// Claim up to `limit` due jobs of `kind` for `lease_secs`, counting the
+/// attempt. Running jobs whose lease ran out (their worker died) are claimed
+/// again while they have attempts left.
+pub async fn claim_jobs(pool: &Pool, kind: &str, limit: i64, lease_secs: i64) -> AppResult<Vec<BackgroundJob>> {
+    let jobs = sqlx::query_as::<_, BackgroundJob>(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'running', attempts = attempts + 1, locked_until = $3, updated_at = {now}
+        WHERE id IN (
+            SELECT id FROM background_jobs
+            WHERE kind = $1
+              AND ((status = 'queued' AND run_at <= {now})
+                OR (status = 'running' AND locked_until < {now} AND attempts < max_attempts))
+            ORDER BY run_at
+            LIMIT $2
+            {skip_locked}
+        )
+        RETURNING *
+        "#,
+        now = dialect::NOW,
+        skip_locked = dialect::SKIP_LOCKED
+    ))
+    .bind(kind)
+    .bind(limit)
+    .bind(Utc::now() + Duration::seconds(lease_secs))
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
+
+/// Fail running jobs whose lease ran out on their last attempt.
+pub async fn fail_abandoned_jobs(pool: &Pool) -> AppResult<u64> {
+    let result = sqlx::query(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'failed', last_error = 'Worker stopped before the job finished',
+            locked_until = NULL, finished_at = {now}, updated_at = {now}
+        WHERE status = 'running' AND locked_until < {now} AND attempts >= max_attempts
+        "#,
+        now = dialect::NOW
+    ))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Replace a running job's payload, e.g. with how far it got.
+pub async fn checkpoint_job(pool: &Pool, job_id: Uuid, payload: &serde_json::Value) -> AppResult<()> {
+    sqlx::query(&format!(
+        "UPDATE background_jobs SET payload = $2, updated_at = {} WHERE id = $1 AND status = 'running'",
+        dialect::NOW
+    ))
+    .bind(job_id)
+    .bind(payload)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mark a running job done. Like `retry_job` and `fail_job`, this leaves a
+/// job cancelled mid-attempt cancelled.
+pub async fn complete_job(pool: &Pool, job_id: Uuid) -> AppResult<()> {
+    sqlx::query(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'completed', locked_until = NULL, finished_at = {now}, updated_at = {now}
+        WHERE id = $1 AND status = 'running'
+        "#,
+        now = dialect::NOW
+    ))
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt and queue the next one `delay_secs` from now.
+pub async fn retry_job(pool: &Pool, job_id: Uuid, error: &str, delay_secs: i64) -> AppResult<()> {
+    sqlx::query(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'queued', last_error = $2, locked_until = NULL, run_at = $3, updated_at = {}
+        WHERE id = $1 AND status = 'running'
+        "#,
+        dialect::NOW
+    ))
+    .bind(job_id)
+    .bind(error)
+    .bind(Utc::now() + Duration::seconds(delay_secs))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn fail_job(pool: &Pool, job_id: Uuid, error: &str) -> AppResult<()> {
+    sqlx::query(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'failed', last_error = $2, locked_until = NULL, finished_at = {now}, updated_at = {now}
+        WHERE id = $1 AND status = 'running'
+        "#,
+        now = dialect::NOW
+    ))
+    .bind(job_id)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Cancel a queued or running job. A running attempt isn't interrupted, but
+/// its outcome is discarded and it isn't retried. None if already finished.
+pub async fn cancel_job(pool: &Pool, job_id: Uuid) -> AppResult<Option<BackgroundJob>> {
+    let job = sqlx::query_as::<_, BackgroundJob>(&format!(
+        r#"
+        UPDATE background_jobs
+        SET status = 'cancelled', locked_until = NULL, finished_at = {now}, updated_at = {now}
+        WHERE id = $1 AND status IN ('queued', 'running')
+        RETURNING *
+        "#,
+        now = dialect::NOW
+    ))
+    .bind(job_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
+
+pub async fn get_job(pool: &Pool, job_id: Uuid) -> AppResult<Option<BackgroundJob>> {
+    let job = sqlx::query_as::<_, BackgroundJob>("SELECT * FROM background_jobs WHERE id = $1")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(job)
+}
+
+/// Jobs newest first, optionally filtered by status and kind.
+pub async fn list_jobs(
+    pool: &Pool,
+    status: Option<&str>,
+    kind: Option<&str>,
+    limit: i64,
+) -> AppResult<Vec<BackgroundJob>> {
+    let jobs = sqlx::query_as::<_, BackgroundJob>(
+        r#"
+        SELECT * FROM background_jobs
+        WHERE ($1 IS NULL OR status = $1)
+          AND ($2 IS NULL OR kind = $2)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(status)
+    .bind(kind)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
+
+/// Delete completed, failed and cancelled jobs that finished over `days` ago.
+pub async fn purge_finished_jobs(pool: &Pool, days: u32) -> AppResult<u64> {
+    let result = sqlx::query(&format!(
+        "DELETE FROM background_jobs WHERE finished_at < {}",
+        dialect::ago(&format!("{} days", days))
+    ))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -1,7 +1,7 @@
 use uuid::Uuid;
 
 use crate::db::Pool;
-use crate::errors::AppResult;
+use crate::errors::{AppError, AppResult};
 use crate::models::*;
 
 // ─── Key Backups ─────────────────────────────────────
@@ -55,3 +55,378 @@ pub async fn delete_key_backup(pool: &Pool, user_id: Uuid) -> AppResult<()> {
         .await?;
     Ok(())
 }
+
+// ─── Key Backup Versions ─────────────────────────────
+
+/// Create the next backup version for a user. Numbers count up across
+/// deleted versions so a stale client can never write into a new backup.
+pub async fn create_key_backup_version(
+    pool: &Pool,
+    user_id: Uuid,
+    algorithm: &str,
+    auth_data: &[u8],
+) -> AppResult<KeyBackupVersion> {
+    let version = sqlx::query_as::<_, KeyBackupVersion>(
+        r#"
+        INSERT INTO key_backup_versions (user_id, version, algorithm, auth_data, created_at)
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, CURRENT_TIMESTAMP
+        FROM key_backup_versions WHERE user_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(algorithm)
+    .bind(auth_data)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            AppError::Conflict("Another backup version was created concurrently".into())
+        }
+        other => AppError::Database(other),
+    })?;
+    Ok(version)
+}
+
+/// The newest non-deleted backup version.
+pub async fn get_current_key_backup_version(pool: &Pool, user_id: Uuid) -> AppResult<Option<KeyBackupVersion>> {
+    let version = sqlx::query_as::<_, KeyBackupVersion>(
+        r#"
+        SELECT * FROM key_backup_versions
+        WHERE user_id = $1 AND deleted_at IS NULL
+        ORDER BY version DESC LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(version)
+}
+
+pub async fn find_key_backup_version(
+    pool: &Pool,
+    user_id: Uuid,
+    version: i32,
+) -> AppResult<Option<KeyBackupVersion>> {
+    let version = sqlx::query_as::<_, KeyBackupVersion>(
+        "SELECT * FROM key_backup_versions WHERE user_id = $1 AND version = $2 AND deleted_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?;
+    Ok(version)
+}
+
+/// Drop a version's session keys and tombstone the version. Returns false if
+/// it did not exist or was already deleted.
+pub async fn delete_key_backup_version(pool: &Pool, user_id: Uuid, version: i32) -> AppResult<bool> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE key_backup_versions SET deleted_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND version = $2 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(version)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM key_backup_sessions WHERE user_id = $1 AND version = $2")
+        .bind(user_id)
+        .bind(version)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn count_key_backup_sessions(pool: &Pool, user_id: Uuid, version: i32) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM key_backup_sessions WHERE user_id = $1 AND version = $2",
+    )
+    .bind(user_id)
+    .bind(version)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Store session keys in a backup version. An existing key is only replaced
+/// by a better one: verified beats unverified, then a lower first message
+/// index (decrypts more history), then fewer forwards.
+pub async fn upsert_key_backup_sessions(
+    pool: &Pool,
+    user_id: Uuid,
+    version: i32,
+    sessions: &[(&BackupSessionKey, Vec<u8>)],
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    for (key, session_data) in sessions {
+        sqlx::query(
+            r#"
+            INSERT INTO key_backup_sessions
+                (user_id, version, channel_id, session_id, first_message_index,
+                 forwarded_count, is_verified, session_data, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)
+            ON CONFLICT (user_id, version, channel_id, session_id) DO UPDATE SET
+                first_message_index = EXCLUDED.first_message_index,
+                forwarded_count = EXCLUDED.forwarded_count,
+                is_verified = EXCLUDED.is_verified,
+                session_data = EXCLUDED.session_data,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE (EXCLUDED.is_verified AND NOT key_backup_sessions.is_verified)
+               OR (EXCLUDED.is_verified = key_backup_sessions.is_verified
+                   AND (EXCLUDED.first_message_index, EXCLUDED.forwarded_count)
+                     < (key_backup_sessions.first_message_index, key_backup_sessions.forwarded_count))
+            "#,
+        )
+        .bind(user_id)
+        .bind(version)
+        .bind(key.channel_id)
+        .bind(&key.session_id)
+        .bind(key.first_message_index)
+        .bind(key.forwarded_count)
+        .bind(key.is_verified)
+        .bind(session_data)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_key_backup_sessions(
+    pool: &Pool,
+    user_id: Uuid,
+    version: i32,
+    channel_id: Option<Uuid>,
+) -> AppResult<Vec<KeyBackupSession>> {
+    let sessions = sqlx::query_as::<_, KeyBackupSession>(
+        r#"
+        SELECT channel_id, session_id, first_message_index, forwarded_count,
+               is_verified, session_data, updated_at
+        FROM key_backup_sessions
+        WHERE user_id = $1 AND version = $2 AND ($3 IS NULL OR channel_id = $3)
+        ORDER BY channel_id, session_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(version)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(sessions)
+}
+
+pub async fn delete_key_backup_sessions(
+    pool: &Pool,
+    user_id: Uuid,
+    version: i32,
+    channel_id: Option<Uuid>,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM key_backup_sessions WHERE user_id = $1 AND version = $2 AND ($3 IS NULL OR channel_id = $3)",
+    )
+    .bind(user_id)
+    .bind(version)
+    .bind(channel_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// ─── Devices ─────────────────────────────────────────
+
+pub async fn insert_user_device(
+    pool: &Pool,
+    user_id: Uuid,
+    name: &str,
+    identity_key: &[u8],
+    signed_prekey: &[u8],
+    signed_prekey_sig: &[u8],
+) -> AppResult<Option<UserDevice>> {
+    let device = sqlx::query_as::<_, UserDevice>(
+        r#"
+        INSERT INTO user_devices (id, user_id, name, identity_key, signed_prekey, signed_prekey_sig)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, identity_key) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(name)
+    .bind(identity_key)
+    .bind(signed_prekey)
+    .bind(signed_prekey_sig)
+    .fetch_optional(pool)
+    .await?;
+    Ok(device)
+}
+
+pub async fn count_user_devices(pool: &Pool, user_id: Uuid) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_devices WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+pub async fn find_user_device(pool: &Pool, device_id: Uuid) -> AppResult<Option<UserDevice>> {
+    let device = sqlx::query_as::<_, UserDevice>("SELECT * FROM user_devices WHERE id = $1")
+        .bind(device_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(device)
+}
+
+/// A user's devices, each with `viewer_id`'s verification status. A verdict
+/// made against an older identity key no longer counts.
+pub async fn get_user_devices_for_viewer(
+    pool: &Pool,
+    user_id: Uuid,
+    viewer_id: Uuid,
+) -> AppResult<Vec<(UserDevice, Option<String>)>> {
+    let devices = sqlx::query_as::<_, UserDevice>(
+        "SELECT * FROM user_devices WHERE user_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let verdicts: Vec<(Uuid, String)> = sqlx::query_as(
+        r#"
+        SELECT dv.device_id, dv.status FROM device_verifications dv
+        INNER JOIN user_devices d ON d.id = dv.device_id AND d.identity_key = dv.identity_key
+        WHERE dv.verifier_id = $1 AND d.user_id = $2
+        "#,
+    )
+    .bind(viewer_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(devices
+        .into_iter()
+        .map(|d| {
+            let status = verdicts.iter().find(|(id, _)| *id == d.id).map(|(_, s)| s.clone());
+            (d, status)
+        })
+        .collect())
+}
+
+pub async fn delete_user_device(pool: &Pool, user_id: Uuid, device_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM user_devices WHERE id = $1 AND user_id = $2")
+        .bind(device_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_device_verification(
+    pool: &Pool,
+    verifier_id: Uuid,
+    device: &UserDevice,
+    status: &str,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO device_verifications (verifier_id, device_id, status, identity_key, updated_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (verifier_id, device_id)
+        DO UPDATE SET status = EXCLUDED.status, identity_key = EXCLUDED.identity_key, updated_at = NOW()
+        "#,
+    )
+    .bind(verifier_id)
+    .bind(device.id)
+    .bind(status)
+    .bind(&device.identity_key)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_device_verification(pool: &Pool, verifier_id: Uuid, device_id: Uuid) -> AppResult<()> {
+    sqlx::query("DELETE FROM device_verifications WHERE verifier_id = $1 AND device_id = $2")
+        .bind(verifier_id)
+        .bind(device_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Users who encrypt to `user_id` directly and so track their device list:
+/// friends and anyone sharing a DM or group DM.
+pub async fn get_device_list_watchers(pool: &Pool, user_id: Uuid) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT other.user_id FROM channel_members me
+        INNER JOIN channels c ON c.id = me.channel_id AND c.channel_type IN ('dm', 'group')
+        INNER JOIN channel_members other ON other.channel_id = me.channel_id AND other.user_id <> $1
+        WHERE me.user_id = $1
+        UNION
+        SELECT CASE WHEN requester_id = $1 THEN addressee_id ELSE requester_id END
+        FROM friendships
+        WHERE (requester_id = $1 OR addressee_id = $1) AND status = 'accepted'
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+// ─── Key Transparency ────────────────────────────────
+
+/// Append an identity key to the transparency log and return its leaf index.
+/// The index is MAX(seq)+1 so leaves stay contiguous; a concurrent append
+/// that grabbed the same index loses on the primary key and retries.
+pub async fn append_key_log_entry(pool: &Pool, user_id: Uuid, identity_key: &[u8]) -> AppResult<i64> {
+    let leaf = crate::key_transparency::leaf_hash(user_id, identity_key);
+    let mut attempts = 0;
+    loop {
+        let result = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO key_transparency_log (seq, user_id, identity_key, leaf_hash, created_at)
+            SELECT COALESCE(MAX(seq) + 1, 0), $1, $2, $3, CURRENT_TIMESTAMP FROM key_transparency_log
+            RETURNING seq
+            "#,
+        )
+        .bind(user_id)
+        .bind(identity_key)
+        .bind(leaf.as_slice())
+        .fetch_one(pool)
+        .await;
+        match result {
+            Ok(seq) => return Ok(seq),
+            Err(sqlx::Error::Database(ref db_err)) if db_err.is_unique_violation() && attempts < 5 => {
+                attempts += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// All leaf hashes in log order.
+pub async fn get_key_log_leaves(pool: &Pool) -> AppResult<Vec<Vec<u8>>> {
+    let leaves = sqlx::query_scalar::<_, Vec<u8>>(
+        "SELECT leaf_hash FROM key_transparency_log ORDER BY seq",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(leaves)
+}
+
+/// The most recent log entry for a user (their current identity key, if the
+/// server is honest).
+pub async fn find_latest_key_log_entry(pool: &Pool, user_id: Uuid) -> AppResult<Option<KeyLogEntry>> {
+    let entry = sqlx::query_as::<_, KeyLogEntry>(
+        "SELECT * FROM key_transparency_log WHERE user_id = $1 ORDER BY seq DESC LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(entry)
+}
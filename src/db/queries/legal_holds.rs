@@ -0,0 +1,145 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Legal Holds ──────────────────────────────────────
+
+/// SQL condition that is true for rows of `messages` (under the given alias)
+/// not covered by an active hold on their sender or their channel's server.
+/// Sweeps that delete messages in bulk add it to their WHERE clause.
+pub fn not_under_legal_hold(messages: &str) -> String {
+    format!(
+        r#"NOT EXISTS (
+            SELECT 1 FROM legal_holds h
+            WHERE h.released_at IS NULL
+              AND ((h.subject_type = 'user' AND h.subject_id = {m}.sender_id)
+                OR (h.subject_type = 'server' AND h.subject_id =
+                    (SELECT hc.server_id FROM channels hc WHERE hc.id = {m}.channel_id))))"#,
+        m = messages
+    )
+}
+
+/// Place a hold. Returns None if the subject is already held.
+pub async fn place_legal_hold(
+    pool: &Pool,
+    subject: LegalHoldSubject,
+    subject_id: Uuid,
+    reason: Option<&str>,
+    placed_by: Uuid,
+) -> AppResult<Option<LegalHold>> {
+    let hold = sqlx::query_as::<_, LegalHold>(
+        r#"
+        INSERT INTO legal_holds (subject_type, subject_id, reason, placed_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (subject_type, subject_id) WHERE released_at IS NULL DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(subject.as_str())
+    .bind(subject_id)
+    .bind(reason)
+    .bind(placed_by)
+    .fetch_optional(pool)
+    .await?;
+    Ok(hold)
+}
+
+/// Release an active hold. Returns None if there is no such active hold.
+pub async fn release_legal_hold(
+    pool: &Pool,
+    hold_id: Uuid,
+    released_by: Uuid,
+    reason: Option<&str>,
+) -> AppResult<Option<LegalHold>> {
+    let hold = sqlx::query_as::<_, LegalHold>(
+        r#"
+        UPDATE legal_holds
+        SET released_at = NOW(), released_by = $2, release_reason = $3
+        WHERE id = $1 AND released_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(hold_id)
+    .bind(released_by)
+    .bind(reason)
+    .fetch_optional(pool)
+    .await?;
+    Ok(hold)
+}
+
+/// Holds, newest first; released ones only when `include_released`.
+pub async fn list_legal_holds(
+    pool: &Pool,
+    include_released: bool,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<LegalHold>> {
+    let holds = sqlx::query_as::<_, LegalHold>(
+        r#"
+        SELECT * FROM legal_holds
+        WHERE $1 OR released_at IS NULL
+        ORDER BY placed_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(include_released)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(holds)
+}
+
+pub async fn is_under_legal_hold(
+    pool: &Pool,
+    subject: LegalHoldSubject,
+    subject_id: Uuid,
+) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM legal_holds
+            WHERE subject_type = $1 AND subject_id = $2 AND released_at IS NULL
+        )
+        "#,
+    )
+    .bind(subject.as_str())
+    .bind(subject_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Whether erasing a user's account would destroy held data: the user is
+/// held, or owns a held server (which account deletion takes with it).
+pub async fn is_user_erasure_held(pool: &Pool, user_id: Uuid) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM legal_holds h
+            WHERE h.released_at IS NULL
+              AND ((h.subject_type = 'user' AND h.subject_id = $1)
+                OR (h.subject_type = 'server'
+                    AND h.subject_id IN (SELECT id FROM servers WHERE owner_id = $1)))
+        )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Whether any of the given messages is covered by an active hold.
+pub async fn any_message_under_legal_hold(pool: &Pool, message_ids: &[Uuid]) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(&format!(
+        "SELECT EXISTS(SELECT 1 FROM messages m WHERE m.id = ANY($1) AND NOT {})",
+        not_under_legal_hold("m")
+    ))
+    .bind(message_ids)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
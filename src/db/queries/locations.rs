@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Location Shares ──────────────────────────────────
+
+/// Mark a message a `location` share and, for a live share, open its
+/// session until `live_until`.
+pub async fn insert_location_share(
+    pool: &Pool,
+    message_id: Uuid,
+    channel_id: Uuid,
+    user_id: Uuid,
+    live_until: Option<DateTime<Utc>>,
+) -> AppResult<Option<LiveLocationSession>> {
+    let session = sqlx::query_as::<_, LiveLocationSession>(
+        r#"
+        WITH typed AS (
+            UPDATE messages SET message_type = 'location' WHERE id = $1
+        )
+        INSERT INTO live_location_sessions (message_id, channel_id, user_id, expires_at)
+        SELECT $1, $2, $3, $4 WHERE $4::timestamptz IS NOT NULL
+        ON CONFLICT (message_id) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(user_id)
+    .bind(live_until)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+pub async fn find_live_location_session(pool: &Pool, message_id: Uuid) -> AppResult<Option<LiveLocationSession>> {
+    let session = sqlx::query_as::<_, LiveLocationSession>(
+        "SELECT * FROM live_location_sessions WHERE message_id = $1",
+    )
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Store a position update on the sender's open session, unless the last
+/// one came less than `min_interval_secs` ago. `None` if nothing was stored.
+pub async fn update_live_location(
+    pool: &Pool,
+    message_id: Uuid,
+    user_id: Uuid,
+    position: &[u8],
+    min_interval_secs: i64,
+) -> AppResult<Option<LiveLocationSession>> {
+    let session = sqlx::query_as::<_, LiveLocationSession>(
+        r#"
+        UPDATE live_location_sessions
+        SET last_position = $3, last_update_at = NOW()
+        WHERE message_id = $1 AND user_id = $2
+          AND ended_at IS NULL AND expires_at > NOW()
+          AND (last_update_at IS NULL OR last_update_at <= NOW() - make_interval(secs => $4))
+        RETURNING *
+        "#,
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(position)
+    .bind(min_interval_secs as f64)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// End the sender's open session now. `None` if it wasn't open.
+pub async fn stop_live_location(
+    pool: &Pool,
+    message_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<LiveLocationSession>> {
+    let session = sqlx::query_as::<_, LiveLocationSession>(
+        r#"
+        UPDATE live_location_sessions SET ended_at = NOW()
+        WHERE message_id = $1 AND user_id = $2 AND ended_at IS NULL AND expires_at > NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(session)
+}
+
+/// End sessions past their expiry; returns them as (channel_id, message_id).
+pub async fn end_expired_live_locations(pool: &Pool) -> AppResult<Vec<(Uuid, Uuid)>> {
+    let ended: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE live_location_sessions SET ended_at = expires_at
+        WHERE ended_at IS NULL AND expires_at <= NOW()
+        RETURNING channel_id, message_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ended)
+}
+
+/// Live location sessions of the given messages, for history reads.
+pub async fn get_live_location_sessions(
+    pool: &Pool,
+    message_ids: &[Uuid],
+) -> AppResult<HashMap<Uuid, LiveLocationSession>> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sessions = sqlx::query_as::<_, LiveLocationSession>(
+        "SELECT * FROM live_location_sessions WHERE message_id = ANY($1)",
+    )
+    .bind(message_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(sessions.into_iter().map(|s| (s.message_id, s)).collect())
+}
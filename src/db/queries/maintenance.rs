@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use crate::db::Pool;
+use crate::db::{Connection, Pool};
 use crate::errors::AppResult;
 use crate::models::*;
 
@@ -147,6 +147,23 @@ pub async fn insert_audit_log(
     target_id: Option<Uuid>,
     changes: Option<&serde_json::Value>,
     reason: Option<&str>,
+) -> AppResult<AuditLogEntry> {
+    let mut conn = pool.acquire().await?;
+    insert_audit_log_in(&mut conn, server_id, actor_id, action, target_type, target_id, changes, reason).await
+}
+
+/// [`insert_audit_log`] on the caller's connection, so the entry commits (or
+/// rolls back) with the transaction it describes.
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_audit_log_in(
+    conn: &mut Connection,
+    server_id: Uuid,
+    actor_id: Uuid,
+    action: &str,
+    target_type: Option<&str>,
+    target_id: Option<Uuid>,
+    changes: Option<&serde_json::Value>,
+    reason: Option<&str>,
 ) -> AppResult<AuditLogEntry> {
     let entry = sqlx::query_as::<_, AuditLogEntry>(
         r#"
@@ -162,7 +179,7 @@ pub async fn insert_audit_log(
     .bind(target_id)
     .bind(changes)
     .bind(reason)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
     Ok(entry)
 }
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Message Mentions ─────────────────────────────────
+
+pub async fn insert_message_mentions(
+    pool: &Pool,
+    message_id: Uuid,
+    channel_id: Uuid,
+    mentions: &MessageMentions,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO message_mentions (message_id, channel_id, user_ids, role_ids, everyone)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (message_id) DO NOTHING
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(&mentions.user_ids)
+    .bind(&mentions.role_ids)
+    .bind(mentions.everyone)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mentions of the given messages, for history reads.
+pub async fn get_message_mentions(
+    pool: &Pool,
+    message_ids: &[Uuid],
+) -> AppResult<HashMap<Uuid, MessageMentions>> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<(Uuid, Vec<Uuid>, Vec<Uuid>, bool)> = sqlx::query_as(
+        "SELECT message_id, user_ids, role_ids, everyone FROM message_mentions WHERE message_id = ANY($1)",
+    )
+    .bind(message_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, user_ids, role_ids, everyone)| (id, MessageMentions { user_ids, role_ids, everyone }))
+        .collect())
+}
+
+/// How many of `role_ids` are roles of `server_id`.
+pub async fn count_server_roles(pool: &Pool, server_id: Uuid, role_ids: &[Uuid]) -> AppResult<i64> {
+    let (count,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM roles WHERE server_id = $1 AND id = ANY($2)")
+            .bind(server_id)
+            .bind(role_ids)
+            .fetch_one(pool)
+            .await?;
+    Ok(count)
+}
+
+/// Members holding any of `role_ids` in the channel's server, unless the
+/// channel suppresses role mentions.
+pub async fn get_role_mention_recipients(
+    pool: &Pool,
+    channel_id: Uuid,
+    role_ids: &[Uuid],
+) -> AppResult<Vec<Uuid>> {
+    if role_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT mr.user_id
+        FROM member_roles mr
+        JOIN channels c ON c.server_id = mr.server_id
+        WHERE c.id = $1 AND mr.role_id = ANY($2) AND NOT c.suppress_role_mentions
+        "#,
+    )
+    .bind(channel_id)
+    .bind(role_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Unread messages mentioning `user_id` (directly, through `@everyone`, or
+/// through one of their roles where the channel allows it) per channel.
+/// The user's own messages don't count.
+pub async fn get_user_mention_counts(
+    pool: &Pool,
+    user_id: Uuid,
+    channel_ids: &[Uuid],
+) -> AppResult<Vec<(Uuid, i64)>> {
+    if channel_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+        r#"
+        SELECT mm.channel_id, COUNT(*)
+        FROM message_mentions mm
+        JOIN messages m ON m.id = mm.message_id AND m.channel_id = mm.channel_id
+        JOIN channels c ON c.id = mm.channel_id
+        LEFT JOIN read_states rs ON rs.user_id = $1 AND rs.channel_id = mm.channel_id
+        WHERE mm.channel_id = ANY($2)
+          AND (rs.last_read_at IS NULL OR m.timestamp > rs.last_read_at)
+          AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+          AND m.sender_id IS DISTINCT FROM $1
+          AND (mm.everyone
+               OR $1 = ANY(mm.user_ids)
+               OR (NOT c.suppress_role_mentions AND EXISTS (
+                   SELECT 1 FROM member_roles mr
+                   WHERE mr.server_id = c.server_id AND mr.user_id = $1 AND mr.role_id = ANY(mm.role_ids))))
+        GROUP BY mm.channel_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(channel_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_suppress_role_mentions(pool: &Pool, channel_id: Uuid) -> AppResult<bool> {
+    let (suppress,): (bool,) = sqlx::query_as("SELECT suppress_role_mentions FROM channels WHERE id = $1")
+        .bind(channel_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(suppress)
+}
+
+pub async fn set_suppress_role_mentions(pool: &Pool, channel_id: Uuid, suppress: bool) -> AppResult<()> {
+    sqlx::query("UPDATE channels SET suppress_role_mentions = $2 WHERE id = $1")
+        .bind(channel_id)
+        .bind(suppress)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
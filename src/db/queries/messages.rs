@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::db::queries::not_under_legal_hold;
 use crate::db::Pool;
 use crate::errors::{AppError, AppResult};
 use crate::models::*;
@@ -47,6 +48,43 @@ pub async fn insert_message(
     Ok(msg)
 }
 
+/// Insert a forwarded copy of `original` into `channel_id`, recording where it
+/// came from.
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_forwarded_message(
+    pool: &Pool,
+    channel_id: Uuid,
+    sender_token: &[u8],
+    encrypted_body: &[u8],
+    expires_at: Option<DateTime<Utc>>,
+    has_attachments: bool,
+    sender_id: Uuid,
+    original: &Message,
+) -> AppResult<Message> {
+    let msg = sqlx::query_as::<_, Message>(
+        r#"
+        INSERT INTO messages (id, channel_id, sender_token, encrypted_body,
+                             timestamp, expires_at, has_attachments, sender_id, message_type,
+                             forwarded_from_id, forwarded_from_channel_id, forwarded_from_timestamp)
+        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, $5, $6, $7, 'forwarded', $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(channel_id)
+    .bind(sender_token)
+    .bind(encrypted_body)
+    .bind(expires_at)
+    .bind(has_attachments)
+    .bind(sender_id)
+    .bind(original.id)
+    .bind(original.channel_id)
+    .bind(original.timestamp)
+    .fetch_one(pool)
+    .await?;
+    Ok(msg)
+}
+
 /// Update encrypted_body of a message (for editing). Only the original sender can edit.
 pub async fn update_message_body(
     pool: &Pool,
@@ -71,13 +109,19 @@ pub async fn update_message_body(
     msg.ok_or_else(|| AppError::Forbidden("Cannot edit this message".into()))
 }
 
-/// Clean up child rows that previously relied on FK CASCADE from messages.
-/// Must be called before deleting messages (partitioned tables can't have FK refs).
-async fn cleanup_message_children(pool: &Pool, message_id: Uuid) -> AppResult<()> {
-    sqlx::query("DELETE FROM attachments WHERE message_id = $1")
+/// Flag a message as carrying attachments (set when an upload is finalized against it).
+pub async fn mark_message_has_attachments(pool: &Pool, message_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE messages SET has_attachments = TRUE WHERE id = $1 AND NOT has_attachments")
         .bind(message_id)
         .execute(pool)
         .await?;
+    Ok(())
+}
+
+/// Clean up child rows that previously relied on FK CASCADE from messages.
+/// Must be called before deleting messages (partitioned tables can't have FK refs).
+/// Attachment rows are left for the attachment GC, which also deletes their blobs.
+async fn cleanup_message_children(pool: &Pool, message_id: Uuid) -> AppResult<()> {
     sqlx::query("DELETE FROM reactions WHERE message_id = $1")
         .bind(message_id)
         .execute(pool)
@@ -167,6 +211,117 @@ pub async fn get_channel_messages(
     Ok(messages)
 }
 
+/// Shadow candidate for [`get_channel_messages`]: builds one statement per
+/// cursor shape instead of `$n IS NULL OR ...` predicates, so the planner can
+/// prune message partitions by timestamp.
+pub async fn get_channel_messages_by_cursor(
+    pool: &Pool,
+    channel_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    after: Option<DateTime<Utc>>,
+    limit: i64,
+) -> AppResult<Vec<Message>> {
+    let mut sql = String::from(
+        "SELECT * FROM messages WHERE channel_id = $1 \
+         AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+    );
+    let mut param = 1;
+    if before.is_some() {
+        param += 1;
+        sql.push_str(&format!(" AND timestamp < ${}", param));
+    }
+    if after.is_some() {
+        param += 1;
+        sql.push_str(&format!(" AND timestamp > ${}", param));
+    }
+    sql.push_str(&format!(" ORDER BY timestamp DESC LIMIT ${}", param + 1));
+
+    let mut query = sqlx::query_as::<_, Message>(&sql).bind(channel_id);
+    if let Some(before) = before {
+        query = query.bind(before);
+    }
+    if let Some(after) = after {
+        query = query.bind(after);
+    }
+    let messages = query.bind(limit).fetch_all(pool).await?;
+    Ok(messages)
+}
+
+// ─── Replies ──────────────────────────────────────────
+
+/// Live messages in `channel_id` replying to `message_id`, newest first.
+pub async fn get_message_replies(
+    pool: &Pool,
+    channel_id: Uuid,
+    message_id: Uuid,
+    before: Option<DateTime<Utc>>,
+    after: Option<DateTime<Utc>>,
+    limit: i64,
+) -> AppResult<Vec<Message>> {
+    let messages = sqlx::query_as::<_, Message>(
+        r#"
+        SELECT * FROM messages
+        WHERE channel_id = $1 AND reply_to_id = $2
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+          AND ($3::timestamptz IS NULL OR timestamp < $3)
+          AND ($4::timestamptz IS NULL OR timestamp > $4)
+        ORDER BY timestamp DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(channel_id)
+    .bind(message_id)
+    .bind(before)
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(messages)
+}
+
+/// The live messages among `ids` in `channel_id`, for nesting as reply previews.
+/// Replies never reach across channels, so anything elsewhere is left out.
+pub async fn get_reply_targets(
+    pool: &Pool,
+    channel_id: Uuid,
+    ids: &[Uuid],
+) -> AppResult<Vec<Message>> {
+    let messages = sqlx::query_as::<_, Message>(
+        r#"
+        SELECT * FROM messages
+        WHERE channel_id = $1 AND id = ANY($2)
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        "#,
+    )
+    .bind(channel_id)
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(messages)
+}
+
+/// Number of live replies to each of `ids` in `channel_id`. Messages without
+/// replies are omitted.
+pub async fn get_reply_counts(
+    pool: &Pool,
+    channel_id: Uuid,
+    ids: &[Uuid],
+) -> AppResult<Vec<(Uuid, i64)>> {
+    let rows = sqlx::query_as::<_, (Uuid, i64)>(
+        r#"
+        SELECT reply_to_id, COUNT(*) FROM messages
+        WHERE channel_id = $1 AND reply_to_id = ANY($2)
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        GROUP BY reply_to_id
+        "#,
+    )
+    .bind(channel_id)
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 /// Get all exportable messages for a channel (excludes disappearing messages).
 /// Used by the bulk export endpoint. Internally paginates in batches of 500.
 pub async fn get_export_messages(
@@ -196,9 +351,10 @@ pub async fn get_export_messages(
 /// Collect channel_id + message_id pairs for expired messages (before purging).
 /// Used by the purge worker to broadcast MessagesExpired events.
 pub async fn get_expired_message_ids(pool: &Pool) -> AppResult<Vec<(Uuid, Uuid)>> {
-    let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(
-        "SELECT channel_id, id FROM messages WHERE expires_at IS NOT NULL AND expires_at < CURRENT_TIMESTAMP"
-    )
+    let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(&format!(
+        "SELECT channel_id, id FROM messages WHERE expires_at IS NOT NULL AND expires_at < CURRENT_TIMESTAMP AND {}",
+        not_under_legal_hold("messages")
+    ))
     .fetch_all(pool)
     .await?;
     Ok(rows)
@@ -215,11 +371,13 @@ pub async fn clear_message_expiry(pool: &Pool, message_id: Uuid) -> AppResult<()
 
 /// Purge expired messages (called by background worker).
 /// Cleans up child rows first since FK cascades were removed for partitioning.
+/// Messages under legal hold stay until the hold is released.
 pub async fn purge_expired_messages(pool: &Pool) -> AppResult<u64> {
-    let expired_condition = "message_id IN (SELECT id FROM messages WHERE expires_at IS NOT NULL AND expires_at < CURRENT_TIMESTAMP)";
-    sqlx::query(&format!("DELETE FROM attachments WHERE {}", expired_condition))
-        .execute(pool)
-        .await?;
+    let expired = format!(
+        "expires_at IS NOT NULL AND expires_at < CURRENT_TIMESTAMP AND {}",
+        not_under_legal_hold("messages")
+    );
+    let expired_condition = format!("message_id IN (SELECT id FROM messages WHERE {})", expired);
     sqlx::query(&format!("DELETE FROM reactions WHERE {}", expired_condition))
         .execute(pool)
         .await?;
@@ -229,48 +387,12 @@ pub async fn purge_expired_messages(pool: &Pool) -> AppResult<u64> {
     sqlx::query(&format!("DELETE FROM reports WHERE {}", expired_condition))
         .execute(pool)
         .await?;
-    let result = sqlx::query("DELETE FROM messages WHERE expires_at IS NOT NULL AND expires_at < CURRENT_TIMESTAMP")
+    let result = sqlx::query(&format!("DELETE FROM messages WHERE {}", expired))
         .execute(pool)
         .await?;
     Ok(result.rows_affected())
 }
 
-/// Ensure monthly message partitions exist for the next 3 months.
-/// Called by a daily background worker. PostgreSQL only — SQLite doesn't support partitioning.
-#[cfg(feature = "postgres")]
-pub async fn ensure_future_partitions(pool: &Pool) -> AppResult<()> {
-    use chrono::Datelike;
-
-    let now = Utc::now();
-    for month_offset in 0..3 {
-        let target = now
-            .checked_add_months(chrono::Months::new(month_offset))
-            .unwrap_or(now);
-        let name = format!("messages_y{}m{:02}", target.year(), target.month());
-        let start = format!("{}-{:02}-01", target.year(), target.month());
-        let next = target
-            .checked_add_months(chrono::Months::new(1))
-            .unwrap_or(target);
-        let end = format!("{}-{:02}-01", next.year(), next.month());
-
-        let sql = format!(
-            "CREATE TABLE IF NOT EXISTS {} PARTITION OF messages FOR VALUES FROM ('{}') TO ('{}')",
-            name, start, end
-        );
-        // Ignore errors — partition may already exist or overlap with default
-        if let Err(e) = sqlx::query(&sql).execute(pool).await {
-            tracing::debug!("Partition {} already exists or overlaps: {}", name, e);
-        }
-    }
-    Ok(())
-}
-
-/// No-op for SQLite — partitioning is not supported or needed.
-#[cfg(feature = "sqlite")]
-pub async fn ensure_future_partitions(_pool: &Pool) -> AppResult<()> {
-    Ok(())
-}
-
 // ─── Pinned Messages ────────────────────────────────
 
 pub async fn pin_message(
@@ -362,10 +484,6 @@ pub async fn bulk_delete_messages(
     message_ids: &[Uuid],
 ) -> AppResult<Vec<Uuid>> {
     // Delete child rows first (no FK cascades on partitioned messages table)
-    sqlx::query("DELETE FROM attachments WHERE message_id = ANY($1)")
-        .bind(message_ids)
-        .execute(pool)
-        .await?;
     sqlx::query("DELETE FROM reactions WHERE message_id = ANY($1)")
         .bind(message_ids)
         .execute(pool)
@@ -393,12 +511,13 @@ pub async fn bulk_delete_messages(
 
 // ─── System Messages ────────────────────────────────
 
-/// Insert a system message (plaintext, not encrypted).
+/// Insert a system message; its body is the plaintext `SystemMessage` JSON.
 pub async fn insert_system_message(
     pool: &Pool,
     channel_id: Uuid,
-    body: &str,
+    system: &SystemMessage,
 ) -> AppResult<Message> {
+    let body = serde_json::to_vec(system).map_err(|e| AppError::Internal(e.into()))?;
     let msg = sqlx::query_as::<_, Message>(
         r#"
         INSERT INTO messages (id, channel_id, sender_token, encrypted_body,
@@ -410,7 +529,7 @@ pub async fn insert_system_message(
     .bind(Uuid::new_v4())
     .bind(channel_id)
     .bind(Vec::<u8>::new()) // empty sender_token
-    .bind(body.as_bytes())
+    .bind(body)
     .fetch_one(pool)
     .await?;
     Ok(msg)
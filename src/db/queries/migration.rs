@@ -0,0 +1,47 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+
+// ─── Server Migration ─────────────────────────────────
+
+/// Every member of a server with their identity key and role ids.
+pub async fn get_migration_members(pool: &Pool, server_id: Uuid) -> AppResult<Vec<(String, Vec<u8>, Vec<Uuid>)>> {
+    let rows = sqlx::query_as(
+        r#"
+        SELECT u.username, u.identity_key,
+               COALESCE(array_agg(mr.role_id) FILTER (WHERE mr.role_id IS NOT NULL), '{}') AS role_ids
+        FROM server_members sm
+        INNER JOIN users u ON u.id = sm.user_id
+        LEFT JOIN member_roles mr ON mr.server_id = sm.server_id AND mr.user_id = sm.user_id
+        WHERE sm.server_id = $1
+        GROUP BY u.id, u.username, u.identity_key
+        ORDER BY u.username
+        "#,
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Local users holding each of `identity_keys`. A key held by more than one
+/// account is ambiguous and left out.
+pub async fn find_users_by_identity_keys(
+    pool: &Pool,
+    identity_keys: &[Vec<u8>],
+) -> AppResult<Vec<(Vec<u8>, Uuid)>> {
+    let rows = sqlx::query_as(
+        r#"
+        SELECT identity_key, (array_agg(id))[1]
+        FROM users
+        WHERE identity_key = ANY($1) AND octet_length(identity_key) > 0 AND is_system = FALSE
+        GROUP BY identity_key
+        HAVING COUNT(*) = 1
+        "#,
+    )
+    .bind(identity_keys)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
@@ -17,6 +17,35 @@ mod admin;
 mod maintenance;
 mod emojis;
 mod system;
+mod federation;
+mod bridges;
+mod announcements;
+mod quotas;
+mod partitions;
+mod sync;
+mod email;
+mod abuse;
+mod retention;
+mod legal_holds;
+mod events;
+mod receipts;
+mod device_queue;
+mod migration;
+mod restore_jobs;
+mod restore_snapshots;
+mod mentions;
+mod notification_rules;
+mod quiet_hours;
+mod onboarding;
+mod screening;
+mod verification;
+mod upload_limits;
+mod voice_notes;
+mod locations;
+mod permission_templates;
+mod insights;
+mod jobs;
+mod ws_outbox;
 
 pub use users::*;
 pub use auth::*;
@@ -37,3 +66,32 @@ pub use admin::*;
 pub use maintenance::*;
 pub use emojis::*;
 pub use system::*;
+pub use federation::*;
+pub use bridges::*;
+pub use announcements::*;
+pub use quotas::*;
+pub use partitions::*;
+pub use sync::*;
+pub use email::*;
+pub use abuse::*;
+pub use retention::*;
+pub use legal_holds::*;
+pub use events::*;
+pub use receipts::*;
+pub use device_queue::*;
+pub use migration::*;
+pub use restore_jobs::*;
+pub use restore_snapshots::*;
+pub use mentions::*;
+pub use notification_rules::*;
+pub use quiet_hours::*;
+pub use onboarding::*;
+pub use screening::*;
+pub use verification::*;
+pub use upload_limits::*;
+pub use voice_notes::*;
+pub use locations::*;
+pub use permission_templates::*;
+pub use insights::*;
+pub use jobs::*;
+pub use ws_outbox::*;
@@ -0,0 +1,126 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Notification Rules ───────────────────────────────
+
+pub async fn list_notification_rules(pool: &Pool, user_id: Uuid) -> AppResult<Vec<NotificationRule>> {
+    let rules = sqlx::query_as::<_, NotificationRule>(
+        "SELECT * FROM notification_rules WHERE user_id = $1 ORDER BY scope, created_at",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rules)
+}
+
+pub async fn count_notification_rules(pool: &Pool, user_id: Uuid) -> AppResult<i64> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM notification_rules WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// The user's rule for a scope and target, if they have one.
+pub async fn find_notification_rule_for_target(
+    pool: &Pool,
+    user_id: Uuid,
+    scope: NotificationScope,
+    target_id: Option<Uuid>,
+) -> AppResult<Option<NotificationRule>> {
+    let rule = sqlx::query_as::<_, NotificationRule>(
+        r#"
+        SELECT * FROM notification_rules
+        WHERE user_id = $1 AND scope = $2 AND target_id IS NOT DISTINCT FROM $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(scope.as_str())
+    .bind(target_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(rule)
+}
+
+pub async fn create_notification_rule(
+    pool: &Pool,
+    user_id: Uuid,
+    scope: NotificationScope,
+    target_id: Option<Uuid>,
+    level: NotificationLevel,
+) -> AppResult<NotificationRule> {
+    let rule = sqlx::query_as::<_, NotificationRule>(
+        r#"
+        INSERT INTO notification_rules (user_id, scope, target_id, level)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(scope.as_str())
+    .bind(target_id)
+    .bind(level.as_str())
+    .fetch_one(pool)
+    .await?;
+    Ok(rule)
+}
+
+/// Change the level of one of the user's rules; None if they have no such rule.
+pub async fn update_notification_rule(
+    pool: &Pool,
+    user_id: Uuid,
+    rule_id: Uuid,
+    level: NotificationLevel,
+) -> AppResult<Option<NotificationRule>> {
+    let rule = sqlx::query_as::<_, NotificationRule>(
+        r#"
+        UPDATE notification_rules SET level = $3, updated_at = NOW()
+        WHERE id = $2 AND user_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(rule_id)
+    .bind(level.as_str())
+    .fetch_optional(pool)
+    .await?;
+    Ok(rule)
+}
+
+pub async fn delete_notification_rule(pool: &Pool, user_id: Uuid, rule_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM notification_rules WHERE id = $2 AND user_id = $1")
+        .bind(user_id)
+        .bind(rule_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every rule that can apply to a message in `channel_id`: rules on the
+/// channel or its server, and the global rules of users who can see it.
+pub async fn get_notification_rules_for_channel(
+    pool: &Pool,
+    channel_id: Uuid,
+    server_id: Option<Uuid>,
+) -> AppResult<Vec<NotificationRule>> {
+    let rules = sqlx::query_as::<_, NotificationRule>(
+        r#"
+        SELECT r.* FROM notification_rules r
+        WHERE (r.scope = 'channel' AND r.target_id = $1)
+           OR (r.scope = 'server' AND r.target_id = $2)
+           OR (r.scope = 'global' AND (
+                EXISTS (SELECT 1 FROM channel_members cm
+                        WHERE cm.channel_id = $1 AND cm.user_id = r.user_id)
+                OR EXISTS (SELECT 1 FROM server_members sm
+                           WHERE sm.server_id = $2 AND sm.user_id = r.user_id)))
+        "#,
+    )
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rules)
+}
@@ -0,0 +1,153 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Server Onboarding ───────────────────────────────
+
+pub async fn get_server_onboarding(pool: &Pool, server_id: Uuid) -> AppResult<Option<ServerOnboarding>> {
+    let onboarding =
+        sqlx::query_as::<_, ServerOnboarding>("SELECT * FROM server_onboarding WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(onboarding)
+}
+
+/// Create or replace a server's onboarding. `reset_acknowledgments` bumps the
+/// rules version so earlier acknowledgments no longer count.
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_server_onboarding(
+    pool: &Pool,
+    server_id: Uuid,
+    enabled: bool,
+    encrypted_meta: &[u8],
+    default_channel_ids: &[Uuid],
+    require_acknowledgment: bool,
+    reset_acknowledgments: bool,
+    prompts: &serde_json::Value,
+) -> AppResult<ServerOnboarding> {
+    let onboarding = sqlx::query_as::<_, ServerOnboarding>(
+        r#"
+        INSERT INTO server_onboarding
+            (server_id, enabled, encrypted_meta, default_channel_ids, require_acknowledgment, prompts)
+        VALUES ($1, $2, $3, $4, $5, $7)
+        ON CONFLICT (server_id) DO UPDATE SET
+            enabled = $2, encrypted_meta = $3, default_channel_ids = $4,
+            require_acknowledgment = $5,
+            rules_version = server_onboarding.rules_version + CASE WHEN $6 THEN 1 ELSE 0 END,
+            prompts = $7, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(enabled)
+    .bind(encrypted_meta)
+    .bind(default_channel_ids)
+    .bind(require_acknowledgment)
+    .bind(reset_acknowledgments)
+    .bind(prompts)
+    .fetch_one(pool)
+    .await?;
+    Ok(onboarding)
+}
+
+pub async fn acknowledge_onboarding_rules(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+    rules_version: i32,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO onboarding_acknowledgments (server_id, user_id, rules_version)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (server_id, user_id) DO UPDATE SET rules_version = $3, acknowledged_at = NOW()
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(rules_version)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Forget a member's acknowledgment, so they acknowledge the rules again
+/// when they rejoin.
+pub async fn clear_onboarding_acknowledgment(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    sqlx::query("DELETE FROM onboarding_acknowledgments WHERE server_id = $1 AND user_id = $2")
+        .bind(server_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn has_acknowledged_onboarding(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+    rules_version: i32,
+) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM onboarding_acknowledgments
+                      WHERE server_id = $1 AND user_id = $2 AND rules_version >= $3)
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(rules_version)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Whether the user still has to acknowledge the server's rules before
+/// posting. The owner never does.
+pub async fn is_onboarding_pending(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM server_onboarding o
+            JOIN servers s ON s.id = o.server_id
+            WHERE o.server_id = $1 AND o.enabled AND o.require_acknowledgment
+              AND s.owner_id <> $2
+              AND NOT EXISTS (SELECT 1 FROM onboarding_acknowledgments a
+                              WHERE a.server_id = o.server_id AND a.user_id = $2
+                                AND a.rules_version >= o.rules_version)
+        )
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Add a new member to the given channels of their server.
+pub async fn add_channel_members_for_channels(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+    channel_ids: &[Uuid],
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO channel_members (id, channel_id, user_id, joined_at)
+        SELECT gen_random_uuid(), c.id, $1, CURRENT_TIMESTAMP
+        FROM channels c
+        WHERE c.server_id = $2 AND c.id = ANY($3)
+        ON CONFLICT (channel_id, user_id) DO UPDATE SET joined_at = EXCLUDED.joined_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(server_id)
+    .bind(channel_ids)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
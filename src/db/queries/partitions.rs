@@ -0,0 +1,90 @@
+use chrono::NaiveDate;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+
+// ─── Message Partitions ───────────────────────────────
+//
+// `messages` is range-partitioned by month (see the partition_messages
+// migration). Partition names are built by `maintenance::partition_name`
+// from dates, never from user input, so they are safe to format into DDL.
+
+/// Create the monthly partition covering `[start, end)` if it does not exist.
+/// Returns false when the partition already existed.
+#[cfg(feature = "postgres")]
+pub async fn create_message_partition(pool: &Pool, name: &str, start: NaiveDate, end: NaiveDate) -> AppResult<bool> {
+    let exists: Option<(i32,)> = sqlx::query_as("SELECT 1 FROM pg_class WHERE relname = $1")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_some() {
+        return Ok(false);
+    }
+    sqlx::query(&format!(
+        "CREATE TABLE {} PARTITION OF messages FOR VALUES FROM ('{}') TO ('{}')",
+        name, start, end
+    ))
+    .execute(pool)
+    .await?;
+    Ok(true)
+}
+
+/// Every partition of `messages` with its planner row estimate and on-disk size.
+#[cfg(feature = "postgres")]
+pub async fn list_message_partitions(pool: &Pool) -> AppResult<Vec<(String, i64, i64)>> {
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT c.relname::text,
+               GREATEST(c.reltuples, 0)::int8,
+               pg_total_relation_size(c.oid)::int8
+        FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'messages'::regclass
+        ORDER BY c.relname
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Detach and drop one partition, removing the reactions, pins and reports
+/// that point at its messages (there are no FK cascades on partitioned tables).
+/// Attachments are left to attachment GC, which collects them once their
+/// message is gone. Returns the number of messages dropped.
+#[cfg(feature = "postgres")]
+pub async fn drop_message_partition(pool: &Pool, name: &str) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+    let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", name))
+        .fetch_one(&mut *tx)
+        .await?;
+    for child in ["reactions", "pinned_messages", "reports"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE message_id IN (SELECT id FROM {})", child, name))
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(&format!("ALTER TABLE messages DETACH PARTITION {}", name))
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&format!("DROP TABLE {}", name))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(count as u64)
+}
+
+/// No-op for SQLite — partitioning is not supported or needed.
+#[cfg(feature = "sqlite")]
+pub async fn create_message_partition(_pool: &Pool, _name: &str, _start: NaiveDate, _end: NaiveDate) -> AppResult<bool> {
+    Ok(false)
+}
+
+#[cfg(feature = "sqlite")]
+pub async fn list_message_partitions(_pool: &Pool) -> AppResult<Vec<(String, i64, i64)>> {
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "sqlite")]
+pub async fn drop_message_partition(_pool: &Pool, _name: &str) -> AppResult<u64> {
+    Ok(0)
+}
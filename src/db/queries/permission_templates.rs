@@ -0,0 +1,120 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::{AppError, AppResult};
+use crate::models::*;
+
+// ─── Permission Templates ────────────────────────────
+
+pub async fn list_permission_templates(pool: &Pool, server_id: Uuid) -> AppResult<Vec<PermissionTemplate>> {
+    let templates = sqlx::query_as::<_, PermissionTemplate>(
+        "SELECT * FROM permission_templates WHERE server_id = $1 ORDER BY name",
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(templates)
+}
+
+pub async fn find_permission_template(
+    pool: &Pool,
+    server_id: Uuid,
+    template_id: Uuid,
+) -> AppResult<Option<PermissionTemplate>> {
+    let template = sqlx::query_as::<_, PermissionTemplate>(
+        "SELECT * FROM permission_templates WHERE id = $1 AND server_id = $2",
+    )
+    .bind(template_id)
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(template)
+}
+
+pub async fn count_permission_templates(pool: &Pool, server_id: Uuid) -> AppResult<i64> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM permission_templates WHERE server_id = $1")
+        .bind(server_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(count.0)
+}
+
+pub async fn create_permission_template(
+    pool: &Pool,
+    server_id: Uuid,
+    name: &str,
+    allow_bits: i64,
+    deny_bits: i64,
+) -> AppResult<PermissionTemplate> {
+    let result = sqlx::query_as::<_, PermissionTemplate>(
+        r#"
+        INSERT INTO permission_templates (server_id, name, allow_bits, deny_bits)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(name)
+    .bind(allow_bits)
+    .bind(deny_bits)
+    .fetch_one(pool)
+    .await;
+    name_taken(result)
+}
+
+/// Update the given fields of a template; `None` when it doesn't exist.
+pub async fn update_permission_template(
+    pool: &Pool,
+    server_id: Uuid,
+    template_id: Uuid,
+    name: Option<&str>,
+    allow_bits: Option<i64>,
+    deny_bits: Option<i64>,
+) -> AppResult<Option<PermissionTemplate>> {
+    let result = sqlx::query_as::<_, PermissionTemplate>(
+        r#"
+        UPDATE permission_templates
+        SET name = COALESCE($3, name),
+            allow_bits = COALESCE($4, allow_bits),
+            deny_bits = COALESCE($5, deny_bits)
+        WHERE id = $1 AND server_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(template_id)
+    .bind(server_id)
+    .bind(name)
+    .bind(allow_bits)
+    .bind(deny_bits)
+    .fetch_optional(pool)
+    .await;
+    name_taken(result)
+}
+
+pub async fn delete_permission_template(pool: &Pool, server_id: Uuid, template_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM permission_templates WHERE id = $1 AND server_id = $2")
+        .bind(template_id)
+        .bind(server_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Give a new server the built-in templates.
+pub async fn create_default_permission_templates(pool: &Pool, server_id: Uuid) -> AppResult<()> {
+    for &(name, allow_bits, deny_bits) in crate::permissions::DEFAULT_TEMPLATES {
+        create_permission_template(pool, server_id, name, allow_bits, deny_bits).await?;
+    }
+    Ok(())
+}
+
+/// Report a clash with the `(server_id, name)` unique constraint as a conflict.
+fn name_taken<T>(result: Result<T, sqlx::Error>) -> AppResult<T> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(sqlx::Error::Database(ref db_err)) if db_err.is_unique_violation() => Err(AppError::Conflict(
+            "A permission template with this name already exists".into(),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -6,11 +6,25 @@ use crate::models::*;
 
 // ─── Pre-Keys ──────────────────────────────────────────
 
-pub async fn insert_prekeys(pool: &Pool, user_id: Uuid, keys: &[(i32, Vec<u8>)]) -> AppResult<()> {
-    // Batch insert using a transaction
+/// Store one-time prekeys, numbering them from the user's prekey counter.
+/// The counter is bumped in the same transaction, so concurrent uploads get
+/// disjoint ids and ids are never reused, even after the keys are consumed
+/// or cleared. Returns the assigned key ids in upload order.
+pub async fn insert_prekeys(pool: &Pool, user_id: Uuid, keys: &[Vec<u8>]) -> AppResult<Vec<i32>> {
     let mut tx = pool.begin().await?;
 
-    for (key_id, public_key) in keys {
+    let (next_id,): (i32,) = sqlx::query_as(
+        "UPDATE users SET next_prekey_id = next_prekey_id + $2 WHERE id = $1 RETURNING next_prekey_id",
+    )
+    .bind(user_id)
+    .bind(keys.len() as i32)
+    .fetch_one(&mut *tx)
+    .await?;
+    let start_id = next_id - keys.len() as i32;
+
+    let mut key_ids = Vec::with_capacity(keys.len());
+    for (i, public_key) in keys.iter().enumerate() {
+        let key_id = start_id + i as i32;
         sqlx::query(
             r#"
             INSERT INTO prekeys (id, user_id, key_id, public_key, used, created_at)
@@ -23,10 +37,11 @@ pub async fn insert_prekeys(pool: &Pool, user_id: Uuid, keys: &[(i32, Vec<u8>)])
         .bind(public_key)
         .execute(&mut *tx)
         .await?;
+        key_ids.push(key_id);
     }
 
     tx.commit().await?;
-    Ok(())
+    Ok(key_ids)
 }
 
 /// Fetch and consume one unused one-time prekey (marks it as used atomically).
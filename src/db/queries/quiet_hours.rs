@@ -0,0 +1,114 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Quiet Hours ─────────────────────────────────────
+
+pub async fn get_quiet_hours(pool: &Pool, user_id: Uuid) -> AppResult<Option<QuietHours>> {
+    let schedule = sqlx::query_as::<_, QuietHours>("SELECT * FROM quiet_hours WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(schedule)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_quiet_hours(
+    pool: &Pool,
+    user_id: Uuid,
+    enabled: bool,
+    start_minute: i32,
+    end_minute: i32,
+    days: i16,
+    utc_offset_minutes: i32,
+    timezone: Option<&str>,
+    priority_user_ids: &[Uuid],
+    active: bool,
+) -> AppResult<QuietHours> {
+    let schedule = sqlx::query_as::<_, QuietHours>(
+        r#"
+        INSERT INTO quiet_hours
+            (user_id, enabled, start_minute, end_minute, days, utc_offset_minutes,
+             timezone, priority_user_ids, active)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (user_id) DO UPDATE SET
+            enabled = $2, start_minute = $3, end_minute = $4, days = $5,
+            utc_offset_minutes = $6, timezone = $7, priority_user_ids = $8,
+            active = $9, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(enabled)
+    .bind(start_minute)
+    .bind(end_minute)
+    .bind(days)
+    .bind(utc_offset_minutes)
+    .bind(timezone)
+    .bind(priority_user_ids)
+    .bind(active)
+    .fetch_one(pool)
+    .await?;
+    Ok(schedule)
+}
+
+/// Remove the user's schedule, returning it if there was one.
+pub async fn delete_quiet_hours(pool: &Pool, user_id: Uuid) -> AppResult<Option<QuietHours>> {
+    let schedule = sqlx::query_as::<_, QuietHours>("DELETE FROM quiet_hours WHERE user_id = $1 RETURNING *")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(schedule)
+}
+
+/// Enabled schedules of the users who can see a message in `channel_id`.
+pub async fn get_quiet_hours_for_channel(
+    pool: &Pool,
+    channel_id: Uuid,
+    server_id: Option<Uuid>,
+) -> AppResult<Vec<QuietHours>> {
+    let schedules = sqlx::query_as::<_, QuietHours>(
+        r#"
+        SELECT q.* FROM quiet_hours q
+        WHERE q.enabled AND (
+            EXISTS (SELECT 1 FROM channel_members cm
+                    WHERE cm.channel_id = $1 AND cm.user_id = q.user_id)
+            OR EXISTS (SELECT 1 FROM server_members sm
+                       WHERE sm.server_id = $2 AND sm.user_id = q.user_id))
+        "#,
+    )
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(schedules)
+}
+
+/// Schedules that may be in effect or just have been: the enabled ones and
+/// any still marked active.
+pub async fn list_quiet_hours_to_check(pool: &Pool) -> AppResult<Vec<QuietHours>> {
+    let schedules = sqlx::query_as::<_, QuietHours>("SELECT * FROM quiet_hours WHERE enabled OR active")
+        .fetch_all(pool)
+        .await?;
+    Ok(schedules)
+}
+
+pub async fn set_quiet_hours_active(pool: &Pool, user_ids: &[Uuid], active: bool) -> AppResult<()> {
+    sqlx::query("UPDATE quiet_hours SET active = $2 WHERE user_id = ANY($1)")
+        .bind(user_ids)
+        .bind(active)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Which of `user_ids` are in their quiet hours, as of the last check.
+pub async fn get_quiet_user_ids(pool: &Pool, user_ids: &[Uuid]) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as("SELECT user_id FROM quiet_hours WHERE active AND user_id = ANY($1)")
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
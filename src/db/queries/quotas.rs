@@ -0,0 +1,85 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Server Quotas ───────────────────────────────────
+
+pub async fn get_server_quota_overrides(pool: &Pool, server_id: Uuid) -> AppResult<ServerQuotaOverrides> {
+    let overrides = sqlx::query_as::<_, ServerQuotaOverrides>(
+        "SELECT max_storage_bytes, max_messages_per_minute, max_members FROM server_quotas WHERE server_id = $1",
+    )
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(overrides.unwrap_or_default())
+}
+
+/// Replace a server's overrides. Clearing every limit removes the row.
+pub async fn set_server_quota_overrides(
+    pool: &Pool,
+    server_id: Uuid,
+    overrides: &ServerQuotaOverrides,
+    updated_by: Uuid,
+) -> AppResult<()> {
+    if overrides.max_storage_bytes.is_none()
+        && overrides.max_messages_per_minute.is_none()
+        && overrides.max_members.is_none()
+    {
+        sqlx::query("DELETE FROM server_quotas WHERE server_id = $1")
+            .bind(server_id)
+            .execute(pool)
+            .await?;
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO server_quotas (server_id, max_storage_bytes, max_messages_per_minute, max_members,
+                                   updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+        ON CONFLICT (server_id) DO UPDATE
+        SET max_storage_bytes = EXCLUDED.max_storage_bytes,
+            max_messages_per_minute = EXCLUDED.max_messages_per_minute,
+            max_members = EXCLUDED.max_members,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(server_id)
+    .bind(overrides.max_storage_bytes)
+    .bind(overrides.max_messages_per_minute)
+    .bind(overrides.max_members)
+    .bind(updated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Bytes a server is charged for: linked attachments not yet orphaned, with
+/// each shared blob counted once however many of them use it, plus the
+/// declared length of resumable uploads still in progress in its channels
+/// (so parallel uploads can't overshoot the quota).
+pub async fn get_server_storage_usage(pool: &Pool, server_id: Uuid) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        r#"
+        SELECT (
+            SELECT COALESCE(SUM(size_bytes), 0) FROM attachments
+            WHERE server_id = $1 AND orphaned_at IS NULL AND content_hash IS NULL
+        )::BIGINT + (
+            SELECT COALESCE(SUM(b.size_bytes), 0) FROM attachment_blobs b
+            WHERE b.content_hash IN (SELECT content_hash FROM attachments
+                                     WHERE server_id = $1 AND orphaned_at IS NULL)
+        )::BIGINT + (
+            SELECT COALESCE(SUM(us.upload_length), 0) FROM upload_sessions us
+            INNER JOIN channels c ON c.id = us.channel_id
+            WHERE c.server_id = $1
+        )::BIGINT
+        "#,
+    )
+    .bind(server_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
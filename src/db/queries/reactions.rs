@@ -1,5 +1,7 @@
 use uuid::Uuid;
 
+#[cfg(feature = "sqlite")]
+use crate::db::dialect;
 use crate::db::Pool;
 use crate::errors::AppResult;
 use crate::models::*;
@@ -69,10 +71,9 @@ pub async fn get_reactions_for_messages(
 
     #[cfg(feature = "sqlite")]
     let reactions = {
-        let placeholders: Vec<String> = (1..=message_ids.len()).map(|i| format!("${}", i)).collect();
         let sql = format!(
             "SELECT * FROM reactions WHERE message_id IN ({}) ORDER BY created_at ASC",
-            placeholders.join(", ")
+            dialect::placeholders(1, message_ids.len())
         );
         let mut query = sqlx::query_as::<_, Reaction>(&sql);
         for id in message_ids {
@@ -97,3 +98,54 @@ pub async fn get_reactions_for_message(
     .await?;
     Ok(reactions)
 }
+
+/// Users who reacted to a message with `emoji`, ordered by user ID.
+/// Pass the last `user_id` of the previous page as `after` to continue.
+pub async fn get_reactors(
+    pool: &Pool,
+    message_id: Uuid,
+    emoji: &str,
+    after: Option<Uuid>,
+    limit: i64,
+) -> AppResult<Vec<Reactor>> {
+    let reactors = sqlx::query_as::<_, Reactor>(
+        r#"
+        SELECT user_id, created_at AS reacted_at FROM reactions
+        WHERE message_id = $1 AND emoji = $2
+          AND ($3::uuid IS NULL OR user_id > $3)
+        ORDER BY user_id
+        LIMIT $4
+        "#,
+    )
+    .bind(message_id)
+    .bind(emoji)
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(reactors)
+}
+
+/// Per-emoji reaction counts for a set of messages, as
+/// `(message_id, emoji, count, viewer_reacted)`, each message's emojis in the
+/// order they were first used.
+pub async fn get_reaction_counts(
+    pool: &Pool,
+    message_ids: &[Uuid],
+    viewer_id: Uuid,
+) -> AppResult<Vec<(Uuid, String, i64, bool)>> {
+    let rows = sqlx::query_as::<_, (Uuid, String, i64, bool)>(
+        r#"
+        SELECT message_id, emoji, COUNT(*), BOOL_OR(user_id = $2)
+        FROM reactions
+        WHERE message_id = ANY($1)
+        GROUP BY message_id, emoji
+        ORDER BY MIN(created_at)
+        "#,
+    )
+    .bind(message_ids)
+    .bind(viewer_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── DM Receipts ──────────────────────────────────────
+
+pub async fn get_receipt_privacy(pool: &Pool, user_id: Uuid) -> AppResult<String> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT receipt_privacy FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|(p,)| p).unwrap_or_else(|| "none".into()))
+}
+
+pub async fn update_receipt_privacy(pool: &Pool, user_id: Uuid, receipt_privacy: &str) -> AppResult<()> {
+    sqlx::query("UPDATE users SET receipt_privacy = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(user_id)
+        .bind(receipt_privacy)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Move a user's delivery watermark in a channel forward to `delivered_at`.
+/// Returns false when it was already there or beyond.
+pub async fn advance_delivery_state(
+    pool: &Pool,
+    user_id: Uuid,
+    channel_id: Uuid,
+    delivered_at: DateTime<Utc>,
+) -> AppResult<bool> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        INSERT INTO delivery_states (user_id, channel_id, last_delivered_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, channel_id) DO UPDATE
+        SET last_delivered_at = EXCLUDED.last_delivered_at
+        WHERE delivery_states.last_delivered_at < EXCLUDED.last_delivered_at
+        RETURNING user_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .bind(delivered_at)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// Delivery and read watermarks of every member of a channel other than
+/// `viewer_id`, with each member's receipt privacy.
+pub async fn get_channel_receipts(
+    pool: &Pool,
+    channel_id: Uuid,
+    viewer_id: Uuid,
+) -> AppResult<Vec<ChannelReceiptRow>> {
+    let rows = sqlx::query_as::<_, ChannelReceiptRow>(
+        r#"
+        SELECT cm.user_id, u.receipt_privacy, d.last_delivered_at, r.last_read_at
+        FROM channel_members cm
+        JOIN users u ON u.id = cm.user_id
+        LEFT JOIN delivery_states d ON d.user_id = cm.user_id AND d.channel_id = cm.channel_id
+        LEFT JOIN read_states r ON r.user_id = cm.user_id AND r.channel_id = cm.channel_id
+        WHERE cm.channel_id = $1 AND cm.user_id <> $2
+        "#,
+    )
+    .bind(channel_id)
+    .bind(viewer_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
@@ -0,0 +1,109 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Restore Jobs ─────────────────────────────────────
+
+pub async fn create_restore_job(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<RestoreJob> {
+    let job = sqlx::query_as::<_, RestoreJob>(
+        r#"
+        INSERT INTO restore_jobs (id, server_id, user_id, phase, done, total, status)
+        VALUES ($1, $2, $3, 'structure', 0, 0, 'running')
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(job)
+}
+
+pub async fn get_restore_job(pool: &Pool, job_id: Uuid) -> AppResult<Option<RestoreJob>> {
+    let job = sqlx::query_as::<_, RestoreJob>("SELECT * FROM restore_jobs WHERE id = $1")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(job)
+}
+
+/// A user's restore jobs on a server, newest first.
+pub async fn list_restore_jobs(pool: &Pool, server_id: Uuid, user_id: Uuid, limit: i64) -> AppResult<Vec<RestoreJob>> {
+    let jobs = sqlx::query_as::<_, RestoreJob>(
+        r#"
+        SELECT * FROM restore_jobs
+        WHERE server_id = $1 AND user_id = $2
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
+
+/// Move a running job to `phase` with fresh counters.
+pub async fn set_restore_job_phase(pool: &Pool, job_id: Uuid, phase: &str, total: i64) -> AppResult<Option<RestoreJob>> {
+    let job = sqlx::query_as::<_, RestoreJob>(
+        r#"
+        UPDATE restore_jobs
+        SET phase = $2, done = 0, total = $3, updated_at = NOW()
+        WHERE id = $1 AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(job_id)
+    .bind(phase)
+    .bind(total)
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
+
+/// Add `count` to a running job's progress, completing it once `done`
+/// reaches `total`. Returns None when the job isn't running.
+pub async fn advance_restore_job(pool: &Pool, job_id: Uuid, count: i64) -> AppResult<Option<RestoreJob>> {
+    let job = sqlx::query_as::<_, RestoreJob>(
+        r#"
+        UPDATE restore_jobs
+        SET done = done + $2,
+            status = CASE WHEN done + $2 >= total THEN 'completed' ELSE 'running' END,
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(job_id)
+    .bind(count)
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
+
+pub async fn finish_restore_job(
+    pool: &Pool,
+    job_id: Uuid,
+    status: &str,
+    error: Option<&str>,
+) -> AppResult<Option<RestoreJob>> {
+    let job = sqlx::query_as::<_, RestoreJob>(
+        r#"
+        UPDATE restore_jobs
+        SET status = $2, error = $3, updated_at = NOW()
+        WHERE id = $1 AND status = 'running'
+        RETURNING *
+        "#,
+    )
+    .bind(job_id)
+    .bind(status)
+    .bind(error)
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
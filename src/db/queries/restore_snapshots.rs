@@ -0,0 +1,293 @@
+use uuid::Uuid;
+
+use crate::db::{Connection, Pool};
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Restore Snapshots ────────────────────────────────
+
+/// Copy a server's structure into a new snapshot and detach its channels
+/// (with their messages) from the server, ready for a restore to replace it.
+pub async fn create_restore_snapshot(
+    conn: &mut Connection,
+    server_id: Uuid,
+    user_id: Uuid,
+    sections: &serde_json::Value,
+    window_hours: i32,
+) -> AppResult<Uuid> {
+    let snapshot_id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO restore_snapshots (id, server_id, created_by, structure, expires_at)
+        SELECT $1, s.id, $3, jsonb_build_object(
+            'system_channel_id', s.system_channel_id,
+            'categories', COALESCE(
+                (SELECT jsonb_agg(to_jsonb(cc)) FROM channel_categories cc WHERE cc.server_id = s.id),
+                '[]'::jsonb),
+            'roles', COALESCE(
+                (SELECT jsonb_agg(to_jsonb(r)) FROM roles r WHERE r.server_id = s.id),
+                '[]'::jsonb),
+            'member_roles', COALESCE(
+                (SELECT jsonb_agg(to_jsonb(mr)) FROM member_roles mr WHERE mr.server_id = s.id),
+                '[]'::jsonb),
+            'channels', COALESCE(
+                (SELECT jsonb_agg(jsonb_build_object('id', c.id, 'category_id', c.category_id))
+                 FROM channels c WHERE c.server_id = s.id),
+                '[]'::jsonb),
+            'channel_members', COALESCE(
+                (SELECT jsonb_agg(to_jsonb(cm)) FROM channel_members cm
+                 JOIN channels c ON c.id = cm.channel_id WHERE c.server_id = s.id),
+                '[]'::jsonb),
+            'sections', $4::jsonb
+        ), NOW() + make_interval(hours => $5)
+        FROM servers s WHERE s.id = $2
+        "#,
+    )
+    .bind(snapshot_id)
+    .bind(server_id)
+    .bind(user_id)
+    .bind(sections)
+    .bind(window_hours)
+    .execute(&mut *conn)
+    .await?;
+
+    // Members are in the snapshot; without them (and without a server) the
+    // detached channels are unreachable
+    sqlx::query("DELETE FROM channel_members WHERE channel_id IN (SELECT id FROM channels WHERE server_id = $1)")
+        .bind(server_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE channels SET server_id = NULL, detached_snapshot_id = $2 WHERE server_id = $1")
+        .bind(server_id)
+        .bind(snapshot_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(snapshot_id)
+}
+
+/// Count the structure rows a restore is about to detach or delete.
+/// Non-default roles only: `@everyone` survives every restore.
+pub async fn count_server_structure(conn: &mut Connection, server_id: Uuid) -> AppResult<StructureCounts> {
+    let counts = sqlx::query_as::<_, StructureCounts>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM channels WHERE server_id = $1) AS channels,
+            (SELECT COUNT(*) FROM channel_members cm
+             JOIN channels c ON c.id = cm.channel_id WHERE c.server_id = $1) AS channel_members,
+            (SELECT COUNT(*) FROM channel_permission_overwrites o
+             JOIN channels c ON c.id = o.channel_id WHERE c.server_id = $1) AS overwrites,
+            (SELECT COUNT(*) FROM channel_categories WHERE server_id = $1) AS categories,
+            (SELECT COUNT(*) FROM roles WHERE server_id = $1 AND is_default = FALSE) AS roles,
+            (SELECT COUNT(*) FROM member_roles mr
+             JOIN roles r ON r.id = mr.role_id
+             WHERE mr.server_id = $1 AND r.is_default = FALSE) AS member_roles
+        "#,
+    )
+    .bind(server_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(counts)
+}
+
+/// Channel-scoped rows that outlive a restore. Each statement copies (or, for
+/// server-level pointers, moves) the rows of a detached channel to the
+/// restored channel that replaced it; `map` pairs them up.
+const CHANNEL_ROW_REMAPS: &[&str] = &[
+    r#"INSERT INTO bridge_channels (bridge_id, channel_id, linked_by, created_at)
+       SELECT bc.bridge_id, map.new_id, bc.linked_by, bc.created_at
+       FROM bridge_channels bc JOIN map ON map.old_id = bc.channel_id
+       ON CONFLICT DO NOTHING"#,
+    r#"INSERT INTO channel_retention_policies
+           (channel_id, max_age_days, max_messages, archive, updated_by, updated_at)
+       SELECT map.new_id, p.max_age_days, p.max_messages, p.archive, p.updated_by, p.updated_at
+       FROM channel_retention_policies p JOIN map ON map.old_id = p.channel_id
+       ON CONFLICT DO NOTHING"#,
+    r#"INSERT INTO sender_key_distributions
+           (id, channel_id, from_user_id, to_user_id, distribution_id, encrypted_skdm, created_at)
+       SELECT gen_random_uuid(), map.new_id, d.from_user_id, d.to_user_id, d.distribution_id,
+              d.encrypted_skdm, d.created_at
+       FROM sender_key_distributions d JOIN map ON map.old_id = d.channel_id
+       ON CONFLICT DO NOTHING"#,
+    r#"UPDATE server_events e SET channel_id = map.new_id
+       FROM map WHERE map.old_id = e.channel_id AND e.server_id = $1"#,
+];
+
+/// Carry channel-scoped rows (bridge links, retention policies, sender key
+/// distributions, event locations) over from the server's detached channels
+/// to the restored channels mapped from them. `channel_map` pairs backup
+/// channel ids with new ids; backup ids that aren't one of this server's
+/// detached channels (a backup of another server) are ignored.
+pub async fn remap_channel_rows(
+    conn: &mut Connection,
+    server_id: Uuid,
+    channel_map: &[(Uuid, Uuid)],
+) -> AppResult<u64> {
+    let (old_ids, new_ids): (Vec<Uuid>, Vec<Uuid>) = channel_map.iter().copied().unzip();
+    let mut remapped = 0;
+    for statement in CHANNEL_ROW_REMAPS {
+        let sql = format!(
+            r#"WITH map AS (
+                 SELECT m.old_id, m.new_id
+                 FROM UNNEST($2::uuid[], $3::uuid[]) AS m(old_id, new_id)
+                 JOIN channels c ON c.id = m.old_id
+                 JOIN restore_snapshots s ON s.id = c.detached_snapshot_id
+                 WHERE s.server_id = $1
+               )
+               {}"#,
+            statement
+        );
+        remapped += sqlx::query(&sql)
+            .bind(server_id)
+            .bind(&old_ids)
+            .bind(&new_ids)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    }
+    Ok(remapped)
+}
+
+/// The newest snapshot of a server that can still be rolled back to.
+pub async fn get_latest_restore_snapshot(pool: &Pool, server_id: Uuid) -> AppResult<Option<RestoreSnapshot>> {
+    let snapshot = sqlx::query_as::<_, RestoreSnapshot>(
+        r#"
+        SELECT * FROM restore_snapshots
+        WHERE server_id = $1 AND expires_at > NOW()
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(snapshot)
+}
+
+/// Put a snapshot's structure back in place of the server's current (already
+/// wiped) structure: recreate its categories and roles under their original
+/// ids, reattach its channels and restore memberships of users who are still
+/// server members. Consumes the snapshot.
+pub async fn apply_restore_snapshot(conn: &mut Connection, snapshot: &RestoreSnapshot) -> AppResult<RestoreRollbackResponse> {
+    let structure = &snapshot.structure;
+
+    let categories_restored = sqlx::query(
+        r#"INSERT INTO channel_categories
+           SELECT * FROM jsonb_populate_recordset(NULL::channel_categories, $1->'categories')"#,
+    )
+    .bind(structure)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    let roles_restored = sqlx::query(
+        r#"INSERT INTO roles
+           SELECT * FROM jsonb_populate_recordset(NULL::roles, $1->'roles') WHERE NOT is_default"#,
+    )
+    .bind(structure)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    sqlx::query(
+        r#"UPDATE roles r SET permissions = s.permissions
+           FROM jsonb_populate_recordset(NULL::roles, $1->'roles') s
+           WHERE r.server_id = $2 AND r.is_default AND s.is_default"#,
+    )
+    .bind(structure)
+    .bind(snapshot.server_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let channels_restored = sqlx::query(
+        r#"UPDATE channels c
+           SET server_id = $2, detached_snapshot_id = NULL, category_id = s.category_id
+           FROM jsonb_to_recordset($1->'channels') AS s(id UUID, category_id UUID)
+           WHERE c.id = s.id AND c.detached_snapshot_id = $3"#,
+    )
+    .bind(structure)
+    .bind(snapshot.server_id)
+    .bind(snapshot.id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    sqlx::query(
+        r#"INSERT INTO member_roles
+           SELECT s.* FROM jsonb_populate_recordset(NULL::member_roles, $1->'member_roles') s
+           WHERE EXISTS (SELECT 1 FROM server_members sm WHERE sm.server_id = $2 AND sm.user_id = s.user_id)
+             AND EXISTS (SELECT 1 FROM roles r WHERE r.id = s.role_id AND r.server_id = $2)
+           ON CONFLICT DO NOTHING"#,
+    )
+    .bind(structure)
+    .bind(snapshot.server_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"INSERT INTO channel_members
+           SELECT s.* FROM jsonb_populate_recordset(NULL::channel_members, $1->'channel_members') s
+           WHERE EXISTS (SELECT 1 FROM server_members sm WHERE sm.server_id = $2 AND sm.user_id = s.user_id)
+             AND EXISTS (SELECT 1 FROM channels c WHERE c.id = s.channel_id AND c.server_id = $2)
+           ON CONFLICT DO NOTHING"#,
+    )
+    .bind(structure)
+    .bind(snapshot.server_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"UPDATE servers SET system_channel_id = (
+             SELECT c.id FROM channels c
+             WHERE c.id = ($1->>'system_channel_id')::uuid AND c.server_id = $2
+           )
+           WHERE id = $2"#,
+    )
+    .bind(structure)
+    .bind(snapshot.server_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("DELETE FROM restore_snapshots WHERE id = $1")
+        .bind(snapshot.id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(RestoreRollbackResponse {
+        snapshot_id: snapshot.id,
+        categories_restored: categories_restored as usize,
+        roles_restored: roles_restored as usize,
+        channels_restored: channels_restored as usize,
+        sections_restored: Default::default(),
+    })
+}
+
+/// Detached channels of expired snapshots, oldest snapshot first.
+pub async fn list_expired_snapshot_channels(pool: &Pool, limit: i64) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT c.id FROM channels c
+        JOIN restore_snapshots s ON s.id = c.detached_snapshot_id
+        WHERE s.expires_at <= NOW()
+        ORDER BY s.expires_at
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Delete expired snapshots whose detached channels are all gone.
+pub async fn delete_expired_restore_snapshots(pool: &Pool) -> AppResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM restore_snapshots s
+        WHERE s.expires_at <= NOW()
+          AND NOT EXISTS (SELECT 1 FROM channels c WHERE c.detached_snapshot_id = s.id)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,120 @@
+use uuid::Uuid;
+
+use crate::db::queries::not_under_legal_hold;
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Channel Retention ────────────────────────────────
+
+pub async fn get_channel_retention(
+    pool: &Pool,
+    channel_id: Uuid,
+) -> AppResult<Option<ChannelRetentionPolicy>> {
+    let policy = sqlx::query_as::<_, ChannelRetentionPolicy>(
+        "SELECT * FROM channel_retention_policies WHERE channel_id = $1",
+    )
+    .bind(channel_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(policy)
+}
+
+/// Set a channel's policy. Both limits None means keep forever, which
+/// removes the policy row.
+pub async fn set_channel_retention(
+    pool: &Pool,
+    channel_id: Uuid,
+    max_age_days: Option<i32>,
+    max_messages: Option<i32>,
+    archive: bool,
+    updated_by: Uuid,
+) -> AppResult<Option<ChannelRetentionPolicy>> {
+    if max_age_days.is_none() && max_messages.is_none() {
+        sqlx::query("DELETE FROM channel_retention_policies WHERE channel_id = $1")
+            .bind(channel_id)
+            .execute(pool)
+            .await?;
+        return Ok(None);
+    }
+    let policy = sqlx::query_as::<_, ChannelRetentionPolicy>(
+        r#"
+        INSERT INTO channel_retention_policies
+            (channel_id, max_age_days, max_messages, archive, updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (channel_id) DO UPDATE SET
+            max_age_days = EXCLUDED.max_age_days,
+            max_messages = EXCLUDED.max_messages,
+            archive = EXCLUDED.archive,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(channel_id)
+    .bind(max_age_days)
+    .bind(max_messages)
+    .bind(archive)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(Some(policy))
+}
+
+/// Every policy on a live server channel, with the server and the user the
+/// sweeper's audit entries are attributed to: whoever last set the policy,
+/// or the server owner once they are gone.
+pub async fn list_retention_sweep_targets(pool: &Pool) -> AppResult<Vec<RetentionSweepTarget>> {
+    let targets = sqlx::query_as::<_, RetentionSweepTarget>(
+        r#"
+        SELECT p.channel_id, c.server_id, COALESCE(p.updated_by, s.owner_id) AS actor_id,
+               p.max_age_days, p.max_messages, p.archive
+        FROM channel_retention_policies p
+        JOIN channels c ON c.id = p.channel_id
+        JOIN servers s ON s.id = c.server_id
+        WHERE s.deleted_at IS NULL
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(targets)
+}
+
+/// Oldest messages of a channel that fall outside its policy: older than
+/// `max_age_days`, or beyond the newest `max_messages`. Pinned messages are
+/// kept and don't count towards the limit; messages under legal hold are kept.
+pub async fn get_messages_past_retention(
+    pool: &Pool,
+    channel_id: Uuid,
+    max_age_days: Option<i32>,
+    max_messages: Option<i32>,
+    limit: i64,
+) -> AppResult<Vec<Message>> {
+    let messages = sqlx::query_as::<_, Message>(&format!(
+        r#"
+        WITH kept AS (
+            SELECT m.id, m.timestamp,
+                   ROW_NUMBER() OVER (ORDER BY m.timestamp DESC, m.id DESC) AS newest_rank
+            FROM messages m
+            WHERE m.channel_id = $1
+              AND NOT EXISTS (SELECT 1 FROM pinned_messages p WHERE p.message_id = m.id)
+        )
+        SELECT m.* FROM messages m
+        JOIN kept k ON k.id = m.id AND k.timestamp = m.timestamp
+        WHERE m.channel_id = $1
+          AND (($2::int IS NOT NULL AND m.timestamp < NOW() - make_interval(days => $2))
+               OR ($3::int IS NOT NULL AND k.newest_rank > $3))
+          AND {}
+        ORDER BY m.timestamp ASC
+        LIMIT $4
+        "#,
+        not_under_legal_hold("m")
+    ))
+    .bind(channel_id)
+    .bind(max_age_days)
+    .bind(max_messages)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(messages)
+}
@@ -1,6 +1,7 @@
 use uuid::Uuid;
 
-use crate::db::Pool;
+use crate::db::queries::SyncEntity;
+use crate::db::{Connection, Pool};
 use crate::errors::{AppError, AppResult};
 use crate::models::*;
 
@@ -30,6 +31,7 @@ pub async fn create_role(
     .bind(is_default)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Role, role.id, false).await?;
     Ok(role)
 }
 
@@ -88,14 +90,18 @@ pub async fn update_role(
     .bind(position)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_sync_change(pool, role.server_id, SyncEntity::Role, role.id, false).await?;
     Ok(role)
 }
 
 pub async fn delete_role(pool: &Pool, role_id: Uuid) -> AppResult<()> {
-    sqlx::query("DELETE FROM roles WHERE id = $1")
+    let deleted: Option<(Uuid,)> = sqlx::query_as("DELETE FROM roles WHERE id = $1 RETURNING server_id")
         .bind(role_id)
-        .execute(pool)
+        .fetch_optional(pool)
         .await?;
+    if let Some((server_id,)) = deleted {
+        crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Role, role_id, true).await?;
+    }
     Ok(())
 }
 
@@ -119,6 +125,7 @@ pub async fn assign_role(
     .bind(role_id)
     .execute(pool)
     .await?;
+    crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Member, user_id, false).await?;
     Ok(())
 }
 
@@ -136,6 +143,63 @@ pub async fn remove_role(
     .bind(role_id)
     .execute(pool)
     .await?;
+    crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Member, user_id, false).await?;
+    Ok(())
+}
+
+/// Renumber the server's non-default roles on the caller's transaction.
+/// Returns false, changing nothing, unless `positions` names exactly those
+/// roles. The caller records the sync changes once the transaction commits.
+pub async fn apply_role_positions(conn: &mut Connection, server_id: Uuid, positions: &[RolePosition]) -> AppResult<bool> {
+    let mut role_ids: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM roles WHERE server_id = $1 AND NOT is_default FOR UPDATE")
+            .bind(server_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    let mut given: Vec<Uuid> = positions.iter().map(|r| r.id).collect();
+    role_ids.sort_unstable();
+    given.sort_unstable();
+    if role_ids != given {
+        return Ok(false);
+    }
+    sqlx::query(
+        r#"
+        UPDATE roles r SET position = v.position
+        FROM UNNEST($2::uuid[], $3::int[]) AS v(id, position)
+        WHERE r.id = v.id AND r.server_id = $1
+        "#,
+    )
+    .bind(server_id)
+    .bind(positions.iter().map(|r| r.id).collect::<Vec<Uuid>>())
+    .bind(positions.iter().map(|r| r.position).collect::<Vec<i32>>())
+    .execute(&mut *conn)
+    .await?;
+    Ok(true)
+}
+
+/// Replace a member's roles with exactly `role_ids`.
+pub async fn set_member_roles(pool: &Pool, server_id: Uuid, user_id: Uuid, role_ids: &[Uuid]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM member_roles WHERE server_id = $1 AND user_id = $2 AND role_id <> ALL($3)")
+        .bind(server_id)
+        .bind(user_id)
+        .bind(role_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO member_roles (server_id, user_id, role_id)
+        SELECT $1, $2, role_id FROM UNNEST($3::uuid[]) AS role_id
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(role_ids)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    crate::db::queries::record_sync_change(pool, server_id, SyncEntity::Member, user_id, false).await?;
     Ok(())
 }
 
@@ -203,6 +267,34 @@ pub async fn get_member_permissions(
     Ok((is_owner, effective))
 }
 
+/// Members of a server holding `permission` (or administrator), including
+/// the owner. Channel overwrites are not considered.
+pub async fn list_server_member_ids_with_permission(
+    pool: &Pool,
+    server_id: Uuid,
+    permission: i64,
+) -> AppResult<Vec<Uuid>> {
+    let mask = permission | crate::permissions::ADMINISTRATOR;
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT sm.user_id FROM server_members sm
+        INNER JOIN servers s ON s.id = sm.server_id
+        WHERE sm.server_id = $1 AND (
+            s.owner_id = sm.user_id
+            OR EXISTS (SELECT 1 FROM roles r
+                       WHERE r.server_id = $1 AND r.is_default AND r.permissions & $2 <> 0)
+            OR EXISTS (SELECT 1 FROM member_roles mr INNER JOIN roles r ON r.id = mr.role_id
+                       WHERE mr.server_id = $1 AND mr.user_id = sm.user_id AND r.permissions & $2 <> 0)
+        )
+        "#,
+    )
+    .bind(server_id)
+    .bind(mask)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
 /// Cached variant — checks cache first, falls back to DB, caches for 2 min.
 pub async fn get_member_permissions_cached(
     pool: &Pool,
@@ -220,6 +312,120 @@ pub async fn get_member_permissions_cached(
     Ok(result)
 }
 
+/// Get a member's effective permissions in a specific server channel
+/// (server-level permissions with the channel's overwrites applied).
+pub async fn get_member_channel_permissions(
+    pool: &Pool,
+    server_id: Uuid,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<i64> {
+    use crate::permissions;
+
+    let (_, base_perms) = get_member_permissions(pool, server_id, user_id).await?;
+    let member_role_ids = get_member_role_ids(pool, server_id, user_id).await?;
+    let everyone_role_id = find_default_role(pool, server_id)
+        .await?
+        .map(|r| r.id)
+        .unwrap_or(Uuid::nil());
+
+    let overwrites = get_channel_overwrites(pool, channel_id).await?;
+
+    Ok(permissions::apply_channel_overwrites(
+        base_perms,
+        &overwrite_tuples(&overwrites),
+        &member_role_ids,
+        user_id,
+        everyone_role_id,
+    ))
+}
+
+/// Shadow candidate for [`get_member_channel_permissions`]: resolves the
+/// server owner, @everyone and the member's roles in a single query, so the
+/// whole computation takes two round trips instead of six.
+pub async fn get_member_channel_permissions_joined(
+    pool: &Pool,
+    server_id: Uuid,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<i64> {
+    use crate::permissions;
+
+    // One row per relevant role (@everyone or assigned); a single row with a
+    // NULL role id when the server has neither.
+    let rows: Vec<(Uuid, Option<Uuid>, i64, bool, bool)> = sqlx::query_as(
+        r#"
+        SELECT s.owner_id, r.id, COALESCE(r.permissions, 0),
+               COALESCE(r.is_default, FALSE), COALESCE(r.assigned, FALSE)
+        FROM servers s
+        LEFT JOIN (
+            SELECT r.id, r.permissions, r.is_default, mr.role_id IS NOT NULL AS assigned
+            FROM roles r
+            LEFT JOIN member_roles mr
+              ON mr.role_id = r.id AND mr.server_id = r.server_id AND mr.user_id = $2
+            WHERE r.server_id = $1 AND (r.is_default OR mr.role_id IS NOT NULL)
+        ) r ON TRUE
+        WHERE s.id = $1 AND s.deleted_at IS NULL
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let owner_id = rows
+        .first()
+        .map(|r| r.0)
+        .ok_or(AppError::NotFound("Server not found".into()))?;
+
+    let mut everyone: Option<(Uuid, i64)> = None;
+    let mut member_role_ids = Vec::new();
+    let mut member_role_perms = Vec::new();
+    for (_, role_id, perms, is_default, assigned) in rows {
+        let Some(role_id) = role_id else { continue };
+        if is_default && everyone.is_none() {
+            everyone = Some((role_id, perms));
+        }
+        if assigned {
+            member_role_ids.push(role_id);
+            member_role_perms.push(perms);
+        }
+    }
+
+    let base_perms = permissions::compute_server_permissions(
+        owner_id == user_id,
+        everyone.map(|(_, p)| p).unwrap_or(permissions::DEFAULT_PERMISSIONS),
+        &member_role_perms,
+    );
+    let overwrites = get_channel_overwrites(pool, channel_id).await?;
+
+    Ok(permissions::apply_channel_overwrites(
+        base_perms,
+        &overwrite_tuples(&overwrites),
+        &member_role_ids,
+        user_id,
+        everyone.map(|(id, _)| id).unwrap_or(Uuid::nil()),
+    ))
+}
+
+fn overwrite_tuples(
+    overwrites: &[ChannelPermissionOverwrite],
+) -> Vec<(crate::permissions::OverwriteTarget, i64, i64)> {
+    use crate::permissions::OverwriteTarget;
+
+    overwrites
+        .iter()
+        .map(|o| {
+            let target = if o.target_type == "role" {
+                OverwriteTarget::Role(o.target_id)
+            } else {
+                OverwriteTarget::Member(o.target_id)
+            };
+            (target, o.allow_bits, o.deny_bits)
+        })
+        .collect()
+}
+
 /// Check if a user has a required permission on a server. Returns error if not.
 pub async fn require_server_permission(
     pool: &Pool,
@@ -275,6 +481,7 @@ pub async fn set_channel_overwrite(
     .bind(deny_bits)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
     Ok(row)
 }
 
@@ -292,5 +499,6 @@ pub async fn delete_channel_overwrite(
     .bind(target_id)
     .execute(pool)
     .await?;
+    crate::db::queries::record_channel_change(pool, channel_id).await?;
     Ok(())
 }
@@ -0,0 +1,168 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Member Screening ────────────────────────────────
+
+pub async fn get_server_screening(pool: &Pool, server_id: Uuid) -> AppResult<Option<ServerScreening>> {
+    let screening =
+        sqlx::query_as::<_, ServerScreening>("SELECT * FROM server_screening WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(screening)
+}
+
+pub async fn upsert_server_screening(
+    pool: &Pool,
+    server_id: Uuid,
+    enabled: bool,
+    questions: &serde_json::Value,
+) -> AppResult<ServerScreening> {
+    let screening = sqlx::query_as::<_, ServerScreening>(
+        r#"
+        INSERT INTO server_screening (server_id, enabled, questions)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (server_id) DO UPDATE SET enabled = $2, questions = $3, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(enabled)
+    .bind(questions)
+    .fetch_one(pool)
+    .await?;
+    Ok(screening)
+}
+
+/// Whether joining the server requires an approved application.
+pub async fn is_screening_enabled(pool: &Pool, server_id: Uuid) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM server_screening WHERE server_id = $1 AND enabled)",
+    )
+    .bind(server_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+// ─── Applications ────────────────────────────────────
+
+const APPLICATION_COLUMNS: &str = r#"
+    a.id, a.server_id, a.user_id, u.username, a.invite_id, a.answers, a.status,
+    a.reviewed_by, a.reviewed_at, a.deny_reason, a.created_at
+"#;
+
+pub async fn create_server_application(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+    invite_id: Uuid,
+    answers: &serde_json::Value,
+) -> AppResult<Uuid> {
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO server_applications (server_id, user_id, invite_id, answers)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .bind(invite_id)
+    .bind(answers)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+pub async fn find_server_application(pool: &Pool, application_id: Uuid) -> AppResult<Option<ServerApplication>> {
+    let application = sqlx::query_as::<_, ServerApplication>(&format!(
+        "SELECT {} FROM server_applications a JOIN users u ON u.id = a.user_id WHERE a.id = $1",
+        APPLICATION_COLUMNS
+    ))
+    .bind(application_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(application)
+}
+
+pub async fn has_pending_application(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM server_applications
+                      WHERE server_id = $1 AND user_id = $2 AND status = 'pending')
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// A server's applications with the given status, oldest first.
+pub async fn list_server_applications(
+    pool: &Pool,
+    server_id: Uuid,
+    status: ApplicationStatus,
+) -> AppResult<Vec<ServerApplication>> {
+    let applications = sqlx::query_as::<_, ServerApplication>(&format!(
+        r#"
+        SELECT {} FROM server_applications a JOIN users u ON u.id = a.user_id
+        WHERE a.server_id = $1 AND a.status = $2
+        ORDER BY a.created_at
+        LIMIT 500
+        "#,
+        APPLICATION_COLUMNS
+    ))
+    .bind(server_id)
+    .bind(status.as_str())
+    .fetch_all(pool)
+    .await?;
+    Ok(applications)
+}
+
+/// A user's applications, newest first.
+pub async fn list_user_applications(pool: &Pool, user_id: Uuid) -> AppResult<Vec<ServerApplication>> {
+    let applications = sqlx::query_as::<_, ServerApplication>(&format!(
+        r#"
+        SELECT {} FROM server_applications a JOIN users u ON u.id = a.user_id
+        WHERE a.user_id = $1
+        ORDER BY a.created_at DESC
+        LIMIT 100
+        "#,
+        APPLICATION_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(applications)
+}
+
+/// Record a moderator's decision on a pending application. Returns false if
+/// it was no longer pending.
+pub async fn decide_server_application(
+    pool: &Pool,
+    application_id: Uuid,
+    status: ApplicationStatus,
+    reviewed_by: Uuid,
+    deny_reason: Option<&str>,
+) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE server_applications
+        SET status = $2, reviewed_by = $3, reviewed_at = NOW(), deny_reason = $4
+        WHERE id = $1 AND status = 'pending'
+        "#,
+    )
+    .bind(application_id)
+    .bind(status.as_str())
+    .bind(reviewed_by)
+    .bind(deny_reason)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
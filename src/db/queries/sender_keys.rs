@@ -1,6 +1,8 @@
 use uuid::Uuid;
 
-use crate::db::Pool;
+#[cfg(feature = "sqlite")]
+use crate::db::dialect;
+use crate::db::{Connection, Pool};
 use crate::errors::AppResult;
 use crate::models::*;
 
@@ -14,7 +16,39 @@ pub async fn insert_sender_key_distributions(
     distributions: &[(Uuid, Uuid, Vec<u8>)], // (to_user_id, distribution_id, encrypted_skdm)
 ) -> AppResult<()> {
     let mut tx = pool.begin().await?;
+    insert_distributions(&mut tx, channel_id, from_user_id, distributions).await?;
+    tx.commit().await?;
+    Ok(())
+}
 
+/// Replace every SKDM a sender has in a channel with a freshly rotated batch,
+/// so recipients never see a mix of burned and current sender keys.
+pub async fn replace_sender_key_distributions(
+    pool: &Pool,
+    channel_id: Uuid,
+    from_user_id: Uuid,
+    distributions: &[(Uuid, Uuid, Vec<u8>)], // (to_user_id, distribution_id, encrypted_skdm)
+) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+    let superseded = sqlx::query(
+        "DELETE FROM sender_key_distributions WHERE channel_id = $1 AND from_user_id = $2",
+    )
+    .bind(channel_id)
+    .bind(from_user_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    insert_distributions(&mut tx, channel_id, from_user_id, distributions).await?;
+    tx.commit().await?;
+    Ok(superseded)
+}
+
+async fn insert_distributions(
+    conn: &mut Connection,
+    channel_id: Uuid,
+    from_user_id: Uuid,
+    distributions: &[(Uuid, Uuid, Vec<u8>)],
+) -> AppResult<()> {
     for (to_user_id, distribution_id, encrypted_skdm) in distributions {
         sqlx::query(
             r#"
@@ -31,11 +65,9 @@ pub async fn insert_sender_key_distributions(
         .bind(to_user_id)
         .bind(distribution_id)
         .bind(encrypted_skdm)
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
-
-    tx.commit().await?;
     Ok(())
 }
 
@@ -71,6 +103,44 @@ pub async fn clear_sender_key_distributions_for_user(
     Ok(())
 }
 
+/// Delete a channel's SKDMs sent by or to a user (used when they leave a
+/// group DM, so the remaining members' chains must be redistributed).
+pub async fn clear_channel_sender_keys_for_member(
+    pool: &Pool,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<()> {
+    sqlx::query(
+        "DELETE FROM sender_key_distributions WHERE channel_id = $1 AND (to_user_id = $2 OR from_user_id = $2)",
+    )
+    .bind(channel_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Channels of a server in which a user sent or received SKDMs — the
+/// channels whose sender keys are burned when they leave the server.
+pub async fn get_server_sender_key_channels_for_user(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT skd.channel_id FROM sender_key_distributions skd
+        INNER JOIN channels c ON c.id = skd.channel_id
+        WHERE c.server_id = $1 AND (skd.to_user_id = $2 OR skd.from_user_id = $2)
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
 /// Delete consumed SKDMs (after client has fetched them).
 pub async fn delete_sender_key_distributions(
     pool: &Pool,
@@ -90,10 +160,9 @@ pub async fn delete_sender_key_distributions(
 
     #[cfg(feature = "sqlite")]
     {
-        let placeholders: Vec<String> = (1..=ids.len()).map(|i| format!("${}", i)).collect();
         let sql = format!(
             "DELETE FROM sender_key_distributions WHERE id IN ({})",
-            placeholders.join(", ")
+            dialect::placeholders(1, ids.len())
         );
         let mut query = sqlx::query(&sql);
         for id in ids {
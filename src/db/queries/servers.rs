@@ -1,9 +1,11 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::db::queries::{record_sync_change, record_sync_changes, SyncEntity};
 use crate::db::Pool;
 use crate::errors::AppResult;
 use crate::models::*;
+use crate::profile_media::{self, MediaSlot};
 
 // ─── Servers ───────────────────────────────────────────
 
@@ -28,13 +30,32 @@ pub async fn create_server(
 }
 
 pub async fn find_server_by_id(pool: &Pool, id: Uuid) -> AppResult<Option<Server>> {
-    let server = sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE id = $1")
+    let server = sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE id = $1 AND deleted_at IS NULL")
         .bind(id)
         .fetch_optional(pool)
         .await?;
     Ok(server)
 }
 
+/// A server's upload tier ("standard" or "boosted"), or None if the server doesn't exist.
+pub async fn get_server_upload_tier(pool: &Pool, server_id: Uuid) -> AppResult<Option<String>> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT upload_tier FROM servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Set a server's upload tier. Returns false if the server doesn't exist.
+pub async fn set_server_upload_tier(pool: &Pool, server_id: Uuid, tier: &str) -> AppResult<bool> {
+    let result = sqlx::query("UPDATE servers SET upload_tier = $2 WHERE id = $1")
+        .bind(server_id)
+        .bind(tier)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 /// Cached variant — checks cache first, falls back to DB, caches for 5 min.
 pub async fn find_server_by_id_cached(
     pool: &Pool,
@@ -58,7 +79,7 @@ pub async fn get_user_servers(pool: &Pool, user_id: Uuid) -> AppResult<Vec<Serve
         r#"
         SELECT s.* FROM servers s
         INNER JOIN server_members sm ON s.id = sm.server_id
-        WHERE sm.user_id = $1
+        WHERE sm.user_id = $1 AND s.deleted_at IS NULL
         ORDER BY s.created_at DESC
         "#,
     )
@@ -78,6 +99,7 @@ pub async fn update_system_channel(
         .bind(server_id)
         .execute(pool)
         .await?;
+    record_sync_change(pool, server_id, SyncEntity::Server, server_id, false).await?;
     Ok(())
 }
 
@@ -91,6 +113,7 @@ pub async fn update_server_meta(
         .bind(server_id)
         .execute(pool)
         .await?;
+    record_sync_change(pool, server_id, SyncEntity::Server, server_id, false).await?;
     Ok(())
 }
 
@@ -104,6 +127,40 @@ pub async fn update_server_icon(
         .bind(server_id)
         .execute(pool)
         .await?;
+    record_sync_change(pool, server_id, SyncEntity::Server, server_id, false).await?;
+    Ok(())
+}
+
+// ─── List Versions ─────────────────────────────────────
+//
+// `servers.list_version` backs the ETags of a server's channel, role and
+// member lists. Every query that changes what those lists return bumps it,
+// directly or by recording a sync change (see sync.rs).
+
+pub async fn get_server_list_version(pool: &Pool, server_id: Uuid) -> AppResult<Option<i64>> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT list_version FROM servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.0))
+}
+
+pub async fn bump_server_list_version(pool: &Pool, server_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE servers SET list_version = list_version + 1 WHERE id = $1")
+        .bind(server_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Bump every server a user is in — their name and avatar show in each member list.
+pub async fn bump_user_server_list_versions(pool: &Pool, user_id: Uuid) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE servers SET list_version = list_version + 1 WHERE id IN (SELECT server_id FROM server_members WHERE user_id = $1)",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
@@ -115,12 +172,20 @@ pub async fn add_server_member(
     user_id: Uuid,
     encrypted_role: &[u8],
 ) -> AppResult<ServerMember> {
+    // `xmax = 0` tells a fresh insert from an update of an existing member;
+    // only the insert is a join for insights.
     let member = sqlx::query_as::<_, ServerMember>(
         r#"
-        INSERT INTO server_members (id, server_id, user_id, encrypted_role, joined_at)
-        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-        ON CONFLICT (server_id, user_id) DO UPDATE SET encrypted_role = EXCLUDED.encrypted_role
-        RETURNING *
+        WITH member AS (
+            INSERT INTO server_members (id, server_id, user_id, encrypted_role, joined_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            ON CONFLICT (server_id, user_id) DO UPDATE SET encrypted_role = EXCLUDED.encrypted_role
+            RETURNING *, (xmax = 0) AS inserted
+        ), joined AS (
+            INSERT INTO server_membership_events (server_id, joined)
+            SELECT server_id, TRUE FROM member WHERE inserted
+        )
+        SELECT * FROM member
         "#,
     )
     .bind(Uuid::new_v4())
@@ -129,12 +194,19 @@ pub async fn add_server_member(
     .bind(encrypted_role)
     .fetch_one(pool)
     .await?;
+    record_sync_change(pool, server_id, SyncEntity::Member, user_id, false).await?;
     Ok(member)
 }
 
 pub async fn is_server_member(pool: &Pool, server_id: Uuid, user_id: Uuid) -> AppResult<bool> {
     let row: (bool,) = sqlx::query_as(
-        "SELECT EXISTS(SELECT 1 FROM server_members WHERE server_id = $1 AND user_id = $2)",
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM server_members sm
+            JOIN servers s ON s.id = sm.server_id
+            WHERE sm.server_id = $1 AND sm.user_id = $2 AND s.deleted_at IS NULL
+        )
+        "#,
     )
     .bind(server_id)
     .bind(user_id)
@@ -152,29 +224,152 @@ pub async fn get_server_members(
     offset: i64,
 ) -> AppResult<Vec<ServerMemberResponse>> {
     // Step 1: Get members (paginated)
-    #[allow(clippy::type_complexity)]
-    let rows: Vec<(Uuid, String, Option<String>, Option<String>, DateTime<Utc>, Option<String>, Option<DateTime<Utc>>, bool)> =
-        sqlx::query_as(
-            r#"
-            SELECT sm.user_id, u.username, u.display_name, u.avatar_url, sm.joined_at, sm.nickname, sm.timed_out_until, u.is_system
-            FROM server_members sm
-            INNER JOIN users u ON u.id = sm.user_id
-            WHERE sm.server_id = $1
-            ORDER BY sm.joined_at ASC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(server_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(pool)
-        .await?;
+    let rows: Vec<MemberRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT {}
+        FROM server_members sm
+        INNER JOIN users u ON u.id = sm.user_id
+        LEFT JOIN user_profile_media pm
+            ON pm.user_id = sm.user_id AND pm.slot = 'server_avatar:' || sm.server_id::text
+        WHERE sm.server_id = $1
+        ORDER BY sm.joined_at ASC
+        LIMIT $2 OFFSET $3
+        "#,
+        MEMBER_COLUMNS
+    ))
+    .bind(server_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    // Step 2: Attach role assignments
+    member_responses(pool, server_id, rows).await
+}
+
+/// One chunk of a server's roster in join order, starting after the
+/// `(joined_at, user_id)` cursor. With `prefix`, only members whose username,
+/// display name or nickname starts with it (case-insensitive).
+pub async fn get_server_member_chunk(
+    pool: &Pool,
+    server_id: Uuid,
+    after: Option<(DateTime<Utc>, Uuid)>,
+    prefix: Option<&str>,
+    limit: i64,
+) -> AppResult<Vec<ServerMemberResponse>> {
+    let pattern = prefix.map(|p| format!("{}%", escape_like(p)));
+    let rows: Vec<MemberRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT {}
+        FROM server_members sm
+        INNER JOIN users u ON u.id = sm.user_id
+        LEFT JOIN user_profile_media pm
+            ON pm.user_id = sm.user_id AND pm.slot = 'server_avatar:' || sm.server_id::text
+        WHERE sm.server_id = $1
+          AND ($2::timestamptz IS NULL OR (sm.joined_at, sm.user_id) > ($2, $3::uuid))
+          AND ($4::text IS NULL
+               OR u.username ILIKE $4 OR u.display_name ILIKE $4 OR sm.nickname ILIKE $4)
+        ORDER BY sm.joined_at ASC, sm.user_id ASC
+        LIMIT $5
+        "#,
+        MEMBER_COLUMNS
+    ))
+    .bind(server_id)
+    .bind(after.map(|(joined_at, _)| joined_at))
+    .bind(after.map(|(_, user_id)| user_id))
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
 
-    // Step 2: Get all role assignments for this server in one query
+    member_responses(pool, server_id, rows).await
+}
+
+/// Members whose username, display name or nickname contains `query`
+/// (case-insensitive, served by the trigram indexes), best matches first.
+/// `role_id` keeps members holding that role; `timed_out` keeps members who
+/// are (or are not) currently timed out.
+#[allow(clippy::too_many_arguments)]
+pub async fn search_server_members(
+    pool: &Pool,
+    server_id: Uuid,
+    query: &str,
+    role_id: Option<Uuid>,
+    timed_out: Option<bool>,
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<ServerMemberResponse>> {
+    let rows: Vec<MemberRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT {}
+        FROM server_members sm
+        INNER JOIN users u ON u.id = sm.user_id
+        LEFT JOIN user_profile_media pm
+            ON pm.user_id = sm.user_id AND pm.slot = 'server_avatar:' || sm.server_id::text
+        WHERE sm.server_id = $1
+          AND (u.username ILIKE $2 OR u.display_name ILIKE $2 OR sm.nickname ILIKE $2)
+          AND ($3::uuid IS NULL OR EXISTS (
+                SELECT 1 FROM member_roles mr
+                WHERE mr.server_id = sm.server_id AND mr.user_id = sm.user_id AND mr.role_id = $3))
+          AND ($4::bool IS NULL
+               OR (sm.timed_out_until IS NOT NULL AND sm.timed_out_until > NOW()) = $4)
+        ORDER BY GREATEST(
+                     similarity(u.username, $5),
+                     similarity(COALESCE(u.display_name, ''), $5),
+                     similarity(COALESCE(sm.nickname, ''), $5)
+                 ) DESC,
+                 sm.joined_at ASC, sm.user_id ASC
+        LIMIT $6 OFFSET $7
+        "#,
+        MEMBER_COLUMNS
+    ))
+    .bind(server_id)
+    .bind(format!("%{}%", escape_like(query)))
+    .bind(role_id)
+    .bind(timed_out)
+    .bind(query)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    member_responses(pool, server_id, rows).await
+}
+
+/// Escape LIKE wildcards so user input matches literally.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+const MEMBER_COLUMNS: &str = "sm.user_id, u.username, u.display_name, u.avatar_url, sm.joined_at, \
+    sm.nickname, pm.content_hash, sm.timed_out_until, u.is_system, u.abuse_score, sm.temporary";
+
+type MemberRow = (
+    Uuid,
+    String,
+    Option<String>,
+    Option<String>,
+    DateTime<Utc>,
+    Option<String>,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    bool,
+    Option<i16>,
+    bool,
+);
+
+/// Build member responses, fetching role assignments for just these members.
+async fn member_responses(
+    pool: &Pool,
+    server_id: Uuid,
+    rows: Vec<MemberRow>,
+) -> AppResult<Vec<ServerMemberResponse>> {
+    let user_ids: Vec<Uuid> = rows.iter().map(|row| row.0).collect();
     let role_assignments: Vec<(Uuid, Uuid)> = sqlx::query_as(
-        "SELECT user_id, role_id FROM member_roles WHERE server_id = $1",
+        "SELECT user_id, role_id FROM member_roles WHERE server_id = $1 AND user_id = ANY($2)",
     )
     .bind(server_id)
+    .bind(&user_ids)
     .fetch_all(pool)
     .await?;
 
@@ -185,10 +380,22 @@ pub async fn get_server_members(
         role_map.entry(uid).or_default().push(rid);
     }
 
+    // `abuse` content filters: (minimum score, action), strictest first
+    let mut abuse_filters: Vec<(i16, String)> = sqlx::query_as::<_, (String, String)>(
+        "SELECT pattern, action FROM content_filters WHERE server_id = $1 AND filter_type = 'abuse'",
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|(pattern, action)| Some((pattern.parse().ok()?, action)))
+    .collect();
+    abuse_filters.sort_by_key(|(_, action)| action != "hide");
+
     Ok(rows
         .into_iter()
         .map(
-            |(user_id, username, display_name, avatar_url, joined_at, nickname, timed_out_until, is_sys)| {
+            |(user_id, username, display_name, avatar_url, joined_at, nickname, server_avatar_hash, timed_out_until, is_sys, abuse_score, temporary)| {
                 // Only include timed_out_until if it's still in the future
                 let active_timeout = timed_out_until.filter(|t| *t > Utc::now());
                 ServerMemberResponse {
@@ -198,9 +405,19 @@ pub async fn get_server_members(
                     avatar_url,
                     joined_at,
                     nickname,
+                    server_avatar_url: server_avatar_hash.map(|hash| {
+                        profile_media::url(MediaSlot::ServerAvatar(server_id), user_id, &hash)
+                    }),
                     role_ids: role_map.remove(&user_id).unwrap_or_default(),
                     timed_out_until: active_timeout,
                     is_system: if is_sys { Some(true) } else { None },
+                    automod_action: abuse_score.and_then(|score| {
+                        abuse_filters
+                            .iter()
+                            .find(|(min, _)| score >= *min)
+                            .map(|(_, action)| action.clone())
+                    }),
+                    temporary: if temporary { Some(true) } else { None },
                 }
             },
         )
@@ -234,13 +451,23 @@ pub async fn remove_server_member(
     .execute(pool)
     .await?;
 
-    // Remove from server
-    sqlx::query("DELETE FROM server_members WHERE server_id = $1 AND user_id = $2")
-        .bind(server_id)
-        .bind(user_id)
-        .execute(pool)
-        .await?;
+    // Remove from server, logging the leave for insights
+    sqlx::query(
+        r#"
+        WITH removed AS (
+            DELETE FROM server_members WHERE server_id = $1 AND user_id = $2
+            RETURNING server_id
+        )
+        INSERT INTO server_membership_events (server_id, joined)
+        SELECT server_id, FALSE FROM removed
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
 
+    record_sync_change(pool, server_id, SyncEntity::Member, user_id, true).await?;
     Ok(())
 }
 
@@ -253,6 +480,7 @@ pub async fn count_server_members(pool: &Pool, server_id: Uuid) -> AppResult<i64
 }
 
 pub async fn delete_server(pool: &Pool, server_id: Uuid) -> AppResult<()> {
+    let member_ids = get_server_member_ids(pool, server_id).await?;
     // All child tables use ON DELETE CASCADE, so this single delete
     // removes server_members, channels (→ messages, channel_members, etc.),
     // roles, member_roles, invites, bans, categories, etc.
@@ -260,9 +488,163 @@ pub async fn delete_server(pool: &Pool, server_id: Uuid) -> AppResult<()> {
         .bind(server_id)
         .execute(pool)
         .await?;
+    // Tell every former member's delta sync that the server is gone
+    record_sync_changes(pool, server_id, SyncEntity::Member, &member_ids, true).await?;
     Ok(())
 }
 
+// ─── Server Deletion ───────────────────────────────
+//
+// Deleting a server only stamps `deleted_at`; the chokepoints above
+// (find_server_by_id, get_user_servers, is_server_member) then treat it as
+// gone. The deleted-servers maintenance job purges it after the grace period.
+
+/// Mark a server deleted. Returns the deletion time, or None if it already was.
+pub async fn soft_delete_server(
+    pool: &Pool,
+    server_id: Uuid,
+    deleted_by: Uuid,
+) -> AppResult<Option<DateTime<Utc>>> {
+    let row: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        r#"
+        UPDATE servers SET deleted_at = NOW(), deleted_by = $2
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING deleted_at
+        "#,
+    )
+    .bind(server_id)
+    .bind(deleted_by)
+    .fetch_optional(pool)
+    .await?;
+    if row.is_some() {
+        // Members' delta sync drops the server as if they had left
+        let member_ids = get_server_member_ids(pool, server_id).await?;
+        record_sync_changes(pool, server_id, SyncEntity::Member, &member_ids, true).await?;
+    }
+    Ok(row.map(|r| r.0))
+}
+
+/// Undo a soft delete. Returns false if the server isn't in its grace period.
+pub async fn restore_deleted_server(pool: &Pool, server_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        "UPDATE servers SET deleted_at = NULL, deleted_by = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+    )
+    .bind(server_id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    let member_ids = get_server_member_ids(pool, server_id).await?;
+    record_sync_changes(pool, server_id, SyncEntity::Member, &member_ids, false).await?;
+    Ok(true)
+}
+
+/// A soft-deleted server, with its owner, if it is still in its grace period.
+pub async fn find_deleted_server(pool: &Pool, server_id: Uuid) -> AppResult<Option<(Uuid, DeletedServer)>> {
+    #[allow(clippy::type_complexity)]
+    let row: Option<(Uuid, Uuid, Vec<u8>, Option<String>, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT owner_id, id, encrypted_meta, icon_url, deleted_at FROM servers WHERE id = $1 AND deleted_at IS NOT NULL",
+    )
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(owner_id, id, encrypted_meta, icon_url, deleted_at)| {
+        (owner_id, DeletedServer { id, encrypted_meta, icon_url, deleted_at })
+    }))
+}
+
+pub async fn get_deleted_servers_owned_by(pool: &Pool, user_id: Uuid) -> AppResult<Vec<DeletedServer>> {
+    let servers = sqlx::query_as::<_, DeletedServer>(
+        r#"
+        SELECT id, encrypted_meta, icon_url, deleted_at FROM servers
+        WHERE owner_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(servers)
+}
+
+/// Servers deleted more than `grace_days` ago, oldest first. Servers under
+/// legal hold wait until the hold is released.
+pub async fn list_servers_due_for_purge(pool: &Pool, grace_days: i32, limit: i64) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT id FROM servers
+        WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - make_interval(days => $1)
+          AND NOT EXISTS (
+                SELECT 1 FROM legal_holds h
+                WHERE h.subject_type = 'server' AND h.subject_id = servers.id
+                  AND h.released_at IS NULL)
+        ORDER BY deleted_at ASC
+        LIMIT $2
+        "#,
+    )
+    .bind(grace_days)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Storage and thumbnail keys of every attachment in a server's channels.
+/// The storage key is absent for attachments in a shared blob; purging the
+/// server's messages releases those instead.
+pub async fn get_server_attachment_keys(
+    pool: &Pool,
+    server_id: Uuid,
+) -> AppResult<Vec<(Uuid, Option<String>, Option<String>)>> {
+    let rows = sqlx::query_as(
+        r#"
+        SELECT a.id, CASE WHEN a.content_hash IS NULL THEN a.storage_key END, a.thumbnail_key
+        FROM attachments a
+        WHERE a.server_id = $1
+           OR a.message_id IN (
+                SELECT m.id FROM messages m
+                JOIN channels c ON c.id = m.channel_id
+                WHERE c.server_id = $1)
+        "#,
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Delete a server's messages and the rows that reference them. Reactions,
+/// pins, reports and attachments have no FK to the partitioned messages
+/// table, so the server's cascade wouldn't reach them. Shared attachment
+/// blobs are released, for the GC to delete once nothing else uses them.
+pub async fn purge_server_messages(pool: &Pool, server_id: Uuid) -> AppResult<u64> {
+    const SERVER_MESSAGES: &str = "SELECT m.id FROM messages m JOIN channels c ON c.id = m.channel_id WHERE c.server_id = $1";
+    let mut tx = pool.begin().await?;
+    for table in ["reactions", "pinned_messages", "reports"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE message_id IN ({})", table, SERVER_MESSAGES))
+            .bind(server_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(&format!(
+        "WITH gone AS (DELETE FROM attachments WHERE server_id = $1 OR message_id IN ({}) RETURNING content_hash) {}",
+        SERVER_MESSAGES,
+        crate::db::queries::RELEASE_DELETED_BLOBS
+    ))
+    .bind(server_id)
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query(
+        "DELETE FROM messages WHERE channel_id IN (SELECT id FROM channels WHERE server_id = $1)",
+    )
+    .bind(server_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
 // ─── Servers (ownership) ────────────────────────────
 
 pub async fn get_servers_owned_by(pool: &Pool, user_id: Uuid) -> AppResult<Vec<Server>> {
@@ -0,0 +1,157 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Sync Journal ─────────────────────────────────────
+//
+// Changes are recorded after the write they describe, so a client that reads
+// version N has already seen every change journaled at or before N (modulo
+// concurrent writers committing out of sequence order). Recording a change
+// also bumps the server's list version behind the REST ETags.
+
+/// What a journal entry refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncEntity {
+    Server,
+    Channel,
+    Role,
+    /// `entity_id` is the member's user id.
+    Member,
+}
+
+impl SyncEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncEntity::Server => "server",
+            SyncEntity::Channel => "channel",
+            SyncEntity::Role => "role",
+            SyncEntity::Member => "member",
+        }
+    }
+}
+
+pub async fn record_sync_change(
+    pool: &Pool,
+    server_id: Uuid,
+    entity: SyncEntity,
+    entity_id: Uuid,
+    deleted: bool,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        WITH change AS (
+            INSERT INTO sync_changes (server_id, entity_type, entity_id, deleted)
+            VALUES ($1, $2, $3, $4)
+            RETURNING server_id
+        )
+        UPDATE servers SET list_version = list_version + 1
+        WHERE id = (SELECT server_id FROM change)
+        "#,
+    )
+    .bind(server_id)
+    .bind(entity.as_str())
+    .bind(entity_id)
+    .bind(deleted)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a change to a server channel (no-op for DMs and group DMs).
+pub async fn record_channel_change(pool: &Pool, channel_id: Uuid) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        WITH change AS (
+            INSERT INTO sync_changes (server_id, entity_type, entity_id)
+            SELECT server_id, 'channel', id FROM channels
+            WHERE id = $1 AND server_id IS NOT NULL
+            RETURNING server_id
+        )
+        UPDATE servers SET list_version = list_version + 1
+        WHERE id IN (SELECT server_id FROM change)
+        "#,
+    )
+    .bind(channel_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record the same kind of change for many entities of one server at once.
+pub async fn record_sync_changes(
+    pool: &Pool,
+    server_id: Uuid,
+    entity: SyncEntity,
+    entity_ids: &[Uuid],
+    deleted: bool,
+) -> AppResult<()> {
+    if entity_ids.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO sync_changes (server_id, entity_type, entity_id, deleted)
+        SELECT $1, $2, id, $4 FROM UNNEST($3::uuid[]) AS id
+        "#,
+    )
+    .bind(server_id)
+    .bind(entity.as_str())
+    .bind(entity_ids)
+    .bind(deleted)
+    .execute(pool)
+    .await?;
+    crate::db::queries::bump_server_list_version(pool, server_id).await
+}
+
+/// Newest and oldest versions still in the journal (0 and None when empty).
+/// The newest is read from the table, not the sequence, so it never covers a
+/// change that is not yet visible.
+pub async fn sync_journal_bounds(pool: &Pool) -> AppResult<(i64, Option<i64>)> {
+    let row: (i64, Option<i64>) =
+        sqlx::query_as("SELECT COALESCE(MAX(version), 0), MIN(version) FROM sync_changes")
+            .fetch_one(pool)
+            .await?;
+    Ok(row)
+}
+
+/// Changes after `since` that matter to `user_id`: anything in `server_ids`
+/// (their servers) except other members joining or leaving, plus their own
+/// membership changes anywhere. Only the latest entry per entity is returned.
+pub async fn get_sync_changes(
+    pool: &Pool,
+    user_id: Uuid,
+    server_ids: &[Uuid],
+    since: i64,
+) -> AppResult<Vec<SyncChange>> {
+    let changes = sqlx::query_as::<_, SyncChange>(
+        r#"
+        SELECT DISTINCT ON (server_id, entity_type, entity_id)
+               version, server_id, entity_type, entity_id, deleted
+        FROM sync_changes
+        WHERE version > $3
+          AND ((server_id = ANY($2) AND entity_type <> 'member')
+               OR (entity_type = 'member' AND entity_id = $1))
+        ORDER BY server_id, entity_type, entity_id, version DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(server_ids)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    Ok(changes)
+}
+
+/// Delete journal entries older than `retention_days`; clients that were away
+/// longer are told to do a full sync.
+pub async fn purge_old_sync_changes(pool: &Pool, retention_days: u32) -> AppResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM sync_changes WHERE changed_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(retention_days as i32)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
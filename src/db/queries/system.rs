@@ -69,6 +69,7 @@ pub async fn set_member_timeout(
     .bind(user_id)
     .execute(pool)
     .await?;
+    crate::db::queries::bump_server_list_version(pool, server_id).await?;
     Ok(())
 }
 
@@ -103,5 +104,45 @@ pub async fn update_member_nickname(
     .bind(user_id)
     .execute(pool)
     .await?;
+    crate::db::queries::bump_server_list_version(pool, server_id).await?;
     Ok(())
 }
+
+/// Members of a server that have a nickname set.
+pub async fn list_member_nicknames(pool: &Pool, server_id: Uuid) -> AppResult<Vec<(Uuid, String)>> {
+    let rows = sqlx::query_as(
+        "SELECT user_id, nickname FROM server_members WHERE server_id = $1 AND nickname IS NOT NULL",
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// A member's per-server profile: (nickname, server avatar URL).
+/// None if the user is not a member.
+pub async fn get_member_profile(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<(Option<String>, Option<String>)>> {
+    let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT sm.nickname, pm.content_hash
+        FROM server_members sm
+        LEFT JOIN user_profile_media pm
+            ON pm.user_id = sm.user_id AND pm.slot = 'server_avatar:' || sm.server_id::text
+        WHERE sm.server_id = $1 AND sm.user_id = $2
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(nickname, hash)| {
+        let url = hash.map(|h| {
+            crate::profile_media::url(crate::profile_media::MediaSlot::ServerAvatar(server_id), user_id, &h)
+        });
+        (nickname, url)
+    }))
+}
@@ -0,0 +1,127 @@
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Upload Limits ───────────────────────────────────
+
+pub async fn list_upload_limits(pool: &Pool, server_id: Uuid) -> AppResult<Vec<UploadLimit>> {
+    let limits = sqlx::query_as::<_, UploadLimit>(
+        "SELECT * FROM upload_limits WHERE server_id = $1 ORDER BY role_id NULLS LAST, channel_id",
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(limits)
+}
+
+pub async fn set_role_upload_limit(
+    pool: &Pool,
+    server_id: Uuid,
+    role_id: Uuid,
+    max_size_bytes: Option<i64>,
+    max_count: Option<i32>,
+) -> AppResult<UploadLimit> {
+    let limit = sqlx::query_as::<_, UploadLimit>(
+        r#"
+        INSERT INTO upload_limits (server_id, role_id, max_size_bytes, max_count)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (role_id) WHERE role_id IS NOT NULL
+        DO UPDATE SET max_size_bytes = $3, max_count = $4, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(role_id)
+    .bind(max_size_bytes)
+    .bind(max_count)
+    .fetch_one(pool)
+    .await?;
+    Ok(limit)
+}
+
+pub async fn set_channel_upload_limit(
+    pool: &Pool,
+    server_id: Uuid,
+    channel_id: Uuid,
+    max_size_bytes: Option<i64>,
+    max_count: Option<i32>,
+) -> AppResult<UploadLimit> {
+    let limit = sqlx::query_as::<_, UploadLimit>(
+        r#"
+        INSERT INTO upload_limits (server_id, channel_id, max_size_bytes, max_count)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (channel_id) WHERE channel_id IS NOT NULL
+        DO UPDATE SET max_size_bytes = $3, max_count = $4, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(channel_id)
+    .bind(max_size_bytes)
+    .bind(max_count)
+    .fetch_one(pool)
+    .await?;
+    Ok(limit)
+}
+
+pub async fn delete_role_upload_limit(pool: &Pool, server_id: Uuid, role_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM upload_limits WHERE server_id = $1 AND role_id = $2")
+        .bind(server_id)
+        .bind(role_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_channel_upload_limit(pool: &Pool, server_id: Uuid, channel_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM upload_limits WHERE server_id = $1 AND channel_id = $2")
+        .bind(server_id)
+        .bind(channel_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Limits set on the member's roles, @everyone included.
+pub async fn get_member_role_upload_limits(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Vec<UploadLimit>> {
+    let limits = sqlx::query_as::<_, UploadLimit>(
+        r#"
+        SELECT l.* FROM upload_limits l
+        JOIN roles r ON r.id = l.role_id
+        WHERE r.server_id = $1
+          AND (r.is_default OR EXISTS(SELECT 1 FROM member_roles mr
+                                      WHERE mr.role_id = r.id AND mr.user_id = $2))
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(limits)
+}
+
+pub async fn get_channel_upload_limit(pool: &Pool, channel_id: Uuid) -> AppResult<Option<UploadLimit>> {
+    let limit = sqlx::query_as::<_, UploadLimit>("SELECT * FROM upload_limits WHERE channel_id = $1")
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(limit)
+}
+
+/// Unexpired upload sessions the user has open in the channel.
+pub async fn count_active_upload_sessions(pool: &Pool, channel_id: Uuid, user_id: Uuid) -> AppResult<i64> {
+    let (count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM upload_sessions WHERE channel_id = $1 AND user_id = $2 AND expires_at > NOW()",
+    )
+    .bind(channel_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
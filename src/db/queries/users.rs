@@ -3,6 +3,7 @@ use uuid::Uuid;
 use crate::db::Pool;
 use crate::errors::{AppError, AppResult};
 use crate::models::*;
+use crate::profile_media::MediaSlot;
 
 // ─── Users ─────────────────────────────────────────────
 
@@ -68,7 +69,7 @@ pub async fn find_user_basic_by_id(pool: &Pool, id: Uuid) -> AppResult<Option<Us
     let user = sqlx::query_as::<_, UserBasic>(
         "SELECT id, username, display_name, avatar_url, about_me, \
          custom_status, custom_status_emoji, banner_url, dm_privacy, \
-         is_instance_admin, is_system, created_at, updated_at \
+         is_instance_admin, instance_role, is_system, created_at, updated_at \
          FROM users WHERE id = $1"
     )
     .bind(id)
@@ -114,6 +115,24 @@ pub async fn update_user_keys(
     Ok(())
 }
 
+/// Replace only the signed prekey; the identity key stays as it is.
+pub async fn update_signed_prekey(
+    pool: &Pool,
+    user_id: Uuid,
+    signed_prekey: &[u8],
+    signed_prekey_sig: &[u8],
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE users SET signed_prekey = $1, signed_prekey_sig = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $3",
+    )
+    .bind(signed_prekey)
+    .bind(signed_prekey_sig)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 pub async fn set_user_totp_secret(pool: &Pool, user_id: Uuid, secret: &str) -> AppResult<()> {
     sqlx::query("UPDATE users SET totp_secret = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
         .bind(secret)
@@ -193,10 +212,13 @@ pub async fn update_user_profile(
     .bind(encrypted_profile)
     .fetch_one(pool)
     .await?;
+    if display_name.is_some() {
+        crate::db::queries::bump_user_server_list_versions(pool, user_id).await?;
+    }
     Ok(user)
 }
 
-pub async fn update_user_avatar(pool: &Pool, user_id: Uuid, avatar_url: &str) -> AppResult<User> {
+pub async fn update_user_avatar(pool: &Pool, user_id: Uuid, avatar_url: Option<&str>) -> AppResult<User> {
     let user = sqlx::query_as::<_, User>(
         "UPDATE users SET avatar_url = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1 RETURNING *",
     )
@@ -204,10 +226,11 @@ pub async fn update_user_avatar(pool: &Pool, user_id: Uuid, avatar_url: &str) ->
     .bind(avatar_url)
     .fetch_one(pool)
     .await?;
+    crate::db::queries::bump_user_server_list_versions(pool, user_id).await?;
     Ok(user)
 }
 
-pub async fn update_user_banner(pool: &Pool, user_id: Uuid, banner_url: &str) -> AppResult<User> {
+pub async fn update_user_banner(pool: &Pool, user_id: Uuid, banner_url: Option<&str>) -> AppResult<User> {
     let user = sqlx::query_as::<_, User>(
         "UPDATE users SET banner_url = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1 RETURNING *",
     )
@@ -218,6 +241,104 @@ pub async fn update_user_banner(pool: &Pool, user_id: Uuid, banner_url: &str) ->
     Ok(user)
 }
 
+// ─── Profile Media ───────────────────────────────────
+
+pub async fn find_profile_media(pool: &Pool, user_id: Uuid, slot: &str) -> AppResult<Option<ProfileMedia>> {
+    let media = sqlx::query_as::<_, ProfileMedia>(
+        "SELECT * FROM user_profile_media WHERE user_id = $1 AND slot = $2",
+    )
+    .bind(user_id)
+    .bind(slot)
+    .fetch_optional(pool)
+    .await?;
+    Ok(media)
+}
+
+/// Bytes of profile media a user has stored, optionally ignoring one slot
+/// (the slot an upload is about to replace).
+pub async fn profile_media_usage(pool: &Pool, user_id: Uuid, excluding_slot: Option<&str>) -> AppResult<i64> {
+    let row: (i64,) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(size_bytes), 0)::BIGINT FROM user_profile_media
+        WHERE user_id = $1 AND ($2::TEXT IS NULL OR slot <> $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(excluding_slot)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+pub async fn upsert_profile_media(
+    pool: &Pool,
+    user_id: Uuid,
+    slot: &str,
+    content_hash: &str,
+    content_type: &str,
+    size_bytes: i64,
+) -> AppResult<ProfileMedia> {
+    let media = sqlx::query_as::<_, ProfileMedia>(
+        r#"
+        INSERT INTO user_profile_media (user_id, slot, content_hash, content_type, size_bytes)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, slot) DO UPDATE
+        SET content_hash = $3, content_type = $4, size_bytes = $5, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(slot)
+    .bind(content_hash)
+    .bind(content_type)
+    .bind(size_bytes)
+    .fetch_one(pool)
+    .await?;
+    bump_server_avatar_list_version(pool, slot).await?;
+    Ok(media)
+}
+
+pub async fn delete_profile_media(pool: &Pool, user_id: Uuid, slot: &str) -> AppResult<Option<ProfileMedia>> {
+    let media = sqlx::query_as::<_, ProfileMedia>(
+        "DELETE FROM user_profile_media WHERE user_id = $1 AND slot = $2 RETURNING *",
+    )
+    .bind(user_id)
+    .bind(slot)
+    .fetch_optional(pool)
+    .await?;
+    if media.is_some() {
+        bump_server_avatar_list_version(pool, slot).await?;
+    }
+    Ok(media)
+}
+
+/// Server avatars show in that server's member list.
+async fn bump_server_avatar_list_version(pool: &Pool, slot: &str) -> AppResult<()> {
+    if let Some(MediaSlot::ServerAvatar(server_id)) = MediaSlot::parse(slot) {
+        crate::db::queries::bump_server_list_version(pool, server_id).await?;
+    }
+    Ok(())
+}
+
+/// All of a user's profile media rows (for blob cleanup on account deletion).
+pub async fn list_profile_media(pool: &Pool, user_id: Uuid) -> AppResult<Vec<ProfileMedia>> {
+    let media = sqlx::query_as::<_, ProfileMedia>("SELECT * FROM user_profile_media WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(media)
+}
+
+/// Delete every member's rows for one profile slot (e.g. a deleted server's
+/// avatars), returning them for blob cleanup.
+pub async fn delete_profile_media_by_slot(pool: &Pool, slot: &str) -> AppResult<Vec<ProfileMedia>> {
+    let media = sqlx::query_as::<_, ProfileMedia>("DELETE FROM user_profile_media WHERE slot = $1 RETURNING *")
+        .bind(slot)
+        .fetch_all(pool)
+        .await?;
+    Ok(media)
+}
+
 // ─── Blocked Users ───────────────────────────────────
 
 pub async fn block_user(pool: &Pool, blocker_id: Uuid, blocked_id: Uuid) -> AppResult<()> {
@@ -274,6 +395,26 @@ pub async fn get_blocked_users(pool: &Pool, blocker_id: Uuid, limit: i64, offset
     Ok(rows)
 }
 
+/// Whether `sender_id` is blocked by the other party of a 1:1 DM channel.
+/// Always false for other channel types.
+pub async fn is_blocked_in_dm(pool: &Pool, channel_id: Uuid, sender_id: Uuid) -> AppResult<bool> {
+    let row: (bool,) = sqlx::query_as(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM channels c
+            INNER JOIN channel_members cm ON cm.channel_id = c.id AND cm.user_id <> $2
+            INNER JOIN blocked_users bu ON bu.blocker_id = cm.user_id AND bu.blocked_id = $2
+            WHERE c.id = $1 AND c.channel_type = 'dm'
+        )
+        "#,
+    )
+    .bind(channel_id)
+    .bind(sender_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
 pub async fn get_blocked_user_ids(pool: &Pool, blocker_id: Uuid) -> AppResult<Vec<Uuid>> {
     let rows: Vec<(Uuid,)> =
         sqlx::query_as("SELECT blocked_id FROM blocked_users WHERE blocker_id = $1")
@@ -282,3 +423,13 @@ pub async fn get_blocked_user_ids(pool: &Pool, blocker_id: Uuid) -> AppResult<Ve
             .await?;
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
+
+/// Users who have blocked `blocked_id` — used to flag their copy of its messages.
+pub async fn get_blocker_ids(pool: &Pool, blocked_id: Uuid) -> AppResult<Vec<Uuid>> {
+    let rows: Vec<(Uuid,)> =
+        sqlx::query_as("SELECT blocker_id FROM blocked_users WHERE blocked_id = $1")
+            .bind(blocked_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
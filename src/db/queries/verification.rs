@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Server Verification Levels ──────────────────────
+
+pub async fn get_server_verification(pool: &Pool, server_id: Uuid) -> AppResult<Option<ServerVerification>> {
+    let verification =
+        sqlx::query_as::<_, ServerVerification>("SELECT * FROM server_verification WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(verification)
+}
+
+pub async fn upsert_server_verification(
+    pool: &Pool,
+    server_id: Uuid,
+    level: VerificationLevel,
+    min_account_age_days: i32,
+    min_membership_minutes: i32,
+) -> AppResult<ServerVerification> {
+    let verification = sqlx::query_as::<_, ServerVerification>(
+        r#"
+        INSERT INTO server_verification (server_id, level, min_account_age_days, min_membership_minutes)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (server_id) DO UPDATE
+        SET level = $2, min_account_age_days = $3, min_membership_minutes = $4, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(server_id)
+    .bind(level.as_str())
+    .bind(min_account_age_days)
+    .bind(min_membership_minutes)
+    .fetch_one(pool)
+    .await?;
+    Ok(verification)
+}
+
+/// What the posting checks need about a member, or None if they aren't one.
+pub async fn get_member_verification_facts(
+    pool: &Pool,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Option<MemberVerificationFacts>> {
+    let facts = sqlx::query_as::<_, MemberVerificationFacts>(
+        r#"
+        SELECT s.owner_id = u.id AS is_owner,
+               EXISTS(SELECT 1 FROM member_roles mr
+                      WHERE mr.server_id = sm.server_id AND mr.user_id = sm.user_id) AS has_roles,
+               u.email_verified_at,
+               u.created_at AS account_created_at,
+               sm.joined_at
+        FROM server_members sm
+        JOIN servers s ON s.id = sm.server_id
+        JOIN users u ON u.id = sm.user_id
+        WHERE sm.server_id = $1 AND sm.user_id = $2
+        "#,
+    )
+    .bind(server_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(facts)
+}
+
+// ─── Email Verification ──────────────────────────────
+
+pub async fn get_email_verified_at(pool: &Pool, user_id: Uuid) -> AppResult<Option<DateTime<Utc>>> {
+    let row: Option<(Option<DateTime<Utc>>,)> =
+        sqlx::query_as("SELECT email_verified_at FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|r| r.0))
+}
+
+/// Replace the user's outstanding code, unless one was sent within
+/// `cooldown_secs`. Returns false when still cooling down.
+pub async fn store_email_verification_code(
+    pool: &Pool,
+    user_id: Uuid,
+    code_hash: &str,
+    expires_at: DateTime<Utc>,
+    cooldown_secs: i64,
+) -> AppResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO email_verification_codes (user_id, code_hash, expires_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) DO UPDATE
+        SET code_hash = $2, expires_at = $3, attempts = 0, sent_at = NOW()
+        WHERE email_verification_codes.sent_at < NOW() - make_interval(secs => $4)
+        "#,
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .bind(expires_at)
+    .bind(cooldown_secs as f64)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Count an attempt against the user's unexpired code and return its hash,
+/// or None once it has expired or run out of attempts.
+pub async fn take_email_verification_attempt(
+    pool: &Pool,
+    user_id: Uuid,
+    max_attempts: i32,
+) -> AppResult<Option<String>> {
+    let row: Option<(String,)> = sqlx::query_as(
+        r#"
+        UPDATE email_verification_codes SET attempts = attempts + 1
+        WHERE user_id = $1 AND expires_at > NOW() AND attempts < $2
+        RETURNING code_hash
+        "#,
+    )
+    .bind(user_id)
+    .bind(max_attempts)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.0))
+}
+
+/// Mark the user's email verified and drop their code.
+pub async fn mark_email_verified(pool: &Pool, user_id: Uuid) -> AppResult<DateTime<Utc>> {
+    let mut tx = pool.begin().await?;
+    let (verified_at,): (DateTime<Utc>,) = sqlx::query_as(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = $1 RETURNING email_verified_at",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM email_verification_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(verified_at)
+}
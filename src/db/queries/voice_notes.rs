@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::errors::AppResult;
+use crate::models::*;
+
+// ─── Voice Notes ──────────────────────────────────────
+
+/// Store a voice note's metadata and mark its message a `voice_note`.
+pub async fn insert_message_voice_note(
+    pool: &Pool,
+    message_id: Uuid,
+    channel_id: Uuid,
+    voice_note: &VoiceNote,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        WITH typed AS (
+            UPDATE messages SET message_type = 'voice_note' WHERE id = $1
+        )
+        INSERT INTO message_voice_notes (message_id, channel_id, duration_ms, waveform)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (message_id) DO NOTHING
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(voice_note.duration_ms as i32)
+    .bind(&voice_note.waveform)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Voice notes of the given messages, for history reads.
+pub async fn get_message_voice_notes(
+    pool: &Pool,
+    message_ids: &[Uuid],
+) -> AppResult<HashMap<Uuid, VoiceNote>> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<(Uuid, i32, Vec<u8>)> = sqlx::query_as(
+        "SELECT message_id, duration_ms, waveform FROM message_voice_notes WHERE message_id = ANY($1)",
+    )
+    .bind(message_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, duration_ms, waveform)| (id, VoiceNote { duration_ms: duration_ms as u32, waveform }))
+        .collect())
+}
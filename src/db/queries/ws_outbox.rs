@@ -0,0 +1,142 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::{dialect, Connection, Pool};
+use crate::errors::AppResult;
+
+// ─── WebSocket event outbox ───────────────────────────
+
+/// Stage a WS event on the caller's connection, so it commits (or rolls
+/// back) with the change it announces.
+pub async fn insert_ws_event_in(
+    conn: &mut Connection,
+    target: &str,
+    target_id: Option<Uuid>,
+    event: &serde_json::Value,
+) -> AppResult<()> {
+    sqlx::query("INSERT INTO ws_outbox (target, target_id, event) VALUES ($1, $2, $3)")
+        .bind(target)
+        .bind(target_id)
+        .bind(event)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Claim up to `limit` unsent events, oldest first, leasing each for
+/// `lease_secs` and counting the attempt. An event waits while an earlier
+/// event for the same target is leased, so targets see their events in
+/// order. Returns `(id, target, target_id, event, attempts)`.
+pub async fn claim_ws_events(
+    pool: &Pool,
+    limit: i64,
+    lease_secs: i64,
+) -> AppResult<Vec<(i64, String, Option<Uuid>, serde_json::Value, i32)>> {
+    let rows = sqlx::query_as(&format!(
+        r#"
+        UPDATE ws_outbox
+        SET locked_until = $2, attempts = attempts + 1
+        WHERE id IN (
+            SELECT o.id FROM ws_outbox o
+            WHERE o.sent_at IS NULL AND (o.locked_until IS NULL OR o.locked_until < {now})
+              AND NOT EXISTS (
+                  SELECT 1 FROM ws_outbox e
+                  WHERE e.sent_at IS NULL AND e.id < o.id AND e.locked_until >= {now}
+                    AND e.target = o.target
+                    AND (e.target_id = o.target_id OR (e.target_id IS NULL AND o.target_id IS NULL))
+              )
+            ORDER BY o.id
+            LIMIT $1
+            {skip_locked}
+        )
+        RETURNING id, target, target_id, event, attempts
+        "#,
+        now = dialect::NOW,
+        skip_locked = dialect::SKIP_LOCKED
+    ))
+    .bind(limit)
+    .bind(Utc::now() + Duration::seconds(lease_secs))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn mark_ws_events_sent(pool: &Pool, ids: &[i64]) -> AppResult<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "postgres")]
+    {
+        sqlx::query("UPDATE ws_outbox SET sent_at = NOW(), locked_until = NULL WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(pool)
+            .await?;
+    }
+
+    #[cfg(feature = "sqlite")]
+    {
+        let sql = format!(
+            "UPDATE ws_outbox SET sent_at = {}, locked_until = NULL WHERE id IN ({})",
+            dialect::NOW,
+            dialect::placeholders(1, ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        query.execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+/// Give back the attempt counted for claimed events that were held back
+/// unpublished. They keep their lease, so they are claimed again together
+/// with the failed event they wait for.
+pub async fn refund_ws_event_attempts(pool: &Pool, ids: &[i64]) -> AppResult<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "postgres")]
+    {
+        sqlx::query("UPDATE ws_outbox SET attempts = attempts - 1 WHERE id = ANY($1) AND attempts > 0")
+            .bind(ids)
+            .execute(pool)
+            .await?;
+    }
+
+    #[cfg(feature = "sqlite")]
+    {
+        let sql = format!(
+            "UPDATE ws_outbox SET attempts = attempts - 1 WHERE id IN ({}) AND attempts > 0",
+            dialect::placeholders(1, ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        query.execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn count_pending_ws_events(pool: &Pool) -> AppResult<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ws_outbox WHERE sent_at IS NULL")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// Delete events sent over `hours` ago.
+pub async fn purge_sent_ws_events(pool: &Pool, hours: u32) -> AppResult<u64> {
+    let result = sqlx::query(&format!(
+        "DELETE FROM ws_outbox WHERE sent_at < {}",
+        dialect::ago(&format!("{} hours", hours))
+    ))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,139 @@
+//! Shadow reads for risky query rewrites.
+//!
+//! A rewritten hot query runs alongside the query it is meant to replace on a
+//! sampled fraction of requests. The caller always returns the primary result;
+//! the candidate runs in the background and its result is compared against
+//! the primary one. Mismatches are logged with both results and counted per
+//! query, so a rewrite can be promoted once it has run clean under real traffic.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::errors::AppResult;
+
+/// Per-query shadow counters since startup.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowStats {
+    /// Candidate executions that completed (matched or not).
+    pub runs: u64,
+    /// Runs whose result differed from the primary result.
+    pub mismatches: u64,
+    /// Candidate executions that returned an error.
+    pub errors: u64,
+}
+
+#[derive(Clone)]
+pub struct ShadowReads {
+    sample_rate: f64,
+    stats: Arc<DashMap<&'static str, ShadowStats>>,
+}
+
+impl ShadowReads {
+    /// `sample_rate` is clamped to 0.0..=1.0; 0.0 disables shadowing.
+    pub fn new(sample_rate: f64) -> Self {
+        let sample_rate = if sample_rate.is_finite() { sample_rate.clamp(0.0, 1.0) } else { 0.0 };
+        Self {
+            sample_rate,
+            stats: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    fn sampled(&self) -> bool {
+        self.sample_rate > 0.0 && rand::random::<f64>() < self.sample_rate
+    }
+
+    /// On a sampled request, run `candidate` in the background and compare its
+    /// result with `primary`. Never affects the caller's response.
+    pub fn compare<T, F, Fut>(&self, query: &'static str, primary: &T, candidate: F)
+    where
+        T: PartialEq + Debug + Clone + Send + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AppResult<T>> + Send + 'static,
+    {
+        if !self.sampled() {
+            return;
+        }
+        let primary = primary.clone();
+        let fut = candidate();
+        let stats = self.stats.clone();
+        tokio::spawn(async move {
+            let result = fut.await;
+            let mut entry = stats.entry(query).or_default();
+            match result {
+                Ok(shadow) if shadow == primary => entry.runs += 1,
+                Ok(shadow) => {
+                    entry.runs += 1;
+                    entry.mismatches += 1;
+                    tracing::warn!(
+                        "Shadow read mismatch in {}: primary={:?} shadow={:?}",
+                        query, primary, shadow
+                    );
+                }
+                Err(e) => {
+                    entry.errors += 1;
+                    tracing::warn!("Shadow read {} failed: {}", query, e);
+                }
+            }
+        });
+    }
+
+    /// Counters per shadowed query, sorted by name.
+    pub fn stats(&self) -> Vec<(&'static str, ShadowStats)> {
+        let mut stats: Vec<_> = self.stats.iter().map(|e| (*e.key(), *e.value())).collect();
+        stats.sort_by_key(|(query, _)| *query);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn settle(shadow: &ShadowReads, runs: u64) -> Vec<(&'static str, ShadowStats)> {
+        for _ in 0..100 {
+            let stats = shadow.stats();
+            if stats.iter().map(|(_, s)| s.runs + s.errors).sum::<u64>() >= runs {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        shadow.stats()
+    }
+
+    #[tokio::test]
+    async fn counts_matches_mismatches_and_errors() {
+        let shadow = ShadowReads::new(1.0);
+        shadow.compare("q", &1, || async { Ok(1) });
+        shadow.compare("q", &1, || async { Ok(2) });
+        shadow.compare("q", &1, || async {
+            Err(crate::errors::AppError::BadRequest("boom".into()))
+        });
+
+        let stats = settle(&shadow, 3).await;
+        assert_eq!(stats, vec![("q", ShadowStats { runs: 2, mismatches: 1, errors: 1 })]);
+    }
+
+    #[tokio::test]
+    async fn zero_sample_rate_never_runs_candidate() {
+        let shadow = ShadowReads::new(0.0);
+        shadow.compare("q", &1, || -> std::future::Ready<AppResult<i32>> {
+            panic!("candidate must not be built")
+        });
+        assert!(shadow.stats().is_empty());
+    }
+
+    #[test]
+    fn sample_rate_is_clamped() {
+        assert_eq!(ShadowReads::new(3.0).sample_rate(), 1.0);
+        assert_eq!(ShadowReads::new(-1.0).sample_rate(), 0.0);
+        assert_eq!(ShadowReads::new(f64::NAN).sample_rate(), 0.0);
+    }
+}
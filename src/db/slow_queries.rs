@@ -0,0 +1,305 @@
+//! Slow statement log with fingerprints.
+//!
+//! sqlx reports statements slower than `DB_SLOW_QUERY_MS` as WARN events on
+//! the `sqlx::query` target. [`layer`] catches them and reduces the SQL to a
+//! fingerprint (literals and placeholders replaced, `IN` lists collapsed,
+//! whitespace and case normalized) so one statement run with different
+//! arguments groups together. Each is logged with its fingerprint and counted
+//! for `GET /admin/db-pool`.
+
+use std::fmt::Debug;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::models::DbSlowQuery;
+
+/// Distinct fingerprints kept; later ones are still logged, not counted.
+const MAX_FINGERPRINTS: usize = 500;
+/// Normalized statements are stored up to this many characters.
+const MAX_STATEMENT_LEN: usize = 1000;
+
+struct SlowQueryStats {
+    statement: String,
+    count: u64,
+    total_ms: u64,
+    max_ms: u64,
+    last_seen: DateTime<Utc>,
+}
+
+fn stats() -> &'static DashMap<String, SlowQueryStats> {
+    static STATS: OnceLock<DashMap<String, SlowQueryStats>> = OnceLock::new();
+    STATS.get_or_init(DashMap::new)
+}
+
+/// Set sqlx's slow statement threshold on connect options; 0 turns it off.
+pub fn configure<O: sqlx::ConnectOptions>(options: O, threshold_ms: u64) -> O {
+    if threshold_ms == 0 {
+        options.log_slow_statements(log::LevelFilter::Off, Duration::ZERO)
+    } else {
+        options.log_slow_statements(log::LevelFilter::Warn, Duration::from_millis(threshold_ms))
+    }
+}
+
+/// Tracing layer that records sqlx slow statement events.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    SlowQueryLayer.with_filter(Targets::new().with_target("sqlx::query", Level::WARN))
+}
+
+struct SlowQueryLayer;
+
+impl<S: Subscriber> Layer<S> for SlowQueryLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = QueryFields::default();
+        event.record(&mut fields);
+        // Short statements are logged whole as the summary
+        let sql = if fields.statement.trim().is_empty() {
+            &fields.summary
+        } else {
+            &fields.statement
+        };
+        if sql.trim().is_empty() {
+            return;
+        }
+        let elapsed = fields.elapsed.unwrap_or_default();
+        let fingerprint = record(sql, elapsed, Utc::now());
+        tracing::warn!(
+            fingerprint = %fingerprint,
+            elapsed_ms = elapsed.as_millis() as u64,
+            "Slow query: {}",
+            fields.summary
+        );
+    }
+}
+
+#[derive(Default)]
+struct QueryFields {
+    summary: String,
+    statement: String,
+    elapsed: Option<Duration>,
+}
+
+impl Visit for QueryFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" && value.is_finite() {
+            self.elapsed = Some(Duration::from_secs_f64(value.max(0.0)));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "elapsed" && self.elapsed.is_none() {
+            self.elapsed = parse_duration(&format!("{:?}", value));
+        }
+    }
+}
+
+/// Parse a `Duration`'s `Debug` output, e.g. `1.5s`, `12.3ms`, `80µs`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let (number, unit_secs) = [
+        ("ns", 1e-9),
+        ("µs", 1e-6),
+        ("us", 1e-6),
+        ("ms", 1e-3),
+        ("s", 1.0),
+    ]
+    .iter()
+    .find_map(|(suffix, scale)| s.strip_suffix(suffix).map(|n| (n, *scale)))?;
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value >= 0.0).then(|| Duration::from_secs_f64(value * unit_secs))
+}
+
+/// Reduce a statement to its shape: string and numeric literals and `$n`
+/// placeholders become `?`, lists of them collapse to one, whitespace runs
+/// become one space and everything outside quoted identifiers is lowercased.
+pub fn normalize(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // '' inside a literal is an escaped quote
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+            }
+            '"' => {
+                out.push('"');
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                while chars.peek().is_some_and(char::is_ascii_digit) {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c if c.is_ascii_digit()
+                && !out.ends_with(|p: char| p.is_alphanumeric() || p == '_') =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !out.is_empty() && !out.ends_with(' ') {
+                    out.push(' ');
+                }
+            }
+            c => out.extend(c.to_lowercase()),
+        }
+    }
+    let mut out = out.trim_end().to_string();
+    while out.contains("?, ?") {
+        out = out.replace("?, ?", "?");
+    }
+    out
+}
+
+/// Stable id for a normalized statement: the first 8 bytes of its SHA-256.
+pub fn fingerprint(normalized: &str) -> String {
+    hex::encode(&Sha256::digest(normalized.as_bytes())[..8])
+}
+
+/// Count one slow execution of `sql`. Returns its fingerprint.
+fn record(sql: &str, elapsed: Duration, now: DateTime<Utc>) -> String {
+    let normalized = normalize(sql);
+    let id = fingerprint(&normalized);
+    let ms = elapsed.as_millis() as u64;
+    let stats = stats();
+    if let Some(mut entry) = stats.get_mut(&id) {
+        entry.count += 1;
+        entry.total_ms += ms;
+        entry.max_ms = entry.max_ms.max(ms);
+        entry.last_seen = now;
+    } else if stats.len() < MAX_FINGERPRINTS {
+        stats.insert(
+            id.clone(),
+            SlowQueryStats {
+                statement: normalized.chars().take(MAX_STATEMENT_LEN).collect(),
+                count: 1,
+                total_ms: ms,
+                max_ms: ms,
+                last_seen: now,
+            },
+        );
+    }
+    id
+}
+
+/// The `limit` fingerprints with the most total time since startup.
+pub fn top(limit: usize) -> Vec<DbSlowQuery> {
+    let mut queries: Vec<DbSlowQuery> = stats()
+        .iter()
+        .map(|e| DbSlowQuery {
+            fingerprint: e.key().clone(),
+            statement: e.statement.clone(),
+            count: e.count,
+            total_ms: e.total_ms,
+            max_ms: e.max_ms,
+            last_seen: e.last_seen,
+        })
+        .collect();
+    queries.sort_by(|a, b| {
+        b.total_ms
+            .cmp(&a.total_ms)
+            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+    });
+    queries.truncate(limit);
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_replaces_literals_and_placeholders() {
+        assert_eq!(
+            normalize("SELECT id FROM users\n  WHERE username = 'o''brien' AND created_at > NOW() - INTERVAL '1 hour' LIMIT 50"),
+            "select id from users where username = ? and created_at > now() - interval ? limit ?"
+        );
+        assert_eq!(
+            normalize(r#"SELECT "Name", col2 FROM t2 WHERE id = $1 AND x IN ($2, $3, $4)"#),
+            r#"select "Name", col2 from t2 where id = ? and x in (?)"#
+        );
+    }
+
+    #[test]
+    fn same_statement_with_other_arguments_shares_a_fingerprint() {
+        let a = normalize("SELECT * FROM messages WHERE channel_id IN ($1, $2) LIMIT 10");
+        let b = normalize("select *  from messages where channel_id in ($1, $2, $3, $4) limit 200");
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(
+            fingerprint(&a),
+            fingerprint(&normalize("SELECT * FROM messages"))
+        );
+        assert_eq!(fingerprint(&a).len(), 16);
+    }
+
+    #[test]
+    fn parses_duration_debug_output() {
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("80µs"), Some(Duration::from_micros(80)));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[test]
+    fn record_groups_executions_by_fingerprint() {
+        let now = Utc::now();
+        let id = record(
+            "SELECT pg_sleep(1) /* slow_queries test */",
+            Duration::from_millis(1200),
+            now,
+        );
+        record(
+            "SELECT pg_sleep(2) /* slow_queries test */",
+            Duration::from_millis(2100),
+            now,
+        );
+
+        let query = top(MAX_FINGERPRINTS)
+            .into_iter()
+            .find(|q| q.fingerprint == id)
+            .unwrap();
+        assert_eq!(
+            query.statement,
+            "select pg_sleep(?) /* slow_queries test */"
+        );
+        assert_eq!((query.count, query.total_ms, query.max_ms), (2, 3300, 2100));
+    }
+}
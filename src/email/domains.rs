@@ -0,0 +1,121 @@
+//! Checks on the domain of an address asking for a beta code, so one person
+//! can't drain the beta cap with throwaway inboxes.
+//!
+//! Domains on the bundled list of disposable providers
+//! (`disposable_domains.txt`, on unless `BETA_BLOCK_DISPOSABLE_EMAILS=false`)
+//! or on the operator's `BETA_BLOCKED_EMAIL_DOMAINS` are refused, along with
+//! their subdomains. With `BETA_REQUIRE_MX`, a domain must also be able to
+//! receive mail: it needs an MX record, or an A/AAAA record standing in for
+//! one, and a null MX (RFC 7505) counts as none. DNS failures other than a
+//! definite "no such record" let the request through rather than turning a
+//! resolver outage into refused signups.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::TokioAsyncResolver;
+
+use crate::config::AppConfig;
+
+fn disposable() -> &'static HashSet<&'static str> {
+    static DOMAINS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    DOMAINS.get_or_init(|| {
+        include_str!("disposable_domains.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    })
+}
+
+/// The domain part of an address, without a trailing root dot.
+pub fn domain_of(email: &str) -> Option<&str> {
+    let (_, domain) = email.rsplit_once('@')?;
+    let domain = domain.trim_end_matches('.');
+    (!domain.is_empty()).then_some(domain)
+}
+
+/// Whether `domain` (lowercase) or a parent of it is on a deny-list.
+pub fn is_blocked(domain: &str, config: &AppConfig) -> bool {
+    let extra: Vec<&str> = config
+        .beta_blocked_email_domains
+        .split(',')
+        .map(|d| d.trim().trim_start_matches('.'))
+        .filter(|d| !d.is_empty())
+        .collect();
+    let mut parents =
+        std::iter::successors(Some(domain), |d| d.split_once('.').map(|(_, rest)| rest));
+    parents.any(|d| {
+        (config.beta_block_disposable_emails && disposable().contains(d))
+            || extra.iter().any(|e| e.eq_ignore_ascii_case(d))
+    })
+}
+
+fn resolver() -> &'static TokioAsyncResolver {
+    static RESOLVER: OnceLock<TokioAsyncResolver> = OnceLock::new();
+    RESOLVER.get_or_init(|| {
+        let (config, mut opts) = hickory_resolver::system_conf::read_system_conf()
+            .unwrap_or_else(|_| (ResolverConfig::default(), ResolverOpts::default()));
+        opts.timeout = Duration::from_secs(3);
+        opts.attempts = 2;
+        TokioAsyncResolver::tokio(config, opts)
+    })
+}
+
+fn no_records(e: &ResolveError) -> bool {
+    matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. })
+}
+
+/// Whether `domain` can receive mail, judged from its MX and address records.
+pub async fn accepts_mail(domain: &str) -> bool {
+    // Fully qualified, so the resolver's search domains aren't tried
+    let fqdn = format!("{}.", domain);
+    match resolver().mx_lookup(fqdn.as_str()).await {
+        Ok(mx) => mx.iter().any(|record| !record.exchange().is_root()),
+        Err(e) if no_records(&e) => match resolver().lookup_ip(fqdn.as_str()).await {
+            Ok(ips) => ips.iter().next().is_some(),
+            Err(e) if no_records(&e) => false,
+            Err(e) => {
+                tracing::warn!("Address lookup for {} failed: {}", domain, e);
+                true
+            }
+        },
+        Err(e) => {
+            tracing::warn!("MX lookup for {} failed: {}", domain, e);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_is_taken_after_the_last_at() {
+        assert_eq!(domain_of("a@b@Example.com."), Some("Example.com"));
+        assert_eq!(domain_of("nobody@"), None);
+        assert_eq!(domain_of("no-at-sign"), None);
+    }
+
+    #[test]
+    fn bundled_and_operator_domains_are_blocked_with_subdomains() {
+        let mut config = AppConfig::test_default();
+        assert!(is_blocked("mailinator.com", &config));
+        assert!(is_blocked("eu.mailinator.com", &config));
+        assert!(!is_blocked("example.com", &config));
+        assert!(!is_blocked("notmailinator.com", &config));
+
+        config.beta_blocked_email_domains = " spam.example , .junk.test".into();
+        assert!(is_blocked("spam.example", &config));
+        assert!(is_blocked("a.b.junk.test", &config));
+        assert!(!is_blocked("example", &config));
+
+        config.beta_block_disposable_emails = false;
+        assert!(!is_blocked("mailinator.com", &config));
+        assert!(is_blocked("spam.example", &config));
+    }
+}
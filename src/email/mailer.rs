@@ -0,0 +1,577 @@
+//! Email delivery providers.
+//!
+//! `EMAIL_PROVIDER` picks how rendered emails leave the server: `smtp` (the
+//! default), the SendGrid or Mailgun HTTP APIs, or `noop`, which accepts and
+//! discards everything (development, load tests).
+//!
+//! Emails are never sent straight from a request. [`enqueue`] stores each
+//! one in the `email_outbox` table, encrypted with the storage key, and
+//! [`drain_outbox`] sends it, right away and then on a schedule. Transient
+//! failures are retried with exponential backoff up to [`MAX_ATTEMPTS`]
+//! times; an email that is rejected outright or runs out of attempts moves
+//! to `email_dead_letters` until an operator retries or discards it. A
+//! restart loses nothing still queued.
+//!
+//! Provider settings are checked by [`from_config`], which `AppConfig::validate`
+//! runs at startup and on reload, so a half-configured provider is refused
+//! instead of failing on the first send.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use base64::Engine;
+use futures::future::BoxFuture;
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{RenderedEmail, LOGO_CID};
+use crate::config::AppConfig;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::jobs;
+use crate::AppState;
+
+const SENDGRID_API_URL: &str = "https://api.sendgrid.com";
+const MAILGUN_API_URL: &str = "https://api.mailgun.net";
+
+/// An email ready for a provider. `logo` is the instance logo (bytes and
+/// content type), attached inline as `cid:instance-logo`.
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    pub logo: Option<(Vec<u8>, String)>,
+}
+
+#[derive(Debug)]
+pub struct SendError {
+    pub message: String,
+    /// Retrying won't help (rejected address, bad credentials, ...).
+    pub permanent: bool,
+}
+
+impl SendError {
+    fn transient(message: impl Into<String>) -> Self {
+        Self { message: message.into(), permanent: false }
+    }
+
+    fn permanent(message: impl Into<String>) -> Self {
+        Self { message: message.into(), permanent: true }
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A way of sending email.
+pub trait Mailer: Send + Sync {
+    /// The `EMAIL_PROVIDER` value that selects this mailer.
+    fn name(&self) -> &'static str;
+
+    /// Make one delivery attempt.
+    fn send<'a>(&'a self, email: &'a OutgoingEmail) -> BoxFuture<'a, Result<(), SendError>>;
+}
+
+/// The mailer `config` selects, `None` if email is disabled (SMTP without a
+/// host), or a description of what is missing or invalid.
+pub fn from_config(config: &AppConfig) -> Result<Option<Box<dyn Mailer>>, String> {
+    match config.email_provider.as_str() {
+        "smtp" => Ok(SmtpMailer::from_config(config)?.map(|m| Box::new(m) as Box<dyn Mailer>)),
+        "sendgrid" => Ok(Some(Box::new(SendGridMailer {
+            api_key: required(&config.email_api_key, "EMAIL_API_KEY", "sendgrid")?,
+            from: parse_from(config)?,
+            base_url: api_url(config, SENDGRID_API_URL),
+        }))),
+        "mailgun" => Ok(Some(Box::new(MailgunMailer {
+            api_key: required(&config.email_api_key, "EMAIL_API_KEY", "mailgun")?,
+            domain: required(&config.email_domain, "EMAIL_DOMAIN", "mailgun")?,
+            from: parse_from(config)?,
+            base_url: api_url(config, MAILGUN_API_URL),
+        }))),
+        "noop" => Ok(Some(Box::new(NoopMailer))),
+        other => Err(format!(
+            "EMAIL_PROVIDER must be 'smtp', 'sendgrid', 'mailgun' or 'noop', got '{}'.",
+            other
+        )),
+    }
+}
+
+fn required(value: &str, var: &str, provider: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err(format!("{} is required when EMAIL_PROVIDER is {}.", var, provider));
+    }
+    Ok(value.to_string())
+}
+
+/// `SMTP_FROM` is the sender for every provider.
+fn parse_from(config: &AppConfig) -> Result<Mailbox, String> {
+    let from = config.smtp_from.trim().trim_matches('"');
+    from.parse()
+        .map_err(|e| format!("SMTP_FROM '{}' is not a valid address: {}", from, e))
+}
+
+fn api_url(config: &AppConfig, default: &str) -> String {
+    let url = if config.email_api_url.is_empty() { default } else { &config.email_api_url };
+    url.trim_end_matches('/').to_string()
+}
+
+fn http() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .expect("HTTP client builds")
+    })
+}
+
+/// Map a provider API's non-success status to a retryable or final error.
+fn status_error(provider: &str, status: reqwest::StatusCode, body: &str) -> SendError {
+    let message = format!("{} returned {}: {}", provider, status, body.chars().take(200).collect::<String>());
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        SendError::transient(message)
+    } else {
+        SendError::permanent(message)
+    }
+}
+
+async fn check_response(provider: &str, result: reqwest::Result<reqwest::Response>) -> Result<(), SendError> {
+    let response = result.map_err(|e| SendError::transient(format!("{} request failed: {}", provider, e)))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(status_error(provider, status, &body))
+}
+
+// ─── SMTP ─────────────────────────────────────────────
+
+struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    fn from_config(config: &AppConfig) -> Result<Option<Self>, String> {
+        if config.smtp_host.is_empty() {
+            return Ok(None);
+        }
+        if config.smtp_username.is_empty() {
+            return Err("SMTP_USERNAME is required when SMTP_HOST is set.".into());
+        }
+        let from = parse_from(config)?;
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+            .map_err(|e| format!("SMTP_HOST '{}' is invalid: {}", config.smtp_host, e))?
+            .port(config.smtp_port)
+            .credentials(Credentials::new(config.smtp_username.clone(), config.smtp_password.clone()))
+            .build();
+        Ok(Some(Self { transport, from }))
+    }
+
+    fn message(&self, email: &OutgoingEmail) -> Result<Message, SendError> {
+        let to = email
+            .to
+            .trim()
+            .parse()
+            .map_err(|e| SendError::permanent(format!("Failed to parse To address: {}", e)))?;
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject.clone());
+        let message = match &email.logo {
+            Some((data, content_type)) => {
+                let content_type = content_type
+                    .parse()
+                    .map_err(|e| SendError::permanent(format!("Invalid logo content type: {}", e)))?;
+                builder.multipart(
+                    MultiPart::related()
+                        .singlepart(SinglePart::html(email.html.clone()))
+                        .singlepart(Attachment::new_inline(LOGO_CID.to_string()).body(data.clone(), content_type)),
+                )
+            }
+            None => builder.header(ContentType::TEXT_HTML).body(email.html.clone()),
+        };
+        message.map_err(|e| SendError::permanent(format!("Failed to build message: {}", e)))
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send<'a>(&'a self, email: &'a OutgoingEmail) -> BoxFuture<'a, Result<(), SendError>> {
+        Box::pin(async move {
+            let message = self.message(email)?;
+            self.transport.send(message).await.map(|_| ()).map_err(|e| {
+                if e.is_permanent() {
+                    SendError::permanent(e.to_string())
+                } else {
+                    SendError::transient(e.to_string())
+                }
+            })
+        })
+    }
+}
+
+// ─── SendGrid ─────────────────────────────────────────
+
+struct SendGridMailer {
+    api_key: String,
+    from: Mailbox,
+    base_url: String,
+}
+
+impl SendGridMailer {
+    fn payload(&self, email: &OutgoingEmail) -> serde_json::Value {
+        let mut from = serde_json::json!({ "email": self.from.email.to_string() });
+        if let Some(name) = &self.from.name {
+            from["name"] = name.as_str().into();
+        }
+        let mut payload = serde_json::json!({
+            "personalizations": [{ "to": [{ "email": email.to.trim() }] }],
+            "from": from,
+            "subject": email.subject,
+            "content": [{ "type": "text/html", "value": email.html }],
+        });
+        if let Some((data, content_type)) = &email.logo {
+            payload["attachments"] = serde_json::json!([{
+                "content": base64::engine::general_purpose::STANDARD.encode(data),
+                "type": content_type,
+                "filename": "logo",
+                "disposition": "inline",
+                "content_id": LOGO_CID,
+            }]);
+        }
+        payload
+    }
+}
+
+impl Mailer for SendGridMailer {
+    fn name(&self) -> &'static str {
+        "sendgrid"
+    }
+
+    fn send<'a>(&'a self, email: &'a OutgoingEmail) -> BoxFuture<'a, Result<(), SendError>> {
+        Box::pin(async move {
+            let result = http()
+                .post(format!("{}/v3/mail/send", self.base_url))
+                .bearer_auth(&self.api_key)
+                .json(&self.payload(email))
+                .send()
+                .await;
+            check_response("SendGrid", result).await
+        })
+    }
+}
+
+// ─── Mailgun ──────────────────────────────────────────
+
+struct MailgunMailer {
+    api_key: String,
+    domain: String,
+    from: Mailbox,
+    base_url: String,
+}
+
+impl Mailer for MailgunMailer {
+    fn name(&self) -> &'static str {
+        "mailgun"
+    }
+
+    fn send<'a>(&'a self, email: &'a OutgoingEmail) -> BoxFuture<'a, Result<(), SendError>> {
+        Box::pin(async move {
+            let mut form = reqwest::multipart::Form::new()
+                .text("from", self.from.to_string())
+                .text("to", email.to.trim().to_string())
+                .text("subject", email.subject.clone())
+                .text("html", email.html.clone());
+            if let Some((data, content_type)) = &email.logo {
+                // Mailgun uses the file name as the Content-ID
+                let part = reqwest::multipart::Part::bytes(data.clone())
+                    .file_name(LOGO_CID)
+                    .mime_str(content_type)
+                    .map_err(|e| SendError::permanent(format!("Invalid logo content type: {}", e)))?;
+                form = form.part("inline", part);
+            }
+            let result = http()
+                .post(format!("{}/v3/{}/messages", self.base_url, self.domain))
+                .basic_auth("api", Some(&self.api_key))
+                .multipart(form)
+                .send()
+                .await;
+            check_response("Mailgun", result).await
+        })
+    }
+}
+
+// ─── No-op ────────────────────────────────────────────
+
+/// Accepts every email and sends nothing.
+struct NoopMailer;
+
+impl Mailer for NoopMailer {
+    fn name(&self) -> &'static str {
+        "noop"
+    }
+
+    fn send<'a>(&'a self, email: &'a OutgoingEmail) -> BoxFuture<'a, Result<(), SendError>> {
+        Box::pin(async move {
+            tracing::info!("Discarded email \"{}\" (EMAIL_PROVIDER=noop)", email.subject);
+            Ok(())
+        })
+    }
+}
+
+// ─── Outbox ───────────────────────────────────────────
+
+/// Attempts per email before it is dead-lettered.
+pub const MAX_ATTEMPTS: u32 = 8;
+
+/// How long a claimed email is hidden from other workers while it is sent.
+const CLAIM_LEASE_SECS: i64 = 600;
+const CLAIM_BATCH: i64 = 20;
+
+/// Wait after failed attempt `attempt`: 30s, 1m, 2m, ... capped at an hour,
+/// plus up to 5s of jitter so a backlog doesn't retry in lockstep.
+fn backoff(attempt: u32) -> Duration {
+    let secs = (30u64 << attempt.saturating_sub(1).min(7)).min(3600);
+    Duration::from_secs(secs) + Duration::from_millis(rand::thread_rng().gen_range(0..5000))
+}
+
+/// Retry delay after failed attempt `attempt`, or `None` to give up.
+fn retry_after(attempt: u32, error: &SendError) -> Option<Duration> {
+    (!error.permanent && attempt < MAX_ATTEMPTS).then(|| backoff(attempt))
+}
+
+/// What the outbox and dead letters keep, encrypted. The logo isn't stored;
+/// each attempt attaches the current one.
+#[derive(Serialize, Deserialize)]
+struct QueuedEmail {
+    to: String,
+    subject: String,
+    html: String,
+    inline_logo: bool,
+}
+
+impl QueuedEmail {
+    fn open(payload: &[u8], state: &AppState) -> Option<Self> {
+        let bytes = crate::storage::decrypt_blob(payload, &state.storage_key).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    async fn into_outgoing(self, state: &AppState) -> OutgoingEmail {
+        let logo = if self.inline_logo { crate::api::branding::load_logo(state).await } else { None };
+        OutgoingEmail { to: self.to, subject: self.subject, html: self.html, logo }
+    }
+}
+
+fn configured_mailer(state: &AppState) -> AppResult<Box<dyn Mailer>> {
+    from_config(&state.live_config.get())
+        .ok()
+        .flatten()
+        .ok_or_else(|| AppError::BadRequest("Email is not configured".into()))
+}
+
+/// Queue an email for `to` and start delivering it. `kind` names the email
+/// (its template) in the queue and dead letters; `inline_logo` attaches the
+/// instance logo as `cid:instance-logo`. The address is kept encrypted until
+/// the email is delivered, then deleted.
+pub async fn enqueue(
+    state: &AppState,
+    kind: &str,
+    to: String,
+    email: RenderedEmail,
+    inline_logo: bool,
+) -> AppResult<()> {
+    configured_mailer(state)?;
+    let queued = QueuedEmail { to, subject: email.subject, html: email.html, inline_logo };
+    let payload = serde_json::to_vec(&queued).map_err(|e| AppError::Internal(e.into()))?;
+    let payload = crate::storage::encrypt_blob(&payload, &state.storage_key)
+        .map_err(|e| AppError::Internal(e.into()))?;
+    queries::enqueue_email(state.db.write(), Uuid::new_v4(), kind, &payload).await?;
+
+    // Send now rather than on the worker's next tick
+    let drain = jobs::NewJob::maintenance(crate::maintenance::Job::EmailOutbox);
+    if let Err(e) = jobs::enqueue(state, drain).await {
+        tracing::error!("Failed to queue an email outbox run: {}", e);
+    }
+    Ok(())
+}
+
+/// Make one attempt at every due email in the outbox. Runs right after an
+/// email is queued and on a schedule for retries. Returns how many were
+/// delivered. Nothing is sent while email is unconfigured; the queue waits.
+pub async fn drain_outbox(state: &AppState) -> AppResult<u64> {
+    let Ok(mailer) = configured_mailer(state) else {
+        return Ok(0);
+    };
+    let pool = state.db.primary();
+    let mut delivered = 0;
+    loop {
+        let batch = queries::claim_email_outbox(pool, CLAIM_BATCH, CLAIM_LEASE_SECS).await?;
+        if batch.is_empty() {
+            return Ok(delivered);
+        }
+        for (id, kind, payload, attempts) in batch {
+            let attempt = attempts as u32 + 1;
+            let Some(queued) = QueuedEmail::open(&payload, state) else {
+                tracing::error!("Could not decrypt queued {} email {}", kind, id);
+                let error = "Could not decrypt the queued email";
+                queries::dead_letter_email_outbox(pool, id, mailer.name(), error, attempt as i32).await?;
+                continue;
+            };
+            let email = queued.into_outgoing(state).await;
+            let error = match mailer.send(&email).await {
+                Ok(()) => {
+                    queries::delete_email_outbox(pool, id).await?;
+                    tracing::info!("Sent {} email via {}", kind, mailer.name());
+                    delivered += 1;
+                    continue;
+                }
+                Err(e) => e,
+            };
+            match retry_after(attempt, &error) {
+                Some(delay) => {
+                    tracing::warn!(
+                        "{} email attempt {} via {} failed, retrying in {}s: {}",
+                        kind, attempt, mailer.name(), delay.as_secs(), error
+                    );
+                    queries::reschedule_email_outbox(pool, id, &error.message, delay.as_secs() as i64).await?;
+                }
+                None => {
+                    tracing::error!(
+                        "Failed to send {} email via {} after {} attempt(s): {}",
+                        kind, mailer.name(), attempt, error
+                    );
+                    queries::dead_letter_email_outbox(pool, id, mailer.name(), &error.message, attempt as i32)
+                        .await?;
+                }
+            }
+        }
+    }
+}
+
+/// Make one more attempt at a dead-lettered email through the current
+/// provider. It is removed on success; on failure its error and attempt
+/// count are updated and the error returned.
+pub async fn retry_dead_letter(state: &AppState, id: Uuid) -> AppResult<()> {
+    let payload = queries::get_email_dead_letter_payload(state.db.primary(), id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Dead letter not found".into()))?;
+    let queued = QueuedEmail::open(&payload, state)
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Could not decrypt dead letter {}", id)))?;
+    let mailer = configured_mailer(state)?;
+
+    let email = queued.into_outgoing(state).await;
+    match mailer.send(&email).await {
+        Ok(()) => {
+            queries::delete_email_dead_letter(state.db.write(), id).await?;
+            Ok(())
+        }
+        Err(e) => {
+            queries::update_email_dead_letter_error(state.db.write(), id, mailer.name(), &e.to_string())
+                .await?;
+            Err(AppError::BadRequest(format!("Delivery failed: {}", e)).with_code("EMAIL_SEND_FAILED"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(provider: &str) -> AppConfig {
+        let mut config = AppConfig::test_default();
+        config.email_provider = provider.into();
+        config.smtp_from = "Acme <noreply@acme.example>".into();
+        config
+    }
+
+    fn error_of(config: &AppConfig) -> String {
+        from_config(config).err().expect("config should be rejected")
+    }
+
+    #[test]
+    fn provider_settings_are_validated() {
+        assert!(from_config(&config("smtp")).unwrap().is_none());
+        assert_eq!(from_config(&config("noop")).unwrap().unwrap().name(), "noop");
+        assert!(error_of(&config("postmark")).contains("EMAIL_PROVIDER"));
+
+        let mut smtp = config("smtp");
+        smtp.smtp_host = "smtp.acme.example".into();
+        assert!(error_of(&smtp).contains("SMTP_USERNAME"));
+        smtp.smtp_username = "acme".into();
+        assert_eq!(from_config(&smtp).unwrap().unwrap().name(), "smtp");
+        smtp.smtp_from = "not an address".into();
+        assert!(error_of(&smtp).contains("SMTP_FROM"));
+
+        let mut sendgrid = config("sendgrid");
+        assert!(error_of(&sendgrid).contains("EMAIL_API_KEY"));
+        sendgrid.email_api_key = "SG.key".into();
+        assert_eq!(from_config(&sendgrid).unwrap().unwrap().name(), "sendgrid");
+
+        let mut mailgun = config("mailgun");
+        mailgun.email_api_key = "key".into();
+        assert!(error_of(&mailgun).contains("EMAIL_DOMAIN"));
+        mailgun.email_domain = "mg.acme.example".into();
+        assert_eq!(from_config(&mailgun).unwrap().unwrap().name(), "mailgun");
+    }
+
+    #[test]
+    fn sendgrid_payload_attaches_the_logo_inline() {
+        let mailer = SendGridMailer {
+            api_key: "SG.key".into(),
+            from: "Acme <noreply@acme.example>".parse().unwrap(),
+            base_url: SENDGRID_API_URL.into(),
+        };
+        let email = OutgoingEmail {
+            to: " user@example.com ".into(),
+            subject: "Hi".into(),
+            html: "<p>Hi</p>".into(),
+            logo: Some((vec![1, 2, 3], "image/png".into())),
+        };
+        let payload = mailer.payload(&email);
+        assert_eq!(payload["personalizations"][0]["to"][0]["email"], "user@example.com");
+        assert_eq!(payload["from"]["name"], "Acme");
+        assert_eq!(payload["attachments"][0]["content"], "AQID");
+        assert_eq!(payload["attachments"][0]["content_id"], LOGO_CID);
+    }
+
+    #[test]
+    fn only_throttling_and_server_errors_are_retried() {
+        use reqwest::StatusCode;
+        assert!(!status_error("SendGrid", StatusCode::TOO_MANY_REQUESTS, "").permanent);
+        assert!(!status_error("SendGrid", StatusCode::BAD_GATEWAY, "").permanent);
+        assert!(status_error("SendGrid", StatusCode::UNAUTHORIZED, "").permanent);
+        assert!(status_error("Mailgun", StatusCode::BAD_REQUEST, &"x".repeat(1000)).message.len() < 300);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        for (attempt, base) in [(1, 30), (2, 60), (3, 120), (7, 1920), (8, 3600), (20, 3600)] {
+            let wait = backoff(attempt);
+            let base = Duration::from_secs(base);
+            assert!(wait >= base && wait < base + Duration::from_secs(5), "attempt {}", attempt);
+        }
+    }
+
+    #[test]
+    fn only_transient_failures_with_attempts_left_are_retried() {
+        let down = SendError::transient("down");
+        assert!(retry_after(1, &down).is_some());
+        assert!(retry_after(MAX_ATTEMPTS - 1, &down).is_some());
+        assert!(retry_after(MAX_ATTEMPTS, &down).is_none());
+        assert!(retry_after(1, &SendError::permanent("rejected")).is_none());
+    }
+}
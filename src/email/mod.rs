@@ -0,0 +1,261 @@
+//! Transactional email: templates here, delivery in [`mailer`], checks on
+//! recipient domains in [`domains`].
+//!
+//! Emails are rendered from Tera templates. The built-in set under
+//! `templates/email/` is compiled into the binary; an operator can replace
+//! any of them, or add translations, by placing files with the same names in
+//! `EMAIL_TEMPLATE_DIR`. Each email is a pair of templates: `<name>.html`
+//! for the body and `<name>.subject.txt` for the subject line. Bodies extend
+//! `base.html`, which holds the branded frame and legal links.
+//!
+//! Localized variants sit next to the default as `<name>.<locale>.html`
+//! (e.g. `beta_code.pt-br.html`, `beta_code.de.subject.txt`). A lookup for
+//! `pt-BR` tries `pt-br`, then `pt`, then the untranslated template.
+//!
+//! Templates are read once at startup; a restart picks up edited files.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use tera::{Context, Tera};
+
+use crate::api::branding::escape_html;
+use crate::models::InstanceBranding;
+
+pub mod domains;
+pub mod mailer;
+
+/// Content-ID of the inline logo image in branded emails.
+pub const LOGO_CID: &str = "instance-logo";
+
+/// Built-in templates, compiled in. Every email an override directory may
+/// replace must be listed here.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("../../templates/email/base.html")),
+    ("beta_code.html", include_str!("../../templates/email/beta_code.html")),
+    ("beta_code.subject.txt", include_str!("../../templates/email/beta_code.subject.txt")),
+    ("beta_waitlist.html", include_str!("../../templates/email/beta_waitlist.html")),
+    ("beta_waitlist.subject.txt", include_str!("../../templates/email/beta_waitlist.subject.txt")),
+    ("email_verification.html", include_str!("../../templates/email/email_verification.html")),
+    ("email_verification.subject.txt", include_str!("../../templates/email/email_verification.subject.txt")),
+    ("password_reset.html", include_str!("../../templates/email/password_reset.html")),
+    ("password_reset.subject.txt", include_str!("../../templates/email/password_reset.subject.txt")),
+    ("digest.html", include_str!("../../templates/email/digest.html")),
+    ("digest.subject.txt", include_str!("../../templates/email/digest.subject.txt")),
+];
+
+/// A rendered email, ready to send.
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+}
+
+#[derive(Clone)]
+pub struct EmailTemplates {
+    tera: Arc<Tera>,
+}
+
+impl EmailTemplates {
+    /// The compiled-in templates only.
+    pub fn builtin() -> Self {
+        Self::load("").expect("built-in email templates are valid")
+    }
+
+    /// The built-in templates with any `.html` / `.txt` files in `dir`
+    /// layered over them. An empty `dir` means no overrides.
+    pub fn load(dir: &str) -> anyhow::Result<Self> {
+        let mut sources: BTreeMap<String, String> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(n, c)| (n.to_string(), c.to_string()))
+            .collect();
+        if !dir.is_empty() {
+            for (name, content) in read_dir(Path::new(dir))? {
+                if !sources.contains_key(&name) && !is_translation(&name) {
+                    tracing::warn!("Email template {} does not match a known email", name);
+                }
+                sources.insert(name, content);
+            }
+        }
+        Self::from_sources(sources)
+    }
+
+    fn from_sources(sources: BTreeMap<String, String>) -> anyhow::Result<Self> {
+        let mut tera = Tera::default();
+        // Tera's own escaper also encodes `/`, which breaks URLs in hrefs
+        tera.set_escape_fn(escape_html);
+        tera.add_raw_templates(sources).context("Invalid email template")?;
+        Ok(Self { tera: Arc::new(tera) })
+    }
+
+    /// Render email `name` for `locale`, branded for this instance. `vars`
+    /// holds the email-specific values; the branding ones (`instance_name`,
+    /// `accent_color`, `background_color`, `terms_url`, `privacy_url`,
+    /// `logo_cid`) are added here. With `inline_logo`, the body references
+    /// the logo as `cid:instance-logo`.
+    pub fn render(
+        &self,
+        name: &str,
+        locale: Option<&str>,
+        branding: &InstanceBranding,
+        inline_logo: bool,
+        mut vars: Context,
+    ) -> anyhow::Result<RenderedEmail> {
+        vars.insert("instance_name", &branding.name);
+        vars.insert("accent_color", &branding.accent_color);
+        vars.insert("background_color", &branding.background_color);
+        vars.insert("terms_url", &branding.terms_url);
+        vars.insert("privacy_url", &branding.privacy_url);
+        vars.insert("logo_cid", &inline_logo.then_some(LOGO_CID));
+
+        let subject = self
+            .tera
+            .render(&self.resolve(name, "subject.txt", locale), &vars)
+            .with_context(|| format!("Failed to render subject of email {}", name))?;
+        let html = self
+            .tera
+            .render(&self.resolve(name, "html", locale), &vars)
+            .with_context(|| format!("Failed to render email {}", name))?;
+        Ok(RenderedEmail { subject: subject.trim().to_string(), html })
+    }
+
+    /// Most specific template for `locale`: `name.pt-br.ext`, `name.pt.ext`,
+    /// then `name.ext`.
+    fn resolve(&self, name: &str, ext: &str, locale: Option<&str>) -> String {
+        let fallback = format!("{}.{}", name, ext);
+        let Some(locale) = locale.and_then(normalize_locale) else {
+            return fallback;
+        };
+        let language = locale.split('-').next().unwrap_or_default().to_string();
+        [locale, language]
+            .into_iter()
+            .map(|tag| format!("{}.{}.{}", name, tag, ext))
+            .find(|candidate| self.tera.get_template_names().any(|n| n == candidate))
+            .unwrap_or(fallback)
+    }
+}
+
+/// Lowercased BCP 47-ish tag (`pt_BR` → `pt-br`), or `None` if it isn't
+/// one; the tag ends up in a template name, so anything else is dropped.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let tag = locale.trim().replace('_', "-").to_ascii_lowercase();
+    let plain = !tag.is_empty()
+        && tag.len() <= 16
+        && tag.split('-').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric()));
+    plain.then_some(tag)
+}
+
+/// The first language of an `Accept-Language` header value.
+pub fn preferred_locale(accept_language: &str) -> Option<String> {
+    let first = accept_language.split(',').next()?.split(';').next()?;
+    normalize_locale(first)
+}
+
+/// `beta_code.de.html` is a translation; `beta_code.html` is not.
+fn is_translation(name: &str) -> bool {
+    name.matches('.').count() > name.ends_with(".subject.txt") as usize + 1
+}
+
+fn read_dir(dir: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let mut templates = Vec::new();
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read EMAIL_TEMPLATE_DIR {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || !(name.ends_with(".html") || name.ends_with(".txt")) {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read email template {}", path.display()))?;
+        templates.push((name.to_string(), content));
+    }
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding() -> InstanceBranding {
+        InstanceBranding {
+            name: "Acme <Chat>".into(),
+            has_logo: false,
+            accent_color: "#123456".into(),
+            background_color: "#ABCDEF".into(),
+            terms_url: Some("https://acme.example/terms".into()),
+            privacy_url: Some("https://acme.example/privacy".into()),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn every_builtin_email_renders() {
+        let templates = EmailTemplates::builtin();
+        let mut reset = Context::new();
+        reset.insert("reset_url", "https://acme.example/reset?token=abc&x=1");
+        reset.insert("expires_minutes", &30);
+        let mut digest = Context::new();
+        digest.insert("unread_messages", &1);
+        digest.insert("unread_channels", &2);
+        digest.insert("mentions", &0);
+        digest.insert("open_url", "https://acme.example/");
+        let mut verification = Context::new();
+        verification.insert("code", "482913");
+        verification.insert("expires_minutes", &15);
+
+        let email = templates.render("password_reset", None, &branding(), false, reset).unwrap();
+        assert_eq!(email.subject, "Reset your Acme <Chat> password");
+        assert!(email.html.contains(r#"href="https://acme.example/reset?token=abc&amp;x=1""#));
+        assert!(email.html.contains("Terms</a> &middot; <a"));
+
+        let email = templates.render("digest", None, &branding(), false, digest).unwrap();
+        assert_eq!(email.subject, "1 unread message on Acme <Chat>");
+        assert!(email.html.contains("in 2 channels."));
+
+        let email = templates.render("email_verification", None, &branding(), false, verification).unwrap();
+        assert_eq!(email.subject, "Your Acme <Chat> verification code: 482913");
+        assert!(email.html.contains("within 15 minutes"));
+    }
+
+    #[test]
+    fn overrides_and_translations_come_from_the_template_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("beta_code.subject.txt"), "Code for {{ instance_name }}").unwrap();
+        std::fs::write(dir.path().join("beta_code.de.subject.txt"), "Dein Code: {{ code }}").unwrap();
+        let templates = EmailTemplates::load(dir.path().to_str().unwrap()).unwrap();
+
+        let vars = || {
+            let mut vars = Context::new();
+            vars.insert("code", "CODE123");
+            vars.insert("expiry_days", &7);
+            vars
+        };
+        let render = |locale| templates.render("beta_code", locale, &branding(), false, vars()).unwrap();
+        assert_eq!(render(None).subject, "Code for Acme <Chat>");
+        assert_eq!(render(Some("de-AT")).subject, "Dein Code: CODE123");
+        assert_eq!(render(Some("fr")).subject, "Code for Acme <Chat>");
+        assert!(render(Some("de")).html.contains("CODE123"));
+    }
+
+    #[test]
+    fn locales_are_reduced_to_plain_tags() {
+        assert_eq!(normalize_locale("pt_BR").as_deref(), Some("pt-br"));
+        assert!(normalize_locale("../base").is_none());
+        assert!(normalize_locale("de-").is_none());
+        assert_eq!(preferred_locale("fr-CH, fr;q=0.9, en;q=0.8").as_deref(), Some("fr-ch"));
+        assert!(preferred_locale("*").is_none());
+    }
+
+    #[test]
+    fn translations_are_recognized_by_name() {
+        assert!(is_translation("beta_code.de.html"));
+        assert!(is_translation("beta_code.pt-br.subject.txt"));
+        assert!(!is_translation("beta_code.html"));
+        assert!(!is_translation("beta_code.subject.txt"));
+    }
+}
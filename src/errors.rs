@@ -1,9 +1,43 @@
+//! Error type shared by every handler, and the JSON body it turns into.
+//!
+//! Every error response carries a stable machine-readable `code` alongside
+//! the human-readable `error` message, so clients never have to match on
+//! English text. Each variant has a default code; call sites that clients
+//! need to tell apart attach a more specific one with
+//! [`AppError::with_code`]. Request validation failures list the offending
+//! fields in `details`, and `trace_id` matches the request's `X-Request-Id`.
+
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
+
+use crate::middleware::trace_id;
+
+/// One invalid field of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, utoipa::ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    /// Machine-readable reason, e.g. `length`
+    pub code: String,
+    pub message: String,
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ErrorResponse {
+    /// Human-readable message; may change between releases
+    pub error: String,
+    pub status: u16,
+    /// Stable machine-readable code, e.g. `RESTORE_LIMIT_EXCEEDED`
+    pub code: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
@@ -25,6 +59,10 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Request fields that failed validation, one entry per problem.
+    #[error("Validation error: {}", join_messages(.0))]
+    InvalidFields(Vec<FieldError>),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
@@ -37,6 +75,21 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// A server-wide quota (storage, members) would be exceeded.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// A server-wide rate quota is exhausted for now.
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    /// The request body is larger than its route accepts.
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
     #[error("Prekey exhausted for user {0}")]
     PrekeyExhausted(String),
 
@@ -48,21 +101,77 @@ pub enum AppError {
 
     #[error("Internal server error")]
     Internal(#[from] anyhow::Error),
+
+    /// Another error with a more specific code than its variant's default.
+    #[error("{source}")]
+    Coded {
+        code: &'static str,
+        source: Box<AppError>,
+    },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match &self {
+fn join_messages(fields: &[FieldError]) -> String {
+    fields.iter().map(|f| f.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+impl AppError {
+    /// Attach a specific code, e.g. `INVALID_SIGNATURE`, keeping the
+    /// status and message of the underlying error.
+    pub fn with_code(self, code: &'static str) -> Self {
+        match self {
+            AppError::Coded { source, .. } => AppError::Coded { code, source },
+            other => AppError::Coded { code, source: Box::new(other) },
+        }
+    }
+
+    /// Stable machine-readable code for the response body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::AuthError(_) => "AUTH_FAILED",
+            AppError::InvalidToken => "INVALID_TOKEN",
+            AppError::TokenExpired => "TOKEN_EXPIRED",
+            AppError::UserNotFound => "USER_NOT_FOUND",
+            AppError::UsernameTaken => "USERNAME_TAKEN",
+            AppError::Validation(_) | AppError::InvalidFields(_) => "VALIDATION_FAILED",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::RateLimited => "RATE_LIMITED",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            AppError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
+            AppError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            AppError::PrekeyExhausted(_) => "PREKEY_EXHAUSTED",
+            AppError::Database(_) | AppError::Redis(_) | AppError::Internal(_) => "INTERNAL_ERROR",
+            AppError::Coded { code, .. } => code,
+        }
+    }
+
+    fn details(&self) -> Vec<FieldError> {
+        match self {
+            AppError::InvalidFields(fields) => fields.clone(),
+            AppError::Coded { source, .. } => source.details(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
             AppError::AuthError(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
             AppError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".into()),
             AppError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired".into()),
             AppError::UserNotFound => (StatusCode::NOT_FOUND, "User not found".into()),
             AppError::UsernameTaken => (StatusCode::CONFLICT, "Username already taken".into()),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::InvalidFields(fields) => (StatusCode::BAD_REQUEST, join_messages(fields)),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AppError::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "Rate limited".into()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
+            AppError::QuotaExceeded(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone()),
+            AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg.clone()),
             AppError::PrekeyExhausted(id) => (
                 StatusCode::GONE,
                 format!("No prekeys available for user {id}"),
@@ -79,15 +188,86 @@ impl IntoResponse for AppError {
                 tracing::error!("Internal error: {:?}", e);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".into())
             }
-        };
+            AppError::Coded { source, .. } => source.status_and_message(),
+        }
+    }
+}
 
-        let body = Json(json!({
-            "error": message,
-            "status": status.as_u16(),
-        }));
+impl From<validator::ValidationErrors> for AppError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let mut fields: Vec<FieldError> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |e| FieldError {
+                    field: field.to_string(),
+                    code: e.code.to_string(),
+                    message: e
+                        .message
+                        .as_ref()
+                        .map_or_else(|| format!("Invalid {}", field), |m| m.to_string()),
+                })
+            })
+            .collect();
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::InvalidFields(fields)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, message) = self.status_and_message();
+        let body = Json(ErrorResponse {
+            error: message,
+            status: status.as_u16(),
+            code: self.code().to_string(),
+            details: self.details(),
+            trace_id: trace_id::current(),
+        });
 
         (status, body).into_response()
     }
 }
 
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(error: AppError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn specific_code_keeps_status_and_message() {
+        let error = AppError::Validation("Too many roles (max 250)".into())
+            .with_code("RESTORE_LIMIT_EXCEEDED");
+        let (status, value) = body_json(error).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(value["code"], "RESTORE_LIMIT_EXCEEDED");
+        assert_eq!(value["error"], "Too many roles (max 250)");
+        assert!(value.get("details").is_none());
+
+        let (_, value) = body_json(AppError::NotFound("Channel not found".into())).await;
+        assert_eq!(value["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn field_errors_are_listed_in_details() {
+        let error = AppError::InvalidFields(vec![FieldError {
+            field: "username".into(),
+            code: "length".into(),
+            message: "Username must be 3-32 characters".into(),
+        }]);
+        let (status, value) = body_json(error).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(value["code"], "VALIDATION_FAILED");
+        assert_eq!(value["error"], "Username must be 3-32 characters");
+        assert_eq!(value["details"][0]["field"], "username");
+        assert_eq!(value["details"][0]["code"], "length");
+    }
+}
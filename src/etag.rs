@@ -0,0 +1,88 @@
+//! Conditional GETs for a server's channel, role and member lists.
+//!
+//! Each server carries a `list_version` counter that every query changing
+//! those lists bumps. The ETag hashes that version together with the
+//! requesting user and the list (plus its page), since the channel list is
+//! filtered by the caller's permissions. Handlers must read the version
+//! before the list itself, from the same pool: a racing write then yields a
+//! stale tag on fresh data (one extra refetch), never the reverse.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Weak ETag for one user's view of a server list at `version`.
+pub fn list_etag(server_id: Uuid, version: i64, user_id: Uuid, list: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(version.to_be_bytes());
+    hasher.update(user_id.as_bytes());
+    hasher.update(list.as_bytes());
+    format!("W/\"{}\"", &hex::encode(hasher.finalize())[..16])
+}
+
+/// Whether `If-None-Match` lists `etag` (weak comparison, `*` matches anything).
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let wanted = opaque(etag);
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == wanted)
+}
+
+/// `304 Not Modified` for a client that already has `etag`.
+pub fn not_modified(etag: String) -> Response {
+    (StatusCode::NOT_MODIFIED, cache_headers(etag)).into_response()
+}
+
+/// JSON list tagged with `etag`.
+pub fn with_etag<T: Serialize>(etag: String, body: T) -> Response {
+    (cache_headers(etag), Json(body)).into_response()
+}
+
+/// Clients may keep the list but must revalidate before reusing it.
+fn cache_headers(etag: String) -> [(header::HeaderName, String); 2] {
+    [(header::ETAG, etag), (header::CACHE_CONTROL, "private, no-cache".to_string())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn etag_changes_with_version_user_and_list() {
+        let (server, user) = (Uuid::new_v4(), Uuid::new_v4());
+        let tag = list_etag(server, 1, user, "channels");
+        assert_eq!(tag, list_etag(server, 1, user, "channels"));
+        assert_ne!(tag, list_etag(server, 2, user, "channels"));
+        assert_ne!(tag, list_etag(server, 1, Uuid::new_v4(), "channels"));
+        assert_ne!(tag, list_etag(server, 1, user, "roles"));
+        assert!(tag.starts_with("W/\"") && tag.ends_with('"'));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let tag = "W/\"abc\"";
+        assert!(if_none_match(&headers("W/\"abc\""), tag));
+        assert!(if_none_match(&headers("\"abc\""), tag));
+        assert!(if_none_match(&headers("\"x\", W/\"abc\""), tag));
+        assert!(if_none_match(&headers("*"), tag));
+        assert!(!if_none_match(&headers("W/\"abd\""), tag));
+        assert!(!if_none_match(&HeaderMap::new(), tag));
+    }
+}
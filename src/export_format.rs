@@ -0,0 +1,232 @@
+//! The `.haven` export format.
+//!
+//! **v1** is a single JSON document: the signed manifest plus the restore
+//! document (`server`, `categories`, `channels`, `roles`,
+//! `permission_overwrites`, `sections`) inline.
+//!
+//! **v2** splits the document into named sections, each serialized as JSON,
+//! cut into chunks of at most [`CHUNK_SIZE`] bytes and compressed with zstd.
+//! The manifest carries `"format_version": 2` and lists every chunk in stream
+//! order under `chunks` with its section, index, uncompressed size and the
+//! SHA-256 of its compressed bytes. Since the signature covers the manifest,
+//! it commits to every chunk: a reader can verify and decompress chunks one at
+//! a time as they arrive ([`decode_chunk`]) without holding the archive.
+//!
+//! A restore reassembles the document by parsing each section's JSON as the
+//! top-level field of the same name; sections a restore doesn't know (message
+//! history, which is imported separately) are ignored.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{AppError, AppResult};
+
+pub const FORMAT_V1: u32 = 1;
+pub const FORMAT_V2: u32 = 2;
+
+/// Uncompressed bytes per chunk.
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+/// Upper bound on a whole archive once decompressed.
+pub const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A chunk as listed in the v2 manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ChunkEntry {
+    pub section: String,
+    pub index: u32,
+    /// Hex SHA-256 of the compressed chunk
+    pub sha256: String,
+    /// Uncompressed length in bytes
+    pub size: u64,
+}
+
+/// A chunk's compressed bytes as sent alongside the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ExportChunk {
+    pub section: String,
+    pub index: u32,
+    pub data: String, // base64 zstd
+}
+
+/// Format version a manifest declares; manifests without one are v1.
+pub fn format_version(manifest: &serde_json::Value) -> AppResult<u32> {
+    match manifest.get("format_version") {
+        None | Some(serde_json::Value::Null) => Ok(FORMAT_V1),
+        Some(v) => match v.as_u64() {
+            Some(1) => Ok(FORMAT_V1),
+            Some(2) => Ok(FORMAT_V2),
+            _ => Err(AppError::Validation(format!("Unsupported export format version {}", v))
+                .with_code("UNSUPPORTED_FORMAT")),
+        },
+    }
+}
+
+/// The chunk list of a v2 manifest. Each section's chunks must be numbered
+/// from 0 in stream order, and the declared sizes must fit [`MAX_ARCHIVE_SIZE`].
+pub fn manifest_chunks(manifest: &serde_json::Value) -> AppResult<Vec<ChunkEntry>> {
+    let invalid = |msg: &str| AppError::Validation(msg.into()).with_code("INVALID_MANIFEST");
+
+    let entries: Vec<ChunkEntry> = manifest
+        .get("chunks")
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .ok_or_else(|| invalid("manifest.chunks must list the archive's chunks"))?;
+
+    let mut next_index: HashMap<&str, u32> = HashMap::new();
+    let mut total = 0u64;
+    for entry in &entries {
+        if entry.section.is_empty() {
+            return Err(invalid("Chunk section names must not be empty"));
+        }
+        let expected = next_index.entry(entry.section.as_str()).or_insert(0);
+        if entry.index != *expected {
+            return Err(invalid(&format!(
+                "Chunk {} of section \"{}\" is out of order",
+                entry.index, entry.section
+            )));
+        }
+        *expected += 1;
+        if entry.size > CHUNK_SIZE as u64 {
+            return Err(invalid("Chunk exceeds the maximum chunk size"));
+        }
+        total += entry.size;
+    }
+    if total > MAX_ARCHIVE_SIZE {
+        return Err(invalid("Archive exceeds the maximum decompressed size"));
+    }
+    Ok(entries)
+}
+
+/// Split one section's JSON into compressed chunks, returning each chunk's
+/// manifest entry and its compressed bytes.
+pub fn encode_section(section: &str, data: &[u8]) -> AppResult<Vec<(ChunkEntry, Vec<u8>)>> {
+    // An empty section still gets one (empty) chunk so it appears in the manifest
+    let pieces: Vec<&[u8]> = if data.is_empty() { vec![data] } else { data.chunks(CHUNK_SIZE).collect() };
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(index, piece)| {
+            let compressed = zstd::bulk::compress(piece, COMPRESSION_LEVEL)
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("zstd compression failed: {e}")))?;
+            let entry = ChunkEntry {
+                section: section.to_string(),
+                index: index as u32,
+                sha256: hex::encode(Sha256::digest(&compressed)),
+                size: piece.len() as u64,
+            };
+            Ok((entry, compressed))
+        })
+        .collect()
+}
+
+/// Check a chunk against its manifest entry and decompress it.
+pub fn decode_chunk(entry: &ChunkEntry, compressed: &[u8]) -> AppResult<Vec<u8>> {
+    if hex::encode(Sha256::digest(compressed)) != entry.sha256.to_ascii_lowercase() {
+        return Err(AppError::Validation(format!(
+            "Chunk {} of section \"{}\" does not match the manifest",
+            entry.index, entry.section
+        ))
+        .with_code("CHUNK_HASH_MISMATCH"));
+    }
+    let data = zstd::bulk::decompress(compressed, entry.size as usize)
+        .ok()
+        .filter(|data| data.len() as u64 == entry.size)
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Chunk {} of section \"{}\" is corrupt",
+                entry.index, entry.section
+            ))
+            .with_code("CHUNK_CORRUPT")
+        })?;
+    Ok(data)
+}
+
+/// Verify and decompress every chunk of an archive, in manifest order, and
+/// concatenate each section's chunks.
+pub fn decode_sections(entries: &[ChunkEntry], chunks: &[ExportChunk]) -> AppResult<HashMap<String, Vec<u8>>> {
+    if chunks.len() != entries.len() {
+        return Err(AppError::Validation(format!(
+            "Manifest lists {} chunks but {} were sent",
+            entries.len(),
+            chunks.len()
+        ))
+        .with_code("CHUNK_MISSING"));
+    }
+
+    let mut sections: HashMap<String, Vec<u8>> = HashMap::new();
+    for (entry, chunk) in entries.iter().zip(chunks) {
+        if chunk.section != entry.section || chunk.index != entry.index {
+            return Err(AppError::Validation(format!(
+                "Expected chunk {} of section \"{}\"",
+                entry.index, entry.section
+            ))
+            .with_code("CHUNK_MISSING"));
+        }
+        let compressed = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &chunk.data)
+            .map_err(|_| AppError::Validation("Invalid base64 chunk data".into()).with_code("CHUNK_CORRUPT"))?;
+        let data = decode_chunk(entry, &compressed)?;
+        sections.entry(entry.section.clone()).or_default().extend_from_slice(&data);
+    }
+    Ok(sections)
+}
+
+/// Rebuild the v1-shaped document from decoded sections: each section's JSON
+/// becomes the top-level field of the same name.
+pub fn assemble_document(sections: HashMap<String, Vec<u8>>) -> AppResult<serde_json::Value> {
+    let mut document = serde_json::Map::new();
+    for (name, data) in sections {
+        let value: serde_json::Value = serde_json::from_slice(&data)
+            .map_err(|e| AppError::Validation(format!("Section \"{}\" is not valid JSON: {}", name, e)))?;
+        document.insert(name, value);
+    }
+    Ok(serde_json::Value::Object(document))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_round_trip_across_chunks() {
+        let data = vec![b'x'; CHUNK_SIZE * 2 + 10];
+        let encoded = encode_section("channels", &data).unwrap();
+        assert_eq!(encoded.len(), 3);
+        assert_eq!(encoded[2].0.size, 10);
+
+        let (entries, chunks): (Vec<_>, Vec<_>) = encoded
+            .into_iter()
+            .map(|(entry, bytes)| {
+                let chunk = ExportChunk {
+                    section: entry.section.clone(),
+                    index: entry.index,
+                    data: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes),
+                };
+                (entry, chunk)
+            })
+            .unzip();
+        let sections = decode_sections(&entries, &chunks).unwrap();
+        assert_eq!(sections["channels"], data);
+    }
+
+    #[test]
+    fn tampered_chunk_is_rejected() {
+        let (mut entry, bytes) = encode_section("roles", b"[]").unwrap().remove(0);
+        entry.sha256 = hex::encode([0u8; 32]);
+        assert!(decode_chunk(&entry, &bytes).is_err());
+    }
+
+    #[test]
+    fn manifest_versions_and_chunk_order() {
+        assert_eq!(format_version(&serde_json::json!({})).unwrap(), FORMAT_V1);
+        assert_eq!(format_version(&serde_json::json!({ "format_version": 2 })).unwrap(), FORMAT_V2);
+        assert!(format_version(&serde_json::json!({ "format_version": 3 })).is_err());
+
+        let chunk = |index: u32| serde_json::json!({ "section": "a", "index": index, "sha256": "", "size": 1 });
+        assert!(manifest_chunks(&serde_json::json!({ "chunks": [chunk(0), chunk(1)] })).is_ok());
+        assert!(manifest_chunks(&serde_json::json!({ "chunks": [chunk(1)] })).is_err());
+    }
+}
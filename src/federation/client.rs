@@ -0,0 +1,132 @@
+//! Outbound federation HTTP calls.
+
+use std::time::Duration;
+
+use base64::Engine;
+use serde::de::DeserializeOwned;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::federation::signing;
+use crate::models::{FederatedProfile, FederationKeyResponse, FederationTransaction};
+use crate::AppState;
+
+const API_PREFIX: &str = "/api/v1/federation";
+
+fn http_client() -> AppResult<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("HTTP client error: {e}")))
+}
+
+fn remote_error(server: &str, e: impl std::fmt::Display) -> AppError {
+    tracing::warn!("Federation request to {} failed: {}", server, e);
+    AppError::Validation(format!("Could not reach {}", server))
+}
+
+/// Send a signed request to `destination` and decode the JSON reply.
+async fn signed_request<T: DeserializeOwned>(
+    state: &AppState,
+    method: reqwest::Method,
+    destination: &str,
+    path: &str,
+    body: Option<Vec<u8>>,
+) -> AppResult<T> {
+    let key = signing::signing_key(&state.config)?;
+    let full_path = format!("{}{}", API_PREFIX, path);
+    let body = body.unwrap_or_default();
+    let auth = signing::sign_request(
+        &key,
+        method.as_str(),
+        &full_path,
+        &state.config.federation_server_name,
+        destination,
+        &body,
+    );
+
+    let mut request = http_client()?
+        .request(method, format!("https://{}{}", destination, full_path))
+        .header(reqwest::header::AUTHORIZATION, auth);
+    if !body.is_empty() {
+        request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+    }
+
+    let response = request.send().await.map_err(|e| remote_error(destination, e))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound(format!("Not found on {}", destination)));
+    }
+    if !response.status().is_success() {
+        return Err(remote_error(destination, response.status()));
+    }
+    response.json::<T>().await.map_err(|e| remote_error(destination, e))
+}
+
+/// Fetch a remote server's public signing key (unauthenticated).
+pub async fn fetch_server_key(server: &str) -> AppResult<Vec<u8>> {
+    if !crate::federation::valid_origin(server) {
+        return Err(AppError::Validation(format!("Invalid server name {}", server)));
+    }
+    let response = http_client()?
+        .get(format!("https://{}{}/key", server, API_PREFIX))
+        .send()
+        .await
+        .map_err(|e| remote_error(server, e))?;
+    if !response.status().is_success() {
+        return Err(remote_error(server, response.status()));
+    }
+    let body: FederationKeyResponse = response.json().await.map_err(|e| remote_error(server, e))?;
+    if body.server_name != server {
+        return Err(remote_error(server, "server_name mismatch"));
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(&body.public_key)
+        .map_err(|e| remote_error(server, e))
+}
+
+/// Look up a user on a remote server by username.
+pub async fn fetch_user(state: &AppState, server: &str, username: &str) -> AppResult<FederatedProfile> {
+    signed_request(
+        state,
+        reqwest::Method::GET,
+        server,
+        &format!("/users/{}", username),
+        None,
+    )
+    .await
+}
+
+/// Deliver a transaction to a remote server.
+pub async fn send_transaction(
+    state: &AppState,
+    destination: &str,
+    txn_id: &str,
+    txn: &FederationTransaction,
+) -> AppResult<()> {
+    let body = serde_json::to_vec(txn).map_err(|e| AppError::Internal(e.into()))?;
+    let _: serde_json::Value = signed_request(
+        state,
+        reqwest::Method::PUT,
+        destination,
+        &format!("/transactions/{}", txn_id),
+        Some(body),
+    )
+    .await?;
+    Ok(())
+}
+
+/// The public key to verify `server`'s requests with, from cache when fresh.
+pub async fn server_key(state: &AppState, server: &str) -> AppResult<Vec<u8>> {
+    let ttl = chrono::Duration::seconds(state.config.federation_key_cache_ttl_secs as i64);
+    if let Some((key, fetched_at)) = queries::get_federation_server_key(state.db.read(), server).await? {
+        if fetched_at + ttl > chrono::Utc::now() {
+            return Ok(key);
+        }
+    }
+    let key = fetch_server_key(server).await?;
+    queries::upsert_federation_server_key(state.db.write(), server, &key).await?;
+    Ok(key)
+}
@@ -0,0 +1,250 @@
+//! DM-only federation between Haven instances.
+//!
+//! A user on server A can open a DM with `bob@b.example`. Server A fetches
+//! Bob's public identity over a signed request, caches it as a local shadow
+//! account (`federated_users`), and from then on the DM is an ordinary local
+//! channel: every message sent into it is also pushed to server B as a signed
+//! transaction. Server B mirrors the channel under the same id with a shadow
+//! account for the sender. Message bodies stay end-to-end encrypted; servers
+//! only relay ciphertext.
+//!
+//! Disabled unless `federation_server_name` and `federation_signing_key` are
+//! set. `federation_allowlist` / `federation_denylist` (comma-separated server
+//! names) restrict which servers we talk to in either direction.
+
+pub mod client;
+pub mod signing;
+
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::{FederatedProfile, FederationEvent, FederationTransaction, Message, User};
+use crate::AppState;
+
+fn server_list(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Whether we accept traffic from / send traffic to `server`.
+pub fn server_allowed(config: &AppConfig, server: &str) -> bool {
+    if server.eq_ignore_ascii_case(&config.federation_server_name) {
+        return false;
+    }
+    if server_list(&config.federation_denylist).any(|s| s.eq_ignore_ascii_case(server)) {
+        return false;
+    }
+    let mut allow = server_list(&config.federation_allowlist).peekable();
+    allow.peek().is_none() || allow.any(|s| s.eq_ignore_ascii_case(server))
+}
+
+/// A plausible host name, optionally with a port. Keeps user-supplied
+/// addresses from smuggling paths or credentials into outbound URLs. IP
+/// literals are refused: URL parsers read `127.1` or `0x7f.1` as addresses,
+/// so the last label must start with a letter, as every TLD does.
+fn valid_server_name(server: &str) -> bool {
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (server, None),
+    };
+    !host.is_empty()
+        && host.len() <= 253
+        && host.contains('.')
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        && host.rsplit('.').next().and_then(|tld| tld.chars().next()).is_some_and(|c| c.is_ascii_alphabetic())
+        && port.is_none_or(|p| p.parse::<u16>().is_ok())
+}
+
+/// Whether `server` may be the origin of an inbound request. Checked before
+/// its key is fetched, since the name comes from an unauthenticated header:
+/// a bare host name, no port.
+pub fn valid_origin(server: &str) -> bool {
+    !server.contains(':') && valid_server_name(server)
+}
+
+/// Split `username@server` into its parts.
+pub fn parse_address(address: &str) -> Option<(&str, &str)> {
+    let (username, server) = address.trim().split_once('@')?;
+    if username.is_empty() || !valid_server_name(server) {
+        return None;
+    }
+    Some((username, server))
+}
+
+/// Shadow accounts are named `username@server`; local usernames cannot
+/// contain `@`.
+pub fn is_remote_username(username: &str) -> bool {
+    username.contains('@')
+}
+
+fn decode_key(field: &str, value: &str) -> AppResult<Vec<u8>> {
+    base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)
+        .map_err(|_| AppError::Validation(format!("Invalid {} in remote profile", field)))
+}
+
+/// Create or refresh the shadow account for a profile received from `server`.
+pub async fn store_remote_profile(state: &AppState, server: &str, profile: &FederatedProfile) -> AppResult<User> {
+    if profile.username.is_empty() || is_remote_username(&profile.username) {
+        return Err(AppError::Validation("Invalid remote username".into()));
+    }
+    let identity_key = decode_key("identity_key", &profile.identity_key)?;
+    let signed_prekey = decode_key("signed_prekey", &profile.signed_prekey)?;
+    let signed_prekey_sig = decode_key("signed_prekey_signature", &profile.signed_prekey_signature)?;
+    queries::upsert_federated_user(
+        state.db.write(),
+        server,
+        profile,
+        &identity_key,
+        &signed_prekey,
+        &signed_prekey_sig,
+    )
+    .await
+}
+
+/// Resolve `username@server` to a local shadow account, fetching the remote
+/// profile when we have none or the cached copy is older than the TTL.
+pub async fn resolve_remote_user(state: &AppState, address: &str) -> AppResult<User> {
+    if !state.config.federation_enabled() {
+        return Err(AppError::Forbidden("Federation is disabled on this server".into()));
+    }
+    let (username, server) =
+        parse_address(address).ok_or(AppError::Validation("Address must look like user@server".into()))?;
+    if !server_allowed(&state.config, server) {
+        return Err(AppError::Forbidden("Federation with this server is not allowed".into()));
+    }
+
+    let cached = queries::find_user_by_username(state.db.read(), &format!("{}@{}", username, server)).await?;
+    if let Some(user) = cached {
+        if let Some(fed) = queries::find_federated_user(state.db.read(), user.id).await? {
+            let ttl = chrono::Duration::seconds(state.config.federation_user_cache_ttl_secs as i64);
+            if fed.fetched_at + ttl > chrono::Utc::now() {
+                return Ok(user);
+            }
+        }
+    }
+
+    let profile = client::fetch_user(state, server, username).await?;
+    store_remote_profile(state, server, &profile).await
+}
+
+/// Push events to every remote server with a member in `channel_id`.
+/// Delivery happens in the background; failures are logged, not retried.
+async fn relay_to_channel_servers(state: &AppState, channel_id: Uuid, events: Vec<FederationEvent>) {
+    if !state.config.federation_enabled() {
+        return;
+    }
+    let members = match queries::get_channel_federated_members(state.db.read(), channel_id).await {
+        Ok(m) => m,
+        Err(e) => {
+            tracing::error!("Failed to load federated members of {}: {}", channel_id, e);
+            return;
+        }
+    };
+    let mut servers: Vec<String> = members.into_iter().map(|m| m.server_name).collect();
+    servers.sort();
+    servers.dedup();
+
+    for server in servers {
+        if !server_allowed(&state.config, &server) {
+            continue;
+        }
+        let state = state.clone();
+        let txn = FederationTransaction {
+            origin: state.config.federation_server_name.clone(),
+            events: events.clone(),
+        };
+        tokio::spawn(async move {
+            let txn_id = Uuid::new_v4().to_string();
+            if let Err(e) = client::send_transaction(&state, &server, &txn_id, &txn).await {
+                tracing::warn!("Federation delivery to {} failed: {}", server, e);
+            }
+        });
+    }
+}
+
+/// Tell the remote side of a new DM about it. `target` is the shadow account.
+pub async fn announce_dm(state: &AppState, channel_id: Uuid, sender: &User, target: Uuid) {
+    let Ok(Some(fed)) = queries::find_federated_user(state.db.read(), target).await else {
+        return;
+    };
+    relay_to_channel_servers(
+        state,
+        channel_id,
+        vec![FederationEvent::DmCreate {
+            channel_id,
+            sender: FederatedProfile::from(sender),
+            target_user_id: fed.remote_user_id,
+        }],
+    )
+    .await;
+}
+
+/// Forward a locally sent message to the remote members of its channel.
+/// Messages that arrived over federation are not sent back out.
+pub async fn relay_message(state: &AppState, message: &Message) {
+    let Some(sender_id) = message.sender_id else {
+        return;
+    };
+    if !state.config.federation_enabled() {
+        return;
+    }
+    if let Ok(Some(_)) = queries::find_federated_user(state.db.read(), sender_id).await {
+        return;
+    }
+    let b64 = |v: &[u8]| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, v);
+    relay_to_channel_servers(
+        state,
+        message.channel_id,
+        vec![FederationEvent::Message {
+            channel_id: message.channel_id,
+            message_id: message.id,
+            sender_id,
+            sender_token: b64(&message.sender_token),
+            encrypted_body: b64(&message.encrypted_body),
+            timestamp: message.timestamp,
+        }],
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_and_deny_lists() {
+        let mut config = AppConfig::test_default();
+        config.federation_server_name = "home.example".into();
+        assert!(server_allowed(&config, "other.example"));
+        assert!(!server_allowed(&config, "home.example"));
+
+        config.federation_denylist = "bad.example".into();
+        assert!(!server_allowed(&config, "BAD.example"));
+
+        config.federation_allowlist = "friend.example, pal.example".into();
+        assert!(server_allowed(&config, "pal.example"));
+        assert!(!server_allowed(&config, "other.example"));
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(parse_address("bob@chat.example"), Some(("bob", "chat.example")));
+        assert_eq!(parse_address("bob@chat.example:8443"), Some(("bob", "chat.example:8443")));
+        assert_eq!(parse_address("bob@localhost"), None);
+        assert_eq!(parse_address("bob@evil.example/path"), None);
+        assert_eq!(parse_address("@chat.example"), None);
+        assert_eq!(parse_address("bob@127.0.0.1"), None);
+        assert_eq!(parse_address("bob@127.1:8080"), None);
+    }
+
+    #[test]
+    fn origins_are_bare_host_names() {
+        assert!(valid_origin("chat.example"));
+        assert!(!valid_origin("chat.example:8443"));
+        assert!(!valid_origin("10.0.0.1"));
+        assert!(!valid_origin("0x7f.1"));
+        assert!(!valid_origin("internal"));
+        assert!(!valid_origin("chat.example/path"));
+    }
+}
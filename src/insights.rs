@@ -0,0 +1,85 @@
+//! Server insights: daily aggregates of a server's activity for its admins.
+//!
+//! The `insights` job rolls up messages per day and per channel (count and
+//! distinct senders) and joins and leaves per day into summary tables. Days
+//! are UTC. Each run recomputes from the latest day already rolled up through
+//! today, so a day finishes on the first run after midnight and a manual run
+//! refreshes today's partial numbers. Only counts are kept: joins and leaves
+//! are logged without user ids and dropped once counted, and rollups older
+//! than the reporting window are purged. Messages that expire or are deleted
+//! before their day is rolled up are not counted.
+
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::models::ServerInsightsDay;
+use crate::AppState;
+
+/// Longest window `GET /servers/:id/insights` reports, and how long rollups
+/// are kept.
+pub const MAX_DAYS: u32 = 90;
+
+/// Roll up every day from the latest rolled-up one through today. Returns
+/// the number of server-days written.
+pub async fn rollup(state: &AppState) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let today = Utc::now().date_naive();
+    let from = rollup_start(queries::latest_insights_day(pool).await?, today);
+    let written = queries::rollup_insights(pool, from, today).await?;
+    queries::purge_old_insights(pool, window_start(today, MAX_DAYS)).await?;
+    Ok(written)
+}
+
+/// First day of a `days`-day window ending `today`.
+pub fn window_start(today: NaiveDate, days: u32) -> NaiveDate {
+    today - Duration::days(i64::from(days.max(1)) - 1)
+}
+
+/// The latest rolled-up day may have been partial, so it is redone; without
+/// one, the whole window is backfilled.
+fn rollup_start(latest: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+    let earliest = window_start(today, MAX_DAYS);
+    latest.map_or(earliest, |day| day.clamp(earliest, today))
+}
+
+/// One entry per day of `from..=to`, zero where nothing was rolled up.
+pub fn fill_days(from: NaiveDate, to: NaiveDate, rows: Vec<ServerInsightsDay>) -> Vec<ServerInsightsDay> {
+    let mut rows = rows.into_iter().peekable();
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| match rows.next_if(|row| row.day == day) {
+            Some(row) => row,
+            None => ServerInsightsDay { day, messages: 0, active_members: 0, joins: 0, leaves: 0 },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn rollup_redoes_latest_day_within_window() {
+        let today = date(10, 17);
+        assert_eq!(window_start(today, 1), today);
+        assert_eq!(window_start(today, 30), date(9, 18));
+        assert_eq!(rollup_start(None, today), date(7, 20));
+        assert_eq!(rollup_start(Some(date(10, 16)), today), date(10, 16));
+        assert_eq!(rollup_start(Some(date(1, 1)), today), date(7, 20));
+    }
+
+    #[test]
+    fn fill_days_zeroes_quiet_days() {
+        let row = |day, messages| ServerInsightsDay { day, messages, active_members: 1, joins: 0, leaves: 0 };
+        let filled = fill_days(date(10, 1), date(10, 4), vec![row(date(10, 2), 5), row(date(10, 4), 2)]);
+        let messages: Vec<i32> = filled.iter().map(|d| d.messages).collect();
+        assert_eq!(messages, [0, 5, 0, 2]);
+        assert_eq!(filled[0].day, date(10, 1));
+        assert_eq!(filled[3].day, date(10, 4));
+    }
+}
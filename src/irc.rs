@@ -0,0 +1,627 @@
+//! IRC gateway (built with `--features irc`, enabled by `IRC_PORT`).
+//!
+//! Lets a plain IRC client log in with a Haven username and password and talk
+//! in the *unencrypted* text channels it can already see. Each Haven channel
+//! is exposed as `#<channel_id>`; `LIST` shows them with "Server / channel" as
+//! the topic. Encrypted channels and DMs are never reachable from here — the
+//! server cannot read or produce their ciphertext.
+//!
+//! Messages use the same plaintext wire format as other unencrypted channel
+//! posts (`0x00` + `{"text", "sender_id"}`) and go out through the normal
+//! broadcast path, so web clients and IRC users see each other.
+//!
+//! Supported: CAP (empty), PASS, NICK, USER, PING, JOIN, PART, PRIVMSG, LIST,
+//! QUIT. The connection is plain TCP; put a TLS terminator in front of it.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::auth::verify_password;
+use crate::db::queries;
+use crate::errors::AppError;
+use crate::middleware::RateLimiter;
+use crate::models::{Channel, MessageResponse, User, WsServerMessage};
+use crate::quota;
+use crate::ws::deliver_new_message;
+use crate::AppState;
+
+const SERVER_NAME: &str = "haven";
+const MAX_LINE_LEN: u64 = 4096;
+const MAX_TEXT_LEN: usize = 4000;
+
+/// Bind the gateway on `host:irc_port` and serve connections in the background.
+pub fn spawn(state: AppState) {
+    let addr = format!("{}:{}", state.config.host, state.config.irc_port);
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!("IRC gateway failed to bind {}: {}", addr, e);
+                return;
+            }
+        };
+        tracing::info!("IRC gateway listening on {}", addr);
+
+        // Password attempts per IP, shared across connections.
+        let login_limiter = RateLimiter::new(5, 60);
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    tracing::warn!("IRC accept failed: {}", e);
+                    continue;
+                }
+            };
+            let state = state.clone();
+            let login_limiter = login_limiter.clone();
+            tokio::spawn(async move {
+                handle_connection(state, stream, peer, login_limiter).await;
+            });
+        }
+    });
+}
+
+// ─── Protocol helpers ────────────────────────────────
+
+/// A parsed client line: `[:prefix] COMMAND params... [:trailing]`.
+#[derive(Debug, PartialEq)]
+struct IrcLine {
+    command: String,
+    params: Vec<String>,
+}
+
+fn parse_line(line: &str) -> Option<IrcLine> {
+    let mut rest = line.trim_end_matches(['\r', '\n']).trim_start();
+    if rest.starts_with(':') {
+        rest = rest.split_once(' ').map(|(_, r)| r.trim_start()).unwrap_or("");
+    }
+    let (command, mut rest) = match rest.split_once(' ') {
+        Some((c, r)) => (c, r),
+        None => (rest, ""),
+    };
+    if command.is_empty() {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(' ');
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(trailing) = rest.strip_prefix(':') {
+            params.push(trailing.to_string());
+            break;
+        }
+        match rest.split_once(' ') {
+            Some((p, r)) => {
+                params.push(p.to_string());
+                rest = r;
+            }
+            None => {
+                params.push(rest.to_string());
+                break;
+            }
+        }
+    }
+    Some(IrcLine { command: command.to_ascii_uppercase(), params })
+}
+
+fn channel_name(channel_id: Uuid) -> String {
+    format!("#{}", channel_id)
+}
+
+fn parse_channel_name(name: &str) -> Option<Uuid> {
+    name.strip_prefix('#').and_then(|id| Uuid::parse_str(id).ok())
+}
+
+/// Plaintext body for an unencrypted channel message.
+fn encode_text(text: &str, sender_id: Uuid) -> Vec<u8> {
+    let payload = serde_json::json!({ "text": text, "sender_id": sender_id });
+    let mut body = vec![0u8];
+    body.extend_from_slice(payload.to_string().as_bytes());
+    body
+}
+
+/// Text of a plaintext body; None for anything else (ciphertext,
+/// attachments-only posts). The payload's `sender_id` is only a client claim,
+/// so the author always comes from the stored message instead.
+fn decode_text(body: &[u8]) -> Option<String> {
+    let payload: serde_json::Value = serde_json::from_slice(body.strip_prefix(&[0u8])?).ok()?;
+    Some(payload.get("text")?.as_str()?.to_string())
+}
+
+/// The `name` field of a plaintext server or channel meta blob.
+fn meta_name(meta: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(meta).ok()?;
+    value.get("name")?.as_str().map(str::to_string)
+}
+
+// ─── Connection ──────────────────────────────────────
+
+struct Session {
+    state: AppState,
+    out: mpsc::UnboundedSender<String>,
+    pass: Option<String>,
+    nick: Option<String>,
+    user_sent: bool,
+    user: Option<User>,
+    joined: HashMap<Uuid, JoinHandle<()>>,
+}
+
+impl Session {
+    fn send(&self, line: String) {
+        let _ = self.out.send(line);
+    }
+
+    fn nick(&self) -> &str {
+        self.nick.as_deref().unwrap_or("*")
+    }
+
+    fn numeric(&self, code: &str, rest: &str) {
+        self.send(format!(":{} {} {} {}", SERVER_NAME, code, self.nick(), rest));
+    }
+
+    fn prefix(&self) -> String {
+        format!("{0}!{0}@{1}", self.nick(), SERVER_NAME)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        for (_, handle) in self.joined.drain() {
+            handle.abort();
+        }
+    }
+}
+
+async fn handle_connection(state: AppState, stream: TcpStream, peer: SocketAddr, login_limiter: RateLimiter) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(line) = out_rx.recv().await {
+            if writer.write_all(format!("{}\r\n", line).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session {
+        state,
+        out: out_tx,
+        pass: None,
+        nick: None,
+        user_sent: false,
+        user: None,
+        joined: HashMap::new(),
+    };
+
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        match (&mut reader).take(MAX_LINE_LEN).read_line(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if !buf.ends_with('\n') {
+            session.send("ERROR :Line too long".into());
+            break;
+        }
+        let Some(line) = parse_line(&buf) else {
+            continue;
+        };
+        if !handle_line(&mut session, line, peer, &login_limiter).await {
+            break;
+        }
+    }
+
+    drop(session);
+    // Let queued lines (e.g. a final ERROR) flush before the socket closes.
+    let _ = writer_task.await;
+}
+
+/// Handle one client line. Returns false to close the connection.
+async fn handle_line(session: &mut Session, line: IrcLine, peer: SocketAddr, login_limiter: &RateLimiter) -> bool {
+    let params = &line.params;
+    match line.command.as_str() {
+        "CAP" => {
+            if params.first().map(String::as_str) == Some("LS") {
+                session.send(format!(":{} CAP * LS :", SERVER_NAME));
+            }
+        }
+        "PING" => {
+            let token = params.first().cloned().unwrap_or_default();
+            session.send(format!(":{0} PONG {0} :{1}", SERVER_NAME, token));
+        }
+        "QUIT" => {
+            session.send("ERROR :Closing link".into());
+            return false;
+        }
+        "PASS" if session.user.is_none() => session.pass = params.first().cloned(),
+        "NICK" if session.user.is_none() => {
+            let Some(nick) = params.first() else {
+                session.numeric("431", ":No nickname given");
+                return true;
+            };
+            session.nick = Some(nick.clone());
+            return try_register(session, peer, login_limiter).await;
+        }
+        "NICK" => session.numeric("432", ":Nick changes are not supported; your nick is your Haven username"),
+        "USER" if session.user.is_none() => {
+            session.user_sent = true;
+            return try_register(session, peer, login_limiter).await;
+        }
+        "PASS" | "USER" => session.numeric("462", ":You may not reregister"),
+        _ if session.user.is_none() => session.numeric("451", ":You have not registered"),
+        // Bans and suspensions issued mid-session end it on the next command.
+        _ if is_banned(&session.state, session.user.as_ref().map(|u| u.id).unwrap_or_default()).await => {
+            session.send("ERROR :Your account has been banned from this platform".into());
+            return false;
+        }
+        "JOIN" => {
+            let Some(targets) = params.first() else {
+                session.numeric("461", "JOIN :Not enough parameters");
+                return true;
+            };
+            for target in targets.split(',') {
+                join_channel(session, target).await;
+            }
+        }
+        "PART" => {
+            let Some(targets) = params.first() else {
+                session.numeric("461", "PART :Not enough parameters");
+                return true;
+            };
+            for target in targets.split(',') {
+                match parse_channel_name(target).and_then(|id| session.joined.remove(&id)) {
+                    Some(handle) => {
+                        handle.abort();
+                        session.send(format!(":{} PART {}", session.prefix(), target));
+                    }
+                    None => session.numeric("442", &format!("{} :You're not on that channel", target)),
+                }
+            }
+        }
+        "PRIVMSG" => {
+            let (Some(target), Some(text)) = (params.first(), params.get(1)) else {
+                session.numeric("461", "PRIVMSG :Not enough parameters");
+                return true;
+            };
+            send_privmsg(session, target, text).await;
+        }
+        "LIST" => list_channels(session).await,
+        other => session.numeric("421", &format!("{} :Unknown command", other)),
+    }
+    true
+}
+
+/// Authenticate once both NICK and USER have arrived. The password may carry
+/// a TOTP code as `password:123456` for accounts with 2FA.
+async fn try_register(session: &mut Session, peer: SocketAddr, login_limiter: &RateLimiter) -> bool {
+    if session.user.is_some() || !session.user_sent {
+        return true;
+    }
+    let Some(nick) = session.nick.clone() else {
+        return true;
+    };
+
+    if !login_limiter.check(peer.ip()) {
+        session.send("ERROR :Too many login attempts".into());
+        return false;
+    }
+
+    match authenticate(&session.state, &nick, session.pass.as_deref().unwrap_or("")).await {
+        Some(user) => {
+            session.nick = Some(user.username.clone());
+            session.user = Some(user);
+            session.numeric("001", &format!(":Welcome to Haven, {}", session.nick()));
+            session.numeric("422", ":MOTD File is missing");
+            true
+        }
+        None => {
+            session.numeric("464", ":Password incorrect");
+            session.send("ERROR :Authentication failed".into());
+            false
+        }
+    }
+}
+
+async fn authenticate(state: &AppState, username: &str, pass: &str) -> Option<User> {
+    let user = queries::find_user_by_username(state.db.read(), username).await.ok()??;
+    if user.is_system {
+        return None;
+    }
+
+    let password_ok = match user.totp_secret {
+        Some(ref secret) => {
+            let (password, code) = pass.rsplit_once(':')?;
+            verify_password(password, &user.password_hash).ok()?
+                && crate::auth::verify_totp(secret, code).ok()?
+        }
+        None => verify_password(pass, &user.password_hash).ok()?,
+    };
+    if !password_ok {
+        return None;
+    }
+
+    // Suspensions are instance bans with an expiry, so this covers both.
+    if is_banned(state, user.id).await {
+        return None;
+    }
+    Some(user)
+}
+
+/// Instance ban or active suspension, cache-first like the HTTP extractor.
+/// Lookup failures count as banned so an outage can't let anyone in.
+async fn is_banned(state: &AppState, user_id: Uuid) -> bool {
+    if let Some(cached) = state.ban_cache.get(&user_id) {
+        return cached;
+    }
+    match queries::is_instance_banned(state.db.read(), user_id).await {
+        Ok(banned) => {
+            state.ban_cache.set(user_id, banned);
+            banned
+        }
+        Err(_) => true,
+    }
+}
+
+/// Unencrypted text channel the user may access, or None.
+async fn irc_channel(state: &AppState, channel_id: Uuid, user_id: Uuid) -> Option<Channel> {
+    let channel = queries::find_channel_by_id(state.db.read(), channel_id).await.ok()??;
+    if channel.encrypted || channel.server_id.is_none() || channel.channel_type != "text" {
+        return None;
+    }
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await.ok()? {
+        return None;
+    }
+    Some(channel)
+}
+
+async fn channel_topic(state: &AppState, channel: &Channel) -> String {
+    let channel_name = meta_name(&channel.encrypted_meta).unwrap_or_else(|| "channel".into());
+    let server_name = match channel.server_id {
+        Some(server_id) => queries::find_server_by_id(state.db.read(), server_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|s| meta_name(&s.encrypted_meta)),
+        None => None,
+    };
+    match server_name {
+        Some(server) => format!("{} / {}", server, channel_name),
+        None => channel_name,
+    }
+}
+
+async fn join_channel(session: &mut Session, target: &str) {
+    let user_id = session.user.as_ref().map(|u| u.id).unwrap_or_default();
+    let Some(channel_id) = parse_channel_name(target) else {
+        session.numeric("403", &format!("{} :No such channel", target));
+        return;
+    };
+    if session.joined.contains_key(&channel_id) {
+        return;
+    }
+    let Some(channel) = irc_channel(&session.state, channel_id, user_id).await else {
+        session.numeric("403", &format!("{} :No such channel", target));
+        return;
+    };
+
+    let state = session.state.clone();
+    let broadcaster = state
+        .channel_broadcasts
+        .entry(channel_id)
+        .or_insert_with(|| {
+            let (tx, _) = broadcast::channel(state.config.broadcast_channel_capacity);
+            tx
+        })
+        .clone();
+
+    let handle = tokio::spawn(forward_channel(
+        state.clone(),
+        broadcaster.subscribe(),
+        session.out.clone(),
+        channel_id,
+        user_id,
+    ));
+    session.joined.insert(channel_id, handle);
+
+    let name = channel_name(channel_id);
+    let topic = channel_topic(&state, &channel).await;
+    session.send(format!(":{} JOIN {}", session.prefix(), name));
+    session.numeric("332", &format!("{} :{}", name, topic));
+    session.numeric("353", &format!("= {} :{}", name, session.nick()));
+    session.numeric("366", &format!("{} :End of /NAMES list.", name));
+}
+
+/// Relay new messages in one channel to the IRC client as PRIVMSGs.
+async fn forward_channel(
+    state: AppState,
+    mut rx: broadcast::Receiver<WsServerMessage>,
+    out: mpsc::UnboundedSender<String>,
+    channel_id: Uuid,
+    user_id: Uuid,
+) {
+    let name = channel_name(channel_id);
+    let mut nicks: HashMap<Uuid, String> = HashMap::new();
+    loop {
+        let msg = match rx.recv().await {
+            Ok(msg) => msg,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let WsServerMessage::NewMessage(MessageResponse { id, encrypted_body, blocked_by, .. }) = msg else {
+            continue;
+        };
+        if blocked_by.contains(&user_id) {
+            continue;
+        }
+        let Ok(body) = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &encrypted_body) else {
+            continue;
+        };
+        let Some(text) = decode_text(&body) else {
+            continue;
+        };
+        let sender_id = match queries::find_message_by_id(state.db.read(), id).await {
+            Ok(Some(stored)) => stored.sender_id,
+            _ => None,
+        };
+        // IRC clients echo their own lines locally.
+        if sender_id == Some(user_id) {
+            continue;
+        }
+
+        let nick = match sender_id {
+            Some(id) => match nicks.get(&id) {
+                Some(nick) => nick.clone(),
+                None => {
+                    let nick = queries::find_user_by_id(state.db.read(), id)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(|u| u.username)
+                        .unwrap_or_else(|| SERVER_NAME.into());
+                    nicks.insert(id, nick.clone());
+                    nick
+                }
+            },
+            None => SERVER_NAME.into(),
+        };
+        for part in text.lines().filter(|l| !l.is_empty()) {
+            if out.send(format!(":{0}!{0}@{1} PRIVMSG {2} :{3}", nick, SERVER_NAME, name, part)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+async fn send_privmsg(session: &mut Session, target: &str, text: &str) {
+    let Some(user_id) = session.user.as_ref().map(|u| u.id) else {
+        return;
+    };
+    let Some(channel_id) = parse_channel_name(target).filter(|id| session.joined.contains_key(id)) else {
+        session.numeric("404", &format!("{} :Cannot send to channel", target));
+        return;
+    };
+    if text.len() > MAX_TEXT_LEN {
+        session.numeric("404", &format!("{} :Message too long", target));
+        return;
+    }
+    let state = session.state.clone();
+    if !state.ws_rate_limiter.check(user_id) {
+        session.numeric("404", &format!("{} :Rate limited, slow down", target));
+        return;
+    }
+
+    // Re-check: the channel may have been made encrypted or access revoked.
+    let Some(channel) = irc_channel(&state, channel_id, user_id).await else {
+        session.numeric("404", &format!("{} :Cannot send to channel", target));
+        return;
+    };
+    if let Some(server_id) = channel.server_id {
+        if queries::is_member_timed_out(state.db.read(), server_id, user_id)
+            .await
+            .unwrap_or(false)
+        {
+            session.numeric("404", &format!("{} :You are timed out in this server", target));
+            return;
+        }
+        if let Err(e) = quota::record_message(&state, server_id).await {
+            let reason = match e {
+                AppError::TooManyRequests(msg) => msg,
+                _ => "Cannot send to channel".into(),
+            };
+            session.numeric("404", &format!("{} :{}", target, reason));
+            return;
+        }
+    }
+
+    let expires_at = channel
+        .message_ttl
+        .map(|ttl| chrono::Utc::now() + chrono::Duration::seconds(ttl as i64));
+    let message = match queries::insert_message(
+        state.db.write(),
+        channel_id,
+        &[0u8],
+        &encode_text(text, user_id),
+        expires_at,
+        false,
+        user_id,
+        None,
+    )
+    .await
+    {
+        Ok(m) => m,
+        Err(e) => {
+            tracing::error!("IRC: failed to persist message: {}", e);
+            session.numeric("404", &format!("{} :Failed to save message", target));
+            return;
+        }
+    };
+    if let Err(e) = deliver_new_message(&state, message).await {
+        tracing::warn!("IRC: failed to fan out message: {}", e);
+    }
+}
+
+async fn list_channels(session: &mut Session) {
+    let Some(user_id) = session.user.as_ref().map(|u| u.id) else {
+        return;
+    };
+    let state = session.state.clone();
+    session.numeric("321", "Channel :Users Name");
+    let servers = queries::get_user_servers(state.db.read(), user_id).await.unwrap_or_default();
+    for server in servers {
+        let channels = queries::get_server_channels(state.db.read(), server.id).await.unwrap_or_default();
+        for channel in channels {
+            if irc_channel(&state, channel.id, user_id).await.is_none() {
+                continue;
+            }
+            let topic = channel_topic(&state, &channel).await;
+            session.numeric("322", &format!("{} 0 :{}", channel_name(channel.id), topic));
+        }
+    }
+    session.numeric("323", ":End of /LIST");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_prefix_params_and_trailing() {
+        let line = parse_line(":nick!u@h privmsg #chan :hello there\r\n").unwrap();
+        assert_eq!(line.command, "PRIVMSG");
+        assert_eq!(line.params, vec!["#chan".to_string(), "hello there".to_string()]);
+
+        let line = parse_line("USER alice 0 * :Alice Liddell").unwrap();
+        assert_eq!(line.params, vec!["alice", "0", "*", "Alice Liddell"]);
+
+        assert_eq!(parse_line("PING").unwrap().params, Vec::<String>::new());
+        assert!(parse_line("\r\n").is_none());
+    }
+
+    #[test]
+    fn channel_names_round_trip() {
+        let id = Uuid::new_v4();
+        assert_eq!(parse_channel_name(&channel_name(id)), Some(id));
+        assert_eq!(parse_channel_name("#general"), None);
+    }
+
+    #[test]
+    fn text_bodies_round_trip() {
+        let sender = Uuid::new_v4();
+        let body = encode_text("hi \"there\"", sender);
+        assert_eq!(body[0], 0);
+        assert_eq!(decode_text(&body), Some("hi \"there\"".to_string()));
+        assert_eq!(decode_text(b"\x01ciphertext"), None);
+    }
+}
@@ -0,0 +1,290 @@
+//! Persistent background jobs.
+//!
+//! Work that should outlive the request that started it is queued in
+//! `background_jobs` instead of being `tokio::spawn`ed, so a restart doesn't
+//! lose it. Each instance runs a runner that claims due jobs under a lease:
+//! if the instance dies mid-job, another picks the job up once the lease runs
+//! out. A failed attempt is retried with backoff until `max_attempts`, unless
+//! the error is one retrying can't fix. Every kind has a per-instance
+//! concurrency limit and a timeout. Operators can list, schedule and cancel
+//! jobs at `/admin/jobs`.
+//!
+//! Kinds:
+//! - `maintenance`: run a [`maintenance::Job`], payload `{"job": "<name>"}`.
+//! - `announcement-dms`: DM a published announcement to every user, payload
+//!   `{"announcement_id": "<id>", "after": "<user id>" | null}`. `after` is
+//!   checkpointed per batch so a retry resumes where the last attempt stopped.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde_json::json;
+use tokio::sync::{Notify, Semaphore};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::maintenance;
+use crate::models::BackgroundJob;
+use crate::AppState;
+
+pub const KIND_MAINTENANCE: &str = "maintenance";
+pub const KIND_ANNOUNCEMENT_DMS: &str = "announcement-dms";
+
+/// Attempts before a job that keeps failing is marked failed.
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+/// How often the runner looks for due jobs when nothing wakes it.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Lease beyond a kind's timeout, so a live runner always records the outcome
+/// before another instance may claim the job.
+const LEASE_MARGIN: Duration = Duration::from_secs(60);
+/// Finished jobs are kept this long for `/admin/jobs`.
+pub const FINISHED_RETENTION_DAYS: u32 = 7;
+
+struct Kind {
+    name: &'static str,
+    /// Jobs of this kind one instance runs at once.
+    concurrency: usize,
+    /// An attempt running longer than this fails (and is retried).
+    timeout: Duration,
+}
+
+static KINDS: [Kind; 2] = [
+    Kind { name: KIND_MAINTENANCE, concurrency: 2, timeout: Duration::from_secs(15 * 60) },
+    Kind { name: KIND_ANNOUNCEMENT_DMS, concurrency: 1, timeout: Duration::from_secs(30 * 60) },
+];
+
+impl Kind {
+    fn lease_secs(&self) -> i64 {
+        (self.timeout + LEASE_MARGIN).as_secs() as i64
+    }
+}
+
+/// Wakes this instance's runner when a job is queued, so it starts now
+/// rather than on the next poll.
+#[derive(Clone, Default)]
+pub struct JobQueue {
+    wake: Arc<Notify>,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// A job to queue; see the module docs for the kinds.
+pub struct NewJob {
+    kind: &'static str,
+    payload: serde_json::Value,
+    run_at: Option<DateTime<Utc>>,
+    dedupe_key: Option<String>,
+    created_by: Option<Uuid>,
+}
+
+impl NewJob {
+    /// Run a maintenance job. Queuing one that is already waiting to run
+    /// returns the waiting job instead of adding another.
+    pub fn maintenance(job: maintenance::Job) -> Self {
+        Self {
+            kind: KIND_MAINTENANCE,
+            payload: json!({ "job": job.as_str() }),
+            run_at: None,
+            dedupe_key: Some(format!("{}:{}", KIND_MAINTENANCE, job.as_str())),
+            created_by: None,
+        }
+    }
+
+    pub fn announcement_dms(announcement_id: Uuid) -> Self {
+        Self {
+            kind: KIND_ANNOUNCEMENT_DMS,
+            payload: json!({ "announcement_id": announcement_id, "after": null }),
+            run_at: None,
+            dedupe_key: Some(format!("{}:{}", KIND_ANNOUNCEMENT_DMS, announcement_id)),
+            created_by: None,
+        }
+    }
+
+    /// Run no sooner than `run_at` instead of now.
+    pub fn at(mut self, run_at: DateTime<Utc>) -> Self {
+        self.run_at = Some(run_at);
+        self
+    }
+
+    pub fn created_by(mut self, user_id: Uuid) -> Self {
+        self.created_by = Some(user_id);
+        self
+    }
+}
+
+/// Persist a job and wake the runner if it is due now.
+pub async fn enqueue(state: &AppState, job: NewJob) -> AppResult<BackgroundJob> {
+    let now = Utc::now();
+    let queued = queries::enqueue_job(
+        state.db.write(),
+        Uuid::new_v4(),
+        job.kind,
+        &job.payload,
+        job.run_at.unwrap_or(now),
+        DEFAULT_MAX_ATTEMPTS,
+        job.dedupe_key.as_deref(),
+        job.created_by,
+    )
+    .await?;
+    if queued.run_at <= now {
+        state.jobs.wake();
+    }
+    Ok(queued)
+}
+
+/// Run due jobs on this instance for the rest of the process.
+pub fn spawn_runner(state: AppState) {
+    tokio::spawn(async move {
+        let limits: Vec<Arc<Semaphore>> = KINDS.iter().map(|kind| Arc::new(Semaphore::new(kind.concurrency))).collect();
+        loop {
+            if let Err(e) = dispatch(&state, &limits).await {
+                tracing::error!("Background job dispatch failed: {}", e);
+            }
+            tokio::select! {
+                _ = state.jobs.wake.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Claim as many due jobs of each kind as it has free slots and start them.
+async fn dispatch(state: &AppState, limits: &[Arc<Semaphore>]) -> AppResult<()> {
+    let pool = state.db.primary();
+    let abandoned = queries::fail_abandoned_jobs(pool).await?;
+    if abandoned > 0 {
+        tracing::warn!("{} background jobs failed after their worker stopped", abandoned);
+    }
+    for (kind, limit) in KINDS.iter().zip(limits) {
+        let free = limit.available_permits();
+        if free == 0 {
+            continue;
+        }
+        for job in queries::claim_jobs(pool, kind.name, free as i64, kind.lease_secs()).await? {
+            let Ok(permit) = limit.clone().try_acquire_owned() else {
+                break;
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                run_one(&state, kind, job).await;
+                drop(permit);
+                state.jobs.wake();
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Make one attempt at a claimed job and record how it went.
+async fn run_one(state: &AppState, kind: &Kind, job: BackgroundJob) {
+    let pool = state.db.primary();
+    let outcome = match tokio::time::timeout(kind.timeout, execute(state, &job)).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(AppError::Internal(anyhow::anyhow!("Timed out after {}s", kind.timeout.as_secs()))),
+    };
+    let recorded = match outcome {
+        Ok(count) => {
+            tracing::debug!("Background job {} ({}) completed: {}", job.id, job.kind, count);
+            queries::complete_job(pool, job.id).await
+        }
+        Err(e) if is_permanent(&e) || job.attempts >= job.max_attempts => {
+            tracing::error!("Background job {} ({}) failed after {} attempts: {}", job.id, job.kind, job.attempts, e);
+            queries::fail_job(pool, job.id, &describe(&e)).await
+        }
+        Err(e) => {
+            let delay = backoff(job.attempts as u32);
+            tracing::warn!(
+                "Background job {} ({}) attempt {} failed, retrying in {}s: {}",
+                job.id, job.kind, job.attempts, delay.as_secs(), e
+            );
+            queries::retry_job(pool, job.id, &describe(&e), delay.as_secs() as i64).await
+        }
+    };
+    if let Err(e) = recorded {
+        tracing::error!("Failed to record the outcome of background job {}: {}", job.id, e);
+    }
+}
+
+async fn execute(state: &AppState, job: &BackgroundJob) -> AppResult<u64> {
+    match job.kind.as_str() {
+        KIND_MAINTENANCE => {
+            let name = job.payload["job"].as_str().unwrap_or_default();
+            let task = maintenance::Job::parse(name)
+                .ok_or_else(|| AppError::Validation(format!("Unknown maintenance job '{}'", name)))?;
+            maintenance::run(state, task).await
+        }
+        KIND_ANNOUNCEMENT_DMS => crate::api::announcements::send_dms(state, job).await,
+        other => Err(AppError::Validation(format!("Unknown job kind '{}'", other))),
+    }
+}
+
+/// Errors another attempt won't fix: bad payloads, deleted targets, a
+/// disabled setting. Everything else (database, network) is retried.
+fn is_permanent(error: &AppError) -> bool {
+    match error {
+        AppError::Validation(_)
+        | AppError::InvalidFields(_)
+        | AppError::BadRequest(_)
+        | AppError::NotFound(_)
+        | AppError::Forbidden(_)
+        | AppError::Conflict(_) => true,
+        AppError::Coded { source, .. } => is_permanent(source),
+        _ => false,
+    }
+}
+
+/// The error as stored in `last_error`, with its cause chain.
+fn describe(error: &AppError) -> String {
+    match error {
+        AppError::Internal(e) => format!("{:#}", e),
+        e => e.to_string(),
+    }
+}
+
+/// Wait after failed attempt `attempt`: 30s, 1m, 2m, ... capped at an hour,
+/// plus up to 5s of jitter so a backlog doesn't retry in lockstep.
+fn backoff(attempt: u32) -> Duration {
+    let secs = (30u64 << attempt.saturating_sub(1).min(7)).min(3600);
+    Duration::from_secs(secs) + Duration::from_millis(rand::thread_rng().gen_range(0..5000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        for (attempt, base) in [(1, 30), (2, 60), (3, 120), (8, 3600), (20, 3600)] {
+            let wait = backoff(attempt);
+            let base = Duration::from_secs(base);
+            assert!(wait >= base && wait < base + Duration::from_secs(5), "attempt {}", attempt);
+        }
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        assert!(is_permanent(&AppError::NotFound("Announcement".into())));
+        assert!(is_permanent(&AppError::BadRequest("Audit log retention is disabled".into())));
+        assert!(is_permanent(&AppError::Validation("bad".into()).with_code("BAD_PAYLOAD")));
+        assert!(!is_permanent(&AppError::Internal(anyhow::anyhow!("connection reset"))));
+    }
+
+    #[test]
+    fn maintenance_jobs_dedupe_by_name() {
+        let job = NewJob::maintenance(maintenance::Job::EmailOutbox);
+        assert_eq!(job.kind, KIND_MAINTENANCE);
+        assert_eq!(job.payload["job"], "email-outbox");
+        assert_eq!(job.dedupe_key.as_deref(), Some("maintenance:email-outbox"));
+    }
+}
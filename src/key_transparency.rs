@@ -0,0 +1,227 @@
+//! Key transparency log.
+//!
+//! Every identity key a user publishes is appended to an append-only log
+//! (`key_transparency_log`), hashed into a Merkle tree following RFC 6962.
+//! Clients fetch an inclusion proof for a contact's key alongside the current
+//! tree head and remember the head; a later consistency proof shows the old
+//! tree is a prefix of the new one. A server that silently swaps a key has to
+//! either log the swap (which the owner's client can see) or fork the log
+//! (which consistency checks expose).
+//!
+//! Leaf hash: SHA-256(0x00 || user_id (16 bytes) || identity_key)
+//! Node hash: SHA-256(0x01 || left || right)
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+pub type Hash = [u8; 32];
+
+/// Hash a log entry into a Merkle leaf.
+pub fn leaf_hash(user_id: Uuid, identity_key: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(user_id.as_bytes());
+    hasher.update(identity_key);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Largest power of two strictly less than `n` (n >= 2).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Merkle tree hash over `leaves`. The empty tree hashes to SHA-256("").
+pub fn root_hash(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest(b"").into(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&root_hash(&leaves[..k]), &root_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path proving `leaves[index]` is in the tree over `leaves`.
+pub fn inclusion_proof(leaves: &[Hash], index: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    if index < k {
+        let mut path = inclusion_proof(&leaves[..k], index);
+        path.push(root_hash(&leaves[k..]));
+        path
+    } else {
+        let mut path = inclusion_proof(&leaves[k..], index - k);
+        path.push(root_hash(&leaves[..k]));
+        path
+    }
+}
+
+/// Proof that the tree over the first `old_size` leaves is a prefix of the
+/// tree over `leaves`. Empty when `old_size` is 0 or equals the full size.
+pub fn consistency_proof(leaves: &[Hash], old_size: usize) -> Vec<Hash> {
+    if old_size == 0 || old_size >= leaves.len() {
+        return Vec::new();
+    }
+    subproof(old_size, leaves, true)
+}
+
+fn subproof(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![root_hash(leaves)] };
+    }
+    let k = split_point(n);
+    if m <= k {
+        let mut proof = subproof(m, &leaves[..k], complete);
+        proof.push(root_hash(&leaves[k..]));
+        proof
+    } else {
+        let mut proof = subproof(m - k, &leaves[k..], false);
+        proof.push(root_hash(&leaves[..k]));
+        proof
+    }
+}
+
+/// Check an audit path the way a client would (RFC 9162 §2.1.3.2).
+pub fn verify_inclusion(leaf: &Hash, index: usize, tree_size: usize, path: &[Hash], root: &Hash) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+    let (mut fnode, mut snode) = (index, tree_size - 1);
+    let mut r = *leaf;
+    for p in path {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            r = node_hash(p, &r);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    snode == 0 && r == *root
+}
+
+/// Check a consistency proof between two tree heads (RFC 9162 §2.1.4.2).
+pub fn verify_consistency(
+    old_size: usize,
+    new_size: usize,
+    old_root: &Hash,
+    new_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if old_size == 0 || old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && old_root == new_root;
+    }
+    let mut path: Vec<Hash> = Vec::with_capacity(proof.len() + 1);
+    if old_size.is_power_of_two() {
+        path.push(*old_root);
+    }
+    path.extend_from_slice(proof);
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+
+    let (mut fnode, mut snode) = (old_size - 1, new_size - 1);
+    while fnode & 1 == 1 {
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    let (mut fr, mut sr) = (*first, *first);
+    for c in rest {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    fr == *old_root && sr == *new_root && snode == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n).map(|i| leaf_hash(Uuid::from_u128(i as u128), &[i as u8; 32])).collect()
+    }
+
+    #[test]
+    fn inclusion_proofs_verify_for_every_leaf() {
+        for n in 1..=17 {
+            let tree = leaves(n);
+            let root = root_hash(&tree);
+            for (i, leaf) in tree.iter().enumerate() {
+                let path = inclusion_proof(&tree, i);
+                assert!(verify_inclusion(leaf, i, n, &path, &root), "n={n} i={i}");
+            }
+        }
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_swapped_key() {
+        let tree = leaves(5);
+        let root = root_hash(&tree);
+        let path = inclusion_proof(&tree, 2);
+        let forged = leaf_hash(Uuid::from_u128(2), &[0xff; 32]);
+        assert!(!verify_inclusion(&forged, 2, 5, &path, &root));
+    }
+
+    #[test]
+    fn consistency_proofs_verify_for_every_prefix() {
+        for n in 1..=17 {
+            let tree = leaves(n);
+            let new_root = root_hash(&tree);
+            for m in 1..=n {
+                let old_root = root_hash(&tree[..m]);
+                let proof = consistency_proof(&tree, m);
+                assert!(verify_consistency(m, n, &old_root, &new_root, &proof), "m={m} n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_proof_rejects_rewritten_history() {
+        let tree = leaves(8);
+        let mut forked = tree.clone();
+        forked[1] = leaf_hash(Uuid::from_u128(1), &[0xee; 32]);
+        let old_root = root_hash(&tree[..3]);
+        let proof = consistency_proof(&forked, 3);
+        assert!(!verify_consistency(3, 8, &old_root, &root_hash(&forked), &proof));
+    }
+}
@@ -2,31 +2,62 @@
 // The binary crate (main.rs) uses these modules directly via `mod`.
 // Integration tests in tests/ import them from this lib crate.
 
+pub mod abuse;
 pub mod api;
+pub mod api_version;
+pub mod attachment_gc;
 pub mod auth;
 pub mod cache;
 pub mod config;
 pub mod crypto;
 pub mod db;
+pub mod email;
 pub mod errors;
+pub mod etag;
+pub mod export_format;
+pub mod federation;
+pub mod insights;
+pub mod jobs;
+pub mod key_transparency;
 pub mod memory_store;
 pub mod middleware;
 pub mod models;
+pub mod notification_rules;
+pub mod openapi;
 pub mod permissions;
+pub mod profile_media;
 pub mod pubsub;
+pub mod quiet_hours;
+pub mod quota;
+pub mod ranges;
+pub mod restore_jobs;
+pub mod restore_sections;
+pub mod retention;
+pub mod scanning;
+pub mod shutdown;
+pub mod signed_urls;
 pub mod storage;
+pub mod thumbnails;
 pub mod tls;
+pub mod uploads;
+pub mod usage_stats;
 pub mod livekit_proc;
+pub mod maintenance;
+pub mod voice;
 pub mod ws;
+pub mod ws_codec;
+pub mod ws_outbox;
 #[cfg(feature = "embed-ui")]
 pub mod embedded_ui;
+#[cfg(feature = "irc")]
+pub mod irc;
 
 use axum::{
     extract::DefaultBodyLimit,
     http::{header, HeaderValue, Method},
     middleware as axum_mw,
-    routing::{delete, get, post, put},
-    Router,
+    routing::{delete, get, head, post, put},
+    Extension, Router,
 };
 use tower_http::{
     compression::CompressionLayer,
@@ -34,10 +65,16 @@ use tower_http::{
     set_header::SetResponseHeaderLayer,
     trace::{DefaultOnResponse, TraceLayer},
 };
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
-use middleware::{rate_limit_middleware, RateLimiter, UserRateLimiter};
+use middleware::{
+    db_breaker_middleware, json_guard_middleware, latency_budget_middleware, policy_rate_limit_middleware,
+    rate_limit_middleware, trace_id_middleware, LatencyBudgets, RatePolicies, RateLimiter, UserRateLimiter,
+};
 
-use config::AppConfig;
+use api_version::ApiVersion;
+use config::{AppConfig, LiveConfig};
 use ws::{ChannelBroadcastMap, ConnectionMap};
 
 // ─── Application State ─────────────────────────────────
@@ -47,11 +84,20 @@ pub struct AppState {
     pub db: db::DbPools,
     pub redis: Option<redis::aio::ConnectionManager>,
     pub config: AppConfig,
+    /// Config as of the last reload; read reloadable settings from here
+    pub live_config: LiveConfig,
     pub storage_key: [u8; 32],
     pub storage: storage::Storage,
+    /// Destination of retention archives; None without BACKUP_STORAGE_DIR / BACKUP_S3_BUCKET
+    pub backup_storage: Option<storage::Storage>,
+    /// Built-in email templates with EMAIL_TEMPLATE_DIR overrides
+    pub email_templates: email::EmailTemplates,
+    /// IP ranges from ABUSE_IP_LISTS, refreshed by the abuse-lists job
+    pub abuse_lists: abuse::IpLists,
     pub connections: ConnectionMap,
     pub channel_broadcasts: ChannelBroadcastMap,
-    pub pubsub_subscriptions: pubsub::PubSubSubscriptions,
+    /// Cross-instance WS fan-out (Redis pub/sub or local-only)
+    pub pubsub: pubsub::PubSub,
     pub memory: memory_store::MemoryStore,
     /// Per-user rate limiter for WebSocket message sending (30 msg / 10s)
     pub ws_rate_limiter: UserRateLimiter,
@@ -61,6 +107,59 @@ pub struct AppState {
     pub sessions: ws::SessionMap,
     /// In-memory cache for instance ban status (avoids DB query per request)
     pub ban_cache: cache::BanCache,
+    /// Per-route handler timeouts and budget violation counts
+    pub latency_budgets: LatencyBudgets,
+    /// Per-user and per-route token buckets (RATE_LIMIT_PER_USER / RATE_LIMIT_ROUTES)
+    pub rate_policies: RatePolicies,
+    /// Sampled shadow execution of rewritten hot queries
+    pub shadow_reads: db::shadow::ShadowReads,
+    /// Primary pool acquire latency, in-flight requests and the load-shedding breaker
+    pub db_pool: db::pool_monitor::PoolMonitor,
+    /// Wakes the background job runner when a job is queued
+    pub jobs: jobs::JobQueue,
+    /// Wakes the WS outbox dispatcher when staged events commit
+    pub ws_outbox: ws_outbox::WsOutbox,
+    /// Heartbeat, missed-heartbeat and reaped-connection counts across WS connections
+    pub ws_heartbeats: ws::HeartbeatStats,
+    /// Set on SIGTERM; WebSocket connections hand off to other instances while draining
+    pub shutdown: shutdown::Shutdown,
+}
+
+impl AppState {
+    /// Apply the reloadable settings of a freshly loaded config (SIGHUP or
+    /// `POST /admin/config/reload`). Returns the settings that changed.
+    pub fn reload_config(&self, fresh: &AppConfig) -> Vec<&'static str> {
+        let changed = self.live_config.apply(fresh);
+        if changed.iter().any(|field| field.starts_with("rate_limit_")) {
+            self.rate_policies.reload(&self.live_config.get());
+        }
+        // A higher cap or a newly configured email provider can let waitlisted emails in
+        if changed
+            .iter()
+            .any(|field| *field == "beta_code_limit" || field.starts_with("smtp_") || field.starts_with("email_"))
+        {
+            let state = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = api::beta::promote_waitlist(&state).await {
+                    tracing::error!("Beta waitlist promotion failed: {}", e);
+                }
+            });
+        }
+        if changed.contains(&"abuse_ip_lists") {
+            let state = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = abuse::refresh_lists(&state).await {
+                    tracing::error!("Abuse IP list refresh failed: {}", e);
+                }
+            });
+        }
+        if changed.is_empty() {
+            tracing::info!("Config reloaded, nothing changed");
+        } else {
+            tracing::info!("Config reloaded: {} changed", changed.join(", "));
+        }
+        changed
+    }
 }
 
 // ─── Router ────────────────────────────────────────────
@@ -80,7 +179,7 @@ pub fn build_router(state: AppState) -> Router {
                 Method::DELETE,
                 Method::OPTIONS,
             ])
-            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, middleware::trace_id::REQUEST_ID_HEADER])
     } else {
         // Production: whitelist specific origins
         let origins: Vec<HeaderValue> = state
@@ -99,14 +198,15 @@ pub fn build_router(state: AppState) -> Router {
                 Method::DELETE,
                 Method::OPTIONS,
             ])
-            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, middleware::trace_id::REQUEST_ID_HEADER])
     };
 
     // ─── Rate Limiting ─────────────────────────────────
-    // Global: per-IP, based on config max_requests_per_minute
+    // Global: per-IP, based on config max_requests_per_minute (reloadable)
     let mut global_limiter = RateLimiter::new(state.config.max_requests_per_minute, 60);
     global_limiter.trust_proxy = state.config.trust_proxy;
     middleware::spawn_rate_limit_cleanup(global_limiter.clone());
+    let live_config = state.live_config.clone();
 
     // Stricter limit for auth endpoints (10 req/min per IP to resist brute-force)
     let mut auth_limiter = RateLimiter::new(10, 60);
@@ -134,6 +234,8 @@ pub fn build_router(state: AppState) -> Router {
         .route("/totp/setup", post(api::auth_routes::totp_setup))
         .route("/totp/verify", post(api::auth_routes::totp_verify))
         .route("/totp", delete(api::auth_routes::totp_disable))
+        .route("/email/verify", post(api::auth_routes::send_email_verification))
+        .route("/email/confirm", post(api::auth_routes::confirm_email_verification))
         .route("/delete-account", post(api::auth_routes::delete_account));
 
     // Key management routes
@@ -141,29 +243,100 @@ pub fn build_router(state: AppState) -> Router {
         .route("/identity", put(api::keys::update_identity_keys))
         .route("/prekeys", post(api::keys::upload_prekeys).delete(api::keys::delete_prekeys))
         .route("/prekeys/count", get(api::keys::prekey_count))
+        .route("/signed-prekey", put(api::keys::update_signed_prekey))
+        .route(
+            "/devices",
+            post(api::devices::register_device).get(api::devices::list_own_devices),
+        )
+        .route("/devices/:device_id", delete(api::devices::remove_device))
+        .route(
+            "/devices/:device_id/verification",
+            put(api::devices::set_device_verification),
+        )
         .route(
             "/backup",
             put(api::key_backup::upload_key_backup)
                 .get(api::key_backup::get_key_backup)
                 .delete(api::key_backup::delete_key_backup),
         )
-        .route("/backup/status", get(api::key_backup::get_key_backup_status));
+        .route("/backup/status", get(api::key_backup::get_key_backup_status))
+        .route(
+            "/backup/versions",
+            post(api::key_backup::create_backup_version).get(api::key_backup::get_current_backup_version),
+        )
+        .route(
+            "/backup/versions/:version",
+            get(api::key_backup::get_backup_version).delete(api::key_backup::delete_backup_version),
+        )
+        .route(
+            "/backup/versions/:version/sessions",
+            put(api::key_backup::upload_backup_sessions)
+                .get(api::key_backup::get_backup_sessions)
+                .delete(api::key_backup::delete_backup_sessions),
+        );
+
+    // Key transparency log (Merkle proofs over published identity keys)
+    let key_transparency_routes = Router::new()
+        .route("/head", get(api::key_transparency::get_head))
+        .route("/proof/:user_id", get(api::key_transparency::get_proof))
+        .route("/consistency", get(api::key_transparency::get_consistency));
 
     // User routes
     let user_routes = Router::new()
         .route("/:user_id/keys", get(api::keys::get_key_bundle))
+        .route("/:user_id/prekey-bundle", get(api::keys::get_prekey_bundle))
+        .route("/:user_id/devices", get(api::devices::list_user_devices))
         .route("/:user_id/profile", get(api::users::get_profile))
         .route("/:user_id/avatar", get(api::users::get_avatar))
+        .route("/:user_id/avatar/:hash", get(api::users::get_avatar_version))
         .route("/:user_id/banner", get(api::users::get_banner))
+        .route("/:user_id/banner/:hash", get(api::users::get_banner_version))
         .route(
             "/:user_id/block",
             post(api::users::block_user).delete(api::users::unblock_user),
         )
         .route("/search", get(api::users::get_user_by_username))
         .route("/profile", put(api::users::update_profile))
-        .route("/avatar", post(api::users::upload_avatar))
-        .route("/banner", post(api::users::upload_banner))
+        .route(
+            "/me/avatar",
+            put(api::users::upload_avatar)
+                .delete(api::users::delete_avatar)
+                .layer(DefaultBodyLimit::max(profile_media::MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/me/banner",
+            put(api::users::upload_banner)
+                .delete(api::users::delete_banner)
+                .layer(DefaultBodyLimit::max(profile_media::MAX_UPLOAD_SIZE)),
+        )
+        .route("/me/media-usage", get(api::users::get_media_usage))
+        .route("/me/devices/:device_id/queue", get(api::devices::drain_device_queue))
+        .route("/me/relationships", get(api::friends::list_relationships))
+        .route(
+            "/me/notification-rules",
+            get(api::notification_rules::list_rules).post(api::notification_rules::create_rule),
+        )
+        .route(
+            "/me/notification-rules/:rule_id",
+            axum::routing::patch(api::notification_rules::update_rule).delete(api::notification_rules::delete_rule),
+        )
+        .route("/me/applications", get(api::screening::list_my_applications))
+        .route(
+            "/me/quiet-hours",
+            get(api::quiet_hours::get_quiet_hours)
+                .put(api::quiet_hours::set_quiet_hours)
+                .delete(api::quiet_hours::delete_quiet_hours),
+        )
+        .route(
+            "/avatar",
+            post(api::users::upload_avatar).layer(DefaultBodyLimit::max(profile_media::MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/banner",
+            post(api::users::upload_banner).layer(DefaultBodyLimit::max(profile_media::MAX_UPLOAD_SIZE)),
+        )
         .route("/blocked", get(api::users::get_blocked_users))
+        .route("/support-access", get(api::users::get_support_access_log))
         .route("/profile-keys", put(api::users::distribute_profile_keys))
         .route("/:user_id/profile-key", get(api::users::get_profile_key));
 
@@ -171,7 +344,9 @@ pub fn build_router(state: AppState) -> Router {
     let server_routes = Router::new()
         .route("/", get(api::servers::list_servers))
         .route("/", post(api::servers::create_server))
+        .route("/deleted", get(api::servers::list_deleted_servers))
         .route("/:server_id", get(api::servers::get_server).patch(api::servers::update_server).delete(api::servers::delete_server))
+        .route("/:server_id/undelete", post(api::servers::undelete_server))
         .route(
             "/:server_id/channels",
             get(api::servers::list_server_channels),
@@ -184,6 +359,10 @@ pub fn build_router(state: AppState) -> Router {
             "/:server_id/channels/reorder",
             put(api::channels::reorder_channels),
         )
+        .route(
+            "/:server_id/channels/positions",
+            axum::routing::patch(api::channels::update_channel_positions),
+        )
         .route(
             "/:server_id/categories",
             get(api::categories::list_categories)
@@ -198,6 +377,51 @@ pub fn build_router(state: AppState) -> Router {
             put(api::categories::update_category)
                 .delete(api::categories::delete_category),
         )
+        .route(
+            "/:server_id/events",
+            get(api::events::list_events).post(api::events::create_event),
+        )
+        .route(
+            "/:server_id/events/:event_id",
+            axum::routing::patch(api::events::update_event).delete(api::events::delete_event),
+        )
+        .route(
+            "/:server_id/events/:event_id/rsvp",
+            put(api::events::set_rsvp).delete(api::events::remove_rsvp),
+        )
+        .route(
+            "/:server_id/screening",
+            get(api::screening::get_screening).put(api::screening::set_screening),
+        )
+        .route("/:server_id/applications", get(api::screening::list_applications))
+        .route(
+            "/:server_id/applications/:application_id/approve",
+            post(api::screening::approve_application),
+        )
+        .route(
+            "/:server_id/applications/:application_id/deny",
+            post(api::screening::deny_application),
+        )
+        .route(
+            "/:server_id/onboarding",
+            get(api::onboarding::get_onboarding).put(api::onboarding::set_onboarding),
+        )
+        .route("/:server_id/onboarding/complete", post(api::onboarding::complete_onboarding))
+        .route(
+            "/:server_id/verification",
+            get(api::verification::get_verification).put(api::verification::set_verification),
+        )
+        .route("/:server_id/insights", get(api::insights::get_server_insights))
+        .route("/:server_id/upload-limits", get(api::upload_limits::list_upload_limits))
+        .route(
+            "/:server_id/upload-limits/roles/:role_id",
+            put(api::upload_limits::set_role_upload_limit).delete(api::upload_limits::delete_role_upload_limit),
+        )
+        .route(
+            "/:server_id/upload-limits/channels/:channel_id",
+            put(api::upload_limits::set_channel_upload_limit)
+                .delete(api::upload_limits::delete_channel_upload_limit),
+        )
         .route(
             "/:server_id/invites",
             get(api::invites::list_invites),
@@ -222,18 +446,42 @@ pub fn build_router(state: AppState) -> Router {
             "/:server_id/members",
             get(api::invites::list_members),
         )
+        .route(
+            "/:server_id/members/search",
+            get(api::invites::search_members),
+        )
         .route(
             "/:server_id/members/:user_id",
-            delete(api::invites::kick_member),
+            delete(api::invites::kick_member)
+                .patch(api::servers::update_member)
+                .layer(DefaultBodyLimit::max(profile_media::MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/:server_id/members/:user_id/avatar/:hash",
+            get(api::servers::get_member_avatar),
         )
         .route(
             "/:server_id/roles",
             get(api::roles::list_roles).post(api::roles::create_role),
         )
+        .route(
+            "/:server_id/roles/positions",
+            axum::routing::patch(api::roles::update_role_positions),
+        )
         .route(
             "/:server_id/roles/:role_id",
             put(api::roles::update_role).delete(api::roles::delete_role),
         )
+        .route(
+            "/:server_id/permission-templates",
+            get(api::permission_templates::list_permission_templates)
+                .post(api::permission_templates::create_permission_template),
+        )
+        .route(
+            "/:server_id/permission-templates/:template_id",
+            axum::routing::patch(api::permission_templates::update_permission_template)
+                .delete(api::permission_templates::delete_permission_template),
+        )
         .route(
             "/:server_id/members/:user_id/roles",
             put(api::roles::assign_role),
@@ -266,10 +514,20 @@ pub fn build_router(state: AppState) -> Router {
             "/:server_id/export",
             get(api::servers::export_server),
         )
+        .route("/:server_id/migrate", post(api::migration::export_migration))
+        .route(
+            "/:server_id/migrate/import",
+            post(api::migration::import_migration)
+                .layer(DefaultBodyLimit::max(middleware::body_limit::RESTORE_LIMIT)),
+        )
         .route(
             "/:server_id/restore",
-            post(api::exports::restore_server),
+            post(api::exports::restore_server)
+                .layer(DefaultBodyLimit::max(middleware::body_limit::RESTORE_LIMIT)),
         )
+        .route("/:server_id/restore/rollback", post(api::exports::rollback_restore))
+        .route("/:server_id/restore-jobs", get(api::exports::list_restore_jobs))
+        .route("/:server_id/restore-jobs/:job_id", get(api::exports::get_restore_job))
         .route(
             "/:server_id/audit-log",
             get(api::servers::get_audit_log),
@@ -307,10 +565,20 @@ pub fn build_router(state: AppState) -> Router {
     let channel_routes = Router::new()
         .route("/read-states", get(api::channels::get_read_states))
         .route("/:channel_id/read-state", put(api::channels::mark_channel_read))
-        .route("/:channel_id", put(api::channels::update_channel))
+        .route("/:channel_id", put(api::channels::update_channel).patch(api::channels::patch_channel))
         .route("/:channel_id", delete(api::channels::delete_channel))
         .route("/:channel_id/join", post(api::channels::join_channel))
         .route("/:channel_id/message-ttl", put(api::channels::set_message_ttl))
+        .route(
+            "/:channel_id/retention",
+            get(api::channels::get_retention).put(api::channels::set_retention),
+        )
+        .route(
+            "/:channel_id/mention-settings",
+            get(api::channels::get_mention_settings).put(api::channels::set_mention_settings),
+        )
+        .route("/:channel_id/calls", post(api::calls::start_call))
+        .route("/:channel_id/attachments", post(api::attachments::create_upload_session))
         .route("/:channel_id/category", put(api::categories::set_channel_category))
         .route(
             "/:channel_id/overwrites",
@@ -322,7 +590,8 @@ pub fn build_router(state: AppState) -> Router {
         )
         .route(
             "/:channel_id/import-messages",
-            post(api::exports::import_messages),
+            post(api::exports::import_messages)
+                .layer(DefaultBodyLimit::max(middleware::body_limit::IMPORT_MESSAGES_LIMIT)),
         )
         .route(
             "/:channel_id/messages",
@@ -332,6 +601,18 @@ pub fn build_router(state: AppState) -> Router {
             "/:channel_id/messages",
             post(api::messages::send_message),
         )
+        .route(
+            "/:channel_id/messages/:message_id/reactions/:emoji",
+            get(api::messages::get_reactors),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/reactions/:emoji/@me",
+            put(api::messages::add_own_reaction).delete(api::messages::remove_own_reaction),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/forward",
+            post(api::messages::forward_message),
+        )
         .route(
             "/:channel_id/messages/bulk-delete",
             post(api::messages::bulk_delete_messages),
@@ -339,7 +620,8 @@ pub fn build_router(state: AppState) -> Router {
         .route(
             "/:channel_id/sender-keys",
             get(api::sender_keys::get_sender_keys)
-                .post(api::sender_keys::distribute_sender_keys),
+                .post(api::sender_keys::distribute_sender_keys)
+                .put(api::sender_keys::rotate_sender_keys),
         )
         .route(
             "/:channel_id/members/keys",
@@ -349,11 +631,20 @@ pub fn build_router(state: AppState) -> Router {
             "/:channel_id/reactions",
             get(api::messages::get_channel_reactions),
         )
+        .route("/:channel_id/receipts", get(api::receipts::get_channel_receipts))
         .route(
             "/:channel_id/members",
             get(api::channels::list_channel_members)
                 .post(api::channels::add_group_member),
         )
+        .route(
+            "/:channel_id/members/:user_id",
+            delete(api::channels::remove_group_member),
+        )
+        .route(
+            "/:channel_id/owner",
+            put(api::channels::transfer_group_owner),
+        )
         .route(
             "/:channel_id/leave",
             delete(api::channels::leave_channel),
@@ -377,6 +668,11 @@ pub fn build_router(state: AppState) -> Router {
         .route(
             "/:channel_id/pin-ids",
             get(api::messages::get_pin_ids),
+        )
+        .route("/:channel_id/bridges", get(api::bridges::list_channel_bridges))
+        .route(
+            "/:channel_id/bridges/:bridge_id",
+            put(api::bridges::link_channel).delete(api::bridges::unlink_channel),
         );
 
     // Friend routes
@@ -395,14 +691,27 @@ pub fn build_router(state: AppState) -> Router {
         .route("/requests", get(api::friends::list_dm_requests))
         .route("/:channel_id/request", post(api::friends::handle_dm_request));
 
-    // Invite join route
+    // Invite join and screening routes
     let invite_routes = Router::new()
-        .route("/:code/join", post(api::invites::join_by_invite));
+        .route("/:code/join", post(api::invites::join_by_invite))
+        .route("/:code/screening", get(api::screening::get_invite_screening))
+        .route("/:code/apply", post(api::screening::apply));
 
     // Attachment routes
     let attachment_routes = Router::new()
         .route("/upload", post(api::attachments::upload))
+        .route(
+            "/uploads/:upload_id",
+            head(api::attachments::get_upload_offset)
+                .put(api::attachments::upload_chunk)
+                .delete(api::attachments::cancel_upload)
+                .layer(DefaultBodyLimit::max(uploads::MAX_CHUNK_SIZE as usize)),
+        )
+        .route("/uploads/:upload_id/finalize", post(api::attachments::finalize_upload))
         .route("/:attachment_id", get(api::attachments::download))
+        .route("/:attachment_id/thumbnail", get(api::attachments::download_thumbnail))
+        .route("/:attachment_id/signed-url", get(api::attachments::create_signed_url))
+        .route("/signed/*storage_key", get(api::attachments::download_signed))
         .layer(DefaultBodyLimit::max(state.config.max_upload_size_bytes as usize));
 
     // Link preview
@@ -415,7 +724,11 @@ pub fn build_router(state: AppState) -> Router {
 
     // DM privacy route
     let dm_privacy_routes = Router::new()
-        .route("/users/dm-privacy", put(api::friends::update_dm_privacy));
+        .route("/users/dm-privacy", put(api::friends::update_dm_privacy))
+        .route(
+            "/users/receipt-privacy",
+            get(api::receipts::get_receipt_privacy).put(api::receipts::update_receipt_privacy),
+        );
 
     // Report routes
     let report_routes = Router::new()
@@ -423,6 +736,7 @@ pub fn build_router(state: AppState) -> Router {
 
     // Voice routes
     let voice_routes = Router::new()
+        .route("/turn-credentials", get(api::calls::get_turn_credentials))
         .route("/:channel_id/join", post(api::voice::join_voice))
         .route("/:channel_id/leave", post(api::voice::leave_voice))
         .route(
@@ -479,11 +793,60 @@ pub fn build_router(state: AppState) -> Router {
     let registration_invite_routes = Router::new()
         .route("/", get(api::registration_invites::list_my_invites));
 
-    // Admin routes (requires instance admin)
+    // Admin routes (requires an instance staff role; capability checked per handler)
     let admin_routes = Router::new()
         .route("/stats", get(api::admin::get_stats))
+        .route("/usage", get(api::admin::get_usage))
+        .route("/latency-budgets", get(api::admin::get_latency_budgets))
+        .route("/shadow-reads", get(api::admin::get_shadow_reads))
+        .route("/ws-heartbeats", get(api::admin::get_ws_heartbeats))
+        .route("/db-replicas", get(api::admin::get_db_replicas))
+        .route("/db-pool", get(api::admin::get_db_pool))
+        .route("/branding", put(api::branding::update_branding))
+        .route(
+            "/branding/logo",
+            put(api::branding::upload_logo).delete(api::branding::delete_logo),
+        )
+        .route(
+            "/attachment-gc",
+            get(api::admin::get_attachment_gc).post(api::admin::run_attachment_gc),
+        )
         .route("/users", get(api::admin::list_users))
         .route("/users/:user_id/admin", put(api::admin::set_admin))
+        .route("/users/:user_id/staff-role", put(api::admin::set_staff_role))
+        .route("/users/:user_id/support", get(api::admin::get_support_view))
+        .route("/users/:user_id/disconnect", post(api::admin::disconnect_user))
+        .route("/staff", get(api::admin::list_staff))
+        .route("/audit-log", get(api::admin::get_instance_audit_log))
+        .route("/servers", get(api::admin::list_servers))
+        .route("/servers/:server_id/upload-tier", put(api::admin::set_server_upload_tier))
+        .route(
+            "/servers/:server_id/quotas",
+            get(api::admin::get_server_quotas).put(api::admin::set_server_quotas),
+        )
+        .route("/beta-stats", get(api::admin::get_beta_stats))
+        .route("/beta/codes", get(api::beta::list_codes).post(api::beta::generate_codes))
+        .route("/beta/codes/:invite_id/revoke", post(api::beta::revoke_code))
+        .route("/beta/limit", put(api::beta::set_code_limit))
+        .route("/maintenance/:job", post(api::admin::run_maintenance_job))
+        .route("/jobs", get(api::admin::list_jobs).post(api::admin::schedule_job))
+        .route("/jobs/:job_id", get(api::admin::get_job))
+        .route("/jobs/:job_id/cancel", post(api::admin::cancel_job))
+        .route("/config/reload", post(api::admin::reload_config))
+        .route("/registrations/pending", get(api::admin::list_pending_registrations))
+        .route("/registrations/:user_id/approve", post(api::admin::approve_registration))
+        .route("/registrations/:user_id/reject", post(api::admin::reject_registration))
+        .route(
+            "/legal-holds",
+            get(api::admin::list_legal_holds).post(api::admin::place_legal_hold),
+        )
+        .route("/legal-holds/:hold_id/release", post(api::admin::release_legal_hold))
+        .route("/abuse/events", get(api::admin::list_abuse_events))
+        .route("/email/queue", get(api::admin::list_email_queue))
+        .route("/email/dead-letters", get(api::admin::list_email_dead_letters))
+        .route("/email/dead-letters/:letter_id", delete(api::admin::delete_email_dead_letter))
+        .route("/email/dead-letters/:letter_id/retry", post(api::admin::retry_email_dead_letter))
+        .route("/partitions", get(api::admin::get_partitions))
         .route("/users/:user_id", delete(api::admin::delete_user))
         .route(
             "/registration-invites",
@@ -512,6 +875,19 @@ pub fn build_router(state: AppState) -> Router {
         .route(
             "/blocked-hashes/:hash_id",
             delete(api::admin::delete_blocked_hash),
+        )
+        .route(
+            "/bridges",
+            get(api::bridges::list_bridges).post(api::bridges::create_bridge),
+        )
+        .route("/bridges/:bridge_id", delete(api::bridges::delete_bridge))
+        .route(
+            "/announcements",
+            get(api::announcements::list_announcements).post(api::announcements::create_announcement),
+        )
+        .route(
+            "/announcements/:announcement_id",
+            delete(api::announcements::delete_announcement),
         );
 
     // Beta code request (public, strict rate limit: 3 req/min per IP)
@@ -531,7 +907,33 @@ pub fn build_router(state: AppState) -> Router {
         .route("/trending", get(api::gifs::trending_gifs));
 
     let message_routes = Router::new()
-        .route("/:message_id/reactions", get(api::messages::get_message_reactions));
+        .route("/:message_id/reactions", get(api::messages::get_message_reactions))
+        .route("/:message_id/replies", get(api::messages::get_message_replies))
+        .route("/:message_id/live-location", delete(api::locations::stop_live_location));
+
+    // Instance branding (public, consumed by clients before login)
+    let instance_routes = Router::new()
+        .route("/branding", get(api::branding::get_branding))
+        .route("/branding/logo", get(api::branding::get_logo));
+
+    // Federation (server-to-server requests are signature-authenticated)
+    let federation_routes = Router::new()
+        .route("/key", get(api::federation::get_server_key))
+        .route("/users/:username", get(api::federation::get_user_profile))
+        .route("/transactions/:txn_id", put(api::federation::receive_transaction))
+        .route("/resolve", post(api::federation::resolve_user));
+
+    // Bridge API (authenticated with a bridge token, not a user JWT)
+    let bridge_routes = Router::new()
+        .route("/whoami", get(api::bridges::whoami))
+        .route("/puppets", put(api::bridges::upsert_puppet))
+        .route("/messages", post(api::bridges::send_message))
+        .route("/events", get(api::bridges::get_events));
+
+    // Instance announcements (published by operators, dismissed per user)
+    let announcement_routes = Router::new()
+        .route("/", get(api::announcements::get_announcements))
+        .route("/:announcement_id/dismiss", post(api::announcements::dismiss_announcement));
 
     // Export routes
     let export_routes = Router::new()
@@ -543,6 +945,7 @@ pub fn build_router(state: AppState) -> Router {
         .nest("/auth", auth_routes.merge(auth_protected))
         .nest("/admin", admin_routes)
         .nest("/keys", key_routes)
+        .nest("/key-transparency", key_transparency_routes)
         .nest("/users", user_routes)
         .nest("/servers", server_routes)
         .nest("/channels", channel_routes)
@@ -559,30 +962,82 @@ pub fn build_router(state: AppState) -> Router {
         .nest("/gifs", gif_routes)
         .nest("/beta", beta_routes)
         .nest("/registration-invites", registration_invite_routes)
-        .nest("/exports", export_routes);
+        .nest("/exports", export_routes)
+        .nest("/federation", federation_routes)
+        .nest("/bridge", bridge_routes)
+        .nest("/instance", instance_routes)
+        .nest("/announcements", announcement_routes)
+        .route("/sync", get(api::sync::get_sync))
+        // JSON size/depth guard: innermost, so rate-limited requests are never buffered
+        .route_layer(axum_mw::from_fn(json_guard_middleware))
+        // Latency budgets: route_layer so the matched route template is known
+        .route_layer(axum_mw::from_fn_with_state(
+            state.latency_budgets.clone(),
+            latency_budget_middleware,
+        ))
+        // Per-user / per-route token buckets, outside the latency budget
+        .route_layer(axum_mw::from_fn_with_state(
+            state.clone(),
+            policy_rate_limit_middleware,
+        ))
+        // Shed load with 503s while the database pool is saturated, outermost
+        // so shed requests don't spend rate limit tokens
+        .route_layer(axum_mw::from_fn_with_state(
+            state.db_pool.clone(),
+            db_breaker_middleware,
+        ));
+
+    // Every API version is served by the same routes; handlers read the
+    // version via the `ApiVersion` extractor where response shapes differ.
+    let deprecations = api_version::parse_deprecations(&state.config.api_deprecated_versions);
+    let mut versioned = Router::new();
+    for version in ApiVersion::ALL {
+        let mut routes = api.clone().layer(Extension(version));
+        if let Some(deprecation) = deprecations.iter().find(|d| d.version == version) {
+            routes = routes.layer(axum_mw::from_fn_with_state(
+                deprecation.clone(),
+                api_version::deprecation_middleware,
+            ));
+        }
+        versioned = versioned
+            .route(&format!("{}/ws", version.prefix()), get(ws::ws_handler))
+            .nest(version.prefix(), routes);
+    }
 
-    Router::new()
-        .route("/api/v1/ws", get(ws::ws_handler))
-        .nest("/api/v1", api)
+    versioned
+        // OpenAPI spec generated from the handler annotations, plus Swagger UI
+        .merge(SwaggerUi::new("/api/v1/docs").url("/api/v1/openapi.json", openapi::ApiDoc::openapi()))
         .route("/health", get(health_check))
+        .route("/healthz", get(api::health::healthz))
+        .route("/readyz", get(api::health::readyz))
         .layer(CompressionLayer::new())
         // TraceLayer: custom span excludes remote_addr (IP privacy)
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|req: &axum::extract::Request| {
+                    let trace_id = req
+                        .headers()
+                        .get(middleware::trace_id::REQUEST_ID_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default();
                     tracing::info_span!(
                         "http_request",
                         method = %req.method(),
                         uri = %req.uri(),
                         version = ?req.version(),
+                        trace_id = %trace_id,
                     )
                 })
                 .on_response(DefaultOnResponse::new().level(tracing::Level::DEBUG)),
         )
         .layer(axum_mw::from_fn(move |req, next| {
-            let limiter = global_limiter.clone();
+            let limiter = global_limiter
+                .clone()
+                .with_max_requests(live_config.get().max_requests_per_minute);
             rate_limit_middleware(limiter, req, next)
         }))
+        // Outside the limiter and TraceLayer so both see the request's trace id
+        .layer(axum_mw::from_fn(trace_id_middleware))
         .layer(cors)
         // ─── Security Headers ──────────────────────────
         .layer(SetResponseHeaderLayer::overriding(
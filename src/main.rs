@@ -1,17 +1,29 @@
+use std::future::IntoFuture;
 use std::sync::Arc;
+use std::time::Duration;
 
 use dashmap::DashMap;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 use haven_backend::{
+    api,
+    attachment_gc,
     build_router,
-    config::AppConfig,
-    db::{self, DbPools},
+    config::{AppConfig, LiveConfig},
+    db::{self, pool_monitor::PoolMonitor, shadow::ShadowReads, DbPools},
+    jobs::{self, JobQueue},
     livekit_proc,
+    maintenance,
     memory_store::MemoryStore,
-    middleware::{spawn_user_rate_limit_cleanup, UserRateLimiter},
-    models,
+    middleware::{
+        spawn_rate_policy_cleanup, spawn_user_rate_limit_cleanup, LatencyBudgets, RatePolicies,
+        UserRateLimiter,
+    },
     pubsub,
+    shutdown::Shutdown,
     storage::Storage,
+    ws::HeartbeatStats,
+    ws_outbox::{self, WsOutbox},
     AppState,
 };
 
@@ -27,13 +39,15 @@ async fn main() {
     // Load .env file if present
     dotenvy::dotenv().ok();
 
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "haven_backend=debug,tower_http=debug".into()),
+    // Initialize tracing, plus the layer that fingerprints sqlx's slow statement events
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer().json().with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| "haven_backend=debug,tower_http=debug".into()),
+            ),
         )
-        .json()
+        .with(db::slow_queries::layer())
         .init();
 
     // Load configuration
@@ -41,6 +55,7 @@ async fn main() {
     // PostgreSQL mode: use environment variables (existing behavior)
     #[cfg(feature = "sqlite")]
     let config = {
+        tracing::warn!("SQLite mode is experimental and not supported; use PostgreSQL for real instances");
         let config_path = std::env::var("HAVEN_CONFIG")
             .unwrap_or_else(|_| "./data/haven.toml".into());
         AppConfig::from_file_or_generate(&config_path)
@@ -76,8 +91,11 @@ async fn main() {
 
     tracing::info!("Starting Haven backend on {}:{}", config.host, config.port);
 
-    // Initialize database pools (primary + optional replica)
+    // Initialize database pools (primary + read replicas) and keep replica health current
     let db = DbPools::init(&config).await;
+    db::spawn_replica_monitor(db.clone());
+    let db_pool = PoolMonitor::new(Duration::from_millis(config.db_pool_saturation_ms));
+    db::pool_monitor::spawn_pool_monitor(db.clone(), db_pool.clone());
 
     // Initialize Redis (optional — if redis_url is empty, use in-memory stores only)
     let redis = if config.redis_url.is_empty() {
@@ -111,32 +129,61 @@ async fn main() {
     // Initialize storage backend (local or S3 based on config)
     let storage = Storage::from_config(&config).await;
     let storage_key = *storage.encryption_key();
+    let backup_storage = Storage::backup_from_config(&config, storage_key);
 
     // Per-user rate limiters
     let ws_rate_limiter = UserRateLimiter::new(30, 10); // 30 messages per 10 seconds
     let api_rate_limiter = UserRateLimiter::new(30, 60); // 30 write ops per minute
     spawn_user_rate_limit_cleanup(ws_rate_limiter.clone());
     spawn_user_rate_limit_cleanup(api_rate_limiter.clone());
+    let rate_policies = RatePolicies::new(&config, redis.clone().filter(|_| config.rate_limit_redis));
+    spawn_rate_policy_cleanup(rate_policies.clone());
 
-    // Build application state (pubsub_subscriptions added after start_subscriber)
-    let mut state = AppState {
+    let pubsub = pubsub::PubSub::from_config(&config, redis.clone());
+
+    let email_templates = haven_backend::email::EmailTemplates::load(&config.email_template_dir)
+        .expect("Failed to load email templates");
+
+    // Build application state
+    let state = AppState {
         db: db.clone(),
         redis,
         config: config.clone(),
+        live_config: LiveConfig::new(config.clone()),
         storage_key,
         storage,
+        backup_storage,
+        email_templates,
+        abuse_lists: Default::default(),
         connections: Arc::new(DashMap::new()),
         channel_broadcasts: Arc::new(DashMap::new()),
-        pubsub_subscriptions: pubsub::empty_subscriptions(),
+        pubsub,
         memory,
         ws_rate_limiter,
         api_rate_limiter,
         sessions: Arc::new(DashMap::new()),
         ban_cache: haven_backend::cache::BanCache::new(60),
+        latency_budgets: LatencyBudgets::new(
+            Duration::from_secs(config.interactive_timeout_secs),
+            Duration::from_secs(config.job_timeout_secs),
+        ),
+        rate_policies,
+        shadow_reads: ShadowReads::new(config.shadow_read_sample_rate),
+        db_pool,
+        jobs: JobQueue::new(),
+        ws_outbox: WsOutbox::new(),
+        ws_heartbeats: HeartbeatStats::new(),
+        shutdown: Shutdown::new(Duration::from_secs(config.shutdown_drain_secs)),
     };
 
-    // Start Redis pub/sub subscriber and store the subscriptions handle
-    state.pubsub_subscriptions = pubsub::start_subscriber(state.clone());
+    // Start the Redis pub/sub subscriber (no-op for local fan-out)
+    pubsub::start_subscriber(state.clone());
+
+    // Run persistent background jobs (emails, announcement DMs, scheduled maintenance)
+    jobs::spawn_runner(state.clone());
+
+    // Publish WS events staged in the outbox by committed transactions
+    ws_outbox::spawn_dispatcher(state.clone());
 
     // Spawn background workers
     spawn_background_workers(db.clone(), &config, state.clone());
@@ -191,6 +238,45 @@ async fn main() {
         });
     }
 
+    // ─── IRC gateway ──────────────────────────────────────
+    #[cfg(feature = "irc")]
+    if config.irc_port != 0 {
+        haven_backend::irc::spawn(state.clone());
+    }
+    #[cfg(not(feature = "irc"))]
+    if config.irc_port != 0 {
+        tracing::warn!("IRC_PORT is set but this build lacks the `irc` feature — gateway disabled");
+    }
+
+    // Reload the reloadable settings on SIGHUP
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .expect("Failed to install SIGHUP handler");
+            while hangup.recv().await.is_some() {
+                match AppConfig::reload_from_source() {
+                    Ok(fresh) => {
+                        state.reload_config(&fresh);
+                    }
+                    Err(e) => tracing::error!("Config reload failed, keeping current settings: {}", e),
+                }
+            }
+        });
+    }
+
+    // Stop serving on SIGTERM / Ctrl+C; see `shutdown` for the drain sequence
+    let shutdown = state.shutdown.clone();
+    let connections = state.connections.clone();
+    {
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown.trigger();
+        });
+    }
+
     // Build router
     let app = build_router(state);
 
@@ -243,27 +329,48 @@ async fn main() {
             .layer(axum::middleware::from_fn(inject_https_proto));
 
         let http_server = axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
-            .with_graceful_shutdown(shutdown_signal());
+            .with_graceful_shutdown(shutdown.signal());
 
+        let https_handle = axum_server::Handle::new();
+        {
+            let handle = https_handle.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                shutdown.triggered().await;
+                handle.graceful_shutdown(Some(shutdown.drain_window()));
+            });
+        }
         let https_server = axum_server::bind_rustls(tls_addr, rustls_config)
+            .handle(https_handle)
             .serve(app_https.into_make_service());
 
         tokio::select! {
-            result = http_server => {
-                result.expect("HTTP server error");
+            result = async { tokio::try_join!(http_server.into_future(), https_server) } => {
+                result.expect("Server error");
             }
-            result = https_server => {
-                result.expect("HTTPS server error");
+            _ = shutdown.deadline() => {
+                tracing::warn!("Drain window elapsed with requests still in flight");
             }
         }
     } else {
         // HTTP only
-        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
-            .with_graceful_shutdown(shutdown_signal())
-            .await
-            .expect("Server error");
+        let http_server = axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .with_graceful_shutdown(shutdown.signal());
+
+        tokio::select! {
+            result = http_server => {
+                result.expect("Server error");
+            }
+            _ = shutdown.deadline() => {
+                tracing::warn!("Drain window elapsed with requests still in flight");
+            }
+        }
     }
 
+    // Listeners are closed; let WebSocket clients finish handing off and
+    // in-flight transactions commit before exiting
+    shutdown.drain(&connections, &db).await;
+
     tracing::info!("Haven backend shut down gracefully");
 }
 
@@ -308,48 +415,17 @@ async fn inject_https_proto(
 fn spawn_background_workers(db: DbPools, config: &AppConfig, app_state: AppState) {
     let pool = db.primary().clone();
     let pool2 = pool.clone();
-    let pool3 = pool.clone();
 
     // Worker: Purge expired messages every 60 seconds
-    // Collects expired message IDs first, broadcasts MessagesExpired, then deletes.
+    // Broadcasts MessagesExpired to subscribed clients for what was deleted.
     let purge_state = app_state.clone();
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
         loop {
             interval.tick().await;
-            // Collect expired IDs before purging so we can notify clients
-            let expired = db::queries::get_expired_message_ids(&pool)
-                .await
-                .unwrap_or_default();
-
-            match db::queries::purge_expired_messages(&pool).await {
-                Ok(count) if count > 0 => {
-                    tracing::info!("Purged {} expired messages", count);
-
-                    // Group by channel_id and broadcast MessagesExpired
-                    let mut by_channel: std::collections::HashMap<uuid::Uuid, Vec<uuid::Uuid>> =
-                        std::collections::HashMap::new();
-                    for (channel_id, message_id) in expired {
-                        by_channel.entry(channel_id).or_default().push(message_id);
-                    }
-                    for (channel_id, message_ids) in by_channel {
-                        let msg = models::WsServerMessage::MessagesExpired {
-                            channel_id,
-                            message_ids,
-                        };
-                        if let Some(broadcaster) = purge_state.channel_broadcasts.get(&channel_id) {
-                            let _ = broadcaster.send(msg.clone());
-                        }
-                        pubsub::publish_channel_event(
-                            purge_state.redis.clone().as_mut(),
-                            channel_id,
-                            &msg,
-                        ).await;
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to purge expired messages: {}", e);
-                }
+            match maintenance::purge_expired_messages(&purge_state).await {
+                Ok(count) if count > 0 => tracing::info!("Purged {} expired messages", count),
+                Err(e) => tracing::error!("Failed to purge expired messages: {}", e),
                 _ => {}
             }
         }
@@ -372,19 +448,282 @@ fn spawn_background_workers(db: DbPools, config: &AppConfig, app_state: AppState
         }
     });
 
-    // Worker: Ensure message partitions exist 3 months ahead (runs daily)
-    // PostgreSQL only — SQLite ensure_future_partitions is a no-op
+    // Worker: Purge expired upload sessions and their chunk blobs (hourly)
+    let upload_state = app_state.clone();
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400));
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
         loop {
             interval.tick().await;
-            match db::queries::ensure_future_partitions(&pool3).await {
-                Ok(()) => {
-                    tracing::debug!("Partition maintenance completed");
+            match maintenance::run(&upload_state, maintenance::Job::UploadSessions).await {
+                Ok(count) if count > 0 => {
+                    tracing::info!("Purged {} chunks from expired upload sessions", count);
                 }
-                Err(e) => {
-                    tracing::error!("Partition maintenance failed: {}", e);
+                Err(e) => tracing::error!("Failed to purge expired upload sessions: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Delete attachments whose message is gone, after the grace period (hourly)
+    let gc_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            match attachment_gc::run(&gc_state).await {
+                Ok(pass) if gc_state.config.attachment_gc_dry_run && pass.collectable > 0 => {
+                    tracing::info!("Attachment GC (dry run): {} orphaned attachments past grace period", pass.collectable)
+                }
+                Ok(pass) if pass.deleted > 0 || pass.blobs_deleted > 0 => tracing::info!(
+                    "Attachment GC deleted {} orphaned attachments and {} shared blobs",
+                    pass.deleted,
+                    pass.blobs_deleted
+                ),
+                Err(e) => tracing::error!("Attachment GC failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Publish scheduled announcements (every 30 seconds)
+    let announcement_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            match api::announcements::publish_due(&announcement_state).await {
+                Ok(published) if !published.is_empty() => {
+                    tracing::info!("Published {} scheduled announcements", published.len())
+                }
+                Err(e) => tracing::error!("Failed to publish scheduled announcements: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Issue beta codes to the waitlist as codes expire (every 5 minutes)
+    let waitlist_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&waitlist_state, maintenance::Job::BetaWaitlist).await {
+                Ok(count) if count > 0 => tracing::info!("Promoted {} emails from the beta waitlist", count),
+                Err(e) => tracing::error!("Beta waitlist promotion failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Retry queued emails whose backoff has passed (every 30 seconds)
+    let outbox_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            if let Err(e) = maintenance::run(&outbox_state, maintenance::Job::EmailOutbox).await {
+                tracing::error!("Email outbox run failed: {}", e);
+            }
+        }
+    });
+
+    // Worker: Erase raw IPs of abuse-scored requests past ABUSE_IP_RETENTION_HOURS
+    // and drop old abuse events (every 10 minutes)
+    let abuse_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = maintenance::run(&abuse_state, maintenance::Job::AbuseEvents).await {
+                tracing::error!("Abuse event purge failed: {}", e);
+            }
+        }
+    });
+
+    // Worker: Reload the ABUSE_IP_LISTS (at startup, then every 6 hours)
+    let lists_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(6 * 3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&lists_state, maintenance::Job::AbuseLists).await {
+                Ok(count) if count > 0 => tracing::info!("Loaded {} abuse IP ranges", count),
+                Err(e) => tracing::error!("Abuse IP list refresh failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Enforce per-channel message retention policies (runs hourly)
+    let retention_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&retention_state, maintenance::Job::ChannelRetention).await {
+                Ok(count) if count > 0 => tracing::info!("Retention sweep deleted {} messages", count),
+                Err(e) => tracing::error!("Retention sweep failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Remind members who RSVPed to events that are about to start
+    if config.event_reminder_minutes > 0 {
+        let reminder_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                match maintenance::run(&reminder_state, maintenance::Job::EventReminders).await {
+                    Ok(count) if count > 0 => tracing::info!("Sent reminders for {} events", count),
+                    Err(e) => tracing::error!("Event reminders failed: {}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // Worker: Re-run malware scans an instance restart left pending (every 10 minutes)
+    if !app_state.config.attachment_scanner.is_empty() {
+        let scan_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(600));
+            loop {
+                interval.tick().await;
+                match maintenance::run(&scan_state, maintenance::Job::AttachmentScans).await {
+                    Ok(count) if count > 0 => tracing::info!("Re-ran {} pending attachment scans", count),
+                    Err(e) => tracing::error!("Resuming attachment scans failed: {}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // Worker: Start and end Do Not Disturb schedules (every minute)
+    let quiet_hours_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&quiet_hours_state, maintenance::Job::QuietHours).await {
+                Ok(count) if count > 0 => tracing::debug!("{} quiet hours schedules started or ended", count),
+                Err(e) => tracing::error!("Quiet hours sweep failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: End live location sessions past their expiry (every 30 seconds)
+    let live_location_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&live_location_state, maintenance::Job::LiveLocations).await {
+                Ok(count) if count > 0 => tracing::debug!("Ended {} expired live location sessions", count),
+                Err(e) => tracing::error!("Live location expiry failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Roll up server insights (daily)
+    let insights_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(24 * 3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&insights_state, maintenance::Job::Insights).await {
+                Ok(count) => tracing::info!("Rolled up insights for {} server-days", count),
+                Err(e) => tracing::error!("Insights rollup failed: {}", e),
+            }
+        }
+    });
+
+    // Worker: Drop finished background jobs past their retention (daily)
+    let jobs_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(24 * 3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&jobs_state, maintenance::Job::BackgroundJobs).await {
+                Ok(count) if count > 0 => tracing::info!("Purged {} finished background jobs", count),
+                Err(e) => tracing::error!("Background job purge failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Drop WS outbox events sent over a day ago (hourly)
+    let outbox_purge_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&outbox_purge_state, maintenance::Job::WsOutbox).await {
+                Ok(count) if count > 0 => tracing::debug!("Purged {} sent WS outbox events", count),
+                Err(e) => tracing::error!("WS outbox purge failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Report usage stats to TELEMETRY_ENDPOINT, if set (daily)
+    let telemetry_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(24 * 3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&telemetry_state, maintenance::Job::Telemetry).await {
+                Ok(count) if count > 0 => tracing::info!("Reported usage stats"),
+                Err(e) => tracing::error!("Usage stats report failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Purge servers whose deletion grace period has passed (runs hourly)
+    let purge_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&purge_state, maintenance::Job::DeletedServers).await {
+                Ok(count) if count > 0 => tracing::info!("Purged {} deleted servers", count),
+                Err(e) => tracing::error!("Deleted server purge failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Drop restore snapshots past their rollback window (hourly)
+    let snapshot_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            match maintenance::run(&snapshot_state, maintenance::Job::RestoreSnapshots).await {
+                Ok(count) if count > 0 => tracing::info!("Purged {} expired restore snapshots", count),
+                Err(e) => tracing::error!("Restore snapshot purge failed: {}", e),
+                _ => {}
+            }
+        }
+    });
+
+    // Worker: Create message partitions ahead of need and drop those past
+    // retention (runs daily). PostgreSQL only — a no-op on SQLite.
+    let partition_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400));
+        loop {
+            interval.tick().await;
+            match maintenance::manage_partitions(&partition_state).await {
+                Ok(dropped) if dropped > 0 => {
+                    tracing::info!("Partition maintenance dropped {} expired messages", dropped)
                 }
+                Ok(_) => tracing::debug!("Partition maintenance completed"),
+                Err(e) => tracing::error!("Partition maintenance failed: {}", e),
             }
         }
     });
@@ -423,6 +762,40 @@ fn spawn_background_workers(db: DbPools, config: &AppConfig, app_state: AppState
         });
     }
 
+    // Worker: Prune the WS delta sync journal (daily)
+    if config.sync_journal_retention_days > 0 {
+        let pool = db.primary().clone();
+        let days = config.sync_journal_retention_days;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400));
+            loop {
+                interval.tick().await;
+                match db::queries::purge_old_sync_changes(&pool, days).await {
+                    Ok(count) if count > 0 => tracing::info!("Pruned {} sync journal entries", count),
+                    Err(e) => tracing::error!("Failed to prune sync journal: {}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    // Worker: Trim unacknowledged per-device message queues (hourly)
+    if config.device_queue_ttl_days > 0 {
+        let pool = db.primary().clone();
+        let days = config.device_queue_ttl_days;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                match db::queries::purge_expired_device_messages(&pool, days).await {
+                    Ok(count) if count > 0 => tracing::info!("Trimmed {} queued device messages", count),
+                    Err(e) => tracing::error!("Failed to trim device message queues: {}", e),
+                    _ => {}
+                }
+            }
+        });
+    }
+
     // Worker: Purge expired invites (hourly)
     if config.expired_invite_cleanup {
         let pool = db.primary().clone();
@@ -0,0 +1,345 @@
+//! Maintenance jobs that operators can trigger on demand.
+//!
+//! The background workers in `main.rs` run these on a schedule; the admin API
+//! (`POST /admin/maintenance/:job`) runs one immediately, e.g. after changing
+//! a retention setting or to reclaim space without waiting for the next tick.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::{MessagePartition, PartitionStatusResponse, WsServerMessage};
+use crate::pubsub;
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Job {
+    ExpiredMessages,
+    RefreshTokens,
+    UploadSessions,
+    ExpiredInvites,
+    AuditLog,
+    ResolvedReports,
+    ExpiredSuspensions,
+    Partitions,
+    SyncJournal,
+    BetaWaitlist,
+    EmailOutbox,
+    AbuseEvents,
+    AbuseLists,
+    DeletedServers,
+    ChannelRetention,
+    EventReminders,
+    DeviceQueues,
+    RestoreSnapshots,
+    QuietHours,
+    AttachmentScans,
+    LiveLocations,
+    Insights,
+    Telemetry,
+    BackgroundJobs,
+    WsOutbox,
+}
+
+impl Job {
+    pub const ALL: [Job; 25] = [
+        Job::ExpiredMessages,
+        Job::RefreshTokens,
+        Job::UploadSessions,
+        Job::ExpiredInvites,
+        Job::AuditLog,
+        Job::ResolvedReports,
+        Job::ExpiredSuspensions,
+        Job::Partitions,
+        Job::SyncJournal,
+        Job::BetaWaitlist,
+        Job::EmailOutbox,
+        Job::AbuseEvents,
+        Job::AbuseLists,
+        Job::DeletedServers,
+        Job::ChannelRetention,
+        Job::EventReminders,
+        Job::DeviceQueues,
+        Job::RestoreSnapshots,
+        Job::QuietHours,
+        Job::AttachmentScans,
+        Job::LiveLocations,
+        Job::Insights,
+        Job::Telemetry,
+        Job::BackgroundJobs,
+        Job::WsOutbox,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Job::ExpiredMessages => "expired-messages",
+            Job::RefreshTokens => "refresh-tokens",
+            Job::UploadSessions => "upload-sessions",
+            Job::ExpiredInvites => "expired-invites",
+            Job::AuditLog => "audit-log",
+            Job::ResolvedReports => "resolved-reports",
+            Job::ExpiredSuspensions => "expired-suspensions",
+            Job::Partitions => "partitions",
+            Job::SyncJournal => "sync-journal",
+            Job::BetaWaitlist => "beta-waitlist",
+            Job::EmailOutbox => "email-outbox",
+            Job::AbuseEvents => "abuse-events",
+            Job::AbuseLists => "abuse-lists",
+            Job::DeletedServers => "deleted-servers",
+            Job::ChannelRetention => "channel-retention",
+            Job::EventReminders => "event-reminders",
+            Job::DeviceQueues => "device-queues",
+            Job::RestoreSnapshots => "restore-snapshots",
+            Job::QuietHours => "quiet-hours",
+            Job::AttachmentScans => "attachment-scans",
+            Job::LiveLocations => "live-locations",
+            Job::Insights => "insights",
+            Job::Telemetry => "telemetry",
+            Job::BackgroundJobs => "background-jobs",
+            Job::WsOutbox => "ws-outbox",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|job| job.as_str() == s)
+    }
+}
+
+/// Run one job and return how many rows (or blobs) it removed.
+/// Retention jobs refuse to run while their retention setting is disabled.
+pub async fn run(state: &AppState, job: Job) -> AppResult<u64> {
+    let pool = state.db.primary();
+    match job {
+        Job::ExpiredMessages => purge_expired_messages(state).await,
+        Job::RefreshTokens => queries::purge_expired_refresh_tokens(pool).await,
+        Job::UploadSessions => {
+            let part_ids = queries::purge_expired_upload_sessions(pool).await?;
+            crate::api::attachments::delete_parts(state, &part_ids).await;
+            Ok(part_ids.len() as u64)
+        }
+        Job::ExpiredInvites => queries::purge_expired_invites(pool).await,
+        Job::AuditLog => match state.config.audit_log_retention_days {
+            0 => Err(AppError::BadRequest("Audit log retention is disabled".into())),
+            days => queries::purge_old_audit_logs(pool, days).await,
+        },
+        Job::ResolvedReports => match state.config.resolved_report_retention_days {
+            0 => Err(AppError::BadRequest("Resolved report retention is disabled".into())),
+            days => queries::purge_old_resolved_reports(pool, days).await,
+        },
+        Job::ExpiredSuspensions => queries::purge_expired_instance_bans(pool).await,
+        Job::Partitions => manage_partitions(state).await,
+        Job::SyncJournal => match state.config.sync_journal_retention_days {
+            0 => Err(AppError::BadRequest("Sync journal retention is disabled".into())),
+            days => queries::purge_old_sync_changes(pool, days).await,
+        },
+        Job::BetaWaitlist => crate::api::beta::promote_waitlist(state).await,
+        Job::EmailOutbox => crate::email::mailer::drain_outbox(state).await,
+        Job::AbuseEvents => crate::abuse::purge_events(state).await,
+        Job::AbuseLists => crate::abuse::refresh_lists(state).await,
+        Job::DeletedServers => purge_deleted_servers(state).await,
+        Job::ChannelRetention => crate::retention::sweep(state).await,
+        Job::EventReminders => crate::api::events::send_reminders(state).await,
+        Job::DeviceQueues => match state.config.device_queue_ttl_days {
+            0 => Err(AppError::BadRequest("Device queue TTL is disabled".into())),
+            days => queries::purge_expired_device_messages(pool, days).await,
+        },
+        Job::RestoreSnapshots => purge_restore_snapshots(state).await,
+        Job::QuietHours => crate::quiet_hours::sweep(state).await,
+        Job::AttachmentScans => crate::scanning::resume_pending(state).await,
+        Job::LiveLocations => crate::api::locations::expire_sessions(state).await,
+        Job::Insights => crate::insights::rollup(state).await,
+        Job::Telemetry => crate::usage_stats::report(state).await,
+        Job::BackgroundJobs => queries::purge_finished_jobs(pool, crate::jobs::FINISHED_RETENTION_DAYS).await,
+        Job::WsOutbox => queries::purge_sent_ws_events(pool, crate::ws_outbox::SENT_RETENTION_HOURS).await,
+    }
+}
+
+/// Delete restore snapshots past their rollback window, along with the
+/// channels and messages they kept detached, a batch of channels at a time.
+async fn purge_restore_snapshots(state: &AppState) -> AppResult<u64> {
+    let pool = state.db.primary();
+    for channel_id in queries::list_expired_snapshot_channels(pool, 100).await? {
+        if let Err(e) = queries::delete_channel(pool, channel_id).await {
+            tracing::error!("Failed to purge detached channel {}: {}", channel_id, e);
+        }
+    }
+    queries::delete_expired_restore_snapshots(pool).await
+}
+
+/// Purge servers whose deletion grace period has run out, a batch at a time.
+/// A server that fails to purge is logged and retried on the next run.
+async fn purge_deleted_servers(state: &AppState) -> AppResult<u64> {
+    let due = queries::list_servers_due_for_purge(
+        state.db.primary(),
+        crate::api::servers::DELETION_GRACE_DAYS,
+        100,
+    )
+    .await?;
+    let mut purged = 0;
+    for server_id in due {
+        match crate::api::servers::purge_server(state, server_id).await {
+            Ok(()) => purged += 1,
+            Err(e) => tracing::error!("Failed to purge deleted server {}: {}", server_id, e),
+        }
+    }
+    Ok(purged)
+}
+
+/// Delete expired messages and tell subscribed clients which ones went away.
+/// IDs are collected before the purge so the notification matches what was
+/// deleted.
+pub async fn purge_expired_messages(state: &AppState) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let expired = queries::get_expired_message_ids(pool).await.unwrap_or_default();
+    let count = queries::purge_expired_messages(pool).await?;
+    if count == 0 {
+        return Ok(0);
+    }
+
+    let mut by_channel: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (channel_id, message_id) in expired {
+        by_channel.entry(channel_id).or_default().push(message_id);
+    }
+    for (channel_id, message_ids) in by_channel {
+        let msg = WsServerMessage::MessagesExpired { channel_id, message_ids };
+        if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+            let _ = broadcaster.send(msg.clone());
+        }
+        pubsub::publish_channel_event(state, channel_id, &msg).await;
+    }
+    Ok(count)
+}
+
+// ─── Message Partitions ──────────────────────────────
+
+/// Name of the monthly `messages` partition starting at `month`.
+pub fn partition_name(month: NaiveDate) -> String {
+    format!("messages_y{}m{:02}", month.year(), month.month())
+}
+
+/// First day of the month a partition covers, parsed from its name.
+/// `None` for the default partition and anything not created by us.
+pub fn parse_partition_name(name: &str) -> Option<NaiveDate> {
+    let (year, month) = name.strip_prefix("messages_y")?.split_once('m')?;
+    if year.len() != 4 || month.len() != 2 {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)
+}
+
+fn current_month() -> NaiveDate {
+    let today = Utc::now().date_naive();
+    NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today)
+}
+
+/// Partitions ending on or before this date are past retention.
+/// `None` when retention is disabled.
+fn retention_cutoff(this_month: NaiveDate, retention_months: u32) -> Option<NaiveDate> {
+    (retention_months > 0).then(|| this_month - Months::new(retention_months))
+}
+
+/// Create partitions for this month and the next `MESSAGE_PARTITION_MONTHS_AHEAD`
+/// months, then drop partitions that ended before the retention window.
+/// Returns how many messages the dropped partitions held.
+pub async fn manage_partitions(state: &AppState) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let this_month = current_month();
+
+    for offset in 0..=state.config.message_partition_months_ahead {
+        let start = this_month + Months::new(offset);
+        let name = partition_name(start);
+        match queries::create_message_partition(pool, &name, start, start + Months::new(1)).await {
+            Ok(true) => tracing::info!("Created message partition {}", name),
+            Ok(false) => {}
+            // Usually rows for that month already landed in messages_default;
+            // they have to be moved out by hand before the partition can exist.
+            Err(e) => tracing::warn!("Could not create message partition {}: {}", name, e),
+        }
+    }
+
+    let Some(cutoff) = retention_cutoff(this_month, state.config.message_partition_retention_months) else {
+        return Ok(0);
+    };
+    let mut dropped = 0;
+    for (name, _, _) in queries::list_message_partitions(pool).await? {
+        if parse_partition_name(&name).is_some_and(|month| month + Months::new(1) <= cutoff) {
+            let rows = queries::drop_message_partition(pool, &name).await?;
+            tracing::info!("Dropped message partition {} ({} messages) past retention", name, rows);
+            dropped += rows;
+        }
+    }
+    Ok(dropped)
+}
+
+/// Partitions with their size and drop date, plus any month in the
+/// create-ahead window that is still missing its partition.
+pub async fn partition_status(state: &AppState) -> AppResult<PartitionStatusResponse> {
+    let config = &state.config;
+    let retention = config.message_partition_retention_months;
+    let partitions: Vec<MessagePartition> = queries::list_message_partitions(state.db.primary())
+        .await?
+        .into_iter()
+        .map(|(name, estimated_rows, size_bytes)| {
+            let month = parse_partition_name(&name);
+            MessagePartition {
+                drop_after: month.filter(|_| retention > 0).map(|m| m + Months::new(retention + 1)),
+                name,
+                month,
+                estimated_rows,
+                size_bytes,
+            }
+        })
+        .collect();
+
+    let this_month = current_month();
+    let missing = if partitions.is_empty() {
+        Vec::new() // no partitioning (SQLite)
+    } else {
+        (0..=config.message_partition_months_ahead)
+            .map(|offset| partition_name(this_month + Months::new(offset)))
+            .filter(|name| !partitions.iter().any(|p| &p.name == name))
+            .collect()
+    };
+
+    Ok(PartitionStatusResponse {
+        months_ahead: config.message_partition_months_ahead,
+        retention_months: retention,
+        missing,
+        partitions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_names_round_trip() {
+        for job in Job::ALL {
+            assert_eq!(Job::parse(job.as_str()), Some(job));
+        }
+        assert_eq!(Job::parse("attachment-gc"), None);
+    }
+
+    #[test]
+    fn partition_names_round_trip() {
+        let month = NaiveDate::from_ymd_opt(2027, 3, 1).unwrap();
+        assert_eq!(partition_name(month), "messages_y2027m03");
+        assert_eq!(parse_partition_name("messages_y2027m03"), Some(month));
+        assert_eq!(parse_partition_name("messages_default"), None);
+        assert_eq!(parse_partition_name("messages_y2027m13"), None);
+    }
+
+    #[test]
+    fn retention_cutoff_keeps_whole_months() {
+        let october = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        assert_eq!(retention_cutoff(october, 0), None);
+        // With 6 months retention the March partition (ends April 1) goes,
+        // April stays.
+        let cutoff = retention_cutoff(october, 6).unwrap();
+        assert_eq!(cutoff, NaiveDate::from_ymd_opt(2026, 4, 1).unwrap());
+    }
+}
@@ -30,6 +30,8 @@ pub struct MemoryStore {
     pub pow_challenges: Arc<DashMap<String, Instant>>,
     /// Voice channel participants: channel_id → set of user_ids
     pub voice_participants: Arc<DashMap<Uuid, HashSet<Uuid>>>,
+    /// Serializes SFU room provisioning and teardown per voice channel
+    pub voice_room_locks: Arc<DashMap<Uuid, Arc<tokio::sync::Mutex<()>>>>,
     /// Server-muted users per voice channel
     pub voice_muted: Arc<DashMap<Uuid, HashSet<Uuid>>>,
     /// Server-deafened users per voice channel
@@ -38,8 +40,11 @@ pub struct MemoryStore {
     pub active_calls: Arc<DashMap<Uuid, ActiveCall>>,
     /// Connected (accepted) calls: channel_id → connected call state
     pub connected_calls: Arc<DashMap<Uuid, ConnectedCall>>,
-    /// Pending file hashes: attachment_id → SHA-256 hash (set during upload, consumed during link)
-    pub pending_file_hashes: Arc<DashMap<Uuid, String>>,
+    /// Messages sent per server in the current minute: server_id → (unix minute, count)
+    pub server_message_counts: Arc<DashMap<Uuid, (i64, u32)>>,
+    /// Open WebSocket connections per registered device on this instance:
+    /// device_id → count. Devices without one get DM/group messages queued.
+    pub connected_devices: Arc<DashMap<Uuid, usize>>,
 }
 
 impl Default for MemoryStore {
@@ -49,11 +54,13 @@ impl Default for MemoryStore {
             cache: Arc::new(DashMap::new()),
             pow_challenges: Arc::new(DashMap::new()),
             voice_participants: Arc::new(DashMap::new()),
+            voice_room_locks: Arc::new(DashMap::new()),
             voice_muted: Arc::new(DashMap::new()),
             voice_deafened: Arc::new(DashMap::new()),
             active_calls: Arc::new(DashMap::new()),
             connected_calls: Arc::new(DashMap::new()),
-            pending_file_hashes: Arc::new(DashMap::new()),
+            server_message_counts: Arc::new(DashMap::new()),
+            connected_devices: Arc::new(DashMap::new()),
         }
     }
 }
@@ -63,10 +70,12 @@ impl MemoryStore {
         Self::default()
     }
 
-    /// Spawn a background task that prunes expired cache and PoW entries every 60 seconds.
+    /// Spawn a background task that prunes expired cache, PoW and message
+    /// counter entries every 60 seconds.
     pub fn spawn_cleanup_task(&self) {
         let cache = self.cache.clone();
         let pow = self.pow_challenges.clone();
+        let message_counts = self.server_message_counts.clone();
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(60));
@@ -79,6 +88,10 @@ impl MemoryStore {
 
                 // Prune expired PoW challenges
                 pow.retain(|_, expiry| *expiry > now);
+
+                // Prune message counters from past minutes
+                let minute = chrono::Utc::now().timestamp() / 60;
+                message_counts.retain(|_, (m, _)| *m >= minute);
             }
         });
     }
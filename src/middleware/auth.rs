@@ -4,9 +4,11 @@ use axum::{
 };
 use uuid::Uuid;
 
-use crate::auth::{user_id_from_claims, validate_access_token};
+use crate::auth::{hash_refresh_token, user_id_from_claims, validate_access_token, BRIDGE_TOKEN_PREFIX};
 use crate::db::queries;
-use crate::errors::AppError;
+use crate::errors::{AppError, AppResult};
+use crate::models::Bridge;
+use crate::permissions::InstanceRole;
 use crate::AppState;
 
 /// Extractor that validates JWT and provides the authenticated user ID.
@@ -87,6 +89,75 @@ impl FromRequestParts<AppState> for AdminUser {
     }
 }
 
+/// Extractor that validates JWT and verifies the user holds an instance staff role
+/// (operator, instance moderator, or support). Handlers must still call
+/// `require` with the capability they need.
+/// Use in handler signatures: `staff: StaffUser`
+#[derive(Debug, Clone)]
+pub struct StaffUser {
+    pub user_id: Uuid,
+    pub role: InstanceRole,
+}
+
+impl StaffUser {
+    /// Fail with 403 unless this staff member's role grants `capability`
+    /// (one of the `permissions::INSTANCE_*` bits).
+    pub fn require(&self, capability: i64) -> AppResult<()> {
+        if !self.role.has(capability) {
+            return Err(AppError::Forbidden(format!(
+                "Instance role '{}' is not permitted to perform this action",
+                self.role.as_str()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for StaffUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let AuthUser(user_id) = AuthUser::from_request_parts(parts, state).await?;
+
+        let user = queries::find_user_basic_by_id(state.db.read(), user_id)
+            .await?
+            .ok_or(AppError::AuthError("User not found".into()))?;
+
+        let role = InstanceRole::resolve(user.is_instance_admin, user.instance_role.as_deref())
+            .ok_or_else(|| AppError::Forbidden("Instance staff access required".into()))?;
+
+        Ok(StaffUser { user_id, role })
+    }
+}
+
+/// Extractor for bridge integrations. Bridges authenticate with the opaque
+/// token issued when an operator registered them, not a user JWT.
+/// Use in handler signatures: `BridgeAuth(bridge): BridgeAuth`
+#[derive(Debug, Clone)]
+pub struct BridgeAuth(pub Bridge);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for BridgeAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .filter(|t| t.starts_with(BRIDGE_TOKEN_PREFIX))
+            .ok_or(AppError::AuthError("Missing or invalid bridge token".into()))?;
+
+        let bridge = queries::find_bridge_by_token_hash(state.db.read(), &hash_refresh_token(token))
+            .await?
+            .ok_or(AppError::AuthError("Invalid bridge token".into()))?;
+
+        Ok(BridgeAuth(bridge))
+    }
+}
+
 /// Optional auth extractor — returns None if no valid token present.
 /// Useful for endpoints that behave differently for authenticated users.
 #[derive(Debug, Clone)]
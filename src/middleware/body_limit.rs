@@ -0,0 +1,199 @@
+//! Per-route JSON body limits and a structural guard on JSON bodies.
+//!
+//! JSON requests are buffered up to their route's size limit and scanned
+//! before any handler deserializes them: a body nested deeper than
+//! [`MAX_JSON_DEPTH`] or holding more than [`MAX_JSON_VALUES`] values is
+//! rejected, so a hostile payload can't blow the stack or allocate an
+//! outsized `serde_json::Value` tree. Non-JSON bodies (uploads) pass through
+//! untouched and keep their own `DefaultBodyLimit`.
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::errors::AppError;
+
+/// axum's own default, kept for routes not listed in [`ROUTE_LIMITS`].
+pub const DEFAULT_JSON_LIMIT: usize = 2 * 1024 * 1024;
+/// Message imports carry up to 200 encrypted messages per batch.
+pub const IMPORT_MESSAGES_LIMIT: usize = 16 * 1024 * 1024;
+/// A full server structure: up to 500 channels, 250 roles and their overwrites
+/// (plus, for a migration bundle, its members' role assignments).
+pub const RESTORE_LIMIT: usize = 8 * 1024 * 1024;
+
+pub const MAX_JSON_DEPTH: usize = 64;
+pub const MAX_JSON_VALUES: usize = 250_000;
+
+/// Routes whose JSON bodies get a limit other than [`DEFAULT_JSON_LIMIT`].
+/// Paths are matched route templates relative to the version prefix. Limits
+/// above the default also need `DefaultBodyLimit::max` on the route so the
+/// `Json` extractor accepts them.
+const ROUTE_LIMITS: &[(Method, &str, usize)] = &[
+    (Method::POST, "/channels/:channel_id/import-messages", IMPORT_MESSAGES_LIMIT),
+    (Method::POST, "/servers/:server_id/restore", RESTORE_LIMIT),
+    (Method::POST, "/servers/:server_id/migrate/import", RESTORE_LIMIT),
+    (Method::POST, "/exports/verify", 1024 * 1024),
+    (Method::POST, "/auth/register", 64 * 1024),
+    (Method::POST, "/auth/login", 4 * 1024),
+    (Method::POST, "/beta/request-code", 4 * 1024),
+];
+
+/// JSON body limit for a matched route template.
+pub fn limit_for(method: &Method, route: &str) -> usize {
+    let route = crate::api_version::unversioned(route);
+    ROUTE_LIMITS
+        .iter()
+        .find(|(m, r, _)| m == method && *r == route)
+        .map_or(DEFAULT_JSON_LIMIT, |(_, _, limit)| *limit)
+}
+
+/// Check nesting depth and value count without parsing. Brackets and commas
+/// inside strings are skipped; the count is one per container plus one per
+/// separator, which bounds the number of values the body can decode to.
+fn check_structure(body: &[u8]) -> Result<(), AppError> {
+    let mut depth = 0usize;
+    let mut values = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &b in body {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                values += 1;
+                if depth > MAX_JSON_DEPTH {
+                    return Err(AppError::BadRequest(format!(
+                        "JSON body is nested too deeply (max {})",
+                        MAX_JSON_DEPTH
+                    ))
+                    .with_code("JSON_TOO_COMPLEX"));
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            b',' => values += 1,
+            _ => {}
+        }
+        if values > MAX_JSON_VALUES {
+            return Err(AppError::BadRequest(format!(
+                "JSON body has too many values (max {})",
+                MAX_JSON_VALUES
+            ))
+            .with_code("JSON_TOO_COMPLEX"));
+        }
+    }
+    Ok(())
+}
+
+fn is_json(req: &Request) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|mime| {
+            let mime = mime.trim();
+            mime.eq_ignore_ascii_case("application/json") || mime.ends_with("+json")
+        })
+}
+
+/// Enforce the route's JSON body limit and the structural guard.
+/// Must be installed with `route_layer` so the matched route is known.
+pub async fn json_guard_middleware(req: Request, next: Next) -> Response {
+    if !is_json(&req) {
+        return next.run(req).await;
+    }
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let limit = limit_for(req.method(), &route);
+    let too_large = || AppError::PayloadTooLarge(format!("Request body exceeds {} bytes", limit));
+
+    // Refuse up front when the client announces an oversized body
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared.is_some_and(|len| len > limit) {
+        return too_large().into_response();
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::to_bytes(body, limit).await {
+        Ok(bytes) => bytes,
+        Err(_) => return too_large().into_response(),
+    };
+    if let Err(e) = check_structure(&bytes) {
+        return e.into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn listed_routes_get_their_own_limit() {
+        assert_eq!(
+            limit_for(&Method::POST, "/api/v1/channels/:channel_id/import-messages"),
+            IMPORT_MESSAGES_LIMIT
+        );
+        assert_eq!(limit_for(&Method::POST, "/api/v2/beta/request-code"), 4 * 1024);
+        assert_eq!(limit_for(&Method::POST, "/api/v1/servers"), DEFAULT_JSON_LIMIT);
+    }
+
+    #[test]
+    fn structure_guard_counts_depth_and_values_outside_strings() {
+        assert!(check_structure(br#"{"a":[1,2,{"b":"[[[,,,"}]}"#).is_ok());
+        assert!(check_structure(br#"{"a":"\"[[["}"#).is_ok());
+
+        let deep = "[".repeat(MAX_JSON_DEPTH + 1);
+        assert!(check_structure(deep.as_bytes()).is_err());
+        let within = "[".repeat(MAX_JSON_DEPTH) + &"]".repeat(MAX_JSON_DEPTH);
+        assert!(check_structure(within.as_bytes()).is_ok());
+
+        let wide = format!("[{}0]", "0,".repeat(MAX_JSON_VALUES));
+        assert!(check_structure(wide.as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn oversized_and_deep_bodies_are_rejected() {
+        let app = Router::new()
+            .route("/beta/request-code", post(|body: String| async move { body }))
+            .route_layer(middleware::from_fn(json_guard_middleware));
+        let send = |body: String| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/beta/request-code")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        let ok = send(r#"{"email":"a@b.c"}"#.into()).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        let big = send(format!(r#"{{"email":"{}"}}"#, "a".repeat(8 * 1024))).await.unwrap();
+        assert_eq!(big.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let deep = send("[".repeat(100)).await.unwrap();
+        assert_eq!(deep.status(), StatusCode::BAD_REQUEST);
+    }
+}
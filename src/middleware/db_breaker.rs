@@ -0,0 +1,90 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::db::pool_monitor::{PoolMonitor, BREAKER_COOLDOWN};
+
+/// Shed API requests with a 503 while the primary pool is saturated, rather
+/// than letting each one hang waiting for a connection. Otherwise counts the
+/// request as in flight for the pool's waiter estimate. Admin routes are never
+/// shed, so operators can still read pool metrics during an overload.
+pub async fn db_breaker_middleware(
+    State(monitor): State<PoolMonitor>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if monitor.is_open() && !is_admin_route(&req) {
+        monitor.record_shed();
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, BREAKER_COOLDOWN.as_secs().to_string())],
+            Json(json!({
+                "error": "The server is overloaded, retry shortly",
+                "status": StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                "code": "DB_POOL_SATURATED",
+                "trace_id": super::trace_id::current(),
+            })),
+        )
+            .into_response();
+    }
+    let _in_flight = monitor.track();
+    next.run(req).await
+}
+
+fn is_admin_route(req: &Request) -> bool {
+    let route = req.extensions().get::<MatchedPath>().map_or(req.uri().path(), |p| p.as_str());
+    crate::api_version::unversioned(route).starts_with("/admin/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn open_breaker_sheds_with_503_and_retry_after() {
+        let monitor = PoolMonitor::new(Duration::from_millis(100));
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                monitor.clone(),
+                db_breaker_middleware,
+            ));
+        let request = || Request::builder().uri("/ok").body(Body::empty()).unwrap();
+
+        let res = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        monitor.observe(Duration::from_millis(150), Instant::now());
+        let res = app.oneshot(request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()[header::RETRY_AFTER], "5");
+        assert_eq!(monitor.shed(), 1);
+        assert_eq!(monitor.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn open_breaker_lets_admin_routes_through() {
+        let monitor = PoolMonitor::new(Duration::from_millis(100));
+        let app = Router::new()
+            .route("/api/v1/admin/db-pool", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                monitor.clone(),
+                db_breaker_middleware,
+            ));
+
+        monitor.observe(Duration::from_millis(150), Instant::now());
+        assert!(monitor.is_open());
+        let request = Request::builder().uri("/api/v1/admin/db-pool").body(Body::empty()).unwrap();
+        let res = app.oneshot(request).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(monitor.shed(), 0);
+    }
+}
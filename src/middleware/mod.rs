@@ -1,8 +1,17 @@
 pub mod auth;
+pub mod body_limit;
+pub mod db_breaker;
 pub mod rate_limit;
+pub mod timeout;
+pub mod trace_id;
 
-pub use auth::{AdminUser, AuthUser};
+pub use auth::{AdminUser, AuthUser, BridgeAuth, StaffUser};
+pub use body_limit::json_guard_middleware;
+pub use db_breaker::db_breaker_middleware;
 pub use rate_limit::{
-    rate_limit_middleware, spawn_rate_limit_cleanup, spawn_user_rate_limit_cleanup, RateLimiter,
+    policy_rate_limit_middleware, rate_limit_middleware, spawn_rate_limit_cleanup,
+    spawn_rate_policy_cleanup, spawn_user_rate_limit_cleanup, RatePolicies, RateLimiter,
     UserRateLimiter,
 };
+pub use timeout::{latency_budget_middleware, LatencyBudgets};
+pub use trace_id::trace_id_middleware;
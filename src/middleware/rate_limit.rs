@@ -1,18 +1,25 @@
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Instant;
 
 use axum::{
-    extract::{ConnectInfo, Request},
-    http::StatusCode,
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
+    Json,
 };
 use dashmap::DashMap;
 use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
 use uuid::Uuid;
 
+use crate::auth::{user_id_from_claims, validate_access_token};
+use crate::config::AppConfig;
+use crate::AppState;
+
 type HmacSha256 = Hmac<Sha256>;
 
 /// Hash an IP address so we never store raw IPs in memory.
@@ -51,21 +58,39 @@ impl RateLimiter {
         }
     }
 
+    /// The same limiter (sharing its counters) with a different limit, for a
+    /// limit that can change on config reload.
+    pub fn with_max_requests(mut self, max_requests: u32) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
     /// Returns true if the request should be allowed.
     pub fn check(&self, ip: IpAddr) -> bool {
+        self.acquire(ip).is_ok()
+    }
+
+    /// Count a request. When the limit is exceeded, returns the number of
+    /// seconds until the window resets.
+    pub fn acquire(&self, ip: IpAddr) -> Result<(), u64> {
         let hashed = hash_ip(ip, &*self.ip_hash_key);
         let now = Instant::now();
         let mut entry = self.state.entry(hashed).or_insert((0, now));
         let (count, window_start) = entry.value_mut();
 
         // Reset window if expired
-        if now.duration_since(*window_start).as_secs() >= self.window_secs {
+        let elapsed = now.duration_since(*window_start).as_secs();
+        if elapsed >= self.window_secs {
             *count = 0;
             *window_start = now;
         }
 
         *count += 1;
-        *count <= self.max_requests
+        if *count <= self.max_requests {
+            Ok(())
+        } else {
+            Err(self.window_secs.saturating_sub(elapsed).max(1))
+        }
     }
 
     /// Periodic cleanup of expired entries to prevent unbounded growth.
@@ -109,6 +134,20 @@ impl UserRateLimiter {
         *count <= self.max_requests
     }
 
+    /// Read-only view of a user's current window: (requests used, limit, seconds until reset).
+    /// Does not count as a request.
+    pub fn usage(&self, user_id: Uuid) -> (u32, u32, u64) {
+        let Some(entry) = self.state.get(&user_id) else {
+            return (0, self.max_requests, 0);
+        };
+        let (count, window_start) = *entry.value();
+        let elapsed = Instant::now().duration_since(window_start).as_secs();
+        if elapsed >= self.window_secs {
+            return (0, self.max_requests, 0);
+        }
+        (count, self.max_requests, self.window_secs - elapsed)
+    }
+
     pub fn cleanup(&self) {
         let now = Instant::now();
         self.state.retain(|_, (_, window_start)| {
@@ -138,19 +177,32 @@ pub fn extract_ip(req: &Request, trust_proxy: bool) -> IpAddr {
         .unwrap_or(IpAddr::from([127, 0, 0, 1]))
 }
 
+/// 429 response in the usual error shape, telling the client when to retry.
+fn too_many_requests(retry_after_secs: u64) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "error": "Rate limited",
+            "status": StatusCode::TOO_MANY_REQUESTS.as_u16(),
+            "code": "RATE_LIMITED",
+            "trace_id": super::trace_id::current(),
+            "retry_after": retry_after_secs,
+        })),
+    )
+        .into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+    response
+}
+
 /// Middleware that enforces rate limits. Returns 429 if limit exceeded.
-pub async fn rate_limit_middleware(
-    rate_limiter: RateLimiter,
-    req: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
+pub async fn rate_limit_middleware(rate_limiter: RateLimiter, req: Request, next: Next) -> Response {
     let ip = extract_ip(&req, rate_limiter.trust_proxy);
 
-    if !rate_limiter.check(ip) {
-        return Err(StatusCode::TOO_MANY_REQUESTS);
+    if let Err(retry_after) = rate_limiter.acquire(ip) {
+        return too_many_requests(retry_after);
     }
 
-    Ok(next.run(req).await)
+    next.run(req).await
 }
 
 /// Spawn a background task that cleans up stale rate limit entries every 5 minutes.
@@ -174,3 +226,448 @@ pub fn spawn_user_rate_limit_cleanup(limiter: UserRateLimiter) {
         }
     });
 }
+
+// ─── Policy Rate Limiting ──────────────────────────────
+
+/// Token bucket holding up to `burst` requests, refilled evenly so a full
+/// bucket's worth comes back every `window_secs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatePolicy {
+    pub burst: u32,
+    pub window_secs: u64,
+}
+
+impl RatePolicy {
+    fn refill_per_sec(&self) -> f64 {
+        self.burst as f64 / self.window_secs as f64
+    }
+
+    /// Outcome of taking one token when `tokens` were left after the take
+    /// attempt.
+    fn decision(&self, allowed: bool, tokens: f64) -> RateDecision {
+        let rate = self.refill_per_sec();
+        RateDecision {
+            allowed,
+            limit: self.burst,
+            remaining: tokens.floor().max(0.0) as u32,
+            reset_secs: ((self.burst as f64 - tokens) / rate).ceil().max(0.0) as u64,
+            retry_after_secs: if allowed { 0 } else { ((1.0 - tokens) / rate).ceil().max(1.0) as u64 },
+        }
+    }
+}
+
+/// Result of checking one bucket; rendered as `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next request would be allowed (0 when allowed).
+    pub retry_after_secs: u64,
+}
+
+/// Atomic take across several token buckets, shared by every instance when
+/// Redis is enabled. A token leaves every bucket or none, so a request denied
+/// by one bucket costs nothing in the others. ARGV is `now` followed by
+/// `burst, rate, ttl` per key. Returns {allowed, tokens left per key}, all as
+/// strings (Lua numbers truncate).
+static TOKEN_BUCKET_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r#"
+local now = tonumber(ARGV[1])
+local tokens = {}
+local allowed = 1
+for i, key in ipairs(KEYS) do
+    local burst = tonumber(ARGV[i * 3 - 1])
+    local rate = tonumber(ARGV[i * 3])
+    local state = redis.call('HMGET', key, 'tokens', 'ts')
+    local left = tonumber(state[1]) or burst
+    local ts = tonumber(state[2]) or now
+    tokens[i] = math.min(burst, left + math.max(0, now - ts) / 1000 * rate)
+    if tokens[i] < 1 then
+        allowed = 0
+    end
+end
+local result = {tostring(allowed)}
+for i, key in ipairs(KEYS) do
+    if allowed == 1 then
+        tokens[i] = tokens[i] - 1
+    end
+    redis.call('HSET', key, 'tokens', tostring(tokens[i]), 'ts', now)
+    redis.call('EXPIRE', key, ARGV[i * 3 + 1])
+    result[i + 1] = tostring(tokens[i])
+end
+return result
+"#,
+    )
+});
+
+/// The configured limits, replaced as a whole on config reload.
+struct PolicyRules {
+    per_user: Option<RatePolicy>,
+    /// (method, route template relative to the version prefix, policy)
+    routes: Vec<(Method, String, RatePolicy)>,
+}
+
+impl PolicyRules {
+    fn from_config(config: &AppConfig) -> Self {
+        Self {
+            per_user: (config.rate_limit_per_user > 0).then_some(RatePolicy {
+                burst: config.rate_limit_per_user,
+                window_secs: 60,
+            }),
+            routes: parse_route_policies(&config.rate_limit_routes),
+        }
+    }
+}
+
+/// Per-user and per-route token-bucket policies for the API.
+/// The coarse per-IP limit stays with [`RateLimiter`]; these buckets sit on
+/// top of it. Buckets live in memory, or in Redis (`RATE_LIMIT_REDIS`) so
+/// every instance draws from the same bucket.
+#[derive(Clone)]
+pub struct RatePolicies {
+    rules: Arc<watch::Sender<Arc<PolicyRules>>>,
+    /// bucket key -> (tokens, last refill)
+    buckets: Arc<DashMap<String, (f64, Instant)>>,
+    /// Serializes local takes so a multi-bucket take is all-or-nothing.
+    local_lock: Arc<Mutex<()>>,
+    redis: Option<redis::aio::ConnectionManager>,
+    /// Key for hashing IPs of unauthenticated callers in bucket keys.
+    ip_hash_key: Arc<[u8; 32]>,
+    trust_proxy: bool,
+}
+
+impl RatePolicies {
+    /// Build the policies from config. Pass a Redis connection to share
+    /// buckets across instances; the in-memory buckets remain the fallback
+    /// when Redis is unreachable.
+    pub fn new(config: &AppConfig, redis: Option<redis::aio::ConnectionManager>) -> Self {
+        // Shared buckets need the same IP hash on every instance, so derive
+        // the key from the JWT secret; otherwise keep it random per process.
+        let ip_hash_key: [u8; 32] = if redis.is_some() {
+            Sha256::new()
+                .chain_update(b"haven-rate-limit:")
+                .chain_update(config.jwt_secret.as_bytes())
+                .finalize()
+                .into()
+        } else {
+            let mut key = [0u8; 32];
+            use rand::RngCore;
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        };
+
+        Self {
+            rules: Arc::new(watch::channel(Arc::new(PolicyRules::from_config(config))).0),
+            buckets: Arc::new(DashMap::new()),
+            local_lock: Arc::new(Mutex::new(())),
+            redis,
+            ip_hash_key: Arc::new(ip_hash_key),
+            trust_proxy: config.trust_proxy,
+        }
+    }
+
+    /// Replace the per-user and per-route limits after a config reload.
+    /// Existing buckets keep their tokens.
+    pub fn reload(&self, config: &AppConfig) {
+        self.rules.send_replace(Arc::new(PolicyRules::from_config(config)));
+    }
+
+    fn rules(&self) -> Arc<PolicyRules> {
+        self.rules.borrow().clone()
+    }
+
+    fn is_empty(&self) -> bool {
+        let rules = self.rules();
+        rules.per_user.is_none() && rules.routes.is_empty()
+    }
+
+    fn per_user(&self) -> Option<RatePolicy> {
+        self.rules().per_user
+    }
+
+    fn route_policy(&self, method: &Method, route: &str) -> Option<RatePolicy> {
+        let route = crate::api_version::unversioned(route);
+        self.rules()
+            .routes
+            .iter()
+            .find(|(m, r, _)| m == method && r == route)
+            .map(|(_, _, policy)| *policy)
+    }
+
+    /// Take one token from every bucket in `buckets`, or from none of them
+    /// if any is empty. Decisions come back in the same order.
+    pub async fn take(&self, buckets: &[(&str, RatePolicy)]) -> Vec<RateDecision> {
+        if let Some(mut redis) = self.redis.clone() {
+            let mut invocation = TOKEN_BUCKET_SCRIPT.prepare_invoke();
+            invocation.arg(chrono::Utc::now().timestamp_millis());
+            for (key, policy) in buckets {
+                invocation
+                    .key(format!("haven:ratelimit:{}", key))
+                    .arg(policy.burst)
+                    .arg(policy.refill_per_sec())
+                    .arg(policy.window_secs * 2);
+            }
+            let result: Result<Vec<String>, redis::RedisError> = invocation.invoke_async(&mut redis).await;
+            match result {
+                Ok(result) if result.len() == buckets.len() + 1 => {
+                    let allowed = result[0] == "1";
+                    return buckets
+                        .iter()
+                        .zip(&result[1..])
+                        .map(|((_, policy), tokens)| policy.decision(allowed, tokens.parse().unwrap_or(0.0)))
+                        .collect();
+                }
+                Ok(result) => tracing::warn!("Unexpected Redis rate limit reply {:?}, using local buckets", result),
+                Err(e) => tracing::warn!("Redis rate limit check failed, using local buckets: {}", e),
+            }
+        }
+        self.take_local(buckets)
+    }
+
+    fn take_local(&self, buckets: &[(&str, RatePolicy)]) -> Vec<RateDecision> {
+        let _guard = self.local_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+
+        // Refill every bucket first, one entry at a time (holding two DashMap
+        // entries at once can deadlock on a shared shard).
+        let refilled: Vec<f64> = buckets
+            .iter()
+            .map(|(key, policy)| {
+                let mut entry = self
+                    .buckets
+                    .entry(key.to_string())
+                    .or_insert((policy.burst as f64, now));
+                let (tokens, last) = entry.value_mut();
+                let elapsed = now.duration_since(*last).as_secs_f64();
+                *tokens = (*tokens + elapsed * policy.refill_per_sec()).min(policy.burst as f64);
+                *last = now;
+                *tokens
+            })
+            .collect();
+
+        let allowed = refilled.iter().all(|&tokens| tokens >= 1.0);
+        buckets
+            .iter()
+            .zip(refilled)
+            .map(|((key, policy), mut tokens)| {
+                if allowed {
+                    if let Some(mut entry) = self.buckets.get_mut(*key) {
+                        entry.0 -= 1.0;
+                    }
+                    tokens -= 1.0;
+                }
+                policy.decision(allowed, tokens)
+            })
+            .collect()
+    }
+
+    /// Drop buckets idle long enough to have refilled completely; a missing
+    /// bucket is treated as full, so this never changes a decision.
+    pub fn cleanup(&self) {
+        let rules = self.rules();
+        let longest = rules
+            .routes
+            .iter()
+            .map(|(_, _, p)| p.window_secs)
+            .chain(rules.per_user.map(|p| p.window_secs))
+            .max()
+            .unwrap_or(0);
+        let now = Instant::now();
+        self.buckets
+            .retain(|_, (_, last)| now.duration_since(*last).as_secs() < longest);
+    }
+}
+
+/// Parse `RATE_LIMIT_ROUTES`: comma-separated `METHOD /route=BURST/SECS`
+/// entries, e.g. `POST /channels/:channel_id/messages=30/60`. Routes are
+/// templates relative to the version prefix (`/api/v1`, `/api/v2`). Invalid
+/// entries are logged and skipped.
+pub fn parse_route_policies(spec: &str) -> Vec<(Method, String, RatePolicy)> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let parsed = entry.split_once('=').and_then(|(route, limit)| {
+                let (method, path) = route.trim().split_once(' ')?;
+                let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes()).ok()?;
+                let (burst, window) = limit.trim().split_once('/')?;
+                let policy = RatePolicy {
+                    burst: burst.trim().parse().ok().filter(|&b| b > 0)?,
+                    window_secs: window.trim().parse().ok().filter(|&w| w > 0)?,
+                };
+                let path = path.trim();
+                path.starts_with('/').then(|| (method, path.to_string(), policy))
+            });
+            if parsed.is_none() {
+                tracing::warn!("Ignoring invalid RATE_LIMIT_ROUTES entry: {}", entry);
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateDecision) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(decision.reset_secs));
+}
+
+/// Enforce the per-route and per-user policies. Must be installed with
+/// `route_layer` so the matched route is known.
+///
+/// Callers are identified by their access token's user; unauthenticated
+/// callers share per-route buckets by hashed IP. Responses carry
+/// `X-RateLimit-*` headers for the tightest bucket that applied, and a 429
+/// carries `Retry-After`.
+pub async fn policy_rate_limit_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let policies = &state.rate_policies;
+    if policies.is_empty() {
+        return next.run(req).await;
+    }
+
+    // Only the signature is checked here; bans and revocation are the
+    // extractors' job.
+    let user_id = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .and_then(|token| validate_access_token(token, &state.config).ok())
+        .and_then(|claims| user_id_from_claims(&claims).ok());
+    let caller = match user_id {
+        Some(user_id) => format!("u:{}", user_id),
+        None => format!(
+            "ip:{}",
+            hex::encode(hash_ip(extract_ip(&req, policies.trust_proxy), &*policies.ip_hash_key))
+        ),
+    };
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let method = req.method().clone();
+
+    let mut buckets: Vec<(String, RatePolicy)> = Vec::new();
+    if let Some(policy) = policies.route_policy(&method, &route) {
+        // Versions share a bucket: the same route under /api/v2 is no escape hatch
+        let key = format!("route:{} {}:{}", method, crate::api_version::unversioned(&route), caller);
+        buckets.push((key, policy));
+    }
+    if let (Some(user_id), Some(policy)) = (user_id, policies.per_user()) {
+        buckets.push((format!("user:{}", user_id), policy));
+    }
+    if buckets.is_empty() {
+        return next.run(req).await;
+    }
+
+    // Both buckets are checked in one take, so a request the per-user bucket
+    // refuses doesn't also spend a route token (and vice versa).
+    let buckets: Vec<(&str, RatePolicy)> = buckets.iter().map(|(key, policy)| (key.as_str(), *policy)).collect();
+    let decisions = policies.take(&buckets).await;
+    // Report the bucket that ran dry, not one that merely wasn't charged.
+    let denied = decisions
+        .iter()
+        .filter(|d| !d.allowed)
+        .min_by_key(|d| (d.remaining, std::cmp::Reverse(d.retry_after_secs)));
+    if let Some(denied) = denied {
+        let mut response = too_many_requests(denied.retry_after_secs);
+        set_rate_limit_headers(response.headers_mut(), denied);
+        return response;
+    }
+    let tightest = decisions.into_iter().min_by_key(|d| d.remaining);
+
+    let mut response = next.run(req).await;
+    if let Some(decision) = tightest {
+        set_rate_limit_headers(response.headers_mut(), &decision);
+    }
+    response
+}
+
+/// Spawn cleanup for the policy buckets.
+pub fn spawn_rate_policy_cleanup(policies: RatePolicies) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+            policies.cleanup();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_route_policies_and_skips_invalid_entries() {
+        let routes = parse_route_policies(
+            "POST /channels/:channel_id/messages=30/60, get /gifs/search=10/10, PUT nope=1/1, POST /x=0/60, junk",
+        );
+        assert_eq!(
+            routes,
+            vec![
+                (Method::POST, "/channels/:channel_id/messages".to_string(), RatePolicy { burst: 30, window_secs: 60 }),
+                (Method::GET, "/gifs/search".to_string(), RatePolicy { burst: 10, window_secs: 10 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_bucket_drains_then_refuses() {
+        let mut config = AppConfig::test_default();
+        config.rate_limit_per_user = 0;
+        config.rate_limit_routes = "GET /ping=2/60".into();
+        let policies = RatePolicies::new(&config, None);
+        let policy = policies.route_policy(&Method::GET, "/api/v1/ping").unwrap();
+
+        let first = policies.take_local(&[("k", policy)])[0];
+        assert!(first.allowed);
+        assert_eq!((first.limit, first.remaining), (2, 1));
+        assert!(policies.take_local(&[("k", policy)])[0].allowed);
+        let denied = policies.take_local(&[("k", policy)])[0];
+        assert!(!denied.allowed);
+        assert_eq!(denied.remaining, 0);
+        assert!(denied.retry_after_secs >= 1 && denied.retry_after_secs <= 30);
+        assert!(policies.take_local(&[("other", policy)])[0].allowed);
+    }
+
+    #[test]
+    fn denied_multi_bucket_take_spends_nothing() {
+        let mut config = AppConfig::test_default();
+        config.rate_limit_per_user = 0;
+        config.rate_limit_routes = "GET /ping=1/60".into();
+        let policies = RatePolicies::new(&config, None);
+        let policy = policies.route_policy(&Method::GET, "/api/v1/ping").unwrap();
+        let roomy = RatePolicy { burst: 5, window_secs: 60 };
+
+        assert!(policies.take_local(&[("route", policy)])[0].allowed);
+        let denied = policies.take_local(&[("route", policy), ("user", roomy)]);
+        assert!(denied.iter().all(|d| !d.allowed));
+        assert_eq!(denied[0].remaining, 0);
+
+        // The per-user bucket was refilled but not charged.
+        let next = policies.take_local(&[("user", roomy)])[0];
+        assert!(next.allowed);
+        assert_eq!(next.remaining, 4);
+    }
+
+    #[test]
+    fn reload_replaces_limits_for_every_clone() {
+        let mut config = AppConfig::test_default();
+        config.rate_limit_per_user = 0;
+        config.rate_limit_routes = String::new();
+        let policies = RatePolicies::new(&config, None);
+        assert!(policies.is_empty());
+
+        config.rate_limit_per_user = 120;
+        config.rate_limit_routes = "GET /ping=2/60".into();
+        policies.clone().reload(&config);
+        assert_eq!(policies.per_user(), Some(RatePolicy { burst: 120, window_secs: 60 }));
+        assert!(policies.route_policy(&Method::GET, "/api/v2/ping").is_some());
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dashmap::DashMap;
+use serde_json::json;
+
+/// Routes that submit long-running work (large uploads, cascading deletes)
+/// and get the long budget. Everything else is interactive.
+/// Paths are matched route templates relative to the version prefix.
+const JOB_ROUTES: &[(Method, &str)] = &[
+    (Method::POST, "/attachments/upload"),
+    (Method::PUT, "/attachments/uploads/:upload_id"),
+    (Method::POST, "/attachments/uploads/:upload_id/finalize"),
+    (Method::POST, "/users/avatar"),
+    (Method::POST, "/users/banner"),
+    (Method::PUT, "/users/me/avatar"),
+    (Method::PUT, "/users/me/banner"),
+    (Method::POST, "/servers/:server_id/icon"),
+    (Method::PATCH, "/servers/:server_id/members/:user_id"),
+    (Method::POST, "/servers/:server_id/emojis"),
+    (Method::PUT, "/keys/backup"),
+    (Method::DELETE, "/admin/users/:user_id"),
+];
+
+/// Per-route latency budgets with a count of budget violations per route.
+#[derive(Clone)]
+pub struct LatencyBudgets {
+    interactive: Duration,
+    job: Duration,
+    /// "METHOD /route/template" -> number of requests aborted for exceeding their budget
+    violations: Arc<DashMap<String, u64>>,
+}
+
+impl LatencyBudgets {
+    pub fn new(interactive: Duration, job: Duration) -> Self {
+        Self {
+            interactive,
+            job,
+            violations: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn interactive(&self) -> Duration {
+        self.interactive
+    }
+
+    pub fn job(&self) -> Duration {
+        self.job
+    }
+
+    /// Budget for a matched route template.
+    pub fn budget_for(&self, method: &Method, route: &str) -> Duration {
+        let route = crate::api_version::unversioned(route);
+        if JOB_ROUTES.iter().any(|(m, r)| m == method && *r == route) {
+            self.job
+        } else {
+            self.interactive
+        }
+    }
+
+    /// Violation counts per route, most frequent first.
+    pub fn violations(&self) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self
+            .violations
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    fn record_violation(&self, key: String) {
+        *self.violations.entry(key).or_insert(0) += 1;
+    }
+}
+
+/// Abort handlers that exceed their route's latency budget with a 504.
+/// Must be installed with `route_layer` so the matched route is known.
+pub async fn latency_budget_middleware(
+    State(budgets): State<LatencyBudgets>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let method = req.method().clone();
+    let budget = budgets.budget_for(&method, &route);
+
+    match tokio::time::timeout(budget, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            let key = format!("{} {}", method, route);
+            tracing::warn!("Latency budget of {:?} exceeded: {}", budget, key);
+            budgets.record_violation(key);
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(json!({
+                    "error": "Request exceeded its latency budget",
+                    "status": StatusCode::GATEWAY_TIMEOUT.as_u16(),
+                    "code": "LATENCY_BUDGET_EXCEEDED",
+                    "trace_id": super::trace_id::current(),
+                    "budget_ms": budget.as_millis() as u64,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn budgets() -> LatencyBudgets {
+        LatencyBudgets::new(Duration::from_millis(50), Duration::from_secs(5))
+    }
+
+    #[test]
+    fn job_routes_get_the_long_budget() {
+        let b = budgets();
+        assert_eq!(b.budget_for(&Method::POST, "/api/v1/attachments/upload"), b.job());
+        assert_eq!(b.budget_for(&Method::DELETE, "/api/v1/admin/users/:user_id"), b.job());
+        assert_eq!(b.budget_for(&Method::GET, "/api/v1/admin/users/:user_id"), b.interactive());
+        assert_eq!(b.budget_for(&Method::GET, "/api/v1/servers"), b.interactive());
+    }
+
+    #[tokio::test]
+    async fn slow_handler_returns_504_and_records_violation() {
+        let b = budgets();
+        let app = Router::new()
+            .route(
+                "/slow/:id",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    "late"
+                }),
+            )
+            .route("/fast", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(b.clone(), latency_budget_middleware));
+
+        let res = app
+            .clone()
+            .oneshot(Request::builder().uri("/fast").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = app
+            .oneshot(Request::builder().uri("/slow/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(b.violations(), vec![("GET /slow/:id".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Per-request trace ids.
+//!
+//! Every request gets an id: the caller's `X-Request-Id` if it sent a sane
+//! one, otherwise a fresh one. It is echoed back in the `X-Request-Id`
+//! response header, recorded on the request's tracing span, and included as
+//! `trace_id` in error bodies, so a user's bug report can be matched to the
+//! server logs.
+
+use axum::{
+    extract::Request,
+    http::{header::HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// The trace id of the request being handled, if called from within one.
+pub fn current() -> Option<String> {
+    TRACE_ID.try_with(Clone::clone).ok()
+}
+
+/// Accept a caller-supplied id only if it is short and plain enough to log.
+fn sanitize(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?;
+    let plain = !id.is_empty()
+        && id.len() <= 64
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    plain.then(|| id.to_string())
+}
+
+pub async fn trace_id_middleware(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(sanitize)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    let header = HeaderValue::from_str(&id).expect("trace ids are plain ASCII");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+    let mut response = TRACE_ID.scope(id, next.run(req)).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_ids_are_accepted() {
+        assert_eq!(sanitize(&HeaderValue::from_static("abc-123_X")).as_deref(), Some("abc-123_X"));
+        assert!(sanitize(&HeaderValue::from_static("")).is_none());
+        assert!(sanitize(&HeaderValue::from_static("has space")).is_none());
+        assert!(sanitize(&HeaderValue::from_str(&"a".repeat(65)).unwrap()).is_none());
+    }
+
+    #[tokio::test]
+    async fn current_is_scoped_to_the_request() {
+        assert!(current().is_none());
+        let seen = TRACE_ID.scope("t1".into(), async { current() }).await;
+        assert_eq!(seen.as_deref(), Some("t1"));
+    }
+}
@@ -1,12 +1,14 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
 // ─── Pagination ────────────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PaginationQuery {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
@@ -45,7 +47,7 @@ pub struct User {
     pub custom_status_emoji: Option<String>,
     pub avatar_url: Option<String>,
     pub banner_url: Option<String>,
-    pub dm_privacy: String, // "everyone", "friends_only", "server_members"
+    pub dm_privacy: String, // "everyone", "friends_only", "server_members", "friends_strict"
     pub encrypted_profile: Option<Vec<u8>>,
     pub is_instance_admin: bool,
     pub is_system: bool,
@@ -65,12 +67,13 @@ pub struct UserBasic {
     pub banner_url: Option<String>,
     pub dm_privacy: String,
     pub is_instance_admin: bool,
+    pub instance_role: Option<String>,
     pub is_system: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserPublic {
     pub id: Uuid,
     pub username: String,
@@ -117,7 +120,7 @@ impl From<User> for UserPublic {
 
 // ─── Auth Requests / Responses ─────────────────────────
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequest {
     #[validate(length(min = 3, max = 32, message = "Username must be 3-32 characters"))]
     #[validate(custom(function = "validate_username"))]
@@ -139,29 +142,48 @@ pub struct RegisterRequest {
     pub pow_challenge: String,
     pub pow_nonce: String,
 
-    /// Registration invite code (required when REGISTRATION_INVITE_ONLY=true)
+    /// Registration invite code (required when REGISTRATION_MODE=invite)
     pub invite_code: Option<String>,
 
     /// Cloudflare Turnstile token (required when TURNSTILE_SECRET_KEY is set)
     pub turnstile_token: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
     pub totp_code: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthResponse {
     pub access_token: String,
     pub refresh_token: String,
     pub user: UserPublic,
 }
 
+/// Registration returns auth tokens, or, under `REGISTRATION_MODE=approval`,
+/// the new account's id with `202 Accepted` while it awaits an operator.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum RegisterResponse {
+    Success(Box<AuthResponse>),
+    PendingApproval { pending_approval: bool, user_id: Uuid },
+}
+
+impl axum::response::IntoResponse for RegisterResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match &self {
+            RegisterResponse::Success(_) => axum::http::StatusCode::OK,
+            RegisterResponse::PendingApproval { .. } => axum::http::StatusCode::ACCEPTED,
+        };
+        (status, axum::Json(self)).into_response()
+    }
+}
+
 /// Login endpoint returns either full auth tokens or a TOTP challenge.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(untagged)]
 pub enum LoginResponse {
     Success(Box<AuthResponse>),
@@ -174,14 +196,14 @@ impl axum::response::IntoResponse for LoginResponse {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
 // ─── Proof-of-Work Challenge ──────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PowChallengeResponse {
     pub challenge: String,
     /// Number of leading zero bits required in SHA-256(challenge + nonce)
@@ -193,13 +215,13 @@ pub struct PowChallengeResponse {
 
 // ─── TOTP ──────────────────────────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct TotpSetupResponse {
     pub secret: String,
     pub qr_code_uri: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct TotpVerifyRequest {
     pub code: String,
 }
@@ -216,20 +238,28 @@ pub struct PreKey {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct KeyBundle {
     pub identity_key: String,       // base64
     pub signed_prekey: String,      // base64
     pub signed_prekey_sig: String,  // base64
     pub one_time_prekey: Option<String>, // base64, consumed on fetch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub one_time_prekey_id: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UploadPreKeysRequest {
     pub prekeys: Vec<String>, // base64-encoded public keys
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateSignedPreKeyRequest {
+    pub signed_prekey: String,           // base64
+    pub signed_prekey_signature: String, // base64
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateKeysRequest {
     pub identity_key: String,          // base64
     pub signed_prekey: String,         // base64
@@ -249,12 +279,41 @@ pub struct Server {
     pub is_system: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateServerRequest {
     pub encrypted_meta: String, // base64
 }
 
-#[derive(Debug, Serialize)]
+/// Deleting a server re-authenticates the owner: their password, plus a
+/// TOTP code when two-factor auth is enabled.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteServerRequest {
+    pub password: String,
+    #[serde(default)]
+    pub totp_code: Option<String>,
+}
+
+/// A server in its deletion grace period.
+#[derive(Debug, Clone, FromRow)]
+pub struct DeletedServer {
+    pub id: Uuid,
+    pub encrypted_meta: Vec<u8>,
+    pub icon_url: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletedServerResponse {
+    pub id: Uuid,
+    pub encrypted_meta: String, // base64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+    /// When the deleted-servers job purges the server for good.
+    pub purge_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerResponse {
     pub id: Uuid,
     pub encrypted_meta: String, // base64
@@ -288,9 +347,12 @@ pub struct Channel {
     pub encrypted: bool,
     pub export_allowed: bool,
     pub message_ttl: Option<i32>,
+    pub owner_id: Option<Uuid>, // group DMs only
+    pub topic: Option<String>,  // unencrypted channels only
+    pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateChannelRequest {
     pub encrypted_meta: String, // base64
     pub channel_type: Option<String>,
@@ -299,9 +361,24 @@ pub struct CreateChannelRequest {
     pub is_private: Option<bool>,
     pub encrypted: Option<bool>,
     pub message_ttl: Option<i32>,
+    /// Permission template applied as the channel's @everyone overwrite
+    pub template_id: Option<Uuid>,
+}
+
+/// PATCH /channels/:channel_id. Absent fields are left alone, `null` clears
+/// them. Encrypted channels keep their topic and description inside
+/// `encrypted_meta` and send that instead of the plaintext fields.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PatchChannelRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub topic: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub description: Option<Option<String>>,
+    /// Re-encrypted metadata carrying the new topic (base64)
+    pub encrypted_meta: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChannelResponse {
     pub id: Uuid,
     pub server_id: Option<Uuid>,
@@ -319,6 +396,35 @@ pub struct ChannelResponse {
     pub export_allowed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message_ttl: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl From<Channel> for ChannelResponse {
+    fn from(c: Channel) -> Self {
+        Self {
+            id: c.id,
+            server_id: c.server_id,
+            encrypted_meta: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &c.encrypted_meta),
+            channel_type: c.channel_type,
+            position: c.position,
+            created_at: c.created_at,
+            category_id: c.category_id,
+            dm_status: c.dm_status,
+            last_message_id: None,
+            is_private: c.is_private,
+            encrypted: c.encrypted,
+            export_allowed: c.export_allowed,
+            message_ttl: c.message_ttl,
+            owner_id: c.owner_id,
+            topic: c.topic,
+            description: c.description,
+        }
+    }
 }
 
 // ─── Channel Categories ──────────────────────────────
@@ -330,50 +436,76 @@ pub struct ChannelCategory {
     pub name: String,
     pub position: i32,
     pub created_at: DateTime<Utc>,
+    pub collapsed_by_default: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateCategoryRequest {
     pub name: String,
     pub position: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateCategoryRequest {
     pub name: Option<String>,
     pub position: Option<i32>,
+    pub collapsed_by_default: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReorderCategoriesRequest {
     pub order: Vec<CategoryPosition>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CategoryPosition {
     pub id: Uuid,
     pub position: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReorderChannelsRequest {
     pub order: Vec<ChannelPosition>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChannelPosition {
     pub id: Uuid,
     pub position: i32,
     pub category_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize)]
+/// PATCH /servers/:server_id/channels/positions. The server's whole layout
+/// in display order: every category, and every channel with the category it
+/// sits in. Positions are numbered from the order given.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateChannelPositionsRequest {
+    pub categories: Vec<Uuid>,
+    pub channels: Vec<ChannelPlacement>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChannelPlacement {
+    pub id: Uuid,
+    pub category_id: Option<Uuid>,
+}
+
+/// The layout as applied, also sent as `ChannelPositionsUpdated`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChannelPositionsResponse {
+    pub categories: Vec<CategoryPosition>,
+    pub channels: Vec<ChannelPosition>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CategoryResponse {
     pub id: Uuid,
     pub server_id: Uuid,
     pub name: String,
     pub position: i32,
     pub created_at: DateTime<Utc>,
+    /// Shown collapsed until the member expands it
+    pub collapsed_by_default: bool,
 }
 
 impl From<ChannelCategory> for CategoryResponse {
@@ -384,15 +516,105 @@ impl From<ChannelCategory> for CategoryResponse {
             name: c.name,
             position: c.position,
             created_at: c.created_at,
+            collapsed_by_default: c.collapsed_by_default,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SetChannelCategoryRequest {
     pub category_id: Option<Uuid>,
 }
 
+// ─── Channel Retention ─────────────────────────────────
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ChannelRetentionPolicy {
+    pub channel_id: Uuid,
+    pub max_age_days: Option<i32>,
+    pub max_messages: Option<i32>,
+    pub archive: bool,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A policy as the channel-retention job sees it.
+#[derive(Debug, Clone, FromRow)]
+pub struct RetentionSweepTarget {
+    pub channel_id: Uuid,
+    pub server_id: Uuid,
+    pub actor_id: Uuid,
+    pub max_age_days: Option<i32>,
+    pub max_messages: Option<i32>,
+    pub archive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionMode {
+    /// Keep every message (the default)
+    Forever,
+    /// Delete messages older than `value` days
+    Days,
+    /// Keep only the newest `value` messages
+    Messages,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetChannelRetentionRequest {
+    pub mode: RetentionMode,
+    /// Days or message count; required unless `mode` is `forever`.
+    pub value: Option<i32>,
+    /// Ship purged messages to backup storage first (needs BACKUP_STORAGE_DIR or BACKUP_S3_BUCKET).
+    #[serde(default)]
+    pub archive: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChannelRetentionResponse {
+    pub channel_id: Uuid,
+    pub mode: RetentionMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<i32>,
+    pub archive: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ChannelRetentionResponse {
+    pub fn new(channel_id: Uuid, policy: Option<ChannelRetentionPolicy>) -> Self {
+        let Some(p) = policy else {
+            return Self {
+                channel_id,
+                mode: RetentionMode::Forever,
+                value: None,
+                archive: false,
+                updated_at: None,
+            };
+        };
+        let (mode, value) = match (p.max_age_days, p.max_messages) {
+            (Some(days), _) => (RetentionMode::Days, Some(days)),
+            (None, Some(count)) => (RetentionMode::Messages, Some(count)),
+            (None, None) => (RetentionMode::Forever, None),
+        };
+        Self { channel_id, mode, value, archive: p.archive, updated_at: Some(p.updated_at) }
+    }
+}
+
+// ─── Channel Mention Settings ──────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetChannelMentionSettingsRequest {
+    pub suppress_role_mentions: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChannelMentionSettingsResponse {
+    pub channel_id: Uuid,
+    /// Role mentions are shown but notify no one and don't count as mentions
+    pub suppress_role_mentions: bool,
+}
+
 // ─── Members ───────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -415,7 +637,7 @@ pub struct ChannelMember {
 
 // ─── Messages ──────────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
 pub struct Message {
     pub id: Uuid,
     pub channel_id: Uuid,
@@ -427,10 +649,13 @@ pub struct Message {
     pub sender_id: Option<Uuid>,  // for edit authorization; null for legacy messages
     pub edited_at: Option<DateTime<Utc>>,
     pub reply_to_id: Option<Uuid>,
-    pub message_type: String,     // "user" or "system"
+    pub message_type: String,     // "user", "system", "forwarded", "voice_note" or "location"
+    pub forwarded_from_id: Option<Uuid>,
+    pub forwarded_from_channel_id: Option<Uuid>,
+    pub forwarded_from_timestamp: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     pub channel_id: Uuid,
     pub sender_token: String,    // base64
@@ -438,9 +663,188 @@ pub struct SendMessageRequest {
     pub expires_at: Option<DateTime<Utc>>,
     pub has_attachments: bool,
     pub reply_to_id: Option<Uuid>,
+    #[serde(default)]
+    pub mentions: Option<MessageMentions>,
+    /// Makes this a `voice_note` message; needs an attached audio file
+    #[serde(default)]
+    pub voice_note: Option<VoiceNote>,
+    /// Makes this a `location` message, optionally with a live session
+    #[serde(default)]
+    pub location: Option<LocationShare>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Player metadata of a voice note, computed by the sending client. The
+/// audio itself is the message's attachment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VoiceNote {
+    pub duration_ms: u32,
+    /// Peak amplitude per bar, 0–255, at most 256 bars
+    pub waveform: Vec<u8>,
+}
+
+/// A location share. The coordinates are in the encrypted body; with
+/// `live_duration_secs` the sender may push position updates over WS until
+/// the session is stopped or expires.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LocationShare {
+    #[serde(default)]
+    pub live_duration_secs: Option<u32>,
+}
+
+/// Where a live location session stands, returned with its message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LiveLocationState {
+    pub expires_at: DateTime<Utc>,
+    /// Set once the sender stopped sharing or the session expired
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Latest encrypted position update (base64)
+    pub encrypted_position: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct LiveLocationSession {
+    pub message_id: Uuid,
+    pub channel_id: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub last_position: Option<Vec<u8>>,
+    pub last_update_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<LiveLocationSession> for LiveLocationState {
+    fn from(s: LiveLocationSession) -> Self {
+        Self {
+            expires_at: s.expires_at,
+            ended_at: s.ended_at,
+            encrypted_position: s
+                .last_position
+                .map(|p| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, p)),
+            updated_at: s.last_update_at,
+        }
+    }
+}
+
+/// What a `system` message records. Stored as its parameters rather than
+/// prose, so clients phrase and localize it themselves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SystemEvent {
+    MemberJoined,
+    MemberLeft,
+    MemberKicked,
+    MemberAdded { added_by: Uuid },
+    MemberRemoved { removed_by: Uuid },
+    OwnerChanged,
+    /// The channel's encrypted name or other metadata changed
+    ChannelRenamed,
+    /// The new topic of an unencrypted channel; absent when it was cleared
+    /// or lives in `encrypted_meta`
+    TopicChanged {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        topic: Option<String>,
+    },
+    MessagePinned { message_id: Uuid },
+    MessageUnpinned { message_id: Uuid },
+    CallEnded { duration_secs: u64 },
+    CallMissed,
+    EncryptionEnabled,
+    EncryptionDisabled,
+    DisappearingMessagesSet { ttl_secs: i32 },
+    DisappearingMessagesOff,
+    ExportConsentEnabled,
+    ExportConsentDisabled,
+}
+
+/// A system message's event and the member it is about (the actor, or the
+/// subject of membership changes). This is also the plaintext body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SystemMessage {
+    #[serde(flatten)]
+    pub event: SystemEvent,
+    pub user_id: Uuid,
+    /// Display name at the time, for members clients can no longer look up
+    pub username: String,
+}
+
+impl SystemMessage {
+    pub fn new(event: SystemEvent, user_id: Uuid, username: impl Into<String>) -> Self {
+        Self { event, user_id, username: username.into() }
+    }
+}
+
+/// Who a message mentions. Bodies are encrypted, so the sending client
+/// declares its mentions alongside; they drive mention counts and
+/// notifications, not rendering.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MessageMentions {
+    #[serde(default)]
+    pub user_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub role_ids: Vec<Uuid>,
+    /// `@everyone`; needs MENTION_EVERYONE in server channels
+    #[serde(default)]
+    pub everyone: bool,
+}
+
+impl MessageMentions {
+    pub fn is_empty(&self) -> bool {
+        self.user_ids.is_empty() && self.role_ids.is_empty() && !self.everyone
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ForwardMessageRequest {
+    pub target_channel_id: Uuid,
+    pub sender_token: String,           // base64
+    /// Copy re-encrypted for the target channel (base64). May be omitted only
+    /// when both channels are unencrypted, in which case the original body is
+    /// re-posted as-is.
+    pub encrypted_body: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub has_attachments: bool,
+}
+
+/// The message a reply points at, nested in list responses so clients can
+/// render the quote without another round trip.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ReplyPreview {
+    pub id: Uuid,
+    pub sender_token: String,    // base64
+    pub encrypted_body: String,  // base64
+    pub timestamp: DateTime<Utc>,
+    pub has_attachments: bool,
+    pub edited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+}
+
+impl From<&MessageResponse> for ReplyPreview {
+    fn from(m: &MessageResponse) -> Self {
+        Self {
+            id: m.id,
+            sender_token: m.sender_token.clone(),
+            encrypted_body: m.encrypted_body.clone(),
+            timestamp: m.timestamp,
+            has_attachments: m.has_attachments,
+            edited: m.edited,
+            message_type: m.message_type.clone(),
+        }
+    }
+}
+
+/// Where a forwarded message came from.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ForwardedFrom {
+    pub message_id: Uuid,
+    pub channel_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct MessageResponse {
     pub id: Uuid,
     pub channel_id: Uuid,
@@ -452,8 +856,74 @@ pub struct MessageResponse {
     pub edited: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reply_to_id: Option<Uuid>,
+    /// Preview of the message replied to; filled in on history reads and
+    /// absent when it has been deleted or expired.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<ReplyPreview>,
+    /// Live replies to this message; filled in on history reads.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reply_count: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<ForwardedFrom>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachment_previews: Vec<AttachmentPreview>,
+    /// Per-emoji reaction counts; filled in on history reads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<ReactionCount>,
+    /// Set per viewer when the author is someone the viewer blocked, so the
+    /// client can collapse the message.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_blocked: bool,
+    /// Recipients that blocked the author; consumed during fan-out to set
+    /// `from_blocked` per connection and never sent on the wire.
+    #[serde(skip)]
+    pub blocked_by: Vec<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<MessageMentions>,
+    /// Duration and waveform of a `voice_note` message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_note: Option<VoiceNote>,
+    /// Session of a live `location` message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_location: Option<LiveLocationState>,
+    /// Event and parameters of a `system` message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemMessage>,
+    /// Set per viewer on fan-out when the message mentions them, so clients
+    /// (and push) can tell a mention from ordinary traffic.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mentions_me: bool,
+    /// Users the mentions notify (direct mentions and holders of mentioned
+    /// roles); consumed during fan-out like `blocked_by`.
+    #[serde(skip)]
+    pub mentioned: Vec<Uuid>,
+    /// Set per viewer on fan-out when their notification rules say the
+    /// message shouldn't notify them. It is delivered all the same.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub silent: bool,
+    /// Recipients whose notification rules silence the message; consumed
+    /// during fan-out like `blocked_by`.
+    #[serde(skip)]
+    pub silenced: Vec<Uuid>,
+}
+
+fn is_zero(n: &i64) -> bool {
+    *n == 0
+}
+
+impl MessageResponse {
+    /// Personalize a fanned-out copy for `viewer_id`.
+    pub fn for_viewer(mut self, viewer_id: Uuid) -> Self {
+        self.from_blocked = self.blocked_by.contains(&viewer_id);
+        self.blocked_by = Vec::new();
+        self.mentions_me = self.mentions.as_ref().is_some_and(|m| m.everyone) || self.mentioned.contains(&viewer_id);
+        self.mentioned = Vec::new();
+        self.silent = self.silenced.contains(&viewer_id);
+        self.silenced = Vec::new();
+        self
+    }
 }
 
 impl From<Message> for MessageResponse {
@@ -463,6 +933,19 @@ impl From<Message> for MessageResponse {
         } else {
             None
         };
+        let system = if m.message_type == "system" {
+            serde_json::from_slice(&m.encrypted_body).ok()
+        } else {
+            None
+        };
+        let forwarded_from = match (m.forwarded_from_id, m.forwarded_from_channel_id, m.forwarded_from_timestamp) {
+            (Some(message_id), Some(channel_id), Some(timestamp)) => Some(ForwardedFrom {
+                message_id,
+                channel_id,
+                timestamp,
+            }),
+            _ => None,
+        };
         Self {
             id: m.id,
             channel_id: m.channel_id,
@@ -479,7 +962,22 @@ impl From<Message> for MessageResponse {
             has_attachments: m.has_attachments,
             edited: m.edited_at.is_some(),
             reply_to_id: m.reply_to_id,
+            reply_to: None,
+            reply_count: 0,
             message_type,
+            forwarded_from,
+            attachment_previews: Vec::new(),
+            reactions: Vec::new(),
+            from_blocked: false,
+            blocked_by: Vec::new(),
+            mentions: None,
+            voice_note: None,
+            live_location: None,
+            system,
+            mentions_me: false,
+            mentioned: Vec::new(),
+            silent: false,
+            silenced: Vec::new(),
         }
     }
 }
@@ -495,12 +993,256 @@ pub struct Attachment {
     pub size_bucket: i32,
     pub created_at: DateTime<Utc>,
     pub file_hash: Option<String>,
+    pub thumbnail_key: Option<String>,
+    pub blurhash: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub size_bytes: i64,
+    pub server_id: Option<Uuid>,
+    /// BLAKE3 content address of the shared blob; absent on older attachments
+    pub content_hash: Option<String>,
+    /// Malware scan state; absent when the attachment wasn't scanned
+    pub scan_status: Option<String>,
+    pub scan_signature: Option<String>,
+    pub scanned_at: Option<DateTime<Utc>>,
+    /// Declared MIME type; only kept in unencrypted channels
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    Pending,
+    Clean,
+    Infected,
+    /// The scanner could not be reached or returned an error
+    Failed,
+}
+
+impl ScanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanStatus::Pending => "pending",
+            ScanStatus::Clean => "clean",
+            ScanStatus::Infected => "infected",
+            ScanStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(ScanStatus::Pending),
+            "clean" => Some(ScanStatus::Clean),
+            "infected" => Some(ScanStatus::Infected),
+            "failed" => Some(ScanStatus::Failed),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+/// Thumbnail + blurhash for an image attachment in an unencrypted channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AttachmentPreview {
+    pub attachment_id: Uuid,
+    pub blurhash: String,
+    pub width: i32,  // of the original image
+    pub height: i32,
+    pub thumbnail_url: String,
+}
+
+impl AttachmentPreview {
+    pub fn new(attachment_id: Uuid, blurhash: String, width: i32, height: i32) -> Self {
+        Self {
+            attachment_id,
+            blurhash,
+            width,
+            height,
+            thumbnail_url: format!("/api/v1/attachments/{}/thumbnail", attachment_id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UploadResponse {
     pub attachment_id: Uuid,
     pub storage_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<AttachmentPreview>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UploadSession {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub user_id: Uuid,
+    pub content_type: String,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub part_ids: Vec<Uuid>,
+    pub file_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A simple upload's blob reference, held until a message links it.
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingUpload {
+    pub attachment_id: Uuid,
+    pub user_id: Uuid,
+    pub content_hash: String,
+    pub size_bytes: i64,
+    pub file_hash: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateUploadSessionRequest {
+    pub upload_length: u64,
+    pub content_type: String,
+    pub file_hash: Option<String>, // hex SHA-256, checked against blocked hashes
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadSessionResponse {
+    pub upload_id: Uuid,
+    pub upload_offset: i64,
+    pub upload_length: i64,
+    pub max_chunk_size: u64,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<UploadSession> for UploadSessionResponse {
+    fn from(s: UploadSession) -> Self {
+        Self {
+            upload_id: s.id,
+            upload_offset: s.upload_offset,
+            upload_length: s.upload_length,
+            max_chunk_size: crate::uploads::MAX_CHUNK_SIZE,
+            expires_at: s.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FinalizeUploadRequest {
+    pub message_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetUploadTierRequest {
+    pub upload_tier: String,
+    pub reason: Option<String>,
+}
+
+/// Upload limits set on one role or one channel.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct UploadLimit {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub role_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+    /// Largest file, in bytes; absent leaves it to the role or the tier
+    pub max_size_bytes: Option<i64>,
+    /// Uploads a member may have in progress at once; 0 forbids uploads
+    pub max_count: Option<i32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetUploadLimitRequest {
+    pub max_size_bytes: Option<i64>,
+    pub max_count: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AttachmentDownloadQuery {
+    /// Always download (`Content-Disposition: attachment`), even media
+    #[serde(default)]
+    pub download: bool,
+    /// File name to suggest when saving
+    pub filename: Option<String>,
+}
+
+/// A download URL an edge worker can verify without the database.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignedAttachmentUrl {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Query string of a signed attachment URL (see `signed_urls`).
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SignedAttachmentQuery {
+    /// Content-Type to serve
+    pub ct: String,
+    /// Content-Disposition to serve
+    pub cd: String,
+    /// Expiry, unix seconds
+    pub exp: i64,
+    /// Id of the signing key
+    pub kid: String,
+    /// base64url HMAC-SHA256 signature
+    pub sig: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MyPermissionsQuery {
+    /// Also report permissions and upload limits in this channel
+    pub channel_id: Option<Uuid>,
+}
+
+/// The upload limits that apply to a member, and where each came from
+/// ("tier", "role" or "channel").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EffectiveUploadLimits {
+    pub max_size_bytes: u64,
+    pub max_size_source: String,
+    /// Absent when uploads in progress aren't capped
+    pub max_count: Option<u32>,
+    pub max_count_source: String,
+}
+
+// ─── Server Insights ──────────────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ServerInsightsQuery {
+    /// Days to report, ending today (UTC); 1-90, default 30
+    pub days: Option<u32>,
+}
+
+/// One day's activity in a server, from the nightly rollup.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ServerInsightsDay {
+    pub day: chrono::NaiveDate,
+    pub messages: i32,
+    /// Members who sent at least one message that day
+    pub active_members: i32,
+    pub joins: i32,
+    pub leaves: i32,
+}
+
+/// A channel's activity over the reported days.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ChannelInsights {
+    pub channel_id: Uuid,
+    pub messages: i64,
+    /// Days with at least one message
+    pub active_days: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServerInsightsResponse {
+    /// Every day in the window, oldest first; days without activity are zero
+    pub days: Vec<ServerInsightsDay>,
+    /// Channels with messages in the window, most active first
+    pub channels: Vec<ChannelInsights>,
+    pub member_count: i64,
+    /// When the rollup last covered this server; absent before the first run
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 // ─── Sender Key Distributions ─────────────────────────
@@ -516,19 +1258,19 @@ pub struct SenderKeyDistribution {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct DistributeSenderKeyRequest {
     pub distributions: Vec<SenderKeyDistributionEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SenderKeyDistributionEntry {
     pub to_user_id: Uuid,
     pub distribution_id: Uuid,
     pub encrypted_skdm: String, // base64
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SenderKeyDistributionResponse {
     pub id: Uuid,
     pub channel_id: Uuid,
@@ -538,36 +1280,112 @@ pub struct SenderKeyDistributionResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChannelMemberKeyInfo {
     pub user_id: Uuid,
     pub identity_key: String, // base64
 }
 
-// ─── Key Backups ─────────────────────────────────────
+// ─── Devices ─────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
-pub struct KeyBackup {
+pub struct UserDevice {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub encrypted_data: Vec<u8>,
-    pub nonce: Vec<u8>,
-    pub salt: Vec<u8>,
-    pub version: i32,
+    pub name: String,
+    pub identity_key: Vec<u8>,
+    pub signed_prekey: Vec<u8>,
+    pub signed_prekey_sig: Vec<u8>,
     pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct UploadKeyBackupRequest {
-    pub encrypted_data: String, // base64
-    pub nonce: String,          // base64
-    pub salt: String,           // base64
-    pub version: Option<i32>,
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterDeviceRequest {
+    pub name: String,
+    pub identity_key: String,            // base64
+    pub signed_prekey: String,           // base64
+    pub signed_prekey_signature: String, // base64
 }
 
-#[derive(Debug, Serialize)]
-pub struct KeyBackupResponse {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetDeviceVerificationRequest {
+    pub status: String, // "verified", "blocked" or "unverified"
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub identity_key: String,      // base64
+    pub signed_prekey: String,     // base64
+    pub signed_prekey_sig: String, // base64
+    pub created_at: DateTime<Utc>,
+    /// The requester's verdict on this device; None = unverified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<String>,
+}
+
+impl DeviceResponse {
+    pub fn new(d: UserDevice, verification: Option<String>) -> Self {
+        let b64 = &base64::engine::general_purpose::STANDARD;
+        Self {
+            id: d.id,
+            user_id: d.user_id,
+            name: d.name,
+            identity_key: base64::Engine::encode(b64, &d.identity_key),
+            signed_prekey: base64::Engine::encode(b64, &d.signed_prekey),
+            signed_prekey_sig: base64::Engine::encode(b64, &d.signed_prekey_sig),
+            created_at: d.created_at,
+            verification,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceQueueQuery {
+    pub since: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueuedMessage {
+    pub seq: i64,
+    pub message: MessageResponse,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceQueueResponse {
+    pub messages: Vec<QueuedMessage>,
+    /// Pass back as `since` to acknowledge these messages and get the next batch.
+    pub next_since: i64,
+}
+
+// ─── Key Backups ─────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KeyBackup {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub encrypted_data: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub salt: Vec<u8>,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UploadKeyBackupRequest {
+    pub encrypted_data: String, // base64
+    pub nonce: String,          // base64
+    pub salt: String,           // base64
+    pub version: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyBackupResponse {
     pub encrypted_data: String, // base64
     pub nonce: String,          // base64
     pub salt: String,           // base64
@@ -575,13 +1393,142 @@ pub struct KeyBackupResponse {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct KeyBackupStatusResponse {
     pub has_backup: bool,
     pub version: Option<i32>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KeyBackupVersion {
+    pub user_id: Uuid,
+    pub version: i32,
+    pub algorithm: String,
+    pub auth_data: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateKeyBackupVersionRequest {
+    pub algorithm: String,
+    pub auth_data: String, // base64 — recovery public key + signatures, opaque to the server
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyBackupVersionResponse {
+    pub version: i32,
+    pub algorithm: String,
+    pub auth_data: String, // base64
+    pub count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KeyBackupSession {
+    pub channel_id: Uuid,
+    pub session_id: String,
+    pub first_message_index: i32,
+    pub forwarded_count: i32,
+    pub is_verified: bool,
+    pub session_data: Vec<u8>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BackupSessionKey {
+    pub channel_id: Uuid,
+    pub session_id: String,
+    pub first_message_index: i32,
+    #[serde(default)]
+    pub forwarded_count: i32,
+    #[serde(default)]
+    pub is_verified: bool,
+    pub session_data: String, // base64, encrypted to the backup's recovery key
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UploadBackupSessionsRequest {
+    pub sessions: Vec<BackupSessionKey>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BackupSessionsQuery {
+    pub channel_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BackupSessionResponse {
+    pub channel_id: Uuid,
+    pub session_id: String,
+    pub first_message_index: i32,
+    pub forwarded_count: i32,
+    pub is_verified: bool,
+    pub session_data: String, // base64
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<KeyBackupSession> for BackupSessionResponse {
+    fn from(s: KeyBackupSession) -> Self {
+        Self {
+            channel_id: s.channel_id,
+            session_id: s.session_id,
+            first_message_index: s.first_message_index,
+            forwarded_count: s.forwarded_count,
+            is_verified: s.is_verified,
+            session_data: base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                &s.session_data,
+            ),
+            updated_at: s.updated_at,
+        }
+    }
+}
+
+// ─── Key Transparency ────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct KeyLogEntry {
+    pub seq: i64,
+    pub user_id: Uuid,
+    pub identity_key: Vec<u8>,
+    pub leaf_hash: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyTransparencyHead {
+    pub tree_size: i64,
+    pub root_hash: String, // base64
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyTransparencyProofResponse {
+    pub user_id: Uuid,
+    pub identity_key: String, // base64
+    pub leaf_index: i64,
+    pub logged_at: DateTime<Utc>,
+    pub tree_size: i64,
+    pub root_hash: String,        // base64
+    pub audit_path: Vec<String>,  // base64, leaf to root
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct KeyTransparencyConsistencyQuery {
+    pub from: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyTransparencyConsistencyResponse {
+    pub from_size: i64,
+    pub tree_size: i64,
+    pub root_hash: String,   // base64
+    pub proof: Vec<String>,  // base64
+}
+
 // ─── Reactions ────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -595,7 +1542,7 @@ pub struct Reaction {
 }
 
 /// Aggregated reaction info for a single emoji on a message.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ReactionGroup {
     pub message_id: Uuid,
     pub emoji: String,
@@ -603,14 +1550,38 @@ pub struct ReactionGroup {
     pub user_ids: Vec<Uuid>,
 }
 
+/// Reaction count for one emoji, embedded in message responses.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    /// Whether the viewer is among the reactors.
+    pub me: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow, ToSchema)]
+pub struct Reactor {
+    pub user_id: Uuid,
+    pub reacted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReactorQuery {
+    /// Return reactors with a user ID greater than this (the last one seen).
+    pub after: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
 // ─── Link Previews ────────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LinkPreviewQuery {
     pub url: String,
 }
 
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Serialize, Default, ToSchema)]
 pub struct LinkPreviewResponse {
     pub url: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -625,14 +1596,34 @@ pub struct LinkPreviewResponse {
 
 // ─── Voice ────────────────────────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct VoiceTokenResponse {
     pub token: String,
     pub url: String,
     pub channel_id: Uuid,
+    /// Whether the token allows publishing a camera track (VIDEO)
+    pub can_video: bool,
+    /// Whether the token allows screen sharing (STREAM)
+    pub can_stream: bool,
 }
 
-#[derive(Debug, Serialize)]
+/// Short-lived TURN relay credentials (coturn REST API scheme).
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TurnCredentials {
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CallStartResponse {
+    pub channel_id: Uuid,
+    /// None when the instance has no TURN relay configured.
+    pub turn: Option<TurnCredentials>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct VoiceParticipantResponse {
     pub user_id: Uuid,
     pub username: String,
@@ -642,12 +1633,12 @@ pub struct VoiceParticipantResponse {
     pub server_deafened: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct VoiceMuteRequest {
     pub muted: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct VoiceDeafenRequest {
     pub deafened: bool,
 }
@@ -665,6 +1656,12 @@ pub enum WsClientMessage {
         expires_at: Option<DateTime<Utc>>,
         attachment_ids: Option<Vec<Uuid>>,
         reply_to_id: Option<Uuid>,
+        #[serde(default)]
+        mentions: Option<MessageMentions>,
+        #[serde(default)]
+        voice_note: Option<VoiceNote>,
+        #[serde(default)]
+        location: Option<LocationShare>,
     },
     /// Edit a previously sent message
     EditMessage {
@@ -699,14 +1696,39 @@ pub enum WsClientMessage {
     CallEnd { channel_id: Uuid },
     /// Ping (keepalive)
     Ping,
+    /// Heartbeat, sent every `heartbeat_interval_ms` from Hello. `seq` is
+    /// echoed back in HeartbeatAck so the client can match acks and measure
+    /// round-trip time.
+    Heartbeat { seq: u64 },
     /// Mark a channel as read (up to latest message)
     MarkRead { channel_id: Uuid },
     /// Resume a previous session after reconnect
     Resume { session_id: Uuid },
+    /// Load a server's member list as `MemberChunk` events. With `query`,
+    /// only members whose name starts with it (at most `limit`, default 100).
+    RequestMembers {
+        server_id: Uuid,
+        #[serde(default)]
+        query: Option<String>,
+        #[serde(default)]
+        limit: Option<u32>,
+        /// Echoed back on every chunk so the client can match responses.
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+    /// After a reconnect: fetch servers, channels and roles changed since
+    /// the version of the last `GET /sync` or `SyncDelta`.
+    Sync { since_version: i64 },
+    /// Push a new encrypted position to one of the sender's live location
+    /// sessions (base64)
+    UpdateLiveLocation { message_id: Uuid, encrypted_position: String },
+    /// Stop sharing a live location before it expires
+    StopLiveLocation { message_id: Uuid },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", content = "payload")]
+#[allow(clippy::large_enum_variant)]
 pub enum WsServerMessage {
     /// New message in a subscribed channel
     NewMessage(MessageResponse),
@@ -716,6 +1738,12 @@ pub enum WsServerMessage {
         channel_id: Uuid,
         encrypted_body: String,
     },
+    /// A thumbnail and blurhash were generated for an attachment
+    AttachmentPreviewReady {
+        channel_id: Uuid,
+        message_id: Uuid,
+        preview: AttachmentPreview,
+    },
     /// Typing indicator from another user
     UserTyping {
         channel_id: Uuid,
@@ -728,10 +1756,23 @@ pub enum WsServerMessage {
     Error { message: String },
     /// Pong (keepalive response)
     Pong,
+    /// Acknowledges a Heartbeat
+    HeartbeatAck { seq: u64 },
+    /// This instance is shutting down and closes the socket right after;
+    /// reconnect and resume from the last sync version.
+    Reconnect,
     /// Subscribed confirmation
     Subscribed { channel_id: Uuid },
     /// New sender key distributions are available for a channel
     SenderKeysUpdated { channel_id: Uuid },
+    /// A user added or removed a device; re-fetch their device list before
+    /// encrypting to them again
+    DeviceListUpdated { user_id: Uuid },
+    /// Your one-time prekeys dropped below the low watermark; upload more
+    PreKeysLow { remaining: i64 },
+    /// A member was removed from an encrypted channel; sender keys they held
+    /// are burned and each recipient must rotate before sending again
+    SenderKeyRotationRequired { channel_id: Uuid, removed_user_id: Uuid },
     /// A message was deleted
     MessageDeleted {
         message_id: Uuid,
@@ -761,6 +1802,17 @@ pub enum WsServerMessage {
     FriendRequestAccepted { user_id: Uuid, username: String, friendship_id: Uuid },
     /// A friend was removed
     FriendRemoved { user_id: Uuid },
+    /// Your relationship with another user changed (None = no relationship left)
+    RelationshipUpdate { user_id: Uuid, relationship: Option<RelationshipResponse> },
+    /// A group DM's members, owner or encrypted name/icon changed. Sent to
+    /// current members and to anyone just removed. Members should
+    /// redistribute their sender key to `member_ids` on receipt.
+    GroupDmUpdated {
+        channel_id: Uuid,
+        owner_id: Option<Uuid>,
+        member_ids: Vec<Uuid>,
+        encrypted_meta: String,
+    },
     /// A DM message request was received (pending channel)
     DmRequestReceived { channel_id: Uuid, from_user_id: Uuid },
     /// A message was pinned
@@ -823,11 +1875,37 @@ pub enum WsServerMessage {
         user_id: Uuid,
         timed_out_until: Option<DateTime<Utc>>,
     },
+    /// A member's per-server profile (nickname, server avatar) changed
+    MemberUpdated {
+        server_id: Uuid,
+        user_id: Uuid,
+        nickname: Option<String>,
+        server_avatar_url: Option<String>,
+    },
+    /// One chunk of a member list requested with `RequestMembers`
+    MemberChunk {
+        server_id: Uuid,
+        members: Vec<ServerMemberResponse>,
+        chunk_index: u32,
+        chunk_count: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        nonce: Option<String>,
+    },
+    /// Reply to `Sync`
+    SyncDelta(SyncDelta),
     /// Read state synced across devices
     ReadStateUpdated {
         channel_id: Uuid,
         last_read_at: DateTime<Utc>,
     },
+    /// A DM member received (`kind` "delivered") or read ("read") every
+    /// message up to `up_to`
+    ReceiptUpdated {
+        channel_id: Uuid,
+        user_id: Uuid,
+        kind: String,
+        up_to: DateTime<Utc>,
+    },
     /// Sent on initial connection with session info
     Hello {
         session_id: Uuid,
@@ -839,6 +1917,60 @@ pub enum WsServerMessage {
     },
     /// Server structure changed (channels/categories created/updated/deleted)
     ServerUpdated { server_id: Uuid },
+    /// The role hierarchy was reordered at once
+    RolePositionsUpdated {
+        server_id: Uuid,
+        roles: Vec<RolePosition>,
+    },
+    /// A member's roles were replaced; `role_ids` is the full new set
+    MemberRolesUpdated {
+        server_id: Uuid,
+        user_id: Uuid,
+        role_ids: Vec<Uuid>,
+    },
+    /// The whole channel and category layout was replaced at once
+    ChannelPositionsUpdated {
+        server_id: Uuid,
+        categories: Vec<CategoryPosition>,
+        channels: Vec<ChannelPosition>,
+    },
+    /// A backup was restored over the server. `channel_id_map` maps each
+    /// backup channel id (the replaced channel's id, for a backup of this
+    /// server) to its restored channel, so open clients can remap their state
+    /// instead of refetching everything
+    ServerRestored {
+        server_id: Uuid,
+        channel_id_map: std::collections::HashMap<String, String>,
+        counts: RestoreCounts,
+    },
+    /// A scheduled event was created or changed, including its RSVP counts
+    EventUpdated {
+        server_id: Uuid,
+        event: ServerEventResponse,
+    },
+    /// A scheduled event was deleted
+    EventDeleted { server_id: Uuid, event_id: Uuid },
+    /// A moderator approved or denied this user's application to join a server
+    ApplicationDecided {
+        server_id: Uuid,
+        application_id: Uuid,
+        status: String,
+    },
+    /// A restore job started by this user made progress
+    RestoreProgress {
+        job_id: Uuid,
+        server_id: Uuid,
+        phase: String,
+        done: i64,
+        total: i64,
+        status: String,
+    },
+    /// An event the user RSVPed to is about to start
+    EventReminder {
+        server_id: Uuid,
+        event_id: Uuid,
+        starts_at: DateTime<Utc>,
+    },
     /// Channel disappearing-message settings changed
     ChannelSettingsUpdated {
         channel_id: Uuid,
@@ -850,18 +1982,45 @@ pub enum WsServerMessage {
         channel_id: Uuid,
         message_ids: Vec<Uuid>,
     },
+    /// An operator published an instance-wide announcement
+    Announcement(AnnouncementResponse),
+    /// The user dismissed an announcement on another device
+    AnnouncementDismissed { announcement_id: Uuid },
+    /// An attachment in the server failed its malware scan and was
+    /// quarantined (sent to members who can manage messages)
+    AttachmentQuarantined {
+        server_id: Uuid,
+        channel_id: Uuid,
+        message_id: Uuid,
+        attachment_id: Uuid,
+        signature: String,
+    },
+    /// The sender of a live location pushed a new encrypted position
+    LiveLocationUpdated {
+        channel_id: Uuid,
+        message_id: Uuid,
+        encrypted_position: String,
+        updated_at: DateTime<Utc>,
+    },
+    /// A live location session ended: stopped by its sender, or `expired`
+    LiveLocationEnded {
+        channel_id: Uuid,
+        message_id: Uuid,
+        expired: bool,
+    },
     /// Session expired or invalid — do a full reconnect
     InvalidSession,
 }
 
 // ─── Presence ─────────────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PresenceQuery {
     pub user_ids: String, // comma-separated UUIDs
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PresenceEntry {
     pub user_id: Uuid,
     pub status: String,
@@ -890,7 +2049,7 @@ pub struct RefreshToken {
 }
 
 /// Response for the session list endpoint.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SessionResponse {
     pub id: Uuid,
     pub family_id: Option<Uuid>,
@@ -913,15 +2072,20 @@ pub struct Invite {
     pub use_count: i32,
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    pub temporary: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateInviteRequest {
     pub max_uses: Option<i32>,
     pub expires_in_hours: Option<f64>,
+    /// Members who join with this invite are removed when they go offline
+    /// without having been given a role
+    #[serde(default)]
+    pub temporary: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct InviteResponse {
     pub id: Uuid,
     pub code: String,
@@ -930,6 +2094,7 @@ pub struct InviteResponse {
     pub use_count: i32,
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    pub temporary: bool,
 }
 
 impl From<Invite> for InviteResponse {
@@ -942,6 +2107,7 @@ impl From<Invite> for InviteResponse {
             use_count: i.use_count,
             expires_at: i.expires_at,
             created_at: i.created_at,
+            temporary: i.temporary,
         }
     }
 }
@@ -958,9 +2124,12 @@ pub struct RegistrationInvite {
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub email_hash: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Event a generated beta code was made for
+    pub label: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RegistrationInviteResponse {
     pub id: Uuid,
     pub code: String,
@@ -985,25 +2154,81 @@ impl From<RegistrationInvite> for RegistrationInviteResponse {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AdminCreateInvitesRequest {
     pub count: Option<u32>,
 }
 
 // ─── Beta Code Request ──────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct BetaCodeRequest {
     pub email: String,
+    /// Language for the email (e.g. `de`, `pt-BR`); defaults to the
+    /// request's Accept-Language
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct BetaCodeResponse {
     pub success: bool,
     pub message: String,
 }
 
-#[derive(Debug, Serialize)]
+/// A beta code as operators see it. Beta codes are registration invites with
+/// no creator: emailed on request, or generated in bulk for an event.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AdminBetaCode {
+    pub id: Uuid,
+    pub code: String,
+    /// SHA-256 of the email the code was sent to; `None` for generated codes
+    pub email_hash: Option<String>,
+    pub label: Option<String>,
+    pub status: String, // "active", "redeemed", "expired" or "revoked"
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminBetaCodeQuery {
+    /// Only codes in this status: active, redeemed, expired or revoked
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GenerateBetaCodesRequest {
+    pub count: u32,
+    /// Defaults to `BETA_CODE_EXPIRY_DAYS`
+    pub expiry_days: Option<i64>,
+    /// Event the codes are for, shown in the code list
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetBetaCodeLimitRequest {
+    /// New cap on emailed beta codes; `null` goes back to `BETA_CODE_LIMIT`
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MemberSearchQuery {
+    pub q: String,
+    /// Only members holding this role.
+    pub role_id: Option<Uuid>,
+    /// Only members who are (true) or are not (false) currently timed out.
+    pub timed_out: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerMemberResponse {
     pub user_id: Uuid,
     pub username: String,
@@ -1012,19 +2237,50 @@ pub struct ServerMemberResponse {
     pub joined_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub nickname: Option<String>,
+    /// Per-server avatar, shown instead of `avatar_url` inside this server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_avatar_url: Option<String>,
     pub role_ids: Vec<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timed_out_until: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_system: Option<bool>,
+    /// Action of the server's strictest `abuse` content filter this member's
+    /// registration score meets ("hide" or "warn"); the score itself is not shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub automod_action: Option<String>,
+    /// Joined with a temporary invite: removed on going offline while holding no role
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temporary: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateNicknameRequest {
     pub nickname: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// PATCH /servers/:server_id/members/:user_id. Absent fields are left alone,
+/// `null` clears them.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateMemberRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub nickname: Option<Option<String>>,
+    /// Base64-encoded PNG, JPEG, GIF or WebP.
+    #[serde(default, deserialize_with = "double_option")]
+    pub avatar: Option<Option<String>>,
+}
+
+/// Deserialize a nullable field so that an explicit `null` (`Some(None)`)
+/// can be told apart from an absent one (`None`).
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateServerRequest {
     pub system_channel_id: Option<Uuid>,
     pub encrypted_meta: Option<String>,
@@ -1032,7 +2288,7 @@ pub struct UpdateServerRequest {
 
 // ─── Channel Member Info ─────────────────────────────
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct ChannelMemberInfo {
     pub user_id: Uuid,
     pub username: String,
@@ -1043,15 +2299,20 @@ pub struct ChannelMemberInfo {
 
 // ─── Group DM ────────────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateGroupDmRequest {
     pub member_ids: Vec<Uuid>,
     pub encrypted_meta: String, // base64
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferGroupOwnerRequest {
+    pub user_id: Uuid,
+}
+
 // ─── Change Password ─────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChangePasswordRequest {
     pub current_password: String,
     pub new_password: String,
@@ -1059,7 +2320,7 @@ pub struct ChangePasswordRequest {
 
 // ─── User Profiles ───────────────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserProfileResponse {
     pub id: Uuid,
     pub username: String,
@@ -1085,7 +2346,7 @@ pub struct UserProfileResponse {
     pub is_system: Option<bool>,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct MutualFriendInfo {
     pub user_id: Uuid,
     pub username: String,
@@ -1093,7 +2354,7 @@ pub struct MutualFriendInfo {
     pub avatar_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateProfileRequest {
     pub display_name: Option<String>,
     pub about_me: Option<String>,
@@ -1113,26 +2374,44 @@ pub struct ProfileKeyDistribution {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ProfileKeyDistributionEntry {
     pub to_user_id: Uuid,
     pub encrypted_profile_key: String, // base64
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct DistributeProfileKeysRequest {
     pub distributions: Vec<ProfileKeyDistributionEntry>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ProfileKeyResponse {
     pub from_user_id: Uuid,
     pub encrypted_profile_key: String, // base64
 }
 
+// ─── Profile Media ───────────────────────────────────
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ProfileMedia {
+    pub user_id: Uuid,
+    pub slot: String, // "avatar", "banner", "server_avatar:<server_id>"
+    pub content_hash: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProfileMediaUsageResponse {
+    pub used_bytes: i64,
+    pub quota_bytes: u64,
+}
+
 // ─── Blocked Users ───────────────────────────────────
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct BlockedUserResponse {
     pub user_id: Uuid,
     pub username: String,
@@ -1155,16 +2434,19 @@ pub struct Role {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateRoleRequest {
     pub name: String,
     pub color: Option<String>,
     /// Permissions as string to avoid JS precision loss with i64
     pub permissions: Option<String>,
     pub position: Option<i32>,
+    /// Permission template whose allow bits become the role's permissions;
+    /// can't be combined with `permissions`
+    pub template_id: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateRoleRequest {
     pub name: Option<String>,
     pub color: Option<String>,
@@ -1172,7 +2454,7 @@ pub struct UpdateRoleRequest {
     pub position: Option<i32>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RoleResponse {
     pub id: Uuid,
     pub server_id: Uuid,
@@ -1200,9 +2482,77 @@ impl From<Role> for RoleResponse {
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// PUT /servers/:server_id/members/:user_id/roles. `role_id` adds one
+/// role; `role_ids` replaces the member's roles with exactly those.
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AssignRoleRequest {
-    pub role_id: Uuid,
+    pub role_id: Option<Uuid>,
+    pub role_ids: Option<Vec<Uuid>>,
+}
+
+/// PATCH /servers/:server_id/roles/positions. Every role except the default
+/// one, lowest first; positions are numbered 1, 2, 3… from this order.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateRolePositionsRequest {
+    pub roles: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RolePosition {
+    pub id: Uuid,
+    pub position: i32,
+}
+
+/// A named allow/deny bitfield pair kept by a server for new roles and
+/// channels.
+#[derive(Debug, Clone, FromRow)]
+pub struct PermissionTemplate {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub name: String,
+    pub allow_bits: i64,
+    pub deny_bits: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PermissionTemplateResponse {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub name: String,
+    /// Bits as strings to avoid JS precision loss
+    pub allow_bits: String,
+    pub deny_bits: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PermissionTemplate> for PermissionTemplateResponse {
+    fn from(t: PermissionTemplate) -> Self {
+        Self {
+            id: t.id,
+            server_id: t.server_id,
+            name: t.name,
+            allow_bits: t.allow_bits.to_string(),
+            deny_bits: t.deny_bits.to_string(),
+            created_at: t.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePermissionTemplateRequest {
+    pub name: String,
+    pub allow_bits: Option<String>,
+    pub deny_bits: Option<String>,
+}
+
+/// PATCH /servers/:server_id/permission-templates/:template_id. Absent
+/// fields are left alone.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePermissionTemplateRequest {
+    pub name: Option<String>,
+    pub allow_bits: Option<String>,
+    pub deny_bits: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -1215,7 +2565,7 @@ pub struct ChannelPermissionOverwrite {
     pub deny_bits: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SetOverwriteRequest {
     pub target_type: String,   // "role" or "member"
     pub target_id: Uuid,
@@ -1223,7 +2573,7 @@ pub struct SetOverwriteRequest {
     pub deny_bits: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct OverwriteResponse {
     pub id: Uuid,
     pub channel_id: Uuid,
@@ -1258,7 +2608,7 @@ pub struct Friendship {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct FriendResponse {
     pub id: Uuid,            // friendship ID
     pub user_id: Uuid,       // the other user
@@ -1272,25 +2622,62 @@ pub struct FriendResponse {
     pub is_system: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+/// One entry of `GET /users/me/relationships`, from the viewer's side.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RelationshipResponse {
+    pub user_id: Uuid,       // the other user
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,        // "friend", "incoming", "outgoing", "blocked"
+    pub friendship_id: Option<Uuid>,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct FriendRequestBody {
     pub username: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct DmRequestAction {
     pub action: String, // "accept" or "decline"
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateDmPrivacyRequest {
-    pub dm_privacy: String, // "everyone", "friends_only", "server_members"
+    pub dm_privacy: String, // "everyone", "friends_only", "server_members", "friends_strict"
 }
 
-// ─── Pinned Messages ────────────────────────────────
+// ─── DM Receipts ─────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
-pub struct PinnedMessage {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateReceiptPrivacyRequest {
+    pub receipt_privacy: String, // "all", "delivered", "none"
+}
+
+/// How far another DM member has received and read. Each field is `null`
+/// unless both that member and the viewer share that kind of receipt.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DmReceipt {
+    pub user_id: Uuid,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct ChannelReceiptRow {
+    pub user_id: Uuid,
+    pub receipt_privacy: String,
+    pub last_delivered_at: Option<DateTime<Utc>>,
+    pub last_read_at: Option<DateTime<Utc>>,
+}
+
+// ─── Pinned Messages ────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PinnedMessage {
     pub id: Uuid,
     pub channel_id: Uuid,
     pub message_id: Uuid,
@@ -1311,14 +2698,14 @@ pub struct Report {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateReportRequest {
     pub message_id: Uuid,
     pub channel_id: Uuid,
     pub reason: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ReportResponse {
     pub id: Uuid,
     pub message_id: Uuid,
@@ -1339,7 +2726,7 @@ pub struct Ban {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct BanResponse {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -1349,14 +2736,14 @@ pub struct BanResponse {
     pub created_at: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateBanRequest {
     pub reason: Option<String>,
 }
 
 // ─── Admin Report Triage ─────────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AdminReportResponse {
     pub id: Uuid,
     pub reporter_id: Uuid,
@@ -1374,20 +2761,21 @@ pub struct AdminReportResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateReportRequest {
     pub status: String,
     pub admin_notes: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ReportFilterQuery {
     pub status: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ReportCounts {
     pub pending: i64,
     pub reviewed: i64,
@@ -1404,9 +2792,11 @@ pub struct InstanceBan {
     pub reason: Option<String>,
     pub banned_by: Uuid,
     pub created_at: DateTime<Utc>,
+    /// Set for suspensions; the ban stops applying once it passes.
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct InstanceBanResponse {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -1415,11 +2805,14 @@ pub struct InstanceBanResponse {
     pub banned_by: Uuid,
     pub banned_by_username: String,
     pub created_at: String,
+    pub expires_at: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateInstanceBanRequest {
     pub reason: Option<String>,
+    /// Suspend until this time instead of banning permanently.
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 // ─── Content Filters ─────────────────────────────────
@@ -1435,7 +2828,7 @@ pub struct ContentFilter {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ContentFilterResponse {
     pub id: Uuid,
     pub pattern: String,
@@ -1445,9 +2838,11 @@ pub struct ContentFilterResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateContentFilterRequest {
     pub pattern: String,
+    /// "keyword" (default), "regex", or "abuse", whose pattern is the lowest
+    /// registration abuse score (1-100) the action applies to
     pub filter_type: Option<String>,
     pub action: Option<String>,
 }
@@ -1463,7 +2858,7 @@ pub struct BlockedHash {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct BlockedHashResponse {
     pub id: Uuid,
     pub hash: String,
@@ -1472,7 +2867,7 @@ pub struct BlockedHashResponse {
     pub created_at: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateBlockedHashRequest {
     pub hash: String,
     pub description: Option<String>,
@@ -1491,7 +2886,7 @@ pub struct CustomEmoji {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CustomEmojiResponse {
     pub id: Uuid,
     pub server_id: Uuid,
@@ -1516,19 +2911,20 @@ impl CustomEmoji {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CreateEmojiQuery {
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RenameEmojiRequest {
     pub name: String,
 }
 
 // ─── Delete Account ──────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct DeleteAccountRequest {
     pub password: String,
 }
@@ -1548,7 +2944,7 @@ pub struct AuditLogEntry {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuditLogResponse {
     pub id: Uuid,
     pub actor_id: Uuid,
@@ -1563,18 +2959,19 @@ pub struct AuditLogResponse {
 
 // ─── Moderation ──────────────────────────────────────
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct TimeoutMemberRequest {
     pub duration_seconds: i64,
     pub reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct BulkDeleteRequest {
     pub message_ids: Vec<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AuditLogQuery {
     pub limit: Option<i64>,
     pub before: Option<DateTime<Utc>>,
@@ -1582,24 +2979,86 @@ pub struct AuditLogQuery {
 
 // ─── Read States ─────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct ReadState {
     pub user_id: Uuid,
     pub channel_id: Uuid,
     pub last_read_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChannelUnreadInfo {
     pub channel_id: Uuid,
     pub last_message_id: Option<Uuid>,
     pub last_message_at: Option<DateTime<Utc>>,
     pub unread_count: i64,
+    /// Unread messages mentioning the user
+    pub mention_count: i64,
+}
+
+// ─── Initial Sync ────────────────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// Comma-separated `server_id:version` pairs the client already holds;
+    /// servers still at that version come back without channels and roles.
+    pub known: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SyncServer {
+    #[serde(flatten)]
+    pub server: ServerResponse,
+    /// Version behind the server's list ETags; send it back in `known`.
+    pub version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<ChannelResponse>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<RoleResponse>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncResponse {
+    /// Journal version this snapshot is at; pass it to the WS `Sync` command
+    /// after a reconnect.
+    pub version: i64,
+    pub servers: Vec<SyncServer>,
+    pub dm_channels: Vec<ChannelResponse>,
+    pub read_states: Vec<ChannelUnreadInfo>,
+    pub relationships: Vec<RelationshipResponse>,
+}
+
+/// One `sync_changes` row (the latest for its entity).
+#[derive(Debug, Clone, FromRow)]
+pub struct SyncChange {
+    pub version: i64,
+    pub server_id: Uuid,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub deleted: bool,
+}
+
+/// Answer to the WS `Sync` command: what changed after `since_version`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncDelta {
+    /// Version to send next time.
+    pub version: i64,
+    /// The journal no longer reaches back to `since_version`; fetch `GET /sync` instead.
+    pub full_sync_required: bool,
+    /// Servers to replace wholesale: joined, or changed at the server or role level.
+    pub servers: Vec<SyncServer>,
+    /// Created or updated channels in the remaining servers.
+    pub channels: Vec<ChannelResponse>,
+    /// Channels that were deleted or are no longer visible.
+    pub deleted_channels: Vec<Uuid>,
+    /// Servers the user left, was removed from, or that were deleted.
+    pub removed_servers: Vec<Uuid>,
 }
 
 // ─── Admin Dashboard ─────────────────────────────────
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AdminStats {
     pub total_users: i64,
     pub total_servers: i64,
@@ -1608,14 +3067,175 @@ pub struct AdminStats {
     pub active_connections: usize,
 }
 
-#[derive(Debug, Deserialize)]
+/// Instance-wide usage, as shown to staff and sent by the telemetry reporter.
+/// Counts only: no user, server or channel is identified.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InstanceUsageStats {
+    /// `TELEMETRY_INSTANCE_ID`, if set, so a fleet collector can tell instances apart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    pub version: String,
+    pub total_users: i64,
+    /// Users who connected in the last day.
+    pub daily_active_users: i64,
+    /// Users who connected in the last 30 days.
+    pub monthly_active_users: i64,
+    pub total_servers: i64,
+    pub total_messages: i64,
+    pub messages_last_day: i64,
+    pub messages_last_30_days: i64,
+    /// Attachments and profile media, counting deduplicated blobs once.
+    pub storage_bytes: i64,
+    pub active_connections: usize,
+    pub collected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatencyBudgetReport {
+    pub interactive_timeout_ms: u64,
+    pub job_timeout_ms: u64,
+    pub violations: Vec<LatencyBudgetViolation>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatencyBudgetViolation {
+    /// "METHOD /route/template"
+    pub route: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentGcReport {
+    pub dry_run: bool,
+    pub grace_hours: u32,
+    /// Orphaned attachments still inside the grace period
+    pub pending: i64,
+    /// Orphaned attachments past the grace period (what the next pass deletes)
+    pub collectable: i64,
+    /// Shared blobs with no references, still inside the grace period
+    pub blobs_pending: i64,
+    /// Shared blobs with no references past the grace period
+    pub blobs_collectable: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentGcRunResponse {
+    pub dry_run: bool,
+    pub marked: u64,
+    pub collectable: u64,
+    pub deleted: u64,
+    /// Simple uploads that expired unsent and gave up their blob reference
+    pub pending_released: u64,
+    /// Shared blobs deleted after their last attachment went
+    pub blobs_deleted: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WsHeartbeatReport {
+    pub heartbeat_interval_ms: u64,
+    pub heartbeat_timeout_ms: u64,
+    /// Open WebSocket connections on this instance.
+    pub connections: u64,
+    /// Heartbeats (and legacy Pings) received since startup.
+    pub heartbeats: u64,
+    /// Heartbeat intervals that passed without one.
+    pub missed: u64,
+    /// Connections closed for staying silent past the timeout.
+    pub reaped: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShadowReadReport {
+    pub sample_rate: f64,
+    pub queries: Vec<ShadowReadStats>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShadowReadStats {
+    pub query: String,
+    pub runs: u64,
+    pub mismatches: u64,
+    pub errors: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbReplicaReport {
+    pub strategy: String,
+    /// 0 = no lag cutoff.
+    pub max_lag_secs: u64,
+    #[schema(value_type = Vec<Object>)]
+    pub replicas: Vec<crate::db::ReplicaStatus>,
+}
+
+/// Connection counts of one pool at the time of the request.
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+pub struct DbPoolGauges {
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max_connections: u32,
+}
+
+/// How long the sampler waited to acquire a primary connection, over the
+/// last minute of samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DbAcquireLatency {
+    pub last_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbBreakerStatus {
+    /// API requests are being shed with 503s.
+    pub open: bool,
+    /// 0 = the breaker never opens.
+    pub saturation_ms: u64,
+    /// Times the breaker opened since startup.
+    pub trips: u64,
+    /// Requests shed since startup.
+    pub shed: u64,
+}
+
+/// A slow statement, grouped by fingerprint across argument values.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DbSlowQuery {
+    pub fingerprint: String,
+    /// Normalized statement: literals and placeholders as `?`.
+    pub statement: String,
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbPoolReport {
+    pub primary: DbPoolGauges,
+    pub replicas: Vec<DbPoolGauges>,
+    pub acquire: DbAcquireLatency,
+    /// API requests in flight on this instance.
+    pub in_flight: u64,
+    /// Estimated requests waiting for a primary connection: those in flight
+    /// beyond the connections in use, while none is idle.
+    pub waiting: u64,
+    pub breaker: DbBreakerStatus,
+    /// 0 = slow statements aren't recorded.
+    pub slow_query_ms: u64,
+    /// Slowest fingerprints by total time since startup.
+    pub slow_queries: Vec<DbSlowQuery>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AdminSearchQuery {
     pub search: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct AdminUserResponse {
     pub id: Uuid,
     pub username: String,
@@ -1623,126 +3243,1509 @@ pub struct AdminUserResponse {
     pub avatar_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub is_instance_admin: bool,
+    pub instance_role: Option<String>,
     pub server_count: i64,
+    /// Under an active instance ban or suspension.
+    pub is_banned: bool,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct SetAdminRequest {
-    pub is_admin: bool,
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AdminServerResponse {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub owner_username: String,
+    pub is_system: bool,
+    pub upload_tier: String,
+    pub created_at: DateTime<Utc>,
+    pub member_count: i64,
+    pub channel_count: i64,
+    pub message_count: i64,
+    pub attachment_count: i64,
 }
 
-// ─── GIF Search (Giphy Proxy) ────────────────────────
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DisconnectUserRequest {
+    pub reason: Option<String>,
+    /// Also revoke refresh tokens so clients cannot silently reconnect.
+    #[serde(default)]
+    pub revoke_sessions: bool,
+}
 
-#[derive(Debug, Deserialize)]
-pub struct GifSearchQuery {
-    pub q: String,
-    pub offset: Option<u32>,
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DisconnectUserResponse {
+    pub user_id: Uuid,
+    pub connections_closed: usize,
+    pub sessions_revoked: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BetaInviteStats {
+    /// Cap on beta codes: the operator override if set, else `BETA_CODE_LIMIT`.
+    pub limit: u32,
+    /// Whether `limit` comes from the operator override.
+    pub limit_overridden: bool,
+    pub issued: i64,
+    pub redeemed: i64,
+    pub expired: i64,
+    pub revoked: i64,
+    /// Emails waiting for a slot under the cap.
+    pub waitlisted: i64,
+    pub outstanding: i64,
+    pub issued_last_7_days: i64,
+    pub redeemed_last_7_days: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MaintenanceJobResponse {
+    pub job: &'static str,
+    /// Rows (or blobs) removed or created by the run.
+    pub affected: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigReloadResponse {
+    /// Settings whose value changed with this reload.
+    pub changed: Vec<&'static str>,
+    /// Every setting a reload can change; the rest need a restart.
+    pub reloadable: Vec<&'static str>,
+}
+
+// ─── Email ─────────────────────────────────────────────
+
+/// An email waiting in the outbound queue. Recipient and content stay
+/// encrypted and are not shown.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct EmailOutboxEntry {
+    pub id: Uuid,
+    /// Which email it is, e.g. "beta_code"
+    pub kind: String,
+    /// Failed attempts so far
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct GifSearchResponse {
-    pub results: Vec<GifResult>,
-    pub total_count: u32,
+/// An email that failed every delivery attempt. Recipient and content stay
+/// encrypted and are not shown.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct EmailDeadLetter {
+    pub id: Uuid,
+    /// Which email it is, e.g. "beta_code"
+    pub kind: String,
+    /// Provider of the last attempt
+    pub provider: String,
+    pub error: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub last_attempt_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct GifResult {
-    pub id: String,
-    pub title: String,
-    pub url: String,
-    pub preview_url: String,
-    pub width: u32,
-    pub height: u32,
+// ─── Pending Registrations ───────────────────────────
+
+/// An account registered under `REGISTRATION_MODE=approval`, waiting for an
+/// operator.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct PendingRegistration {
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    /// Abuse score of the registration request (see `/admin/abuse/events`)
+    pub abuse_score: Option<i16>,
+    pub created_at: DateTime<Utc>,
 }
 
-// ─── Server Restore ─────────────────────────────────
+// ─── Notification Rules ──────────────────────────────
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreServerRequest {
-    pub server: RestoreServerMeta,
-    pub categories: Vec<RestoreCategory>,
-    pub channels: Vec<RestoreChannel>,
-    pub roles: Vec<RestoreRole>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationScope {
+    /// Every server and DM
+    Global,
+    Server,
+    Channel,
+}
+
+impl NotificationScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationScope::Global => "global",
+            NotificationScope::Server => "server",
+            NotificationScope::Channel => "channel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    All,
+    /// Only messages that mention the user
+    Mentions,
+    None,
+}
+
+impl NotificationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationLevel::All => "all",
+            NotificationLevel::Mentions => "mentions",
+            NotificationLevel::None => "none",
+        }
+    }
+}
+
+/// A user's notification rule; the most specific one matching a message
+/// (channel, then server, then global) decides whether it notifies.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct NotificationRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// "global", "server" or "channel"
+    pub scope: String,
+    /// Server or channel id; absent for global rules
+    pub target_id: Option<Uuid>,
+    /// "all", "mentions" or "none"
+    pub level: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateNotificationRuleRequest {
+    pub scope: NotificationScope,
+    pub target_id: Option<Uuid>,
+    pub level: NotificationLevel,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateNotificationRuleRequest {
+    pub level: NotificationLevel,
+}
+
+// ─── Quiet Hours ─────────────────────────────────────
+
+/// A user's Do Not Disturb schedule. While it is in effect their presence
+/// shows as "dnd" and messages reach them `silent`, except from
+/// `priority_user_ids`.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct QuietHours {
+    pub user_id: Uuid,
+    pub enabled: bool,
+    /// Minutes after local midnight
+    pub start_minute: i32,
+    /// Minutes after local midnight; before `start_minute` for an overnight schedule
+    pub end_minute: i32,
+    /// Weekdays a quiet period starts on, bit 0 = Monday ... bit 6 = Sunday
+    pub days: i16,
+    pub utc_offset_minutes: i32,
+    /// IANA timezone name, stored for clients
+    pub timezone: Option<String>,
+    pub priority_user_ids: Vec<Uuid>,
+    /// Whether the schedule is in effect right now
+    pub active: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetQuietHoursRequest {
+    /// Defaults to true
+    pub enabled: Option<bool>,
+    pub start_minute: i32,
+    pub end_minute: i32,
+    /// Defaults to every day
+    pub days: Option<i16>,
+    /// The client's current UTC offset; send again when it changes
+    pub utc_offset_minutes: i32,
+    pub timezone: Option<String>,
     #[serde(default)]
-    pub permission_overwrites: Vec<RestoreOverwrite>,
+    pub priority_user_ids: Vec<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreServerMeta {
-    pub id: String,
-    pub name: String,
-    pub description: Option<String>,
+// ─── Legal Holds ─────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LegalHoldSubject {
+    Server,
+    User,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreCategory {
-    pub id: String,
-    pub name: String,
-    pub position: i32,
+impl LegalHoldSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegalHoldSubject::Server => "server",
+            LegalHoldSubject::User => "user",
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreChannel {
-    pub id: String,
-    pub name: String,
-    #[serde(rename = "type")]
-    pub channel_type: String,
-    pub category_id: Option<String>,
-    pub position: i32,
-    pub encrypted: bool,
-    pub is_private: bool,
+/// A legal hold, active until `released_at` is set.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct LegalHold {
+    pub id: Uuid,
+    pub subject_type: String,
+    pub subject_id: Uuid,
+    pub reason: Option<String>,
+    pub placed_by: Option<Uuid>,
+    pub placed_at: DateTime<Utc>,
+    pub released_by: Option<Uuid>,
+    pub released_at: Option<DateTime<Utc>>,
+    pub release_reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreRole {
-    pub id: String,
-    pub name: String,
-    pub color: Option<String>,
-    pub permissions: i64,
-    pub position: i32,
-    pub is_default: bool,
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PlaceLegalHoldRequest {
+    pub subject_type: LegalHoldSubject,
+    pub subject_id: Uuid,
+    pub reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct RestoreOverwrite {
-    pub channel_id: String,
-    pub target_type: String,
-    pub target_id: String,
-    pub allow: i64,
-    pub deny: i64,
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReleaseLegalHoldRequest {
+    pub reason: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct RestoreServerResponse {
-    pub categories_created: usize,
-    pub channels_created: usize,
-    pub roles_created: usize,
-    pub roles_updated: usize,
-    pub overwrites_applied: usize,
-    pub channel_id_map: std::collections::HashMap<String, String>,
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LegalHoldQuery {
+    /// Include released holds (default: active only)
+    #[serde(default)]
+    pub include_released: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
 }
 
-// ─── Message Import ─────────────────────────────────
+// ─── Server Events ───────────────────────────────────
 
-#[derive(Debug, Deserialize)]
-pub struct ImportMessagesRequest {
-    pub messages: Vec<ImportMessage>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpStatus {
+    Interested,
+    Going,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ImportMessage {
-    pub sender_token: String,
-    pub encrypted_body: String,
-    pub timestamp: String,
-    pub sender_id: Option<String>,
-    pub message_type: String,
-    pub reply_to_id: Option<String>,
-    pub has_attachments: bool,
+impl RsvpStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RsvpStatus::Interested => "interested",
+            RsvpStatus::Going => "going",
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ImportMessagesResponse {
-    pub imported: usize,
+/// A scheduled server event with its RSVP counts, as one viewer sees it.
+#[derive(Debug, Clone, FromRow)]
+pub struct ServerEvent {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub creator_id: Option<Uuid>,
+    pub encrypted_meta: Vec<u8>, // title, description, external location
+    pub channel_id: Option<Uuid>, // set when the event happens in a server channel
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub reminder_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub interested_count: i64,
+    pub going_count: i64,
+    pub viewer_rsvp: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ServerEventResponse {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub creator_id: Option<Uuid>,
+    pub encrypted_meta: String, // base64
+    /// Server channel the event takes place in; `null` for external events,
+    /// whose location is part of `encrypted_meta`.
+    pub channel_id: Option<Uuid>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub interested_count: i64,
+    pub going_count: i64,
+    /// The caller's RSVP ("interested" or "going"); never set on broadcasts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub my_rsvp: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ServerEvent> for ServerEventResponse {
+    fn from(e: ServerEvent) -> Self {
+        Self {
+            id: e.id,
+            server_id: e.server_id,
+            creator_id: e.creator_id,
+            encrypted_meta: base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                &e.encrypted_meta,
+            ),
+            channel_id: e.channel_id,
+            starts_at: e.starts_at,
+            ends_at: e.ends_at,
+            interested_count: e.interested_count,
+            going_count: e.going_count,
+            my_rsvp: e.viewer_rsvp,
+            created_at: e.created_at,
+            updated_at: e.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateServerEventRequest {
+    pub encrypted_meta: String, // base64
+    pub channel_id: Option<Uuid>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// PATCH /servers/:server_id/events/:event_id. Absent fields are left alone,
+/// `null` clears them.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateServerEventRequest {
+    pub encrypted_meta: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub channel_id: Option<Option<Uuid>>,
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub ends_at: Option<Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EventRsvpRequest {
+    pub status: RsvpStatus,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ServerEventQuery {
+    /// Include events that have already ended (default: upcoming and ongoing only)
+    #[serde(default)]
+    pub include_past: bool,
+}
+
+// ─── Server Onboarding ───────────────────────────────
+
+/// A role-selection prompt. Its title and option labels are in the
+/// onboarding's `encrypted_meta`, keyed by these ids.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OnboardingPrompt {
+    pub id: Uuid,
+    /// At most one option may be picked
+    #[serde(default)]
+    pub single_select: bool,
+    /// Onboarding can't be completed without picking an option
+    #[serde(default)]
+    pub required: bool,
+    pub options: Vec<OnboardingPromptOption>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OnboardingPromptOption {
+    pub id: Uuid,
+    /// Roles granted to members who pick this option
+    pub role_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ServerOnboarding {
+    pub server_id: Uuid,
+    pub enabled: bool,
+    pub encrypted_meta: Vec<u8>, // welcome description, rules text, prompt labels
+    pub default_channel_ids: Vec<Uuid>,
+    pub require_acknowledgment: bool,
+    pub rules_version: i32,
+    pub prompts: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ServerOnboarding {
+    pub fn prompts(&self) -> Vec<OnboardingPrompt> {
+        serde_json::from_value(self.prompts.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OnboardingResponse {
+    pub server_id: Uuid,
+    pub enabled: bool,
+    pub encrypted_meta: String, // base64
+    /// Channels new members join; empty means every channel
+    pub default_channel_ids: Vec<Uuid>,
+    /// Members must acknowledge the rules before they can post
+    pub require_acknowledgment: bool,
+    pub rules_version: i32,
+    pub prompts: Vec<OnboardingPrompt>,
+    /// Whether the caller has acknowledged the current rules
+    pub acknowledged: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetOnboardingRequest {
+    pub enabled: bool,
+    pub encrypted_meta: String, // base64
+    #[serde(default)]
+    pub default_channel_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub require_acknowledgment: bool,
+    /// Ask every member to acknowledge the rules again
+    #[serde(default)]
+    pub reset_acknowledgments: bool,
+    #[serde(default)]
+    pub prompts: Vec<OnboardingPrompt>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OnboardingSelection {
+    pub prompt_id: Uuid,
+    pub option_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CompleteOnboardingRequest {
+    #[serde(default)]
+    pub acknowledge_rules: bool,
+    #[serde(default)]
+    pub selections: Vec<OnboardingSelection>,
+}
+
+// ─── Member Screening ────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScreeningQuestion {
+    pub id: Uuid,
+    pub prompt: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ServerScreening {
+    pub server_id: Uuid,
+    pub enabled: bool,
+    pub questions: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ServerScreening {
+    pub fn questions(&self) -> Vec<ScreeningQuestion> {
+        serde_json::from_value(self.questions.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScreeningResponse {
+    pub server_id: Uuid,
+    /// Joining requires an approved application
+    pub enabled: bool,
+    pub questions: Vec<ScreeningQuestion>,
+}
+
+impl From<ServerScreening> for ScreeningResponse {
+    fn from(s: ServerScreening) -> Self {
+        Self {
+            server_id: s.server_id,
+            enabled: s.enabled,
+            questions: s.questions(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetScreeningRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub questions: Vec<ScreeningQuestion>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplicationStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl ApplicationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApplicationStatus::Pending => "pending",
+            ApplicationStatus::Approved => "approved",
+            ApplicationStatus::Denied => "denied",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScreeningAnswer {
+    pub question_id: Uuid,
+    pub answer: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApplyToServerRequest {
+    #[serde(default)]
+    pub answers: Vec<ScreeningAnswer>,
+}
+
+/// An application to join a screened server, with the applicant's username.
+#[derive(Debug, Clone, FromRow)]
+pub struct ServerApplication {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub invite_id: Option<Uuid>,
+    pub answers: serde_json::Value,
+    pub status: String,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub deny_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServerApplicationResponse {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub answers: Vec<ScreeningAnswer>,
+    /// "pending", "approved" or "denied"
+    pub status: String,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub deny_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ServerApplication> for ServerApplicationResponse {
+    fn from(a: ServerApplication) -> Self {
+        Self {
+            id: a.id,
+            server_id: a.server_id,
+            user_id: a.user_id,
+            username: a.username,
+            answers: serde_json::from_value(a.answers).unwrap_or_default(),
+            status: a.status,
+            reviewed_by: a.reviewed_by,
+            reviewed_at: a.reviewed_at,
+            deny_reason: a.deny_reason,
+            created_at: a.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApplicationQuery {
+    /// Defaults to "pending"
+    pub status: Option<ApplicationStatus>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DenyApplicationRequest {
+    pub reason: Option<String>,
+}
+
+// ─── Verification Levels ─────────────────────────────
+
+/// What a member must have before posting in a server. Each level includes
+/// the requirements of the ones below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationLevel {
+    None,
+    /// A verified email address
+    Email,
+    /// An account at least `min_account_age_days` old
+    AccountAge,
+    /// A member of the server for at least `min_membership_minutes`
+    Membership,
+    /// A verified phone number; needs an SMS provider, which this server
+    /// doesn't have, so it can't be selected
+    Phone,
+}
+
+impl VerificationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationLevel::None => "none",
+            VerificationLevel::Email => "email",
+            VerificationLevel::AccountAge => "account_age",
+            VerificationLevel::Membership => "membership",
+            VerificationLevel::Phone => "phone",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "email" => VerificationLevel::Email,
+            "account_age" => VerificationLevel::AccountAge,
+            "membership" => VerificationLevel::Membership,
+            "phone" => VerificationLevel::Phone,
+            _ => VerificationLevel::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ServerVerification {
+    pub server_id: Uuid,
+    pub level: String,
+    pub min_account_age_days: i32,
+    pub min_membership_minutes: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ServerVerification {
+    pub fn level(&self) -> VerificationLevel {
+        VerificationLevel::parse(&self.level)
+    }
+}
+
+/// What the posting checks need to know about a member.
+#[derive(Debug, Clone, FromRow)]
+pub struct MemberVerificationFacts {
+    pub is_owner: bool,
+    /// Members holding any role are exempt, as moderators hand out roles
+    pub has_roles: bool,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub account_created_at: DateTime<Utc>,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VerificationResponse {
+    pub server_id: Uuid,
+    pub level: VerificationLevel,
+    pub min_account_age_days: i32,
+    pub min_membership_minutes: i32,
+    /// Whether the caller meets the level and may post
+    pub satisfied: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetVerificationRequest {
+    pub level: VerificationLevel,
+    pub min_account_age_days: Option<i32>,
+    pub min_membership_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SendEmailVerificationRequest {
+    /// Must be the address the account registered with
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfirmEmailVerificationRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmailVerificationResponse {
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+// ─── Abuse Scoring ───────────────────────────────────
+
+/// A scored registration or beta code request.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AbuseEvent {
+    pub id: i64,
+    /// "register" or "beta"
+    pub kind: String,
+    /// Keyed hash of the requester's /24 (IPv4) or /48 (IPv6), for grouping;
+    /// absent for local or unknown addresses
+    pub network: Option<String>,
+    /// Raw IP, kept only for `ABUSE_IP_RETENTION_HOURS`
+    pub ip: Option<String>,
+    /// 0 (clean) to 100
+    pub score: i16,
+    /// Signals that contributed, e.g. "listed", "velocity", "proxy_headers"
+    pub reasons: Vec<String>,
+    /// Account the registration created
+    pub user_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AbuseEventQuery {
+    /// Only the registration of this account
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// ─── Message Partitions ────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessagePartition {
+    pub name: String,
+    /// First day of the month covered; `None` for the default partition.
+    pub month: Option<chrono::NaiveDate>,
+    /// Planner estimate, refreshed by autovacuum/ANALYZE.
+    pub estimated_rows: i64,
+    pub size_bytes: i64,
+    /// Date from which the partition is past retention and gets dropped.
+    pub drop_after: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PartitionStatusResponse {
+    pub months_ahead: u32,
+    /// 0 = partitions are kept forever.
+    pub retention_months: u32,
+    /// Months in the create-ahead window that have no partition yet.
+    pub missing: Vec<String>,
+    pub partitions: Vec<MessagePartition>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetAdminRequest {
+    pub is_admin: bool,
+}
+
+// ─── Instance Staff ──────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetStaffRoleRequest {
+    /// "operator", "instance_moderator", "support", or null to revoke.
+    pub role: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct StaffMemberResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub instance_role: String,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct InstanceAuditLogResponse {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub actor_username: Option<String>,
+    pub actor_role: String,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub details: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InstanceAuditLogQuery {
+    pub actor_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+// ─── Support Access ──────────────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SupportAccessQuery {
+    /// Why staff need to look at this account. Required; shown to the user.
+    pub reason: Option<String>,
+}
+
+/// Read-only, non-content view of an account for instance support staff.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SupportUserView {
+    pub account: SupportAccountInfo,
+    pub memberships: Vec<SupportMembership>,
+    pub devices: Vec<SupportDevice>,
+    pub unused_prekeys: i64,
+    pub rate_limits: SupportRateLimits,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SupportAccountInfo {
+    pub id: Uuid,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    pub instance_role: Option<String>,
+    pub totp_enabled: bool,
+    pub instance_banned: bool,
+}
+
+/// Server membership without the (encrypted) server metadata.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct SupportMembership {
+    pub server_id: Uuid,
+    pub is_owner: bool,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// Active login session. IP addresses are deliberately omitted.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SupportDevice {
+    pub family_id: Option<Uuid>,
+    pub device_name: Option<String>,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SupportRateLimits {
+    pub api: RateLimitUsage,
+    pub websocket: RateLimitUsage,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RateLimitUsage {
+    pub used: u32,
+    pub limit: u32,
+    pub resets_in_secs: u64,
+}
+
+/// A support access entry as shown to the user whose account was viewed.
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct SupportAccessEntry {
+    pub id: Uuid,
+    pub staff_role: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// ─── Instance Branding ───────────────────────────────
+
+#[derive(Debug, Clone, FromRow)]
+pub struct InstanceBranding {
+    pub name: String,
+    pub has_logo: bool,
+    pub accent_color: String,
+    pub background_color: String,
+    pub terms_url: Option<String>,
+    pub privacy_url: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InstanceBrandingResponse {
+    pub name: String,
+    pub logo_url: Option<String>,
+    pub accent_color: String,
+    pub background_color: String,
+    pub terms_url: Option<String>,
+    pub privacy_url: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Omitted fields are left unchanged; an empty string clears a URL.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateBrandingRequest {
+    pub name: Option<String>,
+    pub accent_color: Option<String>,
+    pub background_color: Option<String>,
+    pub terms_url: Option<String>,
+    pub privacy_url: Option<String>,
+    pub reason: Option<String>,
+}
+
+// ─── GIF Search (Giphy Proxy) ────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GifSearchQuery {
+    pub q: String,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GifSearchResponse {
+    pub results: Vec<GifResult>,
+    pub total_count: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GifResult {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub preview_url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ─── Server Restore ─────────────────────────────────
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreServerRequest {
+    pub server: RestoreServerMeta,
+    pub categories: Vec<RestoreCategory>,
+    pub channels: Vec<RestoreChannel>,
+    pub roles: Vec<RestoreRole>,
+    #[serde(default)]
+    pub permission_overwrites: Vec<RestoreOverwrite>,
+    /// Pluggable configuration sections, keyed by section name (see `restore_sections`)
+    #[serde(default)]
+    pub sections: std::collections::HashMap<String, serde_json::Value>,
+}
+
+/// A v2 `.haven` archive: the signed manifest and its zstd chunks, which
+/// reassemble into a `RestoreServerRequest` (see `export_format`).
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RestoreArchive {
+    pub format_version: u32,
+    pub manifest: serde_json::Value,
+    pub chunks: Vec<crate::export_format::ExportChunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreServerMeta {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Backup id of the channel that receives system messages
+    #[serde(default)]
+    pub system_channel_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreCategory {
+    pub id: String,
+    pub name: String,
+    pub position: i32,
+    #[serde(default)]
+    pub collapsed_by_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreChannel {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub channel_type: String,
+    pub category_id: Option<String>,
+    pub position: i32,
+    pub encrypted: bool,
+    pub is_private: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreRole {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub permissions: i64,
+    pub position: i32,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreOverwrite {
+    pub channel_id: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub allow: i64,
+    pub deny: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RestoreServerResponse {
+    pub categories_created: usize,
+    pub channels_created: usize,
+    pub roles_created: usize,
+    pub roles_updated: usize,
+    pub overwrites_applied: usize,
+    /// Channel-scoped rows (bridge links, retention policies, sender key
+    /// distributions, event locations) carried over from replaced channels
+    pub channel_rows_remapped: usize,
+    pub channel_id_map: std::collections::HashMap<String, String>,
+    /// Entries restored per configuration section
+    pub sections_restored: std::collections::HashMap<String, usize>,
+    /// Sections present in the backup that this server doesn't know
+    pub sections_skipped: Vec<String>,
+    /// Restore job tracking this restore and its message import batches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<Uuid>,
+    /// Snapshot of the replaced structure, for `POST .../restore/rollback`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<Uuid>,
+}
+
+/// What a restore created, as announced to the server in `ServerRestored`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreCounts {
+    pub categories_created: usize,
+    pub channels_created: usize,
+    pub roles_created: usize,
+    pub roles_updated: usize,
+    pub overwrites_applied: usize,
+    pub channel_rows_remapped: usize,
+}
+
+impl From<&RestoreServerResponse> for RestoreCounts {
+    fn from(r: &RestoreServerResponse) -> Self {
+        Self {
+            categories_created: r.categories_created,
+            channels_created: r.channels_created,
+            roles_created: r.roles_created,
+            roles_updated: r.roles_updated,
+            overwrites_applied: r.overwrites_applied,
+            channel_rows_remapped: r.channel_rows_remapped,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RestoreServerQuery {
+    /// Messages the client will import after the structure, so progress can
+    /// report against a total
+    pub total_messages: Option<i64>,
+}
+
+// ─── Restore Snapshots ──────────────────────────────
+
+/// A server's structure as it was before a restore replaced it.
+#[derive(Debug, Clone, FromRow)]
+pub struct RestoreSnapshot {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub created_by: Option<Uuid>,
+    /// Categories, roles and memberships as rows, plus exported sections
+    pub structure: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Row counts of a server's structure, recorded in the audit log before a
+/// restore detaches or deletes it.
+#[derive(Debug, Clone, Copy, Default, Serialize, FromRow)]
+pub struct StructureCounts {
+    pub channels: i64,
+    pub channel_members: i64,
+    pub overwrites: i64,
+    pub categories: i64,
+    pub roles: i64,
+    pub member_roles: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RestoreRollbackResponse {
+    pub snapshot_id: Uuid,
+    pub categories_restored: usize,
+    pub roles_restored: usize,
+    pub channels_restored: usize,
+    pub sections_restored: std::collections::HashMap<String, usize>,
+}
+
+// ─── Restore Jobs ───────────────────────────────────
+
+/// A server restore in progress, re-attachable after a page refresh.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RestoreJob {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    /// "structure" or "messages"
+    pub phase: String,
+    pub done: i64,
+    pub total: i64,
+    /// "running", "completed" or "failed"
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A persistent background job (see `jobs`).
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct BackgroundJob {
+    pub id: Uuid,
+    /// "maintenance" or "announcement-dms"
+    pub kind: String,
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    /// "queued", "running", "completed", "failed" or "cancelled"
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    /// When the job (or its next retry) may start.
+    pub run_at: DateTime<Utc>,
+    /// While running: when another worker may take the job over.
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub dedupe_key: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminJobsQuery {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScheduleJobRequest {
+    /// A maintenance job name, as for `POST /admin/maintenance/:job`.
+    pub job: String,
+    /// Defaults to now.
+    pub run_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ImportMessagesQuery {
+    /// Restore job to advance by the number of messages imported
+    pub job_id: Option<Uuid>,
+}
+
+// ─── Server Migration ───────────────────────────────
+
+/// A server's structure, configuration and members' role assignments, moved
+/// between instances. Members are identified by identity key, since user ids
+/// differ across instances.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrationBundle {
+    pub version: u32,
+    pub source_server_id: Uuid,
+    pub exported_at: DateTime<Utc>,
+    pub structure: RestoreServerRequest,
+    pub members: Vec<MigrationMember>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrationMember {
+    pub identity_key: String, // base64
+    /// Username on the source instance, reported back when no local user matches
+    pub username: String,
+    /// Bundle role ids (the `id`s in `structure.roles`)
+    pub role_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MigrationImportResponse {
+    pub restore: RestoreServerResponse,
+    pub members_added: usize,
+    pub roles_assigned: usize,
+    /// Source usernames with no local user holding their identity key
+    pub unmatched_members: Vec<String>,
+}
+
+// ─── Message Import ─────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportMessagesRequest {
+    pub messages: Vec<ImportMessage>,
+    /// Import the valid messages and report the rest in `failed`, instead of
+    /// rejecting the whole batch
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportMessage {
+    pub sender_token: String,
+    pub encrypted_body: String,
+    pub timestamp: String,
+    pub sender_id: Option<String>,
+    pub message_type: String,
+    pub reply_to_id: Option<String>,
+    pub has_attachments: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportMessagesResponse {
+    pub imported: usize,
+    /// Messages skipped with `continue_on_error`
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportFailure {
+    /// Position of the message in the batch
+    pub index: usize,
+    pub reason: String,
+}
+
+// ─── Federation ─────────────────────────────────────
+
+/// Local shadow account standing in for a user on another Haven server.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FederatedUser {
+    pub user_id: Uuid,
+    pub server_name: String,
+    pub remote_user_id: Uuid,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Public identity of a user as published to other servers.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FederatedProfile {
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub identity_key: String,           // base64
+    pub signed_prekey: String,          // base64
+    pub signed_prekey_signature: String, // base64
+}
+
+impl From<&User> for FederatedProfile {
+    fn from(u: &User) -> Self {
+        let b64 = |v: &[u8]| base64::Engine::encode(&base64::engine::general_purpose::STANDARD, v);
+        Self {
+            user_id: u.id,
+            username: u.username.clone(),
+            display_name: u.display_name.clone(),
+            identity_key: b64(&u.identity_key),
+            signed_prekey: b64(&u.signed_prekey),
+            signed_prekey_signature: b64(&u.signed_prekey_sig),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FederationEvent {
+    /// A user on the origin opened a DM with `target_user_id` on the destination.
+    DmCreate {
+        channel_id: Uuid,
+        sender: FederatedProfile,
+        target_user_id: Uuid,
+    },
+    /// A message in a federated DM. `sender_id` is the origin's own user id.
+    Message {
+        channel_id: Uuid,
+        message_id: Uuid,
+        sender_id: Uuid,
+        sender_token: String,   // base64
+        encrypted_body: String, // base64
+        timestamp: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationTransaction {
+    pub origin: String,
+    pub events: Vec<FederationEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FederationKeyResponse {
+    pub server_name: String,
+    pub public_key: String, // base64 Ed25519
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResolveFederatedUserRequest {
+    pub address: String, // "username@server.name"
+}
+
+// ─── Health ──────────────────────────────────────────
+
+/// One dependency's probe result. `status` is "ok", "down" or "disabled".
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    /// "ok" when every checked component is up, otherwise "down".
+    pub status: &'static str,
+    pub components: std::collections::BTreeMap<&'static str, ComponentHealth>,
+}
+
+// ─── Bridges ─────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Bridge {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub user_prefix: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Server the bridge is registered to; `None` for instance-wide bridges.
+    pub server_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateBridgeRequest {
+    pub name: String,
+    /// Every puppet username starts with this, e.g. "matrix_".
+    pub user_prefix: String,
+    /// Register the bridge to one server so its managers can link it.
+    pub server_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateBridgeResponse {
+    #[serde(flatten)]
+    pub bridge: Bridge,
+    /// Shown once; only its hash is stored.
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePuppetRequest {
+    /// The bridged network's id for this user (e.g. "@alice:matrix.org").
+    pub remote_id: String,
+    /// Username without the bridge prefix.
+    pub localpart: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BridgeSendMessageRequest {
+    pub channel_id: Uuid,
+    /// Puppet to post as; must belong to the calling bridge.
+    pub sender_id: Uuid,
+    pub sender_token: String,   // base64
+    pub encrypted_body: String, // base64
+    /// Original send time on the bridged network; defaults to now.
+    pub timestamp: Option<DateTime<Utc>>,
+    pub reply_to_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BridgeEventsQuery {
+    pub since: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BridgeEvent {
+    pub seq: i64,
+    pub channel_id: Uuid,
+    pub message: MessageResponse,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BridgeEventsResponse {
+    pub events: Vec<BridgeEvent>,
+    /// Pass back as `since` to acknowledge these events and get the next batch.
+    pub next_since: i64,
+}
+
+// ─── Announcements ───────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Announcement {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    /// "info", "maintenance" or "release".
+    pub kind: String,
+    pub send_dm: bool,
+    pub publish_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When it was broadcast; None while still scheduled.
+    pub published_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAnnouncementRequest {
+    pub title: String,
+    pub body: String,
+    pub kind: Option<String>,
+    /// Also deliver as a DM from the Haven system user.
+    #[serde(default)]
+    pub send_dm: bool,
+    /// Schedule for later; publishes immediately when omitted.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Stop showing it after this time.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What users see (over WS and `GET /announcements`).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnouncementResponse {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub kind: String,
+    pub published_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<Announcement> for AnnouncementResponse {
+    fn from(a: Announcement) -> Self {
+        Self {
+            id: a.id,
+            title: a.title,
+            body: a.body,
+            kind: a.kind,
+            published_at: a.published_at.unwrap_or(a.publish_at),
+            expires_at: a.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AdminAnnouncementResponse {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub announcement: Announcement,
+    pub dismissal_count: i64,
+}
+
+// ─── Server Quotas ───────────────────────────────────
+
+/// Operator overrides for one server. `None` uses the instance default;
+/// `Some(0)` lifts the limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ServerQuotaOverrides {
+    pub max_storage_bytes: Option<i64>,
+    pub max_messages_per_minute: Option<i32>,
+    pub max_members: Option<i32>,
+}
+
+/// Limits in force for a server after applying overrides (0 = unlimited).
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+pub struct ServerLimits {
+    pub max_storage_bytes: u64,
+    pub max_messages_per_minute: u32,
+    pub max_members: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServerQuotaUsage {
+    pub storage_bytes: i64,
+    pub members: i64,
+    pub messages_this_minute: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ServerQuotaResponse {
+    pub server_id: Uuid,
+    pub overrides: ServerQuotaOverrides,
+    pub limits: ServerLimits,
+    pub usage: ServerQuotaUsage,
+}
+
+/// Replaces all overrides for a server; omitted or null fields go back to
+/// the instance default.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetServerQuotasRequest {
+    #[serde(flatten)]
+    pub overrides: ServerQuotaOverrides,
+    pub reason: Option<String>,
 }
 
 // ─── Validation helpers ───────────────────────────────
@@ -1753,7 +4756,7 @@ static USERNAME_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
     regex::Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap()
 });
 
-fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
+pub(crate) fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
     if !USERNAME_REGEX.is_match(username) {
         return Err(validator::ValidationError::new("invalid_username"));
     }
@@ -0,0 +1,103 @@
+//! Per-user notification routing.
+//!
+//! A user's rules say what notifies them everywhere (`global`), in a server
+//! or in a channel: every message, only mentions, or nothing. The most
+//! specific rule matching a message wins, and without one every message
+//! notifies. Rules are evaluated when a message is sent; recipients they
+//! silence still get the message, flagged `silent`, so clients and push
+//! skip the notification. Bodies and channel names are end-to-end
+//! encrypted, so rules can't match on keywords.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::models::{MessageResponse, NotificationRule};
+use crate::AppState;
+
+/// Whether a message in `channel_id` (of `server_id`) notifies the owner of
+/// `rules`, who is `mentioned` by it or not.
+pub fn should_notify(rules: &[&NotificationRule], server_id: Option<Uuid>, channel_id: Uuid, mentioned: bool) -> bool {
+    let find = |scope: &str, target_id: Option<Uuid>| {
+        rules.iter().find(|r| r.scope == scope && r.target_id == target_id)
+    };
+    let rule = find("channel", Some(channel_id))
+        .or_else(|| server_id.and_then(|id| find("server", Some(id))))
+        .or_else(|| find("global", None));
+    match rule.map(|r| r.level.as_str()) {
+        Some("none") => false,
+        Some("mentions") => mentioned,
+        _ => true,
+    }
+}
+
+/// Evaluate the recipients' rules and quiet hours for a new message from
+/// `sender_id`, recording whom it silences on the response for `for_viewer`
+/// to flag during fan-out. Run after its mentions are resolved.
+pub async fn apply(state: &AppState, response: &mut MessageResponse, sender_id: Option<Uuid>) -> AppResult<()> {
+    let server_id = queries::find_channel_by_id(state.db.read(), response.channel_id)
+        .await?
+        .and_then(|c| c.server_id);
+    let rules = queries::get_notification_rules_for_channel(state.db.read(), response.channel_id, server_id).await?;
+    let mut by_user: HashMap<Uuid, Vec<&NotificationRule>> = HashMap::new();
+    for rule in &rules {
+        by_user.entry(rule.user_id).or_default().push(rule);
+    }
+
+    let everyone = response.mentions.as_ref().is_some_and(|m| m.everyone);
+    response.silenced = by_user
+        .into_iter()
+        .filter(|(user_id, rules)| {
+            let mentioned = everyone || response.mentioned.contains(user_id);
+            !should_notify(rules, server_id, response.channel_id, mentioned)
+        })
+        .map(|(user_id, _)| user_id)
+        .collect();
+    for user_id in crate::quiet_hours::quiet_recipients(state, response.channel_id, server_id, sender_id).await? {
+        if !response.silenced.contains(&user_id) {
+            response.silenced.push(user_id);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn rule(scope: &str, target_id: Option<Uuid>, level: &str) -> NotificationRule {
+        NotificationRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            scope: scope.into(),
+            target_id,
+            level: level.into(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn no_rules_notify_for_everything() {
+        assert!(should_notify(&[], Some(Uuid::new_v4()), Uuid::new_v4(), false));
+    }
+
+    #[test]
+    fn most_specific_rule_wins() {
+        let (server, channel, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let global = rule("global", None, "none");
+        let in_server = rule("server", Some(server), "mentions");
+        let in_channel = rule("channel", Some(channel), "all");
+        let rules = [&global, &in_server, &in_channel];
+
+        assert!(should_notify(&rules, Some(server), channel, false));
+        assert!(!should_notify(&rules, Some(server), other, false));
+        assert!(should_notify(&rules, Some(server), other, true));
+        assert!(!should_notify(&rules, Some(Uuid::new_v4()), other, true));
+        // DMs only match global rules
+        assert!(!should_notify(&rules, None, other, true));
+    }
+}
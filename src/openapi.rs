@@ -0,0 +1,253 @@
+//! OpenAPI document for the REST API, served at `/api/v1/openapi.json` with a
+//! Swagger UI at `/api/v1/docs`.
+//!
+//! Paths come from the `#[utoipa::path]` attributes on the handlers and
+//! schemas from the request/response structs themselves. New handlers and the
+//! types they take or return must also be listed in `ApiDoc`.
+
+use utoipa::openapi::security::{Http, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::api;
+use crate::errors::{ErrorResponse, FieldError};
+use crate::models::*;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Haven API", description = "Haven REST API. Real-time events use the WebSocket at `/api/v1/ws`."),
+    paths(
+        api::auth_routes::pow_challenge, api::auth_routes::register, api::auth_routes::login,
+        api::auth_routes::refresh_token, api::auth_routes::logout,
+        api::auth_routes::change_password, api::auth_routes::list_sessions,
+        api::auth_routes::revoke_session, api::auth_routes::totp_setup,
+        api::auth_routes::totp_verify, api::auth_routes::totp_disable,
+        api::auth_routes::send_email_verification, api::auth_routes::confirm_email_verification,
+        api::auth_routes::delete_account,
+        api::registration_invites::invite_required, api::registration_invites::list_my_invites,
+        api::registration_invites::admin_list_invites,
+        api::registration_invites::admin_create_invites,
+        api::registration_invites::admin_delete_invite,
+        api::keys::update_identity_keys, api::keys::upload_prekeys, api::keys::delete_prekeys,
+        api::keys::prekey_count, api::keys::update_signed_prekey, api::keys::get_key_bundle,
+        api::keys::get_prekey_bundle,
+        api::devices::register_device, api::devices::list_own_devices, api::devices::remove_device,
+        api::devices::set_device_verification, api::devices::list_user_devices,
+        api::devices::drain_device_queue,
+        api::key_backup::upload_key_backup, api::key_backup::get_key_backup,
+        api::key_backup::delete_key_backup, api::key_backup::get_key_backup_status,
+        api::key_backup::create_backup_version, api::key_backup::get_current_backup_version,
+        api::key_backup::get_backup_version, api::key_backup::delete_backup_version,
+        api::key_backup::upload_backup_sessions, api::key_backup::get_backup_sessions,
+        api::key_backup::delete_backup_sessions,
+        api::key_transparency::get_head, api::key_transparency::get_proof,
+        api::key_transparency::get_consistency,
+        api::users::get_profile, api::users::get_avatar, api::users::get_avatar_version,
+        api::users::get_banner, api::users::get_banner_version, api::users::block_user,
+        api::users::unblock_user, api::users::get_user_by_username, api::users::update_profile,
+        api::users::upload_avatar, api::users::delete_avatar, api::users::upload_banner,
+        api::users::delete_banner, api::users::get_media_usage, api::users::get_blocked_users,
+        api::users::get_support_access_log, api::users::distribute_profile_keys,
+        api::users::get_profile_key,
+        api::friends::list_relationships, api::friends::list_friends,
+        api::friends::send_friend_request, api::friends::accept_friend_request,
+        api::friends::decline_friend_request, api::friends::remove_friend,
+        api::friends::list_dm_requests, api::friends::handle_dm_request,
+        api::friends::update_dm_privacy,
+        api::receipts::get_receipt_privacy, api::receipts::update_receipt_privacy,
+        api::receipts::get_channel_receipts,
+        api::notification_rules::list_rules, api::notification_rules::create_rule,
+        api::notification_rules::update_rule, api::notification_rules::delete_rule,
+        api::quiet_hours::get_quiet_hours, api::quiet_hours::set_quiet_hours,
+        api::quiet_hours::delete_quiet_hours,
+        api::servers::list_servers, api::servers::create_server, api::servers::get_server,
+        api::servers::update_server, api::servers::delete_server,
+        api::servers::list_deleted_servers, api::servers::undelete_server,
+        api::servers::list_server_channels, api::servers::get_my_permissions,
+        api::servers::leave_server, api::servers::update_member, api::servers::get_member_avatar,
+        api::servers::set_nickname, api::servers::set_member_nickname, api::servers::timeout_member,
+        api::servers::export_server, api::servers::get_audit_log, api::servers::upload_icon,
+        api::servers::get_icon, api::servers::delete_icon, api::servers::list_content_filters,
+        api::servers::create_content_filter, api::servers::delete_content_filter,
+        api::channels::create_channel, api::channels::reorder_channels, api::channels::update_channel_positions,
+        api::channels::get_read_states, api::channels::mark_channel_read,
+        api::channels::update_channel, api::channels::patch_channel, api::channels::delete_channel, api::channels::join_channel,
+        api::channels::set_message_ttl, api::channels::list_channel_members,
+        api::channels::get_retention, api::channels::set_retention,
+        api::channels::get_mention_settings, api::channels::set_mention_settings,
+        api::channels::add_group_member, api::channels::remove_group_member,
+        api::channels::transfer_group_owner, api::channels::leave_channel,
+        api::channels::export_channel, api::channels::set_export_consent,
+        api::channels::hide_channel, api::channels::list_dm_channels, api::channels::create_dm,
+        api::channels::create_group_dm,
+        api::categories::list_categories, api::categories::create_category,
+        api::categories::reorder_categories, api::categories::update_category,
+        api::categories::delete_category, api::categories::set_channel_category,
+        api::events::list_events, api::events::create_event, api::events::update_event,
+        api::events::delete_event, api::events::set_rsvp, api::events::remove_rsvp,
+        api::onboarding::get_onboarding, api::onboarding::set_onboarding, api::onboarding::complete_onboarding,
+        api::invites::list_invites, api::invites::create_invite, api::invites::delete_invite,
+        api::invites::list_members, api::invites::search_members, api::invites::kick_member,
+        api::invites::join_by_invite,
+        api::screening::get_screening, api::screening::set_screening, api::screening::get_invite_screening,
+        api::screening::apply, api::screening::list_applications, api::screening::approve_application,
+        api::screening::deny_application, api::screening::list_my_applications,
+        api::verification::get_verification, api::verification::set_verification,
+        api::insights::get_server_insights,
+        api::upload_limits::list_upload_limits, api::upload_limits::set_role_upload_limit,
+        api::upload_limits::delete_role_upload_limit, api::upload_limits::set_channel_upload_limit,
+        api::upload_limits::delete_channel_upload_limit,
+        api::roles::list_roles, api::roles::create_role, api::roles::update_role,
+        api::roles::delete_role, api::roles::update_role_positions, api::roles::assign_role, api::roles::unassign_role,
+        api::roles::list_overwrites, api::roles::set_overwrite, api::roles::delete_overwrite,
+        api::permission_templates::list_permission_templates, api::permission_templates::create_permission_template,
+        api::permission_templates::update_permission_template, api::permission_templates::delete_permission_template,
+        api::bans::list_bans, api::bans::ban_member, api::bans::revoke_ban,
+        api::exports::restore_server, api::exports::import_messages, api::exports::verify_export,
+        api::exports::rollback_restore, api::exports::list_restore_jobs, api::exports::get_restore_job,
+        api::migration::export_migration, api::migration::import_migration,
+        api::exports::log_export,
+        api::emojis::list_emojis, api::emojis::upload_emoji, api::emojis::update_emoji,
+        api::emojis::delete_emoji, api::emojis::get_emoji_image,
+        api::calls::start_call, api::calls::get_turn_credentials,
+        api::attachments::create_upload_session, api::attachments::upload,
+        api::attachments::get_upload_offset, api::attachments::upload_chunk,
+        api::attachments::cancel_upload, api::attachments::finalize_upload,
+        api::attachments::download, api::attachments::download_thumbnail,
+        api::attachments::create_signed_url, api::attachments::download_signed,
+        api::messages::get_messages, api::messages::send_message, api::messages::forward_message,
+        api::messages::bulk_delete_messages, api::messages::get_channel_reactions,
+        api::messages::get_pins, api::messages::get_pin_ids, api::messages::get_message_reactions,
+        api::messages::get_message_replies, api::messages::add_own_reaction,
+        api::locations::stop_live_location,
+        api::messages::remove_own_reaction, api::messages::get_reactors,
+        api::sender_keys::get_sender_keys, api::sender_keys::distribute_sender_keys,
+        api::sender_keys::rotate_sender_keys, api::sender_keys::get_channel_member_keys,
+        api::bridges::list_channel_bridges, api::bridges::link_channel,
+        api::bridges::unlink_channel, api::bridges::list_bridges, api::bridges::create_bridge,
+        api::bridges::delete_bridge, api::bridges::whoami, api::bridges::upsert_puppet,
+        api::bridges::send_message, api::bridges::get_events,
+        api::link_preview::fetch_link_preview,
+        api::presence::get_presence,
+        api::reports::create_report,
+        api::voice::join_voice, api::voice::leave_voice, api::voice::get_participants,
+        api::voice::server_mute, api::voice::server_deafen,
+        api::admin::get_stats, api::admin::get_usage, api::admin::get_latency_budgets, api::admin::get_shadow_reads,
+        api::admin::get_ws_heartbeats, api::admin::get_db_replicas, api::admin::get_db_pool,
+        api::admin::get_attachment_gc,
+        api::admin::run_attachment_gc,
+        api::admin::list_users, api::admin::set_admin, api::admin::set_staff_role,
+        api::admin::get_support_view, api::admin::disconnect_user, api::admin::list_staff,
+        api::admin::get_instance_audit_log, api::admin::list_servers,
+        api::admin::set_server_upload_tier, api::admin::get_server_quotas,
+        api::admin::set_server_quotas, api::admin::get_beta_stats, api::admin::run_maintenance_job,
+        api::admin::list_jobs, api::admin::get_job, api::admin::schedule_job, api::admin::cancel_job,
+        api::admin::reload_config, api::admin::get_partitions,
+        api::admin::list_pending_registrations, api::admin::approve_registration, api::admin::reject_registration,
+        api::admin::list_legal_holds, api::admin::place_legal_hold, api::admin::release_legal_hold,
+        api::admin::list_abuse_events, api::admin::list_email_queue, api::admin::list_email_dead_letters, api::admin::retry_email_dead_letter,
+        api::admin::delete_email_dead_letter, api::admin::delete_user,
+        api::admin::list_reports,
+        api::admin::report_counts, api::admin::get_report, api::admin::update_report,
+        api::admin::list_instance_bans, api::admin::instance_ban_user,
+        api::admin::instance_revoke_ban, api::admin::list_blocked_hashes,
+        api::admin::create_blocked_hash, api::admin::delete_blocked_hash,
+        api::branding::update_branding, api::branding::upload_logo, api::branding::delete_logo,
+        api::branding::get_branding, api::branding::get_logo,
+        api::announcements::list_announcements, api::announcements::create_announcement,
+        api::announcements::delete_announcement, api::announcements::get_announcements,
+        api::announcements::dismiss_announcement,
+        api::beta::request_beta_code, api::beta::list_codes, api::beta::generate_codes,
+        api::beta::revoke_code, api::beta::set_code_limit,
+        api::gifs::search_gifs, api::gifs::trending_gifs,
+        api::federation::get_server_key, api::federation::get_user_profile,
+        api::federation::receive_transaction, api::federation::resolve_user,
+        api::sync::get_sync,
+    ),
+    components(schemas(
+        ErrorResponse, FieldError,
+        UserPublic, RegisterRequest, LoginRequest, AuthResponse, RegisterResponse, LoginResponse, RefreshRequest,
+        PowChallengeResponse, TotpSetupResponse, TotpVerifyRequest, KeyBundle, UploadPreKeysRequest,
+        UpdateSignedPreKeyRequest, UpdateKeysRequest, CreateServerRequest, ServerResponse,
+        CreateChannelRequest, PatchChannelRequest, ChannelResponse, CreateCategoryRequest, UpdateCategoryRequest,
+        ReorderCategoriesRequest, CategoryPosition, ReorderChannelsRequest, ChannelPosition, UpdateChannelPositionsRequest, ChannelPlacement, ChannelPositionsResponse,
+        CategoryResponse, SetChannelCategoryRequest, SendMessageRequest, MessageResponse,
+        ForwardMessageRequest, ForwardedFrom, ReplyPreview, ReactionCount, Reactor,
+        AttachmentPreview, UploadResponse, CreateUploadSessionRequest, FinalizeUploadRequest,
+        SetUploadTierRequest, DistributeSenderKeyRequest, SenderKeyDistributionEntry,
+        SenderKeyDistributionResponse, ChannelMemberKeyInfo, RegisterDeviceRequest,
+        SetDeviceVerificationRequest, DeviceResponse, QueuedMessage, DeviceQueueResponse,
+        UploadKeyBackupRequest, KeyBackupResponse,
+        KeyBackupStatusResponse, CreateKeyBackupVersionRequest, KeyBackupVersionResponse,
+        BackupSessionKey, UploadBackupSessionsRequest, BackupSessionResponse, KeyTransparencyHead,
+        KeyTransparencyProofResponse, KeyTransparencyConsistencyResponse, ReactionGroup,
+        LinkPreviewResponse, VoiceTokenResponse, TurnCredentials, CallStartResponse, SignedAttachmentUrl,
+        VoiceParticipantResponse, VoiceMuteRequest, VoiceDeafenRequest, PresenceEntry,
+        SessionResponse, CreateInviteRequest, InviteResponse, RegistrationInviteResponse,
+        AdminCreateInvitesRequest, BetaCodeRequest, BetaCodeResponse, AdminBetaCode,
+        GenerateBetaCodesRequest, SetBetaCodeLimitRequest, ServerMemberResponse,
+        UpdateNicknameRequest, UpdateMemberRequest, UpdateServerRequest, ChannelMemberInfo,
+        CreateGroupDmRequest, TransferGroupOwnerRequest, ChangePasswordRequest, UserProfileResponse,
+        MutualFriendInfo, UpdateProfileRequest, ProfileKeyDistributionEntry,
+        DistributeProfileKeysRequest, ProfileKeyResponse, ProfileMediaUsageResponse,
+        BlockedUserResponse, CreateRoleRequest, UpdateRoleRequest, RoleResponse, AssignRoleRequest, UpdateRolePositionsRequest, RolePosition, PermissionTemplateResponse, CreatePermissionTemplateRequest, UpdatePermissionTemplateRequest,
+        SetOverwriteRequest, OverwriteResponse, FriendResponse, RelationshipResponse,
+        FriendRequestBody, DmRequestAction, UpdateDmPrivacyRequest, UpdateReceiptPrivacyRequest, DmReceipt, CreateReportRequest,
+        ReportResponse, BanResponse, CreateBanRequest, AdminReportResponse, UpdateReportRequest,
+        ReportCounts, InstanceBanResponse, CreateInstanceBanRequest, ContentFilterResponse,
+        CreateContentFilterRequest, BlockedHashResponse, CreateBlockedHashRequest,
+        CustomEmojiResponse, RenameEmojiRequest, DeleteAccountRequest, AuditLogResponse,
+        DeleteServerRequest, DeletedServerResponse,
+        RetentionMode, SetChannelRetentionRequest, ChannelRetentionResponse,
+        MessageMentions, VoiceNote, LocationShare, LiveLocationState, SystemEvent, SystemMessage, SetChannelMentionSettingsRequest, ChannelMentionSettingsResponse,
+        NotificationScope, NotificationLevel, NotificationRule, CreateNotificationRuleRequest,
+        UpdateNotificationRuleRequest, QuietHours, SetQuietHoursRequest,
+        TimeoutMemberRequest, BulkDeleteRequest, ReadState, ChannelUnreadInfo, SyncServer,
+        SyncResponse, AdminStats, InstanceUsageStats, LatencyBudgetReport, LatencyBudgetViolation, AttachmentGcReport,
+        AttachmentGcRunResponse, ShadowReadReport, ShadowReadStats, DbReplicaReport, WsHeartbeatReport,
+        DbPoolReport, DbPoolGauges, DbAcquireLatency, DbBreakerStatus, DbSlowQuery,
+        AdminUserResponse, AdminServerResponse, DisconnectUserRequest, DisconnectUserResponse,
+        BetaInviteStats, MaintenanceJobResponse, BackgroundJob, ScheduleJobRequest, ConfigReloadResponse, PendingRegistration, LegalHold, LegalHoldSubject, PlaceLegalHoldRequest, ReleaseLegalHoldRequest, RsvpStatus, ServerEventResponse, CreateServerEventRequest, UpdateServerEventRequest, EventRsvpRequest, OnboardingPrompt, OnboardingPromptOption, OnboardingResponse, SetOnboardingRequest, OnboardingSelection, CompleteOnboardingRequest, ScreeningQuestion, ScreeningResponse, SetScreeningRequest, ApplicationStatus, ScreeningAnswer, ApplyToServerRequest, ServerApplicationResponse, DenyApplicationRequest, VerificationLevel, VerificationResponse, SetVerificationRequest, SendEmailVerificationRequest, ConfirmEmailVerificationRequest, EmailVerificationResponse, UploadLimit, SetUploadLimitRequest, EffectiveUploadLimits, ServerInsightsDay, ChannelInsights, ServerInsightsResponse, AbuseEvent, EmailOutboxEntry, EmailDeadLetter, MessagePartition, PartitionStatusResponse,
+        SetAdminRequest, SetStaffRoleRequest, StaffMemberResponse, InstanceAuditLogResponse,
+        SupportUserView, SupportAccountInfo, SupportMembership, SupportDevice, SupportRateLimits,
+        RateLimitUsage, SupportAccessEntry, InstanceBrandingResponse, UpdateBrandingRequest,
+        GifSearchResponse, GifResult, RestoreServerRequest, RestoreArchive, RestoreJob, RestoreRollbackResponse, MigrationBundle, MigrationMember,
+        MigrationImportResponse, RestoreServerMeta, RestoreCategory,
+        RestoreChannel, RestoreRole, RestoreOverwrite, RestoreServerResponse, ImportMessagesRequest,
+        ImportMessage, ImportMessagesResponse, ImportFailure, FederatedProfile, FederationKeyResponse,
+        ResolveFederatedUserRequest, Bridge, CreateBridgeRequest, CreateBridgeResponse,
+        CreatePuppetRequest, BridgeSendMessageRequest, BridgeEvent, BridgeEventsResponse,
+        Announcement, CreateAnnouncementRequest, AnnouncementResponse, AdminAnnouncementResponse,
+        ServerQuotaOverrides, ServerLimits, ServerQuotaUsage, ServerQuotaResponse,
+        SetServerQuotasRequest, api::channels::AddGroupMemberRequest,
+        api::channels::CreateDmRequest, api::channels::SetMessageTtlRequest,
+        api::channels::UpdateChannelRequest, api::channels::ChannelExportResponse,
+        api::channels::ExportConsentRequest, api::exports::VerifyExportRequest,
+        api::exports::VerifyExportSigner, api::exports::VerifyExportResponse,
+        crate::export_format::ChunkEntry, crate::export_format::ExportChunk,
+        api::exports::LogExportRequest, api::servers::ServerExportChannelData,
+        api::servers::ServerExportResponse,
+    )),
+    modifiers(&SecurityAddon),
+    security(("bearer" = []))
+)]
+pub struct ApiDoc;
+
+/// User JWTs (`bearer`) and bridge tokens (`bridge_token`) are both sent as
+/// `Authorization: Bearer ...`.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build(),
+            ),
+        );
+        components.add_security_scheme(
+            "bridge_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
@@ -17,7 +17,7 @@ pub const ATTACH_FILES: i64          = 1 << 13;
 pub const READ_MESSAGE_HISTORY: i64  = 1 << 14;
 pub const MANAGE_EMOJIS: i64         = 1 << 15;
 pub const MUTE_MEMBERS: i64         = 1 << 16;
-pub const STREAM: i64               = 1 << 17;
+pub const STREAM: i64               = 1 << 17; // screen sharing (incl. screen audio)
 pub const PRIORITY_SPEAKER: i64     = 1 << 18;
 pub const USE_VOICE_ACTIVITY: i64   = 1 << 19;
 pub const USE_EXTERNAL_EMOJIS: i64  = 1 << 20;
@@ -27,11 +27,27 @@ pub const MANAGE_EVENTS: i64        = 1 << 23;
 pub const MANAGE_THREADS: i64       = 1 << 24;
 pub const MODERATE_MEMBERS: i64     = 1 << 25;
 pub const MANAGE_NICKNAMES: i64     = 1 << 26;
+pub const VIDEO: i64                = 1 << 27; // camera
+pub const SEND_VOICE_NOTES: i64     = 1 << 28;
 
 /// Default permissions for the @everyone role.
 pub const DEFAULT_PERMISSIONS: i64 =
     VIEW_CHANNELS | SEND_MESSAGES | ADD_REACTIONS | READ_MESSAGE_HISTORY
-    | CREATE_INVITES | ATTACH_FILES | STREAM | USE_VOICE_ACTIVITY | USE_EXTERNAL_EMOJIS;
+    | CREATE_INVITES | ATTACH_FILES | STREAM | VIDEO | USE_VOICE_ACTIVITY | USE_EXTERNAL_EMOJIS
+    | SEND_VOICE_NOTES;
+
+/// Permission templates every new server starts with, as
+/// `(name, allow_bits, deny_bits)`. The migration that added templates
+/// seeds the same values into existing servers.
+pub const DEFAULT_TEMPLATES: &[(&str, i64, i64)] = &[
+    (
+        "Moderator",
+        KICK_MEMBERS | MANAGE_MESSAGES | MUTE_MEMBERS | VIEW_AUDIT_LOG | MANAGE_THREADS
+            | MODERATE_MEMBERS | MANAGE_NICKNAMES,
+        0,
+    ),
+    ("Read-only", 0, SEND_MESSAGES | ADD_REACTIONS | ATTACH_FILES | SEND_VOICE_NOTES),
+];
 
 /// Check if a permission bitfield has a specific permission.
 #[inline]
@@ -130,11 +146,114 @@ pub enum OverwriteTarget {
     Member(uuid::Uuid),
 }
 
+// ─── Instance Staff Permissions ───────────────────────
+//
+// Instance-level capabilities for the admin API. Separate bit space from
+// server permissions — these are granted by an instance staff role, never by
+// a server role.
+
+pub const INSTANCE_VIEW_STATS: i64            = 1 << 0;
+pub const INSTANCE_VIEW_USERS: i64            = 1 << 1;
+pub const INSTANCE_VIEW_INVITES: i64          = 1 << 2;
+pub const INSTANCE_MANAGE_INVITES: i64        = 1 << 3;
+pub const INSTANCE_MANAGE_REPORTS: i64        = 1 << 4;
+pub const INSTANCE_BAN_USERS: i64             = 1 << 5;
+pub const INSTANCE_MANAGE_BLOCKED_HASHES: i64 = 1 << 6;
+pub const INSTANCE_VIEW_AUDIT_LOG: i64        = 1 << 7;
+pub const INSTANCE_DELETE_USERS: i64          = 1 << 8;
+pub const INSTANCE_MANAGE_STAFF: i64          = 1 << 9;
+pub const INSTANCE_SUPPORT_ACCESS: i64        = 1 << 10;
+pub const INSTANCE_MANAGE_SERVERS: i64        = 1 << 11;
+pub const INSTANCE_MANAGE_BRANDING: i64       = 1 << 12;
+pub const INSTANCE_MANAGE_BRIDGES: i64        = 1 << 13;
+pub const INSTANCE_RUN_MAINTENANCE: i64       = 1 << 14;
+pub const INSTANCE_MANAGE_ANNOUNCEMENTS: i64  = 1 << 15;
+pub const INSTANCE_MANAGE_CONFIG: i64         = 1 << 16;
+pub const INSTANCE_MANAGE_LEGAL_HOLDS: i64    = 1 << 17;
+
+/// Instance staff roles, lowest first so `Ord` follows the hierarchy.
+/// Each role is a strict superset of the one below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstanceRole {
+    Support,
+    InstanceModerator,
+    Operator,
+}
+
+impl InstanceRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstanceRole::Operator => "operator",
+            InstanceRole::InstanceModerator => "instance_moderator",
+            InstanceRole::Support => "support",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "operator" => Some(InstanceRole::Operator),
+            "instance_moderator" => Some(InstanceRole::InstanceModerator),
+            "support" => Some(InstanceRole::Support),
+            _ => None,
+        }
+    }
+
+    /// Resolve a user's staff role. `is_instance_admin` predates staff roles
+    /// and always means operator.
+    pub fn resolve(is_instance_admin: bool, instance_role: Option<&str>) -> Option<Self> {
+        if is_instance_admin {
+            return Some(InstanceRole::Operator);
+        }
+        instance_role.and_then(Self::parse)
+    }
+
+    /// Capabilities granted by this role.
+    pub fn permissions(&self) -> i64 {
+        let support = INSTANCE_VIEW_STATS
+            | INSTANCE_VIEW_USERS
+            | INSTANCE_VIEW_INVITES
+            | INSTANCE_SUPPORT_ACCESS;
+        let moderator = support
+            | INSTANCE_MANAGE_REPORTS
+            | INSTANCE_BAN_USERS
+            | INSTANCE_MANAGE_BLOCKED_HASHES
+            | INSTANCE_VIEW_AUDIT_LOG;
+        match self {
+            InstanceRole::Support => support,
+            InstanceRole::InstanceModerator => moderator,
+            InstanceRole::Operator => {
+                moderator
+                    | INSTANCE_MANAGE_INVITES
+                    | INSTANCE_DELETE_USERS
+                    | INSTANCE_MANAGE_STAFF
+                    | INSTANCE_MANAGE_SERVERS
+                    | INSTANCE_MANAGE_BRANDING
+                    | INSTANCE_MANAGE_BRIDGES
+                    | INSTANCE_RUN_MAINTENANCE
+                    | INSTANCE_MANAGE_ANNOUNCEMENTS
+                    | INSTANCE_MANAGE_CONFIG
+                    | INSTANCE_MANAGE_LEGAL_HOLDS
+            }
+        }
+    }
+
+    pub fn has(&self, required: i64) -> bool {
+        self.permissions() & required == required
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use uuid::Uuid;
 
+    #[test]
+    fn default_templates_match_migration_seed() {
+        // 20260425000001_permission_templates.sql hardcodes these values
+        assert_eq!(DEFAULT_TEMPLATES[0], ("Moderator", 121700432, 0));
+        assert_eq!(DEFAULT_TEMPLATES[1], ("Read-only", 0, 268445952));
+    }
+
     // ─── has_permission ───────────────────────────────
 
     #[test]
@@ -176,6 +295,8 @@ mod tests {
         assert!(has_permission(DEFAULT_PERMISSIONS, SEND_MESSAGES));
         assert!(has_permission(DEFAULT_PERMISSIONS, ADD_REACTIONS));
         assert!(has_permission(DEFAULT_PERMISSIONS, CREATE_INVITES));
+        assert!(has_permission(DEFAULT_PERMISSIONS, STREAM | VIDEO));
+        assert!(has_permission(DEFAULT_PERMISSIONS, SEND_VOICE_NOTES));
         assert!(!has_permission(DEFAULT_PERMISSIONS, MANAGE_CHANNELS));
         assert!(!has_permission(DEFAULT_PERMISSIONS, ADMINISTRATOR));
     }
@@ -265,6 +386,18 @@ mod tests {
         assert!(has_permission(result, VIEW_CHANNELS)); // not denied
     }
 
+    #[test]
+    fn overwrite_can_deny_video_and_stream_independently_of_voice() {
+        let (user_id, everyone_role, _, _) = make_ids();
+        let overwrites = vec![
+            (OverwriteTarget::Role(everyone_role), 0, VIDEO | STREAM),
+        ];
+        let result = apply_channel_overwrites(DEFAULT_PERMISSIONS, &overwrites, &[], user_id, everyone_role);
+        assert!(!has_permission(result, VIDEO));
+        assert!(!has_permission(result, STREAM));
+        assert!(has_permission(result, VIEW_CHANNELS | USE_VOICE_ACTIVITY));
+    }
+
     #[test]
     fn overwrite_everyone_allow_adds_permission() {
         let (user_id, everyone_role, _, _) = make_ids();
@@ -355,4 +488,54 @@ mod tests {
         let result = apply_channel_overwrites(base, &[], &[], user_id, everyone_role);
         assert_eq!(result, base);
     }
+
+    // ─── InstanceRole ─────────────────────────────────
+
+    #[test]
+    fn instance_role_admin_flag_is_operator() {
+        assert_eq!(InstanceRole::resolve(true, None), Some(InstanceRole::Operator));
+        assert_eq!(InstanceRole::resolve(true, Some("support")), Some(InstanceRole::Operator));
+    }
+
+    #[test]
+    fn instance_role_resolves_from_column() {
+        assert_eq!(InstanceRole::resolve(false, None), None);
+        assert_eq!(InstanceRole::resolve(false, Some("support")), Some(InstanceRole::Support));
+        assert_eq!(InstanceRole::resolve(false, Some("bogus")), None);
+    }
+
+    #[test]
+    fn instance_role_hierarchy_is_nested() {
+        let support = InstanceRole::Support.permissions();
+        let moderator = InstanceRole::InstanceModerator.permissions();
+        let operator = InstanceRole::Operator.permissions();
+        assert_eq!(support & moderator, support);
+        assert_eq!(moderator & operator, moderator);
+        assert!(InstanceRole::Operator > InstanceRole::Support);
+    }
+
+    #[test]
+    fn instance_support_can_view_invites_but_not_delete() {
+        let role = InstanceRole::Support;
+        assert!(role.has(INSTANCE_VIEW_INVITES));
+        assert!(role.has(INSTANCE_SUPPORT_ACCESS));
+        assert!(!role.has(INSTANCE_MANAGE_INVITES));
+        assert!(!role.has(INSTANCE_DELETE_USERS));
+        assert!(!role.has(INSTANCE_BAN_USERS));
+    }
+
+    #[test]
+    fn instance_moderator_cannot_manage_staff() {
+        let role = InstanceRole::InstanceModerator;
+        assert!(role.has(INSTANCE_BAN_USERS));
+        assert!(!role.has(INSTANCE_MANAGE_STAFF));
+        assert!(!role.has(INSTANCE_DELETE_USERS));
+        assert!(!role.has(INSTANCE_MANAGE_SERVERS));
+        assert!(!role.has(INSTANCE_MANAGE_BRANDING));
+        assert!(!role.has(INSTANCE_MANAGE_BRIDGES));
+        assert!(!role.has(INSTANCE_RUN_MAINTENANCE));
+        assert!(!role.has(INSTANCE_MANAGE_ANNOUNCEMENTS));
+        assert!(!role.has(INSTANCE_MANAGE_CONFIG));
+        assert!(!role.has(INSTANCE_MANAGE_LEGAL_HOLDS));
+    }
 }
@@ -0,0 +1,230 @@
+//! User profile media (avatars, banners and per-server avatars).
+//!
+//! Uploads are decoded to prove they are images and downscaled to the slot's
+//! maximum dimensions. The stored bytes are content-addressed: the storage key
+//! and public URL both carry a hash of the image, so a URL never changes
+//! meaning and clients may cache it indefinitely. Everything a user stores
+//! here counts against `profile_media_quota_bytes`.
+
+use std::io::Cursor;
+
+use image::{imageops::FilterType, ImageFormat};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::storage;
+use crate::thumbnails;
+
+/// Largest upload accepted for any slot (before resizing).
+pub const MAX_UPLOAD_SIZE: usize = 8 * 1024 * 1024;
+
+/// Hex characters of the SHA-256 kept in keys and URLs.
+const HASH_LEN: usize = 32;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Where a profile image is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaSlot {
+    Avatar,
+    Banner,
+    /// Avatar shown in place of the global one inside a single server.
+    ServerAvatar(Uuid),
+}
+
+impl MediaSlot {
+    /// Human-readable name, for error messages.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaSlot::Avatar => "avatar",
+            MediaSlot::Banner => "banner",
+            MediaSlot::ServerAvatar(_) => "server avatar",
+        }
+    }
+
+    /// Value of `user_profile_media.slot` (also part of the storage key).
+    pub fn key(&self) -> String {
+        match self {
+            MediaSlot::Avatar => "avatar".into(),
+            MediaSlot::Banner => "banner".into(),
+            MediaSlot::ServerAvatar(server_id) => format!("server_avatar:{}", server_id),
+        }
+    }
+
+    /// Inverse of `key()`.
+    pub fn parse(key: &str) -> Option<Self> {
+        match key {
+            "avatar" => Some(MediaSlot::Avatar),
+            "banner" => Some(MediaSlot::Banner),
+            _ => key
+                .strip_prefix("server_avatar:")
+                .and_then(|id| id.parse().ok())
+                .map(MediaSlot::ServerAvatar),
+        }
+    }
+
+    /// Largest upload accepted for this slot.
+    pub fn max_upload_size(&self) -> usize {
+        match self {
+            MediaSlot::Avatar | MediaSlot::ServerAvatar(_) => 2 * 1024 * 1024,
+            MediaSlot::Banner => MAX_UPLOAD_SIZE,
+        }
+    }
+
+    /// Images larger than this (width, height) are downscaled to fit.
+    pub fn max_dimensions(&self) -> (u32, u32) {
+        match self {
+            MediaSlot::Avatar | MediaSlot::ServerAvatar(_) => (512, 512),
+            MediaSlot::Banner => (1920, 768),
+        }
+    }
+}
+
+/// A validated image ready to store.
+#[derive(Debug)]
+pub struct ProcessedImage {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub hash: String,
+}
+
+impl ProcessedImage {
+    fn new(data: Vec<u8>, content_type: &'static str) -> Self {
+        let hash = content_hash(&data);
+        Self { data, content_type, hash }
+    }
+}
+
+/// Validate and, if needed, downscale an uploaded image. Returns None for
+/// anything that isn't a decodable PNG, JPEG, GIF or WebP. Images that already
+/// fit are stored as uploaded, which keeps GIF animation. CPU-bound: call from
+/// `spawn_blocking`.
+pub fn process(slot: MediaSlot, data: &[u8]) -> Option<ProcessedImage> {
+    let content_type = match image::guess_format(data).ok()? {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+        _ => return None,
+    };
+    let image = thumbnails::decode(data)?;
+
+    let (max_width, max_height) = slot.max_dimensions();
+    if image.width() <= max_width && image.height() <= max_height {
+        return Some(ProcessedImage::new(data.to_vec(), content_type));
+    }
+
+    let resized = image.resize(max_width, max_height, FilterType::Lanczos3);
+    let mut out = Vec::new();
+    if resized.color().has_alpha() {
+        resized.write_to(&mut Cursor::new(&mut out), ImageFormat::Png).ok()?;
+        Some(ProcessedImage::new(out, "image/png"))
+    } else {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+        resized.to_rgb8().write_with_encoder(encoder).ok()?;
+        Some(ProcessedImage::new(out, "image/jpeg"))
+    }
+}
+
+/// Truncated hex SHA-256 of stored image bytes.
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hash = hex::encode(Sha256::digest(data));
+    hash.truncate(HASH_LEN);
+    hash
+}
+
+/// Whether a URL path segment could be a content hash.
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == HASH_LEN && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
+
+/// Storage key for one version of a user's profile image.
+pub fn storage_key(server_key: &[u8; 32], slot: MediaSlot, user_id: Uuid, hash: &str) -> String {
+    storage::obfuscated_key(server_key, &format!("{}:{}:{}", slot.key(), user_id, hash))
+}
+
+/// Storage key used before profile media was content-addressed.
+pub fn legacy_storage_key(server_key: &[u8; 32], slot: MediaSlot, user_id: Uuid) -> String {
+    storage::obfuscated_key(server_key, &format!("{}:{}", slot.key(), user_id))
+}
+
+/// Public, immutable URL for one version of a user's profile image.
+pub fn url(slot: MediaSlot, user_id: Uuid, hash: &str) -> String {
+    match slot {
+        MediaSlot::ServerAvatar(server_id) => {
+            format!("/api/v1/servers/{}/members/{}/avatar/{}", server_id, user_id, hash)
+        }
+        _ => format!("/api/v1/users/{}/{}/{}", user_id, slot.key(), hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        image.write_to(&mut Cursor::new(&mut out), format).unwrap();
+        out
+    }
+
+    #[test]
+    fn small_images_are_kept_verbatim() {
+        let png = encode(DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([1, 2, 3]))), ImageFormat::Png);
+        let processed = process(MediaSlot::Avatar, &png).unwrap();
+        assert_eq!(processed.data, png);
+        assert_eq!(processed.content_type, "image/png");
+        assert_eq!(processed.hash, content_hash(&png));
+    }
+
+    #[test]
+    fn large_images_are_downscaled() {
+        let opaque = encode(DynamicImage::ImageRgb8(RgbImage::from_pixel(1024, 2048, Rgb([9, 9, 9]))), ImageFormat::Png);
+        let processed = process(MediaSlot::Avatar, &opaque).unwrap();
+        assert_eq!(processed.content_type, "image/jpeg");
+        let resized = image::load_from_memory(&processed.data).unwrap();
+        assert_eq!((resized.width(), resized.height()), (256, 512));
+
+        let transparent = encode(
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(3840, 768, Rgba([9, 9, 9, 0]))),
+            ImageFormat::Png,
+        );
+        let processed = process(MediaSlot::Banner, &transparent).unwrap();
+        assert_eq!(processed.content_type, "image/png");
+        let resized = image::load_from_memory(&processed.data).unwrap();
+        assert_eq!((resized.width(), resized.height()), (1920, 384));
+    }
+
+    #[test]
+    fn non_images_are_rejected() {
+        assert!(process(MediaSlot::Avatar, b"<svg xmlns='http://www.w3.org/2000/svg'/>").is_none());
+        // Valid magic bytes, garbage body
+        assert!(process(MediaSlot::Avatar, &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn hashes_are_url_safe() {
+        let hash = content_hash(b"avatar");
+        assert!(is_valid_hash(&hash));
+        assert!(!is_valid_hash("../../etc/passwd"));
+        assert!(!is_valid_hash(&hash.to_uppercase()));
+        assert_ne!(
+            storage_key(&[0; 32], MediaSlot::Avatar, Uuid::nil(), &hash),
+            storage_key(&[0; 32], MediaSlot::Banner, Uuid::nil(), &hash)
+        );
+    }
+
+    #[test]
+    fn slot_keys_round_trip() {
+        let server_id = Uuid::new_v4();
+        for slot in [MediaSlot::Avatar, MediaSlot::Banner, MediaSlot::ServerAvatar(server_id)] {
+            assert_eq!(MediaSlot::parse(&slot.key()), Some(slot));
+        }
+        assert_eq!(MediaSlot::parse("server_avatar:nope"), None);
+        assert_eq!(
+            url(MediaSlot::ServerAvatar(server_id), Uuid::nil(), "ab"),
+            format!("/api/v1/servers/{}/members/{}/avatar/ab", server_id, Uuid::nil())
+        );
+    }
+}
@@ -1,69 +1,176 @@
-use std::collections::HashSet;
-use std::sync::Arc;
+//! Cross-instance WebSocket fan-out.
+//!
+//! Every WS event is delivered to this instance's connections directly and,
+//! when `PUBSUB_BACKEND=redis`, published to Redis so the other instances can
+//! deliver it to theirs. Each instance holds one pattern subscription to
+//! `haven:ws:*` and drops events that have no local recipient, so no
+//! per-channel subscription bookkeeping is needed and nothing is missed while
+//! a connection is subscribing. Events carry the publishing instance's id so
+//! an instance never re-delivers its own events.
 
 use futures::StreamExt;
-use tokio::sync::Mutex;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::config::AppConfig;
 use crate::models::WsServerMessage;
 use crate::AppState;
 
-/// Publish a channel-scoped WS event via Redis for cross-instance delivery.
-/// No-op if Redis is not configured (single-instance mode uses local broadcasts).
-pub async fn publish_channel_event(
-    redis: Option<&mut redis::aio::ConnectionManager>,
-    channel_id: Uuid,
-    msg: &WsServerMessage,
-) {
-    let Some(redis) = redis else { return };
-    let channel = format!("haven:ws:ch:{}", channel_id);
-    if let Ok(payload) = serde_json::to_string(msg) {
-        let _: Result<(), _> = redis::cmd("PUBLISH")
-            .arg(&channel)
-            .arg(&payload)
-            .query_async(redis)
-            .await;
-    }
+const KEY_PREFIX: &str = "haven:ws:";
+/// Redis channel for events addressed to every connected user.
+const INSTANCE_CHANNEL: &str = "haven:ws:all";
+
+#[derive(Serialize)]
+struct OutgoingEvent<'a> {
+    origin: Uuid,
+    event: &'a WsServerMessage,
+}
+
+#[derive(Deserialize)]
+struct IncomingEvent {
+    origin: Uuid,
+    event: WsServerMessage,
+}
+
+/// Fan-out handle: which backend carries events between instances.
+#[derive(Clone)]
+pub struct PubSub {
+    /// Identifies this process's own events on the bus.
+    node_id: Uuid,
+    /// Publishing connection; `None` when fan-out is local-only.
+    redis: Option<redis::aio::ConnectionManager>,
 }
 
-/// Publish a user-directed WS event via Redis for cross-instance delivery.
-/// No-op if Redis is not configured.
-pub async fn publish_user_event(
-    redis: Option<&mut redis::aio::ConnectionManager>,
-    user_id: Uuid,
-    msg: &WsServerMessage,
-) {
-    let Some(redis) = redis else { return };
-    let channel = format!("haven:ws:user:{}", user_id);
-    if let Ok(payload) = serde_json::to_string(msg) {
-        let _: Result<(), _> = redis::cmd("PUBLISH")
-            .arg(&channel)
+impl PubSub {
+    /// Single-instance mode: events only reach this process's connections.
+    pub fn local() -> Self {
+        Self { node_id: Uuid::new_v4(), redis: None }
+    }
+
+    /// Pick the backend from `PUBSUB_BACKEND`. Redis fan-out needs a Redis
+    /// connection; without one this falls back to local-only.
+    pub fn from_config(config: &AppConfig, redis: Option<redis::aio::ConnectionManager>) -> Self {
+        match (config.pubsub_backend.as_str(), redis) {
+            ("redis", Some(redis)) => {
+                tracing::info!("WebSocket fan-out via Redis pub/sub");
+                Self { node_id: Uuid::new_v4(), redis: Some(redis) }
+            }
+            ("redis", None) => {
+                tracing::info!("Redis not configured — WebSocket fan-out is local (single-instance mode)");
+                Self::local()
+            }
+            _ => {
+                tracing::info!("PUBSUB_BACKEND=local — WebSocket fan-out is local (single-instance mode)");
+                Self::local()
+            }
+        }
+    }
+
+    pub fn is_distributed(&self) -> bool {
+        self.redis.is_some()
+    }
+
+    /// Returns false if the event didn't reach the bus (always true when
+    /// fan-out is local-only).
+    async fn publish(&self, channel: &str, msg: &WsServerMessage) -> bool {
+        let Some(mut redis) = self.redis.clone() else { return true };
+        let envelope = OutgoingEvent { origin: self.node_id, event: msg };
+        let Ok(payload) = serde_json::to_string(&envelope) else { return false };
+        if let Err(e) = redis::cmd("PUBLISH")
+            .arg(channel)
             .arg(&payload)
-            .query_async(redis)
-            .await;
+            .query_async::<_, i64>(&mut redis)
+            .await
+        {
+            tracing::warn!("Failed to publish WS event to {}: {}", channel, e);
+            return false;
+        }
+        true
     }
 }
 
-/// Tracks which Redis channels this instance is subscribed to.
-pub type PubSubSubscriptions = Arc<Mutex<HashSet<String>>>;
+/// Publish a channel-scoped WS event to the other instances.
+/// Callers deliver to local subscribers themselves.
+pub async fn publish_channel_event(state: &AppState, channel_id: Uuid, msg: &WsServerMessage) {
+    state.pubsub.publish(&format!("{}ch:{}", KEY_PREFIX, channel_id), msg).await;
+}
+
+/// Publish a user-directed WS event to the other instances.
+/// Callers deliver to local connections themselves.
+pub async fn publish_user_event(state: &AppState, user_id: Uuid, msg: &WsServerMessage) {
+    state.pubsub.publish(&format!("{}user:{}", KEY_PREFIX, user_id), msg).await;
+}
+
+/// Deliver a channel-scoped WS event to its subscribers on every instance.
+/// The `broadcast_*` functions return false if the other instances weren't
+/// reached; the WS outbox retries those events.
+pub async fn broadcast_channel_event(state: &AppState, channel_id: Uuid, msg: &WsServerMessage) -> bool {
+    deliver_to_channel_local(state, channel_id, msg.clone());
+    state.pubsub.publish(&format!("{}ch:{}", KEY_PREFIX, channel_id), msg).await
+}
+
+/// Deliver a user-directed WS event to the user's connections on every instance.
+pub async fn broadcast_user_event(state: &AppState, user_id: Uuid, msg: &WsServerMessage) -> bool {
+    deliver_to_user_local(state, user_id, msg);
+    state.pubsub.publish(&format!("{}user:{}", KEY_PREFIX, user_id), msg).await
+}
+
+/// Deliver a WS event to every connected user on every instance.
+pub async fn broadcast_instance_event(state: &AppState, msg: &WsServerMessage) -> bool {
+    deliver_to_all_local(state, msg);
+    state.pubsub.publish(INSTANCE_CHANNEL, msg).await
+}
 
-/// Create an empty subscriptions set (used for initial AppState construction).
-pub fn empty_subscriptions() -> PubSubSubscriptions {
-    Arc::new(Mutex::new(HashSet::new()))
+fn deliver_to_channel_local(state: &AppState, channel_id: Uuid, msg: WsServerMessage) {
+    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+        let _ = broadcaster.send(msg);
+    }
 }
 
-/// Start the Redis subscriber background task.
-/// Returns empty subscriptions immediately if Redis is not configured.
-pub fn start_subscriber(state: AppState) -> PubSubSubscriptions {
-    let subscriptions: PubSubSubscriptions = Arc::new(Mutex::new(HashSet::new()));
+/// Returns whether the user had a connection here to deliver to.
+fn deliver_to_user_local(state: &AppState, user_id: Uuid, msg: &WsServerMessage) -> bool {
+    let Some(conns) = state.connections.get(&user_id) else { return false };
+    for tx in conns.iter() {
+        let _ = tx.send(msg.clone());
+    }
+    true
+}
 
-    // No Redis → no pub/sub subscriber needed (single-instance mode)
-    let Some(_) = &state.redis else {
-        tracing::info!("Redis not configured — pub/sub disabled (single-instance mode)");
-        return subscriptions;
-    };
+fn deliver_to_all_local(state: &AppState, msg: &WsServerMessage) {
+    for conns in state.connections.iter() {
+        for tx in conns.value().iter() {
+            let _ = tx.send(msg.clone());
+        }
+    }
+}
 
-    let subs_clone = subscriptions.clone();
+/// Route an event received from another instance to local recipients.
+fn dispatch(state: &AppState, redis_channel: &str, msg: WsServerMessage) {
+    let Some(target) = redis_channel.strip_prefix(KEY_PREFIX) else { return };
+    if redis_channel == INSTANCE_CHANNEL {
+        deliver_to_all_local(state, &msg);
+    } else if let Some(id) = target.strip_prefix("ch:") {
+        if let Ok(channel_id) = Uuid::parse_str(id) {
+            deliver_to_channel_local(state, channel_id, msg);
+        }
+    } else if let Some(id) = target.strip_prefix("user:") {
+        if let Ok(user_id) = Uuid::parse_str(id) {
+            // User-directed messages are DM fan-out from another instance;
+            // reaching a connection here is what counts as delivery.
+            if deliver_to_user_local(state, user_id, &msg) {
+                crate::api::receipts::spawn_record_delivery(state, user_id, &msg);
+            }
+        }
+    }
+}
+
+/// Start the Redis subscriber background task. No-op unless Redis fan-out
+/// is enabled. Reconnects with a 5s backoff; events published while the
+/// subscriber is disconnected are not replayed (clients resync on resume).
+pub fn start_subscriber(state: AppState) {
+    if !state.pubsub.is_distributed() {
+        return;
+    }
 
     tokio::spawn(async move {
         // Create a dedicated Redis client for pub/sub (can't reuse ConnectionManager)
@@ -74,108 +181,59 @@ pub fn start_subscriber(state: AppState) -> PubSubSubscriptions {
                 return;
             }
         };
+        let pattern = format!("{}*", KEY_PREFIX);
 
         loop {
-            match client.get_async_pubsub().await {
-                Ok(mut pubsub) => {
-                    tracing::info!("Redis pub/sub subscriber connected");
-
-                    // Re-subscribe to all tracked channels on reconnect
-                    {
-                        let subs = subs_clone.lock().await;
-                        for channel in subs.iter() {
-                            if let Err(e) = pubsub.subscribe(channel).await {
-                                tracing::error!("Failed to resubscribe to {}: {}", channel, e);
-                            }
-                        }
-                    }
-
-                    // Process incoming messages
-                    let mut msg_stream = pubsub.on_message();
-                    while let Some(msg) = msg_stream.next().await {
-                        let payload: String = match msg.get_payload() {
-                            Ok(p) => p,
-                            Err(_) => continue,
-                        };
-
-                        let ws_msg: WsServerMessage = match serde_json::from_str(&payload) {
-                            Ok(m) => m,
-                            Err(_) => continue,
-                        };
-
-                        let redis_channel: String = msg.get_channel_name().to_string();
-
-                        if let Some(channel_id_str) = redis_channel.strip_prefix("haven:ws:ch:") {
-                            // Channel-scoped event — forward to local broadcast
-                            if let Ok(channel_id) = Uuid::parse_str(channel_id_str) {
-                                if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-                                    let _ = broadcaster.send(ws_msg);
-                                }
-                            }
-                        } else if let Some(user_id_str) = redis_channel.strip_prefix("haven:ws:user:") {
-                            // User-directed event — forward to user's connections
-                            if let Ok(user_id) = Uuid::parse_str(user_id_str) {
-                                if let Some(conns) = state.connections.get(&user_id) {
-                                    for tx in conns.iter() {
-                                        let _ = tx.send(ws_msg.clone());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    tracing::warn!("Redis pub/sub stream ended, reconnecting...");
-                }
+            let mut pubsub = match client.get_async_pubsub().await {
+                Ok(pubsub) => pubsub,
                 Err(e) => {
                     tracing::error!("Failed to connect Redis pub/sub: {}, retrying in 5s", e);
                     tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+            if let Err(e) = pubsub.psubscribe(&pattern).await {
+                tracing::error!("Failed to subscribe to {}: {}, retrying in 5s", pattern, e);
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                continue;
+            }
+            tracing::info!("Redis pub/sub subscriber connected");
+
+            let mut msg_stream = pubsub.on_message();
+            while let Some(msg) = msg_stream.next().await {
+                let Ok(payload) = msg.get_payload::<String>() else { continue };
+                let Ok(incoming) = serde_json::from_str::<IncomingEvent>(&payload) else { continue };
+                if incoming.origin == state.pubsub.node_id {
+                    continue;
                 }
+                dispatch(&state, msg.get_channel_name(), incoming.event);
             }
+            tracing::warn!("Redis pub/sub stream ended, reconnecting...");
         }
     });
-
-    subscriptions
 }
 
-/// Subscribe this instance to a Redis channel (called when a local user subscribes).
-/// No-op if Redis is not configured.
-pub async fn subscribe_redis_channel(
-    state: &AppState,
-    channel_id: Uuid,
-) {
-    if state.redis.is_none() { return; }
-    let channel = format!("haven:ws:ch:{}", channel_id);
-    let mut subs = state.pubsub_subscriptions.lock().await;
-    if subs.insert(channel.clone()) {
-        tracing::debug!("Tracked Redis subscription: {}", channel);
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Subscribe this instance to a user's Redis channel (called on WS connect).
-/// No-op if Redis is not configured.
-pub async fn subscribe_redis_user(
-    state: &AppState,
-    user_id: Uuid,
-) {
-    if state.redis.is_none() { return; }
-    let channel = format!("haven:ws:user:{}", user_id);
-    let mut subs = state.pubsub_subscriptions.lock().await;
-    if subs.insert(channel.clone()) {
-        tracing::debug!("Tracked Redis user subscription: {}", channel);
+    #[test]
+    fn envelope_round_trips_with_origin() {
+        let origin = Uuid::new_v4();
+        let channel_id = Uuid::new_v4();
+        let event = WsServerMessage::Subscribed { channel_id };
+        let payload = serde_json::to_string(&OutgoingEvent { origin, event: &event }).unwrap();
+
+        let incoming: IncomingEvent = serde_json::from_str(&payload).unwrap();
+        assert_eq!(incoming.origin, origin);
+        assert!(matches!(incoming.event, WsServerMessage::Subscribed { channel_id: id } if id == channel_id));
     }
-}
 
-/// Unsubscribe from a user's Redis channel (called on WS disconnect).
-/// No-op if Redis is not configured.
-pub async fn unsubscribe_redis_user(
-    state: &AppState,
-    user_id: Uuid,
-) {
-    if state.redis.is_none() { return; }
-    // Only unsubscribe if no local connections remain
-    if state.connections.get(&user_id).is_some() {
-        return;
+    #[test]
+    fn local_backend_is_not_distributed() {
+        let mut config = AppConfig::test_default();
+        assert!(!PubSub::from_config(&config, None).is_distributed());
+        config.pubsub_backend = "local".into();
+        assert!(!PubSub::from_config(&config, None).is_distributed());
     }
-    let channel = format!("haven:ws:user:{}", user_id);
-    let mut subs = state.pubsub_subscriptions.lock().await;
-    subs.remove(&channel);
 }
@@ -0,0 +1,159 @@
+//! Per-user Do Not Disturb schedules.
+//!
+//! A schedule is a daily window in the user's local time, optionally limited
+//! to some weekdays. While it is in effect the user shows as "dnd" instead
+//! of online or idle, and new messages reach them flagged `silent`, except
+//! messages from their priority senders.
+//! The server has no timezone database, so schedules carry the client's UTC
+//! offset and clients send it again when it changes.
+
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::models::QuietHours;
+use crate::AppState;
+
+/// Whether `now` falls in a window from `start` to `end` (minutes after local
+/// midnight at `utc_offset_minutes`) starting on one of `days` (bit 0 is
+/// Monday). A window whose end is before its start runs past midnight.
+pub fn in_window(start: i32, end: i32, days: i16, utc_offset_minutes: i32, now: DateTime<Utc>) -> bool {
+    let local = now + Duration::minutes(utc_offset_minutes as i64);
+    let minute = (local.hour() * 60 + local.minute()) as i32;
+    let today = local.weekday().num_days_from_monday();
+    let starts_on = |day: u32| days & (1 << day) != 0;
+    if start < end {
+        starts_on(today) && (start..end).contains(&minute)
+    } else {
+        (minute >= start && starts_on(today)) || (minute < end && starts_on((today + 6) % 7))
+    }
+}
+
+pub fn is_quiet(schedule: &QuietHours, now: DateTime<Utc>) -> bool {
+    schedule.enabled
+        && in_window(
+            schedule.start_minute,
+            schedule.end_minute,
+            schedule.days,
+            schedule.utc_offset_minutes,
+            now,
+        )
+}
+
+/// Recipients of a message in `channel_id` who are in their quiet hours and
+/// haven't made its sender a priority sender.
+pub async fn quiet_recipients(
+    state: &AppState,
+    channel_id: Uuid,
+    server_id: Option<Uuid>,
+    sender_id: Option<Uuid>,
+) -> AppResult<Vec<Uuid>> {
+    let now = Utc::now();
+    let schedules = queries::get_quiet_hours_for_channel(state.db.read(), channel_id, server_id).await?;
+    Ok(schedules
+        .into_iter()
+        .filter(|s| is_quiet(s, now))
+        .filter(|s| !sender_id.is_some_and(|id| s.priority_user_ids.contains(&id)))
+        .map(|s| s.user_id)
+        .collect())
+}
+
+/// The presence others see for a user whose own status is `status`: quiet
+/// hours turn "online" and "idle" into "dnd".
+pub async fn presence_status(state: &AppState, user_id: Uuid, status: &str) -> String {
+    if status == "online" || status == "idle" {
+        match queries::get_quiet_user_ids(state.db.read(), &[user_id]).await {
+            Ok(ids) if !ids.is_empty() => return "dnd".into(),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to check quiet hours of {}: {}", user_id, e),
+        }
+    }
+    status.to_string()
+}
+
+/// Tell the user's channels about their presence after quiet hours started
+/// or ended, if they are connected and not invisible or set to dnd already.
+pub async fn announce(state: &AppState, user_id: Uuid, quiet: bool) {
+    let status = if let Some(mut redis) = state.redis.clone() {
+        redis::cmd("HGET")
+            .arg("haven:presence")
+            .arg(user_id.to_string())
+            .query_async::<_, Option<String>>(&mut redis)
+            .await
+            .unwrap_or(None)
+    } else {
+        state.memory.presence.get(&user_id).map(|v| v.value().clone())
+    };
+    if let Some(status) = status.filter(|s| s == "online" || s == "idle") {
+        let shown = if quiet { "dnd" } else { status.as_str() };
+        crate::ws::announce_presence(user_id, shown, state).await;
+    }
+}
+
+/// Mark schedules that started or ended since the last run and announce the
+/// change in presence. Returns how many schedules changed.
+pub async fn sweep(state: &AppState) -> AppResult<u64> {
+    let now = Utc::now();
+    let (mut started, mut ended) = (Vec::new(), Vec::new());
+    for schedule in queries::list_quiet_hours_to_check(state.db.primary()).await? {
+        match (is_quiet(&schedule, now), schedule.active) {
+            (true, false) => started.push(schedule.user_id),
+            (false, true) => ended.push(schedule.user_id),
+            _ => {}
+        }
+    }
+    if !started.is_empty() {
+        queries::set_quiet_hours_active(state.db.primary(), &started, true).await?;
+    }
+    if !ended.is_empty() {
+        queries::set_quiet_hours_active(state.db.primary(), &ended, false).await?;
+    }
+    for &user_id in &started {
+        announce(state, user_id, true).await;
+    }
+    for &user_id in &ended {
+        announce(state, user_id, false).await;
+    }
+    Ok((started.len() + ended.len()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const EVERY_DAY: i16 = 0b111_1111;
+
+    // 2026-06-01 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn daytime_window_is_half_open() {
+        assert!(!in_window(9 * 60, 17 * 60, EVERY_DAY, 0, at(1, 8, 59)));
+        assert!(in_window(9 * 60, 17 * 60, EVERY_DAY, 0, at(1, 9, 0)));
+        assert!(!in_window(9 * 60, 17 * 60, EVERY_DAY, 0, at(1, 17, 0)));
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_the_day_it_starts() {
+        let weekdays = 0b001_1111;
+        // Friday night into Saturday morning
+        assert!(in_window(22 * 60, 7 * 60, weekdays, 0, at(5, 23, 0)));
+        assert!(in_window(22 * 60, 7 * 60, weekdays, 0, at(6, 6, 0)));
+        // Saturday night and Monday morning are off
+        assert!(!in_window(22 * 60, 7 * 60, weekdays, 0, at(6, 23, 0)));
+        assert!(!in_window(22 * 60, 7 * 60, weekdays, 0, at(8, 6, 0)));
+    }
+
+    #[test]
+    fn offset_shifts_the_window_into_local_time() {
+        // 22:00 at UTC-5 is 03:00 UTC the next day
+        assert!(in_window(22 * 60, 7 * 60, EVERY_DAY, -300, at(2, 3, 0)));
+        assert!(!in_window(22 * 60, 7 * 60, EVERY_DAY, -300, at(2, 13, 0)));
+        // Monday 00:30 at UTC+2 is still Sunday in UTC
+        assert!(in_window(0, 60, 0b000_0001, 120, at(7, 22, 30)));
+    }
+}
@@ -0,0 +1,162 @@
+//! Per-server quotas: attachment storage, message rate and member count.
+//!
+//! Instance defaults come from config (`SERVER_STORAGE_QUOTA_BYTES`,
+//! `SERVER_MESSAGE_RATE_PER_MINUTE`, `SERVER_MAX_MEMBERS`); operators can
+//! override them per server through the admin API. A limit of 0 is unlimited.
+//! DMs and group DMs have no server and are never charged.
+
+use uuid::Uuid;
+
+use crate::cache;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::{ServerLimits, ServerQuotaOverrides};
+use crate::AppState;
+
+/// Overrides change rarely; cache them so the message path skips the DB.
+const OVERRIDES_CACHE_TTL_SECS: u64 = 60;
+
+fn overrides_cache_key(server_id: Uuid) -> String {
+    format!("haven:quota:{}", server_id)
+}
+
+fn message_count_key(server_id: Uuid, minute: i64) -> String {
+    format!("haven:quota:msgs:{}:{}", server_id, minute)
+}
+
+/// Apply overrides on top of the instance defaults.
+pub fn effective_limits(state: &AppState, overrides: &ServerQuotaOverrides) -> ServerLimits {
+    let config = &state.config;
+    ServerLimits {
+        max_storage_bytes: overrides
+            .max_storage_bytes
+            .map_or(config.server_storage_quota_bytes, |v| v.max(0) as u64),
+        max_messages_per_minute: overrides
+            .max_messages_per_minute
+            .map_or(config.server_message_rate_per_minute, |v| v.max(0) as u32),
+        max_members: overrides
+            .max_members
+            .map_or(config.server_max_members, |v| v.max(0) as u32),
+    }
+}
+
+pub async fn get_overrides(state: &AppState, server_id: Uuid) -> AppResult<ServerQuotaOverrides> {
+    let key = overrides_cache_key(server_id);
+    if let Some(cached) = cache::get_cached(state.redis.clone().as_mut(), &state.memory, &key).await {
+        return Ok(cached);
+    }
+    let overrides = queries::get_server_quota_overrides(state.db.read(), server_id).await?;
+    cache::set_cached(
+        state.redis.clone().as_mut(),
+        &state.memory,
+        &key,
+        &overrides,
+        OVERRIDES_CACHE_TTL_SECS,
+    )
+    .await;
+    Ok(overrides)
+}
+
+pub async fn limits(state: &AppState, server_id: Uuid) -> AppResult<ServerLimits> {
+    Ok(effective_limits(state, &get_overrides(state, server_id).await?))
+}
+
+/// Drop cached overrides after an operator changes them.
+pub async fn invalidate(state: &AppState, server_id: Uuid) {
+    cache::invalidate(state.redis.clone().as_mut(), &state.memory, &overrides_cache_key(server_id)).await;
+}
+
+/// Refuse an upload of `additional` bytes that would take the server past
+/// its storage quota.
+pub async fn check_storage(state: &AppState, server_id: Uuid, additional: u64) -> AppResult<()> {
+    let max = limits(state, server_id).await?.max_storage_bytes;
+    if max == 0 {
+        return Ok(());
+    }
+    let used = queries::get_server_storage_usage(state.db.read(), server_id).await?.max(0) as u64;
+    if used + additional > max {
+        return Err(AppError::QuotaExceeded(format!(
+            "Server storage quota exceeded ({} of {} bytes used)",
+            used, max
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse a join once the server is at its member cap.
+pub async fn check_members(state: &AppState, server_id: Uuid) -> AppResult<()> {
+    let max = limits(state, server_id).await?.max_members;
+    if max == 0 {
+        return Ok(());
+    }
+    let members = queries::count_server_members(state.db.read(), server_id).await?;
+    if members >= max as i64 {
+        return Err(AppError::QuotaExceeded(format!(
+            "This server has reached its member limit ({})",
+            max
+        )));
+    }
+    Ok(())
+}
+
+/// Count a message against the server's per-minute rate and refuse it when
+/// the rate is exhausted. With Redis the count is shared by all instances.
+pub async fn record_message(state: &AppState, server_id: Uuid) -> AppResult<()> {
+    let max = limits(state, server_id).await?.max_messages_per_minute;
+    if max == 0 {
+        return Ok(());
+    }
+    let minute = chrono::Utc::now().timestamp() / 60;
+    let count = increment_message_count(state, server_id, minute).await;
+    if count > max {
+        return Err(AppError::TooManyRequests(format!(
+            "This server is limited to {} messages per minute — try again shortly",
+            max
+        )));
+    }
+    Ok(())
+}
+
+async fn increment_message_count(state: &AppState, server_id: Uuid, minute: i64) -> u32 {
+    if let Some(mut redis) = state.redis.clone() {
+        let key = message_count_key(server_id, minute);
+        let result: Result<(u32,), redis::RedisError> = redis::pipe()
+            .incr(&key, 1)
+            .expire(&key, 120)
+            .ignore()
+            .query_async(&mut redis)
+            .await;
+        match result {
+            Ok((count,)) => return count,
+            Err(e) => tracing::warn!("Redis message quota count failed, counting locally: {}", e),
+        }
+    }
+    let mut entry = state.memory.server_message_counts.entry(server_id).or_insert((minute, 0));
+    let (m, count) = entry.value_mut();
+    if *m != minute {
+        *m = minute;
+        *count = 0;
+    }
+    *count += 1;
+    *count
+}
+
+/// Messages counted for the server so far this minute.
+pub async fn messages_this_minute(state: &AppState, server_id: Uuid) -> u32 {
+    let minute = chrono::Utc::now().timestamp() / 60;
+    if let Some(mut redis) = state.redis.clone() {
+        let result: Result<Option<u32>, redis::RedisError> = redis::cmd("GET")
+            .arg(message_count_key(server_id, minute))
+            .query_async(&mut redis)
+            .await;
+        if let Ok(count) = result {
+            return count.unwrap_or(0);
+        }
+    }
+    state
+        .memory
+        .server_message_counts
+        .get(&server_id)
+        .filter(|e| e.value().0 == minute)
+        .map_or(0, |e| e.value().1)
+}
@@ -0,0 +1,238 @@
+//! Byte ranges and disposition for attachment downloads.
+//!
+//! Attachments never change once linked, so the attachment id makes a
+//! strong ETag and its creation time the Last-Modified date; signed URLs,
+//! which never see the attachment row, tag by storage key instead. A single
+//! `Range` is honoured (several ranges get the whole body, which RFC 9110
+//! allows), and `If-Range` falls back to the whole body when the client's
+//! copy is stale. Storage can't read part of a blob, so a range is cut
+//! from the loaded attachment.
+//!
+//! The disposition decides whether a browser may render the file in the
+//! page: media and plain text display inline, anything that could run
+//! script (HTML, SVG, XML) or is unknown is downloaded.
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Content types served `inline`; everything else is an `attachment`.
+const INLINE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "text/plain",
+    "application/pdf",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Serve everything (no usable range, or a stale `If-Range`).
+    Full,
+    /// Inclusive first and last byte.
+    Partial { start: u64, end: u64 },
+    /// The range starts past the end (416).
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// `Content-Range` value for a partial or unsatisfiable response.
+    pub fn content_range(&self, len: u64) -> Option<String> {
+        match self {
+            ByteRange::Full => None,
+            ByteRange::Partial { start, end } => Some(format!("bytes {}-{}/{}", start, end, len)),
+            ByteRange::Unsatisfiable => Some(format!("bytes */{}", len)),
+        }
+    }
+}
+
+/// Strong ETag for an attachment's bytes.
+pub fn attachment_etag(attachment_id: Uuid) -> String {
+    format!("\"{}\"", attachment_id.simple())
+}
+
+/// Strong ETag for a blob served by storage key (signed URLs).
+pub fn blob_etag(storage_key: &str) -> String {
+    format!("\"{}\"", storage_key.replace('/', ""))
+}
+
+/// HTTP date for `Last-Modified`.
+pub fn http_date(at: DateTime<Utc>) -> String {
+    at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// The range to serve of a `len`-byte body, from `Range` and `If-Range`.
+/// Without a `last_modified`, a date in `If-Range` never matches.
+pub fn requested_range(headers: &HeaderMap, len: u64, etag: &str, last_modified: Option<DateTime<Utc>>) -> ByteRange {
+    let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return ByteRange::Full;
+    };
+    if let Some(if_range) = headers.get(header::IF_RANGE) {
+        let current = if_range.to_str().is_ok_and(|v| if_range_matches(v, etag, last_modified));
+        if !current {
+            return ByteRange::Full;
+        }
+    }
+    parse_range(range, len)
+}
+
+/// `If-Range` holds a strong ETag or an HTTP date; weak tags never match.
+fn if_range_matches(value: &str, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') {
+        return value == etag;
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+    let Some(last_modified) = last_modified else {
+        return false;
+    };
+    DateTime::parse_from_rfc2822(value).is_ok_and(|date| date.timestamp() == last_modified.timestamp())
+}
+
+/// Parse a single `bytes=` range against a `len`-byte body.
+fn parse_range(value: &str, len: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial { start: len.saturating_sub(n), end: len - 1 },
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end: end.min(len - 1) }
+}
+
+/// `Content-Disposition` for an attachment of `content_type`. `download`
+/// forces `attachment`; `filename` is the name the client wants saved.
+pub fn content_disposition(content_type: &str, download: bool, filename: Option<&str>) -> String {
+    let inline = !download && is_inline_type(content_type);
+    let kind = if inline { "inline" } else { "attachment" };
+    match filename.map(sanitize_filename).filter(|name| !name.is_empty()) {
+        Some(name) => format!(
+            "{}; filename=\"{}\"; filename*=UTF-8''{}",
+            kind,
+            name.replace(|c: char| !c.is_ascii() || c == '"' || c == '\\', "_"),
+            urlencoding::encode(&name)
+        ),
+        None => kind.to_string(),
+    }
+}
+
+fn is_inline_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("video/") || essence.starts_with("audio/") || INLINE_TYPES.contains(&essence)
+}
+
+/// Drop path separators and control characters, and cap the length.
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control() && *c != '/' && *c != '\\')
+        .take(255)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_range("bytes=0-3", 10), ByteRange::Partial { start: 0, end: 3 });
+        assert_eq!(parse_range("bytes=4-", 10), ByteRange::Partial { start: 4, end: 9 });
+        assert_eq!(parse_range("bytes=8-100", 10), ByteRange::Partial { start: 8, end: 9 });
+        assert_eq!(parse_range("bytes=-3", 10), ByteRange::Partial { start: 7, end: 9 });
+        assert_eq!(parse_range("bytes=-30", 10), ByteRange::Partial { start: 0, end: 9 });
+        assert_eq!(parse_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        // Ignored rather than refused
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), ByteRange::Full);
+        assert_eq!(parse_range("bytes=5-2", 10), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 10), ByteRange::Full);
+        assert_eq!(parse_range("bytes=a-b", 10), ByteRange::Full);
+
+        assert_eq!(
+            ByteRange::Partial { start: 0, end: 3 }.content_range(10).as_deref(),
+            Some("bytes 0-3/10")
+        );
+        assert_eq!(ByteRange::Unsatisfiable.content_range(10).as_deref(), Some("bytes */10"));
+    }
+
+    #[test]
+    fn if_range_falls_back_to_full_body_when_stale() {
+        let modified = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let etag = attachment_etag(Uuid::nil());
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=0-1"));
+        let partial = ByteRange::Partial { start: 0, end: 1 };
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), partial);
+
+        headers.insert(header::IF_RANGE, HeaderValue::from_str(&etag).unwrap());
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), partial);
+        headers.insert(header::IF_RANGE, HeaderValue::from_static("\"other\""));
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), ByteRange::Full);
+        headers.insert(header::IF_RANGE, HeaderValue::from_str(&format!("W/{}", etag)).unwrap());
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), ByteRange::Full);
+
+        headers.insert(header::IF_RANGE, HeaderValue::from_str(&http_date(modified)).unwrap());
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), partial);
+        headers.insert(header::IF_RANGE, HeaderValue::from_static("Sun, 01 Mar 2026 11:00:00 GMT"));
+        assert_eq!(requested_range(&headers, 10, &etag, Some(modified)), ByteRange::Full);
+        headers.insert(header::IF_RANGE, HeaderValue::from_str(&http_date(modified)).unwrap());
+        assert_eq!(requested_range(&headers, 10, &etag, None), ByteRange::Full);
+        assert_eq!(blob_etag("ab/cdef"), "\"abcdef\"");
+    }
+
+    #[test]
+    fn disposition_by_content_type() {
+        assert_eq!(content_disposition("video/mp4", false, None), "inline");
+        assert_eq!(content_disposition("image/png", false, None), "inline");
+        assert_eq!(content_disposition("text/plain; charset=utf-8", false, None), "inline");
+        assert_eq!(content_disposition("text/html", false, None), "attachment");
+        assert_eq!(content_disposition("image/svg+xml", false, None), "attachment");
+        assert_eq!(content_disposition("application/octet-stream", false, None), "attachment");
+        assert_eq!(content_disposition("video/mp4", true, None), "attachment");
+        assert_eq!(
+            content_disposition("video/mp4", true, Some("../clip \"1\".mp4")),
+            "attachment; filename=\"..clip _1_.mp4\"; filename*=UTF-8''..clip%20%221%22.mp4"
+        );
+        assert_eq!(
+            content_disposition("image/png", false, Some("café.png")),
+            "inline; filename=\"caf_.png\"; filename*=UTF-8''caf%C3%A9.png"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Progress tracking for server restores.
+//!
+//! A restore is one `restore_server` call followed by any number of
+//! `import_messages` batches, each a separate request. A [`RestoreTracker`]
+//! ties them together as one job: every change of phase or progress is
+//! persisted to `restore_jobs` and pushed to the requester as a
+//! `RestoreProgress` event on all their connections, so a client that
+//! reloads mid-restore can fetch the job and keep following it.
+//!
+//! Phases are `structure` (the restore itself, total 1) and `messages`
+//! (total = the message count the client announced). The job completes when
+//! the last phase's `done` reaches its `total`, or fails with the error of
+//! the first request that failed.
+
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::models::{RestoreJob, WsServerMessage};
+use crate::pubsub;
+use crate::AppState;
+
+pub const PHASE_STRUCTURE: &str = "structure";
+pub const PHASE_MESSAGES: &str = "messages";
+
+pub struct RestoreTracker<'a> {
+    state: &'a AppState,
+    job_id: Uuid,
+}
+
+impl<'a> RestoreTracker<'a> {
+    /// Create a job in the structure phase.
+    pub async fn start(state: &'a AppState, server_id: Uuid, user_id: Uuid) -> AppResult<Self> {
+        let job = queries::create_restore_job(state.db.write(), server_id, user_id).await?;
+        let tracker = Self { state, job_id: job.id };
+        tracker.set_phase(PHASE_STRUCTURE, 1).await?;
+        Ok(tracker)
+    }
+
+    /// Follow an existing job, e.g. from an import batch.
+    pub fn attach(state: &'a AppState, job_id: Uuid) -> Self {
+        Self { state, job_id }
+    }
+
+    pub fn job_id(&self) -> Uuid {
+        self.job_id
+    }
+
+    pub async fn set_phase(&self, phase: &str, total: i64) -> AppResult<()> {
+        let job = queries::set_restore_job_phase(self.state.db.write(), self.job_id, phase, total).await?;
+        self.report(job).await;
+        Ok(())
+    }
+
+    pub async fn advance(&self, count: i64) -> AppResult<()> {
+        let job = queries::advance_restore_job(self.state.db.write(), self.job_id, count).await?;
+        self.report(job).await;
+        Ok(())
+    }
+
+    pub async fn complete(&self) -> AppResult<()> {
+        let job = queries::finish_restore_job(self.state.db.write(), self.job_id, "completed", None).await?;
+        self.report(job).await;
+        Ok(())
+    }
+
+    /// Best effort: the request is already failing with `error`.
+    pub async fn fail(&self, error: &str) {
+        match queries::finish_restore_job(self.state.db.write(), self.job_id, "failed", Some(error)).await {
+            Ok(job) => self.report(job).await,
+            Err(e) => tracing::warn!(job_id = %self.job_id, "Failed to mark restore job failed: {:?}", e),
+        }
+    }
+
+    /// Push the job's new state; `None` means it had already finished.
+    async fn report(&self, job: Option<RestoreJob>) {
+        let Some(job) = job else { return };
+        let msg = WsServerMessage::RestoreProgress {
+            job_id: job.id,
+            server_id: job.server_id,
+            phase: job.phase,
+            done: job.done,
+            total: job.total,
+            status: job.status,
+        };
+        pubsub::broadcast_user_event(self.state, job.user_id, &msg).await;
+    }
+}
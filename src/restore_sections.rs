@@ -0,0 +1,428 @@
+//! Pluggable sections of the .haven backup format.
+//!
+//! `restore_server` handles the core structure (categories, channels, roles,
+//! overwrites) itself. Every other configuration subsystem registers a
+//! [`RestoreSection`] here: the server export writes each section's data under
+//! `sections.<key>`, and a restore hands that data back to the same section
+//! inside the restore transaction, after the core structure exists so sections
+//! can map backup channel/role ids to the new ones.
+//!
+//! Sections must never export secrets, and must skip (not fail on) entries
+//! that no longer apply to the target server.
+
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::{Connection, Pool};
+use crate::errors::{AppError, AppResult};
+
+/// Ids resolved by the core restore, available to every section.
+pub struct RestoreContext<'a> {
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    /// backup channel id → new channel id
+    pub channel_map: &'a HashMap<String, Uuid>,
+    /// backup role id → new role id
+    pub role_map: &'a HashMap<String, Uuid>,
+}
+
+/// A configuration subsystem that round-trips through backup and restore.
+pub trait RestoreSection: Send + Sync {
+    /// Key under `sections` in the backup document. Never change once shipped.
+    fn key(&self) -> &'static str;
+
+    /// Serialize this server's configuration for the backup.
+    fn export<'a>(&'a self, pool: &'a Pool, server_id: Uuid) -> BoxFuture<'a, AppResult<serde_json::Value>>;
+
+    /// Apply backed-up configuration inside the restore transaction.
+    /// Returns the number of entries restored.
+    fn restore<'a>(
+        &'a self,
+        conn: &'a mut Connection,
+        ctx: &'a RestoreContext<'a>,
+        data: serde_json::Value,
+    ) -> BoxFuture<'a, AppResult<usize>>;
+}
+
+/// Every registered section, in restore order.
+pub fn registry() -> &'static [&'static dyn RestoreSection] {
+    &[&ContentFilterSection, &EmojiSection, &MemberNicknameSection, &PermissionTemplateSection]
+}
+
+/// Look up a registered section by key.
+pub fn find(key: &str) -> Option<&'static dyn RestoreSection> {
+    registry().iter().copied().find(|s| s.key() == key)
+}
+
+/// Export every registered section, keyed by section key.
+pub async fn export_all(pool: &Pool, server_id: Uuid) -> AppResult<HashMap<String, serde_json::Value>> {
+    let mut sections = HashMap::new();
+    for section in registry() {
+        sections.insert(section.key().to_string(), section.export(pool, server_id).await?);
+    }
+    Ok(sections)
+}
+
+fn parse_section<T: serde::de::DeserializeOwned>(key: &str, data: serde_json::Value) -> AppResult<T> {
+    serde_json::from_value(data)
+        .map_err(|e| AppError::Validation(format!("Invalid \"{}\" section: {}", key, e)))
+}
+
+// ─── Content filters (automod) ───────────────────────
+
+/// Server content filters. Restoring replaces the server's filters with the
+/// backed-up set, attributed to the restoring user.
+struct ContentFilterSection;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ContentFilterEntry {
+    pattern: String,
+    filter_type: String,
+    action: String,
+}
+
+impl ContentFilterEntry {
+    /// Same rules as the content-filter API.
+    fn is_valid(&self) -> bool {
+        !self.pattern.is_empty()
+            && self.pattern.len() <= 200
+            && matches!(self.action.as_str(), "hide" | "warn")
+            && match self.filter_type.as_str() {
+                "keyword" => true,
+                "regex" => regex::Regex::new(&self.pattern).is_ok(),
+                "abuse" => matches!(self.pattern.parse::<i16>(), Ok(1..=100)),
+                _ => false,
+            }
+    }
+}
+
+impl RestoreSection for ContentFilterSection {
+    fn key(&self) -> &'static str {
+        "content_filters"
+    }
+
+    fn export<'a>(&'a self, pool: &'a Pool, server_id: Uuid) -> BoxFuture<'a, AppResult<serde_json::Value>> {
+        Box::pin(async move {
+            let entries: Vec<ContentFilterEntry> = crate::db::queries::list_content_filters(pool, server_id)
+                .await?
+                .into_iter()
+                .map(|f| ContentFilterEntry { pattern: f.pattern, filter_type: f.filter_type, action: f.action })
+                .collect();
+            Ok(serde_json::to_value(entries).expect("content filter entries serialize"))
+        })
+    }
+
+    fn restore<'a>(
+        &'a self,
+        conn: &'a mut Connection,
+        ctx: &'a RestoreContext<'a>,
+        data: serde_json::Value,
+    ) -> BoxFuture<'a, AppResult<usize>> {
+        Box::pin(async move {
+            let entries: Vec<ContentFilterEntry> = parse_section(self.key(), data)?;
+            if entries.len() > 50 {
+                return Err(AppError::Validation("Too many content filters (max 50)".into()));
+            }
+
+            sqlx::query("DELETE FROM content_filters WHERE server_id = $1")
+                .bind(ctx.server_id)
+                .execute(&mut *conn)
+                .await?;
+
+            let mut restored = 0;
+            for entry in entries.iter().filter(|e| e.is_valid()) {
+                sqlx::query(
+                    r#"INSERT INTO content_filters (server_id, pattern, filter_type, action, created_by)
+                       VALUES ($1, $2, $3, $4, $5)"#,
+                )
+                .bind(ctx.server_id)
+                .bind(&entry.pattern)
+                .bind(&entry.filter_type)
+                .bind(&entry.action)
+                .bind(ctx.user_id)
+                .execute(&mut *conn)
+                .await?;
+                restored += 1;
+            }
+            Ok(restored)
+        })
+    }
+}
+
+// ─── Emojis ──────────────────────────────────────────
+
+/// Custom emoji metadata. Images are not part of the backup, so restoring only
+/// renames emojis that still exist on the server; missing ones are skipped.
+struct EmojiSection;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmojiEntry {
+    id: Uuid,
+    name: String,
+    animated: bool,
+}
+
+impl RestoreSection for EmojiSection {
+    fn key(&self) -> &'static str {
+        "emojis"
+    }
+
+    fn export<'a>(&'a self, pool: &'a Pool, server_id: Uuid) -> BoxFuture<'a, AppResult<serde_json::Value>> {
+        Box::pin(async move {
+            let entries: Vec<EmojiEntry> = crate::db::queries::list_server_emojis(pool, server_id)
+                .await?
+                .into_iter()
+                .map(|e| EmojiEntry { id: e.id, name: e.name, animated: e.animated })
+                .collect();
+            Ok(serde_json::to_value(entries).expect("emoji entries serialize"))
+        })
+    }
+
+    fn restore<'a>(
+        &'a self,
+        conn: &'a mut Connection,
+        ctx: &'a RestoreContext<'a>,
+        data: serde_json::Value,
+    ) -> BoxFuture<'a, AppResult<usize>> {
+        Box::pin(async move {
+            let entries: Vec<EmojiEntry> = parse_section(self.key(), data)?;
+            if entries.len() > 500 {
+                return Err(AppError::Validation("Too many emojis (max 500)".into()));
+            }
+
+            let mut restored = 0;
+            for entry in entries {
+                let valid_name = (2..=64).contains(&entry.name.len())
+                    && entry.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid_name {
+                    continue;
+                }
+                // Skip names already taken by another emoji: a unique violation
+                // would abort the whole restore transaction.
+                let result = sqlx::query(
+                    r#"UPDATE custom_emojis SET name = $3
+                       WHERE id = $1 AND server_id = $2
+                         AND NOT EXISTS (
+                           SELECT 1 FROM custom_emojis
+                           WHERE server_id = $2 AND name = $3 AND id <> $1
+                         )"#,
+                )
+                .bind(entry.id)
+                .bind(ctx.server_id)
+                .bind(&entry.name)
+                .execute(&mut *conn)
+                .await?;
+                restored += result.rows_affected() as usize;
+            }
+            Ok(restored)
+        })
+    }
+}
+
+// ─── Member nicknames ────────────────────────────────
+
+/// Per-server nicknames. Restoring sets the nickname of each backed-up member
+/// who is still in the server; members who left are skipped.
+struct MemberNicknameSection;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MemberNicknameEntry {
+    user_id: Uuid,
+    nickname: String,
+}
+
+impl RestoreSection for MemberNicknameSection {
+    fn key(&self) -> &'static str {
+        "member_nicknames"
+    }
+
+    fn export<'a>(&'a self, pool: &'a Pool, server_id: Uuid) -> BoxFuture<'a, AppResult<serde_json::Value>> {
+        Box::pin(async move {
+            let entries: Vec<MemberNicknameEntry> = crate::db::queries::list_member_nicknames(pool, server_id)
+                .await?
+                .into_iter()
+                .map(|(user_id, nickname)| MemberNicknameEntry { user_id, nickname })
+                .collect();
+            Ok(serde_json::to_value(entries).expect("member nickname entries serialize"))
+        })
+    }
+
+    fn restore<'a>(
+        &'a self,
+        conn: &'a mut Connection,
+        ctx: &'a RestoreContext<'a>,
+        data: serde_json::Value,
+    ) -> BoxFuture<'a, AppResult<usize>> {
+        Box::pin(async move {
+            let entries: Vec<MemberNicknameEntry> = parse_section(self.key(), data)?;
+
+            let mut restored = 0;
+            for entry in entries.iter().filter(|e| crate::api::servers::is_valid_nickname(&e.nickname)) {
+                let result = sqlx::query(
+                    "UPDATE server_members SET nickname = $3 WHERE server_id = $1 AND user_id = $2",
+                )
+                .bind(ctx.server_id)
+                .bind(entry.user_id)
+                .bind(&entry.nickname)
+                .execute(&mut *conn)
+                .await?;
+                restored += result.rows_affected() as usize;
+            }
+            if restored > 0 {
+                sqlx::query("UPDATE servers SET list_version = list_version + 1 WHERE id = $1")
+                    .bind(ctx.server_id)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            Ok(restored)
+        })
+    }
+}
+
+// ─── Permission templates ────────────────────────────
+
+/// Permission templates for new roles and channels. Restoring replaces the
+/// server's templates with the backed-up set.
+struct PermissionTemplateSection;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PermissionTemplateEntry {
+    name: String,
+    /// Bits as strings, like the API
+    allow_bits: String,
+    deny_bits: String,
+}
+
+impl PermissionTemplateEntry {
+    /// The entry's bits, if they follow the API rules.
+    fn bits(&self) -> Option<(i64, i64)> {
+        let allow: i64 = self.allow_bits.parse().ok()?;
+        let deny: i64 = self.deny_bits.parse().ok()?;
+        let name = self.name.trim();
+        let valid = !name.is_empty()
+            && name.chars().count() <= 64
+            && allow >= 0
+            && deny >= 0
+            && allow & deny == 0;
+        valid.then_some((allow, deny))
+    }
+}
+
+impl RestoreSection for PermissionTemplateSection {
+    fn key(&self) -> &'static str {
+        "permission_templates"
+    }
+
+    fn export<'a>(&'a self, pool: &'a Pool, server_id: Uuid) -> BoxFuture<'a, AppResult<serde_json::Value>> {
+        Box::pin(async move {
+            let entries: Vec<PermissionTemplateEntry> =
+                crate::db::queries::list_permission_templates(pool, server_id)
+                    .await?
+                    .into_iter()
+                    .map(|t| PermissionTemplateEntry {
+                        name: t.name,
+                        allow_bits: t.allow_bits.to_string(),
+                        deny_bits: t.deny_bits.to_string(),
+                    })
+                    .collect();
+            Ok(serde_json::to_value(entries).expect("permission template entries serialize"))
+        })
+    }
+
+    fn restore<'a>(
+        &'a self,
+        conn: &'a mut Connection,
+        ctx: &'a RestoreContext<'a>,
+        data: serde_json::Value,
+    ) -> BoxFuture<'a, AppResult<usize>> {
+        Box::pin(async move {
+            let entries: Vec<PermissionTemplateEntry> = parse_section(self.key(), data)?;
+            if entries.len() > 25 {
+                return Err(AppError::Validation("Too many permission templates (max 25)".into()));
+            }
+
+            sqlx::query("DELETE FROM permission_templates WHERE server_id = $1")
+                .bind(ctx.server_id)
+                .execute(&mut *conn)
+                .await?;
+
+            let mut restored = 0;
+            for entry in &entries {
+                let Some((allow, deny)) = entry.bits() else {
+                    continue;
+                };
+                // A repeated name would abort the restore transaction
+                let result = sqlx::query(
+                    r#"INSERT INTO permission_templates (server_id, name, allow_bits, deny_bits)
+                       VALUES ($1, $2, $3, $4)
+                       ON CONFLICT (server_id, name) DO NOTHING"#,
+                )
+                .bind(ctx.server_id)
+                .bind(entry.name.trim())
+                .bind(allow)
+                .bind(deny)
+                .execute(&mut *conn)
+                .await?;
+                restored += result.rows_affected() as usize;
+            }
+            Ok(restored)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_keys_are_unique() {
+        let mut keys: Vec<&str> = registry().iter().map(|s| s.key()).collect();
+        let total = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), total);
+    }
+
+    #[test]
+    fn content_filter_entries_follow_api_rules() {
+        let entry = |pattern: &str, filter_type: &str, action: &str| ContentFilterEntry {
+            pattern: pattern.into(),
+            filter_type: filter_type.into(),
+            action: action.into(),
+        };
+        assert!(entry("spam", "keyword", "hide").is_valid());
+        assert!(entry("^sp+am$", "regex", "warn").is_valid());
+        assert!(!entry("(", "regex", "hide").is_valid());
+        assert!(entry("60", "abuse", "warn").is_valid());
+        assert!(!entry("0", "abuse", "hide").is_valid());
+        assert!(!entry("spam", "keyword", "ban").is_valid());
+        assert!(!entry("", "keyword", "hide").is_valid());
+    }
+
+    #[test]
+    fn permission_template_entries_follow_api_rules() {
+        let entry = |name: &str, allow: &str, deny: &str| PermissionTemplateEntry {
+            name: name.into(),
+            allow_bits: allow.into(),
+            deny_bits: deny.into(),
+        };
+        assert_eq!(entry("Moderator", "80", "0").bits(), Some((80, 0)));
+        assert_eq!(entry("Read-only", "0", "256").bits(), Some((0, 256)));
+        assert!(entry("Both", "256", "256").bits().is_none());
+        assert!(entry("Negative", "-1", "0").bits().is_none());
+        assert!(entry("Junk", "lots", "0").bits().is_none());
+        assert!(entry("  ", "0", "0").bits().is_none());
+    }
+
+    #[test]
+    fn find_resolves_registered_sections() {
+        assert_eq!(find("emojis").map(|s| s.key()), Some("emojis"));
+        assert_eq!(find("content_filters").map(|s| s.key()), Some("content_filters"));
+        assert_eq!(find("member_nicknames").map(|s| s.key()), Some("member_nicknames"));
+        assert_eq!(find("permission_templates").map(|s| s.key()), Some("permission_templates"));
+        assert!(find("automod").is_none());
+    }
+}
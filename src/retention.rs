@@ -0,0 +1,140 @@
+//! Per-channel message retention.
+//!
+//! A server channel can keep messages forever (no policy), for N days, or
+//! only its newest N messages; pinned messages are always kept. The
+//! channel-retention job deletes what falls outside each policy in batches,
+//! tells subscribed clients with `MessagesExpired`, and writes the count to
+//! the server's audit log. With `archive` set, every batch is first stored in
+//! backup storage as an encrypted JSON archive, and a batch whose archive
+//! can't be written is left in place for the next run. Attachments of purged
+//! messages are reclaimed by the attachment GC.
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::queries;
+use crate::errors::AppResult;
+use crate::models::{MessageResponse, RetentionSweepTarget, WsServerMessage};
+use crate::pubsub;
+use crate::AppState;
+
+/// Messages deleted (and archived) per batch.
+const BATCH_SIZE: i64 = 1000;
+/// Batches per channel per run, so one large backlog can't hold up the rest.
+const MAX_BATCHES: usize = 20;
+
+/// Backup storage key of an archive: grouped by server and channel, named by
+/// time so a listing sorts chronologically.
+fn archive_key(server_id: Uuid, channel_id: Uuid) -> String {
+    format!(
+        "retention/{}/{}/{}-{}.json",
+        server_id,
+        channel_id,
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        Uuid::new_v4()
+    )
+}
+
+/// Apply every channel's policy. Returns the number of messages deleted.
+pub async fn sweep(state: &AppState) -> AppResult<u64> {
+    let targets = queries::list_retention_sweep_targets(state.db.primary()).await?;
+    let mut total = 0;
+    for target in targets {
+        match sweep_channel(state, &target).await {
+            Ok(deleted) => total += deleted,
+            Err(e) => tracing::error!(
+                "Retention sweep of channel {} failed: {}",
+                target.channel_id,
+                e
+            ),
+        }
+    }
+    Ok(total)
+}
+
+async fn sweep_channel(state: &AppState, target: &RetentionSweepTarget) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let backup = match (target.archive, &state.backup_storage) {
+        (true, None) => {
+            tracing::warn!(
+                "Channel {} archives before purging but no backup storage is configured; skipping",
+                target.channel_id
+            );
+            return Ok(0);
+        }
+        (archive, backup) => backup.as_ref().filter(|_| archive),
+    };
+
+    let mut deleted = 0u64;
+    for _ in 0..MAX_BATCHES {
+        let messages = queries::get_messages_past_retention(
+            pool,
+            target.channel_id,
+            target.max_age_days,
+            target.max_messages,
+            BATCH_SIZE,
+        )
+        .await?;
+        if messages.is_empty() {
+            break;
+        }
+        let batch_len = messages.len();
+        let ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+
+        if let Some(backup) = backup {
+            let archive = serde_json::json!({
+                "server_id": target.server_id,
+                "channel_id": target.channel_id,
+                "archived_at": Utc::now(),
+                "message_count": batch_len,
+                "messages": messages.into_iter().map(MessageResponse::from).collect::<Vec<_>>(),
+            });
+            let key = archive_key(target.server_id, target.channel_id);
+            if let Err(e) = backup
+                .store_blob(&key, archive.to_string().as_bytes())
+                .await
+            {
+                tracing::error!(
+                    "Failed to archive messages of channel {}: {}",
+                    target.channel_id,
+                    e
+                );
+                break;
+            }
+        }
+
+        let removed = queries::bulk_delete_messages(pool, target.channel_id, &ids).await?;
+        deleted += removed.len() as u64;
+        if !removed.is_empty() {
+            let msg = WsServerMessage::MessagesExpired {
+                channel_id: target.channel_id,
+                message_ids: removed,
+            };
+            if let Some(broadcaster) = state.channel_broadcasts.get(&target.channel_id) {
+                let _ = broadcaster.send(msg.clone());
+            }
+            pubsub::publish_channel_event(state, target.channel_id, &msg).await;
+        }
+        if (batch_len as i64) < BATCH_SIZE {
+            break;
+        }
+    }
+
+    if deleted > 0 {
+        let _ = queries::insert_audit_log(
+            pool,
+            target.server_id,
+            target.actor_id,
+            "channel_retention_purge",
+            Some("channel"),
+            Some(target.channel_id),
+            Some(&serde_json::json!({
+                "deleted_messages": deleted,
+                "archived": backup.is_some(),
+            })),
+            None,
+        )
+        .await;
+    }
+    Ok(deleted)
+}
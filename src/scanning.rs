@@ -0,0 +1,440 @@
+//! Malware scanning of attachments.
+//!
+//! When `ATTACHMENT_SCANNER` is set, attachments posted to unencrypted
+//! channels are queued for a scan as they are linked to their message and
+//! can't be downloaded until the verdict is in. The scanner is clamd (its
+//! INSTREAM command over TCP or a Unix socket) or an ICAP service (RESPMOD,
+//! which must answer 204 for clean files). Positives are quarantined: the
+//! row and blob stay, downloads are refused, the server's audit log gets an
+//! entry and members who can manage messages are told over WS. Ciphertext
+//! can't be scanned, so attachments in encrypted channels are never queued.
+//!
+//! Scans run in background tasks. The `attachment-scans` maintenance job
+//! picks up any left pending, e.g. by a restart.
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::{Attachment, ScanStatus, WsServerMessage};
+use crate::permissions;
+use crate::AppState;
+
+/// Bytes per INSTREAM chunk; clamd's StreamMaxLength caps the total.
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+const ICAP_DEFAULT_PORT: u16 = 1344;
+/// Longest ICAP response head we read before giving up on it.
+const ICAP_MAX_HEAD: usize = 64 * 1024;
+/// Pending scans older than this are assumed lost and run again.
+const STALE_PENDING_MINS: i32 = 10;
+const RESUME_BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// Signature name as reported by the scanner
+    Infected(String),
+}
+
+pub trait Scanner: Send + Sync {
+    fn scan<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, anyhow::Result<ScanVerdict>>;
+}
+
+/// The scanner `config` selects, `None` if scanning is off, or a
+/// description of what is invalid.
+pub fn from_config(config: &AppConfig) -> Result<Option<Box<dyn Scanner>>, String> {
+    let url = config.attachment_scanner_url.as_str();
+    match config.attachment_scanner.as_str() {
+        "" => Ok(None),
+        "clamd" => Ok(Some(Box::new(ClamdScanner { addr: ClamdAddr::parse(url)? }))),
+        "icap" => Ok(Some(Box::new(IcapScanner::parse(url)?))),
+        other => Err(format!("ATTACHMENT_SCANNER must be empty, 'clamd' or 'icap', got '{}'.", other)),
+    }
+}
+
+/// Whether attachments posted to a channel are scanned.
+pub fn should_scan(config: &AppConfig, channel_encrypted: bool) -> bool {
+    !config.attachment_scanner.is_empty() && !channel_encrypted
+}
+
+/// Refuse downloads of an attachment its scan hasn't cleared.
+pub fn check_downloadable(config: &AppConfig, att: &Attachment) -> AppResult<()> {
+    match att.scan_status.as_deref().and_then(ScanStatus::parse) {
+        Some(ScanStatus::Pending) => Err(AppError::Conflict("Attachment is still being scanned".into())
+            .with_code("ATTACHMENT_SCAN_PENDING")),
+        Some(ScanStatus::Infected) => Err(AppError::Forbidden(
+            "Attachment was quarantined by the malware scanner".into(),
+        )
+        .with_code("ATTACHMENT_QUARANTINED")),
+        Some(ScanStatus::Failed) if !config.attachment_scan_fail_open => {
+            Err(AppError::Conflict("Attachment could not be scanned".into()).with_code("ATTACHMENT_SCAN_FAILED"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Scan a linked attachment in the background and record the verdict.
+pub fn spawn_scan(state: &AppState, attachment_id: Uuid, storage_key: String) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = scan_attachment(&state, attachment_id, &storage_key).await {
+            tracing::error!("Failed to scan attachment {}: {}", attachment_id, e);
+        }
+    });
+}
+
+/// Run scans left pending (the `attachment-scans` job). Returns how many.
+pub async fn resume_pending(state: &AppState) -> AppResult<u64> {
+    if state.config.attachment_scanner.is_empty() {
+        return Err(AppError::BadRequest("Attachment scanning is disabled".into()));
+    }
+    let pending =
+        queries::list_stale_pending_scans(state.db.primary(), STALE_PENDING_MINS, RESUME_BATCH_SIZE).await?;
+    let count = pending.len() as u64;
+    for (attachment_id, storage_key) in pending {
+        scan_attachment(state, attachment_id, &storage_key).await?;
+    }
+    Ok(count)
+}
+
+async fn scan_attachment(state: &AppState, attachment_id: Uuid, storage_key: &str) -> AppResult<()> {
+    let scanner = from_config(&state.config)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!(e)))?
+        .ok_or_else(|| AppError::BadRequest("Attachment scanning is disabled".into()))?;
+
+    let result = match load_attachment(state, storage_key).await {
+        Ok(data) => {
+            let timeout = Duration::from_secs(state.config.attachment_scan_timeout_secs);
+            match tokio::time::timeout(timeout, scanner.scan(&data)).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("timed out after {:?}", timeout)),
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    let pool = state.db.primary();
+    match result {
+        Ok(ScanVerdict::Clean) => {
+            queries::set_attachment_scan_verdict(pool, attachment_id, ScanStatus::Clean, None).await?;
+        }
+        Ok(ScanVerdict::Infected(signature)) => {
+            tracing::warn!("Attachment {} quarantined: {}", attachment_id, signature);
+            if queries::set_attachment_scan_verdict(pool, attachment_id, ScanStatus::Infected, Some(&signature))
+                .await?
+            {
+                notify_moderators(state, attachment_id, &signature).await?;
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Malware scan of attachment {} failed: {}", attachment_id, e);
+            queries::set_attachment_scan_verdict(pool, attachment_id, ScanStatus::Failed, None).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Attachments are stored raw with the CDN and encrypted at rest without it.
+async fn load_attachment(state: &AppState, storage_key: &str) -> anyhow::Result<Vec<u8>> {
+    let data = if state.config.cdn_enabled {
+        state.storage.load_blob_raw(storage_key).await
+    } else {
+        state.storage.load_blob(storage_key).await
+    };
+    data.map_err(|e| anyhow::anyhow!("failed to load attachment: {}", e))
+}
+
+/// Record the quarantine in the server's audit log and tell the members
+/// who can manage messages.
+async fn notify_moderators(state: &AppState, attachment_id: Uuid, signature: &str) -> AppResult<()> {
+    let pool = state.db.primary();
+    let Some(att) = queries::find_attachment_by_id(pool, attachment_id).await? else {
+        return Ok(());
+    };
+    let Some(server_id) = att.server_id else {
+        return Ok(());
+    };
+    let Some(message) = queries::find_message_by_id(pool, att.message_id).await? else {
+        return Ok(());
+    };
+
+    if let Some(system_user) = queries::find_system_user(pool).await? {
+        let _ = queries::insert_audit_log(
+            pool,
+            server_id,
+            system_user.id,
+            "attachment_quarantine",
+            Some("attachment"),
+            Some(attachment_id),
+            Some(&serde_json::json!({
+                "signature": signature,
+                "message_id": message.id,
+                "channel_id": message.channel_id,
+            })),
+            None,
+        )
+        .await;
+    }
+
+    let msg = WsServerMessage::AttachmentQuarantined {
+        server_id,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        attachment_id,
+        signature: signature.to_string(),
+    };
+    let moderators =
+        queries::list_server_member_ids_with_permission(pool, server_id, permissions::MANAGE_MESSAGES).await?;
+    for user_id in moderators {
+        crate::pubsub::broadcast_user_event(state, user_id, &msg).await;
+    }
+    Ok(())
+}
+
+// ─── clamd ───────────────────────────────────────────
+
+enum ClamdAddr {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl ClamdAddr {
+    fn parse(url: &str) -> Result<Self, String> {
+        if let Some(addr) = url.strip_prefix("tcp://").filter(|addr| !addr.is_empty()) {
+            return Ok(ClamdAddr::Tcp(addr.to_string()));
+        }
+        #[cfg(unix)]
+        if let Some(path) = url.strip_prefix("unix://").filter(|path| !path.is_empty()) {
+            return Ok(ClamdAddr::Unix(path.into()));
+        }
+        Err(format!(
+            "ATTACHMENT_SCANNER_URL must be tcp://host:port or unix:///path for clamd, got '{}'.",
+            url
+        ))
+    }
+}
+
+struct ClamdScanner {
+    addr: ClamdAddr,
+}
+
+impl Scanner for ClamdScanner {
+    fn scan<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, anyhow::Result<ScanVerdict>> {
+        Box::pin(async move {
+            match &self.addr {
+                ClamdAddr::Tcp(addr) => clamd_instream(TcpStream::connect(addr).await?, data).await,
+                #[cfg(unix)]
+                ClamdAddr::Unix(path) => clamd_instream(tokio::net::UnixStream::connect(path).await?, data).await,
+            }
+        })
+    }
+}
+
+/// clamd's INSTREAM: length-prefixed chunks, ended by an empty one.
+async fn clamd_instream<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, data: &[u8]) -> anyhow::Result<ScanVerdict> {
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in data.chunks(CLAMD_CHUNK_SIZE) {
+        stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    parse_clamd_reply(&String::from_utf8_lossy(&reply))
+}
+
+/// `stream: OK`, `stream: <signature> FOUND` or `<message> ERROR`.
+fn parse_clamd_reply(reply: &str) -> anyhow::Result<ScanVerdict> {
+    let reply = reply.trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+    let result = reply.strip_prefix("stream:").map_or(reply, str::trim);
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean);
+    }
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(ScanVerdict::Infected(signature.trim().to_string()));
+    }
+    anyhow::bail!("clamd replied '{}'", reply)
+}
+
+// ─── ICAP ────────────────────────────────────────────
+
+struct IcapScanner {
+    /// host:port to connect to
+    addr: String,
+    host: String,
+    uri: String,
+}
+
+impl IcapScanner {
+    fn parse(url: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "ATTACHMENT_SCANNER_URL must be icap://host[:port]/service for icap, got '{}'.",
+                url
+            )
+        };
+        let (authority, service) = url
+            .strip_prefix("icap://")
+            .and_then(|rest| rest.split_once('/'))
+            .filter(|(authority, service)| !authority.is_empty() && !service.is_empty())
+            .ok_or_else(invalid)?;
+        let addr = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:{}", authority, ICAP_DEFAULT_PORT)
+        };
+        Ok(IcapScanner {
+            addr,
+            host: authority.to_string(),
+            uri: format!("icap://{}/{}", authority, service),
+        })
+    }
+}
+
+impl Scanner for IcapScanner {
+    fn scan<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, anyhow::Result<ScanVerdict>> {
+        Box::pin(async move {
+            let mut stream = TcpStream::connect(&self.addr).await?;
+
+            // The attachment goes as the body of a made-up HTTP response
+            let res_hdr = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+                data.len()
+            );
+            let head = format!(
+                "RESPMOD {} ICAP/1.0\r\nHost: {}\r\nAllow: 204\r\nConnection: close\r\n\
+                 Encapsulated: res-hdr=0, res-body={}\r\n\r\n{}",
+                self.uri,
+                self.host,
+                res_hdr.len(),
+                res_hdr
+            );
+            stream.write_all(head.as_bytes()).await?;
+            if !data.is_empty() {
+                stream.write_all(format!("{:x}\r\n", data.len()).as_bytes()).await?;
+                stream.write_all(data).await?;
+                stream.write_all(b"\r\n").await?;
+            }
+            stream.write_all(b"0\r\n\r\n").await?;
+
+            let mut response = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !response.windows(4).any(|w| w == b"\r\n\r\n") && response.len() < ICAP_MAX_HEAD {
+                let n = stream.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                response.extend_from_slice(&buf[..n]);
+            }
+            parse_icap_response(&String::from_utf8_lossy(&response))
+        })
+    }
+}
+
+/// 204 means unmodified (clean). 200 means the service replaced the
+/// response, which it only does to block it; the threat is named in
+/// `X-Infection-Found` or `X-Virus-ID` when the service says.
+fn parse_icap_response(response: &str) -> anyhow::Result<ScanVerdict> {
+    let mut lines = response.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .strip_prefix("ICAP/")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow::anyhow!("malformed ICAP response '{}'", status_line))?;
+    match status {
+        204 => Ok(ScanVerdict::Clean),
+        200 => {
+            for line in lines.take_while(|line| !line.is_empty()) {
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                if name.eq_ignore_ascii_case("X-Infection-Found") {
+                    let threat = value
+                        .split(';')
+                        .find_map(|part| part.trim().strip_prefix("Threat="))
+                        .unwrap_or(value.trim());
+                    return Ok(ScanVerdict::Infected(threat.to_string()));
+                }
+                if name.eq_ignore_ascii_case("X-Virus-ID") {
+                    return Ok(ScanVerdict::Infected(value.trim().to_string()));
+                }
+            }
+            Ok(ScanVerdict::Infected("unknown".into()))
+        }
+        _ => anyhow::bail!("ICAP service replied '{}'", status_line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamd_replies() {
+        assert_eq!(parse_clamd_reply("stream: OK\0").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Eicar-Test-Signature FOUND\0").unwrap(),
+            ScanVerdict::Infected("Eicar-Test-Signature".into())
+        );
+        assert!(parse_clamd_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+        assert!(parse_clamd_reply("").is_err());
+    }
+
+    #[test]
+    fn icap_responses() {
+        assert_eq!(
+            parse_icap_response("ICAP/1.0 204 No Content\r\nISTag: \"x\"\r\n\r\n").unwrap(),
+            ScanVerdict::Clean
+        );
+        let blocked = "ICAP/1.0 200 OK\r\n\
+                       X-Infection-Found: Type=0; Resolution=2; Threat=Eicar-Test-Signature;\r\n\
+                       Encapsulated: res-hdr=0, res-body=100\r\n\r\n";
+        assert_eq!(
+            parse_icap_response(blocked).unwrap(),
+            ScanVerdict::Infected("Eicar-Test-Signature".into())
+        );
+        assert_eq!(
+            parse_icap_response("ICAP/1.0 200 OK\r\nX-Virus-ID: EICAR\r\n\r\n").unwrap(),
+            ScanVerdict::Infected("EICAR".into())
+        );
+        assert_eq!(
+            parse_icap_response("ICAP/1.0 200 OK\r\n\r\n").unwrap(),
+            ScanVerdict::Infected("unknown".into())
+        );
+        assert!(parse_icap_response("ICAP/1.0 500 Server Error\r\n\r\n").is_err());
+        assert!(parse_icap_response("HTTP/1.1 200 OK\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn scanner_config() {
+        let mut config = AppConfig::test_default();
+        assert!(from_config(&config).unwrap().is_none());
+        assert!(!should_scan(&config, false));
+
+        config.attachment_scanner = "clamd".into();
+        config.attachment_scanner_url = "tcp://127.0.0.1:3310".into();
+        assert!(from_config(&config).unwrap().is_some());
+        assert!(should_scan(&config, false));
+        assert!(!should_scan(&config, true));
+        config.attachment_scanner_url = "127.0.0.1:3310".into();
+        assert!(from_config(&config).is_err());
+
+        config.attachment_scanner = "icap".into();
+        config.attachment_scanner_url = "icap://scanner/avscan".into();
+        assert!(from_config(&config).unwrap().is_some());
+        config.attachment_scanner_url = "icap://scanner".into();
+        assert!(from_config(&config).is_err());
+
+        config.attachment_scanner = "virustotal".into();
+        assert!(from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Graceful shutdown with connection draining.
+//!
+//! On SIGTERM (or Ctrl+C) the listeners stop accepting connections and
+//! in-flight requests run to completion. WebSocket connections are handed off
+//! instead: each is sent `Reconnect` and closed at a random point in the first
+//! half of the drain window, so clients spread their reconnects over the
+//! remaining instances rather than arriving at one all at once. Once they are
+//! gone the database pools are closed, which waits for in-flight transactions
+//! to return their connections. Anything still running when
+//! `SHUTDOWN_DRAIN_SECS` runs out is dropped.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use tokio::sync::watch;
+
+use crate::db::DbPools;
+use crate::ws::ConnectionMap;
+
+#[derive(Clone)]
+pub struct Shutdown {
+    /// When shutdown began; `None` while serving.
+    started: Arc<watch::Sender<Option<Instant>>>,
+    drain_window: Duration,
+}
+
+impl Shutdown {
+    pub fn new(drain_window: Duration) -> Self {
+        Self {
+            started: Arc::new(watch::channel(None).0),
+            drain_window,
+        }
+    }
+
+    pub fn drain_window(&self) -> Duration {
+        self.drain_window
+    }
+
+    /// Begin draining. Later calls keep the original start time.
+    pub fn trigger(&self) {
+        self.started.send_if_modified(|started| {
+            if started.is_some() {
+                return false;
+            }
+            *started = Some(Instant::now());
+            true
+        });
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.started.borrow().is_some()
+    }
+
+    /// Resolves once shutdown has begun, with the time it began.
+    pub async fn triggered(&self) -> Instant {
+        let mut rx = self.started.subscribe();
+        let started = rx.wait_for(Option::is_some).await.map(|started| *started);
+        // The sender lives in `self`, so the wait can't fail
+        started.ok().flatten().unwrap_or_else(Instant::now)
+    }
+
+    /// Owned form of [`Shutdown::triggered`] for `with_graceful_shutdown`.
+    pub fn signal(&self) -> impl Future<Output = ()> + Send + 'static {
+        let this = self.clone();
+        async move {
+            this.triggered().await;
+        }
+    }
+
+    /// Resolves at this connection's turn to be sent `Reconnect`: a random
+    /// point in the first half of the drain window.
+    pub async fn reconnect_turn(&self) {
+        let started = self.triggered().await;
+        let offset = (self.drain_window / 2).mul_f64(rand::thread_rng().gen::<f64>());
+        tokio::time::sleep_until(tokio::time::Instant::from_std(started + offset)).await;
+    }
+
+    /// Resolves when the drain window has run out.
+    pub async fn deadline(&self) {
+        let started = self.triggered().await;
+        tokio::time::sleep_until(tokio::time::Instant::from_std(started + self.drain_window)).await;
+    }
+
+    /// Run once the listeners have stopped: wait for the WebSocket handoff to
+    /// finish, then close the database pools, within what is left of the
+    /// drain window.
+    pub async fn drain(&self, connections: &ConnectionMap, db: &DbPools) {
+        let finish = async {
+            while !connections.is_empty() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            db.close().await;
+        };
+        tokio::select! {
+            _ = finish => tracing::info!("Connections drained"),
+            _ = self.deadline() => tracing::warn!(
+                "Drain window elapsed with {} user(s) still connected or transactions in flight",
+                connections.len()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn trigger_keeps_the_first_start_time() {
+        let shutdown = Shutdown::new(Duration::from_secs(30));
+        assert!(!shutdown.is_draining());
+        shutdown.trigger();
+        let started = shutdown.triggered().await;
+        shutdown.trigger();
+        assert!(shutdown.is_draining());
+        assert_eq!(shutdown.triggered().await, started);
+    }
+
+    #[tokio::test]
+    async fn reconnect_turn_falls_in_the_first_half_of_the_window() {
+        let shutdown = Shutdown::new(Duration::from_millis(200));
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown.reconnect_turn().await;
+                Instant::now()
+            }
+        });
+        shutdown.trigger();
+        let started = shutdown.triggered().await;
+        let turn = waiter.await.unwrap();
+        assert!(turn.duration_since(started) < Duration::from_millis(150));
+    }
+}
@@ -0,0 +1,242 @@
+//! Signed, expiring attachment URLs that an edge worker can verify without
+//! touching the database, so attachment traffic can be served off the API
+//! nodes.
+//!
+//! A URL is
+//! `{base}/api/v1/attachments/signed/{storage_key}?ct=&cd=&exp=&kid=&sig=`
+//! where `sig` is `base64url(HMAC-SHA256(secret, canonical))` and the
+//! canonical string is `{storage_key}\n{ct}\n{cd}\n{exp}`. The content type
+//! and disposition are signed so a download link can't be replayed to render
+//! inline. A verifier looks the secret up by `kid`, recomputes the HMAC,
+//! checks `exp` against its clock and serves the blob with `ct` and `cd` as
+//! headers.
+//!
+//! Keys come from `ATTACHMENT_URL_SIGNING_KEYS` as comma-separated
+//! `kid:secret` pairs. The first key signs and every listed key verifies, so
+//! rotating is: prepend a new key and reload, then drop the old one once the
+//! URL TTL has passed. The setting is hot-reloadable.
+//!
+//! Nothing is checked against the database once a URL is issued: it stays
+//! valid until `exp` even if the message is deleted or the attachment is
+//! quarantined, which is why the TTL is short.
+
+use base64::Engine;
+use chrono::{DateTime, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::config::AppConfig;
+use crate::errors::{AppError, AppResult};
+use crate::models::{SignedAttachmentQuery, SignedAttachmentUrl};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MIN_SECRET_LEN: usize = 32;
+
+/// The configured signing keys, in order; the first one signs.
+#[derive(Debug, Default)]
+pub struct SigningKeys(Vec<(String, Vec<u8>)>);
+
+impl SigningKeys {
+    /// Parse `kid:secret,kid:secret`. An empty string means signed URLs are off.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut keys: Vec<(String, Vec<u8>)> = Vec::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((kid, secret)) = entry.split_once(':') else {
+                return Err(format!("'{}' is not kid:secret", entry));
+            };
+            let kid = kid.trim();
+            if kid.is_empty() || !kid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(format!("key id '{}' must be letters, digits, '-' or '_'", kid));
+            }
+            if secret.len() < MIN_SECRET_LEN {
+                return Err(format!("secret of key '{}' is shorter than {} characters", kid, MIN_SECRET_LEN));
+            }
+            if keys.iter().any(|(existing, _)| existing == kid) {
+                return Err(format!("key id '{}' is listed twice", kid));
+            }
+            keys.push((kid.to_string(), secret.as_bytes().to_vec()));
+        }
+        Ok(Self(keys))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn signing_key(&self) -> Option<&(String, Vec<u8>)> {
+        self.0.first()
+    }
+
+    fn find(&self, kid: &str) -> Option<&[u8]> {
+        self.0.iter().find(|(k, _)| k == kid).map(|(_, secret)| secret.as_slice())
+    }
+}
+
+fn keys(config: &AppConfig) -> AppResult<SigningKeys> {
+    SigningKeys::parse(&config.attachment_url_signing_keys)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid ATTACHMENT_URL_SIGNING_KEYS: {}", e)))
+}
+
+/// Issue a URL serving the blob at `storage_key` with the given headers.
+pub fn issue(
+    config: &AppConfig,
+    storage_key: &str,
+    content_type: &str,
+    disposition: &str,
+) -> AppResult<SignedAttachmentUrl> {
+    let keys = keys(config)?;
+    let Some((kid, secret)) = keys.signing_key() else {
+        return Err(AppError::BadRequest("Signed attachment URLs are not enabled".into())
+            .with_code("SIGNED_URLS_DISABLED"));
+    };
+    let expires_at = Utc::now() + chrono::Duration::seconds(config.attachment_url_ttl_secs as i64);
+    let exp = expires_at.timestamp();
+    let sig = sign(secret, storage_key, content_type, disposition, exp);
+    let url = format!(
+        "{}/api/v1/attachments/signed/{}?ct={}&cd={}&exp={}&kid={}&sig={}",
+        config.attachment_url_base.trim_end_matches('/'),
+        storage_key,
+        urlencoding::encode(content_type),
+        urlencoding::encode(disposition),
+        exp,
+        kid,
+        sig
+    );
+    Ok(SignedAttachmentUrl {
+        url,
+        expires_at: Utc.timestamp_opt(exp, 0).single().unwrap_or(expires_at),
+    })
+}
+
+/// Check a signed URL for `storage_key` against the configured keys.
+pub fn verify(config: &AppConfig, storage_key: &str, query: &SignedAttachmentQuery) -> AppResult<()> {
+    verify_with(&keys(config)?, storage_key, query, Utc::now())
+}
+
+fn verify_with(
+    keys: &SigningKeys,
+    storage_key: &str,
+    query: &SignedAttachmentQuery,
+    now: DateTime<Utc>,
+) -> AppResult<()> {
+    let invalid = || AppError::Forbidden("Invalid attachment URL signature".into()).with_code("SIGNED_URL_INVALID");
+    let secret = keys.find(&query.kid).ok_or_else(invalid)?;
+    let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(&query.sig)
+        .map_err(|_| invalid())?;
+    mac(secret, storage_key, &query.ct, &query.cd, query.exp)
+        .verify_slice(&sig)
+        .map_err(|_| invalid())?;
+    if query.exp < now.timestamp() {
+        return Err(AppError::Forbidden("Attachment URL has expired".into()).with_code("SIGNED_URL_EXPIRED"));
+    }
+    Ok(())
+}
+
+fn sign(secret: &[u8], storage_key: &str, content_type: &str, disposition: &str, exp: i64) -> String {
+    let mac = mac(secret, storage_key, content_type, disposition, exp);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+fn mac(secret: &[u8], storage_key: &str, content_type: &str, disposition: &str, exp: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key size");
+    mac.update(format!("{}\n{}\n{}\n{}", storage_key, content_type, disposition, exp).as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "old:0123456789abcdef0123456789abcdef";
+    const NEW: &str = "new:fedcba9876543210fedcba9876543210";
+
+    fn query_from(url: &str) -> (String, SignedAttachmentQuery) {
+        let (path, query) = url.split_once('?').unwrap();
+        let storage_key = path.strip_prefix("/api/v1/attachments/signed/").unwrap().to_string();
+        let param = |name: &str| {
+            let value = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+                .unwrap();
+            urlencoding::decode(value).unwrap().into_owned()
+        };
+        let query = SignedAttachmentQuery {
+            ct: param("ct"),
+            cd: param("cd"),
+            exp: param("exp").parse().unwrap(),
+            kid: param("kid"),
+            sig: param("sig"),
+        };
+        (storage_key, query)
+    }
+
+    fn config_with(keys: &str) -> AppConfig {
+        let mut config = AppConfig::test_default();
+        config.attachment_url_signing_keys = keys.into();
+        config
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert!(SigningKeys::parse("").unwrap().is_empty());
+        assert_eq!(SigningKeys::parse(&format!("{}, {}", NEW, OLD)).unwrap().0.len(), 2);
+        assert!(SigningKeys::parse("nokid").is_err());
+        assert!(SigningKeys::parse("k:short").is_err());
+        assert!(SigningKeys::parse(&format!("{},{}", OLD, OLD)).is_err());
+        assert!(SigningKeys::parse("bad kid:0123456789abcdef0123456789abcdef").is_err());
+    }
+
+    #[test]
+    fn disabled_without_keys() {
+        let err = issue(&config_with(""), "ab/cd", "image/png", "inline").unwrap_err();
+        assert_eq!(err.code(), "SIGNED_URLS_DISABLED");
+    }
+
+    #[test]
+    fn issued_urls_verify_until_expiry() {
+        let config = config_with(OLD);
+        let signed = issue(&config, "ab/cdef", "text/plain; charset=utf-8", "inline").unwrap();
+        let (storage_key, query) = query_from(&signed.url);
+        assert_eq!(storage_key, "ab/cdef");
+        assert_eq!(query.ct, "text/plain; charset=utf-8");
+        assert_eq!(query.kid, "old");
+        assert_eq!(query.exp, signed.expires_at.timestamp());
+
+        let keys = SigningKeys::parse(OLD).unwrap();
+        assert!(verify_with(&keys, &storage_key, &query, Utc::now()).is_ok());
+        let later = signed.expires_at + chrono::Duration::seconds(1);
+        let err = verify_with(&keys, &storage_key, &query, later).unwrap_err();
+        assert_eq!(err.code(), "SIGNED_URL_EXPIRED");
+    }
+
+    #[test]
+    fn tampering_breaks_the_signature() {
+        let signed = issue(&config_with(OLD), "ab/cdef", "application/pdf", "attachment").unwrap();
+        let keys = SigningKeys::parse(OLD).unwrap();
+        let (storage_key, query) = query_from(&signed.url);
+
+        let inline = SignedAttachmentQuery { cd: "inline".into(), ..query.clone() };
+        assert_eq!(verify_with(&keys, &storage_key, &inline, Utc::now()).unwrap_err().code(), "SIGNED_URL_INVALID");
+        let extended = SignedAttachmentQuery { exp: query.exp + 3600, ..query.clone() };
+        assert!(verify_with(&keys, &storage_key, &extended, Utc::now()).is_err());
+        assert!(verify_with(&keys, "ab/other", &query, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn rotation_keeps_old_urls_valid_until_the_key_is_dropped() {
+        let signed = issue(&config_with(OLD), "ab/cdef", "image/png", "inline").unwrap();
+        let (storage_key, query) = query_from(&signed.url);
+
+        // New key prepended: it signs, the old one still verifies
+        let rotated = config_with(&format!("{},{}", NEW, OLD));
+        assert!(verify(&rotated, &storage_key, &query).is_ok());
+        let fresh = issue(&rotated, "ab/cdef", "image/png", "inline").unwrap();
+        assert_eq!(query_from(&fresh.url).1.kid, "new");
+
+        // Old key dropped
+        let err = verify(&config_with(NEW), &storage_key, &query).unwrap_err();
+        assert_eq!(err.code(), "SIGNED_URL_INVALID");
+    }
+}
@@ -1,10 +1,11 @@
 use std::io;
 use std::path::PathBuf;
 
-use aes_gcm::aead::{Aead, OsRng};
+use aes_gcm::aead::{Aead, OsRng, Payload};
 use aes_gcm::{AeadCore, Aes256Gcm, Key, KeyInit, Nonce};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
 
 use crate::config::AppConfig;
 
@@ -24,7 +25,7 @@ pub fn obfuscated_key(server_key: &[u8; 32], attachment_id: &str) -> String {
 
 // ─── Encryption helpers ──────────────────────────────────
 
-fn encrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+pub(crate) fn encrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
     let key = Key::<Aes256Gcm>::from_slice(server_key);
     let cipher = Aes256Gcm::new(key);
     let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
@@ -39,7 +40,7 @@ fn encrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
     Ok(output)
 }
 
-fn decrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+pub(crate) fn decrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
     if data.len() < 12 {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -57,8 +58,245 @@ fn decrypt_blob(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
         .map_err(|e| io::Error::other(format!("Decryption failed: {}", e)))
 }
 
+// ─── Segmented blobs ─────────────────────────────────────
+//
+// Blobs assembled from upload parts are written one segment at a time so the
+// whole file never sits in memory. An encrypted segmented blob starts with
+// `SEGMENTED_MAGIC`, then each segment as a big-endian u32 length followed by
+// `nonce || ciphertext`. A segment's AAD is its index and whether it is the
+// last, so segments can't be reordered, dropped or truncated unnoticed. A
+// single-shot blob starts with its random nonce, which matches the magic with
+// probability 2^-64. Raw (CDN) segmented blobs are the plain concatenation.
+
+const SEGMENTED_MAGIC: &[u8; 8] = b"HVNSEG01";
+
+/// S3 rejects multipart parts under 5 MiB, except the last.
+const S3_MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+fn segment_aad(index: u64, last: bool) -> [u8; 9] {
+    let mut aad = [0u8; 9];
+    aad[..8].copy_from_slice(&index.to_be_bytes());
+    aad[8] = last as u8;
+    aad
+}
+
+fn encrypt_segment(data: &[u8], index: u64, last: bool, server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(server_key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let aad = segment_aad(index, last);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: data, aad: &aad })
+        .map_err(|e| io::Error::other(format!("Encryption failed: {}", e)))?;
+
+    let mut output = Vec::with_capacity(4 + 12 + ciphertext.len());
+    output.extend_from_slice(&((12 + ciphertext.len()) as u32).to_be_bytes());
+    output.extend_from_slice(nonce.as_slice());
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+fn decrypt_segments(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(server_key));
+    let mut output = Vec::new();
+    let mut rest = &data[SEGMENTED_MAGIC.len()..];
+    let mut index = 0u64;
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err(invalid("Truncated segment header"));
+        }
+        let (len, tail) = rest.split_at(4);
+        let len = u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize;
+        if len < 12 || tail.len() < len {
+            return Err(invalid("Truncated segment"));
+        }
+        let (segment, tail) = tail.split_at(len);
+        let (nonce, ciphertext) = segment.split_at(12);
+        let aad = segment_aad(index, tail.is_empty());
+        let plain = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|e| io::Error::other(format!("Decryption failed: {}", e)))?;
+        output.extend_from_slice(&plain);
+        rest = tail;
+        index += 1;
+    }
+    if index == 0 {
+        return Err(invalid("Segmented blob has no segments"));
+    }
+    Ok(output)
+}
+
+/// Decrypt a stored blob in either format.
+fn decrypt_stored(data: &[u8], server_key: &[u8; 32]) -> io::Result<Vec<u8>> {
+    if data.starts_with(SEGMENTED_MAGIC) {
+        decrypt_segments(data, server_key)
+    } else {
+        decrypt_blob(data, server_key)
+    }
+}
+
+enum BlobSink {
+    /// Written to `partial` and renamed to `path` once complete.
+    Local {
+        file: tokio::fs::File,
+        partial: PathBuf,
+        path: PathBuf,
+    },
+    S3 {
+        client: aws_sdk_s3::Client,
+        bucket: String,
+        upload_id: String,
+        buffer: Vec<u8>,
+        parts: Vec<aws_sdk_s3::types::CompletedPart>,
+    },
+}
+
+/// Writes a blob a segment at a time; see [`Storage::blob_writer`]. Call
+/// [`BlobWriter::finish`] to publish it, or [`BlobWriter::abort`] to discard it.
+pub struct BlobWriter {
+    storage_key: String,
+    /// None for a raw (unencrypted) blob.
+    encryption_key: Option<[u8; 32]>,
+    index: u64,
+    /// The latest segment, held back until we know whether it is the last.
+    pending: Option<Vec<u8>>,
+    sink: BlobSink,
+}
+
+impl BlobWriter {
+    /// Append the next segment.
+    pub async fn write(&mut self, data: Vec<u8>) -> io::Result<()> {
+        if let Some(previous) = self.pending.replace(data) {
+            self.emit(previous, false).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the last segment and make the blob visible under its key.
+    pub async fn finish(mut self) -> io::Result<()> {
+        let last = self.pending.take().unwrap_or_default();
+        self.emit(last, true).await?;
+        match self.sink {
+            BlobSink::Local { mut file, partial, path } => {
+                file.flush().await?;
+                file.sync_all().await?;
+                drop(file);
+                tokio::fs::rename(&partial, &path).await
+            }
+            BlobSink::S3 { client, bucket, upload_id, buffer, mut parts } => {
+                if !buffer.is_empty() || parts.is_empty() {
+                    parts.push(upload_s3_part(&client, &bucket, &self.storage_key, &upload_id, parts.len(), buffer).await?);
+                }
+                client
+                    .complete_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&self.storage_key)
+                    .upload_id(&upload_id)
+                    .multipart_upload(
+                        aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        io::Error::other(format!("S3 complete multipart upload failed: {}", e))
+                    })?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Discard everything written so far (best-effort).
+    pub async fn abort(self) {
+        let result = match self.sink {
+            BlobSink::Local { file, partial, .. } => {
+                drop(file);
+                tokio::fs::remove_file(&partial).await
+            }
+            BlobSink::S3 { client, bucket, upload_id, .. } => client
+                .abort_multipart_upload()
+                .bucket(&bucket)
+                .key(&self.storage_key)
+                .upload_id(&upload_id)
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| io::Error::other(format!("S3 abort multipart upload failed: {}", e))),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to discard partial blob {}: {}", self.storage_key, e);
+        }
+    }
+
+    async fn emit(&mut self, data: Vec<u8>, last: bool) -> io::Result<()> {
+        let bytes = match &self.encryption_key {
+            Some(key) => encrypt_segment(&data, self.index, last, key)?,
+            None => data,
+        };
+        self.index += 1;
+        match &mut self.sink {
+            BlobSink::Local { file, .. } => file.write_all(&bytes).await,
+            BlobSink::S3 { client, bucket, upload_id, buffer, parts } => {
+                buffer.extend_from_slice(&bytes);
+                if buffer.len() >= S3_MIN_PART_SIZE {
+                    let body = std::mem::take(buffer);
+                    parts.push(upload_s3_part(client, bucket, &self.storage_key, upload_id, parts.len(), body).await?);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn upload_s3_part(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    storage_key: &str,
+    upload_id: &str,
+    index: usize,
+    body: Vec<u8>,
+) -> io::Result<aws_sdk_s3::types::CompletedPart> {
+    let part_number = index as i32 + 1;
+    let output = client
+        .upload_part()
+        .bucket(bucket)
+        .key(storage_key)
+        .upload_id(upload_id)
+        .part_number(part_number)
+        .body(aws_sdk_s3::primitives::ByteStream::from(body))
+        .send()
+        .await
+        .map_err(|e| io::Error::other(format!("S3 upload part failed: {}", e)))?;
+    Ok(aws_sdk_s3::types::CompletedPart::builder()
+        .set_e_tag(output.e_tag().map(str::to_string))
+        .part_number(part_number)
+        .build())
+}
+
 // ─── Storage Backend ──────────────────────────────────────
 
+fn s3_client(config: &AppConfig) -> aws_sdk_s3::Client {
+    let creds = aws_credential_types::Credentials::new(
+        &config.s3_access_key,
+        &config.s3_secret_key,
+        None,
+        None,
+        "haven-env",
+    );
+
+    let mut s3_config_builder = aws_sdk_s3::config::Builder::new()
+        .region(aws_sdk_s3::config::Region::new(config.s3_region.clone()))
+        .credentials_provider(creds)
+        .force_path_style(true); // Required for MinIO / custom endpoints
+
+    if !config.s3_endpoint.is_empty() {
+        s3_config_builder = s3_config_builder.endpoint_url(&config.s3_endpoint);
+    }
+
+    aws_sdk_s3::Client::from_conf(s3_config_builder.build())
+}
+
 /// Abstraction over local filesystem and S3 storage.
 /// Both backends apply the same AES-256-GCM server-side encryption.
 #[derive(Clone)]
@@ -84,26 +322,7 @@ impl Storage {
             .expect("STORAGE_ENCRYPTION_KEY must be exactly 32 bytes (64 hex chars)");
 
         if config.storage_backend == "s3" {
-            let creds = aws_credential_types::Credentials::new(
-                &config.s3_access_key,
-                &config.s3_secret_key,
-                None,
-                None,
-                "haven-env",
-            );
-
-            let mut s3_config_builder = aws_sdk_s3::config::Builder::new()
-                .region(aws_sdk_s3::config::Region::new(config.s3_region.clone()))
-                .credentials_provider(creds)
-                .force_path_style(true); // Required for MinIO / custom endpoints
-
-            if !config.s3_endpoint.is_empty() {
-                s3_config_builder = s3_config_builder
-                    .endpoint_url(&config.s3_endpoint);
-            }
-
-            let client = aws_sdk_s3::Client::from_conf(s3_config_builder.build());
-
+            let client = s3_client(config);
             tracing::info!("S3 storage initialized (bucket: {})", config.s3_bucket);
             Storage::S3 {
                 client,
@@ -121,6 +340,30 @@ impl Storage {
         }
     }
 
+    /// The backup backend for retention archives (`BACKUP_STORAGE_DIR`, or
+    /// `BACKUP_S3_BUCKET` with the S3 credentials). None when neither is set.
+    /// Blobs are encrypted with the same storage key.
+    pub fn backup_from_config(config: &AppConfig, encryption_key: [u8; 32]) -> Option<Self> {
+        if !config.backup_storage_dir.is_empty() {
+            std::fs::create_dir_all(&config.backup_storage_dir)
+                .expect("Failed to create backup storage directory");
+            tracing::info!("Backup storage at {}", config.backup_storage_dir);
+            Some(Storage::Local {
+                dir: PathBuf::from(&config.backup_storage_dir),
+                encryption_key,
+            })
+        } else if !config.backup_s3_bucket.is_empty() {
+            tracing::info!("Backup storage in S3 bucket {}", config.backup_s3_bucket);
+            Some(Storage::S3 {
+                client: s3_client(config),
+                bucket: config.backup_s3_bucket.clone(),
+                encryption_key,
+            })
+        } else {
+            None
+        }
+    }
+
     /// Returns the raw encryption key (needed for obfuscated_key derivation).
     pub fn encryption_key(&self) -> &[u8; 32] {
         match self {
@@ -260,6 +503,31 @@ impl Storage {
         }
     }
 
+    /// Check the backend is reachable: the local directory exists, or the S3
+    /// bucket answers a HEAD request with our credentials.
+    pub async fn check_health(&self) -> io::Result<()> {
+        match self {
+            Storage::Local { dir, .. } => {
+                let meta = tokio::fs::metadata(dir).await?;
+                if !meta.is_dir() {
+                    return Err(io::Error::other("storage path is not a directory"));
+                }
+                Ok(())
+            }
+            Storage::S3 { client, bucket, .. } => {
+                client
+                    .head_bucket()
+                    .bucket(bucket)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        io::Error::other(format!("S3 head bucket failed: {}", e))
+                    })?;
+                Ok(())
+            }
+        }
+    }
+
     /// Delete a stored blob (file or S3 object).
     pub async fn delete_blob(&self, storage_key: &str) -> io::Result<()> {
         match self {
@@ -286,6 +554,55 @@ impl Storage {
         }
     }
 
+    /// Start writing a blob a segment at a time, encrypted unless `raw`.
+    /// [`Storage::load_blob`] and [`Storage::load_blob_raw`] read it like any
+    /// other blob.
+    pub async fn blob_writer(&self, storage_key: &str, raw: bool) -> io::Result<BlobWriter> {
+        let encryption_key = (!raw).then(|| *self.encryption_key());
+        let header: &[u8] = if raw { &[] } else { SEGMENTED_MAGIC };
+        let sink = match self {
+            Storage::Local { dir, .. } => {
+                let path = dir.join(storage_key);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                let partial = PathBuf::from(format!("{}.partial", path.display()));
+                let mut file = tokio::fs::File::create(&partial).await?;
+                file.write_all(header).await?;
+                BlobSink::Local { file, partial, path }
+            }
+            Storage::S3 { client, bucket, .. } => {
+                let output = client
+                    .create_multipart_upload()
+                    .bucket(bucket)
+                    .key(storage_key)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        io::Error::other(format!("S3 create multipart upload failed: {}", e))
+                    })?;
+                let upload_id = output
+                    .upload_id()
+                    .ok_or_else(|| io::Error::other("S3 create multipart upload returned no upload id"))?
+                    .to_string();
+                BlobSink::S3 {
+                    client: client.clone(),
+                    bucket: bucket.clone(),
+                    upload_id,
+                    buffer: header.to_vec(),
+                    parts: Vec::new(),
+                }
+            }
+        };
+        Ok(BlobWriter {
+            storage_key: storage_key.to_string(),
+            encryption_key,
+            index: 0,
+            pending: None,
+            sink,
+        })
+    }
+
     /// Load and decrypt data.
     pub async fn load_blob(&self, storage_key: &str) -> io::Result<Vec<u8>> {
         let encrypted = match self {
@@ -316,7 +633,7 @@ impl Storage {
             }
         };
 
-        decrypt_blob(&encrypted, self.encryption_key())
+        decrypt_stored(&encrypted, self.encryption_key())
     }
 }
 
@@ -474,4 +791,69 @@ mod tests {
         let result = storage.presign_url("key", 3600, "").await;
         assert!(result.is_none());
     }
+
+    // ─── Storage::blob_writer (Local backend) ───────────
+
+    #[tokio::test]
+    async fn storage_local_segmented_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::Local {
+            dir: dir.path().to_path_buf(),
+            encryption_key: [3u8; 32],
+        };
+
+        let mut writer = storage.blob_writer("seg/test.enc", false).await.unwrap();
+        writer.write(b"first ".to_vec()).await.unwrap();
+        writer.write(b"second ".to_vec()).await.unwrap();
+        writer.write(b"third".to_vec()).await.unwrap();
+        writer.finish().await.unwrap();
+
+        let loaded = storage.load_blob("seg/test.enc").await.unwrap();
+        assert_eq!(loaded, b"first second third");
+        assert!(!dir.path().join("seg/test.enc.partial").exists());
+    }
+
+    #[tokio::test]
+    async fn storage_local_segmented_raw_is_plain_concatenation() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::Local {
+            dir: dir.path().to_path_buf(),
+            encryption_key: [0u8; 32],
+        };
+
+        let mut writer = storage.blob_writer("seg/raw.bin", true).await.unwrap();
+        writer.write(b"ab".to_vec()).await.unwrap();
+        writer.write(b"cd".to_vec()).await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(storage.load_blob_raw("seg/raw.bin").await.unwrap(), b"abcd");
+    }
+
+    #[tokio::test]
+    async fn storage_local_aborted_segmented_blob_leaves_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::Local {
+            dir: dir.path().to_path_buf(),
+            encryption_key: [0u8; 32],
+        };
+
+        let mut writer = storage.blob_writer("seg/gone.enc", false).await.unwrap();
+        writer.write(b"data".to_vec()).await.unwrap();
+        writer.abort().await;
+
+        assert!(!dir.path().join("seg/gone.enc").exists());
+        assert!(!dir.path().join("seg/gone.enc.partial").exists());
+    }
+
+    #[test]
+    fn truncated_segmented_blob_fails_to_decrypt() {
+        let key = [5u8; 32];
+        let mut blob = SEGMENTED_MAGIC.to_vec();
+        blob.extend(encrypt_segment(b"one", 0, false, &key).unwrap());
+        let truncated = blob.clone();
+        blob.extend(encrypt_segment(b"two", 1, true, &key).unwrap());
+
+        assert_eq!(decrypt_stored(&blob, &key).unwrap(), b"onetwo");
+        assert!(decrypt_stored(&truncated, &key).is_err());
+    }
 }
@@ -0,0 +1,145 @@
+//! Server-side image previews for unencrypted channels.
+//!
+//! With `thumbnails_enabled`, an image uploaded to a channel that is not
+//! end-to-end encrypted gets a small JPEG thumbnail and a blurhash placeholder
+//! when its upload is finalized. Encrypted channels never get previews: the
+//! server only ever sees ciphertext there.
+
+use std::io::Cursor;
+
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits};
+use uuid::Uuid;
+
+use crate::storage;
+
+/// Longest edge of a generated thumbnail, in pixels.
+pub const THUMBNAIL_MAX_DIMENSION: u32 = 320;
+
+/// Larger sources are not decoded.
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+/// Uploads larger than this get no preview, so finalizing never holds a
+/// bigger file in memory.
+pub const MAX_SOURCE_BYTES: u64 = 32 * 1024 * 1024;
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Blurhash component counts (x, y); 4x3 is the reference default.
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Edge length the image is shrunk to before blurhash encoding.
+const BLURHASH_SAMPLE_DIMENSION: u32 = 32;
+
+const JPEG_QUALITY: u8 = 80;
+
+/// A generated preview.
+#[derive(Debug)]
+pub struct Preview {
+    /// JPEG thumbnail bytes.
+    pub thumbnail: Vec<u8>,
+    /// Dimensions of the original image.
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: String,
+}
+
+/// Storage key for an attachment's thumbnail.
+pub fn thumbnail_key(server_key: &[u8; 32], attachment_id: Uuid) -> String {
+    storage::obfuscated_key(server_key, &format!("thumbnail:{}", attachment_id))
+}
+
+/// Whether a declared content type is worth trying to decode.
+pub fn is_previewable(content_type: &str) -> bool {
+    matches!(
+        content_type.to_ascii_lowercase().as_str(),
+        "image/png" | "image/jpeg" | "image/jpg" | "image/gif" | "image/webp"
+    )
+}
+
+/// Decode an image and build its preview. Returns None for data that isn't a
+/// supported image or exceeds the decode limits. CPU-bound: call from
+/// `spawn_blocking`.
+pub fn generate(data: &[u8]) -> Option<Preview> {
+    let image = decode(data)?;
+    let (width, height) = (image.width(), image.height());
+
+    let thumb = if width > THUMBNAIL_MAX_DIMENSION || height > THUMBNAIL_MAX_DIMENSION {
+        image.resize(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION, FilterType::Triangle)
+    } else {
+        image
+    };
+
+    let sample = thumb
+        .resize(BLURHASH_SAMPLE_DIMENSION, BLURHASH_SAMPLE_DIMENSION, FilterType::Triangle)
+        .to_rgba8();
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS.0,
+        BLURHASH_COMPONENTS.1,
+        sample.width(),
+        sample.height(),
+        sample.as_raw(),
+    )
+    .ok()?;
+
+    // JPEG has no alpha channel
+    let mut thumbnail = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut thumbnail, JPEG_QUALITY);
+    DynamicImage::ImageRgb8(thumb.to_rgb8()).write_with_encoder(encoder).ok()?;
+
+    Some(Preview { thumbnail, width, height, blurhash })
+}
+
+/// Decode an image of any supported format within the decode limits.
+pub fn decode(data: &[u8]) -> Option<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format().ok()?;
+    reader.limits(limits);
+    reader.decode().ok()
+}
+
+/// Content type of generated thumbnails.
+pub fn thumbnail_content_type() -> &'static str {
+    ImageFormat::Jpeg.to_mime_type()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbImage::from_fn(width, height, |x, y| Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut out = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn large_images_are_downscaled() {
+        let preview = generate(&png(1280, 640)).unwrap();
+        assert_eq!((preview.width, preview.height), (1280, 640));
+        assert!(!preview.blurhash.is_empty());
+
+        let thumb = image::load_from_memory(&preview.thumbnail).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (320, 160));
+    }
+
+    #[test]
+    fn small_images_keep_their_size() {
+        let preview = generate(&png(40, 30)).unwrap();
+        let thumb = image::load_from_memory(&preview.thumbnail).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (40, 30));
+    }
+
+    #[test]
+    fn non_images_have_no_preview() {
+        assert!(generate(b"definitely not an image").is_none());
+        assert!(is_previewable("IMAGE/PNG"));
+        assert!(!is_previewable("image/svg+xml"));
+        assert!(!is_previewable("application/pdf"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Resumable attachment uploads and per-server upload tiers.
+//!
+//! Clients open an upload session for a channel, PUT the (client-encrypted)
+//! file in chunks at the offset the server reports, and finalize the session
+//! against a message once every byte has arrived. Each chunk is stored as its
+//! own part blob; finalization concatenates the parts into the attachment blob.
+//!
+//! Attachment blobs are content-addressed by the BLAKE3 hash of the bytes as
+//! uploaded, and reference-counted in `attachment_blobs`: identical uploads
+//! (a forwarded file, the same ciphertext posted again) share one stored blob.
+//! Files are encrypted client-side, so only uploads of the same ciphertext
+//! match.
+
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::models::{EffectiveUploadLimits, UploadLimit};
+use crate::storage;
+
+/// Largest chunk accepted by a single PUT.
+pub const MAX_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// How long an upload session stays resumable after it is created.
+pub const SESSION_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// How long a simple upload may wait to be sent before the attachment GC
+/// releases its blob.
+pub const PENDING_UPLOAD_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Content types accepted on the standard tier. Matched as exact types, or as
+/// prefixes when they end in `/`.
+const STANDARD_CONTENT_TYPES: &[&str] = &[
+    "image/",
+    "video/",
+    "audio/",
+    "text/plain",
+    "application/pdf",
+    "application/zip",
+];
+
+/// Multiplier applied to `max_upload_size_bytes` for boosted servers.
+const BOOSTED_SIZE_MULTIPLIER: u64 = 4;
+
+/// A server's upload tier. DMs and group DMs always use the standard tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadTier {
+    Standard,
+    Boosted,
+}
+
+impl UploadTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadTier::Standard => "standard",
+            UploadTier::Boosted => "boosted",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "standard" => Some(UploadTier::Standard),
+            "boosted" => Some(UploadTier::Boosted),
+            _ => None,
+        }
+    }
+
+    /// Largest file this tier accepts, given the instance-wide base limit.
+    pub fn max_size(&self, base_limit: u64) -> u64 {
+        match self {
+            UploadTier::Standard => base_limit,
+            UploadTier::Boosted => base_limit.saturating_mul(BOOSTED_SIZE_MULTIPLIER),
+        }
+    }
+
+    /// Whether this tier accepts a file of the given (declared) content type.
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        match self {
+            UploadTier::Boosted => true,
+            UploadTier::Standard => {
+                let content_type = content_type.to_ascii_lowercase();
+                STANDARD_CONTENT_TYPES.iter().any(|allowed| {
+                    if allowed.ends_with('/') {
+                        content_type.starts_with(allowed)
+                    } else {
+                        content_type == *allowed
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// The upload limits for a member. Each limit is the most generous one set
+/// on their roles, replaced by the channel's when the channel sets it; file
+/// size never exceeds the tier's `tier_max`.
+pub fn effective_limits(
+    tier_max: u64,
+    role_limits: &[UploadLimit],
+    channel_limit: Option<&UploadLimit>,
+) -> EffectiveUploadLimits {
+    let mut limits = EffectiveUploadLimits {
+        max_size_bytes: tier_max,
+        max_size_source: "tier".into(),
+        max_count: None,
+        max_count_source: "tier".into(),
+    };
+    if let Some(size) = role_limits.iter().filter_map(|l| l.max_size_bytes).max() {
+        limits.max_size_bytes = size.max(0) as u64;
+        limits.max_size_source = "role".into();
+    }
+    if let Some(count) = role_limits.iter().filter_map(|l| l.max_count).max() {
+        limits.max_count = Some(count.max(0) as u32);
+        limits.max_count_source = "role".into();
+    }
+    if let Some(channel) = channel_limit {
+        if let Some(size) = channel.max_size_bytes {
+            limits.max_size_bytes = size.max(0) as u64;
+            limits.max_size_source = "channel".into();
+        }
+        if let Some(count) = channel.max_count {
+            limits.max_count = Some(count.max(0) as u32);
+            limits.max_count_source = "channel".into();
+        }
+    }
+    if limits.max_size_bytes > tier_max {
+        limits.max_size_bytes = tier_max;
+        limits.max_size_source = "tier".into();
+    }
+    limits
+}
+
+/// Hex BLAKE3 hash of an attachment's bytes, its content address.
+pub fn content_hash(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+/// Storage key of the shared blob with the given content hash.
+pub fn blob_key(server_key: &[u8; 32], content_hash: &str) -> String {
+    storage::obfuscated_key(server_key, &format!("attachment-blob:{}", content_hash))
+}
+
+/// Storage key for one uploaded chunk.
+pub fn part_key(server_key: &[u8; 32], part_id: Uuid) -> String {
+    storage::obfuscated_key(server_key, &format!("upload-part:{}", part_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tier_round_trips() {
+        for tier in [UploadTier::Standard, UploadTier::Boosted] {
+            assert_eq!(UploadTier::parse(tier.as_str()), Some(tier));
+        }
+        assert_eq!(UploadTier::parse("gold"), None);
+    }
+
+    #[test]
+    fn boosted_tier_raises_size_limit() {
+        assert_eq!(UploadTier::Standard.max_size(100), 100);
+        assert_eq!(UploadTier::Boosted.max_size(100), 400);
+        assert_eq!(UploadTier::Boosted.max_size(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn standard_tier_limits_content_types() {
+        let tier = UploadTier::Standard;
+        assert!(tier.allows_content_type("image/png"));
+        assert!(tier.allows_content_type("Video/MP4"));
+        assert!(tier.allows_content_type("application/pdf"));
+        assert!(!tier.allows_content_type("application/x-msdownload"));
+        assert!(!tier.allows_content_type("text/html"));
+        assert!(UploadTier::Boosted.allows_content_type("application/x-msdownload"));
+    }
+
+    fn limit(max_size_bytes: Option<i64>, max_count: Option<i32>) -> UploadLimit {
+        UploadLimit {
+            id: Uuid::new_v4(),
+            server_id: Uuid::nil(),
+            role_id: None,
+            channel_id: None,
+            max_size_bytes,
+            max_count,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn roles_give_their_most_generous_limit_and_channels_override() {
+        let limits = effective_limits(100, &[], None);
+        assert_eq!((limits.max_size_bytes, limits.max_count), (100, None));
+        assert_eq!(limits.max_size_source, "tier");
+
+        let roles = [limit(Some(10), Some(2)), limit(Some(40), None), limit(None, Some(5))];
+        let limits = effective_limits(100, &roles, None);
+        assert_eq!((limits.max_size_bytes, limits.max_count), (40, Some(5)));
+        assert_eq!(limits.max_count_source, "role");
+
+        let memes = limit(Some(90), None);
+        let limits = effective_limits(100, &roles, Some(&memes));
+        assert_eq!((limits.max_size_bytes, limits.max_count), (90, Some(5)));
+        assert_eq!(limits.max_size_source, "channel");
+
+        let announcements = limit(None, Some(0));
+        assert_eq!(effective_limits(100, &roles, Some(&announcements)).max_count, Some(0));
+    }
+
+    #[test]
+    fn tier_caps_file_size() {
+        let limits = effective_limits(100, &[limit(Some(500), None)], Some(&limit(Some(1000), None)));
+        assert_eq!(limits.max_size_bytes, 100);
+        assert_eq!(limits.max_size_source, "tier");
+    }
+
+    #[test]
+    fn identical_content_shares_a_blob_key() {
+        let key = [7u8; 32];
+        let hash = content_hash(b"meme");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(b"meme"));
+        assert_ne!(hash, content_hash(b"other meme"));
+        assert_eq!(blob_key(&key, &hash), blob_key(&key, &content_hash(b"meme")));
+        assert_ne!(blob_key(&key, &hash), blob_key(&[8u8; 32], &hash));
+    }
+
+    #[test]
+    fn part_keys_are_distinct() {
+        let key = [7u8; 32];
+        assert_ne!(part_key(&key, Uuid::new_v4()), part_key(&key, Uuid::new_v4()));
+    }
+}
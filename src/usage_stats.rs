@@ -0,0 +1,81 @@
+//! Instance usage statistics and the opt-in telemetry reporter.
+//!
+//! [`collect`] gathers instance-wide counts (users, daily and monthly active
+//! users, messages, storage) for `GET /admin/usage`. An operator running
+//! several instances can set `TELEMETRY_ENDPOINT` to have the `telemetry`
+//! job POST the same JSON there daily, with `TELEMETRY_TOKEN` as a bearer
+//! token. Nothing is sent unless the endpoint is configured, and the report
+//! holds counts alone. Users count as active when they open a WebSocket
+//! session, which stamps them at most once an hour.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::InstanceUsageStats;
+use crate::AppState;
+
+fn http() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("HTTP client builds")
+    })
+}
+
+/// Current usage of the instance.
+pub async fn collect(state: &AppState) -> AppResult<InstanceUsageStats> {
+    let pool = state.db.read();
+    let (total_users, (daily_active_users, monthly_active_users), total_servers, total_messages, recent, storage_bytes) =
+        tokio::try_join!(
+            queries::count_all_users(pool),
+            queries::count_active_users(pool),
+            queries::count_all_servers(pool),
+            queries::count_all_messages(pool),
+            queries::count_recent_messages(pool),
+            queries::total_storage_bytes(pool),
+        )?;
+    let instance_id = state.live_config.get().telemetry_instance_id.clone();
+
+    Ok(InstanceUsageStats {
+        instance_id: (!instance_id.is_empty()).then_some(instance_id),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        total_users,
+        daily_active_users,
+        monthly_active_users,
+        total_servers,
+        total_messages,
+        messages_last_day: recent.0,
+        messages_last_30_days: recent.1,
+        storage_bytes,
+        active_connections: state.connections.len(),
+        collected_at: Utc::now(),
+    })
+}
+
+/// Send the current usage to `TELEMETRY_ENDPOINT` (the `telemetry` job).
+/// Returns 1 once reported, 0 when no endpoint is configured.
+pub async fn report(state: &AppState) -> AppResult<u64> {
+    let config = state.live_config.get();
+    if config.telemetry_endpoint.is_empty() {
+        return Ok(0);
+    }
+    let stats = collect(state).await?;
+
+    let mut request = http().post(&config.telemetry_endpoint).json(&stats);
+    if !config.telemetry_token.is_empty() {
+        request = request.bearer_auth(&config.telemetry_token);
+    }
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| AppError::BadRequest(format!("Could not send usage stats: {}", e)))?;
+    Ok(1)
+}
@@ -0,0 +1,4 @@
+//! Voice and call infrastructure shared by the voice and call REST handlers.
+
+pub mod sfu;
+pub mod turn;
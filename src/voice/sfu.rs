@@ -0,0 +1,248 @@
+//! SFU (LiveKit) room lifecycle and join-token minting.
+//!
+//! Haven never relays media itself. When a voice channel becomes active we
+//! provision a room on the SFU, hand each participant a short-lived join token
+//! whose grants mirror their Haven permissions, and delete the room again once
+//! the last participant leaves.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use livekit_api::access_token::{AccessToken, VideoGrants};
+use livekit_api::services::room::{CreateRoomOptions, RoomClient};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::db::queries;
+use crate::errors::{AppError, AppResult};
+use crate::models::Channel;
+use crate::permissions;
+use crate::AppState;
+
+/// How long a join token stays valid.
+const JOIN_TOKEN_TTL: Duration = Duration::from_secs(6 * 3600);
+
+/// Seconds the SFU keeps an empty room alive if our teardown never arrives
+/// (e.g. the Haven process crashed mid-call).
+const ROOM_EMPTY_TIMEOUT_SECS: u32 = 300;
+
+/// LiveKit track source names.
+const SOURCE_MICROPHONE: &str = "microphone";
+const SOURCE_CAMERA: &str = "camera";
+const SOURCE_SCREEN_SHARE: &str = "screen_share";
+const SOURCE_SCREEN_SHARE_AUDIO: &str = "screen_share_audio";
+
+/// SFU room name for a Haven channel.
+pub fn room_name(channel_id: Uuid) -> String {
+    channel_id.to_string()
+}
+
+/// Build the SFU grants for a participant from their effective channel permissions
+/// and current server mute/deafen state.
+pub fn grants_for(channel_id: Uuid, perms: i64, server_muted: bool, server_deafened: bool) -> VideoGrants {
+    let mut sources = Vec::new();
+    if !server_muted {
+        sources.push(SOURCE_MICROPHONE.to_string());
+    }
+    if permissions::has_permission(perms, permissions::VIDEO) {
+        sources.push(SOURCE_CAMERA.to_string());
+    }
+    if permissions::has_permission(perms, permissions::STREAM) {
+        sources.push(SOURCE_SCREEN_SHARE.to_string());
+        sources.push(SOURCE_SCREEN_SHARE_AUDIO.to_string());
+    }
+
+    VideoGrants {
+        room_join: true,
+        room: room_name(channel_id),
+        can_publish: !sources.is_empty(),
+        can_publish_sources: sources,
+        can_subscribe: !server_deafened,
+        can_publish_data: true,
+        ..Default::default()
+    }
+}
+
+/// Mint a join token for `user_id` in the room backing `channel_id`.
+pub fn mint_join_token(
+    config: &AppConfig,
+    user_id: Uuid,
+    display_name: &str,
+    grants: VideoGrants,
+) -> AppResult<String> {
+    AccessToken::with_api_key(&config.livekit_api_key, &config.livekit_api_secret)
+        .with_identity(&user_id.to_string())
+        .with_name(display_name)
+        .with_ttl(JOIN_TOKEN_TTL)
+        .with_grants(grants)
+        .to_jwt()
+        .map_err(|e| AppError::BadRequest(format!("Failed to generate voice token: {}", e)))
+}
+
+/// Effective permissions a user has inside a voice-capable channel.
+/// DM and group calls are not permission-gated, so they get the defaults.
+pub async fn channel_voice_permissions(state: &AppState, channel: &Channel, user_id: Uuid) -> AppResult<i64> {
+    match channel.server_id {
+        Some(server_id) => {
+            let perms =
+                queries::get_member_channel_permissions(state.db.read(), server_id, channel.id, user_id).await?;
+            let channel_id = channel.id;
+            state.shadow_reads.compare("member_channel_permissions", &perms, || {
+                let pool = state.db.read().clone();
+                async move {
+                    queries::get_member_channel_permissions_joined(&pool, server_id, channel_id, user_id).await
+                }
+            });
+            Ok(perms)
+        }
+        None => Ok(permissions::DEFAULT_PERMISSIONS),
+    }
+}
+
+/// Create the SFU room for a channel that just became active.
+/// Failures are logged, not returned — LiveKit also auto-creates rooms on first join.
+pub async fn provision_room(state: &AppState, channel_id: Uuid) {
+    let Some(client) = room_client(&state.config) else { return };
+    let lock = room_lock(state, channel_id);
+    let _guard = lock.lock().await;
+    let options = CreateRoomOptions {
+        empty_timeout: ROOM_EMPTY_TIMEOUT_SECS,
+        ..Default::default()
+    };
+    match client.create_room(&room_name(channel_id), options).await {
+        Ok(_) => tracing::debug!("Provisioned SFU room for channel {}", channel_id),
+        Err(e) => tracing::warn!("Failed to provision SFU room for channel {}: {}", channel_id, e),
+    }
+}
+
+/// Delete the SFU room for a channel whose last participant left.
+/// Runs in the background so leave/disconnect paths never wait on the SFU. The
+/// channel is checked again under the room lock, so a join that landed in the
+/// meantime keeps its room.
+fn spawn_teardown_room(state: &AppState, channel_id: Uuid) {
+    let Some(client) = room_client(&state.config) else { return };
+    let state = state.clone();
+    tokio::spawn(async move {
+        let lock = room_lock(&state, channel_id);
+        let _guard = lock.lock().await;
+        let occupied = state.memory.voice_participants
+            .get(&channel_id)
+            .is_some_and(|set| !set.is_empty());
+        if occupied {
+            return;
+        }
+        match client.delete_room(&room_name(channel_id)).await {
+            Ok(()) => tracing::debug!("Tore down SFU room for channel {}", channel_id),
+            Err(e) => tracing::warn!("Failed to tear down SFU room for channel {}: {}", channel_id, e),
+        }
+        state.memory.voice_room_locks.remove_if(&channel_id, |_, l| Arc::strong_count(l) <= 2);
+    });
+}
+
+/// Remove a user from a channel's in-memory participant set.
+/// Returns true if the user was present. Tears down the SFU room if the channel is now empty.
+pub fn remove_participant(state: &AppState, channel_id: Uuid, user_id: Uuid) -> bool {
+    let (removed, now_empty) = match state.memory.voice_participants.get_mut(&channel_id) {
+        Some(mut set) => {
+            let removed = set.remove(&user_id);
+            (removed, set.is_empty())
+        }
+        None => (false, false),
+    };
+
+    if removed && now_empty {
+        state.memory.voice_participants.remove_if(&channel_id, |_, set| set.is_empty());
+        spawn_teardown_room(state, channel_id);
+    }
+    removed
+}
+
+fn room_lock(state: &AppState, channel_id: Uuid) -> Arc<Mutex<()>> {
+    state.memory.voice_room_locks.entry(channel_id).or_default().clone()
+}
+
+fn room_client(config: &AppConfig) -> Option<RoomClient> {
+    if !config.livekit_enabled() {
+        return None;
+    }
+    Some(RoomClient::with_api_key(
+        &api_host(&config.livekit_url),
+        &config.livekit_api_key,
+        &config.livekit_api_secret,
+    ))
+}
+
+/// The room service speaks HTTP; the configured LiveKit URL is usually a WebSocket URL.
+fn api_host(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_host_rewrites_websocket_schemes() {
+        assert_eq!(api_host("ws://127.0.0.1:7880"), "http://127.0.0.1:7880");
+        assert_eq!(api_host("wss://lk.example.com"), "https://lk.example.com");
+        assert_eq!(api_host("https://lk.example.com"), "https://lk.example.com");
+    }
+
+    #[test]
+    fn grants_default_permissions_allow_all_sources() {
+        let grants = grants_for(Uuid::new_v4(), permissions::DEFAULT_PERMISSIONS, false, false);
+        assert!(grants.room_join);
+        assert!(grants.can_publish);
+        assert!(grants.can_subscribe);
+        assert!(grants.can_publish_sources.contains(&SOURCE_MICROPHONE.to_string()));
+        assert!(grants.can_publish_sources.contains(&SOURCE_CAMERA.to_string()));
+        assert!(grants.can_publish_sources.contains(&SOURCE_SCREEN_SHARE.to_string()));
+    }
+
+    #[test]
+    fn grants_without_stream_or_video_are_audio_only() {
+        let perms = permissions::DEFAULT_PERMISSIONS & !permissions::STREAM & !permissions::VIDEO;
+        let grants = grants_for(Uuid::new_v4(), perms, false, false);
+        assert_eq!(grants.can_publish_sources, vec![SOURCE_MICROPHONE.to_string()]);
+    }
+
+    #[test]
+    fn grants_video_without_stream_allow_camera_only() {
+        let perms = permissions::DEFAULT_PERMISSIONS & !permissions::STREAM;
+        let grants = grants_for(Uuid::new_v4(), perms, false, false);
+        assert_eq!(
+            grants.can_publish_sources,
+            vec![SOURCE_MICROPHONE.to_string(), SOURCE_CAMERA.to_string()]
+        );
+    }
+
+    #[test]
+    fn grants_stream_without_video_allow_screen_share_only() {
+        let perms = permissions::DEFAULT_PERMISSIONS & !permissions::VIDEO;
+        let grants = grants_for(Uuid::new_v4(), perms, false, false);
+        assert!(!grants.can_publish_sources.contains(&SOURCE_CAMERA.to_string()));
+        assert!(grants.can_publish_sources.contains(&SOURCE_SCREEN_SHARE.to_string()));
+        assert!(grants.can_publish_sources.contains(&SOURCE_SCREEN_SHARE_AUDIO.to_string()));
+    }
+
+    #[test]
+    fn grants_server_muted_without_media_cannot_publish() {
+        let perms = permissions::DEFAULT_PERMISSIONS & !permissions::STREAM & !permissions::VIDEO;
+        let grants = grants_for(Uuid::new_v4(), perms, true, false);
+        assert!(!grants.can_publish);
+        assert!(grants.can_publish_sources.is_empty());
+    }
+
+    #[test]
+    fn grants_server_deafened_cannot_subscribe() {
+        let grants = grants_for(Uuid::new_v4(), permissions::DEFAULT_PERMISSIONS, false, true);
+        assert!(!grants.can_subscribe);
+    }
+}
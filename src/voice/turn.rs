@@ -0,0 +1,78 @@
+//! Short-lived TURN credentials for 1:1 DM calls.
+//!
+//! Uses the coturn REST API scheme (`use-auth-secret`): the username is
+//! `<expiry unix timestamp>:<user id>` and the password is
+//! `base64(HMAC-SHA1(turn_secret, username))`. The TURN server verifies the
+//! HMAC with the same shared secret and rejects the credential once the
+//! timestamp has passed, so nothing needs to be stored or revoked.
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::models::TurnCredentials;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Mint TURN credentials for `user_id`, or `None` if no TURN relay is configured.
+pub fn mint_credentials(config: &AppConfig, user_id: Uuid) -> Option<TurnCredentials> {
+    if !config.turn_enabled() {
+        return None;
+    }
+    let expires_at = chrono::Utc::now().timestamp() + config.turn_credential_ttl_secs as i64;
+    let username = format!("{}:{}", expires_at, user_id);
+    Some(TurnCredentials {
+        urls: turn_urls(&config.turn_urls),
+        credential: sign(&config.turn_secret, &username),
+        username,
+        ttl_secs: config.turn_credential_ttl_secs,
+    })
+}
+
+fn sign(secret: &str, username: &str) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(username.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+fn turn_urls(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_without_config() {
+        let config = AppConfig::test_default();
+        assert!(mint_credentials(&config, Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn credentials_embed_expiry_and_user() {
+        let mut config = AppConfig::test_default();
+        config.turn_urls = "turn:a.example.com:3478, turns:a.example.com:5349".into();
+        config.turn_secret = "secret".into();
+        let user_id = Uuid::new_v4();
+
+        let creds = mint_credentials(&config, user_id).unwrap();
+        assert_eq!(creds.urls, vec!["turn:a.example.com:3478", "turns:a.example.com:5349"]);
+        let (expiry, user) = creds.username.split_once(':').unwrap();
+        assert_eq!(user, user_id.to_string());
+        assert!(expiry.parse::<i64>().unwrap() > chrono::Utc::now().timestamp());
+        assert_eq!(creds.credential, sign("secret", &creds.username));
+    }
+
+    #[test]
+    fn sign_matches_coturn_reference() {
+        // echo -n "1700000000:alice" | openssl dgst -sha1 -hmac "north" -binary | base64
+        assert_eq!(sign("north", "1700000000:alice"), "Cd/49soE35ICqcJF/bCTn8Z4OyE=");
+    }
+}
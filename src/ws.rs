@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -16,12 +17,17 @@ use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use crate::api::messages::MessageKind;
 use crate::auth::{validate_access_token, user_id_from_claims};
 use crate::db::queries;
-use crate::errors::AppError;
+use crate::errors::{AppError, AppResult};
 use crate::memory_store::{ActiveCall, ConnectedCall};
-use crate::models::{MessageResponse, WsClientMessage, WsServerMessage};
+use crate::models::{
+    Channel, LocationShare, MessageMentions, MessageResponse, SystemEvent, SystemMessage, VoiceNote,
+    WsClientMessage, WsServerMessage,
+};
 use crate::pubsub;
+use crate::ws_codec::{WsCodec, WsCompression, WsEncoding};
 use crate::AppState;
 
 /// Tracks all connected clients. Maps user_id -> list of sender channels.
@@ -50,6 +56,13 @@ pub type SessionMap = Arc<DashMap<Uuid, Arc<WsSession>>>;
 #[derive(Debug, Deserialize)]
 pub struct WsAuthQuery {
     pub token: String,
+    /// `json` (default) or `msgpack`
+    pub encoding: Option<String>,
+    /// `zlib` to compress every outgoing frame on one zlib stream
+    pub compress: Option<String>,
+    /// Registered device this connection belongs to; while it is connected,
+    /// DM and group messages are not queued for it
+    pub device_id: Option<Uuid>,
 }
 
 /// WebSocket upgrade handler.
@@ -61,6 +74,16 @@ pub async fn ws_handler(
     // Authenticate before upgrading
     let claims = validate_access_token(&auth.token, &state.config)?;
     let user_id = user_id_from_claims(&claims)?;
+    let encoding = WsEncoding::parse(auth.encoding.as_deref())?;
+    let compression = WsCompression::parse(auth.compress.as_deref())?;
+    if let Some(device_id) = auth.device_id {
+        let owned = queries::find_user_device(state.db.read(), device_id)
+            .await?
+            .is_some_and(|device| device.user_id == user_id);
+        if !owned {
+            return Err(AppError::NotFound("Device not found".into()));
+        }
+    }
 
     // Check instance ban (cache-first to avoid DB query on every connection)
     let is_banned = if let Some(cached) = state.ban_cache.get(&user_id) {
@@ -91,11 +114,19 @@ pub async fn ws_handler(
         )));
     }
 
-    Ok(ws.on_upgrade(move |socket| handle_socket(socket, user_id, state)))
+    let device_id = auth.device_id;
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, user_id, device_id, encoding, compression, state)))
 }
 
 /// Handles an individual WebSocket connection.
-async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
+async fn handle_socket(
+    socket: WebSocket,
+    user_id: Uuid,
+    device_id: Option<Uuid>,
+    encoding: WsEncoding,
+    compression: WsCompression,
+    state: AppState,
+) {
     let (mut ws_sink, mut ws_stream) = socket.split();
 
     // Create a channel for sending messages to this specific connection
@@ -125,18 +156,22 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
         .entry(user_id)
         .or_default()
         .push(tx.clone());
+    if let Some(device_id) = device_id {
+        *state.memory.connected_devices.entry(device_id).or_insert(0) += 1;
+    }
 
     tracing::info!("WebSocket connected: user={}, session={}", user_id, session_id);
 
     // Send Hello immediately
     let hello = WsServerMessage::Hello {
         session_id,
-        heartbeat_interval_ms: (state.config.ws_heartbeat_timeout_secs * 1000) / 3,
+        heartbeat_interval_ms: state.config.ws_heartbeat_interval_secs * 1000,
     };
     let _ = tx.send(hello);
 
-    // Track this user in Redis pub/sub for cross-instance delivery
-    pubsub::subscribe_redis_user(&state, user_id).await;
+    if let Err(e) = queries::touch_user_activity(state.db.write(), user_id).await {
+        tracing::warn!("Failed to record activity for user {}: {}", user_id, e);
+    }
 
     // Always broadcast online — handles reconnect-before-disconnect race on page refresh
     broadcast_presence(user_id, "online", &state).await;
@@ -144,8 +179,44 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
     // Task: forward messages from our channel to the WebSocket sink,
     // and buffer events in the session for resume support.
     let session_for_send = session.clone();
+    let mut coalescer = EventCoalescer::new(Duration::from_millis(state.config.ws_coalesce_window_ms));
+    let mut codec = WsCodec::new(encoding, compression);
+    let shutdown = state.shutdown.clone();
     let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
+        let reconnect = shutdown.reconnect_turn();
+        tokio::pin!(reconnect);
+        loop {
+            let msg = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = &mut reconnect => {
+                    // Draining: hand the client over to another instance
+                    let reconnect = codec
+                        .serialize(&WsServerMessage::Reconnect)
+                        .and_then(|payload| codec.frame(payload));
+                    if let Ok(frame) = reconnect {
+                        let _ = ws_sink.send(frame).await;
+                    }
+                    let _ = ws_sink.send(Message::Close(None)).await;
+                    break;
+                }
+            };
+            let payload = match codec.serialize(&msg) {
+                Ok(p) => p,
+                Err(e) => {
+                    tracing::error!("Failed to serialize WS message: {}", e);
+                    continue;
+                }
+            };
+
+            // Drop repeats of the event just delivered (before buffering, so
+            // a resume doesn't replay the burst either)
+            if !coalescer.admit(&msg, &payload, Instant::now()) {
+                continue;
+            }
+
             // Buffer the event for resume (skip Hello/Resumed/InvalidSession/Pong)
             if should_buffer_event(&msg) {
                 let mut buf = session_for_send.event_buffer.lock().await;
@@ -155,56 +226,89 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
                 buf.push_back(msg.clone());
             }
 
-            let text = match serde_json::to_string(&msg) {
-                Ok(t) => t,
+            let frame = match codec.frame(payload) {
+                Ok(f) => f,
                 Err(e) => {
-                    tracing::error!("Failed to serialize WS message: {}", e);
-                    continue;
+                    tracing::error!("Failed to encode WS frame: {}", e);
+                    break;
                 }
             };
-            if ws_sink.send(Message::Text(text)).await.is_err() {
+            if ws_sink.send(frame).await.is_err() {
                 break;
             }
         }
     });
 
-    // Task: read messages from the WebSocket and process them, with heartbeat timeout.
+    // Task: read messages from the WebSocket and process them, reaping the
+    // connection once it has been silent past the heartbeat timeout.
+    let heartbeat_interval = Duration::from_secs(state.config.ws_heartbeat_interval_secs);
     let heartbeat_timeout = Duration::from_secs(state.config.ws_heartbeat_timeout_secs);
     let state_clone = state.clone();
     let tx_clone = tx.clone();
     let subs_clone = subscriptions.clone();
     let session_for_recv = session.clone();
     let recv_task = tokio::spawn(async move {
+        let heartbeats = &state_clone.ws_heartbeats;
+        let mut monitor = HeartbeatMonitor::new(heartbeat_interval, heartbeat_timeout, Instant::now());
         loop {
-            match tokio::time::timeout(heartbeat_timeout, ws_stream.next()).await {
-                Ok(Some(Ok(msg))) => {
-                    // Update session last_active on any message
-                    *session_for_recv.last_active.lock().await = Instant::now();
-                    match msg {
-                        Message::Text(text) => {
-                            handle_client_message(&text, user_id, &state_clone, &tx_clone, &subs_clone).await;
+            let frame = tokio::select! {
+                frame = ws_stream.next() => frame,
+                _ = tokio::time::sleep_until(tokio::time::Instant::from_std(monitor.deadline())) => {
+                    match monitor.check(Instant::now()) {
+                        Some(Liveness::Missed) => {
+                            heartbeats.record_missed();
+                            tracing::debug!("WebSocket heartbeat missed: user={}, session={}", user_id, session_id);
+                        }
+                        Some(Liveness::Dead) => {
+                            heartbeats.record_reaped();
+                            tracing::info!("WebSocket heartbeat timeout: user={}, session={}", user_id, session_id);
+                            break;
                         }
-                        Message::Close(_) => break,
-                        Message::Ping(_) => {} // axum auto-responds with pong
-                        _ => {}
+                        None => {}
                     }
+                    continue;
                 }
-                Ok(Some(Err(_))) => break, // WebSocket error
-                Ok(None) => break,         // Stream ended
-                Err(_) => {
-                    // Heartbeat timeout — client is dead
-                    tracing::info!("WebSocket heartbeat timeout: user={}, session={}", user_id, session_id);
-                    break;
-                }
+            };
+            let Some(Ok(msg)) = frame else {
+                break; // WebSocket error or stream ended
+            };
+
+            // Any frame proves the socket is alive
+            let now = Instant::now();
+            monitor.seen(now);
+            *session_for_recv.last_active.lock().await = now;
+            match msg {
+                Message::Close(_) => break,
+                Message::Ping(_) => {} // axum auto-responds with pong
+                data => match encoding.decode(&data) {
+                    Some(Ok(client_msg)) => {
+                        if matches!(client_msg, WsClientMessage::Heartbeat { .. } | WsClientMessage::Ping) {
+                            monitor.heartbeat(now);
+                            heartbeats.record_heartbeat();
+                        }
+                        handle_client_message(client_msg, user_id, &state_clone, &tx_clone, &subs_clone).await;
+                    }
+                    Some(Err(e)) => {
+                        let _ = tx_clone.send(WsServerMessage::Error {
+                            message: format!("Invalid message format: {}", e),
+                        });
+                    }
+                    None => {}
+                },
             }
         }
     });
 
-    // Wait for either task to finish (connection closed)
+    // Wait for either task to finish (connection closed), then stop the
+    // other so a reaped socket isn't kept open by its half still running
+    let send_abort = send_task.abort_handle();
+    let recv_abort = recv_task.abort_handle();
     tokio::select! {
         _ = send_task => {},
         _ = recv_task => {},
     }
+    send_abort.abort();
+    recv_abort.abort();
 
     // Snapshot subscribed channels into the session for resume
     {
@@ -232,7 +336,7 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
     let was_last_connection = {
         let mut is_last = false;
         if let Some(mut conns) = state.connections.get_mut(&user_id) {
-            conns.retain(|sender| !sender.is_closed());
+            conns.retain(|sender| !sender.is_closed() && !sender.same_channel(&tx));
             if conns.is_empty() {
                 is_last = true;
                 drop(conns);
@@ -241,53 +345,201 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
         }
         is_last
     };
+    if let Some(device_id) = device_id {
+        state.memory.connected_devices.remove_if_mut(&device_id, |_, count| {
+            *count -= 1;
+            *count == 0
+        });
+    }
 
-    if was_last_connection {
+    // While draining, the client is reconnecting to another instance, so it
+    // neither goes offline nor leaves its voice channel or call
+    if was_last_connection && !state.shutdown.is_draining() {
         broadcast_presence(user_id, "offline", &state).await;
+        // Going offline ends memberships from temporary invites
+        crate::api::invites::remove_lapsed_temporary_members(&state, user_id).await;
         // Clean up voice state — remove from any voice channel
         crate::api::voice::cleanup_voice_state(&state, user_id).await;
         // Clean up any active calls this user initiated
         cleanup_call_state(&state, user_id).await;
-        // Unsubscribe from Redis user channel
-        pubsub::unsubscribe_redis_user(&state, user_id).await;
     }
 
     tracing::info!("WebSocket disconnected: user={}, session={}", user_id, session_id);
 }
 
 /// Returns true if this event type should be buffered for resume support.
-/// Transient control messages (Hello, Pong, Resumed, InvalidSession) are not buffered.
+/// Transient control messages (Hello, Pong, HeartbeatAck, Reconnect, Resumed, InvalidSession) are not buffered,
+/// nor are member chunks and sync deltas — they answer a request the client
+/// re-sends after resuming.
 fn should_buffer_event(msg: &WsServerMessage) -> bool {
     !matches!(
         msg,
         WsServerMessage::Hello { .. }
             | WsServerMessage::Pong
+            | WsServerMessage::HeartbeatAck { .. }
+            | WsServerMessage::Reconnect
             | WsServerMessage::Resumed { .. }
             | WsServerMessage::InvalidSession
             | WsServerMessage::Subscribed { .. }
             | WsServerMessage::Error { .. }
             | WsServerMessage::CallRinging { .. }
+            | WsServerMessage::MemberChunk { .. }
+            | WsServerMessage::SyncDelta(_)
+    )
+}
+
+/// Events that only tell the client to refresh some state, so delivering the
+/// same one twice in a row carries no new information.
+fn is_coalescible_event(msg: &WsServerMessage) -> bool {
+    matches!(
+        msg,
+        WsServerMessage::PresenceUpdate { .. }
+            | WsServerMessage::ServerUpdated { .. }
+            | WsServerMessage::ChannelSettingsUpdated { .. }
+            | WsServerMessage::SenderKeysUpdated { .. }
+            | WsServerMessage::GroupDmUpdated { .. }
+            | WsServerMessage::VoiceMuteUpdate { .. }
+            | WsServerMessage::ReadStateUpdated { .. }
     )
 }
 
+/// Per-connection dedupe of identical consecutive events.
+///
+/// Bursts such as repeated ServerUpdated during a multi-step restore, or the
+/// "online" broadcasts of a reconnect storm, collapse into one delivery per
+/// window. Only the immediately preceding event is remembered, so any other
+/// event in between resets the dedupe.
+struct EventCoalescer {
+    window: Duration,
+    last: Option<(Vec<u8>, Instant)>,
+}
+
+impl EventCoalescer {
+    fn new(window: Duration) -> Self {
+        Self { window, last: None }
+    }
+
+    /// Returns false if `payload` repeats the previous event within the window.
+    fn admit(&mut self, msg: &WsServerMessage, payload: impl AsRef<[u8]>, now: Instant) -> bool {
+        if self.window.is_zero() || !is_coalescible_event(msg) {
+            self.last = None;
+            return true;
+        }
+        let payload = payload.as_ref();
+        if let Some((last, delivered_at)) = &self.last {
+            if last.as_slice() == payload && now.duration_since(*delivered_at) < self.window {
+                return false;
+            }
+        }
+        self.last = Some((payload.to_vec(), now));
+        true
+    }
+}
+
+/// Heartbeat counters since startup, across every connection on this instance.
+#[derive(Clone, Default)]
+pub struct HeartbeatStats {
+    heartbeats: Arc<AtomicU64>,
+    missed: Arc<AtomicU64>,
+    reaped: Arc<AtomicU64>,
+}
+
+impl HeartbeatStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record_heartbeat(&self) {
+        self.heartbeats.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_missed(&self) {
+        self.missed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_reaped(&self) {
+        self.reaped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn heartbeats(&self) -> u64 {
+        self.heartbeats.load(Ordering::Relaxed)
+    }
+
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+
+    pub fn reaped(&self) -> u64 {
+        self.reaped.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Liveness {
+    /// A heartbeat interval passed without a heartbeat.
+    Missed,
+    /// Nothing at all arrived within the timeout.
+    Dead,
+}
+
+/// Per-connection heartbeat tracking.
+///
+/// A heartbeat is due every interval, with half an interval of slack for
+/// jitter, and each interval that passes without one counts as missed. Any
+/// frame proves the socket is alive, so a connection is only reaped once it
+/// has sent nothing at all for the whole timeout.
+struct HeartbeatMonitor {
+    interval: Duration,
+    timeout: Duration,
+    last_seen: Instant,
+    next_due: Instant,
+}
+
+impl HeartbeatMonitor {
+    fn new(interval: Duration, timeout: Duration, now: Instant) -> Self {
+        let interval = interval.max(Duration::from_secs(1));
+        Self {
+            interval,
+            timeout,
+            last_seen: now,
+            next_due: now + interval + interval / 2,
+        }
+    }
+
+    fn seen(&mut self, now: Instant) {
+        self.last_seen = now;
+    }
+
+    fn heartbeat(&mut self, now: Instant) {
+        self.last_seen = now;
+        self.next_due = now + self.interval + self.interval / 2;
+    }
+
+    /// When to call [`HeartbeatMonitor::check`] if nothing arrives before then.
+    fn deadline(&self) -> Instant {
+        self.next_due.min(self.last_seen + self.timeout)
+    }
+
+    fn check(&mut self, now: Instant) -> Option<Liveness> {
+        if now >= self.last_seen + self.timeout {
+            return Some(Liveness::Dead);
+        }
+        if now >= self.next_due {
+            self.next_due += self.interval;
+            return Some(Liveness::Missed);
+        }
+        None
+    }
+}
+
 /// Process an incoming client message.
 async fn handle_client_message(
-    text: &str,
+    client_msg: WsClientMessage,
     user_id: Uuid,
     state: &AppState,
     reply_tx: &mpsc::UnboundedSender<WsServerMessage>,
     subscriptions: &Arc<tokio::sync::Mutex<HashMap<Uuid, JoinHandle<()>>>>,
 ) {
-    let client_msg: WsClientMessage = match serde_json::from_str(text) {
-        Ok(m) => m,
-        Err(e) => {
-            let _ = reply_tx.send(WsServerMessage::Error {
-                message: format!("Invalid message format: {}", e),
-            });
-            return;
-        }
-    };
-
     match client_msg {
         WsClientMessage::SendMessage {
             channel_id,
@@ -296,6 +548,9 @@ async fn handle_client_message(
             expires_at,
             attachment_ids,
             reply_to_id,
+            mentions,
+            voice_note,
+            location,
         } => {
             // Per-user rate limit on message sending
             if !state.ws_rate_limiter.check(user_id) {
@@ -312,6 +567,9 @@ async fn handle_client_message(
                 expires_at,
                 attachment_ids,
                 reply_to_id,
+                mentions.unwrap_or_default(),
+                voice_note,
+                location,
                 state,
                 reply_tx,
             )
@@ -392,26 +650,76 @@ async fn handle_client_message(
             handle_resume(session_id, user_id, state, reply_tx).await;
         }
 
-        WsClientMessage::Ping => {
-            let _ = reply_tx.send(WsServerMessage::Pong);
-            // Refresh presence on each ping to handle stale entries
-            if let Some(mut redis) = state.redis.clone() {
-                let current: Option<String> = redis::cmd("HGET")
-                    .arg("haven:presence")
-                    .arg(user_id.to_string())
-                    .query_async(&mut redis)
-                    .await
-                    .unwrap_or(None);
-                if let Some(status) = current {
-                    let _: Result<(), _> = redis::cmd("HSET")
-                        .arg("haven:presence")
-                        .arg(user_id.to_string())
-                        .arg(status)
-                        .query_async(&mut redis)
-                        .await;
+        WsClientMessage::RequestMembers { server_id, query, limit, nonce } => {
+            if !state.ws_rate_limiter.check(user_id) {
+                let _ = reply_tx.send(WsServerMessage::Error {
+                    message: "Rate limit exceeded — slow down".into(),
+                });
+                return;
+            }
+            handle_request_members(user_id, server_id, query, limit, nonce, state, reply_tx).await;
+        }
+
+        WsClientMessage::Sync { since_version } => {
+            if !state.ws_rate_limiter.check(user_id) {
+                let _ = reply_tx.send(WsServerMessage::Error {
+                    message: "Rate limit exceeded — slow down".into(),
+                });
+                return;
+            }
+            match crate::api::sync::sync_delta(state.db.read(), user_id, since_version).await {
+                Ok(delta) => {
+                    let _ = reply_tx.send(WsServerMessage::SyncDelta(delta));
+                }
+                Err(e) => {
+                    let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
                 }
             }
-            // In-memory presence is always kept up-to-date via broadcast_presence
+        }
+
+        WsClientMessage::UpdateLiveLocation { message_id, encrypted_position } => {
+            if let Err(e) =
+                crate::api::locations::update_position(state, user_id, message_id, &encrypted_position).await
+            {
+                let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+            }
+        }
+
+        WsClientMessage::StopLiveLocation { message_id } => {
+            if let Err(e) = crate::api::locations::stop(state, user_id, message_id).await {
+                let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+            }
+        }
+
+        WsClientMessage::Ping => {
+            let _ = reply_tx.send(WsServerMessage::Pong);
+            refresh_presence(user_id, state).await;
+        }
+
+        WsClientMessage::Heartbeat { seq } => {
+            let _ = reply_tx.send(WsServerMessage::HeartbeatAck { seq });
+            refresh_presence(user_id, state).await;
+        }
+    }
+}
+
+/// Refresh the user's Redis presence entry on each keepalive to handle stale
+/// entries. In-memory presence is always kept up-to-date via broadcast_presence.
+async fn refresh_presence(user_id: Uuid, state: &AppState) {
+    if let Some(mut redis) = state.redis.clone() {
+        let current: Option<String> = redis::cmd("HGET")
+            .arg("haven:presence")
+            .arg(user_id.to_string())
+            .query_async(&mut redis)
+            .await
+            .unwrap_or(None);
+        if let Some(status) = current {
+            let _: Result<(), _> = redis::cmd("HSET")
+                .arg("haven:presence")
+                .arg(user_id.to_string())
+                .arg(status)
+                .query_async(&mut redis)
+                .await;
         }
     }
 }
@@ -448,7 +756,7 @@ async fn handle_mark_read(
                     let _ = conn.send(sync_msg.clone());
                 }
             }
-            pubsub::publish_user_event(state.redis.clone().as_mut(), user_id, &sync_msg).await;
+            pubsub::publish_user_event(state, user_id, &sync_msg).await;
         }
         Err(e) => {
             tracing::warn!("Failed to upsert read state: {}", e);
@@ -456,6 +764,100 @@ async fn handle_mark_read(
     }
 }
 
+/// Members per `MemberChunk` when streaming a full roster.
+const MEMBER_CHUNK_SIZE: i64 = 1000;
+/// Default and maximum result count for a prefix `RequestMembers` query.
+const MEMBER_QUERY_DEFAULT_LIMIT: u32 = 100;
+const MEMBER_QUERY_MAX_LIMIT: u32 = 1000;
+const MEMBER_QUERY_MAX_LEN: usize = 32;
+
+/// Handle a RequestMembers command. A prefix query is answered with a single
+/// chunk; a full roster is streamed in join order, one chunk at a time, from
+/// a background task so the connection keeps processing other commands.
+async fn handle_request_members(
+    user_id: Uuid,
+    server_id: Uuid,
+    query: Option<String>,
+    limit: Option<u32>,
+    nonce: Option<String>,
+    state: &AppState,
+    reply_tx: &mpsc::UnboundedSender<WsServerMessage>,
+) {
+    match queries::is_server_member(state.db.read(), server_id, user_id).await {
+        Ok(true) => {}
+        _ => {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: "Not a member of this server".into(),
+            });
+            return;
+        }
+    }
+
+    if let Some(query) = query.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        if query.chars().count() > MEMBER_QUERY_MAX_LEN {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: format!("Member query must be at most {} characters", MEMBER_QUERY_MAX_LEN),
+            });
+            return;
+        }
+        let limit = limit.unwrap_or(MEMBER_QUERY_DEFAULT_LIMIT).clamp(1, MEMBER_QUERY_MAX_LIMIT);
+        match queries::get_server_member_chunk(state.db.read(), server_id, None, Some(query), limit as i64).await {
+            Ok(members) => {
+                let _ = reply_tx.send(WsServerMessage::MemberChunk {
+                    server_id,
+                    members,
+                    chunk_index: 0,
+                    chunk_count: 1,
+                    nonce,
+                });
+            }
+            Err(e) => {
+                let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+            }
+        }
+        return;
+    }
+
+    let state = state.clone();
+    let reply_tx = reply_tx.clone();
+    tokio::spawn(async move {
+        let total = match queries::count_server_members(state.db.read(), server_id).await {
+            Ok(total) => total,
+            Err(e) => {
+                let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+                return;
+            }
+        };
+        let chunk_count = ((total + MEMBER_CHUNK_SIZE - 1) / MEMBER_CHUNK_SIZE).max(1) as u32;
+        let mut after = None;
+        for chunk_index in 0..chunk_count {
+            let members = match queries::get_server_member_chunk(
+                state.db.read(), server_id, after, None, MEMBER_CHUNK_SIZE,
+            )
+            .await
+            {
+                Ok(members) => members,
+                Err(e) => {
+                    let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+                    return;
+                }
+            };
+            after = members.last().map(|m| (m.joined_at, m.user_id));
+            let chunk = WsServerMessage::MemberChunk {
+                server_id,
+                members,
+                chunk_index,
+                chunk_count,
+                nonce: nonce.clone(),
+            };
+            // Stop early if the connection went away
+            if reply_tx.send(chunk).is_err() {
+                return;
+            }
+        }
+    });
+}
+
 /// Handle a Resume command: replay buffered events from a previous session.
 async fn handle_resume(
     session_id: Uuid,
@@ -516,6 +918,9 @@ async fn handle_send_message(
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
     attachment_ids: Option<Vec<Uuid>>,
     reply_to_id: Option<Uuid>,
+    mut mentions: MessageMentions,
+    voice_note: Option<VoiceNote>,
+    location: Option<LocationShare>,
     state: &AppState,
     reply_tx: &mpsc::UnboundedSender<WsServerMessage>,
 ) {
@@ -538,17 +943,105 @@ async fn handle_send_message(
     }
 
     // Check if member is timed out (server channels only)
-    if let Ok(Some(channel)) = queries::find_channel_by_id(state.db.read(), channel_id).await {
-        if let Some(server_id) = channel.server_id {
-            if queries::is_member_timed_out(state.db.read(), server_id, user_id)
-                .await
-                .unwrap_or(false)
-            {
-                let _ = reply_tx.send(WsServerMessage::Error {
-                    message: "You are timed out in this server".into(),
-                });
-                return;
+    let (server_id, channel_encrypted) = match queries::find_channel_by_id(state.db.read(), channel_id).await {
+        Ok(Some(channel)) => (channel.server_id, channel.encrypted),
+        _ => (None, true),
+    };
+    if let Some(server_id) = server_id {
+        if queries::is_member_timed_out(state.db.read(), server_id, user_id)
+            .await
+            .unwrap_or(false)
+        {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: "You are timed out in this server".into(),
+            });
+            return;
+        }
+        if queries::is_onboarding_pending(state.db.read(), server_id, user_id)
+            .await
+            .unwrap_or(false)
+        {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: "Acknowledge the server rules before posting".into(),
+            });
+            return;
+        }
+        if let Ok(Some(reason)) = crate::api::verification::posting_blocked_reason(state, server_id, user_id).await {
+            let _ = reply_tx.send(WsServerMessage::Error { message: reason.into() });
+            return;
+        }
+
+        // Server quotas: message rate, and storage for uploaded attachments
+        let mut attachment_bytes: u64 = 0;
+        for id in attachment_ids.iter().flatten() {
+            if let Ok(Some(pending)) = queries::find_pending_upload(state.db.write(), *id, user_id).await {
+                attachment_bytes += pending.size_bytes as u64;
+            }
+        }
+        let quota_check = match crate::quota::record_message(state, server_id).await {
+            Ok(()) if attachment_bytes > 0 => {
+                crate::quota::check_storage(state, server_id, attachment_bytes).await
             }
+            result => result,
+        };
+        if let Err(e) = quota_check {
+            let message = match e {
+                AppError::QuotaExceeded(msg) | AppError::TooManyRequests(msg) => msg,
+                e => {
+                    tracing::error!("Failed to check server quota: {}", e);
+                    "Internal error".into()
+                }
+            };
+            let _ = reply_tx.send(WsServerMessage::Error { message });
+            return;
+        }
+    }
+
+    // A 1:1 DM is closed once the other party has blocked the sender
+    if queries::is_blocked_in_dm(state.db.read(), channel_id, user_id)
+        .await
+        .unwrap_or(false)
+    {
+        let _ = reply_tx.send(WsServerMessage::Error {
+            message: "You cannot message this user".into(),
+        });
+        return;
+    }
+
+    let has_attachments = attachment_ids.as_ref().is_some_and(|ids| !ids.is_empty());
+
+    if let Some(location) = &location {
+        if let Err(e) = crate::api::locations::check_location(state, location, voice_note.is_some()) {
+            let _ = reply_tx.send(WsServerMessage::Error { message: e.to_string() });
+            return;
+        }
+    }
+
+    if !mentions.is_empty() || voice_note.is_some() {
+        let checked = match queries::find_channel_by_id(state.db.read(), channel_id).await {
+            Ok(Some(channel)) => {
+                let checked = crate::api::messages::check_mentions(state, user_id, &channel, &mut mentions).await;
+                match &voice_note {
+                    Some(voice_note) if checked.is_ok() => {
+                        crate::api::messages::check_voice_note(state, user_id, &channel, voice_note, has_attachments)
+                            .await
+                    }
+                    _ => checked,
+                }
+            }
+            Ok(None) => Err(AppError::NotFound("Channel not found".into())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = checked {
+            let message = match e {
+                AppError::Database(_) | AppError::Redis(_) | AppError::Internal(_) => {
+                    tracing::error!("Failed to check mentions: {}", e);
+                    "Internal error".into()
+                }
+                e => e.to_string(),
+            };
+            let _ = reply_tx.send(WsServerMessage::Error { message });
+            return;
         }
     }
 
@@ -587,8 +1080,6 @@ async fn handle_send_message(
         return;
     }
 
-    let has_attachments = attachment_ids.as_ref().is_some_and(|ids| !ids.is_empty());
-
     // Apply channel default TTL if client didn't set an explicit expires_at
     let effective_expires_at = match expires_at {
         Some(ea) => Some(ea),
@@ -626,16 +1117,57 @@ async fn handle_send_message(
 
     // Link attachments to the message
     if let Some(ids) = attachment_ids {
+        let scan = crate::scanning::should_scan(&state.config, channel_encrypted);
         for att_id in ids {
-            let storage_key = crate::storage::obfuscated_key(&state.storage_key, &att_id.to_string());
-            let file_hash = state.memory.pending_file_hashes.remove(&att_id).map(|(_, v)| v);
-            if let Err(e) = queries::link_attachment(state.db.write(), att_id, message.id, &storage_key, file_hash.as_deref()).await {
+            let pending = match queries::take_pending_upload(state.db.write(), att_id, user_id).await {
+                Ok(Some(pending)) => pending,
+                Ok(None) => {
+                    tracing::warn!("Attachment {} is not a pending upload of {}", att_id, user_id);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to look up pending upload {}: {}", att_id, e);
+                    continue;
+                }
+            };
+            let storage_key = crate::uploads::blob_key(&state.storage_key, &pending.content_hash);
+            if let Err(e) = queries::link_attachment(
+                state.db.write(), att_id, message.id, &storage_key, pending.file_hash.as_deref(), server_id,
+                pending.size_bytes, Some(&pending.content_hash), scan.then_some(crate::models::ScanStatus::Pending),
+                None,
+            )
+            .await
+            {
                 tracing::error!("Failed to link attachment {}: {}", att_id, e);
+                let _ = queries::release_attachment_blob(state.db.write(), &pending.content_hash).await;
+            } else if scan {
+                crate::scanning::spawn_scan(state, att_id, storage_key);
             }
         }
     }
 
-    let msg_response: MessageResponse = message.into();
+    crate::federation::relay_message(state, &message).await;
+    crate::api::bridges::enqueue_message(state, &message).await;
+    crate::api::devices::enqueue_for_offline_devices(state, message.channel_id, message.id).await;
+
+    let mut msg_response: MessageResponse = message.into();
+    msg_response.blocked_by = queries::get_blocker_ids(state.db.read(), user_id)
+        .await
+        .unwrap_or_default();
+    if let Err(e) = crate::api::messages::record_mentions(state, &mut msg_response, mentions).await {
+        tracing::error!("Failed to record mentions of message {}: {}", msg_response.id, e);
+    }
+    let kind = match (voice_note, location) {
+        (Some(voice_note), _) => MessageKind::VoiceNote(voice_note),
+        (None, Some(location)) => MessageKind::Location(location),
+        (None, None) => MessageKind::Plain,
+    };
+    if let Err(e) = crate::api::messages::record_kind(state, &mut msg_response, user_id, kind).await {
+        tracing::error!("Failed to record the kind of message {}: {}", msg_response.id, e);
+    }
+    if let Err(e) = crate::notification_rules::apply(state, &mut msg_response, Some(user_id)).await {
+        tracing::error!("Failed to apply notification rules to message {}: {}", msg_response.id, e);
+    }
 
     // Send ACK to sender
     let _ = reply_tx.send(WsServerMessage::MessageAck {
@@ -648,7 +1180,7 @@ async fn handle_send_message(
         let _ = broadcaster.send(new_msg.clone());
     }
     // Publish to Redis for cross-instance delivery
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &new_msg).await;
+    pubsub::publish_channel_event(state, channel_id, &new_msg).await;
 
     // For DM/group channels, also deliver directly to all member connections.
     // Members may not have subscribed to the channel broadcast yet (e.g., new DM
@@ -660,11 +1192,14 @@ async fn handle_send_message(
             if let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await {
                 for member_id in member_ids {
                     if member_id == user_id { continue; } // Skip the sender
+                    let personal = personalize(new_msg.clone(), member_id);
                     if let Some(conns) = state.connections.get(&member_id) {
                         for conn in conns.iter() {
-                            let _ = conn.send(new_msg.clone());
+                            let _ = conn.send(personal.clone());
                         }
+                        crate::api::receipts::spawn_record_delivery(state, member_id, &personal);
                     }
+                    pubsub::publish_user_event(state, member_id, &personal).await;
                 }
             }
         }
@@ -716,7 +1251,7 @@ async fn handle_subscribe(
         // Spawn a task to forward broadcast messages to this connection
         let handle = tokio::spawn(async move {
             while let Ok(msg) = rx.recv().await {
-                if reply_tx.send(msg).is_err() {
+                if reply_tx.send(personalize(msg, user_id)).is_err() {
                     break;
                 }
             }
@@ -725,12 +1260,62 @@ async fn handle_subscribe(
         subscriptions.lock().await.insert(channel_id, handle);
     }
 
-    // Track in Redis pub/sub for cross-instance delivery
-    pubsub::subscribe_redis_channel(state, channel_id).await;
-
     let _ = reply_tx.send(WsServerMessage::Subscribed { channel_id });
 }
 
+/// Fan out a message that was stored outside the normal send path (relayed
+/// from another server or posted by a bridge): bridge and device queues, channel broadcast,
+/// Redis, and direct delivery to DM/group members, same as `handle_send_message`.
+pub(crate) async fn deliver_new_message(
+    state: &AppState,
+    message: crate::models::Message,
+) -> AppResult<MessageResponse> {
+    crate::api::bridges::enqueue_message(state, &message).await;
+    crate::api::devices::enqueue_for_offline_devices(state, message.channel_id, message.id).await;
+
+    let channel_id = message.channel_id;
+    let sender_id = message.sender_id;
+    let mut response: MessageResponse = message.into();
+    if let Some(sender_id) = sender_id {
+        response.blocked_by = queries::get_blocker_ids(state.db.read(), sender_id).await?;
+    }
+    crate::notification_rules::apply(state, &mut response, sender_id).await?;
+    let new_msg = WsServerMessage::NewMessage(response.clone());
+
+    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+        let _ = broadcaster.send(new_msg.clone());
+    }
+    pubsub::publish_channel_event(state, channel_id, &new_msg).await;
+
+    if let Some(channel) = queries::find_channel_by_id(state.db.read(), channel_id).await? {
+        if channel.channel_type == "dm" || channel.channel_type == "group" {
+            let _ = queries::unhide_channel_for_members(state.db.write(), channel_id).await;
+            for member_id in queries::get_channel_member_ids(state.db.read(), channel_id).await? {
+                if Some(member_id) == sender_id {
+                    continue;
+                }
+                let personal = personalize(new_msg.clone(), member_id);
+                if let Some(conns) = state.connections.get(&member_id) {
+                    for conn in conns.iter() {
+                        let _ = conn.send(personal.clone());
+                    }
+                    crate::api::receipts::spawn_record_delivery(state, member_id, &personal);
+                }
+                pubsub::publish_user_event(state, member_id, &personal).await;
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Flag a fanned-out message for a viewer who blocked its author.
+pub(crate) fn personalize(msg: WsServerMessage, viewer_id: Uuid) -> WsServerMessage {
+    match msg {
+        WsServerMessage::NewMessage(m) => WsServerMessage::NewMessage(m.for_viewer(viewer_id)),
+        other => other,
+    }
+}
+
 /// Handle Unsubscribe — cancel the subscription task for this channel.
 async fn handle_unsubscribe(
     channel_id: Uuid,
@@ -772,24 +1357,8 @@ async fn handle_set_status(
     state.memory.presence.insert(user_id, status.to_string());
 
     // Broadcast to all channels this user belongs to
-    let channel_ids = match queries::get_user_channel_ids(state.db.read(), user_id).await {
-        Ok(ids) => ids,
-        Err(_) => return,
-    };
-
-    let msg = WsServerMessage::PresenceUpdate {
-        user_id,
-        status: broadcast_status.to_string(),
-    };
-
-    for channel_id in &channel_ids {
-        if let Some(broadcaster) = state.channel_broadcasts.get(channel_id) {
-            let _ = broadcaster.send(msg.clone());
-        }
-    }
-    for channel_id in channel_ids {
-        pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &msg).await;
-    }
+    let shown = crate::quiet_hours::presence_status(state, user_id, broadcast_status).await;
+    announce_presence(user_id, &shown, state).await;
 }
 
 /// Handle typing indicator — ephemeral, no persistence.
@@ -808,7 +1377,7 @@ async fn handle_typing(user_id: Uuid, channel_id: Uuid, state: &AppState) {
     if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
         let _ = broadcaster.send(typing_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &typing_msg).await;
+    pubsub::publish_channel_event(state, channel_id, &typing_msg).await;
 }
 
 /// Handle an EditMessage command: verify ownership, update DB, broadcast.
@@ -863,7 +1432,7 @@ async fn handle_edit_message(
     if let Some(broadcaster) = state.channel_broadcasts.get(&message.channel_id) {
         let _ = broadcaster.send(edit_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), message.channel_id, &edit_msg).await;
+    pubsub::publish_channel_event(state, message.channel_id, &edit_msg).await;
 }
 
 /// Handle a DeleteMessage command: verify ownership or server admin, delete from DB, broadcast.
@@ -873,6 +1442,22 @@ async fn handle_delete_message(
     state: &AppState,
     reply_tx: &mpsc::UnboundedSender<WsServerMessage>,
 ) {
+    match queries::any_message_under_legal_hold(state.db.read(), &[message_id]).await {
+        Ok(false) => {}
+        Ok(true) => {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: "This message is under a legal hold and cannot be deleted".into(),
+            });
+            return;
+        }
+        Err(e) => {
+            let _ = reply_tx.send(WsServerMessage::Error {
+                message: format!("Failed to delete message: {}", e),
+            });
+            return;
+        }
+    }
+
     // Try deleting as sender first (fast path)
     let message = match queries::delete_message(state.db.write(), message_id, user_id).await {
         Ok(m) => m,
@@ -962,7 +1547,7 @@ async fn handle_delete_message(
     if let Some(broadcaster) = state.channel_broadcasts.get(&message.channel_id) {
         let _ = broadcaster.send(del_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), message.channel_id, &del_msg).await;
+    pubsub::publish_channel_event(state, message.channel_id, &del_msg).await;
 }
 
 /// Handle an AddReaction command: persist and broadcast.
@@ -1027,7 +1612,7 @@ async fn handle_add_reaction(
     if let Some(broadcaster) = state.channel_broadcasts.get(&message.channel_id) {
         let _ = broadcaster.send(react_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), message.channel_id, &react_msg).await;
+    pubsub::publish_channel_event(state, message.channel_id, &react_msg).await;
 }
 
 /// Handle a RemoveReaction command: delete and broadcast.
@@ -1079,7 +1664,7 @@ async fn handle_remove_reaction(
     if let Some(broadcaster) = state.channel_broadcasts.get(&message.channel_id) {
         let _ = broadcaster.send(unreact_msg.clone());
     }
-    pubsub::publish_channel_event(state.redis.clone().as_mut(), message.channel_id, &unreact_msg).await;
+    pubsub::publish_channel_event(state, message.channel_id, &unreact_msg).await;
 }
 
 /// Broadcast a presence update (online/offline) to all channels the user belongs to,
@@ -1112,7 +1697,13 @@ pub(crate) async fn broadcast_presence(user_id: Uuid, status: &str, state: &AppS
         state.memory.presence.insert(user_id, status.to_string());
     }
 
-    // Get all channels this user belongs to and broadcast
+    let shown = crate::quiet_hours::presence_status(state, user_id, status).await;
+    announce_presence(user_id, &shown, state).await;
+}
+
+/// Send a presence update to all channels the user belongs to, without
+/// touching the presence stores.
+pub(crate) async fn announce_presence(user_id: Uuid, status: &str, state: &AppState) {
     let channel_ids = match queries::get_user_channel_ids(state.db.read(), user_id).await {
         Ok(ids) => ids,
         Err(e) => {
@@ -1133,7 +1724,7 @@ pub(crate) async fn broadcast_presence(user_id: Uuid, status: &str, state: &AppS
     }
     // Publish presence to all subscribed channels via Redis
     for ch_id in channel_ids {
-        pubsub::publish_channel_event(state.redis.clone().as_mut(), ch_id, &msg).await;
+        pubsub::publish_channel_event(state, ch_id, &msg).await;
     }
 }
 
@@ -1183,26 +1774,19 @@ async fn handle_pin_message(
             if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
                 let _ = broadcaster.send(pin_msg.clone());
             }
-            pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &pin_msg).await;
+            pubsub::publish_channel_event(state, channel_id, &pin_msg).await;
 
             // Insert system message for pin
             if let Ok(Some(user)) = queries::find_user_basic_by_id(state.db.read(), user_id).await {
                 let username = user.display_name.as_deref().unwrap_or(&user.username);
-                let body = serde_json::json!({
-                    "event": "message_pinned",
-                    "username": username,
-                    "user_id": user_id.to_string(),
-                    "message_id": message_id.to_string(),
-                });
-                if let Ok(sys_msg) = queries::insert_system_message(
-                    state.db.write(), channel_id, &body.to_string(),
-                ).await {
+                let body = SystemMessage::new(SystemEvent::MessagePinned { message_id }, user_id, username);
+                if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
                     let response: MessageResponse = sys_msg.into();
                     let sys_ws_msg = WsServerMessage::NewMessage(response);
                     if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
                         let _ = broadcaster.send(sys_ws_msg.clone());
                     }
-                    pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &sys_ws_msg).await;
+                    pubsub::publish_channel_event(state, channel_id, &sys_ws_msg).await;
                 }
             }
         }
@@ -1243,26 +1827,19 @@ async fn handle_unpin_message(
             if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
                 let _ = broadcaster.send(unpin_msg.clone());
             }
-            pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &unpin_msg).await;
+            pubsub::publish_channel_event(state, channel_id, &unpin_msg).await;
 
             // Insert system message for unpin
             if let Ok(Some(user)) = queries::find_user_basic_by_id(state.db.read(), user_id).await {
                 let username = user.display_name.as_deref().unwrap_or(&user.username);
-                let body = serde_json::json!({
-                    "event": "message_unpinned",
-                    "username": username,
-                    "user_id": user_id.to_string(),
-                    "message_id": message_id.to_string(),
-                });
-                if let Ok(sys_msg) = queries::insert_system_message(
-                    state.db.write(), channel_id, &body.to_string(),
-                ).await {
+                let body = SystemMessage::new(SystemEvent::MessageUnpinned { message_id }, user_id, username);
+                if let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await {
                     let response: MessageResponse = sys_msg.into();
                     let sys_ws_msg = WsServerMessage::NewMessage(response);
                     if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
                         let _ = broadcaster.send(sys_ws_msg.clone());
                     }
-                    pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &sys_ws_msg).await;
+                    pubsub::publish_channel_event(state, channel_id, &sys_ws_msg).await;
                 }
             }
         }
@@ -1277,13 +1854,22 @@ async fn handle_unpin_message(
     }
 }
 
-/// Broadcast a WS message to all subscribers of a server's channels.
+/// Broadcast a WS message to all subscribers of a server's channels, on
+/// every instance.
 pub async fn broadcast_to_server(state: &AppState, server_id: Uuid, msg: WsServerMessage) {
     if let Ok(channels) = queries::get_server_channels(state.db.read(), server_id).await {
         for ch in channels {
-            if let Some(broadcaster) = state.channel_broadcasts.get(&ch.id) {
-                let _ = broadcaster.send(msg.clone());
-            }
+            pubsub::broadcast_channel_event(state, ch.id, &msg).await;
+        }
+    }
+}
+
+/// Send a WS message to every member of a server on their own connections,
+/// for events about channels they aren't subscribed to (yet).
+pub async fn broadcast_to_server_members(state: &AppState, server_id: Uuid, msg: WsServerMessage) {
+    if let Ok(members) = queries::get_server_member_ids(state.db.read(), server_id).await {
+        for member_id in members {
+            pubsub::broadcast_user_event(state, member_id, &msg).await;
         }
     }
 }
@@ -1307,7 +1893,7 @@ async fn send_to_channel_members(
                     let _ = tx.send(msg.clone());
                 }
             }
-            pubsub::publish_user_event(state.redis.clone().as_mut(), mid, &msg).await;
+            pubsub::publish_user_event(state, mid, &msg).await;
         }
     }
 }
@@ -1319,7 +1905,6 @@ async fn handle_call_invite(
     state: &AppState,
     reply_tx: &mpsc::UnboundedSender<WsServerMessage>,
 ) {
-    // Verify channel exists and is DM or group
     let channel = match queries::find_channel_by_id(state.db.read(), channel_id).await {
         Ok(Some(ch)) => ch,
         _ => {
@@ -1330,43 +1915,49 @@ async fn handle_call_invite(
         }
     };
 
-    if !matches!(channel.channel_type.as_str(), "dm" | "group") {
-        let _ = reply_tx.send(WsServerMessage::Error {
-            message: "Calls are only supported in DMs and group DMs".into(),
-        });
-        return;
+    if let Err(e) = start_call(state, user_id, &channel).await {
+        let message = match e {
+            AppError::BadRequest(msg) | AppError::Forbidden(msg) => msg,
+            other => other.to_string(),
+        };
+        let _ = reply_tx.send(WsServerMessage::Error { message });
     }
+}
 
-    // Verify caller is a member
-    match queries::can_access_channel(state.db.read(), channel_id, user_id).await {
-        Ok(true) => {}
-        _ => {
-            let _ = reply_tx.send(WsServerMessage::Error {
-                message: "Not a member of this channel".into(),
-            });
-            return;
-        }
+/// Start ringing a DM/group call: registers the call, sends `CallRinging` to
+/// the other members and ends the call as missed if nobody answers within 30s.
+/// Shared by the `CallInvite` WS message and `POST /channels/:channel_id/calls`.
+pub async fn start_call(state: &AppState, user_id: Uuid, channel: &Channel) -> Result<(), AppError> {
+    let channel_id = channel.id;
+
+    if !matches!(channel.channel_type.as_str(), "dm" | "group") {
+        return Err(AppError::BadRequest("Calls are only supported in DMs and group DMs".into()));
     }
 
-    // Check no active call already exists for this channel
-    if state.memory.active_calls.contains_key(&channel_id) {
-        let _ = reply_tx.send(WsServerMessage::Error {
-            message: "A call is already active in this channel".into(),
-        });
-        return;
+    if !queries::can_access_channel(state.db.read(), channel_id, user_id).await? {
+        return Err(AppError::Forbidden("Not a member of this channel".into()));
     }
 
-    // Look up caller name
-    let caller_name = match queries::find_user_basic_by_id(state.db.read(), user_id).await {
-        Ok(Some(u)) => u.display_name.unwrap_or(u.username),
-        _ => return,
-    };
+    let caller_name = queries::find_user_basic_by_id(state.db.read(), user_id)
+        .await?
+        .map(|u| u.display_name.unwrap_or(u.username))
+        .ok_or(AppError::UserNotFound)?;
 
-    // Register the active call
-    state.memory.active_calls.insert(channel_id, ActiveCall {
-        caller_id: user_id,
-        started_at: Instant::now(),
-    });
+    // Register the active call (rejecting if one is already ringing or connected)
+    if state.memory.connected_calls.contains_key(&channel_id) {
+        return Err(AppError::BadRequest("A call is already active in this channel".into()));
+    }
+    match state.memory.active_calls.entry(channel_id) {
+        dashmap::mapref::entry::Entry::Occupied(_) => {
+            return Err(AppError::BadRequest("A call is already active in this channel".into()));
+        }
+        dashmap::mapref::entry::Entry::Vacant(entry) => {
+            entry.insert(ActiveCall {
+                caller_id: user_id,
+                started_at: Instant::now(),
+            });
+        }
+    }
 
     // Send CallRinging to all other channel members
     let msg = WsServerMessage::CallRinging {
@@ -1383,6 +1974,7 @@ async fn handle_call_invite(
 
         // If the call is still ringing (not accepted/rejected/ended), end it
         if state_clone.memory.active_calls.remove(&channel_id).is_some() {
+            post_call_system_message(&state_clone, channel_id, user_id, SystemEvent::CallMissed).await;
             let end_msg = WsServerMessage::CallEnded {
                 channel_id,
                 ended_by: user_id,
@@ -1390,6 +1982,38 @@ async fn handle_call_invite(
             send_to_channel_members(&state_clone, channel_id, None, end_msg).await;
         }
     });
+
+    Ok(())
+}
+
+/// Insert a call system message (`call_ended`, `call_missed`) and deliver it
+/// to the channel and directly to each member.
+async fn post_call_system_message(state: &AppState, channel_id: Uuid, user_id: Uuid, event: SystemEvent) {
+    let username = match queries::find_user_basic_by_id(state.db.read(), user_id).await {
+        Ok(Some(u)) => u.display_name.unwrap_or(u.username),
+        _ => "Someone".to_string(),
+    };
+    let body = SystemMessage::new(event, user_id, username);
+
+    let Ok(sys_msg) = queries::insert_system_message(state.db.write(), channel_id, &body).await else {
+        return;
+    };
+    let response: MessageResponse = sys_msg.into();
+    let sys_ws_msg = WsServerMessage::NewMessage(response);
+    // Broadcast via channel + direct delivery for DMs
+    if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
+        let _ = broadcaster.send(sys_ws_msg.clone());
+    }
+    if let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await {
+        for mid in member_ids {
+            if let Some(conns) = state.connections.get(&mid) {
+                for tx in conns.iter() {
+                    let _ = tx.send(sys_ws_msg.clone());
+                }
+            }
+        }
+    }
+    pubsub::publish_channel_event(state, channel_id, &sys_ws_msg).await;
 }
 
 /// Handle CallAccept: callee accepts an incoming call.
@@ -1449,8 +2073,11 @@ async fn handle_call_end(
     channel_id: Uuid,
     state: &AppState,
 ) {
-    // Remove from active_calls if still ringing
-    state.memory.active_calls.remove(&channel_id);
+    // Remove from active_calls if still ringing — the caller hung up before
+    // anyone answered, so the callee sees a missed call.
+    if let Some((_, ringing)) = state.memory.active_calls.remove(&channel_id) {
+        post_call_system_message(state, channel_id, ringing.caller_id, SystemEvent::CallMissed).await;
+    }
 
     // Remove connected call and calculate duration
     let duration_secs = state.memory.connected_calls.remove(&channel_id)
@@ -1458,36 +2085,7 @@ async fn handle_call_end(
 
     // Insert a system message with call duration (only if the call was connected)
     if let Some(secs) = duration_secs {
-        let caller_name = match queries::find_user_basic_by_id(state.db.read(), user_id).await {
-            Ok(Some(u)) => u.display_name.unwrap_or(u.username),
-            _ => "Someone".to_string(),
-        };
-        let body = serde_json::json!({
-            "event": "call_ended",
-            "username": caller_name,
-            "user_id": user_id.to_string(),
-            "duration_secs": secs,
-        });
-        if let Ok(sys_msg) = queries::insert_system_message(
-            state.db.write(), channel_id, &body.to_string(),
-        ).await {
-            let response: MessageResponse = sys_msg.into();
-            let sys_ws_msg = WsServerMessage::NewMessage(response);
-            // Broadcast via channel + direct delivery for DMs
-            if let Some(broadcaster) = state.channel_broadcasts.get(&channel_id) {
-                let _ = broadcaster.send(sys_ws_msg.clone());
-            }
-            if let Ok(member_ids) = queries::get_channel_member_ids(state.db.read(), channel_id).await {
-                for mid in member_ids {
-                    if let Some(conns) = state.connections.get(&mid) {
-                        for tx in conns.iter() {
-                            let _ = tx.send(sys_ws_msg.clone());
-                        }
-                    }
-                }
-            }
-            pubsub::publish_channel_event(state.redis.clone().as_mut(), channel_id, &sys_ws_msg).await;
-        }
+        post_call_system_message(state, channel_id, user_id, SystemEvent::CallEnded { duration_secs: secs }).await;
     }
 
     // Notify all channel members
@@ -1510,6 +2108,7 @@ pub async fn cleanup_call_state(state: &AppState, user_id: Uuid) {
     }
     for channel_id in to_end {
         if state.memory.active_calls.remove(&channel_id).is_some() {
+            post_call_system_message(state, channel_id, user_id, SystemEvent::CallMissed).await;
             let msg = WsServerMessage::CallEnded {
                 channel_id,
                 ended_by: user_id,
@@ -1534,3 +2133,85 @@ pub async fn cleanup_call_state(state: &AppState, user_id: Uuid) {
         handle_call_end(user_id, channel_id, state).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presence(status: &str) -> (WsServerMessage, String) {
+        let msg = WsServerMessage::PresenceUpdate {
+            user_id: Uuid::nil(),
+            status: status.into(),
+        };
+        let text = serde_json::to_string(&msg).unwrap();
+        (msg, text)
+    }
+
+    #[test]
+    fn coalescer_drops_identical_events_within_window() {
+        let mut c = EventCoalescer::new(Duration::from_millis(250));
+        let t0 = Instant::now();
+        let (msg, text) = presence("online");
+        assert!(c.admit(&msg, &text, t0));
+        assert!(!c.admit(&msg, &text, t0 + Duration::from_millis(100)));
+        // The window is measured from the delivered event, not the last drop
+        assert!(c.admit(&msg, &text, t0 + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn coalescer_only_collapses_consecutive_events() {
+        let mut c = EventCoalescer::new(Duration::from_millis(250));
+        let now = Instant::now();
+        let (online, online_text) = presence("online");
+        let (idle, idle_text) = presence("idle");
+        assert!(c.admit(&online, &online_text, now));
+        assert!(c.admit(&idle, &idle_text, now));
+        assert!(c.admit(&online, &online_text, now));
+    }
+
+    #[test]
+    fn coalescer_never_drops_messages_or_when_disabled() {
+        let ack = WsServerMessage::MessageAck { message_id: Uuid::nil() };
+        let ack_text = serde_json::to_string(&ack).unwrap();
+        let mut c = EventCoalescer::new(Duration::from_millis(250));
+        let now = Instant::now();
+        assert!(c.admit(&ack, &ack_text, now));
+        assert!(c.admit(&ack, &ack_text, now));
+
+        let mut disabled = EventCoalescer::new(Duration::ZERO);
+        let (msg, text) = presence("online");
+        assert!(disabled.admit(&msg, &text, now));
+        assert!(disabled.admit(&msg, &text, now));
+    }
+
+    #[test]
+    fn heartbeat_monitor_counts_missed_intervals_then_reaps() {
+        let t0 = Instant::now();
+        let secs = Duration::from_secs;
+        let mut m = HeartbeatMonitor::new(secs(10), secs(30), t0);
+        // Half an interval of slack before the first heartbeat counts as missed
+        assert_eq!(m.deadline(), t0 + secs(15));
+        assert_eq!(m.check(t0 + secs(14)), None);
+        assert_eq!(m.check(t0 + secs(15)), Some(Liveness::Missed));
+        assert_eq!(m.deadline(), t0 + secs(25));
+
+        // Other traffic keeps the socket alive but doesn't stand in for heartbeats
+        m.seen(t0 + secs(20));
+        assert_eq!(m.check(t0 + secs(25)), Some(Liveness::Missed));
+        assert_eq!(m.check(t0 + secs(35)), Some(Liveness::Missed));
+        assert_eq!(m.deadline(), t0 + secs(45));
+        assert_eq!(m.check(t0 + secs(45)), Some(Liveness::Missed));
+        assert_eq!(m.deadline(), t0 + secs(50));
+        assert_eq!(m.check(t0 + secs(50)), Some(Liveness::Dead));
+    }
+
+    #[test]
+    fn heartbeat_resets_the_schedule() {
+        let t0 = Instant::now();
+        let secs = Duration::from_secs;
+        let mut m = HeartbeatMonitor::new(secs(10), secs(30), t0);
+        m.heartbeat(t0 + secs(9));
+        assert_eq!(m.deadline(), t0 + secs(24));
+        assert_eq!(m.check(t0 + secs(15)), None);
+    }
+}
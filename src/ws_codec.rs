@@ -0,0 +1,199 @@
+//! Wire format of a WebSocket connection, negotiated at connect with
+//! `?encoding=json|msgpack&compress=zlib`.
+//!
+//! `msgpack` sends events as binary MessagePack maps with the same field
+//! names and string ids as the JSON encoding, and accepts client messages in
+//! either encoding. `compress=zlib` runs every outgoing frame through one
+//! zlib stream per connection, each frame ending on a sync flush
+//! (`00 00 ff ff`), so the client keeps a single inflate context for the
+//! whole connection. Client messages are never compressed.
+
+use axum::extract::ws::Message;
+use flate2::{Compress, Compression, FlushCompress};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::errors::{AppError, AppResult};
+use crate::models::{WsClientMessage, WsServerMessage};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsEncoding {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsCompression {
+    #[default]
+    None,
+    Zlib,
+}
+
+impl WsEncoding {
+    pub fn parse(value: Option<&str>) -> AppResult<Self> {
+        match value {
+            None | Some("json") => Ok(WsEncoding::Json),
+            Some("msgpack") => Ok(WsEncoding::MsgPack),
+            Some(other) => Err(AppError::BadRequest(format!("Unsupported encoding: {}", other))),
+        }
+    }
+
+    /// Decode a client data frame. Text frames are always JSON; binary frames
+    /// are MessagePack on `msgpack` connections. Returns `None` for control
+    /// frames.
+    pub fn decode(&self, msg: &Message) -> Option<anyhow::Result<WsClientMessage>> {
+        match msg {
+            Message::Text(text) => Some(serde_json::from_str(text).map_err(Into::into)),
+            Message::Binary(bytes) => Some(match self {
+                WsEncoding::MsgPack => from_msgpack(bytes).map_err(Into::into),
+                WsEncoding::Json => Err(anyhow::anyhow!("Binary frames need encoding=msgpack")),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl WsCompression {
+    pub fn parse(value: Option<&str>) -> AppResult<Self> {
+        match value {
+            None | Some("") => Ok(WsCompression::None),
+            Some("zlib") => Ok(WsCompression::Zlib),
+            Some(other) => Err(AppError::BadRequest(format!("Unsupported compression: {}", other))),
+        }
+    }
+}
+
+/// Per-connection encoder. Owns the connection's zlib stream, so
+/// frames must be passed to [`WsCodec::frame`] in the order they are sent.
+pub struct WsCodec {
+    encoding: WsEncoding,
+    deflate: Option<Compress>,
+}
+
+impl WsCodec {
+    pub fn new(encoding: WsEncoding, compression: WsCompression) -> Self {
+        Self {
+            encoding,
+            deflate: (compression == WsCompression::Zlib).then(|| Compress::new(Compression::default(), true)),
+        }
+    }
+
+    /// Encode an event, uncompressed. Identical events encode identically,
+    /// which the coalescer relies on.
+    pub fn serialize(&self, msg: &WsServerMessage) -> anyhow::Result<Vec<u8>> {
+        Ok(match self.encoding {
+            WsEncoding::Json => serde_json::to_vec(msg)?,
+            WsEncoding::MsgPack => to_msgpack(msg)?,
+        })
+    }
+
+    /// Turn an encoded event into the frame to send, compressing it if the
+    /// connection asked for zlib.
+    pub fn frame(&mut self, payload: Vec<u8>) -> anyhow::Result<Message> {
+        if let Some(deflate) = self.deflate.as_mut() {
+            return Ok(Message::Binary(deflate_frame(deflate, &payload)?));
+        }
+        Ok(match self.encoding {
+            WsEncoding::Json => Message::Text(String::from_utf8(payload)?),
+            WsEncoding::MsgPack => Message::Binary(payload),
+        })
+    }
+}
+
+/// Ids, timestamps and other types with a human-readable form keep it, so a
+/// MessagePack event decodes to the same value as its JSON counterpart.
+fn to_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut buf = Vec::new();
+    let mut serializer = rmp_serde::Serializer::new(&mut buf)
+        .with_struct_map()
+        .with_human_readable();
+    value.serialize(&mut serializer)?;
+    Ok(buf)
+}
+
+fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, rmp_serde::decode::Error> {
+    let mut deserializer = rmp_serde::Deserializer::new(bytes).with_human_readable();
+    serde::Deserialize::deserialize(&mut deserializer)
+}
+
+/// Compress `input` on the connection's stream and sync-flush it, so the
+/// frame can be inflated on its own given everything sent before it.
+fn deflate_frame(deflate: &mut Compress, input: &[u8]) -> Result<Vec<u8>, flate2::CompressError> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 64);
+    let mut consumed = 0;
+    loop {
+        if out.capacity() - out.len() < 64 {
+            out.reserve(out.capacity().max(64));
+        }
+        let before = deflate.total_in();
+        deflate.compress_vec(&input[consumed..], &mut out, FlushCompress::Sync)?;
+        consumed += (deflate.total_in() - before) as usize;
+        // The flush is complete once all input is in and output space is left over
+        if consumed == input.len() && out.len() < out.capacity() {
+            return Ok(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Decompress, FlushDecompress};
+    use uuid::Uuid;
+
+    fn inflate(inflater: &mut Decompress, frame: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4096);
+        inflater.decompress_vec(frame, &mut out, FlushDecompress::Sync).unwrap();
+        out
+    }
+
+    #[test]
+    fn parses_connect_params() {
+        assert_eq!(WsEncoding::parse(None).unwrap(), WsEncoding::Json);
+        assert_eq!(WsEncoding::parse(Some("msgpack")).unwrap(), WsEncoding::MsgPack);
+        assert!(WsEncoding::parse(Some("etf")).is_err());
+        assert_eq!(WsCompression::parse(None).unwrap(), WsCompression::None);
+        assert_eq!(WsCompression::parse(Some("zlib")).unwrap(), WsCompression::Zlib);
+        assert!(WsCompression::parse(Some("zstd")).is_err());
+    }
+
+    #[test]
+    fn msgpack_events_match_the_json_shape() {
+        let mut codec = WsCodec::new(WsEncoding::MsgPack, WsCompression::None);
+        let msg = WsServerMessage::MessageAck { message_id: Uuid::new_v4() };
+        let payload = codec.serialize(&msg).unwrap();
+        let Message::Binary(bytes) = codec.frame(payload).unwrap() else {
+            panic!("msgpack frames are binary");
+        };
+        let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, serde_json::to_value(&msg).unwrap());
+    }
+
+    #[test]
+    fn msgpack_codec_accepts_both_client_encodings() {
+        let codec = WsEncoding::MsgPack;
+        let binary = Message::Binary(to_msgpack(&serde_json::json!({ "type": "Ping" })).unwrap());
+        assert!(matches!(codec.decode(&binary), Some(Ok(WsClientMessage::Ping))));
+        let text = Message::Text(r#"{"type":"Ping"}"#.into());
+        assert!(matches!(codec.decode(&text), Some(Ok(WsClientMessage::Ping))));
+
+        let json = WsEncoding::Json;
+        assert!(matches!(json.decode(&binary), Some(Err(_))));
+        assert!(json.decode(&Message::Ping(vec![])).is_none());
+    }
+
+    #[test]
+    fn zlib_frames_inflate_on_one_stream() {
+        let mut codec = WsCodec::new(WsEncoding::Json, WsCompression::Zlib);
+        let mut inflater = Decompress::new(true);
+        for _ in 0..3 {
+            let msg = WsServerMessage::MessageAck { message_id: Uuid::new_v4() };
+            let payload = codec.serialize(&msg).unwrap();
+            let Message::Binary(frame) = codec.frame(payload.clone()).unwrap() else {
+                panic!("compressed frames are binary");
+            };
+            assert!(frame.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+            assert_eq!(inflate(&mut inflater, &frame), payload);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Transactional outbox for WebSocket events.
+//!
+//! Broadcasting after commit is best-effort: a crash between the two loses
+//! the event. A change whose event must not be lost stages it with [`stage`]
+//! in the same transaction and calls [`WsOutbox::wake`] once it commits. The
+//! dispatcher then publishes each target's pending events in order and marks
+//! them sent. Delivery is at-least-once: a crash after publishing but before
+//! marking an event sent publishes it again, so only stage events that are
+//! safe to apply twice (full state, or keyed by id). Events that don't reach
+//! the other instances over Redis are retried until `MAX_ATTEMPTS`; later
+//! events for the same target wait until then.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::db::{queries, Connection};
+use crate::errors::{AppError, AppResult};
+use crate::models::WsServerMessage;
+use crate::pubsub;
+use crate::AppState;
+
+/// Events claimed per query.
+const BATCH_SIZE: i64 = 200;
+/// How long a claimed event is skipped by other dispatchers.
+const LEASE: Duration = Duration::from_secs(30);
+/// Publish attempts before an event is given up on.
+const MAX_ATTEMPTS: i32 = 10;
+/// How often the dispatcher looks for pending events when nothing wakes it,
+/// e.g. for events staged on another instance that crashed before sending.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Sent events are kept this long.
+pub const SENT_RETENTION_HOURS: u32 = 24;
+
+/// Who receives an event, as for the `pubsub::broadcast_*` functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Channel(Uuid),
+    User(Uuid),
+    /// Every channel of the server, resolved when the event is published.
+    Server(Uuid),
+    Instance,
+}
+
+impl Target {
+    fn parts(self) -> (&'static str, Option<Uuid>) {
+        match self {
+            Target::Channel(id) => ("channel", Some(id)),
+            Target::User(id) => ("user", Some(id)),
+            Target::Server(id) => ("server", Some(id)),
+            Target::Instance => ("instance", None),
+        }
+    }
+
+    fn from_parts(target: &str, id: Option<Uuid>) -> Option<Self> {
+        match (target, id) {
+            ("channel", Some(id)) => Some(Target::Channel(id)),
+            ("user", Some(id)) => Some(Target::User(id)),
+            ("server", Some(id)) => Some(Target::Server(id)),
+            ("instance", None) => Some(Target::Instance),
+            _ => None,
+        }
+    }
+}
+
+/// Wakes this instance's dispatcher once staged events have committed.
+#[derive(Clone, Default)]
+pub struct WsOutbox {
+    wake: Arc<Notify>,
+}
+
+impl WsOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish committed events now rather than on the next poll.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Write `event` to the outbox on the caller's transaction.
+pub async fn stage(conn: &mut Connection, target: Target, event: &WsServerMessage) -> AppResult<()> {
+    let (target, target_id) = target.parts();
+    let event = serde_json::to_value(event).map_err(|e| AppError::Internal(e.into()))?;
+    queries::insert_ws_event_in(conn, target, target_id, &event).await
+}
+
+/// Publish pending events for the rest of the process.
+pub fn spawn_dispatcher(state: AppState) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = dispatch(&state).await {
+                tracing::error!("WS outbox dispatch failed: {}", e);
+            }
+            tokio::select! {
+                _ = state.ws_outbox.wake.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Publish every pending event, oldest first. Once an event for a target
+/// fails, the batch's later events for that target are held back with it.
+/// Returns how many were sent.
+pub async fn dispatch(state: &AppState) -> AppResult<u64> {
+    let pool = state.db.primary();
+    let mut sent = 0;
+    loop {
+        let mut batch = queries::claim_ws_events(pool, BATCH_SIZE, LEASE.as_secs() as i64).await?;
+        let more = batch.len() as i64 == BATCH_SIZE;
+        batch.sort_unstable_by_key(|(id, ..)| *id);
+
+        let mut done = Vec::with_capacity(batch.len());
+        let mut held = Vec::new();
+        let mut failed: HashSet<Target> = HashSet::new();
+        for (id, target, target_id, event, attempts) in batch {
+            let target = Target::from_parts(&target, target_id);
+            let event = serde_json::from_value::<WsServerMessage>(event).ok();
+            let (Some(target), Some(event)) = (target, event) else {
+                tracing::warn!("Dropping unreadable WS outbox event {}", id);
+                done.push(id);
+                continue;
+            };
+            if failed.contains(&target) {
+                held.push(id);
+                continue;
+            }
+            if publish(state, target, &event).await {
+                sent += 1;
+            } else if attempts < MAX_ATTEMPTS {
+                failed.insert(target);
+                continue;
+            } else {
+                tracing::warn!("Giving up on WS outbox event {} after {} attempts", id, attempts);
+            }
+            done.push(id);
+        }
+        queries::mark_ws_events_sent(pool, &done).await?;
+        queries::refund_ws_event_attempts(pool, &held).await?;
+        if !more {
+            return Ok(sent);
+        }
+    }
+}
+
+/// Deliver one event locally and to the other instances. False if any of it
+/// didn't reach the bus.
+async fn publish(state: &AppState, target: Target, event: &WsServerMessage) -> bool {
+    match target {
+        Target::Channel(channel_id) => pubsub::broadcast_channel_event(state, channel_id, event).await,
+        Target::User(user_id) => pubsub::broadcast_user_event(state, user_id, event).await,
+        Target::Server(server_id) => {
+            let Ok(channels) = queries::get_server_channels(state.db.read(), server_id).await else {
+                return false;
+            };
+            let mut published = true;
+            for channel in channels {
+                published &= pubsub::broadcast_channel_event(state, channel.id, event).await;
+            }
+            published
+        }
+        Target::Instance => pubsub::broadcast_instance_event(state, event).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_round_trip_through_their_columns() {
+        let id = Uuid::new_v4();
+        for target in [Target::Channel(id), Target::User(id), Target::Server(id), Target::Instance] {
+            let (name, target_id) = target.parts();
+            assert_eq!(Target::from_parts(name, target_id), Some(target));
+        }
+        assert_eq!(Target::from_parts("instance", Some(id)), None);
+        assert_eq!(Target::from_parts("server", None), None);
+        assert_eq!(Target::from_parts("guild", Some(id)), None);
+    }
+}
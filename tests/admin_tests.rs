@@ -1,8 +1,15 @@
 mod common;
 
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use axum::http::{Method, StatusCode};
+use haven_backend::db::pool_monitor::PoolMonitor;
+use haven_backend::db::shadow::ShadowReads;
 use haven_backend::db::Pool;
-use serde_json::json;
+use haven_backend::middleware::LatencyBudgets;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
 use common::TestApp;
@@ -50,6 +57,60 @@ async fn admin_stats_no_auth_returns_401(pool: Pool) {
     assert_eq!(status, StatusCode::UNAUTHORIZED);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_usage_counts_active_users_and_messages(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("usage_admin").await;
+    app.make_admin(user_id).await;
+    let (_, dormant_id) = app.register_user("usage_dormant").await;
+    let (_, lapsed_id) = app.register_user("usage_lapsed").await;
+    let server_id = app.create_server(&token, "Usage").await;
+    let channel_id = app.create_channel(&token, server_id, "general").await;
+    app.send_message(&token, channel_id).await;
+    app.send_message(&token, channel_id).await;
+
+    sqlx::query("UPDATE users SET last_active_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE users SET last_active_at = NOW() - interval '10 days' WHERE id = $1")
+        .bind(lapsed_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE users SET last_active_at = NOW() - interval '40 days' WHERE id = $1")
+        .bind(dormant_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/usage", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["daily_active_users"], 1);
+    assert_eq!(value["monthly_active_users"], 2);
+    assert_eq!(value["messages_last_day"], 2);
+    assert_eq!(value["messages_last_30_days"], 2);
+    assert!(value["total_users"].as_i64().unwrap() >= 3);
+    assert!(value["storage_bytes"].is_number());
+    assert!(value.get("instance_id").is_none());
+
+    // Nothing is reported without TELEMETRY_ENDPOINT
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/telemetry", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"], 0);
+
+    let (member_token, _) = app.register_user("usage_member").await;
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/usage", Some(&member_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
 // ─── Admin Users ──────────────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -354,6 +415,581 @@ async fn admin_cannot_ban_self(pool: Pool) {
     assert_eq!(status, StatusCode::BAD_REQUEST);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_suspension_lapses_and_is_purged(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("admin_suspend").await;
+    app.make_admin(user_id).await;
+    let (target_token, target_id) = app.register_user("suspendme").await;
+    let ban_uri = format!("/api/v1/admin/bans/{}", target_id);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &ban_uri,
+            Some(&token),
+            Some(json!({ "expires_at": "2000-01-01T00:00:00Z" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &ban_uri,
+            Some(&token),
+            Some(json!({ "reason": "cool off", "expires_at": "2999-01-01T00:00:00Z" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Suspend failed: {}", value);
+    assert!(value["expires_at"].as_str().unwrap().starts_with("2999-01-01"));
+    let (status, _) = app.request(Method::GET, "/api/v1/servers", Some(&target_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // A suspension that has passed no longer applies, and the maintenance
+    // job clears it out.
+    let (lapsed_token, lapsed_id) = app.register_user("lapsed").await;
+    sqlx::query(
+        "INSERT INTO instance_bans (user_id, banned_by, expires_at) VALUES ($1, $2, NOW() - INTERVAL '1 minute')",
+    )
+    .bind(lapsed_id)
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let (status, _) = app.request(Method::GET, "/api/v1/servers", Some(&lapsed_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/expired-suspensions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"].as_u64(), Some(1));
+    let (_, bans) = app.request(Method::GET, "/api/v1/admin/bans", Some(&token), None).await;
+    let bans = bans.as_array().unwrap();
+    assert_eq!(bans.len(), 1);
+    assert_eq!(bans[0]["user_id"], target_id.to_string());
+}
+
+// ─── Admin Operator Tools ─────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_lists_servers_with_sizes(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("admin_servers").await;
+    app.make_admin(user_id).await;
+    let server_id = app.create_server(&token, "Sized").await;
+    let channel_id = app.create_channel(&token, server_id, "general").await;
+    app.send_message(&token, channel_id).await;
+    app.send_message(&token, channel_id).await;
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/servers", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let server = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["id"] == server_id.to_string())
+        .expect("server missing from listing");
+    assert_eq!(server["owner_username"], "admin_servers");
+    assert_eq!(server["member_count"].as_i64(), Some(1));
+    assert!(server["channel_count"].as_i64().unwrap() >= 1);
+    assert_eq!(server["message_count"].as_i64(), Some(2));
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["issued"].as_i64(), Some(0));
+    assert!(value["limit"].is_number());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_manages_beta_codes(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("admin_beta").await;
+    app.make_admin(user_id).await;
+    let (user_token, _) = app.register_user("beta_user").await;
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/beta/codes", Some(&user_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/beta/codes",
+            Some(&token),
+            Some(json!({ "count": 3, "label": "meetup" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    let codes = value.as_array().unwrap();
+    assert_eq!(codes.len(), 3);
+    assert!(codes.iter().all(|c| c["label"] == "meetup" && c["status"] == "active"));
+    assert!(codes[0]["email_hash"].is_null());
+    let revoked_id = codes[0]["id"].as_str().unwrap().to_string();
+    let revoked_code = codes[0]["code"].as_str().unwrap().to_string();
+
+    let revoke_uri = format!("/api/v1/admin/beta/codes/{}/revoke", revoked_id);
+    let (status, _) = app.request(Method::POST, &revoke_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::POST, &revoke_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/beta/codes?status=revoked", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value.as_array().unwrap().len(), 1);
+    assert_eq!(value[0]["id"], revoked_id.as_str());
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/beta/codes?status=active", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value.as_array().unwrap().len(), 2);
+
+    // A revoked code no longer registers anyone
+    app.reload_config(|config| config.registration_invite_only = true);
+    let (status, value) = app.try_register("beta_revoked", Some(&revoked_code)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVITE_REVOKED");
+
+    // The cap override wins over BETA_CODE_LIMIT until cleared
+    let (status, value) = app
+        .request(Method::PUT, "/api/v1/admin/beta/limit", Some(&token), Some(json!({ "limit": 5000 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["limit"].as_u64(), Some(5000));
+    assert_eq!(value["limit_overridden"], true);
+    assert_eq!(value["issued"].as_i64(), Some(3));
+    assert_eq!(value["revoked"].as_i64(), Some(1));
+    assert_eq!(value["outstanding"].as_i64(), Some(2));
+    let (_, value) = app
+        .request(Method::PUT, "/api/v1/admin/beta/limit", Some(&token), Some(json!({ "limit": null })))
+        .await;
+    assert_eq!(value["limit_overridden"], false);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn beta_requests_past_the_cap_are_waitlisted_and_promoted(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("admin_waitlist").await;
+    app.make_admin(user_id).await;
+    // Sends fail in the background; codes are issued regardless
+    app.reload_config(|config| {
+        config.smtp_host = "localhost".into();
+        config.smtp_username = "haven".into();
+        config.smtp_from = "beta@haven.test".into();
+    });
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/admin/beta/limit", Some(&token), Some(json!({ "limit": 1 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for email in ["first@example.com", "second@example.com", "second@example.com"] {
+        let (status, value) = app
+            .request(Method::POST, "/api/v1/beta/request-code", None, Some(json!({ "email": email })))
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", value);
+        // The response never reveals whether the request was waitlisted
+        assert_eq!(value["message"], "If slots are available, you'll receive a code shortly.");
+    }
+
+    let (_, stats) = app
+        .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+        .await;
+    assert_eq!(stats["issued"].as_i64(), Some(1));
+    assert_eq!(stats["waitlisted"].as_i64(), Some(1));
+
+    // Raising the cap promotes the waiting email straight away
+    let (status, stats) = app
+        .request(Method::PUT, "/api/v1/admin/beta/limit", Some(&token), Some(json!({ "limit": 2 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stats["issued"].as_i64(), Some(2));
+    assert_eq!(stats["waitlisted"].as_i64(), Some(0));
+
+    let (_, codes) = app
+        .request(Method::GET, "/api/v1/admin/beta/codes", Some(&token), None)
+        .await;
+    assert!(codes.as_array().unwrap().iter().all(|c| c["email_hash"].is_string()));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn beta_requests_from_blocked_domains_are_refused(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("admin_domains").await;
+    app.make_admin(user_id).await;
+    app.reload_config(|config| config.email_provider = "noop".into());
+
+    let request = |email: &'static str| {
+        app.request(Method::POST, "/api/v1/beta/request-code", None, Some(json!({ "email": email })))
+    };
+    let (status, value) = request("drain@Mailinator.com").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "EMAIL_DOMAIN_BLOCKED");
+    let (status, _) = request("drain@inbox.yopmail.com").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Operators extend the bundled list
+    let (status, _) = request("first@spam.example").await;
+    assert_eq!(status, StatusCode::OK);
+    app.reload_config(|config| {
+        config.email_provider = "noop".into();
+        config.beta_blocked_email_domains = "spam.example".into();
+    });
+    let (status, value) = request("second@spam.example").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "EMAIL_DOMAIN_BLOCKED");
+
+    let (_, stats) = app
+        .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+        .await;
+    assert_eq!(stats["issued"].as_i64(), Some(1));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn abuse_scores_are_recorded_and_listed_networks_blocked(pool: Pool) {
+    let mut app = TestApp::new(pool.clone()).await;
+    app.set_trust_proxy(true);
+    let (token, user_id) = app.register_user("admin_abuse").await;
+    app.make_admin(user_id).await;
+
+    let list = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(list.path(), "# hosting ranges\n198.51.100.0/24\n").unwrap();
+    let lists = list.path().to_str().unwrap().to_string();
+    app.reload_config(|config| {
+        config.email_provider = "noop".into();
+        config.abuse_ip_lists = lists.clone();
+    });
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/abuse-lists", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"].as_u64(), Some(1));
+
+    let request = |ip: &'static str, email: &'static str| {
+        let body = serde_json::to_vec(&json!({ "email": email })).unwrap();
+        let headers = [
+            ("content-type", "application/json"),
+            ("x-forwarded-for", ip),
+            ("user-agent", "Mozilla/5.0"),
+            ("accept-language", "en"),
+        ];
+        let app = &app;
+        async move {
+            let (status, _, value) = app
+                .request_with_headers(Method::POST, "/api/v1/beta/request-code", None, &headers, body)
+                .await;
+            (status, value)
+        }
+    };
+    let neighbours = [
+        ("203.0.113.1", "a@haven.test"),
+        ("203.0.113.2", "b@haven.test"),
+        ("203.0.113.3", "c@haven.test"),
+    ];
+    for (ip, email) in neighbours {
+        let (status, _) = request(ip, email).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = request("198.51.100.9", "d@haven.test").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, events) = app
+        .request(Method::GET, "/api/v1/admin/abuse/events", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let events = events.as_array().unwrap();
+    let beta: Vec<&Value> = events.iter().filter(|e| e["kind"] == "beta").collect();
+    assert_eq!(beta.len(), 4);
+    assert_eq!(beta[0]["ip"], "198.51.100.9");
+    assert_eq!(beta[0]["score"].as_i64(), Some(50));
+    assert_eq!(beta[0]["reasons"], json!(["listed"]));
+    // Newest first: the third request from 203.0.113.0/24 saw two before it
+    let scores: Vec<i64> = beta[1..].iter().map(|e| e["score"].as_i64().unwrap()).collect();
+    assert_eq!(scores, vec![30, 15, 0]);
+    assert_eq!(beta[1]["reasons"], json!(["velocity"]));
+    assert_eq!(beta[1]["network"], beta[3]["network"]);
+    assert_ne!(beta[0]["network"], beta[1]["network"]);
+
+    // The admin's own registration is attached to their account
+    let uri = format!("/api/v1/admin/abuse/events?user_id={}", user_id);
+    let (_, mine) = app.request(Method::GET, &uri, Some(&token), None).await;
+    let mine = mine.as_array().unwrap();
+    assert_eq!(mine.len(), 1);
+    assert_eq!(mine[0]["kind"], "register");
+
+    app.reload_config(|config| {
+        config.email_provider = "noop".into();
+        config.abuse_ip_lists = lists.clone();
+        config.abuse_block_score = 50;
+    });
+    let (status, value) = request("198.51.100.10", "e@haven.test").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "ABUSE_SCORE_TOO_HIGH");
+
+    // Raw IPs go once ABUSE_IP_RETENTION_HOURS (24) has passed
+    sqlx::query("UPDATE abuse_events SET created_at = NOW() - interval '2 days'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/abuse-events", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, events) = app
+        .request(Method::GET, "/api/v1/admin/abuse/events", Some(&token), None)
+        .await;
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 6);
+    assert!(events.iter().all(|e| e.get("ip").is_none_or(Value::is_null)));
+    assert!(events.iter().filter(|e| e["kind"] == "beta").all(|e| e["network"].is_string()));
+}
+
+/// A stand-in for the SendGrid API. Answers sends with the status in the
+/// returned cell, recording the bodies of those it accepts.
+async fn fake_sendgrid() -> (String, Arc<AtomicU16>, Arc<Mutex<Vec<Value>>>) {
+    let status = Arc::new(AtomicU16::new(202));
+    let received = Arc::new(Mutex::new(Vec::<Value>::new()));
+    let provider = axum::Router::new().route(
+        "/v3/mail/send",
+        axum::routing::post({
+            let (status, received) = (status.clone(), received.clone());
+            move |axum::Json(body): axum::Json<Value>| async move {
+                let status = StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap();
+                if status.is_success() {
+                    received.lock().unwrap().push(body);
+                }
+                status
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, provider).await });
+    (url, status, received)
+}
+
+fn use_sendgrid(app: &TestApp, url: String) {
+    app.reload_config(|config| {
+        config.email_provider = "sendgrid".into();
+        config.email_api_key = "SG.test".into();
+        config.email_api_url = url;
+        config.smtp_from = "Haven <beta@haven.test>".into();
+    });
+}
+
+/// Poll an admin list until it has entries (sends happen in the background).
+async fn wait_for_entries(app: &TestApp, token: &str, uri: &str) -> Value {
+    for _ in 0..50 {
+        let (_, list) = app.request(Method::GET, uri, Some(token), None).await;
+        if list.as_array().is_some_and(|l| !l.is_empty()) {
+            return list;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} stayed empty", uri);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn queued_emails_are_retried_after_transient_failures(pool: Pool) {
+    let (url, provider_status, received) = fake_sendgrid().await;
+    provider_status.store(503, Ordering::SeqCst);
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("admin_queue").await;
+    app.make_admin(user_id).await;
+    use_sendgrid(&app, url);
+
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/beta/request-code", None, Some(json!({ "email": "slow@example.com" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The first attempt fails; the email stays queued for a later attempt
+    let mut queue = Value::Null;
+    for _ in 0..50 {
+        (_, queue) = app.request(Method::GET, "/api/v1/admin/email/queue", Some(&token), None).await;
+        if queue[0]["attempts"] == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(queue[0]["kind"], "beta_code");
+    assert_eq!(queue[0]["attempts"], 1);
+    assert!(queue[0]["last_error"].as_str().unwrap().contains("503"));
+    assert!(!queue.to_string().contains("slow@example.com"));
+
+    // Once the provider recovers and the backoff has passed, the worker sends it
+    provider_status.store(202, Ordering::SeqCst);
+    sqlx::query("UPDATE email_outbox SET next_attempt_at = NOW()")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/email-outbox", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"], 1);
+    assert_eq!(received.lock().unwrap()[0]["personalizations"][0]["to"][0]["email"], "slow@example.com");
+
+    let (_, queue) = app.request(Method::GET, "/api/v1/admin/email/queue", Some(&token), None).await;
+    assert_eq!(queue.as_array().unwrap().len(), 0);
+    let (_, letters) = app
+        .request(Method::GET, "/api/v1/admin/email/dead-letters", Some(&token), None)
+        .await;
+    assert_eq!(letters.as_array().unwrap().len(), 0);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn failed_emails_are_dead_lettered_and_can_be_retried(pool: Pool) {
+    let (url, provider_status, received) = fake_sendgrid().await;
+    provider_status.store(400, Ordering::SeqCst);
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("admin_mail").await;
+    app.make_admin(user_id).await;
+    use_sendgrid(&app, url);
+
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/beta/request-code", None, Some(json!({ "email": "late@example.com" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A rejected send is permanent, so it is dead-lettered without retries
+    let letters = wait_for_entries(&app, &token, "/api/v1/admin/email/dead-letters").await;
+    let letter = &letters[0];
+    assert_eq!(letter["kind"], "beta_code");
+    assert_eq!(letter["provider"], "sendgrid");
+    assert_eq!(letter["attempts"], 1);
+    assert!(letter["error"].as_str().unwrap().contains("400"));
+    assert!(!letters.to_string().contains("late@example.com"));
+    let (_, queue) = app.request(Method::GET, "/api/v1/admin/email/queue", Some(&token), None).await;
+    assert_eq!(queue.as_array().unwrap().len(), 0);
+
+    let retry_uri = format!("/api/v1/admin/email/dead-letters/{}/retry", letter["id"].as_str().unwrap());
+    let (status, value) = app.request(Method::POST, &retry_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "EMAIL_SEND_FAILED");
+
+    provider_status.store(202, Ordering::SeqCst);
+    let (status, value) = app.request(Method::POST, &retry_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    let sent = received.lock().unwrap().clone();
+    assert_eq!(sent[0]["personalizations"][0]["to"][0]["email"], "late@example.com");
+
+    let (_, letters) = app
+        .request(Method::GET, "/api/v1/admin/email/dead-letters", Some(&token), None)
+        .await;
+    assert_eq!(letters.as_array().unwrap().len(), 0);
+    let (status, _) = app.request(Method::POST, &retry_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_disconnect_and_maintenance_require_privileges(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("admin_ops").await;
+    app.make_admin(user_id).await;
+    let (user_token, target_id) = app.register_user("ops_target").await;
+
+    let disconnect_uri = format!("/api/v1/admin/users/{}/disconnect", target_id);
+    let (status, _) = app
+        .request(Method::POST, &disconnect_uri, Some(&user_token), Some(json!({})))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/refresh-tokens", Some(&user_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Revoking sessions invalidates the target's refresh tokens.
+    let (status, value) = app
+        .request(Method::POST, &disconnect_uri, Some(&token), Some(json!({ "revoke_sessions": true })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Disconnect failed: {}", value);
+    assert_eq!(value["connections_closed"].as_u64(), Some(0));
+    assert_eq!(value["sessions_revoked"].as_bool(), Some(true));
+    let (remaining,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM refresh_tokens WHERE user_id = $1")
+        .bind(target_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/refresh-tokens", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["job"], "refresh-tokens");
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/vacuum-everything", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn partition_job_creates_ahead_and_drops_past_retention(pool: Pool) {
+    let mut app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("partition_admin").await;
+    app.make_admin(user_id).await;
+    let server_id = app.create_server(&token, "Partitions").await;
+    let channel_id = app.create_channel(&token, server_id, "old-news").await;
+    let (old_id, _) = app.send_message(&token, channel_id).await;
+    let (recent_id, _) = app.send_message(&token, channel_id).await;
+    // Move one message into a month long past any retention window
+    sqlx::query("UPDATE messages SET timestamp = '2025-01-15T00:00:00Z' WHERE id = $1")
+        .bind(old_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Without retention the job only creates partitions
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/partitions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"].as_u64(), Some(0));
+    let (status, report) = app.request(Method::GET, "/api/v1/admin/partitions", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(report["missing"].as_array().unwrap().is_empty(), "unexpected report: {}", report);
+    let names = |report: &Value| -> Vec<String> {
+        report["partitions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert!(names(&report).contains(&"messages_y2025m01".to_string()));
+    assert!(names(&report).contains(&"messages_default".to_string()));
+
+    app.set_message_partition_retention(1);
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/partitions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"].as_u64(), Some(1));
+
+    let (_, report) = app.request(Method::GET, "/api/v1/admin/partitions", Some(&token), None).await;
+    assert!(!names(&report).contains(&"messages_y2025m01".to_string()));
+    assert!(report["partitions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|p| p["month"].is_string())
+        .all(|p| p["drop_after"].is_string()));
+    let remaining: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM messages WHERE channel_id = $1")
+        .bind(channel_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, vec![(recent_id,)]);
+}
+
 // ─── Admin Blocked Hashes ─────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -467,3 +1103,847 @@ async fn admin_registration_invite_crud(pool: Pool) {
     assert_eq!(status, StatusCode::OK);
     assert_eq!(value["deleted"].as_bool(), Some(true));
 }
+
+// ─── Instance Staff Roles ─────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn staff_role_assignment_is_audited(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("operator1").await;
+    app.make_admin(user_id).await;
+    let (_, target_id) = app.register_user("support1").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", target_id);
+    let (status, value) = app
+        .request(
+            Method::PUT,
+            &uri,
+            Some(&token),
+            Some(json!({ "role": "support", "reason": "helpdesk rota" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["instance_role"].as_str(), Some("support"));
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/staff", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let staff = value.as_array().unwrap();
+    assert!(staff
+        .iter()
+        .any(|s| s["id"] == json!(target_id) && s["instance_role"] == "support"));
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/audit-log", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let entry = &value.as_array().unwrap()[0];
+    assert_eq!(entry["action"].as_str(), Some("staff_role_update"));
+    assert_eq!(entry["actor_role"].as_str(), Some("operator"));
+    assert_eq!(entry["reason"].as_str(), Some("helpdesk rota"));
+    assert_eq!(entry["details"]["to"].as_str(), Some("support"));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn staff_role_invalid_returns_400(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("operator2").await;
+    app.make_admin(user_id).await;
+    let (_, target_id) = app.register_user("target_bad_role").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", target_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&token), Some(json!({ "role": "owner" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn support_can_view_invites_but_not_delete_users(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (op_token, op_id) = app.register_user("operator3").await;
+    app.make_admin(op_id).await;
+    let (support_token, support_id) = app.register_user("support2").await;
+    let (_, victim_id) = app.register_user("victim1").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", support_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&op_token), Some(json!({ "role": "support" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/registration-invites", Some(&support_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/registration-invites",
+            Some(&support_token),
+            Some(json!({ "count": 1 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let uri = format!("/api/v1/admin/users/{}", victim_id);
+    let (status, _) = app
+        .request(Method::DELETE, &uri, Some(&support_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/audit-log", Some(&support_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn instance_moderator_can_ban_but_not_assign_staff(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (op_token, op_id) = app.register_user("operator4").await;
+    app.make_admin(op_id).await;
+    let (mod_token, mod_id) = app.register_user("imod1").await;
+    let (_, target_id) = app.register_user("bannable1").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", mod_id);
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &uri,
+            Some(&op_token),
+            Some(json!({ "role": "instance_moderator" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/api/v1/admin/bans/{}", target_id);
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&mod_token), Some(json!({ "reason": "spam" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", target_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&mod_token), Some(json!({ "role": "support" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn operator_role_grants_full_access(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (op_token, op_id) = app.register_user("operator5").await;
+    app.make_admin(op_id).await;
+    let (new_op_token, new_op_id) = app.register_user("operator6").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", new_op_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&op_token), Some(json!({ "role": "operator" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/audit-log", Some(&new_op_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Revoking the role also clears the legacy admin flag
+    let uri = format!("/api/v1/admin/users/{}/staff-role", new_op_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&op_token), Some(json!({ "role": null })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/stats", Some(&new_op_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// ─── Support Access ───────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn support_view_requires_reason(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("support_op1").await;
+    app.make_admin(user_id).await;
+    let (target_token, target_id) = app.register_user("support_target1").await;
+
+    let uri = format!("/api/v1/admin/users/{}/support", target_id);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let uri = format!("/api/v1/admin/users/{}/support?reason=short", target_id);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Rejected attempts are not shown to the user
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/users/support-access", Some(&target_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value.as_array().unwrap().is_empty());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn support_view_returns_metadata_and_is_visible_to_user(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (op_token, op_id) = app.register_user("support_op2").await;
+    app.make_admin(op_id).await;
+    let (support_token, support_id) = app.register_user("support_agent").await;
+    let (target_token, target_id) = app.register_user("support_target2").await;
+    let server_id = app.create_server(&target_token, "Target Server").await;
+
+    let uri = format!("/api/v1/admin/users/{}/staff-role", support_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&op_token), Some(json!({ "role": "support" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!(
+        "/api/v1/admin/users/{}/support?reason=Ticket%20123%20cannot%20log%20in",
+        target_id
+    );
+    let (status, value) = app.request(Method::GET, &uri, Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["account"]["username"].as_str(), Some("support_target2"));
+    assert_eq!(value["account"]["totp_enabled"].as_bool(), Some(false));
+    let memberships = value["memberships"].as_array().unwrap();
+    assert!(memberships
+        .iter()
+        .any(|m| m["server_id"] == json!(server_id) && m["is_owner"] == true));
+    assert!(value["devices"].is_array());
+    assert!(value["rate_limits"]["api"]["limit"].as_u64().unwrap() > 0);
+    // Never exposes IPs or server content
+    assert!(value["devices"][0].get("ip_address").is_none());
+    assert!(memberships[0].get("encrypted_meta").is_none());
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/users/support-access", Some(&target_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let entries = value.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["staff_role"].as_str(), Some("support"));
+    assert_eq!(entries[0]["reason"].as_str(), Some("Ticket 123 cannot log in"));
+
+    // The entry is also in the staff audit log
+    let (_, value) = app
+        .request(Method::GET, "/api/v1/admin/audit-log", Some(&op_token), None)
+        .await;
+    assert!(value
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|e| e["action"] == "support_access" && e["target_id"] == json!(target_id)));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn support_view_requires_staff(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("not_staff").await;
+    let (_, target_id) = app.register_user("support_target3").await;
+
+    let uri = format!(
+        "/api/v1/admin/users/{}/support?reason=just%20curious%20about%20this",
+        target_id
+    );
+    let (status, _) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// ─── Latency Budgets ──────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn handler_over_budget_returns_504_and_is_counted(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("budget_user").await;
+
+    // A zero budget aborts any handler that has to wait on the database
+    let budgets = LatencyBudgets::new(Duration::ZERO, Duration::from_secs(600));
+    app.set_latency_budgets(budgets.clone());
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/servers", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(value["status"].as_u64(), Some(504));
+    assert_eq!(value["budget_ms"].as_u64(), Some(0));
+    assert_eq!(budgets.violations(), vec![("GET /api/v1/servers".to_string(), 1)]);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_latency_budgets_report(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("budget_admin").await;
+    app.make_admin(user_id).await;
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/latency-budgets", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["interactive_timeout_ms"].as_u64(), Some(15_000));
+    assert_eq!(value["job_timeout_ms"].as_u64(), Some(600_000));
+    assert!(value["violations"].as_array().unwrap().is_empty());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_db_replicas_report(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("replica_admin").await;
+    app.make_admin(user_id).await;
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/db-replicas", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["strategy"], "round-robin");
+    assert_eq!(value["max_lag_secs"].as_u64(), Some(10));
+    assert!(value["replicas"].as_array().unwrap().is_empty());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_db_pool_report_and_load_shedding(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("pool_admin").await;
+    app.make_admin(user_id).await;
+    let monitor = PoolMonitor::new(Duration::from_millis(100));
+    app.set_db_pool(monitor.clone());
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/db-pool", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["primary"]["max_connections"].as_u64().unwrap() > 0);
+    assert!(value["replicas"].as_array().unwrap().is_empty());
+    assert_eq!(value["breaker"]["open"], false);
+    assert_eq!(value["breaker"]["saturation_ms"].as_u64(), Some(100));
+    // The report request itself is in flight
+    assert_eq!(value["in_flight"].as_u64(), Some(1));
+
+    // A probe that waited past the threshold opens the breaker
+    monitor.observe(Duration::from_millis(250), std::time::Instant::now());
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/servers", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(value["code"], "DB_POOL_SATURATED");
+    assert_eq!(monitor.shed(), 1);
+    assert_eq!(monitor.trips(), 1);
+}
+
+// ─── Background Jobs ─────────────────────────────────────
+
+/// Poll a job until it leaves the queue.
+async fn wait_for_job(app: &TestApp, token: &str, job_id: &str) -> Value {
+    let uri = format!("/api/v1/admin/jobs/{}", job_id);
+    for _ in 0..100 {
+        let (status, job) = app.request(Method::GET, &uri, Some(token), None).await;
+        assert_eq!(status, StatusCode::OK, "Get job failed: {}", job);
+        if job["status"] != "queued" && job["status"] != "running" {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Job {} did not finish", job_id);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn admin_schedules_and_inspects_background_jobs(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("jobs_admin").await;
+    app.make_admin(user_id).await;
+    let (user_token, _) = app.register_user("jobs_user").await;
+
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&user_token), Some(json!({ "job": "expired-invites" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&token), Some(json!({ "job": "reindex-everything" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, job) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&token), Some(json!({ "job": "expired-invites" })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Schedule job failed: {}", job);
+    assert_eq!(job["kind"], "maintenance");
+    assert_eq!(job["payload"]["job"], "expired-invites");
+    assert_eq!(job["created_by"], json!(user_id));
+
+    // The runner picks it up right away
+    let job = wait_for_job(&app, &token, job["id"].as_str().unwrap()).await;
+    assert_eq!(job["status"], "completed", "Job did not complete: {}", job);
+    assert_eq!(job["attempts"].as_i64(), Some(1));
+    assert!(job["finished_at"].is_string());
+
+    let (status, listed) = app
+        .request(Method::GET, "/api/v1/admin/jobs?status=completed&kind=maintenance", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(listed.as_array().unwrap().iter().any(|j| j["id"] == job["id"]));
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/jobs?status=sleeping", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/admin/jobs/{}", Uuid::new_v4()), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn scheduled_job_dedupes_and_can_be_cancelled(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("jobs_canceller").await;
+    app.make_admin(user_id).await;
+
+    let schedule = |run_at: &'static str| json!({ "job": "insights", "run_at": run_at });
+    let (status, later) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&token), Some(schedule("2999-01-01T00:00:00Z")))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Schedule job failed: {}", later);
+    assert_eq!(later["status"], "queued");
+
+    // Scheduling the same job again returns the waiting one, moved up
+    let (status, sooner) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&token), Some(schedule("2998-01-01T00:00:00Z")))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sooner["id"], later["id"]);
+    assert_eq!(sooner["run_at"], "2998-01-01T00:00:00Z");
+
+    let cancel = format!("/api/v1/admin/jobs/{}/cancel", later["id"].as_str().unwrap());
+    let (status, cancelled) = app.request(Method::POST, &cancel, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cancelled["status"], "cancelled");
+    assert_eq!(cancelled["attempts"].as_i64(), Some(0));
+    let (status, _) = app.request(Method::POST, &cancel, Some(&token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // A cancelled job no longer blocks scheduling a new one
+    let (status, fresh) = app
+        .request(Method::POST, "/api/v1/admin/jobs", Some(&token), Some(schedule("2999-01-01T00:00:00Z")))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(fresh["id"], later["id"]);
+}
+
+// ─── Instance Branding ───────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn operator_updates_instance_branding(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (op_token, op_id) = app.register_user("brand_operator").await;
+    app.make_admin(op_id).await;
+    let (mod_token, mod_id) = app.register_user("brand_moderator").await;
+    let uri = format!("/api/v1/admin/users/{}/staff-role", mod_id);
+    app.request(Method::PUT, &uri, Some(&op_token), Some(json!({ "role": "instance_moderator" })))
+        .await;
+
+    // Defaults are public and unauthenticated
+    let (status, value) = app.request(Method::GET, "/api/v1/instance/branding", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["name"], "Haven");
+    assert!(value["logo_url"].is_null());
+
+    let update = json!({
+        "name": "Acme Chat",
+        "accent_color": "#123abc",
+        "terms_url": "https://acme.example/terms",
+    });
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/admin/branding", Some(&mod_token), Some(update.clone()))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/admin/branding", Some(&op_token), Some(json!({ "accent_color": "red" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/admin/branding", Some(&op_token), Some(update))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0];
+    let (status, _) = app
+        .request_bytes(Method::PUT, "/api/v1/admin/branding/logo", Some(&op_token), b"not an image".to_vec())
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .request_bytes(Method::PUT, "/api/v1/admin/branding/logo", Some(&op_token), png)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/instance/branding", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["name"], "Acme Chat");
+    assert_eq!(value["accent_color"], "#123ABC");
+    assert_eq!(value["background_color"], "#F5F0E8");
+    assert_eq!(value["terms_url"], "https://acme.example/terms");
+    assert!(value["privacy_url"].is_null());
+    assert_eq!(value["logo_url"], "/api/v1/instance/branding/logo");
+
+    let (status, _) = app.request(Method::GET, "/api/v1/instance/branding/logo", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Empty string clears a URL; removing the logo hides it again
+    let (status, value) = app
+        .request(Method::PUT, "/api/v1/admin/branding", Some(&op_token), Some(json!({ "terms_url": "" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["terms_url"].is_null());
+    let (status, _) = app.request(Method::DELETE, "/api/v1/admin/branding/logo", Some(&op_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::GET, "/api/v1/instance/branding/logo", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ─── Shadow Reads ────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn shadow_reads_match_primary_queries(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    app.set_shadow_reads(ShadowReads::new(1.0));
+    let (owner, owner_id) = app.register_user("shadow_owner").await;
+    let (member, member_id) = app.register_user("shadow_member").await;
+    app.make_admin(owner_id).await;
+    let server_id = app.create_server(&owner, "Shadow").await;
+    let channel_id = app.create_channel(&owner, server_id, "general").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+
+    let (_, role) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/roles", server_id),
+            Some(&owner),
+            Some(json!({ "name": "Uploader", "position": 1 })),
+        )
+        .await;
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/api/v1/servers/{}/members/{}/roles", server_id, member_id),
+            Some(&owner),
+            Some(json!({ "role_id": role["id"] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for _ in 0..3 {
+        app.send_message(&owner, channel_id).await;
+    }
+    let (status, page) = app
+        .request(Method::GET, &format!("/api/v1/channels/{}/messages?limit=2", channel_id), Some(&member), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let oldest = page[1]["timestamp"].as_str().unwrap().to_string();
+    let (status, _) = app
+        .request(
+            Method::GET,
+            &format!("/api/v1/channels/{}/messages?before={}", channel_id, urlencoding::encode(&oldest)),
+            Some(&member),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Opening an upload session resolves channel permissions
+    for token in [&owner, &member] {
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &format!("/api/v1/channels/{}/attachments", channel_id),
+                Some(token),
+                Some(json!({ "upload_length": 4, "content_type": "text/plain" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    // Candidates run in the background; wait for all four to land
+    let mut report = json!(null);
+    for _ in 0..50 {
+        let (status, value) = app
+            .request(Method::GET, "/api/v1/admin/shadow-reads", Some(&owner), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        report = value;
+        let done: u64 = report["queries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|q| q["runs"].as_u64().unwrap() + q["errors"].as_u64().unwrap())
+            .sum();
+        if done >= 4 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(report["sample_rate"].as_f64(), Some(1.0));
+    let queries = report["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 2, "unexpected report: {}", report);
+    for q in queries {
+        assert_eq!(q["runs"].as_u64(), Some(2), "unexpected report: {}", report);
+        assert_eq!(q["mismatches"].as_u64(), Some(0), "unexpected report: {}", report);
+        assert_eq!(q["errors"].as_u64(), Some(0), "unexpected report: {}", report);
+    }
+}
+
+// ─── Server Quotas ───────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn server_quota_overrides_are_enforced(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (admin, admin_id) = app.register_user("quota_admin").await;
+    app.make_admin(admin_id).await;
+    let (owner, _) = app.register_user("quota_owner").await;
+    let (joiner, _) = app.register_user("quota_joiner").await;
+    let (late, _) = app.register_user("quota_late").await;
+    let server_id = app.create_server(&owner, "Quota Server").await;
+    let channel_id = app.create_channel(&owner, server_id, "general").await;
+
+    // Instance defaults in tests are unlimited
+    let uri = format!("/api/v1/admin/servers/{}/quotas", server_id);
+    let (status, value) = app.request(Method::GET, &uri, Some(&admin), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["limits"]["max_members"], 0);
+    assert_eq!(value["overrides"]["max_members"], json!(null));
+    assert_eq!(value["usage"]["members"], 1);
+
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&owner), Some(json!({ "max_members": 2 })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&admin), Some(json!({ "max_members": -1 })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, value) = app
+        .request(
+            Method::PUT,
+            &uri,
+            Some(&admin),
+            Some(json!({
+                "max_members": 2,
+                "max_messages_per_minute": 2,
+                "max_storage_bytes": 1000,
+                "reason": "noisy server",
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert_eq!(value["limits"]["max_members"], 2);
+    assert_eq!(value["limits"]["max_storage_bytes"], 1000);
+
+    // Members: the second join fills the server, the third is refused
+    app.invite_and_join(&owner, &joiner, server_id).await;
+    let (status, inv) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/invites", server_id),
+            Some(&owner),
+            Some(json!({ "expires_in_hours": 24 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let join_uri = format!("/api/v1/invites/{}/join", inv["code"].as_str().unwrap());
+    let (status, value) = app.request(Method::POST, &join_uri, Some(&late), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(value["error"].as_str().unwrap().contains("member limit"), "{}", value);
+
+    // Message rate: shared by everyone in the server
+    app.send_message(&owner, channel_id).await;
+    app.send_message(&joiner, channel_id).await;
+    let body = json!({
+        "channel_id": channel_id,
+        "sender_token": "dGVzdA==",
+        "encrypted_body": "dGVzdA==",
+        "has_attachments": false,
+    });
+    let (status, value) = app
+        .request(Method::POST, &format!("/api/v1/channels/{}/messages", channel_id), Some(&owner), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(value["error"].as_str().unwrap().contains("2 messages per minute"), "{}", value);
+
+    // Storage: in-progress uploads count against the quota
+    let attach_uri = format!("/api/v1/channels/{}/attachments", channel_id);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &attach_uri,
+            Some(&owner),
+            Some(json!({ "upload_length": 600, "content_type": "text/plain" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &attach_uri,
+            Some(&owner),
+            Some(json!({ "upload_length": 600, "content_type": "text/plain" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(value["error"].as_str().unwrap().contains("storage quota"), "{}", value);
+
+    // Clearing the overrides restores the (unlimited) defaults
+    let (status, value) = app.request(Method::PUT, &uri, Some(&admin), Some(json!({}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["limits"]["max_members"], 0);
+    assert_eq!(value["usage"]["storage_bytes"], 600);
+    let (status, _) = app.request(Method::POST, &join_uri, Some(&late), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn config_reload_applies_reloadable_settings(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("reload_admin").await;
+    app.make_admin(user_id).await;
+    let (user_token, _) = app.register_user("reload_user").await;
+
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/config/reload", Some(&user_token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let changed = app.reload_config(|config| {
+        config.beta_code_limit = 7;
+        config.registration_invite_only = true;
+        config.rate_limit_per_user = 3;
+        // Needs a restart, so it's ignored
+        config.port = 1;
+    });
+    assert_eq!(changed, vec!["rate_limit_per_user", "beta_code_limit", "registration_invite_only"]);
+
+    let (_, value) = app
+        .request(Method::GET, "/api/v1/auth/invite-required", None, None)
+        .await;
+    assert_eq!(value["invite_required"].as_bool(), Some(true));
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["limit"].as_u64(), Some(7));
+
+    // The new per-user bucket (3 per minute) is already in force
+    for _ in 0..2 {
+        let (status, _) = app
+            .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/beta-stats", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn legal_holds_block_deletion_until_released(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (admin, admin_id) = app.register_user("hold_admin").await;
+    app.make_admin(admin_id).await;
+    let (owner, owner_id) = app.register_user("hold_owner").await;
+    let server_id = app.create_server(&owner, "Held").await;
+    let channel_id = app.create_channel(&owner, server_id, "held-ch").await;
+    let (first, _) = app.send_message(&owner, channel_id).await;
+    app.send_message(&owner, channel_id).await;
+
+    let place = |subject_type: &str, subject_id: Uuid| {
+        json!({ "subject_type": subject_type, "subject_id": subject_id, "reason": "case 42" })
+    };
+    let (status, hold) = app
+        .request(Method::POST, "/api/v1/admin/legal-holds", Some(&admin), Some(place("server", server_id)))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/legal-holds", Some(&admin), Some(place("server", server_id)))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/admin/legal-holds", Some(&owner), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Deleting held messages, channels or the server is refused
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/channels/{}/messages/bulk-delete", channel_id),
+            Some(&owner),
+            Some(json!({ "message_ids": [first] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "LEGAL_HOLD");
+    let (status, _) = app
+        .request(Method::DELETE, &format!("/api/v1/channels/{}", channel_id), Some(&owner), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app
+        .request(
+            Method::DELETE,
+            &format!("/api/v1/servers/{}", server_id),
+            Some(&owner),
+            Some(json!({ "password": "testpassword123" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "LEGAL_HOLD");
+
+    // Retention sweeps skip held messages
+    let retention = format!("/api/v1/channels/{}/retention", channel_id);
+    app.request(Method::PUT, &retention, Some(&owner), Some(json!({ "mode": "messages", "value": 1 })))
+        .await;
+    let job = "/api/v1/admin/maintenance/channel-retention";
+    let (_, value) = app.request(Method::POST, job, Some(&admin), None).await;
+    assert_eq!(value["affected"], 0);
+
+    // Owning a held server also blocks account erasure
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/auth/delete-account",
+            Some(&owner),
+            Some(json!({ "password": "testpassword123" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "LEGAL_HOLD");
+    let (status, _) = app
+        .request(Method::DELETE, &format!("/api/v1/admin/users/{}", owner_id), Some(&admin), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Releasing the hold lets the sweep through and keeps the trail
+    let release = format!("/api/v1/admin/legal-holds/{}/release", hold["id"].as_str().unwrap());
+    let body = json!({ "reason": "case closed" });
+    let (status, released) = app.request(Method::POST, &release, Some(&admin), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(released["released_by"], json!(admin_id));
+    let (status, _) = app.request(Method::POST, &release, Some(&admin), Some(body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, value) = app.request(Method::POST, job, Some(&admin), None).await;
+    assert_eq!(value["affected"], 1);
+
+    let (_, active) = app.request(Method::GET, "/api/v1/admin/legal-holds", Some(&admin), None).await;
+    assert!(active.as_array().unwrap().is_empty());
+    let (_, all) = app
+        .request(Method::GET, "/api/v1/admin/legal-holds?include_released=true", Some(&admin), None)
+        .await;
+    assert_eq!(all.as_array().unwrap().len(), 1);
+    let (_, log) = app.request(Method::GET, "/api/v1/admin/audit-log", Some(&admin), None).await;
+    let actions: Vec<&str> = log.as_array().unwrap().iter().filter_map(|e| e["action"].as_str()).collect();
+    assert!(actions.contains(&"legal_hold_place") && actions.contains(&"legal_hold_release"));
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use haven_backend::db::Pool;
+use haven_backend::models::WsServerMessage;
+use serde_json::json;
+use uuid::Uuid;
+
+use common::TestApp;
+
+/// Messages the Haven system user has sent to `user_id` in their DM.
+async fn system_dm_count(pool: &Pool, user_id: Uuid) -> i64 {
+    let (count,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*) FROM messages m
+        INNER JOIN channels c ON c.id = m.channel_id AND c.channel_type = 'dm'
+        INNER JOIN channel_members cm ON cm.channel_id = c.id AND cm.user_id = $1
+        INNER JOIN users s ON s.id = m.sender_id AND s.is_system = TRUE
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    count
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn announcement_is_published_dmed_and_dismissed(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (admin_token, admin_id) = app.register_user("announcer").await;
+    app.make_admin(admin_id).await;
+    let (user_token, user_id) = app.register_user("listener").await;
+    let dms_before = system_dm_count(&pool, user_id).await;
+
+    let (status, created) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/announcements",
+            Some(&admin_token),
+            Some(json!({
+                "title": "Maintenance tonight",
+                "body": "Haven will be down for 10 minutes at 02:00 UTC.",
+                "kind": "maintenance",
+                "send_dm": true
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create announcement failed: {}", created);
+    assert!(created["published_at"].is_string());
+
+    let (status, active) = app.request(Method::GET, "/api/v1/announcements", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let active = active.as_array().unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0]["id"], created["id"]);
+    assert_eq!(active[0]["kind"], "maintenance");
+
+    // DMs go out in the background.
+    let mut dms = dms_before;
+    for _ in 0..50 {
+        dms = system_dm_count(&pool, user_id).await;
+        if dms > dms_before {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(dms, dms_before + 1);
+
+    let dismiss = format!("/api/v1/announcements/{}/dismiss", created["id"].as_str().unwrap());
+    let (status, _) = app.request(Method::POST, &dismiss, Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::POST, &dismiss, Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, active) = app.request(Method::GET, "/api/v1/announcements", Some(&user_token), None).await;
+    assert!(active.as_array().unwrap().is_empty());
+
+    let (status, listed) = app
+        .request(Method::GET, "/api/v1/admin/announcements", Some(&admin_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed[0]["dismissal_count"].as_i64(), Some(1));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn scheduled_announcement_waits_and_requires_privileges(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (admin_token, admin_id) = app.register_user("scheduler").await;
+    app.make_admin(admin_id).await;
+    let (user_token, _) = app.register_user("waiter").await;
+
+    let request = json!({
+        "title": "v2.0 is coming",
+        "body": "New release next week.",
+        "kind": "release",
+        "publish_at": "2999-01-01T00:00:00Z"
+    });
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/admin/announcements", Some(&user_token), Some(request.clone()))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, created) = app
+        .request(Method::POST, "/api/v1/admin/announcements", Some(&admin_token), Some(request))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create announcement failed: {}", created);
+    assert!(created["published_at"].is_null());
+
+    // Not visible or dismissable until it is published.
+    let (_, active) = app.request(Method::GET, "/api/v1/announcements", Some(&user_token), None).await;
+    assert!(active.as_array().unwrap().is_empty());
+    let id = created["id"].as_str().unwrap();
+    let (status, _) = app
+        .request(Method::POST, &format!("/api/v1/announcements/{}/dismiss", id), Some(&user_token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/announcements",
+            Some(&admin_token),
+            Some(json!({ "title": "Bad", "body": "kind", "kind": "urgent" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Cancelling a scheduled announcement removes it.
+    let uri = format!("/api/v1/admin/announcements/{}", id);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn published_announcement_is_broadcast_through_the_outbox(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (admin_token, admin_id) = app.register_user("outbox_announcer").await;
+    app.make_admin(admin_id).await;
+    let (_, user_id) = app.register_user("outbox_listener").await;
+    let mut rx = app.connect_user(user_id);
+
+    let (status, created) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/announcements",
+            Some(&admin_token),
+            Some(json!({ "title": "Upgrade done", "body": "Haven is on v2.", "kind": "release" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create announcement failed: {}", created);
+
+    // The dispatcher publishes the staged event once the claim commits
+    let event = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(WsServerMessage::Announcement(announcement)) = rx.recv().await {
+                return announcement;
+            }
+        }
+    })
+    .await
+    .expect("Announcement event was not delivered");
+    assert_eq!(json!(event.id), created["id"]);
+
+    // ...and then marks it sent
+    let mut pending: i64 = 1;
+    for _ in 0..50 {
+        (pending,) = sqlx::query_as("SELECT COUNT(*) FROM ws_outbox WHERE sent_at IS NULL")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        if pending == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(pending, 0);
+}
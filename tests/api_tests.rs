@@ -41,11 +41,49 @@ async fn login_with_wrong_password_returns_401(pool: Pool) {
     app.register_user("carol").await;
 
     let body = json!({ "username": "carol", "password": "wrongpassword" });
-    let (status, _) = app
+    let (status, value) = app
         .request(Method::POST, "/api/v1/auth/login", None, Some(body))
         .await;
 
     assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(value["code"], "INVALID_CREDENTIALS");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn register_validation_error_lists_invalid_fields(pool: Pool) {
+    let app = TestApp::new(pool).await;
+
+    let body = json!({
+        "username": "ab",
+        "password": "short",
+        "identity_key": "",
+        "signed_prekey": "",
+        "signed_prekey_signature": "",
+        "one_time_prekeys": [],
+        "pow_challenge": "",
+        "pow_nonce": ""
+    });
+    let (status, headers, value) = app
+        .request_with_headers(
+            Method::POST,
+            "/api/v1/auth/register",
+            None,
+            &[("content-type", "application/json"), ("x-request-id", "bug-report-42")],
+            serde_json::to_vec(&body).unwrap(),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "VALIDATION_FAILED");
+    let fields: Vec<&str> = value["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["field"].as_str().unwrap())
+        .collect();
+    assert!(fields.contains(&"username") && fields.contains(&"password"), "{}", value);
+    assert_eq!(value["trace_id"], "bug-report-42");
+    assert_eq!(headers["x-request-id"], "bug-report-42");
 }
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -608,6 +646,26 @@ async fn health_check_returns_ok(pool: Pool) {
     assert_eq!(value.as_str(), Some("ok"));
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn readiness_reports_component_status(pool: Pool) {
+    let app = TestApp::new(pool).await;
+
+    let (status, value) = app.request(Method::GET, "/healthz", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["components"]["database_primary"]["status"], "ok");
+    assert!(value["components"].get("storage").is_none());
+
+    let (status, value) = app.request(Method::GET, "/readyz", None, None).await;
+    assert_eq!(status, StatusCode::OK, "readyz: {}", value);
+    assert_eq!(value["status"], "ok");
+    assert_eq!(value["components"]["database_primary"]["status"], "ok");
+    assert_eq!(value["components"]["database_replica"]["status"], "disabled");
+    assert_eq!(value["components"]["redis"]["status"], "ok");
+    assert_eq!(value["components"]["storage"]["status"], "ok");
+    assert_eq!(value["components"]["smtp"]["status"], "disabled");
+}
+
 // ─── Auth Extended ──────────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -1036,7 +1094,7 @@ async fn cannot_block_self(pool: Pool) {
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
-async fn blocked_user_cannot_send_friend_request(pool: Pool) {
+async fn blocked_user_friend_request_is_silently_dropped(pool: Pool) {
     let app = TestApp::new(pool).await;
     let (token_a, _) = app.register_user("block_fr_a").await;
     let (token_b, user_b) = app.register_user("block_fr_b").await;
@@ -1045,8 +1103,8 @@ async fn blocked_user_cannot_send_friend_request(pool: Pool) {
     let block_uri = format!("/api/v1/users/{}/block", user_b);
     app.request(Method::POST, &block_uri, Some(&token_a), None).await;
 
-    // B tries to friend A
-    let (status, _) = app
+    // B tries to friend A: looks accepted, but A never sees it
+    let (status, value) = app
         .request(
             Method::POST,
             "/api/v1/friends/request",
@@ -1054,7 +1112,64 @@ async fn blocked_user_cannot_send_friend_request(pool: Pool) {
             Some(json!({ "username": "block_fr_a" })),
         )
         .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["status"], "pending");
+
+    let (_, value) = app.request(Method::GET, "/api/v1/friends", Some(&token_a), None).await;
+    assert!(!value.as_array().unwrap().iter().any(|f| f["user_id"] == user_b.to_string()));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn blocked_user_cannot_open_or_use_dm(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("block_dm_a").await;
+    let (token_b, user_b) = app.register_user("block_dm_b").await;
+    let dm_id = app.create_dm(&token_b, user_a).await;
+
+    let block_uri = format!("/api/v1/users/{}/block", user_b);
+    app.request(Method::POST, &block_uri, Some(&token_a), None).await;
+
+    let body = json!({ "target_user_id": user_a, "encrypted_meta": B64.encode(b"dm-meta") });
+    let (status, _) = app.request(Method::POST, "/api/v1/dm", Some(&token_b), Some(body)).await;
     assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let body = json!({
+        "channel_id": dm_id,
+        "sender_token": B64.encode(b"t"),
+        "encrypted_body": B64.encode(b"b"),
+        "has_attachments": false
+    });
+    let uri = format!("/api/v1/channels/{}/messages", dm_id);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token_b), Some(body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The blocker can still write into the DM
+    app.send_message(&token_a, dm_id).await;
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn messages_from_blocked_users_are_flagged(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("block_msg_a").await;
+    let (token_b, user_b) = app.register_user("block_msg_b").await;
+    let server_id = app.create_server(&token_a, "Block Flags").await;
+    let channel_id = app.create_channel(&token_a, server_id, "general").await;
+    app.invite_and_join(&token_a, &token_b, server_id).await;
+
+    let block_uri = format!("/api/v1/users/{}/block", user_b);
+    app.request(Method::POST, &block_uri, Some(&token_a), None).await;
+    let (msg_id, _) = app.send_message(&token_b, channel_id).await;
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    let msg = value.as_array().unwrap().iter().find(|m| m["id"] == msg_id.to_string()).unwrap();
+    assert_eq!(msg["from_blocked"], true);
+
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_b), None).await;
+    let msg = value.as_array().unwrap().iter().find(|m| m["id"] == msg_id.to_string()).unwrap();
+    assert!(msg.get("from_blocked").is_none());
 }
 
 // ─── DMs ────────────────────────────────────────────────
@@ -1084,14 +1199,14 @@ async fn create_and_list_dm(pool: Pool) {
         .request(Method::GET, "/api/v1/dm", Some(&token_a), None)
         .await;
     assert_eq!(status, StatusCode::OK);
-    assert!(value.as_array().unwrap().len() >= 1);
+    assert!(!value.as_array().unwrap().is_empty());
 
     // User B should also see the DM (may also include auto-created Haven DM)
     let (status, value) = app
         .request(Method::GET, "/api/v1/dm", Some(&token_b), None)
         .await;
     assert_eq!(status, StatusCode::OK);
-    assert!(value.as_array().unwrap().len() >= 1);
+    assert!(!value.as_array().unwrap().is_empty());
 }
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -1156,7 +1271,7 @@ async fn list_channel_members(pool: Pool) {
     let (status, value) = app.request(Method::GET, &uri, Some(&token), None).await;
     assert_eq!(status, StatusCode::OK);
     let members = value.as_array().unwrap();
-    assert!(members.len() >= 1);
+    assert!(!members.is_empty());
 }
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -1329,6 +1444,158 @@ async fn unassign_role_from_member(pool: Pool) {
     assert_eq!(status, StatusCode::OK);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn role_positions_and_bulk_member_roles(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("bulk_owner").await;
+    let (token_mod, mod_id) = app.register_user("bulk_mod").await;
+    let (token_bob, bob_id) = app.register_user("bulk_bob").await;
+    let server_id = app.create_server(&token_owner, "Bulk Roles").await;
+    app.invite_and_join(&token_owner, &token_mod, server_id).await;
+    app.invite_and_join(&token_owner, &token_bob, server_id).await;
+
+    let roles_uri = format!("/api/v1/servers/{}/roles", server_id);
+    let create = |name: &'static str, position: i32, permissions: &'static str| {
+        let body = json!({ "name": name, "position": position, "permissions": permissions });
+        let (app, uri, token) = (&app, &roles_uri, token_owner.clone());
+        async move {
+            let (_, role) = app.request(Method::POST, uri, Some(&token), Some(body)).await;
+            role["id"].as_str().unwrap().to_string()
+        }
+    };
+    let a = create("A", 1, "0").await;
+    let b = create("B", 2, "0").await;
+    let moderator = create("Mod", 3, "4").await;
+
+    let member_roles = |user_id: Uuid| format!("/api/v1/servers/{}/members/{}/roles", server_id, user_id);
+    let (status, _) = app
+        .request(Method::PUT, &member_roles(mod_id), Some(&token_owner), Some(json!({ "role_ids": [&moderator] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The moderator may reorder roles below theirs, not move their own
+    let uri = format!("/api/v1/servers/{}/roles/positions", server_id);
+    let (status, _) = app
+        .request(Method::PATCH, &uri, Some(&token_mod), Some(json!({ "roles": [&moderator, &a, &b] })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app
+        .request(Method::PATCH, &uri, Some(&token_mod), Some(json!({ "roles": [&b, &a, &moderator] })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Reorder failed: {}", value);
+    assert_eq!(value, json!([{ "id": b, "position": 1 }, { "id": a, "position": 2 }, { "id": moderator, "position": 3 }]));
+    let (status, value) = app
+        .request(Method::PATCH, &uri, Some(&token_owner), Some(json!({ "roles": [&a, &moderator] })))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "ROLE_LAYOUT_STALE");
+    let (status, _) = app
+        .request(Method::PATCH, &uri, Some(&token_bob), Some(json!({ "roles": [&a, &b, &moderator] })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Bulk replacement adds and removes in one change
+    let (status, value) = app
+        .request(Method::PUT, &member_roles(bob_id), Some(&token_mod), Some(json!({ "role_ids": [&a, &b] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["role_ids"].as_array().unwrap().len(), 2);
+    let (status, _) = app
+        .request(Method::PUT, &member_roles(bob_id), Some(&token_mod), Some(json!({ "role_ids": [&a] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(Method::PUT, &member_roles(bob_id), Some(&token_mod), Some(json!({ "role_ids": [&a, &moderator] })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let audit = format!("/api/v1/servers/{}/audit-log", server_id);
+    let (_, entries) = app.request(Method::GET, &audit, Some(&token_owner), None).await;
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.iter().filter(|e| e["action"] == "role_reorder").count(), 1);
+    let update = entries
+        .iter()
+        .find(|e| e["action"] == "member_roles_update" && e["target_id"] == bob_id.to_string() && e["changes"]["added"] == json!([]))
+        .unwrap();
+    assert_eq!(update["changes"]["removed"][0]["role_name"], "B");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn permission_templates_seed_new_roles_and_channels(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("tpl_owner").await;
+    let (token_bob, _) = app.register_user("tpl_bob").await;
+    let server_id = app.create_server(&token_owner, "Templates").await;
+    app.invite_and_join(&token_owner, &token_bob, server_id).await;
+
+    // New servers start with the built-in presets
+    let uri = format!("/api/v1/servers/{}/permission-templates", server_id);
+    let (status, value) = app.request(Method::GET, &uri, Some(&token_bob), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = value.as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Moderator", "Read-only"]);
+    let read_only = value[1]["id"].as_str().unwrap().to_string();
+    assert_eq!(value[1]["deny_bits"], "268445952");
+
+    let body = json!({ "name": "Helper", "allow_bits": "64" });
+    let (status, _) = app.request(Method::POST, &uri, Some(&token_bob), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, helper) = app.request(Method::POST, &uri, Some(&token_owner), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "Create template failed: {}", helper);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token_owner), Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token_owner), Some(json!({ "name": "Odd", "allow_bits": "256", "deny_bits": "256" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let helper_uri = format!("{}/{}", uri, helper["id"].as_str().unwrap());
+    let (status, helper) = app
+        .request(Method::PATCH, &helper_uri, Some(&token_owner), Some(json!({ "allow_bits": "8256" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(helper["allow_bits"], "8256");
+    assert_eq!(helper["name"], "Helper");
+
+    // A role takes the template's allow bits
+    let roles_uri = format!("/api/v1/servers/{}/roles", server_id);
+    let (status, role) = app
+        .request(Method::POST, &roles_uri, Some(&token_owner), Some(json!({ "name": "Helpers", "template_id": helper["id"] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(role["permissions"], "8256");
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &roles_uri,
+            Some(&token_owner),
+            Some(json!({ "name": "Both", "permissions": "0", "template_id": helper["id"] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A channel gets the template as its @everyone overwrite
+    let channels_uri = format!("/api/v1/servers/{}/channels", server_id);
+    let body = json!({ "encrypted_meta": "YW5ub3VuY2VtZW50cw==", "template_id": read_only });
+    let (status, channel) = app.request(Method::POST, &channels_uri, Some(&token_owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK, "Create channel failed: {}", channel);
+    let ow_uri = format!("/api/v1/channels/{}/overwrites", channel["id"].as_str().unwrap());
+    let (_, overwrites) = app.request(Method::GET, &ow_uri, Some(&token_owner), None).await;
+    assert_eq!(overwrites.as_array().unwrap().len(), 1);
+    assert_eq!(overwrites[0]["deny_bits"], "268445952");
+    let body = json!({ "encrypted_meta": "eA==", "template_id": Uuid::new_v4() });
+    let (status, _) = app.request(Method::POST, &channels_uri, Some(&token_owner), Some(body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Deleting a template leaves what was created from it
+    let (status, _) = app.request(Method::DELETE, &helper_uri, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, roles) = app.request(Method::GET, &roles_uri, Some(&token_owner), None).await;
+    assert!(roles.as_array().unwrap().iter().any(|r| r["name"] == "Helpers" && r["permissions"] == "8256"));
+    let (status, _) = app.request(Method::DELETE, &helper_uri, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
 // ─── Categories Extended ────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -1510,6 +1777,148 @@ async fn list_server_members(pool: Pool) {
     assert_eq!(members.len(), 2);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn search_server_members_by_name_and_role(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("search_owner").await;
+    let (token_alice, alice_id) = app.register_user("search_alice").await;
+    let (token_bob, _) = app.register_user("search_bob").await;
+    let (token_outsider, _) = app.register_user("search_outsider").await;
+    let server_id = app.create_server(&token_owner, "Member Search").await;
+    app.invite_and_join(&token_owner, &token_alice, server_id).await;
+    app.invite_and_join(&token_owner, &token_bob, server_id).await;
+
+    let nick_uri = format!("/api/v1/servers/{}/nickname", server_id);
+    let (status, _) = app
+        .request(Method::PUT, &nick_uri, Some(&token_bob), Some(json!({ "nickname": "Zephyr" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let search = |q: &str| format!("/api/v1/servers/{}/members/search?q={}", server_id, q);
+
+    // Substring match on username, case-insensitive
+    let (status, value) = app.request(Method::GET, &search("ALICE"), Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let members = value.as_array().unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["username"], "search_alice");
+
+    // Matches nicknames too
+    let (_, value) = app.request(Method::GET, &search("ephy"), Some(&token_owner), None).await;
+    assert_eq!(value.as_array().unwrap().len(), 1);
+    assert_eq!(value[0]["nickname"], "Zephyr");
+
+    // LIKE wildcards are literal
+    let (_, value) = app.request(Method::GET, &search("%25"), Some(&token_owner), None).await;
+    assert!(value.as_array().unwrap().is_empty());
+
+    // Pagination
+    let (_, value) = app.request(Method::GET, &format!("{}&limit=2", search("search_")), Some(&token_owner), None).await;
+    assert_eq!(value.as_array().unwrap().len(), 2);
+    let (_, value) = app.request(Method::GET, &format!("{}&limit=2&offset=2", search("search_")), Some(&token_owner), None).await;
+    assert_eq!(value.as_array().unwrap().len(), 1);
+
+    // Role filter
+    let roles_uri = format!("/api/v1/servers/{}/roles", server_id);
+    let (_, role_val) = app
+        .request(Method::POST, &roles_uri, Some(&token_owner), Some(json!({ "name": "Searchable", "position": 1 })))
+        .await;
+    let role_id = role_val["id"].as_str().unwrap();
+    let assign_uri = format!("/api/v1/servers/{}/members/{}/roles", server_id, alice_id);
+    let (status, _) = app
+        .request(Method::PUT, &assign_uri, Some(&token_owner), Some(json!({ "role_id": role_id })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app
+        .request(Method::GET, &format!("{}&role_id={}", search("search_"), role_id), Some(&token_owner), None)
+        .await;
+    let members = value.as_array().unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["user_id"], alice_id.to_string());
+
+    // Empty query is rejected; non-members are forbidden
+    let (status, _) = app.request(Method::GET, &search(""), Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app.request(Method::GET, &search("alice"), Some(&token_outsider), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn server_lists_support_if_none_match(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("etag_owner").await;
+    let (token_member, _) = app.register_user("etag_member").await;
+    let server_id = app.create_server(&token_owner, "ETag Test").await;
+
+    app.invite_and_join(&token_owner, &token_member, server_id).await;
+
+    for list in ["channels", "roles", "members"] {
+        let uri = format!("/api/v1/servers/{}/{}", server_id, list);
+        let (status, headers, _) = app
+            .request_with_headers(Method::GET, &uri, Some(&token_owner), &[], vec![])
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", list);
+        let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+
+        // Unchanged: 304 with no body
+        let (status, headers, value) = app
+            .request_with_headers(Method::GET, &uri, Some(&token_owner), &[("if-none-match", &etag)], vec![])
+            .await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED, "{}", list);
+        assert_eq!(headers.get("etag").unwrap().to_str().unwrap(), etag);
+        assert!(value.is_null());
+
+        // The tag is per user
+        let (status, _, _) = app
+            .request_with_headers(Method::GET, &uri, Some(&token_member), &[("if-none-match", &etag)], vec![])
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", list);
+    }
+
+    // Member, channel and role mutations all bump the version
+    let uri = format!("/api/v1/servers/{}/members", server_id);
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[], vec![])
+        .await;
+    let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+    let nick_uri = format!("/api/v1/servers/{}/nickname", server_id);
+    let (status, _) = app
+        .request(Method::PUT, &nick_uri, Some(&token_member), Some(json!({ "nickname": "Tagged" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, value) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[("if-none-match", &etag)], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value.as_array().unwrap().iter().any(|m| m["nickname"] == "Tagged"));
+
+    let uri = format!("/api/v1/servers/{}/channels", server_id);
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[], vec![])
+        .await;
+    let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+    app.create_channel(&token_owner, server_id, "new-channel").await;
+    let (status, _, value) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[("if-none-match", &etag)], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!value.as_array().unwrap().is_empty());
+
+    let uri = format!("/api/v1/servers/{}/roles", server_id);
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[], vec![])
+        .await;
+    let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token_owner), Some(json!({ "name": "Tagged", "position": 1 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_owner), &[("if-none-match", &etag)], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
 async fn kick_member(pool: Pool) {
@@ -1535,6 +1944,57 @@ async fn kick_member(pool: Pool) {
     assert_eq!(value.as_array().unwrap().len(), 1);
 }
 
+// ─── Initial Sync ───────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn sync_returns_all_sections_and_skips_known_servers(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, owner_id) = app.register_user("sync_owner").await;
+    let (token_friend, friend_id) = app.register_user("sync_friend").await;
+    let server_id = app.create_server(&token_owner, "Sync Test").await;
+    let channel_id = app.create_channel(&token_owner, server_id, "sync-channel").await;
+    app.send_message(&token_owner, channel_id).await;
+
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/friends/request", Some(&token_owner), Some(json!({ "username": "sync_friend" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/sync", Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let servers = value["servers"].as_array().unwrap();
+    let server = servers.iter().find(|s| s["id"] == server_id.to_string()).unwrap();
+    assert!(server["my_permissions"].is_string());
+    assert!(server["channels"].as_array().unwrap().iter().any(|c| c["id"] == channel_id.to_string()));
+    assert!(!server["roles"].as_array().unwrap().is_empty());
+    assert!(value["dm_channels"].is_array());
+    assert!(value["read_states"].as_array().unwrap().iter().any(|r| r["channel_id"] == channel_id.to_string()));
+    let relationship_with = |value: &serde_json::Value, user: Uuid| {
+        value["relationships"].as_array().unwrap().iter().find(|r| r["user_id"] == user.to_string()).cloned()
+    };
+    assert_eq!(relationship_with(&value, friend_id).unwrap()["type"], "outgoing");
+
+    // Known at the current version: channels and roles are skipped
+    let version = server["version"].as_i64().unwrap();
+    let uri = format!("/api/v1/sync?known={}:{}", server_id, version);
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_owner), None).await;
+    let server = value["servers"].as_array().unwrap().iter().find(|s| s["id"] == server_id.to_string()).unwrap().clone();
+    assert!(server.get("channels").is_none());
+    assert!(server.get("roles").is_none());
+
+    // After a change the version moves on and the lists come back
+    app.create_channel(&token_owner, server_id, "another").await;
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_owner), None).await;
+    let server = value["servers"].as_array().unwrap().iter().find(|s| s["id"] == server_id.to_string()).unwrap().clone();
+    assert!(server["version"].as_i64().unwrap() > version);
+    assert_eq!(server["channels"].as_array().unwrap().len(), 3);
+
+    let (status, _) = app.request(Method::GET, "/api/v1/sync?known=garbage", Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, value) = app.request(Method::GET, "/api/v1/sync", Some(&token_friend), None).await;
+    assert_eq!(relationship_with(&value, owner_id).unwrap()["type"], "incoming");
+}
+
 // ─── Channel Update ─────────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -1727,74 +2187,1017 @@ async fn upload_and_count_prekeys(pool: Pool) {
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
-async fn update_identity_keys(pool: Pool) {
+async fn prekey_bundle_consumes_one_time_prekeys_in_order(pool: Pool) {
     let app = TestApp::new(pool).await;
-    let (token, _) = app.register_user("key_updater").await;
-
-    use base64::Engine;
-    let b64 = &base64::engine::general_purpose::STANDARD;
-    let body = json!({
-        "identity_key": b64.encode([1u8; 32]),
-        "signed_prekey": b64.encode([2u8; 32]),
-        "signed_prekey_signature": b64.encode([3u8; 64])
-    });
+    let (token_a, _) = app.register_user("pkb_requester").await;
+    let (token_b, user_b) = app.register_user("pkb_target").await;
 
-    let (status, _) = app
-        .request(
-            Method::PUT,
-            "/api/v1/keys/identity",
-            Some(&token),
-            Some(body),
-        )
+    let prekeys: Vec<String> = (0..2).map(|i| B64.encode([i as u8; 32])).collect();
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/keys/prekeys", Some(&token_b), Some(json!({ "prekeys": prekeys })))
         .await;
     assert_eq!(status, StatusCode::OK);
-}
-
-// ─── Attachments ────────────────────────────────────────
+    assert_eq!(value["key_ids"], json!([0, 1]));
 
-#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
-#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
-async fn upload_attachment(pool: Pool) {
-    let app = TestApp::new(pool).await;
-    let (token, _) = app.register_user("attach_user").await;
+    let uri = format!("/api/v1/users/{}/prekey-bundle", user_b);
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(value["one_time_prekey_id"], 0);
+    assert_eq!(value["one_time_prekey"], B64.encode([0u8; 32]));
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(value["one_time_prekey_id"], 1);
+    let (status, value) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["one_time_prekey"].is_null());
 
-    let data = vec![0u8; 1024]; // 1KB of zeros
-    let (status, value) = app
-        .request_bytes(Method::POST, "/api/v1/attachments/upload", Some(&token), data)
+    // Ids keep counting up after the earlier keys were consumed
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/keys/prekeys", Some(&token_b), Some(json!({ "prekeys": [B64.encode([9u8; 32])] })))
         .await;
+    assert_eq!(value["key_ids"], json!([2]));
+
+    // Clearing unused keys doesn't hand their ids out again
+    let (status, _) = app.request(Method::DELETE, "/api/v1/keys/prekeys", Some(&token_b), None).await;
     assert_eq!(status, StatusCode::OK);
-    assert!(value["attachment_id"].as_str().is_some());
-    assert!(value["storage_key"].as_str().is_some());
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/keys/prekeys", Some(&token_b), Some(json!({ "prekeys": [B64.encode([7u8; 32])] })))
+        .await;
+    assert_eq!(value["key_ids"], json!([3]));
+
+    // Concurrent uploads get disjoint ids
+    let body = json!({ "prekeys": [B64.encode([5u8; 32]), B64.encode([6u8; 32])] });
+    let (first, second) = tokio::join!(
+        app.request(Method::POST, "/api/v1/keys/prekeys", Some(&token_b), Some(body.clone())),
+        app.request(Method::POST, "/api/v1/keys/prekeys", Some(&token_b), Some(body.clone())),
+    );
+    let mut ids: Vec<i64> = [first.1, second.1]
+        .iter()
+        .flat_map(|v| v["key_ids"].as_array().unwrap().iter().map(|id| id.as_i64().unwrap()).collect::<Vec<_>>())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![4, 5, 6, 7]);
 }
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
-async fn upload_empty_attachment_fails(pool: Pool) {
+async fn rotate_signed_prekey_keeps_identity_key(pool: Pool) {
     let app = TestApp::new(pool).await;
-    let (token, _) = app.register_user("empty_attach").await;
+    let (token_a, _) = app.register_user("spk_requester").await;
+    let (token_b, user_b) = app.register_user("spk_target").await;
 
+    let uri = format!("/api/v1/users/{}/keys", user_b);
+    let (_, before) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+
+    let body = json!({
+        "signed_prekey": B64.encode([7u8; 32]),
+        "signed_prekey_signature": B64.encode([8u8; 64])
+    });
     let (status, _) = app
-        .request_bytes(Method::POST, "/api/v1/attachments/upload", Some(&token), vec![])
+        .request(Method::PUT, "/api/v1/keys/signed-prekey", Some(&token_b), Some(body))
         .await;
-    assert_ne!(status, StatusCode::OK);
-}
+    assert_eq!(status, StatusCode::OK);
 
-// ─── DM Privacy ─────────────────────────────────────────
+    let (_, after) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(after["identity_key"], before["identity_key"]);
+    assert_eq!(after["signed_prekey"], B64.encode([7u8; 32]));
+}
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
-async fn update_dm_privacy(pool: Pool) {
+async fn register_list_verify_and_remove_devices(pool: Pool) {
     let app = TestApp::new(pool).await;
-    let (token, _) = app.register_user("dm_priv_user").await;
-
-    let body = json!({ "dm_privacy": "friends_only" });
+    let (token_a, _) = app.register_user("dev_viewer").await;
+    let (token_b, user_b) = app.register_user("dev_owner").await;
+
+    let device_body = |key: u8| {
+        json!({
+            "name": format!("laptop-{}", key),
+            "identity_key": B64.encode([key; 32]),
+            "signed_prekey": B64.encode([key + 1; 32]),
+            "signed_prekey_signature": B64.encode([key + 2; 64])
+        })
+    };
     let (status, value) = app
-        .request(
-            Method::PUT,
-            "/api/v1/users/dm-privacy",
-            Some(&token),
-            Some(body),
-        )
+        .request(Method::POST, "/api/v1/keys/devices", Some(&token_b), Some(device_body(10)))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let device_id = value["id"].as_str().unwrap().to_string();
+
+    // Same identity key twice is a conflict
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/keys/devices", Some(&token_b), Some(device_body(10)))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    app.request(Method::POST, "/api/v1/keys/devices", Some(&token_b), Some(device_body(20)))
+        .await;
+
+    let uri = format!("/api/v1/keys/devices/{}/verification", device_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&token_a), Some(json!({ "status": "verified" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let list_uri = format!("/api/v1/users/{}/devices", user_b);
+    let (status, value) = app.request(Method::GET, &list_uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let devices = value.as_array().unwrap();
+    assert_eq!(devices.len(), 2);
+    let verified = devices.iter().find(|d| d["id"] == device_id.as_str()).unwrap();
+    assert_eq!(verified["verification"], "verified");
+
+    // The verdict is the viewer's own; the owner sees it unverified
+    let (_, value) = app.request(Method::GET, "/api/v1/keys/devices", Some(&token_b), None).await;
+    assert!(value.as_array().unwrap().iter().all(|d| d.get("verification").is_none()));
+
+    let uri = format!("/api/v1/keys/devices/{}", device_id);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app.request(Method::GET, &list_uri, Some(&token_a), None).await;
+    assert_eq!(value.as_array().unwrap().len(), 1);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn offline_device_queue_drains_and_acknowledges(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("queue_sender").await;
+    let (token_b, user_b) = app.register_user("queue_owner").await;
+
+    let body = json!({
+        "name": "phone",
+        "identity_key": B64.encode([30u8; 32]),
+        "signed_prekey": B64.encode([31u8; 32]),
+        "signed_prekey_signature": B64.encode([32u8; 64])
+    });
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/keys/devices", Some(&token_b), Some(body))
+        .await;
+    let device_id = value["id"].as_str().unwrap().to_string();
+
+    // The device has no connection, so DM messages queue for it
+    let dm_id = app.create_dm(&token_a, user_b).await;
+    let (first_id, _) = app.send_message(&token_a, dm_id).await;
+    let (second_id, _) = app.send_message(&token_a, dm_id).await;
+
+    let uri = format!("/api/v1/users/me/devices/{}/queue", device_id);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, value) = app.request(Method::GET, &format!("{}?limit=1", uri), Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let messages = value["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["message"]["id"], first_id.to_string());
+    let since = value["next_since"].as_i64().unwrap();
+
+    // Acknowledging the first batch returns the rest, then nothing
+    let (_, value) = app.request(Method::GET, &format!("{}?since={}", uri, since), Some(&token_b), None).await;
+    let messages = value["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["message"]["id"], second_id.to_string());
+    let since = value["next_since"].as_i64().unwrap();
+
+    let (_, value) = app.request(Method::GET, &format!("{}?since={}", uri, since), Some(&token_b), None).await;
+    assert!(value["messages"].as_array().unwrap().is_empty());
+    assert_eq!(value["next_since"].as_i64().unwrap(), since);
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_b), None).await;
+    assert!(value["messages"].as_array().unwrap().is_empty());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn update_identity_keys(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("key_updater").await;
+
+    use base64::Engine;
+    let b64 = &base64::engine::general_purpose::STANDARD;
+    let body = json!({
+        "identity_key": b64.encode([1u8; 32]),
+        "signed_prekey": b64.encode([2u8; 32]),
+        "signed_prekey_signature": b64.encode([3u8; 64])
+    });
+
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            "/api/v1/keys/identity",
+            Some(&token),
+            Some(body),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn key_transparency_proofs_track_identity_key_changes(pool: Pool) {
+    use haven_backend::key_transparency::{self as kt, Hash};
+
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("kt_viewer").await;
+    let (token_b, user_b) = app.register_user("kt_owner").await;
+    let decode = |v: &serde_json::Value| -> Hash { B64.decode(v.as_str().unwrap()).unwrap().try_into().unwrap() };
+
+    let (status, head) = app.request(Method::GET, "/api/v1/key-transparency/head", Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let old_size = head["tree_size"].as_i64().unwrap();
+    let old_root = decode(&head["root_hash"]);
+
+    // Rotating the identity key appends a new leaf
+    let new_key = [42u8; 32];
+    let body = json!({
+        "identity_key": B64.encode(new_key),
+        "signed_prekey": B64.encode([2u8; 32]),
+        "signed_prekey_signature": B64.encode([3u8; 64])
+    });
+    app.request(Method::PUT, "/api/v1/keys/identity", Some(&token_b), Some(body)).await;
+
+    let uri = format!("/api/v1/key-transparency/proof/{}", user_b);
+    let (status, proof) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof["identity_key"], B64.encode(new_key));
+    let tree_size = proof["tree_size"].as_i64().unwrap();
+    assert_eq!(tree_size, old_size + 1);
+    let root = decode(&proof["root_hash"]);
+    let path: Vec<Hash> = proof["audit_path"].as_array().unwrap().iter().map(decode).collect();
+    let leaf = kt::leaf_hash(user_b, &new_key);
+    let index = proof["leaf_index"].as_i64().unwrap() as usize;
+    assert!(kt::verify_inclusion(&leaf, index, tree_size as usize, &path, &root));
+
+    // The earlier head is a prefix of the current tree
+    let uri = format!("/api/v1/key-transparency/consistency?from={}", old_size);
+    let (status, value) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let proof: Vec<Hash> = value["proof"].as_array().unwrap().iter().map(decode).collect();
+    assert!(kt::verify_consistency(old_size as usize, tree_size as usize, &old_root, &root, &proof));
+
+    let uri = format!("/api/v1/key-transparency/consistency?from={}", tree_size + 1);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ─── Attachments ────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn upload_attachment(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("attach_user").await;
+
+    let data = vec![0u8; 1024]; // 1KB of zeros
+    let (status, value) = app
+        .request_bytes(Method::POST, "/api/v1/attachments/upload", Some(&token), data)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["attachment_id"].as_str().is_some());
+    assert!(value["storage_key"].as_str().is_some());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn upload_empty_attachment_fails(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("empty_attach").await;
+
+    let (status, _) = app
+        .request_bytes(Method::POST, "/api/v1/attachments/upload", Some(&token), vec![])
+        .await;
+    assert_ne!(status, StatusCode::OK);
+}
+
+// ─── Resumable Uploads ──────────────────────────────────
+
+/// Open an upload session on a channel and return its id.
+async fn open_upload(app: &TestApp, token: &str, channel_id: Uuid, len: usize, content_type: &str) -> Uuid {
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/channels/{}/attachments", channel_id),
+            Some(token),
+            Some(json!({ "upload_length": len, "content_type": content_type })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "Open upload failed: {}", value);
+    assert_eq!(value["upload_offset"], 0);
+    Uuid::parse_str(value["upload_id"].as_str().unwrap()).unwrap()
+}
+
+async fn put_chunk(app: &TestApp, token: &str, upload_id: Uuid, offset: usize, chunk: &[u8]) -> (StatusCode, Option<String>) {
+    let offset = offset.to_string();
+    let (status, headers, _) = app
+        .request_with_headers(
+            Method::PUT,
+            &format!("/api/v1/attachments/uploads/{}", upload_id),
+            Some(token),
+            &[("upload-offset", &offset), ("content-type", "application/offset+octet-stream")],
+            chunk.to_vec(),
+        )
+        .await;
+    let new_offset = headers
+        .get("upload-offset")
+        .map(|v| v.to_str().unwrap().to_string());
+    (status, new_offset)
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn resumable_upload_chunks_resume_and_finalize(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("chunk_owner").await;
+    let server_id = app.create_server(&token, "Uploads").await;
+    let channel_id = app.create_channel(&token, server_id, "files").await;
+    let (message_id, _) = app.send_message(&token, channel_id).await;
+
+    let data = b"hello, resumable world";
+    let upload_id = open_upload(&app, &token, channel_id, data.len(), "image/png").await;
+    let uri = format!("/api/v1/attachments/uploads/{}", upload_id);
+
+    let (status, offset) = put_chunk(&app, &token, upload_id, 0, &data[..10]).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(offset.as_deref(), Some("10"));
+
+    // Replaying the first chunk (e.g. after a dropped response) is rejected
+    let (status, _) = put_chunk(&app, &token, upload_id, 0, &data[..10]).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // HEAD tells the client where to resume
+    let (status, headers, _) = app
+        .request_with_headers(Method::HEAD, &uri, Some(&token), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["upload-offset"], "10");
+    assert_eq!(headers["upload-length"], data.len().to_string().as_str());
+
+    // Finalizing early fails
+    let (status, _) = app
+        .request(Method::POST, &format!("{}/finalize", uri), Some(&token), Some(json!({ "message_id": message_id })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Chunks may not run past the declared length
+    let mut overflow = data[10..].to_vec();
+    overflow.push(b'!');
+    let (status, _) = put_chunk(&app, &token, upload_id, 10, &overflow).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, offset) = put_chunk(&app, &token, upload_id, 10, &data[10..]).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(offset.as_deref(), Some(data.len().to_string().as_str()));
+
+    let (status, value) = app
+        .request(Method::POST, &format!("{}/finalize", uri), Some(&token), Some(json!({ "message_id": message_id })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Finalize failed: {}", value);
+    assert_eq!(value["attachment_id"], upload_id.to_string());
+
+    // The session is gone and the assembled blob downloads intact
+    let (status, _, _) = app
+        .request_with_headers(Method::HEAD, &uri, Some(&token), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = app
+        .request(Method::GET, &format!("/api/v1/attachments/{}", upload_id), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_str(), Some("hello, resumable world"));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn upload_session_enforces_server_tier_limits(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("tier_owner").await;
+    let server_id = app.create_server(&token, "Tiered").await;
+    let channel_id = app.create_channel(&token, server_id, "files").await;
+    let uri = format!("/api/v1/channels/{}/attachments", channel_id);
+    let base_limit: u64 = 10_000_000; // max_upload_size_bytes in the test config
+
+    // Standard tier: instance limit and media/document types only
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token), Some(json!({ "upload_length": 10, "content_type": "application/x-msdownload" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token), Some(json!({ "upload_length": base_limit + 1, "content_type": "video/mp4" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Only instance operators can change a server's tier
+    let tier_uri = format!("/api/v1/admin/servers/{}/upload-tier", server_id);
+    let (status, _) = app
+        .request(Method::PUT, &tier_uri, Some(&token), Some(json!({ "upload_tier": "boosted" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (admin_token, admin_id) = app.register_user("tier_admin").await;
+    app.make_admin(admin_id).await;
+    let (status, _) = app
+        .request(Method::PUT, &tier_uri, Some(&admin_token), Some(json!({ "upload_tier": "platinum" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, value) = app
+        .request(Method::PUT, &tier_uri, Some(&admin_token), Some(json!({ "upload_tier": "boosted" })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Set tier failed: {}", value);
+    assert_eq!(value["upload_tier"], "boosted");
+
+    open_upload(&app, &token, channel_id, 10, "application/x-msdownload").await;
+    open_upload(&app, &token, channel_id, (base_limit + 1) as usize, "video/mp4").await;
+
+    // Non-members cannot open sessions at all
+    let (outsider, _) = app.register_user("tier_outsider").await;
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&outsider), Some(json!({ "upload_length": 10, "content_type": "image/png" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn upload_session_enforces_role_and_channel_limits(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("limits_owner").await;
+    let (member, _) = app.register_user("limits_member").await;
+    let server_id = app.create_server(&owner, "Limited").await;
+    let general = app.create_channel(&owner, server_id, "general").await;
+    let memes = app.create_channel(&owner, server_id, "memes").await;
+    let announcements = app.create_channel(&owner, server_id, "announcements").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let try_open = |token: String, channel_id: Uuid, len: u64| {
+        let app = &app;
+        async move {
+            app.request(
+                Method::POST,
+                &format!("/api/v1/channels/{}/attachments", channel_id),
+                Some(&token),
+                Some(json!({ "upload_length": len, "content_type": "image/png" })),
+            )
+            .await
+        }
+    };
+
+    // A small default for @everyone, and two channel overrides
+    let (_, roles) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/roles", server_id), Some(&owner), None)
+        .await;
+    let everyone = roles.as_array().unwrap().iter().find(|r| r["is_default"] == true).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let limits = format!("/api/v1/servers/{}/upload-limits", server_id);
+    let role_limit = format!("{}/roles/{}", limits, everyone);
+    let body = json!({ "max_size_bytes": 1000, "max_count": 2 });
+    let (status, _) = app.request(Method::PUT, &role_limit, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app.request(Method::PUT, &role_limit, Some(&owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    let memes_limit = format!("{}/channels/{}", limits, memes);
+    let (status, _) = app
+        .request(Method::PUT, &memes_limit, Some(&owner), Some(json!({ "max_size_bytes": 5000 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let announcements_limit = format!("{}/channels/{}", limits, announcements);
+    let (status, _) = app
+        .request(Method::PUT, &announcements_limit, Some(&owner), Some(json!({ "max_count": 0 })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = try_open(member.clone(), general, 1001).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    open_upload(&app, &member, memes, 4000, "image/png").await;
+    let (status, value) = try_open(member.clone(), announcements, 10).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "UPLOAD_LIMIT");
+
+    // The count caps uploads in progress at once
+    open_upload(&app, &member, general, 10, "image/png").await;
+    open_upload(&app, &member, general, 10, "image/png").await;
+    let (status, value) = try_open(member.clone(), general, 10).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "UPLOAD_LIMIT");
+
+    // The owner is held only to the tier
+    open_upload(&app, &owner, announcements, 5000, "image/png").await;
+
+    // The permissions endpoint reports what applies and why
+    let uri = format!("/api/v1/servers/{}/members/@me/permissions?channel_id={}", server_id, memes);
+    let (status, value) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert!(value["channel_permissions"].is_string());
+    assert_eq!(value["upload_limits"]["max_size_bytes"], 5000);
+    assert_eq!(value["upload_limits"]["max_size_source"], "channel");
+    assert_eq!(value["upload_limits"]["max_count"], 2);
+    assert_eq!(value["upload_limits"]["max_count_source"], "role");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn upload_session_is_private_to_uploader(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("session_owner").await;
+    let (member, _) = app.register_user("session_member").await;
+    let server_id = app.create_server(&owner, "Shared").await;
+    let channel_id = app.create_channel(&owner, server_id, "files").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let (other_message, _) = app.send_message(&member, channel_id).await;
+
+    let upload_id = open_upload(&app, &owner, channel_id, 4, "text/plain").await;
+
+    // Another member can't see or write to the session
+    let (status, _) = put_chunk(&app, &member, upload_id, 0, b"evil").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = put_chunk(&app, &owner, upload_id, 0, b"data").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // ...and the owner can't attach it to someone else's message
+    let uri = format!("/api/v1/attachments/uploads/{}", upload_id);
+    let (status, _) = app
+        .request(Method::POST, &format!("{}/finalize", uri), Some(&owner), Some(json!({ "message_id": other_message })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Cancelling discards the session
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&owner), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = put_chunk(&app, &owner, upload_id, 4, b"more").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn attachment_gc_collects_attachments_of_deleted_messages(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("gc_owner").await;
+    app.make_admin(user_id).await;
+    let server_id = app.create_server(&token, "GC").await;
+    let channel_id = app.create_channel(&token, server_id, "files").await;
+    let (kept_message, _) = app.send_message(&token, channel_id).await;
+    let (doomed_message, _) = app.send_message(&token, channel_id).await;
+
+    let mut attachments = Vec::new();
+    for message_id in [kept_message, doomed_message] {
+        let upload_id = open_upload(&app, &token, channel_id, 4, "text/plain").await;
+        put_chunk(&app, &token, upload_id, 0, b"data").await;
+        let (status, _) = app
+            .request(
+                Method::POST,
+                &format!("/api/v1/attachments/uploads/{}/finalize", upload_id),
+                Some(&token),
+                Some(json!({ "message_id": message_id })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        attachments.push(upload_id);
+    }
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/channels/{}/messages/bulk-delete", channel_id),
+            Some(&token),
+            Some(json!({ "message_ids": [doomed_message] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Test config uses a zero grace period, so one pass stamps and collects
+    let (status, value) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "GC run failed: {}", value);
+    assert_eq!(value["dry_run"], false);
+    assert_eq!(value["marked"], 1);
+    assert_eq!(value["deleted"], 1);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["pending"], 0);
+    assert_eq!(value["collectable"], 0);
+
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/attachments/{}", attachments[0]), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/attachments/{}", attachments[1]), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Regular users can't trigger GC
+    let (other, _) = app.register_user("gc_other").await;
+    let (status, _) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&other), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn identical_attachments_share_one_blob(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("blob_owner").await;
+    app.make_admin(user_id).await;
+    let server_id = app.create_server(&token, "Blobs").await;
+    let channel_id = app.create_channel(&token, server_id, "files").await;
+    let (first, _) = app.send_message(&token, channel_id).await;
+    let (second, _) = app.send_message(&token, channel_id).await;
+
+    let data = b"identical ciphertext";
+    let mut keys = Vec::new();
+    for message_id in [first, second] {
+        let value = upload_and_finalize(&app, &token, channel_id, message_id, data, "text/plain").await;
+        keys.push(value["storage_key"].as_str().unwrap().to_string());
+    }
+    assert_eq!(keys[0], keys[1]);
+
+    // The shared blob is charged once
+    let quota_uri = format!("/api/v1/admin/servers/{}/quotas", server_id);
+    let (status, value) = app.request(Method::GET, &quota_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["usage"]["storage_bytes"], data.len());
+
+    // The blob outlives the first message and goes with the last
+    let bulk_delete = format!("/api/v1/channels/{}/messages/bulk-delete", channel_id);
+    let (status, _) = app
+        .request(Method::POST, &bulk_delete, Some(&token), Some(json!({ "message_ids": [first] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, value) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "GC run failed: {}", value);
+    assert_eq!(value["deleted"], 1);
+    assert_eq!(value["blobs_deleted"], 0);
+
+    let (status, _) = app
+        .request(Method::POST, &bulk_delete, Some(&token), Some(json!({ "message_ids": [second] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, value) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "GC run failed: {}", value);
+    assert_eq!(value["deleted"], 1);
+    assert_eq!(value["blobs_deleted"], 1);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["blobs_pending"], 0);
+    assert_eq!(value["blobs_collectable"], 0);
+
+    let (status, value) = app.request(Method::GET, &quota_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["usage"]["storage_bytes"], 0);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn unsent_simple_uploads_release_their_blob(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("pending_owner").await;
+    app.make_admin(user_id).await;
+
+    let (status, value) = app
+        .request_bytes(Method::POST, "/api/v1/attachments/upload", Some(&token), b"never sent".to_vec())
+        .await;
+    assert_eq!(status, StatusCode::OK, "Upload failed: {}", value);
+
+    // A pending upload keeps its blob referenced
+    let (status, value) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "GC run failed: {}", value);
+    assert_eq!(value["pending_released"], 0);
+    assert_eq!(value["blobs_deleted"], 0);
+
+    // Once it expires unsent, the GC releases it and collects the blob
+    sqlx::query("UPDATE pending_uploads SET expires_at = NOW() - interval '1 minute'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, value) = app.request(Method::POST, "/api/v1/admin/attachment-gc", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "GC run failed: {}", value);
+    assert_eq!(value["pending_released"], 1);
+    assert_eq!(value["blobs_deleted"], 1);
+}
+
+/// Upload `data` to `channel_id` in one chunk and finalize it onto `message_id`.
+async fn upload_and_finalize(
+    app: &TestApp,
+    token: &str,
+    channel_id: Uuid,
+    message_id: Uuid,
+    data: &[u8],
+    content_type: &str,
+) -> serde_json::Value {
+    let upload_id = open_upload(app, token, channel_id, data.len(), content_type).await;
+    let (status, _) = put_chunk(app, token, upload_id, 0, data).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/attachments/uploads/{}/finalize", upload_id),
+            Some(token),
+            Some(json!({ "message_id": message_id })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Finalize failed: {}", value);
+    value
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn attachment_downloads_support_ranges(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("range_owner").await;
+    let server_id = app.create_server(&token, "Ranges").await;
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/channels", server_id),
+            Some(&token),
+            Some(json!({ "encrypted_meta": B64.encode(b"clips"), "encrypted": false })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create channel failed: {}", value);
+    let plain_channel = Uuid::parse_str(value["id"].as_str().unwrap()).unwrap();
+    let encrypted_channel = app.create_channel(&token, server_id, "secrets").await;
+
+    let (message_id, _) = app.send_message(&token, plain_channel).await;
+    let value = upload_and_finalize(&app, &token, plain_channel, message_id, b"abcdefghij", "video/mp4").await;
+    let uri = format!("/api/v1/attachments/{}", value["attachment_id"].as_str().unwrap());
+
+    let (status, headers, body) = app.request_with_headers(Method::GET, &uri, Some(&token), &[], vec![]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "abcdefghij");
+    assert_eq!(headers["accept-ranges"], "bytes");
+    assert_eq!(headers["content-type"], "video/mp4");
+    assert_eq!(headers["content-disposition"], "inline");
+    let etag = headers["etag"].to_str().unwrap().to_string();
+
+    let (status, headers, body) = app
+        .request_with_headers(Method::GET, &uri, Some(&token), &[("range", "bytes=2-5")], vec![])
+        .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body, "cdef");
+    assert_eq!(headers["content-range"], "bytes 2-5/10");
+
+    // Resuming with a current validator gets the rest; a stale one the whole file
+    let (status, _, body) = app
+        .request_with_headers(Method::GET, &uri, Some(&token), &[("range", "bytes=7-"), ("if-range", etag.as_str())], vec![])
+        .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body, "hij");
+    let (status, _, body) = app
+        .request_with_headers(Method::GET, &uri, Some(&token), &[("range", "bytes=7-"), ("if-range", "\"stale\"")], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "abcdefghij");
+
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token), &[("range", "bytes=20-")], vec![])
+        .await;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(headers["content-range"], "bytes */10");
+
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, &format!("{}?download=true&filename=clip.mp4", uri), Some(&token), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-disposition"], "attachment; filename=\"clip.mp4\"; filename*=UTF-8''clip.mp4");
+
+    // Ciphertext is always an octet-stream download, whatever was declared
+    let (message_id, _) = app.send_message(&token, encrypted_channel).await;
+    let value = upload_and_finalize(&app, &token, encrypted_channel, message_id, b"ciphertext", "video/mp4").await;
+    let uri = format!("/api/v1/attachments/{}", value["attachment_id"].as_str().unwrap());
+    let (status, headers, _) = app.request_with_headers(Method::GET, &uri, Some(&token), &[], vec![]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/octet-stream");
+    assert_eq!(headers["content-disposition"], "attachment");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn signed_attachment_urls_serve_without_auth(pool: Pool) {
+    const KEY_1: &str = "k1:0123456789abcdef0123456789abcdef";
+    const KEY_2: &str = "k2:fedcba9876543210fedcba9876543210";
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("signed_owner").await;
+    let (outsider, _) = app.register_user("signed_outsider").await;
+    let server_id = app.create_server(&token, "Signed").await;
+    let channel_id = app.create_channel(&token, server_id, "files").await;
+    let (message_id, _) = app.send_message(&token, channel_id).await;
+    let value = upload_and_finalize(&app, &token, channel_id, message_id, b"ciphertext", "image/png").await;
+    let uri = format!("/api/v1/attachments/{}/signed-url", value["attachment_id"].as_str().unwrap());
+
+    let (status, value) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "SIGNED_URLS_DISABLED");
+
+    app.reload_config(|c| c.attachment_url_signing_keys = KEY_1.into());
+    let (status, _) = app.request(Method::GET, &uri, Some(&outsider), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "Issue failed: {}", value);
+    let url = value["url"].as_str().unwrap().to_string();
+    assert!(url.starts_with("/api/v1/attachments/signed/"));
+    assert!(url.contains("&kid=k1&"));
+
+    // No token needed; ranges work as on the direct download
+    let (status, headers, body) = app.request_with_headers(Method::GET, &url, None, &[], vec![]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "ciphertext");
+    assert_eq!(headers["content-type"], "application/octet-stream");
+    assert_eq!(headers["content-disposition"], "attachment");
+    let (status, _, body) = app
+        .request_with_headers(Method::GET, &url, None, &[("range", "bytes=0-5")], vec![])
+        .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body, "cipher");
+
+    // Any change to the signed fields is refused
+    let tampered = url.replace("cd=attachment", "cd=inline");
+    let (status, _, body) = app.request_with_headers(Method::GET, &tampered, None, &[], vec![]).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "SIGNED_URL_INVALID");
+
+    // Rotation: the old key verifies until it is dropped
+    app.reload_config(|c| c.attachment_url_signing_keys = format!("{},{}", KEY_2, KEY_1));
+    let (status, _, _) = app.request_with_headers(Method::GET, &url, None, &[], vec![]).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert!(value["url"].as_str().unwrap().contains("&kid=k2&"));
+    app.reload_config(|c| c.attachment_url_signing_keys = KEY_2.into());
+    let (status, _, _) = app.request_with_headers(Method::GET, &url, None, &[], vec![]).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn attachment_scanner_quarantines_positives(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    app.set_attachment_scanner("clamd", &fake_clamd().await);
+    let (token, _) = app.register_user("scan_owner").await;
+    let server_id = app.create_server(&token, "Scanned").await;
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/channels", server_id),
+            Some(&token),
+            Some(json!({ "encrypted_meta": B64.encode(b"files"), "encrypted": false })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create channel failed: {}", value);
+    let plain_channel = Uuid::parse_str(value["id"].as_str().unwrap()).unwrap();
+    let encrypted_channel = app.create_channel(&token, server_id, "secrets").await;
+
+    let eicar = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+    let mut uploads = Vec::new();
+    for data in [&b"harmless"[..], &eicar[..]] {
+        let (message_id, _) = app.send_message(&token, plain_channel).await;
+        let value = upload_and_finalize(&app, &token, plain_channel, message_id, data, "text/plain").await;
+        uploads.push(value["attachment_id"].as_str().unwrap().to_string());
+    }
+
+    // Scans run in the background; downloads wait for the verdict
+    let mut verdicts = Vec::new();
+    for attachment_id in &uploads {
+        let uri = format!("/api/v1/attachments/{}", attachment_id);
+        let mut result = app.request(Method::GET, &uri, Some(&token), None).await;
+        for _ in 0..50 {
+            if result.0 != StatusCode::CONFLICT {
+                break;
+            }
+            assert_eq!(result.1["code"], "ATTACHMENT_SCAN_PENDING");
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            result = app.request(Method::GET, &uri, Some(&token), None).await;
+        }
+        verdicts.push(result);
+    }
+    assert_eq!(verdicts[0].0, StatusCode::OK);
+    assert_eq!(verdicts[1].0, StatusCode::FORBIDDEN);
+    assert_eq!(verdicts[1].1["code"], "ATTACHMENT_QUARANTINED");
+
+    let (status, value) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/audit-log", server_id), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let entry = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["action"] == "attachment_quarantine")
+        .expect("quarantine not audited");
+    assert_eq!(entry["target_id"], uploads[1].as_str());
+
+    // Ciphertext isn't scanned
+    let (message_id, _) = app.send_message(&token, encrypted_channel).await;
+    let value = upload_and_finalize(&app, &token, encrypted_channel, message_id, eicar, "text/plain").await;
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/attachments/{}", value["attachment_id"].as_str().unwrap()), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+/// A stand-in clamd answering INSTREAM scans; flags anything containing "EICAR".
+async fn fake_clamd() -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut command = [0u8; 10];
+                socket.read_exact(&mut command).await.unwrap();
+                assert_eq!(&command, b"zINSTREAM\0");
+                let mut data = Vec::new();
+                loop {
+                    let mut len = [0u8; 4];
+                    socket.read_exact(&mut len).await.unwrap();
+                    let mut chunk = vec![0u8; u32::from_be_bytes(len) as usize];
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    socket.read_exact(&mut chunk).await.unwrap();
+                    data.extend_from_slice(&chunk);
+                }
+                let reply: &[u8] = if data.windows(5).any(|w| w == b"EICAR") {
+                    b"stream: Eicar-Test-Signature FOUND\0"
+                } else {
+                    b"stream: OK\0"
+                };
+                socket.write_all(reply).await.unwrap();
+            });
+        }
+    });
+    format!("tcp://{}", addr)
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn image_previews_only_in_unencrypted_channels(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("preview_owner").await;
+    let server_id = app.create_server(&token, "Previews").await;
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/channels", server_id),
+            Some(&token),
+            Some(json!({ "encrypted_meta": B64.encode(b"memes"), "encrypted": false })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create channel failed: {}", value);
+    let plain_channel = Uuid::parse_str(value["id"].as_str().unwrap()).unwrap();
+    let encrypted_channel = app.create_channel(&token, server_id, "secrets").await;
+
+    let png = png(640, 480, [200, 40, 40]);
+
+    let (message_id, _) = app.send_message(&token, plain_channel).await;
+    let value = upload_and_finalize(&app, &token, plain_channel, message_id, &png, "image/png").await;
+    let preview = &value["preview"];
+    assert_eq!(preview["width"], 640);
+    assert_eq!(preview["height"], 480);
+    assert!(!preview["blurhash"].as_str().unwrap().is_empty());
+
+    // History carries the preview so clients needn't fetch the full image
+    let (status, messages) = app
+        .request(Method::GET, &format!("/api/v1/channels/{}/messages", plain_channel), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(messages[0]["attachment_previews"][0], *preview);
+
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, preview["thumbnail_url"].as_str().unwrap(), Some(&token), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "image/jpeg");
+
+    let (outsider, _) = app.register_user("preview_outsider").await;
+    let (status, _) = app
+        .request(Method::GET, preview["thumbnail_url"].as_str().unwrap(), Some(&outsider), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Encrypted channels never get previews, even for decodable images
+    let (message_id, _) = app.send_message(&token, encrypted_channel).await;
+    let value = upload_and_finalize(&app, &token, encrypted_channel, message_id, &png, "image/png").await;
+    assert!(value.get("preview").is_none());
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/attachments/{}/thumbnail", value["attachment_id"].as_str().unwrap()), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, messages) = app
+        .request(Method::GET, &format!("/api/v1/channels/{}/messages", encrypted_channel), Some(&token), None)
+        .await;
+    assert!(messages[0].get("attachment_previews").is_none());
+}
+
+// ─── DM Privacy ─────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn update_dm_privacy(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("dm_priv_user").await;
+
+    let body = json!({ "dm_privacy": "friends_only" });
+    let (status, value) = app
+        .request(
+            Method::PUT,
+            "/api/v1/users/dm-privacy",
+            Some(&token),
+            Some(body),
+        )
         .await;
     assert_eq!(status, StatusCode::OK);
     assert_eq!(value["dm_privacy"].as_str(), Some("friends_only"));
@@ -1848,8 +3251,81 @@ async fn dm_friends_only_creates_pending_dm(pool: Pool) {
     assert_eq!(value["dm_status"].as_str(), Some("pending"));
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn dm_friends_strict_refuses_non_friends(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("dm_fs_a").await;
+    let (token_b, user_b) = app.register_user("dm_fs_b").await;
+
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            "/api/v1/users/dm-privacy",
+            Some(&token_b),
+            Some(json!({ "dm_privacy": "friends_strict" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let body = json!({ "target_user_id": user_b, "encrypted_meta": B64.encode(b"dm-meta") });
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/dm", Some(&token_a), Some(body.clone()))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    app.make_friends(&token_a, &token_b, "dm_fs_b").await;
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/dm", Some(&token_a), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["dm_status"].as_str(), Some("active"));
+}
+
 // ─── Friends Extended ───────────────────────────────────
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn relationships_list_friends_requests_and_blocks(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("rel_a").await;
+    let (token_b, user_b) = app.register_user("rel_b").await;
+    let (token_c, user_c) = app.register_user("rel_c").await;
+    let (_, user_d) = app.register_user("rel_d").await;
+
+    app.make_friends(&token_a, &token_b, "rel_b").await;
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/friends/request", Some(&token_c), Some(json!({ "username": "rel_a" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(Method::POST, &format!("/api/v1/users/{}/block", user_d), Some(&token_a), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/users/me/relationships", Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let kind_of = |value: &serde_json::Value, user: Uuid| {
+        value.as_array().unwrap().iter().find(|r| r["user_id"] == user.to_string()).map(|r| r["type"].clone())
+    };
+    assert_eq!(kind_of(&value, user_b), Some(json!("friend")));
+    assert_eq!(kind_of(&value, user_c), Some(json!("incoming")));
+    assert_eq!(kind_of(&value, user_d), Some(json!("blocked")));
+
+    let (_, value) = app.request(Method::GET, "/api/v1/users/me/relationships", Some(&token_c), None).await;
+    assert_eq!(kind_of(&value, user_a), Some(json!("outgoing")));
+
+    // Blocking a friend ends the friendship on both sides
+    let (status, _) = app
+        .request(Method::POST, &format!("/api/v1/users/{}/block", user_b), Some(&token_a), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app.request(Method::GET, "/api/v1/users/me/relationships", Some(&token_a), None).await;
+    assert_eq!(kind_of(&value, user_b), Some(json!("blocked")));
+    let (_, value) = app.request(Method::GET, "/api/v1/users/me/relationships", Some(&token_b), None).await;
+    assert_eq!(kind_of(&value, user_a), None);
+}
+
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
 async fn remove_friend(pool: Pool) {
@@ -2307,6 +3783,73 @@ async fn reorder_channels_requires_permission(pool: Pool) {
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn update_channel_positions_replaces_whole_layout(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("ch_layout").await;
+    let (member, _) = app.register_user("ch_layout_mem").await;
+    let server_id = app.create_server(&token, "Layout").await;
+    app.invite_and_join(&token, &member, server_id).await;
+    app.create_channel(&token, server_id, "alpha").await;
+    app.create_channel(&token, server_id, "beta").await;
+    let cat_uri = format!("/api/v1/servers/{}/categories", server_id);
+    let (status, cat) = app.request(Method::POST, &cat_uri, Some(&token), Some(json!({ "name": "Moved" }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let list_uri = format!("/api/v1/servers/{}/channels", server_id);
+    let (_, channels) = app.request(Method::GET, &list_uri, Some(&token), None).await;
+    let (_, categories) = app.request(Method::GET, &cat_uri, Some(&token), None).await;
+    let mut channel_ids: Vec<String> =
+        channels.as_array().unwrap().iter().map(|c| c["id"].as_str().unwrap().to_string()).collect();
+    channel_ids.reverse();
+    let mut category_ids: Vec<String> =
+        categories.as_array().unwrap().iter().map(|c| c["id"].as_str().unwrap().to_string()).collect();
+    category_ids.retain(|id| id != cat["id"].as_str().unwrap());
+    category_ids.insert(0, cat["id"].as_str().unwrap().to_string());
+
+    // Last two channels go into the new category, the rest are uncategorized
+    let placements: Vec<_> = channel_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let category = if i < 2 { cat["id"].clone() } else { json!(null) };
+            json!({ "id": id, "category_id": category })
+        })
+        .collect();
+    let body = json!({ "categories": category_ids, "channels": placements });
+    let uri = format!("/api/v1/servers/{}/channels/positions", server_id);
+    let (status, _) = app.request(Method::PATCH, &uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app.request(Method::PATCH, &uri, Some(&token), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "Layout failed: {}", value);
+    assert_eq!(value["categories"][0]["id"], cat["id"]);
+    assert_eq!(value["categories"][0]["position"], 0);
+
+    let (_, channels) = app.request(Method::GET, &list_uri, Some(&token), None).await;
+    let find = |id: &String| channels.as_array().unwrap().iter().find(|c| c["id"] == id.as_str()).unwrap().clone();
+    assert_eq!(find(&channel_ids[0])["position"], 0);
+    assert_eq!(find(&channel_ids[1])["position"], 1);
+    assert_eq!(find(&channel_ids[1])["category_id"], cat["id"]);
+    assert_eq!(find(&channel_ids[2])["position"], 0);
+    assert!(find(&channel_ids[2])["category_id"].is_null());
+
+    // Listing a channel twice, or an unlisted category, is refused
+    let mut twice = body.clone();
+    twice["channels"].as_array_mut().unwrap().push(body["channels"][0].clone());
+    let (status, _) = app.request(Method::PATCH, &uri, Some(&token), Some(twice)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let mut unlisted = body.clone();
+    unlisted["categories"].as_array_mut().unwrap().remove(0);
+    let (status, _) = app.request(Method::PATCH, &uri, Some(&token), Some(unlisted)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A channel created since the layout was loaded makes it stale
+    app.create_channel(&token, server_id, "gamma").await;
+    let (status, value) = app.request(Method::PATCH, &uri, Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "CHANNEL_LAYOUT_STALE");
+}
+
 // ─── Avatar Upload/Download ──────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -2315,11 +3858,8 @@ async fn upload_and_download_avatar(pool: Pool) {
     let app = TestApp::new(pool).await;
     let (token, user_id) = app.register_user("avatar_user").await;
 
-    // Upload a fake PNG (starts with PNG magic bytes)
-    let mut png_data = vec![0x89, 0x50, 0x4E, 0x47]; // PNG magic
-    png_data.extend_from_slice(&[0u8; 100]);
     let (status, value) = app
-        .request_bytes(Method::POST, "/api/v1/users/avatar", Some(&token), png_data)
+        .request_bytes(Method::POST, "/api/v1/users/avatar", Some(&token), png(64, 64, [10, 20, 30]))
         .await;
     assert_eq!(status, StatusCode::OK);
     assert!(value["avatar_url"].as_str().is_some());
@@ -2330,6 +3870,187 @@ async fn upload_and_download_avatar(pool: Pool) {
     assert_eq!(status, StatusCode::OK);
 }
 
+/// Encode a solid-color PNG.
+fn png(width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+    let mut out = Vec::new();
+    image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(width, height, image::Rgb(color)))
+        .write_to(&mut std::io::Cursor::new(&mut out), image::ImageFormat::Png)
+        .unwrap();
+    out
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn avatar_urls_are_content_addressed(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("avatar_hashed").await;
+
+    let (status, value) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/avatar", Some(&token), png(1024, 1024, [1, 2, 3]))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Upload failed: {}", value);
+    let first_url = value["avatar_url"].as_str().unwrap().to_string();
+    assert!(first_url.starts_with(&format!("/api/v1/users/{}/avatar/", user_id)));
+
+    // The versioned URL is immutable and serves the downscaled image
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, &first_url, None, &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["cache-control"], "public, max-age=31536000, immutable");
+    assert_eq!(headers["content-type"], "image/jpeg");
+
+    // The unversioned URL revalidates against the current hash
+    let current_uri = format!("/api/v1/users/{}/avatar", user_id);
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, &current_uri, None, &[], vec![])
+        .await;
+    let etag = headers["etag"].to_str().unwrap().to_string();
+    let (status, _, _) = app
+        .request_with_headers(Method::GET, &current_uri, None, &[("if-none-match", &etag)], vec![])
+        .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    // A new upload gets a new URL and the old version is gone
+    let (status, value) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/avatar", Some(&token), png(32, 32, [200, 0, 0]))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(value["avatar_url"].as_str().unwrap(), first_url);
+    let (status, _) = app.request(Method::GET, &first_url, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, value) = app.request(Method::DELETE, "/api/v1/users/me/avatar", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["avatar_url"].is_null());
+    let (status, _) = app.request(Method::GET, &current_uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn profile_media_rejects_non_images_and_enforces_quota(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("avatar_quota").await;
+
+    // PNG magic bytes alone don't make an image
+    let mut fake_png = vec![0x89, 0x50, 0x4E, 0x47];
+    fake_png.extend_from_slice(&[0u8; 100]);
+    let (status, _) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/avatar", Some(&token), fake_png)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let avatar = png(64, 64, [5, 5, 5]);
+    let banner = png(300, 100, [6, 6, 6]);
+    app.set_profile_media_quota((avatar.len() + banner.len() - 1) as u64);
+
+    let (status, _) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/avatar", Some(&token), avatar.clone())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, value) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/banner", Some(&token), banner)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(value["error"].as_str().unwrap().contains("quota"));
+
+    // Replacing a slot only counts the new version
+    let (status, _) = app
+        .request_bytes(Method::PUT, "/api/v1/users/me/avatar", Some(&token), avatar.clone())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, value) = app.request(Method::GET, "/api/v1/users/me/media-usage", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["used_bytes"], avatar.len());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn member_profiles_set_nickname_and_server_avatar(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, owner_id) = app.register_user("mp_owner").await;
+    let (token_member, member_id) = app.register_user("mp_member").await;
+    let server_id = app.create_server(&token_owner, "Member Profiles").await;
+    app.invite_and_join(&token_owner, &token_member, server_id).await;
+
+    let member_uri = format!("/api/v1/servers/{}/members/{}", server_id, member_id);
+    let avatar = B64.encode(png(48, 48, [0, 90, 0]));
+    let (status, value) = app
+        .request(
+            Method::PATCH,
+            &member_uri,
+            Some(&token_member),
+            Some(json!({ "nickname": "Sprout", "avatar": avatar })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Update failed: {}", value);
+    assert_eq!(value["nickname"], "Sprout");
+    let avatar_url = value["server_avatar_url"].as_str().unwrap().to_string();
+    assert!(avatar_url.starts_with(&format!("/api/v1/servers/{}/members/{}/avatar/", server_id, member_id)));
+
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, &avatar_url, None, &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["cache-control"], "public, max-age=31536000, immutable");
+
+    let (_, members) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/members", server_id), Some(&token_owner), None)
+        .await;
+    let entry = members.as_array().unwrap().iter().find(|m| m["user_id"] == member_id.to_string()).unwrap();
+    assert_eq!(entry["nickname"], "Sprout");
+    assert_eq!(entry["server_avatar_url"], avatar_url.as_str());
+
+    // Other members need MANAGE_NICKNAMES
+    let owner_uri = format!("/api/v1/servers/{}/members/{}", server_id, owner_id);
+    let (status, _) = app
+        .request(Method::PATCH, &owner_uri, Some(&token_member), Some(json!({ "nickname": "Boss" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Absent fields are untouched, null clears
+    let (status, value) = app
+        .request(Method::PATCH, &member_uri, Some(&token_owner), Some(json!({ "avatar": null })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["nickname"], "Sprout");
+    assert!(value["server_avatar_url"].is_null());
+    let (status, _) = app.request(Method::GET, &avatar_url, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, value) = app
+        .request(Method::PATCH, &member_uri, Some(&token_member), Some(json!({ "nickname": " " })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", value);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn kicked_members_lose_their_server_avatar(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("mp_kick_owner").await;
+    let (token_member, member_id) = app.register_user("mp_kick_member").await;
+    let server_id = app.create_server(&token_owner, "Member Kick").await;
+    app.invite_and_join(&token_owner, &token_member, server_id).await;
+
+    let member_uri = format!("/api/v1/servers/{}/members/{}", server_id, member_id);
+    let avatar = B64.encode(png(16, 16, [1, 1, 1]));
+    let (status, value) = app
+        .request(Method::PATCH, &member_uri, Some(&token_member), Some(json!({ "avatar": avatar })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let avatar_url = value["server_avatar_url"].as_str().unwrap().to_string();
+
+    let (status, _) = app.request(Method::DELETE, &member_uri, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::GET, &avatar_url, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, value) = app.request(Method::GET, "/api/v1/users/me/media-usage", Some(&token_member), None).await;
+    assert_eq!(value["used_bytes"], 0);
+}
+
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 #[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
 async fn upload_empty_avatar_fails(pool: Pool) {
@@ -2635,6 +4356,56 @@ async fn sender_keys_non_member_forbidden(pool: Pool) {
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn rotated_sender_keys_supersede_and_exclude_removed_members(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("skr_a").await;
+    let (token_b, user_b) = app.register_user("skr_b").await;
+    let (token_c, user_c) = app.register_user("skr_c").await;
+    app.make_friends(&token_a, &token_b, "skr_b").await;
+    app.make_friends(&token_a, &token_c, "skr_c").await;
+
+    let body = json!({ "member_ids": [user_b, user_c], "encrypted_meta": B64.encode(b"group-meta") });
+    let (_, value) = app.request(Method::POST, "/api/v1/dm/group", Some(&token_a), Some(body)).await;
+    let channel_id = value["id"].as_str().unwrap().to_string();
+    let uri = format!("/api/v1/channels/{}/sender-keys", channel_id);
+
+    let old_dist = Uuid::new_v4();
+    let entries = |dist: Uuid, to: &[Uuid]| {
+        let d: Vec<_> = to
+            .iter()
+            .map(|u| json!({ "to_user_id": u, "distribution_id": dist, "encrypted_skdm": B64.encode(b"skdm") }))
+            .collect();
+        json!({ "distributions": d })
+    };
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token_a), Some(entries(old_dist, &[user_a, user_b, user_c])))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Removing C burns A's key; a rotation may not include C any more
+    let remove_uri = format!("/api/v1/channels/{}/members/{}", channel_id, user_c);
+    app.request(Method::DELETE, &remove_uri, Some(&token_a), None).await;
+
+    let new_dist = Uuid::new_v4();
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&token_a), Some(entries(new_dist, &[user_a, user_b, user_c])))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, value) = app
+        .request(Method::PUT, &uri, Some(&token_a), Some(entries(new_dist, &[user_a, user_b])))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["superseded"].as_i64(), Some(2));
+
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_b), None).await;
+    let keys = value.as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0]["distribution_id"].as_str().unwrap(), new_dist.to_string());
+}
+
 // ─── User Profile Extended ───────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -2847,6 +4618,79 @@ async fn add_member_to_group_dm(pool: Pool) {
     assert_eq!(value.as_array().unwrap().len(), 4);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn group_dm_owner_removes_and_transfers(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("grp_own_a").await;
+    let (token_b, user_b) = app.register_user("grp_own_b").await;
+    let (token_c, user_c) = app.register_user("grp_own_c").await;
+    app.make_friends(&token_a, &token_b, "grp_own_b").await;
+    app.make_friends(&token_a, &token_c, "grp_own_c").await;
+
+    let body = json!({ "member_ids": [user_b, user_c], "encrypted_meta": B64.encode(b"group-meta") });
+    let (status, value) = app.request(Method::POST, "/api/v1/dm/group", Some(&token_a), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["owner_id"], user_a.to_string());
+    let channel_id = value["id"].as_str().unwrap().to_string();
+
+    // Only the owner may remove members
+    let uri = format!("/api/v1/channels/{}/members/{}", channel_id, user_c);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let mem_uri = format!("/api/v1/channels/{}/members", channel_id);
+    let (_, value) = app.request(Method::GET, &mem_uri, Some(&token_a), None).await;
+    assert_eq!(value.as_array().unwrap().len(), 2);
+    let (status, _) = app.request(Method::GET, &mem_uri, Some(&token_c), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Hand ownership to B, who can then rename the group
+    let owner_uri = format!("/api/v1/channels/{}/owner", channel_id);
+    let (status, _) = app
+        .request(Method::PUT, &owner_uri, Some(&token_a), Some(json!({ "user_id": user_b })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(Method::PUT, &owner_uri, Some(&token_a), Some(json!({ "user_id": user_a })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let uri = format!("/api/v1/channels/{}", channel_id);
+    let (status, value) = app
+        .request(Method::PUT, &uri, Some(&token_a), Some(json!({ "encrypted_meta": B64.encode(b"renamed") })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["owner_id"], user_b.to_string());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn group_dm_owner_leaving_passes_ownership(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("grp_leave_own_a").await;
+    let (token_b, user_b) = app.register_user("grp_leave_own_b").await;
+    let (token_c, user_c) = app.register_user("grp_leave_own_c").await;
+    app.make_friends(&token_a, &token_b, "grp_leave_own_b").await;
+    app.make_friends(&token_a, &token_c, "grp_leave_own_c").await;
+
+    let body = json!({ "member_ids": [user_b, user_c], "encrypted_meta": B64.encode(b"group-meta") });
+    let (_, value) = app.request(Method::POST, "/api/v1/dm/group", Some(&token_a), Some(body)).await;
+    let channel_id = value["id"].as_str().unwrap().to_string();
+
+    let uri = format!("/api/v1/channels/{}/leave", channel_id);
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // One of the remaining members now owns the group and can remove the other
+    let (_, value) = app.request(Method::GET, "/api/v1/dm", Some(&token_b), None).await;
+    let group = value.as_array().unwrap().iter().find(|c| c["id"] == channel_id.as_str()).unwrap();
+    let owner = group["owner_id"].as_str().unwrap().to_string();
+    assert!(owner == user_b.to_string() || owner == user_c.to_string());
+}
+
 // ─── Message Search ──────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -3214,3 +5058,115 @@ async fn update_channel_requires_permission(pool: Pool) {
         .await;
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
+
+// ─── Rate Limit Policies ─────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn route_and_user_rate_limits_set_headers_and_refuse(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("rl_alice").await;
+    let (token_b, _) = app.register_user("rl_bob").await;
+    app.set_rate_limits(3, "GET /users/:user_id/profile=2/60");
+
+    let uri = format!("/api/v1/users/{}/profile", user_a);
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_a), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // The route bucket is the tightest one
+    assert_eq!(headers["x-ratelimit-limit"], "2");
+    assert_eq!(headers["x-ratelimit-remaining"], "1");
+    assert!(headers.contains_key("x-ratelimit-reset"));
+
+    let (status, _, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_a), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, headers, body) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_a), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(headers["x-ratelimit-remaining"], "0");
+    let retry_after: u64 = headers["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!((1..=30).contains(&retry_after));
+    assert_eq!(body["retry_after"], retry_after);
+
+    // Buckets are per caller
+    let (status, _, _) = app
+        .request_with_headers(Method::GET, &uri, Some(&token_b), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Per-user bucket applies across routes
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, "/api/v1/announcements", Some(&token_b), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["x-ratelimit-limit"], "3");
+    assert_eq!(headers["x-ratelimit-remaining"], "1");
+    let (status, _, _) = app
+        .request_with_headers(Method::GET, "/api/v1/announcements", Some(&token_b), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, "/api/v1/announcements", Some(&token_b), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(headers.contains_key("retry-after"));
+}
+
+// ─── OpenAPI ─────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn openapi_spec_is_served(pool: Pool) {
+    let app = TestApp::new(pool).await;
+
+    let (status, spec) = app.request(Method::GET, "/api/v1/openapi.json", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+
+    let restore = &spec["paths"]["/api/v1/servers/{server_id}/restore"]["post"];
+    assert_eq!(
+        restore["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/RestoreServerRequest"
+    );
+    assert!(spec["components"]["schemas"]["RestoreServerRequest"]["properties"].is_object());
+    assert!(spec["paths"]["/api/v1/sync"]["get"].is_object());
+    assert!(spec["components"]["securitySchemes"]["bearer"].is_object());
+
+    // Public endpoints opt out of the global bearer requirement
+    assert_eq!(spec["paths"]["/api/v1/auth/login"]["post"]["security"], json!([{}]));
+}
+
+// ─── API Versions ────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn api_versions_share_routes_and_mark_deprecated_ones(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("versioned").await;
+    let server_id = app.create_server(&token, "Versioned").await;
+
+    for prefix in ["/api/v1", "/api/v2"] {
+        let (status, headers, body) = app
+            .request_with_headers(Method::GET, &format!("{}/servers", prefix), Some(&token), &[], vec![])
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["id"], server_id.to_string());
+        assert!(!headers.contains_key("deprecation"));
+    }
+
+    app.set_deprecated_api_versions("v1=2027-06-30");
+    let (status, headers, _) = app
+        .request_with_headers(Method::GET, "/api/v1/servers", Some(&token), &[], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["deprecation"], "true");
+    assert_eq!(headers["sunset"], "Wed, 30 Jun 2027 00:00:00 GMT");
+    assert_eq!(headers["link"], "</api/v2>; rel=\"successor-version\"");
+
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, "/api/v2/servers", Some(&token), &[], vec![])
+        .await;
+    assert!(!headers.contains_key("deprecation"));
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use base64::Engine;
+use haven_backend::db::Pool;
+use serde_json::json;
+use uuid::Uuid;
+
+use common::TestApp;
+
+const B64: &base64::engine::GeneralPurpose = &base64::engine::general_purpose::STANDARD;
+
+/// Register a bridge as an operator and return (bridge_id, bridge_token).
+async fn register_bridge(app: &TestApp, admin_token: &str, prefix: &str, server_id: Option<Uuid>) -> (Uuid, String) {
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/bridges",
+            Some(admin_token),
+            Some(json!({ "name": "Matrix", "user_prefix": prefix, "server_id": server_id })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Register bridge failed: {}", value);
+    let token = value["token"].as_str().unwrap().to_string();
+    assert!(token.starts_with("hvb_"));
+    (Uuid::parse_str(value["id"].as_str().unwrap()).unwrap(), token)
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn bridge_puppets_post_and_receive_filtered_events(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (admin_token, admin_id) = app.register_user("bridge_admin").await;
+    app.make_admin(admin_id).await;
+    let server_id = app.create_server(&admin_token, "Bridged").await;
+    let channel_id = app.create_channel(&admin_token, server_id, "general").await;
+    let other_channel = app.create_channel(&admin_token, server_id, "offtopic").await;
+
+    let (bridge_id, bridge_token) = register_bridge(&app, &admin_token, "mx_", None).await;
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/api/v1/channels/{}/bridges/{}", channel_id, bridge_id),
+            Some(&admin_token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Puppet creation is idempotent per remote id.
+    let puppet_req = json!({ "remote_id": "@alice:matrix.org", "localpart": "alice", "display_name": "Alice" });
+    let (status, puppet) = app
+        .request(Method::PUT, "/api/v1/bridge/puppets", Some(&bridge_token), Some(puppet_req.clone()))
+        .await;
+    assert_eq!(status, StatusCode::OK, "Create puppet failed: {}", puppet);
+    assert_eq!(puppet["username"], "mx_alice");
+    let (_, again) = app
+        .request(Method::PUT, "/api/v1/bridge/puppets", Some(&bridge_token), Some(puppet_req))
+        .await;
+    assert_eq!(again["id"], puppet["id"]);
+
+    // Local messages in linked channels are queued; unlinked channels are not.
+    let (local_msg, _) = app.send_message(&admin_token, channel_id).await;
+    app.send_message(&admin_token, other_channel).await;
+
+    // The puppet posts with the bridged network's timestamp.
+    let (status, sent) = app
+        .request(
+            Method::POST,
+            "/api/v1/bridge/messages",
+            Some(&bridge_token),
+            Some(json!({
+                "channel_id": channel_id,
+                "sender_id": puppet["id"],
+                "sender_token": B64.encode(b"token"),
+                "encrypted_body": B64.encode(b"hello from matrix"),
+                "timestamp": "2025-06-01T12:00:00Z"
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Bridge send failed: {}", sent);
+    assert_eq!(sent["timestamp"], "2025-06-01T12:00:00Z");
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/bridge/messages",
+            Some(&bridge_token),
+            Some(json!({
+                "channel_id": other_channel,
+                "sender_id": puppet["id"],
+                "sender_token": B64.encode(b"token"),
+                "encrypted_body": B64.encode(b"nope")
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Replies must target a message in the same channel.
+    let (foreign_msg, _) = app.send_message(&admin_token, other_channel).await;
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/bridge/messages",
+            Some(&bridge_token),
+            Some(json!({
+                "channel_id": channel_id,
+                "sender_id": puppet["id"],
+                "sender_token": B64.encode(b"token"),
+                "encrypted_body": B64.encode(b"re: elsewhere"),
+                "reply_to_id": foreign_msg
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The bridge sees the local message but not its own puppet's echo.
+    let (status, events) = app
+        .request(Method::GET, "/api/v1/bridge/events", Some(&bridge_token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let list = events["events"].as_array().unwrap();
+    assert_eq!(list.len(), 1, "unexpected events: {}", events);
+    assert_eq!(list[0]["message"]["id"], local_msg.to_string());
+    assert_eq!(list[0]["channel_id"], channel_id.to_string());
+
+    // Acknowledging drains the queue.
+    let next = events["next_since"].as_i64().unwrap();
+    let (_, drained) = app
+        .request(
+            Method::GET,
+            &format!("/api/v1/bridge/events?since={}", next),
+            Some(&bridge_token),
+            None,
+        )
+        .await;
+    assert!(drained["events"].as_array().unwrap().is_empty());
+    assert_eq!(drained["next_since"], next);
+
+    // Channel members see the puppet's message in history.
+    let (_, history) = app
+        .request(
+            Method::GET,
+            &format!("/api/v1/channels/{}/messages", channel_id),
+            Some(&admin_token),
+            None,
+        )
+        .await;
+    assert!(history
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|m| m["id"] == sent["id"] && m["timestamp"] == "2025-06-01T12:00:00Z"));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn bridge_management_requires_privileges(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (admin_token, admin_id) = app.register_user("bridge_op").await;
+    app.make_admin(admin_id).await;
+    let (user_token, _) = app.register_user("bridge_user").await;
+    let server_id = app.create_server(&user_token, "Mine").await;
+    let channel_id = app.create_channel(&user_token, server_id, "general").await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/admin/bridges",
+            Some(&user_token),
+            Some(json!({ "name": "IRC", "user_prefix": "irc_" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (bridge_id, bridge_token) = register_bridge(&app, &admin_token, "irc_", Some(server_id)).await;
+
+    // A user JWT is not a bridge token and vice versa.
+    let (status, _) = app.request(Method::GET, "/api/v1/bridge/whoami", Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, me) = app.request(Method::GET, "/api/v1/bridge/whoami", Some(&bridge_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["id"], bridge_id.to_string());
+    assert!(me.get("token_hash").is_none());
+
+    // Only someone who can manage the channel may link it; the operator is
+    // not a member of this server.
+    let link = format!("/api/v1/channels/{}/bridges/{}", channel_id, bridge_id);
+    let (status, _) = app.request(Method::PUT, &link, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::PUT, &link, Some(&user_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Bridges registered elsewhere, or instance-wide, can't be linked by a
+    // server's managers.
+    let (instance_bridge, _) = register_bridge(&app, &admin_token, "xmpp_", None).await;
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/api/v1/channels/{}/bridges/{}", channel_id, instance_bridge),
+            Some(&user_token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Deleting the bridge revokes its token.
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/api/v1/admin/bridges/{}", bridge_id),
+            Some(&admin_token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::GET, "/api/v1/bridge/whoami", Some(&bridge_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -1,8 +1,9 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
     body::Body,
-    http::{header, Method, Request, StatusCode},
+    http::{header, HeaderMap, Method, Request, StatusCode},
     Router,
 };
 use dashmap::DashMap;
@@ -14,7 +15,7 @@ use uuid::Uuid;
 
 use base64::Engine;
 use sha2::{Digest, Sha256};
-use haven_backend::{build_router, config::AppConfig, memory_store::MemoryStore, middleware::UserRateLimiter, AppState};
+use haven_backend::{build_router, config::{AppConfig, LiveConfig}, db::{pool_monitor::PoolMonitor, shadow::ShadowReads}, jobs::JobQueue, memory_store::MemoryStore, middleware::{LatencyBudgets, RatePolicies, UserRateLimiter}, shutdown::Shutdown, ws::HeartbeatStats, ws_outbox::WsOutbox, AppState};
 
 /// Solve a PoW challenge by brute-forcing a nonce until SHA-256(challenge + nonce)
 /// has the required number of leading zero bits.
@@ -63,7 +64,13 @@ impl TestApp {
             database_url: String::new(),
             database_replica_url: String::new(),
             db_max_connections: 5,
+            db_replica_strategy: "round-robin".into(),
+            db_replica_max_lag_secs: 10,
+            db_auto_migrate: true,
+            db_slow_query_ms: 500,
+            db_pool_saturation_ms: 1000,
             redis_url: "redis://127.0.0.1:6379".into(),
+            pubsub_backend: "redis".into(),
             jwt_secret: "test-jwt-secret-that-is-long-enough-for-hmac".into(),
             jwt_expiry_hours: 24,
             refresh_token_expiry_days: 30,
@@ -75,14 +82,36 @@ impl TestApp {
             s3_access_key: String::new(),
             s3_secret_key: String::new(),
             s3_region: String::new(),
+            backup_storage_dir: "/tmp/haven-test-backups".into(),
+            backup_s3_bucket: String::new(),
             cors_origins: "*".into(),
+            api_deprecated_versions: String::new(),
             max_requests_per_minute: 10000,
+            rate_limit_per_user: 0,
+            rate_limit_routes: String::new(),
+            rate_limit_redis: false,
             max_ws_connections_per_user: 10,
             broadcast_channel_capacity: 4096,
             ws_heartbeat_timeout_secs: 30,
+            ws_heartbeat_interval_secs: 10,
             ws_session_buffer_size: 500,
             ws_session_ttl_secs: 300,
+            ws_coalesce_window_ms: 250,
             max_upload_size_bytes: 10_000_000,
+            profile_media_quota_bytes: 16 * 1024 * 1024,
+            voice_note_max_duration_secs: 300,
+            live_location_max_duration_secs: 28_800,
+            server_storage_quota_bytes: 0,
+            server_message_rate_per_minute: 0,
+            server_max_members: 0,
+            max_group_dm_members: 10,
+            prekey_low_watermark: 20,
+            event_reminder_minutes: 15,
+            interactive_timeout_secs: 15,
+            job_timeout_secs: 600,
+            shutdown_drain_secs: 2,
+            shadow_read_sample_rate: 0.0,
+            thumbnails_enabled: true,
             cdn_enabled: false,
             cdn_base_url: String::new(),
             cdn_presign_expiry_secs: 3600,
@@ -92,29 +121,68 @@ impl TestApp {
             livekit_api_secret: String::new(),
             livekit_bundled: false,
             livekit_port: 7880,
+            turn_urls: "turn:turn.example.com:3478".into(),
+            turn_secret: "test-turn-secret".into(),
+            turn_credential_ttl_secs: 3600,
+            federation_server_name: String::new(),
+            federation_signing_key: String::new(),
+            federation_allowlist: String::new(),
+            federation_denylist: String::new(),
+            federation_user_cache_ttl_secs: 3600,
+            federation_key_cache_ttl_secs: 86400,
+            irc_port: 0,
             tls_enabled: false,
             tls_port: 8443,
             tls_cert_path: "./data/certs/cert.pem".into(),
             tls_key_path: "./data/certs/key.pem".into(),
             tls_auto_generate: false,
             audit_log_retention_days: 90,
+            message_partition_months_ahead: 3,
+            message_partition_retention_months: 0,
             resolved_report_retention_days: 180,
+            sync_journal_retention_days: 7,
+            device_queue_ttl_days: 30,
             expired_invite_cleanup: true,
+            attachment_gc_grace_hours: 0,
+            attachment_gc_dry_run: false,
+            attachment_scanner: String::new(),
+            attachment_scanner_url: String::new(),
+            attachment_scan_timeout_secs: 30,
+            attachment_scan_fail_open: false,
+            attachment_url_signing_keys: String::new(),
+            attachment_url_ttl_secs: 300,
+            attachment_url_base: String::new(),
+            telemetry_endpoint: String::new(),
+            telemetry_token: String::new(),
+            telemetry_instance_id: String::new(),
             registration_invite_only: false,
+            registration_mode: "open".into(),
             registration_invites_per_user: 3,
             giphy_api_key: String::new(),
 
             turnstile_site_key: String::new(),
             turnstile_secret_key: String::new(),
 
+            abuse_ip_lists: String::new(),
+            abuse_block_score: 0,
+            abuse_ip_retention_hours: 24,
+
             smtp_host: String::new(),
             smtp_port: 587,
             smtp_username: String::new(),
             smtp_password: String::new(),
             smtp_from: String::new(),
+            email_provider: "smtp".into(),
+            email_api_key: String::new(),
+            email_domain: String::new(),
+            email_api_url: String::new(),
+            email_template_dir: String::new(),
 
             beta_code_limit: 50,
             beta_code_expiry_days: 7,
+            beta_block_disposable_emails: true,
+            beta_blocked_email_domains: String::new(),
+            beta_require_mx: false,
             trust_proxy: false,
         };
 
@@ -133,25 +201,135 @@ impl TestApp {
             encryption_key: storage_key,
         };
 
+        let backup_storage = haven_backend::storage::Storage::backup_from_config(&config, storage_key);
+
+        let rate_policies = RatePolicies::new(&config, None);
+
         let state = AppState {
             db: haven_backend::db::DbPools::from_single(pool),
             redis: Some(redis),
+            live_config: LiveConfig::new(config.clone()),
             config,
             storage_key,
+            backup_storage,
             storage,
+            email_templates: haven_backend::email::EmailTemplates::builtin(),
+            abuse_lists: Default::default(),
             connections: Arc::new(DashMap::new()),
             channel_broadcasts: Arc::new(DashMap::new()),
-            pubsub_subscriptions: haven_backend::pubsub::empty_subscriptions(),
+            pubsub: haven_backend::pubsub::PubSub::local(),
             memory: MemoryStore::new(),
             ws_rate_limiter: UserRateLimiter::new(1000, 10),
             api_rate_limiter: UserRateLimiter::new(1000, 60),
             sessions: Arc::new(DashMap::new()),
             ban_cache: haven_backend::cache::BanCache::new(60),
+            latency_budgets: LatencyBudgets::new(Duration::from_secs(15), Duration::from_secs(600)),
+            rate_policies,
+            shadow_reads: ShadowReads::new(0.0),
+            db_pool: PoolMonitor::new(Duration::from_millis(1000)),
+            jobs: JobQueue::new(),
+            ws_outbox: WsOutbox::new(),
+            ws_heartbeats: HeartbeatStats::new(),
+            shutdown: Shutdown::new(Duration::from_secs(2)),
         };
 
+        haven_backend::jobs::spawn_runner(state.clone());
+        haven_backend::ws_outbox::spawn_dispatcher(state.clone());
+
         TestApp { state }
     }
 
+    /// Replace the latency budgets (e.g. to force handler timeouts).
+    pub fn set_latency_budgets(&mut self, budgets: LatencyBudgets) {
+        self.state.latency_budgets = budgets;
+    }
+
+    /// Rebuild the rate limit policies from config, e.g. after changing
+    /// `rate_limit_per_user` or `rate_limit_routes`.
+    pub fn set_rate_limits(&mut self, per_user: u32, routes: &str) {
+        self.state.config.rate_limit_per_user = per_user;
+        self.state.config.rate_limit_routes = routes.into();
+        self.state.rate_policies = RatePolicies::new(&self.state.config, None);
+    }
+
+    /// Mark API versions deprecated (`API_DEPRECATED_VERSIONS` syntax).
+    pub fn set_deprecated_api_versions(&mut self, spec: &str) {
+        self.state.config.api_deprecated_versions = spec.into();
+    }
+
+    /// Trust `X-Forwarded-For` / `X-Real-IP` for the client IP.
+    pub fn set_trust_proxy(&mut self, trust: bool) {
+        self.state.config.trust_proxy = trust;
+    }
+
+    /// Change the per-user profile media quota.
+    pub fn set_profile_media_quota(&mut self, bytes: u64) {
+        self.state.config.profile_media_quota_bytes = bytes;
+    }
+
+    /// Cap the duration of voice notes.
+    pub fn set_voice_note_max_duration(&mut self, secs: u32) {
+        self.state.config.voice_note_max_duration_secs = secs;
+    }
+
+    /// Scan attachments in unencrypted channels (`ATTACHMENT_SCANNER` syntax).
+    pub fn set_attachment_scanner(&mut self, scanner: &str, url: &str) {
+        self.state.config.attachment_scanner = scanner.into();
+        self.state.config.attachment_scanner_url = url.into();
+    }
+
+    /// Drop message partitions older than this many months (0 = keep forever).
+    pub fn set_message_partition_retention(&mut self, months: u32) {
+        self.state.config.message_partition_retention_months = months;
+    }
+
+    /// Change how often WS clients must heartbeat and how long a silent
+    /// connection lives before it is reaped.
+    pub fn set_ws_heartbeat(&mut self, interval_secs: u64, timeout_secs: u64) {
+        self.state.config.ws_heartbeat_interval_secs = interval_secs;
+        self.state.config.ws_heartbeat_timeout_secs = timeout_secs;
+    }
+
+    /// Reload the config as SIGHUP would, with `edit` applied to the current one.
+    pub fn reload_config(&self, edit: impl FnOnce(&mut AppConfig)) -> Vec<&'static str> {
+        let mut fresh = self.state.config.clone();
+        edit(&mut fresh);
+        self.state.reload_config(&fresh)
+    }
+
+    /// Start draining as if the process got SIGTERM.
+    pub fn begin_shutdown(&self) {
+        self.state.shutdown.trigger();
+    }
+
+    /// Replace the shadow-read harness (e.g. to shadow every read).
+    pub fn set_shadow_reads(&mut self, shadow_reads: ShadowReads) {
+        self.state.shadow_reads = shadow_reads;
+    }
+
+    /// Replace the pool monitor (e.g. to open the load-shedding breaker).
+    pub fn set_db_pool(&mut self, db_pool: PoolMonitor) {
+        self.state.db_pool = db_pool;
+    }
+
+    /// Turn on federation as `server_name`, signing with the given 32-byte seed.
+    pub fn enable_federation(&mut self, server_name: &str, seed: [u8; 32]) {
+        self.state.config.federation_server_name = server_name.into();
+        self.state.config.federation_signing_key = base64::engine::general_purpose::STANDARD.encode(seed);
+    }
+
+    /// Refuse federation with the given comma-separated servers.
+    pub fn set_federation_denylist(&mut self, servers: &str) {
+        self.state.config.federation_denylist = servers.into();
+    }
+
+    /// Pin a remote server's signing key so no network fetch is needed.
+    pub async fn trust_federation_server(&self, server_name: &str, public_key: &[u8]) {
+        haven_backend::db::queries::upsert_federation_server_key(self.state.db.write(), server_name, public_key)
+            .await
+            .expect("Failed to pin federation server key");
+    }
+
     /// Get a fresh clone of the router for a `oneshot` request.
     fn router(&self) -> Router {
         build_router(self.state.clone())
@@ -222,6 +400,18 @@ impl TestApp {
 
     /// Register a new user with dummy crypto keys. Returns (access_token, user_id).
     pub async fn register_user(&self, username: &str) -> (String, Uuid) {
+        let (status, value) = self.try_register(username, None).await;
+
+        assert_eq!(status, StatusCode::OK, "Registration failed: {}", value);
+
+        let token = value["access_token"].as_str().unwrap().to_string();
+        let user_id = Uuid::parse_str(value["user"]["id"].as_str().unwrap()).unwrap();
+        (token, user_id)
+    }
+
+    /// Attempt a registration, optionally with a registration invite code.
+    /// Returns the raw (status, body).
+    pub async fn try_register(&self, username: &str, invite_code: Option<&str>) -> (StatusCode, Value) {
         // Step 1: Get a PoW challenge from the server
         let (challenge_status, challenge_value) = self
             .request(Method::GET, "/api/v1/auth/challenge", None, None)
@@ -246,16 +436,11 @@ impl TestApp {
             "signed_prekey_signature": fake_sig,
             "one_time_prekeys": [],
             "pow_challenge": challenge,
-            "pow_nonce": nonce
+            "pow_nonce": nonce,
+            "invite_code": invite_code
         });
 
-        let (status, value) = self.request(Method::POST, "/api/v1/auth/register", None, Some(body)).await;
-
-        assert_eq!(status, StatusCode::OK, "Registration failed: {}", value);
-
-        let token = value["access_token"].as_str().unwrap().to_string();
-        let user_id = Uuid::parse_str(value["user"]["id"].as_str().unwrap()).unwrap();
-        (token, user_id)
+        self.request(Method::POST, "/api/v1/auth/register", None, Some(body)).await
     }
 
     /// Login an existing user. Returns (access_token, refresh_token, user_id).
@@ -425,6 +610,14 @@ impl TestApp {
         Uuid::parse_str(value["id"].as_str().unwrap()).unwrap()
     }
 
+    /// Register a stand-in WebSocket connection for `user_id` and return the
+    /// receiving end of everything fanned out to it.
+    pub fn connect_user(&self, user_id: Uuid) -> tokio::sync::mpsc::UnboundedReceiver<haven_backend::models::WsServerMessage> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.state.connections.entry(user_id).or_default().push(tx);
+        rx
+    }
+
     /// Send raw bytes as a request body (for attachment upload).
     pub async fn request_bytes(
         &self,
@@ -458,4 +651,43 @@ impl TestApp {
 
         (status, value)
     }
+
+    /// Send raw bytes with extra headers; returns (status, response headers, body).
+    /// Used by the resumable upload tests, which speak Upload-Offset headers.
+    pub async fn request_with_headers(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        headers: &[(&str, &str)],
+        body_bytes: Vec<u8>,
+    ) -> (StatusCode, HeaderMap, Value) {
+        let mut builder = Request::builder().method(method).uri(uri);
+
+        if let Some(t) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", t));
+        }
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+
+        let req = builder.body(Body::from(body_bytes)).unwrap();
+
+        let response = self.router().oneshot(req).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), 1024 * 1024)
+            .await
+            .unwrap();
+
+        let value = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap_or(Value::String(
+                String::from_utf8_lossy(&bytes).to_string(),
+            ))
+        };
+
+        (status, headers, value)
+    }
 }
@@ -3,6 +3,7 @@ mod common;
 use axum::http::{Method, StatusCode};
 use base64::Engine;
 use haven_backend::db::Pool;
+use haven_backend::models::WsServerMessage;
 use serde_json::json;
 use uuid::Uuid;
 
@@ -36,7 +37,7 @@ async fn verify_export_invalid_signature_length_returns_400(pool: Pool) {
     let app = TestApp::new(pool).await;
     let (_, user_id) = app.register_user("verify1").await;
 
-    let (status, _) = app
+    let (status, value) = app
         .request(
             Method::POST,
             "/api/v1/exports/verify",
@@ -49,6 +50,34 @@ async fn verify_export_invalid_signature_length_returns_400(pool: Pool) {
         .await;
 
     assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_SIGNATURE");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn verify_export_rejects_oversized_and_deeply_nested_manifests(pool: Pool) {
+    let app = TestApp::new(pool).await;
+
+    let huge = json!({ "manifest": { "padding": "a".repeat(2 * 1024 * 1024) }, "signature": "" });
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/exports/verify", None, Some(huge))
+        .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(value["code"], "PAYLOAD_TOO_LARGE");
+
+    let mut nested = json!({});
+    for _ in 0..100 {
+        nested = json!({ "n": nested });
+    }
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/exports/verify",
+            None,
+            Some(json!({ "manifest": nested, "signature": "" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "JSON_TOO_COMPLEX");
 }
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -132,6 +161,152 @@ async fn restore_server_as_owner(pool: Pool) {
     assert!(value["channels_created"].is_number());
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_keeps_layout_and_system_channel(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("restore_layout").await;
+    let server_id = app.create_server(&token, "Layout Server").await;
+
+    let channel = |id: &str, position: i32| {
+        json!({
+            "id": id, "name": id, "type": "text", "category_id": "cat-1",
+            "position": position, "encrypted": false, "is_private": false
+        })
+    };
+    let uri = format!("/api/v1/servers/{}/restore", server_id);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({
+                "server": { "id": "old", "name": "Restored", "system_channel_id": "welcome" },
+                "categories": [{ "id": "cat-1", "name": "Info", "position": 3, "collapsed_by_default": true }],
+                // Tied positions keep backup order
+                "channels": [channel("rules", 5), channel("welcome", 0), channel("faq", 5)],
+                "roles": [],
+                "permission_overwrites": []
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let mapped = |id: &str| value["channel_id_map"][id].as_str().unwrap().to_string();
+
+    let (_, server) = app.request(Method::GET, &format!("/api/v1/servers/{}", server_id), Some(&token), None).await;
+    assert_eq!(server["system_channel_id"].as_str().unwrap(), mapped("welcome"));
+
+    let (_, categories) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/categories", server_id), Some(&token), None)
+        .await;
+    assert_eq!(categories[0]["position"], 0);
+    assert_eq!(categories[0]["collapsed_by_default"], true);
+
+    let (_, channels) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/channels", server_id), Some(&token), None)
+        .await;
+    let position = |id: String| {
+        channels.as_array().unwrap().iter().find(|c| c["id"] == id.as_str()).unwrap()["position"].as_i64()
+    };
+    assert_eq!(position(mapped("welcome")), Some(0));
+    assert_eq!(position(mapped("rules")), Some(1));
+    assert_eq!(position(mapped("faq")), Some(2));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_carries_channel_settings_to_restored_channels(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("restore_remap").await;
+    let server_id = app.create_server(&token, "Remap Server").await;
+    let channel_id = app.create_channel(&token, server_id, "logs").await;
+
+    let retention = format!("/api/v1/channels/{}/retention", channel_id);
+    let body = json!({ "mode": "days", "value": 30 });
+    let (status, _) = app.request(Method::PUT, &retention, Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A backup of this same server refers to the channel by its current id
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Restored" },
+                "categories": [],
+                "channels": [{
+                    "id": channel_id.to_string(),
+                    "name": "logs",
+                    "type": "text",
+                    "category_id": null,
+                    "position": 0,
+                    "encrypted": false,
+                    "is_private": false
+                }],
+                "roles": [],
+                "permission_overwrites": []
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["channel_rows_remapped"], 1);
+
+    let restored = value["channel_id_map"][channel_id.to_string()].as_str().unwrap();
+    let (status, policy) = app
+        .request(Method::GET, &format!("/api/v1/channels/{}/retention", restored), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(policy["mode"], "days");
+    assert_eq!(policy["value"], 30);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_audits_what_it_removed(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("restore_audit").await;
+    let server_id = app.create_server(&token, "Audit Server").await;
+    app.create_channel(&token, server_id, "doomed").await;
+    let roles = format!("/api/v1/servers/{}/roles", server_id);
+    let body = json!({ "name": "Doomed", "permissions": "0", "position": 1 });
+    let (status, _) = app.request(Method::POST, &roles, Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Restored" },
+                "categories": [],
+                "channels": [],
+                "roles": [],
+                "permission_overwrites": []
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let audit = format!("/api/v1/servers/{}/audit-log", server_id);
+    let (_, entries) = app.request(Method::GET, &audit, Some(&token), None).await;
+    let entry = |action: &str| {
+        entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["action"] == action)
+            .unwrap_or_else(|| panic!("no {} entry", action))["changes"]
+            .clone()
+    };
+
+    // Channels were detached into the snapshot, so the wipe only deletes the role
+    let detach = entry("server_restore_detach");
+    assert!(detach["channels_detached"].as_i64().unwrap() >= 1);
+    assert!(detach["snapshot_id"].is_string());
+    let wipe = entry("server_restore_wipe");
+    assert_eq!(wipe["roles_deleted"], 1);
+    assert_eq!(wipe["channels_deleted"], 0);
+}
+
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
 async fn restore_server_non_member_returns_403(pool: Pool) {
     let app = TestApp::new(pool).await;
@@ -156,3 +331,534 @@ async fn restore_server_non_member_returns_403(pool: Pool) {
 
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_over_limit_returns_coded_error(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("restore_limit").await;
+    let server_id = app.create_server(&token, "Restore Limit").await;
+
+    let roles: Vec<_> = (0..251)
+        .map(|i| {
+            json!({
+                "id": format!("role-{}", i),
+                "name": "r",
+                "permissions": 0,
+                "position": i,
+                "is_default": false
+            })
+        })
+        .collect();
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Too Many Roles" },
+                "categories": [],
+                "channels": [],
+                "roles": roles
+            })),
+        )
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "RESTORE_LIMIT_EXCEEDED");
+    assert!(value["trace_id"].as_str().is_some_and(|id| !id.is_empty()));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_round_trips_config_sections(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("restore_sections").await;
+    let server_id = app.create_server(&token, "Sections").await;
+
+    let filters_uri = format!("/api/v1/servers/{}/content-filters", server_id);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &filters_uri,
+            Some(&token),
+            Some(json!({ "pattern": "spam+", "filter_type": "regex", "action": "warn" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, export) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/export", server_id), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let filters = export["sections"]["content_filters"].as_array().unwrap();
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0]["pattern"], "spam+");
+    assert!(export["sections"]["emojis"].is_array());
+    // Emojis are exported once, as a section
+    assert!(export.get("emojis").is_none());
+
+    // Restore the exported sections, plus one from a newer server version
+    let mut sections = export["sections"].clone();
+    sections["webhooks"] = json!([{ "name": "ci" }]);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Sections" },
+                "categories": [],
+                "channels": [],
+                "roles": [],
+                "sections": sections
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Restore failed: {}", value);
+    assert_eq!(value["sections_restored"]["content_filters"], 1);
+    assert_eq!(value["sections_skipped"], json!(["webhooks"]));
+
+    // Restoring replaced, rather than duplicated, the filter
+    let (_, listed) = app.request(Method::GET, &filters_uri, Some(&token), None).await;
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["filter_type"], "regex");
+    assert_eq!(listed[0]["action"], "warn");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_round_trips_member_nicknames(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("restore_nicks").await;
+    let server_id = app.create_server(&token, "Nicknames").await;
+    let member_uri = format!("/api/v1/servers/{}/members/{}", server_id, user_id);
+
+    let (status, _) = app
+        .request(Method::PATCH, &member_uri, Some(&token), Some(json!({ "nickname": "Archivist" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, export) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/export", server_id), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        export["sections"]["member_nicknames"],
+        json!([{ "user_id": user_id.to_string(), "nickname": "Archivist" }])
+    );
+
+    let (status, _) = app
+        .request(Method::PATCH, &member_uri, Some(&token), Some(json!({ "nickname": null })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Entries for users who are no longer members are skipped
+    let mut nicknames = export["sections"]["member_nicknames"].clone();
+    nicknames.as_array_mut().unwrap().push(json!({ "user_id": Uuid::new_v4().to_string(), "nickname": "Ghost" }));
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Nicknames" },
+                "categories": [],
+                "channels": [],
+                "roles": [],
+                "sections": { "member_nicknames": nicknames }
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "Restore failed: {}", value);
+    assert_eq!(value["sections_restored"]["member_nicknames"], 1);
+
+    let (_, members) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/members", server_id), Some(&token), None)
+        .await;
+    assert_eq!(members[0]["nickname"], "Archivist");
+}
+
+// ─── Export Format v2 ─────────────────────────────────────
+
+/// Build a v2 manifest and its chunks from `(section, json)` pairs.
+fn v2_archive(user_id: Uuid, sections: &[(&str, serde_json::Value)]) -> (serde_json::Value, Vec<serde_json::Value>) {
+    use haven_backend::export_format::encode_section;
+
+    let mut entries = Vec::new();
+    let mut chunks = Vec::new();
+    for (name, data) in sections {
+        for (entry, bytes) in encode_section(name, &serde_json::to_vec(data).unwrap()).unwrap() {
+            chunks.push(json!({ "section": entry.section, "index": entry.index, "data": B64.encode(bytes) }));
+            entries.push(entry);
+        }
+    }
+    let manifest = json!({
+        "format_version": 2,
+        "exported_by": { "user_id": user_id.to_string() },
+        "compression": "zstd",
+        "chunks": entries,
+    });
+    (manifest, chunks)
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_server_from_v2_archive(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("restore_v2").await;
+    let server_id = app.create_server(&token, "Restore V2").await;
+
+    let (manifest, mut chunks) = v2_archive(
+        user_id,
+        &[
+            ("server", json!({ "id": server_id.to_string(), "name": "Restored" })),
+            ("categories", json!([{ "id": "cat-1", "name": "General", "position": 0 }])),
+            (
+                "channels",
+                json!([{
+                    "id": "ch-1",
+                    "name": "general",
+                    "type": "text",
+                    "category_id": "cat-1",
+                    "position": 0,
+                    "encrypted": false,
+                    "is_private": false
+                }]),
+            ),
+            ("roles", json!([])),
+            // Message history is imported separately; restore ignores it
+            ("messages", json!([])),
+        ],
+    );
+
+    let uri = format!("/api/v1/servers/{}/restore", server_id);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({ "format_version": 2, "manifest": manifest, "chunks": chunks })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["categories_created"], 1);
+    assert_eq!(value["channels_created"], 1);
+
+    // A chunk that doesn't match its manifest hash is refused
+    chunks[1]["data"] = json!(B64.encode(zstd_of(b"[]")));
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({ "format_version": 2, "manifest": manifest, "chunks": chunks })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "CHUNK_HASH_MISMATCH");
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({ "format_version": 3, "manifest": manifest, "chunks": chunks })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "UNSUPPORTED_FORMAT");
+}
+
+fn zstd_of(data: &[u8]) -> Vec<u8> {
+    haven_backend::export_format::encode_section("x", data).unwrap().remove(0).1
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn verify_export_checks_v2_chunks(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (_, user_id) = app.register_user("verify_v2").await;
+
+    let (manifest, mut chunks) = v2_archive(user_id, &[("roles", json!([]))]);
+    let verify = |manifest: serde_json::Value, chunks: Option<Vec<serde_json::Value>>| {
+        json!({ "manifest": manifest, "signature": B64.encode([0u8; 64]), "chunks": chunks })
+    };
+
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/exports/verify", None, Some(verify(manifest.clone(), None)))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["format_version"], 2);
+    assert!(value.get("chunks_valid").is_none());
+
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/exports/verify", None, Some(verify(manifest.clone(), Some(chunks.clone()))))
+        .await;
+    assert_eq!(value["chunks_valid"], true);
+
+    chunks[0]["data"] = json!(B64.encode(zstd_of(b"{}")));
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/exports/verify", None, Some(verify(manifest, Some(chunks))))
+        .await;
+    assert_eq!(value["chunks_valid"], false);
+
+    // A v2 manifest without a usable chunk list is malformed
+    let broken = json!({ "format_version": 2, "exported_by": { "user_id": user_id.to_string() } });
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/exports/verify", None, Some(verify(broken, None)))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_MANIFEST");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_job_tracks_message_import_progress(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("restore_job").await;
+    let (other_token, _) = app.register_user("restore_job_other").await;
+    let server_id = app.create_server(&token, "Restore Job").await;
+    let mut rx = app.connect_user(user_id);
+
+    let uri = format!("/api/v1/servers/{}/restore?total_messages=3", server_id);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Restored" },
+                "categories": [],
+                "channels": [{
+                    "id": "ch-1", "name": "general", "type": "text", "category_id": null,
+                    "position": 0, "encrypted": false, "is_private": false
+                }],
+                "roles": [],
+                "permission_overwrites": []
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let job_id = value["job_id"].as_str().unwrap().to_string();
+    let channel_id = value["channel_id_map"]["ch-1"].as_str().unwrap().to_string();
+
+    let message = |n: u8| {
+        json!({
+            "sender_token": B64.encode([n]),
+            "encrypted_body": B64.encode([n]),
+            "timestamp": "2024-01-01T00:00:00.000Z",
+            "message_type": "user",
+            "has_attachments": false
+        })
+    };
+    let import_uri = format!("/api/v1/channels/{}/import-messages?job_id={}", channel_id, job_id);
+    let (status, _) = app
+        .request(Method::POST, &import_uri, Some(&token), Some(json!({ "messages": [message(1), message(2)] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A reloaded client finds the running job and re-attaches
+    let (status, jobs) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/restore-jobs", server_id), Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(jobs[0]["id"], job_id.as_str());
+    assert_eq!(jobs[0]["phase"], "messages");
+    assert_eq!(jobs[0]["done"], 2);
+    assert_eq!(jobs[0]["total"], 3);
+    assert_eq!(jobs[0]["status"], "running");
+
+    let (status, _) = app
+        .request(Method::POST, &import_uri, Some(&token), Some(json!({ "messages": [message(3)] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let job_uri = format!("/api/v1/servers/{}/restore-jobs/{}", server_id, job_id);
+    let (_, job) = app.request(Method::GET, &job_uri, Some(&token), None).await;
+    assert_eq!(job["done"], 3);
+    assert_eq!(job["status"], "completed");
+
+    // Other users can neither see nor advance the job
+    let (status, _) = app.request(Method::GET, &job_uri, Some(&other_token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Every step was pushed to the requester's connection, and members
+    // learned the channel map instead of having to refetch everything
+    let mut progress = Vec::new();
+    let mut restored = None;
+    while let Ok(msg) = rx.try_recv() {
+        match msg {
+            WsServerMessage::RestoreProgress { phase, done, total, status, .. } => {
+                progress.push((phase, done, total, status))
+            }
+            WsServerMessage::ServerRestored { channel_id_map, counts, .. } => restored = Some((channel_id_map, counts)),
+            _ => {}
+        }
+    }
+    let (channel_id_map, counts) = restored.unwrap();
+    assert_eq!(channel_id_map["ch-1"], channel_id);
+    assert_eq!(counts.channels_created, 1);
+    let step = |phase: &str, done: i64, total: i64, status: &str| {
+        (phase.to_string(), done, total, status.to_string())
+    };
+    assert_eq!(
+        progress,
+        vec![
+            step("structure", 0, 1, "running"),
+            step("messages", 0, 3, "running"),
+            step("messages", 2, 3, "running"),
+            step("messages", 3, 3, "completed"),
+        ]
+    );
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn restore_rollback_brings_back_channels_and_messages(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("rollback1").await;
+    let server_id = app.create_server(&token, "Rollback Server").await;
+    let channel_id = app.create_channel(&token, server_id, "history").await;
+    let (message_id, _) = app.send_message(&token, channel_id).await;
+
+    let channels_uri = format!("/api/v1/servers/{}/channels", server_id);
+    let messages_uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (_, before) = app.request(Method::GET, &channels_uri, Some(&token), None).await;
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/restore", server_id),
+            Some(&token),
+            Some(json!({
+                "server": { "id": server_id.to_string(), "name": "Restored" },
+                "categories": [],
+                "channels": [{
+                    "id": "ch-1", "name": "restored", "type": "text", "category_id": null,
+                    "position": 0, "encrypted": false, "is_private": false
+                }],
+                "roles": [],
+                "permission_overwrites": []
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["snapshot_id"].is_string());
+
+    // The old channel is detached: gone from the server and unreadable
+    let (_, during) = app.request(Method::GET, &channels_uri, Some(&token), None).await;
+    assert_eq!(during.as_array().unwrap().len(), 1);
+    let (status, _) = app.request(Method::GET, &messages_uri, Some(&token), None).await;
+    assert_ne!(status, StatusCode::OK);
+
+    let rollback_uri = format!("/api/v1/servers/{}/restore/rollback", server_id);
+    let (status, value) = app.request(Method::POST, &rollback_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["channels_restored"].as_u64(), Some(before.as_array().unwrap().len() as u64));
+
+    let (_, after) = app.request(Method::GET, &channels_uri, Some(&token), None).await;
+    let ids = |list: &serde_json::Value| {
+        let mut ids: Vec<String> = list.as_array().unwrap().iter().map(|c| c["id"].to_string()).collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(&after), ids(&before));
+
+    let (status, messages) = app.request(Method::GET, &messages_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(messages.to_string().contains(&message_id.to_string()));
+
+    // The snapshot is used up
+    let (status, value) = app.request(Method::POST, &rollback_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(value["code"], "NO_RESTORE_SNAPSHOT");
+}
+
+// ─── Server Migration ─────────────────────────────────────
+
+async fn set_identity_key(app: &TestApp, token: &str, key: u8) {
+    let body = json!({
+        "identity_key": B64.encode([key; 32]),
+        "signed_prekey": B64.encode([key; 32]),
+        "signed_prekey_signature": B64.encode([key; 64])
+    });
+    let (status, _) = app.request(Method::PUT, "/api/v1/keys/identity", Some(token), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn migrate_server_maps_members_by_identity_key(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("migrate_owner").await;
+    let (token_member, member_id) = app.register_user("migrate_member").await;
+    set_identity_key(&app, &token_owner, 11).await;
+    set_identity_key(&app, &token_member, 12).await;
+
+    let source_id = app.create_server(&token_owner, "Source").await;
+    app.create_channel(&token_owner, source_id, "general").await;
+    app.invite_and_join(&token_owner, &token_member, source_id).await;
+    let (_, role) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/roles", source_id),
+            Some(&token_owner),
+            Some(json!({ "name": "Moderator", "position": 1 })),
+        )
+        .await;
+    let role_id = role["id"].as_str().unwrap().to_string();
+    app.request(
+        Method::PUT,
+        &format!("/api/v1/servers/{}/members/{}/roles", source_id, member_id),
+        Some(&token_owner),
+        Some(json!({ "role_id": role_id })),
+    )
+    .await;
+
+    // Only the owner can move a server
+    let export_uri = format!("/api/v1/servers/{}/migrate", source_id);
+    let (status, _) = app.request(Method::POST, &export_uri, Some(&token_member), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, mut bundle) = app.request(Method::POST, &export_uri, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bundle["version"], 1);
+    let exported = bundle["members"].as_array().unwrap();
+    assert_eq!(exported.len(), 2);
+    let member = exported.iter().find(|m| m["username"] == "migrate_member").unwrap();
+    assert_eq!(member["identity_key"], B64.encode([12u8; 32]));
+    assert_eq!(member["role_ids"], json!([role_id]));
+
+    // Someone who never brought their key to this instance
+    bundle["members"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({ "identity_key": B64.encode([99u8; 32]), "username": "ghost", "role_ids": [role_id] }));
+
+    let target_id = app.create_server(&token_owner, "Target").await;
+    let import_uri = format!("/api/v1/servers/{}/migrate/import", target_id);
+    let (status, _) = app.request(Method::POST, &import_uri, Some(&token_member), Some(bundle.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, value) = app.request(Method::POST, &import_uri, Some(&token_owner), Some(bundle)).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert_eq!(value["members_added"], 1);
+    assert_eq!(value["roles_assigned"], 1);
+    assert_eq!(value["unmatched_members"], json!(["ghost"]));
+    assert_eq!(value["restore"]["roles_created"], 1);
+
+    let (_, roles) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/roles", target_id), Some(&token_owner), None)
+        .await;
+    let new_role_id = roles
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["name"] == "Moderator")
+        .map(|r| r["id"].clone())
+        .unwrap();
+    let (_, members) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/members", target_id), Some(&token_owner), None)
+        .await;
+    let migrated = members
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["user_id"] == member_id.to_string())
+        .expect("member was not migrated");
+    assert_eq!(migrated["role_ids"], json!([new_role_id]));
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use base64::Engine;
+use ed25519_dalek::SigningKey;
+use haven_backend::db::Pool;
+use haven_backend::federation::signing;
+use serde_json::json;
+use uuid::Uuid;
+
+use common::TestApp;
+
+const B64: &base64::engine::GeneralPurpose = &base64::engine::general_purpose::STANDARD;
+const LOCAL: &str = "home.test";
+const REMOTE: &str = "remote.test";
+
+async fn federated_app(pool: Pool) -> (TestApp, SigningKey) {
+    let mut app = TestApp::new(pool).await;
+    app.enable_federation(LOCAL, [1u8; 32]);
+    let remote_key = SigningKey::from_bytes(&[2u8; 32]);
+    app.trust_federation_server(REMOTE, &remote_key.verifying_key().to_bytes())
+        .await;
+    (app, remote_key)
+}
+
+async fn put_transaction(
+    app: &TestApp,
+    key: &SigningKey,
+    txn_id: &str,
+    body: serde_json::Value,
+) -> StatusCode {
+    let path = format!("/api/v1/federation/transactions/{}", txn_id);
+    let bytes = serde_json::to_vec(&body).unwrap();
+    let auth = signing::sign_request(key, "PUT", &path, REMOTE, LOCAL, &bytes);
+    let (status, _, _) = app
+        .request_with_headers(
+            Method::PUT,
+            &path,
+            None,
+            &[("authorization", auth.as_str()), ("content-type", "application/json")],
+            bytes,
+        )
+        .await;
+    status
+}
+
+fn remote_profile(user_id: Uuid) -> serde_json::Value {
+    json!({
+        "user_id": user_id,
+        "username": "alice",
+        "display_name": "Alice",
+        "identity_key": B64.encode([3u8; 32]),
+        "signed_prekey": B64.encode([4u8; 32]),
+        "signed_prekey_signature": B64.encode([5u8; 64])
+    })
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn inbound_dm_and_message_reach_local_user(pool: Pool) {
+    let (app, remote_key) = federated_app(pool).await;
+    let (token, user_id) = app.register_user("fed_bob").await;
+
+    let channel_id = Uuid::new_v4();
+    let alice_id = Uuid::new_v4();
+    let message_id = Uuid::new_v4();
+    let txn = json!({
+        "origin": REMOTE,
+        "events": [
+            { "type": "dm_create", "channel_id": channel_id, "sender": remote_profile(alice_id), "target_user_id": user_id },
+            {
+                "type": "message",
+                "channel_id": channel_id,
+                "message_id": message_id,
+                "sender_id": alice_id,
+                "sender_token": B64.encode(b"token"),
+                "encrypted_body": B64.encode(b"ciphertext"),
+                "timestamp": "2026-01-01T00:00:00Z"
+            }
+        ]
+    });
+    assert_eq!(put_transaction(&app, &remote_key, "t1", txn.clone()).await, StatusCode::OK);
+    // A retried delivery is acknowledged but not applied twice
+    assert_eq!(put_transaction(&app, &remote_key, "t1", txn.clone()).await, StatusCode::OK);
+    // Nor is the same message resent under a new transaction and timestamp
+    let mut resent = txn;
+    resent["events"][1]["timestamp"] = json!("2026-01-02T00:00:00Z");
+    assert_eq!(put_transaction(&app, &remote_key, "t2", resent).await, StatusCode::OK);
+
+    let (_, dms) = app.request(Method::GET, "/api/v1/dm", Some(&token), None).await;
+    let dm = dms.as_array().unwrap().iter().find(|c| c["id"] == channel_id.to_string()).unwrap();
+    assert_eq!(dm["dm_status"], "pending");
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (status, messages) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let messages = messages.as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["id"], message_id.to_string());
+    assert_eq!(messages[0]["encrypted_body"], B64.encode(b"ciphertext"));
+
+    // The shadow account is visible as alice@remote.test but cannot log in
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/v1/auth/login",
+            None,
+            Some(json!({ "username": "alice@remote.test", "password": "anything" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn unsigned_or_denied_transactions_are_rejected(pool: Pool) {
+    let (mut app, remote_key) = federated_app(pool).await;
+    let txn = json!({ "origin": REMOTE, "events": [] });
+
+    // Signed by the wrong key
+    let forged = SigningKey::from_bytes(&[9u8; 32]);
+    assert_eq!(put_transaction(&app, &forged, "t1", txn.clone()).await, StatusCode::UNAUTHORIZED);
+
+    // No signature at all
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/federation/transactions/t2", None, Some(txn.clone()))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Signed too long ago: a captured request can't be replayed
+    let path = "/api/v1/federation/transactions/t5";
+    let bytes = serde_json::to_vec(&txn).unwrap();
+    let stale = chrono::Utc::now().timestamp() - signing::MAX_CLOCK_SKEW_SECS - 60;
+    let auth = signing::sign_request_at(&remote_key, "PUT", path, REMOTE, LOCAL, &bytes, stale);
+    let (status, _, _) = app
+        .request_with_headers(
+            Method::PUT,
+            path,
+            None,
+            &[("authorization", auth.as_str()), ("content-type", "application/json")],
+            bytes,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    assert_eq!(put_transaction(&app, &remote_key, "t3", txn.clone()).await, StatusCode::OK);
+
+    app.set_federation_denylist(REMOTE);
+    assert_eq!(put_transaction(&app, &remote_key, "t4", txn).await, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn server_key_is_published_only_when_enabled(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (status, _) = app.request(Method::GET, "/api/v1/federation/key", None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (app, _) = federated_app(pool).await;
+    let (status, value) = app.request(Method::GET, "/api/v1/federation/key", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["server_name"], LOCAL);
+    let expected = SigningKey::from_bytes(&[1u8; 32]).verifying_key().to_bytes();
+    assert_eq!(value["public_key"], B64.encode(expected));
+}
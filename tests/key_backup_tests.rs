@@ -249,3 +249,91 @@ async fn key_backup_status_with_backup(pool: Pool) {
     assert!(value["version"].is_number());
     assert!(value["updated_at"].is_string());
 }
+
+// ─── Versioned Session Backup ─────────────────────────────
+
+fn session_body(session_id: &str, first_message_index: i32, data: &[u8]) -> serde_json::Value {
+    json!({
+        "channel_id": uuid::Uuid::nil(),
+        "session_id": session_id,
+        "first_message_index": first_message_index,
+        "session_data": B64.encode(data)
+    })
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn backup_version_session_roundtrip(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("backupv1").await;
+
+    let (status, _) = app
+        .request(Method::GET, "/api/v1/keys/backup/versions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/keys/backup/versions",
+            Some(&token),
+            Some(json!({ "algorithm": "haven.curve25519-aes-sha2", "auth_data": B64.encode([5u8; 32]) })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["version"], 1);
+
+    let body = json!({ "sessions": [session_body("s1", 10, b"late"), session_body("s2", 0, b"other")] });
+    let (status, value) = app
+        .request(Method::PUT, "/api/v1/keys/backup/versions/1/sessions", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["count"], 2);
+
+    // A key that decrypts more history replaces the stored one; a worse one doesn't
+    let body = json!({ "sessions": [session_body("s1", 2, b"early")] });
+    app.request(Method::PUT, "/api/v1/keys/backup/versions/1/sessions", Some(&token), Some(body))
+        .await;
+    let body = json!({ "sessions": [session_body("s1", 5, b"worse")] });
+    app.request(Method::PUT, "/api/v1/keys/backup/versions/1/sessions", Some(&token), Some(body))
+        .await;
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/keys/backup/versions/1/sessions", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let s1 = value.as_array().unwrap().iter().find(|s| s["session_id"] == "s1").unwrap();
+    assert_eq!(s1["first_message_index"], 2);
+    assert_eq!(s1["session_data"], B64.encode(b"early"));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn backup_sessions_only_accepted_for_current_version(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("backupv2").await;
+
+    let create = json!({ "algorithm": "haven.curve25519-aes-sha2", "auth_data": B64.encode([5u8; 32]) });
+    app.request(Method::POST, "/api/v1/keys/backup/versions", Some(&token), Some(create.clone()))
+        .await;
+    app.request(Method::POST, "/api/v1/keys/backup/versions", Some(&token), Some(create.clone()))
+        .await;
+
+    let body = json!({ "sessions": [session_body("s1", 0, b"data")] });
+    let (status, _) = app
+        .request(Method::PUT, "/api/v1/keys/backup/versions/1/sessions", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Deleted version numbers are never handed out again
+    let (status, _) = app
+        .request(Method::DELETE, "/api/v1/keys/backup/versions/2", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app
+        .request(Method::GET, "/api/v1/keys/backup/versions", Some(&token), None)
+        .await;
+    assert_eq!(value["version"], 1);
+    let (_, value) = app
+        .request(Method::POST, "/api/v1/keys/backup/versions", Some(&token), Some(create))
+        .await;
+    assert_eq!(value["version"], 3);
+}
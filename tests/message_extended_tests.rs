@@ -3,6 +3,7 @@ mod common;
 use axum::http::{Method, StatusCode};
 use base64::Engine;
 use haven_backend::db::Pool;
+use haven_backend::models::WsServerMessage;
 use serde_json::json;
 use uuid::Uuid;
 
@@ -192,3 +193,524 @@ async fn send_message_non_member_returns_403(pool: Pool) {
 
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
+
+// ─── Forward Message ──────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn forward_message_records_provenance(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("fwd1").await;
+    let (token_outsider, _) = app.register_user("fwd2").await;
+    let server_id = app.create_server(&token, "Forward Server").await;
+    let source_id = app.create_channel(&token, server_id, "fwd-src").await;
+    let target_id = app.create_channel(&token, server_id, "fwd-dst").await;
+    let (msg_id, _) = app.send_message(&token, source_id).await;
+
+    let uri = format!("/api/v1/channels/{}/messages/{}/forward", source_id, msg_id);
+
+    // Encrypted channels need a copy re-encrypted for the target
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({
+                "target_channel_id": target_id,
+                "sender_token": B64.encode(b"fwd-token"),
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "REENCRYPTION_REQUIRED");
+
+    let body = json!({
+        "target_channel_id": target_id,
+        "sender_token": B64.encode(b"fwd-token"),
+        "encrypted_body": B64.encode(b"re-encrypted-body"),
+    });
+    let (status, value) = app
+        .request(Method::POST, &uri, Some(&token), Some(body.clone()))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert_eq!(value["channel_id"], target_id.to_string());
+    assert_eq!(value["message_type"], "forwarded");
+    assert_eq!(value["forwarded_from"]["message_id"], msg_id.to_string());
+    assert_eq!(value["forwarded_from"]["channel_id"], source_id.to_string());
+    assert_eq!(value["encrypted_body"], B64.encode(b"re-encrypted-body"));
+
+    // Users without access to the source channel cannot forward from it
+    let (status, _) = app
+        .request(Method::POST, &uri, Some(&token_outsider), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// ─── Replies ──────────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn replies_are_previewed_counted_and_listed(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("reply1").await;
+    let (token_outsider, _) = app.register_user("reply2").await;
+    let server_id = app.create_server(&token, "Reply Server").await;
+    let channel_id = app.create_channel(&token, server_id, "reply-ch").await;
+    let (parent_id, _) = app.send_message(&token, channel_id).await;
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (status, value) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token),
+            Some(json!({
+                "channel_id": channel_id,
+                "sender_token": B64.encode(b"token"),
+                "encrypted_body": B64.encode(b"reply"),
+                "has_attachments": false,
+                "reply_to_id": parent_id
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    let reply_id = value["id"].as_str().unwrap().to_string();
+
+    let (status, value) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let messages = value.as_array().unwrap();
+    let reply = messages.iter().find(|m| m["id"] == reply_id).unwrap();
+    assert_eq!(reply["reply_to"]["id"], parent_id.to_string());
+    assert_eq!(reply["reply_to"]["encrypted_body"], B64.encode(b"test-encrypted-body"));
+    let parent = messages.iter().find(|m| m["id"] == parent_id.to_string()).unwrap();
+    assert_eq!(parent["reply_count"], 1);
+
+    let replies_uri = format!("/api/v1/messages/{}/replies", parent_id);
+    let (status, value) = app.request(Method::GET, &replies_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let replies = value.as_array().unwrap();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["id"], reply_id);
+
+    let (status, _) = app
+        .request(Method::GET, &replies_uri, Some(&token_outsider), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// ─── Reactions ────────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn reactions_via_rest_are_counted_and_listed(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("react1").await;
+    let (token_b, _) = app.register_user("react2").await;
+    let server_id = app.create_server(&token_a, "React Server").await;
+    app.invite_and_join(&token_a, &token_b, server_id).await;
+    let channel_id = app.create_channel(&token_a, server_id, "react-ch").await;
+    let (msg_id, _) = app.send_message(&token_a, channel_id).await;
+
+    let me_uri = format!(
+        "/api/v1/channels/{}/messages/{}/reactions/%F0%9F%91%8D/@me",
+        channel_id, msg_id
+    );
+    for token in [&token_a, &token_b, &token_b] {
+        let (status, _) = app.request(Method::PUT, &me_uri, Some(token), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let list_uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (status, value) = app.request(Method::GET, &list_uri, Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let reactions = &value[0]["reactions"];
+    assert_eq!(reactions[0]["emoji"], "👍");
+    assert_eq!(reactions[0]["count"], 2);
+    assert_eq!(reactions[0]["me"], true);
+
+    let reactors_uri = format!(
+        "/api/v1/channels/{}/messages/{}/reactions/%F0%9F%91%8D?limit=1",
+        channel_id, msg_id
+    );
+    let (status, value) = app.request(Method::GET, &reactors_uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value.as_array().unwrap().len(), 1);
+    let first = value[0]["user_id"].as_str().unwrap().to_string();
+    let (status, value) = app
+        .request(
+            Method::GET,
+            &format!("{}&after={}", reactors_uri, first),
+            Some(&token_a),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value.as_array().unwrap().len(), 1);
+    assert_ne!(value[0]["user_id"], first);
+
+    let (status, _) = app.request(Method::DELETE, &me_uri, Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, value) = app.request(Method::GET, &list_uri, Some(&token_b), None).await;
+    assert_eq!(value[0]["reactions"][0]["count"], 1);
+    assert_eq!(value[0]["reactions"][0]["me"], false);
+}
+
+// ─── Mentions ─────────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn mentions_are_checked_and_counted_in_read_states(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("mention_owner").await;
+    let (member, member_id) = app.register_user("mention_member").await;
+    let server_id = app.create_server(&owner, "Mention Server").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let channel_id = app.create_channel(&owner, server_id, "mentions").await;
+
+    let (_, role) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/roles", server_id),
+            Some(&owner),
+            Some(json!({ "name": "Mods", "position": 1 })),
+        )
+        .await;
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/api/v1/servers/{}/members/{}/roles", server_id, member_id),
+            Some(&owner),
+            Some(json!({ "role_id": role["id"] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let send = |token: &String, mentions: serde_json::Value| {
+        let body = json!({
+            "channel_id": channel_id,
+            "sender_token": B64.encode(b"token"),
+            "encrypted_body": B64.encode(b"body"),
+            "has_attachments": false,
+            "mentions": mentions
+        });
+        let (app, uri, token) = (&app, &uri, token.clone());
+        async move { app.request(Method::POST, uri, Some(&token), Some(body)).await }
+    };
+
+    // @everyone needs MENTION_EVERYONE, which members lack by default
+    let (status, _) = send(&member, json!({ "everyone": true })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = send(&owner, json!({ "role_ids": [Uuid::new_v4()] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_MENTION");
+
+    let (status, value) = send(&owner, json!({ "role_ids": [role["id"]] })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["mentions"]["role_ids"][0], role["id"]);
+    send(&owner, json!({ "user_ids": [member_id] })).await;
+    app.send_message(&owner, channel_id).await;
+
+    let mention_count = || async {
+        let (_, states) = app.request(Method::GET, "/api/v1/channels/read-states", Some(&member), None).await;
+        let state = states
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["channel_id"] == channel_id.to_string())
+            .unwrap()
+            .clone();
+        (state["unread_count"].as_i64().unwrap(), state["mention_count"].as_i64().unwrap())
+    };
+    assert_eq!(mention_count().await, (3, 2));
+
+    // Suppressed role mentions stay on the message but stop counting
+    let settings = format!("/api/v1/channels/{}/mention-settings", channel_id);
+    let body = json!({ "suppress_role_mentions": true });
+    let (status, _) = app.request(Method::PUT, &settings, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app.request(Method::PUT, &settings, Some(&owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["suppress_role_mentions"], true);
+    assert_eq!(mention_count().await, (3, 1));
+
+    let (_, history) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert!(history
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|m| m["mentions"]["role_ids"][0] == role["id"]));
+}
+
+// ─── Voice Notes ──────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn voice_notes_are_validated_and_returned_in_history(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    app.set_voice_note_max_duration(60);
+    let (owner, _) = app.register_user("voice_owner").await;
+    let (member, _) = app.register_user("voice_member").await;
+    let server_id = app.create_server(&owner, "Voice Notes").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let channel_id = app.create_channel(&owner, server_id, "voice-notes").await;
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let send = |token: &String, has_attachments: bool, voice_note: serde_json::Value| {
+        let body = json!({
+            "channel_id": channel_id,
+            "sender_token": B64.encode(b"token"),
+            "encrypted_body": B64.encode(b"body"),
+            "has_attachments": has_attachments,
+            "voice_note": voice_note
+        });
+        let (app, uri, token) = (&app, &uri, token.clone());
+        async move { app.request(Method::POST, uri, Some(&token), Some(body)).await }
+    };
+
+    let (status, value) = send(&member, false, json!({ "duration_ms": 4200, "waveform": [0, 128, 255] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_VOICE_NOTE");
+    let (status, value) = send(&member, true, json!({ "duration_ms": 4200, "waveform": vec![1; 257] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_VOICE_NOTE");
+    let (status, value) = send(&member, true, json!({ "duration_ms": 61_000, "waveform": [1] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "VOICE_NOTE_TOO_LONG");
+
+    let (status, value) = send(&member, true, json!({ "duration_ms": 4200, "waveform": [0, 128, 255] })).await;
+    assert_eq!(status, StatusCode::OK, "Send voice note failed: {}", value);
+    assert_eq!(value["message_type"], "voice_note");
+    assert_eq!(value["voice_note"]["duration_ms"], 4200);
+    let voice_note_id = value["id"].clone();
+    app.send_message(&member, channel_id).await;
+
+    let (_, history) = app.request(Method::GET, &uri, Some(&owner), None).await;
+    let history = history.as_array().unwrap();
+    let voice_note = history.iter().find(|m| m["id"] == voice_note_id).unwrap();
+    assert_eq!(voice_note["message_type"], "voice_note");
+    assert_eq!(voice_note["voice_note"]["waveform"], json!([0, 128, 255]));
+    assert!(history.iter().filter(|m| m["id"] != voice_note_id).all(|m| m.get("voice_note").is_none()));
+
+    // Denying SEND_VOICE_NOTES leaves ordinary messages alone
+    let (_, roles) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/roles", server_id), Some(&owner), None)
+        .await;
+    let everyone = roles.as_array().unwrap().iter().find(|r| r["is_default"] == true).unwrap();
+    let body = json!({
+        "target_type": "role",
+        "target_id": everyone["id"],
+        "allow_bits": "0",
+        "deny_bits": "268435456"
+    });
+    let (status, _) = app
+        .request(Method::PUT, &format!("/api/v1/channels/{}/overwrites", channel_id), Some(&owner), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&member, true, json!({ "duration_ms": 1000, "waveform": [1] })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    app.send_message(&member, channel_id).await;
+    let (status, _) = send(&owner, true, json!({ "duration_ms": 1000, "waveform": [1] })).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn live_locations_end_when_stopped_or_expired(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (owner, owner_id) = app.register_user("location_owner").await;
+    app.make_admin(owner_id).await;
+    let (member, _) = app.register_user("location_member").await;
+    let server_id = app.create_server(&owner, "Locations").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let channel_id = app.create_channel(&owner, server_id, "locations").await;
+
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let send = |location: serde_json::Value| {
+        let body = json!({
+            "channel_id": channel_id,
+            "sender_token": B64.encode(b"token"),
+            "encrypted_body": B64.encode(b"body"),
+            "has_attachments": false,
+            "location": location
+        });
+        let (app, uri, token) = (&app, &uri, member.clone());
+        async move { app.request(Method::POST, uri, Some(&token), Some(body)).await }
+    };
+
+    let (status, value) = send(json!({ "live_duration_secs": 5 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVALID_LOCATION");
+
+    let (status, pin) = send(json!({})).await;
+    assert_eq!(status, StatusCode::OK, "Send location failed: {}", pin);
+    assert_eq!(pin["message_type"], "location");
+    assert!(pin.get("live_location").is_none());
+    let (status, live) = send(json!({ "live_duration_secs": 900 })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(live["live_location"]["ended_at"].is_null());
+    let (_, expiring) = send(json!({ "live_duration_secs": 900 })).await;
+
+    // Only the sender can stop a session, and only once
+    let stop_uri = |message: &serde_json::Value| {
+        format!("/api/v1/messages/{}/live-location", message["id"].as_str().unwrap())
+    };
+    let (status, _) = app.request(Method::DELETE, &stop_uri(&live), Some(&owner), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::DELETE, &stop_uri(&live), Some(&member), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, value) = app.request(Method::DELETE, &stop_uri(&live), Some(&member), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "LIVE_LOCATION_ENDED");
+    let (status, _) = app.request(Method::DELETE, &stop_uri(&pin), Some(&member), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A lapsed session is closed by the maintenance job
+    sqlx::query("UPDATE live_location_sessions SET expires_at = NOW() - INTERVAL '1 minute' WHERE message_id = $1")
+        .bind(Uuid::parse_str(expiring["id"].as_str().unwrap()).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/live-locations", Some(&owner), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"].as_u64(), Some(1));
+
+    let (_, history) = app.request(Method::GET, &uri, Some(&owner), None).await;
+    let history = history.as_array().unwrap();
+    let find = |message: &serde_json::Value| history.iter().find(|m| m["id"] == message["id"]).unwrap();
+    assert_eq!(find(&pin)["message_type"], "location");
+    assert!(find(&pin).get("live_location").is_none());
+    assert!(find(&live)["live_location"]["ended_at"].is_string());
+    assert!(find(&expiring)["live_location"]["ended_at"].is_string());
+}
+
+// ─── Notification Rules ───────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn notification_rules_silence_messages_for_their_owner(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("rules_owner").await;
+    let (member, member_id) = app.register_user("rules_member").await;
+    let server_id = app.create_server(&owner, "Rules Server").await;
+    let channel_id = app.create_channel(&owner, server_id, "rules").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+
+    let rules = "/api/v1/users/me/notification-rules";
+    let body = json!({ "scope": "global", "target_id": server_id, "level": "none" });
+    let (status, _) = app.request(Method::POST, rules, Some(&member), Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body = json!({ "scope": "server", "target_id": server_id, "level": "mentions" });
+    let (status, rule) = app.request(Method::POST, rules, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rule["level"], "mentions");
+    let (status, value) = app.request(Method::POST, rules, Some(&member), Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "NOTIFICATION_RULE_EXISTS");
+
+    // Only mentions notify in the server; everything is still delivered
+    let mut rx = app.connect_user(member_id);
+    app.send_message(&owner, channel_id).await;
+    let body = json!({
+        "channel_id": channel_id,
+        "sender_token": B64.encode(b"token"),
+        "encrypted_body": B64.encode(b"body"),
+        "has_attachments": false,
+        "mentions": { "user_ids": [member_id] }
+    });
+    let uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let (status, _) = app.request(Method::POST, &uri, Some(&owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let mut delivered = Vec::new();
+    while let Ok(msg) = rx.try_recv() {
+        if let WsServerMessage::NewMessage(m) = msg {
+            delivered.push((m.silent, m.mentions_me));
+        }
+    }
+    assert_eq!(delivered, vec![(true, false), (false, true)]);
+
+    // A channel rule overrides the server rule; rules can be changed and removed
+    let rule_uri = format!("{}/{}", rules, rule["id"].as_str().unwrap());
+    let body = json!({ "level": "none" });
+    let (status, value) = app.request(Method::PATCH, &rule_uri, Some(&member), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["level"], "none");
+    let body = json!({ "scope": "channel", "target_id": channel_id, "level": "all" });
+    let (status, _) = app.request(Method::POST, rules, Some(&member), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    app.send_message(&owner, channel_id).await;
+    let silent = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|msg| match msg {
+        WsServerMessage::NewMessage(m) => Some(m.silent),
+        _ => None,
+    });
+    assert_eq!(silent, Some(false));
+
+    let (status, _) = app.request(Method::DELETE, &rule_uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, list) = app.request(Method::GET, rules, Some(&member), None).await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["scope"], "channel");
+}
+
+// ─── Quiet Hours ──────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn quiet_hours_silence_all_but_priority_senders(pool: Pool) {
+    use chrono::Timelike;
+
+    let app = TestApp::new(pool).await;
+    let (owner, owner_id) = app.register_user("quiet_owner").await;
+    let (member, member_id) = app.register_user("quiet_member").await;
+    let server_id = app.create_server(&owner, "Quiet Server").await;
+    let channel_id = app.create_channel(&owner, server_id, "quiet").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+
+    let uri = "/api/v1/users/me/quiet-hours";
+    let (status, _) = app.request(Method::GET, uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let body = json!({ "start_minute": 600, "end_minute": 600, "utc_offset_minutes": 0 });
+    let (status, _) = app.request(Method::PUT, uri, Some(&member), Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A window around the current time, at UTC+1
+    let now = chrono::Utc::now();
+    let local_minute = (now.hour() * 60 + now.minute() + 60) % 1440;
+    let body = json!({
+        "start_minute": (local_minute + 1380) % 1440,
+        "end_minute": (local_minute + 60) % 1440,
+        "utc_offset_minutes": 60,
+        "timezone": "Europe/Paris",
+    });
+    let (status, schedule) = app.request(Method::PUT, uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(schedule["active"], true);
+    assert_eq!(schedule["days"], 127);
+
+    let mut rx = app.connect_user(member_id);
+    let next_silent = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<WsServerMessage>| {
+        std::iter::from_fn(|| rx.try_recv().ok()).find_map(|msg| match msg {
+            WsServerMessage::NewMessage(m) => Some(m.silent),
+            _ => None,
+        })
+    };
+    app.send_message(&owner, channel_id).await;
+    assert_eq!(next_silent(&mut rx), Some(true));
+
+    // Priority senders get through
+    let mut body = body;
+    body["priority_user_ids"] = json!([owner_id, owner_id]);
+    let (status, schedule) = app.request(Method::PUT, uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(schedule["priority_user_ids"].as_array().unwrap().len(), 1);
+    app.send_message(&owner, channel_id).await;
+    assert_eq!(next_silent(&mut rx), Some(false));
+
+    // A disabled schedule is never in effect
+    body["priority_user_ids"] = json!([]);
+    body["enabled"] = json!(false);
+    let (_, schedule) = app.request(Method::PUT, uri, Some(&member), Some(body)).await;
+    assert_eq!(schedule["active"], false);
+    app.send_message(&owner, channel_id).await;
+    assert_eq!(next_silent(&mut rx), Some(false));
+
+    let (status, _) = app.request(Method::DELETE, uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app.request(Method::DELETE, uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -2,7 +2,9 @@ mod common;
 
 use axum::http::{Method, StatusCode};
 use haven_backend::db::Pool;
+use haven_backend::models::WsServerMessage;
 use serde_json::json;
+use uuid::Uuid;
 
 use common::TestApp;
 
@@ -124,3 +126,122 @@ async fn beta_request_code_smtp_not_configured_returns_400(pool: Pool) {
 
     assert_eq!(status, StatusCode::BAD_REQUEST);
 }
+
+// ─── Schema Pre-flight ────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn schema_preflight_accepts_migrated_database(pool: Pool) {
+    use haven_backend::db::migrations;
+
+    let status = migrations::inspect(&pool).await.unwrap();
+    assert_eq!(status, migrations::SchemaStatus::default());
+    assert_eq!(migrations::prepare(&pool, false).await.unwrap(), 0);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn schema_preflight_refuses_unknown_migration(pool: Pool) {
+    use haven_backend::db::migrations::{self, SchemaError};
+
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+         VALUES (99990101000000, 'from a newer release', TRUE, '\\x00', 0)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let err = migrations::prepare(&pool, true).await.unwrap_err();
+    assert!(matches!(err, SchemaError::Incompatible(ref s) if s.unknown == [99990101000000]));
+    assert!(err.to_string().contains("newer than this build"));
+}
+
+// ─── WS Outbox ────────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_outbox_delivers_events_a_crash_left_unsent(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (_, user_id) = app.register_user("outbox_survivor").await;
+    let mut rx = app.connect_user(user_id);
+
+    // Committed by an instance that died before publishing it
+    let channel_id = Uuid::new_v4();
+    let event = serde_json::to_value(WsServerMessage::Subscribed { channel_id }).unwrap();
+    sqlx::query("INSERT INTO ws_outbox (target, target_id, event) VALUES ('user', $1, $2)")
+        .bind(user_id)
+        .bind(&event)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Picked up by the dispatcher's next poll
+    let delivered = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("Outbox event was not delivered");
+    assert!(matches!(delivered, Some(WsServerMessage::Subscribed { channel_id: id }) if id == channel_id));
+
+    let mut sent_at: Option<chrono::DateTime<chrono::Utc>> = None;
+    for _ in 0..50 {
+        sent_at = sqlx::query_scalar("SELECT sent_at FROM ws_outbox WHERE target_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        if sent_at.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(sent_at.is_some());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_outbox_holds_later_events_behind_a_retrying_one(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (_, user_id) = app.register_user("outbox_ordered").await;
+    let (_, other_id) = app.register_user("outbox_bystander").await;
+    let mut rx = app.connect_user(user_id);
+    let mut other_rx = app.connect_user(other_id);
+
+    let stage = |target_id: Uuid, channel_id: Uuid, leased: bool| {
+        let pool = pool.clone();
+        async move {
+            let event = serde_json::to_value(WsServerMessage::Subscribed { channel_id }).unwrap();
+            let locked_until = leased.then(|| chrono::Utc::now() + chrono::Duration::hours(1));
+            sqlx::query(
+                "INSERT INTO ws_outbox (target, target_id, event, attempts, locked_until) VALUES ('user', $1, $2, $3, $4)",
+            )
+                .bind(target_id)
+                .bind(&event)
+                .bind(leased as i32)
+                .bind(locked_until)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    };
+
+    // The first event is out on a failed attempt; the second must wait for it
+    let (first, second, unrelated) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    stage(user_id, first, true).await;
+    stage(user_id, second, false).await;
+    stage(other_id, unrelated, false).await;
+
+    let delivered = tokio::time::timeout(std::time::Duration::from_secs(5), other_rx.recv())
+        .await
+        .expect("Unrelated outbox event was not delivered");
+    assert!(matches!(delivered, Some(WsServerMessage::Subscribed { channel_id }) if channel_id == unrelated));
+    assert!(rx.try_recv().is_err(), "Event overtook the one before it");
+
+    // Once the first event's lease runs out, both go out in order
+    sqlx::query("UPDATE ws_outbox SET locked_until = NULL WHERE target_id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    for expected in [first, second] {
+        let delivered = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .expect("Outbox event was not delivered");
+        assert!(matches!(delivered, Some(WsServerMessage::Subscribed { channel_id }) if channel_id == expected));
+    }
+}
@@ -102,3 +102,101 @@ async fn admin_create_registration_invites_default_count(pool: Pool) {
     assert_eq!(invites.len(), 1);
     assert!(invites[0]["code"].is_string());
 }
+
+// ─── Registration Modes ───────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn closed_and_invite_modes_refuse_registration(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    app.register_user("rm_first").await;
+
+    app.reload_config(|config| config.registration_mode = "closed".into());
+    let (status, value) = app.try_register("rm_closed", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "REGISTRATION_CLOSED");
+    let (_, value) = app
+        .request(Method::GET, "/api/v1/auth/invite-required", None, None)
+        .await;
+    assert_eq!(value["registration_mode"], "closed");
+    assert_eq!(value["invite_required"].as_bool(), Some(false));
+
+    app.reload_config(|config| config.registration_mode = "invite".into());
+    let (status, value) = app.try_register("rm_invite", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "INVITE_REQUIRED");
+    let (_, value) = app
+        .request(Method::GET, "/api/v1/auth/invite-required", None, None)
+        .await;
+    assert_eq!(value["invite_required"].as_bool(), Some(true));
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn approval_mode_holds_accounts_until_approved(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, admin_id) = app.register_user("rm_admin").await;
+    app.make_admin(admin_id).await;
+    app.reload_config(|config| config.registration_mode = "approval".into());
+
+    let (status, value) = app.try_register("rm_waiting", None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(value["pending_approval"].as_bool(), Some(true));
+    assert!(value.get("access_token").is_none());
+    let user_id = value["user_id"].as_str().unwrap().to_string();
+
+    let login = json!({ "username": "rm_waiting", "password": "testpassword123" });
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/auth/login",
+            None,
+            Some(login.clone()),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "ACCOUNT_PENDING_APPROVAL");
+
+    let (status, value) = app
+        .request(
+            Method::GET,
+            "/api/v1/admin/registrations/pending",
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let pending = value.as_array().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0]["username"], "rm_waiting");
+
+    let uri = format!("/api/v1/admin/registrations/{}/approve", user_id);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Approved accounts log in and get their personal server
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/auth/login", None, Some(login))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let user_token = value["access_token"].as_str().unwrap();
+    let (_, servers) = app
+        .request(Method::GET, "/api/v1/servers", Some(user_token), None)
+        .await;
+    assert_eq!(servers.as_array().map(Vec::len), Some(1));
+
+    // Rejected accounts are deleted
+    let (status, value) = app.try_register("rm_rejected", None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let uri = format!(
+        "/api/v1/admin/registrations/{}/reject",
+        value["user_id"].as_str().unwrap()
+    );
+    let (status, _) = app.request(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let login = json!({ "username": "rm_rejected", "password": "testpassword123" });
+    let (status, _) = app
+        .request(Method::POST, "/api/v1/auth/login", None, Some(login))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -2,7 +2,9 @@ mod common;
 
 use axum::http::{Method, StatusCode};
 use haven_backend::db::Pool;
+use haven_backend::models::WsServerMessage;
 use serde_json::json;
+use uuid::Uuid;
 
 use common::TestApp;
 
@@ -15,10 +17,17 @@ async fn delete_server_as_owner(pool: Pool) {
     let server_id = app.create_server(&token, "Delete Me").await;
 
     let uri = format!("/api/v1/servers/{}", server_id);
-    let (status, value) = app.request(Method::DELETE, &uri, Some(&token), None).await;
+    let (status, _) = app
+        .request(Method::DELETE, &uri, Some(&token), Some(json!({ "password": "wrongpassword" })))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let body = json!({ "password": "testpassword123" });
+    let (status, value) = app.request(Method::DELETE, &uri, Some(&token), Some(body)).await;
 
     assert_eq!(status, StatusCode::OK);
-    assert_eq!(value["ok"].as_bool(), Some(true));
+    assert_eq!(value["id"], json!(server_id));
+    assert!(value["purge_at"].as_str().unwrap() > value["deleted_at"].as_str().unwrap());
 
     // Verify server is gone
     let (status, _) = app.request(Method::GET, &uri, Some(&token), None).await;
@@ -36,11 +45,96 @@ async fn delete_server_non_owner_returns_403(pool: Pool) {
         .await;
 
     let uri = format!("/api/v1/servers/{}", server_id);
+    let body = json!({ "password": "testpassword123" });
     let (status, _) = app
-        .request(Method::DELETE, &uri, Some(&token_member), None)
+        .request(Method::DELETE, &uri, Some(&token_member), Some(body))
+        .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn delete_server_requires_totp_when_enabled(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token, user_id) = app.register_user("srv_del_totp").await;
+    let server_id = app.create_server(&token, "Guarded").await;
+    sqlx::query("UPDATE users SET totp_secret = 'JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP' WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let uri = format!("/api/v1/servers/{}", server_id);
+    let (status, value) = app
+        .request(Method::DELETE, &uri, Some(&token), Some(json!({ "password": "testpassword123" })))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(value["code"], "TOTP_REQUIRED");
+
+    let body = json!({ "password": "testpassword123", "totp_code": "000000" });
+    let (status, value) = app.request(Method::DELETE, &uri, Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(value["code"], "INVALID_TOTP");
+
+    let (status, _) = app.request(Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn deleted_server_is_restorable_until_purged(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token_owner, owner_id) = app.register_user("srv_grace1").await;
+    let (token_member, _) = app.register_user("srv_grace2").await;
+    let server_id = app.create_server(&token_owner, "Grace").await;
+    app.invite_and_join(&token_owner, &token_member, server_id).await;
+
+    let uri = format!("/api/v1/servers/{}", server_id);
+    let body = json!({ "password": "testpassword123" });
+    let (status, _) = app.request(Method::DELETE, &uri, Some(&token_owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Gone for members, listed for the owner
+    let (status, _) = app.request(Method::GET, &uri, Some(&token_member), None).await;
+    assert_ne!(status, StatusCode::OK);
+    let (_, servers) = app.request(Method::GET, "/api/v1/servers", Some(&token_member), None).await;
+    assert!(servers.as_array().unwrap().iter().all(|s| s["id"] != json!(server_id)));
+    let (_, deleted) = app
+        .request(Method::GET, "/api/v1/servers/deleted", Some(&token_owner), None)
         .await;
+    assert_eq!(deleted[0]["id"], json!(server_id));
 
+    // Only the owner can restore it
+    let undelete = format!("/api/v1/servers/{}/undelete", server_id);
+    let (status, _) = app.request(Method::POST, &undelete, Some(&token_member), None).await;
     assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::POST, &undelete, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token_member), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Past the grace period the deleted-servers job purges it
+    let body = json!({ "password": "testpassword123" });
+    app.request(Method::DELETE, &uri, Some(&token_owner), Some(body)).await;
+    sqlx::query("UPDATE servers SET deleted_at = NOW() - interval '8 days' WHERE id = $1")
+        .bind(server_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    app.make_admin(owner_id).await;
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/deleted-servers", Some(&token_owner), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["affected"], 1);
+
+    let (status, _) = app.request(Method::POST, &undelete, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let remaining: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM channels WHERE server_id = $1")
+        .bind(server_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining.0, 0);
 }
 
 // ─── Leave Server ─────────────────────────────────────────
@@ -417,6 +511,63 @@ async fn create_content_filter_no_permission_returns_403(pool: Pool) {
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn abuse_filters_flag_members_by_registration_score(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (token_owner, owner_id) = app.register_user("cf_abuse1").await;
+    let (token_member, member_id) = app.register_user("cf_abuse2").await;
+    let server_id = app.create_server(&token_owner, "CF Abuse").await;
+
+    app.invite_and_join(&token_owner, &token_member, server_id)
+        .await;
+    sqlx::query("UPDATE users SET abuse_score = 70 WHERE id = $1")
+        .bind(member_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let uri = format!("/api/v1/servers/{}/content-filters", server_id);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&token_owner),
+            Some(json!({ "pattern": "0", "filter_type": "abuse" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    for (pattern, action) in [("60", "hide"), ("10", "warn")] {
+        let body = json!({ "pattern": pattern, "filter_type": "abuse", "action": action });
+        let (status, _) = app
+            .request(Method::POST, &uri, Some(&token_owner), Some(body))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Test registrations send no User-Agent or Accept-Language, scoring 15
+    let members_uri = format!("/api/v1/servers/{}/members", server_id);
+    let (status, value) = app
+        .request(Method::GET, &members_uri, Some(&token_member), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let member_of = |id: Uuid| {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["user_id"].as_str() == Some(&id.to_string()))
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(
+        member_of(member_id)["automod_action"].as_str(),
+        Some("hide")
+    );
+    assert_eq!(member_of(owner_id)["automod_action"].as_str(), Some("warn"));
+    assert!(member_of(owner_id).get("abuse_score").is_none());
+}
+
 // ─── Audit Log ────────────────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -489,3 +640,495 @@ async fn export_server_non_member_returns_403(pool: Pool) {
 
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
+
+// ─── Scheduled Events ─────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn scheduled_events_with_rsvps_and_reminders(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, owner_id) = app.register_user("evt_owner").await;
+    let (member, _) = app.register_user("evt_member").await;
+    let server_id = app.create_server(&owner, "Event Server").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+    let channel_id = app.create_channel(&owner, server_id, "stage").await;
+
+    let uri = format!("/api/v1/servers/{}/events", server_id);
+    let starts_at = chrono::Utc::now() + chrono::Duration::days(2);
+    let body = json!({
+        "encrypted_meta": "ZXZlbnQ=",
+        "channel_id": channel_id,
+        "starts_at": starts_at,
+    });
+
+    // Members without MANAGE_EVENTS cannot schedule
+    let (status, _) = app.request(Method::POST, &uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, event) = app.request(Method::POST, &uri, Some(&owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK, "{}", event);
+    assert_eq!(event["channel_id"], channel_id.to_string());
+    let event_id = event["id"].as_str().unwrap().to_string();
+
+    let rsvp = format!("{}/{}/rsvp", uri, event_id);
+    let (status, value) = app
+        .request(Method::PUT, &rsvp, Some(&member), Some(json!({ "status": "going" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["going_count"], 1);
+    assert_eq!(value["my_rsvp"], "going");
+    let (_, value) = app
+        .request(Method::PUT, &rsvp, Some(&owner), Some(json!({ "status": "interested" })))
+        .await;
+    assert_eq!(value["interested_count"], 1);
+    assert_eq!(value["going_count"], 1);
+
+    let (status, list) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["my_rsvp"], "going");
+
+    // Not due yet, then due once moved into the reminder window
+    app.make_admin(owner_id).await;
+    let job = "/api/v1/admin/maintenance/event-reminders";
+    let (_, value) = app.request(Method::POST, job, Some(&owner), None).await;
+    assert_eq!(value["affected"], 0);
+    let soon = chrono::Utc::now() + chrono::Duration::minutes(5);
+    let (status, _) = app
+        .request(
+            Method::PATCH,
+            &format!("{}/{}", uri, event_id),
+            Some(&owner),
+            Some(json!({ "starts_at": soon, "channel_id": null })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, value) = app.request(Method::POST, job, Some(&owner), None).await;
+    assert_eq!(value["affected"], 1);
+    let (_, value) = app.request(Method::POST, job, Some(&owner), None).await;
+    assert_eq!(value["affected"], 0);
+
+    let (status, _) = app
+        .request(Method::DELETE, &format!("{}/{}", uri, event_id), Some(&member), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::DELETE, &format!("{}/{}", uri, event_id), Some(&owner), None)
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, list) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert!(list.as_array().unwrap().is_empty());
+}
+
+// ─── Onboarding ───────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn onboarding_gates_posting_and_grants_prompt_roles(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (owner, _) = app.register_user("onb_owner").await;
+    let (member, member_id) = app.register_user("onb_member").await;
+    let server_id = app.create_server(&owner, "Onboarding Server").await;
+    let rules_channel = app.create_channel(&owner, server_id, "rules").await;
+    let other_channel = app.create_channel(&owner, server_id, "other").await;
+    let (_, role) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/roles", server_id),
+            Some(&owner),
+            Some(json!({ "name": "Artist", "permissions": "0", "position": 1 })),
+        )
+        .await;
+    let role_id = role["id"].as_str().unwrap().to_string();
+
+    let uri = format!("/api/v1/servers/{}/onboarding", server_id);
+    let (prompt_id, artist, lurker) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let mut body = json!({
+        "enabled": true,
+        "encrypted_meta": "d2VsY29tZQ==",
+        "default_channel_ids": [rules_channel],
+        "require_acknowledgment": true,
+        "prompts": [{
+            "id": prompt_id,
+            "single_select": true,
+            "required": true,
+            "options": [
+                { "id": artist, "role_ids": [role_id] },
+                { "id": lurker, "role_ids": [] },
+            ],
+        }],
+    });
+    let (status, onboarding) = app.request(Method::PUT, &uri, Some(&owner), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{}", onboarding);
+    assert_eq!(onboarding["rules_version"], 1);
+
+    // New members only join the default channels and can't post yet
+    app.invite_and_join(&owner, &member, server_id).await;
+    let (status, _) = app.request(Method::PUT, &uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let joined: Vec<(Uuid,)> = sqlx::query_as(
+        "SELECT cm.channel_id FROM channel_members cm JOIN channels c ON c.id = cm.channel_id \
+         WHERE cm.user_id = $1 AND c.server_id = $2",
+    )
+    .bind(member_id)
+    .bind(server_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(joined, vec![(rules_channel,)]);
+
+    let send_uri = format!("/api/v1/channels/{}/messages", other_channel);
+    let send_body = json!({
+        "channel_id": other_channel,
+        "sender_token": "dG9rZW4=",
+        "encrypted_body": "Ym9keQ==",
+        "has_attachments": false,
+    });
+    let (status, value) = app.request(Method::POST, &send_uri, Some(&member), Some(send_body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "ONBOARDING_REQUIRED");
+
+    // Completing needs the acknowledgment and one option for the required prompt
+    let complete = format!("{}/complete", uri);
+    let pick = |options: Vec<Uuid>, acknowledge: bool| {
+        json!({
+            "acknowledge_rules": acknowledge,
+            "selections": [{ "prompt_id": prompt_id, "option_ids": options }],
+        })
+    };
+    for bad in [pick(vec![artist], false), pick(vec![], true), pick(vec![artist, lurker], true)] {
+        let (status, _) = app.request(Method::POST, &complete, Some(&member), Some(bad)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, value) = app.request(Method::POST, &complete, Some(&member), Some(pick(vec![artist], true))).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert_eq!(value["acknowledged"], true);
+    let granted: (bool,) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM member_roles WHERE user_id = $1 AND role_id = $2::uuid)",
+    )
+    .bind(member_id)
+    .bind(&role_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert!(granted.0);
+    let (status, _) = app.request(Method::POST, &send_uri, Some(&member), Some(send_body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Resetting acknowledgments gates posting again
+    body["reset_acknowledgments"] = json!(true);
+    let (_, onboarding) = app.request(Method::PUT, &uri, Some(&owner), Some(body)).await;
+    assert_eq!(onboarding["rules_version"], 2);
+    let (status, _) = app.request(Method::POST, &send_uri, Some(&member), Some(send_body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, value) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert_eq!(value["acknowledged"], false);
+}
+
+// ─── Member Screening ─────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn screened_servers_admit_applicants_on_approval(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("scr_owner").await;
+    let (member, _) = app.register_user("scr_member").await;
+    let (applicant, applicant_id) = app.register_user("scr_applicant").await;
+    let (other, _) = app.register_user("scr_other").await;
+    let server_id = app.create_server(&owner, "Screened Server").await;
+    let channel_id = app.create_channel(&owner, server_id, "general").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+
+    let (required, optional) = (Uuid::new_v4(), Uuid::new_v4());
+    let body = json!({
+        "enabled": true,
+        "questions": [
+            { "id": required, "prompt": "Why do you want to join?", "required": true },
+            { "id": optional, "prompt": "Anything else?" },
+        ],
+    });
+    let uri = format!("/api/v1/servers/{}/screening", server_id);
+    let (status, _) = app.request(Method::PUT, &uri, Some(&member), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::PUT, &uri, Some(&owner), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, invite) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/invites", server_id),
+            Some(&owner),
+            Some(json!({ "expires_in_hours": 24 })),
+        )
+        .await;
+    let code = invite["code"].as_str().unwrap();
+
+    // Joining directly is refused; applicants see the questions and apply
+    let (status, value) = app
+        .request(Method::POST, &format!("/api/v1/invites/{}/join", code), Some(&applicant), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "SCREENING_REQUIRED");
+    let (_, screening) = app
+        .request(Method::GET, &format!("/api/v1/invites/{}/screening", code), Some(&applicant), None)
+        .await;
+    assert_eq!(screening["questions"].as_array().unwrap().len(), 2);
+
+    let apply = format!("/api/v1/invites/{}/apply", code);
+    let answers = |answer: &str| json!({ "answers": [{ "question_id": required, "answer": answer }] });
+    let (status, _) = app.request(Method::POST, &apply, Some(&applicant), Some(answers(" "))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, application) = app.request(Method::POST, &apply, Some(&applicant), Some(answers("Hi!"))).await;
+    assert_eq!(status, StatusCode::OK, "{}", application);
+    assert_eq!(application["status"], "pending");
+    let (status, value) = app.request(Method::POST, &apply, Some(&applicant), Some(answers("Hi!"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "APPLICATION_PENDING");
+    let (status, _) = app.request(Method::POST, &apply, Some(&other), Some(answers("Me too"))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Pending applicants have no channel access
+    let history = format!("/api/v1/channels/{}/messages", channel_id);
+    let (status, _) = app.request(Method::GET, &history, Some(&applicant), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Only moderators see the queue
+    let queue = format!("/api/v1/servers/{}/applications", server_id);
+    let (status, _) = app.request(Method::GET, &queue, Some(&member), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, list) = app.request(Method::GET, &queue, Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let list = list.as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["username"], "scr_applicant");
+    assert_eq!(list[0]["answers"][0]["answer"], "Hi!");
+
+    let mut rx = app.connect_user(applicant_id);
+    let application_id = application["id"].as_str().unwrap();
+    let approve = format!("{}/{}/approve", queue, application_id);
+    let (status, value) = app.request(Method::POST, &approve, Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+    assert_eq!(value["status"], "approved");
+    let decided = std::iter::from_fn(|| rx.try_recv().ok()).any(|msg| {
+        matches!(msg, WsServerMessage::ApplicationDecided { ref status, .. } if status == "approved")
+    });
+    assert!(decided);
+    let (status, value) = app.request(Method::POST, &approve, Some(&owner), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(value["code"], "APPLICATION_DECIDED");
+    let (status, _) = app.request(Method::GET, &history, Some(&applicant), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Denied applicants can see why
+    let other_id = list[1]["id"].as_str().unwrap();
+    let deny = format!("{}/{}/deny", queue, other_id);
+    let (status, _) = app
+        .request(Method::POST, &deny, Some(&owner), Some(json!({ "reason": "Not now" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, mine) = app.request(Method::GET, "/api/v1/users/me/applications", Some(&other), None).await;
+    assert_eq!(mine[0]["status"], "denied");
+    assert_eq!(mine[0]["deny_reason"], "Not now");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn screened_temporary_invites_admit_temporary_members(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (owner, _) = app.register_user("scr_temp_owner").await;
+    let (applicant, applicant_id) = app.register_user("scr_temp_guest").await;
+    let server_id = app.create_server(&owner, "Screened Temporary").await;
+
+    let uri = format!("/api/v1/servers/{}/screening", server_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&owner), Some(json!({ "enabled": true, "questions": [] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, invite) = app
+        .request(
+            Method::POST,
+            &format!("/api/v1/servers/{}/invites", server_id),
+            Some(&owner),
+            Some(json!({ "temporary": true })),
+        )
+        .await;
+    assert_eq!(invite["temporary"], true);
+    let apply = format!("/api/v1/invites/{}/apply", invite["code"].as_str().unwrap());
+    let (status, application) = app
+        .request(Method::POST, &apply, Some(&applicant), Some(json!({ "answers": [] })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", application);
+
+    let members_uri = format!("/api/v1/servers/{}/members", server_id);
+    let (_, headers, _) = app
+        .request_with_headers(Method::GET, &members_uri, Some(&owner), &[], vec![])
+        .await;
+    let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+
+    let approve = format!(
+        "/api/v1/servers/{}/applications/{}/approve",
+        server_id,
+        application["id"].as_str().unwrap()
+    );
+    let (status, value) = app.request(Method::POST, &approve, Some(&owner), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+
+    // The approved applicant joins as a temporary member
+    let (status, _, members) = app
+        .request_with_headers(Method::GET, &members_uri, Some(&owner), &[("if-none-match", &etag)], vec![])
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let guest = members
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["user_id"] == applicant_id.to_string())
+        .unwrap();
+    assert_eq!(guest["temporary"], true);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn verification_levels_gate_posting(pool: Pool) {
+    let app = TestApp::new(pool.clone()).await;
+    let (owner, _) = app.register_user("ver_owner").await;
+    let (member, member_id) = app.register_user("ver_member").await;
+    let server_id = app.create_server(&owner, "Verified Server").await;
+    let channel_id = app.create_channel(&owner, server_id, "general").await;
+    app.invite_and_join(&owner, &member, server_id).await;
+
+    let uri = format!("/api/v1/servers/{}/verification", server_id);
+    let (status, _) = app
+        .request(Method::PUT, &uri, Some(&member), Some(json!({ "level": "email" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app
+        .request(Method::PUT, &uri, Some(&owner), Some(json!({ "level": "phone" })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "PHONE_VERIFICATION_UNAVAILABLE");
+    let (status, value) = app
+        .request(Method::PUT, &uri, Some(&owner), Some(json!({ "level": "email" })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", value);
+
+    // Unverified members can't post; the owner can
+    let send_uri = format!("/api/v1/channels/{}/messages", channel_id);
+    let send_body = json!({
+        "channel_id": channel_id,
+        "sender_token": "dG9rZW4=",
+        "encrypted_body": "Ym9keQ==",
+        "has_attachments": false,
+    });
+    let (status, value) = app.request(Method::POST, &send_uri, Some(&member), Some(send_body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "VERIFICATION_REQUIRED");
+    app.send_message(&owner, channel_id).await;
+    let (_, value) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert_eq!(value["satisfied"], false);
+
+    // Only the registered address can be verified
+    let (status, value) = app
+        .request(
+            Method::POST,
+            "/api/v1/auth/email/verify",
+            Some(&member),
+            Some(json!({ "email": "someone@example.com" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(value["code"], "EMAIL_MISMATCH");
+
+    sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE id = $1")
+        .bind(member_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    app.send_message(&member, channel_id).await;
+
+    // Higher levels add account age and time since joining
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &uri,
+            Some(&owner),
+            Some(json!({ "level": "membership", "min_account_age_days": 2, "min_membership_minutes": 30 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, value) = app.request(Method::POST, &send_uri, Some(&member), Some(send_body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(value["code"], "VERIFICATION_REQUIRED");
+    sqlx::query("UPDATE users SET created_at = NOW() - INTERVAL '3 days' WHERE id = $1")
+        .bind(member_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE server_members SET joined_at = NOW() - INTERVAL '31 minutes' WHERE user_id = $1")
+        .bind(member_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (_, value) = app.request(Method::GET, &uri, Some(&member), None).await;
+    assert_eq!(value["satisfied"], true);
+    assert_eq!(value["min_membership_minutes"], 30);
+    app.send_message(&member, channel_id).await;
+}
+
+// ─── Insights ───────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn insights_roll_up_daily_activity(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, owner_id) = app.register_user("insights_owner").await;
+    let (token_member, _) = app.register_user("insights_member").await;
+    let (token_leaver, _) = app.register_user("insights_leaver").await;
+    let server_id = app.create_server(&token_owner, "Insights").await;
+    let busy = app.create_channel(&token_owner, server_id, "busy").await;
+    let quiet = app.create_channel(&token_owner, server_id, "quiet").await;
+    app.invite_and_join(&token_owner, &token_member, server_id).await;
+    app.invite_and_join(&token_owner, &token_leaver, server_id).await;
+    app.make_admin(owner_id).await;
+
+    app.send_message(&token_owner, busy).await;
+    app.send_message(&token_member, busy).await;
+    app.send_message(&token_member, busy).await;
+    app.send_message(&token_owner, quiet).await;
+    let leave_uri = format!("/api/v1/servers/{}/members/@me", server_id);
+    let (status, _) = app.request(Method::DELETE, &leave_uri, Some(&token_leaver), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/api/v1/servers/{}/insights?days=7", server_id);
+    let (status, _) = app.request(Method::GET, &uri, Some(&token_member), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, value) = app.request(Method::GET, &uri, Some(&token_owner), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["days"].as_array().unwrap().len(), 7);
+    assert!(value["updated_at"].is_null());
+
+    let (status, value) = app
+        .request(Method::POST, "/api/v1/admin/maintenance/insights", Some(&token_owner), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(value["affected"].as_u64().unwrap() >= 1);
+
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_owner), None).await;
+    let today = &value["days"][6];
+    assert_eq!(today["messages"], 4);
+    assert_eq!(today["active_members"], 2);
+    // The owner's creation, two invite joins and one leave
+    assert_eq!(today["joins"], 3);
+    assert_eq!(today["leaves"], 1);
+    assert_eq!(value["days"][0]["messages"], 0);
+    assert_eq!(value["channels"][0]["channel_id"], busy.to_string());
+    assert_eq!(value["channels"][0]["messages"], 3);
+    assert_eq!(value["channels"][1]["channel_id"], quiet.to_string());
+    assert_eq!(value["member_count"], 2);
+    assert!(value["updated_at"].is_string());
+
+    // Rerunning recomputes rather than adds up
+    app.request(Method::POST, "/api/v1/admin/maintenance/insights", Some(&token_owner), None).await;
+    let (_, value) = app.request(Method::GET, &uri, Some(&token_owner), None).await;
+    assert_eq!(value["days"][6]["messages"], 4);
+    assert_eq!(value["days"][6]["joins"], 3);
+
+    let (status, _) = app
+        .request(Method::GET, &format!("/api/v1/servers/{}/insights?days=91", server_id), Some(&token_owner), None)
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
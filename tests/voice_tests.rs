@@ -181,3 +181,64 @@ async fn voice_deafen_no_permission_returns_403(pool: Pool) {
 
     assert_eq!(status, StatusCode::FORBIDDEN);
 }
+
+// ─── DM Calls ─────────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn turn_credentials_are_short_lived_and_user_bound(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, user_id) = app.register_user("turn1").await;
+
+    let (status, value) = app
+        .request(Method::GET, "/api/v1/voice/turn-credentials", Some(&token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["urls"][0].as_str(), Some("turn:turn.example.com:3478"));
+    assert_eq!(value["ttl_secs"].as_u64(), Some(3600));
+    let username = value["username"].as_str().unwrap();
+    assert!(username.ends_with(&format!(":{}", user_id)));
+    assert!(!value["credential"].as_str().unwrap().is_empty());
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn start_call_in_dm_returns_turn_credentials(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("caller1").await;
+    let (_, user_b_id) = app.register_user("callee1").await;
+    let channel_id = app.create_dm(&token_a, user_b_id).await;
+
+    let uri = format!("/api/v1/channels/{}/calls", channel_id);
+    let (status, value) = app.request(Method::POST, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["channel_id"], json!(channel_id));
+    assert!(value["turn"]["credential"].is_string());
+
+    // A second ring while the first is still pending is rejected
+    let (status, _) = app.request(Method::POST, &uri, Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn start_call_in_server_channel_returns_400(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("caller2").await;
+    let server_id = app.create_server(&token, "Call Server").await;
+    let channel_id = app.create_voice_channel(&token, server_id, "vc-call").await;
+
+    let uri = format!("/api/v1/channels/{}/calls", channel_id);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn start_call_non_member_returns_403(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, _) = app.register_user("caller3").await;
+    let (_, user_b_id) = app.register_user("callee3").await;
+    let (token_c, _) = app.register_user("outsider3").await;
+    let channel_id = app.create_dm(&token_a, user_b_id).await;
+
+    let uri = format!("/api/v1/channels/{}/calls", channel_id);
+    let (status, _) = app.request(Method::POST, &uri, Some(&token_c), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
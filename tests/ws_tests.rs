@@ -56,7 +56,7 @@ async fn ws_send(
     >,
     msg: Value,
 ) {
-    sink.send(Message::Text(msg.to_string().into()))
+    sink.send(Message::Text(msg.to_string()))
         .await
         .unwrap();
 }
@@ -124,6 +124,74 @@ async fn ws_ping_returns_pong(pool: Pool) {
     assert_eq!(msg["type"].as_str(), Some("Pong"));
 }
 
+// ─── Heartbeat ──────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_heartbeat_acked_and_silent_connection_reaped(pool: Pool) {
+    let mut app = TestApp::new(pool).await;
+    app.set_ws_heartbeat(1, 3);
+    let (token, user_id) = app.register_user("ws_heartbeat").await;
+    app.make_admin(user_id).await;
+    let addr = start_server(&app).await;
+
+    let (mut sink, mut stream) = ws_connect(&addr, &token).await;
+    let hello = ws_recv_matching(&mut stream, |v| v["type"] == "Hello").await;
+    assert_eq!(hello["payload"]["heartbeat_interval_ms"].as_u64(), Some(1000));
+
+    ws_send(&mut sink, json!({"type": "Heartbeat", "payload": {"seq": 7}})).await;
+    let ack = ws_recv_matching(&mut stream, |v| v["type"] == "HeartbeatAck").await;
+    assert_eq!(ack["payload"]["seq"].as_u64(), Some(7));
+
+    // Go silent: the server counts missed heartbeats, then drops the socket
+    let reaped = tokio::time::timeout(std::time::Duration::from_secs(6), async {
+        while let Some(Ok(_)) = stream.next().await {}
+    })
+    .await;
+    assert!(reaped.is_ok(), "silent connection was not reaped");
+
+    let mut report = json!(null);
+    for _ in 0..50 {
+        let (status, value) = app
+            .request(axum::http::Method::GET, "/api/v1/admin/ws-heartbeats", Some(&token), None)
+            .await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        report = value;
+        if report["connections"].as_u64() == Some(0) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(report["connections"].as_u64(), Some(0), "unexpected report: {}", report);
+    assert_eq!(report["heartbeats"].as_u64(), Some(1));
+    assert!(report["missed"].as_u64().unwrap() >= 1, "unexpected report: {}", report);
+    assert_eq!(report["reaped"].as_u64(), Some(1));
+}
+
+// ─── Shutdown ───────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_shutdown_sends_reconnect_and_closes(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("ws_shutdown").await;
+    let addr = start_server(&app).await;
+
+    let (_sink, mut stream) = ws_connect(&addr, &token).await;
+    ws_recv_matching(&mut stream, |v| v["type"] == "Hello").await;
+
+    app.begin_shutdown();
+    // The turn falls within the first half of the (2s) drain window
+    let reconnect = ws_recv_matching(&mut stream, |v| v["type"] == "Reconnect").await;
+    assert!(reconnect.get("payload").is_none());
+    let closed = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+        .await
+        .expect("socket was not closed");
+    assert!(matches!(closed, Some(Ok(Message::Close(_))) | None));
+
+    let (status, value) = app.request(axum::http::Method::GET, "/readyz", None, None).await;
+    assert_eq!(status, axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(value["components"]["shutdown"]["error"], "draining");
+}
+
 // ─── Subscribe / Subscribed ─────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -303,6 +371,36 @@ async fn ws_set_status_invalid(pool: Pool) {
         .contains("Invalid status"));
 }
 
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+#[cfg_attr(feature = "sqlite", sqlx::test(migrations = "./migrations_sqlite"))]
+async fn ws_repeated_status_is_coalesced(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a) = app.register_user("ws_flap_a").await;
+    let (token_b, _) = app.register_user("ws_flap_b").await;
+    let server_id = app.create_server(&token_a, "Flaps").await;
+    let channel_id = app.create_channel(&token_a, server_id, "general").await;
+    app.invite_and_join(&token_a, &token_b, server_id).await;
+    let addr = start_server(&app).await;
+
+    let (mut sink_a, _stream_a) = ws_connect(&addr, &token_a).await;
+    let (mut sink_b, mut stream_b) = ws_connect(&addr, &token_b).await;
+    ws_send(&mut sink_b, json!({"type": "Subscribe", "payload": {"channel_id": channel_id}})).await;
+    ws_recv_matching(&mut stream_b, |v| v["type"] == "Subscribed").await;
+
+    // A burst of identical status changes, then a different one
+    for status in ["dnd", "dnd", "dnd", "idle"] {
+        ws_send(&mut sink_a, json!({"type": "SetStatus", "payload": {"status": status}})).await;
+    }
+
+    let is_a_presence = |v: &Value| {
+        v["type"] == "PresenceUpdate" && v["payload"]["user_id"] == user_a.to_string()
+    };
+    let first = ws_recv_matching(&mut stream_b, is_a_presence).await;
+    assert_eq!(first["payload"]["status"], "dnd");
+    let second = ws_recv_matching(&mut stream_b, is_a_presence).await;
+    assert_eq!(second["payload"]["status"], "idle");
+}
+
 // ─── Typing indicator ───────────────────────────────────
 
 #[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
@@ -432,3 +530,284 @@ async fn ws_send_message_invalid_base64(pool: Pool) {
         .unwrap()
         .contains("Invalid sender_token"));
 }
+
+// ─── DM Calls ───────────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_dm_call_rings_peer_and_cancel_posts_missed_call(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_a, user_a_id) = app.register_user("ws_caller").await;
+    let (token_b, user_b_id) = app.register_user("ws_callee").await;
+    let channel_id = app.create_dm(&token_a, user_b_id).await;
+    let addr = start_server(&app).await;
+
+    let (mut sink_a, _stream_a) = ws_connect(&addr, &token_a).await;
+    let (_sink_b, mut stream_b) = ws_connect(&addr, &token_b).await;
+
+    let uri = format!("/api/v1/channels/{}/calls", channel_id);
+    let (status, _) = app
+        .request(axum::http::Method::POST, &uri, Some(&token_a), None)
+        .await;
+    assert_eq!(status, axum::http::StatusCode::OK);
+
+    let ringing = ws_recv_matching(&mut stream_b, |v| v["type"] == "CallRinging").await;
+    assert_eq!(ringing["payload"]["channel_id"], json!(channel_id));
+    assert_eq!(ringing["payload"]["caller_id"], json!(user_a_id));
+
+    // Caller hangs up before the callee answers
+    ws_send(&mut sink_a, json!({"type": "CallEnd", "payload": {"channel_id": channel_id}})).await;
+
+    let missed = ws_recv_matching(&mut stream_b, |v| v["type"] == "NewMessage").await;
+    let body = B64
+        .decode(missed["payload"]["encrypted_body"].as_str().unwrap())
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["event"], "call_missed");
+    assert_eq!(body["user_id"], user_a_id.to_string());
+    assert_eq!(missed["payload"]["message_type"], "system");
+    assert_eq!(missed["payload"]["system"], body);
+
+    ws_recv_matching(&mut stream_b, |v| v["type"] == "CallEnded").await;
+}
+
+// ─── Temporary membership ───────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_disconnect_removes_roleless_temporary_members(pool: Pool) {
+    use axum::http::{Method, StatusCode};
+
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("ws_temp_owner").await;
+    let (token_guest, guest_id) = app.register_user("ws_temp_guest").await;
+    let (token_kept, kept_id) = app.register_user("ws_temp_kept").await;
+    let server_id = app.create_server(&token_owner, "Temporary").await;
+    let addr = start_server(&app).await;
+
+    let uri = format!("/api/v1/servers/{}/invites", server_id);
+    let (status, invite) = app
+        .request(Method::POST, &uri, Some(&token_owner), Some(json!({ "temporary": true })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(invite["temporary"], true);
+    let join_uri = format!("/api/v1/invites/{}/join", invite["code"].as_str().unwrap());
+    for token in [&token_guest, &token_kept] {
+        let (status, _) = app.request(Method::POST, &join_uri, Some(token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // A role makes the membership stick
+    let roles_uri = format!("/api/v1/servers/{}/roles", server_id);
+    let (_, role) = app
+        .request(Method::POST, &roles_uri, Some(&token_owner), Some(json!({ "name": "Regular" })))
+        .await;
+    let assign_uri = format!("/api/v1/servers/{}/members/{}/roles", server_id, kept_id);
+    let (status, _) = app
+        .request(Method::PUT, &assign_uri, Some(&token_owner), Some(json!({ "role_id": role["id"] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let members_uri = format!("/api/v1/servers/{}/members", server_id);
+    let (_, members) = app.request(Method::GET, &members_uri, Some(&token_owner), None).await;
+    let guest = members.as_array().unwrap().iter().find(|m| m["user_id"] == guest_id.to_string()).unwrap();
+    assert_eq!(guest["temporary"], true);
+
+    for token in [&token_guest, &token_kept] {
+        let (mut sink, _stream) = ws_connect(&addr, token).await;
+        sink.close().await.unwrap();
+    }
+
+    let mut member_ids = Vec::new();
+    for _ in 0..100 {
+        let (_, members) = app.request(Method::GET, &members_uri, Some(&token_owner), None).await;
+        member_ids = members
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["user_id"].as_str().unwrap().to_string())
+            .collect();
+        if !member_ids.contains(&guest_id.to_string()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(!member_ids.contains(&guest_id.to_string()), "Temporary member was not removed");
+    assert!(member_ids.contains(&kept_id.to_string()));
+}
+
+// ─── Member chunks ──────────────────────────────────────
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_request_members_returns_chunks(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, owner_id) = app.register_user("ws_roster_owner").await;
+    let (token_b, _) = app.register_user("ws_roster_bob").await;
+    let (token_c, _) = app.register_user("ws_roster_carol").await;
+    let (token_out, _) = app.register_user("ws_roster_outsider").await;
+    let server_id = app.create_server(&token_owner, "Roster").await;
+    app.invite_and_join(&token_owner, &token_b, server_id).await;
+    app.invite_and_join(&token_owner, &token_c, server_id).await;
+    let addr = start_server(&app).await;
+
+    let (mut sink, mut stream) = ws_connect(&addr, &token_owner).await;
+
+    // Full roster, in join order
+    ws_send(
+        &mut sink,
+        json!({"type": "RequestMembers", "payload": {"server_id": server_id, "nonce": "all"}}),
+    )
+    .await;
+    let chunk = ws_recv_matching(&mut stream, |v| v["type"] == "MemberChunk").await;
+    assert_eq!(chunk["payload"]["nonce"], "all");
+    assert_eq!(chunk["payload"]["chunk_index"], 0);
+    assert_eq!(chunk["payload"]["chunk_count"], 1);
+    let members = chunk["payload"]["members"].as_array().unwrap();
+    assert_eq!(members.len(), 3);
+    assert_eq!(members[0]["user_id"], json!(owner_id));
+
+    // Prefix search is case-insensitive and treats LIKE wildcards literally
+    ws_send(
+        &mut sink,
+        json!({"type": "RequestMembers", "payload": {"server_id": server_id, "query": "WS_ROSTER_C"}}),
+    )
+    .await;
+    let chunk = ws_recv_matching(&mut stream, |v| v["type"] == "MemberChunk").await;
+    let members = chunk["payload"]["members"].as_array().unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["username"], "ws_roster_carol");
+    assert!(chunk["payload"].get("nonce").is_none());
+
+    ws_send(
+        &mut sink,
+        json!({"type": "RequestMembers", "payload": {"server_id": server_id, "query": "%"}}),
+    )
+    .await;
+    let chunk = ws_recv_matching(&mut stream, |v| v["type"] == "MemberChunk").await;
+    assert!(chunk["payload"]["members"].as_array().unwrap().is_empty());
+
+    // Non-members get an error, not the roster
+    let (mut sink_out, mut stream_out) = ws_connect(&addr, &token_out).await;
+    ws_send(
+        &mut sink_out,
+        json!({"type": "RequestMembers", "payload": {"server_id": server_id}}),
+    )
+    .await;
+    let err = ws_recv_matching(&mut stream_out, |v| v["type"] == "Error").await;
+    assert_eq!(err["payload"]["message"], "Not a member of this server");
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_sync_returns_changes_since_version(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token_owner, _) = app.register_user("ws_sync_owner").await;
+    let (token_member, _) = app.register_user("ws_sync_member").await;
+    let kept = app.create_server(&token_owner, "Kept").await;
+    let doomed = app.create_server(&token_owner, "Doomed").await;
+    app.invite_and_join(&token_owner, &token_member, kept).await;
+    app.invite_and_join(&token_owner, &token_member, doomed).await;
+    let old_channel = app.create_channel(&token_owner, kept, "old").await;
+
+    let (_, snapshot) = app.request(axum::http::Method::GET, "/api/v1/sync", Some(&token_member), None).await;
+    let since = snapshot["version"].as_i64().unwrap();
+
+    // While the member is away: a channel is added and one removed, they join
+    // a new server, and another server is deleted
+    let new_channel = app.create_channel(&token_owner, kept, "new").await;
+    let (status, _) = app
+        .request(axum::http::Method::DELETE, &format!("/api/v1/channels/{}", old_channel), Some(&token_owner), None)
+        .await;
+    assert_eq!(status, axum::http::StatusCode::OK);
+    let joined = app.create_server(&token_owner, "Joined").await;
+    app.invite_and_join(&token_owner, &token_member, joined).await;
+    let (status, _) = app
+        .request(
+            axum::http::Method::DELETE,
+            &format!("/api/v1/servers/{}", doomed),
+            Some(&token_owner),
+            Some(json!({ "password": "testpassword123" })),
+        )
+        .await;
+    assert_eq!(status, axum::http::StatusCode::OK);
+
+    let addr = start_server(&app).await;
+    let (mut sink, mut stream) = ws_connect(&addr, &token_member).await;
+    ws_send(&mut sink, json!({"type": "Sync", "payload": {"since_version": since}})).await;
+    let delta = ws_recv_matching(&mut stream, |v| v["type"] == "SyncDelta").await;
+    let delta = &delta["payload"];
+    assert_eq!(delta["full_sync_required"], false);
+    assert!(delta["version"].as_i64().unwrap() > since);
+    let ids = |key: &str| -> Vec<Value> {
+        delta[key].as_array().unwrap().iter().map(|v| v.get("id").unwrap_or(v).clone()).collect()
+    };
+    assert_eq!(ids("channels"), vec![json!(new_channel)]);
+    assert_eq!(ids("deleted_channels"), vec![json!(old_channel)]);
+    assert_eq!(ids("servers"), vec![json!(joined)]);
+    assert!(delta["servers"][0]["channels"].as_array().is_some_and(|c| !c.is_empty()));
+    assert_eq!(ids("removed_servers"), vec![json!(doomed)]);
+
+    // Nothing new since the returned version
+    let version = delta["version"].as_i64().unwrap();
+    ws_send(&mut sink, json!({"type": "Sync", "payload": {"since_version": version}})).await;
+    let delta = ws_recv_matching(&mut stream, |v| v["type"] == "SyncDelta").await;
+    assert!(delta["payload"]["channels"].as_array().unwrap().is_empty());
+    assert!(delta["payload"]["servers"].as_array().unwrap().is_empty());
+
+    // A version the journal never reached asks for a full sync
+    ws_send(&mut sink, json!({"type": "Sync", "payload": {"since_version": version + 1000}})).await;
+    let delta = ws_recv_matching(&mut stream, |v| v["type"] == "SyncDelta").await;
+    assert_eq!(delta["payload"]["full_sync_required"], true);
+}
+
+/// Helper: receive the next frame of a `encoding=msgpack&compress=zlib`
+/// connection, inflated on the connection's zlib stream.
+async fn ws_recv_msgpack_zlib(
+    stream: &mut futures::stream::SplitStream<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    >,
+    inflater: &mut flate2::Decompress,
+) -> Value {
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(3), stream.next())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+        .unwrap();
+    let Message::Binary(bytes) = frame else {
+        panic!("expected a binary frame, got {:?}", frame);
+    };
+    let mut payload = Vec::with_capacity(64 * 1024);
+    inflater
+        .decompress_vec(&bytes, &mut payload, flate2::FlushDecompress::Sync)
+        .unwrap();
+    rmp_serde::from_slice(&payload).unwrap()
+}
+
+#[cfg_attr(feature = "postgres", sqlx::test(migrations = "./migrations"))]
+async fn ws_msgpack_with_zlib_compression(pool: Pool) {
+    let app = TestApp::new(pool).await;
+    let (token, _) = app.register_user("ws_msgpack").await;
+    let addr = start_server(&app).await;
+
+    let bad = format!("ws://{}/api/v1/ws?token={}&encoding=etf", addr, token);
+    assert!(connect_async(&bad).await.is_err());
+
+    let url = format!("ws://{}/api/v1/ws?token={}&encoding=msgpack&compress=zlib", addr, token);
+    let (ws_stream, _) = connect_async(&url).await.expect("WS connect failed");
+    let (mut sink, mut stream) = ws_stream.split();
+    // One inflate context for the whole connection
+    let mut inflater = flate2::Decompress::new(true);
+
+    let hello = ws_recv_msgpack_zlib(&mut stream, &mut inflater).await;
+    assert_eq!(hello["type"], "Hello");
+    // Ids keep their string form, as in JSON
+    assert!(hello["payload"]["session_id"].as_str().is_some());
+
+    let ping = rmp_serde::to_vec_named(&json!({ "type": "Ping" })).unwrap();
+    sink.send(Message::Binary(ping)).await.unwrap();
+    for _ in 0..10 {
+        if ws_recv_msgpack_zlib(&mut stream, &mut inflater).await["type"] == "Pong" {
+            return;
+        }
+    }
+    panic!("No Pong received");
+}